@@ -13,6 +13,8 @@
     mov fs, ax
     mov gs, ax
     mov ss, ax
+    ; 进入长模式后寄存器高32位未定义，清零启动信息指针的高位
+    mov edi, edi
     ; 设置内核栈
     mov rsp, 0xFFFF_FFFF_FFDF_FFF8
     ; 调用内核kmain
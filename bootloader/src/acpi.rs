@@ -0,0 +1,27 @@
+use core::{ptr, slice};
+
+/// 在BIOS内存区域中查找ACPI RSDP，返回其物理地址
+///
+/// RSDP位于EBDA的前1K中，或位于0xE0000~0xFFFFF之间，均按16字节对齐
+pub fn find_rsdp() -> Option<u32> {
+    // EBDA的段地址记录在BDA的0x40E处
+    // Safety: BDA位于实模式内存中，一定可读
+    let ebda = (unsafe { ptr::read_volatile(0x40E as *const u16) } as u32) << 4;
+    if ebda != 0
+        && let Some(rsdp) = scan(ebda, ebda + 1024)
+    {
+        return Some(rsdp);
+    }
+    scan(0xE0000, 0x10_0000)
+}
+
+fn scan(start: u32, end: u32) -> Option<u32> {
+    (start..end).step_by(16).find(|&address| is_rsdp(address))
+}
+
+/// 检查签名及ACPI 1.0部分（前20字节）的校验和
+fn is_rsdp(address: u32) -> bool {
+    // Safety: 扫描范围均位于1M以下的BIOS内存区域，一定可读
+    let bytes = unsafe { slice::from_raw_parts(address as *const u8, 20) };
+    &bytes[..8] == b"RSD PTR " && bytes.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)) == 0
+}
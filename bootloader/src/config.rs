@@ -0,0 +1,35 @@
+use core::ptr;
+
+/// 命令行的最大长度
+pub const CMDLINE_CAPACITY: usize = 256;
+
+/// 由build-scripts写入的引导配置
+///
+/// build-scripts通过magic在loader镜像中找到此结构并写入内容，布局需与build-scripts保持一致
+#[repr(C)]
+pub struct BootConfig {
+    magic: [u8; 16],
+    /// 内核命令行
+    cmdline_length: u64,
+    cmdline: [u8; CMDLINE_CAPACITY],
+}
+
+#[used]
+static BOOT_CONFIG: BootConfig = BootConfig {
+    magic: *b"COS_BOOT_CONFIG_",
+    cmdline_length: 0,
+    cmdline: [0; CMDLINE_CAPACITY],
+};
+
+/// 获取引导配置
+pub fn boot_config() -> &'static BootConfig {
+    &BOOT_CONFIG
+}
+
+impl BootConfig {
+    /// 内核命令行
+    pub fn cmdline(&self) -> &[u8] {
+        let length = unsafe { ptr::read_volatile(&raw const self.cmdline_length) } as usize;
+        &self.cmdline[..length.min(CMDLINE_CAPACITY)]
+    }
+}
@@ -0,0 +1,286 @@
+//! FAT32文件系统，用于从启动磁盘的FAT32分区中读取内核镜像与启动选择记录
+//!
+//! 仅实现按路径查找文件、读取文件内容与原地覆盖文件开头。文件名按ASCII忽略大小写匹配，
+//! 支持8.3短文件名，以及只占一个长文件名条目（不超过13个字符）的长文件名
+
+use core::ptr;
+
+use crate::loader::{ata_read_disk, ata_write_disk};
+
+const SECTOR_SIZE: usize = 512;
+/// 目录项大小
+const ENTRY_SIZE: usize = 32;
+const ATTR_VOLUME_ID: u8 = 0x08;
+const ATTR_DIRECTORY: u8 = 0x10;
+const ATTR_LONG_NAME: u8 = 0x0F;
+/// 目录项首字节为此值时表示已删除
+const ENTRY_DELETED: u8 = 0xE5;
+/// 簇号大于等于此值时表示簇链结束
+const END_OF_CHAIN: u32 = 0x0FFF_FFF8;
+/// 长文件名条目中的字符数
+const LONG_NAME_CHARS: usize = 13;
+
+pub struct Fat32 {
+    disk: u8,
+    // FAT表的起始扇区
+    fat_start: u32,
+    // 数据区的起始扇区
+    data_start: u32,
+    sectors_per_cluster: u32,
+    // 根目录的首簇号
+    root_cluster: u32,
+    // 最近读取的FAT表扇区，读取簇链时通常连续访问同一扇区
+    fat_cache: Option<(u32, [u8; SECTOR_SIZE])>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FileEntry {
+    // 首簇号
+    cluster: u32,
+    /// 文件大小，单位为字节
+    pub size: u32,
+    pub directory: bool,
+}
+
+impl Fat32 {
+    /// 挂载起始于partition_start扇区的FAT32分区，BPB不合法时返回None
+    pub fn mount(disk: u8, partition_start: u32) -> Option<Self> {
+        let sector = read_sector(disk, partition_start);
+        let u16_at = |offset: usize| u16::from_le_bytes([sector[offset], sector[offset + 1]]);
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes([
+                sector[offset],
+                sector[offset + 1],
+                sector[offset + 2],
+                sector[offset + 3],
+            ])
+        };
+        let bytes_per_sector = u16_at(11) as usize;
+        let sectors_per_cluster = sector[13] as u32;
+        let reserved_sectors = u16_at(14) as u32;
+        let num_fats = sector[16] as u32;
+        let fat_size = u32_at(36);
+        let root_cluster = u32_at(44);
+        if sector[510..] != [0x55, 0xAA]
+            || bytes_per_sector != SECTOR_SIZE
+            || sectors_per_cluster == 0
+            || num_fats == 0
+            || fat_size == 0
+        {
+            return None;
+        }
+
+        let fat_start = partition_start + reserved_sectors;
+        Some(Self {
+            disk,
+            fat_start,
+            data_start: fat_start + num_fats * fat_size,
+            sectors_per_cluster,
+            root_cluster,
+            fat_cache: None,
+        })
+    }
+
+    /// 按路径查找文件，path依次为各级目录与文件的名称
+    pub fn find(&mut self, path: &[&str]) -> Option<FileEntry> {
+        let mut entry = FileEntry {
+            cluster: self.root_cluster,
+            size: 0,
+            directory: true,
+        };
+        for name in path {
+            if !entry.directory {
+                return None;
+            }
+            entry = self.find_in_directory(entry.cluster, name)?;
+        }
+        Some(entry)
+    }
+
+    /// 将文件内容读取到destination
+    ///
+    /// Safety: destination开始的file.size字节必须可写，且不与loader使用的内存重叠
+    pub unsafe fn read(&mut self, file: &FileEntry, destination: *mut u8) {
+        let mut remain = file.size as usize;
+        let mut destination = destination;
+        let mut cluster = file.cluster;
+        while remain > 0 {
+            if !(2..END_OF_CHAIN).contains(&cluster) {
+                panic!("fat32 cluster chain is shorter than file size");
+            }
+            for index in 0..self.sectors_per_cluster {
+                if remain == 0 {
+                    break;
+                }
+                let sector = read_sector(self.disk, self.cluster_sector(cluster) + index);
+                let len = remain.min(SECTOR_SIZE);
+                // Safety: 由调用者保证目标内存可写
+                unsafe {
+                    ptr::copy_nonoverlapping(sector.as_ptr(), destination, len);
+                    destination = destination.add(len);
+                }
+                remain -= len;
+            }
+            cluster = self.next_cluster(cluster);
+        }
+    }
+
+    /// 读取文件开头的至多一个扇区，返回的内容中超出文件大小的部分无意义
+    pub fn read_head(&mut self, file: &FileEntry) -> [u8; SECTOR_SIZE] {
+        read_sector(self.disk, self.cluster_sector(file.cluster))
+    }
+
+    /// 原地覆盖文件开头的内容，不修改文件大小与簇链，data不能超过文件大小与一个扇区
+    pub fn overwrite(&mut self, file: &FileEntry, data: &[u8]) {
+        assert!(data.len() <= file.size as usize && data.len() <= SECTOR_SIZE);
+        let block = self.cluster_sector(file.cluster);
+        let mut sector = read_sector(self.disk, block);
+        sector[..data.len()].copy_from_slice(data);
+        // Safety: loader运行时无并发且已关中断，写入的扇区属于此文件
+        unsafe { ata_write_disk(self.disk, block, &sector) };
+    }
+
+    fn find_in_directory(&mut self, cluster: u32, name: &str) -> Option<FileEntry> {
+        let mut long_name = [0u16; LONG_NAME_CHARS];
+        // 当前短文件名条目之前的长文件名条目数量
+        let mut long_count = 0;
+        let mut cluster = cluster;
+        while (2..END_OF_CHAIN).contains(&cluster) {
+            for index in 0..self.sectors_per_cluster {
+                let sector = read_sector(self.disk, self.cluster_sector(cluster) + index);
+                for entry in sector.chunks_exact(ENTRY_SIZE) {
+                    match (entry[0], entry[11]) {
+                        // 目录结束
+                        (0, _) => return None,
+                        (ENTRY_DELETED, _) => long_count = 0,
+                        (_, ATTR_LONG_NAME) => {
+                            // 名称依次位于1~10、14~25、28~31字节
+                            let units = entry[1..11]
+                                .chunks_exact(2)
+                                .chain(entry[14..26].chunks_exact(2))
+                                .chain(entry[28..32].chunks_exact(2))
+                                .map(|unit| u16::from_le_bytes([unit[0], unit[1]]));
+                            for (slot, unit) in long_name.iter_mut().zip(units) {
+                                *slot = unit;
+                            }
+                            long_count += 1;
+                        }
+                        (_, attr) => {
+                            let matched = match long_count {
+                                0 => short_name_matches(&entry[..11], name),
+                                1 => long_name_matches(&long_name, name),
+                                _ => false,
+                            };
+                            long_count = 0;
+                            if matched && attr & ATTR_VOLUME_ID == 0 {
+                                return Some(FileEntry {
+                                    cluster: u16::from_le_bytes([entry[20], entry[21]]) as u32
+                                        | (u16::from_le_bytes([entry[26], entry[27]]) as u32) << 16,
+                                    size: u32::from_le_bytes([
+                                        entry[28], entry[29], entry[30], entry[31],
+                                    ]),
+                                    directory: attr & ATTR_DIRECTORY != 0,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+            cluster = self.next_cluster(cluster);
+        }
+        None
+    }
+
+    fn cluster_sector(&self, cluster: u32) -> u32 {
+        self.data_start + (cluster - 2) * self.sectors_per_cluster
+    }
+
+    fn next_cluster(&mut self, cluster: u32) -> u32 {
+        let offset = cluster as usize * 4;
+        let sector = self.fat_start + (offset / SECTOR_SIZE) as u32;
+        if !matches!(&self.fat_cache, Some((cached, _)) if *cached == sector) {
+            self.fat_cache = Some((sector, read_sector(self.disk, sector)));
+        }
+        let Some((_, data)) = &self.fat_cache else {
+            unreachable!("codebug: fat cache should be filled");
+        };
+        let offset = offset % SECTOR_SIZE;
+        u32::from_le_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ]) & 0x0FFF_FFFF
+    }
+}
+
+/// ata_read_disk要求写入位置16bit对齐
+#[repr(C, align(4))]
+struct Sector([u8; SECTOR_SIZE]);
+
+fn read_sector(disk: u8, block: u32) -> [u8; SECTOR_SIZE] {
+    let mut sector = Sector([0; SECTOR_SIZE]);
+    // Safety: loader运行时无并发且已关中断，读取的位置均由分区表与BPB得出
+    unsafe { ata_read_disk(disk, block, sector.0.as_mut_ptr()) };
+    sector.0
+}
+
+/// 短文件名为8字节名称与3字节扩展名，均以空格填充
+fn short_name_matches(short_name: &[u8], name: &str) -> bool {
+    let (base, ext) = name.split_once('.').unwrap_or((name, ""));
+    let field_matches = |field: &[u8], part: &str| {
+        part.len() <= field.len()
+            && field
+                .iter()
+                .zip(part.bytes().chain(core::iter::repeat(b' ')))
+                .all(|(a, b)| a.eq_ignore_ascii_case(&b))
+    };
+    field_matches(&short_name[..8], base) && field_matches(&short_name[8..11], ext)
+}
+
+/// 长文件名以0结尾，之后以0xFFFF填充；名称恰好为13个字符时没有结尾
+fn long_name_matches(long_name: &[u16; LONG_NAME_CHARS], name: &str) -> bool {
+    let len = long_name
+        .iter()
+        .position(|&unit| unit == 0 || unit == 0xFFFF)
+        .unwrap_or(LONG_NAME_CHARS);
+    len == name.len()
+        && long_name[..len]
+            .iter()
+            .zip(name.bytes())
+            .all(|(&unit, byte)| unit < 0x80 && (unit as u8).eq_ignore_ascii_case(&byte))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn long_name(name: &str) -> [u16; LONG_NAME_CHARS] {
+        let mut units = [0xFFFF; LONG_NAME_CHARS];
+        for (slot, byte) in units.iter_mut().zip(name.bytes().chain([0])) {
+            *slot = byte as u16;
+        }
+        units
+    }
+
+    #[test_case]
+    fn match_short_name() {
+        assert!(short_name_matches(b"KERNEL  BIN", "kernel.bin"));
+        assert!(short_name_matches(b"SYSTEM     ", "system"));
+        assert!(!short_name_matches(b"KERNEL  BIN", "kernel"));
+        assert!(!short_name_matches(b"KERNEL  BIN", "kernel.bi"));
+        assert!(!short_name_matches(b"SYSTEM     ", "system.d"));
+    }
+
+    #[test_case]
+    fn match_long_name() {
+        assert!(long_name_matches(&long_name("kernel.bin"), "kernel.bin"));
+        assert!(long_name_matches(&long_name("Kernel.BIN"), "kernel.bin"));
+        assert!(long_name_matches(
+            &long_name("thirteen.char"),
+            "thirteen.char"
+        ));
+        assert!(!long_name_matches(&long_name("kernel.bin"), "kernel.bi"));
+        assert!(!long_name_matches(&long_name("kernel"), "kernel.bin"));
+    }
+}
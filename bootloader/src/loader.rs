@@ -1,15 +1,106 @@
 use core::{arch::asm, hint::spin_loop, ptr::copy_nonoverlapping};
 
-// 加载内核
-pub fn load_kernel(disk: u8) {
-    let start_block = unsafe { *((0x7C00 + 446 + 12) as *mut u32) } + 1;
-    let block_count = unsafe { *((0x7C00 + 446 + 12 + 16) as *mut u32) } as u32;
-    let start_ptr = 0x20_0000 as *mut u8; // 2M，对齐Huge Page
+use boot_info::{
+    KERNEL_PHYSICAL_BASE,
+    slot::{RECORD_SIZE, Slot, SlotRecord},
+};
 
-    for i in 0..block_count {
-        unsafe {
-            ata_read_disk(disk, start_block + i, start_ptr.add((512 * i) as usize));
-        }
+use crate::fat32::{Fat32, FileEntry};
+
+/// boot.s将MBR加载到此处，进入loader后不会被覆盖
+const MBR_ADDRESS: usize = 0x7C00;
+/// FAT32（LBA）分区的分区类型，与library/filesystem/src/device/mbr.rs保持一致
+const PARTITION_TYPE_FAT32: u8 = 0x0C;
+/// 内核镜像与启动选择记录所在的目录
+const SYSTEM_DIR: &str = "system";
+/// 启动选择记录的文件名，与boot_info::slot::RECORD_PATH一致
+const RECORD_NAME: &str = "boot-slot";
+/// 内核镜像结尾的内核ELF位置信息的magic，需与build-scripts保持一致
+const KERNEL_TRAILER_MAGIC: &[u8; 16] = b"COS_KERNEL_ELF__";
+/// 内核ELF位置信息的大小，依次为magic、偏移与长度
+const KERNEL_TRAILER_SIZE: u64 = 32;
+
+// 从启动磁盘的FAT32分区中按启动选择记录加载内核镜像，返回启动的槽位与内核镜像的大小，单位为字节
+pub fn load_kernel(disk: u8) -> (Slot, u64) {
+    let partition_start = fat32_partition_start().expect("fat32 partition is not found");
+    let mut fs = Fat32::mount(disk, partition_start).expect("invalid fat32 partition");
+
+    // 记录不存在或无效时（如旧的镜像）从槽位A启动，且不写回记录
+    let record = fs
+        .find(&[SYSTEM_DIR, RECORD_NAME])
+        .filter(|file| !file.directory && file.size as usize >= RECORD_SIZE);
+    let (slot, update) = record
+        .and_then(|file| SlotRecord::parse(&fs.read_head(&file)))
+        .unwrap_or(SlotRecord::DEFAULT)
+        .select();
+    if let (Some(file), Some(update)) = (record, update) {
+        fs.overwrite(&file, &update.to_bytes());
+    }
+
+    // 选择的槽位中没有内核镜像时，尝试另一个槽位
+    let (slot, kernel) = [slot, slot.other()]
+        .into_iter()
+        .find_map(|slot| find_kernel(&mut fs, slot).map(|kernel| (slot, kernel)))
+        .expect("kernel image is not found in /system");
+    // Safety: 2M之后为内核镜像所在的区域，loader位于1M以下
+    unsafe { fs.read(&kernel, KERNEL_PHYSICAL_BASE as usize as *mut u8) };
+    (slot, kernel.size as u64)
+}
+
+fn find_kernel(fs: &mut Fat32, slot: Slot) -> Option<FileEntry> {
+    fs.find(&[SYSTEM_DIR, slot.file_name()])
+        .filter(|file| !file.directory && file.size as u64 >= KERNEL_TRAILER_SIZE)
+}
+
+/// 内核ELF文件相对于内核镜像起始位置的偏移及长度，由内核镜像结尾的位置信息得出
+///
+/// 位置信息由build-scripts附加在内核ELF之后，使每个内核镜像都能独立启动，不依赖loader的引导配置
+///
+/// Safety: 内核镜像必须已位于2M处，kernel_size为其大小
+pub unsafe fn kernel_elf(kernel_size: u64) -> Option<(u64, u64)> {
+    if kernel_size < KERNEL_TRAILER_SIZE {
+        return None;
+    }
+    let trailer = (KERNEL_PHYSICAL_BASE + kernel_size - KERNEL_TRAILER_SIZE) as usize as *const u8;
+    // Safety: 由调用者保证内核镜像可读
+    let (magic, offset, length) = unsafe {
+        (
+            &*(trailer as *const [u8; 16]),
+            (trailer.add(16) as *const u64).read_unaligned(),
+            (trailer.add(24) as *const u64).read_unaligned(),
+        )
+    };
+    (magic == KERNEL_TRAILER_MAGIC
+        && length != 0
+        && offset
+            .checked_add(length)
+            .is_some_and(|end| end <= kernel_size - KERNEL_TRAILER_SIZE))
+    .then_some((offset, length))
+}
+
+/// loader镜像的大小，单位为字节，由MBR分区表中loader分区的扇区数得出
+pub fn loader_size() -> u64 {
+    partition_entry(0).1 as u64 * 512
+}
+
+/// MBR分区表中第一个FAT32分区的起始扇区
+fn fat32_partition_start() -> Option<u32> {
+    (0..4)
+        .map(partition_entry)
+        .find(|(partition_type, _, _)| *partition_type == PARTITION_TYPE_FAT32)
+        .map(|(_, start, _)| start)
+}
+
+/// MBR分区表中的第index项，依次为分区类型、起始扇区与扇区数
+fn partition_entry(index: usize) -> (u8, u32, u32) {
+    let entry = MBR_ADDRESS + 446 + 16 * index;
+    // Safety: MBR位于0x7C00，一定可读
+    unsafe {
+        (
+            *((entry + 4) as *const u8),
+            ((entry + 8) as *const u32).read_unaligned(),
+            ((entry + 12) as *const u32).read_unaligned(),
+        )
     }
 }
 
@@ -20,18 +111,84 @@ pub fn load_kernel(disk: u8) {
 /// ptr: 数据写入位置，必须16bit对齐
 ///
 /// Safety: 不能并发读，必须关中断，硬盘指定位置必须存在
-unsafe fn ata_read_disk(disk: u8, block: u32, ptr: *mut u8) {
-    const ATA_DATA: u16 = 0x1F0;
-    const ATA_SECTOR_COUNT: u16 = 0x1F2;
-    const ATA_SECTOR: u16 = 0x1F3;
-    const ATA_CYL_LO: u16 = 0x1F4;
-    const ATA_CYL_HI: u16 = 0x1F5;
-    const ATA_HEAD: u16 = 0x1F6;
-    const ATA_STATUS: u16 = 0x1F7;
-    const ATA_COMMAND: u16 = 0x1F7;
+pub unsafe fn ata_read_disk(disk: u8, block: u32, ptr: *mut u8) {
+    assert!((ptr as usize & 1) == 0);
+
+    // Safety: 由调用者保证
+    unsafe { ata_command(disk, block, ATA_COMMAND_READ) };
+    ata_wait_data(disk, block);
+
+    // 数据传输
+    let mut buf = [0u8; 512];
+    assert!((buf.as_ptr() as usize) < 0x1_0000);
+    unsafe {
+        asm!(
+            "rep insw",
+            in("dx") ATA_DATA,
+            in("di") buf.as_mut_ptr() as u16,
+            in("cx") 256,
+            options(nostack, preserves_flags),
+        );
+
+        copy_nonoverlapping(buf.as_ptr(), ptr, 512);
+    }
+}
+
+/// 使用ata lba写磁盘，写入后刷新磁盘缓存
+///
+/// disk: 硬盘号
+/// block: 逻辑扇区，不能超过0x1000_000
+/// data: 写入的扇区内容
+///
+/// Safety: 不能并发读写，必须关中断，硬盘指定位置必须存在
+pub unsafe fn ata_write_disk(disk: u8, block: u32, data: &[u8; 512]) {
+    // Safety: 由调用者保证
+    unsafe { ata_command(disk, block, ATA_COMMAND_WRITE) };
+    ata_wait_data(disk, block);
+
+    // 数据传输，每次写入16bit
+    for word in data.chunks_exact(2) {
+        unsafe {
+            asm!(
+                "out dx, ax",
+                in("dx") ATA_DATA,
+                in("ax") u16::from_le_bytes([word[0], word[1]]),
+                options(nostack, preserves_flags),
+            );
+        }
+    }
+    ata_wait_idle(disk, block);
 
+    // 刷新磁盘缓存，确保断电后数据仍然有效
+    unsafe {
+        asm!(
+            "out dx, al",
+            in("dx") ATA_COMMAND,
+            in("al") ATA_COMMAND_FLUSH,
+            options(nostack, preserves_flags),
+        );
+    }
+    ata_wait_idle(disk, block);
+}
+
+const ATA_DATA: u16 = 0x1F0;
+const ATA_SECTOR_COUNT: u16 = 0x1F2;
+const ATA_SECTOR: u16 = 0x1F3;
+const ATA_CYL_LO: u16 = 0x1F4;
+const ATA_CYL_HI: u16 = 0x1F5;
+const ATA_HEAD: u16 = 0x1F6;
+const ATA_STATUS: u16 = 0x1F7;
+const ATA_COMMAND: u16 = 0x1F7;
+
+const ATA_COMMAND_READ: u8 = 0x20;
+const ATA_COMMAND_WRITE: u8 = 0x30;
+const ATA_COMMAND_FLUSH: u8 = 0xE7;
+
+/// 设置扇区并发送读写命令
+///
+/// Safety: 不能并发读写，必须关中断，硬盘指定位置必须存在
+unsafe fn ata_command(disk: u8, block: u32, command: u8) {
     assert!(block < 0x1000_0000);
-    assert!((ptr as usize & 1) == 0);
 
     // 设置扇区数
     unsafe {
@@ -91,46 +248,44 @@ unsafe fn ata_read_disk(disk: u8, block: u32, ptr: *mut u8) {
         asm!(
             "out dx, al",
             in("dx") ATA_COMMAND,
-            in("al") 0x20 as u8,
+            in("al") command,
             options(nostack, preserves_flags),
         );
     }
+}
 
-    // 等待
-    loop {
-        let status: u8;
-        unsafe {
-            asm!(
-                "in al, dx",
-                out("al") status,
-                in("dx") ATA_STATUS,
-                options(nostack, preserves_flags),
-            )
-        }
+fn ata_status(disk: u8, block: u32) -> u8 {
+    let status: u8;
+    unsafe {
+        asm!(
+            "in al, dx",
+            out("al") status,
+            in("dx") ATA_STATUS,
+            options(nostack, preserves_flags),
+        )
+    }
 
-        if status & 1 != 0 {
-            panic!("read disk error, disk={disk}, block={block}, ptr={ptr:?}");
-        }
+    if status & 1 != 0 {
+        panic!("ata command error, disk={disk}, block={block}");
+    }
+    status
+}
 
+/// 等待磁盘可以传输数据
+fn ata_wait_data(disk: u8, block: u32) {
+    loop {
+        let status = ata_status(disk, block);
         if (status & 0x80) == 0 && (status & 0x08) != 0 {
             break;
         }
 
         spin_loop();
     }
+}
 
-    // 数据传输
-    let mut buf = [0u8; 256];
-    assert!((buf.as_ptr() as usize) < 0x1_0000);
-    unsafe {
-        asm!(
-            "rep insw",
-            in("dx") ATA_DATA,
-            in("di") buf.as_mut_ptr() as u16,
-            in("cx") 256,
-            options(nostack, preserves_flags),
-        );
-
-        copy_nonoverlapping(buf.as_ptr(), ptr, 512);
+/// 等待磁盘完成命令
+fn ata_wait_idle(disk: u8, block: u32) {
+    while ata_status(disk, block) & 0x80 != 0 {
+        spin_loop();
     }
 }
@@ -1,22 +1,38 @@
 #![no_std]
 #![no_main]
 #![feature(custom_test_frameworks)]
-#![test_runner(test_runner)]
+#![test_runner(testing::run)]
+#![reexport_test_harness_main = "test_main"]
 
 use core::{arch::asm, fmt::Write, slice};
 
+use boot_info::{BootInfo, KERNEL_PHYSICAL_BASE, MemoryRegion};
+
 use crate::{
+    acpi::find_rsdp,
     bit64::{enable_64bit_mode, test_cpu_is_support_64bit},
-    loader::load_kernel,
-    memory::{MemoryRegion, normalize_memory_region},
+    config::boot_config,
+    loader::{kernel_elf, load_kernel, loader_size},
+    memory::normalize_memory_region,
     vga::VgaText,
 };
 
+mod acpi;
 mod bit64;
+mod config;
+mod fat32;
 mod loader;
 mod memory;
+mod multiboot;
+#[cfg(test)]
+mod testing;
 mod vga;
 
+/// 传递给内核的启动信息
+///
+/// magic非零，因此位于.data段，包含在loader镜像中，进入长模式后仍被映射
+static mut BOOT_INFO: BootInfo = BootInfo::EMPTY;
+
 #[derive(Debug, Clone, Copy)]
 #[repr(C, packed)]
 pub struct RealBiosInfo {
@@ -33,6 +49,10 @@ pub unsafe extern "C" fn _start(bios_info: &RealBiosInfo) -> ! {
     let mut vga = unsafe { VgaText::new() };
     writeln!(vga, "COS Entered 32-bit mode").unwrap();
 
+    // 测试模式下运行测试用例，完成后退出qemu，不再加载内核
+    #[cfg(test)]
+    test_main();
+
     // Safety: boot传递给我们的指针，一定可读
     let mut memory_region = unsafe {
         slice::from_raw_parts_mut(bios_info.memory, bios_info.memory_region_size as usize)
@@ -40,17 +60,43 @@ pub unsafe extern "C" fn _start(bios_info: &RealBiosInfo) -> ! {
 
     // 规整内存
     memory_region = normalize_memory_region(memory_region);
+    print_memory_region(&mut vga, memory_region);
+
+    // 检测CPU是否为64位，并开启sse
+    prepare_cpu();
+
+    // 加载内核
+    let (slot, kernel_size) = load_kernel(bios_info.startup_disk);
+    writeln!(vga, "finish read kernel from slot {}", slot.name()).unwrap();
+
+    // 准备启动信息
+    // Safety: 无并发，仅在此处准备启动信息
+    let boot_info =
+        unsafe { prepare_boot_info(memory_region, bios_info.startup_disk as u32, kernel_size) };
+    boot_info.boot_slot = slot.boot_info_value();
+    writeln!(vga, "rsdp: 0x{:x}", boot_info.rsdp).unwrap();
+
+    // 启用长模式
+    // Safety: 我们已经加载完内核，并判断CPU支持长模式，可以进入长模式
+    unsafe {
+        enable_64bit_mode(boot_info, loader_size(), kernel_size);
+    }
+}
+
+fn print_memory_region(vga: &mut VgaText, memory_region: &[MemoryRegion]) {
     writeln!(vga, "memory ptr: {:?}", memory_region.as_ptr()).unwrap();
     for memory_region in memory_region.iter() {
         writeln!(
             vga,
             "memory: 0x{:x} - 0x{:x}",
-            memory_region.base_addr,
+            { memory_region.base_addr },
             memory_region.base_addr + memory_region.length
         )
         .unwrap();
     }
+}
 
+fn prepare_cpu() {
     // 检测CPU是否为64位
     if !test_cpu_is_support_64bit() {
         panic!("cpu is not support 64bit mode");
@@ -58,16 +104,30 @@ pub unsafe extern "C" fn _start(bios_info: &RealBiosInfo) -> ! {
 
     // 开启sse
     enable_sse();
+}
 
-    // 加载内核
-    load_kernel(bios_info.startup_disk);
-    writeln!(vga, "finish read kernel").unwrap();
-
-    // 启用长模式
-    // Safety: 我们已经加载完内核，并判断CPU支持长模式，可以进入长模式
-    unsafe {
-        enable_64bit_mode(memory_region, bios_info.startup_disk);
+/// 以内存信息、引导配置与内核镜像准备启动信息
+///
+/// Safety: 无并发，只能调用一次，内核镜像需已位于2M处，kernel_size为其大小
+unsafe fn prepare_boot_info(
+    memory_region: &[MemoryRegion],
+    startup_disk: u32,
+    kernel_size: u64,
+) -> &'static mut BootInfo {
+    // Safety: 由调用者保证无并发，且仅在此处访问BOOT_INFO
+    let boot_info = unsafe { &mut *(&raw mut BOOT_INFO) };
+    *boot_info = BootInfo::new(memory_region, startup_disk);
+    boot_info.rsdp = find_rsdp().unwrap_or(0) as u64;
+    let config = boot_config();
+    let cmdline = config.cmdline();
+    boot_info.cmdline_ptr = cmdline.as_ptr() as u64;
+    boot_info.cmdline_len = cmdline.len() as u64;
+    // Safety: 由调用者保证内核镜像已位于2M处
+    if let Some((offset, length)) = unsafe { kernel_elf(kernel_size) } {
+        boot_info.kernel_elf_ptr = KERNEL_PHYSICAL_BASE + offset;
+        boot_info.kernel_elf_len = length;
     }
+    boot_info
 }
 
 fn enable_sse() {
@@ -95,6 +155,8 @@ fn loop_halt() -> ! {
     }
 }
 
+// 测试模式下使用 [`testing`] 中的panic处理函数
+#[cfg(not(test))]
 #[panic_handler]
 fn panic(info: &core::panic::PanicInfo) -> ! {
     let mut vga = unsafe { VgaText::new() };
@@ -107,6 +169,3 @@ fn panic(info: &core::panic::PanicInfo) -> ! {
 
     loop_halt()
 }
-
-#[cfg(test)]
-fn test_runner(_test_cases: &[&dyn Fn()]) {}
@@ -1,19 +1,4 @@
-#[derive(Debug, Clone, Copy)]
-#[repr(C)]
-pub struct MemoryRegion {
-    pub base_addr: u64,
-    pub length: u64,
-    pub region_type: u32,
-}
-
-const _: () = {
-    assert!(size_of::<MemoryRegion>() == 20);
-};
-
-impl MemoryRegion {
-    const TYPE_USABLE: u32 = 1;
-    const TYPE_RESERVED: u32 = 2;
-}
+use boot_info::MemoryRegion;
 
 /// 规整内存
 ///
@@ -106,3 +91,69 @@ pub fn normalize_memory_region(mut memory_region: &mut [MemoryRegion]) -> &mut [
 
     &mut memory_region[..len]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(base_addr: u64, length: u64, region_type: u32) -> MemoryRegion {
+        MemoryRegion {
+            base_addr,
+            length,
+            region_type,
+        }
+    }
+
+    fn layout(regions: &[MemoryRegion]) -> [(u64, u64, u32); 4] {
+        let mut layout = [(0, 0, 0); 4];
+        for (slot, region) in layout.iter_mut().zip(regions) {
+            *slot = (region.base_addr, region.length, region.region_type);
+        }
+        layout
+    }
+
+    #[test_case]
+    fn merge_adjacent_regions() {
+        let mut regions = [
+            region(0x2000, 0x1000, MemoryRegion::TYPE_USABLE),
+            region(0x1000, 0x1000, MemoryRegion::TYPE_USABLE),
+        ];
+        let regions = normalize_memory_region(&mut regions);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(
+            layout(regions)[0],
+            (0x1000, 0x2000, MemoryRegion::TYPE_USABLE)
+        );
+    }
+
+    #[test_case]
+    fn reserved_overrides_usable() {
+        let mut regions = [
+            region(0x1000, 0x3000, MemoryRegion::TYPE_USABLE),
+            region(0x2000, 0x1000, 7),
+        ];
+        let regions = normalize_memory_region(&mut regions);
+        assert_eq!(regions.len(), 2);
+        assert_eq!(
+            layout(regions)[..2],
+            [
+                (0x1000, 0x1000, MemoryRegion::TYPE_USABLE),
+                (0x2000, 0x1000, MemoryRegion::TYPE_RESERVED),
+            ]
+        );
+    }
+
+    #[test_case]
+    fn remove_empty_regions() {
+        let mut regions = [
+            region(0x1000, 0, MemoryRegion::TYPE_USABLE),
+            region(0x3000, 0x1000, MemoryRegion::TYPE_USABLE),
+        ];
+        let regions = normalize_memory_region(&mut regions);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(
+            layout(regions)[0],
+            (0x3000, 0x1000, MemoryRegion::TYPE_USABLE)
+        );
+    }
+}
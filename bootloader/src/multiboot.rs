@@ -0,0 +1,322 @@
+//! Multiboot2启动
+//!
+//! loader镜像带有Multiboot2头，可以由GRUB以 `multiboot2` 命令加载到0x8000，内核镜像（附加了initramfs与内核ELF，
+//! 与写入FAT32分区的/system/kernel-a.bin一致）需作为第一个模块以 `module2` 命令加载。引导程序在32位保护模式下跳转到 `multiboot_entry`，
+//! 此时loader将Multiboot2信息转换为启动信息：
+//! * 内存信息来自内存映射
+//! * 命令行非空时代替引导配置中的命令行
+//! * 帧缓冲区仅支持32位直接色彩，VGA文本模式下不提供
+//! * ACPI RSDP优先使用引导程序提供的副本
+//!
+//! 随后将内核镜像复制到2M，之后与BIOS启动的流程一致
+
+use core::{arch::global_asm, fmt::Write, ptr, str};
+
+use boot_info::{Framebuffer, KERNEL_PHYSICAL_BASE, MemoryRegion};
+
+use crate::{
+    _start, bit64::enable_64bit_mode, config::CMDLINE_CAPACITY, memory::normalize_memory_region,
+    prepare_boot_info, prepare_cpu, print_memory_region, vga::VgaText,
+};
+
+/// Multiboot2头的magic
+const HEADER_MAGIC: u32 = 0xE852_50D6;
+/// 引导程序通过eax传递的magic
+const BOOTLOADER_MAGIC: u32 = 0x36D7_6289;
+/// 入口使用的栈大小
+const STACK_SIZE: usize = 0x4000;
+/// 内存信息的最大数量，超出的部分被忽略
+const MAX_MEMORY_REGIONS: usize = 128;
+/// Multiboot2没有提供BIOS磁盘号，约定COS磁盘为第一个硬盘，与UEFI引导程序一致
+const STARTUP_DISK: u32 = 0x80;
+
+const TAG_END: u32 = 0;
+const TAG_CMDLINE: u32 = 1;
+const TAG_MODULE: u32 = 3;
+const TAG_MEMORY_MAP: u32 = 6;
+const TAG_FRAMEBUFFER: u32 = 8;
+const TAG_ACPI_OLD: u32 = 14;
+const TAG_ACPI_NEW: u32 = 15;
+
+/// 帧缓冲区类型：直接色彩
+const FRAMEBUFFER_TYPE_RGB: u8 = 1;
+
+// Multiboot2头与入口
+//
+// loader是扁平二进制，需通过地址标签告知引导程序加载位置，load_end_addr与bss_end_addr为0表示加载整个文件。
+// 进入时GDTR可能无效，因此先加载与boot.s一致的GDT，再设置栈并以cdecl调用multiboot_main
+global_asm!(
+    ".pushsection .text.multiboot, \"ax\"",
+    ".balign 8",
+    "multiboot_header:",
+    ".long {magic}",
+    // 架构：i386 32位保护模式
+    ".long 0",
+    ".long multiboot_header_end - multiboot_header",
+    ".long 0x100000000 - {magic} - (multiboot_header_end - multiboot_header)",
+    // 地址标签
+    ".balign 8",
+    ".short 2, 0",
+    ".long 24",
+    ".long multiboot_header",
+    ".long {load}",
+    ".long 0",
+    ".long 0",
+    // 入口地址标签
+    ".balign 8",
+    ".short 3, 0",
+    ".long 12",
+    ".long multiboot_entry",
+    // 结束标签
+    ".balign 8",
+    ".short 0, 0",
+    ".long 8",
+    "multiboot_header_end:",
+    "multiboot_entry:",
+    "cli",
+    "lgdt [multiboot_gdtr]",
+    "ljmp 0x08, offset multiboot_reload",
+    "multiboot_reload:",
+    "mov cx, 0x10",
+    "mov ds, cx",
+    "mov es, cx",
+    "mov fs, cx",
+    "mov gs, cx",
+    "mov ss, cx",
+    "lea esp, [{stack} + {stack_size}]",
+    "push ebx",
+    "push eax",
+    "call {main}",
+    "ud2",
+    ".balign 8",
+    "multiboot_gdt:",
+    ".quad 0",
+    ".quad 0x00CF9A000000FFFF",
+    ".quad 0x00CF92000000FFFF",
+    "multiboot_gdtr:",
+    ".short multiboot_gdtr - multiboot_gdt - 1",
+    ".long multiboot_gdt",
+    ".popsection",
+    magic = const HEADER_MAGIC,
+    load = sym _start,
+    stack = sym STACK,
+    stack_size = const STACK_SIZE,
+    main = sym multiboot_main,
+);
+
+unsafe extern "C" {
+    /// 扁平二进制的结尾，由linker.ld定义
+    static __loader_end: u8;
+}
+
+static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
+/// 内存信息，引导程序提供的内存映射可能被内核覆盖，因此复制到loader镜像中
+static mut MEMORY_REGIONS: [MemoryRegion; MAX_MEMORY_REGIONS] = [MemoryRegion {
+    base_addr: 0,
+    length: 0,
+    region_type: 0,
+}; MAX_MEMORY_REGIONS];
+/// 命令行的副本
+static mut CMDLINE: [u8; CMDLINE_CAPACITY] = [0; CMDLINE_CAPACITY];
+/// ACPI RSDP的副本，ACPI 2.0的RSDP为36字节
+static mut RSDP: Rsdp = Rsdp([0; 36]);
+
+#[repr(C, align(16))]
+struct Rsdp([u8; 36]);
+
+/// 从Multiboot2信息中取得的内容
+struct MultibootInfo {
+    memory_region: &'static mut [MemoryRegion],
+    /// 第一个模块的起止地址
+    kernel: Option<(usize, usize)>,
+    cmdline: &'static [u8],
+    framebuffer: Option<Framebuffer>,
+    rsdp: Option<u64>,
+}
+
+// Safety: 由multiboot_entry在加载GDT、设置栈后调用，magic与info为引导程序传入的值
+unsafe extern "C" fn multiboot_main(magic: u32, info: u32) -> ! {
+    // Safety: 在Multiboot2启动流程中，我们仅创建一个VgaText
+    let mut vga = unsafe { VgaText::new() };
+    writeln!(vga, "COS Entered 32-bit mode (multiboot2)").unwrap();
+    if magic != BOOTLOADER_MAGIC {
+        panic!("invalid multiboot2 magic: 0x{magic:x}");
+    }
+
+    // 内核镜像复制到2M时可能覆盖Multiboot2信息，因此先取出全部所需内容
+    // Safety: info由引导程序提供，指向有效的Multiboot2信息
+    let info = unsafe { parse(info as usize) };
+    let memory_region = normalize_memory_region(info.memory_region);
+    print_memory_region(&mut vga, memory_region);
+
+    prepare_cpu();
+
+    let (kernel_start, kernel_end) = info
+        .kernel
+        .expect("kernel image is not loaded as multiboot2 module");
+    let kernel_size = kernel_end - kernel_start;
+    // Safety: 模块由引导程序加载，一定可读；2M之后为内核镜像所在的区域，内存检测保证其可用。
+    // 两者可能重叠，因此使用copy
+    unsafe {
+        ptr::copy(
+            kernel_start as *const u8,
+            KERNEL_PHYSICAL_BASE as usize as *mut u8,
+            kernel_size,
+        );
+    }
+    writeln!(vga, "finish copy kernel").unwrap();
+
+    // Safety: 无并发，仅在此处准备启动信息
+    let boot_info = unsafe { prepare_boot_info(memory_region, STARTUP_DISK, kernel_size as u64) };
+    if let Some(rsdp) = info.rsdp {
+        boot_info.rsdp = rsdp;
+    }
+    if !info.cmdline.is_empty() {
+        boot_info.cmdline_ptr = info.cmdline.as_ptr() as u64;
+        boot_info.cmdline_len = info.cmdline.len() as u64;
+    }
+    if let Some(framebuffer) = info.framebuffer {
+        boot_info.framebuffer = framebuffer;
+    }
+    writeln!(vga, "rsdp: 0x{:x}", boot_info.rsdp).unwrap();
+
+    let loader_size = (&raw const __loader_end as usize - _start as *const () as usize) as u64;
+    // Safety: 已将内核复制到2M，并判断CPU支持长模式，GDT的1/2号为入口设置的段
+    unsafe { enable_64bit_mode(boot_info, loader_size, kernel_size as u64) }
+}
+
+/// 遍历Multiboot2信息中的标签，取出启动所需的内容
+///
+/// Safety: info必须指向有效的Multiboot2信息，只能调用一次
+unsafe fn parse(info: usize) -> MultibootInfo {
+    let mut result = MultibootInfo {
+        memory_region: &mut [],
+        kernel: None,
+        cmdline: &[],
+        framebuffer: None,
+        rsdp: None,
+    };
+    // 信息以总长度与保留字段开头，之后为8字节对齐的标签
+    let total_size = unsafe { read::<u32>(info) } as usize;
+    let mut tag = info + 8;
+    while tag + 8 <= info + total_size {
+        let (kind, size) = unsafe { (read::<u32>(tag), read::<u32>(tag + 4) as usize) };
+        match kind {
+            TAG_END => break,
+            TAG_CMDLINE => result.cmdline = unsafe { copy_cmdline(tag + 8) },
+            TAG_MODULE if result.kernel.is_none() => {
+                let (start, end) = unsafe { (read::<u32>(tag + 8), read::<u32>(tag + 12)) };
+                result.kernel = Some((start as usize, end as usize));
+            }
+            TAG_MEMORY_MAP => result.memory_region = unsafe { copy_memory_map(tag, size) },
+            TAG_FRAMEBUFFER => result.framebuffer = unsafe { framebuffer(tag) },
+            // 新版RSDP优先
+            TAG_ACPI_OLD if result.rsdp.is_none() => result.rsdp = unsafe { copy_rsdp(tag, size) },
+            TAG_ACPI_NEW => result.rsdp = unsafe { copy_rsdp(tag, size) },
+            _ => {}
+        }
+        tag += size.next_multiple_of(8).max(8);
+    }
+    result
+}
+
+/// 将内存映射转换为内存信息，保存在 [MEMORY_REGIONS] 中
+///
+/// Safety: tag必须指向有效的内存映射标签，只能调用一次
+unsafe fn copy_memory_map(tag: usize, size: usize) -> &'static mut [MemoryRegion] {
+    // Safety: 无并发，仅在此处访问MEMORY_REGIONS
+    let regions = unsafe { &mut *(&raw mut MEMORY_REGIONS) };
+    let entry_size = unsafe { read::<u32>(tag + 8) } as usize;
+    if entry_size < 24 {
+        return &mut [];
+    }
+    let mut len = 0;
+    for entry in (tag + 16..tag + size).step_by(entry_size) {
+        if len == MAX_MEMORY_REGIONS || entry + 24 > tag + size {
+            break;
+        }
+        // 可用内存的类型与MemoryRegion::TYPE_USABLE相同，其他类型由normalize_memory_region视为不可用
+        regions[len] = unsafe {
+            MemoryRegion {
+                base_addr: read(entry),
+                length: read(entry + 8),
+                region_type: read(entry + 16),
+            }
+        };
+        len += 1;
+    }
+    &mut regions[..len]
+}
+
+/// 将命令行复制到 [CMDLINE] 中，过长时截断
+///
+/// Safety: address必须指向以0结尾的字符串，只能调用一次
+unsafe fn copy_cmdline(address: usize) -> &'static [u8] {
+    // Safety: 无并发，仅在此处访问CMDLINE
+    let cmdline = unsafe { &mut *(&raw mut CMDLINE) };
+    let mut len = 0;
+    while len < CMDLINE_CAPACITY {
+        let byte = unsafe { read::<u8>(address + len) };
+        if byte == 0 {
+            break;
+        }
+        cmdline[len] = byte;
+        len += 1;
+    }
+    // 截断可能破坏UTF-8字符，只保留有效的部分
+    let len = str::from_utf8(&cmdline[..len]).map_or_else(|error| error.valid_up_to(), str::len);
+    &cmdline[..len]
+}
+
+/// 将RSDP复制到 [RSDP] 中，返回副本的地址
+///
+/// Safety: tag必须指向有效的ACPI标签
+unsafe fn copy_rsdp(tag: usize, size: usize) -> Option<u64> {
+    let len = size.checked_sub(8)?.min(size_of::<Rsdp>());
+    // Safety: 无并发，仅在此处访问RSDP，标签中的内容一定可读
+    unsafe {
+        let rsdp = &raw mut RSDP;
+        ptr::copy_nonoverlapping((tag + 8) as *const u8, rsdp.cast::<u8>(), len);
+        Some(rsdp as usize as u64)
+    }
+}
+
+/// 将帧缓冲区标签转换为启动信息中的帧缓冲区，仅支持每像素4字节的RGB或BGR格式
+///
+/// Safety: tag必须指向有效的帧缓冲区标签
+unsafe fn framebuffer(tag: usize) -> Option<Framebuffer> {
+    let (base, pitch, width, height, bpp, kind) = unsafe {
+        (
+            read::<u64>(tag + 8),
+            read::<u32>(tag + 16),
+            read::<u32>(tag + 20),
+            read::<u32>(tag + 24),
+            read::<u8>(tag + 28),
+            read::<u8>(tag + 29),
+        )
+    };
+    if kind != FRAMEBUFFER_TYPE_RGB || bpp != 32 {
+        return None;
+    }
+    // 红色分量在像素中的位偏移
+    let pixel_format = match unsafe { read::<u8>(tag + 32) } {
+        0 => Framebuffer::FORMAT_RGB,
+        16 => Framebuffer::FORMAT_BGR,
+        _ => return None,
+    };
+    Some(Framebuffer {
+        base,
+        size: pitch as u64 * height as u64,
+        width,
+        height,
+        stride: pitch / 4,
+        pixel_format,
+    })
+}
+
+/// 读取Multiboot2信息中的字段，字段不保证对齐
+///
+/// Safety: address必须可读
+unsafe fn read<T: Copy>(address: usize) -> T {
+    unsafe { ptr::read_unaligned(address as *const T) }
+}
@@ -0,0 +1,129 @@
+use core::{arch::asm, fmt::Write, panic::PanicInfo};
+
+/// COM1端口基地址
+const COM1: u16 = 0x3F8;
+/// isa-debug-exit设备的端口，需与build-scripts中qemu的参数保持一致
+const DEBUG_EXIT_PORT: u16 = 0xF4;
+
+/// 测试全部通过时写入isa-debug-exit的值，qemu的退出码为1
+const EXIT_SUCCESS: u32 = 0;
+/// 测试失败时写入isa-debug-exit的值，qemu的退出码为3
+const EXIT_FAILED: u32 = 1;
+
+/// 正在运行的测试名，panic时据此报告失败的用例
+static mut CURRENT_TEST: Option<&'static str> = None;
+/// 已通过的测试数量
+static mut PASSED: usize = 0;
+
+/// 由`#[test_case]`标记的测试用例
+pub trait Testable {
+    /// 测试名，即测试函数的完整路径
+    fn name(&self) -> &'static str;
+
+    fn run(&self);
+}
+
+impl<T: Fn()> Testable for T {
+    fn name(&self) -> &'static str {
+        core::any::type_name::<T>()
+    }
+
+    fn run(&self) {
+        self()
+    }
+}
+
+/// 依次运行测试用例，通过串口报告结果后退出qemu
+///
+/// 输出格式与内核的测试相同，由build-scripts中的集成测试解析。
+/// loader以panic=abort编译，测试失败即panic，之后的用例不再运行
+pub fn run(tests: &[&dyn Testable]) -> ! {
+    init_serial();
+    _ = writeln!(Serial, "COS-TEST BEGIN {}", tests.len());
+    for test in tests {
+        let name = test.name();
+        // Safety: loader是单线程的，且不开中断，仅在此处与panic处理函数中访问测试状态
+        unsafe {
+            CURRENT_TEST = Some(name);
+        }
+        test.run();
+        unsafe {
+            CURRENT_TEST = None;
+            PASSED += 1;
+        }
+        _ = writeln!(Serial, "COS-TEST PASS {name}");
+    }
+    // Safety: 同上
+    let passed = unsafe { PASSED };
+    _ = writeln!(Serial, "COS-TEST END {passed} 0");
+    exit(EXIT_SUCCESS)
+}
+
+/// 测试模式下的panic处理函数，报告正在运行的测试失败后退出qemu
+#[panic_handler]
+fn report_panic(info: &PanicInfo) -> ! {
+    // Safety: 同run
+    let (current, passed) = unsafe { (CURRENT_TEST, PASSED) };
+    match current {
+        Some(name) => {
+            _ = writeln!(Serial, "COS-TEST FAIL {name} {}", info.message());
+            if let Some(location) = info.location() {
+                _ = writeln!(Serial, "at {location}");
+            }
+            _ = writeln!(Serial, "COS-TEST END {passed} 1");
+        }
+        None => _ = writeln!(Serial, "COS-TEST PANIC {}", info.message()),
+    }
+    exit(EXIT_FAILED)
+}
+
+/// 通过串口输出测试结果，不检查串口是否存在
+struct Serial;
+
+impl Write for Serial {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for &byte in s.as_bytes() {
+            unsafe {
+                // 等待发送缓冲区为空
+                while inb(COM1 + 5) & (1 << 5) == 0 {}
+                outb(COM1, byte);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 115200波特率，8数据位，无校验，1停止位，与内核的串口设置相同
+fn init_serial() {
+    unsafe {
+        outb(COM1 + 1, 0x00);
+        outb(COM1 + 3, 0x80);
+        outb(COM1, 0x01);
+        outb(COM1 + 1, 0x00);
+        outb(COM1 + 3, 0x03);
+        outb(COM1 + 2, 0xC7);
+        outb(COM1 + 4, 0x0F);
+    }
+}
+
+fn exit(code: u32) -> ! {
+    unsafe {
+        asm!("out dx, eax", in("dx") DEBUG_EXIT_PORT, in("eax") code);
+    }
+    // qemu未配置isa-debug-exit设备
+    crate::loop_halt()
+}
+
+unsafe fn outb(port: u16, value: u8) {
+    unsafe {
+        asm!("out dx, al", in("dx") port, in("al") value);
+    }
+}
+
+unsafe fn inb(port: u16) -> u8 {
+    let value: u8;
+    unsafe {
+        asm!("in al, dx", in("dx") port, out("al") value);
+    }
+    value
+}
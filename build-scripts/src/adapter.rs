@@ -7,11 +7,20 @@ use std::{
 
 use filesystem::{
     BoxFuture,
-    device::{BlockDevice, BlockDeviceError},
+    device::{
+        BlockDevice, BlockDeviceError,
+        qcow2::{QCOW2_MAGIC, Qcow2Device},
+    },
 };
 
+use crate::{block_on, config::DiskFormat};
+
+/// 以宿主机文件作为块设备
+///
+/// 文件短于设备容量时，超出文件结尾的部分读取为零，写入时文件随之增长。
 pub struct HostFileBlockDevice {
     file: Mutex<File>,
+    // 设备容量，单位为字节
     file_size: u64,
 }
 
@@ -33,6 +42,37 @@ impl HostFileBlockDevice {
             file_size,
         }))
     }
+
+    /// 创建空文件作为容量为 `max_size` 的块设备，文件长度随写入增长
+    pub fn growable<P>(path: P, max_size: u64) -> Result<Arc<Self>, io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let file = OpenOptions::new()
+            .write(true)
+            .read(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        Ok(Arc::new(Self {
+            file: Mutex::new(file),
+            file_size: max_size / 512 * 512,
+        }))
+    }
+
+    /// 打开已存在的镜像文件，不修改其内容
+    pub fn open<P>(path: P) -> Result<Arc<Self>, io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let file = OpenOptions::new().write(true).read(true).open(path)?;
+        // 不足一个扇区的尾部无法访问
+        let file_size = file.metadata()?.len() / 512 * 512;
+        Ok(Arc::new(Self {
+            file: Mutex::new(file),
+            file_size,
+        }))
+    }
 }
 
 impl BlockDevice for HostFileBlockDevice {
@@ -41,7 +81,7 @@ impl BlockDevice for HostFileBlockDevice {
     }
 
     fn block_count(&self) -> u64 {
-        self.file_size
+        self.file_size / 512
     }
 
     fn write_block<'fut>(
@@ -73,7 +113,7 @@ impl BlockDevice for HostFileBlockDevice {
             assert!(buf.len() == 512);
             let mut file = self.file.lock().unwrap();
             file.seek(SeekFrom::Start(block_index * 512))?;
-            file.read_exact(buf)?;
+            read_to_fill(&mut file, buf)?;
             Ok(())
         })
     }
@@ -109,8 +149,60 @@ impl BlockDevice for HostFileBlockDevice {
             assert!(buf.len() as u64 == 512 * count);
             let mut file = self.file.lock().unwrap();
             file.seek(SeekFrom::Start(block_index * 512))?;
-            file.read_exact(buf)?;
+            read_to_fill(&mut file, buf)?;
             Ok(())
         })
     }
 }
+
+/// 读满缓冲区，超出文件结尾的部分填充为零
+fn read_to_fill(file: &mut File, mut buf: &mut [u8]) -> Result<(), io::Error> {
+    while !buf.is_empty() {
+        match file.read(buf) {
+            Ok(0) => break,
+            Ok(n) => buf = &mut std::mem::take(&mut buf)[n..],
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    buf.fill(0);
+    Ok(())
+}
+
+/// 根据文件头部识别镜像格式
+pub fn detect_format<P>(path: P) -> Result<DiskFormat, io::Error>
+where
+    P: AsRef<Path>,
+{
+    let mut magic = [0; QCOW2_MAGIC.len()];
+    let mut file = File::open(path)?;
+    read_to_fill(&mut file, &mut magic)?;
+    Ok(if magic == QCOW2_MAGIC {
+        DiskFormat::Qcow2
+    } else {
+        DiskFormat::Raw
+    })
+}
+
+/// 打开已存在的镜像，qcow2镜像打开为其中的虚拟磁盘
+pub fn open_image<P>(path: P) -> Result<Arc<dyn BlockDevice>, io::Error>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    match detect_format(path)? {
+        DiskFormat::Raw => Ok(HostFileBlockDevice::open(path)?),
+        DiskFormat::Qcow2 => {
+            // qcow2镜像的写入范围由其中的引用计数表限制，文件可以继续增长
+            let file = OpenOptions::new().write(true).read(true).open(path)?;
+            let file = Arc::new(HostFileBlockDevice {
+                file: Mutex::new(file),
+                file_size: u64::MAX / 512 * 512,
+            });
+            let qcow2 = block_on(Qcow2Device::open(file)).map_err(|error| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("{error:?}"))
+            })?;
+            Ok(Arc::new(qcow2))
+        }
+    }
+}
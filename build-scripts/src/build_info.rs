@@ -0,0 +1,127 @@
+//! 写入镜像的构建信息与内核符号表
+//!
+//! [BUILD_INFO_PATH] 每行一项 `<键>=<值>`：
+//! * `git`：git提交的短哈希，工作区有未提交的修改时带有 `-dirty` 后缀，无法获取时为 `unknown`
+//! * `time`：构建时间（UTC），设置了环境变量 `SOURCE_DATE_EPOCH` 时使用该时间，便于复现构建
+//! * `profile`：内核的编译配置，`debug`、`release` 或 `test`
+//!
+//! [KERNEL_MAP_PATH] 每行一个函数符号 `<地址> <大小> <名称>`，地址与大小为十六进制，按地址升序排列，
+//! 名称已经过demangle
+
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+    time::SystemTime,
+};
+
+/// 构建信息路径，需与kernel/src/build_info.rs保持一致
+pub const BUILD_INFO_PATH: &str = "/system/build-info";
+/// 内核符号表路径，需与kernel/src/build_info.rs保持一致
+pub const KERNEL_MAP_PATH: &str = "/system/kernel.map";
+
+/// 当前源码的git版本，无法获取时（如不在git仓库中）返回 `unknown`
+pub fn git_version() -> String {
+    let Some(hash) = git(&["rev-parse", "--short=12", "HEAD"]) else {
+        return "unknown".to_string();
+    };
+    match git(&["status", "--porcelain", "--untracked-files=no"]) {
+        Some(status) if status.is_empty() => hash,
+        _ => format!("{hash}-dirty"),
+    }
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// 生成构建信息
+pub fn build_info(git_version: &str, profile: &str) -> Vec<u8> {
+    let time = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs())
+        });
+    format!(
+        "git={git_version}\ntime={}\nprofile={profile}\n",
+        format_utc(time)
+    )
+    .into_bytes()
+}
+
+/// 以rust-nm提取内核ELF中的函数符号
+pub fn kernel_map(elf: &Path) -> Vec<u8> {
+    let output = Command::new("rust-nm")
+        .args([
+            "--defined-only",
+            "--demangle",
+            "--numeric-sort",
+            "--print-size",
+        ])
+        .arg(elf)
+        .stderr(Stdio::inherit())
+        .output()
+        .unwrap_or_else(|error| {
+            panic!("failed to extract kernel symbols: {error}. may rust-nm is not installed?")
+        });
+    if !output.status.success() {
+        panic!(
+            "failed to extract kernel symbols: rust-nm exit with non-zero status: {}",
+            output.status
+        );
+    }
+    let mut map = String::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Some((address, size, name)) = parse_nm_line(line) {
+            map.push_str(&format!("{address:016x} {size:x} {name}\n"));
+        }
+    }
+    map.into_bytes()
+}
+
+/// 解析 `nm --print-size` 的一行，只保留代码段中的符号。没有大小的符号缺少大小一列
+fn parse_nm_line(line: &str) -> Option<(u64, u64, &str)> {
+    let (address, rest) = line.split_once(' ')?;
+    let address = u64::from_str_radix(address, 16).ok()?;
+    let (field, rest) = rest.split_once(' ')?;
+    let (size, kind, name) = if field.len() == 1 {
+        (0, field, rest)
+    } else {
+        let (kind, name) = rest.split_once(' ')?;
+        (u64::from_str_radix(field, 16).ok()?, kind, name)
+    };
+    matches!(kind, "T" | "t" | "W" | "w").then_some((address, size, name))
+}
+
+/// 将Unix时间戳格式化为 `YYYY-MM-DD hh:mm:ss UTC`
+fn format_utc(time: u64) -> String {
+    let days = time / 86400;
+    let seconds = time % 86400;
+    // 以3月1日为一年的开始计算公历日期，闰日位于一年的末尾
+    let days = days as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
@@ -0,0 +1,259 @@
+//! 构建配置，读取自项目根目录下的 `cos-build.toml`
+//!
+//! 配置文件不存在时使用默认配置，与 `cos-build.toml` 中的默认值一致
+
+use std::{fs, io, path::Path};
+
+use filesystem::fs::fat32::FormatOptions;
+use serde::Deserialize;
+
+/// 配置文件路径
+pub const CONFIG_PATH: &str = "./cos-build.toml";
+
+/// 引导程序分区之外，MBR最多还能容纳的分区数量
+const MAX_EXTRA_PARTITIONS: usize = 3;
+
+/// 内核命令行的最大长度，与bootloader/src/config.rs保持一致
+pub const MAX_CMDLINE_LEN: usize = 256;
+
+const MIB: u64 = 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuildConfig {
+    /// 传递给内核的命令行
+    #[serde(default)]
+    pub cmdline: String,
+    #[serde(default)]
+    pub disk: DiskConfig,
+    /// 引导程序分区之后的分区，按顺序排列
+    #[serde(default = "default_partitions")]
+    pub partitions: Vec<PartitionConfig>,
+    /// 打包进磁盘与initramfs的系统应用，位于user/system中
+    #[serde(default = "default_applications")]
+    pub applications: Vec<String>,
+    /// 打包进/system/drivers的内核模块，位于user/drivers中
+    #[serde(default)]
+    pub drivers: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiskConfig {
+    /// 磁盘镜像大小，单位为MiB
+    pub size_mib: u64,
+    /// 磁盘镜像格式，默认为raw
+    #[serde(default)]
+    pub format: DiskFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiskFormat {
+    /// 与磁盘大小相同的原始镜像
+    #[default]
+    Raw,
+    /// qcow2镜像，只保存写入过的簇，文件大小与磁盘大小无关
+    Qcow2,
+}
+
+impl DiskFormat {
+    /// qemu中 `-drive format=` 的取值
+    pub fn qemu_name(self) -> &'static str {
+        match self {
+            Self::Raw => "raw",
+            Self::Qcow2 => "qcow2",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartitionConfig {
+    #[serde(rename = "type")]
+    pub kind: PartitionKind,
+    /// 分区大小，单位为MiB，省略时占用剩余全部空间
+    pub size_mib: Option<u64>,
+    /// 以下为fat32分区的格式化选项，省略时使用默认值
+    /// 每簇大小，单位为KiB，必须为2的整数次幂，默认为4
+    pub cluster_size_kib: Option<u64>,
+    /// 卷标，最长11个ASCII字符
+    pub volume_label: Option<String>,
+    /// OEM标识，最长8个ASCII字符
+    pub oem_id: Option<String>,
+    /// FAT表数量，1或2，默认为2
+    pub fat_count: Option<u8>,
+    /// 是否将数据区对齐到1MiB
+    #[serde(default)]
+    pub align_1mib: bool,
+    /// encrypted分区的口令，该类分区必须设置
+    pub passphrase: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartitionKind {
+    /// FAT32文件系统，写入系统应用，作为内核的根文件系统
+    Fat32,
+    /// 未格式化的空白分区
+    Raw,
+    /// 崩溃转储分区，内核panic时写入崩溃转储
+    Crash,
+    /// 加密卷分区，其中为空白的FAT32文件系统，启动后以口令挂载
+    Encrypted,
+}
+
+impl Default for BuildConfig {
+    fn default() -> Self {
+        Self {
+            cmdline: String::new(),
+            disk: DiskConfig::default(),
+            partitions: default_partitions(),
+            applications: default_applications(),
+            drivers: Vec::new(),
+        }
+    }
+}
+
+impl Default for DiskConfig {
+    fn default() -> Self {
+        Self {
+            size_mib: 10,
+            format: DiskFormat::Raw,
+        }
+    }
+}
+
+fn default_partitions() -> Vec<PartitionConfig> {
+    let partition = |kind, size_mib| PartitionConfig {
+        kind,
+        size_mib,
+        cluster_size_kib: None,
+        volume_label: None,
+        oem_id: None,
+        fat_count: None,
+        align_1mib: false,
+        passphrase: None,
+    };
+    vec![
+        partition(PartitionKind::Crash, Some(1)),
+        partition(PartitionKind::Fat32, None),
+    ]
+}
+
+fn default_applications() -> Vec<String> {
+    [
+        "init", "shell", "edit", "cp", "mv", "rm", "cat", "hexdump", "stat", "chmod", "chown",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+impl BuildConfig {
+    /// 读取并校验配置文件，配置文件不存在时返回默认配置
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let config = match fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content)
+                .unwrap_or_else(|error| panic!("failed to parse {}: {error}", path.display())),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(error) => panic!("failed to read {}: {error}", path.display()),
+        };
+        config.validate();
+        config
+    }
+
+    fn validate(&self) {
+        assert!(
+            self.cmdline.len() <= MAX_CMDLINE_LEN,
+            "cmdline must not exceed {MAX_CMDLINE_LEN} bytes"
+        );
+        assert!(self.disk.size_mib > 0, "disk size must not be zero");
+        assert!(
+            self.partitions.len() <= MAX_EXTRA_PARTITIONS,
+            "at most {MAX_EXTRA_PARTITIONS} partitions can be configured"
+        );
+        assert_eq!(
+            self.partitions
+                .iter()
+                .filter(|partition| partition.kind == PartitionKind::Fat32)
+                .count(),
+            1,
+            "exactly one fat32 partition is required"
+        );
+        assert!(
+            self.partitions
+                .iter()
+                .filter(|partition| partition.kind == PartitionKind::Crash)
+                .count()
+                <= 1,
+            "at most one crash partition can be configured"
+        );
+        assert!(
+            self.partitions
+                .iter()
+                .rev()
+                .skip(1)
+                .all(|partition| partition.size_mib.is_some()),
+            "only the last partition can omit size"
+        );
+        for partition in &self.partitions {
+            assert_eq!(
+                partition.passphrase.is_some(),
+                partition.kind == PartitionKind::Encrypted,
+                "passphrase is required for encrypted partitions and only allowed for them"
+            );
+            if partition.kind != PartitionKind::Fat32 {
+                assert!(
+                    partition.cluster_size_kib.is_none()
+                        && partition.volume_label.is_none()
+                        && partition.oem_id.is_none()
+                        && partition.fat_count.is_none()
+                        && !partition.align_1mib,
+                    "format options are only allowed for fat32 partitions"
+                );
+            }
+            if let Some(cluster_size_kib) = partition.cluster_size_kib {
+                assert!(
+                    cluster_size_kib.is_power_of_two() && cluster_size_kib <= 64,
+                    "cluster size must be a power of two not exceeding 64 KiB"
+                );
+            }
+        }
+        assert!(
+            self.applications
+                .iter()
+                .any(|application| application == "init"),
+            "application init is required"
+        );
+    }
+
+    /// 磁盘镜像大小，单位为字节
+    pub fn disk_size(&self) -> u64 {
+        self.disk.size_mib * MIB
+    }
+}
+
+impl PartitionConfig {
+    /// 分区大小，单位为扇区
+    pub fn sectors(&self) -> Option<u32> {
+        self.size_mib
+            .map(|size| u32::try_from(size * MIB / 512).expect("partition is too large for mbr"))
+    }
+
+    /// fat32分区的格式化选项，扇区大小为512字节
+    pub fn format_options(&self) -> FormatOptions {
+        let default = FormatOptions::default();
+        FormatOptions {
+            sectors_per_cluster: self
+                .cluster_size_kib
+                .map_or(default.sectors_per_cluster, |size| {
+                    (size * 1024 / 512) as u8
+                }),
+            volume_label: self.volume_label.clone().unwrap_or(default.volume_label),
+            oem_id: self.oem_id.clone().unwrap_or(default.oem_id),
+            num_fats: self.fat_count.unwrap_or(default.num_fats),
+            align_1mib: self.align_1mib,
+        }
+    }
+}
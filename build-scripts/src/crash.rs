@@ -0,0 +1,184 @@
+//! 在宿主机上读取磁盘镜像中的崩溃转储，格式见kernel/src/crash_dump.rs
+//!
+//! 崩溃转储读取自崩溃转储分区，即使系统已无法启动也可以查看。调用栈与栈中的地址按内核符号表解析为符号，
+//! 符号表默认读取自镜像中的 [KERNEL_MAP_PATH]
+
+use std::{fs, path::Path, sync::Arc};
+
+use filesystem::{
+    device::{
+        BlockDevice,
+        mbr::{MbrPartitionDevice, PARTITION_TYPE_CRASH, PARTITION_TYPE_FAT32},
+    },
+    fs::{FileSystem, fat32::Fat32FileSystem},
+    path::PathBuf,
+};
+
+use crate::{adapter, block_on, build_info::KERNEL_MAP_PATH};
+
+/// 崩溃转储的magic，需与kernel/src/crash_dump.rs保持一致
+const DUMP_MAGIC: &[u8; 16] = b"COS_CRASH_DUMP__";
+/// 崩溃转储格式的版本，需与kernel/src/crash_dump.rs保持一致
+const DUMP_VERSION: u32 = 1;
+/// 崩溃转储尚未被内核保存，需与kernel/src/crash_dump.rs保持一致
+const FLAG_UNREAD: u32 = 1;
+/// 头部记录的最大调用栈深度，需与kernel/src/crash_dump.rs保持一致
+const MAX_FRAMES: usize = 32;
+const SECTOR_SIZE: usize = 512;
+
+/// 崩溃转储的头部
+struct Header {
+    flags: u32,
+    /// panic时的启动时间，单位为纳秒
+    uptime: u64,
+    report_size: usize,
+    stack_size: usize,
+    stack_address: u64,
+    frames: Vec<u64>,
+}
+
+/// 内核符号表，按地址升序排列的 (地址, 大小, 名称)
+struct SymbolMap(Vec<(u64, u64, String)>);
+
+pub fn inspect_crash(image: &Path, map: Option<&Path>, stack: bool) {
+    let disk = adapter::open_image(image)
+        .unwrap_or_else(|error| panic!("failed to open {}: {error}", image.display()));
+    let partitions =
+        block_on(MbrPartitionDevice::mount(disk)).expect("failed to read mbr partition table");
+    let (mut crash, mut root) = (None, None);
+    for partition in partitions.into_iter().flatten() {
+        match partition.get_partition_type() {
+            PARTITION_TYPE_CRASH => crash = Some(partition),
+            PARTITION_TYPE_FAT32 => root = Some(partition),
+            _ => {}
+        }
+    }
+    let Some(partition) = crash else {
+        println!("no crash dump partition in image");
+        return;
+    };
+
+    let mut sector = [0u8; SECTOR_SIZE];
+    block_on(partition.read_block(0, &mut sector)).expect("failed to read crash dump partition");
+    let Some(header) = parse_header(&sector) else {
+        println!("no crash dump in image");
+        return;
+    };
+    let report_sectors = header.report_size.div_ceil(SECTOR_SIZE);
+    let sectors = (1 + report_sectors + header.stack_size.div_ceil(SECTOR_SIZE)) as u64;
+    assert!(
+        sectors <= partition.block_count(),
+        "crash dump exceeds crash dump partition"
+    );
+    let mut dump = vec![0u8; sectors as usize * SECTOR_SIZE];
+    block_on(partition.read_blocks(0, sectors, &mut dump))
+        .expect("failed to read crash dump partition");
+
+    let symbols = match map {
+        Some(map) => fs::read(map)
+            .unwrap_or_else(|error| panic!("failed to read {}: {error}", map.display())),
+        None => root.map(read_image_map).unwrap_or_default(),
+    };
+    let symbols = SymbolMap::parse(&String::from_utf8_lossy(&symbols));
+
+    let state = if header.flags & FLAG_UNREAD != 0 {
+        "not saved by kernel yet"
+    } else {
+        "saved by kernel"
+    };
+    println!(
+        "crash dump at {}.{:06}s after boot ({state})",
+        header.uptime / 1_000_000_000,
+        header.uptime % 1_000_000_000 / 1000
+    );
+    println!();
+    let report = &dump[SECTOR_SIZE..SECTOR_SIZE + header.report_size];
+    print!("{}", String::from_utf8_lossy(report));
+    println!();
+    println!("backtrace:");
+    for (index, &frame) in header.frames.iter().enumerate() {
+        println!("  #{index:<2} {frame:016x} {}", symbols.describe(frame));
+    }
+
+    if stack {
+        println!();
+        println!("stack:");
+        let offset = SECTOR_SIZE * (1 + report_sectors);
+        let words = dump[offset..offset + header.stack_size].chunks_exact(8);
+        for (index, word) in words.enumerate() {
+            let value = u64::from_le_bytes(word.try_into().unwrap());
+            println!(
+                "  {:016x}: {value:016x} {}",
+                header.stack_address + index as u64 * 8,
+                symbols.describe(value)
+            );
+        }
+    }
+}
+
+fn parse_header(sector: &[u8; SECTOR_SIZE]) -> Option<Header> {
+    let u32_at = |offset: usize| u32::from_le_bytes(sector[offset..offset + 4].try_into().unwrap());
+    let u64_at = |offset: usize| u64::from_le_bytes(sector[offset..offset + 8].try_into().unwrap());
+    if &sector[..DUMP_MAGIC.len()] != DUMP_MAGIC || u32_at(16) != DUMP_VERSION {
+        return None;
+    }
+    let depth = (u32_at(48) as usize).min(MAX_FRAMES);
+    Some(Header {
+        flags: u32_at(20),
+        uptime: u64_at(24),
+        report_size: u32_at(32) as usize,
+        stack_size: u32_at(36) as usize,
+        stack_address: u64_at(40),
+        frames: (0..depth).map(|index| u64_at(56 + index * 8)).collect(),
+    })
+}
+
+/// 从镜像的FAT32分区中读取内核符号表，无法读取时返回空
+fn read_image_map(partition: MbrPartitionDevice) -> Vec<u8> {
+    let Ok(fs) = block_on(Fat32FileSystem::mount(Arc::new(partition))) else {
+        return Vec::new();
+    };
+    let path =
+        PathBuf::from_str(KERNEL_MAP_PATH).expect("codebug: kernel map path should be valid");
+    let mut content = Vec::new();
+    if let Ok(mut file) = block_on(fs.open_file(path.as_path())) {
+        let mut buf = [0u8; 8192];
+        while let Ok(len) = block_on(file.read(&mut buf))
+            && len > 0
+        {
+            content.extend_from_slice(&buf[..len as usize]);
+        }
+        _ = block_on(file.close());
+    }
+    _ = block_on(fs.unmount());
+    content
+}
+
+impl SymbolMap {
+    /// 解析符号表，格式见build-scripts/src/build_info.rs
+    fn parse(map: &str) -> Self {
+        let symbols = map
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, ' ');
+                let address = u64::from_str_radix(fields.next()?, 16).ok()?;
+                let size = u64::from_str_radix(fields.next()?, 16).ok()?;
+                Some((address, size, fields.next()?.to_string()))
+            })
+            .collect();
+        Self(symbols)
+    }
+
+    /// 地址所在的符号，如 `kernel::main+0x1a`，不在任何符号中时返回空字符串
+    fn describe(&self, address: u64) -> String {
+        let index = self.0.partition_point(|(start, _, _)| *start <= address);
+        let Some((start, size, name)) = index.checked_sub(1).map(|index| &self.0[index]) else {
+            return String::new();
+        };
+        // 没有大小的符号只匹配其起始地址
+        if address - start >= (*size).max(1) {
+            return String::new();
+        }
+        format!("{name}+{:#x}", address - start)
+    }
+}
@@ -0,0 +1,42 @@
+//! 增量构建：根据修改时间判断构建阶段的产物是否需要重新生成
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// 判断产物是否比全部输入都新
+///
+/// 输入可以是文件或目录，目录会被递归遍历（跳过cargo的target目录）。
+/// 产物不存在时返回false；不存在的输入会被忽略，缺失的必要输入由构建阶段自身报错
+pub fn is_up_to_date(output: impl AsRef<Path>, inputs: &[PathBuf]) -> bool {
+    let Some(output_time) = modified(output.as_ref()) else {
+        return false;
+    };
+    inputs
+        .iter()
+        .filter_map(|input| newest(input))
+        .all(|input_time| input_time <= output_time)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// 路径下最新的修改时间
+fn newest(path: &Path) -> Option<SystemTime> {
+    let metadata = fs::metadata(path).ok()?;
+    if !metadata.is_dir() {
+        return metadata.modified().ok();
+    }
+
+    fs::read_dir(path)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name() != "target")
+        .filter_map(|entry| newest(&entry.path()))
+        .max()
+}
@@ -0,0 +1,250 @@
+//! 在宿主机上检查、修改COS磁盘镜像，无需启动qemu
+
+use std::{
+    fs::{self, File},
+    io::{Read, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use filesystem::{
+    device::{
+        BlockDevice,
+        mbr::{
+            MbrPartitionDevice, PARTITION_TYPE_BOOTLOADER, PARTITION_TYPE_CRASH,
+            PARTITION_TYPE_FAT32,
+        },
+    },
+    fs::{FileSystem, FileSystemError, fat32::Fat32FileSystem},
+    path::PathBuf,
+};
+
+use crate::{adapter, block_on};
+
+#[derive(clap::Subcommand)]
+pub enum InspectCommand {
+    /// 列出分区表
+    Partitions,
+    /// 列出文件系统中的目录树
+    Tree {
+        /// 镜像中的目录
+        #[arg(default_value = "/")]
+        path: String,
+    },
+    /// 将镜像中的文件或目录提取到宿主机
+    Extract {
+        /// 镜像中的文件或目录
+        path: String,
+        /// 宿主机中的目标路径
+        output: std::path::PathBuf,
+    },
+    /// 将宿主机中的文件或目录写入镜像，已存在的文件会被覆盖，缺失的父目录会被创建
+    Inject {
+        /// 宿主机中的文件或目录
+        input: std::path::PathBuf,
+        /// 镜像中的目标路径
+        path: String,
+    },
+}
+
+pub fn inspect(image: &Path, command: InspectCommand) {
+    let disk = adapter::open_image(image)
+        .unwrap_or_else(|error| panic!("failed to open {}: {error}", image.display()));
+    let partitions =
+        block_on(MbrPartitionDevice::mount(disk)).expect("failed to read mbr partition table");
+
+    if let InspectCommand::Partitions = command {
+        print_partitions(&partitions);
+        return;
+    }
+
+    let partition = partitions
+        .into_iter()
+        .flatten()
+        .find(|partition| partition.get_partition_type() == PARTITION_TYPE_FAT32)
+        .expect("no fat32 partition in image");
+    let fs = block_on(Fat32FileSystem::mount(Arc::new(partition)))
+        .expect("failed to mount fat32 partition");
+
+    match command {
+        InspectCommand::Partitions => unreachable!(),
+        InspectCommand::Tree { path } => {
+            let path = parse_path(&path);
+            println!("{}", display_path(&path));
+            print_tree(&fs, &path, 1);
+        }
+        InspectCommand::Extract { path, output } => extract(&fs, &parse_path(&path), &output),
+        InspectCommand::Inject { input, path } => {
+            let path = parse_path(&path);
+            create_parent_directories(&fs, &path);
+            inject(&fs, &input, &path);
+        }
+    }
+
+    block_on(fs.unmount()).expect("failed to unmount file system");
+}
+
+fn print_partitions(partitions: &[Option<MbrPartitionDevice>]) {
+    println!("index  type  start     sectors   size");
+    for (index, partition) in partitions.iter().enumerate() {
+        let Some(partition) = partition else {
+            continue;
+        };
+        let partition_type = partition.get_partition_type();
+        let description = match partition_type {
+            PARTITION_TYPE_BOOTLOADER => "bootloader",
+            PARTITION_TYPE_FAT32 => "fat32",
+            PARTITION_TYPE_CRASH => "crash",
+            _ => "unknown",
+        };
+        let sectors = partition.block_count();
+        println!(
+            "{index:<6} {partition_type:#04x}  {:<9} {sectors:<9} {} ({description})",
+            partition.get_partition_start(),
+            format_size(sectors * partition.block_size()),
+        );
+    }
+}
+
+fn print_tree(fs: &Fat32FileSystem, path: &PathBuf, depth: usize) {
+    let mut entries = block_on(fs.list_directory(path.as_path()))
+        .unwrap_or_else(|error| panic!("failed to list {}: {error:?}", display_path(path)));
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for entry in entries {
+        let indent = "  ".repeat(depth);
+        if entry.is_directory {
+            println!("{indent}{}/", entry.name);
+            print_tree(fs, &join(path, &entry.name), depth + 1);
+        } else {
+            println!("{indent}{} ({})", entry.name, format_size(entry.size));
+        }
+    }
+}
+
+fn extract(fs: &Fat32FileSystem, path: &PathBuf, output: &Path) {
+    if is_directory(fs, path) {
+        fs::create_dir_all(output)
+            .unwrap_or_else(|error| panic!("failed to create {}: {error}", output.display()));
+        let entries = block_on(fs.list_directory(path.as_path()))
+            .unwrap_or_else(|error| panic!("failed to list {}: {error:?}", display_path(path)));
+        for entry in entries {
+            extract(fs, &join(path, &entry.name), &output.join(&entry.name));
+        }
+        return;
+    }
+
+    let mut file = block_on(fs.open_file(path.as_path()))
+        .unwrap_or_else(|error| panic!("failed to open {}: {error:?}", display_path(path)));
+    let mut host_file = File::create(output)
+        .unwrap_or_else(|error| panic!("failed to create {}: {error}", output.display()));
+    let mut buf = [0u8; 8192];
+    loop {
+        let len = block_on(file.read(&mut buf)).expect("failed to read from file") as usize;
+        if len == 0 {
+            break;
+        }
+        host_file
+            .write_all(&buf[..len])
+            .expect("failed to write to host file");
+    }
+    block_on(file.close()).expect("failed to close file");
+    println!("{} -> {}", display_path(path), output.display());
+}
+
+fn inject(fs: &Fat32FileSystem, input: &Path, path: &PathBuf) {
+    if input.is_dir() {
+        match block_on(fs.create_directory(path.as_path())) {
+            Ok(()) | Err(FileSystemError::FileExists) => {}
+            Err(error) => panic!("failed to create {}: {error:?}", display_path(path)),
+        }
+        let entries = fs::read_dir(input)
+            .unwrap_or_else(|error| panic!("failed to read {}: {error}", input.display()));
+        for entry in entries {
+            let entry = entry.expect("failed to read host directory entry");
+            let name = entry.file_name();
+            let name = name.to_str().expect("host file name is not valid utf-8");
+            inject(fs, &entry.path(), &join(path, name));
+        }
+        return;
+    }
+
+    // 覆盖已存在的文件
+    match block_on(fs.delete_file(path.as_path())) {
+        Ok(()) | Err(FileSystemError::FileNotFound) => {}
+        Err(error) => panic!("failed to replace {}: {error:?}", display_path(path)),
+    }
+    block_on(fs.create_file(path.as_path()))
+        .unwrap_or_else(|error| panic!("failed to create {}: {error:?}", display_path(path)));
+    let mut file = block_on(fs.open_file(path.as_path())).expect("failed to open file");
+    let mut host_file = File::open(input)
+        .unwrap_or_else(|error| panic!("failed to open {}: {error}", input.display()));
+    let mut buf = [0u8; 8192];
+    loop {
+        let len = host_file
+            .read(&mut buf)
+            .expect("failed to read from host file");
+        if len == 0 {
+            break;
+        }
+        block_on(file.write(&buf[..len])).expect("failed to write to file");
+    }
+    block_on(file.close()).expect("failed to close file");
+    println!("{} -> {}", input.display(), display_path(path));
+}
+
+fn create_parent_directories(fs: &Fat32FileSystem, path: &PathBuf) {
+    let path = path.as_path();
+    let mut parent = PathBuf::default();
+    let mut segments = path.iter().peekable();
+    while let Some(segment) = segments.next() {
+        // 最后一级由调用方创建
+        if segments.peek().is_none() {
+            break;
+        }
+        parent = join(&parent, segment);
+        match block_on(fs.create_directory(parent.as_path())) {
+            Ok(()) | Err(FileSystemError::FileExists) => {}
+            Err(error) => panic!("failed to create {}: {error:?}", display_path(&parent)),
+        }
+    }
+}
+
+fn is_directory(fs: &Fat32FileSystem, path: &PathBuf) -> bool {
+    if path.as_path().is_root() {
+        return true;
+    }
+    block_on(fs.get_metadata(path.as_path()))
+        .unwrap_or_else(|error| panic!("failed to access {}: {error:?}", display_path(path)))
+        .is_directory
+}
+
+fn parse_path(path: &str) -> PathBuf {
+    PathBuf::from_str(path).unwrap_or_else(|_| panic!("invalid path: {path}"))
+}
+
+fn join(path: &PathBuf, name: &str) -> PathBuf {
+    let mut path = path.clone();
+    path.extends(&parse_path(name));
+    path
+}
+
+fn display_path(path: &PathBuf) -> String {
+    let mut display = String::new();
+    for segment in path.as_path().iter() {
+        display.push('/');
+        display.push_str(segment);
+    }
+    if display.is_empty() {
+        display.push('/');
+    }
+    display
+}
+
+fn format_size(size: u64) -> String {
+    match size {
+        0..1024 => format!("{size} B"),
+        1024..0x100000 => format!("{:.1} KiB", size as f64 / 1024.0),
+        _ => format!("{:.1} MiB", size as f64 / 1024.0 / 1024.0),
+    }
+}
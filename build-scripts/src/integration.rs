@@ -0,0 +1,172 @@
+//! 集成测试：在qemu中启动测试镜像，通过串口收集测试结果
+//!
+//! loader与内核的测试用例（`#[test_case]`）以及user/system/test-runner均逐行输出以下格式的结果：
+//!
+//! ```txt
+//! COS-TEST BEGIN <用例数量>
+//! COS-TEST PASS <用例名>
+//! COS-TEST FAIL <用例名> <失败原因>
+//! COS-TEST PANIC <信息>
+//! COS-TEST END <通过数量> <失败数量>
+//! ```
+
+use std::{
+    io::{BufRead, BufReader},
+    path::Path,
+    process::{Command, Stdio},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::adapter;
+
+/// 结果行的前缀
+const RESULT_PREFIX: &str = "COS-TEST ";
+
+/// isa-debug-exit设备参数，端口需与kernel/src/io/qemu.rs保持一致
+const DEBUG_EXIT_DEVICE: &str = "isa-debug-exit,iobase=0xf4,iosize=0x04";
+
+/// 测试全部通过时qemu的退出码，即 `(0 << 1) | 1`
+const QEMU_EXIT_SUCCESS: i32 = 1;
+
+/// 测试结果中的一行
+#[derive(Debug)]
+enum TestEvent {
+    Begin(usize),
+    Pass(String),
+    Fail(String, String),
+    Panic(String),
+    End(usize, usize),
+}
+
+/// 启动qemu运行测试镜像，输出测试结果，返回是否全部通过
+pub fn run_tests(image: &Path, timeout: Duration) -> bool {
+    let format = adapter::detect_format(image)
+        .unwrap_or_else(|error| panic!("failed to open {}: {error}", image.display()));
+    let mut cmd = Command::new("qemu-system-x86_64");
+    cmd.arg("-drive").arg(format!(
+        "format={},file={}",
+        format.qemu_name(),
+        image.display()
+    ));
+    cmd.args(["-serial", "stdio", "-display", "none", "-no-reboot"]);
+    cmd.args(["-device", DEBUG_EXIT_DEVICE]);
+
+    cmd.stdin(Stdio::null());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::inherit());
+
+    let mut child = cmd
+        .spawn()
+        .expect("failed to start qemu-system-x86_64. may qemu is not installed?");
+
+    // 串口输出在单独的线程中读取，以便主线程处理超时
+    let stdout = child.stdout.take().expect("codebug: stdout is piped");
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let deadline = Instant::now() + timeout;
+    let mut expected = None;
+    let mut failures = Vec::new();
+    let mut passed = 0;
+    let mut finished = false;
+    let mut panicked = false;
+    loop {
+        let line = match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => {
+                println!("test timed out after {} seconds", timeout.as_secs());
+                _ = child.kill();
+                break;
+            }
+            // qemu已退出
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        let Some(event) = parse_line(&line) else {
+            // 内核日志等其他输出原样打印
+            println!("{line}");
+            continue;
+        };
+        match event {
+            TestEvent::Begin(count) => {
+                println!("running {count} tests");
+                expected = Some(count);
+            }
+            TestEvent::Pass(name) => {
+                println!("test {name} ... ok");
+                passed += 1;
+            }
+            TestEvent::Fail(name, reason) => {
+                println!("test {name} ... FAILED: {reason}");
+                failures.push(name);
+            }
+            TestEvent::Panic(message) => {
+                println!("test runner panicked: {message}");
+                panicked = true;
+            }
+            TestEvent::End(reported_passed, reported_failed) => {
+                finished = reported_passed == passed && reported_failed == failures.len();
+                if !finished {
+                    println!(
+                        "test runner reported {reported_passed} passed, {reported_failed} failed, but {passed} passed, {} failed were received",
+                        failures.len()
+                    );
+                }
+            }
+        }
+    }
+
+    let status = child.wait().expect("failed to wait qemu");
+    let exited_by_runner = status.code() == Some(QEMU_EXIT_SUCCESS);
+
+    println!();
+    if !failures.is_empty() {
+        println!("failures:");
+        for name in &failures {
+            println!("    {name}");
+        }
+    }
+    let complete = finished && expected == Some(passed + failures.len());
+    if !complete {
+        println!("test runner did not complete, qemu exited with {status}");
+    }
+    let success = complete && failures.is_empty() && !panicked && exited_by_runner;
+    println!(
+        "test result: {}. {passed} passed; {} failed",
+        if success { "ok" } else { "FAILED" },
+        failures.len()
+    );
+
+    success
+}
+
+/// 解析测试结果行，其他输出返回None
+fn parse_line(line: &str) -> Option<TestEvent> {
+    let line = line.trim_end().strip_prefix(RESULT_PREFIX)?;
+    let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+    match kind {
+        "BEGIN" => rest.parse().ok().map(TestEvent::Begin),
+        "PASS" => Some(TestEvent::Pass(rest.to_string())),
+        "FAIL" => {
+            let (name, reason) = rest.split_once(' ').unwrap_or((rest, ""));
+            Some(TestEvent::Fail(name.to_string(), reason.to_string()))
+        }
+        "PANIC" => Some(TestEvent::Panic(rest.to_string())),
+        "END" => {
+            let (passed, failed) = rest.split_once(' ')?;
+            Some(TestEvent::End(passed.parse().ok()?, failed.parse().ok()?))
+        }
+        _ => None,
+    }
+}
@@ -3,32 +3,86 @@
 //! 此crate在宿主机环境上运行，不会打包到产物中，因此此项目无需#![no_std]
 
 use std::{
-    fs::{self, File},
-    io::Read,
-    path::PathBuf,
+    ffi::OsStr,
+    fs,
+    hash::{DefaultHasher, Hasher},
+    io,
+    path::{Path, PathBuf},
     pin::pin,
-    process::{Command, Stdio},
+    process::{Child, Command, Stdio},
     str::FromStr,
     sync::Arc,
     task::{Context, Poll, Waker},
+    time::{Duration, SystemTime},
 };
 
 use clap::Parser;
 use filesystem::{
     device::{
         BlockDevice,
+        crypt::{CryptDevice, DEFAULT_ITERATIONS},
         mbr::{
-            MbrPartitionDevice, MbrPartitionEntry, PARTITION_TYPE_BOOTLOADER, PARTITION_TYPE_FAT32,
+            MbrPartitionDevice, MbrPartitionEntry, PARTITION_TYPE_BOOTLOADER, PARTITION_TYPE_CRASH,
+            PARTITION_TYPE_CRYPT, PARTITION_TYPE_FAT32,
         },
+        qcow2::{DEFAULT_CLUSTER_BITS, Qcow2Device},
     },
-    fs::{FileSystem, fat32::Fat32FileSystem},
+    fs::{
+        FileSystem,
+        fat32::Fat32FileSystem,
+        permission::{FilePermission, PermissionTable, ROOT_UID},
+    },
+    initramfs::InitramfsBuilder,
 };
 
-use crate::adapter::HostFileBlockDevice;
+use crate::{
+    adapter::HostFileBlockDevice,
+    build_info::{BUILD_INFO_PATH, KERNEL_MAP_PATH},
+    config::{BuildConfig, CONFIG_PATH, DiskFormat, MAX_CMDLINE_LEN, PartitionKind},
+    incremental::is_up_to_date,
+    inspect::InspectCommand,
+};
 
 mod adapter;
+mod build_info;
+mod config;
+mod crash;
+mod incremental;
+mod inspect;
+mod integration;
 
-const KERNEL_DISK_SIZE: u64 = 1024 * 1024 * 10; // 10M
+/// 空白分区的分区类型（Non-FS data）
+const PARTITION_TYPE_RAW: u8 = 0xDA;
+
+/// loader扁平二进制路径
+const LOADER_BINARY: &str = "./build/loader.bin";
+/// 磁盘镜像路径
+const DISK_IMAGE: &str = "./build/disk.img";
+/// 附加了initramfs与内核ELF的内核镜像，可在系统中以update命令安装到另一个内核槽位
+const KERNEL_IMAGE: &str = "./build/kernel.img";
+/// UEFI启动时的ESP目录，运行时由qemu作为FAT磁盘挂载
+const ESP_DIR: &str = "./build/esp";
+/// GRUB以Multiboot2启动时的目录，包含loader、内核镜像与grub.cfg
+const MULTIBOOT_DIR: &str = "./build/multiboot";
+/// 由MULTIBOOT_DIR生成的GRUB光盘镜像
+const MULTIBOOT_ISO: &str = "./build/multiboot.iso";
+/// GRUB配置，multiboot2命令中loader之后的参数非空时代替cos-build.toml中的命令行
+const GRUB_CONFIG: &str = r#"set timeout=0
+menuentry "COS" {
+    multiboot2 /boot/cos/loader.bin
+    module2 /boot/cos/kernel.img
+}
+"#;
+/// 测试镜像路径，与磁盘镜像相同，但/system/init被替换为测试程序
+const TEST_IMAGE: &str = "./build/test.img";
+/// 测试程序，位于user/system中
+const TEST_RUNNER: &str = "test-runner";
+/// loader测试镜像路径，loader启动后运行测试用例，不加载内核
+const LOADER_TEST_IMAGE: &str = "./build/test-loader.img";
+/// 内核测试镜像路径，内核初始化后运行测试用例，不启动init
+const KERNEL_TEST_IMAGE: &str = "./build/test-kernel.img";
+/// 系统调用模糊测试镜像路径，与内核测试镜像相同，但以启动选项fuzz=<seed>代替测试用例
+const KERNEL_FUZZ_IMAGE: &str = "./build/test-fuzz.img";
 
 #[derive(clap::Parser)]
 enum BuildArgs {
@@ -37,40 +91,303 @@ enum BuildArgs {
         /// 以debug模式编译内核，附带符号表
         #[arg(long)]
         debug: bool,
+        /// 忽略增量构建，重新生成全部产物
+        #[arg(long)]
+        force: bool,
+        /// 额外编译UEFI引导程序，并生成UEFI启动所需的ESP目录
+        #[arg(long)]
+        uefi: bool,
+        /// 额外生成GRUB以Multiboot2启动所需的目录及光盘镜像，需安装grub-mkrescue
+        #[arg(long)]
+        multiboot: bool,
     },
     /// 运行项目
     Run {
         /// qemu附加-S -s -no-reboot、-no-shutdown参数以便调试
         #[arg(long)]
         debug: bool,
+        /// 通过UEFI固件启动，需先执行build --uefi
+        #[arg(long)]
+        uefi: bool,
+        /// UEFI固件路径
+        #[arg(long, default_value = "/usr/share/ovmf/OVMF.fd")]
+        firmware: PathBuf,
+        /// 从GRUB光盘镜像以Multiboot2启动，需先执行build --multiboot
+        #[arg(long, conflicts_with = "uefi")]
+        multiboot: bool,
+    },
+    /// 编译测试镜像，在qemu中依次运行loader与内核的测试用例及集成测试，测试失败时以非零状态退出
+    Test {
+        /// 以debug模式编译内核，附带符号表
+        #[arg(long)]
+        debug: bool,
+        /// 测试超时时间，单位为秒
+        #[arg(long, default_value_t = 120)]
+        timeout: u64,
+        /// 系统调用模糊测试的随机种子，用于复现失败的测试，省略时随机选取
+        #[arg(long)]
+        fuzz_seed: Option<u64>,
+    },
+    /// 检查或修改磁盘镜像，无需启动qemu
+    Inspect {
+        /// 磁盘镜像路径
+        #[arg(long, default_value = "./build/disk.img")]
+        image: PathBuf,
+        #[command(subcommand)]
+        command: InspectCommand,
+    },
+    /// 查看磁盘镜像崩溃转储分区中的崩溃转储，调用栈按内核符号表解析为符号
+    InspectCrash {
+        /// 磁盘镜像路径
+        #[arg(long, default_value = "./build/disk.img")]
+        image: PathBuf,
+        /// 内核符号表路径，省略时读取镜像中的/system/kernel.map
+        #[arg(long)]
+        map: Option<PathBuf>,
+        /// 同时输出panic时的内核栈
+        #[arg(long)]
+        stack: bool,
     },
 }
 
-const SYSTEM_APPLICATIONS: &[&str] = &["init", "shell"];
+const WELCOME_MESSAGE: &[u8] =
+    b"Welcome to COS shell!\nThis welcome message is from /system/welcome.txt!\n";
+
+/// 系统程序摘要清单路径，需与kernel/src/multitask/exec_verify.rs保持一致
+const MANIFEST_PATH: &str = "/system/manifest.sha256";
+
+/// 内核槽位A的路径，需与library/boot_info/src/slot.rs保持一致
+const KERNEL_SLOT_A_PATH: &str = "/system/kernel-a.bin";
+/// 内核槽位B的路径，需与library/boot_info/src/slot.rs保持一致。构建时不写入，由内核更新时创建
+const KERNEL_SLOT_B_PATH: &str = "/system/kernel-b.bin";
+/// 启动选择记录的路径，需与library/boot_info/src/slot.rs保持一致
+const BOOT_SLOT_PATH: &str = "/system/boot-slot";
+/// 初始的启动选择记录：从槽位A启动，且已确认成功。格式见library/boot_info/src/slot.rs
+const BOOT_SLOT_RECORD: [u8; 16] = *b"COS_SLOT\0\0\0\0\0\0\0\0";
+/// 内核镜像结尾的内核ELF位置信息的magic，需与bootloader/src/loader.rs保持一致
+const KERNEL_TRAILER_MAGIC: &[u8; 16] = b"COS_KERNEL_ELF__";
+
+/// 内核模块所在目录，需与kernel/src/module/mod.rs保持一致
+const DRIVER_DIR: &str = "/system/drivers";
+/// 内核模块签名的结尾标记，需与kernel/src/module/mod.rs保持一致
+const MODULE_SIGNATURE_MAGIC: &[u8] = b"~cos module signature~\n";
+/// 内核模块签名私钥（Ed25519种子，64位十六进制）的环境变量，只由构建脚本读取
+const MODULE_KEY_ENV: &str = "COS_MODULE_KEY";
+/// 内核模块验证公钥的环境变量，需与kernel/src/module/mod.rs保持一致
+const MODULE_PUBLIC_KEY_ENV: &str = "COS_MODULE_PUBLIC_KEY";
+/// 内核模块编译产物目录
+const DRIVER_OUTPUT_DIR: &str = "./build/drivers";
+
+/// 内核中记录initramfs位置的结构的magic，需与kernel/src/io/initramfs.rs保持一致
+const INITRAMFS_LOCATION_MAGIC: &[u8; 16] = b"COS_INITRAMFS_AT";
+/// loader中引导配置结构的magic，需与bootloader/src/config.rs保持一致
+const BOOT_CONFIG_MAGIC: &[u8; 16] = b"COS_BOOT_CONFIG_";
+
+/// 内核编译产物
+struct KernelOutput {
+    /// 扁平二进制，由BIOS引导程序加载
+    binary: PathBuf,
+    /// 去除调试信息的ELF，保留符号表，附加在内核镜像之后供内核使用，UEFI引导程序也从中加载内核
+    elf: PathBuf,
+    /// 编译配置，写入镜像的构建信息
+    profile: &'static str,
+}
 
 fn main() {
     let arg = BuildArgs::parse();
 
     match arg {
-        BuildArgs::Build { debug } => build(debug),
-        BuildArgs::Run { debug } => run(debug),
+        BuildArgs::Build {
+            debug,
+            force,
+            uefi,
+            multiboot,
+        } => build(debug, force, uefi, multiboot),
+        BuildArgs::Run {
+            debug,
+            uefi,
+            firmware,
+            multiboot,
+        } => run(debug, uefi.then_some(firmware.as_path()), multiboot),
+        BuildArgs::Test {
+            debug,
+            timeout,
+            fuzz_seed,
+        } => test(debug, timeout, fuzz_seed),
+        BuildArgs::Inspect { image, command } => inspect::inspect(&image, command),
+        BuildArgs::InspectCrash { image, map, stack } => {
+            crash::inspect_crash(&image, map.as_deref(), stack)
+        }
+    }
+}
+
+fn build(debug: bool, force: bool, uefi: bool, multiboot: bool) {
+    let config = BuildConfig::load(CONFIG_PATH);
+    // UEFI引导程序与其他项目并行编译
+    let uefi_loader = uefi.then(compile_uefi_loader);
+    let kernel = compile(&config, debug, force);
+    let applications = read_system_applications(&config);
+    build_image(
+        &config,
+        Path::new(LOADER_BINARY),
+        &kernel,
+        DISK_IMAGE,
+        &applications,
+        force,
+    );
+    write_kernel_image(&kernel, &applications);
+
+    if let Some(uefi_loader) = uefi_loader {
+        wait_cargo(uefi_loader, "uefi loader");
+        build_esp(&config, &kernel, &applications);
+    }
+    if multiboot {
+        build_multiboot(&config);
     }
 }
 
-fn build(debug: bool) {
+fn test(debug: bool, timeout: u64, fuzz_seed: Option<u64>) {
+    let config = BuildConfig::load(CONFIG_PATH);
+    let kernel = compile(&config, debug, false);
+    let applications = read_system_applications(&config);
+    let timeout = Duration::from_secs(timeout);
+    let mut success = true;
+
+    // loader与内核的测试用例以cargo test编译，分别替换镜像中的loader与内核
+    println!("loader tests:");
+    let loader_tests = extract_loader_test_binary(&compile_tests("./bootloader", false));
+    build_image(
+        &config,
+        &loader_tests,
+        &kernel,
+        LOADER_TEST_IMAGE,
+        &applications,
+        false,
+    );
+    success &= integration::run_tests(Path::new(LOADER_TEST_IMAGE), timeout);
+
+    println!();
+    println!("kernel tests:");
+    let kernel_tests = extract_kernel_test_output(&compile_tests("./kernel", debug));
+    build_image(
+        &config,
+        Path::new(LOADER_BINARY),
+        &kernel_tests,
+        KERNEL_TEST_IMAGE,
+        &applications,
+        false,
+    );
+    success &= integration::run_tests(Path::new(KERNEL_TEST_IMAGE), timeout);
+
+    // 内核测试构建以fuzz启动选项启动时，以随机的系统调用测试桩进程
+    let fuzz_seed = fuzz_seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64)
+    });
+    println!();
+    println!("syscall fuzzing (seed {fuzz_seed}, reproduce with --fuzz-seed {fuzz_seed}):");
+    let mut fuzz_config = BuildConfig::load(CONFIG_PATH);
+    fuzz_config.cmdline = format!("{} fuzz={fuzz_seed}", fuzz_config.cmdline)
+        .trim_start()
+        .to_string();
+    build_image(
+        &fuzz_config,
+        Path::new(LOADER_BINARY),
+        &kernel_tests,
+        KERNEL_FUZZ_IMAGE,
+        &applications,
+        false,
+    );
+    success &= integration::run_tests(Path::new(KERNEL_FUZZ_IMAGE), timeout);
+
+    // 测试程序作为init启动，从而拥有特权，测试结束后可以退出qemu
+    println!();
+    println!("system tests:");
+    let test_runner = read_system_application(TEST_RUNNER);
+    let applications = applications
+        .into_iter()
+        .map(|(name, binary)| match name.as_str() {
+            "init" => (name, test_runner.clone()),
+            _ => (name, binary),
+        })
+        .collect::<Vec<_>>();
+    build_image(
+        &config,
+        Path::new(LOADER_BINARY),
+        &kernel,
+        TEST_IMAGE,
+        &applications,
+        false,
+    );
+    success &= integration::run_tests(Path::new(TEST_IMAGE), timeout);
+
+    if !success {
+        std::process::exit(1);
+    }
+}
+
+/// 编译引导程序、内核、系统应用与内核模块，返回内核产物的路径
+fn compile(config: &BuildConfig, debug: bool, force: bool) -> KernelOutput {
     fs::create_dir_all("build").expect("failed to create build cache dir");
-    compile_boot_asm();
-    compile_loader();
-    extract_loader_binary();
-    compile_kernel(debug);
-    extract_kernel_binary(debug);
-    compile_system_application();
-    build_image();
+
+    // 各cargo项目相互独立，并行编译。cargo自身是增量的，无需跳过
+    let loader = compile_loader();
+    let kernel = compile_kernel(debug);
+    let system_application = compile_system_application();
+    let drivers = config
+        .drivers
+        .iter()
+        .map(|driver| (driver, compile_driver(driver)))
+        .collect::<Vec<_>>();
+    compile_boot_asm(force);
+    wait_cargo(loader, "bootloader");
+    wait_cargo(kernel, "kernel");
+    wait_cargo(system_application, "system application");
+    for (driver, child) in drivers {
+        wait_cargo(child, &format!("driver {driver}"));
+    }
+
+    extract_loader_binary(force);
+    KernelOutput {
+        binary: extract_kernel_binary(debug, force),
+        elf: extract_kernel_elf(debug, force),
+        profile: if debug { "debug" } else { "release" },
+    }
 }
 
-fn run(debug: bool) {
+/// 启动qemu，指定UEFI固件时通过UEFI启动，指定multiboot时从GRUB光盘镜像启动
+fn run(debug: bool, firmware: Option<&Path>, multiboot: bool) {
+    let format = adapter::detect_format(DISK_IMAGE)
+        .unwrap_or_else(|error| panic!("failed to open {DISK_IMAGE}: {error}"))
+        .qemu_name();
     let mut cmd = Command::new("qemu-system-x86_64");
-    cmd.args(["-drive", "format=raw,file=./build/disk.img"]);
+    match firmware {
+        None => {
+            cmd.arg("-drive")
+                .arg(format!("format={format},file={DISK_IMAGE}"));
+            // 光盘位于IDE副通道，COS磁盘仍为第一个硬盘
+            if multiboot {
+                cmd.args(["-cdrom", MULTIBOOT_ISO, "-boot", "d"]);
+            }
+        }
+        Some(firmware) => {
+            cmd.arg("-bios").arg(firmware);
+            // 内核通过IDE主通道主盘访问COS磁盘，ESP目录接在主通道从盘
+            cmd.arg("-drive")
+                .arg(format!("format={format},file={DISK_IMAGE},if=ide,index=0"));
+            cmd.arg("-drive")
+                .arg(format!("format=raw,file=fat:{ESP_DIR},if=ide,index=1"));
+            // UEFI下屏幕不显示VGA文本，内核日志通过串口查看
+            cmd.args(["-serial", "stdio"]);
+        }
+    }
+    // 使用qemu用户模式网络，内核仅支持virtio网卡
+    // 宿主机的7777端口转发至echo-server监听的7端口
+    cmd.args([
+        "-nic",
+        "user,model=virtio-net-pci,hostfwd=tcp:127.0.0.1:7777-:7",
+    ]);
     if debug {
         cmd.arg("-S")
             .arg("-s")
@@ -92,7 +409,11 @@ fn run(debug: bool) {
     }
 }
 
-fn compile_boot_asm() {
+fn compile_boot_asm(force: bool) {
+    if !force && is_up_to_date("./build/boot.bin", &[PathBuf::from("./bootloader/asm")]) {
+        return;
+    }
+
     let mut cmd = Command::new("nasm");
     cmd.arg("-f").arg("bin");
     cmd.arg("./bootloader/asm/boot.s");
@@ -112,7 +433,7 @@ fn compile_boot_asm() {
     }
 }
 
-fn compile_loader() {
+fn compile_loader() -> Child {
     let mut cmd = Command::new("cargo");
     cmd.arg("build").arg("--release");
     cmd.current_dir(
@@ -124,14 +445,10 @@ fn compile_loader() {
     cmd.stdin(Stdio::inherit());
     cmd.stdout(Stdio::inherit());
     cmd.stderr(Stdio::inherit());
-    let mut child = cmd.spawn().expect("failed to build bootloader");
-    let status = child.wait().expect("failed to build bootloader");
-    if !status.success() {
-        panic!("failed to build bootloader: cargo exit with non-zero status: {status}")
-    }
+    cmd.spawn().expect("failed to build bootloader")
 }
 
-fn compile_kernel(debug: bool) {
+fn compile_kernel(debug: bool) -> Child {
     let mut cmd = Command::new("cargo");
     cmd.arg("build");
     if !debug {
@@ -143,17 +460,44 @@ fn compile_kernel(debug: bool) {
             .canonicalize()
             .unwrap(),
     );
+    // 内核只编译进公钥，私钥不传给内核的编译过程
+    cmd.env_remove(MODULE_KEY_ENV);
+    if let Some(secret) = module_secret_key() {
+        let public = crypto::ed25519::public_key(&secret);
+        let public: String = public.iter().map(|byte| format!("{byte:02x}")).collect();
+        cmd.env(MODULE_PUBLIC_KEY_ENV, public);
+    }
+    cmd.stdin(Stdio::inherit());
+    cmd.stdout(Stdio::inherit());
+    cmd.stderr(Stdio::inherit());
+    cmd.spawn().expect("failed to build kernel")
+}
+
+fn compile_uefi_loader() -> Child {
+    let mut cmd = Command::new("cargo");
+    cmd.arg("build").arg("--release");
+    cmd.current_dir(
+        PathBuf::from_str("./uefi-loader")
+            .unwrap()
+            .canonicalize()
+            .unwrap(),
+    );
     cmd.stdin(Stdio::inherit());
     cmd.stdout(Stdio::inherit());
     cmd.stderr(Stdio::inherit());
-    let mut child = cmd.spawn().expect("failed to build kernel");
-    let status = child.wait().expect("failed to build kernel");
+    cmd.spawn().expect("failed to build uefi loader")
+}
+
+fn wait_cargo(mut child: Child, name: &str) {
+    let status = child
+        .wait()
+        .unwrap_or_else(|error| panic!("failed to build {name}: {error}"));
     if !status.success() {
-        panic!("failed to build kernel: cargo exit with non-zero status: {status}")
+        panic!("failed to build {name}: cargo exit with non-zero status: {status}")
     }
 }
 
-fn compile_system_application() {
+fn compile_system_application() -> Child {
     let mut cmd = Command::new("cargo");
     cmd.arg("build").arg("--release");
     cmd.current_dir(
@@ -165,14 +509,49 @@ fn compile_system_application() {
     cmd.stdin(Stdio::inherit());
     cmd.stdout(Stdio::inherit());
     cmd.stderr(Stdio::inherit());
-    let mut child = cmd.spawn().expect("failed to build system application");
-    let status = child.wait().expect("failed to build system application");
-    if !status.success() {
-        panic!("failed to build system application: cargo exit with non-zero status: {status}")
-    }
+    cmd.spawn().expect("failed to build system application")
 }
 
-fn extract_loader_binary() {
+/// 将user/drivers中的内核模块编译为可重定位目标文件，输出到 [DRIVER_OUTPUT_DIR]
+fn compile_driver(driver: &str) -> Child {
+    fs::create_dir_all(DRIVER_OUTPUT_DIR).expect("failed to create driver output dir");
+    let output = PathBuf::from_str(DRIVER_OUTPUT_DIR)
+        .unwrap()
+        .canonicalize()
+        .unwrap()
+        .join(format!("{driver}.o"));
+    let mut cmd = Command::new("cargo");
+    cmd.arg("rustc")
+        .arg("--release")
+        .arg("-p")
+        .arg(driver)
+        .arg("--")
+        .arg(format!("--emit=obj={}", output.display()));
+    cmd.current_dir(
+        PathBuf::from_str("./user/drivers")
+            .unwrap()
+            .canonicalize()
+            .unwrap(),
+    );
+    cmd.stdin(Stdio::inherit());
+    cmd.stdout(Stdio::inherit());
+    cmd.stderr(Stdio::inherit());
+    cmd.spawn()
+        .unwrap_or_else(|error| panic!("failed to build driver {driver}: {error}"))
+}
+
+fn extract_loader_binary(force: bool) {
+    if !force
+        && is_up_to_date(
+            "./build/loader.bin",
+            &[PathBuf::from(
+                "./bootloader/target/i386-unknown-none/release/bootloader",
+            )],
+        )
+    {
+        return;
+    }
+
     let mut cmd = Command::new("rust-objcopy");
     cmd.arg("./target/i386-unknown-none/release/bootloader")
         .arg("-O")
@@ -198,19 +577,25 @@ fn extract_loader_binary() {
     }
 }
 
-fn extract_kernel_binary(debug: bool) {
-    let elf_path = if debug {
-        "./target/x86_64-unknown-cos/debug/kernel"
-    } else {
-        "./target/x86_64-unknown-cos/release/kernel"
-    };
+/// 提取内核二进制，返回产物路径
+///
+/// debug与release的产物分别保存，切换模式时无需重新提取
+fn extract_kernel_binary(debug: bool, force: bool) -> PathBuf {
+    let profile = if debug { "debug" } else { "release" };
+    let elf_path = format!("./target/x86_64-unknown-cos/{profile}/kernel");
+    let binary = format!("kernel-{profile}.bin");
+    let binary_path = PathBuf::from("./build").join(&binary);
+    if !force && is_up_to_date(&binary_path, &[PathBuf::from("./kernel").join(&elf_path)]) {
+        return binary_path;
+    }
+
     let mut cmd = Command::new("rust-objcopy");
     cmd.arg(elf_path)
         .arg("-O")
         .arg("binary")
         .arg("--gap-fill")
         .arg("0x00")
-        .arg("./../build/kernel.bin");
+        .arg(format!("./../build/{binary}"));
     cmd.current_dir(
         PathBuf::from_str("./kernel")
             .unwrap()
@@ -227,49 +612,250 @@ fn extract_kernel_binary(debug: bool) {
     if !status.success() {
         panic!("failed to extract kernel binary: rust-objcopy exit with non-zero status: {status}")
     }
+    binary_path
+}
+
+/// 提取去除调试信息的内核ELF，保留符号表，返回产物路径
+fn extract_kernel_elf(debug: bool, force: bool) -> PathBuf {
+    let profile = if debug { "debug" } else { "release" };
+    let elf_path = format!("./target/x86_64-unknown-cos/{profile}/kernel");
+    let output = format!("kernel-{profile}.elf");
+    let output_path = PathBuf::from("./build").join(&output);
+    if !force && is_up_to_date(&output_path, &[PathBuf::from("./kernel").join(&elf_path)]) {
+        return output_path;
+    }
+
+    let mut cmd = Command::new("rust-objcopy");
+    cmd.arg("--strip-debug")
+        .arg(elf_path)
+        .arg(format!("./../build/{output}"));
+    cmd.current_dir(
+        PathBuf::from_str("./kernel")
+            .unwrap()
+            .canonicalize()
+            .unwrap(),
+    );
+    cmd.stdin(Stdio::inherit());
+    cmd.stdout(Stdio::inherit());
+    cmd.stderr(Stdio::inherit());
+    let mut child = cmd
+        .spawn()
+        .expect("failed to extract kernel elf. may rust-objcopy is not installed?");
+    let status = child.wait().expect("failed to extract kernel elf");
+    if !status.success() {
+        panic!("failed to extract kernel elf: rust-objcopy exit with non-zero status: {status}")
+    }
+    output_path
+}
+
+/// 以cargo test编译项目中的测试用例，返回测试程序的路径
+///
+/// 测试程序的文件名带有cargo生成的哈希，需从cargo的JSON输出中获取
+fn compile_tests(project: &str, debug: bool) -> PathBuf {
+    let mut cmd = Command::new("cargo");
+    cmd.arg("test")
+        .arg("--no-run")
+        .arg("--message-format=json-render-diagnostics");
+    if !debug {
+        cmd.arg("--release");
+    }
+    cmd.current_dir(PathBuf::from_str(project).unwrap().canonicalize().unwrap());
+    cmd.stdin(Stdio::null());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::inherit());
+    let output = cmd
+        .output()
+        .unwrap_or_else(|error| panic!("failed to build {project} tests: {error}"));
+    if !output.status.success() {
+        panic!(
+            "failed to build {project} tests: cargo exit with non-zero status: {}",
+            output.status
+        )
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| line.contains("\"test\":true"))
+        .find_map(test_executable)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| panic!("test executable of {project} is not found in cargo output"))
+}
+
+/// 从cargo的compiler-artifact消息中取出可执行文件路径
+fn test_executable(message: &str) -> Option<&str> {
+    const KEY: &str = "\"executable\":\"";
+    let start = message.find(KEY)? + KEY.len();
+    let length = message[start..].find('"')?;
+    Some(&message[start..start + length])
+}
+
+/// 提取loader测试程序的扁平二进制，返回产物路径
+fn extract_loader_test_binary(executable: &Path) -> PathBuf {
+    let output = PathBuf::from("./build/loader-test.bin");
+    run_objcopy(
+        &[
+            executable.as_os_str(),
+            OsStr::new("-O"),
+            OsStr::new("binary"),
+            OsStr::new("--gap-fill"),
+            OsStr::new("0x00"),
+            output.as_os_str(),
+        ],
+        "loader test binary",
+    );
+    output
+}
+
+/// 提取内核测试程序的扁平二进制与去除调试信息的ELF
+fn extract_kernel_test_output(executable: &Path) -> KernelOutput {
+    let binary = PathBuf::from("./build/kernel-test.bin");
+    let elf = PathBuf::from("./build/kernel-test.elf");
+    run_objcopy(
+        &[
+            executable.as_os_str(),
+            OsStr::new("-O"),
+            OsStr::new("binary"),
+            OsStr::new("--gap-fill"),
+            OsStr::new("0x00"),
+            binary.as_os_str(),
+        ],
+        "kernel test binary",
+    );
+    run_objcopy(
+        &[
+            OsStr::new("--strip-debug"),
+            executable.as_os_str(),
+            elf.as_os_str(),
+        ],
+        "kernel test elf",
+    );
+    KernelOutput {
+        binary,
+        elf,
+        profile: "test",
+    }
 }
 
-fn build_image() {
+fn run_objcopy(args: &[&OsStr], name: &str) {
+    let mut cmd = Command::new("rust-objcopy");
+    cmd.args(args);
+    cmd.stdin(Stdio::inherit());
+    cmd.stdout(Stdio::inherit());
+    cmd.stderr(Stdio::inherit());
+    let mut child = cmd.spawn().unwrap_or_else(|error| {
+        panic!("failed to extract {name}: {error}. may rust-objcopy is not installed?")
+    });
+    let status = child
+        .wait()
+        .unwrap_or_else(|error| panic!("failed to extract {name}: {error}"));
+    if !status.success() {
+        panic!("failed to extract {name}: rust-objcopy exit with non-zero status: {status}")
+    }
+}
+
+fn build_image(
+    config: &BuildConfig,
+    loader: &Path,
+    kernel_output: &KernelOutput,
+    image: &str,
+    applications: &[(String, Vec<u8>)],
+    force: bool,
+) {
     let boot = fs::read("./build/boot.bin").expect("failed to read ./build/boot.bin");
-    let mut loader = fs::read("./build/loader.bin").expect("failed to read ./build/loader.bin");
-    let mut kernel = fs::read("./build/kernel.bin").expect("failed to read ./build/kernel.bin");
+    let mut loader = fs::read(loader)
+        .unwrap_or_else(|error| panic!("failed to read {}: {error}", loader.display()));
+    let kernel = fs::read(&kernel_output.binary).unwrap_or_else(|error| {
+        panic!("failed to read {}: {error}", kernel_output.binary.display())
+    });
+    let kernel_elf = fs::read(&kernel_output.elf)
+        .unwrap_or_else(|error| panic!("failed to read {}: {error}", kernel_output.elf.display()));
+
+    // 镜像内容的摘要，所有输入均未变化时，跳过镜像生成。构建时间不计入摘要
+    let git_version = build_info::git_version();
+    let stamp_path = format!("{image}.stamp");
+    let stamp = image_stamp(
+        config,
+        &[
+            &boot,
+            &loader,
+            &kernel,
+            &kernel_elf,
+            git_version.as_bytes(),
+            kernel_output.profile.as_bytes(),
+        ],
+        applications,
+    );
+    if !force
+        && fs::metadata(image).is_ok()
+        && fs::read_to_string(&stamp_path).is_ok_and(|old| old == stamp)
+    {
+        return;
+    }
+    // 生成失败时不应留下旧的摘要
+    _ = fs::remove_file(&stamp_path);
+
+    let data_files = build_data_files(kernel_output, &git_version);
+    let kernel = build_kernel_image(kernel, &kernel_elf, applications, &data_files);
+    write_boot_config(&mut loader, &config.cmdline);
 
     pad_to_fam(&mut loader);
-    pad_to_fam(&mut kernel);
 
     let loader_size = calc_fam_size(loader.len(), u8::MAX as usize);
-    let kernel_size = calc_fam_size(kernel.len(), u16::MAX as usize);
 
-    let disk = HostFileBlockDevice::new("./build/disk.img", KERNEL_DISK_SIZE)
-        .expect("failed to create ./build/disk.img");
+    let disk: Arc<dyn BlockDevice> = match config.disk.format {
+        DiskFormat::Raw => HostFileBlockDevice::new(image, config.disk_size())
+            .unwrap_or_else(|error| panic!("failed to create {image}: {error}")),
+        DiskFormat::Qcow2 => {
+            let max_size = Qcow2Device::max_file_size(config.disk_size(), DEFAULT_CLUSTER_BITS)
+                .expect("invalid disk size for qcow2");
+            let file = HostFileBlockDevice::growable(image, max_size)
+                .unwrap_or_else(|error| panic!("failed to create {image}: {error}"));
+            let qcow2 = block_on(Qcow2Device::create(
+                file,
+                config.disk_size(),
+                DEFAULT_CLUSTER_BITS,
+            ))
+            .unwrap_or_else(|error| panic!("failed to create qcow2 in {image}: {error:?}"));
+            Arc::new(qcow2)
+        }
+    };
 
     block_on(disk.write_block(0, &boot)).expect("failed to write mbr boot for disk.img");
 
-    let mut mbr = block_on(MbrPartitionDevice::format(
-        disk,
-        [
-            Some(MbrPartitionEntry {
-                bootable: true,
-                start: 1,
-                end: loader_size + 1,
-                partition_type: PARTITION_TYPE_BOOTLOADER,
-            }),
-            Some(MbrPartitionEntry {
-                bootable: false,
-                start: loader_size + 1,
-                end: loader_size + kernel_size + 1,
-                partition_type: PARTITION_TYPE_BOOTLOADER,
-            }),
-            Some(MbrPartitionEntry {
-                bootable: false,
-                start: loader_size + kernel_size + 1,
-                end: (KERNEL_DISK_SIZE / 512) as u32,
-                partition_type: PARTITION_TYPE_FAT32,
-            }),
-            None,
-        ],
-    ))
-    .expect("failed to mbr format disk.img");
+    let mut partitions = [None; 4];
+    partitions[0] = Some(MbrPartitionEntry {
+        bootable: true,
+        start: 1,
+        end: loader_size + 1,
+        partition_type: PARTITION_TYPE_BOOTLOADER,
+    });
+    let disk_sectors = u32::try_from(config.disk_size() / 512).expect("disk is too large for mbr");
+    let mut start = loader_size + 1;
+    for (index, partition) in config.partitions.iter().enumerate() {
+        let end = partition
+            .sectors()
+            .map_or(disk_sectors, |sectors| start.saturating_add(sectors));
+        assert!(
+            start < end && end <= disk_sectors,
+            "partitions do not fit in {} MiB disk",
+            config.disk.size_mib
+        );
+        partitions[index + 1] = Some(MbrPartitionEntry {
+            bootable: false,
+            start,
+            end,
+            partition_type: match partition.kind {
+                PartitionKind::Fat32 => PARTITION_TYPE_FAT32,
+                PartitionKind::Raw => PARTITION_TYPE_RAW,
+                PartitionKind::Crash => PARTITION_TYPE_CRASH,
+                PartitionKind::Encrypted => PARTITION_TYPE_CRYPT,
+            },
+        });
+        start = end;
+    }
+
+    let mut mbr = block_on(MbrPartitionDevice::format(disk, partitions))
+        .expect("failed to mbr format disk.img");
 
     let loader_partition = mbr[0]
         .take()
@@ -277,48 +863,57 @@ fn build_image() {
     block_on(loader_partition.write_blocks(0, loader_size as u64, &loader))
         .expect("failed to write loader partition to disk.img");
 
-    let kernel_partition = mbr[1]
-        .take()
-        .expect("codebug: block device should not be none since we format it already (kernel)");
-    block_on(kernel_partition.write_blocks(0, kernel_size as u64, &kernel))
-        .expect("failed to write kernel partition to disk.img");
-
-    let file_system_partition = mbr[2].take().expect(
+    let file_system_index = config
+        .partitions
+        .iter()
+        .position(|partition| partition.kind == PartitionKind::Fat32)
+        .expect("codebug: config should contain a fat32 partition since it is validated");
+    let format_options = config.partitions[file_system_index].format_options();
+    let file_system_partition = mbr[file_system_index + 1].take().expect(
         "codebug: block device should not be none since we format it already (file_system)",
     );
-    let fs = block_on(Fat32FileSystem::with_format(Arc::new(
-        file_system_partition,
-    )))
+    let fs = block_on(Fat32FileSystem::with_format_options(
+        Arc::new(file_system_partition),
+        &format_options,
+    ))
     .expect("failed to format file system for disk.img");
 
     let system_application_dir = filesystem::path::PathBuf::from_str("/system")
         .expect("codebug: failed to create system application path");
     block_on(fs.create_directory(system_application_dir.as_path()))
         .expect("failed to create system application path");
+    let driver_dir = filesystem::path::PathBuf::from_str(DRIVER_DIR)
+        .expect("codebug: failed to create driver path");
+    block_on(fs.create_directory(driver_dir.as_path())).expect("failed to create driver path");
 
-    for system_application in SYSTEM_APPLICATIONS {
+    for (system_application, binary) in applications {
         let mut filepath = system_application_dir.clone();
         filepath.extends(
-            &filesystem::path::PathBuf::from_str(&system_application)
+            &filesystem::path::PathBuf::from_str(system_application)
                 .expect("failed to create system application path"),
         );
         block_on(fs.create_file(filepath.as_path())).expect("failed to create file");
         let mut file = block_on(fs.open_file(filepath.as_path())).expect("failed to open file");
+        block_on(file.write(binary)).expect("failed to write to file");
+        block_on(file.close()).expect("failed to close file");
+    }
 
-        let mut host_file = File::open(format!(
-            "./user/system/target/x86_64-unknown-cos/release/{system_application}"
-        ))
-        .expect("failed to open host file");
-        let mut buf = [0u8; 8192];
-        loop {
-            let len = host_file
-                .read(&mut buf)
-                .expect("failed to read from host file");
-            if len == 0 {
-                break;
-            }
-            block_on(file.write(&buf[..len])).expect("failed to write to file");
-        }
+    let manifest_path =
+        filesystem::path::PathBuf::from_str(MANIFEST_PATH).expect("failed to create manifest path");
+    block_on(fs.create_file(manifest_path.as_path())).expect("failed to create file");
+    let mut file = block_on(fs.open_file(manifest_path.as_path())).expect("failed to open file");
+    block_on(file.write(&build_manifest(applications))).expect("failed to write to file");
+    block_on(file.close()).expect("failed to close file");
+
+    // loader按启动选择记录从根文件系统中加载内核镜像。镜像中只有槽位A，槽位B由系统中的update命令写入
+    for (path, content) in [
+        (KERNEL_SLOT_A_PATH, kernel.as_slice()),
+        (BOOT_SLOT_PATH, BOOT_SLOT_RECORD.as_slice()),
+    ] {
+        let path = filesystem::path::PathBuf::from_str(path).expect("failed to create boot path");
+        block_on(fs.create_file(path.as_path())).expect("failed to create file");
+        let mut file = block_on(fs.open_file(path.as_path())).expect("failed to open file");
+        block_on(file.write(content)).expect("failed to write to file");
         block_on(file.close()).expect("failed to close file");
     }
 
@@ -326,11 +921,370 @@ fn build_image() {
         .expect("failed to create welcome path");
     block_on(fs.create_file(welcome_path.as_path())).expect("failed to create file");
     let mut file = block_on(fs.open_file(welcome_path.as_path())).expect("failed to open file");
-    let welcome = b"Welcome to COS shell!\nThis welcome message is from /system/welcome.txt!\n";
-    block_on(file.write(welcome)).expect("failed to write to file");
+    block_on(file.write(WELCOME_MESSAGE)).expect("failed to write to file");
     block_on(file.close()).expect("failed to close file");
 
+    for (path, content) in &data_files {
+        let path = filesystem::path::PathBuf::from_str(path).expect("failed to create data path");
+        block_on(fs.create_file(path.as_path())).expect("failed to create file");
+        let mut file = block_on(fs.open_file(path.as_path())).expect("failed to open file");
+        block_on(file.write(content)).expect("failed to write to file");
+        block_on(file.close()).expect("failed to close file");
+    }
+
+    block_on(build_permissions(applications, &data_files).save(&fs))
+        .expect("failed to write permissions");
+
     block_on(fs.unmount()).expect("failed to unmount formatted file system for disk.img");
+
+    for (index, partition) in config.partitions.iter().enumerate() {
+        let Some(passphrase) = &partition.passphrase else {
+            continue;
+        };
+        let device = mbr[index + 1].take().expect(
+            "codebug: block device should not be none since we format it already (encrypted)",
+        );
+        format_encrypted(Arc::new(device), passphrase.as_bytes());
+    }
+
+    fs::write(&stamp_path, stamp).expect("failed to write disk image stamp");
+}
+
+/// 在分区上创建加密卷，并在其中格式化空白的FAT32文件系统
+fn format_encrypted(device: Arc<dyn BlockDevice>, passphrase: &[u8]) {
+    // 盐值随机生成，相同口令在不同镜像中派生出不同的密钥
+    let mut salt = [0u8; 16];
+    fs::File::open("/dev/urandom")
+        .and_then(|mut urandom| io::Read::read_exact(&mut urandom, &mut salt))
+        .expect("failed to generate salt for encrypted partition");
+    let crypt = block_on(CryptDevice::format(
+        device,
+        passphrase,
+        salt,
+        DEFAULT_ITERATIONS,
+    ))
+    .expect("failed to create encrypted volume for disk.img");
+    let fs = block_on(Fat32FileSystem::with_format(Arc::new(crypt)))
+        .expect("failed to format encrypted file system for disk.img");
+    block_on(fs.unmount()).expect("failed to unmount encrypted file system for disk.img");
+}
+
+/// 生成UEFI启动所需的ESP目录
+///
+/// UEFI引导程序直接加载内核ELF，并将initramfs与内核ELF附加在内核之后，与BIOS启动时的内核镜像布局一致
+fn build_esp(config: &BuildConfig, kernel: &KernelOutput, applications: &[(String, Vec<u8>)]) {
+    let esp = Path::new(ESP_DIR);
+    let boot_dir = esp.join("EFI").join("BOOT");
+    let cos_dir = esp.join("cos");
+    for dir in [&boot_dir, &cos_dir] {
+        fs::create_dir_all(dir)
+            .unwrap_or_else(|error| panic!("failed to create {}: {error}", dir.display()));
+    }
+
+    fs::copy(
+        "./uefi-loader/target/x86_64-unknown-uefi/release/uefi-loader.efi",
+        boot_dir.join("BOOTX64.EFI"),
+    )
+    .expect("failed to copy uefi loader to esp");
+    fs::copy(&kernel.elf, cos_dir.join("kernel.elf")).expect("failed to copy kernel to esp");
+    let data_files = build_data_files(kernel, &build_info::git_version());
+    fs::write(
+        cos_dir.join("initramfs.img"),
+        build_initramfs(applications, &data_files),
+    )
+    .expect("failed to write initramfs to esp");
+    // 命令行为空时不生成文件，并移除之前生成的文件
+    let cmdline_path = cos_dir.join("cmdline.txt");
+    if config.cmdline.is_empty() {
+        _ = fs::remove_file(cmdline_path);
+    } else {
+        fs::write(cmdline_path, &config.cmdline).expect("failed to write cmdline to esp");
+    }
+}
+
+/// 生成GRUB以Multiboot2启动所需的目录，并以grub-mkrescue生成光盘镜像
+///
+/// loader的内容与写入磁盘的loader分区一致，内核镜像复制自 [KERNEL_IMAGE]，作为Multiboot2模块加载
+fn build_multiboot(config: &BuildConfig) {
+    let cos_dir = Path::new(MULTIBOOT_DIR).join("boot").join("cos");
+    let grub_dir = Path::new(MULTIBOOT_DIR).join("boot").join("grub");
+    for dir in [&cos_dir, &grub_dir] {
+        fs::create_dir_all(dir)
+            .unwrap_or_else(|error| panic!("failed to create {}: {error}", dir.display()));
+    }
+
+    let mut loader = fs::read(LOADER_BINARY).expect("failed to read loader binary");
+    write_boot_config(&mut loader, &config.cmdline);
+    fs::write(cos_dir.join("loader.bin"), loader).expect("failed to write loader to multiboot dir");
+    fs::copy(KERNEL_IMAGE, cos_dir.join("kernel.img"))
+        .expect("failed to copy kernel to multiboot dir");
+    fs::write(grub_dir.join("grub.cfg"), GRUB_CONFIG).expect("failed to write grub.cfg");
+
+    let status = Command::new("grub-mkrescue")
+        .arg("-o")
+        .arg(MULTIBOOT_ISO)
+        .arg(MULTIBOOT_DIR)
+        .stdout(Stdio::null())
+        .status()
+        .expect("failed to create multiboot iso. may grub-mkrescue is not installed?");
+    if !status.success() {
+        panic!("failed to create multiboot iso: grub-mkrescue exit with non-zero status: {status}");
+    }
+}
+
+/// 读取配置中的全部系统应用，返回文件名及其内容
+///
+/// 应用在user/system/manifests中有能力清单时，清单同样写入/system，文件名为 `<应用名>.manifest`；
+/// 内核模块签名后写入/system/drivers，文件名为 `drivers/<模块名>.ko`
+fn read_system_applications(config: &BuildConfig) -> Vec<(String, Vec<u8>)> {
+    let mut applications = Vec::new();
+    for system_application in &config.applications {
+        applications.push((
+            system_application.clone(),
+            read_system_application(system_application),
+        ));
+        let manifest_name = format!("{system_application}.manifest");
+        match fs::read(format!("./user/system/manifests/{manifest_name}")) {
+            Ok(manifest) => applications.push((manifest_name, manifest)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => panic!("failed to read manifest of {system_application}: {error}"),
+        }
+    }
+    for driver in &config.drivers {
+        applications.push((format!("drivers/{driver}.ko"), read_driver(driver)));
+    }
+    applications
+}
+
+/// 读取环境变量 [MODULE_KEY_ENV] 中的模块签名私钥
+///
+/// 未设置时返回None，格式错误时中止构建
+fn module_secret_key() -> Option<[u8; crypto::ed25519::SECRET_KEY_SIZE]> {
+    let hex = std::env::var(MODULE_KEY_ENV).ok()?;
+    let invalid = || panic!("{MODULE_KEY_ENV} should be 32 bytes in hexadecimal");
+    if hex.len() != crypto::ed25519::SECRET_KEY_SIZE * 2 {
+        invalid();
+    }
+    let mut secret = [0; crypto::ed25519::SECRET_KEY_SIZE];
+    for (index, byte) in secret.iter_mut().enumerate() {
+        *byte = hex
+            .get(index * 2..index * 2 + 2)
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+            .unwrap_or_else(invalid);
+    }
+    Some(secret)
+}
+
+/// 读取编译好的内核模块，并以环境变量 [MODULE_KEY_ENV] 中的私钥附加Ed25519签名
+///
+/// 签名格式见kernel/src/module/mod.rs，内核需以对应的公钥编译才能加载模块
+fn read_driver(driver: &str) -> Vec<u8> {
+    let mut secret = module_secret_key()
+        .unwrap_or_else(|| panic!("{MODULE_KEY_ENV} is required to sign driver {driver}"));
+    let path = format!("{DRIVER_OUTPUT_DIR}/{driver}.o");
+    let mut module =
+        fs::read(&path).unwrap_or_else(|error| panic!("failed to read driver {path}: {error}"));
+    let signature = crypto::ed25519::sign(&secret, &module);
+    crypto::zeroize(&mut secret);
+    module.extend_from_slice(&signature);
+    module.extend_from_slice(MODULE_SIGNATURE_MAGIC);
+    module
+}
+
+fn read_system_application(system_application: &str) -> Vec<u8> {
+    fs::read(format!(
+        "./user/system/target/x86_64-unknown-cos/release/{system_application}"
+    ))
+    .unwrap_or_else(|error| {
+        panic!("failed to read system application {system_application}: {error}")
+    })
+}
+
+/// 计算镜像全部输入的摘要
+fn image_stamp(
+    config: &BuildConfig,
+    binaries: &[&[u8]],
+    applications: &[(String, Vec<u8>)],
+) -> String {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(format!("{config:?}").as_bytes());
+    for binary in binaries {
+        hasher.write(binary);
+    }
+    for (name, binary) in applications {
+        hasher.write(name.as_bytes());
+        hasher.write(binary);
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+/// 生成写入/system的构建信息与内核符号表，返回路径及其内容
+fn build_data_files(kernel: &KernelOutput, git_version: &str) -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        (
+            BUILD_INFO_PATH,
+            build_info::build_info(git_version, kernel.profile),
+        ),
+        (KERNEL_MAP_PATH, build_info::kernel_map(&kernel.elf)),
+    ]
+}
+
+/// 生成initramfs，内容与磁盘中的/system目录一致，在磁盘不可用时作为根文件系统
+fn build_initramfs(applications: &[(String, Vec<u8>)], data_files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    let mut builder = InitramfsBuilder::new();
+    builder.add_directory("/system");
+    builder.add_directory(DRIVER_DIR);
+    for (system_application, binary) in applications {
+        builder.add_file(&format!("/system/{system_application}"), binary);
+    }
+    builder.add_file(MANIFEST_PATH, &build_manifest(applications));
+    builder.add_file("/system/welcome.txt", WELCOME_MESSAGE);
+    for (path, content) in data_files {
+        builder.add_file(path, content);
+    }
+    builder.add_file(
+        PermissionTable::SIDECAR,
+        &build_permissions(applications, data_files).serialize(),
+    );
+    builder.build()
+}
+
+/// 生成/system的权限表：全部文件属于超级用户，其他用户可以读取与执行，但不能修改
+fn build_permissions(
+    applications: &[(String, Vec<u8>)],
+    data_files: &[(&str, Vec<u8>)],
+) -> PermissionTable {
+    let executable = FilePermission {
+        uid: ROOT_UID,
+        gid: ROOT_UID,
+        mode: 0o755,
+    };
+    let readonly = FilePermission {
+        mode: 0o644,
+        ..executable
+    };
+    let mut table = PermissionTable::new();
+    let mut set = |path: &str, permission| {
+        let path = filesystem::path::PathBuf::from_str(path).expect("failed to create path");
+        table.set(path.as_path(), permission);
+    };
+    set("/system", executable);
+    set(DRIVER_DIR, executable);
+    for (system_application, _) in applications {
+        let permission =
+            if system_application.ends_with(".manifest") || system_application.ends_with(".ko") {
+                readonly
+            } else {
+                executable
+            };
+        set(&format!("/system/{system_application}"), permission);
+    }
+    set(MANIFEST_PATH, readonly);
+    set(KERNEL_SLOT_A_PATH, readonly);
+    set(KERNEL_SLOT_B_PATH, readonly);
+    set(BOOT_SLOT_PATH, readonly);
+    set("/system/welcome.txt", readonly);
+    for (path, _) in data_files {
+        set(path, readonly);
+    }
+    table
+}
+
+/// 生成系统程序的SHA-256摘要清单，格式与sha256sum的输出一致
+fn build_manifest(applications: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut manifest = String::new();
+    for (system_application, binary) in applications {
+        for byte in crypto::sha256::sha256(binary) {
+            manifest.push_str(&format!("{byte:02x}"));
+        }
+        manifest.push_str(&format!("  /system/{system_application}\n"));
+    }
+    manifest.into_bytes()
+}
+
+/// 将initramfs附加到内核镜像之后，并将其位置写入内核
+fn append_initramfs(kernel: &mut Vec<u8>, initramfs: &[u8]) {
+    let mut positions = kernel
+        .windows(INITRAMFS_LOCATION_MAGIC.len())
+        .enumerate()
+        .filter(|(_, window)| window == INITRAMFS_LOCATION_MAGIC)
+        .map(|(position, _)| position);
+    let location = positions
+        .next()
+        .expect("initramfs location is not found in kernel binary");
+    assert!(
+        positions.next().is_none(),
+        "initramfs location is found more than once in kernel binary"
+    );
+
+    // initramfs按页对齐
+    kernel.resize(kernel.len().next_multiple_of(4096), 0);
+    let offset = kernel.len() as u64;
+    let length = initramfs.len() as u64;
+    kernel.extend_from_slice(initramfs);
+
+    let location = location + INITRAMFS_LOCATION_MAGIC.len();
+    kernel[location..location + 8].copy_from_slice(&offset.to_le_bytes());
+    kernel[location + 8..location + 16].copy_from_slice(&length.to_le_bytes());
+}
+
+/// 生成内核镜像并写入 [KERNEL_IMAGE]，供系统中的update命令安装或作为Multiboot2模块加载
+fn write_kernel_image(kernel: &KernelOutput, applications: &[(String, Vec<u8>)]) {
+    let binary = fs::read(&kernel.binary)
+        .unwrap_or_else(|error| panic!("failed to read {}: {error}", kernel.binary.display()));
+    let kernel_elf = fs::read(&kernel.elf)
+        .unwrap_or_else(|error| panic!("failed to read {}: {error}", kernel.elf.display()));
+    let data_files = build_data_files(kernel, &build_info::git_version());
+    let image = build_kernel_image(binary, &kernel_elf, applications, &data_files);
+    fs::write(KERNEL_IMAGE, image).expect("failed to write kernel image");
+}
+
+/// 生成内核镜像：依次为内核扁平二进制、initramfs与内核ELF
+fn build_kernel_image(
+    mut kernel: Vec<u8>,
+    kernel_elf: &[u8],
+    applications: &[(String, Vec<u8>)],
+    data_files: &[(&str, Vec<u8>)],
+) -> Vec<u8> {
+    append_initramfs(&mut kernel, &build_initramfs(applications, data_files));
+    append_kernel_elf(&mut kernel, kernel_elf);
+    kernel
+}
+
+/// 将内核ELF附加到内核镜像之后，并在结尾附加其位置信息
+///
+/// 位置信息依次为magic、ELF相对于镜像起始位置的偏移及长度，布局需与bootloader/src/loader.rs保持一致
+fn append_kernel_elf(kernel: &mut Vec<u8>, elf: &[u8]) {
+    // 内核ELF按页对齐
+    kernel.resize(kernel.len().next_multiple_of(4096), 0);
+    let offset = kernel.len() as u64;
+    kernel.extend_from_slice(elf);
+    kernel.extend_from_slice(KERNEL_TRAILER_MAGIC);
+    kernel.extend_from_slice(&offset.to_le_bytes());
+    kernel.extend_from_slice(&(elf.len() as u64).to_le_bytes());
+}
+
+/// 将内核命令行写入loader的引导配置
+fn write_boot_config(loader: &mut [u8], cmdline: &str) {
+    let mut positions = loader
+        .windows(BOOT_CONFIG_MAGIC.len())
+        .enumerate()
+        .filter(|(_, window)| window == BOOT_CONFIG_MAGIC)
+        .map(|(position, _)| position);
+    let location = positions
+        .next()
+        .expect("boot config is not found in loader binary");
+    assert!(
+        positions.next().is_none(),
+        "boot config is found more than once in loader binary"
+    );
+
+    // 布局与bootloader/src/config.rs中的BootConfig一致
+    let location = location + BOOT_CONFIG_MAGIC.len();
+    let cmdline = cmdline.as_bytes();
+    assert!(cmdline.len() <= MAX_CMDLINE_LEN, "cmdline is too long");
+    loader[location..location + 8].copy_from_slice(&(cmdline.len() as u64).to_le_bytes());
+    loader[location + 8..location + 8 + cmdline.len()].copy_from_slice(cmdline);
 }
 
 fn pad_to_fam(binary: &mut Vec<u8>) {
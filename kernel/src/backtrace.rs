@@ -0,0 +1,37 @@
+use crate::memory;
+
+/// 沿rbp链回溯当前调用栈，将返回地址由内向外依次写入frames，返回写入的数量
+#[inline(always)]
+pub fn capture(frames: &mut [usize]) -> usize {
+    let rbp: usize;
+    unsafe {
+        core::arch::asm!("mov {}, rbp", out(reg) rbp, options(nomem, nostack, preserves_flags));
+    }
+    capture_from(rbp, frames)
+}
+
+/// 从指定的rbp开始沿rbp链回溯调用栈，将返回地址由内向外依次写入frames，返回写入的数量
+///
+/// 未启用帧指针时rbp可能是任意值，因此每一帧在读取前都检查其是否已映射
+pub fn capture_from(mut rbp: usize, frames: &mut [usize]) -> usize {
+    // 内核栈位于高半部分，其余地址（包括非规范地址）均视为回溯结束
+    let readable = |address: usize| {
+        address >= 0xFFFF_8000_0000_0000 && memory::page::kernel_physical_address(address).is_some()
+    };
+    let mut depth = 0;
+    for frame in frames {
+        if !rbp.is_multiple_of(8) || !readable(rbp) || !readable(rbp + 8) {
+            break;
+        }
+        let (next, return_address) =
+            unsafe { (*(rbp as *const usize), *((rbp + 8) as *const usize)) };
+        *frame = return_address;
+        depth += 1;
+        // 栈向低地址增长，外层的帧位于更高的地址
+        if next <= rbp {
+            break;
+        }
+        rbp = next;
+    }
+    depth
+}
@@ -0,0 +1,155 @@
+//! A/B内核槽位
+//!
+//! BIOS引导程序按启动选择记录从两个内核槽位中选择一个启动，规则见 `boot_info::slot`。
+//! 内核启动init后确认本次启动成功；更新内核时将新的内核镜像写入另一个槽位，并将其设为待启动，
+//! 下次启动时生效。新内核未能确认成功时，再下次启动会回退到原来的槽位
+
+use alloc::{boxed::Box, vec};
+
+use boot_info::slot::{RECORD_PATH, RECORD_SIZE, Slot, SlotRecord};
+use filesystem::{
+    fs::{FileHandle, FileSystemError},
+    path::{Path, PathBuf},
+};
+
+use crate::{bootloader, io, klog};
+
+/// 内核镜像结尾的内核ELF位置信息的magic，需与build-scripts保持一致
+const KERNEL_TRAILER_MAGIC: &[u8; 16] = b"COS_KERNEL_ELF__";
+/// 复制内核镜像时的缓冲区大小
+const COPY_BUFFER_SIZE: usize = 4096;
+/// 内核槽位与启动选择记录的权限位：超级用户可读写，其他用户只读
+const SLOT_FILE_MODE: u16 = 0o644;
+
+#[derive(Debug)]
+pub enum UpdateError {
+    /// 本次启动不是由BIOS引导程序从内核槽位启动（如UEFI启动）
+    NotSupported,
+    /// 新内核镜像的路径为内核槽位
+    BadPath,
+    /// 文件不是由build-scripts生成的内核镜像
+    BadImage,
+    FileSystem(FileSystemError),
+}
+
+impl From<FileSystemError> for UpdateError {
+    fn from(value: FileSystemError) -> Self {
+        Self::FileSystem(value)
+    }
+}
+
+/// 确认本次启动成功，由内核在启动init后调用
+///
+/// 本次启动的槽位处于尝试启动状态时，将其标记为已确认，此后引导程序不再回退到另一个槽位
+pub async fn confirm() {
+    let Some(booted) = bootloader::boot_slot() else {
+        return;
+    };
+    let record = match read_record().await {
+        Ok(record) => record,
+        Err(error) => {
+            klog!(warn, "boot", "failed to read {RECORD_PATH}: {error:?}");
+            return;
+        }
+    };
+    let Some(confirmed) = record.confirm(booted) else {
+        return;
+    };
+    match write_record(confirmed).await {
+        Ok(()) => klog!(info, "boot", "kernel slot {} confirmed", booted.name()),
+        Err(error) => klog!(warn, "boot", "failed to confirm kernel slot: {error:?}"),
+    }
+}
+
+/// 将image处的内核镜像写入未启动的槽位，并设为下次启动的槽位，返回写入的槽位
+pub async fn install(image: Path<'_>) -> Result<Slot, UpdateError> {
+    let booted = bootloader::boot_slot().ok_or(UpdateError::NotSupported)?;
+    let target = booted.other();
+    if [Slot::A, Slot::B]
+        .iter()
+        .any(|slot| path(slot.path()).as_path() == image)
+    {
+        return Err(UpdateError::BadPath);
+    }
+
+    let (fs, image) = io::vfs::resolve(&image).ok_or(FileSystemError::FileNotFound)?;
+    let size = fs.get_metadata(image).await?.size;
+    let mut source = fs.open_file(image).await?;
+    let result = async {
+        check_image(source.as_mut(), size).await?;
+        copy_to_slot(source.as_mut(), target).await
+    }
+    .await;
+    source.close().await?;
+    result?;
+
+    // 写入记录后，新的槽位才会被引导程序选择。写入失败时仍从原来的槽位启动
+    write_record(SlotRecord::installed(booted)).await?;
+    klog!(info, "boot", "kernel installed to slot {}", target.name());
+    Ok(target)
+}
+
+/// 检查文件结尾是否带有内核ELF位置信息
+async fn check_image(file: &mut dyn FileHandle, size: u64) -> Result<(), UpdateError> {
+    let trailer_size = KERNEL_TRAILER_MAGIC.len() as u64 + 16;
+    if size < trailer_size {
+        return Err(UpdateError::BadImage);
+    }
+    file.move_pointer(size - trailer_size).await?;
+    let mut magic = [0; KERNEL_TRAILER_MAGIC.len()];
+    if file.read(&mut magic).await? != magic.len() as u64 || &magic != KERNEL_TRAILER_MAGIC {
+        return Err(UpdateError::BadImage);
+    }
+    file.move_pointer(0).await?;
+    Ok(())
+}
+
+async fn copy_to_slot(source: &mut dyn FileHandle, slot: Slot) -> Result<(), UpdateError> {
+    let mut file = open_or_create(slot.path()).await?;
+    let result = async {
+        let mut buffer = vec![0; COPY_BUFFER_SIZE];
+        loop {
+            let count = source.read(&mut buffer).await? as usize;
+            if count == 0 {
+                break;
+            }
+            file.write(&buffer[..count]).await?;
+        }
+        file.truncate().await
+    }
+    .await;
+    file.close().await?;
+    Ok(result?)
+}
+
+async fn read_record() -> Result<SlotRecord, FileSystemError> {
+    let content = io::vfs::read_file(&path(RECORD_PATH).as_path(), RECORD_SIZE).await?;
+    Ok(SlotRecord::parse(&content).unwrap_or(SlotRecord::DEFAULT))
+}
+
+/// 原地覆盖启动选择记录，记录大小不变，引导程序也只需覆盖其第一个扇区
+async fn write_record(record: SlotRecord) -> Result<(), FileSystemError> {
+    let mut file = open_or_create(RECORD_PATH).await?;
+    let result = file.write(&record.to_bytes()).await;
+    file.close().await?;
+    result
+}
+
+/// 打开文件，文件不存在时创建
+///
+/// 内核槽位与启动选择记录只允许超级用户修改，与构建时写入的权限一致
+async fn open_or_create(path: &str) -> Result<Box<dyn FileHandle>, FileSystemError> {
+    let path = self::path(path);
+    let path = path.as_path();
+    let (fs, relative) = io::vfs::resolve(&path).ok_or(FileSystemError::FileNotFound)?;
+    match fs.create_file(relative).await {
+        Ok(()) | Err(FileSystemError::FileExists) => (),
+        Err(error) => return Err(error),
+    }
+    io::permission::created_by_kernel(path, SLOT_FILE_MODE).await;
+    fs.open_file(relative).await
+}
+
+fn path(path: &str) -> PathBuf {
+    PathBuf::from_str(path).expect("codebug: boot slot path should be valid")
+}
@@ -1,11 +1,85 @@
-#[derive(Debug, Clone, Copy)]
-#[repr(C, packed)]
-pub struct MemoryRegion {
-    pub base_addr: u64,
-    pub length: u64,
-    pub region_type: u32,
+use core::slice;
+
+use async_locks::once::OnceLock;
+pub use boot_info::{BootInfo, Framebuffer, MemoryRegion};
+use boot_info::{KERNEL_IMAGE_BASE, KERNEL_PHYSICAL_BASE, slot::Slot};
+
+/// 引导程序传入的启动信息，在kmain开始时复制到此处
+static BOOT_INFO: OnceLock<BootInfo> = OnceLock::new();
+
+/// 保存引导程序传入的启动信息
+///
+/// 启动信息的magic或版本与内核不一致时，说明引导程序与内核不匹配，无法继续启动
+///
+/// # Safety
+///
+/// 只能在kmain开始时调用一次，boot_info必须指向引导程序准备的启动信息
+pub unsafe fn init(boot_info: *const BootInfo) {
+    let boot_info = unsafe { boot_info.read() };
+    if !boot_info.is_valid() {
+        panic!(
+            "invalid boot info: magic 0x{:x}, version {}, expect version {}",
+            boot_info.magic,
+            boot_info.version,
+            boot_info::BOOT_INFO_VERSION
+        );
+    }
+    if BOOT_INFO.set(boot_info).is_err() {
+        panic!("codebug: boot info initialized twice");
+    }
+}
+
+/// 启动信息
+pub fn boot_info() -> BootInfo {
+    BOOT_INFO.get().copied().unwrap_or(BootInfo::EMPTY)
+}
+
+/// 引导程序提供的内存信息
+pub fn memory_regions() -> &'static [MemoryRegion] {
+    // Safety: 内存信息位于2M以下，引导程序建立的恒等映射在内核运行期间一直保留
+    unsafe { boot_info().memory_regions() }
+}
+
+/// 启动磁盘号
+pub fn startup_disk() -> u8 {
+    boot_info().startup_disk as u8
 }
 
-const _: () = {
-    assert!(size_of::<MemoryRegion>() == 20);
-};
+/// ACPI RSDP的物理地址
+pub fn rsdp() -> Option<u64> {
+    let rsdp = boot_info().rsdp;
+    (rsdp != 0).then_some(rsdp)
+}
+
+/// 固件提供的帧缓冲区，VGA文本模式下不可用
+pub fn framebuffer() -> Option<Framebuffer> {
+    boot_info().framebuffer()
+}
+
+/// 启动的内核槽位，不是由BIOS引导程序从槽位启动时（如UEFI启动）为None
+pub fn boot_slot() -> Option<Slot> {
+    Slot::from_boot_info_value(boot_info().boot_slot)
+}
+
+/// 内核命令行
+pub fn cmdline() -> &'static str {
+    // Safety: 命令行位于引导程序的恒等映射区域中，在内核运行期间一直保留
+    unsafe { boot_info().cmdline() }
+}
+
+/// 内核自身的ELF文件，可用于解析符号表
+pub fn kernel_elf() -> Option<&'static [u8]> {
+    let boot_info = boot_info();
+    if boot_info.kernel_elf_len == 0 {
+        return None;
+    }
+    // 内核ELF附加在内核镜像之后，与内核一同映射
+    let address = KERNEL_IMAGE_BASE + (boot_info.kernel_elf_ptr - KERNEL_PHYSICAL_BASE);
+    // Safety: 引导程序保证该区域位于内核镜像中
+    Some(unsafe {
+        slice::from_raw_parts(
+            address as usize as *const u8,
+            boot_info.kernel_elf_len as usize,
+        )
+    })
+}
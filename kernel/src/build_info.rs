@@ -0,0 +1,57 @@
+//! 镜像的构建信息
+//!
+//! build-scripts生成镜像时在/system中写入构建信息与内核符号表，格式见build-scripts/src/build_info.rs。
+//! 内核启动时输出构建信息，便于确认运行的镜像版本
+
+use alloc::string::String;
+use filesystem::path::PathBuf;
+
+use crate::{io, klog};
+
+/// 构建信息路径，需与build-scripts/src/build_info.rs保持一致
+pub const BUILD_INFO_PATH: &str = "/system/build-info";
+/// 构建信息的最大长度
+const BUILD_INFO_MAX_SIZE: usize = 4096;
+
+/// 读取构建信息并输出到内核日志，文件不存在时（如旧的镜像）不影响启动
+pub async fn log() {
+    let path =
+        PathBuf::from_str(BUILD_INFO_PATH).expect("codebug: build info path should be valid");
+    match io::vfs::read_file(&path.as_path(), BUILD_INFO_MAX_SIZE).await {
+        Ok(content) => {
+            let content = String::from_utf8_lossy(&content);
+            klog!(
+                info,
+                "boot",
+                "cos {} ({}), built at {}",
+                field(&content, "git"),
+                field(&content, "profile"),
+                field(&content, "time")
+            );
+        }
+        Err(error) => klog!(warn, "boot", "failed to read {BUILD_INFO_PATH}: {error:?}"),
+    }
+}
+
+/// 构建信息中指定键的值，缺失时返回 `unknown`
+fn field<'a>(content: &'a str, key: &str) -> &'a str {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+        .unwrap_or("unknown")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_field() {
+        let content = "git=0123456789ab-dirty\ntime=2025-01-01 00:00:00 UTC\nprofile=debug\n";
+        assert_eq!(field(content, "git"), "0123456789ab-dirty");
+        assert_eq!(field(content, "time"), "2025-01-01 00:00:00 UTC");
+        assert_eq!(field(content, "profile"), "debug");
+        assert_eq!(field(content, "prof"), "unknown");
+        assert_eq!(field("", "git"), "unknown");
+    }
+}
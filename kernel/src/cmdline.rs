@@ -0,0 +1,168 @@
+use async_locks::once::OnceLock;
+use filesystem::path::PathBuf;
+
+use crate::{bootloader, klog, kprintln};
+
+/// 内核日志级别，低于设定级别的日志不输出
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+/// 内核启动选项，解析自引导程序传入的命令行
+///
+/// 命令行由空格分隔的 `key=value` 组成，支持的选项：
+///
+/// - `loglevel=error|warn|info|debug`：日志级别，默认为info。运行时可按目标单独调整，见 [crate::klog::set_filter]
+/// - `bluescreen=on|off`：panic时是否展示蓝屏，默认为on。关闭时仅向串口输出panic信息并立即复位
+/// - `init=<path>`：第一个用户程序的路径，默认为/system/init
+/// - `hz=<n>`：计时器中断频率，同时决定调度的时间片，默认约为18Hz
+/// - `loop=<image>:<path>`：将镜像文件作为FAT32文件系统挂载到指定路径，如`loop=/system/extra.img:/extra`
+/// - `aslr=on|off`：是否随机化内核堆与用户内存的起始地址，默认为on。调试时可关闭以使地址固定
+/// - `tz=<+|-><hh>[:<mm>]`：本地时区相对UTC的偏移，如`tz=+08:00`，默认为UTC。RTC总是视为UTC时间
+/// - `watchdog=<seconds>|off`：CPU停留在内核中超过指定秒数而没有调度进展时输出警告，默认为10秒
+/// - `watchdog_panic=on|off`：检测到上述情况时是否panic，默认为off
+/// - `logfile=on|off`：是否将内核日志写入/system/logs/kernel.log，默认为on，见 [crate::log_file]
+/// - `fuzz=<seed>`：仅用于内核测试构建，以指定的随机种子进行系统调用模糊测试，代替测试用例，见 [crate::fuzz]
+///
+/// 未知的选项或无效的值会被忽略，并输出提示
+#[derive(Debug, Clone, Copy)]
+pub struct BootOptions {
+    pub log_level: LogLevel,
+    pub blue_screen: bool,
+    pub init: &'static str,
+    /// 计时器中断频率（Hz），为None时保持硬件计时器的默认频率
+    pub timer_hz: Option<u32>,
+    /// 启动时挂载的镜像文件及其挂载路径
+    pub loop_mount: Option<(&'static str, &'static str)>,
+    /// 是否随机化内核堆与用户内存的起始地址
+    pub aslr: bool,
+    /// 本地时区相对UTC的偏移（秒），东时区为正
+    pub utc_offset: i32,
+    /// 看门狗的超时时间（秒），为None时关闭看门狗
+    pub watchdog: Option<u32>,
+    /// 看门狗超时时是否panic
+    pub watchdog_panic: bool,
+    /// 是否将内核日志写入日志文件
+    pub log_file: bool,
+    /// 系统调用模糊测试的随机种子，为None时运行测试用例
+    #[cfg(test)]
+    pub fuzz: Option<u64>,
+}
+
+impl BootOptions {
+    const DEFAULT: Self = Self {
+        log_level: LogLevel::Info,
+        blue_screen: true,
+        init: "/system/init",
+        timer_hz: None,
+        loop_mount: None,
+        aslr: true,
+        utc_offset: 0,
+        watchdog: Some(10),
+        watchdog_panic: false,
+        log_file: true,
+        #[cfg(test)]
+        fuzz: None,
+    };
+
+    /// 应用一个选项，选项未知或值无效时返回None
+    fn apply(&mut self, key: &str, value: &'static str) -> Option<()> {
+        match key {
+            "loglevel" => self.log_level = parse_log_level(value)?,
+            "bluescreen" => self.blue_screen = parse_switch(value)?,
+            "init" if value.starts_with('/') => self.init = value,
+            "hz" => self.timer_hz = Some(value.parse().ok().filter(|hz| *hz > 0)?),
+            "loop" => {
+                let (image, path) = value.split_once(':')?;
+                if !image.starts_with('/') || !path.starts_with('/') {
+                    return None;
+                }
+                PathBuf::from_str(image).ok()?;
+                PathBuf::from_str(path).ok()?;
+                self.loop_mount = Some((image, path));
+            }
+            "aslr" => self.aslr = parse_switch(value)?,
+            "tz" => self.utc_offset = parse_utc_offset(value)?,
+            "watchdog" if value == "off" => self.watchdog = None,
+            "watchdog" => self.watchdog = Some(value.parse().ok().filter(|seconds| *seconds > 0)?),
+            "watchdog_panic" => self.watchdog_panic = parse_switch(value)?,
+            "logfile" => self.log_file = parse_switch(value)?,
+            #[cfg(test)]
+            "fuzz" => self.fuzz = Some(value.parse().ok()?),
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+static OPTIONS: OnceLock<BootOptions> = OnceLock::new();
+
+/// 解析命令行中的启动选项
+///
+/// # Safety
+///
+/// 只能在kmain中调用一次，且必须在启动信息保存之后、其他模块读取选项之前调用
+pub unsafe fn init() {
+    let options = parse(bootloader::cmdline());
+    if OPTIONS.set(options).is_err() {
+        panic!("codebug: boot options initialized twice");
+    }
+    klog!(debug, "boot", "options: {options:?}");
+}
+
+/// 启动选项
+pub fn options() -> BootOptions {
+    // 解析之前（如输出启动日志时）使用默认选项
+    OPTIONS.get().copied().unwrap_or(BootOptions::DEFAULT)
+}
+
+/// 指定级别的日志是否需要输出
+pub fn log_enabled(level: LogLevel) -> bool {
+    level <= options().log_level
+}
+
+fn parse(cmdline: &'static str) -> BootOptions {
+    let mut options = BootOptions::DEFAULT;
+    for option in cmdline.split_ascii_whitespace() {
+        let (key, value) = option.split_once('=').unwrap_or((option, ""));
+        if options.apply(key, value).is_none() {
+            kprintln!("ignore unknown or invalid boot option: {option}");
+        }
+    }
+    options
+}
+
+fn parse_log_level(value: &str) -> Option<LogLevel> {
+    match value {
+        "error" => Some(LogLevel::Error),
+        "warn" => Some(LogLevel::Warn),
+        "info" => Some(LogLevel::Info),
+        "debug" => Some(LogLevel::Debug),
+        _ => None,
+    }
+}
+
+/// 解析`+08:00`、`-05`形式的时区偏移，返回秒数
+fn parse_utc_offset(value: &str) -> Option<i32> {
+    let (sign, value) = match value.as_bytes().first()? {
+        b'+' => (1, &value[1..]),
+        b'-' => (-1, &value[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = value.split_once(':').unwrap_or((value, "0"));
+    let hours: i32 = hours.parse().ok().filter(|hours| *hours <= 14)?;
+    let minutes: i32 = minutes.parse().ok().filter(|minutes| *minutes < 60)?;
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+fn parse_switch(value: &str) -> Option<bool> {
+    match value {
+        "on" | "1" => Some(true),
+        "off" | "0" => Some(false),
+        _ => None,
+    }
+}
@@ -0,0 +1,405 @@
+//! 崩溃转储
+//!
+//! 内核panic时，将panic信息、寄存器、调用栈、线程列表、最近的日志与内核栈写入启动磁盘上的崩溃转储分区。
+//! 下次启动时内核将其保存为 [DUMP_PATH]，可在shell中以 `crashinfo` 查看，
+//! 也可在宿主机上以build-scripts的 `inspect-crash` 命令从磁盘镜像中读取。
+//!
+//! panic时系统状态不可信，写入崩溃转储不进行堆分配，以轮询方式直接写入ATA硬盘，
+//! 因此只有启动磁盘为ATA硬盘时才会写入崩溃转储。
+//!
+//! 崩溃转储依次为头部、报告与栈，各部分均对齐到扇区：
+//! * 头部占一个扇区，各字段均为小端序：
+//!   * 0~16：magic [DUMP_MAGIC]
+//!   * 16~20：版本 [DUMP_VERSION]
+//!   * 20~24：标志，[FLAG_UNREAD] 表示尚未被内核保存
+//!   * 24~32：panic时的启动时间，单位为纳秒
+//!   * 32~36：报告的字节数
+//!   * 36~40：栈的字节数
+//!   * 40~48：栈的起始地址
+//!   * 48~52：调用栈深度，不超过 [MAX_FRAMES]
+//!   * 56起：调用栈中的返回地址，由内向外排列，每个8字节
+//! * 报告为UTF-8文本，包括panic信息、异常寄存器、线程列表与最近的日志
+//! * 栈为panic时内核栈的原始内容，从栈顶（低地址）开始
+
+use core::{arch::asm, fmt::Write, panic::PanicInfo, ptr};
+
+use alloc::{string::String, sync::Arc, vec};
+use filesystem::{
+    device::{BlockDevice, mbr::MbrPartitionDevice},
+    fs::FileSystemError,
+    path::PathBuf,
+};
+
+use crate::{
+    backtrace, io, klog, memory, multitask,
+    sync::{int::IrqGuard, spin::SpinLock},
+    time::clocksource,
+    trap,
+};
+
+/// 崩溃转储的magic，需与build-scripts/src/crash.rs、user/system/shell/src/crash.rs保持一致
+const DUMP_MAGIC: &[u8; 16] = b"COS_CRASH_DUMP__";
+/// 崩溃转储格式的版本
+const DUMP_VERSION: u32 = 1;
+/// 崩溃转储尚未被内核保存
+const FLAG_UNREAD: u32 = 1;
+/// 头部记录的最大调用栈深度
+const MAX_FRAMES: usize = 32;
+/// 崩溃转储保存的路径，需与user/system/shell/src/crash.rs保持一致
+const DUMP_PATH: &str = "/system/crash/last.dump";
+/// 崩溃转储保存的目录
+const DUMP_DIR: &str = "/system/crash";
+/// 转储目录与文件的权限位，只有超级用户可以读写
+const DUMP_MODE: u16 = 0o600;
+
+const SECTOR_SIZE: usize = 512;
+/// 报告的最大字节数
+const REPORT_SIZE: usize = 32 * 1024;
+/// 栈的最大字节数
+const STACK_SIZE: usize = 8 * 1024;
+
+/// 崩溃转储分区
+static REGION: SpinLock<Option<Region>> = SpinLock::new(None);
+/// panic时组装崩溃转储的缓冲区
+static DUMP: SpinLock<[u8; SECTOR_SIZE + REPORT_SIZE + STACK_SIZE]> =
+    SpinLock::new([0; SECTOR_SIZE + REPORT_SIZE + STACK_SIZE]);
+
+struct Region {
+    /// ATA硬盘号，见 [io::disk::ata_lba::AtaLbaDriver::disk]
+    disk: u8,
+    /// 分区的起始扇区
+    start: u64,
+    /// 分区的扇区数
+    sectors: u64,
+    /// 分区设备，启动时读取上次的崩溃转储
+    device: Arc<dyn BlockDevice>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Header {
+    flags: u32,
+    /// panic时的启动时间，单位为纳秒
+    uptime: u64,
+    report_size: u32,
+    stack_size: u32,
+    stack_address: u64,
+    depth: u32,
+    frames: [u64; MAX_FRAMES],
+}
+
+impl Header {
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < SECTOR_SIZE || &bytes[..DUMP_MAGIC.len()] != DUMP_MAGIC {
+            return None;
+        }
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        if u32_at(16) != DUMP_VERSION {
+            return None;
+        }
+        let depth = u32_at(48);
+        if depth as usize > MAX_FRAMES {
+            return None;
+        }
+        Some(Self {
+            flags: u32_at(20),
+            uptime: u64_at(24),
+            report_size: u32_at(32),
+            stack_size: u32_at(36),
+            stack_address: u64_at(40),
+            depth,
+            frames: core::array::from_fn(|index| u64_at(56 + index * 8)),
+        })
+    }
+
+    fn write_to(&self, bytes: &mut [u8]) {
+        bytes[..SECTOR_SIZE].fill(0);
+        bytes[..DUMP_MAGIC.len()].copy_from_slice(DUMP_MAGIC);
+        bytes[16..20].copy_from_slice(&DUMP_VERSION.to_le_bytes());
+        bytes[20..24].copy_from_slice(&self.flags.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.uptime.to_le_bytes());
+        bytes[32..36].copy_from_slice(&self.report_size.to_le_bytes());
+        bytes[36..40].copy_from_slice(&self.stack_size.to_le_bytes());
+        bytes[40..48].copy_from_slice(&self.stack_address.to_le_bytes());
+        bytes[48..52].copy_from_slice(&self.depth.to_le_bytes());
+        for (index, frame) in self.frames.iter().enumerate() {
+            bytes[56 + index * 8..64 + index * 8].copy_from_slice(&frame.to_le_bytes());
+        }
+    }
+
+    /// 崩溃转储的总扇区数
+    fn sectors(&self) -> u64 {
+        1 + (self.report_size as u64).div_ceil(SECTOR_SIZE as u64)
+            + (self.stack_size as u64).div_ceil(SECTOR_SIZE as u64)
+    }
+}
+
+/// 写入固定大小的缓冲区，超出部分被丢弃
+struct BufferWriter<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl Write for BufferWriter<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let len = s.len().min(self.buffer.len() - self.len);
+        self.buffer[self.len..self.len + len].copy_from_slice(&s.as_bytes()[..len]);
+        self.len += len;
+        Ok(())
+    }
+}
+
+/// 设置崩溃转储分区，由磁盘初始化时调用
+///
+/// ata_disk为启动磁盘的ATA硬盘号，启动磁盘不是ATA硬盘时为None，此时无法写入崩溃转储
+pub fn set_region(partition: MbrPartitionDevice, ata_disk: Option<u8>) {
+    let Some(disk) = ata_disk else {
+        klog!(info, "crash", "crash dump requires an ata startup disk");
+        return;
+    };
+    let region = Region {
+        disk,
+        start: partition.get_partition_start() as u64,
+        sectors: partition.block_count(),
+        device: Arc::new(partition),
+    };
+    let _guard = IrqGuard::cli();
+    *REGION.lock() = Some(region);
+}
+
+/// 将崩溃转储写入崩溃转储分区，返回是否写入成功，由panic处理函数调用
+///
+/// 不进行堆分配。没有崩溃转储分区，或分区、缓冲区被占用时直接返回false
+pub fn write(info: &PanicInfo) -> bool {
+    let Some((disk, start, sectors)) = REGION
+        .try_lock()
+        .and_then(|region| region.as_ref().map(|r| (r.disk, r.start, r.sectors)))
+    else {
+        return false;
+    };
+    let Some(mut dump) = DUMP.try_lock() else {
+        return false;
+    };
+    let capacity = (sectors as usize)
+        .saturating_mul(SECTOR_SIZE)
+        .min(dump.len());
+    if capacity < SECTOR_SIZE * 2 {
+        return false;
+    }
+
+    let (header, body) = dump.split_at_mut(SECTOR_SIZE);
+    // 报告与栈的大小不超过分区的容量
+    let report_capacity = REPORT_SIZE.min(capacity - SECTOR_SIZE);
+    let mut report = BufferWriter {
+        buffer: &mut body[..report_capacity],
+        len: 0,
+    };
+    write_report(&mut report, info);
+    let report_size = report.len;
+    let stack_offset = report_size.next_multiple_of(SECTOR_SIZE);
+    let stack_capacity = STACK_SIZE.min(capacity - SECTOR_SIZE - stack_offset);
+
+    // 由CPU异常引发的panic，记录异常发生时的栈，否则记录当前的栈
+    let (rsp, rbp) = match trap::exception::last_kernel_exception() {
+        Some(record) => (record.registers.rsp, record.registers.rbp),
+        None => current_stack(),
+    };
+    let stack = &mut body[stack_offset..stack_offset + stack_capacity];
+    let stack_size = copy_stack(rsp, stack);
+    stack[stack_size..stack_size.next_multiple_of(SECTOR_SIZE)].fill(0);
+    body[report_size..stack_offset].fill(0);
+
+    let mut frames = [0usize; MAX_FRAMES];
+    let depth = backtrace::capture_from(rbp as usize, &mut frames);
+    let header_value = Header {
+        flags: FLAG_UNREAD,
+        uptime: clocksource::now_ns(),
+        report_size: report_size as u32,
+        stack_size: stack_size as u32,
+        stack_address: rsp,
+        depth: depth as u32,
+        frames: frames.map(|frame| frame as u64),
+    };
+    header_value.write_to(header);
+
+    let size = header_value.sectors() as usize * SECTOR_SIZE;
+    // Safety: panic时已关中断，其他代码不会再访问磁盘
+    unsafe { io::disk::ata_lba::write_polled(disk, start, &dump[..size]) }
+}
+
+/// 写入报告：panic信息、异常寄存器、线程列表与最近的日志
+fn write_report(writer: &mut BufferWriter, info: &PanicInfo) {
+    _ = writeln!(writer, "kernel panic: {}", info.message());
+    if let Some(location) = info.location() {
+        _ = writeln!(writer, "at {location}");
+    }
+    let now = clocksource::now_ns();
+    _ = writeln!(
+        writer,
+        "uptime: {}.{:06}s",
+        now / 1_000_000_000,
+        now % 1_000_000_000 / 1000
+    );
+    if let Some(record) = trap::exception::last_kernel_exception() {
+        _ = writeln!(writer, "\nexception:");
+        _ = record.write_details(writer);
+    }
+    _ = writeln!(writer, "\nthreads:");
+    _ = multitask::thread::write_threads(writer);
+    _ = writeln!(writer, "\nrecent log:");
+    let len = klog::copy_recent(&mut writer.buffer[writer.len..]);
+    writer.len += len;
+}
+
+/// 当前的rsp与rbp
+#[inline(always)]
+fn current_stack() -> (u64, u64) {
+    let (rsp, rbp): (u64, u64);
+    unsafe {
+        asm!(
+            "mov {}, rsp",
+            "mov {}, rbp",
+            out(reg) rsp,
+            out(reg) rbp,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    (rsp, rbp)
+}
+
+/// 从rsp开始复制内核栈，直到缓冲区已满或遇到未映射的页，返回复制的字节数
+fn copy_stack(rsp: u64, buffer: &mut [u8]) -> usize {
+    // 内核栈位于高半部分
+    if rsp < 0xFFFF_8000_0000_0000 {
+        return 0;
+    }
+    let mut copied = 0;
+    while copied < buffer.len() {
+        let Some(address) = (rsp as usize).checked_add(copied) else {
+            break;
+        };
+        if memory::page::kernel_physical_address(address).is_none() {
+            break;
+        }
+        let len = (0x1000 - address % 0x1000).min(buffer.len() - copied);
+        // Safety: 地址所在的页已映射
+        unsafe {
+            ptr::copy_nonoverlapping(address as *const u8, buffer[copied..].as_mut_ptr(), len);
+        }
+        copied += len;
+    }
+    copied
+}
+
+/// 读取上次panic时写入的崩溃转储，保存为 [DUMP_PATH] 并标记为已保存，由内核在挂载根文件系统后调用
+pub async fn collect() {
+    let device = {
+        let _guard = IrqGuard::cli();
+        match REGION.lock().as_ref() {
+            Some(region) => region.device.clone(),
+            None => return,
+        }
+    };
+    let mut header = vec![0; SECTOR_SIZE];
+    if let Err(error) = device.read_block(0, &mut header).await {
+        klog!(warn, "crash", "failed to read crash dump: {error:?}");
+        return;
+    }
+    let Some(mut parsed) = Header::parse(&header) else {
+        return;
+    };
+    if parsed.flags & FLAG_UNREAD == 0 {
+        return;
+    }
+
+    let sectors = parsed.sectors().min(device.block_count());
+    let mut dump = vec![0; sectors as usize * SECTOR_SIZE];
+    if let Err(error) = device.read_blocks(0, sectors, &mut dump).await {
+        klog!(warn, "crash", "failed to read crash dump: {error:?}");
+        return;
+    }
+    let report =
+        &dump[SECTOR_SIZE..][..(parsed.report_size as usize).min(dump.len() - SECTOR_SIZE)];
+    let message = report.split(|&ch| ch == b'\n').next().unwrap_or_default();
+    klog!(
+        warn,
+        "crash",
+        "kernel crashed in the last boot: {}",
+        String::from_utf8_lossy(message)
+    );
+    if let Err(error) = save(&dump).await {
+        klog!(warn, "crash", "failed to save crash dump: {error:?}");
+        return;
+    }
+
+    // 保存后清除标志，下次启动不再重复保存
+    parsed.flags &= !FLAG_UNREAD;
+    parsed.write_to(&mut header);
+    match device.write_block(0, &header).await {
+        Ok(()) => klog!(info, "crash", "crash dump saved to {DUMP_PATH}"),
+        Err(error) => klog!(
+            warn,
+            "crash",
+            "failed to mark crash dump as saved: {error:?}"
+        ),
+    }
+}
+
+/// 将转储写入 [DUMP_PATH]
+///
+/// 转储含有内核栈等内存内容，目录与文件只允许超级用户访问
+async fn save(dump: &[u8]) -> Result<(), FileSystemError> {
+    let dir = PathBuf::from_str(DUMP_DIR).expect("codebug: crash dump dir should be valid");
+    let dir = dir.as_path();
+    let (fs, relative) = io::vfs::resolve(&dir).ok_or(FileSystemError::FileNotFound)?;
+    match fs.create_directory(relative).await {
+        Ok(()) | Err(FileSystemError::FileExists) => (),
+        Err(error) => return Err(error),
+    }
+    io::permission::created_by_kernel(dir, DUMP_MODE).await;
+
+    let path = PathBuf::from_str(DUMP_PATH).expect("codebug: crash dump path should be valid");
+    let path = path.as_path();
+    let (fs, relative) = io::vfs::resolve(&path).ok_or(FileSystemError::FileNotFound)?;
+    match fs.create_file(relative).await {
+        Ok(()) | Err(FileSystemError::FileExists) => (),
+        Err(error) => return Err(error),
+    }
+    io::permission::created_by_kernel(path, DUMP_MODE).await;
+    let mut file = fs.open_file(relative).await?;
+    let result = async {
+        file.write(dump).await?;
+        file.truncate().await
+    }
+    .await;
+    file.close().await?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_header() {
+        let header = Header {
+            flags: FLAG_UNREAD,
+            uptime: 1_500_000_000,
+            report_size: 1000,
+            stack_size: 4096,
+            stack_address: 0xFFFF_8000_0001_0000,
+            depth: 2,
+            frames: core::array::from_fn(|index| if index < 2 { 0x1000 + index as u64 } else { 0 }),
+        };
+        let mut bytes = [0xFF; SECTOR_SIZE];
+        header.write_to(&mut bytes);
+        assert_eq!(Header::parse(&bytes), Some(header));
+        assert_eq!(header.sectors(), 1 + 2 + 8);
+
+        bytes[16] = 2;
+        assert_eq!(Header::parse(&bytes), None);
+        assert_eq!(Header::parse(&[0; SECTOR_SIZE]), None);
+    }
+}
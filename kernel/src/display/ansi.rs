@@ -0,0 +1,334 @@
+/// 控制序列参数的最大数量，多出的参数被忽略
+const MAX_PARAMS: usize = 8;
+
+/// ANSI转义序列的解析器
+///
+/// 逐字节输入，跨多次写入保持状态，因此用户程序可以逐字符输出转义序列。支持的子集：
+///
+/// - `ESC [ n A/B/C/D`：光标上、下、右、左移动n格
+/// - `ESC [ r ; c H`、`ESC [ r ; c f`：光标移动到第r行第c列（从1开始）
+/// - `ESC [ n J`：清屏，n为0时清除光标至屏幕末尾，1时清除屏幕开头至光标，2时清除整个屏幕
+/// - `ESC [ n K`：清除行，n的含义同上，范围为光标所在行
+/// - `ESC [ n S`：屏幕向上滚动n行
+/// - `ESC [ ... m`：设置颜色，支持0、1、30~37、39、40~47、49、90~97、100~107
+/// - `ESC [ s`、`ESC [ u`：保存、恢复光标位置
+/// - `ESC [ ? 25 h/l`：显示、隐藏光标
+///
+/// 不支持的序列被完整读取后丢弃
+#[derive(Default)]
+pub struct AnsiParser {
+    state: State,
+    params: Params,
+    // 正在读取的参数的下标，可能超出MAX_PARAMS，此时读取的参数被忽略
+    index: usize,
+    // 是否为`ESC [ ?`开头的私有序列
+    private: bool,
+}
+
+#[derive(Clone, Copy, Default)]
+enum State {
+    #[default]
+    Ground,
+    Escape,
+    Csi,
+}
+
+/// 控制序列的参数，省略的参数记为0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Params {
+    values: [u16; MAX_PARAMS],
+    len: usize,
+}
+
+/// 解析得到的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnsiAction {
+    /// 输出字符，包括换行、退格等控制字符
+    Print(u8),
+    CursorUp(u16),
+    CursorDown(u16),
+    CursorForward(u16),
+    CursorBack(u16),
+    /// 移动光标到指定位置，从0开始
+    CursorPosition {
+        row: u16,
+        col: u16,
+    },
+    EraseDisplay(EraseMode),
+    EraseLine(EraseMode),
+    ScrollUp(u16),
+    /// 设置颜色，参数为SGR参数
+    SetGraphics(Params),
+    SaveCursor,
+    RestoreCursor,
+    ShowCursor(bool),
+}
+
+/// 清除的范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EraseMode {
+    /// 光标至末尾（含光标）
+    ToEnd,
+    /// 开头至光标（含光标）
+    ToStart,
+    All,
+}
+
+impl Params {
+    const fn new() -> Self {
+        Self {
+            values: [0; MAX_PARAMS],
+            len: 0,
+        }
+    }
+
+    /// 第index个参数，省略时为0
+    pub fn get(&self, index: usize) -> u16 {
+        if index < self.len {
+            self.values[index]
+        } else {
+            0
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        self.values[..self.len].iter().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 第index个参数，省略或为0时为1，用于移动距离等计数
+    fn count(&self, index: usize) -> u16 {
+        self.get(index).max(1)
+    }
+
+    fn erase_mode(&self) -> Option<EraseMode> {
+        match self.get(0) {
+            0 => Some(EraseMode::ToEnd),
+            1 => Some(EraseMode::ToStart),
+            2 => Some(EraseMode::All),
+            _ => None,
+        }
+    }
+}
+
+impl AnsiParser {
+    pub const fn new() -> Self {
+        Self {
+            state: State::Ground,
+            params: Params::new(),
+            index: 0,
+            private: false,
+        }
+    }
+
+    /// 输入一个字节，序列尚未结束或被丢弃时返回None
+    pub fn advance(&mut self, byte: u8) -> Option<AnsiAction> {
+        match self.state {
+            State::Ground => {
+                if byte == 0x1B {
+                    self.state = State::Escape;
+                    None
+                } else {
+                    Some(AnsiAction::Print(byte))
+                }
+            }
+            State::Escape => {
+                match byte {
+                    b'[' => {
+                        self.state = State::Csi;
+                        self.params = Params::new();
+                        self.index = 0;
+                        self.private = false;
+                    }
+                    // 中间字节，如`ESC ( B`，序列尚未结束
+                    0x20..=0x2F => (),
+                    // 仅支持CSI序列，其他序列在此结束，直接丢弃
+                    _ => self.state = State::Ground,
+                }
+                None
+            }
+            State::Csi => match byte {
+                b'0'..=b'9' => {
+                    if self.params.len == 0 {
+                        self.params.len = 1;
+                    }
+                    if let Some(value) = self.params.values.get_mut(self.index) {
+                        *value = value
+                            .saturating_mul(10)
+                            .saturating_add((byte - b'0') as u16);
+                    }
+                    None
+                }
+                b';' => {
+                    // 省略的参数同样占据一个位置
+                    if self.params.len == 0 {
+                        self.params.len = 1;
+                    }
+                    self.index = self.index.saturating_add(1);
+                    if self.index < MAX_PARAMS {
+                        self.params.len = self.index + 1;
+                    }
+                    None
+                }
+                b'?' if self.params.is_empty() && !self.private => {
+                    self.private = true;
+                    None
+                }
+                // 中间字节，本解析器不使用
+                0x20..=0x3F => None,
+                0x40..=0x7E => {
+                    self.state = State::Ground;
+                    self.dispatch(byte)
+                }
+                // 序列中出现控制字符，放弃该序列
+                _ => {
+                    self.state = State::Ground;
+                    None
+                }
+            },
+        }
+    }
+
+    fn dispatch(&self, final_byte: u8) -> Option<AnsiAction> {
+        let params = &self.params;
+        if self.private {
+            return match (final_byte, params.get(0)) {
+                (b'h', 25) => Some(AnsiAction::ShowCursor(true)),
+                (b'l', 25) => Some(AnsiAction::ShowCursor(false)),
+                _ => None,
+            };
+        }
+        match final_byte {
+            b'A' => Some(AnsiAction::CursorUp(params.count(0))),
+            b'B' => Some(AnsiAction::CursorDown(params.count(0))),
+            b'C' => Some(AnsiAction::CursorForward(params.count(0))),
+            b'D' => Some(AnsiAction::CursorBack(params.count(0))),
+            b'H' | b'f' => Some(AnsiAction::CursorPosition {
+                row: params.count(0) - 1,
+                col: params.count(1) - 1,
+            }),
+            b'J' => params.erase_mode().map(AnsiAction::EraseDisplay),
+            b'K' => params.erase_mode().map(AnsiAction::EraseLine),
+            b'S' => Some(AnsiAction::ScrollUp(params.count(0))),
+            b'm' => Some(AnsiAction::SetGraphics(*params)),
+            b's' => Some(AnsiAction::SaveCursor),
+            b'u' => Some(AnsiAction::RestoreCursor),
+            _ => None,
+        }
+    }
+}
+
+/// 将SGR参数应用于VGA文本样式，返回新的样式
+///
+/// 样式低4位为前景色，4~6位为背景色。第7位在默认配置下表示闪烁，因此亮色背景按普通背景色处理
+pub fn apply_graphics(style: u8, default_style: u8, params: &Params) -> u8 {
+    // ANSI颜色顺序（黑红绿黄蓝品青白）对应的VGA颜色
+    const VGA_COLORS: [u8; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+    if params.is_empty() {
+        return default_style;
+    }
+    let mut style = style;
+    for param in params.iter() {
+        style = match param {
+            0 => default_style,
+            // 粗体以高亮前景色表示，设置前景色时保留
+            1 => style | 0x08,
+            30..=37 => (style & 0xF8) | VGA_COLORS[(param - 30) as usize],
+            39 => (style & 0xF8) | (default_style & 0x07),
+            40..=47 => (style & 0x0F) | (VGA_COLORS[(param - 40) as usize] << 4),
+            49 => (style & 0x0F) | (default_style & 0xF0),
+            90..=97 => (style & 0xF0) | VGA_COLORS[(param - 90) as usize] | 0x08,
+            100..=107 => (style & 0x0F) | (VGA_COLORS[(param - 100) as usize] << 4),
+            _ => style,
+        };
+    }
+    style
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    fn parse(bytes: &[u8]) -> Vec<AnsiAction> {
+        let mut parser = AnsiParser::new();
+        bytes
+            .iter()
+            .filter_map(|&byte| parser.advance(byte))
+            .collect()
+    }
+
+    #[test_case]
+    fn plain_text() {
+        assert_eq!(
+            parse(b"a\n"),
+            [AnsiAction::Print(b'a'), AnsiAction::Print(b'\n')]
+        );
+    }
+
+    #[test_case]
+    fn cursor_movement() {
+        assert_eq!(
+            parse(b"\x1b[A\x1b[3C\x1b[0D"),
+            [
+                AnsiAction::CursorUp(1),
+                AnsiAction::CursorForward(3),
+                AnsiAction::CursorBack(1)
+            ]
+        );
+        assert_eq!(
+            parse(b"\x1b[5;10H\x1b[H\x1b[;2f"),
+            [
+                AnsiAction::CursorPosition { row: 4, col: 9 },
+                AnsiAction::CursorPosition { row: 0, col: 0 },
+                AnsiAction::CursorPosition { row: 0, col: 1 }
+            ]
+        );
+    }
+
+    #[test_case]
+    fn erase_and_private() {
+        assert_eq!(
+            parse(b"\x1b[2J\x1b[K\x1b[3K\x1b[?25l"),
+            [
+                AnsiAction::EraseDisplay(EraseMode::All),
+                AnsiAction::EraseLine(EraseMode::ToEnd),
+                AnsiAction::ShowCursor(false)
+            ]
+        );
+    }
+
+    #[test_case]
+    fn unsupported_sequence_is_dropped() {
+        assert_eq!(
+            parse(b"\x1b[1;2;3;4;5;6;7;8;9;10Z\x1b(Bx"),
+            [AnsiAction::Print(b'x')]
+        );
+    }
+
+    #[test_case]
+    fn graphics() {
+        let [AnsiAction::SetGraphics(params)] = parse(b"\x1b[1;31;44m")[..] else {
+            panic!("expected a single SGR action");
+        };
+        assert_eq!(apply_graphics(0x07, 0x07, &params), 0x1C);
+        let [AnsiAction::SetGraphics(reset)] = parse(b"\x1b[m")[..] else {
+            panic!("expected a single SGR action");
+        };
+        assert_eq!(apply_graphics(0x1C, 0x07, &reset), 0x07);
+    }
+
+    #[test_case]
+    fn extra_params_are_ignored() {
+        let [AnsiAction::SetGraphics(params)] = parse(b"\x1b[0;0;0;0;0;0;0;31;44;1m")[..] else {
+            panic!("expected a single SGR action");
+        };
+        assert_eq!(params.iter().count(), MAX_PARAMS);
+        assert_eq!(params.get(MAX_PARAMS - 1), 31);
+    }
+}
@@ -0,0 +1,173 @@
+use async_locks::{once::OnceLock, watch};
+
+use crate::{
+    display::vga_text::VgaTextWriter,
+    sync::{int::IrqGuard, spin::SpinLock},
+};
+
+/// 虚拟控制台的数量，以Alt+F1~F4切换
+pub const COUNT: usize = 4;
+/// 用户进程默认使用的控制台，即VT1
+pub const DEFAULT: usize = 0;
+/// 内核日志输出的控制台，即VT2
+pub const LOG: usize = 1;
+
+static SCREENS: SpinLock<Option<Screens>> = SpinLock::new(None);
+
+/// 各控制台大小或显示模式变化的次数，变化时通知等待者，见 [wait_resize]
+static RESIZES: OnceLock<ResizeWatch> = OnceLock::new();
+
+// 发布各控制台大小变化次数的watch，以及用于复制的订阅者
+type ResizeWatch = (
+    SpinLock<watch::Publisher<[u64; COUNT]>>,
+    watch::Subscriber<[u64; COUNT]>,
+);
+
+/// 未显示的控制台的后台缓冲区，显示中的控制台使用显存
+///
+/// 切换控制台时缓冲区与显存交换内容与所有权，因此总共只需要COUNT-1个后台缓冲区。
+/// 在堆内存初始化之前即需要使用，因此为静态分配
+static mut OFF_SCREEN_BUFFERS: [[u16; VgaTextWriter::SIZE]; COUNT - 1] =
+    [[0; VgaTextWriter::SIZE]; COUNT - 1];
+
+struct Screens {
+    // 各控制台的屏幕内容、光标与样式
+    writers: [VgaTextWriter; COUNT],
+    // 正在显示的控制台
+    active: usize,
+    // 各控制台大小或显示模式变化的次数
+    resizes: [u64; COUNT],
+}
+
+impl Screens {
+    /// 记录控制台的大小或显示模式变化并发布
+    ///
+    /// 调用者持有SCREENS时发布，保证发布的次数不会倒退
+    fn changed(&mut self, console: usize, publisher: &SpinLock<watch::Publisher<[u64; COUNT]>>) {
+        self.resizes[console] += 1;
+        publisher.lock().send(self.resizes);
+    }
+}
+
+pub fn init() {
+    let _guard = IrqGuard::cli();
+    let mut screens = SCREENS.lock();
+    if screens.is_some() {
+        return;
+    }
+    let buffers = &raw mut OFF_SCREEN_BUFFERS;
+    // Safety: SCREENS为None时尚未创建任何VgaTextWriter，后台缓冲区只在此处取得一次
+    let mut buffers = unsafe { &mut *buffers }.iter_mut();
+    let writers = core::array::from_fn(|console| {
+        if console == DEFAULT {
+            // Safety: 显存只由显示中的控制台持有
+            unsafe { VgaTextWriter::new() }
+        } else {
+            VgaTextWriter::off_screen(buffers.next().unwrap())
+        }
+    });
+    screens.replace(Screens {
+        writers,
+        active: DEFAULT,
+        resizes: [0; COUNT],
+    });
+}
+
+/// 正在显示的控制台
+pub fn active() -> usize {
+    let _guard = IrqGuard::cli();
+    SCREENS
+        .lock()
+        .as_ref()
+        .map_or(DEFAULT, |screens| screens.active)
+}
+
+/// 切换显示的控制台，console超出范围时不切换
+///
+/// 切换后的控制台由后台缓冲区改为显示在屏幕上，视为显示模式变化，通知 [wait_resize] 的等待者，
+/// 以便全屏程序重新查询大小并重新绘制（如屏幕曾被其他控制台的图形程序占用）
+pub fn switch(console: usize) {
+    if console >= COUNT {
+        return;
+    }
+    let publisher = &resizes_watch().0;
+    let _guard = IrqGuard::cli();
+    let mut screens = SCREENS.lock();
+    let screens = screens.as_mut().expect("vga_text is not available");
+    if screens.active == console {
+        return;
+    }
+    let [from, to] = screens
+        .writers
+        .get_disjoint_mut([screens.active, console])
+        .unwrap();
+    from.switch_to(to);
+    screens.active = console;
+    screens.changed(console, publisher);
+}
+
+/// 以指定控制台的VgaTextWriter执行f
+pub fn with_writer<R>(console: usize, f: impl FnOnce(&mut VgaTextWriter) -> R) -> R {
+    let _guard = IrqGuard::cli();
+    let mut screens = SCREENS.lock();
+    let screens = screens.as_mut().expect("vga_text is not available");
+    f(&mut screens.writers[console])
+}
+
+/// 以指定控制台的VgaTextWriter执行f，控制台被占用时不执行并返回None
+///
+/// 用于中断处理等不能等待锁的场景
+pub fn try_with_writer<R>(console: usize, f: impl FnOnce(&mut VgaTextWriter) -> R) -> Option<R> {
+    let _guard = IrqGuard::cli();
+    let mut screens = SCREENS.try_lock()?;
+    Some(f(&mut screens.as_mut()?.writers[console]))
+}
+
+/// 控制台文本区域的行数与列数
+pub fn size(console: usize) -> (u8, u8) {
+    with_writer(console, |writer| writer.size())
+}
+
+/// 修改控制台文本区域的大小，超出屏幕的部分被限制在屏幕大小，返回修改后的大小
+///
+/// 大小发生变化时通知 [wait_resize] 的等待者，以便全屏程序重新绘制
+pub fn resize(console: usize, rows: u8, cols: u8) -> (u8, u8) {
+    let publisher = &resizes_watch().0;
+    let _guard = IrqGuard::cli();
+    let mut screens = SCREENS.lock();
+    let screens = screens.as_mut().expect("vga_text is not available");
+    let writer = &mut screens.writers[console];
+    let old = writer.size();
+    let size = writer.resize(rows, cols);
+    if size != old {
+        screens.changed(console, publisher);
+    }
+    size
+}
+
+/// 控制台大小或显示模式变化的次数，用于 [wait_resize]
+pub fn resize_count(console: usize) -> u64 {
+    resizes_watch().1.clone().borrow()[console]
+}
+
+/// 等待控制台大小或显示模式变化的次数不再等于seen，返回新的次数
+pub async fn wait_resize(console: usize, seen: u64) -> u64 {
+    let mut subscriber = resizes_watch().1.clone();
+    loop {
+        // borrow同步最新的版本，此后的变化都会唤醒wait
+        let count = subscriber.borrow()[console];
+        if count != seen {
+            return count;
+        }
+        if subscriber.wait().await.is_err() {
+            panic!("codebug: publisher of console resizes is never dropped");
+        }
+    }
+}
+
+fn resizes_watch() -> &'static ResizeWatch {
+    RESIZES.get_or_init(|| {
+        let (publisher, subscriber) = watch::pair([0; COUNT]);
+        (SpinLock::new(publisher), subscriber)
+    })
+}
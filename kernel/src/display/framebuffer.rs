@@ -0,0 +1,49 @@
+use core::ptr;
+
+use async_locks::once::OnceLock;
+
+use crate::{
+    bootloader::{self, Framebuffer},
+    memory,
+};
+
+/// 帧缓冲区在内核空间中的地址，首次输出时映射，映射失败时为None
+static MAPPED: OnceLock<Option<usize>> = OnceLock::new();
+
+/// 固件提供的帧缓冲区，VGA文本模式下返回None
+pub fn info() -> Option<Framebuffer> {
+    bootloader::framebuffer()
+}
+
+/// 将一行像素写入帧缓冲区的 (x, y) 处，超出屏幕的部分被忽略
+///
+/// 像素格式为0x00RRGGBB，按帧缓冲区的像素格式转换后写入。帧缓冲区不可用或映射失败时返回false
+pub fn write_row(x: u32, y: u32, pixels: &[u32]) -> bool {
+    let Some(framebuffer) = info() else {
+        return false;
+    };
+    let Some(address) = *MAPPED.get_or_init(|| {
+        memory::map_mmio(framebuffer.base, framebuffer.size as usize).map(|region| region.leak())
+    }) else {
+        return false;
+    };
+    if x >= framebuffer.width || y >= framebuffer.height {
+        return true;
+    }
+    let count = pixels.len().min((framebuffer.width - x) as usize);
+    let offset = (y as usize * framebuffer.stride as usize + x as usize) * 4;
+    for (index, pixel) in pixels[..count].iter().enumerate() {
+        let pixel = match framebuffer.pixel_format {
+            // 内存中依次为R、G、B，交换红色与蓝色分量
+            Framebuffer::FORMAT_RGB => {
+                (pixel & 0xff00) | ((pixel >> 16) & 0xff) | ((pixel & 0xff) << 16)
+            }
+            _ => *pixel,
+        };
+        // Safety: 像素位于帧缓冲区内，帧缓冲区已映射到内核空间
+        unsafe {
+            ptr::write_volatile((address + offset + index * 4) as *mut u32, pixel);
+        }
+    }
+    true
+}
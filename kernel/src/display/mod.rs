@@ -1 +1,4 @@
+pub mod ansi;
+pub mod console;
+pub mod framebuffer;
 pub mod vga_text;
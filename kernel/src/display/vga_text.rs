@@ -1,12 +1,13 @@
 use core::{
     arch::asm,
     fmt::{Arguments, Write},
-    ptr, slice,
+    slice,
 };
 
-use crate::sync::{int::IrqGuard, spin::SpinLock};
-
-pub static WRITER: SpinLock<Option<VgaTextWriter>> = SpinLock::new(None);
+use crate::display::{
+    ansi::{self, AnsiAction, AnsiParser, EraseMode},
+    console,
+};
 
 #[macro_export]
 macro_rules! kprint {
@@ -26,49 +27,56 @@ macro_rules! kprintln {
 }
 
 pub fn init() {
-    let mut writer = WRITER.lock();
-    if writer.is_none() {
-        writer.replace(unsafe { VgaTextWriter::new() });
-    }
+    console::init();
 }
 
 pub struct VgaTextWriter {
-    buffer: &'static mut [u16],
-    cursor: (u8, u8), // row, col
+    buffer: &'static mut [u16], // 显示中时为显存，否则为后台缓冲区
+    visible: bool,              // 是否正在显示，只有显示中的控制台操作硬件光标
+    cursor_shown: bool,         // 由`ESC [ ? 25 h/l`设置的光标可见性
+    cursor: (u8, u8),           // row, col
+    size: (u8, u8),             // 文本区域的行数与列数，不超过屏幕大小，区域之外保持空白
     style: u8,
+    default_style: u8,      // 创建时的样式，用于重置颜色
+    saved_cursor: (u8, u8), // 由`ESC [ s`保存的光标位置
+    parser: AnsiParser,     // 转义序列的解析状态，可能跨多次写入
 }
 
 impl VgaTextWriter {
     const ADDRESS: usize = 0xb8000; // Buffer 地址
     const WIDTH: usize = 80; // 宽度
     const HEIGHT: usize = 25; // 高度
+    /// 缓冲区的字符数
+    pub const SIZE: usize = Self::WIDTH * Self::HEIGHT;
     const DEFAULT_STYLE: u8 = 0x07; // 默认样式，黑底白字
 
     /// 创建 VgaTextWriter
     ///
     /// Safety: 保证只创建一个VgaTextWriter，否则会有数据竞争
     /// VGA必须处于文本模式
-    unsafe fn new() -> Self {
-        // Safety: bootloader已经将此区域加入页表
-        let buffer = unsafe {
-            slice::from_raw_parts_mut(Self::ADDRESS as *mut u16, Self::WIDTH * Self::HEIGHT)
-        };
+    pub(super) unsafe fn new() -> Self {
+        unsafe { Self::with_style(Self::DEFAULT_STYLE) }
+    }
 
-        // 清空缓冲区
+    /// 创建写入后台缓冲区的VgaTextWriter，切换到该控制台时才显示
+    pub(super) fn off_screen(buffer: &'static mut [u16]) -> Self {
+        assert_eq!(buffer.len(), Self::SIZE);
         buffer.fill(Self::char_with_style(Self::DEFAULT_STYLE, b' '));
-
-        // 复位光标
-        Self::hw_set_cursor(0, 0);
-
         Self {
             buffer,
+            visible: false,
+            cursor_shown: true,
             cursor: (0, 0),
+            size: (Self::HEIGHT as u8, Self::WIDTH as u8),
             style: Self::DEFAULT_STYLE,
+            default_style: Self::DEFAULT_STYLE,
+            saved_cursor: (0, 0),
+            parser: AnsiParser::new(),
         }
     }
 
     /// 创建带有样式的VgaTextWriter
-    /// 
+    ///
     /// 使用pub公开，因为在panic需要重新创建，而不能信任全局写入的对象
     pub unsafe fn with_style(style: u8) -> Self {
         // Safety: bootloader已经将此区域加入页表
@@ -81,28 +89,23 @@ impl VgaTextWriter {
 
         // 复位光标
         Self::hw_set_cursor(0, 0);
+        Self::hw_show_cursor(true);
 
         Self {
             buffer,
+            visible: true,
+            cursor_shown: true,
             cursor: (0, 0),
+            size: (Self::HEIGHT as u8, Self::WIDTH as u8),
             style,
+            default_style: style,
+            saved_cursor: (0, 0),
+            parser: AnsiParser::new(),
         }
     }
 
     /// 设置光标位置
     fn hw_set_cursor(row: u8, col: u8) {
-        #[inline]
-        unsafe fn outb(port: u16, val: u8) {
-            unsafe {
-                asm!(
-                    "out dx, al",
-                    in("dx") port,
-                    in("al") val,
-                    options(nostack, preserves_flags)
-                );
-            }
-        }
-
         let pos = (row as usize * Self::WIDTH + col as usize) as u16;
         unsafe {
             outb(0x3D4, 0x0F);
@@ -112,6 +115,26 @@ impl VgaTextWriter {
         }
     }
 
+    /// 显示或隐藏光标，光标起始扫描线寄存器的第5位为1时隐藏光标
+    fn hw_show_cursor(show: bool) {
+        unsafe {
+            outb(0x3D4, 0x0A);
+            let start = inb(0x3D5);
+            outb(0x3D5, if show { start & !0x20 } else { start | 0x20 });
+        }
+    }
+
+    /// 将显示从self切换到to：交换两者缓冲区的内容与所有权，显存交给to，并恢复to的光标
+    pub(super) fn switch_to(&mut self, to: &mut Self) {
+        debug_assert!(self.visible && !to.visible);
+        self.buffer.swap_with_slice(to.buffer);
+        core::mem::swap(&mut self.buffer, &mut to.buffer);
+        self.visible = false;
+        to.visible = true;
+        Self::hw_set_cursor(to.cursor.0, to.cursor.1);
+        Self::hw_show_cursor(to.cursor_shown);
+    }
+
     pub fn row(&self) -> u8 {
         self.cursor.0
     }
@@ -120,61 +143,165 @@ impl VgaTextWriter {
         self.cursor.1
     }
 
+    /// 文本区域的行数与列数
+    pub fn size(&self) -> (u8, u8) {
+        self.size
+    }
+
+    /// 修改文本区域的大小，超出屏幕的部分被限制在屏幕大小，返回修改后的大小
+    ///
+    /// 区域内的内容保持不变，区域外的内容被清除，光标被限制在区域内
+    pub(super) fn resize(&mut self, rows: u8, cols: u8) -> (u8, u8) {
+        let rows = rows.clamp(1, Self::HEIGHT as u8);
+        let cols = cols.clamp(1, Self::WIDTH as u8);
+        let blank = Self::char_with_style(self.default_style, b' ');
+        for (row, line) in self.buffer.chunks_exact_mut(Self::WIDTH).enumerate() {
+            let start = if row < rows as usize {
+                cols as usize
+            } else {
+                0
+            };
+            line[start..].fill(blank);
+        }
+        self.size = (rows, cols);
+        let clamp = |(row, col): (u8, u8)| (row.min(rows - 1), col.min(cols - 1));
+        self.cursor = clamp(self.cursor);
+        self.saved_cursor = clamp(self.saved_cursor);
+        if self.visible {
+            Self::hw_set_cursor(self.cursor.0, self.cursor.1);
+        }
+        self.size
+    }
+
     pub fn set_cursor(&mut self, row: u8, col: u8) {
-        assert!((row as usize) < Self::HEIGHT);
-        assert!((col as usize) < Self::WIDTH);
+        assert!(row < self.size.0);
+        assert!(col < self.size.1);
 
         self.cursor = (row, col);
-        Self::hw_set_cursor(row, col);
+        if self.visible {
+            Self::hw_set_cursor(row, col);
+        }
     }
 
+    /// 写入字节，其中的ANSI转义序列被解释执行，见 [AnsiParser]
     pub fn write_bytes(&mut self, bytes: &[u8]) {
-        const TAB_SIZE: u8 = 2;
-
         for byte in bytes {
-            match *byte {
-                // 回车
-                b'\r' => {
-                    self.cursor.1 = 0;
-                }
+            if let Some(action) = self.parser.advance(*byte) {
+                self.apply(action);
+            }
+        }
 
-                // 换行
-                b'\n' => {
-                    self.cursor.0 += 1;
-                    self.cursor.1 = 0;
-                    self.check_height_overflow();
-                }
+        if self.visible {
+            Self::hw_set_cursor(self.cursor.0, self.cursor.1);
+        }
+    }
 
-                // 退格
-                0x08 => {
-                    self.cursor.1 = self.cursor.1.saturating_sub(1);
+    fn apply(&mut self, action: AnsiAction) {
+        let (row, col) = (self.cursor.0 as usize, self.cursor.1 as usize);
+        match action {
+            AnsiAction::Print(byte) => self.put_byte(byte),
+            AnsiAction::CursorUp(n) => self.move_cursor(row.saturating_sub(n as usize), col),
+            AnsiAction::CursorDown(n) => self.move_cursor(row + n as usize, col),
+            AnsiAction::CursorForward(n) => self.move_cursor(row, col + n as usize),
+            AnsiAction::CursorBack(n) => self.move_cursor(row, col.saturating_sub(n as usize)),
+            AnsiAction::CursorPosition { row, col } => self.move_cursor(row as usize, col as usize),
+            AnsiAction::EraseDisplay(mode) => {
+                let (rows, cols) = (self.size.0 as usize, self.size.1 as usize);
+                let cursor = row * cols + col;
+                match mode {
+                    EraseMode::ToEnd => self.erase(cursor, rows * cols),
+                    EraseMode::ToStart => self.erase(0, cursor + 1),
+                    EraseMode::All => self.erase(0, rows * cols),
                 }
-
-                // TAB
-                b'\t' => {
-                    for _ in 0..TAB_SIZE {
-                        self.write_char(b' ');
-                        self.cursor.1 += 1;
-                        self.check_width_overflow();
-                    }
+            }
+            AnsiAction::EraseLine(mode) => {
+                let cols = self.size.1 as usize;
+                let line = row * cols;
+                match mode {
+                    EraseMode::ToEnd => self.erase(line + col, line + cols),
+                    EraseMode::ToStart => self.erase(line, line + col + 1),
+                    EraseMode::All => self.erase(line, line + cols),
                 }
-
-                // 可打印字符
-                ch @ 0x20..=0x7E => {
-                    self.write_char(ch);
-                    self.cursor.1 += 1;
-                    self.check_width_overflow();
+            }
+            AnsiAction::ScrollUp(n) => {
+                for _ in 0..(n as usize).min(self.size.0 as usize) {
+                    self.scroll_up();
+                }
+            }
+            AnsiAction::SetGraphics(params) => {
+                self.style = ansi::apply_graphics(self.style, self.default_style, &params);
+            }
+            AnsiAction::SaveCursor => self.saved_cursor = self.cursor,
+            AnsiAction::RestoreCursor => self.cursor = self.saved_cursor,
+            AnsiAction::ShowCursor(show) => {
+                self.cursor_shown = show;
+                if self.visible {
+                    Self::hw_show_cursor(show);
                 }
+            }
+        }
+    }
+
+    fn put_byte(&mut self, byte: u8) {
+        const TAB_SIZE: u8 = 2;
+
+        match byte {
+            // 回车
+            b'\r' => {
+                self.cursor.1 = 0;
+            }
+
+            // 换行
+            b'\n' => {
+                self.cursor.0 += 1;
+                self.cursor.1 = 0;
+                self.check_height_overflow();
+            }
 
-                _ => {
-                    self.write_char(b'.');
+            // 退格
+            0x08 => {
+                self.cursor.1 = self.cursor.1.saturating_sub(1);
+            }
+
+            // TAB
+            b'\t' => {
+                for _ in 0..TAB_SIZE {
+                    self.write_char(b' ');
                     self.cursor.1 += 1;
                     self.check_width_overflow();
                 }
             }
+
+            // 可打印字符
+            ch @ 0x20..=0x7E => {
+                self.write_char(ch);
+                self.cursor.1 += 1;
+                self.check_width_overflow();
+            }
+
+            _ => {
+                self.write_char(b'.');
+                self.cursor.1 += 1;
+                self.check_width_overflow();
+            }
         }
+    }
+
+    /// 移动光标，超出文本区域的位置被限制在区域边缘
+    fn move_cursor(&mut self, row: usize, col: usize) {
+        self.cursor = (
+            row.min(self.size.0 as usize - 1) as u8,
+            col.min(self.size.1 as usize - 1) as u8,
+        );
+    }
 
-        Self::hw_set_cursor(self.cursor.0, self.cursor.1);
+    /// 以当前样式的空格填充文本区域的 [start, end)，位置按文本区域逐行编号
+    fn erase(&mut self, start: usize, end: usize) {
+        let cols = self.size.1 as usize;
+        let blank = Self::char_with_style(self.style, b' ');
+        for position in start..end {
+            self.buffer[position / cols * Self::WIDTH + position % cols] = blank;
+        }
     }
 
     const fn char_with_style(style: u8, char: u8) -> u16 {
@@ -187,7 +314,7 @@ impl VgaTextWriter {
     }
 
     fn check_width_overflow(&mut self) {
-        if self.cursor.1 as usize >= Self::WIDTH {
+        if self.cursor.1 >= self.size.1 {
             self.cursor.1 = 0;
             self.cursor.0 += 1;
             self.check_height_overflow();
@@ -195,19 +322,48 @@ impl VgaTextWriter {
     }
 
     fn check_height_overflow(&mut self) {
-        if self.cursor.0 as usize >= Self::HEIGHT {
-            unsafe {
-                ptr::copy(
-                    self.buffer[Self::WIDTH..].as_mut_ptr(),
-                    self.buffer.as_mut_ptr(),
-                    Self::WIDTH * (Self::HEIGHT - 1),
-                );
-            }
-            self.buffer[(Self::WIDTH * (Self::HEIGHT - 1))..]
-                .fill(Self::char_with_style(self.style, b' '));
+        if self.cursor.0 >= self.size.0 {
+            self.scroll_up();
             self.cursor.0 -= 1;
         }
     }
+
+    /// 文本区域的内容向上滚动一行，底部补充空行，光标位置不变
+    fn scroll_up(&mut self) {
+        let (rows, cols) = (self.size.0 as usize, self.size.1 as usize);
+        for row in 1..rows {
+            let start = row * Self::WIDTH;
+            self.buffer
+                .copy_within(start..start + cols, start - Self::WIDTH);
+        }
+        self.erase((rows - 1) * cols, rows * cols);
+    }
+}
+
+#[inline]
+unsafe fn outb(port: u16, val: u8) {
+    unsafe {
+        asm!(
+            "out dx, al",
+            in("dx") port,
+            in("al") val,
+            options(nostack, preserves_flags)
+        );
+    }
+}
+
+#[inline]
+unsafe fn inb(port: u16) -> u8 {
+    let val: u8;
+    unsafe {
+        asm!(
+            "in al, dx",
+            in("dx") port,
+            out("al") val,
+            options(nostack, preserves_flags)
+        );
+    }
+    val
 }
 
 impl Write for VgaTextWriter {
@@ -219,11 +375,80 @@ impl Write for VgaTextWriter {
 
 #[doc(hidden)]
 pub fn _kprint(args: Arguments<'_>) {
-    let _guard = IrqGuard::cli();
-    let mut writer = WRITER.lock();
-    writer
-        .as_mut()
-        .expect("vga_text is not available")
-        .write_fmt(args)
-        .unwrap();
+    _kprint_to(console::LOG, args);
+}
+
+/// 输出到指定的控制台
+#[doc(hidden)]
+pub fn _kprint_to(console: usize, args: Arguments<'_>) {
+    console::with_writer(console, |writer| writer.write_fmt(args).unwrap());
+
+    // 同时输出到串口，便于在宿主机上查看日志
+    crate::io::serial::_write_fmt(args);
+}
+
+/// 同 [_kprint_with_style]，但日志控制台被占用时只输出到串口
+#[doc(hidden)]
+pub fn _try_kprint_with_style(style: u8, args: Arguments<'_>) {
+    console::try_with_writer(console::LOG, |writer| {
+        let prev_style = core::mem::replace(&mut writer.style, style);
+        writer.write_fmt(args).unwrap();
+        writer.style = prev_style;
+    });
+
+    crate::io::serial::_write_fmt(args);
+}
+
+/// 输出字节序列到指定的控制台，用于输出用户程序提供的、不一定是合法UTF-8的数据
+pub fn _kprint_bytes(console: usize, bytes: &[u8]) {
+    console::with_writer(console, |writer| writer.write_bytes(bytes));
+
+    crate::io::serial::write(bytes);
+}
+
+/// 以指定样式输出到日志控制台，输出后恢复原样式。串口输出不含样式
+#[doc(hidden)]
+pub fn _kprint_with_style(style: u8, args: Arguments<'_>) {
+    console::with_writer(console::LOG, |writer| {
+        let prev_style = core::mem::replace(&mut writer.style, style);
+        writer.write_fmt(args).unwrap();
+        writer.style = prev_style;
+    });
+
+    crate::io::serial::_write_fmt(args);
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{boxed::Box, vec};
+
+    use super::*;
+
+    fn writer() -> VgaTextWriter {
+        VgaTextWriter::off_screen(Box::leak(vec![0; VgaTextWriter::SIZE].into_boxed_slice()))
+    }
+
+    fn char_at(writer: &VgaTextWriter, row: usize, col: usize) -> u8 {
+        writer.buffer[row * VgaTextWriter::WIDTH + col] as u8
+    }
+
+    #[test_case]
+    fn test_resize() {
+        let mut writer = writer();
+        writer.write_bytes(b"abc");
+        assert_eq!(writer.resize(2, 2), (2, 2));
+        assert_eq!(char_at(&writer, 0, 1), b'b');
+        assert_eq!(char_at(&writer, 0, 2), b' ');
+        assert_eq!((writer.row(), writer.col()), (0, 1));
+
+        // 超出文本区域时换行并在区域内滚动
+        writer.write_bytes(b"\rxyzw!");
+        assert_eq!(char_at(&writer, 0, 0), b'z');
+        assert_eq!(char_at(&writer, 0, 1), b'w');
+        assert_eq!(char_at(&writer, 1, 0), b'!');
+        assert_eq!(char_at(&writer, 1, 1), b' ');
+        assert_eq!(char_at(&writer, 2, 0), b' ');
+
+        assert_eq!(writer.resize(100, 200), (25, 80));
+    }
 }
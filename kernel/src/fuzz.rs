@@ -0,0 +1,305 @@
+//! 系统调用模糊测试
+//!
+//! 仅在内核测试构建中编译，由启动选项 `fuzz=<seed>` 启用，代替 `#[test_case]` 测试用例运行。
+//! 内核以种子初始化伪随机数生成器，分批生成随机的系统调用编号与参数，写入由内核直接创建的
+//! 用户态桩进程。桩进程依次发起这些系统调用，之后退出；超时未退出（如阻塞在系统调用中）时由内核杀死。
+//! 桩进程以普通用户运行且不具有任何能力，随机调用不能删除文件、修改权限或改写启动内核等，
+//! 测试覆盖的是非特权进程可达的路径。
+//!
+//! 每批结束后检查进程能否被完整回收，全部结束后检查物理页是否泄漏；内核panic或检查失败时，
+//! 输出种子与批次，以相同的种子启动即可复现同一序列的系统调用。
+
+use alloc::{format, string::String, vec::Vec};
+use core::{arch::global_asm, num::NonZeroU64, time::Duration};
+
+use cos_sys::{idx, vdso::VDSO_ADDRESS};
+
+use crate::{
+    io, memory,
+    multitask::{
+        self,
+        capability::Capabilities,
+        process::{self, Credentials, ProcessPageType},
+    },
+    sync::int::IrqGuard,
+    syscall::SYSCALL_HANDLER,
+    testing,
+};
+
+/// 批次数量
+const BATCHES: usize = 128;
+/// 每批的系统调用数量
+const CALLS_PER_BATCH: usize = 32;
+/// 每批的运行时间上限，超时后杀死桩进程
+const BATCH_TIMEOUT: Duration = Duration::from_millis(200);
+/// 杀死桩进程后等待其被回收的时间上限
+const REAP_TIMEOUT: Duration = Duration::from_secs(2);
+/// 检查进程状态的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+/// 内核堆与缓存等会保留少量内存页，不视为泄漏
+const SLACK_FRAMES: u64 = 256;
+
+/// 桩进程代码的地址
+const STUB_CODE: u64 = 0x100_0000_0000;
+/// 控制页的地址，依次为系统调用数量与各次调用的编号及6个参数
+const STUB_CONTROL: u64 = STUB_CODE + 0x1_0000;
+/// 供指针参数指向的可写内存
+const STUB_SCRATCH: u64 = STUB_CODE + 0x2_0000;
+const STUB_SCRATCH_SIZE: u64 = 0x4000;
+
+/// 桩进程的用户与组，不是超级用户
+const STUB_CREDENTIALS: Credentials = Credentials {
+    uid: 1000,
+    gid: 1000,
+};
+
+/// 控制页中每次调用占用的字节数
+const CALL_SIZE: usize = 64;
+const _: () = assert!(8 + CALLS_PER_BATCH * CALL_SIZE <= 0x1000);
+
+/// 会结束测试、干扰结果输出或修改启动内核的系统调用，不进行测试
+const EXCLUDED_SYSCALLS: [u64; 5] = [
+    idx::IDX_SYSTEM_SHUTDOWN,
+    idx::IDX_SYSTEM_REBOOT,
+    idx::IDX_SYSTEM_UPDATE_KERNEL,
+    idx::IDX_DEBUG_EXIT_EMULATOR,
+    idx::IDX_DEBUG_SERIAL_WRITE,
+];
+
+// 桩进程的代码，按控制页依次发起系统调用后退出进程。
+// 系统调用保留rbx、r12与r13，因此用于保存循环状态
+global_asm!(
+    ".pushsection .rodata.fuzz_stub, \"a\"",
+    ".global fuzz_stub_start",
+    ".global fuzz_stub_end",
+    "fuzz_stub_start:",
+    "mov rbx, {control}",
+    "mov r12, [rbx]",
+    "lea r13, [rbx + 8]",
+    "2:",
+    "test r12, r12",
+    "jz 3f",
+    "mov rax, [r13]",
+    "mov rdi, [r13 + 8]",
+    "mov rsi, [r13 + 16]",
+    "mov rdx, [r13 + 24]",
+    "mov r10, [r13 + 32]",
+    "mov r8, [r13 + 40]",
+    "mov r9, [r13 + 48]",
+    "syscall",
+    "add r13, {call_size}",
+    "dec r12",
+    "jmp 2b",
+    "3:",
+    "mov rax, {exit}",
+    "xor edi, edi",
+    "syscall",
+    "ud2",
+    "fuzz_stub_end:",
+    ".popsection",
+    control = const STUB_CONTROL,
+    call_size = const CALL_SIZE,
+    exit = const idx::IDX_EXIT_PROCESS,
+);
+
+unsafe extern "C" {
+    static fuzz_stub_start: u8;
+    static fuzz_stub_end: u8;
+}
+
+/// 一次系统调用的编号与参数
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct Call {
+    id: u64,
+    args: [u64; 6],
+    // 对齐到 CALL_SIZE
+    _reserved: u64,
+}
+
+/// splitmix64伪随机数生成器，同一种子总是产生相同的序列
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// [0, bound) 中的随机数
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    fn choose<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len() as u64) as usize]
+    }
+}
+
+/// 以指定种子运行模糊测试
+pub async fn run(seed: u64) -> Result<(), String> {
+    io::serial::_write_fmt(format_args!(
+        "syscall fuzzing with seed {seed}, reproduce with boot option fuzz={seed}\n"
+    ));
+    let syscalls = SYSCALL_HANDLER
+        .iter()
+        .map(|(id, _)| *id)
+        .filter(|id| !EXCLUDED_SYSCALLS.contains(id))
+        .collect::<Vec<_>>();
+    let mut rng = Rng(seed);
+
+    let mut baseline = None;
+    for batch in 0..BATCHES {
+        testing::set_context(format!("(fuzz={seed}, batch {batch})"));
+        let calls = (0..CALLS_PER_BATCH)
+            .map(|_| random_call(&mut rng, &syscalls))
+            .collect::<Vec<_>>();
+        run_batch(&calls).await?;
+        // 首批调用可能使内核中的缓存增长，不计入统计
+        if baseline.is_none() {
+            baseline = Some(allocated_frames());
+        }
+    }
+
+    testing::set_context(format!("(fuzz={seed})"));
+    let before = baseline.unwrap_or_default();
+    let after = allocated_frames();
+    if after > before + SLACK_FRAMES {
+        return Err(format!(
+            "frames leaked after {BATCHES} batches: {before} -> {after}"
+        ));
+    }
+    Ok(())
+}
+
+/// 创建桩进程执行一批系统调用，等待其退出并被回收
+async fn run_batch(calls: &[Call]) -> Result<(), String> {
+    // Safety: 符号由global_asm定义，位于同一段中
+    let code = unsafe {
+        let start = &raw const fuzz_stub_start;
+        let end = &raw const fuzz_stub_end;
+        core::slice::from_raw_parts(start, end.offset_from_unsigned(start))
+    };
+    let process = process::create_code_process(
+        code,
+        NonZeroU64::new(STUB_CODE).unwrap(),
+        STUB_CREDENTIALS,
+        Capabilities::NONE,
+    )
+    .map_err(|error| format!("create stub process: {error:?}"))?;
+
+    for (address, size) in [(STUB_CONTROL, 0x1000), (STUB_SCRATCH, STUB_SCRATCH_SIZE)] {
+        let address = NonZeroU64::new(address).unwrap();
+        process::create_process_page(
+            &process,
+            size as usize,
+            ProcessPageType::StaticData(address),
+        )
+        .filter(|page| *page == address)
+        .ok_or("failed to map stub memory")?;
+    }
+    let count = calls.len() as u64;
+    unsafe {
+        process::write_user_process_memory_struct(&process, STUB_CONTROL, &count)
+            .and_then(|()| {
+                process::write_user_process_memory(
+                    &process,
+                    STUB_CONTROL + 8,
+                    calls.as_ptr().cast(),
+                    size_of_val(calls),
+                )
+            })
+            .map_err(|error| format!("write stub memory: {error:?}"))?;
+    }
+    process::start_main_thread(&process, STUB_CODE)
+        .map_err(|error| format!("start stub thread: {error:?}"))?;
+    let process_id = process::process_id(&process);
+
+    if wait_reaped(process_id, BATCH_TIMEOUT).await {
+        return Ok(());
+    }
+    // 桩进程阻塞在系统调用中，或随机创建的线程仍在运行
+    {
+        let _guard = IrqGuard::cli();
+        process::set_exit_code(&process, cos_sys::multitask::EXIT_KILL);
+        process::stop_all_thread(&process, cos_sys::multitask::EXIT_KILL);
+    }
+    drop(process);
+    if wait_reaped(process_id, REAP_TIMEOUT).await {
+        Ok(())
+    } else {
+        Err(format!(
+            "stub process {process_id} is not reaped after kill"
+        ))
+    }
+}
+
+/// 等待进程的线程全部停止、进程被回收，超时返回false
+async fn wait_reaped(process_id: u64, timeout: Duration) -> bool {
+    let mut waited = Duration::ZERO;
+    while process::get_process(process_id).is_some() {
+        if waited >= timeout {
+            return false;
+        }
+        multitask::async_task::sleep(POLL_INTERVAL).await;
+        waited += POLL_INTERVAL;
+    }
+    true
+}
+
+fn allocated_frames() -> u64 {
+    memory::reclaim::reclaim();
+    memory::frame_stats().allocated_frames
+}
+
+fn random_call(rng: &mut Rng, syscalls: &[u64]) -> Call {
+    let id = match rng.below(16) {
+        // 未定义的编号
+        0 => rng.next(),
+        // 已有类别中的相邻编号，多数未定义
+        1 => (rng.choose(syscalls) & !0xFFFF) | rng.below(0x20),
+        _ => rng.choose(syscalls),
+    };
+    let mut args = [0; 6];
+    args.iter_mut().for_each(|arg| *arg = random_arg(rng));
+    Call {
+        id,
+        args,
+        _reserved: 0,
+    }
+}
+
+/// 随机参数，偏向于边界值、可写内存附近的指针与较小的整数（如句柄与长度）
+fn random_arg(rng: &mut Rng) -> u64 {
+    const INTERESTING: [u64; 14] = [
+        0,
+        1,
+        0x7F,
+        0xFFF,
+        0x1000,
+        0xFFFF_FFFF,
+        i64::MAX as u64,
+        i64::MIN as u64,
+        u64::MAX,
+        // 非规范地址
+        0x0000_8000_0000_0000,
+        // 内核空间与内核代码
+        0xFFFF_8000_0000_0000,
+        0xFFFF_FFFF_8000_0000,
+        VDSO_ADDRESS,
+        STUB_CODE,
+    ];
+    match rng.below(8) {
+        0 | 1 => rng.choose(&INTERESTING),
+        2 | 3 => rng.below(16),
+        // 可写内存中的指针，可能未对齐
+        4 => STUB_SCRATCH + rng.below(STUB_SCRATCH_SIZE),
+        // 跨越可写内存结尾的指针
+        5 => STUB_SCRATCH + STUB_SCRATCH_SIZE - rng.below(64),
+        6 => 1 << rng.below(64),
+        _ => rng.next(),
+    }
+}
@@ -0,0 +1,52 @@
+use core::arch::asm;
+
+use crate::{memory, sync::int};
+
+/// 内核使用的硬件抽象层实现，直接执行特权指令
+///
+/// 调度、句柄表与页表遍历等与硬件无关的逻辑位于kernel_core中，通过此类型访问硬件
+pub struct X86Hal;
+
+impl kernel_core::hal::Hal for X86Hal {
+    fn interrupts_enabled() -> bool {
+        int::interrupts_enabled()
+    }
+
+    fn disable_interrupts() {
+        int::cli();
+    }
+
+    fn enable_interrupts() {
+        int::sti();
+    }
+
+    fn page_table() -> u64 {
+        let page_table: u64;
+        unsafe {
+            asm!(
+                "mov {}, cr3",
+                out(reg) page_table,
+                options(nostack, preserves_flags)
+            );
+        }
+        page_table
+    }
+
+    unsafe fn set_page_table(page_table: u64) {
+        unsafe {
+            asm!(
+                "mov cr3, {}",
+                in(reg) page_table,
+                options(nostack, preserves_flags)
+            );
+        }
+    }
+
+    unsafe fn read_physical(address: u64, dst: &mut [u8]) {
+        unsafe { memory::read_memory_bytes(address as usize, dst) };
+    }
+
+    unsafe fn write_physical(address: u64, src: &[u8]) {
+        unsafe { memory::write_memory_bytes(address as usize, src) };
+    }
+}
@@ -0,0 +1,493 @@
+use core::{arch::asm, hint::spin_loop};
+
+use alloc::vec::Vec;
+use async_locks::once::OnceLock;
+
+use crate::{bootloader, klog, memory, sync::int::IrqGuard};
+
+/// SDT表头长度
+const SDT_HEADER_LEN: usize = 36;
+/// 单个ACPI表的最大长度，超过时视为表损坏
+const MAX_TABLE_LEN: usize = 1024 * 1024;
+
+/// PM1控制寄存器：SCI_EN，为1时表示已处于ACPI模式
+const PM1_CONTROL_SCI_EN: u16 = 1 << 0;
+/// PM1控制寄存器：SLP_TYP的偏移
+const PM1_CONTROL_SLP_TYP_SHIFT: u16 = 10;
+/// PM1控制寄存器：SLP_EN，写入1时进入SLP_TYP指定的睡眠状态
+const PM1_CONTROL_SLP_EN: u16 = 1 << 13;
+/// FADT标志：RESET_REG_SUP，支持通过复位寄存器重启
+const FADT_FLAG_RESET_REG_SUP: u32 = 1 << 10;
+
+/// 通用地址结构中的地址空间
+const ADDRESS_SPACE_SYSTEM_MEMORY: u8 = 0;
+const ADDRESS_SPACE_SYSTEM_IO: u8 = 1;
+
+/// MADT条目类型
+const MADT_LOCAL_APIC: u8 = 0;
+const MADT_IO_APIC: u8 = 1;
+const MADT_INTERRUPT_OVERRIDE: u8 = 2;
+/// Local APIC条目标志：处理器可用
+const MADT_LOCAL_APIC_ENABLED: u32 = 1 << 0;
+
+/// 启动时解析的ACPI信息
+static ACPI: OnceLock<Acpi> = OnceLock::new();
+
+#[derive(Debug)]
+pub struct Acpi {
+    /// RSDP中的ACPI版本，0表示ACPI 1.0
+    pub revision: u8,
+    pub fadt: Option<Fadt>,
+    pub madt: Option<Madt>,
+    /// HPET寄存器的物理地址
+    pub hpet: Option<u64>,
+}
+
+/// FADT中电源管理相关的信息
+#[derive(Debug, Clone, Copy)]
+pub struct Fadt {
+    smi_command: u32,
+    acpi_enable: u8,
+    pm1a_control: u16,
+    pm1b_control: u16,
+    /// DSDT中\_S5对象的SLP_TYPa与SLP_TYPb，不存在时无法关机
+    s5: Option<(u16, u16)>,
+    /// 复位寄存器及写入的值
+    reset: Option<(GenericAddress, u8)>,
+}
+
+/// 通用地址结构
+#[derive(Debug, Clone, Copy)]
+struct GenericAddress {
+    address_space: u8,
+    address: u64,
+}
+
+/// MADT中的中断控制器信息
+#[derive(Debug, Default)]
+pub struct Madt {
+    /// Local APIC的物理地址
+    pub local_apic_address: u32,
+    /// 可用处理器的Local APIC ID
+    pub processors: Vec<u8>,
+    pub io_apics: Vec<IoApic>,
+    pub interrupt_overrides: Vec<InterruptOverride>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IoApic {
+    pub id: u8,
+    pub address: u32,
+    /// 此IO APIC处理的第一个全局中断号
+    pub interrupt_base: u32,
+}
+
+/// ISA中断到全局中断号的映射
+#[derive(Debug, Clone, Copy)]
+pub struct InterruptOverride {
+    pub source: u8,
+    pub global_interrupt: u32,
+    pub flags: u16,
+}
+
+/// 解析ACPI表
+///
+/// 引导程序未找到RSDP，或表格式错误时，ACPI不可用，关机与重启将使用后备方式
+///
+/// # Safety
+///
+/// 只能在内存初始化后调用一次，不能并发
+pub unsafe fn init() {
+    let acpi = {
+        // 物理内存的临时映射不可重入
+        let _guard = IrqGuard::cli();
+        bootloader::rsdp().and_then(|rsdp| parse(rsdp as usize))
+    };
+    match &acpi {
+        Some(acpi) => {
+            klog!(
+                info,
+                "acpi",
+                "revision {}, s5 {}, reset register {}, {} processors, {} io apics, hpet {}",
+                acpi.revision,
+                acpi.fadt.is_some_and(|fadt| fadt.s5.is_some()),
+                acpi.fadt.is_some_and(|fadt| fadt.reset.is_some()),
+                acpi.madt.as_ref().map_or(0, |madt| madt.processors.len()),
+                acpi.madt.as_ref().map_or(0, |madt| madt.io_apics.len()),
+                acpi.hpet.is_some(),
+            );
+        }
+        None => {
+            klog!(info, "acpi", "not available");
+        }
+    }
+    if let Some(acpi) = acpi
+        && ACPI.set(acpi).is_err()
+    {
+        panic!("codebug: acpi initialized twice");
+    }
+}
+
+/// 启动时解析的ACPI信息
+pub fn acpi() -> Option<&'static Acpi> {
+    ACPI.get()
+}
+
+/// 关机
+///
+/// 通过ACPI进入S5状态。ACPI不可用或关机失败时，关中断并停机
+pub fn shutdown() -> ! {
+    let _guard = IrqGuard::cli();
+    if let Some(fadt) = acpi().and_then(|acpi| acpi.fadt.as_ref())
+        && let Some((slp_typ_a, slp_typ_b)) = fadt.s5
+    {
+        fadt.enable_acpi_mode();
+        unsafe {
+            outw(
+                fadt.pm1a_control,
+                (slp_typ_a << PM1_CONTROL_SLP_TYP_SHIFT) | PM1_CONTROL_SLP_EN,
+            );
+            if fadt.pm1b_control != 0 {
+                outw(
+                    fadt.pm1b_control,
+                    (slp_typ_b << PM1_CONTROL_SLP_TYP_SHIFT) | PM1_CONTROL_SLP_EN,
+                );
+            }
+        }
+        // 等待电源关闭
+        for _ in 0..1_000_000 {
+            spin_loop();
+        }
+    }
+
+    halt()
+}
+
+/// 重启
+///
+/// 优先使用FADT中的复位寄存器，失败时通过键盘控制器复位CPU
+pub fn reboot() -> ! {
+    let _guard = IrqGuard::cli();
+    if let Some((register, value)) = acpi()
+        .and_then(|acpi| acpi.fadt.as_ref())
+        .and_then(|fadt| fadt.reset)
+    {
+        match register.address_space {
+            ADDRESS_SPACE_SYSTEM_IO => unsafe { outb(register.address as u16, value) },
+            // Safety: 复位寄存器地址由固件提供
+            ADDRESS_SPACE_SYSTEM_MEMORY => unsafe {
+                memory::write_memory(register.address as usize, &value)
+            },
+            // PCI配置空间等其他地址空间暂不支持
+            _ => {}
+        }
+        for _ in 0..1_000_000 {
+            spin_loop();
+        }
+    }
+
+    reset_by_keyboard_controller();
+    halt()
+}
+
+/// 通过键盘控制器触发CPU复位，成功时不会返回
+pub fn reset_by_keyboard_controller() {
+    const RESET_CMD: u8 = 0xFE;
+    const PORT: u16 = 0x64;
+    for _ in 0..5 {
+        unsafe {
+            outb(PORT, RESET_CMD);
+        }
+    }
+}
+
+fn halt() -> ! {
+    loop {
+        unsafe {
+            asm!("cli", "hlt", options(nomem, nostack));
+        }
+    }
+}
+
+impl Fadt {
+    /// 部分固件启动时处于传统模式，需要先通过SMI命令切换到ACPI模式，PM1控制寄存器才会生效
+    fn enable_acpi_mode(&self) {
+        if self.smi_command == 0 || self.acpi_enable == 0 {
+            return;
+        }
+        unsafe {
+            if inw(self.pm1a_control) & PM1_CONTROL_SCI_EN != 0 {
+                return;
+            }
+            outb(self.smi_command as u16, self.acpi_enable);
+            for _ in 0..1_000_000 {
+                if inw(self.pm1a_control) & PM1_CONTROL_SCI_EN != 0 {
+                    break;
+                }
+                spin_loop();
+            }
+        }
+    }
+}
+
+fn parse(rsdp_address: usize) -> Option<Acpi> {
+    // RSDP：签名(8) 校验和(1) OEM ID(6) 版本(1) RSDT地址(4) 长度(4) XSDT地址(8) 扩展校验和(1) 保留(3)
+    let mut rsdp = [0u8; 36];
+    unsafe {
+        memory::read_memory_bytes(rsdp_address, &mut rsdp[..20]);
+    }
+    if &rsdp[..8] != b"RSD PTR " || !checksum_ok(&rsdp[..20]) {
+        return None;
+    }
+    let revision = rsdp[15];
+    let entries = if revision >= 2 {
+        unsafe {
+            memory::read_memory_bytes(rsdp_address + 20, &mut rsdp[20..]);
+        }
+        if !checksum_ok(&rsdp) {
+            return None;
+        }
+        let xsdt = read_table(read_u64(&rsdp, 24) as usize, b"XSDT")?;
+        xsdt[SDT_HEADER_LEN..]
+            .chunks_exact(8)
+            .map(|entry| read_u64(entry, 0) as usize)
+            .collect::<Vec<_>>()
+    } else {
+        let rsdt = read_table(read_u32(&rsdp, 16) as usize, b"RSDT")?;
+        rsdt[SDT_HEADER_LEN..]
+            .chunks_exact(4)
+            .map(|entry| read_u32(entry, 0) as usize)
+            .collect::<Vec<_>>()
+    };
+
+    let mut acpi = Acpi {
+        revision,
+        fadt: None,
+        madt: None,
+        hpet: None,
+    };
+    for address in entries {
+        let mut signature = [0u8; 4];
+        unsafe {
+            memory::read_memory_bytes(address, &mut signature);
+        }
+        match &signature {
+            b"FACP" => {
+                acpi.fadt = read_table(address, b"FACP").and_then(|table| parse_fadt(&table))
+            }
+            b"APIC" => {
+                acpi.madt = read_table(address, b"APIC").and_then(|table| parse_madt(&table))
+            }
+            b"HPET" => {
+                acpi.hpet = read_table(address, b"HPET").and_then(|table| parse_hpet(&table))
+            }
+            _ => {}
+        }
+    }
+    Some(acpi)
+}
+
+/// 读取完整的ACPI表，签名或校验和错误时返回None
+fn read_table(address: usize, signature: &[u8; 4]) -> Option<Vec<u8>> {
+    if address == 0 {
+        return None;
+    }
+    let mut header = [0u8; SDT_HEADER_LEN];
+    unsafe {
+        memory::read_memory_bytes(address, &mut header);
+    }
+    let len = read_u32(&header, 4) as usize;
+    if &header[..4] != signature || !(SDT_HEADER_LEN..=MAX_TABLE_LEN).contains(&len) {
+        return None;
+    }
+
+    let mut table = Vec::new();
+    table.try_reserve_exact(len).ok()?;
+    table.resize(len, 0);
+    unsafe {
+        memory::read_memory_bytes(address, &mut table);
+    }
+    checksum_ok(&table).then_some(table)
+}
+
+fn parse_fadt(fadt: &[u8]) -> Option<Fadt> {
+    // ACPI 1.0的FADT长度为116字节
+    if fadt.len() < 116 {
+        return None;
+    }
+    // ACPI 2.0起，X_DSDT优先于DSDT
+    let dsdt = match fadt.get(140..148) {
+        Some(x_dsdt) if read_u64(x_dsdt, 0) != 0 => read_u64(x_dsdt, 0) as usize,
+        _ => read_u32(fadt, 40) as usize,
+    };
+    let flags = read_u32(fadt, 112);
+    let reset = (flags & FADT_FLAG_RESET_REG_SUP != 0 && fadt.len() >= 129).then(|| {
+        (
+            GenericAddress {
+                address_space: fadt[116],
+                address: read_u64(fadt, 120),
+            },
+            fadt[128],
+        )
+    });
+
+    Some(Fadt {
+        smi_command: read_u32(fadt, 48),
+        acpi_enable: fadt[52],
+        pm1a_control: read_u32(fadt, 64) as u16,
+        pm1b_control: read_u32(fadt, 68) as u16,
+        s5: read_table(dsdt, b"DSDT").and_then(|dsdt| find_s5(&dsdt)),
+        reset,
+    })
+}
+
+/// 解析HPET表，返回寄存器的物理地址，寄存器不位于内存地址空间时返回None
+fn parse_hpet(hpet: &[u8]) -> Option<u64> {
+    // 表头(36) 硬件ID(4) 基地址(通用地址结构，12) HPET编号(1) 最小周期(2) 页保护(1)
+    if hpet.len() < 56 || hpet[40] != ADDRESS_SPACE_SYSTEM_MEMORY {
+        return None;
+    }
+    Some(read_u64(hpet, 44)).filter(|&address| address != 0)
+}
+
+/// 在DSDT的AML中查找\_S5对象，返回SLP_TYPa与SLP_TYPb
+///
+/// 不实现完整的AML解释器，仅识别 `Name(_S5, Package() {a, b, ...})` 的常见编码
+fn find_s5(dsdt: &[u8]) -> Option<(u16, u16)> {
+    const NAME_OP: u8 = 0x08;
+    const PACKAGE_OP: u8 = 0x12;
+
+    let aml = &dsdt[SDT_HEADER_LEN..];
+    let position = aml.windows(4).enumerate().position(|(index, window)| {
+        window == b"_S5_"
+            && index >= 1
+            && (aml[index - 1] == NAME_OP
+                || (index >= 2 && aml[index - 2] == NAME_OP && aml[index - 1] == b'\\'))
+            && aml.get(index + 4) == Some(&PACKAGE_OP)
+    })?;
+
+    // 跳过名称与PackageOp
+    let mut rest = &aml[position + 5..];
+    // PkgLength：首字节的高2位表示后续字节数
+    let pkg_length_len = 1 + (*rest.first()? >> 6) as usize;
+    // 跳过PkgLength与NumElements
+    rest = rest.get(pkg_length_len + 1..)?;
+    let (slp_typ_a, len) = parse_aml_integer(rest)?;
+    let (slp_typ_b, _) = parse_aml_integer(rest.get(len..)?)?;
+    Some((slp_typ_a as u16, slp_typ_b as u16))
+}
+
+/// 解析AML整数常量，返回值及其编码长度
+fn parse_aml_integer(aml: &[u8]) -> Option<(u64, usize)> {
+    const ZERO_OP: u8 = 0x00;
+    const ONE_OP: u8 = 0x01;
+    const BYTE_PREFIX: u8 = 0x0A;
+    const WORD_PREFIX: u8 = 0x0B;
+    const DWORD_PREFIX: u8 = 0x0C;
+
+    match *aml.first()? {
+        ZERO_OP => Some((0, 1)),
+        ONE_OP => Some((1, 1)),
+        BYTE_PREFIX => Some((*aml.get(1)? as u64, 2)),
+        WORD_PREFIX => Some((
+            u16::from_le_bytes(aml.get(1..3)?.try_into().ok()?) as u64,
+            3,
+        )),
+        DWORD_PREFIX => Some((
+            u32::from_le_bytes(aml.get(1..5)?.try_into().ok()?) as u64,
+            5,
+        )),
+        _ => None,
+    }
+}
+
+/// 解析MADT，表的长度不足以包含Local APIC地址与标志时返回None
+fn parse_madt(madt: &[u8]) -> Option<Madt> {
+    if madt.len() < SDT_HEADER_LEN + 8 {
+        return None;
+    }
+    let mut result = Madt {
+        local_apic_address: read_u32(madt, SDT_HEADER_LEN),
+        ..Default::default()
+    };
+
+    // 表头之后为Local APIC地址(4)与标志(4)，随后为变长条目：类型(1) 长度(1) 内容
+    let mut entries = &madt[SDT_HEADER_LEN + 8..];
+    while let [entry_type, len, ..] = *entries {
+        let len = len as usize;
+        if len < 2 || len > entries.len() {
+            break;
+        }
+        let entry = &entries[..len];
+        match entry_type {
+            MADT_LOCAL_APIC if len >= 8 => {
+                if read_u32(entry, 4) & MADT_LOCAL_APIC_ENABLED != 0 {
+                    result.processors.push(entry[3]);
+                }
+            }
+            MADT_IO_APIC if len >= 12 => result.io_apics.push(IoApic {
+                id: entry[2],
+                address: read_u32(entry, 4),
+                interrupt_base: read_u32(entry, 8),
+            }),
+            MADT_INTERRUPT_OVERRIDE if len >= 10 => {
+                result.interrupt_overrides.push(InterruptOverride {
+                    source: entry[3],
+                    global_interrupt: read_u32(entry, 4),
+                    flags: u16::from_le_bytes([entry[8], entry[9]]),
+                })
+            }
+            _ => {}
+        }
+        entries = &entries[len..];
+    }
+
+    Some(result)
+}
+
+fn checksum_ok(bytes: &[u8]) -> bool {
+    bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) == 0
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap())
+}
+
+unsafe fn outb(port: u16, value: u8) {
+    unsafe {
+        asm!(
+            "out dx, al",
+            in("dx") port,
+            in("al") value,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+}
+
+unsafe fn outw(port: u16, value: u16) {
+    unsafe {
+        asm!(
+            "out dx, ax",
+            in("dx") port,
+            in("ax") value,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+}
+
+unsafe fn inw(port: u16) -> u16 {
+    let value: u16;
+    unsafe {
+        asm!(
+            "in ax, dx",
+            in("dx") port,
+            out("ax") value,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    value
+}
@@ -0,0 +1,95 @@
+use crate::{
+    display,
+    multitask::{
+        self,
+        workqueue::{self, Priority},
+    },
+    sync::{int::IrqGuard, spin::SpinLock},
+};
+
+/// 各控制台的前台进程组
+static FOREGROUND: SpinLock<[Foreground; display::console::COUNT]> = SpinLock::new(
+    [const {
+        Foreground {
+            session: 0,
+            process_group: 0,
+        }
+    }; display::console::COUNT],
+);
+
+struct Foreground {
+    // 控制控制台的会话ID，0表示尚未被任何会话控制
+    session: u64,
+    // 前台进程组ID，0表示没有前台进程组
+    process_group: u64,
+}
+
+/// 设置前台进程组失败原因
+#[derive(Debug)]
+pub enum ForegroundError {
+    /// 控制台已被其他会话控制
+    NotControllingSession,
+    /// 调用者不是会话首进程
+    NotSessionLeader,
+    /// 会话中不存在指定的进程组
+    GroupNotFound,
+}
+
+/// 获取控制台的前台进程组ID，没有前台进程组时返回0
+pub fn foreground_group(console: usize) -> u64 {
+    let _guard = IrqGuard::cli();
+    FOREGROUND.lock()[console].process_group
+}
+
+/// 设置控制台的前台进程组，process_group为0时取消前台进程组
+///
+/// 只有会话首进程可以设置，caller为调用者的进程ID。首个设置前台进程组的会话成为控制台的控制会话，
+/// 之后只有该会话可以设置，且只能设置为该会话中的进程组，直至会话首进程退出
+pub fn set_foreground_group(
+    console: usize,
+    caller: u64,
+    session: u64,
+    process_group: u64,
+) -> Result<(), ForegroundError> {
+    if caller != session {
+        return Err(ForegroundError::NotSessionLeader);
+    }
+    if process_group != 0 && !multitask::process::group_exists(process_group, session) {
+        return Err(ForegroundError::GroupNotFound);
+    }
+    let _guard = IrqGuard::cli();
+    let mut foreground = FOREGROUND.lock();
+    let foreground = &mut foreground[console];
+    if foreground.session != 0 && foreground.session != session {
+        return Err(ForegroundError::NotControllingSession);
+    }
+    foreground.session = session;
+    foreground.process_group = process_group;
+    Ok(())
+}
+
+/// 会话首进程退出时调用，释放会话控制的控制台，之后其他会话可以成为控制会话
+pub fn release_session(session: u64) {
+    let _guard = IrqGuard::cli();
+    for foreground in FOREGROUND.lock().iter_mut() {
+        if foreground.session == session {
+            foreground.session = 0;
+            foreground.process_group = 0;
+        }
+    }
+}
+
+/// 键盘中断（Ctrl+C）时由键盘中断处理调用，强制停止正在显示的控制台的前台进程组
+///
+/// 存在前台进程组时返回true，此时按键被消耗；否则返回false，按键作为普通字符输入。
+/// 中断上下文中不能停止进程，停止操作交由工作队列执行，工作队列已满时同样返回false
+pub fn interrupt() -> bool {
+    let process_group = foreground_group(display::console::active());
+    if process_group == 0 {
+        return false;
+    }
+    workqueue::enqueue(Priority::High, move || {
+        multitask::process::interrupt_group(process_group)
+    })
+    .is_ok()
+}
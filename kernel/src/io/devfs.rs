@@ -0,0 +1,629 @@
+//! 设备文件系统（devfs）
+//!
+//! 挂载于 [DEVFS_PATH]，将设备以文件的形式展示，打开设备文件得到绑定到对应驱动的文件句柄，
+//! 用户程序通过文件相关的系统调用访问设备：
+//!
+//! - `console`、`tty<n>`：控制台，`console`即VT1，`tty<n>`为第n个控制台。读取键盘输入，写入时输出到控制台
+//! - `null`：读取时立即到达末尾，写入的数据被丢弃
+//! - `zero`：读取到无限的0，写入的数据被丢弃
+//! - `random`：读取到随机数，熵池尚无足够的熵时阻塞，见 [random::is_seeded]。写入的数据被混入熵池
+//! - `speaker`：PC扬声器，每 [speaker::RECORD_SIZE] 字节为一次发声，依次为小端序的频率（Hz）与时长（毫秒），
+//!   见 [speaker::beep]。播放队列已满时写入等待至有空位。读取时立即到达末尾
+//! - `disk<n>`、`disk<n>p<m>`：块设备表中的磁盘与分区，见 [disk::DiskDevice]，可按字节偏移读写
+//!
+//! 块设备、控制台与扬声器只有超级用户可以打开，`null`、`zero`与`random`所有用户均可读写。
+//! 打开控制台还需具有raw-console能力，且为调用者自身的控制台或调用者位于其前台进程组，
+//! 普通程序应通过标准输入输出访问控制台。权限通过文件系统根目录的附属文件提供，
+//! 见 [PermissionTable::SIDECAR]，不支持修改
+//!
+//! 用户程序打开的字符设备（块设备以外的设备）不经过文件句柄，而是得到字符设备句柄，见 [CharDevice]
+
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+use async_locks::once::OnceLock;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use filesystem::{
+    BoxFuture,
+    device::{BlockDevice, BlockDeviceError},
+    fs::{
+        FileHandle, FileMetadata, FileSystem, FileSystemError,
+        permission::{FilePermission, PermissionTable},
+    },
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    display,
+    io::{disk, keyboard, speaker, vfs},
+    random,
+    user::handle::CharDevice,
+};
+
+/// 设备文件系统的挂载路径
+const DEVFS_PATH: &str = "/dev";
+/// 块设备文件的权限
+const BLOCK_PERMISSION: FilePermission = FilePermission {
+    uid: 0,
+    gid: 0,
+    mode: 0o600,
+};
+/// 控制台与扬声器设备文件的权限，其他用户不能读取键盘输入或向其他控制台输出
+const CONSOLE_PERMISSION: FilePermission = FilePermission {
+    uid: 0,
+    gid: 0,
+    mode: 0o600,
+};
+/// 其他设备文件的权限
+const CHAR_PERMISSION: FilePermission = FilePermission {
+    uid: 0,
+    gid: 0,
+    mode: 0o666,
+};
+/// 每次从熵池读取的字节数，读取熵池时中断被关闭，大量读取需分段进行
+const RANDOM_CHUNK_SIZE: usize = 256;
+
+/// 已挂载的设备文件系统，用于判断路径是否位于其中
+static DEVFS: OnceLock<Arc<DevFileSystem>> = OnceLock::new();
+
+pub struct DevFileSystem {
+    unmounted: AtomicBool,
+}
+
+/// 文件系统中的文件或目录
+enum Node {
+    Root,
+    /// 权限表附属文件
+    Permissions,
+    Device(Device),
+}
+
+/// 设备文件绑定的设备
+enum Device {
+    Char(Character),
+    Block(Arc<dyn BlockDevice>),
+}
+
+/// 字符设备
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Character {
+    /// 控制台，值为控制台序号
+    Console(usize),
+    Null,
+    Zero,
+    Random,
+    Speaker,
+}
+
+struct DevFileHandle {
+    target: Target,
+    pointer: u64,
+    closed: bool,
+}
+
+/// 文件句柄绑定的对象
+enum Target {
+    Device(Device),
+    /// 权限表附属文件，内容在打开时生成
+    Permissions(Vec<u8>),
+}
+
+/// 在 [DEVFS_PATH] 挂载设备文件系统
+pub fn mount() {
+    let path = PathBuf::from_str(DEVFS_PATH).expect("codebug: invalid devfs path");
+    let fs = Arc::new(DevFileSystem {
+        unmounted: AtomicBool::new(false),
+    });
+    if DEVFS.set(fs.clone()).is_err() {
+        panic!("codebug: devfs mounted twice");
+    }
+    vfs::mount(path, fs);
+}
+
+/// 路径为设备文件系统中的字符设备时，返回该设备，用于打开字符设备句柄
+///
+/// fs与path为 [vfs::resolve] 的结果
+pub fn open_char_device(fs: &Arc<dyn FileSystem>, path: Path<'_>) -> Option<Arc<dyn CharDevice>> {
+    let devfs = DEVFS.get()?;
+    if !core::ptr::addr_eq(Arc::as_ptr(fs), Arc::as_ptr(devfs)) {
+        return None;
+    }
+    match devfs.lookup(path) {
+        Ok(Node::Device(Device::Char(character))) => Some(Arc::new(character)),
+        _ => None,
+    }
+}
+
+/// 块设备表变化后调用，使新的块设备文件使用正确的权限
+pub async fn devices_changed() {
+    let path = PathBuf::from_str(DEVFS_PATH).expect("codebug: invalid devfs path");
+    vfs::reload_permissions(&path).await;
+}
+
+/// 全部设备文件的名称，块设备在后
+fn device_names() -> Vec<String> {
+    let consoles = (1..=display::console::COUNT).map(|index| format!("tty{index}"));
+    ["console", "null", "zero", "random", "speaker"]
+        .into_iter()
+        .map(String::from)
+        .chain(consoles)
+        .chain(disk::devices().into_iter().map(|device| device.name))
+        .collect()
+}
+
+/// 生成权限表附属文件的内容
+fn permissions() -> Vec<u8> {
+    let mut table = PermissionTable::new();
+    for name in device_names() {
+        let path = PathBuf::from_str(&format!("/{name}"))
+            .expect("codebug: device name should be a valid path");
+        let permission = match DevFileSystem::device(&name) {
+            Some(Device::Block(_)) => BLOCK_PERMISSION,
+            Some(Device::Char(Character::Console(_) | Character::Speaker)) => CONSOLE_PERMISSION,
+            _ => CHAR_PERMISSION,
+        };
+        table.set(path.as_path(), permission);
+    }
+    table.serialize()
+}
+
+impl DevFileSystem {
+    fn check_mounted(&self) -> Result<(), FileSystemError> {
+        if self.unmounted.load(Ordering::Acquire) {
+            return Err(FileSystemError::Unmounted);
+        }
+        Ok(())
+    }
+
+    fn lookup(&self, path: Path<'_>) -> Result<Node, FileSystemError> {
+        self.check_mounted()?;
+        let mut segments = path.iter();
+        let Some(name) = segments.next() else {
+            return Ok(Node::Root);
+        };
+        if segments.next().is_some() {
+            // 根目录中只有设备文件
+            return match Self::device(name) {
+                Some(_) => Err(FileSystemError::FileTypeMismatch),
+                None => Err(FileSystemError::FileNotFound),
+            };
+        }
+        if path.to_string() == PermissionTable::SIDECAR {
+            return Ok(Node::Permissions);
+        }
+        Self::device(name)
+            .map(Node::Device)
+            .ok_or(FileSystemError::FileNotFound)
+    }
+
+    fn device(name: &str) -> Option<Device> {
+        let character = match name {
+            "console" => Character::Console(0),
+            "null" => Character::Null,
+            "zero" => Character::Zero,
+            "random" => Character::Random,
+            "speaker" => Character::Speaker,
+            _ => match name.strip_prefix("tty") {
+                Some(index) => {
+                    let index: usize = index.parse().ok()?;
+                    if !(1..=display::console::COUNT).contains(&index) {
+                        return None;
+                    }
+                    Character::Console(index - 1)
+                }
+                None => return Some(Device::Block(disk::find(name)?.device)),
+            },
+        };
+        Some(Device::Char(character))
+    }
+}
+
+impl Device {
+    /// 设备文件的大小，只有块设备有大小
+    fn size(&self) -> u64 {
+        match self {
+            Device::Block(device) => device.block_size() * device.block_count(),
+            _ => 0,
+        }
+    }
+}
+
+impl FileSystem for DevFileSystem {
+    fn total_space(&self) -> BoxFuture<'_, Result<u64, FileSystemError>> {
+        Box::pin(async {
+            self.check_mounted()?;
+            Ok(0)
+        })
+    }
+
+    fn free_space(&self) -> BoxFuture<'_, Result<u64, FileSystemError>> {
+        Box::pin(async {
+            self.check_mounted()?;
+            Ok(0)
+        })
+    }
+
+    fn create_file<'fut>(
+        &'fut self,
+        _path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async { Err(FileSystemError::OperationNotSupport) })
+    }
+
+    fn create_directory<'fut>(
+        &'fut self,
+        _path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async { Err(FileSystemError::OperationNotSupport) })
+    }
+
+    fn open_file<'fut>(
+        &'fut self,
+        path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<Box<dyn FileHandle>, FileSystemError>> {
+        Box::pin(async move {
+            let target = match self.lookup(path)? {
+                Node::Root => return Err(FileSystemError::FileTypeMismatch),
+                Node::Permissions => Target::Permissions(permissions()),
+                Node::Device(device) => Target::Device(device),
+            };
+            Ok(Box::new(DevFileHandle {
+                target,
+                pointer: 0,
+                closed: false,
+            }) as Box<dyn FileHandle>)
+        })
+    }
+
+    fn delete_file<'fut>(
+        &'fut self,
+        _path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async { Err(FileSystemError::OperationNotSupport) })
+    }
+
+    fn delete_directory<'fut>(
+        &'fut self,
+        _path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async { Err(FileSystemError::OperationNotSupport) })
+    }
+
+    fn rename<'fut>(
+        &'fut self,
+        _old_path: Path<'fut>,
+        _new_path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async { Err(FileSystemError::OperationNotSupport) })
+    }
+
+    fn get_metadata<'fut>(
+        &'fut self,
+        path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<FileMetadata, FileSystemError>> {
+        Box::pin(async move {
+            let (size, is_directory) = match self.lookup(path)? {
+                Node::Root => (0, true),
+                Node::Permissions => (permissions().len() as u64, false),
+                Node::Device(device) => (device.size(), false),
+            };
+            Ok(FileMetadata {
+                name: path.last_segment().unwrap_or("").to_string(),
+                size,
+                is_directory,
+                allocated_size: 0,
+            })
+        })
+    }
+
+    fn list_directory<'fut>(
+        &'fut self,
+        path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<Vec<FileMetadata>, FileSystemError>> {
+        Box::pin(async move {
+            let Node::Root = self.lookup(path)? else {
+                return Err(FileSystemError::FileTypeMismatch);
+            };
+            let entries = device_names()
+                .into_iter()
+                .filter_map(|name| {
+                    // 块设备可能在列出后被移除
+                    let size = Self::device(&name)?.size();
+                    Some(FileMetadata {
+                        name,
+                        size,
+                        is_directory: false,
+                        allocated_size: 0,
+                    })
+                })
+                .collect();
+            Ok(entries)
+        })
+    }
+
+    fn unmount(&self) -> BoxFuture<'_, Result<(), FileSystemError>> {
+        Box::pin(async {
+            self.check_mounted()?;
+            self.unmounted.store(true, Ordering::Release);
+            Ok(())
+        })
+    }
+}
+
+impl DevFileHandle {
+    fn check_open(&self) -> Result<(), FileSystemError> {
+        if self.closed {
+            return Err(FileSystemError::FileClosed);
+        }
+        Ok(())
+    }
+
+    /// 从块设备的文件指针处读取，读取到设备末尾为止
+    async fn read_block(
+        device: &dyn BlockDevice,
+        pointer: u64,
+        buf: &mut [u8],
+    ) -> Result<u64, FileSystemError> {
+        let block_size = device.block_size();
+        let end = (pointer + buf.len() as u64).min(device.block_size() * device.block_count());
+        let mut block = vec![0; block_size as usize];
+        let mut position = pointer;
+        while position < end {
+            let offset = (position % block_size) as usize;
+            let len = (block_size as usize - offset).min((end - position) as usize);
+            let dst = &mut buf[(position - pointer) as usize..][..len];
+            if len == block_size as usize {
+                device.read_block(position / block_size, dst).await?;
+            } else {
+                device.read_block(position / block_size, &mut block).await?;
+                dst.copy_from_slice(&block[offset..offset + len]);
+            }
+            position += len as u64;
+        }
+        Ok(end.saturating_sub(pointer))
+    }
+
+    /// 从块设备的文件指针处写入，不完整的块先读取再写入，超出设备末尾时不写入并返回错误
+    async fn write_block(
+        device: &dyn BlockDevice,
+        pointer: u64,
+        buf: &[u8],
+    ) -> Result<(), FileSystemError> {
+        let block_size = device.block_size();
+        let end = pointer + buf.len() as u64;
+        if end > block_size * device.block_count() {
+            return Err(FileSystemError::DiskFull);
+        }
+        let mut block = vec![0; block_size as usize];
+        let mut position = pointer;
+        while position < end {
+            let offset = (position % block_size) as usize;
+            let len = (block_size as usize - offset).min((end - position) as usize);
+            let src = &buf[(position - pointer) as usize..][..len];
+            if len == block_size as usize {
+                device.write_block(position / block_size, src).await?;
+            } else {
+                device.read_block(position / block_size, &mut block).await?;
+                block[offset..offset + len].copy_from_slice(src);
+                device.write_block(position / block_size, &block).await?;
+            }
+            position += len as u64;
+        }
+        Ok(())
+    }
+}
+
+impl FileHandle for DevFileHandle {
+    fn close(&mut self) -> BoxFuture<'_, Result<(), FileSystemError>> {
+        Box::pin(async {
+            self.check_open()?;
+            self.closed = true;
+            Ok(())
+        })
+    }
+
+    fn move_pointer(&mut self, position: u64) -> BoxFuture<'_, Result<(), FileSystemError>> {
+        Box::pin(async move {
+            self.check_open()?;
+            let size = match &self.target {
+                Target::Device(device) => device.size(),
+                Target::Permissions(content) => content.len() as u64,
+            };
+            self.pointer = position.min(size);
+            Ok(())
+        })
+    }
+
+    fn get_pointer(&mut self) -> BoxFuture<'_, Result<u64, FileSystemError>> {
+        Box::pin(async {
+            self.check_open()?;
+            Ok(self.pointer)
+        })
+    }
+
+    fn read<'fut>(
+        &'fut mut self,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<u64, FileSystemError>> {
+        Box::pin(async move {
+            self.check_open()?;
+            let device = match &self.target {
+                Target::Device(device) => device,
+                Target::Permissions(content) => {
+                    let start = self.pointer as usize;
+                    let len = buf.len().min(content.len() - start);
+                    buf[..len].copy_from_slice(&content[start..start + len]);
+                    self.pointer += len as u64;
+                    return Ok(len as u64);
+                }
+            };
+            match device {
+                Device::Char(character) => character.read(buf).await,
+                Device::Block(device) => {
+                    let count = Self::read_block(&**device, self.pointer, buf).await?;
+                    self.pointer += count;
+                    Ok(count)
+                }
+            }
+        })
+    }
+
+    fn write<'fut>(
+        &'fut mut self,
+        buf: &'fut [u8],
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async move {
+            self.check_open()?;
+            let Target::Device(device) = &self.target else {
+                // 权限表附属文件不支持修改
+                return Err(FileSystemError::OperationNotSupport);
+            };
+            match device {
+                Device::Char(character) => character.write(buf).await,
+                Device::Block(device) => {
+                    Self::write_block(&**device, self.pointer, buf).await?;
+                    self.pointer += buf.len() as u64;
+                    Ok(())
+                }
+            }
+        })
+    }
+
+    fn truncate(&mut self) -> BoxFuture<'_, Result<(), FileSystemError>> {
+        Box::pin(async { Err(FileSystemError::OperationNotSupport) })
+    }
+}
+
+impl CharDevice for Character {
+    fn read<'fut>(
+        &'fut self,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<u64, FileSystemError>> {
+        Box::pin(async move {
+            match self {
+                Character::Console(console) => keyboard::read(*console, buf)
+                    .await
+                    .map(|count| count as u64)
+                    .map_err(|_| FileSystemError::IoError(BlockDeviceError::IoError)),
+                Character::Null | Character::Speaker => Ok(0),
+                Character::Zero => {
+                    buf.fill(0);
+                    Ok(buf.len() as u64)
+                }
+                Character::Random => {
+                    random::wait_seeded().await;
+                    for chunk in buf.chunks_mut(RANDOM_CHUNK_SIZE) {
+                        random::fill(chunk);
+                    }
+                    Ok(buf.len() as u64)
+                }
+            }
+        })
+    }
+
+    fn write<'fut>(&'fut self, buf: &'fut [u8]) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async move {
+            match self {
+                Character::Console(console) => display::vga_text::_kprint_bytes(*console, buf),
+                Character::Null | Character::Zero => {}
+                Character::Random => random::add_entropy(buf),
+                Character::Speaker => {
+                    // 先检查全部记录，避免播放到一半才发现无效的记录
+                    let records =
+                        speaker::parse_records(buf).ok_or(FileSystemError::OperationNotSupport)?;
+                    if records.clone().any(|beep| speaker::validate(beep).is_err()) {
+                        return Err(FileSystemError::OperationNotSupport);
+                    }
+                    for beep in records {
+                        speaker::beep_wait(beep)
+                            .await
+                            .map_err(|_| FileSystemError::OperationNotSupport)?;
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn wait_readable(&self) -> BoxFuture<'_, ()> {
+        Box::pin(async {
+            match self {
+                Character::Console(console) => {
+                    if keyboard::wait_readable(*console).await.is_err() {
+                        core::future::pending().await
+                    }
+                }
+                Character::Random => random::wait_seeded().await,
+                Character::Null | Character::Zero | Character::Speaker => {}
+            }
+        })
+    }
+
+    fn console(&self) -> Option<usize> {
+        match self {
+            Character::Console(console) => Some(*console),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_device() {
+        let character = |name| match DevFileSystem::device(name) {
+            Some(Device::Char(character)) => Some(character),
+            _ => None,
+        };
+        assert_eq!(character("console"), Some(Character::Console(0)));
+        assert_eq!(character("tty2"), Some(Character::Console(1)));
+        assert_eq!(character("tty0"), None);
+        assert_eq!(character("tty99"), None);
+        assert_eq!(character("zero"), Some(Character::Zero));
+        assert_eq!(character("speaker"), Some(Character::Speaker));
+        assert!(DevFileSystem::device("unknown").is_none());
+    }
+
+    /// 轮询一次不会阻塞的设备操作
+    fn ready<T>(mut future: BoxFuture<'_, T>) -> T {
+        let mut context = core::task::Context::from_waker(core::task::Waker::noop());
+        match future.as_mut().poll(&mut context) {
+            core::task::Poll::Ready(output) => output,
+            core::task::Poll::Pending => panic!("device operation should not block"),
+        }
+    }
+
+    #[test_case]
+    fn test_character() {
+        let mut buf = [1u8; 64];
+        assert_eq!(ready(Character::Null.read(&mut buf)).unwrap(), 0);
+        assert_eq!(buf, [1u8; 64]);
+        assert_eq!(ready(Character::Zero.read(&mut buf)).unwrap(), 64);
+        assert_eq!(buf, [0u8; 64]);
+        assert!(ready(Character::Null.write(&[1, 2, 3])).is_ok());
+        assert!(ready(Character::Zero.write(&[1, 2, 3])).is_ok());
+    }
+
+    #[test_case]
+    fn test_permissions() {
+        let table = PermissionTable::parse(str::from_utf8(&permissions()).unwrap());
+        let null = PathBuf::from_str("/null").unwrap();
+        assert_eq!(table.get(null.as_path()).mode, CHAR_PERMISSION.mode);
+        for name in ["/console", "/tty2", "/speaker"] {
+            let path = PathBuf::from_str(name).unwrap();
+            assert_eq!(table.get(path.as_path()).mode, CONSOLE_PERMISSION.mode);
+        }
+        for device in disk::devices() {
+            let path = PathBuf::from_str(&format!("/{}", device.name)).unwrap();
+            assert_eq!(table.get(path.as_path()).mode, BLOCK_PERMISSION.mode);
+        }
+    }
+}
@@ -0,0 +1,696 @@
+use core::{
+    hint::spin_loop,
+    pin::Pin,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering, fence},
+    task::{Context, Poll, Waker},
+};
+
+use alloc::{boxed::Box, collections::vec_deque::VecDeque, sync::Arc, vec::Vec};
+use filesystem::{
+    BoxFuture,
+    device::{BlockDevice, BlockDeviceError},
+};
+
+use crate::{
+    cmdline::LogLevel,
+    io::pci,
+    klog,
+    memory::{
+        self,
+        dma::{DMA_32BIT_LIMIT, DMA_NO_LIMIT, DmaBuffer},
+    },
+    multitask::workqueue::{self, Priority},
+    sync::{int::IrqGuard, spin::SpinLock},
+    trap,
+};
+
+/// PCI类型：大容量存储控制器 / SATA / AHCI 1.0
+const PCI_CLASS_STORAGE: u8 = 0x01;
+const PCI_SUBCLASS_SATA: u8 = 0x06;
+const PCI_PROG_IF_AHCI: u8 = 0x01;
+/// ABAR位于BAR5
+const PCI_BAR_ABAR: u8 = 5;
+
+/// HBA通用寄存器
+const HBA_CAP: usize = 0x00;
+const HBA_GHC: usize = 0x04;
+const HBA_IS: usize = 0x08;
+const HBA_PI: usize = 0x0C;
+/// 端口寄存器的起始偏移及每个端口的长度
+const HBA_PORT_BASE: usize = 0x100;
+const HBA_PORT_SIZE: usize = 0x80;
+const HBA_PORT_COUNT: usize = 32;
+/// ABAR需要映射的长度
+const HBA_MMIO_SIZE: usize = HBA_PORT_BASE + HBA_PORT_COUNT * HBA_PORT_SIZE;
+
+/// CAP：支持64位地址
+const CAP_S64A: u32 = 1 << 31;
+/// GHC：启用AHCI模式
+const GHC_AE: u32 = 1 << 31;
+/// GHC：启用中断
+const GHC_IE: u32 = 1 << 1;
+
+/// 端口寄存器
+const PORT_CLB: usize = 0x00;
+const PORT_CLBU: usize = 0x04;
+const PORT_FB: usize = 0x08;
+const PORT_FBU: usize = 0x0C;
+const PORT_IS: usize = 0x10;
+const PORT_IE: usize = 0x14;
+const PORT_CMD: usize = 0x18;
+const PORT_TFD: usize = 0x20;
+const PORT_SIG: usize = 0x24;
+const PORT_SSTS: usize = 0x28;
+const PORT_SERR: usize = 0x30;
+const PORT_CI: usize = 0x38;
+
+/// PxCMD：开始处理命令列表
+const PORT_CMD_ST: u32 = 1 << 0;
+/// PxCMD：允许接收FIS
+const PORT_CMD_FRE: u32 = 1 << 4;
+/// PxCMD：FIS接收正在运行
+const PORT_CMD_FR: u32 = 1 << 14;
+/// PxCMD：命令列表正在运行
+const PORT_CMD_CR: u32 = 1 << 15;
+/// PxIS/PxIE：收到D2H寄存器FIS，即命令完成
+const PORT_IS_DHRS: u32 = 1 << 0;
+/// PxIS/PxIE：任务文件错误
+const PORT_IS_TFES: u32 = 1 << 30;
+/// PxTFD：状态寄存器
+const TFD_ERR: u32 = 1 << 0;
+const TFD_DRQ: u32 = 1 << 3;
+const TFD_BSY: u32 = 1 << 7;
+/// PxSSTS：设备已连接且已建立通信
+const SSTS_DET_PRESENT: u32 = 3;
+/// PxSSTS：接口处于活动状态
+const SSTS_IPM_ACTIVE: u32 = 1;
+/// PxSIG：SATA硬盘
+const SIG_ATA: u32 = 0x0000_0101;
+
+/// 命令页布局：命令列表（32个命令头，1K）、接收FIS区（256字节）、命令表（128字节对齐）
+const COMMAND_LIST_OFFSET: usize = 0x000;
+const RECEIVED_FIS_OFFSET: usize = 0x400;
+const COMMAND_TABLE_OFFSET: usize = 0x500;
+/// 命令表中PRDT的偏移，命令表仅使用一个PRDT项
+const PRDT_OFFSET: usize = 0x80;
+const COMMAND_TABLE_SIZE: usize = PRDT_OFFSET + 16;
+
+/// 命令头：命令FIS长度（双字）
+const HEADER_CFL: u32 = 5;
+/// 命令头：写设备
+const HEADER_WRITE: u32 = 1 << 6;
+/// PRDT项：完成时产生中断
+const PRDT_INTERRUPT: u32 = 1 << 31;
+
+/// FIS类型：主机到设备的寄存器FIS
+const FIS_TYPE_REG_H2D: u8 = 0x27;
+/// 寄存器FIS：命令寄存器更新
+const FIS_COMMAND: u8 = 0x80;
+/// 设备寄存器：LBA模式
+const DEVICE_LBA: u8 = 1 << 6;
+
+const ATA_CMD_READ_DMA_EXT: u8 = 0x25;
+const ATA_CMD_WRITE_DMA_EXT: u8 = 0x35;
+const ATA_CMD_IDENTIFY: u8 = 0xEC;
+
+const SECTOR_SIZE: usize = 512;
+/// 等待设备状态变化的最大轮询次数
+const MAX_SPIN: usize = 1_000_000;
+
+/// ABAR在内核空间中的地址，为0时表示控制器未初始化
+static HBA: AtomicUsize = AtomicUsize::new(0);
+/// 已初始化的端口
+static PORTS: SpinLock<Vec<Port>> = SpinLock::new(Vec::new());
+/// 是否已有待执行的请求完成处理
+static COMPLETION_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// AHCI SATA 硬盘驱动
+///
+/// 每个实例对应HBA的一个端口。通过DMA读写数据，请求完成时由中断通知。
+/// 同一端口同时只执行一个命令，不使用NCQ
+pub struct AhciDriver {
+    /// 端口号
+    port: u8,
+    /// 扇区数量
+    size: u64,
+}
+
+struct Port {
+    /// 端口号
+    index: u8,
+    /// 命令列表、接收FIS区与命令表
+    command_page: DmaBuffer,
+    /// 读写数据的缓冲区
+    data_page: DmaBuffer,
+    /// 进行中的请求
+    inflight: Option<SyncRequest>,
+    /// 等待执行的请求
+    queue: VecDeque<SyncRequest>,
+    /// 中断处理程序读取的中断状态，由完成处理消费
+    interrupt_status: u32,
+}
+
+type SyncRequest = Arc<SpinLock<Request>>;
+
+struct Request {
+    /// LBA逻辑地址
+    lba: u64,
+    /// 操作
+    operate: Operation,
+    /// 写入时为待写入的数据，读取完成后为读取到的数据
+    buffer: Vec<u8>,
+    /// 异步唤醒
+    waker: Waker,
+    /// 任务状态
+    status: u8,
+    /// 是否发生了错误
+    error: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    Identify,
+    Read,
+    Write,
+}
+
+enum RequestFuture {
+    Init {
+        port: u8,
+        lba: u64,
+        operate: Operation,
+        buffer: Vec<u8>,
+    },
+    WaitDevice {
+        request: SyncRequest,
+    },
+    Done,
+}
+
+impl Request {
+    const STATUS_PENDING: u8 = 1;
+    const STATUS_OK: u8 = 2;
+}
+
+/// 探测AHCI控制器，返回所有可用的SATA硬盘
+///
+/// 不存在AHCI控制器、控制器没有可用的中断线或没有连接硬盘时，返回空列表
+pub async fn probe() -> Vec<Arc<AhciDriver>> {
+    let ports = {
+        let _guard = IrqGuard::cli();
+        init_controller()
+    };
+
+    let mut drivers = Vec::new();
+    for port in ports {
+        match AhciDriver::new(port).await {
+            Ok(driver) => drivers.push(driver),
+            Err(error) => {
+                klog!(warn, "ahci", "failed to identify port {port}: {error:?}");
+            }
+        }
+    }
+    drivers
+}
+
+impl AhciDriver {
+    async fn new(port: u8) -> Result<Arc<Self>, BlockDeviceError> {
+        let identify = RequestFuture::new(port, Operation::Identify, 0, Vec::new()).await?;
+        let word =
+            |index: usize| u16::from_le_bytes([identify[index * 2], identify[index * 2 + 1]]);
+
+        // 83 第10位表示支持LBA48，扇区数位于100~103；否则位于60~61
+        let size = if word(83) & (1 << 10) != 0 {
+            (100..104)
+                .rev()
+                .fold(0, |size, index| (size << 16) | word(index) as u64)
+        } else {
+            (word(60) as u64) | ((word(61) as u64) << 16)
+        };
+
+        if klog::enabled(LogLevel::Info, "ahci") {
+            // 27~46 为型号，每个字中的两个字符高位在前
+            let model = (27..47)
+                .flat_map(|index| word(index).to_be_bytes())
+                .map(char::from)
+                .collect::<alloc::string::String>();
+            klog!(
+                info,
+                "ahci",
+                "port {port}: {}, {size} sectors",
+                model.trim()
+            );
+        }
+
+        Ok(Arc::new(AhciDriver { port, size }))
+    }
+}
+
+impl BlockDevice for AhciDriver {
+    fn block_size(&self) -> u64 {
+        SECTOR_SIZE as u64
+    }
+
+    fn block_count(&self) -> u64 {
+        self.size
+    }
+
+    fn write_block<'fut>(
+        &'fut self,
+        block_index: u64,
+        buf: &'fut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            if block_index >= self.size || buf.len() != SECTOR_SIZE {
+                return Err(BlockDeviceError::OutOfBounds);
+            }
+            RequestFuture::new(self.port, Operation::Write, block_index, buf.to_vec()).await?;
+            Ok(())
+        })
+    }
+
+    fn read_block<'fut>(
+        &'fut self,
+        block_index: u64,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            if block_index >= self.size || buf.len() != SECTOR_SIZE {
+                return Err(BlockDeviceError::OutOfBounds);
+            }
+            let data =
+                RequestFuture::new(self.port, Operation::Read, block_index, Vec::new()).await?;
+            buf.copy_from_slice(&data[..SECTOR_SIZE]);
+            Ok(())
+        })
+    }
+}
+
+impl RequestFuture {
+    fn new(port: u8, operate: Operation, lba: u64, buffer: Vec<u8>) -> Self {
+        Self::Init {
+            port,
+            lba,
+            operate,
+            buffer,
+        }
+    }
+}
+
+impl Future for RequestFuture {
+    type Output = Result<Vec<u8>, BlockDeviceError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            match self.as_mut().get_mut() {
+                Self::Init {
+                    port,
+                    lba,
+                    operate,
+                    buffer,
+                } => {
+                    // 构造请求
+                    let request = Request {
+                        lba: *lba,
+                        operate: *operate,
+                        buffer: core::mem::take(buffer),
+                        waker: cx.waker().clone(),
+                        status: Request::STATUS_PENDING,
+                        error: false,
+                    };
+                    let request = Arc::new(SpinLock::new(request));
+
+                    // 排队
+                    let _guard = IrqGuard::cli();
+                    let mut ports = PORTS.lock();
+                    let Some(port) = ports.iter_mut().find(|item| item.index == *port) else {
+                        *self.as_mut().get_mut() = Self::Done;
+                        return Poll::Ready(Err(BlockDeviceError::IoError));
+                    };
+                    if port.inflight.is_some() {
+                        port.queue.push_back(request.clone());
+                    } else {
+                        port.issue(request.clone());
+                    }
+                    *self.as_mut().get_mut() = Self::WaitDevice { request };
+                }
+                Self::WaitDevice { request } => {
+                    let _guard = IrqGuard::cli();
+                    let mut request = request.lock();
+                    if request.status != Request::STATUS_OK {
+                        request.waker.clone_from(cx.waker());
+                        return Poll::Pending;
+                    }
+
+                    let result = if request.error {
+                        Err(BlockDeviceError::IoError)
+                    } else {
+                        Ok(core::mem::take(&mut request.buffer))
+                    };
+
+                    drop(request);
+                    *self.as_mut().get_mut() = Self::Done;
+                    return Poll::Ready(result);
+                }
+                Self::Done => panic!("future polled after complete"),
+            }
+        }
+    }
+}
+
+/// 初始化控制器及已连接硬盘的端口，返回可用端口号
+fn init_controller() -> Vec<u8> {
+    if HBA.load(Ordering::Acquire) != 0 {
+        return PORTS.lock().iter().map(|port| port.index).collect();
+    }
+
+    let Some(device) = pci::find_by_class(PCI_CLASS_STORAGE, PCI_SUBCLASS_SATA, PCI_PROG_IF_AHCI)
+    else {
+        return Vec::new();
+    };
+    // 完成中断通过传统中断线送达，仅支持已在IDT中注册的PCI中断线
+    let irq = match device.interrupt_line() {
+        Some(irq @ 9..=11) => irq,
+        irq => {
+            klog!(warn, "ahci", "unsupported interrupt line {irq:?}");
+            return Vec::new();
+        }
+    };
+    let Some(abar) = device.memory_bar(PCI_BAR_ABAR) else {
+        return Vec::new();
+    };
+    // 没有可用的端口时，映射随hba_region一起解除
+    let Some(hba_region) = memory::map_mmio(abar, HBA_MMIO_SIZE) else {
+        return Vec::new();
+    };
+    let hba = hba_region.address();
+    device.enable_bus_master();
+
+    unsafe {
+        write_register(hba, HBA_GHC, read_register(hba, HBA_GHC) | GHC_AE);
+    }
+    let supports_64bit = unsafe { read_register(hba, HBA_CAP) } & CAP_S64A != 0;
+    let implemented = unsafe { read_register(hba, HBA_PI) };
+
+    let mut ports = PORTS.lock();
+    for index in 0..HBA_PORT_COUNT as u8 {
+        if implemented & (1 << index) == 0 {
+            continue;
+        }
+        let registers = port_registers(hba, index);
+        let (ssts, sig) = unsafe {
+            (
+                read_register(registers, PORT_SSTS),
+                read_register(registers, PORT_SIG),
+            )
+        };
+        if ssts & 0xF != SSTS_DET_PRESENT || (ssts >> 8) & 0xF != SSTS_IPM_ACTIVE {
+            continue;
+        }
+        // 仅支持SATA硬盘，光驱等ATAPI设备不在此列
+        if sig != SIG_ATA {
+            continue;
+        }
+        if let Some(port) = Port::init(hba, index, supports_64bit) {
+            ports.push(port);
+        }
+    }
+    if ports.is_empty() {
+        return Vec::new();
+    }
+
+    HBA.store(hba_region.leak(), Ordering::Release);
+    unsafe {
+        write_register(hba, HBA_IS, u32::MAX);
+        write_register(hba, HBA_GHC, read_register(hba, HBA_GHC) | GHC_IE);
+    }
+    trap::unmask_irq(irq);
+
+    ports.iter().map(|port| port.index).collect()
+}
+
+impl Port {
+    fn init(hba: usize, index: u8, supports_64bit: bool) -> Option<Self> {
+        let max_phys_addr = if supports_64bit {
+            DMA_NO_LIMIT
+        } else {
+            DMA_32BIT_LIMIT
+        };
+        let port = Self {
+            index,
+            command_page: DmaBuffer::alloc(0x1000, max_phys_addr)?,
+            data_page: DmaBuffer::alloc(0x1000, max_phys_addr)?,
+            inflight: None,
+            queue: VecDeque::new(),
+            interrupt_status: 0,
+        };
+        let registers = port_registers(hba, index);
+        let command_list = port.command_page.physical_address() + COMMAND_LIST_OFFSET as u64;
+        let received_fis = port.command_page.physical_address() + RECEIVED_FIS_OFFSET as u64;
+
+        if !port.stop(registers) {
+            return None;
+        }
+        unsafe {
+            write_register(registers, PORT_CLB, command_list as u32);
+            write_register(registers, PORT_CLBU, (command_list >> 32) as u32);
+            write_register(registers, PORT_FB, received_fis as u32);
+            write_register(registers, PORT_FBU, (received_fis >> 32) as u32);
+            write_register(registers, PORT_IE, PORT_IS_DHRS | PORT_IS_TFES);
+        }
+        port.start(registers);
+        Some(port)
+    }
+
+    fn registers(&self) -> usize {
+        port_registers(HBA.load(Ordering::Acquire), self.index)
+    }
+
+    /// 停止端口，返回端口是否已停止
+    fn stop(&self, registers: usize) -> bool {
+        unsafe {
+            let cmd = read_register(registers, PORT_CMD);
+            write_register(registers, PORT_CMD, cmd & !PORT_CMD_ST);
+        }
+        if !wait_register(registers, PORT_CMD, PORT_CMD_CR, 0) {
+            return false;
+        }
+        unsafe {
+            let cmd = read_register(registers, PORT_CMD);
+            write_register(registers, PORT_CMD, cmd & !PORT_CMD_FRE);
+        }
+        wait_register(registers, PORT_CMD, PORT_CMD_FR, 0)
+    }
+
+    /// 清除错误状态并启动端口
+    fn start(&self, registers: usize) {
+        unsafe {
+            write_register(registers, PORT_SERR, u32::MAX);
+            write_register(registers, PORT_IS, u32::MAX);
+            let cmd = read_register(registers, PORT_CMD);
+            write_register(registers, PORT_CMD, cmd | PORT_CMD_FRE);
+            let cmd = read_register(registers, PORT_CMD);
+            write_register(registers, PORT_CMD, cmd | PORT_CMD_ST);
+        }
+    }
+
+    /// 向设备发送请求，调用方需保证端口没有进行中的请求
+    fn issue(&mut self, raw_request: SyncRequest) {
+        let registers = self.registers();
+        let request = raw_request.lock();
+        let command = self.command_page.virtual_address();
+        let command_table = self.command_page.physical_address() + COMMAND_TABLE_OFFSET as u64;
+        let data = self.data_page.physical_address();
+
+        // 命令头：FIS长度、方向、PRDT项数量及命令表地址
+        let mut flags = HEADER_CFL | (1 << 16);
+        if request.operate == Operation::Write {
+            flags |= HEADER_WRITE;
+        }
+        let header = [flags, 0, command_table as u32, (command_table >> 32) as u32];
+
+        // 命令FIS
+        let (ata_command, count) = match request.operate {
+            Operation::Identify => (ATA_CMD_IDENTIFY, 0),
+            Operation::Read => (ATA_CMD_READ_DMA_EXT, 1),
+            Operation::Write => (ATA_CMD_WRITE_DMA_EXT, 1),
+        };
+        let lba = request.lba.to_le_bytes();
+        let device = if request.operate == Operation::Identify {
+            0
+        } else {
+            DEVICE_LBA
+        };
+        let fis = [
+            FIS_TYPE_REG_H2D,
+            FIS_COMMAND,
+            ata_command,
+            0,
+            lba[0],
+            lba[1],
+            lba[2],
+            device,
+            lba[3],
+            lba[4],
+            lba[5],
+            0,
+            count,
+            0,
+            0,
+            0,
+        ];
+
+        // PRDT：数据页，完成时产生中断
+        let prdt = [
+            data as u32,
+            (data >> 32) as u32,
+            0,
+            (SECTOR_SIZE as u32 - 1) | PRDT_INTERRUPT,
+        ];
+
+        // Safety: 命令页与数据页由端口独占，设备仅在命令发出后访问
+        unsafe {
+            ptr::copy_nonoverlapping(
+                header.as_ptr(),
+                (command + COMMAND_LIST_OFFSET) as *mut u32,
+                header.len(),
+            );
+            let table = (command + COMMAND_TABLE_OFFSET) as *mut u8;
+            ptr::write_bytes(table, 0, COMMAND_TABLE_SIZE);
+            ptr::copy_nonoverlapping(fis.as_ptr(), table, fis.len());
+            ptr::copy_nonoverlapping(
+                prdt.as_ptr(),
+                table.add(PRDT_OFFSET) as *mut u32,
+                prdt.len(),
+            );
+        }
+        if request.operate == Operation::Write {
+            // Safety: 命令尚未发出，设备不会访问数据页
+            let data = unsafe { self.data_page.as_mut_slice() };
+            data[..SECTOR_SIZE].copy_from_slice(&request.buffer[..SECTOR_SIZE]);
+        }
+        drop(request);
+
+        // 命令表写入完成后再通知设备
+        fence(Ordering::SeqCst);
+        wait_register(registers, PORT_TFD, TFD_BSY | TFD_DRQ, 0);
+        unsafe {
+            write_register(registers, PORT_CI, 1);
+        }
+        self.inflight = Some(raw_request);
+    }
+
+    /// 完成进行中的请求，并发送下一个请求
+    fn complete(&mut self) {
+        let Some(raw_request) = self.inflight.as_ref() else {
+            return;
+        };
+        let registers = self.registers();
+        let interrupt_status = core::mem::take(&mut self.interrupt_status);
+        let tfd = unsafe { read_register(registers, PORT_TFD) };
+        let error = interrupt_status & PORT_IS_TFES != 0 || tfd & TFD_ERR != 0;
+        let running = unsafe { read_register(registers, PORT_CI) } & 1 != 0;
+        if running && !error {
+            return;
+        }
+
+        // 发生错误后端口停止处理命令，需要重新启动
+        if error {
+            self.stop(registers);
+            self.start(registers);
+        }
+
+        let mut request = raw_request.lock();
+        request.error = error;
+        if !error && request.operate != Operation::Write {
+            fence(Ordering::SeqCst);
+            // Safety: 请求已完成，设备不再访问数据页
+            request.buffer = unsafe { self.data_page.as_slice() }[..SECTOR_SIZE].to_vec();
+        }
+        request.status = Request::STATUS_OK;
+        request.waker.wake_by_ref();
+        drop(request);
+        self.inflight = None;
+
+        if let Some(next) = self.queue.pop_front() {
+            self.issue(next);
+        }
+    }
+}
+
+/// AHCI中断处理程序
+///
+/// 读取并清除各端口的中断状态，请求的完成处理推迟到工作队列中执行。
+/// 中断线可能与其他设备共享，不是AHCI产生的中断时直接返回
+pub fn ahci_irq() {
+    let hba = HBA.load(Ordering::Acquire);
+    if hba == 0 {
+        return;
+    }
+    let pending = unsafe { read_register(hba, HBA_IS) };
+    if pending == 0 {
+        return;
+    }
+
+    // 中断处理程序中中断已关闭，持有端口锁的代码均关闭了中断，不会死锁
+    let mut ports = PORTS.lock();
+    for index in 0..HBA_PORT_COUNT as u8 {
+        if pending & (1 << index) == 0 {
+            continue;
+        }
+        // 先清除端口的中断状态，再清除HBA的中断状态，否则电平触发的中断会再次产生
+        let registers = port_registers(hba, index);
+        let status = unsafe { read_register(registers, PORT_IS) };
+        unsafe {
+            write_register(registers, PORT_IS, status);
+        }
+        if let Some(port) = ports.iter_mut().find(|port| port.index == index) {
+            port.interrupt_status |= status;
+        }
+    }
+    drop(ports);
+    unsafe {
+        write_register(hba, HBA_IS, pending);
+    }
+
+    // 已有待执行的完成处理时，它会读取最新的状态，无需重复入队
+    if COMPLETION_PENDING.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    // 队列已满时直接在中断中处理，避免请求丢失
+    if workqueue::enqueue(Priority::Normal, complete_requests).is_err() {
+        complete_requests();
+    }
+}
+
+fn complete_requests() {
+    let _guard = IrqGuard::cli();
+    COMPLETION_PENDING.store(false, Ordering::Release);
+    for port in PORTS.lock().iter_mut() {
+        port.complete();
+    }
+}
+
+fn port_registers(hba: usize, index: u8) -> usize {
+    hba + HBA_PORT_BASE + index as usize * HBA_PORT_SIZE
+}
+
+/// 轮询寄存器，直到 `value & mask == expected`，超时返回false
+fn wait_register(base: usize, offset: usize, mask: u32, expected: u32) -> bool {
+    for _ in 0..MAX_SPIN {
+        if unsafe { read_register(base, offset) } & mask == expected {
+            return true;
+        }
+        spin_loop();
+    }
+    false
+}
+
+unsafe fn read_register(base: usize, offset: usize) -> u32 {
+    unsafe { ptr::read_volatile((base + offset) as *const u32) }
+}
+
+unsafe fn write_register(base: usize, offset: usize, value: u32) {
+    unsafe { ptr::write_volatile((base + offset) as *mut u32, value) }
+}
@@ -1,40 +1,81 @@
 use core::{
     arch::asm,
     pin::Pin,
-    ptr::copy_nonoverlapping,
+    ptr::{self, copy_nonoverlapping},
+    sync::atomic::{AtomicBool, Ordering},
     task::{Context, Poll, Waker},
 };
 
-use alloc::{boxed::Box, collections::vec_deque::VecDeque, sync::Arc, vec::Vec};
+use alloc::{boxed::Box, collections::vec_deque::VecDeque, string::String, sync::Arc, vec::Vec};
 use filesystem::{
     BoxFuture,
     device::{BlockDevice, BlockDeviceError},
 };
 
-use crate::sync::{int::IrqGuard, spin::SpinLock};
+use crate::{
+    klog,
+    multitask::workqueue::{self, Priority},
+    sync::{int::IrqGuard, spin::SpinLock},
+    trap,
+};
+
+/// IDE通道，每个通道可连接主从两个设备
+///
+/// 两个通道相互独立，各自拥有请求队列与中断
+struct Channel {
+    /// 命令寄存器基址
+    base: u16,
+    /// 设备控制寄存器
+    control: u16,
+    /// 中断号
+    irq: u8,
+    /// 等待队列
+    /// (inflight, queue)
+    queue: SpinLock<(Option<SyncRequest>, VecDeque<SyncRequest>)>,
+    /// 是否已有待执行的请求完成处理
+    completion_pending: AtomicBool,
+}
 
-/// 全局等待队列
-/// (inflight, queue)
-static ATA_QUEUE: SpinLock<(Option<SyncRequest>, VecDeque<SyncRequest>)> =
-    SpinLock::new((None, VecDeque::new()));
+/// 主通道与从通道
+static CHANNELS: [Channel; 2] = [
+    Channel::new(0x1F0, 0x3F6, 14),
+    Channel::new(0x170, 0x376, 15),
+];
 
 /// ATA LBA 异步读盘驱动
 pub struct AtaLbaDriver {
-    /// 硬盘号
+    /// 硬盘号，第1位为通道（0主通道，1从通道），第0位为主从位置（0主盘，1从盘）
     disk: u8,
     /// 大小
     size: u32,
+    /// 型号
+    model: String,
+    /// 序列号
+    serial: String,
+    /// CHS几何参数 (柱面数, 磁头数, 每磁道扇区数)
+    geometry: (u16, u16, u16),
 }
 
-const ATA_DATA: u16 = 0x1F0;
-const ATA_SECTOR_COUNT: u16 = 0x1F2;
-const ATA_SECTOR: u16 = 0x1F3;
-const ATA_CYL_LO: u16 = 0x1F4;
-const ATA_CYL_HI: u16 = 0x1F5;
-const ATA_HEAD: u16 = 0x1F6;
-const ATA_STATUS: u16 = 0x1F7;
-const ATA_COMMAND: u16 = 0x1F7;
-const ATA_INTERRUPT_ENABLE: u16 = 0x3F6;
+/// 命令寄存器相对于基址的偏移
+const ATA_DATA: u16 = 0;
+const ATA_SECTOR_COUNT: u16 = 2;
+const ATA_SECTOR: u16 = 3;
+const ATA_CYL_LO: u16 = 4;
+const ATA_CYL_HI: u16 = 5;
+const ATA_HEAD: u16 = 6;
+const ATA_STATUS: u16 = 7;
+const ATA_COMMAND: u16 = 7;
+
+/// 设备控制寄存器：禁止设备产生中断
+const CONTROL_NIEN: u8 = 0x02;
+
+/// 状态寄存器
+const STATUS_ERR: u8 = 0x01;
+const STATUS_DRQ: u8 = 0x08;
+const STATUS_BSY: u8 = 0x80;
+
+/// 以轮询方式访问设备时，轮询状态寄存器的最大次数
+const POLL_LIMIT: usize = 100_000;
 
 type SyncRequest = Arc<SpinLock<Request>>;
 
@@ -56,17 +97,10 @@ struct Request {
 }
 
 enum Operation {
-    Identify,
     Read,
     Write,
 }
 
-enum IdentifyDeviceFuture {
-    Init { disk: u8 },
-    WaitDevice { request: SyncRequest },
-    Done,
-}
-
 enum WriteBlockFuture<'a> {
     Init {
         driver: &'a AtaLbaDriver,
@@ -92,20 +126,221 @@ enum ReadBlockFuture<'a> {
     Done,
 }
 
+impl Channel {
+    const fn new(base: u16, control: u16, irq: u8) -> Self {
+        Self {
+            base,
+            control,
+            irq,
+            queue: SpinLock::new((None, VecDeque::new())),
+            completion_pending: AtomicBool::new(false),
+        }
+    }
+
+    fn of(disk: u8) -> &'static Self {
+        &CHANNELS[((disk >> 1) & 1) as usize]
+    }
+
+    fn read_status(&self) -> u8 {
+        unsafe { inb(self.base + ATA_STATUS) }
+    }
+
+    /// 将请求加入队列，通道空闲时立即发送
+    fn submit(&self, request: &SyncRequest) {
+        let _guard = IrqGuard::cli();
+        let mut queue = self.queue.lock();
+        if queue.0.is_some() {
+            queue.1.push_back(request.clone());
+        } else {
+            send_io_command(self, request);
+            queue.0 = Some(request.clone());
+        }
+    }
+}
+
+/// 探测两个通道上的全部ATA硬盘
+///
+/// 依次向主通道主盘、主通道从盘、从通道主盘、从通道从盘发送IDENTIFY命令，返回识别成功的硬盘，
+/// 顺序与BIOS的硬盘编号一致。ATAPI设备（如光驱）与不存在的设备会被跳过。
+///
+/// 探测以轮询方式进行，必须在通道上没有进行中的请求时调用
+pub fn probe() -> Vec<Arc<AtaLbaDriver>> {
+    let mut drivers = Vec::new();
+    for disk in 0..4 {
+        let Some(identify) = identify(disk) else {
+            continue;
+        };
+        let driver = AtaLbaDriver::from_identify(disk, &identify);
+        klog!(
+            info,
+            "ata",
+            "disk {disk}: {}, {} sectors",
+            driver.model,
+            driver.size
+        );
+        drivers.push(Arc::new(driver));
+    }
+    for channel in &CHANNELS {
+        if drivers
+            .iter()
+            .any(|driver| ptr::eq(Channel::of(driver.disk), channel))
+        {
+            trap::unmask_irq(channel.irq);
+        }
+    }
+    drivers
+}
+
+/// 以轮询方式识别设备，设备不存在或不是ATA硬盘时返回None
+fn identify(disk: u8) -> Option<[u16; 256]> {
+    let channel = Channel::of(disk);
+    let _guard = IrqGuard::cli();
+    // 持有队列锁，避免与中断驱动的请求交错
+    let queue = channel.queue.lock();
+    if queue.0.is_some() {
+        return None;
+    }
+
+    unsafe {
+        // 浮空总线：通道上没有控制器
+        if channel.read_status() == 0xFF {
+            return None;
+        }
+        // 探测期间禁止中断，避免产生无人处理的中断
+        outb(channel.control, CONTROL_NIEN);
+        outb(channel.base + ATA_HEAD, 0xA0 | ((disk & 1) << 4));
+        // 切换设备后需等待约400ns，读取4次状态寄存器即可
+        for _ in 0..4 {
+            inb(channel.control);
+        }
+        // 协议要求清除扇区寄存器和LBA寄存器
+        outb(channel.base + ATA_SECTOR_COUNT, 0);
+        outb(channel.base + ATA_SECTOR, 0);
+        outb(channel.base + ATA_CYL_LO, 0);
+        outb(channel.base + ATA_CYL_HI, 0);
+        outb(channel.base + ATA_COMMAND, 0xEC);
+
+        // 状态为0表示设备不存在
+        if channel.read_status() == 0 {
+            return None;
+        }
+        poll_status(channel, |status| status & STATUS_BSY == 0)?;
+        // ATAPI与SATA设备会在LBA寄存器中写入签名，它们不支持IDENTIFY命令
+        if inb(channel.base + ATA_CYL_LO) != 0 || inb(channel.base + ATA_CYL_HI) != 0 {
+            return None;
+        }
+        let status = poll_status(channel, |status| status & (STATUS_DRQ | STATUS_ERR) != 0)?;
+        if status & STATUS_ERR != 0 {
+            return None;
+        }
+
+        let mut identify = [0u16; 256];
+        for word in &mut identify {
+            *word = inw(channel.base + ATA_DATA);
+        }
+        Some(identify)
+    }
+}
+
+/// 以轮询方式写入连续的扇区并刷新设备缓存，data的长度必须为512的整数倍，用于panic时写入崩溃转储
+///
+/// 不经过请求队列，写入期间禁止设备产生中断。设备未就绪或出错时返回false
+///
+/// # Safety
+///
+/// 调用时不能有其他代码访问该通道，进行中的请求将不会完成，只能在panic等系统停止运行时调用
+pub unsafe fn write_polled(disk: u8, lba: u64, data: &[u8]) -> bool {
+    assert!(data.len().is_multiple_of(512));
+    let channel = Channel::of(disk);
+    let ready = |channel: &Channel| {
+        poll_status(channel, |status| status & STATUS_BSY == 0)
+            .is_some_and(|status| status & STATUS_ERR == 0)
+    };
+
+    unsafe {
+        outb(channel.control, CONTROL_NIEN);
+        // 等待进行中的请求结束
+        poll_status(channel, |status| status & STATUS_BSY == 0);
+        for (index, sector) in data.chunks_exact(512).enumerate() {
+            send_lba(channel, disk, lba + index as u64);
+            outb(channel.base + ATA_COMMAND, 0x30);
+            let Some(status) = poll_status(channel, |status| {
+                status & STATUS_BSY == 0 && status & (STATUS_DRQ | STATUS_ERR) != 0
+            }) else {
+                return false;
+            };
+            if status & STATUS_ERR != 0 {
+                return false;
+            }
+            for word in sector.chunks_exact(2) {
+                outw(
+                    channel.base + ATA_DATA,
+                    u16::from_le_bytes([word[0], word[1]]),
+                );
+            }
+            if !ready(channel) {
+                return false;
+            }
+        }
+        // FLUSH CACHE，确保复位前数据已写入磁盘
+        outb(channel.base + ATA_HEAD, 0xE0 | ((disk & 1) << 4));
+        outb(channel.base + ATA_COMMAND, 0xE7);
+        ready(channel)
+    }
+}
+
+/// 轮询状态寄存器，直到满足条件，超时返回None
+fn poll_status(channel: &Channel, f: impl Fn(u8) -> bool) -> Option<u8> {
+    (0..POLL_LIMIT)
+        .map(|_| channel.read_status())
+        .find(|status| f(*status))
+}
+
 impl AtaLbaDriver {
-    pub async fn new(disk: u8) -> Result<Arc<Self>, BlockDeviceError> {
-        let identity_information = IdentifyDeviceFuture::Init { disk }.await?;
-        // 60 低16位
-        // 61 高16位
-        let block_count =
-            (identity_information[60] as u32) | ((identity_information[61] as u32) << 16);
-
-        let driver = AtaLbaDriver {
-            disk,
-            size: block_count,
+    fn from_identify(disk: u8, identify: &[u16; 256]) -> Self {
+        // 字符串中每个字的两个字符高位在前
+        let string = |range: core::ops::Range<usize>| {
+            identify[range]
+                .iter()
+                .flat_map(|word| word.to_be_bytes())
+                .map(char::from)
+                .collect::<String>()
+                .trim()
+                .into()
         };
 
-        Ok(Arc::new(driver))
+        Self {
+            disk,
+            // 60 低16位
+            // 61 高16位
+            // 驱动仅使用LBA28命令，因此不使用100~103中的LBA48扇区数
+            size: (identify[60] as u32) | ((identify[61] as u32) << 16),
+            // 27~46 为型号，10~19 为序列号
+            model: string(27..47),
+            serial: string(10..20),
+            // 1 柱面数，3 磁头数，6 每磁道扇区数
+            geometry: (identify[1], identify[3], identify[6]),
+        }
+    }
+
+    /// 硬盘号，第1位为通道，第0位为主从位置
+    pub fn disk(&self) -> u8 {
+        self.disk
+    }
+
+    /// 硬盘型号
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// 硬盘序列号
+    pub fn serial(&self) -> &str {
+        &self.serial
+    }
+
+    /// CHS几何参数 (柱面数, 磁头数, 每磁道扇区数)
+    pub fn geometry(&self) -> (u16, u16, u16) {
+        self.geometry
     }
 }
 
@@ -179,14 +414,7 @@ impl Future for WriteBlockFuture<'_> {
                     let request = Arc::new(SpinLock::new(request));
 
                     // 排队
-                    let _guard = IrqGuard::cli();
-                    let mut queue = ATA_QUEUE.lock();
-                    if queue.0.is_some() {
-                        queue.1.push_back(request.clone());
-                    } else {
-                        send_io_command(&request);
-                        queue.0 = Some(request.clone());
-                    }
+                    Channel::of(driver.disk).submit(&request);
                     *self.as_mut().get_mut() = Self::WaitDevice { request }
                 }
                 Self::WaitDevice { request } => {
@@ -238,14 +466,7 @@ impl Future for ReadBlockFuture<'_> {
                     let request = Arc::new(SpinLock::new(request));
 
                     // 排队
-                    let _guard = IrqGuard::cli();
-                    let mut queue = ATA_QUEUE.lock();
-                    if queue.0.is_some() {
-                        queue.1.push_back(request.clone());
-                    } else {
-                        send_io_command(&request);
-                        queue.0 = Some(request.clone());
-                    }
+                    Channel::of(driver.disk).submit(&request);
 
                     let buf = core::mem::take(buf);
                     *self.as_mut().get_mut() = Self::WaitDevice { buf, request };
@@ -281,262 +502,121 @@ impl Future for ReadBlockFuture<'_> {
     }
 }
 
-impl Future for IdentifyDeviceFuture {
-    type Output = Result<Vec<u16>, BlockDeviceError>;
-
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        loop {
-            match self.as_mut().get_mut() {
-                Self::Init { disk } => {
-                    // 构造请求
-                    let request = Request {
-                        disk: *disk,
-                        lba: 0,
-                        waker: cx.waker().clone(),
-                        status: Request::STATUS_PENDING,
-                        operate: Operation::Identify,
-                        buffer: alloc::vec![0u16; 256],
-                        error: false,
-                    };
-                    let request = Arc::new(SpinLock::new(request));
-
-                    // 排队
-                    let _guard = IrqGuard::cli();
-                    let mut queue = ATA_QUEUE.lock();
-                    if queue.0.is_some() {
-                        queue.1.push_back(request.clone());
-                    } else {
-                        send_io_command(&request);
-                        queue.0 = Some(request.clone());
-                    }
-
-                    *self.as_mut().get_mut() = Self::WaitDevice { request };
-                }
-                Self::WaitDevice { request } => {
-                    let _guard = IrqGuard::cli();
-                    let mut request = request.lock();
-                    if request.status != Request::STATUS_OK {
-                        return Poll::Pending;
-                    }
-
-                    let result = if request.error {
-                        Poll::Ready(Err(BlockDeviceError::IoError))
-                    } else {
-                        Poll::Ready(Ok(core::mem::take(&mut request.buffer)))
-                    };
-
-                    drop(request);
-                    *self.as_mut().get_mut() = Self::Done;
-
-                    return result;
-                }
-                Self::Done => panic!("future polled after complete"),
-            }
-        }
-    }
-}
-
-fn send_io_command(request: &SyncRequest) {
-    // 设置中断
-    let mut interrupt_enable: u8;
+fn send_io_command(channel: &Channel, request: &SyncRequest) {
+    // 清除nIEN，允许设备产生中断
     unsafe {
-        asm!(
-            "in al, dx",
-            in("dx") ATA_INTERRUPT_ENABLE,
-            out("al") interrupt_enable,
-            options(nostack, preserves_flags),
-        );
-    }
-    interrupt_enable &= 0xfe;
-    unsafe {
-        asm!(
-            "out dx, al",
-            in("dx") ATA_INTERRUPT_ENABLE,
-            in("al") interrupt_enable,
-            options(nostack, preserves_flags),
-        );
+        outb(channel.control, 0);
     }
 
     let _guard = IrqGuard::cli();
     let request = request.lock();
     match request.operate {
-        Operation::Identify => send_identify_command(&request),
-        Operation::Read => send_read_command(&request),
-        Operation::Write => send_write_command(&request),
+        Operation::Read => send_read_command(channel, &request),
+        Operation::Write => send_write_command(channel, &request),
     }
 }
 
-fn send_lba(disk: u8, lba: u64) {
-    // 设置扇区数
+fn send_lba(channel: &Channel, disk: u8, lba: u64) {
     unsafe {
-        asm!(
-            "out dx, al",
-            in("dx") ATA_SECTOR_COUNT,
-            in("al") 1 as u8,
-            options(nostack, preserves_flags),
-        );
-    }
+        // 设置扇区数
+        outb(channel.base + ATA_SECTOR_COUNT, 1);
 
-    // 写LBA低24bit
-    unsafe {
-        asm!(
-            "out dx, al",
-            in("dx") ATA_SECTOR,
-            in("al") (lba & 0xff) as u8,
-            options(nostack, preserves_flags),
-        );
-
-        asm!(
-            "out dx, al",
-            in("dx") ATA_CYL_LO,
-            in("al") ((lba >> 8) & 0xff) as u8,
-            options(nostack, preserves_flags),
-        );
+        // 写LBA低24bit
+        outb(channel.base + ATA_SECTOR, (lba & 0xff) as u8);
+        outb(channel.base + ATA_CYL_LO, ((lba >> 8) & 0xff) as u8);
+        outb(channel.base + ATA_CYL_HI, ((lba >> 16) & 0xff) as u8);
 
-        asm!(
-            "out dx, al",
-            in("dx") ATA_CYL_HI,
-            in("al") ((lba >> 16) & 0xff) as u8,
-            options(nostack, preserves_flags),
-        );
-    }
-
-    // 写高4bit+硬盘号
-    let head = 0xE0 | ((disk & 1) << 4) | ((lba >> 24) & 0x0F) as u8;
-    unsafe {
-        asm!(
-            "out dx, al",
-            in("dx") ATA_HEAD,
-            in("al") head,
-            options(nostack, preserves_flags),
-        );
-    }
-}
-
-fn send_identify_command(request: &Request) {
-    // 发送位置
-    // 协议要求清除扇区寄存器和LBA寄存器，此处request.lba为0，刚好满足要求
-    assert_eq!(request.lba, 0);
-    send_lba(request.disk, request.lba);
-    // 发送identify请求
-    unsafe {
-        asm!(
-            "out dx, al",
-            in("dx") ATA_COMMAND,
-            in("al") 0xECu8,
-            options(nostack, preserves_flags)
-        );
+        // 写高4bit+主从位置
+        let head = 0xE0 | ((disk & 1) << 4) | ((lba >> 24) & 0x0F) as u8;
+        outb(channel.base + ATA_HEAD, head);
     }
 }
 
-fn send_read_command(request: &Request) {
+fn send_read_command(channel: &Channel, request: &Request) {
     // 发送位置
-    send_lba(request.disk, request.lba);
+    send_lba(channel, request.disk, request.lba);
     // 发送读盘请求
     unsafe {
-        asm!(
-            "out dx, al",
-            in("dx") ATA_COMMAND,
-            in("al") 0x20u8,
-            options(nostack, preserves_flags)
-        );
+        outb(channel.base + ATA_COMMAND, 0x20);
     }
 }
 
-fn send_write_command(request: &Request) {
+fn send_write_command(channel: &Channel, request: &Request) {
     // 发送位置
-    send_lba(request.disk, request.lba);
+    send_lba(channel, request.disk, request.lba);
     // 发送写盘请求
     unsafe {
-        asm!(
-            "out dx, al",
-            in("dx") ATA_COMMAND,
-            in("al") 0x30u8,
-            options(nostack, preserves_flags)
-        );
+        outb(channel.base + ATA_COMMAND, 0x30);
     }
     // 等待DRQ
-    wait_drq();
+    wait_drq(channel);
     // PIO方式写数据
-    for i in 0..256 {
+    for &word in &request.buffer {
         unsafe {
-            asm!(
-                "out dx, ax",
-                in("ax") request.buffer[i],
-                in("dx") ATA_DATA,
-                options(nostack, preserves_flags)
-            )
+            outw(channel.base + ATA_DATA, word);
         }
     }
 }
 
-fn wait_drq() {
+fn wait_drq(channel: &Channel) {
     loop {
-        let status: u8;
-        unsafe {
-            asm!(
-                "in al, dx",
-                out("al") status,
-                in("dx") ATA_STATUS,
-                options(nostack, preserves_flags),
-            )
-        }
+        let status = channel.read_status();
 
         // BSY=1，控制器忙
-        if (status & 0x80) != 0 {
+        if (status & STATUS_BSY) != 0 {
             continue;
         }
 
         // DRQ=1，请求主机写数据
-        if (status & 0x08) != 0 {
+        if (status & STATUS_DRQ) != 0 {
             return;
         }
     }
 }
 
-pub fn ata_irq() {
-    let status: u8;
-    unsafe {
-        asm!(
-            "in al, dx",
-            out("al") status,
-            in("dx") ATA_STATUS,
-            options(nostack, preserves_flags),
-        )
+/// 硬盘中断处理程序，`channel`为0表示主通道，1表示从通道
+///
+/// 读取状态寄存器以应答中断，请求的完成处理推迟到工作队列中执行
+pub fn ata_irq(channel: usize) {
+    CHANNELS[channel].read_status();
+
+    // 已有待执行的完成处理时，它会读取最新的状态，无需重复入队
+    if CHANNELS[channel]
+        .completion_pending
+        .swap(true, Ordering::AcqRel)
+    {
+        return;
+    }
+    // 队列已满时直接在中断中处理，避免请求丢失
+    if workqueue::enqueue(Priority::Normal, move || complete_request(channel)).is_err() {
+        complete_request(channel);
     }
+}
+
+/// 完成通道上进行中的请求，并发送下一个请求
+fn complete_request(channel: usize) {
+    let channel = &CHANNELS[channel];
+    // 关中断期间不会响应新的硬盘中断，下一个请求的中断会在本函数返回后到达
+    let _guard = IrqGuard::cli();
+    channel.completion_pending.store(false, Ordering::Release);
 
+    let status = channel.read_status();
     // BSY=1，控制器忙
-    if (status & 0x80) != 0 {
+    if (status & STATUS_BSY) != 0 {
         return;
     }
     // ERR=1，错误
-    let err_reg = (status & 1) != 0;
+    let err_reg = (status & STATUS_ERR) != 0;
     // DRQ=1，请求主机写数据
-    let drq_reg = (status & 0x08) != 0;
+    let drq_reg = (status & STATUS_DRQ) != 0;
 
-    let _guard = IrqGuard::cli();
-    let mut queue = ATA_QUEUE.lock();
+    let mut queue = channel.queue.lock();
 
     if let Some(raw_request) = queue.0.take() {
         let mut request = raw_request.lock();
 
         request.error = err_reg;
         match request.operate {
-            Operation::Identify => {
-                if !err_reg {
-                    // PIO方式读取数据
-                    for i in 0..256 {
-                        unsafe {
-                            asm!(
-                                "in ax, dx",
-                                out("ax") request.buffer[i],
-                                in("dx") ATA_DATA,
-                                options(nostack, preserves_flags)
-                            );
-                        }
-                    }
-                }
-            }
             Operation::Read => {
                 // 读盘需要DRQ=1
                 if !err_reg && !drq_reg {
@@ -547,15 +627,8 @@ pub fn ata_irq() {
 
                 if !err_reg {
                     // PIO方式读取数据
-                    for i in 0..256 {
-                        unsafe {
-                            asm!(
-                                "in ax, dx",
-                                out("ax") request.buffer[i],
-                                in("dx") ATA_DATA,
-                                options(nostack, preserves_flags)
-                            );
-                        }
+                    for word in &mut request.buffer {
+                        *word = unsafe { inw(channel.base + ATA_DATA) };
                     }
                 }
             }
@@ -565,7 +638,55 @@ pub fn ata_irq() {
         request.waker.wake_by_ref();
     }
     if let Some(next) = queue.1.pop_front() {
-        send_io_command(&next);
+        send_io_command(channel, &next);
         queue.0 = Some(next);
     }
 }
+
+unsafe fn outb(port: u16, value: u8) {
+    unsafe {
+        asm!(
+            "out dx, al",
+            in("dx") port,
+            in("al") value,
+            options(nostack, preserves_flags)
+        );
+    }
+}
+
+unsafe fn outw(port: u16, value: u16) {
+    unsafe {
+        asm!(
+            "out dx, ax",
+            in("dx") port,
+            in("ax") value,
+            options(nostack, preserves_flags)
+        );
+    }
+}
+
+unsafe fn inb(port: u16) -> u8 {
+    let value: u8;
+    unsafe {
+        asm!(
+            "in al, dx",
+            in("dx") port,
+            out("al") value,
+            options(nostack, preserves_flags)
+        );
+    }
+    value
+}
+
+unsafe fn inw(port: u16) -> u16 {
+    let value: u16;
+    unsafe {
+        asm!(
+            "in ax, dx",
+            in("dx") port,
+            out("ax") value,
+            options(nostack, preserves_flags)
+        );
+    }
+    value
+}
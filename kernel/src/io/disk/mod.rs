@@ -1,44 +1,218 @@
-use alloc::{collections::btree_map::BTreeMap, sync::Arc};
+use alloc::{collections::btree_map::BTreeMap, format, string::String, sync::Arc, vec::Vec};
 use filesystem::{
-    device::mbr::{MbrPartitionDevice, PARTITION_TYPE_FAT32},
-    fs::{FileSystem, fat32::Fat32FileSystem},
+    device::{
+        BlockDevice, BlockDeviceError,
+        checksum::{ChecksumDevice, ScrubReport},
+        mbr::{MbrPartitionDevice, PARTITION_TYPE_CRASH, PARTITION_TYPE_FAT32},
+    },
+    path::PathBuf,
 };
 
 use crate::{
-    io::disk::ata_lba::AtaLbaDriver,
+    crash_dump,
+    io::{devfs, vfs},
+    multitask,
     sync::{int::IrqGuard, spin::SpinLock},
 };
 
+pub mod ahci;
 pub mod ata_lba;
 
-pub static FILE_SYSTEMS: SpinLock<BTreeMap<u32, Arc<dyn FileSystem>>> =
+pub struct InitDiskError;
+
+/// 已识别的磁盘，下标即磁盘序号
+static DISKS: SpinLock<Vec<Arc<dyn BlockDevice>>> = SpinLock::new(Vec::new());
+/// 块设备表，包括整块磁盘及其分区，由 [`rescan`] 重建
+static DEVICES: SpinLock<Vec<DiskDevice>> = SpinLock::new(Vec::new());
+/// 后台扫描，按块设备名称索引。扫描进行中时为None，完成后保存结果直至被取出
+static SCRUBS: SpinLock<BTreeMap<String, Option<Result<ScrubReport, BlockDeviceError>>>> =
     SpinLock::new(BTreeMap::new());
 
-pub struct InitDiskError;
+/// 后台扫描的状态，由 [`scrub`] 返回
+pub enum ScrubStatus {
+    /// 扫描进行中，包括本次调用刚开始的扫描
+    Running,
+    /// 扫描已完成，结果已被取出
+    Finished(Result<ScrubReport, BlockDeviceError>),
+}
+
+/// 块设备表中的块设备
+///
+/// 磁盘命名为`disk<n>`，其分区命名为`disk<n>p<m>`，m为分区在分区表中的序号，从1开始
+#[derive(Clone)]
+pub struct DiskDevice {
+    pub name: String,
+    pub device: Arc<dyn BlockDevice>,
+    /// 分区类型，整块磁盘为None
+    pub partition_type: Option<u8>,
+    /// 块设备上存在校验卷时为校验设备，此时 [`DiskDevice::device`] 即为该设备
+    pub checksum: Option<Arc<ChecksumDevice>>,
+}
 
 // 初始化磁盘
+//
+// 识别全部AHCI与ATA硬盘并加入块设备表，AHCI硬盘在前。存在AHCI硬盘时，启动磁盘号对应第几个SATA硬盘；
+// 否则对应第几个ATA硬盘。启动磁盘上的FAT32分区挂载为根文件系统，崩溃转储分区用于panic时写入崩溃转储
 pub async fn init_disk(startup_disk: u8) -> Result<(), InitDiskError> {
-    let disk = AtaLbaDriver::new(startup_disk)
-        .await
-        .map_err(|_| InitDiskError)?;
+    let mut disks: Vec<Arc<dyn BlockDevice>> = Vec::new();
+    for disk in ahci::probe().await {
+        disks.push(disk);
+    }
+    let ahci_count = disks.len();
+    let ata_disks = ata_lba::probe();
+    for disk in &ata_disks {
+        disks.push(disk.clone());
+    }
+    let startup_index = (startup_disk & 0x7F) as usize;
+    let startup_ata_disk = startup_index
+        .checked_sub(ahci_count)
+        .and_then(|index| ata_disks.get(index))
+        .map(|disk| disk.disk());
+    let disk = disks.get(startup_index).cloned().ok_or(InitDiskError)?;
+    {
+        let _guard = IrqGuard::cli();
+        *DISKS.lock() = disks;
+    }
+    rescan().await;
+
     let mbr_disk = MbrPartitionDevice::mount(disk)
         .await
         .map_err(|_| InitDiskError)?;
 
-    for disk in mbr_disk {
+    for (index, disk) in mbr_disk.into_iter().enumerate() {
         let Some(disk) = disk else {
             continue;
         };
+        if disk.get_partition_type() == PARTITION_TYPE_CRASH {
+            crash_dump::set_region(disk, startup_ata_disk);
+            continue;
+        }
         if disk.get_partition_type() != PARTITION_TYPE_FAT32 {
             continue;
         }
-        let fs = Fat32FileSystem::mount(Arc::new(disk))
+        let fs = vfs::mount_fat32(Arc::new(disk))
             .await
             .map_err(|_| InitDiskError)?;
 
-        let _guard = IrqGuard::cli();
-        FILE_SYSTEMS.lock().insert(0, Arc::new(fs));
+        let source = partition_name(startup_index, index);
+        vfs::mount_with_source(PathBuf::default(), Arc::new(fs), source);
     }
 
     Ok(())
 }
+
+/// 重新读取所有磁盘的分区表，重建块设备表
+///
+/// 已挂载的分区不受影响：文件系统持有的是重建前的分区设备。分区表无法读取的磁盘视为没有分区。
+/// 磁盘或分区上存在校验卷时，块设备表中记录的是校验设备，整块磁盘为校验卷时从校验设备中读取分区表
+pub async fn rescan() {
+    let disks = {
+        let _guard = IrqGuard::cli();
+        DISKS.lock().clone()
+    };
+
+    let mut devices = Vec::new();
+    for (disk_index, disk) in disks.into_iter().enumerate() {
+        let (disk, checksum) = open_checksum(disk).await;
+        devices.push(DiskDevice {
+            name: format!("disk{disk_index}"),
+            device: disk.clone(),
+            partition_type: None,
+            checksum,
+        });
+        let Ok(partitions) = MbrPartitionDevice::mount(disk).await else {
+            continue;
+        };
+        for (index, partition) in partitions.into_iter().enumerate() {
+            let Some(partition) = partition else {
+                continue;
+            };
+            let partition_type = partition.get_partition_type();
+            let (device, checksum) = open_checksum(Arc::new(partition)).await;
+            devices.push(DiskDevice {
+                name: partition_name(disk_index, index),
+                device,
+                partition_type: Some(partition_type),
+                checksum,
+            });
+        }
+    }
+
+    {
+        let _guard = IrqGuard::cli();
+        *DEVICES.lock() = devices;
+    }
+    devfs::devices_changed().await;
+}
+
+/// 块设备上存在校验卷时，以校验设备代替原设备，读取时即校验数据
+async fn open_checksum(
+    device: Arc<dyn BlockDevice>,
+) -> (Arc<dyn BlockDevice>, Option<Arc<ChecksumDevice>>) {
+    match ChecksumDevice::open(device.clone()).await {
+        Ok(checksum) => {
+            let checksum = Arc::new(checksum);
+            (checksum.clone(), Some(checksum))
+        }
+        Err(_) => (device, None),
+    }
+}
+
+/// 扫描块设备上的校验卷
+///
+/// 块设备上没有进行中的扫描时，在后台开始扫描并返回 [`ScrubStatus::Running`]，期间设备上的其他读写可以继续进行。
+/// 扫描完成后再次调用时取出结果，下一次调用将开始新的扫描
+pub fn scrub(name: &str, checksum: Arc<ChecksumDevice>) -> ScrubStatus {
+    {
+        let _guard = IrqGuard::cli();
+        let mut scrubs = SCRUBS.lock();
+        match scrubs.get(name) {
+            Some(None) => return ScrubStatus::Running,
+            Some(Some(_)) => {
+                let result = scrubs.remove(name).flatten().unwrap();
+                return ScrubStatus::Finished(result);
+            }
+            None => {
+                scrubs.insert(String::from(name), None);
+            }
+        }
+    }
+
+    let name = String::from(name);
+    multitask::async_rt::spawn(async move {
+        let result = checksum.scrub().await;
+        let _guard = IrqGuard::cli();
+        SCRUBS.lock().insert(name, Some(result));
+    });
+    ScrubStatus::Running
+}
+
+/// 块设备表
+pub fn devices() -> Vec<DiskDevice> {
+    let _guard = IrqGuard::cli();
+    DEVICES.lock().clone()
+}
+
+/// 按名称查找块设备
+pub fn find(name: &str) -> Option<DiskDevice> {
+    let _guard = IrqGuard::cli();
+    DEVICES
+        .lock()
+        .iter()
+        .find(|device| device.name == name)
+        .cloned()
+}
+
+/// 两个块设备是否存在重叠，即相同，或一个是另一个的分区
+pub fn overlaps(a: &str, b: &str) -> bool {
+    let is_partition_of = |partition: &str, disk: &str| {
+        partition
+            .strip_prefix(disk)
+            .is_some_and(|rest| rest.starts_with('p'))
+    };
+    a == b || is_partition_of(a, b) || is_partition_of(b, a)
+}
+
+fn partition_name(disk_index: usize, partition_index: usize) -> String {
+    format!("disk{disk_index}p{}", partition_index + 1)
+}
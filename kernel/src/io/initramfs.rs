@@ -0,0 +1,85 @@
+use core::{ptr, slice};
+
+use alloc::sync::Arc;
+use boot_info::KERNEL_IMAGE_BASE;
+use filesystem::{
+    fs::ramfs::RamFileSystem,
+    initramfs::{Initramfs, InitramfsError},
+    path::PathBuf,
+};
+
+use crate::io::vfs;
+
+/// 解包后的内存文件系统在镜像大小之外预留的容量
+const RAMFS_EXTRA_CAPACITY: u64 = 4 * 1024 * 1024;
+
+/// initramfs在内核镜像中的位置
+///
+/// build-scripts将initramfs附加到内核镜像之后，并通过magic找到此结构，写入initramfs相对于镜像起始位置的偏移及长度。
+/// 附加的initramfs会与内核一起被引导程序加载并映射，因此可以直接访问。
+/// 未附加initramfs时，length为0
+#[repr(C)]
+struct InitramfsLocation {
+    magic: [u8; 16],
+    offset: u64,
+    length: u64,
+}
+
+#[used]
+static INITRAMFS_LOCATION: InitramfsLocation = InitramfsLocation {
+    magic: *b"COS_INITRAMFS_AT",
+    offset: 0,
+    length: 0,
+};
+
+#[derive(Debug)]
+pub enum MountInitramfsError {
+    /// 内核镜像中没有附加initramfs
+    Missing,
+    /// initramfs格式错误或解包失败
+    Invalid(InitramfsError),
+    /// 内存不足，无法创建内存文件系统
+    OutOfMemory,
+}
+
+/// 获取附加在内核镜像之后的initramfs
+pub fn image() -> Option<&'static [u8]> {
+    // 字段会在编译后被build-scripts修改，必须使用volatile读取，避免编译器将其视为常量
+    let (offset, length) = unsafe {
+        (
+            ptr::read_volatile(&raw const INITRAMFS_LOCATION.offset),
+            ptr::read_volatile(&raw const INITRAMFS_LOCATION.length),
+        )
+    };
+    if length == 0 {
+        return None;
+    }
+
+    // Safety: build-scripts保证该区域位于内核镜像中，并已由引导程序映射
+    Some(unsafe {
+        slice::from_raw_parts(
+            (KERNEL_IMAGE_BASE + offset) as usize as *const u8,
+            length as usize,
+        )
+    })
+}
+
+/// 将initramfs解包到内存文件系统中，并作为根文件系统挂载
+///
+/// 用于磁盘文件系统不可用时启动系统，会替换已挂载的根文件系统
+pub async fn mount_root() -> Result<(), MountInitramfsError> {
+    let image = image().ok_or(MountInitramfsError::Missing)?;
+    let initramfs = Initramfs::parse(image).map_err(MountInitramfsError::Invalid)?;
+    let fs = RamFileSystem::new(image.len() as u64 + RAMFS_EXTRA_CAPACITY)
+        .map_err(|_| MountInitramfsError::OutOfMemory)?;
+    initramfs
+        .unpack(&fs)
+        .await
+        .map_err(MountInitramfsError::Invalid)?;
+
+    if let Some(old) = vfs::mount(PathBuf::default(), Arc::new(fs)) {
+        // 原根文件系统已不可用，卸载失败也无需处理
+        _ = old.unmount().await;
+    }
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use alloc::{sync::Arc, vec::Vec};
+use async_locks::{
+    channel::spsc::{self, TryReceiveError},
+    mutex::Mutex,
+    once::OnceLock,
+};
+use cos_sys::input::InputEvent;
+
+use crate::{display, io, sync::spin::SpinLock};
+
+/// 各控制台的输入事件队列，事件发送到正在显示的控制台
+///
+/// 事件附带产生时控制台的前台进程组，只有该进程组中的进程可以取出，0表示没有前台进程组
+static INPUT_QUEUES: OnceLock<[InputQueue; display::console::COUNT]> = OnceLock::new();
+
+/// 每个队列最多缓存的事件数量，队列已满时丢弃新的事件
+const QUEUE_SIZE: usize = 0x100;
+
+struct InputQueue {
+    sender: SpinLock<spsc::Sender<(u64, InputEvent)>>,
+    receiver: Arc<Mutex<spsc::Receiver<(u64, InputEvent)>>>,
+}
+
+impl InputQueue {
+    fn new() -> Self {
+        let (sender, receiver) = spsc::channel(QUEUE_SIZE);
+        Self {
+            sender: SpinLock::new(sender),
+            receiver: Arc::new(Mutex::new(receiver)),
+        }
+    }
+}
+
+pub fn init() {
+    let queues = core::array::from_fn(|_| InputQueue::new());
+    if INPUT_QUEUES.set(queues).is_err() {
+        panic!("codebug: input queues initialized twice");
+    }
+}
+
+fn input_queue(console: usize) -> &'static InputQueue {
+    &INPUT_QUEUES
+        .get()
+        .expect("codebug: input queues not initialized")[console]
+}
+
+/// 发送事件到正在显示的控制台，由键盘与鼠标驱动调用
+pub fn push(event: InputEvent) {
+    let console = display::console::active();
+    let process_group = io::console::foreground_group(console);
+    // ignore buffer full
+    let _ = input_queue(console)
+        .sender
+        .lock()
+        .try_send((process_group, event));
+}
+
+/// 取出指定控制台中发送给process_group的事件，最多取出capacity个
+///
+/// 发送给其他进程组的事件（如产生于前台进程组切换之前）被丢弃。
+/// wait为true时，没有事件则等待至少产生一个事件；否则立即返回，可能返回空列表
+pub async fn take(
+    console: usize,
+    process_group: u64,
+    capacity: usize,
+    wait: bool,
+) -> Vec<InputEvent> {
+    let receiver = input_queue(console).receiver.clone();
+    let mut receiver = if wait {
+        receiver.lock().await
+    } else {
+        // 其他线程正在等待事件时，事件将由该线程取出，不等待其释放队列
+        match receiver.try_lock() {
+            Some(receiver) => receiver,
+            None => return Vec::new(),
+        }
+    };
+    let accepts = |target: u64| target == 0 || target == process_group;
+    let mut events = Vec::new();
+    while wait && capacity > 0 && events.is_empty() {
+        // 发送端由静态变量持有，不会被释放
+        let (target, event) = receiver.recv().await.unwrap();
+        if accepts(target) {
+            events.push(event);
+        }
+    }
+    while events.len() < capacity {
+        match receiver.try_recv() {
+            Ok((target, event)) if accepts(target) => events.push(event),
+            Ok(_) => {}
+            Err(TryReceiveError::BufferEmpty | TryReceiveError::SenderLost) => break,
+        }
+    }
+    events
+}
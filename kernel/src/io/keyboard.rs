@@ -1,11 +1,17 @@
 use core::num::NonZeroU8;
 
 use alloc::sync::Arc;
-use async_locks::{channel::spsc, mutex::Mutex};
+use async_locks::{channel::spsc, condvar::Condvar, mutex::Mutex, once::OnceLock};
+use cos_sys::input::{EVENT_KEY, EXTENDED_KEY, InputEvent};
 
-use crate::sync::spin::SpinLock;
+use crate::{
+    display,
+    io::{console, input},
+    sync::{int::IrqGuard, spin::SpinLock},
+};
 
-static mut KEYBOARD_SPSC: Option<KeyboardSpsc> = None;
+/// 各控制台的输入缓冲区，键盘输入发送到正在显示的控制台
+static KEYBOARD_SPSC: OnceLock<[KeyboardSpsc; display::console::COUNT]> = OnceLock::new();
 
 const CODE_ASCII_MAPPING: [Option<NonZeroU8>; 0x80] = const_generate_code_ascii_mapping();
 const CODE_ASCII_SHIFT_MAPPING: [Option<NonZeroU8>; 0x80] =
@@ -13,12 +19,30 @@ const CODE_ASCII_SHIFT_MAPPING: [Option<NonZeroU8>; 0x80] =
 
 const LEFT_SHIFT: u8 = 0x2A;
 const RIGHT_SHIFT: u8 = 0x36;
+/// 左Ctrl，右Ctrl为带扩展键前缀的同一扫描码
+const CTRL: u8 = 0x1D;
+/// 左Alt，右Alt为带扩展键前缀的同一扫描码
+const ALT: u8 = 0x38;
+/// F1，F2~F10依次递增
+const F1: u8 = 0x3B;
+/// Ctrl+C输入的控制字符
+const CTRL_C: u8 = 0x03;
+/// 扩展键的前缀，下一个扫描码为扩展键
+const EXTENDED_PREFIX: u8 = 0xE0;
 
 static SPEC_KEY_STATUS: SpinLock<SpecKeyStatus> = SpinLock::new(SpecKeyStatus::new());
 
 struct KeyboardSpsc {
     sender: SpinLock<spsc::Sender<u8>>,
-    receiver: Arc<Mutex<spsc::Receiver<u8>>>,
+    receiver: Arc<Mutex<KeyboardReceiver>>,
+    // 缓冲区收到输入时通知，等待可读时无需持有接收端
+    readable: Condvar,
+}
+
+struct KeyboardReceiver {
+    receiver: spsc::Receiver<u8>,
+    // 等待可读时已从缓冲区取出、但尚未被读取的字符
+    peeked: Option<u8>,
 }
 
 impl KeyboardSpsc {
@@ -26,7 +50,11 @@ impl KeyboardSpsc {
         let (sender, receiver) = spsc::channel(buffer);
         Self {
             sender: SpinLock::new(sender),
-            receiver: Arc::new(Mutex::new(receiver)),
+            receiver: Arc::new(Mutex::new(KeyboardReceiver {
+                receiver,
+                peeked: None,
+            })),
+            readable: Condvar::new(),
         }
     }
 }
@@ -34,6 +62,12 @@ impl KeyboardSpsc {
 struct SpecKeyStatus {
     left_shift: bool,
     right_shift: bool,
+    left_ctrl: bool,
+    right_ctrl: bool,
+    left_alt: bool,
+    right_alt: bool,
+    // 上一个扫描码为扩展键前缀
+    extended: bool,
 }
 
 impl SpecKeyStatus {
@@ -41,12 +75,26 @@ impl SpecKeyStatus {
         Self {
             left_shift: false,
             right_shift: false,
+            left_ctrl: false,
+            right_ctrl: false,
+            left_alt: false,
+            right_alt: false,
+            extended: false,
         }
     }
 }
 
-pub unsafe fn init() {
-    unsafe { KEYBOARD_SPSC = Some(KeyboardSpsc::new(0x80)) }
+pub fn init() {
+    let spsc = core::array::from_fn(|_| KeyboardSpsc::new(0x80));
+    if KEYBOARD_SPSC.set(spsc).is_err() {
+        panic!("codebug: keyboard initialized twice");
+    }
+}
+
+fn keyboard_spsc(console: usize) -> &'static KeyboardSpsc {
+    &KEYBOARD_SPSC
+        .get()
+        .expect("codebug: keyboard not initialized")[console]
 }
 
 const fn const_generate_code_ascii_mapping() -> [Option<NonZeroU8>; 0x80] {
@@ -179,10 +227,66 @@ const fn const_generate_code_ascii_shift_mapping() -> [Option<NonZeroU8>; 0x80]
     mapping
 }
 
+/// 扩展键对应的输入，方向键等以ANSI转义序列表示，与终端的输入一致
+fn extended_key_sequence(button: u8) -> Option<&'static [u8]> {
+    match button {
+        0x1C => Some(b"\n"),      // Keypad Enter
+        0x35 => Some(b"/"),       // Keypad /
+        0x47 => Some(b"\x1b[H"),  // Home
+        0x48 => Some(b"\x1b[A"),  // Up
+        0x4B => Some(b"\x1b[D"),  // Left
+        0x4D => Some(b"\x1b[C"),  // Right
+        0x4F => Some(b"\x1b[F"),  // End
+        0x50 => Some(b"\x1b[B"),  // Down
+        0x53 => Some(b"\x1b[3~"), // Delete
+        _ => None,
+    }
+}
+
+fn send_bytes(bytes: &[u8]) {
+    let console = display::console::active();
+    let spsc = keyboard_spsc(console);
+    let mut sender = spsc.sender.lock();
+    for byte in bytes {
+        // ignore buffer full
+        let _ = sender.try_send(*byte);
+    }
+    drop(sender);
+    spsc.readable.notify_waiters();
+}
+
 pub fn handle_keyboard_scan(code: u8) {
+    if code == EXTENDED_PREFIX {
+        SPEC_KEY_STATUS.lock().extended = true;
+        return;
+    }
     let pressed = (code & 0x80) == 0;
     let button = code & 0x7f;
 
+    let extended = core::mem::take(&mut SPEC_KEY_STATUS.lock().extended);
+    // 原始按键事件，字符输入之外的按键释放、功能键等信息由此读取
+    input::push(InputEvent {
+        kind: EVENT_KEY,
+        code: button as u32 | if extended { EXTENDED_KEY } else { 0 },
+        pressed: pressed as u32,
+        ..Default::default()
+    });
+    if extended {
+        if button == CTRL {
+            SPEC_KEY_STATUS.lock().right_ctrl = pressed;
+            return;
+        }
+        if button == ALT {
+            SPEC_KEY_STATUS.lock().right_alt = pressed;
+            return;
+        }
+        // 扩展键中的0x2A与0x36为PrintScreen等按键附带的虚拟Shift，不影响Shift状态
+        if pressed && let Some(sequence) = extended_key_sequence(button) {
+            send_bytes(sequence);
+        }
+        return;
+    }
+
     match (button, pressed) {
         // LShift
         (LEFT_SHIFT, pressed) => {
@@ -192,12 +296,29 @@ pub fn handle_keyboard_scan(code: u8) {
         (RIGHT_SHIFT, pressed) => {
             SPEC_KEY_STATUS.lock().right_shift = pressed;
         }
+        // LCtrl
+        (CTRL, pressed) => {
+            SPEC_KEY_STATUS.lock().left_ctrl = pressed;
+        }
+        // LAlt
+        (ALT, pressed) => {
+            SPEC_KEY_STATUS.lock().left_alt = pressed;
+        }
+        // Alt+F1~F4切换控制台
+        (button, true)
+            if (F1..F1 + display::console::COUNT as u8).contains(&button) && alt_pressed() =>
+        {
+            display::console::switch((button - F1) as usize);
+        }
         // other key pressed
         (button, true) => {
-            // is shift pressed?
-            let shift_pressed = {
+            // is shift or ctrl pressed?
+            let (shift_pressed, ctrl_pressed) = {
                 let key_status = SPEC_KEY_STATUS.lock();
-                key_status.left_shift || key_status.right_shift
+                (
+                    key_status.left_shift || key_status.right_shift,
+                    key_status.left_ctrl || key_status.right_ctrl,
+                )
             };
             let mapping = if shift_pressed {
                 &CODE_ASCII_SHIFT_MAPPING
@@ -206,15 +327,17 @@ pub fn handle_keyboard_scan(code: u8) {
             };
 
             if let Some(ascii) = mapping[button as usize] {
-                unsafe {
-                    // ignore buffer full
-                    #[allow(static_mut_refs)]
-                    let _ = KEYBOARD_SPSC
-                        .as_ref()
-                        .unwrap()
-                        .sender
-                        .lock()
-                        .try_send(ascii.get());
+                let ascii = ascii.get();
+                // Ctrl+字母输入对应的控制字符0x01~0x1A，与终端的输入一致
+                if ctrl_pressed && ascii.is_ascii_alphabetic() {
+                    let control = ascii.to_ascii_lowercase() - b'a' + 1;
+                    // Ctrl+C中断前台进程组
+                    if control == CTRL_C && console::interrupt() {
+                        return;
+                    }
+                    send_bytes(&[control]);
+                } else {
+                    send_bytes(&[ascii]);
                 }
             }
         }
@@ -223,9 +346,73 @@ pub fn handle_keyboard_scan(code: u8) {
     }
 }
 
-pub fn receiver() -> Arc<Mutex<spsc::Receiver<u8>>> {
-    unsafe {
-        #[allow(static_mut_refs)]
-        KEYBOARD_SPSC.as_ref().unwrap().receiver.clone()
+fn alt_pressed() -> bool {
+    let key_status = SPEC_KEY_STATUS.lock();
+    key_status.left_alt || key_status.right_alt
+}
+
+fn receiver(console: usize) -> Arc<Mutex<KeyboardReceiver>> {
+    keyboard_spsc(console).receiver.clone()
+}
+
+/// 从指定控制台读取一个字符，如果缓冲区为空，则等待键盘输入
+pub async fn read_char(console: usize) -> Result<u8, spsc::SenderLost> {
+    let receiver = receiver(console);
+    let mut receiver = receiver.lock().await;
+    if let Some(char) = receiver.peeked.take() {
+        return Ok(char);
+    }
+    receiver.receiver.recv().await
+}
+
+/// 从指定控制台读取输入到buf，缓冲区为空时等待键盘输入，返回读取的字符数
+///
+/// 至少读取一个字符，其后只读取缓冲区中已有的字符，不再等待
+pub async fn read(console: usize, buf: &mut [u8]) -> Result<usize, spsc::SenderLost> {
+    let Some((first, rest)) = buf.split_first_mut() else {
+        return Ok(0);
+    };
+    let receiver = receiver(console);
+    let mut receiver = receiver.lock().await;
+    *first = match receiver.peeked.take() {
+        Some(char) => char,
+        None => receiver.receiver.recv().await?,
+    };
+    let mut count = 1;
+    for byte in rest {
+        let Ok(char) = receiver.receiver.try_recv() else {
+            break;
+        };
+        *byte = char;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// 等待指定控制台的键盘可读，不会消耗输入
+///
+/// 取消安全，取消后已取出的字符会保留，并由下一次[`read_char`]返回
+pub async fn wait_readable(console: usize) -> Result<(), spsc::SenderLost> {
+    let spsc = keyboard_spsc(console);
+    let mut receiver = spsc.receiver.lock().await;
+    loop {
+        let wait = {
+            // 检查缓冲区与开始等待之间不能插入键盘中断，否则会错过通知
+            let _guard = IrqGuard::cli();
+            if receiver.peeked.is_some() {
+                return Ok(());
+            }
+            match receiver.receiver.try_recv() {
+                Ok(char) => {
+                    receiver.peeked = Some(char);
+                    return Ok(());
+                }
+                Err(spsc::TryReceiveError::SenderLost) => return Err(spsc::SenderLost),
+                Err(spsc::TryReceiveError::BufferEmpty) => (),
+            }
+            // 等待期间释放接收端，不阻塞其他读取者
+            spsc.readable.wait(receiver)
+        };
+        receiver = wait.await;
     }
 }
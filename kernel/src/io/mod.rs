@@ -1,2 +1,17 @@
+pub mod acpi;
+pub mod console;
+pub mod devfs;
 pub mod disk;
+pub mod initramfs;
+pub mod input;
 pub mod keyboard;
+pub mod mouse;
+pub mod net;
+pub mod pci;
+pub mod permission;
+pub mod procfs;
+pub mod qemu;
+pub mod rtc;
+pub mod serial;
+pub mod speaker;
+pub mod vfs;
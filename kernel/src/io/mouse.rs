@@ -0,0 +1,233 @@
+use core::arch::asm;
+
+use cos_sys::input::{
+    BUTTON_LEFT, BUTTON_MIDDLE, BUTTON_RIGHT, EVENT_MOUSE_BUTTON, EVENT_MOUSE_MOVE, InputEvent,
+};
+
+use crate::{
+    io::input,
+    klog,
+    sync::{int::IrqGuard, spin::SpinLock},
+    trap,
+};
+
+/// PS/2控制器的数据端口
+const DATA_PORT: u16 = 0x60;
+/// PS/2控制器的状态（读）与命令（写）端口
+const COMMAND_PORT: u16 = 0x64;
+/// 状态位：输出缓冲区有数据
+const STATUS_OUTPUT_FULL: u8 = 1 << 0;
+/// 状态位：输入缓冲区已满，不能写入
+const STATUS_INPUT_FULL: u8 = 1 << 1;
+/// 鼠标中断号
+const IRQ_MOUSE: u8 = 12;
+/// 等待控制器的最大轮询次数
+const WAIT_SPINS: usize = 100_000;
+
+/// 控制器命令：启用第二个PS/2端口（鼠标）
+const CMD_ENABLE_AUX: u8 = 0xA8;
+/// 控制器命令：读取配置字节
+const CMD_READ_CONFIG: u8 = 0x20;
+/// 控制器命令：写入配置字节
+const CMD_WRITE_CONFIG: u8 = 0x60;
+/// 控制器命令：下一个写入数据端口的字节发送给鼠标
+const CMD_WRITE_AUX: u8 = 0xD4;
+/// 配置字节：启用鼠标中断
+const CONFIG_AUX_IRQ: u8 = 1 << 1;
+/// 配置字节：禁用鼠标时钟
+const CONFIG_AUX_CLOCK_DISABLED: u8 = 1 << 5;
+
+/// 鼠标命令：恢复默认设置
+const MOUSE_SET_DEFAULTS: u8 = 0xF6;
+/// 鼠标命令：开始发送数据包
+const MOUSE_ENABLE_REPORTING: u8 = 0xF4;
+/// 鼠标对命令的应答
+const MOUSE_ACK: u8 = 0xFA;
+
+/// 数据包首字节：总是为1，用于同步数据包边界
+const PACKET_ALWAYS_ONE: u8 = 1 << 3;
+/// 数据包首字节：x位移的符号位
+const PACKET_X_SIGN: u8 = 1 << 4;
+/// 数据包首字节：y位移的符号位
+const PACKET_Y_SIGN: u8 = 1 << 5;
+/// 数据包首字节：x或y位移溢出
+const PACKET_OVERFLOW: u8 = 0b11 << 6;
+
+static PACKET: SpinLock<Packet> = SpinLock::new(Packet::new());
+
+/// 正在接收的数据包
+struct Packet {
+    // 已接收的字节
+    bytes: [u8; 3],
+    // 已接收的字节数
+    len: usize,
+    // 上一个数据包的按键状态，用于产生按键事件
+    buttons: u8,
+}
+
+impl Packet {
+    const fn new() -> Self {
+        Self {
+            bytes: [0; 3],
+            len: 0,
+            buttons: 0,
+        }
+    }
+}
+
+/// 初始化PS/2鼠标并打开鼠标中断，没有鼠标时仅记录日志
+pub fn init() {
+    let ready = {
+        let _guard = IrqGuard::cli();
+        unsafe { enable_mouse() }
+    };
+    if ready.is_none() {
+        klog!(info, "mouse", "no PS/2 mouse found");
+        return;
+    }
+    trap::unmask_irq(IRQ_MOUSE);
+}
+
+/// 启用鼠标端口与鼠标中断，并让鼠标开始发送数据包
+///
+/// Safety: 须在关中断时调用，避免键盘中断读取控制器的应答
+unsafe fn enable_mouse() -> Option<()> {
+    unsafe {
+        write_command(CMD_ENABLE_AUX)?;
+        write_command(CMD_READ_CONFIG)?;
+        let config = read_data()?;
+        write_command(CMD_WRITE_CONFIG)?;
+        write_data((config | CONFIG_AUX_IRQ) & !CONFIG_AUX_CLOCK_DISABLED)?;
+        write_mouse(MOUSE_SET_DEFAULTS)?;
+        write_mouse(MOUSE_ENABLE_REPORTING)
+    }
+}
+
+unsafe fn write_mouse(command: u8) -> Option<()> {
+    unsafe {
+        write_command(CMD_WRITE_AUX)?;
+        write_data(command)?;
+        (read_data()? == MOUSE_ACK).then_some(())
+    }
+}
+
+unsafe fn write_command(command: u8) -> Option<()> {
+    unsafe {
+        wait_status(|status| status & STATUS_INPUT_FULL == 0)?;
+        outb(COMMAND_PORT, command);
+    }
+    Some(())
+}
+
+unsafe fn write_data(data: u8) -> Option<()> {
+    unsafe {
+        wait_status(|status| status & STATUS_INPUT_FULL == 0)?;
+        outb(DATA_PORT, data);
+    }
+    Some(())
+}
+
+unsafe fn read_data() -> Option<u8> {
+    unsafe {
+        wait_status(|status| status & STATUS_OUTPUT_FULL != 0)?;
+        Some(inb(DATA_PORT))
+    }
+}
+
+/// 轮询状态端口直至满足条件，超时返回None
+unsafe fn wait_status(condition: impl Fn(u8) -> bool) -> Option<()> {
+    for _ in 0..WAIT_SPINS {
+        if condition(unsafe { inb(COMMAND_PORT) }) {
+            return Some(());
+        }
+        core::hint::spin_loop();
+    }
+    None
+}
+
+/// 处理鼠标中断读取到的字节，收到完整的数据包后产生移动与按键事件
+pub fn handle_mouse_byte(byte: u8) {
+    let mut packet = PACKET.lock();
+    // 首字节的第3位总是为1，不满足时丢弃，以便在丢失字节后重新同步
+    if packet.len == 0 && byte & PACKET_ALWAYS_ONE == 0 {
+        return;
+    }
+    let len = packet.len;
+    packet.bytes[len] = byte;
+    packet.len += 1;
+    if packet.len < packet.bytes.len() {
+        return;
+    }
+    packet.len = 0;
+
+    let [flags, x, y] = packet.bytes;
+    let buttons = flags & 0b111;
+    let changed = buttons ^ core::mem::replace(&mut packet.buttons, buttons);
+    drop(packet);
+
+    if flags & PACKET_OVERFLOW == 0 {
+        // 位移为9位补码，符号位位于首字节
+        let dx = x as i32 - if flags & PACKET_X_SIGN != 0 { 0x100 } else { 0 };
+        let dy = y as i32 - if flags & PACKET_Y_SIGN != 0 { 0x100 } else { 0 };
+        if dx != 0 || dy != 0 {
+            input::push(InputEvent {
+                kind: EVENT_MOUSE_MOVE,
+                // PS/2鼠标的y轴向上为正，转换为屏幕坐标
+                dx,
+                dy: -dy,
+                ..Default::default()
+            });
+        }
+    }
+    for (bit, button) in [(0, BUTTON_LEFT), (1, BUTTON_RIGHT), (2, BUTTON_MIDDLE)] {
+        if changed & (1 << bit) != 0 {
+            input::push(InputEvent {
+                kind: EVENT_MOUSE_BUTTON,
+                code: button,
+                pressed: ((buttons >> bit) & 1) as u32,
+                ..Default::default()
+            });
+        }
+    }
+}
+
+unsafe fn outb(port: u16, value: u8) {
+    unsafe {
+        asm!(
+            "out dx, al",
+            in("dx") port,
+            in("al") value,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+}
+
+unsafe fn inb(port: u16) -> u8 {
+    let value: u8;
+    unsafe {
+        asm!(
+            "in al, dx",
+            in("dx") port,
+            out("al") value,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_packet_sync() {
+        // 首字节不满足同步位时被丢弃
+        handle_mouse_byte(0x00);
+        assert_eq!(PACKET.lock().len, 0);
+        handle_mouse_byte(PACKET_ALWAYS_ONE);
+        handle_mouse_byte(1);
+        assert_eq!(PACKET.lock().len, 2);
+        handle_mouse_byte(1);
+        assert_eq!(PACKET.lock().len, 0);
+    }
+}
@@ -0,0 +1,58 @@
+use core::time::Duration;
+
+use alloc::{boxed::Box, sync::Arc};
+use netstack::{
+    BoxFuture,
+    stack::{Ipv4Config, NetStack},
+    time::Clock,
+};
+
+use crate::{klog, multitask, sync::spin::SpinLock, time};
+
+pub mod virtio_net;
+
+static NET_STACK: SpinLock<Option<Arc<NetStack>>> = SpinLock::new(None);
+
+pub struct InitNetError;
+
+// 协议栈使用的系统时钟
+struct KernelClock;
+
+impl Clock for KernelClock {
+    fn now(&self) -> Duration {
+        time::monotonic()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(multitask::async_task::sleep(duration))
+    }
+}
+
+// 初始化网络
+//
+// 目前仅支持virtio网卡，并使用qemu用户模式网络的固定配置，不支持DHCP
+pub async fn init_net() -> Result<(), InitNetError> {
+    let device = virtio_net::probe().ok_or(InitNetError)?;
+    let stack = NetStack::new(device, Arc::new(KernelClock), Ipv4Config::QEMU_USER_NETWORK);
+    klog!(
+        info,
+        "net",
+        "mac {}, address {}",
+        stack.mac_address(),
+        stack.config().address
+    );
+    *NET_STACK.lock() = Some(stack.clone());
+
+    let timer_stack = stack.clone();
+    multitask::async_rt::spawn(async move { timer_stack.run_timers().await });
+    multitask::async_rt::spawn(async move {
+        let error = stack.run().await;
+        klog!(warn, "net", "device stopped: {error:?}");
+    });
+    Ok(())
+}
+
+/// 网络协议栈，未找到网卡时返回None
+pub fn net_stack() -> Option<Arc<NetStack>> {
+    NET_STACK.lock().clone()
+}
@@ -0,0 +1,480 @@
+use core::{
+    arch::asm,
+    future::poll_fn,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicU16, Ordering, fence},
+    task::{Poll, Waker},
+};
+
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use netstack::{
+    BoxFuture,
+    device::{DeviceError, NetworkDevice},
+    wire::MacAddress,
+};
+
+use crate::{
+    io::pci,
+    klog,
+    memory::dma::{DMA_32BIT_LIMIT, DMA_NO_LIMIT, DmaBuffer},
+    multitask::workqueue::{self, Priority},
+    sync::{int::IrqGuard, spin::SpinLock},
+    trap,
+};
+
+/// PCI设备：传统（transitional）virtio网卡
+const PCI_VENDOR_VIRTIO: u16 = 0x1AF4;
+const PCI_DEVICE_NET: u16 = 0x1000;
+
+/// 传统接口的IO寄存器，位于BAR0
+const REG_DEVICE_FEATURES: u16 = 0x00;
+const REG_GUEST_FEATURES: u16 = 0x04;
+const REG_QUEUE_PFN: u16 = 0x08;
+const REG_QUEUE_SIZE: u16 = 0x0C;
+const REG_QUEUE_SELECT: u16 = 0x0E;
+const REG_QUEUE_NOTIFY: u16 = 0x10;
+const REG_DEVICE_STATUS: u16 = 0x12;
+const REG_ISR_STATUS: u16 = 0x13;
+/// 网卡配置：MAC地址
+const REG_MAC: u16 = 0x14;
+
+/// 设备状态
+const STATUS_ACKNOWLEDGE: u8 = 1;
+const STATUS_DRIVER: u8 = 2;
+const STATUS_DRIVER_OK: u8 = 4;
+const STATUS_FAILED: u8 = 0x80;
+
+/// ISR：虚拟队列中断
+const ISR_QUEUE: u8 = 1;
+
+/// 特性：设备提供MAC地址
+const FEATURE_MAC: u32 = 1 << 5;
+
+/// 虚拟队列编号
+const QUEUE_RECEIVE: u16 = 0;
+const QUEUE_TRANSMIT: u16 = 1;
+
+/// 描述符标志：设备写入的缓冲区
+const DESCRIPTOR_WRITE: u16 = 2;
+
+/// 每个收发缓冲区前的virtio-net头部，不协商任何卸载特性时为10字节且内容全为0
+const NET_HEADER_LEN: usize = 10;
+/// 每个收发缓冲区的大小，足够存放头部与一个完整的以太网帧
+const BUFFER_SIZE: usize = 2048;
+/// 每个队列使用的缓冲区数量，队列长度更小时以队列长度为准
+const BUFFER_COUNT: usize = 16;
+/// 以太网帧（不含FCS）的最大长度
+const MAX_FRAME_LEN: usize = 1514;
+
+/// 传统接口使用的IO端口，为0时表示网卡未初始化
+static IO_BASE: AtomicU16 = AtomicU16::new(0);
+static DEVICE: SpinLock<Option<Device>> = SpinLock::new(None);
+/// 是否已有待执行的唤醒处理
+static WAKE_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// virtio网卡驱动
+///
+/// 使用virtio 0.9.5的传统PCI接口，收发各使用一个虚拟队列，不协商校验和卸载等特性。
+/// 仅支持一块网卡
+pub struct VirtioNet {
+    mac_address: MacAddress,
+}
+
+struct Device {
+    receive: Virtqueue,
+    transmit: Virtqueue,
+    /// 空闲的发送缓冲区
+    transmit_free: Vec<u16>,
+    receive_waker: Option<Waker>,
+    transmit_waker: Option<Waker>,
+}
+
+/// 传统布局的虚拟队列
+///
+/// 描述符表与可用环相邻，已用环对齐至下一个4K边界。描述符与缓冲区一一对应，描述符i始终指向第i个缓冲区
+struct Virtqueue {
+    index: u16,
+    size: u16,
+    ring: DmaBuffer,
+    used_offset: usize,
+    buffers: DmaBuffer,
+    buffer_count: u16,
+    /// 下一个可用环项的序号
+    avail_index: u16,
+    /// 下一个待处理的已用环项的序号
+    last_used_index: u16,
+}
+
+#[repr(C)]
+struct Descriptor {
+    address: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+/// 查找并初始化网卡，未找到网卡或初始化失败时返回None
+pub fn probe() -> Option<Arc<VirtioNet>> {
+    if IO_BASE.load(Ordering::Acquire) != 0 {
+        return None;
+    }
+    let device = pci::enumerate().into_iter().find(|device| {
+        device.vendor_id == PCI_VENDOR_VIRTIO && device.device_id == PCI_DEVICE_NET
+    })?;
+    // 与AHCI相同，仅支持已在IDT中注册的PCI中断线
+    let irq = match device.interrupt_line() {
+        Some(irq @ 9..=11) => irq,
+        irq => {
+            klog!(warn, "virtio-net", "unsupported interrupt line {irq:?}");
+            return None;
+        }
+    };
+    let io_base = device.io_bar(0)?;
+    device.enable_bus_master();
+
+    let _guard = IrqGuard::cli();
+    unsafe {
+        // 复位设备
+        outb(io_base + REG_DEVICE_STATUS, 0);
+        outb(
+            io_base + REG_DEVICE_STATUS,
+            STATUS_ACKNOWLEDGE | STATUS_DRIVER,
+        );
+    }
+    let features = unsafe { inl(io_base + REG_DEVICE_FEATURES) } & FEATURE_MAC;
+    unsafe {
+        outl(io_base + REG_GUEST_FEATURES, features);
+    }
+    let mac_address = if features & FEATURE_MAC != 0 {
+        MacAddress(core::array::from_fn(|i| unsafe {
+            inb(io_base + REG_MAC + i as u16)
+        }))
+    } else {
+        // 设备未提供MAC地址，使用qemu的默认地址
+        MacAddress([0x52, 0x54, 0x00, 0x12, 0x34, 0x56])
+    };
+
+    let queues =
+        Virtqueue::new(io_base, QUEUE_RECEIVE).zip(Virtqueue::new(io_base, QUEUE_TRANSMIT));
+    let Some((mut receive, transmit)) = queues else {
+        unsafe {
+            outb(io_base + REG_DEVICE_STATUS, STATUS_FAILED);
+        }
+        return None;
+    };
+    // 全部接收缓冲区交给设备
+    for id in 0..receive.buffer_count {
+        receive.push(id, BUFFER_SIZE as u32, true);
+    }
+    let transmit_free = (0..transmit.buffer_count).rev().collect();
+    *DEVICE.lock() = Some(Device {
+        receive,
+        transmit,
+        transmit_free,
+        receive_waker: None,
+        transmit_waker: None,
+    });
+
+    IO_BASE.store(io_base, Ordering::Release);
+    unsafe {
+        outb(
+            io_base + REG_DEVICE_STATUS,
+            STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_DRIVER_OK,
+        );
+        outw(io_base + REG_QUEUE_NOTIFY, QUEUE_RECEIVE);
+    }
+    trap::unmask_irq(irq);
+
+    Some(Arc::new(VirtioNet { mac_address }))
+}
+
+impl NetworkDevice for VirtioNet {
+    fn mac_address(&self) -> MacAddress {
+        self.mac_address
+    }
+
+    fn transmit<'fut>(&'fut self, frame: &'fut [u8]) -> BoxFuture<'fut, Result<(), DeviceError>> {
+        Box::pin(async move {
+            if frame.len() > MAX_FRAME_LEN {
+                return Err(DeviceError::FrameTooLarge);
+            }
+            poll_fn(|cx| {
+                let _guard = IrqGuard::cli();
+                let mut device = DEVICE.lock();
+                let device = device.as_mut().unwrap();
+                // 回收设备已发送完成的缓冲区
+                while let Some((id, _)) = device.transmit.pop_used() {
+                    device.transmit_free.push(id);
+                }
+                let Some(id) = device.transmit_free.pop() else {
+                    device.transmit_waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                };
+                let buffer = device.transmit.buffer(id);
+                unsafe {
+                    ptr::write_bytes(buffer, 0, NET_HEADER_LEN);
+                    ptr::copy_nonoverlapping(
+                        frame.as_ptr(),
+                        buffer.add(NET_HEADER_LEN),
+                        frame.len(),
+                    );
+                }
+                device
+                    .transmit
+                    .push(id, (NET_HEADER_LEN + frame.len()) as u32, false);
+                device.transmit.notify();
+                Poll::Ready(Ok(()))
+            })
+            .await
+        })
+    }
+
+    fn receive<'fut>(
+        &'fut self,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<usize, DeviceError>> {
+        Box::pin(poll_fn(|cx| {
+            let _guard = IrqGuard::cli();
+            let mut device = DEVICE.lock();
+            let device = device.as_mut().unwrap();
+            let Some((id, len)) = device.receive.pop_used() else {
+                device.receive_waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            };
+            let frame_len = (len as usize)
+                .saturating_sub(NET_HEADER_LEN)
+                .min(BUFFER_SIZE - NET_HEADER_LEN);
+            let copy_len = frame_len.min(buf.len());
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    device.receive.buffer(id).add(NET_HEADER_LEN),
+                    buf.as_mut_ptr(),
+                    copy_len,
+                );
+            }
+            // 缓冲区交还设备
+            device.receive.push(id, BUFFER_SIZE as u32, true);
+            device.receive.notify();
+            Poll::Ready(Ok(copy_len))
+        }))
+    }
+}
+
+impl Virtqueue {
+    fn new(io_base: u16, index: u16) -> Option<Self> {
+        let size = unsafe {
+            outw(io_base + REG_QUEUE_SELECT, index);
+            inw(io_base + REG_QUEUE_SIZE)
+        };
+        if size == 0 {
+            return None;
+        }
+        let used_offset = (16 * size as usize + 6 + 2 * size as usize).next_multiple_of(0x1000);
+        let ring_size = used_offset + (6 + 8 * size as usize).next_multiple_of(0x1000);
+        let buffer_count = BUFFER_COUNT.min(size as usize);
+        // 传统接口的队列地址以页号表示，只有32位
+        let ring = DmaBuffer::alloc(ring_size, DMA_32BIT_LIMIT)?;
+        let buffers = DmaBuffer::alloc(buffer_count * BUFFER_SIZE, DMA_NO_LIMIT)?;
+
+        let queue = Self {
+            index,
+            size,
+            ring,
+            used_offset,
+            buffers,
+            buffer_count: buffer_count as u16,
+            avail_index: 0,
+            last_used_index: 0,
+        };
+        for id in 0..queue.buffer_count {
+            unsafe {
+                queue.descriptor(id).write_volatile(Descriptor {
+                    address: queue.buffers.physical_address() + id as u64 * BUFFER_SIZE as u64,
+                    len: BUFFER_SIZE as u32,
+                    flags: 0,
+                    next: 0,
+                });
+            }
+        }
+        unsafe {
+            outl(
+                io_base + REG_QUEUE_PFN,
+                (queue.ring.physical_address() >> 12) as u32,
+            );
+        }
+        Some(queue)
+    }
+
+    fn descriptor(&self, id: u16) -> *mut Descriptor {
+        (self.ring.virtual_address() + id as usize * size_of::<Descriptor>()) as *mut Descriptor
+    }
+
+    fn buffer(&self, id: u16) -> *mut u8 {
+        (self.buffers.virtual_address() + id as usize * BUFFER_SIZE) as *mut u8
+    }
+
+    /// 可用环的起始地址：flags、idx、ring[size]
+    fn avail(&self) -> *mut u16 {
+        (self.ring.virtual_address() + self.size as usize * size_of::<Descriptor>()) as *mut u16
+    }
+
+    /// 已用环的起始地址：flags、idx、ring[size]（每项为id与len两个u32）
+    fn used(&self) -> *mut u16 {
+        (self.ring.virtual_address() + self.used_offset) as *mut u16
+    }
+
+    /// 将缓冲区放入可用环
+    fn push(&mut self, id: u16, len: u32, writable: bool) {
+        let flags = if writable { DESCRIPTOR_WRITE } else { 0 };
+        let avail = self.avail();
+        unsafe {
+            let descriptor = self.descriptor(id);
+            (&raw mut (*descriptor).len).write_volatile(len);
+            (&raw mut (*descriptor).flags).write_volatile(flags);
+            avail
+                .add(2 + (self.avail_index % self.size) as usize)
+                .write_volatile(id);
+        }
+        self.avail_index = self.avail_index.wrapping_add(1);
+        // 设备必须先看到环项，再看到新的idx
+        fence(Ordering::SeqCst);
+        unsafe {
+            avail.add(1).write_volatile(self.avail_index);
+        }
+    }
+
+    /// 取出一个设备已处理完成的缓冲区，返回描述符编号与设备写入的长度
+    fn pop_used(&mut self) -> Option<(u16, u32)> {
+        let used = self.used();
+        let used_index = unsafe { used.add(1).read_volatile() };
+        if used_index == self.last_used_index {
+            return None;
+        }
+        fence(Ordering::SeqCst);
+        let element = unsafe {
+            used.add(2)
+                .cast::<u32>()
+                .add(2 * (self.last_used_index % self.size) as usize)
+        };
+        let (id, len) = unsafe { (element.read_volatile(), element.add(1).read_volatile()) };
+        self.last_used_index = self.last_used_index.wrapping_add(1);
+        Some((id as u16, len))
+    }
+
+    /// 通知设备可用环已更新
+    fn notify(&self) {
+        fence(Ordering::SeqCst);
+        unsafe {
+            outw(
+                IO_BASE.load(Ordering::Acquire) + REG_QUEUE_NOTIFY,
+                self.index,
+            );
+        }
+    }
+}
+
+/// 网卡中断处理
+///
+/// 读取ISR以清除中断，收发完成的处理交给工作队列
+pub fn virtio_net_irq() {
+    let io_base = IO_BASE.load(Ordering::Acquire);
+    if io_base == 0 {
+        return;
+    }
+    // 与其他设备共享中断线时，ISR为0表示中断不是由网卡产生的
+    if unsafe { inb(io_base + REG_ISR_STATUS) } & ISR_QUEUE == 0 {
+        return;
+    }
+    if WAKE_PENDING.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    if workqueue::enqueue(Priority::Normal, wake_waiters).is_err() {
+        wake_waiters();
+    }
+}
+
+fn wake_waiters() {
+    let (receive_waker, transmit_waker) = {
+        let _guard = IrqGuard::cli();
+        WAKE_PENDING.store(false, Ordering::Release);
+        let mut device = DEVICE.lock();
+        let device = device.as_mut().unwrap();
+        (device.receive_waker.take(), device.transmit_waker.take())
+    };
+    for waker in [receive_waker, transmit_waker].into_iter().flatten() {
+        waker.wake();
+    }
+}
+
+// 写入端口前的内存写入（虚拟队列）必须对设备可见，因此端口操作不使用nomem
+unsafe fn outb(port: u16, value: u8) {
+    unsafe {
+        asm!(
+            "out dx, al",
+            in("dx") port,
+            in("al") value,
+            options(nostack, preserves_flags)
+        );
+    }
+}
+
+unsafe fn outw(port: u16, value: u16) {
+    unsafe {
+        asm!(
+            "out dx, ax",
+            in("dx") port,
+            in("ax") value,
+            options(nostack, preserves_flags)
+        );
+    }
+}
+
+unsafe fn outl(port: u16, value: u32) {
+    unsafe {
+        asm!(
+            "out dx, eax",
+            in("dx") port,
+            in("eax") value,
+            options(nostack, preserves_flags)
+        );
+    }
+}
+
+unsafe fn inb(port: u16) -> u8 {
+    let value: u8;
+    unsafe {
+        asm!(
+            "in al, dx",
+            in("dx") port,
+            out("al") value,
+            options(nostack, preserves_flags)
+        );
+    }
+    value
+}
+
+unsafe fn inw(port: u16) -> u16 {
+    let value: u16;
+    unsafe {
+        asm!(
+            "in ax, dx",
+            in("dx") port,
+            out("ax") value,
+            options(nostack, preserves_flags)
+        );
+    }
+    value
+}
+
+unsafe fn inl(port: u16) -> u32 {
+    let value: u32;
+    unsafe {
+        asm!(
+            "in eax, dx",
+            in("dx") port,
+            out("eax") value,
+            options(nostack, preserves_flags)
+        );
+    }
+    value
+}
@@ -0,0 +1,209 @@
+use core::arch::asm;
+
+use alloc::vec::Vec;
+
+use crate::sync::int::IrqGuard;
+
+/// 配置空间地址端口
+const CONFIG_ADDRESS: u16 = 0xCF8;
+/// 配置空间数据端口
+const CONFIG_DATA: u16 = 0xCFC;
+
+/// 配置空间寄存器偏移
+const REG_VENDOR_ID: u8 = 0x00;
+const REG_COMMAND: u8 = 0x04;
+const REG_CLASS: u8 = 0x08;
+const REG_HEADER_TYPE: u8 = 0x0E;
+const REG_BAR0: u8 = 0x10;
+const REG_INTERRUPT_LINE: u8 = 0x3C;
+
+/// 命令寄存器：响应内存空间访问
+const COMMAND_MEMORY_SPACE: u16 = 1 << 1;
+/// 命令寄存器：允许设备发起DMA
+const COMMAND_BUS_MASTER: u16 = 1 << 2;
+/// 命令寄存器：禁用INTx中断
+const COMMAND_INTERRUPT_DISABLE: u16 = 1 << 10;
+
+/// 头类型：多功能设备
+const HEADER_TYPE_MULTI_FUNCTION: u8 = 0x80;
+
+/// PCI设备（功能）
+///
+/// 通过传统的0xCF8/0xCFC端口访问配置空间，仅支持前256字节
+#[derive(Debug, Clone, Copy)]
+pub struct PciDevice {
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub prog_if: u8,
+}
+
+impl PciDevice {
+    /// 读取指定位置的设备，设备不存在时返回None
+    fn probe(bus: u8, device: u8, function: u8) -> Option<Self> {
+        let id = read_config(bus, device, function, REG_VENDOR_ID);
+        let vendor_id = id as u16;
+        if vendor_id == 0xFFFF {
+            return None;
+        }
+        let class = read_config(bus, device, function, REG_CLASS);
+        Some(Self {
+            bus,
+            device,
+            function,
+            vendor_id,
+            device_id: (id >> 16) as u16,
+            class: (class >> 24) as u8,
+            subclass: (class >> 16) as u8,
+            prog_if: (class >> 8) as u8,
+        })
+    }
+
+    pub fn read_u32(&self, offset: u8) -> u32 {
+        read_config(self.bus, self.device, self.function, offset)
+    }
+
+    pub fn write_u32(&self, offset: u8, value: u32) {
+        write_config(self.bus, self.device, self.function, offset, value);
+    }
+
+    pub fn read_u16(&self, offset: u8) -> u16 {
+        (self.read_u32(offset & !3) >> ((offset & 2) * 8)) as u16
+    }
+
+    pub fn write_u16(&self, offset: u8, value: u16) {
+        let shift = (offset & 2) * 8;
+        let old = self.read_u32(offset & !3) & !(0xFFFF << shift);
+        self.write_u32(offset & !3, old | ((value as u32) << shift));
+    }
+
+    pub fn read_u8(&self, offset: u8) -> u8 {
+        (self.read_u32(offset & !3) >> ((offset & 3) * 8)) as u8
+    }
+
+    fn header_type(&self) -> u8 {
+        self.read_u8(REG_HEADER_TYPE)
+    }
+
+    /// 读取内存空间BAR的物理地址，BAR为IO空间或未配置时返回None
+    ///
+    /// 64位BAR占用两个连续的BAR寄存器，index应为低位所在的寄存器
+    pub fn memory_bar(&self, index: u8) -> Option<u64> {
+        assert!(index < 6);
+        let offset = REG_BAR0 + index * 4;
+        let low = self.read_u32(offset);
+        // 第0位为1表示IO空间
+        if low & 1 != 0 {
+            return None;
+        }
+        let address = match (low >> 1) & 0b11 {
+            // 32位
+            0b00 => (low & !0xF) as u64,
+            // 64位
+            0b10 if index < 5 => ((self.read_u32(offset + 4) as u64) << 32) | (low & !0xF) as u64,
+            _ => return None,
+        };
+        (address != 0).then_some(address)
+    }
+
+    /// 读取IO空间BAR的端口号，BAR为内存空间或未配置时返回None
+    pub fn io_bar(&self, index: u8) -> Option<u16> {
+        assert!(index < 6);
+        let bar = self.read_u32(REG_BAR0 + index * 4);
+        (bar & 1 != 0 && bar & !0x3 != 0).then_some((bar & !0x3) as u16)
+    }
+
+    /// 固件分配的传统中断号（PIC IRQ），未分配时返回None
+    pub fn interrupt_line(&self) -> Option<u8> {
+        let line = self.read_u8(REG_INTERRUPT_LINE);
+        (line < 16).then_some(line)
+    }
+
+    /// 允许设备响应内存空间访问、发起DMA及产生INTx中断
+    pub fn enable_bus_master(&self) {
+        let command = self.read_u16(REG_COMMAND);
+        self.write_u16(
+            REG_COMMAND,
+            (command | COMMAND_MEMORY_SPACE | COMMAND_BUS_MASTER) & !COMMAND_INTERRUPT_DISABLE,
+        );
+    }
+}
+
+/// 枚举总线上的全部设备
+///
+/// 逐一探测每条总线的每个设备号，不解析PCI桥的拓扑结构
+pub fn enumerate() -> Vec<PciDevice> {
+    let mut devices = Vec::new();
+    for bus in 0..=255u8 {
+        for device in 0..32u8 {
+            let Some(first) = PciDevice::probe(bus, device, 0) else {
+                continue;
+            };
+            let multi_function = first.header_type() & HEADER_TYPE_MULTI_FUNCTION != 0;
+            devices.push(first);
+            if multi_function {
+                devices
+                    .extend((1..8).filter_map(|function| PciDevice::probe(bus, device, function)));
+            }
+        }
+    }
+    devices
+}
+
+/// 查找第一个指定类型的设备
+pub fn find_by_class(class: u8, subclass: u8, prog_if: u8) -> Option<PciDevice> {
+    enumerate().into_iter().find(|device| {
+        device.class == class && device.subclass == subclass && device.prog_if == prog_if
+    })
+}
+
+fn config_address(bus: u8, device: u8, function: u8, offset: u8) -> u32 {
+    (1 << 31)
+        | ((bus as u32) << 16)
+        | ((device as u32) << 11)
+        | ((function as u32) << 8)
+        | (offset as u32 & 0xFC)
+}
+
+fn read_config(bus: u8, device: u8, function: u8, offset: u8) -> u32 {
+    // 地址端口与数据端口需要成对访问，不能被打断
+    let _guard = IrqGuard::cli();
+    let value: u32;
+    unsafe {
+        asm!(
+            "out dx, eax",
+            in("dx") CONFIG_ADDRESS,
+            in("eax") config_address(bus, device, function, offset),
+            options(nomem, nostack, preserves_flags)
+        );
+        asm!(
+            "in eax, dx",
+            in("dx") CONFIG_DATA,
+            out("eax") value,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    value
+}
+
+fn write_config(bus: u8, device: u8, function: u8, offset: u8, value: u32) {
+    let _guard = IrqGuard::cli();
+    unsafe {
+        asm!(
+            "out dx, eax",
+            in("dx") CONFIG_ADDRESS,
+            in("eax") config_address(bus, device, function, offset),
+            options(nomem, nostack, preserves_flags)
+        );
+        asm!(
+            "out dx, eax",
+            in("dx") CONFIG_DATA,
+            in("eax") value,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+}
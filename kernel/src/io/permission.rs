@@ -0,0 +1,185 @@
+use filesystem::{
+    fs::{
+        FileSystemError,
+        permission::{FilePermission, PermissionTable, ROOT_UID},
+    },
+    path::Path,
+};
+
+use crate::{io::vfs, klog, multitask::process::Credentials};
+
+/// 修改权限失败原因
+#[derive(Debug)]
+pub enum PermissionError {
+    /// 不是文件的所有者或超级用户
+    PermissionDenied,
+    /// 文件不存在或权限表写入失败
+    FileSystem(FileSystemError),
+}
+
+/// 权限表附属文件本身的权限，只有超级用户可以访问
+const SIDECAR_PERMISSION: FilePermission = FilePermission {
+    uid: 0,
+    gid: 0,
+    mode: 0o600,
+};
+
+/// 权限表无法读取时文件的权限，只有超级用户可以访问
+const UNREADABLE_PERMISSION: FilePermission = FilePermission {
+    uid: 0,
+    gid: 0,
+    mode: 0,
+};
+
+/// 获取文件的所有者与权限，path为绝对路径
+///
+/// 权限表无法读取时返回 [`UNREADABLE_PERMISSION`]，避免以默认权限放行
+pub async fn get(path: Path<'_>) -> FilePermission {
+    let permission = with_table(path, false, |table, path| {
+        if PermissionTable::is_sidecar(path) {
+            SIDECAR_PERMISSION
+        } else {
+            table.get(path)
+        }
+    })
+    .await;
+    match permission {
+        Some(Ok(permission)) => permission,
+        Some(Err(_)) => UNREADABLE_PERMISSION,
+        None => FilePermission::DEFAULT,
+    }
+}
+
+/// 判断用户对文件是否具有access指定的全部权限，access为 [`FilePermission::READ`] 等的组合
+pub async fn check(credentials: Credentials, path: Path<'_>, access: u16) -> bool {
+    get(path)
+        .await
+        .allows(credentials.uid, credentials.gid, access)
+}
+
+/// 判断用户能否在文件所在的目录中创建、删除或重命名该文件，即是否具有父目录的写权限
+pub async fn check_parent(credentials: Credentials, path: Path<'_>) -> bool {
+    check(credentials, path.parent(), FilePermission::WRITE).await
+}
+
+/// 文件被创建后，将创建者记录为文件的所有者
+pub async fn created(credentials: Credentials, path: Path<'_>) {
+    let permission = FilePermission {
+        uid: credentials.uid,
+        gid: credentials.gid,
+        mode: 0o644,
+    };
+    update(path, |table, path| table.set(path, permission)).await;
+}
+
+/// 内核自身创建的文件（如内核槽位与日志），记录为属于超级用户，权限位为mode
+///
+/// 文件已存在时同样覆盖原有记录，避免沿用未记录文件的默认权限
+pub async fn created_by_kernel(path: Path<'_>, mode: u16) {
+    let permission = FilePermission {
+        uid: ROOT_UID,
+        gid: ROOT_UID,
+        mode,
+    };
+    update(path, |table, path| table.set(path, permission)).await;
+}
+
+/// 文件被删除后，移除其记录
+pub async fn removed(path: Path<'_>) {
+    update(path, |table, path| table.remove(path)).await;
+}
+
+/// 文件被重命名后，将其记录移动到新路径，两个路径需位于同一文件系统
+pub async fn renamed(old_path: Path<'_>, new_path: Path<'_>) {
+    let Some((_, _, new_path)) = vfs::resolve_permissions(&new_path) else {
+        return;
+    };
+    update(old_path, |table, old_path| table.rename(old_path, new_path)).await;
+}
+
+/// 修改文件的权限位，只有文件的所有者与超级用户可以修改
+pub async fn chmod(
+    credentials: Credentials,
+    path: Path<'_>,
+    mode: u16,
+) -> Result<(), PermissionError> {
+    let mut permission = get(path).await;
+    if !credentials.is_root() && credentials.uid != permission.uid {
+        return Err(PermissionError::PermissionDenied);
+    }
+    permission.mode = mode & FilePermission::MODE_MASK;
+    set(path, permission).await
+}
+
+/// 修改文件的所有者，只有超级用户可以修改
+pub async fn chown(
+    credentials: Credentials,
+    path: Path<'_>,
+    uid: u32,
+    gid: u32,
+) -> Result<(), PermissionError> {
+    if !credentials.is_root() {
+        return Err(PermissionError::PermissionDenied);
+    }
+    let mut permission = get(path).await;
+    permission.uid = uid;
+    permission.gid = gid;
+    set(path, permission).await
+}
+
+async fn set(path: Path<'_>, permission: FilePermission) -> Result<(), PermissionError> {
+    let (fs, _, relative) = vfs::resolve_permissions(&path)
+        .ok_or(PermissionError::FileSystem(FileSystemError::FileNotFound))?;
+    // 文件须存在
+    fs.get_metadata(relative)
+        .await
+        .map_err(PermissionError::FileSystem)?;
+    drop(fs);
+    with_table(path, true, |table, path| table.set(path, permission))
+        .await
+        .ok_or(PermissionError::FileSystem(FileSystemError::FileNotFound))?
+        .map_err(PermissionError::FileSystem)
+}
+
+/// 修改权限表并写回文件系统，写回失败时仅记录日志，文件操作本身已经完成
+async fn update(path: Path<'_>, f: impl FnOnce(&mut PermissionTable, Path<'_>)) {
+    if let Some(Err(error)) = with_table(path, true, f).await {
+        klog!(
+            warn,
+            "vfs",
+            "failed to save permissions of {path}: {error:?}"
+        );
+    }
+}
+
+/// 以path所在文件系统的权限表执行f，f的第二个参数为path在该文件系统中的路径
+///
+/// 路径不在任何已挂载的文件系统中时返回None。save为true时，执行后将权限表写回文件系统。
+/// 权限表读取失败时返回Err且不执行f，失败的结果不缓存，下次访问时重新读取
+async fn with_table<R>(
+    path: Path<'_>,
+    save: bool,
+    f: impl FnOnce(&mut PermissionTable, Path<'_>) -> R,
+) -> Option<Result<R, FileSystemError>> {
+    let (fs, store, relative) = vfs::resolve_permissions(&path)?;
+    let mut table = store.lock().await;
+    let table = match &mut *table {
+        Some(table) => table,
+        None => match PermissionTable::load(&*fs).await {
+            Ok(loaded) => table.insert(loaded),
+            Err(error) => {
+                klog!(
+                    warn,
+                    "vfs",
+                    "failed to load permissions for {path}: {error:?}"
+                );
+                return Some(Err(error));
+            }
+        },
+    };
+    let result = f(table, relative);
+    if save && let Err(error) = table.save(&*fs).await {
+        return Some(Err(error));
+    }
+    Some(Ok(result))
+}
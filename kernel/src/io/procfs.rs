@@ -0,0 +1,424 @@
+//! 进程文件系统（procfs）
+//!
+//! 挂载于 [PROCFS_PATH]，以只读文件的形式展示内核状态，文件内容在打开时由内核数据结构生成：
+//!
+//! - `uptime`：启动后经过的秒数与其中CPU空闲的秒数
+//! - `meminfo`：物理内存与内核堆的使用情况
+//! - `mounts`：挂载表，每行为块设备名称与挂载路径
+//! - `<pid>/status`：进程信息
+//!
+//! 打开的文件保存生成时的内容，读取期间内核状态的变化不会影响已打开的文件
+
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use filesystem::{
+    BoxFuture,
+    fs::{FileHandle, FileMetadata, FileSystem, FileSystemError},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    io::vfs,
+    memory,
+    multitask::{self, process::Process},
+    sync::spin::SpinLock,
+};
+
+/// 进程文件系统的挂载路径
+const PROCFS_PATH: &str = "/proc";
+
+/// 根目录中的固定文件
+const ROOT_FILES: [(&str, Node); 3] = [
+    ("uptime", Node::Uptime),
+    ("meminfo", Node::Meminfo),
+    ("mounts", Node::Mounts),
+];
+
+pub struct ProcFileSystem {
+    unmounted: AtomicBool,
+}
+
+/// 文件系统中的文件或目录
+#[derive(Debug, Clone, Copy)]
+enum Node {
+    Root,
+    Uptime,
+    Meminfo,
+    Mounts,
+    /// 进程目录
+    Process(u64),
+    /// 进程目录中的status文件
+    ProcessStatus(u64),
+}
+
+struct ProcFileHandle {
+    content: Vec<u8>,
+    pointer: u64,
+    closed: bool,
+}
+
+/// 在 [PROCFS_PATH] 挂载进程文件系统
+pub fn mount() {
+    let path = PathBuf::from_str(PROCFS_PATH).expect("codebug: invalid procfs path");
+    let fs = ProcFileSystem {
+        unmounted: AtomicBool::new(false),
+    };
+    vfs::mount(path, Arc::new(fs));
+}
+
+impl ProcFileSystem {
+    fn check_mounted(&self) -> Result<(), FileSystemError> {
+        if self.unmounted.load(Ordering::Acquire) {
+            return Err(FileSystemError::Unmounted);
+        }
+        Ok(())
+    }
+
+    fn lookup(&self, path: Path<'_>) -> Result<Node, FileSystemError> {
+        self.check_mounted()?;
+        let mut node = Node::Root;
+        for segment in path.iter() {
+            node = match node {
+                Node::Root => ROOT_FILES
+                    .iter()
+                    .find(|(name, _)| *name == segment)
+                    .map(|(_, node)| *node)
+                    .or_else(|| {
+                        // 进程可能已退出
+                        let process_id = segment.parse().ok()?;
+                        multitask::process::get_process(process_id)?;
+                        Some(Node::Process(process_id))
+                    })
+                    .ok_or(FileSystemError::FileNotFound)?,
+                Node::Process(process_id) if segment == "status" => Node::ProcessStatus(process_id),
+                Node::Process(_) => return Err(FileSystemError::FileNotFound),
+                _ => return Err(FileSystemError::FileTypeMismatch),
+            };
+        }
+        Ok(node)
+    }
+}
+
+impl Node {
+    fn is_directory(self) -> bool {
+        matches!(self, Node::Root | Node::Process(_))
+    }
+
+    fn metadata(self, name: &str) -> Result<FileMetadata, FileSystemError> {
+        let size = match self.is_directory() {
+            true => 0,
+            false => self.content()?.len() as u64,
+        };
+        Ok(FileMetadata {
+            name: name.to_string(),
+            size,
+            is_directory: self.is_directory(),
+            allocated_size: 0,
+        })
+    }
+
+    /// 生成文件的内容
+    fn content(self) -> Result<Vec<u8>, FileSystemError> {
+        let mut content = String::new();
+        match self {
+            Node::Root | Node::Process(_) => return Err(FileSystemError::FileTypeMismatch),
+            Node::Uptime => {
+                let (total, idle) = multitask::thread::system_cpu_time();
+                _ = writeln!(
+                    content,
+                    "{}.{:06} {}.{:06}",
+                    total / 1_000_000,
+                    total % 1_000_000,
+                    idle / 1_000_000,
+                    idle % 1_000_000
+                );
+            }
+            Node::Meminfo => write_meminfo(&mut content),
+            Node::Mounts => {
+                for (path, source) in vfs::list_mounts() {
+                    _ = writeln!(content, "{} {path}", source.as_deref().unwrap_or("none"));
+                }
+            }
+            Node::ProcessStatus(process_id) => {
+                let process = multitask::process::get_process(process_id)
+                    .ok_or(FileSystemError::FileNotFound)?;
+                write_status(&mut content, &process);
+            }
+        }
+        Ok(content.into_bytes())
+    }
+}
+
+fn write_meminfo(content: &mut String) {
+    let frame_stats = memory::frame_stats();
+    let heap_stats = memory::heap_stats();
+    let class_pages: u64 = heap_stats.classes.iter().map(|class| class.pages).sum();
+    let heap_allocated = heap_stats
+        .classes
+        .iter()
+        .map(|class| class.allocated * class.size as u64)
+        .sum::<u64>()
+        + heap_stats.large_pages * 4096;
+    let lines = [
+        ("MemTotal", frame_stats.total_memory / 1024),
+        ("MemReserved", frame_stats.reserved_memory / 1024),
+        ("MemAllocated", frame_stats.allocated_frames * 4),
+        ("MemReusable", frame_stats.free_list_frames * 4),
+        ("HeapHeld", (class_pages + heap_stats.large_pages) * 4),
+        ("HeapAllocated", heap_allocated / 1024),
+    ];
+    for (name, value) in lines {
+        _ = writeln!(content, "{:<16}{value:>10} kB", format!("{name}:"));
+    }
+}
+
+fn write_status(content: &mut String, process: &SpinLock<Process>) {
+    let info = multitask::process::process_info(process);
+    let credentials = multitask::process::credentials(process);
+    let args = multitask::process::process_args(process);
+    // 启动参数的第一项为可执行文件路径
+    let name = args.split(|&byte| byte == 0).next().unwrap_or_default();
+    let state = match info.state {
+        cos_sys::multitask::PROCESS_STATE_EXITING => "exiting",
+        _ => "running",
+    };
+    _ = writeln!(content, "Name:\t{}", String::from_utf8_lossy(name));
+    _ = writeln!(content, "Pid:\t{}", info.process_id);
+    _ = writeln!(content, "PPid:\t{}", info.parent_id);
+    _ = writeln!(content, "State:\t{state}");
+    _ = writeln!(content, "Uid:\t{}", credentials.uid);
+    _ = writeln!(content, "Gid:\t{}", credentials.gid);
+    _ = writeln!(content, "Pgrp:\t{}", info.process_group);
+    _ = writeln!(content, "Session:\t{}", info.session);
+    _ = writeln!(content, "Console:\t{}", info.console + 1);
+    _ = writeln!(content, "Threads:\t{}", info.thread_count);
+    _ = writeln!(content, "Handles:\t{}", info.handle_count);
+    _ = writeln!(content, "VmRSS:\t{} kB", info.resident_pages * 4);
+    _ = writeln!(content, "KernelMemory:\t{} bytes", info.kernel_memory);
+    _ = writeln!(content, "CpuTime:\t{} ms", info.cpu_time / 1000);
+}
+
+impl FileSystem for ProcFileSystem {
+    fn total_space(&self) -> BoxFuture<'_, Result<u64, FileSystemError>> {
+        Box::pin(async {
+            self.check_mounted()?;
+            Ok(0)
+        })
+    }
+
+    fn free_space(&self) -> BoxFuture<'_, Result<u64, FileSystemError>> {
+        Box::pin(async {
+            self.check_mounted()?;
+            Ok(0)
+        })
+    }
+
+    fn create_file<'fut>(
+        &'fut self,
+        _path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async { Err(FileSystemError::OperationNotSupport) })
+    }
+
+    fn create_directory<'fut>(
+        &'fut self,
+        _path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async { Err(FileSystemError::OperationNotSupport) })
+    }
+
+    fn open_file<'fut>(
+        &'fut self,
+        path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<Box<dyn FileHandle>, FileSystemError>> {
+        Box::pin(async move {
+            let content = self.lookup(path)?.content()?;
+            Ok(Box::new(ProcFileHandle {
+                content,
+                pointer: 0,
+                closed: false,
+            }) as Box<dyn FileHandle>)
+        })
+    }
+
+    fn delete_file<'fut>(
+        &'fut self,
+        _path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async { Err(FileSystemError::OperationNotSupport) })
+    }
+
+    fn delete_directory<'fut>(
+        &'fut self,
+        _path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async { Err(FileSystemError::OperationNotSupport) })
+    }
+
+    fn rename<'fut>(
+        &'fut self,
+        _old_path: Path<'fut>,
+        _new_path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async { Err(FileSystemError::OperationNotSupport) })
+    }
+
+    fn get_metadata<'fut>(
+        &'fut self,
+        path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<FileMetadata, FileSystemError>> {
+        Box::pin(async move {
+            self.lookup(path)?
+                .metadata(path.last_segment().unwrap_or(""))
+        })
+    }
+
+    fn list_directory<'fut>(
+        &'fut self,
+        path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<Vec<FileMetadata>, FileSystemError>> {
+        Box::pin(async move {
+            let entries = match self.lookup(path)? {
+                Node::Root => {
+                    let processes = multitask::process::list_processes()
+                        .into_iter()
+                        .map(|process_id| (process_id.to_string(), Node::Process(process_id)));
+                    ROOT_FILES
+                        .iter()
+                        .map(|(name, node)| (name.to_string(), *node))
+                        .chain(processes)
+                        .collect()
+                }
+                Node::Process(process_id) => {
+                    Vec::from([("status".to_string(), Node::ProcessStatus(process_id))])
+                }
+                _ => return Err(FileSystemError::FileTypeMismatch),
+            };
+            let mut result = Vec::with_capacity(entries.len());
+            for (name, node) in entries {
+                match node.metadata(&name) {
+                    Ok(metadata) => result.push(metadata),
+                    // 进程可能在列出后退出
+                    Err(FileSystemError::FileNotFound) => continue,
+                    Err(error) => return Err(error),
+                }
+            }
+            Ok(result)
+        })
+    }
+
+    fn unmount(&self) -> BoxFuture<'_, Result<(), FileSystemError>> {
+        Box::pin(async {
+            self.check_mounted()?;
+            self.unmounted.store(true, Ordering::Release);
+            Ok(())
+        })
+    }
+}
+
+impl ProcFileHandle {
+    fn check_open(&self) -> Result<(), FileSystemError> {
+        if self.closed {
+            return Err(FileSystemError::FileClosed);
+        }
+        Ok(())
+    }
+}
+
+impl FileHandle for ProcFileHandle {
+    fn close(&mut self) -> BoxFuture<'_, Result<(), FileSystemError>> {
+        Box::pin(async {
+            self.check_open()?;
+            self.closed = true;
+            self.content = Vec::new();
+            Ok(())
+        })
+    }
+
+    fn move_pointer(&mut self, position: u64) -> BoxFuture<'_, Result<(), FileSystemError>> {
+        Box::pin(async move {
+            self.check_open()?;
+            self.pointer = position.min(self.content.len() as u64);
+            Ok(())
+        })
+    }
+
+    fn get_pointer(&mut self) -> BoxFuture<'_, Result<u64, FileSystemError>> {
+        Box::pin(async {
+            self.check_open()?;
+            Ok(self.pointer)
+        })
+    }
+
+    fn read<'fut>(
+        &'fut mut self,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<u64, FileSystemError>> {
+        Box::pin(async move {
+            self.check_open()?;
+            let start = self.pointer as usize;
+            let len = buf.len().min(self.content.len() - start);
+            buf[..len].copy_from_slice(&self.content[start..start + len]);
+            self.pointer += len as u64;
+            Ok(len as u64)
+        })
+    }
+
+    fn write<'fut>(
+        &'fut mut self,
+        _buf: &'fut [u8],
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async { Err(FileSystemError::OperationNotSupport) })
+    }
+
+    fn truncate(&mut self) -> BoxFuture<'_, Result<(), FileSystemError>> {
+        Box::pin(async { Err(FileSystemError::OperationNotSupport) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(path: &str) -> Result<Node, FileSystemError> {
+        let fs = ProcFileSystem {
+            unmounted: AtomicBool::new(false),
+        };
+        let path = PathBuf::from_str(path).unwrap();
+        fs.lookup(path.as_path())
+    }
+
+    #[test_case]
+    fn test_lookup() {
+        assert!(matches!(lookup("/"), Ok(Node::Root)));
+        assert!(matches!(lookup("/uptime"), Ok(Node::Uptime)));
+        assert!(matches!(lookup("/mounts"), Ok(Node::Mounts)));
+        assert!(matches!(
+            lookup("/unknown"),
+            Err(FileSystemError::FileNotFound)
+        ));
+        assert!(matches!(
+            lookup("/uptime/status"),
+            Err(FileSystemError::FileTypeMismatch)
+        ));
+    }
+
+    #[test_case]
+    fn test_content() {
+        let meminfo = Node::Meminfo.content().unwrap();
+        assert!(meminfo.starts_with(b"MemTotal:"));
+        let uptime = Node::Uptime.content().unwrap();
+        assert!(uptime.ends_with(b"\n"));
+        assert!(Node::Root.content().is_err());
+    }
+}
@@ -0,0 +1,18 @@
+use core::arch::asm;
+
+/// isa-debug-exit设备的端口，需与build-scripts中qemu的参数保持一致
+const DEBUG_EXIT_PORT: u16 = 0xF4;
+
+/// 通过isa-debug-exit设备退出qemu
+///
+/// qemu的退出码为 `(code << 1) | 1`。如果qemu未配置该设备，写入会被忽略，函数正常返回
+pub fn exit(code: u32) {
+    unsafe {
+        asm!(
+            "out dx, eax",
+            in("dx") DEBUG_EXIT_PORT,
+            in("eax") code,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+}
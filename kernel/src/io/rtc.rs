@@ -0,0 +1,180 @@
+use core::{arch::asm, hint::spin_loop};
+
+use cos_sys::time::DateTime;
+
+use crate::sync::int::IrqGuard;
+
+const CMOS_ADDRESS: u16 = 0x70;
+const CMOS_DATA: u16 = 0x71;
+
+// CMOS寄存器
+const REG_SECOND: u8 = 0x00;
+const REG_MINUTE: u8 = 0x02;
+const REG_HOUR: u8 = 0x04;
+const REG_DAY: u8 = 0x07;
+const REG_MONTH: u8 = 0x08;
+const REG_YEAR: u8 = 0x09;
+const REG_STATUS_A: u8 = 0x0A;
+const REG_STATUS_B: u8 = 0x0B;
+
+// 状态寄存器A第7位：正在更新时间
+const STATUS_A_UPDATING: u8 = 1 << 7;
+// 状态寄存器B第1位：24小时制，否则为12小时制
+const STATUS_B_24_HOUR: u8 = 1 << 1;
+// 状态寄存器B第2位：二进制格式，否则为BCD格式
+const STATUS_B_BINARY: u8 = 1 << 2;
+// 12小时制下小时的第7位：下午
+const HOUR_PM: u8 = 1 << 7;
+
+/// 读取RTC的时间，视为UTC时间
+///
+/// RTC只记录两位年份，这里假设年份位于2000年至2099年。寄存器中的值不是合法的日期与时间时返回None
+pub fn read() -> Option<DateTime> {
+    let _guard = IrqGuard::cli();
+
+    // 连续两次读取结果一致时才采用，避免读到更新中途的值
+    let mut last = read_registers();
+    let raw = loop {
+        let current = read_registers();
+        if current == last {
+            break current;
+        }
+        last = current;
+    };
+
+    decode(raw, read_register(REG_STATUS_B))
+}
+
+/// 按状态寄存器B的格式解码时间寄存器，检查各字段的范围
+fn decode(raw: [u8; 6], status_b: u8) -> Option<DateTime> {
+    let decode = |value: u8| {
+        if status_b & STATUS_B_BINARY != 0 {
+            Some(value)
+        } else if value >> 4 <= 9 && value & 0x0F <= 9 {
+            Some((value >> 4) * 10 + (value & 0x0F))
+        } else {
+            None
+        }
+    };
+
+    let [second, minute, hour, day, month, year] = raw;
+    let hour = if status_b & STATUS_B_24_HOUR != 0 {
+        decode(hour)?
+    } else {
+        // 12小时制下，12点表示0点或正午
+        let pm = hour & HOUR_PM != 0;
+        let hour = decode(hour & !HOUR_PM)?;
+        if !(1..=12).contains(&hour) {
+            return None;
+        }
+        if pm { hour % 12 + 12 } else { hour % 12 }
+    };
+
+    let year = 2000 + decode(year)? as i32;
+    let month = decode(month)?;
+    let day = decode(day)?;
+    let (minute, second) = (decode(minute)?, decode(second)?);
+    if year > 2099
+        || !(1..=12).contains(&month)
+        || day == 0
+        || day > days_in_month(year, month)
+        || hour >= 24
+        || minute >= 60
+        || second >= 60
+    {
+        return None;
+    }
+
+    Some(DateTime {
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+        nanosecond: 0,
+    })
+}
+
+fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// 等待更新结束后读取时间寄存器
+fn read_registers() -> [u8; 6] {
+    while read_register(REG_STATUS_A) & STATUS_A_UPDATING != 0 {
+        spin_loop();
+    }
+    [
+        REG_SECOND, REG_MINUTE, REG_HOUR, REG_DAY, REG_MONTH, REG_YEAR,
+    ]
+    .map(read_register)
+}
+
+fn read_register(register: u8) -> u8 {
+    let value: u8;
+    // 地址端口的第7位用于屏蔽NMI，这里保持为0
+    unsafe {
+        asm!(
+            "out dx, al",
+            in("dx") CMOS_ADDRESS,
+            in("al") register & 0x7F,
+            options(nomem, nostack, preserves_flags)
+        );
+        asm!(
+            "in al, dx",
+            in("dx") CMOS_DATA,
+            out("al") value,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn decode_bcd() {
+        // 2024-02-29 23:59:58，BCD格式，24小时制
+        let raw = [0x58, 0x59, 0x23, 0x29, 0x02, 0x24];
+        assert_eq!(
+            decode(raw, STATUS_B_24_HOUR),
+            Some(DateTime {
+                year: 2024,
+                month: 2,
+                day: 29,
+                hour: 23,
+                minute: 59,
+                second: 58,
+                nanosecond: 0,
+            })
+        );
+
+        // 12小时制下的午夜与正午
+        let midnight = [0x00, 0x00, 0x12, 0x01, 0x01, 0x00];
+        assert_eq!(decode(midnight, 0).unwrap().hour, 0);
+        let noon = [0x00, 0x00, 0x12 | HOUR_PM, 0x01, 0x01, 0x00];
+        assert_eq!(decode(noon, 0).unwrap().hour, 12);
+    }
+
+    #[test_case]
+    fn decode_rejects_out_of_range() {
+        // 非BCD数字
+        assert!(decode([0x5A, 0, 0, 1, 1, 0], STATUS_B_24_HOUR).is_none());
+        // 分钟、小时、月份越界
+        assert!(decode([0, 0x60, 0, 1, 1, 0], STATUS_B_24_HOUR).is_none());
+        assert!(decode([0, 0, 0x24, 1, 1, 0], STATUS_B_24_HOUR).is_none());
+        assert!(decode([0, 0, 0, 1, 0x13, 0], STATUS_B_24_HOUR).is_none());
+        // 非闰年的2月29日
+        assert!(decode([0, 0, 0, 0x29, 0x02, 0x23], STATUS_B_24_HOUR).is_none());
+        // 二进制格式下同样检查范围
+        assert!(decode([0, 0, 0, 31, 4, 24], STATUS_B_24_HOUR | STATUS_B_BINARY).is_none());
+    }
+}
@@ -0,0 +1,111 @@
+use core::{
+    arch::asm,
+    fmt::{Arguments, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::sync::{int::IrqGuard, spin::SpinLock};
+
+/// COM1端口基地址
+const COM1: u16 = 0x3F8;
+
+// 寄存器偏移
+const REG_DATA: u16 = 0;
+const REG_INTERRUPT_ENABLE: u16 = 1;
+const REG_FIFO_CONTROL: u16 = 2;
+const REG_LINE_CONTROL: u16 = 3;
+const REG_MODEM_CONTROL: u16 = 4;
+const REG_LINE_STATUS: u16 = 5;
+
+/// 发送缓冲区为空
+const LINE_STATUS_TRANSMIT_EMPTY: u8 = 1 << 5;
+
+/// 串口是否可用，初始化时通过回环测试判断
+static AVAILABLE: AtomicBool = AtomicBool::new(false);
+/// 保证多核或中断中的输出不会交错
+static LOCK: SpinLock<()> = SpinLock::new(());
+
+/// 初始化COM1串口，115200波特率，8数据位，无校验，1停止位
+///
+/// 串口仅用于输出，不开启中断。串口不存在时，后续输出会被忽略
+pub fn init() {
+    unsafe {
+        outb(COM1 + REG_INTERRUPT_ENABLE, 0x00);
+        // 设置波特率除数为1（115200）
+        outb(COM1 + REG_LINE_CONTROL, 0x80);
+        outb(COM1 + REG_DATA, 0x01);
+        outb(COM1 + REG_INTERRUPT_ENABLE, 0x00);
+        // 8N1
+        outb(COM1 + REG_LINE_CONTROL, 0x03);
+        // 启用并清空FIFO
+        outb(COM1 + REG_FIFO_CONTROL, 0xC7);
+
+        // 回环测试
+        outb(COM1 + REG_MODEM_CONTROL, 0x1E);
+        outb(COM1 + REG_DATA, 0xAE);
+        if inb(COM1 + REG_DATA) != 0xAE {
+            return;
+        }
+
+        // 恢复正常模式
+        outb(COM1 + REG_MODEM_CONTROL, 0x0F);
+    }
+    AVAILABLE.store(true, Ordering::Release);
+}
+
+/// 向串口写入数据
+pub fn write(bytes: &[u8]) {
+    if !AVAILABLE.load(Ordering::Acquire) {
+        return;
+    }
+
+    let _guard = IrqGuard::cli();
+    let _lock = LOCK.lock();
+    for &byte in bytes {
+        unsafe {
+            while inb(COM1 + REG_LINE_STATUS) & LINE_STATUS_TRANSMIT_EMPTY == 0 {
+                core::hint::spin_loop();
+            }
+            outb(COM1 + REG_DATA, byte);
+        }
+    }
+}
+
+/// 向串口写入格式化文本，不依赖堆
+pub struct SerialWriter;
+
+impl Write for SerialWriter {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        write(s.as_bytes());
+        Ok(())
+    }
+}
+
+#[doc(hidden)]
+pub fn _write_fmt(args: Arguments<'_>) {
+    _ = SerialWriter.write_fmt(args);
+}
+
+unsafe fn outb(port: u16, value: u8) {
+    unsafe {
+        asm!(
+            "out dx, al",
+            in("dx") port,
+            in("al") value,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+}
+
+unsafe fn inb(port: u16) -> u8 {
+    let value: u8;
+    unsafe {
+        asm!(
+            "in al, dx",
+            in("dx") port,
+            out("al") value,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    value
+}
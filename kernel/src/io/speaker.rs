@@ -0,0 +1,188 @@
+use core::{arch::asm, time::Duration};
+
+use async_locks::{
+    channel::spsc::{self, TrySendError},
+    once::OnceLock,
+};
+
+use crate::{
+    multitask,
+    sync::{int::IrqGuard, spin::SpinLock},
+};
+
+/// PIT的输入时钟频率
+const PIT_FREQUENCY: u32 = 1193182;
+/// PIT的命令端口
+const PIT_COMMAND: u16 = 0x43;
+/// PIT通道2的数据端口，输出连接到扬声器
+const PIT_CHANNEL2: u16 = 0x42;
+/// 通道2，先低后高字节写入计数值，模式3（方波）
+const PIT_CHANNEL2_SQUARE_WAVE: u8 = 0xB6;
+/// 系统控制端口B，第0位为通道2的门控，第1位连接扬声器
+const SPEAKER_PORT: u16 = 0x61;
+const SPEAKER_ENABLE: u8 = 0b11;
+
+/// 可发声的频率范围（Hz）
+pub const FREQUENCY_RANGE: core::ops::RangeInclusive<u32> = 20..=20000;
+/// 单次发声的最长时间
+pub const MAX_DURATION: Duration = Duration::from_secs(10);
+/// 等待播放的最大数量
+const QUEUE_SIZE: usize = 32;
+/// 队列已满时，[beep_wait] 再次尝试加入队列的间隔
+const RETRY_INTERVAL: Duration = Duration::from_millis(10);
+/// 设备文件中每次发声的长度：小端序的频率（Hz，u32）与时长（毫秒，u32）
+pub const RECORD_SIZE: usize = 8;
+
+static QUEUE: OnceLock<SpinLock<spsc::Sender<Beep>>> = OnceLock::new();
+
+/// 一次发声，频率为0时保持静音，用于两次发声之间的间隔
+#[derive(Debug, Clone, Copy)]
+pub struct Beep {
+    // 频率（Hz）
+    pub frequency: u32,
+    pub duration: Duration,
+}
+
+/// 添加发声失败原因
+#[derive(Debug)]
+pub enum BeepError {
+    /// 频率或时长超出范围
+    BadArgument,
+    /// 等待播放的发声过多
+    QueueFull,
+}
+
+/// 创建发声队列并启动播放任务
+///
+/// PIT通道2在启动时还用于校准TSC（见 [crate::time::clocksource]），须在其之后调用
+pub fn init() {
+    let (sender, receiver) = spsc::channel(QUEUE_SIZE);
+    if QUEUE.set(SpinLock::new(sender)).is_err() {
+        panic!("codebug: speaker initialized twice");
+    }
+    multitask::async_rt::spawn(play(receiver));
+}
+
+/// 检查发声的频率与时长是否在范围内
+pub fn validate(beep: Beep) -> Result<(), BeepError> {
+    if (beep.frequency != 0 && !FREQUENCY_RANGE.contains(&beep.frequency))
+        || beep.duration > MAX_DURATION
+    {
+        return Err(BeepError::BadArgument);
+    }
+    Ok(())
+}
+
+/// 添加发声到队列，不等待播放完成
+pub fn beep(beep: Beep) -> Result<(), BeepError> {
+    validate(beep)?;
+    let queue = QUEUE.get().expect("codebug: speaker not initialized");
+    let _guard = IrqGuard::cli();
+    match queue.lock().try_send(beep) {
+        Ok(()) => Ok(()),
+        Err(TrySendError::BufferFull(_)) => Err(BeepError::QueueFull),
+        // 播放任务持有接收端，不会退出
+        Err(TrySendError::ReceiverLost(_)) => panic!("codebug: speaker task exited"),
+    }
+}
+
+/// 添加发声到队列，队列已满时等待至有空位，不等待播放完成
+pub async fn beep_wait(beep: Beep) -> Result<(), BeepError> {
+    loop {
+        match self::beep(beep) {
+            Err(BeepError::QueueFull) => multitask::async_task::sleep(RETRY_INTERVAL).await,
+            result => return result,
+        }
+    }
+}
+
+/// 解析写入设备文件的内容，每 [RECORD_SIZE] 字节为一次发声，长度不是其整数倍时返回None
+pub fn parse_records(buf: &[u8]) -> Option<impl Iterator<Item = Beep> + Clone + '_> {
+    if !buf.len().is_multiple_of(RECORD_SIZE) {
+        return None;
+    }
+    Some(buf.chunks_exact(RECORD_SIZE).map(|record| Beep {
+        frequency: u32::from_le_bytes(record[..4].try_into().unwrap()),
+        duration: Duration::from_millis(u32::from_le_bytes(record[4..].try_into().unwrap()) as u64),
+    }))
+}
+
+/// 依次播放队列中的发声
+async fn play(mut receiver: spsc::Receiver<Beep>) {
+    // 发送端由静态变量持有，不会被释放
+    while let Ok(beep) = receiver.recv().await {
+        if beep.frequency != 0 {
+            start(beep.frequency);
+        }
+        multitask::async_task::sleep(beep.duration).await;
+        stop();
+    }
+}
+
+/// 以指定频率开始发声
+fn start(frequency: u32) {
+    let divisor = (PIT_FREQUENCY / frequency).clamp(1, u16::MAX as u32) as u16;
+    unsafe {
+        outb(PIT_COMMAND, PIT_CHANNEL2_SQUARE_WAVE);
+        outb(PIT_CHANNEL2, (divisor & 0xff) as u8);
+        outb(PIT_CHANNEL2, (divisor >> 8) as u8);
+        let control = inb(SPEAKER_PORT);
+        outb(SPEAKER_PORT, control | SPEAKER_ENABLE);
+    }
+}
+
+/// 停止发声
+fn stop() {
+    unsafe {
+        let control = inb(SPEAKER_PORT);
+        outb(SPEAKER_PORT, control & !SPEAKER_ENABLE);
+    }
+}
+
+unsafe fn outb(port: u16, value: u8) {
+    unsafe {
+        asm!(
+            "out dx, al",
+            in("dx") port,
+            in("al") value,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+}
+
+unsafe fn inb(port: u16) -> u8 {
+    let value: u8;
+    unsafe {
+        asm!(
+            "in al, dx",
+            in("dx") port,
+            out("al") value,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_parse_records() {
+        let mut buf = [0u8; RECORD_SIZE * 2];
+        buf[..4].copy_from_slice(&440u32.to_le_bytes());
+        buf[4..8].copy_from_slice(&200u32.to_le_bytes());
+        let beeps: alloc::vec::Vec<_> = parse_records(&buf).unwrap().collect();
+        assert_eq!(beeps.len(), 2);
+        assert_eq!(beeps[0].frequency, 440);
+        assert_eq!(beeps[0].duration, Duration::from_millis(200));
+        assert_eq!(beeps[1].frequency, 0);
+        assert!(validate(beeps[0]).is_ok());
+        assert!(parse_records(&buf[..5]).is_none());
+        let high = Beep {
+            frequency: 30000,
+            duration: Duration::ZERO,
+        };
+        assert!(validate(high).is_err());
+    }
+}
@@ -0,0 +1,441 @@
+use alloc::{string::String, sync::Arc, vec::Vec};
+use async_locks::mutex::Mutex;
+use filesystem::{
+    device::{
+        BlockDevice,
+        crypt::{CryptDevice, CryptError},
+        file::FileBlockDevice,
+    },
+    fs::{
+        FileSystem, FileSystemError,
+        fat32::{self, Fat32FileSystem, LocalTime},
+        permission::PermissionTable,
+        ramfs::RamFileSystem,
+    },
+    path::{Path, PathBuf},
+};
+use try_alloc::error::AllocError;
+
+use crate::{
+    io::disk,
+    kprintln, multitask,
+    sync::{int::IrqGuard, spin::SpinLock},
+    time,
+};
+
+/// 临时文件系统的挂载路径
+const TMPFS_PATH: &str = "/tmp";
+/// 临时文件系统的容量
+const TMPFS_CAPACITY: u64 = 16 * 1024 * 1024;
+/// 镜像文件作为块设备时的块大小
+const IMAGE_BLOCK_SIZE: u64 = 512;
+
+/// 挂载表，按挂载路径的深度从深到浅排列，查找时第一个匹配的即为最长前缀
+static MOUNTS: SpinLock<Vec<MountPoint>> = SpinLock::new(Vec::new());
+
+struct MountPoint {
+    path: PathBuf,
+    fs: Arc<dyn FileSystem>,
+    // 文件系统所在的块设备名称，见 [`disk::DiskDevice`]
+    source: Option<String>,
+    // 挂载镜像文件时的loop设备
+    loop_device: Option<LoopDevice>,
+    // 文件权限表，首次检查权限时从文件系统读取，见 [`crate::io::permission`]
+    permissions: Arc<PermissionStore>,
+}
+
+/// 文件系统的权限表，尚未读取时为None
+pub type PermissionStore = Mutex<Option<PermissionTable>>;
+
+struct LoopDevice {
+    // 文件系统卸载后需关闭
+    device: Arc<FileBlockDevice>,
+    // 镜像文件所在的文件系统，持有引用使其在镜像卸载前无法被卸载
+    _host: Arc<dyn FileSystem>,
+}
+
+#[derive(Debug)]
+pub enum MountError {
+    /// 块设备不存在，或镜像文件不在任何已挂载的文件系统中
+    NotFound,
+    /// 目标路径已挂载文件系统，或块设备已被挂载
+    AlreadyMounted,
+    /// 打开镜像文件失败
+    FileSystem(FileSystemError),
+    /// 块设备或镜像文件不是有效的FAT32文件系统
+    InvalidFormat(fat32::MountError),
+    /// 加密卷无法打开，如口令错误
+    Crypt(CryptError),
+}
+
+#[derive(Debug)]
+pub enum UnmountError {
+    /// 路径上没有挂载文件系统
+    NotMounted,
+    /// 文件系统中仍有打开的文件，或正在被使用
+    Busy,
+    /// 文件系统卸载失败，文件系统已从挂载表中移除
+    FileSystem(FileSystemError),
+}
+
+/// 将文件系统挂载到指定路径
+///
+/// 挂载路径无需在其他文件系统中存在。如果该路径已挂载文件系统，则替换之，并返回原文件系统，
+/// 原文件系统由调用方负责卸载
+pub fn mount(path: PathBuf, fs: Arc<dyn FileSystem>) -> Option<Arc<dyn FileSystem>> {
+    replace(MountPoint {
+        path,
+        fs,
+        source: None,
+        loop_device: None,
+        permissions: Arc::new(Mutex::new(None)),
+    })
+}
+
+/// 将块设备上的文件系统挂载到指定路径，行为与 [`mount`] 相同
+///
+/// `source`为块设备名称，用于查询块设备的挂载位置，并阻止同一块设备被重复挂载
+pub fn mount_with_source(
+    path: PathBuf,
+    fs: Arc<dyn FileSystem>,
+    source: String,
+) -> Option<Arc<dyn FileSystem>> {
+    replace(MountPoint {
+        path,
+        fs,
+        source: Some(source),
+        loop_device: None,
+        permissions: Arc::new(Mutex::new(None)),
+    })
+}
+
+fn replace(mount_point: MountPoint) -> Option<Arc<dyn FileSystem>> {
+    let _guard = IrqGuard::cli();
+    let mut mounts = MOUNTS.lock();
+    if let Some(existing) = mounts
+        .iter_mut()
+        .find(|existing| existing.path == mount_point.path)
+    {
+        let old = core::mem::replace(existing, mount_point);
+        return Some(old.fs);
+    }
+
+    insert(&mut mounts, mount_point);
+    None
+}
+
+/// 读取块设备上的FAT32文件系统，顺序读取文件时使用内核的异步运行时在后台预读
+pub async fn mount_fat32(
+    device: Arc<dyn BlockDevice>,
+) -> Result<Fat32FileSystem, fat32::MountError> {
+    let fs = Fat32FileSystem::mount(device).await?;
+    fs.set_spawner(Arc::new(multitask::async_rt::spawn)).await;
+    fs.set_clock(Arc::new(|| {
+        let now = time::local_time();
+        LocalTime {
+            year: now.year.clamp(0, u16::MAX as i32) as u16,
+            month: now.month,
+            day: now.day,
+            hour: now.hour,
+            minute: now.minute,
+            second: now.second,
+            millisecond: (now.nanosecond / 1_000_000) as u16,
+        }
+    }))
+    .await;
+    Ok(fs)
+}
+
+/// 将块设备作为FAT32文件系统挂载到指定路径
+///
+/// 与 [`mount`] 不同，目标路径已挂载文件系统，或块设备（及其所在磁盘或其中的分区）已被挂载时返回错误
+pub async fn mount_device(name: &str, path: PathBuf) -> Result<(), MountError> {
+    let device = disk::find(name).ok_or(MountError::NotFound)?;
+    if is_source_mounted(name) {
+        return Err(MountError::AlreadyMounted);
+    }
+    let fs = mount_fat32(device.device)
+        .await
+        .map_err(MountError::InvalidFormat)?;
+
+    try_insert(MountPoint {
+        path,
+        fs: Arc::new(fs),
+        source: Some(device.name),
+        loop_device: None,
+        permissions: Arc::new(Mutex::new(None)),
+    })
+    .await
+}
+
+/// 以口令打开块设备上的加密卷，并将其中的FAT32文件系统挂载到指定路径
+///
+/// 冲突检查同 [`mount_device`]。派生密钥耗时较长，取决于加密卷头部中的迭代次数
+pub async fn mount_encrypted(
+    name: &str,
+    passphrase: &[u8],
+    path: PathBuf,
+) -> Result<(), MountError> {
+    let device = disk::find(name).ok_or(MountError::NotFound)?;
+    if is_source_mounted(name) {
+        return Err(MountError::AlreadyMounted);
+    }
+    let crypt = CryptDevice::open(device.device, passphrase)
+        .await
+        .map_err(MountError::Crypt)?;
+    let fs = mount_fat32(Arc::new(crypt))
+        .await
+        .map_err(MountError::InvalidFormat)?;
+
+    try_insert(MountPoint {
+        path,
+        fs: Arc::new(fs),
+        source: Some(device.name),
+        loop_device: None,
+        permissions: Arc::new(Mutex::new(None)),
+    })
+    .await
+}
+
+/// 将镜像文件作为FAT32文件系统挂载到指定路径（loop挂载）
+///
+/// 镜像文件本身需位于已挂载的文件系统中。与 [`mount`] 不同，目标路径已挂载文件系统时返回错误
+pub async fn mount_image(image: Path<'_>, path: PathBuf) -> Result<(), MountError> {
+    let (host, relative) = resolve(&image).ok_or(MountError::NotFound)?;
+    let device = FileBlockDevice::open(&*host, relative, IMAGE_BLOCK_SIZE)
+        .await
+        .map_err(MountError::FileSystem)?;
+    let device = Arc::new(device);
+    let fs = match mount_fat32(device.clone()).await {
+        Ok(fs) => fs,
+        Err(error) => {
+            let _ = device.close().await;
+            return Err(MountError::InvalidFormat(error));
+        }
+    };
+
+    try_insert(MountPoint {
+        path,
+        fs: Arc::new(fs),
+        source: None,
+        loop_device: Some(LoopDevice {
+            device,
+            _host: host,
+        }),
+        permissions: Arc::new(Mutex::new(None)),
+    })
+    .await
+}
+
+/// 插入挂载表，目标路径已挂载文件系统或块设备已被挂载时卸载新文件系统并返回错误
+async fn try_insert(mount_point: MountPoint) -> Result<(), MountError> {
+    let rejected = {
+        let _guard = IrqGuard::cli();
+        let mut mounts = MOUNTS.lock();
+        let conflict = mounts.iter().any(|existing| {
+            existing.path == mount_point.path
+                || matches!(
+                    (&existing.source, &mount_point.source),
+                    (Some(a), Some(b)) if disk::overlaps(a, b)
+                )
+        });
+        if conflict {
+            Some(mount_point)
+        } else {
+            insert(&mut mounts, mount_point);
+            None
+        }
+    };
+    match rejected {
+        Some(mount_point) => {
+            let _ = release(mount_point).await;
+            Err(MountError::AlreadyMounted)
+        }
+        None => Ok(()),
+    }
+}
+
+/// 按挂载路径的深度插入挂载表
+fn insert(mounts: &mut Vec<MountPoint>, mount_point: MountPoint) {
+    let depth = mount_point.path.as_path().iter().count();
+    let index = mounts
+        .iter()
+        .position(|existing| existing.path.as_path().iter().count() < depth)
+        .unwrap_or(mounts.len());
+    mounts.insert(index, mount_point);
+}
+
+/// 卸载指定路径上的文件系统
+///
+/// 文件系统中仍有打开的文件（包括以其中的镜像文件挂载的文件系统）时返回 [`UnmountError::Busy`]
+pub async fn unmount(path: &PathBuf) -> Result<(), UnmountError> {
+    let mount_point = {
+        let _guard = IrqGuard::cli();
+        let mut mounts = MOUNTS.lock();
+        let index = mounts
+            .iter()
+            .position(|mount_point| mount_point.path == *path)
+            .ok_or(UnmountError::NotMounted)?;
+        // 打开的文件与正在进行的操作均持有文件系统的引用，引用只在持有挂载表锁时从挂载表复制，
+        // 因此在锁内检查并移除不会与新的访问竞争
+        if Arc::strong_count(&mounts[index].fs) > 1 {
+            return Err(UnmountError::Busy);
+        }
+        mounts.remove(index)
+    };
+    release(mount_point).await.map_err(UnmountError::FileSystem)
+}
+
+/// 卸载文件系统，并关闭其loop设备
+async fn release(mount_point: MountPoint) -> Result<(), FileSystemError> {
+    let result = mount_point.fs.unmount().await;
+    if let Some(loop_device) = mount_point.loop_device {
+        loop_device.device.close().await?;
+    }
+    result
+}
+
+/// 块设备的挂载路径，未挂载时返回None
+pub fn mount_path_of(source: &str) -> Option<PathBuf> {
+    let _guard = IrqGuard::cli();
+    MOUNTS
+        .lock()
+        .iter()
+        .find(|mount_point| mount_point.source.as_deref() == Some(source))
+        .map(|mount_point| mount_point.path.clone())
+}
+
+/// 列出全部挂载点的挂载路径与来源，来源为块设备名称，loop挂载时为`loop`，不来自块设备时为None
+pub fn list_mounts() -> Vec<(PathBuf, Option<String>)> {
+    let _guard = IrqGuard::cli();
+    MOUNTS
+        .lock()
+        .iter()
+        .map(|mount_point| {
+            let source = match &mount_point.loop_device {
+                Some(_) => Some(String::from("loop")),
+                None => mount_point.source.clone(),
+            };
+            (mount_point.path.clone(), source)
+        })
+        .collect()
+}
+
+/// 块设备（及其所在磁盘或其中的分区）是否已挂载
+fn is_source_mounted(name: &str) -> bool {
+    let _guard = IrqGuard::cli();
+    MOUNTS.lock().iter().any(|mount_point| {
+        mount_point
+            .source
+            .as_deref()
+            .is_some_and(|source| disk::overlaps(source, name))
+    })
+}
+
+/// 查找路径所在的文件系统
+///
+/// 返回挂载于最长匹配前缀上的文件系统，以及路径在该文件系统中的路径。没有匹配的挂载点时返回None
+pub fn resolve<'p>(path: &'p Path<'_>) -> Option<(Arc<dyn FileSystem>, Path<'p>)> {
+    let _guard = IrqGuard::cli();
+    MOUNTS.lock().iter().find_map(|mount_point| {
+        path.strip_prefix(mount_point.path.as_path())
+            .map(|relative| (mount_point.fs.clone(), relative))
+    })
+}
+
+/// 查找路径所在的文件系统及其权限表，行为与 [`resolve`] 相同
+pub fn resolve_permissions<'p>(
+    path: &'p Path<'_>,
+) -> Option<(Arc<dyn FileSystem>, Arc<PermissionStore>, Path<'p>)> {
+    let _guard = IrqGuard::cli();
+    MOUNTS.lock().iter().find_map(|mount_point| {
+        path.strip_prefix(mount_point.path.as_path())
+            .map(|relative| {
+                (
+                    mount_point.fs.clone(),
+                    mount_point.permissions.clone(),
+                    relative,
+                )
+            })
+    })
+}
+
+/// 丢弃挂载点缓存的权限表，下次检查权限时重新从文件系统读取
+pub async fn reload_permissions(path: &PathBuf) {
+    let permissions = {
+        let _guard = IrqGuard::cli();
+        MOUNTS
+            .lock()
+            .iter()
+            .find(|mount_point| mount_point.path == *path)
+            .map(|mount_point| mount_point.permissions.clone())
+    };
+    if let Some(permissions) = permissions {
+        *permissions.lock().await = None;
+    }
+}
+
+/// 读取整个文件，用于读取内核使用的配置文件
+///
+/// 文件长度超过max_size时返回 [`FileSystemError::FileTooLarge`]
+pub async fn read_file(path: &Path<'_>, max_size: usize) -> Result<Vec<u8>, FileSystemError> {
+    let Some((fs, path)) = resolve(path) else {
+        return Err(FileSystemError::FileNotFound);
+    };
+    let mut file = fs.open_file(path).await?;
+    let mut content = Vec::new();
+    let mut buffer = [0; 512];
+    let result = loop {
+        let count = match file.read(&mut buffer).await {
+            Ok(count) => count as usize,
+            Err(error) => break Err(error),
+        };
+        if count == 0 {
+            break Ok(());
+        }
+        if content.len() + count > max_size {
+            break Err(FileSystemError::FileTooLarge);
+        }
+        content.extend_from_slice(&buffer[..count]);
+    };
+    file.close().await?;
+    result.map(|_| content)
+}
+
+/// 丢弃loop设备缓存中未修改的块，作为内存不足时的回收函数，见 [`crate::memory::reclaim`]
+pub fn shrink_caches() -> usize {
+    let Some(mounts) = MOUNTS.try_lock() else {
+        return 0;
+    };
+    mounts
+        .iter()
+        .filter_map(|mount_point| mount_point.loop_device.as_ref())
+        .map(|loop_device| loop_device.device.shrink())
+        .sum()
+}
+
+/// 在 [`TMPFS_PATH`] 挂载内存文件系统，用于存放临时文件
+pub fn mount_tmpfs() -> Result<(), AllocError> {
+    let fs = RamFileSystem::new(TMPFS_CAPACITY)?;
+    let path = PathBuf::from_str(TMPFS_PATH).expect("codebug: invalid tmpfs path");
+    mount(path, Arc::new(fs));
+    Ok(())
+}
+
+/// 卸载所有文件系统，用于关机与重启前将缓存写回磁盘
+///
+/// 镜像文件挂载的文件系统先于其他文件系统卸载，以便将缓存写回镜像文件所在的文件系统。
+/// 卸载失败的文件系统会被跳过，不影响其他文件系统的卸载
+pub async fn unmount_all() {
+    let mut mounts = {
+        let _guard = IrqGuard::cli();
+        core::mem::take(&mut *MOUNTS.lock())
+    };
+    mounts.sort_by_key(|mount_point| mount_point.loop_device.is_none());
+    for mount_point in mounts {
+        let path = mount_point.path.clone();
+        if let Err(error) = release(mount_point).await {
+            kprintln!("failed to unmount {path:?}: {error:?}");
+        }
+    }
+}
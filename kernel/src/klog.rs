@@ -0,0 +1,199 @@
+use core::{
+    fmt::{self, Arguments, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use alloc::{collections::btree_map::BTreeMap, string::String};
+
+use crate::{
+    cmdline::{self, LogLevel},
+    display::vga_text,
+    sync::{int::IrqGuard, spin::SpinLock},
+    time::clocksource,
+};
+
+/// 按级别与目标输出内核日志
+///
+/// 级别为error、warn、info、debug之一，目标为产生日志的子系统名，如 "disk"：
+///
+/// ```ignore
+/// klog!(warn, "disk", "port {port} reset failed");
+/// ```
+///
+/// 日志是否输出由目标的过滤级别决定，未设置过滤级别的目标使用启动选项`loglevel`。
+/// 屏幕上的日志按级别着色，串口输出不含颜色
+#[macro_export]
+macro_rules! klog {
+    (error, $target:expr, $($arg:tt)*) => {
+        $crate::klog!(@$crate::cmdline::LogLevel::Error, $target, $($arg)*)
+    };
+    (warn, $target:expr, $($arg:tt)*) => {
+        $crate::klog!(@$crate::cmdline::LogLevel::Warn, $target, $($arg)*)
+    };
+    (info, $target:expr, $($arg:tt)*) => {
+        $crate::klog!(@$crate::cmdline::LogLevel::Info, $target, $($arg)*)
+    };
+    (debug, $target:expr, $($arg:tt)*) => {
+        $crate::klog!(@$crate::cmdline::LogLevel::Debug, $target, $($arg)*)
+    };
+    (@$level:expr, $target:expr, $($arg:tt)*) => {
+        if $crate::klog::enabled($level, $target) {
+            $crate::klog::_klog($level, $target, format_args!($($arg)*));
+        }
+    };
+}
+
+/// 各目标的过滤级别，None表示屏蔽该目标的全部日志
+static FILTERS: SpinLock<BTreeMap<String, Option<LogLevel>>> = SpinLock::new(BTreeMap::new());
+/// 是否设置了任何过滤级别，没有时输出日志无需查询过滤表
+static HAS_FILTERS: AtomicBool = AtomicBool::new(false);
+/// 最近输出的日志，panic时写入崩溃转储
+static RECENT: SpinLock<RecentLog> = SpinLock::new(RecentLog::new());
+
+/// 最近日志的环形缓冲区大小
+const RECENT_LOG_SIZE: usize = 16 * 1024;
+
+/// 最近日志的环形缓冲区，写满后覆盖最早的内容
+struct RecentLog {
+    buffer: [u8; RECENT_LOG_SIZE],
+    /// 累计写入的字节数，下一个字节写入 `written % RECENT_LOG_SIZE` 处
+    written: usize,
+}
+
+impl RecentLog {
+    const fn new() -> Self {
+        Self {
+            buffer: [0; RECENT_LOG_SIZE],
+            written: 0,
+        }
+    }
+}
+
+impl Write for RecentLog {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for &byte in s.as_bytes() {
+            self.buffer[self.written % RECENT_LOG_SIZE] = byte;
+            self.written += 1;
+        }
+        Ok(())
+    }
+}
+
+/// 指定级别与目标的日志是否需要输出
+pub fn enabled(level: LogLevel, target: &str) -> bool {
+    if HAS_FILTERS.load(Ordering::Relaxed) {
+        let _guard = IrqGuard::cli();
+        if let Some(filter) = FILTERS.lock().get(target) {
+            return filter.is_some_and(|max| level <= max);
+        }
+    }
+    cmdline::log_enabled(level)
+}
+
+/// 设置目标的过滤级别
+///
+/// filter为`Some(None)`时屏蔽该目标的全部日志，为None时清除过滤级别，恢复使用启动选项`loglevel`
+pub fn set_filter(target: &str, filter: Option<Option<LogLevel>>) {
+    let _guard = IrqGuard::cli();
+    let mut filters = FILTERS.lock();
+    match filter {
+        Some(filter) => {
+            filters.insert(String::from(target), filter);
+        }
+        None => {
+            filters.remove(target);
+        }
+    }
+    HAS_FILTERS.store(!filters.is_empty(), Ordering::Relaxed);
+}
+
+#[doc(hidden)]
+pub fn _klog(level: LogLevel, target: &str, args: Arguments<'_>) {
+    write_log(level, target, args, false);
+}
+
+/// 在中断处理中输出内核日志，不等待任何锁
+///
+/// 被打断的代码可能正持有过滤表或控制台的锁，等待将导致死锁。
+/// 过滤表被占用时按启动选项`loglevel`判断，控制台被占用时只输出到串口
+pub fn klog_from_irq(level: LogLevel, target: &str, args: Arguments<'_>) {
+    let enabled = match FILTERS.try_lock() {
+        Some(filters) if HAS_FILTERS.load(Ordering::Relaxed) => match filters.get(target) {
+            Some(filter) => filter.is_some_and(|max| level <= max),
+            None => cmdline::log_enabled(level),
+        },
+        _ => cmdline::log_enabled(level),
+    };
+    if enabled {
+        write_log(level, target, args, true);
+    }
+}
+
+fn write_log(level: LogLevel, target: &str, args: Arguments<'_>, from_irq: bool) {
+    let (label, style) = match level {
+        LogLevel::Error => ("ERROR", 0x0C), // 黑底亮红字
+        LogLevel::Warn => ("WARN", 0x0E),   // 黑底黄字
+        LogLevel::Info => ("INFO", 0x07),   // 黑底白字
+        LogLevel::Debug => ("DEBUG", 0x08), // 黑底灰字
+    };
+    // 以启动后经过的秒数作为时间戳
+    let now = clocksource::now_ns();
+    let (seconds, micros) = (now / 1_000_000_000, now % 1_000_000_000 / 1000);
+    let line = format_args!("[{seconds:5}.{micros:06} {label} {target}] {args}\n");
+    if from_irq {
+        vga_text::_try_kprint_with_style(style, line);
+    } else {
+        vga_text::_kprint_with_style(style, line);
+    }
+
+    // 缓冲区被占用时（如格式化参数时触发的异常再次输出日志）丢弃本条，避免死锁
+    let _guard = IrqGuard::cli();
+    if let Some(mut recent) = RECENT.try_lock() {
+        _ = writeln!(recent, "[{seconds:5}.{micros:06} {label} {target}] {args}");
+    }
+}
+
+/// 读取自累计位置cursor以来写入的日志，返回 (复制的字节数, 已被覆盖而丢失的字节数)
+///
+/// cursor为此前已读取的累计字节数，初始为0。读取后cursor应增加丢失与复制的字节数之和
+pub fn read_since(cursor: usize, buffer: &mut [u8]) -> (usize, usize) {
+    let _guard = IrqGuard::cli();
+    let recent = RECENT.lock();
+    let lost = (recent.written - cursor).saturating_sub(RECENT_LOG_SIZE);
+    let start = cursor + lost;
+    let len = (recent.written - start).min(buffer.len());
+    for (index, byte) in buffer[..len].iter_mut().enumerate() {
+        *byte = recent.buffer[(start + index) % RECENT_LOG_SIZE];
+    }
+    (len, lost)
+}
+
+/// 累计写入的日志字节数
+pub fn written() -> usize {
+    let _guard = IrqGuard::cli();
+    RECENT.lock().written
+}
+
+/// 将最近的日志复制到buffer，从完整的一行开始，返回复制的字节数
+///
+/// 不进行堆分配，可在panic时调用。缓冲区被占用时（如panic打断了日志输出）返回0
+pub fn copy_recent(buffer: &mut [u8]) -> usize {
+    let Some(recent) = RECENT.try_lock() else {
+        return 0;
+    };
+    let len = recent.written.min(RECENT_LOG_SIZE).min(buffer.len());
+    let start = recent.written - len;
+    for (index, byte) in buffer[..len].iter_mut().enumerate() {
+        *byte = recent.buffer[(start + index) % RECENT_LOG_SIZE];
+    }
+    // 没有从第一个字节开始复制时，跳过开头不完整的一行
+    let skip = match start {
+        0 => 0,
+        _ => buffer[..len]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .map_or(len, |index| index + 1),
+    };
+    buffer.copy_within(skip..len, 0);
+    len - skip
+}
@@ -0,0 +1,191 @@
+//! 持久化的内核日志
+//!
+//! 内核日志先写入 [klog] 的环形缓冲区，再由后台任务追加到 [LOG_PATH]，以便排查用户连接控制台之前发生的问题。
+//! 后台任务采用延迟写入：未写入的日志达到 [FLUSH_THRESHOLD] 字节，或距上次写入超过 [FLUSH_INTERVAL] 时写入一次；
+//! 关机与重启前也会写入一次。
+//!
+//! 日志文件超过 [MAX_FILE_SIZE] 时轮转：`kernel.log` 改名为 `kernel.log.1`，原有的 `kernel.log.<n>`
+//! 改名为 `kernel.log.<n+1>`，最多保留 [ROTATE_COUNT] 个旧文件。
+//!
+//! 文件系统不可用（如只读或已满）时，日志保留在环形缓冲区中，并以指数退避的间隔重试。
+//! 重试前被覆盖的日志会丢失，恢复写入时以一行提示记录丢失的字节数。
+//!
+//! 日志目录与文件属于超级用户，其他用户只能读取
+
+use alloc::{format, vec};
+use core::time::Duration;
+
+use async_locks::mutex::Mutex;
+use filesystem::{
+    fs::FileSystemError,
+    path::{Path, PathBuf},
+};
+
+use crate::{cmdline, io, klog, multitask};
+
+/// 日志文件路径
+const LOG_PATH: &str = "/system/logs/kernel.log";
+/// 日志文件所在目录
+const LOG_DIR: &str = "/system/logs";
+/// 日志文件的权限位
+const FILE_MODE: u16 = 0o644;
+/// 日志目录的权限位
+const DIR_MODE: u16 = 0o755;
+/// 日志文件的最大大小，超过后轮转
+const MAX_FILE_SIZE: u64 = 128 * 1024;
+/// 保留的旧日志文件数量
+const ROTATE_COUNT: usize = 3;
+/// 未写入的日志达到此字节数时立即写入
+const FLUSH_THRESHOLD: usize = 4096;
+/// 写入的最大间隔
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+/// 检查未写入日志的间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// 写入失败后的最大重试间隔
+const MAX_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+/// 每次从环形缓冲区读取的字节数
+const CHUNK_SIZE: usize = 4096;
+
+/// 已写入日志文件的累计位置，见 [klog::read_since]
+static CURSOR: Mutex<usize> = Mutex::new(0);
+
+/// 启动写入日志文件的后台任务，由内核在挂载根文件系统后调用
+///
+/// 启动选项`logfile=off`时不写入日志文件
+pub fn start() {
+    if !cmdline::options().log_file {
+        return;
+    }
+    multitask::async_rt::spawn(async {
+        if let Err(error) = append(b"--- boot ---\n").await {
+            klog!(warn, "klog", "failed to write {LOG_PATH}: {error:?}");
+        }
+        let mut retry_interval = FLUSH_INTERVAL;
+        // 距离下次写入的时间
+        let mut wait = FLUSH_INTERVAL;
+        loop {
+            multitask::async_task::sleep(POLL_INTERVAL).await;
+            wait = wait.saturating_sub(POLL_INTERVAL);
+            let pending = klog::written() - *CURSOR.lock().await;
+            if pending == 0 || (pending < FLUSH_THRESHOLD && !wait.is_zero()) {
+                continue;
+            }
+            match flush().await {
+                Ok(()) => {
+                    retry_interval = FLUSH_INTERVAL;
+                    wait = FLUSH_INTERVAL;
+                }
+                Err(error) => {
+                    // 只在第一次失败时输出，避免失败的日志本身不断触发写入
+                    if retry_interval == FLUSH_INTERVAL {
+                        klog!(warn, "klog", "failed to write {LOG_PATH}: {error:?}");
+                    }
+                    retry_interval = (retry_interval * 2).min(MAX_RETRY_INTERVAL);
+                    wait = retry_interval;
+                }
+            }
+        }
+    });
+}
+
+/// 将环形缓冲区中未写入的日志追加到日志文件，日志文件过大时轮转
+pub async fn flush() -> Result<(), FileSystemError> {
+    let mut cursor = CURSOR.lock().await;
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        let (len, lost) = klog::read_since(*cursor, &mut buffer);
+        if lost > 0 {
+            append(format!("--- {lost} bytes of log lost ---\n").as_bytes()).await?;
+            *cursor += lost;
+        }
+        if len == 0 {
+            return Ok(());
+        }
+        append(&buffer[..len]).await?;
+        *cursor += len;
+    }
+}
+
+/// 追加到日志文件，追加前文件超过 [MAX_FILE_SIZE] 时先轮转
+async fn append(data: &[u8]) -> Result<(), FileSystemError> {
+    let log = path(LOG_PATH);
+    let log = log.as_path();
+    let (fs, relative) = io::vfs::resolve(&log).ok_or(FileSystemError::FileNotFound)?;
+    let size = match fs.get_metadata(relative).await {
+        Ok(metadata) => metadata.size,
+        Err(FileSystemError::FileNotFound) => {
+            create_directory().await?;
+            fs.create_file(relative).await?;
+            io::permission::created_by_kernel(log, FILE_MODE).await;
+            0
+        }
+        Err(error) => return Err(error),
+    };
+    let size = if size >= MAX_FILE_SIZE {
+        rotate().await?;
+        fs.create_file(relative).await?;
+        io::permission::created_by_kernel(log, FILE_MODE).await;
+        0
+    } else {
+        size
+    };
+
+    let mut file = fs.open_file(relative).await?;
+    let result = async {
+        file.move_pointer(size).await?;
+        file.write(data).await
+    }
+    .await;
+    file.close().await?;
+    result
+}
+
+/// 轮转日志文件，完成后 [LOG_PATH] 不存在
+async fn rotate() -> Result<(), FileSystemError> {
+    let rotated = |index: usize| path(&format!("{LOG_PATH}.{index}"));
+    let oldest = rotated(ROTATE_COUNT);
+    let oldest = oldest.as_path();
+    let (fs, relative) = io::vfs::resolve(&oldest).ok_or(FileSystemError::FileNotFound)?;
+    match fs.delete_file(relative).await {
+        Ok(()) => io::permission::removed(oldest).await,
+        Err(FileSystemError::FileNotFound) => (),
+        Err(error) => return Err(error),
+    }
+    for index in (0..ROTATE_COUNT).rev() {
+        let from = match index {
+            0 => path(LOG_PATH),
+            _ => rotated(index),
+        };
+        let to = rotated(index + 1);
+        match rename(from.as_path(), to.as_path()).await {
+            Ok(()) | Err(FileSystemError::FileNotFound) => (),
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(())
+}
+
+/// 重命名同一文件系统中的文件，权限记录随文件移动
+async fn rename(from: Path<'_>, to: Path<'_>) -> Result<(), FileSystemError> {
+    let (fs, from_relative) = io::vfs::resolve(&from).ok_or(FileSystemError::FileNotFound)?;
+    let (_, to_relative) = io::vfs::resolve(&to).ok_or(FileSystemError::FileNotFound)?;
+    fs.rename(from_relative, to_relative).await?;
+    io::permission::renamed(from, to).await;
+    Ok(())
+}
+
+async fn create_directory() -> Result<(), FileSystemError> {
+    let dir = path(LOG_DIR);
+    let dir = dir.as_path();
+    let (fs, relative) = io::vfs::resolve(&dir).ok_or(FileSystemError::FileNotFound)?;
+    match fs.create_directory(relative).await {
+        Ok(()) | Err(FileSystemError::FileExists) => (),
+        Err(error) => return Err(error),
+    }
+    io::permission::created_by_kernel(dir, DIR_MODE).await;
+    Ok(())
+}
+
+fn path(path: &str) -> PathBuf {
+    PathBuf::from_str(path).expect("codebug: log file path should be valid")
+}
@@ -1,74 +1,203 @@
 #![no_std]
 #![no_main]
 #![feature(custom_test_frameworks)]
-#![test_runner(test_runner)]
+#![test_runner(testing::run)]
+#![reexport_test_harness_main = "test_main"]
 
 extern crate alloc;
 extern crate rlibc;
 
+use alloc::vec::Vec;
+use filesystem::{fs::FileSystemError, path::PathBuf};
+
+use crate::multitask::process::CreateProcessError;
+
+pub mod backtrace;
+pub mod boot_slot;
 pub mod bootloader;
+pub mod build_info;
+pub mod cmdline;
+pub mod crash_dump;
 pub mod display;
+#[cfg(test)]
+pub mod fuzz;
+pub mod hal;
 pub mod io;
+pub mod klog;
+pub mod log_file;
 pub mod memory;
+pub mod module;
 pub mod multitask;
 pub mod panicking;
+pub mod random;
 pub mod sync;
+#[cfg(test)]
+pub mod testing;
+pub mod time;
 pub mod trap;
 pub mod user;
 pub mod syscall;
 
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn kmain(
-    memory_region_ptr: *const bootloader::MemoryRegion,
-    memory_region_len: usize,
-    startup_disk: u32,
-) -> ! {
+pub unsafe extern "C" fn kmain(boot_info: *const bootloader::BootInfo) -> ! {
+    // 初始化串口，用于输出日志
+    io::serial::init();
     // 初始化VGA文本缓冲，并输出文本
     display::vga_text::init();
+    // 保存启动信息
+    unsafe {
+        bootloader::init(boot_info);
+    }
+    // 解析启动选项
+    unsafe {
+        cmdline::init();
+    }
+    klog!(
+        info,
+        "boot",
+        "rsdp {:x?}, framebuffer {:x?}, kernel elf {} bytes, cmdline {:?}",
+        bootloader::rsdp(),
+        bootloader::framebuffer().map(|framebuffer| framebuffer.base),
+        bootloader::kernel_elf().map_or(0, <[u8]>::len),
+        bootloader::cmdline()
+    );
     // 初始化中断、异常处理和系统调用
     unsafe {
         trap::init();
     }
     // 初始化内存
     unsafe {
-        memory::init(core::slice::from_raw_parts(
-            memory_region_ptr,
-            memory_region_len,
-        ));
+        memory::init(bootloader::memory_regions());
+    }
+    // 解析ACPI表
+    unsafe {
+        io::acpi::init();
     }
     // 初始化per-cpu结构
     unsafe {
         sync::percpu::init();
     }
+    // 启用SSE及扩展状态的保存
+    unsafe {
+        multitask::fpu::init();
+    }
+    // 初始化熵池
+    random::init();
+    // 选择时钟源，读取RTC初始化系统时间
+    time::init();
+    // 申请映射到每个用户进程的共享页
+    multitask::vdso::init();
 
     // 初始化内核线程
     multitask::thread::create_kernel_async_thread();
     // 初始化IDLE线程
     multitask::thread::create_idle_thread();
+    // 开始记录锁的获取顺序
+    #[cfg(feature = "lockdep")]
+    sync::lockdep::init();
+    // 启动内核工作者
+    multitask::workqueue::init();
+    // 检查进程CPU时间限制
+    multitask::async_rt::spawn(multitask::process::enforce_cpu_limits());
 
-    // 初始化键盘
-    unsafe {
-        io::keyboard::init();
+    // 初始化键盘与鼠标
+    io::input::init();
+    io::keyboard::init();
+    io::mouse::init();
+    // 初始化PC扬声器
+    io::speaker::init();
+
+    // 用户内存复制性能测试
+    #[cfg(feature = "bench-user-copy")]
+    memory::user_copy::benchmark();
+
+    // 测试模式下运行测试用例，完成后退出qemu，不再启动init。指定了fuzz启动选项时改为进行系统调用模糊测试
+    #[cfg(test)]
+    match cmdline::options().fuzz {
+        Some(seed) => {
+            multitask::async_rt::spawn(testing::run_async("syscall_fuzz", fuzz::run(seed)));
+            multitask::async_rt::run()
+        }
+        None => test_main(),
     }
 
     multitask::async_rt::spawn(async move {
-        // 初始化磁盘
-        if io::disk::init_disk(startup_disk as u8).await.is_err() {
-            panic!("failed to init disk");
+        // 初始化磁盘，磁盘不可用时以initramfs作为根文件系统
+        let disk_ready = io::disk::init_disk(bootloader::startup_disk())
+            .await
+            .is_ok();
+        if !disk_ready {
+            klog!(warn, "boot", "failed to init disk, boot from initramfs");
+            if let Err(error) = io::initramfs::mount_root().await {
+                panic!("failed to mount initramfs: {error:?}");
+            }
+        }
+        // 初始化网络，没有网卡时不影响启动
+        if io::net::init_net().await.is_err() {
+            klog!(info, "net", "no network device found");
         }
+        // 内存不足时丢弃文件系统缓存
+        memory::register_shrinker(io::vfs::shrink_caches);
+        // 挂载临时文件系统
+        if io::vfs::mount_tmpfs().is_err() {
+            klog!(warn, "vfs", "failed to mount /tmp");
+        }
+        // 挂载进程文件系统与设备文件系统
+        io::procfs::mount();
+        io::devfs::mount();
+        // 挂载启动选项指定的镜像文件，路径已在解析启动选项时校验
+        if let Some((image, path)) = cmdline::options().loop_mount
+            && let (Ok(image_path), Ok(mount_path)) =
+                (PathBuf::from_str(image), PathBuf::from_str(path))
+            && let Err(error) = io::vfs::mount_image(image_path.as_path(), mount_path).await
+        {
+            klog!(warn, "vfs", "failed to mount {image} at {path}: {error:?}");
+        }
+        // 输出镜像的构建信息
+        build_info::log().await;
+        // 保存上次panic时写入的崩溃转储
+        crash_dump::collect().await;
+        // 将内核日志持续写入日志文件
+        log_file::start();
 
-        // 磁盘初始化完成后，加载第一个用户程序（/system/init）
-        let Some(process) = multitask::process::create_user_process("/system/init").await else {
-            panic!("start /system/init failed");
+        // 磁盘初始化完成后，加载第一个用户程序（默认为/system/init，可通过启动选项指定）
+        let init = cmdline::options().init;
+        let mut process = multitask::process::create_user_process(init, Vec::new(), None).await;
+        // 磁盘中不存在init程序时，同样改为从initramfs启动
+        if disk_ready
+            && matches!(
+                process,
+                Err(CreateProcessError::FileSystem(
+                    FileSystemError::FileNotFound
+                ))
+            )
+        {
+            klog!(
+                warn,
+                "boot",
+                "{init} not found on disk, boot from initramfs"
+            );
+            if let Err(error) = io::initramfs::mount_root().await {
+                panic!("failed to mount initramfs: {error:?}");
+            }
+            process = multitask::process::create_user_process(init, Vec::new(), None).await;
+        }
+        let process = match process {
+            Ok(process) => process,
+            Err(error) => panic!("start {init} failed: {error:?}"),
         };
+        // init 由内核直接启动，拥有特权
+        multitask::process::set_privileged(&process);
+        // init 已启动，确认本次启动的内核槽位
+        boot_slot::confirm().await;
         let mut process_subscriber = multitask::process::get_exit_code_subscriber(&process);
         drop(process);
 
-        // /system/init 不应该结束
+        // init 不应该结束
         loop {
             if process_subscriber.wait().await.is_err() {
                 panic!(
-                    "process /system/init die, exit code: {}",
+                    "process {init} die, exit code: {}",
                     process_subscriber.borrow()
                 );
             }
@@ -78,6 +207,3 @@ pub unsafe extern "C" fn kmain(
     // 运行内核异步主任务
     multitask::async_rt::run()
 }
-
-#[cfg(test)]
-fn test_runner(_test_cases: &[&dyn Fn()]) {}
@@ -0,0 +1,73 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{
+    cmdline, klog,
+    random::{rdrand, rdtsc},
+};
+
+/// 内核堆虚拟地址的起始位置
+pub(super) const KERNEL_SEARCH_START: usize = 0xFFFF_FF80_0000_0000;
+/// 用户虚拟地址的起始位置
+pub(super) const USER_SEARCH_START: usize = 0x0000_0080_0000_0000;
+/// 随机偏移的最大值（不含）
+///
+/// 用户可执行文件链接于0x100_0000_0000，偏移后的用户起始位置仍需位于其之前
+const MAX_OFFSET: usize = 0x10_0000_0000;
+/// 随机偏移的粒度，按2M对齐以免同一页表被拆散到多处
+const OFFSET_ALIGN: usize = 0x20_0000;
+
+static KERNEL_SEARCH_BASE: AtomicUsize = AtomicUsize::new(KERNEL_SEARCH_START);
+static USER_SEARCH_BASE: AtomicUsize = AtomicUsize::new(USER_SEARCH_START);
+
+/// 随机选取本次启动时内核堆与用户内存的起始搜索地址
+///
+/// 启动选项`aslr=off`时保持固定的起始地址，便于调试
+///
+/// Safety: 只能在启动时调用一次，且必须在分配任何虚拟内存之前调用
+pub(super) unsafe fn init() {
+    if !cmdline::options().aslr {
+        return;
+    }
+    let mut seed = boot_entropy();
+    let mut random_offset = || {
+        seed = mix(seed);
+        (seed as usize % (MAX_OFFSET / OFFSET_ALIGN)) * OFFSET_ALIGN
+    };
+    let kernel_base = KERNEL_SEARCH_START + random_offset();
+    let user_base = USER_SEARCH_START + random_offset();
+    KERNEL_SEARCH_BASE.store(kernel_base, Ordering::Relaxed);
+    USER_SEARCH_BASE.store(user_base, Ordering::Relaxed);
+
+    klog!(
+        debug,
+        "aslr",
+        "kernel heap base 0x{kernel_base:x}, user base 0x{user_base:x}"
+    );
+}
+
+/// 本次启动时内核堆虚拟地址的起始搜索位置
+pub(super) fn kernel_search_base() -> usize {
+    KERNEL_SEARCH_BASE.load(Ordering::Relaxed)
+}
+
+/// 本次启动时用户虚拟地址的起始搜索位置
+pub(super) fn user_search_base() -> usize {
+    USER_SEARCH_BASE.load(Ordering::Relaxed)
+}
+
+/// 启动时的熵，CPU支持RDRAND时混合RDRAND与TSC，否则仅使用TSC
+fn boot_entropy() -> u64 {
+    let tsc = rdtsc();
+    match rdrand() {
+        Some(random) => random ^ mix(tsc),
+        None => mix(tsc),
+    }
+}
+
+/// splitmix64的混合函数，使相近的输入（如TSC）产生差异较大的输出
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
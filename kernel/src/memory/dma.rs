@@ -0,0 +1,80 @@
+use core::{ptr, slice};
+
+use crate::{memory::page, sync::int::IrqGuard};
+
+/// 仅支持32位寻址的设备可访问的最大物理地址
+pub const DMA_32BIT_LIMIT: u64 = u32::MAX as u64;
+/// 不限制物理地址
+pub const DMA_NO_LIMIT: u64 = u64::MAX;
+
+/// 供设备DMA访问的内核内存，物理地址连续
+///
+/// 申请时内存被清零，释放时解除映射并回收物理内存。设备可能随时读写这段内存，
+/// 因此需要在设备停止访问后再释放
+pub struct DmaBuffer {
+    virtual_address: usize,
+    physical_address: u64,
+    size: usize,
+}
+
+impl DmaBuffer {
+    /// 申请DMA内存，len会向上对齐至4K
+    ///
+    /// 内存的最后一个字节的物理地址不超过max_phys_addr，见 [`page::alloc_dma_frames`]。
+    /// 内存不足，或没有满足地址限制的物理内存时，返回None
+    pub fn alloc(len: usize, max_phys_addr: u64) -> Option<Self> {
+        let size = len.next_multiple_of(0x1000).max(0x1000);
+        let (virtual_address, physical_address) = {
+            let _guard = IrqGuard::cli();
+            unsafe { page::alloc_dma_frames(size, max_phys_addr) }.ok()?
+        };
+        let buffer = Self {
+            virtual_address: virtual_address.as_ptr() as usize,
+            physical_address,
+            size,
+        };
+        unsafe {
+            ptr::write_bytes(buffer.virtual_address as *mut u8, 0, size);
+        }
+        Some(buffer)
+    }
+
+    pub fn virtual_address(&self) -> usize {
+        self.virtual_address
+    }
+
+    pub fn physical_address(&self) -> u64 {
+        self.physical_address
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// 以字节切片访问内存
+    ///
+    /// # Safety
+    ///
+    /// 设备可能同时写入这段内存，调用方需通过设备的协议（如完成中断）确认设备已完成写入，
+    /// 且在返回的切片存活期间设备不会再写入
+    pub unsafe fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.virtual_address as *const u8, self.size) }
+    }
+
+    /// 以可变字节切片访问内存
+    ///
+    /// # Safety
+    ///
+    /// 调用方需确认在返回的切片存活期间设备不会读写这段内存
+    pub unsafe fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.virtual_address as *mut u8, self.size) }
+    }
+}
+
+impl Drop for DmaBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            page::free_mapped_frame(page::kernel_pml4(), self.virtual_address, self.size);
+        }
+    }
+}
@@ -3,7 +3,7 @@ use core::{
     ptr::NonNull,
 };
 
-use heap::{MemoryPageProvider, RustHeap};
+use heap::{HeapStats, MemoryPageProvider, RustHeap};
 
 use crate::{
     memory::{self, page::AllocateFrameOptions},
@@ -36,6 +36,27 @@ unsafe impl MemoryPageProvider for PhysicalMemoryPageProvider {
             );
         }
     }
+
+    #[cfg(feature = "heap-sanitize")]
+    fn capture_backtrace(&mut self, frames: &mut [usize]) {
+        crate::backtrace::capture(frames);
+    }
+}
+
+/// 获取内核堆的使用情况
+pub fn heap_stats() -> HeapStats {
+    KERNEL_HEAP.lock_irqsave().stats()
+}
+
+/// 将内核堆保留的空闲页归还页帧分配器，返回归还的页数量
+pub(super) fn shrink() -> usize {
+    KERNEL_HEAP.lock_irqsave().shrink()
+}
+
+/// 内核堆是否正被使用，即当前处于堆的分配或释放过程中
+pub(super) fn is_locked() -> bool {
+    let _guard = IrqGuard::cli();
+    KERNEL_HEAP.try_lock().is_none()
 }
 
 #[global_allocator]
@@ -45,15 +66,13 @@ struct GlobalAllocator;
 
 unsafe impl GlobalAlloc for GlobalAllocator {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        let _guard = IrqGuard::cli();
-        KERNEL_HEAP.lock().allocate(layout)
+        KERNEL_HEAP.lock_irqsave().allocate(layout)
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        let _guard = IrqGuard::cli();
         unsafe {
             KERNEL_HEAP
-                .lock()
+                .lock_irqsave()
                 .deallocate(NonNull::new(ptr).unwrap(), layout);
         }
     }
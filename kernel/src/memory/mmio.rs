@@ -0,0 +1,78 @@
+use core::ptr;
+
+use crate::{memory::page, sync::int::IrqGuard};
+
+/// 映射到内核空间的设备寄存器区域（MMIO）
+///
+/// 映射为不可缓存（PCD|PWT，对应PAT默认配置中的UC），对寄存器的读写会直接到达设备。
+/// 释放时解除映射；需要在整个内核运行期间访问的区域可通过 [`MmioRegion::leak`] 保留映射
+pub struct MmioRegion {
+    address: usize,
+    size: usize,
+}
+
+/// 将设备的物理地址区域映射到内核空间
+///
+/// phys与len无需对齐，映射会覆盖其所在的完整页。内核虚拟空间或页表内存不足时返回None
+///
+/// 注意：物理地址不能是页帧分配器管理的普通内存，如PCI BAR、APIC、帧缓冲等设备地址
+pub fn map_mmio(phys: u64, len: usize) -> Option<MmioRegion> {
+    let _guard = IrqGuard::cli();
+    let address = unsafe { page::map_kernel_physical(phys, len) }?;
+    Some(MmioRegion {
+        address: address.as_ptr() as usize,
+        size: len,
+    })
+}
+
+/// 解除通过 [`MmioRegion::leak`] 保留的映射
+///
+/// # Safety
+///
+/// address与size必须与leak前的区域一致，解除映射后不可再访问该区域
+pub unsafe fn unmap_mmio(address: usize, size: usize) {
+    let _guard = IrqGuard::cli();
+    unsafe {
+        page::unmap_kernel_alias(address, size);
+    }
+}
+
+impl MmioRegion {
+    /// 区域在内核空间中的起始地址，对应映射时的物理地址（保留页内偏移）
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// 读取offset处的32位寄存器，offset超出区域时panic
+    pub fn read_u32(&self, offset: usize) -> u32 {
+        assert!(offset + 4 <= self.size);
+        unsafe { ptr::read_volatile((self.address + offset) as *const u32) }
+    }
+
+    /// 写入offset处的32位寄存器，offset超出区域时panic
+    pub fn write_u32(&self, offset: usize, value: u32) {
+        assert!(offset + 4 <= self.size);
+        unsafe { ptr::write_volatile((self.address + offset) as *mut u32, value) }
+    }
+
+    /// 保留映射直至内核停止运行，返回区域的起始地址
+    ///
+    /// 用于中断处理等无法持有区域的场景，此后只能通过 [`unmap_mmio`] 解除映射
+    pub fn leak(self) -> usize {
+        let address = self.address;
+        core::mem::forget(self);
+        address
+    }
+}
+
+impl Drop for MmioRegion {
+    fn drop(&mut self) {
+        unsafe {
+            unmap_mmio(self.address, self.size);
+        }
+    }
+}
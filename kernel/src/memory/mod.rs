@@ -1,10 +1,26 @@
+pub mod dma;
+pub mod mmio;
 pub mod page;
+pub mod protect;
+pub mod reclaim;
+pub mod user_copy;
 
+mod aslr;
 pub(self) mod heap;
 pub(self) mod physics;
 
+pub use heap::heap_stats;
+pub use mmio::{map_mmio, unmap_mmio};
+pub use physics::{
+    FrameStats, frame_stats, read_memory, read_memory_bytes, write_memory, write_memory_bytes,
+};
+pub use reclaim::register_shrinker;
+
 pub unsafe fn init(memory_region: &'static [crate::bootloader::MemoryRegion]) {
     unsafe {
+        // NX需在写入任何页表项之前开启，随机起始地址需在分配任何虚拟内存之前选取
+        protect::init();
+        aslr::init();
         // 页表首先初始化，我们需要接手bootloader设置的页表，
         // 并据此推算内核占用内存大小
         page::init();
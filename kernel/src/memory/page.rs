@@ -6,54 +6,58 @@ use core::{
     ptr::{self, NonNull},
 };
 
+use async_locks::once::OnceLock;
+use kernel_core::paging;
+
 use crate::{
-    memory::physics::{FRAME_ALLOCATOR, read_memory, write_memory, zero_memory},
+    hal::X86Hal,
+    memory::{
+        aslr::{self, KERNEL_SEARCH_START, USER_SEARCH_START},
+        physics::{FRAME_ALLOCATOR, read_memory, write_memory, zero_memory},
+        protect, reclaim,
+    },
     sync::int::IrqGuard,
 };
 
-/// bootloader里定义的4级页表结构
-///
-/// 仅使用了 `PML4[0] = &raw const LOADER_PDPT`
-/// 和 `PML4[511] = &raw const KERNEL_PDPT`
-static mut PML4: Option<*const PageTable> = None;
-
-/// bootloader里定义的3级页表结构
-///
-/// 仅使用了 `LOADER_PDPT[0] = &raw const LOADER_PD`
-static mut LOADER_PDPT: Option<*const PageTable> = None;
-
-/// bootloader里定义的2级页表结构
-///
-/// 仅使用了 `LOADER_PD[0] = &raw const LOADER_PT`
-static mut LOADER_PD: Option<*const PageTable> = None;
-
-/// bootloader里定义的1级页表结构
-///
-/// 仅使用了如下部分
-/// 0x1000 ~ 0x2000   - 内存检测信息
-/// 0x2000 ~ 0x3000   - stub
-/// 0x7000 ~ 0x7FFF   - 栈空间（但我们只会用0x7c00之前的）
-/// 0x8000 ~ X        - text段、bss段、rodata段
-/// 0xb8000 ~ 0xb9000 - VGA 显示
-static mut LOADER_PT: Option<*const PageTable> = None;
+/// bootloader里定义的页表结构，启动时从CR3中读取
+static KERNEL_PAGE_TABLES: OnceLock<KernelPageTables> = OnceLock::new();
 
-/// bootloader里定义的3级页表结构
+/// bootloader里定义的页表结构
 ///
-/// 仅使用了 `KERNEL_PDPT[511] = &raw const KERNEL_PD`
-static mut KERNEL_PDPT: Option<*const PageTable> = None;
+/// 仅使用了 `PML4[0] = LOADER_PDPT`、`LOADER_PDPT[0] = LOADER_PD`、`LOADER_PD[0] = LOADER_PT`
+/// 和 `PML4[511] = KERNEL_PDPT`、`KERNEL_PDPT[511] = KERNEL_PD`，中间各级页表不需要保存
+struct KernelPageTables {
+    /// 4级页表
+    pml4: *const PageTable,
+    /// 1级页表
+    ///
+    /// 仅使用了如下部分
+    /// 0x1000 ~ 0x2000   - 内存检测信息
+    /// 0x2000 ~ 0x3000   - stub
+    /// 0x7000 ~ 0x7FFF   - 栈空间（但我们只会用0x7c00之前的）
+    /// 0x8000 ~ X        - text段、bss段、rodata段
+    /// 0xb8000 ~ 0xb9000 - VGA 显示
+    loader_pt: *const PageTable,
+    /// 2级页表
+    ///
+    /// 仅使用了如下部分：
+    /// 0xFFFF_FFFF_FFC0_0000 ~ 0xFFFF_FFFF_FFDF_FFFF - 栈空间（2M）
+    /// 0xFFFF_FFFF_C000_0000 ~ X                     - text段、bss段、rodata段
+    ///
+    /// 对于超过2M的页，直接在KERNEL_PD中使用大页分配
+    /// 对于不足2M的页，通过KERNEL_PD中的1级页表分配
+    kernel_pd: *const PageTable,
+}
 
-/// bootloader里定义的2级页表结构
-///
-/// 仅使用了如下部分：
-/// 0xFFFF_FFFF_FFC0_0000 ~ 0xFFFF_FFFF_FFDF_FFFF - 栈空间（2M）
-/// 0xFFFF_FFFF_C000_0000 ~ X                     - text段、bss段、rodata段
-///
-/// 对于超过2M的页，直接在KERNEL_PD中使用大页分配
-/// 对于不足2M的页，通过KERNEL_PT分配
-static mut KERNEL_PD: Option<*const PageTable> = None;
+// Safety: 页表位于恒等映射的内存中，在内核运行期间一直有效
+unsafe impl Send for KernelPageTables {}
+unsafe impl Sync for KernelPageTables {}
 
-/// bootloader里定义的1级页表结构
-static mut KERNEL_PT: Option<*const PageTable> = None;
+fn kernel_page_tables() -> &'static KernelPageTables {
+    KERNEL_PAGE_TABLES
+        .get()
+        .expect("codebug: kernel page tables not initialized")
+}
 
 pub(super) unsafe fn init() {
     // 从CR3寄存器中重新获取页表信息
@@ -71,22 +75,19 @@ pub(super) unsafe fn init() {
         let loader_pt = (&*loader_pd)[0].address() as usize as *const PageTable;
         let kernel_pdpt = (&*pml4)[511].address() as usize as *const PageTable;
         let kernel_pd = (&*kernel_pdpt)[511].address() as usize as *const PageTable;
-        let kernel_pt = (&*kernel_pd)
-            .iter()
-            .find(|entry| !entry.ps() && entry.present())
-            .map(|entry| entry.address() as usize as *const PageTable);
-        PML4 = Some(pml4);
-        LOADER_PDPT = Some(loader_pdpt);
-        LOADER_PD = Some(loader_pd);
-        LOADER_PT = Some(loader_pt);
-        KERNEL_PDPT = Some(kernel_pdpt);
-        KERNEL_PD = Some(kernel_pd);
-        KERNEL_PT = kernel_pt;
+        let tables = KernelPageTables {
+            pml4,
+            loader_pt,
+            kernel_pd,
+        };
+        if KERNEL_PAGE_TABLES.set(tables).is_err() {
+            panic!("codebug: kernel page tables initialized twice");
+        }
     };
 }
 
 pub fn kernel_pml4() -> u64 {
-    return unsafe { PML4.unwrap() as usize as u64 };
+    return kernel_page_tables().pml4 as usize as u64;
 }
 
 pub(super) fn get_kernel_used_memory() -> usize {
@@ -95,12 +96,11 @@ pub(super) fn get_kernel_used_memory() -> usize {
     let mut kernel_used_memory = 0;
     const SIZE_2M: usize = 0x20_0000;
 
-    unsafe {
-        if let Some(pd) = KERNEL_PD {
-            for entry in &**pd {
-                if entry.present() {
-                    kernel_used_memory += SIZE_2M;
-                }
+    if let Some(tables) = KERNEL_PAGE_TABLES.get() {
+        // Safety: 页表在内核运行期间一直有效
+        for entry in unsafe { &**tables.kernel_pd } {
+            if entry.present() {
+                kernel_used_memory += SIZE_2M;
             }
         }
     }
@@ -117,8 +117,8 @@ pub(super) fn insert_temp_page_table(address: usize) -> (usize, usize) {
     let virtual_len = aligned + 0x1000 - address;
     // 我们复用LOADER_PT结构，在0x3000~0x4000创建4k内存页
     unsafe {
-        (&mut *(LOADER_PT.unwrap() as *mut PageTable))[3].0 =
-            aligned as u64 | PageEntry::P_PRESENT | PageEntry::P_RW;
+        (&mut *(kernel_page_tables().loader_pt as *mut PageTable))[3].0 =
+            aligned as u64 | PageEntry::P_PRESENT | PageEntry::P_RW | PageEntry::nx();
     }
     // 更新页表缓存
     unsafe {
@@ -167,6 +167,13 @@ impl AllocateFrameOptions {
         static_vaddr: None,
     };
 
+    pub const USER_CONST: Self = Self {
+        user: true,
+        writable: false,
+        executable: false,
+        static_vaddr: None,
+    };
+
     pub const USER_CODE: Self = Self {
         user: true,
         writable: false,
@@ -180,6 +187,14 @@ impl AllocateFrameOptions {
             ..self
         }
     }
+
+    pub fn writable(&self) -> bool {
+        self.writable
+    }
+
+    pub fn executable(&self) -> bool {
+        self.executable
+    }
 }
 
 /// 申请页帧，并映射到虚拟地址空间
@@ -216,9 +231,50 @@ pub unsafe fn alloc_mapped_frame(
     }
     .ok_or(AllocMappedFrameError::OutOfVirtualSpace)?;
 
+    unsafe { map_new_frames(pml4, virtual_memory_start, frame_count, options) }
+}
+
+/// 申请供内核模块使用的页帧，映射为可写、不可执行
+///
+/// 内存位于内核代码之前的2G内，模块代码可以通过32位相对地址访问内核符号。
+/// 模块链接完成后通过[`protect_kernel_pages`]修改权限，卸载时通过[`free_mapped_frame`]释放
+///
+/// # Safety
+///
+/// 页表操作为全局资源操作，必须确保不可被打断（关中断+加锁）。并发安全由调用方保证。
+pub unsafe fn alloc_module_frames(size: usize) -> Result<NonNull<u8>, AllocMappedFrameError> {
+    assert!((size & 0xFFF) == 0);
+
+    let frame_count = size / 0x1000;
+    let pml4 = kernel_page_tables().pml4;
+    let virtual_memory_start =
+        find_free_virtual_memory(MODULE_AREA_START, MODULE_AREA_END, pml4, frame_count)
+            .ok_or(AllocMappedFrameError::OutOfVirtualSpace)?;
+
+    unsafe {
+        map_new_frames(
+            kernel_pml4(),
+            virtual_memory_start,
+            frame_count,
+            AllocateFrameOptions::KERNEL_DATA,
+        )
+    }
+}
+
+/// 为从virtual_memory_start开始的frame_count个页申请物理内存并写入页表
+///
+/// 失败时释放已分配的内存页
+///
+/// Safety: 同[`alloc_mapped_frame`]，且虚拟内存需空闲
+unsafe fn map_new_frames(
+    pml4: u64,
+    virtual_memory_start: NonZeroUsize,
+    frame_count: usize,
+    options: AllocateFrameOptions,
+) -> Result<NonNull<u8>, AllocMappedFrameError> {
     for i in 0..frame_count {
         // 申请物理内存页
-        let Some(physics_memory) = FRAME_ALLOCATOR.lock().alloc_frame() else {
+        let Some(physics_memory) = alloc_frame_or_reclaim() else {
             // 内存不足，将已分配的内存页释放
             unsafe {
                 free_mapped_frame(pml4, virtual_memory_start.get(), i * 0x1000);
@@ -234,6 +290,8 @@ pub unsafe fn alloc_mapped_frame(
             options.writable,
             options.executable,
             options.user,
+            false,
+            false,
         );
 
         // 页表写入也可能失败，因为写页表时可能触发内存页分配
@@ -253,6 +311,93 @@ pub unsafe fn alloc_mapped_frame(
     Ok(NonNull::new(virtual_memory_start.get() as *mut u8).unwrap())
 }
 
+/// 申请一个物理页帧，内存不足时回收内存后重试一次，见 [`reclaim::reclaim`]
+fn alloc_frame_or_reclaim() -> Option<NonZeroUsize> {
+    if let Some(frame) = FRAME_ALLOCATOR.lock().alloc_frame() {
+        return Some(frame);
+    }
+    if !reclaim::reclaim() {
+        return None;
+    }
+    FRAME_ALLOCATOR.lock().alloc_frame()
+}
+
+/// 申请供设备DMA访问的页帧，并映射到内核空间
+///
+/// 物理内存连续，且最后一个字节的物理地址不超过max_phys_addr，用于只支持32位寻址等受限的设备；
+/// 不限制地址时传入`u64::MAX`。len为预期的内存大小，会向上对齐至4K。
+/// x86的PCI设备DMA会被缓存一致性协议侦听，因此内存按普通内存映射为可写回缓存，无需在访问前后刷新缓存。
+///
+/// 当函数成功时，返回虚拟地址空间的起始地址与物理内存的起始地址，内存使用完毕后通过[`free_mapped_frame`]释放。
+/// 当函数失败时，若已分配了物理内存，这部分物理内存将被回收，但此后只能逐页分配
+///
+/// # Safety
+///
+/// 页表操作为全局资源操作，必须确保不可被打断（关中断+加锁）。并发安全由调用方保证。
+pub unsafe fn alloc_dma_frames(
+    len: usize,
+    max_phys_addr: u64,
+) -> Result<(NonNull<u8>, u64), AllocMappedFrameError> {
+    let frame_count = len.div_ceil(0x1000).max(1);
+    let virtual_memory_start = find_kernel_free_virtual_memory(frame_count)
+        .ok_or(AllocMappedFrameError::OutOfVirtualSpace)?;
+    let physics_memory_start = {
+        let mut frame_allocator = FRAME_ALLOCATOR.lock();
+        // 单页不要求连续，优先复用已回收的页帧
+        let reused = if frame_count == 1 {
+            frame_allocator.alloc_frame().and_then(|frame| {
+                if frame.get() as u64 + 0xFFF <= max_phys_addr {
+                    Some(frame)
+                } else {
+                    unsafe {
+                        frame_allocator.delloc_frame(frame);
+                    }
+                    None
+                }
+            })
+        } else {
+            None
+        };
+        reused
+            .or_else(|| frame_allocator.alloc_contiguous_frames(frame_count, max_phys_addr))
+            .ok_or(AllocMappedFrameError::OutOfPhysicalMemory)?
+    };
+
+    for i in 0..frame_count {
+        let result = write_memory_page(
+            virtual_memory_start.get() + i * 0x1000,
+            physics_memory_start.get() + i * 0x1000,
+            kernel_pml4() as usize,
+            true,
+            false,
+            false,
+            false,
+            false,
+        );
+        if result.is_err() {
+            // 将未写入页表的内存页逐页释放
+            let mut frame_allocator = FRAME_ALLOCATOR.lock();
+            for j in i..frame_count {
+                unsafe {
+                    frame_allocator.delloc_frame(
+                        NonZeroUsize::new(physics_memory_start.get() + j * 0x1000).unwrap(),
+                    );
+                }
+            }
+            drop(frame_allocator);
+            unsafe {
+                free_mapped_frame(kernel_pml4(), virtual_memory_start.get(), i * 0x1000);
+            }
+            return Err(AllocMappedFrameError::OutOfPhysicalMemory);
+        }
+    }
+
+    Ok((
+        NonNull::new(virtual_memory_start.get() as *mut u8).unwrap(),
+        physics_memory_start.get() as u64,
+    ))
+}
+
 /// 返还申请的页帧，从虚拟地址空间中移除，并等待再次分配
 ///
 /// 该函数的address必须为虚拟地址空间的起始地址，size需对齐至4K
@@ -266,7 +411,209 @@ pub unsafe fn free_mapped_frame(pml4: u64, address: usize, size: usize) {
     let frame_count = size / 0x1000;
     for i in 0..frame_count {
         unsafe {
-            remove_memory_page(pml4 as usize, address + i * 0x1000);
+            remove_memory_page(pml4 as usize, address + i * 0x1000, true);
+        }
+    }
+}
+
+/// 修改内核空间中一段已映射内存的访问权限
+///
+/// 用于内核模块链接完成后将代码改为只读、可执行，数据改为不可执行。
+/// address与size需对齐至4K，修改只刷新当前CPU的页表缓存
+///
+/// # Safety
+///
+/// 1. 页表操作为全局资源操作，必须确保不可被打断（关中断+加锁）。并发安全由调用方保证。
+/// 2. 内存必须已由[`alloc_module_frames`]映射，且调用方不再以修改前的权限访问该内存
+pub unsafe fn protect_kernel_pages(address: usize, size: usize, writable: bool, executable: bool) {
+    assert!((address & 0xFFF) == 0);
+    assert!((size & 0xFFF) == 0);
+
+    let _guard = IrqGuard::cli();
+    let pml4 = kernel_pml4() as usize;
+    for page in (address..address + size).step_by(0x1000) {
+        let mut table = pml4;
+        for level in [39, 30, 21] {
+            let entry = unsafe { PageTable::get_entry(table, (page >> level) & 0x1FF) };
+            assert!(entry.present() && !entry.ps());
+            table = entry.address() as usize;
+        }
+        let index = (page >> 12) & 0x1FF;
+        let mut entry = unsafe { PageTable::get_entry(table, index) };
+        assert!(entry.present());
+        entry.0 &= !(PageEntry::P_RW | PageEntry::P_NX);
+        if writable {
+            entry.0 |= PageEntry::P_RW;
+        }
+        if !executable {
+            entry.0 |= PageEntry::nx();
+        }
+        unsafe {
+            PageTable::write_entry(table, index, entry);
+            asm!(
+                "invlpg [{}]",
+                in(reg) page,
+                options(nostack, preserves_flags)
+            );
+        }
+    }
+}
+
+/// 将内核持有的物理页以只读方式映射到用户页表的指定虚拟地址，供多个进程共享同一物理页
+///
+/// 页表项带有共享标记，解除映射或释放用户页表时不会归还该物理页，物理页的生命周期由内核管理。
+/// 虚拟地址已被占用或不属于用户空间时返回[`AllocMappedFrameError::ReservedVaddr`]，
+/// 页表内存不足时返回[`AllocMappedFrameError::OutOfPhysicalMemory`]
+///
+/// # Safety
+///
+/// 1. 页表操作为全局资源操作，必须确保不可被打断（关中断+加锁）。并发安全由调用方保证。
+/// 2. pml4必须为用户页表的物理地址，physical在映射存在期间不能被释放
+pub unsafe fn map_user_shared_frame(
+    pml4: u64,
+    vaddr: u64,
+    physical: u64,
+) -> Result<(), AllocMappedFrameError> {
+    assert!((vaddr & 0xFFF) == 0);
+    assert!((physical & 0xFFF) == 0);
+    if !is_user_space_virtual_memory(vaddr as usize) {
+        return Err(AllocMappedFrameError::ReservedVaddr);
+    }
+    find_user_free_virtual_memory_static(1, pml4, vaddr)
+        .ok_or(AllocMappedFrameError::ReservedVaddr)?;
+    write_memory_page(
+        vaddr as usize,
+        physical as usize,
+        pml4 as usize,
+        false,
+        false,
+        true,
+        false,
+        true,
+    )
+    .map_err(|_| AllocMappedFrameError::OutOfPhysicalMemory)
+}
+
+/// 将指定页表中的一段虚拟内存映射到内核空间
+///
+/// 该函数不会申请新的物理内存，而是在内核空间中创建新的映射，指向与原虚拟内存相同的物理内存页，
+/// 使得内核无需切换页表即可直接访问。映射为可写、不可执行。
+///
+/// 当函数成功时，返回addr在内核空间中对应的地址（保留页内偏移）。
+/// 使用完毕后需调用[`unmap_kernel_alias`]解除映射。
+/// 当内核虚拟空间不足、页表内存不足或原虚拟内存未映射时，返回None，此时不会遗留任何映射。
+///
+/// # Safety
+///
+/// 1. 页表操作为全局资源操作，必须确保不可被打断（关中断+加锁）。并发安全由调用方保证。
+/// 2. 在解除映射前，调用方需保证原虚拟内存对应的物理内存页不会被释放
+pub unsafe fn map_kernel_alias(page_table: u64, addr: u64, len: usize) -> Option<NonNull<u8>> {
+    let offset = (addr & 0xfff) as usize;
+    let page_start = (addr & !0xfff) as usize;
+    let frame_count = (offset + len).div_ceil(0x1000).max(1);
+
+    let virtual_memory_start = find_kernel_free_virtual_memory(frame_count)?;
+
+    for i in 0..frame_count {
+        let Some(physics_memory) =
+            get_page_table_mapped_physical(page_table, page_start + i * 0x1000)
+        else {
+            unsafe {
+                remove_kernel_alias_pages(virtual_memory_start.get(), i);
+            }
+            return None;
+        };
+
+        let result = write_memory_page(
+            virtual_memory_start.get() + i * 0x1000,
+            physics_memory.get() as usize,
+            kernel_pml4() as usize,
+            true,
+            false,
+            false,
+            false,
+            false,
+        );
+        if result.is_err() {
+            unsafe {
+                remove_kernel_alias_pages(virtual_memory_start.get(), i);
+            }
+            return None;
+        }
+    }
+
+    NonNull::new((virtual_memory_start.get() + offset) as *mut u8)
+}
+
+/// 将一段物理内存映射到内核空间，映射为可写、不可执行、不可缓存
+///
+/// 用于访问设备寄存器（MMIO），物理内存不需要由页帧分配器管理。
+/// 当函数成功时，返回address在内核空间中对应的地址（保留页内偏移）。
+/// 使用完毕后需调用[`unmap_kernel_alias`]解除映射。
+/// 当内核虚拟空间不足或页表内存不足时，返回None，此时不会遗留任何映射。
+///
+/// # Safety
+///
+/// 1. 页表操作为全局资源操作，必须确保不可被打断（关中断+加锁）。并发安全由调用方保证。
+/// 2. 物理内存不能是页帧分配器管理的普通内存
+pub unsafe fn map_kernel_physical(address: u64, len: usize) -> Option<NonNull<u8>> {
+    let offset = (address & 0xfff) as usize;
+    let page_start = (address & !0xfff) as usize;
+    let frame_count = (offset + len).div_ceil(0x1000).max(1);
+
+    let virtual_memory_start = find_kernel_free_virtual_memory(frame_count)?;
+
+    for i in 0..frame_count {
+        let result = write_memory_page(
+            virtual_memory_start.get() + i * 0x1000,
+            page_start + i * 0x1000,
+            kernel_pml4() as usize,
+            true,
+            false,
+            false,
+            true,
+            false,
+        );
+        if result.is_err() {
+            unsafe {
+                remove_kernel_alias_pages(virtual_memory_start.get(), i);
+            }
+            return None;
+        }
+    }
+
+    NonNull::new((virtual_memory_start.get() + offset) as *mut u8)
+}
+
+/// 获取内核虚拟地址对应的物理地址，地址未映射时返回None
+///
+/// 用于向设备提供DMA地址。注意连续的虚拟内存不一定对应连续的物理内存
+pub fn kernel_physical_address(address: usize) -> Option<NonZeroU64> {
+    get_page_table_mapped_physical(kernel_pml4(), address)
+}
+
+/// 解除[`map_kernel_alias`]或[`map_kernel_physical`]创建的映射
+///
+/// address为映射的起始地址（可以包含页内偏移），size为映射时的长度
+/// 与[`free_mapped_frame`]不同，此函数不会归还物理内存
+///
+/// # Safety
+///
+/// address与size必须与映射时一致，解除映射后不可再访问该区域
+pub unsafe fn unmap_kernel_alias(address: usize, size: usize) {
+    let offset = address & 0xfff;
+    let frame_count = (offset + size).div_ceil(0x1000).max(1);
+    unsafe {
+        remove_kernel_alias_pages(address & !0xfff, frame_count);
+    }
+}
+
+/// 移除内核空间中连续的映射，但不归还物理内存
+unsafe fn remove_kernel_alias_pages(page_start: usize, frame_count: usize) {
+    let _guard = IrqGuard::cli();
+    for i in 0..frame_count {
+        unsafe {
+            remove_memory_page(kernel_pml4() as usize, page_start + i * 0x1000, false);
         }
     }
 }
@@ -276,23 +623,29 @@ unsafe fn is_page_free(virtual_memory: NonZeroUsize, pml4: *const PageTable) ->
     get_page_table_mapped_physical(pml4 as usize as u64, virtual_memory.get()).is_none()
 }
 
+/// 内核模块区域的起始地址
+const MODULE_AREA_START: usize = 0xFFFF_FFFF_8000_0000;
+/// 内核模块区域的结束地址，即内核代码的起始地址
+const MODULE_AREA_END: usize = 0xFFFF_FFFF_C000_0000;
+
 /// 寻找一个连续的、可用的内核虚拟内存位置
 ///
 /// block: 需要的4K页数量
 ///
 /// 如果成功找到，返回对应的虚拟内存起始地址，注意此时页表项尚未加入，虚拟内存尚不可用
 fn find_kernel_free_virtual_memory(block: usize) -> Option<NonZeroUsize> {
-    // 内核堆起始地址
-    const KERNEL_SEARCH_START: usize = 0xFFFF_FF80_0000_0000;
-    // 内核堆结束地址
-    const KERNEL_SEARCH_END: usize = 0xFFFF_FFFF_C000_0000;
-    let pml4 = unsafe { PML4.unwrap() };
-
-    find_free_virtual_memory(KERNEL_SEARCH_START, KERNEL_SEARCH_END, pml4, block)
+    let pml4 = kernel_page_tables().pml4;
+
+    // 内核堆结束于内核模块区域之前
+    find_free_virtual_memory_from(
+        aslr::kernel_search_base(),
+        KERNEL_SEARCH_START,
+        MODULE_AREA_START,
+        pml4,
+        block,
+    )
 }
 
-// 用户起始地址
-const USER_SEARCH_START: usize = 0x0000_0080_0000_0000;
 // 用户结束地址
 const USER_SEARCH_END: usize = 0xFFFF_FF80_0000_0000;
 
@@ -301,8 +654,24 @@ pub fn is_user_space_virtual_memory(ptr: usize) -> bool {
     (USER_SEARCH_START..USER_SEARCH_END).contains(&ptr)
 }
 
+/// 判断地址是否为规范地址，即第48位及以上各位均与第47位相同
+///
+/// 用户空间包含非规范地址的空洞，作为指令地址或段基址写入CPU前必须检查，否则在内核中触发#GP
+pub fn is_canonical(addr: u64) -> bool {
+    (((addr << 16) as i64) >> 16) as u64 == addr
+}
+
+/// 判断指定虚拟内存区域是否完整位于用户空间，size为0时仅检查起始地址
+pub fn is_user_space_range(start: u64, size: usize) -> bool {
+    let Some(end) = (start as usize).checked_add(size) else {
+        return false;
+    };
+    is_user_space_virtual_memory(start as usize) && end <= USER_SEARCH_END
+}
+
 fn find_user_free_virtual_memory(block: usize, pml4: u64) -> Option<NonZeroUsize> {
-    find_free_virtual_memory(
+    find_free_virtual_memory_from(
+        aslr::user_search_base(),
         USER_SEARCH_START,
         USER_SEARCH_END,
         pml4 as usize as *const PageTable,
@@ -328,6 +697,20 @@ fn find_user_free_virtual_memory_static(
     NonZeroUsize::new(page_start)
 }
 
+/// 从base开始寻找连续的空闲虚拟内存，找不到时再从search_start开始寻找
+///
+/// base为本次启动随机选取的起始位置，见 [`aslr`]
+fn find_free_virtual_memory_from(
+    base: usize,
+    search_start: usize,
+    search_end: usize,
+    pml4: *const PageTable,
+    block: usize,
+) -> Option<NonZeroUsize> {
+    find_free_virtual_memory(base, search_end, pml4, block)
+        .or_else(|| find_free_virtual_memory(search_start, search_end, pml4, block))
+}
+
 fn find_free_virtual_memory(
     search_start: usize,
     search_end: usize,
@@ -367,7 +750,10 @@ struct WritePageError;
 /// 写入内核页表时可能需要再次申请物理页，当内存不足时将返回Err，并释放所有过程中已申请的内存
 /// 如果成功，返回Ok，此时虚拟内存已可用且已映射到对应物理内存
 ///
+/// shared为true时，物理内存不属于此页表（见 [`map_user_shared_frame`]），解除映射时不会归还
+///
 /// 注意：该函数仅负责刷新当前CPU的页表缓存，在其他CPU刷新之前，虚拟内存地址不可用
+#[allow(clippy::too_many_arguments)]
 fn write_memory_page(
     virtual_memory: usize,
     physics_memory: usize,
@@ -375,6 +761,8 @@ fn write_memory_page(
     writable: bool,
     executable: bool,
     userusable: bool,
+    uncached: bool,
+    shared: bool,
 ) -> Result<(), WritePageError> {
     /// 获取下一级页表，或者分配一个新的页表
     /// 如果分配新的页表，新页表对应内存会被清空，但不会将页表项写入当前页表
@@ -440,11 +828,17 @@ fn write_memory_page(
         pt_entry.0 |= PageEntry::P_RW;
     }
     if !executable {
-        pt_entry.0 |= PageEntry::P_NX;
+        pt_entry.0 |= PageEntry::nx();
     }
     if userusable {
         pt_entry.0 |= PageEntry::P_US;
     }
+    if uncached {
+        pt_entry.0 |= PageEntry::P_PWT | PageEntry::P_PCD;
+    }
+    if shared {
+        pt_entry.0 |= PageEntry::P_SHARED;
+    }
 
     // 更新各级页表
     unsafe {
@@ -488,13 +882,14 @@ fn write_memory_page(
 ///
 /// pml4: 页表物理地址
 /// virtual_memory: 虚拟内存地址，必须对齐到4K
+/// release_frame: 是否归还物理内存，若为false，则仅移除映射
 ///
 /// 移除映射后，该虚拟内存会在当前CPU立刻刷新缓存。函数返回后，此虚拟内存不再可用。
 ///
 /// Safety:
-/// 调用方需保证此虚拟内存对应的物理内存是独占的，即不存在其余虚拟内存映射到同一物理内存。
+/// 若release_frame为true，调用方需保证此虚拟内存对应的物理内存是独占的，即不存在其余虚拟内存映射到同一物理内存。
 /// 调用方还需保证此内存在内核页表中存在
-unsafe fn remove_memory_page(pml4: usize, virtual_memory: usize) {
+unsafe fn remove_memory_page(pml4: usize, virtual_memory: usize, release_frame: bool) {
     assert!((pml4 & 0xfff) == 0);
     assert!((virtual_memory & 0xfff) == 0);
 
@@ -524,9 +919,11 @@ unsafe fn remove_memory_page(pml4: usize, virtual_memory: usize) {
 
     // 清除页表项，回收物理空间
     unsafe {
-        FRAME_ALLOCATOR
-            .lock()
-            .delloc_frame(NonZero::new(pt_entry.address() as usize).unwrap());
+        if release_frame && !pt_entry.shared() {
+            FRAME_ALLOCATOR
+                .lock()
+                .delloc_frame(NonZero::new(pt_entry.address() as usize).unwrap());
+        }
         PageTable::write_entry(pt_address, pt_index, PageEntry(0));
     }
 
@@ -589,14 +986,17 @@ pub fn alloc_user_page_table() -> Option<NonZeroU64> {
 
     unsafe {
         zero_memory(addr.get(), 4096);
-        write_memory(addr.get(), &(*PML4.unwrap()).0[0]);
-        write_memory(addr.get() + 511 * 8, &(*PML4.unwrap()).0[511]);
+        write_memory(addr.get(), &(*kernel_page_tables().pml4).0[0]);
+        write_memory(addr.get() + 511 * 8, &(*kernel_page_tables().pml4).0[511]);
     }
 
     Some(addr.try_into().unwrap())
 }
 
 /// 释放用户态页表
+///
+/// 逐级遍历用户空间的页表，归还所有映射的物理内存页与各级页表所占的内存页，最后归还PML4。
+/// [0]和[511]与内核共享（见 [`alloc_user_page_table`]），不会被释放
 pub unsafe fn release_user_page_table(addr: NonZeroU64) {
     let _guard = IrqGuard::cli();
 
@@ -607,9 +1007,9 @@ pub unsafe fn release_user_page_table(addr: NonZeroU64) {
         pml4.assume_init()
     };
 
-    // 遍历内存页
-    for page_entry in &pml4.0 {
-        walk_free_page(page_entry, 3);
+    // 遍历用户空间的内存页
+    for page_entry in &pml4.0[1..511] {
+        walk_free_page(page_entry, 4);
     }
 
     // 归还PML4页表所属内存页
@@ -619,6 +1019,7 @@ pub unsafe fn release_user_page_table(addr: NonZeroU64) {
             .delloc_frame(addr.try_into().unwrap());
     }
 
+    // deep为页表项所在页表的级数，4级页表项指向3级页表，1级页表项指向物理内存页
     fn walk_free_page(page_entry: &PageEntry, deep: u8) {
         if !page_entry.present() || !page_entry.user() {
             return;
@@ -644,7 +1045,7 @@ pub unsafe fn release_user_page_table(addr: NonZeroU64) {
                     .lock()
                     .delloc_frame((page_entry.address() as usize).try_into().unwrap());
             }
-        } else {
+        } else if !page_entry.shared() {
             // 归还物理内存
             unsafe {
                 FRAME_ALLOCATOR
@@ -734,67 +1135,57 @@ pub unsafe fn read_page_table_memory(
     Ok(())
 }
 
-/// 根据页表，获取虚拟地址对应的物理地址
-fn get_page_table_mapped_physical(page_table: u64, virtual_memory: usize) -> Option<NonZeroU64> {
-    // 计算虚拟地址所在的各级页表项
-    let pml4_index = (virtual_memory >> 39) & 0x1ff;
-    let pdpt_index = (virtual_memory >> 30) & 0x1ff;
-    let pd_index = (virtual_memory >> 21) & 0x1ff;
-    let pt_index = (virtual_memory >> 12) & 0x1ff;
-    let offset = virtual_memory & 0xfff;
-
-    let mut pml4_entry = PageEntry(0);
-    let mut pdpt_entry = PageEntry(0);
-    let mut pd_entry = PageEntry(0);
-    let mut pt_entry = PageEntry(0);
-
-    unsafe {
-        read_memory(
-            page_table as usize + pml4_index * size_of::<PageEntry>(),
-            &mut pml4_entry,
-        );
-    };
-
-    if !pml4_entry.present() {
-        return None;
-    }
-
-    unsafe {
-        read_memory(
-            pml4_entry.address() as usize + pdpt_index * size_of::<PageEntry>(),
-            &mut pdpt_entry,
-        );
-    }
-
-    if !pdpt_entry.present() {
-        return None;
+/// 检查页表中指定范围的虚拟内存是否均可被用户态访问
+///
+/// 与仅检查首尾地址不同，此函数会遍历范围内的每一个内存页，确保其均已映射且具有用户态权限。
+/// 如果writable为true，还会检查各内存页是否可写。
+///
+/// 若len为0，则直接返回成功
+pub fn check_user_page_table_range(
+    page_table: u64,
+    addr: u64,
+    len: usize,
+    writable: bool,
+) -> Result<(), AccessMemoryError> {
+    if len == 0 {
+        return Ok(());
     }
 
-    unsafe {
-        read_memory(
-            pdpt_entry.address() as usize + pd_index * size_of::<PageEntry>(),
-            &mut pd_entry,
-        );
+    let start = addr as usize;
+    let end = start.checked_add(len).ok_or(AccessMemoryError::PageFault)?;
+    if !is_user_space_virtual_memory(start) || !is_user_space_virtual_memory(end - 1) {
+        return Err(AccessMemoryError::PageFault);
     }
 
-    if !pd_entry.present() {
-        return None;
+    let mut page = start & !0xfff;
+    while page < end {
+        let entry =
+            get_page_table_effective_entry(page_table, page).ok_or(AccessMemoryError::PageFault)?;
+        if !entry.user() || (writable && !entry.writable()) {
+            return Err(AccessMemoryError::PageFault);
+        }
+        page += 0x1000;
     }
 
-    unsafe {
-        read_memory(
-            pd_entry.address() as usize + pt_index * size_of::<PageEntry>(),
-            &mut pt_entry,
-        );
-    }
+    Ok(())
+}
 
-    if !pt_entry.present() {
-        return None;
-    }
+/// 根据页表，获取虚拟地址对应的物理地址
+fn get_page_table_mapped_physical(page_table: u64, virtual_memory: usize) -> Option<NonZeroU64> {
+    let offset = virtual_memory & 0xfff;
+    let pt_entry = get_page_table_effective_entry(page_table, virtual_memory)?;
 
     NonZeroU64::new(pt_entry.address() + offset as u64)
 }
 
+/// 根据页表，获取虚拟地址对应的1级页表项
+///
+/// 返回的页表项中，P_RW与P_US为各级页表项对应位的交集，即CPU实际生效的访问权限
+fn get_page_table_effective_entry(page_table: u64, virtual_memory: usize) -> Option<PageEntry> {
+    // Safety: 页表均已经被映射到虚拟空间（物理地址与虚拟地址一致）
+    unsafe { paging::effective_entry::<X86Hal>(page_table, virtual_memory as u64) }.map(PageEntry)
+}
+
 #[repr(C, align(4096))]
 struct PageTable([PageEntry; 512]);
 
@@ -862,8 +1253,17 @@ impl PageEntry {
     const P_PRESENT: u64 = 1 << 0;
     const P_RW: u64 = 1 << 1;
     const P_US: u64 = 1 << 2;
+    const P_PWT: u64 = 1 << 3;
+    const P_PCD: u64 = 1 << 4;
     const P_PS: u64 = 1 << 7;
-    const P_NX: u64 = 1 << 62;
+    // 软件使用的位（CPU忽略）：物理内存由内核管理，在多个页表间共享，解除映射时不归还
+    const P_SHARED: u64 = 1 << 9;
+    const P_NX: u64 = 1 << 63;
+
+    /// 不可执行页应设置的位，未开启NX时NX位为保留位，不能设置
+    fn nx() -> u64 {
+        if protect::nx_enabled() { Self::P_NX } else { 0 }
+    }
 
     fn address(&self) -> u64 {
         self.0 & 0x000F_FFFF_FFFF_F000
@@ -877,7 +1277,15 @@ impl PageEntry {
         (self.0 & Self::P_US) != 0
     }
 
+    fn writable(&self) -> bool {
+        (self.0 & Self::P_RW) != 0
+    }
+
     fn present(&self) -> bool {
         (self.0 & Self::P_PRESENT) != 0
     }
+
+    fn shared(&self) -> bool {
+        (self.0 & Self::P_SHARED) != 0
+    }
 }
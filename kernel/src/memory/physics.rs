@@ -1,13 +1,23 @@
-use core::{num::NonZeroUsize, ptr};
+use core::{num::NonZeroUsize, ptr, slice};
+
+use async_locks::once::OnceLock;
 
 use crate::{
     bootloader::MemoryRegion,
     memory::page::{get_kernel_used_memory, insert_temp_page_table},
-    sync::spin::SpinLock,
+    sync::{int::IrqGuard, spin::SpinLock},
 };
 
-/// 系统可用的内存范围
-static mut MEMORY_REGION: &[MemoryRegion] = &[];
+/// 系统可用的内存范围，由 [normalize_memory_region] 规整
+static MEMORY_REGION: OnceLock<&[MemoryRegion]> = OnceLock::new();
+/// 内核保存的可用内存范围数量上限，超出的部分不会被分配
+const MAX_MEMORY_REGIONS: usize = 128;
+/// 规整后的可用内存范围，在堆内存初始化之前即需要使用，因此为静态分配
+static mut USABLE_MEMORY_REGIONS: [MemoryRegion; MAX_MEMORY_REGIONS] = [MemoryRegion {
+    base_addr: 0,
+    length: 0,
+    region_type: 0,
+}; MAX_MEMORY_REGIONS];
 /// 页帧分配器
 pub static FRAME_ALLOCATOR: SpinLock<FrameAllocator> = SpinLock::new(FrameAllocator::const_new());
 
@@ -16,10 +26,91 @@ pub static FRAME_ALLOCATOR: SpinLock<FrameAllocator> = SpinLock::new(FrameAlloca
 /// Safety:
 /// 函数当前页表（及所对应的内存）必须可读写。
 pub(super) unsafe fn init(memory_region: &'static [MemoryRegion]) {
+    let buffer = &raw mut USABLE_MEMORY_REGIONS;
+    // Safety: 仅在初始化时写入一次，此时没有并发
+    let buffer = unsafe { &mut *buffer };
+    if MEMORY_REGION
+        .set(normalize_memory_region(memory_region, buffer))
+        .is_err()
+    {
+        panic!("codebug: memory region initialized twice");
+    }
     // 初始化页帧分配器
-    unsafe {
-        MEMORY_REGION = memory_region;
-        FRAME_ALLOCATOR.lock().init();
+    FRAME_ALLOCATOR.lock().init();
+}
+
+/// 系统可用的内存范围，初始化之前为空
+fn memory_region() -> &'static [MemoryRegion] {
+    MEMORY_REGION.get().copied().unwrap_or_default()
+}
+
+/// 规整内存信息：只保留可用内存，按地址排序，并去除重叠部分
+///
+/// 页帧分配器按区域的顺序推进分配位置，因此不能依赖引导程序已经整理过内存信息
+fn normalize_memory_region<'a>(
+    memory_region: &[MemoryRegion],
+    buffer: &'a mut [MemoryRegion],
+) -> &'a [MemoryRegion] {
+    let usable = memory_region
+        .iter()
+        .filter(|region| region.region_type == MemoryRegion::TYPE_USABLE && region.length > 0);
+    let mut len = 0;
+    for (slot, region) in buffer.iter_mut().zip(usable) {
+        *slot = *region;
+        len += 1;
+    }
+
+    let regions = &mut buffer[..len];
+    regions.sort_unstable_by_key(|region| region.base_addr);
+
+    // 与前一个区域重叠的部分已被前一个区域包含，从当前区域中去除
+    let mut len = 0;
+    for i in 0..regions.len() {
+        let mut region = regions[i];
+        if len > 0 {
+            let prev_end = regions[len - 1].base_addr + regions[len - 1].length;
+            let end = region.base_addr + region.length;
+            if end <= prev_end {
+                continue;
+            }
+            if region.base_addr < prev_end {
+                region.base_addr = prev_end;
+                region.length = end - prev_end;
+            }
+        }
+        regions[len] = region;
+        len += 1;
+    }
+
+    &regions[..len]
+}
+
+/// 页帧分配器的使用情况，由 [`frame_stats`] 返回
+#[derive(Debug, Clone, Copy)]
+pub struct FrameStats {
+    /// 系统可用内存总量（字节）
+    pub total_memory: u64,
+    /// 启动时保留的内存（字节），包括loader与内核映像，不由页帧分配器分配
+    pub reserved_memory: u64,
+    /// 已分配的4K页帧数量
+    pub allocated_frames: u64,
+    /// 已回收、等待再次分配的4K页帧数量
+    pub free_list_frames: u64,
+}
+
+/// 获取页帧分配器的使用情况
+pub fn frame_stats() -> FrameStats {
+    let total_memory = memory_region()
+        .iter()
+        .map(|memory_region| memory_region.length)
+        .sum();
+    let _guard = IrqGuard::cli();
+    let frame_allocator = FRAME_ALLOCATOR.lock();
+    FrameStats {
+        total_memory,
+        reserved_memory: (get_kernel_used_memory() + 0x20_0000) as u64,
+        allocated_frames: frame_allocator.allocated_frames,
+        free_list_frames: frame_allocator.free_list_frames,
     }
 }
 
@@ -27,13 +118,30 @@ pub(super) unsafe fn init(memory_region: &'static [MemoryRegion]) {
 ///
 /// 此函数会在页表中添加一个临时项，以允许访问指定物理内存。
 ///
-/// Safety:
+/// # Safety
+///
 /// 该物理内存必须存在。对内存的访问不能违反Rust规则。
 pub unsafe fn read_memory<T: Sized>(address: usize, dst: &mut T) {
-    let size = size_of::<T>();
+    // Safety: 由调用方保证
+    unsafe {
+        read_memory_bytes(
+            address,
+            slice::from_raw_parts_mut(dst as *mut T as *mut u8, size_of::<T>()),
+        );
+    }
+}
+
+/// 直接读取指定物理内存数据到缓冲区
+///
+/// 此函数会在页表中添加一个临时项，以允许访问指定物理内存。
+///
+/// # Safety
+///
+/// 该物理内存必须存在。
+pub unsafe fn read_memory_bytes(address: usize, dst: &mut [u8]) {
     let mut src_start = address;
-    let src_end = address + size;
-    let mut dst_start = dst as *mut T as usize;
+    let src_end = address + dst.len();
+    let mut dst_start = dst.as_mut_ptr() as usize;
     while src_start < src_end {
         let (start, len) = insert_temp_page_table(src_start);
         let len = len.min(src_end - src_start);
@@ -52,13 +160,30 @@ pub unsafe fn read_memory<T: Sized>(address: usize, dst: &mut T) {
 ///
 /// 此函数会在页表中添加一个临时项，以允许访问指定物理内存。
 ///
-/// Safety:
+/// # Safety
+///
 /// 该物理内存必须存在。对内存的访问不能违反Rust规则。
 pub unsafe fn write_memory<T: Sized>(address: usize, src: &T) {
-    let size = size_of::<T>();
+    // Safety: 由调用方保证
+    unsafe {
+        write_memory_bytes(
+            address,
+            slice::from_raw_parts(src as *const T as *const u8, size_of::<T>()),
+        );
+    }
+}
+
+/// 直接写入缓冲区中的数据到指定物理内存
+///
+/// 此函数会在页表中添加一个临时项，以允许访问指定物理内存。
+///
+/// # Safety
+///
+/// 该物理内存必须存在。对内存的访问不能违反Rust规则。
+pub unsafe fn write_memory_bytes(address: usize, src: &[u8]) {
     let mut dst_start = address;
-    let dst_end = address + size;
-    let mut src_start = src as *const T as usize;
+    let dst_end = address + src.len();
+    let mut src_start = src.as_ptr() as usize;
     while dst_start < dst_end {
         let (start, len) = insert_temp_page_table(dst_start);
         let len = len.min(dst_end - dst_start);
@@ -100,6 +225,10 @@ pub struct FrameAllocator {
     first_alloc_address: Option<NonZeroUsize>,
     /// 已经归还的内存，通过链表存储，此处仅存储链表头对应的地址
     linked_free_address: Option<NonZeroUsize>,
+    /// 已分配的页帧数量
+    allocated_frames: u64,
+    /// 链表中的页帧数量
+    free_list_frames: u64,
 }
 
 impl FrameAllocator {
@@ -107,6 +236,8 @@ impl FrameAllocator {
         Self {
             first_alloc_address: None,
             linked_free_address: None,
+            allocated_frames: 0,
+            free_list_frames: 0,
         }
     }
 
@@ -130,12 +261,14 @@ impl FrameAllocator {
             unsafe {
                 read_memory(linked_free_address.get(), &mut self.linked_free_address);
             }
+            self.free_list_frames -= 1;
+            self.allocated_frames += 1;
             return Some(linked_free_address);
         }
 
         if let Some(first_alloc_address) = self.first_alloc_address {
             // 从尚未分配的内存中分配
-            for memory_region in unsafe { MEMORY_REGION } {
+            for memory_region in memory_region() {
                 let region_start = memory_region.base_addr;
                 let region_end = region_start + memory_region.length;
 
@@ -158,6 +291,7 @@ impl FrameAllocator {
 
                 // 更新分配进度
                 self.first_alloc_address = NonZeroUsize::new(alloc_end as usize);
+                self.allocated_frames += 1;
 
                 return NonZeroUsize::new(alloc_start as usize);
             }
@@ -168,6 +302,44 @@ impl FrameAllocator {
         None
     }
 
+    /// 分配物理地址连续的多个4K物理内存，用于需要连续内存的设备DMA
+    ///
+    /// 仅从尚未分配的内存中分配，已回收到链表中的内存不参与连续分配。
+    /// 当前区域剩余内存不足时，跳过该区域的剩余内存，这部分内存不会再被分配。
+    /// 分配的内存的最后一个字节不超过max_address，无法满足时返回None，且不会消耗内存
+    pub fn alloc_contiguous_frames(
+        &mut self,
+        count: usize,
+        max_address: u64,
+    ) -> Option<NonZeroUsize> {
+        let first_alloc_address = self.first_alloc_address?;
+        for memory_region in memory_region() {
+            let region_start = memory_region.base_addr;
+            let region_end = region_start + memory_region.length;
+
+            if region_end <= first_alloc_address.get() as u64 {
+                continue;
+            }
+
+            let alloc_start = region_start
+                .max(first_alloc_address.get() as u64)
+                .next_multiple_of(0x1000);
+            let alloc_end = alloc_start + count as u64 * 0x1000;
+            // 区域已按地址排序且互不重叠，超出地址限制后的区域也不可能满足要求
+            if alloc_end - 1 > max_address {
+                return None;
+            }
+            if alloc_end > region_end {
+                continue;
+            }
+
+            self.first_alloc_address = NonZeroUsize::new(alloc_end as usize);
+            self.allocated_frames += count as u64;
+            return NonZeroUsize::new(alloc_start as usize);
+        }
+        None
+    }
+
     /// 回收4K物理内存，address必须为对应物理内存的起始地址
     /// 回收后的物理内存将用于下次分配
     ///
@@ -180,5 +352,51 @@ impl FrameAllocator {
             write_memory(address.get(), &self.linked_free_address);
         }
         self.linked_free_address = Some(address);
+        self.allocated_frames -= 1;
+        self.free_list_frames += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(base_addr: u64, length: u64, region_type: u32) -> MemoryRegion {
+        MemoryRegion {
+            base_addr,
+            length,
+            region_type,
+        }
+    }
+
+    fn layout(regions: &[MemoryRegion]) -> alloc::vec::Vec<(u64, u64)> {
+        regions
+            .iter()
+            .map(|region| (region.base_addr, region.length))
+            .collect()
+    }
+
+    #[test_case]
+    fn normalize_sorts_usable_regions() {
+        let regions = [
+            region(0x10_0000, 0x1000, MemoryRegion::TYPE_USABLE),
+            region(0x2000, 0x1000, MemoryRegion::TYPE_RESERVED),
+            region(0x1000, 0x1000, MemoryRegion::TYPE_USABLE),
+        ];
+        let mut buffer = [region(0, 0, 0); 4];
+        let regions = normalize_memory_region(&regions, &mut buffer);
+        assert_eq!(layout(regions), [(0x1000, 0x1000), (0x10_0000, 0x1000)]);
+    }
+
+    #[test_case]
+    fn normalize_removes_overlap() {
+        let regions = [
+            region(0x2000, 0x3000, MemoryRegion::TYPE_USABLE),
+            region(0x1000, 0x2000, MemoryRegion::TYPE_USABLE),
+            region(0x3000, 0x1000, MemoryRegion::TYPE_USABLE),
+        ];
+        let mut buffer = [region(0, 0, 0); 4];
+        let regions = normalize_memory_region(&regions, &mut buffer);
+        assert_eq!(layout(regions), [(0x1000, 0x2000), (0x3000, 0x2000)]);
     }
 }
@@ -0,0 +1,125 @@
+use core::{
+    arch::{asm, x86_64::__cpuid_count},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::klog;
+
+const IA32_EFER: u32 = 0xC000_0080;
+/// EFER.NXE，开启后页表项的第63位表示不可执行
+const EFER_NXE: u32 = 1 << 11;
+/// CR0.WP，内核写入只读页同样触发缺页
+const CR0_WP: u64 = 1 << 16;
+/// CR4.SMEP，内核态不可执行用户页
+const CR4_SMEP: u64 = 1 << 20;
+/// CR4.SMAP，内核态不可访问用户页，除非RFLAGS.AC为1
+const CR4_SMAP: u64 = 1 << 21;
+
+static NX_ENABLED: AtomicBool = AtomicBool::new(false);
+static SMEP_ENABLED: AtomicBool = AtomicBool::new(false);
+static SMAP_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 开启写保护，并根据CPU支持情况开启NX、SMEP与SMAP
+///
+/// 必须在写入任何带有NX位的页表项之前调用：未开启EFER.NXE时，NX位是保留位，访问该页会触发缺页
+///
+/// Safety:
+/// 只能在启动时调用一次，此时内核不会访问任何用户页
+pub(super) unsafe fn init() {
+    // 内核经复制函数写入用户的只读页（如代码段）时应当失败，而不是绕过页表权限
+    unsafe {
+        let mut cr0: u64;
+        asm!("mov {}, cr0", out(reg) cr0, options(nostack, preserves_flags));
+        cr0 |= CR0_WP;
+        asm!("mov cr0, {}", in(reg) cr0, options(nostack, preserves_flags));
+    }
+
+    // cpuid 0x80000001 edx第20位：支持NX
+    let (max_extended, _, _, _) = cpuid(0x8000_0000, 0);
+    if max_extended >= 0x8000_0001 && cpuid(0x8000_0001, 0).3 & (1 << 20) != 0 {
+        unsafe {
+            let mut efer_low: u32;
+            let efer_high: u32;
+            asm!(
+                "rdmsr",
+                in("ecx") IA32_EFER,
+                out("eax") efer_low,
+                out("edx") efer_high,
+                options(nostack, preserves_flags)
+            );
+            efer_low |= EFER_NXE;
+            asm!(
+                "wrmsr",
+                in("ecx") IA32_EFER,
+                in("eax") efer_low,
+                in("edx") efer_high,
+                options(nostack, preserves_flags)
+            );
+        }
+        NX_ENABLED.store(true, Ordering::Relaxed);
+    }
+
+    // cpuid 7 ebx第7位：支持SMEP，第20位：支持SMAP
+    let (max_basic, _, _, _) = cpuid(0, 0);
+    if max_basic >= 7 {
+        let (_, ebx, _, _) = cpuid(7, 0);
+        let mut cr4_bits = 0;
+        if ebx & (1 << 7) != 0 {
+            cr4_bits |= CR4_SMEP;
+            SMEP_ENABLED.store(true, Ordering::Relaxed);
+        }
+        if ebx & (1 << 20) != 0 {
+            cr4_bits |= CR4_SMAP;
+            SMAP_ENABLED.store(true, Ordering::Relaxed);
+        }
+        if cr4_bits != 0 {
+            unsafe {
+                let mut cr4: u64;
+                asm!("mov {}, cr4", out(reg) cr4, options(nostack, preserves_flags));
+                cr4 |= cr4_bits;
+                asm!("mov cr4, {}", in(reg) cr4, options(nostack, preserves_flags));
+            }
+        }
+    }
+
+    klog!(
+        info,
+        "memory",
+        "protection: nx={}, smep={}, smap={}",
+        nx_enabled(),
+        smep_enabled(),
+        smap_enabled()
+    );
+}
+
+/// 是否已开启NX，未开启时页表项不能设置NX位，所有页均可执行
+pub fn nx_enabled() -> bool {
+    NX_ENABLED.load(Ordering::Relaxed)
+}
+
+/// 是否已开启SMEP
+pub fn smep_enabled() -> bool {
+    SMEP_ENABLED.load(Ordering::Relaxed)
+}
+
+/// 是否已开启SMAP，开启后内核访问用户页前需执行stac，访问结束后执行clac
+pub fn smap_enabled() -> bool {
+    SMAP_ENABLED.load(Ordering::Relaxed)
+}
+
+/// 清除RFLAGS.AC，用于从用户态进入内核时，避免用户态设置的AC使SMAP失效
+///
+/// syscall进入内核时由FMASK清除AC，中断与异常不会清除AC，需调用此函数
+pub fn clear_user_access() {
+    if smap_enabled() {
+        // Safety: 已确认CPU支持SMAP，clac仅修改RFLAGS.AC
+        unsafe {
+            asm!("clac", options(nomem, nostack));
+        }
+    }
+}
+
+fn cpuid(leaf: u32, subleaf: u32) -> (u32, u32, u32, u32) {
+    let result = __cpuid_count(leaf, subleaf);
+    (result.eax, result.ebx, result.ecx, result.edx)
+}
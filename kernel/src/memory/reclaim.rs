@@ -0,0 +1,44 @@
+use crate::{
+    memory::heap,
+    sync::{int::IrqGuard, spin::SpinLock},
+};
+
+/// 可注册的回收函数数量上限
+const MAX_SHRINKERS: usize = 8;
+
+/// 内存不足时调用的回收函数，如丢弃缓存。注册表不使用堆内存，以便在分配内存的过程中访问
+static SHRINKERS: SpinLock<[Option<Shrinker>; MAX_SHRINKERS]> =
+    SpinLock::new([None; MAX_SHRINKERS]);
+
+/// 回收函数，返回释放的对象数量，没有可释放的对象时返回0
+///
+/// 回收函数在分配物理内存失败时调用，调用方可能持有任意锁，因此回收函数不能分配内存，
+/// 也不能等待锁，只能使用try_lock
+pub type Shrinker = fn() -> usize;
+
+/// 注册回收函数
+pub fn register_shrinker(shrinker: Shrinker) {
+    let _guard = IrqGuard::cli();
+    let mut shrinkers = SHRINKERS.lock();
+    let slot = shrinkers
+        .iter_mut()
+        .find(|slot| slot.is_none())
+        .expect("codebug: too many shrinkers");
+    *slot = Some(shrinker);
+}
+
+/// 内存不足时回收内存，依次调用回收函数，最后将内核堆的空闲页归还页帧分配器
+///
+/// 回收了任何内存时返回true，调用方可重试分配。正在分配内核堆内存时（持有堆的锁），
+/// 缓存释放的内存无法归还内核堆，此时不进行回收，由内核堆自行归还空闲页
+pub fn reclaim() -> bool {
+    let _guard = IrqGuard::cli();
+    if heap::is_locked() {
+        return false;
+    }
+    let shrinkers = *SHRINKERS.lock();
+    let released: usize = shrinkers.iter().flatten().map(|shrinker| shrinker()).sum();
+    // 缓存释放的内存块可能使堆页完全空闲，因此最后归还堆的空闲页
+    let pages = heap::shrink();
+    released > 0 || pages > 0
+}
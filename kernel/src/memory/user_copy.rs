@@ -0,0 +1,212 @@
+use core::arch::naked_asm;
+
+use kernel_core::hal;
+
+use crate::{
+    hal::X86Hal,
+    memory::{page::AccessMemoryError, protect},
+};
+
+/// 单次关中断复制的最大长度
+///
+/// 复制期间需要关中断以避免被调度到其他线程（CR3可能已被切换），
+/// 因此将大缓冲区拆分为多段，避免长时间关中断
+const CHUNK_SIZE: usize = 0x10000;
+
+unsafe extern "C" {
+    /// [`raw_copy`]中`rep movsb`指令的地址，由函数内的汇编标签导出
+    static RAW_COPY_FAULT: u8;
+    /// [`raw_copy`]中缺页后的恢复位置
+    static RAW_COPY_FIXUP: u8;
+    /// [`raw_copy_smap`]中`rep movsb`指令的地址
+    static RAW_COPY_SMAP_FAULT: u8;
+    /// [`raw_copy_smap`]中缺页后的恢复位置（`clac`）
+    static RAW_COPY_SMAP_FIXUP: u8;
+}
+
+/// 从指定页表对应的地址空间复制数据到内核
+///
+/// 与[`crate::memory::page::read_page_table_memory`]不同，此函数不会逐页插入临时页表，
+/// 而是临时将CR3切换到目标页表后直接复制。用户页表与内核共享内核空间的映射，因此切换后内核代码、栈与堆仍然可用。
+///
+/// 如果复制过程中发生缺页，返回[`AccessMemoryError::PageFault`]，此时dst中的数据是不完整的。
+///
+/// # Safety
+///
+/// page_table必须为有效的用户页表，dst必须为内核空间中可写的、长度至少为len的内存
+pub unsafe fn copy_from_user(
+    page_table: u64,
+    addr: u64,
+    dst: *mut u8,
+    len: usize,
+) -> Result<(), AccessMemoryError> {
+    unsafe { copy_in_page_table(page_table, dst, addr as *const u8, len) }
+}
+
+/// 从内核复制数据到指定页表对应的地址空间
+///
+/// 实现方式参考[`copy_from_user`]
+///
+/// # Safety
+///
+/// page_table必须为有效的用户页表，src必须为内核空间中可读的、长度至少为len的内存
+pub unsafe fn copy_to_user(
+    page_table: u64,
+    addr: u64,
+    src: *const u8,
+    len: usize,
+) -> Result<(), AccessMemoryError> {
+    unsafe { copy_in_page_table(page_table, addr as *mut u8, src, len) }
+}
+
+/// 判断缺页是否发生在用户内存复制过程中
+///
+/// 如果是，返回恢复执行的地址，缺页处理程序应将rip设置为该地址，复制函数将返回错误而非触发panic
+pub fn fault_fixup(rip: u64) -> Option<u64> {
+    if rip == &raw const RAW_COPY_FAULT as u64 {
+        Some(&raw const RAW_COPY_FIXUP as u64)
+    } else if rip == &raw const RAW_COPY_SMAP_FAULT as u64 {
+        Some(&raw const RAW_COPY_SMAP_FIXUP as u64)
+    } else {
+        None
+    }
+}
+
+unsafe fn copy_in_page_table(
+    page_table: u64,
+    mut dst: *mut u8,
+    mut src: *const u8,
+    len: usize,
+) -> Result<(), AccessMemoryError> {
+    let mut remain = len;
+    while remain > 0 {
+        let chunk = remain.min(CHUNK_SIZE);
+
+        let not_copied = {
+            unsafe {
+                hal::with_page_table::<X86Hal, _>(page_table, || {
+                    if protect::smap_enabled() {
+                        raw_copy_smap(dst, src, chunk)
+                    } else {
+                        raw_copy(dst, src, chunk)
+                    }
+                })
+            }
+        };
+
+        if not_copied != 0 {
+            return Err(AccessMemoryError::PageFault);
+        }
+
+        remain -= chunk;
+        dst = dst.wrapping_add(chunk);
+        src = src.wrapping_add(chunk);
+    }
+
+    Ok(())
+}
+
+/// 复制内存，返回未复制的字节数
+///
+/// 正常情况下返回0。当`rep movsb`触发缺页时，缺页处理程序会跳转到`mov rax, rcx`，
+/// 此时rcx为剩余未复制的字节数。
+///
+/// 缺页与恢复位置通过汇编标签`RAW_COPY_FAULT`与`RAW_COPY_FIXUP`导出，供[`fault_fixup`]使用
+#[unsafe(naked)]
+unsafe extern "C" fn raw_copy(dst: *mut u8, src: *const u8, len: usize) -> usize {
+    naked_asm!(
+        "mov rcx, rdx",
+        ".global RAW_COPY_FAULT",
+        "RAW_COPY_FAULT:",
+        "rep movsb",
+        ".global RAW_COPY_FIXUP",
+        "RAW_COPY_FIXUP:",
+        "mov rax, rcx",
+        "ret",
+    )
+}
+
+/// 开启SMAP时使用的[`raw_copy`]，复制前执行`stac`允许内核访问用户页，复制后执行`clac`
+///
+/// 缺页时恢复位置为`clac`，确保返回前AC已被清除。未开启SMAP时`stac`/`clac`为非法指令，不能调用此函数
+///
+/// 缺页与恢复位置通过汇编标签`RAW_COPY_SMAP_FAULT`与`RAW_COPY_SMAP_FIXUP`导出，供[`fault_fixup`]使用
+#[unsafe(naked)]
+unsafe extern "C" fn raw_copy_smap(dst: *mut u8, src: *const u8, len: usize) -> usize {
+    naked_asm!(
+        "stac",
+        "mov rcx, rdx",
+        ".global RAW_COPY_SMAP_FAULT",
+        "RAW_COPY_SMAP_FAULT:",
+        "rep movsb",
+        ".global RAW_COPY_SMAP_FIXUP",
+        "RAW_COPY_SMAP_FIXUP:",
+        "clac",
+        "mov rax, rcx",
+        "ret",
+    )
+}
+
+/// 比较临时页表方式与切换CR3方式的复制性能，结果输出到屏幕
+#[cfg(feature = "bench-user-copy")]
+pub fn benchmark() {
+    use core::arch::asm;
+
+    use crate::{
+        kprintln,
+        memory::{self, page::AllocateFrameOptions},
+        sync::int::IrqGuard,
+    };
+
+    fn rdtsc() -> u64 {
+        let low: u32;
+        let high: u32;
+        unsafe {
+            asm!(
+                "rdtsc",
+                out("eax") low,
+                out("edx") high,
+                options(nomem, nostack, preserves_flags)
+            );
+        }
+        ((high as u64) << 32) | low as u64
+    }
+
+    const SIZE: usize = 0x10_0000;
+    let page_table = memory::page::kernel_pml4();
+    let src = unsafe {
+        let _guard = IrqGuard::cli();
+        memory::page::alloc_mapped_frame(page_table, SIZE, AllocateFrameOptions::KERNEL_DATA)
+    };
+    let Ok(src) = src else {
+        kprintln!("user copy benchmark: out of memory");
+        return;
+    };
+    let mut dst = alloc::vec![0u8; SIZE];
+
+    let start = rdtsc();
+    let temp_page_result = unsafe {
+        memory::page::read_page_table_memory(
+            page_table,
+            src.as_ptr() as u64,
+            dst.as_mut_ptr(),
+            SIZE,
+        )
+    };
+    let temp_page_cycles = rdtsc() - start;
+
+    let start = rdtsc();
+    let fast_result =
+        unsafe { copy_from_user(page_table, src.as_ptr() as u64, dst.as_mut_ptr(), SIZE) };
+    let fast_cycles = rdtsc() - start;
+
+    kprintln!(
+        "user copy benchmark ({SIZE} bytes): temp page {temp_page_cycles} cycles (ok={}), cr3 {fast_cycles} cycles (ok={})",
+        temp_page_result.is_ok(),
+        fast_result.is_ok(),
+    );
+
+    unsafe {
+        memory::page::free_mapped_frame(page_table, src.as_ptr() as usize, SIZE);
+    }
+}
@@ -0,0 +1,75 @@
+//! 导出给内核模块的符号
+//!
+//! 模块只能引用此处列出的符号，函数均为 `extern "C"`，修改签名时需同步修改使用它的模块
+
+use core::{alloc::Layout, slice};
+
+use crate::{cmdline::LogLevel, klog, time::clocksource};
+
+// 由compiler_builtins提供
+unsafe extern "C" {
+    fn memcpy(dest: *mut u8, src: *const u8, n: usize) -> *mut u8;
+    fn memmove(dest: *mut u8, src: *const u8, n: usize) -> *mut u8;
+    fn memset(s: *mut u8, c: i32, n: usize) -> *mut u8;
+    fn memcmp(s1: *const u8, s2: *const u8, n: usize) -> i32;
+    fn bcmp(s1: *const u8, s2: *const u8, n: usize) -> i32;
+}
+
+/// 按名称查找导出符号的地址，未导出的符号返回None
+pub(super) fn lookup(name: &str) -> Option<u64> {
+    let address = match name {
+        "cos_log" => cos_log as *const (),
+        "cos_alloc" => cos_alloc as *const (),
+        "cos_free" => cos_free as *const (),
+        "cos_uptime_ns" => cos_uptime_ns as *const (),
+        // 编译器可能为复制、比较等操作生成对以下函数的调用
+        "memcpy" => memcpy as *const (),
+        "memmove" => memmove as *const (),
+        "memset" => memset as *const (),
+        "memcmp" => memcmp as *const (),
+        "bcmp" => bcmp as *const (),
+        _ => return None,
+    };
+    Some(address as u64)
+}
+
+/// 输出内核日志，目标为 "module"
+///
+/// level为0~3，依次对应error、warn、info、debug，message为UTF-8文本，无效的字节被替换
+unsafe extern "C" fn cos_log(level: u32, message: *const u8, len: usize) {
+    let level = match level {
+        0 => LogLevel::Error,
+        1 => LogLevel::Warn,
+        2 => LogLevel::Info,
+        _ => LogLevel::Debug,
+    };
+    // Safety: 由模块保证message指向len字节的有效内存
+    let message = unsafe { slice::from_raw_parts(message, len) };
+    klog!(@level, "module", "{}", alloc::string::String::from_utf8_lossy(message));
+}
+
+/// 从内核堆申请内存，失败或size为0时返回空指针
+extern "C" fn cos_alloc(size: usize, align: usize) -> *mut u8 {
+    match Layout::from_size_align(size, align) {
+        // Safety: 大小不为0
+        Ok(layout) if layout.size() > 0 => unsafe { alloc::alloc::alloc(layout) },
+        _ => core::ptr::null_mut(),
+    }
+}
+
+/// 释放 [cos_alloc] 申请的内存，size与align需与申请时一致
+unsafe extern "C" fn cos_free(ptr: *mut u8, size: usize, align: usize) {
+    if ptr.is_null() {
+        return;
+    }
+    let Ok(layout) = Layout::from_size_align(size, align) else {
+        return;
+    };
+    // Safety: 由模块保证ptr由cos_alloc以相同的参数申请
+    unsafe { alloc::alloc::dealloc(ptr, layout) }
+}
+
+/// 系统启动后经过的时间（纳秒）
+extern "C" fn cos_uptime_ns() -> u64 {
+    clocksource::now_ns()
+}
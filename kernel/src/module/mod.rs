@@ -0,0 +1,276 @@
+//! 内核模块：运行时加载到内核空间（ring 0）执行的驱动
+//!
+//! 模块为位于 [DRIVER_DIR] 的可重定位目标文件（`<模块名>.ko`），以内核相同的目标
+//! （`code-model=kernel`、`relocation-model=static`）编译，只能引用 [export] 中导出的内核符号。
+//! 模块需定义 `extern "C" fn cos_module_init() -> i32`，返回0表示成功，
+//! 可以定义 `extern "C" fn cos_module_exit()`，在卸载时调用。
+//!
+//! 文件末尾附加签名：`<ELF> <Ed25519(私钥, ELF)> <SIGNATURE_MAGIC>`。
+//! 内核只保存公钥，构建时由环境变量 `COS_MODULE_PUBLIC_KEY`（64位十六进制）提供，
+//! 私钥只由构建脚本用于签名，不进入内核镜像。构建时未提供公钥的内核不能加载模块
+
+mod export;
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use async_locks::mutex::Mutex;
+use crypto::ed25519;
+use elf::relocatable::{RegionKind, RelocatableError, RelocatableObject};
+use filesystem::{fs::FileSystemError, path::PathBuf};
+
+use crate::{io, klog, memory::page, sync::int::IrqGuard};
+
+/// 模块所在的目录
+pub const DRIVER_DIR: &str = "/system/drivers";
+/// 签名的结尾标记
+pub const SIGNATURE_MAGIC: &[u8] = b"~cos module signature~\n";
+/// 模块名的最大长度
+pub const MODULE_NAME_LEN: usize = cos_sys::module::MODULE_NAME_LEN;
+/// 模块文件的最大长度
+const MODULE_MAX_SIZE: usize = 1024 * 1024;
+
+/// 验证签名的公钥，构建内核时由环境变量 `COS_MODULE_PUBLIC_KEY` 提供
+const PUBLIC_KEY: Option<[u8; ed25519::PUBLIC_KEY_SIZE]> =
+    match option_env!("COS_MODULE_PUBLIC_KEY") {
+        Some(hex) => Some(parse_public_key(hex)),
+        None => None,
+    };
+
+/// 已加载的模块，加载与卸载期间持有锁，同一时间只有一个模块在初始化或退出
+static MODULES: Mutex<Vec<Module>> = Mutex::new(Vec::new());
+
+struct Module {
+    name: String,
+    // 模块镜像在内核空间中的起始地址
+    address: usize,
+    // 模块镜像的大小，按页对齐
+    size: usize,
+    // 卸载时调用的函数
+    exit: Option<extern "C" fn()>,
+}
+
+/// 已加载模块的信息
+pub struct ModuleSummary {
+    pub name: String,
+    pub address: usize,
+    pub size: usize,
+}
+
+/// 加载或卸载模块失败原因
+#[derive(Debug)]
+pub enum ModuleError {
+    /// 内核构建时未提供公钥，不能加载模块
+    NotSupported,
+    /// 模块名为空、过长或含有字母、数字、`-`、`_` 以外的字符
+    BadName,
+    /// 同名模块已加载
+    AlreadyLoaded,
+    /// 模块未加载
+    NotLoaded,
+    /// 读取模块文件失败
+    FileSystem(FileSystemError),
+    /// 签名缺失或不正确
+    BadSignature,
+    /// 模块文件格式错误，或引用了未导出的符号
+    Format(RelocatableError),
+    /// 内存不足
+    OutOfMemory,
+    /// 初始化函数返回了非0值
+    InitFailed(i32),
+}
+
+/// 内核构建时是否提供了公钥，即能否加载模块
+pub fn supported() -> bool {
+    PUBLIC_KEY.is_some()
+}
+
+/// 从 [DRIVER_DIR] 加载模块并调用其初始化函数
+pub async fn load(name: &str) -> Result<(), ModuleError> {
+    let key = PUBLIC_KEY.ok_or(ModuleError::NotSupported)?;
+    check_name(name)?;
+
+    let mut modules = MODULES.lock().await;
+    if modules.iter().any(|module| module.name == name) {
+        return Err(ModuleError::AlreadyLoaded);
+    }
+
+    let path = PathBuf::from_str(&format!("{DRIVER_DIR}/{name}.ko"))
+        .expect("codebug: module path should be valid since name is checked");
+    let file = io::vfs::read_file(&path.as_path(), MODULE_MAX_SIZE)
+        .await
+        .map_err(ModuleError::FileSystem)?;
+    let object = verify_signature(&key, &file).ok_or_else(|| {
+        klog!(warn, "module", "{path}: bad signature");
+        ModuleError::BadSignature
+    })?;
+
+    let object = RelocatableObject::parse(object).map_err(ModuleError::Format)?;
+    let layout = object.layout();
+    let size = layout.size() as usize;
+    if size == 0 {
+        return Err(ModuleError::Format(RelocatableError::Format));
+    }
+    let address = {
+        let _guard = IrqGuard::cli();
+        unsafe { page::alloc_module_frames(size) }.map_err(|_| ModuleError::OutOfMemory)?
+    };
+    let address = address.as_ptr() as usize;
+    let free = || unsafe { page::free_mapped_frame(page::kernel_pml4(), address, size) };
+
+    // Safety: 内存刚刚映射为可写，且只由此处访问
+    let image = unsafe { core::slice::from_raw_parts_mut(address as *mut u8, size) };
+    let linked = object
+        .link(&layout, image, address as u64, export::lookup)
+        .and_then(|()| {
+            object
+                .symbol_address(&layout, address as u64, "cos_module_init")
+                .ok_or(RelocatableError::UndefinedSymbol("cos_module_init".into()))
+        });
+    let init = match linked {
+        Ok(init) => init,
+        Err(error) => {
+            free();
+            return Err(ModuleError::Format(error));
+        }
+    };
+    let exit = object.symbol_address(&layout, address as u64, "cos_module_exit");
+
+    for region in layout.regions() {
+        let start = address + region.offset as usize;
+        let len = (region.size as usize).next_multiple_of(0x1000);
+        if len == 0 {
+            continue;
+        }
+        let (writable, executable) = match region.kind {
+            RegionKind::Text => (false, true),
+            RegionKind::ReadOnly => (false, false),
+            RegionKind::Data => (true, false),
+        };
+        unsafe { page::protect_kernel_pages(start, len, writable, executable) };
+    }
+
+    // Safety: 符号位于模块的代码区域中，由签名保证模块可信
+    let init = unsafe { core::mem::transmute::<u64, extern "C" fn() -> i32>(init) };
+    let exit = exit.map(|exit| unsafe { core::mem::transmute::<u64, extern "C" fn()>(exit) });
+    let result = init();
+    if result != 0 {
+        klog!(warn, "module", "{name}: init returned {result}");
+        free();
+        return Err(ModuleError::InitFailed(result));
+    }
+
+    klog!(info, "module", "loaded {name} at 0x{address:x}");
+    modules.push(Module {
+        name: name.to_string(),
+        address,
+        size,
+        exit,
+    });
+    Ok(())
+}
+
+/// 调用模块的退出函数并卸载模块
+pub async fn unload(name: &str) -> Result<(), ModuleError> {
+    let mut modules = MODULES.lock().await;
+    let index = modules
+        .iter()
+        .position(|module| module.name == name)
+        .ok_or(ModuleError::NotLoaded)?;
+    let module = modules.remove(index);
+    if let Some(exit) = module.exit {
+        exit();
+    }
+    unsafe { page::free_mapped_frame(page::kernel_pml4(), module.address, module.size) };
+    klog!(info, "module", "unloaded {name}");
+    Ok(())
+}
+
+/// 已加载的模块，按加载顺序排列
+pub async fn list() -> Vec<ModuleSummary> {
+    MODULES
+        .lock()
+        .await
+        .iter()
+        .map(|module| ModuleSummary {
+            name: module.name.clone(),
+            address: module.address,
+            size: module.size,
+        })
+        .collect()
+}
+
+fn check_name(name: &str) -> Result<(), ModuleError> {
+    let valid = !name.is_empty()
+        && name.len() <= MODULE_NAME_LEN
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_');
+    if valid {
+        Ok(())
+    } else {
+        Err(ModuleError::BadName)
+    }
+}
+
+/// 校验附加在文件末尾的签名，成功时返回去除签名后的目标文件
+fn verify_signature<'a>(key: &[u8; ed25519::PUBLIC_KEY_SIZE], file: &'a [u8]) -> Option<&'a [u8]> {
+    let signed = file.strip_suffix(SIGNATURE_MAGIC)?;
+    let (object, signature) =
+        signed.split_at_checked(signed.len().checked_sub(ed25519::SIGNATURE_SIZE)?)?;
+    let signature = signature.try_into().ok()?;
+    ed25519::verify(key, object, signature).then_some(object)
+}
+
+/// 解析十六进制的公钥，格式错误时编译失败
+const fn parse_public_key(hex: &str) -> [u8; ed25519::PUBLIC_KEY_SIZE] {
+    const fn digit(byte: u8) -> u8 {
+        match byte {
+            b'0'..=b'9' => byte - b'0',
+            b'a'..=b'f' => byte - b'a' + 10,
+            b'A'..=b'F' => byte - b'A' + 10,
+            _ => panic!("COS_MODULE_PUBLIC_KEY should be hexadecimal"),
+        }
+    }
+    let hex = hex.as_bytes();
+    assert!(
+        hex.len() == ed25519::PUBLIC_KEY_SIZE * 2,
+        "COS_MODULE_PUBLIC_KEY should be 32 bytes"
+    );
+    let mut key = [0; ed25519::PUBLIC_KEY_SIZE];
+    let mut index = 0;
+    while index < key.len() {
+        key[index] = digit(hex[index * 2]) << 4 | digit(hex[index * 2 + 1]);
+        index += 1;
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_verify_signature() {
+        let secret = [1; ed25519::SECRET_KEY_SIZE];
+        let key = ed25519::public_key(&secret);
+        let mut file = Vec::from(&b"\x7fELF object"[..]);
+        file.extend_from_slice(&ed25519::sign(&secret, &file));
+        file.extend_from_slice(SIGNATURE_MAGIC);
+        assert_eq!(verify_signature(&key, &file), Some(&b"\x7fELF object"[..]));
+        let other = ed25519::public_key(&[2; ed25519::SECRET_KEY_SIZE]);
+        assert_eq!(verify_signature(&other, &file), None);
+        assert_eq!(verify_signature(&key, &file[..file.len() - 1]), None);
+        assert_eq!(verify_signature(&key, SIGNATURE_MAGIC), None);
+    }
+
+    #[test_case]
+    fn test_check_name() {
+        assert!(check_name("hello_driver-2").is_ok());
+        assert!(check_name("").is_err());
+        assert!(check_name("../init").is_err());
+        assert!(check_name(&"a".repeat(MODULE_NAME_LEN + 1)).is_err());
+    }
+}
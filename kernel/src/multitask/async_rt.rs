@@ -17,6 +17,7 @@ use crate::{
     multitask::{
         self,
         thread::{self, Thread, Yield},
+        watchdog,
     },
     sync::{
         int::{IrqGuard, sti},
@@ -28,6 +29,9 @@ use crate::{
 static RUNTIME: SpinLock<Runtimer> = SpinLock::new(Runtimer::new());
 // task id 分配
 static TASK_ID_GENERATOR: AtomicU64 = AtomicU64::new(0);
+// 正在执行的任务ID，u64::MAX表示没有任务在执行，用于死锁检测区分同一线程上的不同任务
+#[cfg(feature = "lockdep")]
+static CURRENT_TASK_ID: AtomicU64 = AtomicU64::new(u64::MAX);
 
 /// 内核使用的异步运行时
 pub struct Runtimer {
@@ -151,6 +155,7 @@ pub fn run() -> ! {
 
         if let Some(task) = task {
             drop((guard, rt));
+            watchdog::touch();
 
             let waker = unsafe { (*task.get()).waker.clone() };
             let mut cx = Context::from_waker(&waker);
@@ -161,7 +166,11 @@ pub fn run() -> ! {
             }
 
             // 执行
+            #[cfg(feature = "lockdep")]
+            CURRENT_TASK_ID.store(unsafe { (*task.get()).task_id }, Ordering::Relaxed);
             let result = unsafe { (*task.get()).future.as_mut().poll(&mut cx) };
+            #[cfg(feature = "lockdep")]
+            CURRENT_TASK_ID.store(u64::MAX, Ordering::Relaxed);
 
             // 如果未完成，则重新放入队列中
             if result.is_pending() {
@@ -201,6 +210,12 @@ pub fn run() -> ! {
     }
 }
 
+/// 内核异步线程正在执行的任务ID
+#[cfg(feature = "lockdep")]
+pub fn current_task_id() -> Option<u64> {
+    Some(CURRENT_TASK_ID.load(Ordering::Relaxed)).filter(|&task_id| task_id != u64::MAX)
+}
+
 /// 生成一个新的异步任务，加入到全局任务池中
 ///
 /// 任务会被pin在堆上，并在专门的线程中执行。执行异步任务的线程栈大小为2M
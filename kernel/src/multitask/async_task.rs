@@ -55,10 +55,15 @@ pub struct Sleep {
     flag: Arc<AtomicU8>,
 }
 
+/// 系统启动后经过的时间，精度为计时器中断的间隔
+pub fn uptime() -> Duration {
+    Duration::from_micros(SYSTEM_INSTANT.load(Ordering::Acquire))
+}
+
 /// 等待指定时间后唤醒
 pub fn sleep(time: Duration) -> Sleep {
     Sleep {
-        duration: time.as_micros() as u64,
+        duration: u64::try_from(time.as_micros()).unwrap_or(u64::MAX),
         flag: Arc::new(AtomicU8::new(FLAG_INIT)),
     }
 }
@@ -73,7 +78,9 @@ impl Future for Sleep {
                 self.flag.store(FLAG_SLEEP, Ordering::Release);
 
                 let wake = WakeQueue {
-                    wake_time: SYSTEM_INSTANT.load(Ordering::Acquire) + self.duration,
+                    wake_time: SYSTEM_INSTANT
+                        .load(Ordering::Acquire)
+                        .saturating_add(self.duration),
                     flag: Arc::downgrade(&self.flag),
                     waker: cx.waker().clone(),
                 };
@@ -0,0 +1,122 @@
+use core::fmt;
+
+use alloc::format;
+use filesystem::{fs::FileSystemError, path::PathBuf};
+
+use crate::{io, klog};
+
+/// 能力清单的扩展名，可执行文件 `/system/shell` 的清单为 `/system/shell.manifest`
+pub const MANIFEST_EXTENSION: &str = ".manifest";
+/// 能力清单的最大长度
+const MANIFEST_MAX_SIZE: usize = 4096;
+
+/// 进程的能力集合
+///
+/// 进程只能执行其能力集合允许的系统调用，缺少能力时系统调用返回PermissionDenied
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities(u32);
+
+impl Capabilities {
+    pub const NONE: Self = Self(0);
+    /// 创建、写入、截断、删除、重命名文件，挂载与卸载文件系统
+    pub const FS_WRITE: Self = Self(1 << 0);
+    /// 创建子进程
+    pub const SPAWN: Self = Self(1 << 1);
+    /// 直接读取键盘事件
+    pub const RAW_CONSOLE: Self = Self(1 << 2);
+    /// 映射图形缓冲区并输出到帧缓冲区
+    pub const GRAPHICS: Self = Self(1 << 3);
+    pub const ALL: Self =
+        Self(Self::FS_WRITE.0 | Self::SPAWN.0 | Self::RAW_CONSOLE.0 | Self::GRAPHICS.0);
+
+    // 清单中的能力名称
+    const NAMES: [(&str, Self); 4] = [
+        ("fs-write", Self::FS_WRITE),
+        ("spawn", Self::SPAWN),
+        ("raw-console", Self::RAW_CONSOLE),
+        ("graphics", Self::GRAPHICS),
+    ];
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// 解析清单
+    ///
+    /// 每行一个能力名称，以#开头的行与空行被忽略，无法识别的名称被忽略
+    pub fn parse(text: &str) -> Self {
+        let mut capabilities = Self::NONE;
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match Self::NAMES.iter().find(|(name, _)| *name == line) {
+                Some((_, capability)) => capabilities = capabilities.union(*capability),
+                None => klog!(
+                    warn,
+                    "exec",
+                    "ignored unknown capability in manifest: {line}"
+                ),
+            }
+        }
+        capabilities
+    }
+}
+
+impl fmt::Debug for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set()
+            .entries(
+                Self::NAMES
+                    .iter()
+                    .filter(|(_, capability)| self.contains(*capability))
+                    .map(|(name, _)| name),
+            )
+            .finish()
+    }
+}
+
+/// 读取可执行文件的能力清单
+///
+/// 清单不存在时返回None，此时进程继承父进程的能力
+pub async fn load_manifest(exe: &PathBuf) -> Result<Option<Capabilities>, FileSystemError> {
+    let Ok(path) = PathBuf::from_str(&format!("{exe}{MANIFEST_EXTENSION}")) else {
+        return Ok(None);
+    };
+    let text = match io::vfs::read_file(&path.as_path(), MANIFEST_MAX_SIZE).await {
+        Ok(text) => text,
+        Err(FileSystemError::FileNotFound) => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    // 清单不是合法的UTF-8时视为不声明任何能力
+    let text = str::from_utf8(&text).unwrap_or_default();
+    Ok(Some(Capabilities::parse(text)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_parse() {
+        let capabilities = Capabilities::parse("# shell\nspawn\n\n  raw-console \nunknown\n");
+        assert!(capabilities.contains(Capabilities::SPAWN));
+        assert!(capabilities.contains(Capabilities::RAW_CONSOLE));
+        assert!(!capabilities.contains(Capabilities::FS_WRITE));
+        assert!(Capabilities::parse("graphics").contains(Capabilities::GRAPHICS));
+        assert_eq!(Capabilities::parse(""), Capabilities::NONE);
+        assert_eq!(
+            Capabilities::ALL.intersection(Capabilities::SPAWN),
+            Capabilities::SPAWN
+        );
+    }
+}
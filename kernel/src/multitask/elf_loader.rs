@@ -1,24 +1,30 @@
 use core::num::NonZeroU64;
 
 use alloc::vec::Vec;
+use filesystem::path::PathBuf;
 
 use crate::{
+    memory,
     multitask::{
         self,
         process::{Process, ProcessMemoryError, ProcessPageType},
+        vma::VmaBacking,
     },
     sync::spin::SpinLock,
 };
 
 pub struct ElfLoader<'loader> {
     process: &'loader SpinLock<Process>,
+    // 可执行文件路径，作为程序段内存区域的后备对象
+    exe: PathBuf,
     allocated_page: Vec<(u64, u64)>,
 }
 
 impl<'loader> ElfLoader<'loader> {
-    pub fn new(process: &'loader SpinLock<Process>) -> Self {
+    pub fn new(process: &'loader SpinLock<Process>, exe: PathBuf) -> Self {
         Self {
             process,
+            exe,
             allocated_page: Vec::new(),
         }
     }
@@ -61,7 +67,7 @@ impl elf::Loader for ElfLoader<'_> {
         }
 
         // 保护内核页
-        if addr < 0x0000_0080_0000_0000 || addr.saturating_add(size) >= 0xFFFF_FF80_0000_0000 {
+        if !memory::page::is_user_space_range(addr, size as usize) {
             return Err(ElfLoaderError::PageReserved);
         }
 
@@ -94,7 +100,14 @@ impl elf::Loader for ElfLoader<'_> {
             ProcessPageType::StaticConst(vaddr)
         };
         let size = (size + 0xfff) & !0xfff;
-        if multitask::process::create_process_page(self.process, size as usize, page_type).is_none()
+        let backing = VmaBacking::File(self.exe.clone());
+        if multitask::process::create_process_page_with_backing(
+            self.process,
+            size as usize,
+            page_type,
+            backing,
+        )
+        .is_none()
         {
             return Err(ElfLoaderError::AllocFail);
         }
@@ -106,7 +119,7 @@ impl elf::Loader for ElfLoader<'_> {
 
     async fn clear_memory(&mut self, addr: u64, len: u64) -> Result<(), Self::LoaderError> {
         // 保护内核页
-        if addr < 0x0000_0080_0000_0000 || addr.saturating_add(len) >= 0xFFFF_FF80_0000_0000 {
+        if !memory::page::is_user_space_range(addr, len as usize) {
             return Err(ElfLoaderError::PageReserved);
         }
 
@@ -124,14 +137,12 @@ impl elf::Loader for ElfLoader<'_> {
 
     async fn write_to_memory(&mut self, addr: u64, data: &[u8]) -> Result<(), Self::LoaderError> {
         // 保护内核页
-        if addr < 0x0000_0080_0000_0000
-            || addr.saturating_add(data.len() as u64) >= 0xFFFF_FF80_0000_0000
-        {
+        if !memory::page::is_user_space_range(addr, data.len()) {
             return Err(ElfLoaderError::PageReserved);
         }
 
         unsafe {
-            multitask::process::write_user_process_memory(
+            multitask::process::write_user_process_image(
                 self.process,
                 addr,
                 data.as_ptr(),
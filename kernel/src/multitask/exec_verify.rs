@@ -0,0 +1,189 @@
+use alloc::{
+    collections::btree_map::BTreeMap,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use async_locks::once_cell::OnceCell;
+use crypto::sha256;
+use filesystem::{
+    fs::{FileHandle, FileSystemError},
+    path::PathBuf,
+};
+
+use crate::{io, klog};
+
+/// 可执行文件摘要清单的路径
+///
+/// 每行为 `<SHA-256摘要的十六进制>  <可执行文件的绝对路径>`，与sha256sum的输出格式一致，
+/// 以#开头的行与空行被忽略
+pub const MANIFEST_PATH: &str = "/system/manifest.sha256";
+/// 清单文件的最大长度
+const MANIFEST_MAX_SIZE: usize = 64 * 1024;
+/// 读取文件时每次读取的长度
+const CHUNK_SIZE: usize = 4096;
+/// 需校验的可执行文件的最大长度，校验时文件被完整读入内存
+const IMAGE_MAX_SIZE: usize = 64 * 1024 * 1024;
+
+/// 首次读取成功的清单，之后不再重新读取
+///
+/// 清单不存在时不缓存，以便根文件系统切换到initramfs后读取其中的清单
+static MANIFEST: OnceCell<BTreeMap<String, [u8; sha256::DIGEST_SIZE]>> = OnceCell::new();
+
+/// 校验可执行文件失败原因
+#[derive(Debug)]
+pub enum VerifyError {
+    /// 文件摘要与清单中记录的不一致
+    Mismatch,
+    /// 读取文件失败
+    FileSystem(FileSystemError),
+}
+
+/// 按清单校验可执行文件
+///
+/// 清单列出了该文件时，将文件完整读入内存，计算其SHA-256摘要并与清单比较，
+/// 一致时返回读入的内容，调用方须从返回的内容加载程序，而不是再次读取文件，
+/// 否则文件可能在校验后被替换。清单不存在或未列出该文件时不校验，返回None。
+/// 返回后文件指针的位置不确定
+pub async fn verify(
+    path: &PathBuf,
+    file: &mut dyn FileHandle,
+) -> Result<Option<Vec<u8>>, VerifyError> {
+    let Ok(manifest) = MANIFEST.get_or_try_init(load_manifest).await else {
+        return Ok(None);
+    };
+    let Some(expected) = manifest.get(&manifest_key(path)) else {
+        return Ok(None);
+    };
+
+    file.move_pointer(0)
+        .await
+        .map_err(VerifyError::FileSystem)?;
+    let mut image = Vec::new();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        let count = file
+            .read(&mut buffer)
+            .await
+            .map_err(VerifyError::FileSystem)? as usize;
+        if count == 0 {
+            break;
+        }
+        if image.len() + count > IMAGE_MAX_SIZE {
+            return Err(VerifyError::FileSystem(FileSystemError::FileTooLarge));
+        }
+        image.extend_from_slice(&buffer[..count]);
+    }
+    if sha256::sha256(&image) != *expected {
+        klog!(
+            warn,
+            "exec",
+            "{path}: digest does not match {MANIFEST_PATH}"
+        );
+        return Err(VerifyError::Mismatch);
+    }
+    Ok(Some(image))
+}
+
+/// 路径在清单中的键
+///
+/// FAT32的文件名不区分大小写，键统一转换为小写，避免以不同大小写的路径绕过校验
+fn manifest_key(path: &PathBuf) -> String {
+    path.normalize().to_string().to_ascii_lowercase()
+}
+
+/// 读取并解析清单，清单不存在时返回Err
+///
+/// 清单存在但无法读取时视为空清单，不再重试
+async fn load_manifest() -> Result<BTreeMap<String, [u8; sha256::DIGEST_SIZE]>, ()> {
+    let path = PathBuf::from_str(MANIFEST_PATH).unwrap();
+    let text = match io::vfs::read_file(&path.as_path(), MANIFEST_MAX_SIZE).await {
+        Ok(text) => text,
+        Err(FileSystemError::FileNotFound) => return Err(()),
+        Err(error) => {
+            klog!(warn, "exec", "failed to read {MANIFEST_PATH}: {error:?}");
+            return Ok(BTreeMap::new());
+        }
+    };
+    let Ok(text) = String::from_utf8(text) else {
+        klog!(warn, "exec", "{MANIFEST_PATH} is not valid UTF-8");
+        return Ok(BTreeMap::new());
+    };
+    let manifest = parse_manifest(&text);
+    klog!(
+        info,
+        "exec",
+        "loaded {} executable digests from {MANIFEST_PATH}",
+        manifest.len()
+    );
+    Ok(manifest)
+}
+
+/// 解析清单，无法识别的行被忽略
+fn parse_manifest(text: &str) -> BTreeMap<String, [u8; sha256::DIGEST_SIZE]> {
+    let mut manifest = BTreeMap::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let entry = line
+            .split_once(char::is_whitespace)
+            .and_then(|(digest, path)| {
+                // sha256sum以*标记二进制模式
+                let path = path.trim_start();
+                let path = path.strip_prefix('*').unwrap_or(path);
+                if !path.starts_with('/') {
+                    return None;
+                }
+                Some((
+                    manifest_key(&PathBuf::from_str(path).ok()?),
+                    parse_digest(digest)?,
+                ))
+            });
+        match entry {
+            Some((path, digest)) => {
+                manifest.insert(path, digest);
+            }
+            None => klog!(
+                warn,
+                "exec",
+                "ignored invalid line in {MANIFEST_PATH}: {line}"
+            ),
+        }
+    }
+    manifest
+}
+
+fn parse_digest(hex: &str) -> Option<[u8; sha256::DIGEST_SIZE]> {
+    if hex.len() != sha256::DIGEST_SIZE * 2 || !hex.is_ascii() {
+        return None;
+    }
+    let mut digest = [0; sha256::DIGEST_SIZE];
+    for (index, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok()?;
+    }
+    Some(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_parse_manifest() {
+        let digest = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let text = alloc::format!(
+            "# comment\n{digest}  /system/init\n\n{digest} */System/Shell\nbad line\n{digest}  relative\n"
+        );
+        let manifest = parse_manifest(&text);
+        assert_eq!(manifest.len(), 2);
+        let expected = parse_digest(digest).unwrap();
+        assert_eq!(manifest.get("/system/init"), Some(&expected));
+        assert_eq!(manifest.get("/system/shell"), Some(&expected));
+        let path = PathBuf::from_str("/SYSTEM/INIT").unwrap();
+        assert_eq!(manifest.get(&manifest_key(&path)), Some(&expected));
+        assert_eq!(expected[0], 0xba);
+        assert!(parse_digest("xy").is_none());
+    }
+}
@@ -0,0 +1,200 @@
+use core::{
+    arch::{
+        asm,
+        x86_64::{__cpuid, __cpuid_count},
+    },
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use alloc::boxed::Box;
+
+use crate::klog;
+
+/// CR0.MP，与TS配合控制WAIT指令
+const CR0_MP: u64 = 1 << 1;
+/// CR0.EM，置位时x87与SSE指令触发#UD
+const CR0_EM: u64 = 1 << 2;
+/// CR0.TS，置位时x87与SSE指令触发#NM
+const CR0_TS: u64 = 1 << 3;
+/// CR4.OSFXSR，允许SSE指令与fxsave/fxrstor
+const CR4_OSFXSR: u64 = 1 << 9;
+/// CR4.OSXMMEXCPT，SIMD浮点异常以#XM报告
+const CR4_OSXMMEXCPT: u64 = 1 << 10;
+/// CR4.OSXSAVE，允许xsave/xrstor与XCR0
+const CR4_OSXSAVE: u64 = 1 << 18;
+
+// XCR0中的状态组件
+const XCR0_X87: u64 = 1 << 0;
+const XCR0_SSE: u64 = 1 << 1;
+const XCR0_AVX: u64 = 1 << 2;
+
+/// 保存区的大小，足够容纳x87、SSE与AVX的状态（832字节）
+const AREA_SIZE: usize = 1024;
+/// x87控制字与MXCSR的初始值，屏蔽全部浮点异常
+const INITIAL_FCW: u16 = 0x037F;
+const INITIAL_MXCSR: u32 = 0x1F80;
+
+/// 是否使用xsave/xrstor，否则使用fxsave/fxrstor
+static XSAVE: AtomicBool = AtomicBool::new(false);
+
+/// xsave与fxsave的保存区，要求64字节对齐
+#[repr(C, align(64))]
+struct Area([u8; AREA_SIZE]);
+
+/// 线程的扩展状态，即x87、SSE寄存器（CPU支持时包括AVX寄存器）
+///
+/// 内核以软浮点编译，不会使用这些寄存器，因此只有用户线程需要保存。
+/// 切换线程时总是保存旧线程的状态并恢复新线程的状态
+pub struct FpuState {
+    area: Box<Area>,
+}
+
+impl FpuState {
+    /// 创建初始状态
+    ///
+    /// 保存区的头部（偏移512）全为0，xrstor会将各组件置为初始状态；
+    /// 控制字与MXCSR位于传统区域，fxrstor与xrstor均会读取
+    pub fn new() -> Self {
+        let mut area = Box::new(Area([0; AREA_SIZE]));
+        area.0[0..2].copy_from_slice(&INITIAL_FCW.to_le_bytes());
+        area.0[24..28].copy_from_slice(&INITIAL_MXCSR.to_le_bytes());
+        Self { area }
+    }
+
+    /// 将当前CPU的扩展状态保存到此处
+    pub fn save(&mut self) {
+        let area = self.area.0.as_mut_ptr();
+        // Safety: 保存区足够大且按64字节对齐
+        unsafe {
+            if XSAVE.load(Ordering::Relaxed) {
+                asm!(
+                    "xsave64 [{}]",
+                    in(reg) area,
+                    in("eax") u32::MAX,
+                    in("edx") u32::MAX,
+                    options(nostack, preserves_flags)
+                );
+            } else {
+                asm!("fxsave64 [{}]", in(reg) area, options(nostack, preserves_flags));
+            }
+        }
+    }
+
+    /// 以此处保存的扩展状态恢复CPU的寄存器
+    pub fn restore(&self) {
+        let area = self.area.0.as_ptr();
+        // Safety: 保存区由new创建或由save写入，内容合法
+        unsafe {
+            if XSAVE.load(Ordering::Relaxed) {
+                asm!(
+                    "xrstor64 [{}]",
+                    in(reg) area,
+                    in("eax") u32::MAX,
+                    in("edx") u32::MAX,
+                    options(nostack, preserves_flags)
+                );
+            } else {
+                asm!("fxrstor64 [{}]", in(reg) area, options(nostack, preserves_flags));
+            }
+        }
+    }
+}
+
+impl Default for FpuState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 启用SSE，CPU支持时启用xsave并开启AVX
+///
+/// # Safety
+///
+/// 只能在启动时调用一次，必须在创建任何用户线程之前调用
+pub unsafe fn init() {
+    unsafe {
+        let mut cr0: u64;
+        asm!("mov {}, cr0", out(reg) cr0, options(nostack, preserves_flags));
+        cr0 = (cr0 | CR0_MP) & !(CR0_EM | CR0_TS);
+        asm!("mov cr0, {}", in(reg) cr0, options(nostack, preserves_flags));
+    }
+
+    // cpuid 1 ecx第26位：支持xsave；第28位：支持AVX
+    let features = __cpuid(1).ecx;
+    let xsave = features & (1 << 26) != 0;
+    unsafe {
+        let mut cr4: u64;
+        asm!("mov {}, cr4", out(reg) cr4, options(nostack, preserves_flags));
+        cr4 |= CR4_OSFXSR | CR4_OSXMMEXCPT;
+        if xsave {
+            cr4 |= CR4_OSXSAVE;
+        }
+        asm!("mov cr4, {}", in(reg) cr4, options(nostack, preserves_flags));
+    }
+
+    let mut components = XCR0_X87 | XCR0_SSE;
+    if xsave {
+        // cpuid 0xD子叶2：AVX状态的大小（eax）与在保存区中的偏移（ebx）
+        // 保存区放不下AVX状态时不开启AVX，否则ymm的高128位无法随线程保存
+        let avx = __cpuid_count(0xD, 2);
+        if features & (1 << 28) != 0 && (avx.ebx + avx.eax) as usize <= AREA_SIZE {
+            components |= XCR0_AVX;
+        }
+        unsafe {
+            asm!(
+                "xsetbv",
+                in("ecx") 0,
+                in("eax") components as u32,
+                in("edx") (components >> 32) as u32,
+                options(nomem, nostack, preserves_flags)
+            );
+        }
+        // cpuid 0xD ebx：按当前XCR0启用的组件所需的保存区大小
+        // 放不下时改用fxsave，此时XCR0只有x87与SSE，fxsave可以完整保存
+        let size = __cpuid_count(0xD, 0).ebx as usize;
+        XSAVE.store(size <= AREA_SIZE, Ordering::Relaxed);
+    }
+
+    klog!(
+        info,
+        "fpu",
+        "{}, components 0x{components:x}",
+        if XSAVE.load(Ordering::Relaxed) {
+            "xsave"
+        } else {
+            "fxsave"
+        }
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_xmm0(value: u64) {
+        unsafe {
+            asm!("movq xmm0, {}", in(reg) value, options(nomem, nostack, preserves_flags));
+        }
+    }
+
+    fn read_xmm0() -> u64 {
+        let value: u64;
+        unsafe {
+            asm!("movq {}, xmm0", out(reg) value, options(nomem, nostack, preserves_flags));
+        }
+        value
+    }
+
+    #[test_case]
+    fn test_save_restore() {
+        let mut state = FpuState::new();
+        write_xmm0(0x0123_4567_89AB_CDEF);
+        state.save();
+        write_xmm0(0);
+        state.restore();
+        assert_eq!(read_xmm0(), 0x0123_4567_89AB_CDEF);
+
+        FpuState::new().restore();
+        assert_eq!(read_xmm0(), 0);
+    }
+}
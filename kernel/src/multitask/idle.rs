@@ -0,0 +1,78 @@
+use core::{
+    arch::{asm, x86_64::__cpuid},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+use crate::time::clocksource;
+
+/// CPU是否支持monitor/mwait
+static MWAIT: AtomicBool = AtomicBool::new(false);
+/// CPU进入空闲状态的时间（纳秒），为0时表示CPU不处于空闲状态
+static IDLE_SINCE: AtomicU64 = AtomicU64::new(0);
+/// 已结束的空闲状态累计的时间（纳秒）
+static IDLE_NANOS: AtomicU64 = AtomicU64::new(0);
+
+/// 检测CPU的休眠指令，由创建IDLE线程时调用
+pub(super) fn init() {
+    // cpuid 1 ecx第3位：支持monitor/mwait
+    MWAIT.store(__cpuid(1).ecx & (1 << 3) != 0, Ordering::Relaxed);
+}
+
+/// 使CPU休眠，直到中断到达或`monitor`所在的缓存行被写入
+///
+/// 调用时中断必须处于关闭状态，调用方在关中断后确认没有可执行的任务，再调用此函数。
+/// sti的效果延迟到下一条指令之后，因此`sti; hlt`之间不会响应中断，
+/// 关中断后到达的中断会在进入休眠后立即唤醒CPU，不会错过唤醒。返回时中断已打开
+///
+/// CPU支持时使用monitor/mwait，其他CPU写入被监视的地址时同样会唤醒本CPU，否则使用hlt
+///
+/// Safety: 调用方需保证此时可以安全开中断，`monitor`为可读的内核地址
+pub(super) unsafe fn halt(monitor: *const u8) {
+    let since = clocksource::now_ns().max(1);
+    IDLE_SINCE.store(since, Ordering::Relaxed);
+    if MWAIT.load(Ordering::Relaxed) {
+        unsafe {
+            asm!(
+                "monitor",
+                in("rax") monitor,
+                in("ecx") 0,
+                in("edx") 0,
+                options(nostack, preserves_flags)
+            );
+            // eax为0：进入C1状态；ecx为0：仅在中断打开时由中断唤醒
+            asm!(
+                "sti",
+                "mwait",
+                in("eax") 0,
+                in("ecx") 0,
+                options(nostack, preserves_flags)
+            );
+        }
+    } else {
+        unsafe {
+            asm!("sti", "hlt", options(nomem, nostack, preserves_flags));
+        }
+    }
+}
+
+/// 结束空闲状态，将本次空闲的时间计入累计的空闲时间
+///
+/// 唤醒CPU的中断可能直接切换到其他线程，因此除休眠返回后外，切换出IDLE线程时同样需要调用
+pub(super) fn exit() {
+    let since = IDLE_SINCE.swap(0, Ordering::Relaxed);
+    if since != 0 {
+        let elapsed = clocksource::now_ns().saturating_sub(since);
+        IDLE_NANOS.fetch_add(elapsed, Ordering::Relaxed);
+    }
+}
+
+/// CPU处于空闲状态的总时间（纳秒），包含正在进行的空闲状态
+pub fn idle_time() -> u64 {
+    let since = IDLE_SINCE.load(Ordering::Relaxed);
+    let current = if since != 0 {
+        clocksource::now_ns().saturating_sub(since)
+    } else {
+        0
+    };
+    IDLE_NANOS.load(Ordering::Relaxed) + current
+}
@@ -1,5 +1,13 @@
 pub mod async_rt;
 pub mod async_task;
+pub mod capability;
 pub mod elf_loader;
+pub mod exec_verify;
+pub mod fpu;
+pub mod idle;
 pub mod process;
 pub mod thread;
+pub mod vdso;
+pub mod vma;
+pub mod watchdog;
+pub mod workqueue;
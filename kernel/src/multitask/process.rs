@@ -2,7 +2,9 @@ use core::{
     arch::naked_asm,
     mem::MaybeUninit,
     num::NonZeroU64,
+    ptr::NonNull,
     sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
 };
 
 use alloc::{
@@ -10,24 +12,39 @@ use alloc::{
     sync::Arc,
     vec::Vec,
 };
+use async_io::{AsyncRead, Seekable, SliceReader};
 use async_locks::{channel::oneshot, watch};
-use elf::ElfFile;
-use filesystem::path::PathBuf;
+use cos_sys::{
+    completion::Completion,
+    multitask::ProcessInfo,
+    vdso::{ProcessData, VDSO_ADDRESS, VDSO_SIZE},
+};
+use elf::{ElfFile, ElfTls};
+use filesystem::{
+    fs::{
+        FileSystemError,
+        permission::{FilePermission, ROOT_UID},
+    },
+    path::PathBuf,
+};
 
 use crate::{
-    io,
+    display, io, klog,
     memory::{
         self,
-        page::{AccessMemoryError, AllocateFrameOptions},
+        page::{AccessMemoryError, AllocMappedFrameError, AllocateFrameOptions},
     },
     multitask::{
         self,
+        capability::{self, Capabilities},
         elf_loader::ElfLoader,
+        exec_verify::{self, VerifyError},
         thread::{RSP0_SIZE, Thread},
+        vma::{Vma, VmaBacking, VmaKind, VmaTree},
     },
     sync::{int::IrqGuard, spin::SpinLock},
-    trap,
-    user::handle::HandleObject,
+    syscall, trap,
+    user::handle::{HandleError, HandleObject, HandleTable},
 };
 
 static PROCESSES: SpinLock<BTreeMap<u64, Arc<SpinLock<Process>>>> = SpinLock::new(BTreeMap::new());
@@ -37,6 +54,8 @@ static PROCESS_ID_GENERATOR: AtomicU64 = AtomicU64::new(0);
 pub struct Process {
     // 进程ID
     pub(super) process_id: u64,
+    // 父进程ID，None表示由内核直接创建
+    parent_id: Option<u64>,
     // 线程ID
     pub(super) thread_ids: BTreeSet<u64>,
     // 页表地址
@@ -48,9 +67,108 @@ pub struct Process {
     // 为其他进程wait预留
     exit_code_sub: watch::Subscriber<u64>,
     // 句柄
-    handles: Vec<Option<Arc<HandleObject>>>,
+    handles: HandleTable,
     // 等待中的线程 (通过wait/wake syscall)
     futex: BTreeMap<u64, VecDeque<oneshot::Sender<()>>>,
+    // 被内核固定的用户内存区域 [start, end)，在解除固定前不允许释放
+    pinned_ranges: Vec<(u64, u64)>,
+    // 是否为特权进程
+    privileged: bool,
+    // 能力集合
+    capabilities: Capabilities,
+    // 用户与组
+    credentials: Credentials,
+    // 进程组ID，即进程组中首个进程的ID
+    process_group: u64,
+    // 会话ID，即创建会话的进程的ID
+    session: u64,
+    // 输入输出使用的控制台
+    console: usize,
+    // 资源限制
+    limits: ProcessLimits,
+    // 已用于系统调用缓冲区的内核内存
+    kernel_memory_used: usize,
+    // 已完成但尚未被取出的异步请求
+    completions: VecDeque<Completion>,
+    // 等待异步请求完成的线程
+    completion_waiters: Vec<oneshot::Sender<()>>,
+    // 进行中的异步请求数量，包括已完成但尚未被取出的请求
+    async_requests: usize,
+    // 已映射的用户内存页数量
+    resident_pages: usize,
+    // 已映射的用户内存区域
+    regions: VmaTree,
+    // 已退出线程占用的CPU时间（us）
+    pub(super) cpu_time: u64,
+    // 启动参数，每个参数以\0结尾
+    args: Vec<u8>,
+    // 可执行文件的线程局部存储段，每个线程创建时据此初始化TLS块
+    tls: Option<ElfTls>,
+}
+
+/// 进程资源限制
+#[derive(Debug, Clone, Copy)]
+pub struct ProcessLimits {
+    /// 进程可用于系统调用缓冲区的内核内存总量
+    pub kernel_memory: usize,
+    /// 单次系统调用可传递的缓冲区最大长度
+    pub syscall_buffer: usize,
+    /// 同时进行中的异步请求数量
+    pub async_requests: usize,
+    /// 进程可持有的句柄数量
+    pub handles: usize,
+    /// 进程可映射的用户内存页数量
+    pub pages: usize,
+    /// 同时存在的子进程数量
+    pub children: usize,
+    /// 进程可占用的CPU时间（us），超出时进程被强制停止
+    pub cpu_time: usize,
+}
+
+impl ProcessLimits {
+    pub const DEFAULT: Self = Self {
+        kernel_memory: 32 * 1024 * 1024,
+        syscall_buffer: 16 * 1024 * 1024,
+        async_requests: 64,
+        handles: 1024,
+        pages: usize::MAX,
+        children: usize::MAX,
+        cpu_time: usize::MAX,
+    };
+}
+
+/// 检查进程CPU时间限制的间隔
+const CPU_LIMIT_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 进程的用户与组，决定进程对文件的访问权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Credentials {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl Credentials {
+    /// 超级用户，由内核直接创建的进程以超级用户运行
+    pub const ROOT: Self = Self {
+        uid: ROOT_UID,
+        gid: ROOT_UID,
+    };
+
+    pub fn is_root(&self) -> bool {
+        self.uid == ROOT_UID
+    }
+}
+
+/// 资源限制类型
+#[derive(Debug, Clone, Copy)]
+pub enum ProcessLimit {
+    KernelMemory,
+    SyscallBuffer,
+    AsyncRequests,
+    Handles,
+    Pages,
+    Children,
+    CpuTime,
 }
 
 impl Drop for Process {
@@ -63,7 +181,7 @@ impl Drop for Process {
 }
 
 /// 创建进程
-fn create_process() -> Option<Arc<SpinLock<Process>>> {
+fn create_process(parent_id: Option<u64>) -> Option<Arc<SpinLock<Process>>> {
     // 需要申请一页内存用作四级页表
     let page_table = memory::page::alloc_user_page_table()?;
 
@@ -71,18 +189,39 @@ fn create_process() -> Option<Arc<SpinLock<Process>>> {
     let (publisher, subscriber) = watch::pair(0);
     let process = Process {
         process_id,
+        parent_id,
         thread_ids: BTreeSet::new(),
         page_table,
         exit_code: publisher,
         exit_code_setted: false,
         exit_code_sub: subscriber,
-        handles: Vec::new(),
+        handles: HandleTable::new(),
         futex: BTreeMap::new(),
+        pinned_ranges: Vec::new(),
+        privileged: false,
+        capabilities: Capabilities::ALL,
+        credentials: Credentials::ROOT,
+        process_group: process_id,
+        session: process_id,
+        console: display::console::DEFAULT,
+        limits: ProcessLimits::DEFAULT,
+        kernel_memory_used: 0,
+        completions: VecDeque::new(),
+        completion_waiters: Vec::new(),
+        async_requests: 0,
+        resident_pages: 0,
+        regions: VmaTree::new(),
+        cpu_time: 0,
+        args: Vec::new(),
+        tls: None,
     };
     let process = Arc::new(SpinLock::new(process));
 
-    let _guard = IrqGuard::cli();
-    PROCESSES.lock().insert(process_id, process.clone());
+    {
+        let _guard = IrqGuard::cli();
+        PROCESSES.lock().insert(process_id, process.clone());
+    }
+    syscall::trace::inherit(parent_id, process_id);
 
     Some(process)
 }
@@ -102,10 +241,67 @@ pub fn get_process(id: u64) -> Option<Arc<SpinLock<Process>>> {
     PROCESSES.lock().get(&id).cloned()
 }
 
+/// 列出全部存活进程的ID，按ID升序排列
+pub fn list_processes() -> Vec<u64> {
+    let _guard = IrqGuard::cli();
+    PROCESSES.lock().keys().copied().collect()
+}
+
+/// 获取进程ID
+pub fn process_id(process: &SpinLock<Process>) -> u64 {
+    let _guard = IrqGuard::cli();
+    process.lock().process_id
+}
+
+/// 获取进程的启动参数
+pub fn process_args(process: &SpinLock<Process>) -> Vec<u8> {
+    let _guard = IrqGuard::cli();
+    process.lock().args.clone()
+}
+
+/// 获取进程信息
+pub fn process_info(process: &SpinLock<Process>) -> ProcessInfo {
+    let _guard = IrqGuard::cli();
+    let (mut info, thread_ids) = {
+        let process = process.lock();
+
+        let state = if process.exit_code_setted {
+            cos_sys::multitask::PROCESS_STATE_EXITING
+        } else {
+            cos_sys::multitask::PROCESS_STATE_RUNNING
+        };
+        let info = ProcessInfo {
+            process_id: process.process_id,
+            parent_id: process.parent_id.unwrap_or(0),
+            state,
+            thread_count: process.thread_ids.len() as u64,
+            handle_count: process.handles.len() as u64,
+            kernel_memory: process.kernel_memory_used as u64,
+            resident_pages: process.resident_pages as u64,
+            cpu_time: process.cpu_time,
+            process_group: process.process_group,
+            session: process.session,
+            console: process.console as u64,
+        };
+        (info, process.thread_ids.clone())
+    };
+
+    // 进程的CPU时间包括已退出线程与存活线程两部分
+    for thread_id in thread_ids {
+        if let Some(thread) = multitask::thread::get_thread(thread_id) {
+            info.cpu_time += multitask::thread::get_cpu_time(&thread);
+        }
+    }
+
+    info
+}
+
 pub enum ProcessPageType {
     Code,
     Stack,
     Data,
+    // 优先使用指定的虚拟地址，不可用时由内核选址
+    DataAt(NonZeroU64),
     StaticCode(NonZeroU64),
     StaticData(NonZeroU64),
     StaticConst(NonZeroU64),
@@ -119,37 +315,191 @@ pub fn create_process_page(
     size: usize,
     page_type: ProcessPageType,
 ) -> Option<NonZeroU64> {
-    let _guard = IrqGuard::cli();
-    let page_table = process.lock().page_table;
+    create_process_page_with_backing(process, size, page_type, VmaBacking::Anonymous)
+}
 
-    let options = match page_type {
-        ProcessPageType::Code => AllocateFrameOptions::USER_CODE,
-        ProcessPageType::Stack => AllocateFrameOptions::USER_DATA,
-        ProcessPageType::Data => AllocateFrameOptions::USER_DATA,
-        ProcessPageType::StaticCode(vaddr) => {
-            AllocateFrameOptions::USER_CODE.with_static_vaddr(vaddr)
+/// 为进程分配页，行为与 [`create_process_page`] 相同
+///
+/// `backing`为内存区域的后备对象，如加载可执行文件时为文件路径，记录于进程的内存区域表
+pub fn create_process_page_with_backing(
+    process: &SpinLock<Process>,
+    size: usize,
+    page_type: ProcessPageType,
+    backing: VmaBacking,
+) -> Option<NonZeroU64> {
+    let _guard = IrqGuard::cli();
+    let page_table = {
+        let process = process.lock();
+        if !has_page_quota(&process, size) {
+            return None;
         }
-        ProcessPageType::StaticData(vaddr) => {
-            AllocateFrameOptions::USER_DATA.with_static_vaddr(vaddr)
+        process.page_table
+    };
+
+    let (options, kind) = match page_type {
+        ProcessPageType::Code => (AllocateFrameOptions::USER_CODE, VmaKind::Image),
+        ProcessPageType::Stack => (AllocateFrameOptions::USER_DATA, VmaKind::Stack),
+        ProcessPageType::Data => (AllocateFrameOptions::USER_DATA, VmaKind::Data),
+        ProcessPageType::DataAt(vaddr) => (
+            AllocateFrameOptions::USER_DATA.with_static_vaddr(vaddr),
+            VmaKind::Data,
+        ),
+        ProcessPageType::StaticCode(vaddr) => (
+            AllocateFrameOptions::USER_CODE.with_static_vaddr(vaddr),
+            VmaKind::Image,
+        ),
+        ProcessPageType::StaticData(vaddr) => (
+            AllocateFrameOptions::USER_DATA.with_static_vaddr(vaddr),
+            VmaKind::Image,
+        ),
+        ProcessPageType::StaticConst(vaddr) => (
+            AllocateFrameOptions::USER_CONST.with_static_vaddr(vaddr),
+            VmaKind::Image,
+        ),
+    };
+
+    let mut virtual_ptr =
+        unsafe { memory::page::alloc_mapped_frame(page_table.get(), size, options) };
+    if virtual_ptr.is_err() && matches!(page_type, ProcessPageType::DataAt(_)) {
+        virtual_ptr = unsafe {
+            memory::page::alloc_mapped_frame(
+                page_table.get(),
+                size,
+                AllocateFrameOptions::USER_DATA,
+            )
+        };
+    }
+    let virtual_ptr = virtual_ptr.ok()?;
+
+    let start = virtual_ptr.addr().get() as u64;
+    let mut process = process.lock();
+    process.resident_pages += size.div_ceil(0x1000);
+    process.regions.insert(Vma {
+        start,
+        size: size as u64,
+        kind,
+        writable: options.writable(),
+        executable: options.executable(),
+        backing,
+    });
+
+    NonZeroU64::new(start)
+}
+
+/// 在已映射的进程内存区域之后原地扩展
+///
+/// [addr, addr + old_size) 必须是通过系统调用申请的内存，否则返回[`ProcessMemoryError::PageFault`]；
+/// 其后的虚拟地址已被占用或超出用户空间时返回[`ProcessMemoryError::Occupied`]，不会移动原区域。
+/// old_size与new_size必须对齐4K，且new_size大于old_size
+pub fn grow_process_page(
+    process: &SpinLock<Process>,
+    addr: u64,
+    old_size: usize,
+    new_size: usize,
+) -> Result<(), ProcessMemoryError> {
+    let _guard = IrqGuard::cli();
+    let page_table = {
+        let process = process.lock();
+        if !process.regions.covers(addr, old_size as u64, VmaKind::Data) {
+            return Err(ProcessMemoryError::PageFault);
         }
-        ProcessPageType::StaticConst(vaddr) => {
-            AllocateFrameOptions::USER_DATA.with_static_vaddr(vaddr)
+        if !has_page_quota(&process, new_size - old_size) {
+            return Err(ProcessMemoryError::QuotaExceeded);
         }
+        process.page_table
     };
 
-    let virtual_ptr = unsafe { memory::page::alloc_mapped_frame(page_table.get(), size, options) };
+    let end = addr + old_size as u64;
+    let grow_size = new_size - old_size;
+    if !memory::page::is_user_space_range(end, grow_size) {
+        return Err(ProcessMemoryError::Occupied);
+    }
+    let options = AllocateFrameOptions::USER_DATA.with_static_vaddr(NonZeroU64::new(end).unwrap());
+    unsafe { memory::page::alloc_mapped_frame(page_table.get(), grow_size, options) }.map_err(
+        |e| match e {
+            AllocMappedFrameError::OutOfPhysicalMemory => ProcessMemoryError::OutOfMemory,
+            AllocMappedFrameError::OutOfVirtualSpace | AllocMappedFrameError::ReservedVaddr => {
+                ProcessMemoryError::Occupied
+            }
+        },
+    )?;
+    let mut process = process.lock();
+    process.resident_pages += grow_size / 0x1000;
+    if !process.regions.extend(end, grow_size as u64) {
+        process.regions.insert(Vma {
+            start: end,
+            size: grow_size as u64,
+            kind: VmaKind::Data,
+            writable: true,
+            executable: false,
+            backing: VmaBacking::Anonymous,
+        });
+    }
 
-    virtual_ptr.ok()?.addr().try_into().ok()
+    Ok(())
 }
 
 /// 释放进程内存页
-pub unsafe fn free_process_page(process: &SpinLock<Process>, addr: usize, size: usize) {
+///
+/// 如果内存页中存在被内核固定的区域，则不会释放，并返回[`ProcessMemoryError::Pinned`]
+/// 如果区域中存在不是通过系统调用申请的内存页（未映射、可执行文件的段或线程栈），则不会释放，
+/// 并返回[`ProcessMemoryError::PageFault`]
+pub unsafe fn free_process_page(
+    process: &SpinLock<Process>,
+    addr: usize,
+    size: usize,
+) -> Result<(), ProcessMemoryError> {
     let _guard = IrqGuard::cli();
-    let page_table = process.lock().page_table;
+    // 释放期间持有进程锁，避免与固定操作交错
+    let mut process = process.lock();
+
+    let start = addr as u64;
+    let end = start + size as u64;
+    if process
+        .pinned_ranges
+        .iter()
+        .any(|&(pin_start, pin_end)| pin_start < end && start < pin_end)
+    {
+        return Err(ProcessMemoryError::Pinned);
+    }
+
+    // 整个区域都必须已映射，否则释放过程会访问不存在的页表项
+    if !process.regions.covers(start, size as u64, VmaKind::Data) {
+        return Err(ProcessMemoryError::PageFault);
+    }
 
     unsafe {
-        memory::page::free_mapped_frame(page_table.get(), addr, size);
+        memory::page::free_mapped_frame(process.page_table.get(), addr, size);
     }
+    process.resident_pages = process.resident_pages.saturating_sub(size.div_ceil(0x1000));
+    process.regions.remove(start, size as u64);
+
+    Ok(())
+}
+
+/// 判断进程能否再映射size字节的用户内存而不超出页数限制
+pub fn page_quota_available(process: &SpinLock<Process>, size: usize) -> bool {
+    let _guard = IrqGuard::cli();
+    has_page_quota(&process.lock(), size)
+}
+
+fn has_page_quota(process: &Process, size: usize) -> bool {
+    process
+        .resident_pages
+        .checked_add(size.div_ceil(0x1000))
+        .is_some_and(|pages| pages <= process.limits.pages)
+}
+
+/// 进程的内存区域，按起始地址升序排列
+pub fn list_process_regions(process: &SpinLock<Process>) -> Vec<Vma> {
+    let _guard = IrqGuard::cli();
+    process.lock().regions.iter().cloned().collect()
+}
+
+/// 查找进程中包含指定地址的内存区域
+pub fn find_process_region(process: &SpinLock<Process>, addr: u64) -> Option<Vma> {
+    let _guard = IrqGuard::cli();
+    process.lock().regions.find(addr).cloned()
 }
 
 #[derive(Debug)]
@@ -158,6 +508,106 @@ pub enum ProcessMemoryError {
     ProcessNotFound,
     /// 页表中不存在此虚拟地址的映射
     PageFault,
+    /// 地址范围溢出或超出用户空间
+    SegmentationFault,
+    /// 内存正在被内核使用
+    Pinned,
+    /// 内核虚拟空间或物理内存不足
+    OutOfMemory,
+    /// 超出进程资源限制
+    QuotaExceeded,
+    /// 虚拟地址已被占用
+    Occupied,
+}
+
+impl ProcessMemoryError {
+    /// 转换为系统调用错误码
+    pub fn error_kind(&self) -> cos_sys::error::ErrorKind {
+        match self {
+            ProcessMemoryError::ProcessNotFound => cos_sys::error::ErrorKind::BadArgument,
+            ProcessMemoryError::PageFault => cos_sys::error::ErrorKind::BadPointer,
+            ProcessMemoryError::SegmentationFault => cos_sys::error::ErrorKind::BadPointer,
+            ProcessMemoryError::Pinned => cos_sys::error::ErrorKind::BadArgument,
+            ProcessMemoryError::OutOfMemory => cos_sys::error::ErrorKind::OutOfMemory,
+            ProcessMemoryError::QuotaExceeded => cos_sys::error::ErrorKind::QuotaExceeded,
+            ProcessMemoryError::Occupied => cos_sys::error::ErrorKind::Occupied,
+        }
+    }
+}
+
+/// 检查并固定用户内存区域
+///
+/// 检查范围内的每一个内存页均已映射且可被用户态访问（如果writable为true，还需可写），
+/// 检查通过后将其加入固定列表，在调用[`unpin_user_process_memory`]之前，该区域不会被释放
+///
+/// 如果len超过进程的系统调用缓冲区限制，返回[`ProcessMemoryError::QuotaExceeded`]
+pub fn pin_user_process_memory(
+    process: &SpinLock<Process>,
+    addr: u64,
+    len: usize,
+    writable: bool,
+) -> Result<(), ProcessMemoryError> {
+    if len == 0 {
+        return Ok(());
+    }
+
+    let _guard = IrqGuard::cli();
+    let mut process = process.lock();
+
+    if len > process.limits.syscall_buffer {
+        return Err(ProcessMemoryError::QuotaExceeded);
+    }
+
+    memory::page::check_user_page_table_range(process.page_table.get(), addr, len, writable)
+        .map_err(|e| match e {
+            AccessMemoryError::PageFault => ProcessMemoryError::PageFault,
+        })?;
+    process.pinned_ranges.push((addr, addr + len as u64));
+
+    Ok(())
+}
+
+/// 将用户内存区域映射到内核空间，返回内核可直接访问的地址
+///
+/// 调用方需先通过[`pin_user_process_memory`]固定该区域，并在解除固定前调用[`unmap_user_process_memory`]
+pub fn map_user_process_memory(
+    process: &SpinLock<Process>,
+    addr: u64,
+    len: usize,
+) -> Result<NonNull<u8>, ProcessMemoryError> {
+    let _guard = IrqGuard::cli();
+    let page_table = process.lock().page_table;
+
+    unsafe { memory::page::map_kernel_alias(page_table.get(), addr, len) }
+        .ok_or(ProcessMemoryError::OutOfMemory)
+}
+
+/// 解除[`map_user_process_memory`]创建的映射
+///
+/// # Safety
+///
+/// ptr与len必须与[`map_user_process_memory`]一致，解除映射后不可再访问该区域
+pub unsafe fn unmap_user_process_memory(ptr: NonNull<u8>, len: usize) {
+    unsafe {
+        memory::page::unmap_kernel_alias(ptr.as_ptr() as usize, len);
+    }
+}
+
+/// 解除用户内存区域的固定
+///
+/// addr与len必须与[`pin_user_process_memory`]成功时的参数一致
+pub fn unpin_user_process_memory(process: &SpinLock<Process>, addr: u64, len: usize) {
+    if len == 0 {
+        return;
+    }
+
+    let _guard = IrqGuard::cli();
+    let mut process = process.lock();
+
+    let range = (addr, addr + len as u64);
+    if let Some(index) = process.pinned_ranges.iter().position(|r| *r == range) {
+        process.pinned_ranges.swap_remove(index);
+    }
 }
 
 /// 向进程空间写入内存
@@ -172,10 +622,9 @@ pub unsafe fn write_user_process_memory(
         process.lock().page_table
     };
     unsafe {
-        memory::page::write_page_table_memory(page_table.get(), addr, src, len)
-            .map_err(|e| match e {
-                AccessMemoryError::PageFault => ProcessMemoryError::PageFault,
-            })
+        memory::user_copy::copy_to_user(page_table.get(), addr, src, len).map_err(|e| match e {
+            AccessMemoryError::PageFault => ProcessMemoryError::PageFault,
+        })
     }
 }
 
@@ -208,6 +657,32 @@ pub unsafe fn write_user_process_memory_bytes(
     }
 }
 
+/// 向进程的程序映像写入内存，用于加载程序
+///
+/// 代码段与只读数据段在写入前已映射为只读，而CR0.WP开启后内核同样不能经用户地址写入只读页。
+/// 此函数通过页表找到物理页直接写入，不受页表权限限制
+///
+/// # Safety
+///
+/// 只能在进程开始执行前写入其程序段，不能用于复制系统调用的用户数据
+pub unsafe fn write_user_process_image(
+    process: &SpinLock<Process>,
+    addr: u64,
+    src: *const u8,
+    len: usize,
+) -> Result<(), ProcessMemoryError> {
+    let page_table = {
+        let _guard = IrqGuard::cli();
+        process.lock().page_table
+    };
+    unsafe {
+        memory::page::write_page_table_memory(page_table.get(), addr, src, len)
+            .map_err(|e| match e {
+                AccessMemoryError::PageFault => ProcessMemoryError::PageFault,
+            })
+    }
+}
+
 /// 从进程空间读取内存
 pub unsafe fn read_user_process_memory(
     process: &SpinLock<Process>,
@@ -220,11 +695,9 @@ pub unsafe fn read_user_process_memory(
         process.lock().page_table
     };
     unsafe {
-        memory::page::read_page_table_memory(page_table.get(), addr, dst, len).map_err(
-            |e| match e {
-                AccessMemoryError::PageFault => ProcessMemoryError::PageFault,
-            },
-        )
+        memory::user_copy::copy_from_user(page_table.get(), addr, dst, len).map_err(|e| match e {
+            AccessMemoryError::PageFault => ProcessMemoryError::PageFault,
+        })
     }
 }
 
@@ -236,42 +709,215 @@ pub unsafe fn read_user_process_memory_struct<T>(
     unsafe { read_user_process_memory(process, addr, dst as *mut T as *mut u8, size_of::<T>()) }
 }
 
+/// 创建用户进程失败原因
+#[derive(Debug)]
+pub enum CreateProcessError {
+    /// 可执行文件路径不合法
+    InvalidPath,
+    /// 文件系统尚未挂载
+    FileSystemUnavailable,
+    /// 打开或读取可执行文件失败
+    FileSystem(FileSystemError),
+    /// 可执行文件格式错误，或程序段不在用户空间
+    InvalidExecutable,
+    /// 可执行文件摘要与清单不一致
+    VerificationFailed,
+    /// 没有执行可执行文件的权限
+    PermissionDenied,
+    /// 内存不足
+    OutOfMemory,
+    /// 父进程的子进程数量已达到限制
+    QuotaExceeded,
+}
+
+/// 修改进程组失败原因
+#[derive(Debug)]
+pub enum ProcessGroupError {
+    /// 目标进程不是调用者或其子进程、位于其他会话，或是会话首进程
+    PermissionDenied,
+    /// 当前会话中不存在指定的进程组
+    GroupNotFound,
+}
+
 /// 创建用户进程
 ///
-/// 指定可执行文件路径，将加载指定可执行文件到用户空间，然后创建其主线程并运行代码
+/// 指定可执行文件路径，将加载指定可执行文件到用户空间，然后创建其主线程并运行代码。
+/// args为启动参数，每个参数以\0结尾，进程可通过系统调用读取。
+/// parent为父进程ID，由内核直接创建的进程为None。子进程继承父进程的资源限制、进程组与会话，
+/// 由内核直接创建的进程自成一个会话
 ///
 /// TODO: 需要优化失败路径的资源回收
-pub async fn create_user_process(exe: &str) -> Option<Arc<SpinLock<Process>>> {
+pub async fn create_user_process(
+    exe: &str,
+    args: Vec<u8>,
+    parent: Option<u64>,
+) -> Result<Arc<SpinLock<Process>>, CreateProcessError> {
     // 打开可执行文件
-    let path = PathBuf::from_str(exe).ok()?;
-    let fs = {
-        let _guard = IrqGuard::cli();
-        io::disk::FILE_SYSTEMS.lock().get(&0).cloned()
-    }?;
-    let mut file = fs.open_file(path.as_path()).await.ok()?;
+    let exe_path = PathBuf::from_str(exe).map_err(|_| CreateProcessError::InvalidPath)?;
+    let path = exe_path.as_path();
+    // 子进程继承父进程的用户与组，须具有可执行文件的执行权限
+    let parent_process = parent.and_then(get_process);
+    let credentials = parent_process
+        .as_ref()
+        .map_or(Credentials::ROOT, |parent| credentials(parent));
+    let (limits, group) = match &parent_process {
+        Some(parent) => {
+            let (limits, group) = {
+                let _guard = IrqGuard::cli();
+                let parent = parent.lock();
+                (
+                    parent.limits,
+                    Some((parent.process_group, parent.session, parent.console)),
+                )
+            };
+            if count_children(parent) >= limits.children {
+                return Err(CreateProcessError::QuotaExceeded);
+            }
+            (limits, group)
+        }
+        None => (ProcessLimits::DEFAULT, None),
+    };
+    if !io::permission::check(credentials, path, FilePermission::EXECUTE).await {
+        return Err(CreateProcessError::PermissionDenied);
+    }
+    let (fs, path) = io::vfs::resolve(&path).ok_or(CreateProcessError::FileSystemUnavailable)?;
+    let mut file = fs
+        .open_file(path)
+        .await
+        .map_err(CreateProcessError::FileSystem)?;
+    // 按清单校验可执行文件，校验过的文件从校验时读入的内容加载
+    let image = match exec_verify::verify(&exe_path, file.as_mut()).await {
+        Ok(image) => image,
+        Err(error) => {
+            file.close().await.map_err(CreateProcessError::FileSystem)?;
+            return Err(match error {
+                VerifyError::Mismatch => CreateProcessError::VerificationFailed,
+                VerifyError::FileSystem(error) => CreateProcessError::FileSystem(error),
+            });
+        }
+    };
+    // 能力集合为清单声明的能力与父进程能力的交集，没有清单时继承父进程的能力
+    let manifest = match capability::load_manifest(&exe_path).await {
+        Ok(manifest) => manifest,
+        Err(error) => {
+            file.close().await.map_err(CreateProcessError::FileSystem)?;
+            return Err(CreateProcessError::FileSystem(error));
+        }
+    };
+    let inherited = parent_process
+        .as_ref()
+        .map_or(Capabilities::ALL, |parent| capabilities(parent));
+    let capabilities = manifest.map_or(inherited, |manifest| manifest.intersection(inherited));
 
     // 创建进程
-    let Some(process) = create_process() else {
-        file.close().await.ok()?;
-        return None;
+    let Some(process) = create_process(parent) else {
+        file.close().await.map_err(CreateProcessError::FileSystem)?;
+        return Err(CreateProcessError::OutOfMemory);
     };
+    {
+        let _guard = IrqGuard::cli();
+        let mut process = process.lock();
+        process.args = args;
+        process.capabilities = capabilities;
+        process.credentials = credentials;
+        process.limits = limits;
+        if let Some((process_group, session, console)) = group {
+            process.process_group = process_group;
+            process.session = session;
+            process.console = console;
+        }
+    }
+    // 映射共享页，须在加载程序段之前，保证固定地址可用
+    if map_vdso(&process).is_none() {
+        file.close().await.map_err(CreateProcessError::FileSystem)?;
+        return Err(CreateProcessError::OutOfMemory);
+    }
 
     // 加载程序段
-    let Ok(mut elf) = ElfFile::from_io(file.as_mut()).await else {
-        file.close().await.ok()?;
-        return None;
+    let loaded = match &image {
+        Some(image) => load_elf(&process, &exe_path, SliceReader::new(image)).await,
+        None => load_elf(&process, &exe_path, file.as_mut()).await,
     };
-    let mut loader = ElfLoader::new(&process);
-    if elf.load(&mut loader).await.is_err() {
-        file.close().await.ok()?;
-        return None;
+    file.close().await.map_err(CreateProcessError::FileSystem)?;
+    let (entry_point, tls) = loaded?;
+    {
+        let _guard = IrqGuard::cli();
+        process.lock().tls = tls;
     }
-    // 入口点
-    let entry_point = elf.header().entry_point;
-    file.close().await.ok()?;
 
+    start_main_thread(&process, entry_point)?;
+
+    Ok(process)
+}
+
+/// 从io读取ELF可执行文件，将其程序段加载到进程中，返回入口点与线程局部存储段
+async fn load_elf<Io>(
+    process: &SpinLock<Process>,
+    exe_path: &PathBuf,
+    io: Io,
+) -> Result<(u64, Option<ElfTls>), CreateProcessError>
+where
+    Io: Seekable + AsyncRead + Send,
+{
+    let mut elf = ElfFile::from_io(io)
+        .await
+        .map_err(|_| CreateProcessError::InvalidExecutable)?;
+    // 加载前检查所有程序段，避免加载到一半才发现越界
+    if elf
+        .segments()
+        .any(|segment| !memory::page::is_user_space_range(segment.vaddr, segment.mem_size as usize))
+    {
+        return Err(CreateProcessError::InvalidExecutable);
+    }
+    let mut loader = ElfLoader::new(process, exe_path.clone());
+    elf.load(&mut loader)
+        .await
+        .map_err(|_| CreateProcessError::InvalidExecutable)?;
+    Ok((elf.header().entry_point, elf.tls()))
+}
+
+/// 以内存中的代码创建用户进程，用于内核测试
+///
+/// 代码复制到address处的只读可执行页中，进程不含其他程序段，以指定的用户与能力运行。
+/// 返回的进程尚未创建线程，调用方准备好进程内存后，以 [`start_main_thread`] 启动
+#[cfg(test)]
+pub fn create_code_process(
+    code: &[u8],
+    address: NonZeroU64,
+    credentials: Credentials,
+    capabilities: Capabilities,
+) -> Result<Arc<SpinLock<Process>>, CreateProcessError> {
+    let process = create_process(None).ok_or(CreateProcessError::OutOfMemory)?;
+    {
+        let _guard = IrqGuard::cli();
+        let mut process = process.lock();
+        process.credentials = credentials;
+        process.capabilities = capabilities;
+    }
+    map_vdso(&process).ok_or(CreateProcessError::OutOfMemory)?;
+    let size = code.len().next_multiple_of(0x1000);
+    create_process_page(&process, size, ProcessPageType::StaticCode(address))
+        .filter(|page| *page == address)
+        .ok_or(CreateProcessError::OutOfMemory)?;
+    unsafe { write_user_process_image(&process, address.get(), code.as_ptr(), code.len()) }
+        .map_err(|_| CreateProcessError::OutOfMemory)?;
+    Ok(process)
+}
+
+/// 创建进程的主线程，从entry_point开始执行
+pub fn start_main_thread(
+    process: &SpinLock<Process>,
+    entry_point: u64,
+) -> Result<(), CreateProcessError> {
     // 主线程用户态栈
-    let stack_page = create_process_page(&process, 0x1000, ProcessPageType::Stack)?;
+    let stack_page = create_process_page(process, 0x1000, ProcessPageType::Stack)
+        .ok_or(CreateProcessError::OutOfMemory)?;
+    // 主线程TLS块
+    let mut thread_pages = Vec::new();
+    let Some(fs_base) = create_thread_tls(process, &mut thread_pages) else {
+        free_thread_pages(process, &thread_pages);
+        return Err(CreateProcessError::OutOfMemory);
+    };
 
     // 主线程内核陷入栈
     let rsp0 = unsafe {
@@ -282,7 +928,10 @@ pub async fn create_user_process(exe: &str) -> Option<Arc<SpinLock<Process>>> {
             AllocateFrameOptions::KERNEL_DATA,
         )
     }
-    .ok()?;
+    .map_err(|_| {
+        free_thread_pages(process, &thread_pages);
+        CreateProcessError::OutOfMemory
+    })?;
     let rsp0 = rsp0.as_ptr() as usize;
 
     // 写入启动地址、栈地址
@@ -294,17 +943,22 @@ pub async fn create_user_process(exe: &str) -> Option<Arc<SpinLock<Process>>> {
 
     // 创建线程
     let _guard = IrqGuard::cli();
-    unsafe {
+    let thread = unsafe {
         multitask::thread::create_thread(
             Some(&mut *process.lock()),
             user_thread_entry as *const () as u64,
             rsp0 as u64 + RSP0_SIZE as u64 - 8 - 16,
             rsp0 as u64,
             false,
-        );
+        )
+    };
+    if let Some(thread) = thread {
+        let mut thread = thread.lock();
+        thread.fs_base = fs_base;
+        thread.user_pages = thread_pages;
     }
 
-    Some(process)
+    Ok(())
 }
 
 pub fn create_user_thread(
@@ -313,6 +967,34 @@ pub fn create_user_thread(
     rsp: u64,
     params: u64,
 ) -> Option<Arc<SpinLock<Thread>>> {
+    let mut thread_pages = Vec::new();
+    let thread = spawn_user_thread(process, rip, rsp, params, &mut thread_pages);
+    match &thread {
+        Some(thread) => thread.lock().user_pages = thread_pages,
+        None => free_thread_pages(process, &thread_pages),
+    }
+    thread
+}
+
+/// 创建用户线程，内核为线程分配的用户内存记录于thread_pages，失败时由调用方释放
+fn spawn_user_thread(
+    process: &SpinLock<Process>,
+    rip: u64,
+    rsp: u64,
+    params: u64,
+    thread_pages: &mut Vec<(u64, usize)>,
+) -> Option<Arc<SpinLock<Thread>>> {
+    // 用户态栈，未指定时由内核分配，线程退出后释放
+    let rsp = if rsp == 0 {
+        let stack = create_process_page(process, 0x1000, ProcessPageType::Stack)?.get();
+        thread_pages.push((stack, 0x1000));
+        stack + 0x1000 - 8
+    } else {
+        rsp
+    };
+    // 线程TLS块
+    let fs_base = create_thread_tls(process, thread_pages)?;
+
     // 线程内核陷入栈
     let rsp0 = unsafe {
         let _guard = IrqGuard::cli();
@@ -334,17 +1016,139 @@ pub fn create_user_thread(
 
     // 创建线程
     let _guard = IrqGuard::cli();
-    unsafe {
+    let thread = unsafe {
         multitask::thread::create_thread(
             Some(&mut *process.lock()),
             user_thread_entry as *const () as u64,
             rsp0 as u64 + RSP0_SIZE as u64 - 8 - 16,
             rsp0 as u64,
             false,
-        );
+        )
+    }?;
+    thread.lock().fs_base = fs_base;
+
+    Some(thread)
+}
+
+/// 映射共享页，见 [cos_sys::vdso]
+///
+/// 第一页为所有进程共享的系统数据页，第二页为进程独占的进程数据页，映射后内容不再改变
+fn map_vdso(process: &SpinLock<Process>) -> Option<()> {
+    let system_page = multitask::vdso::system_page_physical()?;
+    let process_page = NonZeroU64::new(VDSO_ADDRESS + 0x1000).unwrap();
+
+    let _guard = IrqGuard::cli();
+    let mut process = process.lock();
+    let page_table = process.page_table.get();
+    let data = ProcessData {
+        process_id: process.process_id,
+        parent_id: process.parent_id.unwrap_or(0),
+    };
+    unsafe {
+        memory::page::map_user_shared_frame(page_table, VDSO_ADDRESS, system_page).ok()?;
+        memory::page::alloc_mapped_frame(
+            page_table,
+            0x1000,
+            AllocateFrameOptions::USER_CONST.with_static_vaddr(process_page),
+        )
+        .ok()?;
+        // 新申请的页未清零，先清零再写入，避免泄露其他进程的数据
+        memory::page::write_page_table_memory_bytes(page_table, process_page.get(), 0, 0x1000)
+            .ok()?;
+        memory::page::write_page_table_memory(
+            page_table,
+            process_page.get(),
+            &data as *const ProcessData as *const u8,
+            size_of::<ProcessData>(),
+        )
+        .ok()?;
+    }
+
+    process.resident_pages += 1;
+    process.regions.insert(Vma {
+        start: VDSO_ADDRESS,
+        size: VDSO_SIZE,
+        kind: VmaKind::Vdso,
+        writable: false,
+        executable: false,
+        backing: VmaBacking::Anonymous,
+    });
+
+    Some(())
+}
+
+/// 线程控制块的大小，其第一个字保存指向自身的指针
+const TCB_SIZE: u64 = 64;
+
+/// 为新线程创建TLS块，返回线程的FS基址
+///
+/// 采用x86-64的TLS布局（variant II）：TLS块紧邻线程指针（即FS基址）之前，
+/// 线程指针处为线程控制块，fs:0保存线程指针自身，TLS变量以相对线程指针的负偏移访问。
+/// 程序没有TLS段时返回0，内存不足时返回None。TLS块记录于thread_pages，线程退出后释放
+fn create_thread_tls(
+    process: &SpinLock<Process>,
+    thread_pages: &mut Vec<(u64, usize)>,
+) -> Option<u64> {
+    let tls = {
+        let _guard = IrqGuard::cli();
+        process.lock().tls
+    };
+    let Some(tls) = tls else {
+        return Some(0);
+    };
+
+    // 进程页按4K对齐，ELF已限制对齐要求不超过4K，因此TLS块的起始地址总是满足对齐
+    let offset = tls.mem_size.next_multiple_of(tls.align.max(8));
+    let size = (offset + TCB_SIZE).next_multiple_of(0x1000);
+    let block = create_process_page(process, size as usize, ProcessPageType::Stack)?.get();
+    thread_pages.push((block, size as usize));
+    let thread_pointer = block + offset;
+
+    unsafe {
+        // 复制初始化镜像，其余部分（.tbss与线程控制块）清零
+        let mut buf = [0u8; 512];
+        let mut copied = 0;
+        while copied < tls.file_size {
+            let step = buf.len().min((tls.file_size - copied) as usize);
+            read_user_process_memory(process, tls.vaddr + copied, buf.as_mut_ptr(), step).ok()?;
+            write_user_process_memory(process, block + copied, buf.as_ptr(), step).ok()?;
+            copied += step as u64;
+        }
+        write_user_process_memory_bytes(
+            process,
+            block + tls.file_size,
+            0,
+            (size - tls.file_size) as usize,
+        )
+        .ok()?;
+        write_user_process_memory_struct(process, thread_pointer, &thread_pointer).ok()?;
     }
 
-    None
+    Some(thread_pointer)
+}
+
+/// 释放内核为线程分配的用户栈与TLS块
+///
+/// 区域不存在时跳过释放。区域被内核固定时同样跳过，不会归还正在访问的内存
+pub(super) fn free_thread_pages(process: &SpinLock<Process>, thread_pages: &[(u64, usize)]) {
+    let _guard = IrqGuard::cli();
+    let mut process = process.lock();
+    for &(start, size) in thread_pages {
+        let end = start + size as u64;
+        if !process.regions.covers(start, size as u64, VmaKind::Stack)
+            || process
+                .pinned_ranges
+                .iter()
+                .any(|&(pin_start, pin_end)| pin_start < end && start < pin_end)
+        {
+            continue;
+        }
+        unsafe {
+            memory::page::free_mapped_frame(process.page_table.get(), start as usize, size);
+        }
+        process.resident_pages = process.resident_pages.saturating_sub(size.div_ceil(0x1000));
+        process.regions.remove(start, size as u64);
+    }
 }
 
 // 用户线程入口点
@@ -384,36 +1188,396 @@ pub fn stop_all_thread(process: &SpinLock<Process>, exit_code: u64) {
 }
 
 pub(super) fn stop_process(process_id: u64) {
+    {
+        let _guard = IrqGuard::cli();
+        PROCESSES.lock().remove(&process_id);
+    }
+    syscall::trace::forget(process_id);
+    // 会话ID即会话首进程的ID，首进程退出后释放会话控制的控制台
+    io::console::release_session(process_id);
+}
+
+/// 将进程标记为特权进程
+///
+/// 特权进程可以调整其他进程的资源限制
+pub fn set_privileged(process: &SpinLock<Process>) {
     let _guard = IrqGuard::cli();
-    PROCESSES.lock().remove(&process_id);
+    process.lock().privileged = true;
 }
 
-pub fn get_exit_code_subscriber(process: &SpinLock<Process>) -> watch::Subscriber<u64> {
-    process.lock().exit_code_sub.clone()
+/// 判断进程是否为特权进程
+pub fn is_privileged(process: &SpinLock<Process>) -> bool {
+    let _guard = IrqGuard::cli();
+    process.lock().privileged
+}
+
+/// 判断caller能否查看target的内部状态，如内存映射
+///
+/// 仅允许查看自身与子进程，超级用户与特权进程可以查看任意进程
+pub fn can_inspect(caller: &SpinLock<Process>, target: &SpinLock<Process>) -> bool {
+    let (caller_id, root, privileged) = {
+        let _guard = IrqGuard::cli();
+        let caller = caller.lock();
+        (
+            caller.process_id,
+            caller.credentials.is_root(),
+            caller.privileged,
+        )
+    };
+    if root || privileged {
+        return true;
+    }
+    let _guard = IrqGuard::cli();
+    let target = target.lock();
+    target.process_id == caller_id || target.parent_id == Some(caller_id)
+}
+
+/// 获取进程的能力集合
+pub fn capabilities(process: &SpinLock<Process>) -> Capabilities {
+    let _guard = IrqGuard::cli();
+    process.lock().capabilities
 }
 
-pub fn insert_process_handle(process: &SpinLock<Process>, handle: HandleObject) -> usize {
+/// 判断进程是否具有指定能力
+pub fn has_capability(process: &SpinLock<Process>, capability: Capabilities) -> bool {
+    capabilities(process).contains(capability)
+}
+
+/// 获取进程的用户与组
+pub fn credentials(process: &SpinLock<Process>) -> Credentials {
+    let _guard = IrqGuard::cli();
+    process.lock().credentials
+}
+
+/// 修改进程的用户与组
+///
+/// 超级用户可以切换为任意用户与组，其他用户只能切换为自身，因此放弃超级用户身份后无法恢复
+pub fn set_credentials(process: &SpinLock<Process>, credentials: Credentials) -> bool {
+    let _guard = IrqGuard::cli();
     let mut process = process.lock();
+    if !process.credentials.is_root() && process.credentials != credentials {
+        return false;
+    }
+    process.credentials = credentials;
+    true
+}
 
-    for (i, slot) in process.handles.iter_mut().enumerate() {
-        if slot.is_none() {
-            *slot = Some(Arc::new(handle));
-            return i;
+/// 设置进程资源限制
+///
+/// 新的限制仅影响之后的申请，已占用的资源不会被回收
+pub fn set_process_limit(process: &SpinLock<Process>, limit: ProcessLimit, value: usize) {
+    let _guard = IrqGuard::cli();
+    let mut process = process.lock();
+    match limit {
+        ProcessLimit::KernelMemory => process.limits.kernel_memory = value,
+        ProcessLimit::SyscallBuffer => process.limits.syscall_buffer = value,
+        ProcessLimit::AsyncRequests => process.limits.async_requests = value,
+        ProcessLimit::Handles => process.limits.handles = value,
+        ProcessLimit::Pages => process.limits.pages = value,
+        ProcessLimit::Children => process.limits.children = value,
+        ProcessLimit::CpuTime => process.limits.cpu_time = value,
+    }
+}
+
+/// 获取进程资源限制
+pub fn process_limit(process: &SpinLock<Process>, limit: ProcessLimit) -> usize {
+    let _guard = IrqGuard::cli();
+    let limits = process.lock().limits;
+    match limit {
+        ProcessLimit::KernelMemory => limits.kernel_memory,
+        ProcessLimit::SyscallBuffer => limits.syscall_buffer,
+        ProcessLimit::AsyncRequests => limits.async_requests,
+        ProcessLimit::Handles => limits.handles,
+        ProcessLimit::Pages => limits.pages,
+        ProcessLimit::Children => limits.children,
+        ProcessLimit::CpuTime => limits.cpu_time,
+    }
+}
+
+/// 获取进程的进程组ID与会话ID
+pub fn process_group(process: &SpinLock<Process>) -> (u64, u64) {
+    let _guard = IrqGuard::cli();
+    let process = process.lock();
+    (process.process_group, process.session)
+}
+
+/// 获取进程使用的控制台，子进程继承父进程的控制台
+pub fn process_console(process: &SpinLock<Process>) -> usize {
+    let _guard = IrqGuard::cli();
+    process.lock().console
+}
+
+/// 将目标进程移入进程组，返回进程组ID
+///
+/// 目标进程须为调用者自身或其子进程，且与调用者位于同一会话，会话首进程不能移动。
+/// process_group为0时，以目标进程的ID创建新的进程组；否则须为当前会话中已存在的进程组
+pub fn set_process_group(
+    caller: &SpinLock<Process>,
+    target: &SpinLock<Process>,
+    process_group: u64,
+) -> Result<u64, ProcessGroupError> {
+    let (caller_id, session) = {
+        let _guard = IrqGuard::cli();
+        let caller = caller.lock();
+        (caller.process_id, caller.session)
+    };
+    let (target_id, target_parent, target_session) = {
+        let _guard = IrqGuard::cli();
+        let target = target.lock();
+        (target.process_id, target.parent_id, target.session)
+    };
+    if (target_id != caller_id && target_parent != Some(caller_id))
+        || target_session != session
+        || target_id == session
+    {
+        return Err(ProcessGroupError::PermissionDenied);
+    }
+
+    let process_group = if process_group == 0 {
+        target_id
+    } else {
+        process_group
+    };
+    if process_group != target_id && !group_exists(process_group, session) {
+        return Err(ProcessGroupError::GroupNotFound);
+    }
+    let _guard = IrqGuard::cli();
+    target.lock().process_group = process_group;
+    Ok(process_group)
+}
+
+/// 以调用者创建新的会话与进程组，返回会话ID
+///
+/// 调用者已是进程组的首进程时返回None，避免同一进程组分属不同会话
+pub fn create_session(process: &SpinLock<Process>) -> Option<u64> {
+    let _guard = IrqGuard::cli();
+    let mut process = process.lock();
+    if process.process_group == process.process_id {
+        return None;
+    }
+    process.process_group = process.process_id;
+    process.session = process.process_id;
+    Some(process.session)
+}
+
+/// 判断会话中是否存在指定的进程组
+pub fn group_exists(process_group: u64, session: u64) -> bool {
+    processes_snapshot().iter().any(|process| {
+        let _guard = IrqGuard::cli();
+        let process = process.lock();
+        process.process_group == process_group && process.session == session
+    })
+}
+
+/// 强制停止进程组中的全部进程，退出码为 [`cos_sys::multitask::EXIT_INTERRUPT`]
+pub fn interrupt_group(process_group: u64) {
+    for process in processes_snapshot() {
+        let _guard = IrqGuard::cli();
+        if process.lock().process_group != process_group {
+            continue;
         }
+        set_exit_code(&process, cos_sys::multitask::EXIT_INTERRUPT);
+        stop_all_thread(&process, cos_sys::multitask::EXIT_INTERRUPT);
+    }
+}
+
+/// 全部存活进程
+///
+/// 持有PROCESSES锁时不能获取进程锁，线程退出时会在持有进程锁的情况下获取PROCESSES锁，
+/// 因此先复制进程列表，再逐个获取进程锁
+fn processes_snapshot() -> Vec<Arc<SpinLock<Process>>> {
+    let _guard = IrqGuard::cli();
+    PROCESSES.lock().values().cloned().collect()
+}
+
+/// 统计进程存活的子进程数量，包括正在退出的子进程
+fn count_children(process: &SpinLock<Process>) -> usize {
+    let process_id = process_id(process);
+    processes_snapshot()
+        .iter()
+        .filter(|child| {
+            let _guard = IrqGuard::cli();
+            child.lock().parent_id == Some(process_id)
+        })
+        .count()
+}
+
+/// 周期性检查各进程占用的CPU时间，强制停止超出限制的进程，退出码为 [`cos_sys::multitask::EXIT_KILL`]
+///
+/// 由内核在启动时创建为异步任务，不会返回。CPU时间按计时器中断近似统计，
+/// 进程可能在超出限制后继续运行至多一个检查间隔
+pub async fn enforce_cpu_limits() {
+    loop {
+        multitask::async_task::sleep(CPU_LIMIT_CHECK_INTERVAL).await;
+
+        for process in processes_snapshot() {
+            let limit = {
+                let _guard = IrqGuard::cli();
+                let process = process.lock();
+                if process.exit_code_setted {
+                    continue;
+                }
+                process.limits.cpu_time
+            };
+            if limit == usize::MAX {
+                continue;
+            }
+            let info = process_info(&process);
+            if info.cpu_time > limit as u64 {
+                klog!(
+                    warn,
+                    "process",
+                    "process {} exceeded cpu time limit {limit}us, killed",
+                    info.process_id
+                );
+                let _guard = IrqGuard::cli();
+                set_exit_code(&process, cos_sys::multitask::EXIT_KILL);
+                stop_all_thread(&process, cos_sys::multitask::EXIT_KILL);
+            }
+        }
+    }
+}
+
+/// 为进程申请内核内存配额
+///
+/// 当进程已用内核内存加上size超过限制时，返回[`ProcessMemoryError::QuotaExceeded`]
+/// 申请成功后，需在内核内存释放时调用[`release_kernel_memory`]归还配额
+pub fn charge_kernel_memory(
+    process: &SpinLock<Process>,
+    size: usize,
+) -> Result<(), ProcessMemoryError> {
+    let _guard = IrqGuard::cli();
+    let mut process = process.lock();
+
+    let used = process
+        .kernel_memory_used
+        .checked_add(size)
+        .ok_or(ProcessMemoryError::QuotaExceeded)?;
+    if used > process.limits.kernel_memory {
+        return Err(ProcessMemoryError::QuotaExceeded);
+    }
+    process.kernel_memory_used = used;
+
+    Ok(())
+}
+
+/// 归还[`charge_kernel_memory`]申请的内核内存配额
+pub fn release_kernel_memory(process: &SpinLock<Process>, size: usize) {
+    let _guard = IrqGuard::cli();
+    let mut process = process.lock();
+    process.kernel_memory_used = process.kernel_memory_used.saturating_sub(size);
+}
+
+/// 为异步请求预留完成队列位置
+///
+/// 当进行中的异步请求数量达到限制时返回false。
+/// 预留成功后，必须调用[`post_completion`]提交完成结果
+pub fn reserve_completion(process: &SpinLock<Process>) -> bool {
+    let _guard = IrqGuard::cli();
+    let mut process = process.lock();
+
+    if process.async_requests >= process.limits.async_requests {
+        return false;
     }
+    process.async_requests += 1;
+
+    true
+}
+
+/// 提交异步请求的完成结果，并唤醒全部等待中的线程
+pub fn post_completion(process: &SpinLock<Process>, completion: Completion) {
+    let _guard = IrqGuard::cli();
+    let mut process = process.lock();
 
-    process.handles.push(Some(Arc::new(handle)));
-    process.handles.len() - 1
+    process.completions.push_back(completion);
+    // 接收者会收到SenderLost并被唤醒
+    process.completion_waiters.clear();
 }
 
-pub fn get_process_handle(process: &SpinLock<Process>, index: usize) -> Option<Arc<HandleObject>> {
-    process.lock().handles.get(index).cloned().flatten()
+/// 取出最多max个已完成的异步请求
+pub fn take_completions(process: &SpinLock<Process>, max: usize) -> Vec<Completion> {
+    let _guard = IrqGuard::cli();
+    let mut process = process.lock();
+
+    let count = process.completions.len().min(max);
+    let completions: Vec<Completion> = process.completions.drain(..count).collect();
+    process.async_requests -= completions.len();
+
+    completions
 }
 
-pub fn remove_process_handle(process: &SpinLock<Process>, index: usize) {
-    if let Some(slot) = process.lock().handles.get_mut(index) {
-        *slot = None;
+/// 判断进程是否存在进行中的异步请求
+pub fn has_async_requests(process: &SpinLock<Process>) -> bool {
+    let _guard = IrqGuard::cli();
+    process.lock().async_requests != 0
+}
+
+/// 注册等待异步请求完成的线程
+///
+/// 如果完成队列不为空，或者没有进行中的请求，则不会注册并返回false
+pub fn register_completion_waiter(
+    process: &SpinLock<Process>,
+    sender: oneshot::Sender<()>,
+) -> bool {
+    let _guard = IrqGuard::cli();
+    let mut process = process.lock();
+
+    if !process.completions.is_empty() || process.async_requests == 0 {
+        return false;
     }
+    process.completion_waiters.push(sender);
+
+    true
+}
+
+pub fn get_exit_code_subscriber(process: &SpinLock<Process>) -> watch::Subscriber<u64> {
+    process.lock().exit_code_sub.clone()
+}
+
+/// 向进程插入句柄对象，返回用户句柄
+///
+/// 当句柄数量达到进程限制时，返回[`HandleError::TableFull`]
+pub fn insert_process_handle(
+    process: &SpinLock<Process>,
+    handle: HandleObject,
+) -> Result<u64, HandleError> {
+    let _guard = IrqGuard::cli();
+    let mut process = process.lock();
+    let capacity = process.limits.handles;
+    process.handles.insert(handle, capacity)
+}
+
+/// 根据用户句柄获取句柄对象，句柄已关闭或不存在时返回[`HandleError::BadHandle`]
+pub fn get_process_handle(
+    process: &SpinLock<Process>,
+    handle: u64,
+) -> Result<Arc<HandleObject>, HandleError> {
+    let _guard = IrqGuard::cli();
+    process.lock().handles.get(handle)
+}
+
+/// 关闭用户句柄
+///
+/// 句柄对象会在所有引用释放后回收
+pub fn remove_process_handle(process: &SpinLock<Process>, handle: u64) -> Result<(), HandleError> {
+    let object = {
+        let _guard = IrqGuard::cli();
+        process.lock().handles.remove(handle)?
+    };
+    // 句柄对象的释放可能较为耗时（如关闭文件），不应在持有进程锁时进行
+    drop(object);
+    Ok(())
+}
+
+/// 列出进程的全部句柄及其类型
+pub fn list_process_handles(process: &SpinLock<Process>) -> Vec<(u64, u64)> {
+    let _guard = IrqGuard::cli();
+    process
+        .lock()
+        .handles
+        .iter()
+        .map(|(handle, object)| (handle, object.kind()))
+        .collect()
 }
 
 pub fn register_futex_if_match(
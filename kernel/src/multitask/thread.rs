@@ -1,6 +1,7 @@
 use core::{
     arch::{asm, naked_asm},
-    mem::MaybeUninit,
+    fmt::{self, Write},
+    mem::{self, MaybeUninit},
     num::NonZeroU64,
     ptr::{self, null_mut},
     sync::atomic::{AtomicU64, Ordering},
@@ -11,17 +12,24 @@ use alloc::{
     boxed::Box,
     collections::{btree_map::BTreeMap, vec_deque::VecDeque},
     sync::{Arc, Weak},
+    vec::Vec,
 };
 use async_locks::watch;
+use kernel_core::{
+    hal::Hal,
+    sched::{self, ThreadStatus},
+};
 
 use crate::{
+    hal::X86Hal,
     memory,
-    multitask::{self, process::Process},
+    multitask::{self, fpu::FpuState, idle, process::Process},
     sync::{
         self,
-        int::{IrqGuard, sti},
+        int::{IrqGuard, cli, sti},
         spin::SpinLock,
     },
+    time::clocksource,
     trap,
 };
 
@@ -35,6 +43,9 @@ static THREAD_ID_GENERATOR: AtomicU64 = AtomicU64::new(0);
 const RSP0_PAGE_COUNT: usize = 2;
 pub(super) const RSP0_SIZE: usize = 0x1000 * RSP0_PAGE_COUNT;
 
+// FS段基址MSR，用户线程以此作为线程指针
+const IA32_FS_BASE: u32 = 0xC000_0100;
+
 // 内核线程或用户线程
 pub struct Thread {
     // 线程ID
@@ -53,6 +64,14 @@ pub struct Thread {
     exit_code_sub: watch::Subscriber<u64>,
     // 异步任务执行时的唤醒对象
     waker: Option<Waker>,
+    // 占用的CPU时间（us）
+    cpu_time: u64,
+    // 扩展状态（x87、SSE等），内核线程不使用浮点与SIMD指令，为None
+    fpu: Option<FpuState>,
+    // FS段基址，即用户线程的线程指针，0表示未设置
+    pub(super) fs_base: u64,
+    // 内核为线程在进程空间中分配的内存（用户栈、TLS块），线程退出后释放
+    pub(super) user_pages: Vec<(u64, usize)>,
 }
 
 impl Drop for Thread {
@@ -61,6 +80,8 @@ impl Drop for Thread {
         let rsp0 = self.rsp0.take();
         let process_id = self.process_id.take();
         let exit_code = *self.exit_code_sub.borrow();
+        let cpu_time = self.cpu_time;
+        let user_pages = mem::take(&mut self.user_pages);
         multitask::async_rt::spawn(async move {
             if let Some(rsp0) = rsp0 {
                 unsafe {
@@ -74,9 +95,11 @@ impl Drop for Thread {
 
             if let Some(process_id) = process_id {
                 if let Some(process) = multitask::process::get_process(process_id.get()) {
+                    multitask::process::free_thread_pages(&process, &user_pages);
                     let _guard = IrqGuard::cli();
                     let mut process = process.lock();
                     process.thread_ids.remove(&thread_id);
+                    process.cpu_time += cpu_time;
                     if process.thread_ids.is_empty() {
                         multitask::process::set_exit_code_with_lock(&mut *process, exit_code);
                         multitask::process::stop_process(process_id.get());
@@ -100,16 +123,6 @@ pub struct Context {
     pub rsp: u64,
 }
 
-// 线程状态
-#[derive(Debug, Clone, Copy)]
-pub enum ThreadStatus {
-    Ready,       // 就绪，调度器可以将此线程调度到CPU上执行
-    Running,     // 执行，此线程当前正在CPU上执行
-    Suspend,     // 挂起，此线程正在等待某项资源而被暂停执行
-    Terminating, // 停止中，此线程已被要求停止，但它仍在占用CPU，在其离开CPU后会被终止
-    Terminated,  // 终止，此线程已被终止，但其仍持有资源。稍后内核将对其进行清理
-}
-
 impl Context {
     const fn uninit() -> Self {
         unsafe { MaybeUninit::zeroed().assume_init() }
@@ -156,6 +169,10 @@ pub unsafe fn create_thread(
         exit_code: publisher,
         exit_code_sub: subscriber,
         waker: None,
+        cpu_time: 0,
+        fpu: process_id.map(|_| FpuState::new()),
+        fs_base: 0,
+        user_pages: Vec::new(),
     };
     let thread = Arc::new(SpinLock::new(thread));
 
@@ -179,12 +196,20 @@ pub fn create_idle_thread() {
         loop {
             sti();
             try_yield_thread();
-            unsafe {
-                asm!("hlt");
+            // 关中断后再检查就绪队列，此后唤醒线程的中断会在休眠后立即唤醒CPU
+            cli();
+            if READY_THREADS.lock().is_empty() {
+                // Safety: IDLE线程不持有任何锁，可以开中断；就绪队列是静态变量，总是可读
+                unsafe {
+                    idle::halt(&raw const READY_THREADS as *const u8);
+                }
+                idle::exit();
             }
         }
     }
 
+    idle::init();
+
     let stack = Box::leak(Box::new(MaybeUninit::<[u8; 4096]>::uninit())) as *mut _ as usize as u64
         + 4096
         - 8;
@@ -203,6 +228,10 @@ pub fn create_idle_thread() {
         exit_code: publisher,
         exit_code_sub: subscriber,
         waker: None,
+        cpu_time: 0,
+        fpu: None,
+        fs_base: 0,
+        user_pages: Vec::new(),
     };
     let thread = Arc::new(SpinLock::new(thread));
 
@@ -228,6 +257,10 @@ pub fn create_kernel_async_thread() {
         exit_code: publisher,
         exit_code_sub: subscriber,
         waker: None,
+        cpu_time: 0,
+        fpu: None,
+        fs_base: 0,
+        user_pages: Vec::new(),
     };
     let thread = Arc::new(SpinLock::new(thread));
 
@@ -254,9 +287,7 @@ pub fn wake_kernel_thread() {
 pub fn wake_thread(thread: &Arc<SpinLock<Thread>>) {
     let _guard = IrqGuard::cli();
     let mut thread_lock = thread.lock();
-    let status = thread_lock.status;
-    if matches!(status, ThreadStatus::Suspend) {
-        thread_lock.status = ThreadStatus::Ready;
+    if thread_lock.status.wake() {
         drop(thread_lock);
         READY_THREADS.lock().push_back(Arc::downgrade(thread));
     }
@@ -332,19 +363,21 @@ unsafe fn switch_thread(
                 thread_id = thread.thread_id;
                 // 上下文
                 thread.context = ptr::read(ctx);
-                // 状态
-                if matches!(thread.status, ThreadStatus::Terminating) {
-                    thread.status = ThreadStatus::Terminated;
-                } else if suspend {
-                    thread.status = ThreadStatus::Suspend;
-                } else {
-                    thread.status = ThreadStatus::Ready;
+                // 扩展状态
+                if let Some(fpu) = &mut thread.fpu {
+                    fpu.save();
                 }
+                // 状态
+                thread.status = thread.status.switched_out(suspend);
                 thread.status
             };
 
             // 将旧线程放入指定队列，如果是IDLE线程，则不放入
             let is_idle_thread = thread_id == sync::percpu::get_idle_thread_id();
+            if is_idle_thread {
+                // 唤醒CPU的中断直接切换到了其他线程，在此结束空闲计时
+                idle::exit();
+            }
             if !is_idle_thread {
                 match new_status {
                     ThreadStatus::Ready => READY_THREADS.lock().push_back(Arc::downgrade(&thread)),
@@ -373,10 +406,14 @@ unsafe fn switch_thread(
             let thread_id = lock.thread_id;
             // 获取上下文信息
             let context = &raw const lock.context;
-            // 将新线程设置为运行状态
-            if matches!(lock.status, ThreadStatus::Ready) {
-                lock.status = ThreadStatus::Running;
+            // 恢复扩展状态
+            if let Some(fpu) = &lock.fpu {
+                fpu.restore();
             }
+            // 恢复线程指针
+            write_fs_base(lock.fs_base);
+            // 将新线程设置为运行状态
+            lock.status = lock.status.switched_in();
             // 切换栈
             let rsp0 = lock.rsp0;
             // 所属进程
@@ -385,6 +422,7 @@ unsafe fn switch_thread(
             drop(thread);
             // 设置当前线程
             sync::percpu::set_current_thread_id(thread_id);
+            multitask::watchdog::touch();
             // 设置切换栈
             let addr = if let Some(rsp0) = rsp0 {
                 rsp0.get() + RSP0_SIZE as u64
@@ -401,11 +439,7 @@ unsafe fn switch_thread(
             } else {
                 memory::page::kernel_pml4()
             };
-            asm!(
-                "mov cr3, {}",
-                in(reg) pml4,
-                options(nostack, preserves_flags)
-            );
+            X86Hal::set_page_table(pml4);
             // 切换上下文
             switch_to_context(context);
         }
@@ -588,30 +622,15 @@ pub fn thread_yield_with(on_yield: Yield) {
 
 fn thread_yield_internal(suspend: bool, on_yield: Yield) {
     let current_thread = current_thread().unwrap();
-    let mut next_thread = {
+    // 如果当前线程预期要被挂起，且已无线程可执行，则进入IDLE线程
+    let next_thread = {
         let _guard = IrqGuard::cli();
-        loop {
-            let mut queue = READY_THREADS.lock();
-            let Some(thread) = queue.pop_front() else {
-                break None;
-            };
-            drop(queue);
-            if let Some(thread) = thread.upgrade() {
-                let status = thread.lock().status;
-                if matches!(status, ThreadStatus::Ready) {
-                    break Some(thread);
-                }
-            }
-        }
+        sched::pick_next(pop_ready_thread, resolve_ready_thread, suspend, || {
+            let idle_thread_id = sync::percpu::get_idle_thread_id();
+            THREADS.lock().get(&idle_thread_id).cloned()
+        })
     };
 
-    // 如果当前线程预期要被挂起，且已无线程可执行，则进入中断线程
-    if suspend && next_thread.is_none() {
-        let idle_thread_id = sync::percpu::get_idle_thread_id();
-        let _guard = IrqGuard::cli();
-        next_thread = THREADS.lock().get(&idle_thread_id).cloned();
-    }
-
     // 仅当需要切换时，进行线程切换
     if let Some(next_thread) = next_thread {
         let current_thread = Arc::into_raw(current_thread);
@@ -629,19 +648,7 @@ fn thread_yield_internal(suspend: bool, on_yield: Yield) {
 fn try_yield_thread() {
     let next_thread = {
         let _guard = IrqGuard::cli();
-        loop {
-            let mut queue = READY_THREADS.lock();
-            let Some(thread) = queue.pop_front() else {
-                break None;
-            };
-            drop(queue);
-            if let Some(thread) = thread.upgrade() {
-                let status = thread.lock().status;
-                if matches!(status, ThreadStatus::Ready) {
-                    break Some(thread);
-                }
-            }
-        }
+        sched::pick_next(pop_ready_thread, resolve_ready_thread, false, || None)
     };
 
     if let Some(thread) = next_thread {
@@ -659,15 +666,23 @@ fn try_yield_thread() {
     }
 }
 
+/// 从就绪队列中取出一个线程，每次取出后即释放队列锁，检查线程状态时不持有队列锁
+fn pop_ready_thread() -> Option<Weak<SpinLock<Thread>>> {
+    READY_THREADS.lock().pop_front()
+}
+
+/// 线程仍存在且处于就绪状态时返回该线程
+fn resolve_ready_thread(thread: Weak<SpinLock<Thread>>) -> Option<Arc<SpinLock<Thread>>> {
+    let thread = thread.upgrade()?;
+    let status = thread.lock().status;
+    (status == ThreadStatus::Ready).then_some(thread)
+}
+
 pub fn stop_thread(thread: &SpinLock<Thread>, exit_code: u64) {
     let mut thread = thread.lock();
-    if matches!(
-        thread.status,
-        ThreadStatus::Terminated | ThreadStatus::Terminating
-    ) {
+    if !thread.status.stop() {
         return;
     }
-    thread.status = ThreadStatus::Terminating;
     thread.exit_code.send(exit_code);
     // 如果此线程正在等待，将其唤醒
     if let Some(waker) = &thread.waker {
@@ -683,6 +698,91 @@ pub fn get_thread(thread_id: u64) -> Option<Arc<SpinLock<Thread>>> {
     THREADS.lock().get(&thread_id).cloned()
 }
 
+/// 输出全部线程的ID、所属进程、状态与保存的上下文，用于崩溃转储，当前线程以`*`标记
+///
+/// 不进行堆分配，线程表或线程被占用（如panic时正持有锁）时跳过，因此可在panic时调用
+pub fn write_threads<W: Write>(writer: &mut W) -> fmt::Result {
+    let Some(threads) = THREADS.try_lock() else {
+        return writeln!(writer, "  <thread table locked>");
+    };
+    let current = sync::percpu::initialized().then(sync::percpu::get_current_thread_id);
+    for (&thread_id, thread) in threads.iter() {
+        let mark = if Some(thread_id) == current { '*' } else { ' ' };
+        write!(writer, "{mark} thread {thread_id:<4} ")?;
+        let Some(thread) = thread.try_lock() else {
+            writeln!(writer, "<locked>")?;
+            continue;
+        };
+        match thread.process_id {
+            Some(process_id) => write!(writer, "process {process_id:<4} ")?,
+            None => write!(writer, "kernel       ")?,
+        }
+        write!(writer, "{:?}", thread.status)?;
+        // 正在执行的线程没有保存上下文
+        if thread.status != ThreadStatus::Running {
+            write!(
+                writer,
+                " rip {:016x} rsp {:016x}",
+                thread.context.rip, thread.context.rsp
+            )?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// 计时器硬中断使用，将流经的时间（us）计入当前线程
+///
+/// 中断可能打断正持有线程锁的代码，此时放弃本次计数，因此线程的CPU时间是近似值
+///
+/// IDLE线程的时间由 [idle] 精确计量，不在此计入
+pub fn account_cpu_time(elapsed: u64) {
+    let thread_id = sync::percpu::get_current_thread_id();
+    if thread_id == sync::percpu::get_idle_thread_id() {
+        return;
+    }
+
+    let Some(threads) = THREADS.try_lock() else {
+        return;
+    };
+    if let Some(thread) = threads.get(&thread_id)
+        && let Some(mut thread) = thread.try_lock()
+    {
+        thread.cpu_time += elapsed;
+    }
+}
+
+/// 获取线程占用的CPU时间（us）
+pub fn get_cpu_time(thread: &SpinLock<Thread>) -> u64 {
+    let _guard = IrqGuard::cli();
+    thread.lock().cpu_time
+}
+
+/// 获取系统运行的总CPU时间及CPU空闲的时间（us）
+pub fn system_cpu_time() -> (u64, u64) {
+    (clocksource::now_ns() / 1000, idle::idle_time() / 1000)
+}
+
+/// 设置当前线程的FS段基址，立即生效，之后切换回此线程时恢复
+pub fn set_current_fs_base(fs_base: u64) {
+    let thread = current_thread().unwrap();
+    let _guard = IrqGuard::cli();
+    thread.lock().fs_base = fs_base;
+    write_fs_base(fs_base);
+}
+
+fn write_fs_base(fs_base: u64) {
+    unsafe {
+        asm!(
+            "wrmsr",
+            in("ecx") IA32_FS_BASE,
+            in("eax") (fs_base & 0xFFFF_FFFF) as u32,
+            in("edx") ((fs_base >> 32) & 0xFFFF_FFFF) as u32,
+            options(nostack, preserves_flags)
+        )
+    }
+}
+
 pub fn get_exit_code_subscriber(thread: &SpinLock<Thread>) -> watch::Subscriber<u64> {
     let _guard = IrqGuard::cli();
     thread.lock().exit_code_sub.clone()
@@ -0,0 +1,77 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use cos_sys::vdso::SystemData;
+
+use crate::{
+    klog,
+    memory::{self, page::AllocateFrameOptions},
+    sync::int::IrqGuard,
+    time::{self, clocksource},
+};
+
+/// 系统共享页在内核空间的地址，为0时表示尚未初始化
+static SYSTEM_PAGE: AtomicUsize = AtomicUsize::new(0);
+
+/// 申请系统共享页，此后创建的进程均会映射此页
+///
+/// 必须在时间初始化之后调用
+pub fn init() {
+    let page = unsafe {
+        let _guard = IrqGuard::cli();
+        memory::page::alloc_mapped_frame(
+            memory::page::kernel_pml4(),
+            0x1000,
+            AllocateFrameOptions::KERNEL_DATA,
+        )
+    };
+    let Ok(page) = page else {
+        klog!(error, "vdso", "failed to allocate system page");
+        return;
+    };
+    // Safety: 页刚刚申请，大小为4K，SystemData的各字段均为整数，全0是合法的值
+    unsafe {
+        page.as_ptr().write_bytes(0, 0x1000);
+    }
+    SYSTEM_PAGE.store(page.as_ptr() as usize, Ordering::Release);
+    update();
+}
+
+fn system_data() -> Option<&'static SystemData> {
+    let page = SYSTEM_PAGE.load(Ordering::Acquire);
+    // Safety: 系统共享页申请后永不释放
+    (page != 0).then(|| unsafe { &*(page as *const SystemData) })
+}
+
+/// 系统共享页的物理地址，尚未初始化时返回None
+pub(super) fn system_page_physical() -> Option<u64> {
+    let page = SYSTEM_PAGE.load(Ordering::Acquire);
+    if page == 0 {
+        return None;
+    }
+    memory::page::kernel_physical_address(page).map(|address| address.get())
+}
+
+/// 更新系统共享页中的时间，由计时器中断调用
+///
+/// 以顺序锁写入，见 [SystemData]
+pub fn update() {
+    let Some(data) = system_data() else {
+        return;
+    };
+    let _guard = IrqGuard::cli();
+    let sequence = data.sequence.load(Ordering::Relaxed);
+    data.sequence.store(sequence + 1, Ordering::Relaxed);
+    core::sync::atomic::fence(Ordering::Release);
+
+    data.monotonic_ns
+        .store(clocksource::now_ns(), Ordering::Relaxed);
+    data.boot_time_ns
+        .store(time::boot_time().as_nanos() as u64, Ordering::Relaxed);
+    data.utc_offset.store(time::utc_offset(), Ordering::Relaxed);
+    let (tsc_hz, tsc_base, tsc_base_ns) = clocksource::tsc_base().unwrap_or((0, 0, 0));
+    data.tsc_hz.store(tsc_hz, Ordering::Relaxed);
+    data.tsc_base.store(tsc_base, Ordering::Relaxed);
+    data.tsc_base_ns.store(tsc_base_ns, Ordering::Relaxed);
+
+    data.sequence.store(sequence + 2, Ordering::Release);
+}
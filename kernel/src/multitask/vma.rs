@@ -0,0 +1,202 @@
+use alloc::collections::btree_map::BTreeMap;
+use filesystem::path::PathBuf;
+
+/// 虚拟内存区域的用途
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VmaKind {
+    /// 可执行文件的段
+    Image,
+    /// 线程栈
+    Stack,
+    /// 通过系统调用申请的内存
+    Data,
+    /// 内核映射的共享页，见 [cos_sys::vdso]
+    Vdso,
+}
+
+/// 虚拟内存区域的后备对象
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VmaBacking {
+    /// 匿名内存，内容由进程写入
+    Anonymous,
+    /// 内容从文件加载
+    File(PathBuf),
+}
+
+/// 进程的一段虚拟内存区域 [start, start + size)
+#[derive(Debug, Clone)]
+pub struct Vma {
+    pub start: u64,
+    pub size: u64,
+    pub kind: VmaKind,
+    pub writable: bool,
+    pub executable: bool,
+    pub backing: VmaBacking,
+}
+
+impl Vma {
+    /// 区域的结束地址（不含）
+    pub fn end(&self) -> u64 {
+        self.start + self.size
+    }
+}
+
+/// 进程的虚拟内存区域表
+///
+/// 记录进程已映射的每一段用户内存，按起始地址排列，区域之间互不重叠。
+/// 相邻的区域不会合并，以保留各自的用途与后备对象
+#[derive(Default)]
+pub struct VmaTree {
+    regions: BTreeMap<u64, Vma>,
+}
+
+impl VmaTree {
+    pub const fn new() -> Self {
+        Self {
+            regions: BTreeMap::new(),
+        }
+    }
+
+    /// 加入区域，区域不能与已有区域重叠
+    pub fn insert(&mut self, vma: Vma) {
+        debug_assert!(
+            self.regions
+                .range(..vma.end())
+                .next_back()
+                .is_none_or(|(_, existing)| existing.end() <= vma.start),
+            "codebug: overlapping vma"
+        );
+        self.regions.insert(vma.start, vma);
+    }
+
+    /// 查找包含指定地址的区域
+    pub fn find(&self, addr: u64) -> Option<&Vma> {
+        self.regions
+            .range(..=addr)
+            .next_back()
+            .map(|(_, vma)| vma)
+            .filter(|vma| addr < vma.end())
+    }
+
+    /// [start, start + size) 是否完全由指定用途的区域覆盖
+    pub fn covers(&self, start: u64, size: u64, kind: VmaKind) -> bool {
+        let Some(end) = start.checked_add(size) else {
+            return false;
+        };
+        let mut addr = start;
+        while addr < end {
+            match self.find(addr) {
+                Some(vma) if vma.kind == kind => addr = vma.end(),
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// 将结束于end的区域向后扩展size，不存在这样的区域时返回false
+    pub fn extend(&mut self, end: u64, size: u64) -> bool {
+        let Some(start) = self.find(end.wrapping_sub(1)).map(|vma| vma.start) else {
+            return false;
+        };
+        let vma = self.regions.get_mut(&start).unwrap();
+        if vma.end() != end {
+            return false;
+        }
+        vma.size += size;
+        true
+    }
+
+    /// 移除 [start, start + size) 范围内的区域，跨越范围边界的区域被拆分
+    pub fn remove(&mut self, start: u64, size: u64) {
+        let end = start + size;
+        while let Some((&key, vma)) = self.regions.range(..end).next_back() {
+            if vma.end() <= start {
+                break;
+            }
+            let vma = self.regions.remove(&key).unwrap();
+            if vma.end() > end {
+                self.regions.insert(
+                    end,
+                    Vma {
+                        start: end,
+                        size: vma.end() - end,
+                        ..vma.clone()
+                    },
+                );
+            }
+            if vma.start < start {
+                self.regions.insert(
+                    vma.start,
+                    Vma {
+                        size: start - vma.start,
+                        ..vma
+                    },
+                );
+                break;
+            }
+        }
+    }
+
+    /// 按起始地址升序遍历区域
+    pub fn iter(&self) -> impl Iterator<Item = &Vma> {
+        self.regions.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(start: u64, size: u64) -> Vma {
+        Vma {
+            start,
+            size,
+            kind: VmaKind::Data,
+            writable: true,
+            executable: false,
+            backing: VmaBacking::Anonymous,
+        }
+    }
+
+    #[test_case]
+    fn find_region() {
+        let mut tree = VmaTree::new();
+        tree.insert(data(0x1000, 0x2000));
+        assert_eq!(tree.find(0x1000).map(|vma| vma.start), Some(0x1000));
+        assert_eq!(tree.find(0x2FFF).map(|vma| vma.start), Some(0x1000));
+        assert!(tree.find(0x3000).is_none());
+        assert!(tree.find(0xFFF).is_none());
+    }
+
+    #[test_case]
+    fn covers_adjacent_regions() {
+        let mut tree = VmaTree::new();
+        tree.insert(data(0x1000, 0x1000));
+        tree.insert(data(0x2000, 0x1000));
+        assert!(tree.covers(0x1000, 0x2000, VmaKind::Data));
+        assert!(!tree.covers(0x1000, 0x3000, VmaKind::Data));
+        assert!(!tree.covers(0x1000, 0x1000, VmaKind::Stack));
+        assert!(!tree.covers(u64::MAX, 2, VmaKind::Data));
+    }
+
+    #[test_case]
+    fn extend_region() {
+        let mut tree = VmaTree::new();
+        tree.insert(data(0x1000, 0x1000));
+        assert!(tree.extend(0x2000, 0x1000));
+        assert_eq!(tree.find(0x2000).map(Vma::end), Some(0x3000));
+        assert!(!tree.extend(0x2000, 0x1000));
+    }
+
+    #[test_case]
+    fn remove_splits_region() {
+        let mut tree = VmaTree::new();
+        tree.insert(data(0x1000, 0x3000));
+        tree.remove(0x2000, 0x1000);
+        let regions = tree
+            .iter()
+            .map(|vma| (vma.start, vma.size))
+            .collect::<alloc::vec::Vec<_>>();
+        assert_eq!(regions, [(0x1000, 0x1000), (0x3000, 0x1000)]);
+    }
+}
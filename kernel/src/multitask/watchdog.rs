@@ -0,0 +1,86 @@
+use core::{
+    fmt::Arguments,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+use crate::{
+    backtrace,
+    cmdline::{self, LogLevel},
+    klog,
+    sync::percpu,
+    time::clocksource,
+    trap::idt::StackFrame,
+};
+
+/// 调度进展计数，每次切换线程、异步运行时每次取出任务时增加
+static PROGRESS: AtomicU64 = AtomicU64::new(0);
+/// 上次检查时的调度进展计数
+static LAST_PROGRESS: AtomicU64 = AtomicU64::new(0);
+/// 调度进展最后一次被观察到的时间（纳秒）
+static LAST_PROGRESS_NANOS: AtomicU64 = AtomicU64::new(0);
+/// 本次停顿是否已报告，避免每次计时器中断重复输出
+static REPORTED: AtomicBool = AtomicBool::new(false);
+
+/// 回溯调用栈的最大深度
+const BACKTRACE_DEPTH: usize = 16;
+
+/// 记录一次调度进展
+pub fn touch() {
+    PROGRESS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// 由计时器中断调用，检查CPU是否长时间停留在内核中而没有调度进展（soft lockup）
+///
+/// 中断打断用户态代码或IDLE线程时视为正常：用户态代码总是可以被抢占，IDLE线程表示CPU空闲。
+/// 否则若超过启动选项`watchdog`指定的时间仍未切换线程，且异步运行时未取出新任务，
+/// 则输出卡住的线程与调用栈；启动选项`watchdog_panic`开启时随后panic。
+/// 关中断期间计时器中断无法到达，这种情况无法检测
+///
+/// TODO: 多CPU - 进展计数与时间应按CPU分别记录
+pub fn tick(stack: &StackFrame) {
+    let Some(timeout) = cmdline::options().watchdog else {
+        return;
+    };
+    let now = clocksource::now_ns();
+    let progress = PROGRESS.load(Ordering::Relaxed);
+    let thread_id = percpu::get_current_thread_id();
+    let user_mode = stack.cs & 3 != 0;
+    if user_mode
+        || thread_id == percpu::get_idle_thread_id()
+        || LAST_PROGRESS.swap(progress, Ordering::Relaxed) != progress
+    {
+        LAST_PROGRESS_NANOS.store(now, Ordering::Relaxed);
+        REPORTED.store(false, Ordering::Relaxed);
+        return;
+    }
+
+    let stalled = now.saturating_sub(LAST_PROGRESS_NANOS.load(Ordering::Relaxed));
+    if stalled < timeout as u64 * 1_000_000_000 || REPORTED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    let kind = if thread_id == percpu::get_kernel_async_thread_id() {
+        "kernel async thread"
+    } else {
+        "thread"
+    };
+    let stalled_ms = stalled / 1_000_000;
+    // 在计时器中断中输出，被打断的线程可能正持有日志所用的锁
+    report(format_args!(
+        "soft lockup: {kind} {thread_id} stuck in kernel for {stalled_ms}ms"
+    ));
+    report(format_args!("  #0 {:#x}", stack.rip));
+    let mut frames = [0; BACKTRACE_DEPTH];
+    let depth = backtrace::capture_from(stack.rbp as usize, &mut frames);
+    for (index, address) in frames[..depth].iter().enumerate() {
+        report(format_args!("  #{} {address:#x}", index + 1));
+    }
+
+    if cmdline::options().watchdog_panic {
+        panic!("soft lockup: {kind} {thread_id} stuck in kernel for {stalled_ms}ms");
+    }
+}
+
+fn report(args: Arguments<'_>) {
+    klog::klog_from_irq(LogLevel::Warn, "watchdog", args);
+}
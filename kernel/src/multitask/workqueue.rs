@@ -0,0 +1,115 @@
+use core::{
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use alloc::{boxed::Box, collections::vec_deque::VecDeque};
+
+use crate::{
+    multitask,
+    sync::{int::IrqGuard, spin::SpinLock},
+};
+
+// 每个优先级队列的最大长度
+const QUEUE_CAPACITY: usize = 64;
+// 工作者单次调度最多执行的工作数量，超出后让出，避免饿死其他异步任务
+const WORK_BATCH: usize = 16;
+
+static WORK_QUEUE: SpinLock<WorkQueue> = SpinLock::new(WorkQueue::new());
+
+type Work = Box<dyn FnOnce() + Send + 'static>;
+
+/// 工作优先级
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    /// 高优先级，用于对延迟敏感的工作，如输入设备
+    High = 0,
+    /// 普通优先级
+    Normal = 1,
+}
+
+/// 工作队列已满
+#[derive(Debug)]
+pub struct QueueFull;
+
+struct WorkQueue {
+    // 按优先级排列的队列，下标为[`Priority`]的值
+    queues: [VecDeque<Work>; 2],
+    // 等待工作的工作者
+    waker: Option<Waker>,
+}
+
+impl WorkQueue {
+    const fn new() -> Self {
+        Self {
+            queues: [VecDeque::new(), VecDeque::new()],
+            waker: None,
+        }
+    }
+
+    fn pop(&mut self) -> Option<Work> {
+        self.queues.iter_mut().find_map(|queue| queue.pop_front())
+    }
+}
+
+/// 启动内核工作者
+pub fn init() {
+    multitask::async_rt::spawn(Worker);
+}
+
+/// 将工作放入工作队列，可在中断处理程序中调用
+///
+/// 中断处理程序应当尽快返回，耗时的处理（下半部）可以放入工作队列，
+/// 稍后由运行于内核异步运行时中的工作者执行，执行时中断处于开启状态。
+///
+/// 工作者总是先执行高优先级队列中的工作。每个队列的长度是有限的，
+/// 队列已满时返回[`QueueFull`]，由调用方决定丢弃或直接处理。
+pub fn enqueue<F>(priority: Priority, work: F) -> Result<(), QueueFull>
+where
+    F: FnOnce() + Send + 'static,
+{
+    let waker = {
+        let _guard = IrqGuard::cli();
+        let mut work_queue = WORK_QUEUE.lock();
+        let queue = &mut work_queue.queues[priority as usize];
+        if queue.len() >= QUEUE_CAPACITY {
+            return Err(QueueFull);
+        }
+        queue.push_back(Box::new(work));
+        work_queue.waker.take()
+    };
+
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+    Ok(())
+}
+
+/// 内核工作者，不断取出工作队列中的工作并执行
+struct Worker;
+
+impl Future for Worker {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        for _ in 0..WORK_BATCH {
+            let work = {
+                let _guard = IrqGuard::cli();
+                let mut work_queue = WORK_QUEUE.lock();
+                match work_queue.pop() {
+                    Some(work) => work,
+                    None => {
+                        // 在持有锁时登记唤醒对象，避免错过入队通知
+                        work_queue.waker = Some(cx.waker().clone());
+                        return Poll::Pending;
+                    }
+                }
+            };
+            work();
+        }
+
+        // 仍可能有剩余工作，让出后立即重新调度
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
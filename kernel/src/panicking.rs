@@ -5,11 +5,13 @@ use core::{
     sync::atomic::{AtomicU32, Ordering},
 };
 
-use crate::{display, sync};
+use crate::{cmdline, crash_dump, display, io, sync, trap};
 
 static PANIC_COUNT: AtomicU32 = AtomicU32::new(0);
 
-#[panic_handler]
+// 测试模式下使用 [`crate::testing`] 中的panic处理函数
+#[cfg_attr(not(test), panic_handler)]
+#[cfg_attr(test, allow(dead_code))]
 fn panic_entry(info: &PanicInfo) -> ! {
     // 关闭中断
     // TODO: 多核情况，需要通知其他核结束工作
@@ -18,6 +20,8 @@ fn panic_entry(info: &PanicInfo) -> ! {
     // panic 次数
     let panic_count = PANIC_COUNT.fetch_add(1, Ordering::SeqCst);
     match panic_count {
+        // 启动选项关闭了蓝屏，输出信息后立即复位
+        0 if !cmdline::options().blue_screen => dump_to_serial_and_restart(info),
         // 正常panic，自动dump信息并展示蓝屏
         0 => auto_dump_and_print_blue_screen(info),
         // 双重panic，在dump信息时再次触发故障，仅展示静态蓝屏信息
@@ -50,7 +54,15 @@ fn auto_dump_and_print_blue_screen(info: &PanicInfo) -> ! {
         );
     }
     _ = writeln!(writer, "*** MESSAGE: {}", info.message());
+    // 由CPU异常引发的panic，展示异常的错误码与寄存器
+    if let Some(record) = trap::exception::last_kernel_exception() {
+        _ = record.write_summary(&mut writer);
+    }
     _ = writeln!(writer, "");
+    // 写入崩溃转储，下次启动时保存到文件系统
+    if crash_dump::write(info) {
+        _ = writeln!(writer, "A crash dump has been written to disk.");
+    }
     _ = writeln!(writer, "The system has been halted.");
     _ = writeln!(writer, "");
     _ = writeln!(writer, "STOP: 0x0000007E (KERNEL_PANIC)");
@@ -59,6 +71,22 @@ fn auto_dump_and_print_blue_screen(info: &PanicInfo) -> ! {
     loop_hlt();
 }
 
+fn dump_to_serial_and_restart(info: &PanicInfo) -> ! {
+    // 串口输出不依赖堆，可以使用
+    io::serial::_write_fmt(format_args!("kernel panic: {}\n", info.message()));
+    if let Some(location) = info.location() {
+        io::serial::_write_fmt(format_args!("at {location}\n"));
+    }
+    if let Some(record) = trap::exception::last_kernel_exception() {
+        _ = record.write_summary(&mut io::serial::SerialWriter);
+    }
+    if crash_dump::write(info) {
+        io::serial::_write_fmt(format_args!("crash dump written to disk\n"));
+    }
+
+    restart_emergency()
+}
+
 fn print_static_blue_screen() -> ! {
     let mut writer = unsafe { display::vga_text::VgaTextWriter::with_style(0x1f) };
     bluescreen_print_header(&mut writer);
@@ -105,17 +133,7 @@ fn bluescreen_print_header<W: Write>(writer: &mut W) {
 
 fn restart_emergency() -> ! {
     // 尝试通过键盘控制器触发CPU Reset
-    const RESET_CMD: u8 = 0xFE;
-    const PORT: u16 = 0x64;
-    for _ in 0..5 {
-        unsafe {
-            asm!(
-                "out dx, al",
-                in("dx") PORT,
-                in("al") RESET_CMD,
-            );
-        }
-    }
+    io::acpi::reset_by_keyboard_controller();
 
     // 如果尝试失败，进入hlt循环
     loop_hlt()
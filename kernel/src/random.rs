@@ -0,0 +1,167 @@
+use core::{
+    arch::{asm, x86_64::__cpuid},
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use crypto::sha256::{DIGEST_SIZE, Sha256};
+
+use crate::{
+    multitask,
+    sync::{int::IrqGuard, spin::SpinLock},
+};
+
+/// 熵池
+///
+/// 输出由密钥与计数器经SHA-256生成。每次读取前，自上次读取以来累积的中断时间、TSC与RDRAND
+/// 被混入密钥；每次读取后密钥被替换，已输出的数据无法由之后的密钥推出。
+struct EntropyPool {
+    key: [u8; DIGEST_SIZE], // 当前密钥
+    counter: u64,           // 输出计数器
+}
+
+impl EntropyPool {
+    /// 将数据混入密钥
+    fn mix(&mut self, data: &[u8]) {
+        let mut hasher = Sha256::new();
+        hasher.update(&self.key);
+        hasher.update(data);
+        self.key = hasher.finalize();
+    }
+
+    /// 生成一块输出
+    fn generate(&mut self) -> [u8; DIGEST_SIZE] {
+        self.counter += 1;
+        let mut hasher = Sha256::new();
+        hasher.update(&self.key);
+        hasher.update(&self.counter.to_le_bytes());
+        hasher.finalize()
+    }
+}
+
+static POOL: SpinLock<EntropyPool> = SpinLock::new(EntropyPool {
+    key: [0; DIGEST_SIZE],
+    counter: 0,
+});
+
+/// 中断到达时间累积的熵，读取随机数时取出
+static INTERRUPT_ENTROPY: AtomicU64 = AtomicU64::new(0);
+/// 已记录的中断数量
+static INTERRUPT_COUNT: AtomicU64 = AtomicU64::new(0);
+/// 不支持RDRAND时，累积此数量的中断后视为熵池已有足够的熵
+const SEED_INTERRUPT_COUNT: u64 = 64;
+/// 等待熵池具有足够的熵时检查的间隔
+const SEED_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// 初始化熵池，并以随机数设置内核哈希表的种子
+pub fn init() {
+    add_entropy(&rdtsc().to_le_bytes());
+
+    let mut seed = [0u8; 8];
+    fill(&mut seed);
+    try_alloc::collection::hash::SimpleGlobalSeed::set_seed(u64::from_le_bytes(seed));
+}
+
+/// 记录中断到达的时间，由中断处理函数调用
+///
+/// 为避免拖慢中断处理，这里只将TSC累积到原子变量中，读取随机数时再混入熵池
+pub fn add_interrupt_randomness(irq: u8) {
+    let count = INTERRUPT_COUNT.fetch_add(1, Ordering::Relaxed);
+    let value = (rdtsc() ^ ((irq as u64) << 56)).rotate_left((count % 64) as u32);
+    INTERRUPT_ENTROPY.fetch_xor(value, Ordering::Relaxed);
+}
+
+/// 将数据混入熵池
+pub fn add_entropy(data: &[u8]) {
+    let _guard = IrqGuard::cli();
+    POOL.lock().mix(data);
+}
+
+/// 熵池是否已有足够的熵，即CPU支持RDRAND，或已累积足够的中断时间
+pub fn is_seeded() -> bool {
+    INTERRUPT_COUNT.load(Ordering::Relaxed) >= SEED_INTERRUPT_COUNT || rdrand().is_some()
+}
+
+/// 等待熵池具有足够的熵，见 [is_seeded]
+pub async fn wait_seeded() {
+    while !is_seeded() {
+        multitask::async_task::sleep(SEED_POLL_INTERVAL).await;
+    }
+}
+
+/// 以随机数据填充缓冲区
+///
+/// 持有熵池的锁时中断被关闭，大量读取时调用方应分段调用
+pub fn fill(buf: &mut [u8]) {
+    let mut seed = [0u8; 32];
+    seed[..8].copy_from_slice(&rdtsc().to_le_bytes());
+    seed[8..16].copy_from_slice(&rdrand().unwrap_or_default().to_le_bytes());
+    seed[16..24].copy_from_slice(&INTERRUPT_ENTROPY.swap(0, Ordering::Relaxed).to_le_bytes());
+    seed[24..].copy_from_slice(&INTERRUPT_COUNT.load(Ordering::Relaxed).to_le_bytes());
+
+    let _guard = IrqGuard::cli();
+    let mut pool = POOL.lock();
+    pool.mix(&seed);
+    for chunk in buf.chunks_mut(DIGEST_SIZE) {
+        let block = pool.generate();
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+    pool.key = pool.generate();
+}
+
+/// 读取TSC
+pub fn rdtsc() -> u64 {
+    let low: u32;
+    let high: u32;
+    unsafe {
+        asm!(
+            "rdtsc",
+            out("eax") low,
+            out("edx") high,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    ((high as u64) << 32) | low as u64
+}
+
+/// 读取RDRAND，CPU不支持或多次重试仍失败时返回None
+pub fn rdrand() -> Option<u64> {
+    // cpuid 1 ecx第30位：支持RDRAND
+    if __cpuid(1).ecx & (1 << 30) == 0 {
+        return None;
+    }
+    // RDRAND在熵不足时可能暂时失败（CF为0），按推荐重试10次
+    for _ in 0..10 {
+        let value: u64;
+        let success: u8;
+        // Safety: 已确认CPU支持RDRAND
+        unsafe {
+            asm!(
+                "rdrand {value}",
+                "setc {success}",
+                value = out(reg) value,
+                success = out(reg_byte) success,
+                options(nomem, nostack)
+            );
+        }
+        if success != 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_fill_differs() {
+        let mut first = [0u8; 48];
+        let mut second = [0u8; 48];
+        fill(&mut first);
+        fill(&mut second);
+        assert_ne!(first, second);
+        assert_ne!(first, [0u8; 48]);
+    }
+}
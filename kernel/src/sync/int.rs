@@ -1,5 +1,7 @@
 use core::arch::asm;
 
+use crate::{hal::X86Hal, sync::percpu};
+
 /// 关中断
 ///
 /// 关中断后当前核心不会受到硬中断影响，代码执行路径不会被意外打断，其他核心不受影响。
@@ -40,34 +42,30 @@ pub fn interrupts_enabled() -> bool {
     (rflags & (1 << 9)) != 0
 }
 
-pub struct IrqGuard {
-    prev: bool,
-}
+/// 关中断，并在释放后恢复之前的中断状态，见 [`kernel_core::hal::IrqGuard`]
+pub type IrqGuard = kernel_core::hal::IrqGuard<X86Hal>;
 
-impl IrqGuard {
-    /// 关中断，并在IrqGuard释放后恢复之前的中断状态
-    pub fn cli() -> Self {
-        let prev = interrupts_enabled();
-        cli();
-        IrqGuard { prev }
-    }
+/// 硬中断上下文的标记，存在期间 [in_interrupt] 返回true
+///
+/// 由硬中断处理程序在入口创建。处理程序切换线程前须先释放，否则切换到的线程也被视为处于中断上下文
+pub struct IrqContext {
+    _private: (),
+}
 
-    /// 开中断，并在IrqGuard释放后恢复之前的中断状态
-    ///
-    /// Safety: 调用者需保证当前可以安全开中断
-    pub fn sti() -> Self {
-        let prev = interrupts_enabled();
-        sti();
-        IrqGuard { prev }
+impl IrqContext {
+    pub fn enter() -> Self {
+        percpu::set_irq_depth(percpu::get_irq_depth() + 1);
+        Self { _private: () }
     }
 }
 
-impl Drop for IrqGuard {
+impl Drop for IrqContext {
     fn drop(&mut self) {
-        if self.prev {
-            sti();
-        } else {
-            cli();
-        }
+        percpu::set_irq_depth(percpu::get_irq_depth() - 1);
     }
 }
+
+/// 当前是否处于硬中断处理程序中
+pub fn in_interrupt() -> bool {
+    percpu::initialized() && percpu::get_irq_depth() > 0
+}
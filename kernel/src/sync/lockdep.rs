@@ -0,0 +1,179 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use alloc::{collections::btree_map::BTreeMap, vec::Vec};
+use async_locks::lockdep::Hooks;
+use kernel_core::lockdep::LockGraph;
+
+use crate::{
+    backtrace, klog,
+    multitask::async_rt,
+    sync::{int::IrqGuard, percpu, spin::SpinLock},
+};
+
+pub use async_locks::lockdep::LockClass;
+
+/// 每条边记录的调用栈深度
+const BACKTRACE_DEPTH: usize = 16;
+/// 异步任务的上下文标记，与线程ID区分
+const TASK_CONTEXT: u64 = 1 << 63;
+
+/// 是否记录锁的获取顺序，per-cpu结构与内核线程初始化之前获取的锁不被记录
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// 正在记录，此期间获取的锁（记录自身使用的锁、内核堆的锁等）不被记录，避免递归
+///
+/// TODO: 多CPU - 应改为每个CPU独立的标记
+static BUSY: AtomicBool = AtomicBool::new(false);
+
+static STATE: SpinLock<State> = SpinLock::new(State {
+    graph: LockGraph::new(),
+    held: BTreeMap::new(),
+});
+
+static HOOKS: Hooks = Hooks {
+    check,
+    acquired,
+    release,
+};
+
+struct State {
+    // 锁的获取顺序，每条边记录首次以该顺序获取时的调用栈
+    graph: LockGraph<LockClass, Backtrace>,
+    // 上下文 -> 按获取顺序排列的持有的锁
+    held: BTreeMap<u64, Vec<LockClass>>,
+}
+
+#[derive(Clone, Copy)]
+struct Backtrace {
+    frames: [usize; BACKTRACE_DEPTH],
+    depth: usize,
+}
+
+impl Backtrace {
+    #[inline(always)]
+    fn capture() -> Self {
+        let mut frames = [0; BACKTRACE_DEPTH];
+        let depth = backtrace::capture(&mut frames);
+        Self { frames, depth }
+    }
+
+    fn log(&self) {
+        for (index, address) in self.frames[..self.depth].iter().enumerate() {
+            klog!(error, "lockdep", "    #{index} {address:#x}");
+        }
+    }
+}
+
+/// 开始记录锁的获取顺序，须在per-cpu结构与内核异步线程初始化之后调用
+pub fn init() {
+    async_locks::lockdep::set_hooks(&HOOKS);
+    ENABLED.store(true, Ordering::Release);
+    klog!(info, "lockdep", "lock dependency checking enabled");
+}
+
+/// 当前的上下文
+///
+/// 内核异步线程上的各个任务交替执行，跨越await持有异步锁，因此以任务区分；其他情况以线程区分
+fn context() -> Option<u64> {
+    if !percpu::initialized() {
+        return None;
+    }
+    let thread_id = percpu::get_current_thread_id();
+    if thread_id == percpu::get_kernel_async_thread_id()
+        && let Some(task_id) = async_rt::current_task_id()
+    {
+        return Some(TASK_CONTEXT | task_id);
+    }
+    Some(thread_id)
+}
+
+fn with_state<R>(f: impl FnOnce(&mut State) -> R) -> Option<R> {
+    if !ENABLED.load(Ordering::Acquire) {
+        return None;
+    }
+    let _guard = IrqGuard::cli();
+    if BUSY.swap(true, Ordering::Acquire) {
+        return None;
+    }
+    let result = f(&mut STATE.lock());
+    BUSY.store(false, Ordering::Release);
+    Some(result)
+}
+
+/// 即将等待锁，检查获取顺序是否与已有的顺序构成环，构成环时输出两次获取的调用栈并panic
+///
+/// 不构成环时，记录当前持有的各个锁先于此锁获取
+pub fn check(class: LockClass) {
+    let conflict = with_state(|state| {
+        let context = context()?;
+        let State { graph, held } = state;
+        let held = held.get(&context)?;
+        for &held in held {
+            if let Some(conflict) = graph.check(held, class) {
+                let path: Vec<_> = conflict
+                    .path
+                    .into_iter()
+                    .map(|(from, to, backtrace)| (from, to, *backtrace))
+                    .collect();
+                return Some((held, path));
+            }
+        }
+        let mut backtrace = None;
+        for &held in held {
+            graph.add(held, class, || {
+                *backtrace.get_or_insert_with(Backtrace::capture)
+            });
+        }
+        None
+    })
+    .flatten();
+
+    if let Some((held, path)) = conflict {
+        // 停止记录，panic过程中获取的锁不再检查
+        ENABLED.store(false, Ordering::Release);
+        klog!(
+            error,
+            "lockdep",
+            "possible deadlock: acquiring lock created at {class} while holding lock created at {held}"
+        );
+        klog!(error, "lockdep", "  current acquisition:");
+        Backtrace::capture().log();
+        for (from, to, backtrace) in path {
+            klog!(
+                error,
+                "lockdep",
+                "  lock created at {to} was acquired while holding lock created at {from}:"
+            );
+            backtrace.log();
+        }
+        panic!("lockdep: possible deadlock acquiring lock created at {class}");
+    }
+}
+
+/// 已获取锁，返回持有锁的上下文，不记录时返回None
+///
+/// 锁可能由其他上下文释放（如 [crate::multitask::thread::Yield] 在切换到其他线程后释放锁），
+/// 因此释放时以获取时的上下文为准
+pub fn acquired(class: LockClass) -> Option<u64> {
+    with_state(|state| {
+        let context = context()?;
+        state.held.entry(context).or_default().push(class);
+        Some(context)
+    })
+    .flatten()
+}
+
+/// 释放锁，context为 [acquired] 的返回值
+pub fn release(class: LockClass, context: u64) {
+    with_state(|state| {
+        let Some(held) = state.held.get_mut(&context) else {
+            return;
+        };
+        // 锁通常按获取的相反顺序释放
+        if let Some(index) = held.iter().rposition(|held| *held == class) {
+            held.remove(index);
+        }
+        if held.is_empty() {
+            state.held.remove(&context);
+        }
+    });
+}
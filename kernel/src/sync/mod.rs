@@ -1,3 +1,5 @@
 pub mod int;
+#[cfg(feature = "lockdep")]
+pub mod lockdep;
 pub mod percpu;
 pub mod spin;
@@ -1,4 +1,8 @@
-use core::{arch::asm, mem::offset_of};
+use core::{
+    arch::asm,
+    mem::offset_of,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 use alloc::boxed::Box;
 
@@ -14,6 +18,8 @@ pub struct PerCpuStruct {
     pub idle_thread_id: u64,
     // kernel async 线程id
     pub kernel_async_thread_id: u64,
+    // 硬中断处理程序的嵌套深度，见 [crate::sync::int::IrqContext]
+    pub irq_depth: u64,
 }
 
 macro_rules! per_cpu_data {
@@ -79,8 +85,13 @@ per_cpu_data!(
     get_kernel_async_thread_id
 );
 
+per_cpu_data!(irq_depth, OFFSET_IRQ_DEPTH, set_irq_depth, get_irq_depth);
+
 const IA32_KERNEL_GS_BASE: u64 = 0xC0000102;
 
+/// per-cpu结构是否已初始化
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+
 pub unsafe fn init() {
     let per_cpu_struct = Box::new(PerCpuStruct::default());
     let per_cpu_struct = Box::leak(per_cpu_struct) as *mut PerCpuStruct as usize as u64;
@@ -88,6 +99,12 @@ pub unsafe fn init() {
     unsafe {
         asm!("swapgs", options(nostack, preserves_flags));
     }
+    INITIALIZED.store(true, Ordering::Release);
+}
+
+/// per-cpu结构是否已初始化，初始化之前不能读写per-cpu数据
+pub fn initialized() -> bool {
+    INITIALIZED.load(Ordering::Acquire)
 }
 
 fn set_k_gs_base(data: u64) {
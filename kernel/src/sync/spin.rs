@@ -2,15 +2,55 @@ use core::{
     cell::UnsafeCell,
     hint::spin_loop,
     ops::{Deref, DerefMut},
-    sync::atomic::{AtomicBool, Ordering},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
 };
 
+#[cfg(feature = "lockdep")]
+use core::panic::Location;
+
+use crate::sync::int::{self, IrqGuard};
+#[cfg(feature = "lockdep")]
+use crate::sync::lockdep::{self, LockClass};
+
+// 全部自旋锁的统计，见 [stats]
+static ACQUISITIONS: AtomicU64 = AtomicU64::new(0);
+static CONTENDED: AtomicU64 = AtomicU64::new(0);
+static SPINS: AtomicU64 = AtomicU64::new(0);
+static IRQ_ACQUISITIONS: AtomicU64 = AtomicU64::new(0);
+
+/// 全部自旋锁的统计
+#[derive(Debug, Clone, Copy)]
+pub struct SpinLockStats {
+    /// 获取锁的次数，包括try_lock成功的次数
+    pub acquisitions: u64,
+    /// 获取锁时锁已被占用、需要自旋等待的次数
+    pub contended: u64,
+    /// 自旋等待的总次数
+    pub spins: u64,
+    /// 在硬中断处理程序中获取锁的次数
+    pub irq_acquisitions: u64,
+}
+
+/// 获取全部自旋锁的统计
+pub fn stats() -> SpinLockStats {
+    SpinLockStats {
+        acquisitions: ACQUISITIONS.load(Ordering::Relaxed),
+        contended: CONTENDED.load(Ordering::Relaxed),
+        spins: SPINS.load(Ordering::Relaxed),
+        irq_acquisitions: IRQ_ACQUISITIONS.load(Ordering::Relaxed),
+    }
+}
+
 /// 自旋锁
 ///
-/// 注意持有锁时触发中断可能导致死锁！
+/// 注意持有锁时触发中断可能导致死锁！中断处理程序也会获取的锁应使用 [SpinLock::lock_irqsave]，
+/// 只在线程上下文中获取的锁可以使用 [SpinLock::lock_in_thread]
 pub struct SpinLock<T> {
     lock: AtomicBool,
     data: UnsafeCell<T>,
+    // 创建锁的位置，用于死锁检测
+    #[cfg(feature = "lockdep")]
+    class: LockClass,
 }
 
 unsafe impl<T: Send> Send for SpinLock<T> {}
@@ -18,22 +58,24 @@ unsafe impl<T: Send> Sync for SpinLock<T> {}
 
 pub struct SpinLockGuard<'lock, T> {
     lock: &'lock SpinLock<T>,
+    // 持有锁的上下文，见 [lockdep::acquired]
+    #[cfg(feature = "lockdep")]
+    context: Option<u64>,
 }
 
 impl<T> SpinLock<T> {
+    #[track_caller]
     pub const fn new(data: T) -> Self {
         Self {
             lock: AtomicBool::new(false),
             data: UnsafeCell::new(data),
+            #[cfg(feature = "lockdep")]
+            class: Location::caller(),
         }
     }
 
     pub fn try_lock(&self) -> Option<SpinLockGuard<'_, T>> {
-        let locked = self
-            .lock
-            .compare_exchange(false, true, Ordering::SeqCst, Ordering::Relaxed)
-            .is_ok();
-        if locked {
+        if self.try_acquire() {
             Some(SpinLockGuard::new(self))
         } else {
             None
@@ -41,19 +83,68 @@ impl<T> SpinLock<T> {
     }
 
     pub fn lock(&self) -> SpinLockGuard<'_, T> {
+        // 自旋前检查获取顺序，死锁时不会再返回
+        #[cfg(feature = "lockdep")]
+        lockdep::check(self.class);
+        if self.try_acquire() {
+            return SpinLockGuard::new(self);
+        }
+
+        CONTENDED.fetch_add(1, Ordering::Relaxed);
+        let mut spins = 0;
         loop {
-            if let Some(guard) = self.try_lock() {
-                return guard;
+            spin_loop();
+            spins += 1;
+
+            if self.try_acquire() {
+                SPINS.fetch_add(spins, Ordering::Relaxed);
+                return SpinLockGuard::new(self);
             }
+        }
+    }
 
-            spin_loop();
+    /// 关中断并获取锁，守卫释放时先释放锁，再恢复之前的中断状态
+    ///
+    /// 与先创建 [IrqGuard] 再调用 [SpinLock::lock] 相同，但不会因释放顺序错误而在持有锁时开中断
+    pub fn lock_irqsave(&self) -> SpinLockIrqGuard<'_, T> {
+        let irq = IrqGuard::cli();
+        SpinLockIrqGuard {
+            guard: self.lock(),
+            _irq: irq,
         }
     }
+
+    /// 获取只在线程上下文中使用的锁，不关中断
+    ///
+    /// 锁从不在中断处理程序中获取，持有期间被中断打断不会死锁；但持有者可能被抢占，
+    /// 此时其他线程将自旋至持有者再次被调度，因此只适合持有时间很短的锁。
+    /// 调试构建中在中断上下文调用时panic
+    pub fn lock_in_thread(&self) -> SpinLockGuard<'_, T> {
+        debug_assert!(
+            !int::in_interrupt(),
+            "codebug: thread-only spin lock acquired in interrupt context"
+        );
+        self.lock()
+    }
+
+    fn try_acquire(&self) -> bool {
+        self.lock
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::Relaxed)
+            .is_ok()
+    }
 }
 
 impl<'lock, T> SpinLockGuard<'lock, T> {
     fn new(lock: &'lock SpinLock<T>) -> Self {
-        Self { lock }
+        ACQUISITIONS.fetch_add(1, Ordering::Relaxed);
+        if int::in_interrupt() {
+            IRQ_ACQUISITIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        Self {
+            lock,
+            #[cfg(feature = "lockdep")]
+            context: lockdep::acquired(lock.class),
+        }
     }
 }
 
@@ -78,5 +169,53 @@ impl<'lock, T> DerefMut for SpinLockGuard<'lock, T> {
 impl<T> Drop for SpinLockGuard<'_, T> {
     fn drop(&mut self) {
         self.lock.lock.store(false, Ordering::Release);
+        #[cfg(feature = "lockdep")]
+        if let Some(context) = self.context {
+            lockdep::release(self.lock.class, context);
+        }
+    }
+}
+
+/// 同时关闭中断的自旋锁守卫，见 [SpinLock::lock_irqsave]
+pub struct SpinLockIrqGuard<'lock, T> {
+    // 字段按声明顺序释放：先释放锁，再恢复中断状态
+    guard: SpinLockGuard<'lock, T>,
+    _irq: IrqGuard,
+}
+
+impl<'lock, T> Deref for SpinLockIrqGuard<'lock, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<'lock, T> DerefMut for SpinLockIrqGuard<'lock, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_lock_irqsave() {
+        let lock = SpinLock::new(0);
+        let enabled = int::interrupts_enabled();
+        {
+            let mut guard = lock.lock_irqsave();
+            *guard += 1;
+            assert!(!int::interrupts_enabled());
+            assert!(lock.try_lock().is_none());
+        }
+        // 释放锁后恢复之前的中断状态
+        assert_eq!(int::interrupts_enabled(), enabled);
+
+        let before = stats().acquisitions;
+        assert_eq!(*lock.lock_in_thread(), 1);
+        assert!(stats().acquisitions > before);
     }
 }
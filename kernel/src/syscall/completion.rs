@@ -0,0 +1,275 @@
+use core::slice;
+
+use alloc::{sync::Arc, vec};
+use async_locks::channel::oneshot;
+use cos_sys::completion::{Completion, Request};
+use filesystem::fs::{FileSystemError, permission::FilePermission};
+
+use crate::{
+    multitask::{self, capability::Capabilities, process::Process},
+    sync::spin::SpinLock,
+    syscall::{SYSCALL_SUCCESS, filesystem_error, handle_error},
+    syscall_handler,
+    user::{handle::HandleObject, range::UserRange, slice::UserSlice},
+};
+
+syscall_handler! {
+    fn submit(request_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(request_slice) = UserSlice::readable_of::<Request>(&process, request_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        // Safety: Request的字段均为u64，任意字节均为合法值
+        let request = match unsafe { request_slice.read_struct::<Request>() } {
+            Ok(request) => request,
+            Err(error) => return error.error_kind() as u64,
+        };
+        drop(request_slice);
+
+        let result = match request.opcode {
+            cos_sys::completion::OP_FILE_READ => submit_file_read(&process, &request),
+            cos_sys::completion::OP_FILE_WRITE => submit_file_write(&process, &request),
+            cos_sys::completion::OP_PROCESS_WAIT => submit_process_wait(&process, &request),
+            _ => Err(cos_sys::error::ErrorKind::BadArgument as u64),
+        };
+        match result {
+            Ok(()) => SYSCALL_SUCCESS,
+            Err(error) => error,
+        }
+    }
+}
+
+syscall_handler! {
+    fn poll(completions_ptr: u64, capacity: u64, count_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(count_slice) = UserSlice::writable_of::<u64>(&process, count_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let completions_slice = match completions_slice(&process, completions_ptr, capacity) {
+            Ok(completions_slice) => completions_slice,
+            Err(error) => return error,
+        };
+
+        let completions = multitask::process::take_completions(&process, capacity as usize);
+        if completions.is_empty() {
+            return cos_sys::error::ErrorKind::WouldBlock as u64;
+        }
+
+        write_completions(&completions_slice, &count_slice, &completions)
+    }
+}
+
+syscall_handler! {
+    fn wait(completions_ptr: u64, capacity: u64, count_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(count_slice) = UserSlice::writable_of::<u64>(&process, count_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let completions_slice = match completions_slice(&process, completions_ptr, capacity) {
+            Ok(completions_slice) => completions_slice,
+            Err(error) => return error,
+        };
+
+        loop {
+            let completions = multitask::process::take_completions(&process, capacity as usize);
+            if !completions.is_empty() {
+                return write_completions(&completions_slice, &count_slice, &completions);
+            }
+
+            // 没有进行中的请求，等待将永远不会返回
+            if !multitask::process::has_async_requests(&process) {
+                return write_completions(&completions_slice, &count_slice, &completions);
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            if !multitask::process::register_completion_waiter(&process, sender) {
+                continue;
+            }
+            let wait = multitask::async_rt::block_on(async move {
+                _ = receiver.recv().await;
+            });
+            if wait.is_err() {
+                return cos_sys::error::ErrorKind::Unknown as u64;
+            }
+        }
+    }
+}
+
+fn completions_slice(
+    process: &Arc<SpinLock<Process>>,
+    completions_ptr: u64,
+    capacity: u64,
+) -> Result<UserSlice, u64> {
+    if capacity == 0 {
+        return Err(cos_sys::error::ErrorKind::BadArgument as u64);
+    }
+    UserRange::array::<Completion>(completions_ptr, capacity)
+        .and_then(|range| UserSlice::from_range(process, range, true))
+        .map_err(|error| error.error_kind() as u64)
+}
+
+fn write_completions(
+    completions_slice: &UserSlice,
+    count_slice: &UserSlice,
+    completions: &[Completion],
+) -> u64 {
+    // Safety: Completion为repr(C)且仅包含u64字段，不存在填充字节
+    let bytes = unsafe {
+        slice::from_raw_parts(completions.as_ptr() as *const u8, size_of_val(completions))
+    };
+    if completions_slice.write(bytes).is_err() {
+        return cos_sys::error::ErrorKind::BadPointer as u64;
+    }
+    if count_slice
+        .write_struct(&(completions.len() as u64))
+        .is_err()
+    {
+        return cos_sys::error::ErrorKind::BadPointer as u64;
+    }
+
+    SYSCALL_SUCCESS
+}
+
+fn submit_file_read(process: &Arc<SpinLock<Process>>, request: &Request) -> Result<(), u64> {
+    let handle = file_handle(process, request.handle, FilePermission::READ)?;
+
+    // 提交时检查缓冲区，数据先读入内核缓冲区，请求完成时再复制到用户内存
+    // 请求仅持有进程的弱引用，进程退出后读取的数据被丢弃，不会因等待中的读取而无法回收
+    let buffer_ptr = request.buffer;
+    let length = request.length as usize;
+    UserSlice::writable(process, buffer_ptr, length).map_err(|error| error.error_kind() as u64)?;
+    multitask::process::charge_kernel_memory(process, length)
+        .map_err(|error| error.error_kind() as u64)?;
+
+    let weak_process = Arc::downgrade(process);
+    let result = spawn_request(process, request.user_data, async move {
+        let mut buffer = vec![0u8; length];
+        let result = match &*handle {
+            HandleObject::File(handle) => handle.lock().await.read(&mut buffer).await,
+            HandleObject::CharDevice(device) => device.read(&mut buffer).await,
+            _ => Err(FileSystemError::OperationNotSupport),
+        };
+
+        let Some(process) = weak_process.upgrade() else {
+            return Err(cos_sys::error::ErrorKind::Unknown as u64);
+        };
+        multitask::process::release_kernel_memory(&process, length);
+        let count = result.map_err(|error| filesystem_error(&error))?;
+        UserSlice::writable(&process, buffer_ptr, count as usize)
+            .and_then(|slice| slice.write(&buffer[..count as usize]))
+            .map_err(|error| error.error_kind() as u64)?;
+        Ok(count)
+    });
+    if result.is_err() {
+        multitask::process::release_kernel_memory(process, length);
+    }
+    result
+}
+
+fn submit_file_write(process: &Arc<SpinLock<Process>>, request: &Request) -> Result<(), u64> {
+    let handle = file_handle(process, request.handle, FilePermission::WRITE)?;
+    // 写入字符设备（如控制台）不修改文件系统，不需要FS_WRITE能力
+    if matches!(&*handle, HandleObject::File(_))
+        && !multitask::process::has_capability(process, Capabilities::FS_WRITE)
+    {
+        return Err(cos_sys::error::ErrorKind::PermissionDenied as u64);
+    }
+
+    // 提交时复制缓冲区，用户程序可在提交后立即复用缓冲区
+    // buffer_slice需要保留至请求完成，以便在完成后归还内核内存配额
+    let buffer_slice = UserSlice::readable(process, request.buffer, request.length as usize)
+        .map_err(|error| error.error_kind() as u64)?;
+    let buffer = buffer_slice
+        .read_to_vec()
+        .map_err(|error| error.error_kind() as u64)?;
+
+    spawn_request(process, request.user_data, async move {
+        let result = match &*handle {
+            HandleObject::File(handle) => handle.lock().await.write(&buffer).await,
+            HandleObject::CharDevice(device) => device.write(&buffer).await,
+            _ => return Err(cos_sys::error::ErrorKind::BadArgument as u64),
+        };
+        let result = match result {
+            Ok(()) => Ok(buffer.len() as u64),
+            Err(error) => Err(filesystem_error(&error)),
+        };
+        drop(buffer_slice);
+        result
+    })
+}
+
+fn submit_process_wait(process: &Arc<SpinLock<Process>>, request: &Request) -> Result<(), u64> {
+    let handle = multitask::process::get_process_handle(process, request.handle)
+        .map_err(|error| handle_error(&error))?;
+    let HandleObject::Process { exit, .. } = &*handle else {
+        return Err(cos_sys::error::ErrorKind::BadArgument as u64);
+    };
+
+    let mut exit = exit.clone();
+    spawn_request(process, request.user_data, async move {
+        while exit.wait().await.is_ok() {}
+        Ok(*exit.borrow())
+    })
+}
+
+/// 获取文件或字符设备句柄，并检查句柄是否允许access指定的访问
+fn file_handle(
+    process: &Arc<SpinLock<Process>>,
+    handle: u64,
+    access: u16,
+) -> Result<Arc<HandleObject>, u64> {
+    let handle = multitask::process::get_process_handle(process, handle)
+        .map_err(|error| handle_error(&error))?;
+    let allowed = match &*handle {
+        HandleObject::File(file) => file.allows(access),
+        HandleObject::CharDevice(device) => device.allows(access),
+        _ => return Err(cos_sys::error::ErrorKind::BadArgument as u64),
+    };
+    if !allowed {
+        return Err(cos_sys::error::ErrorKind::PermissionDenied as u64);
+    }
+
+    Ok(handle)
+}
+
+/// 在后台执行异步请求，完成后将结果提交至进程的完成队列
+///
+/// 如果进行中的请求数量已达到进程限制，返回[`cos_sys::error::ErrorKind::QuotaExceeded`]，请求不会被执行
+fn spawn_request<Fut>(
+    process: &Arc<SpinLock<Process>>,
+    user_data: u64,
+    request: Fut,
+) -> Result<(), u64>
+where
+    Fut: Future<Output = Result<u64, u64>> + Send + 'static,
+{
+    if !multitask::process::reserve_completion(process) {
+        return Err(cos_sys::error::ErrorKind::QuotaExceeded as u64);
+    }
+
+    // 请求不应阻止进程被回收
+    let process = Arc::downgrade(process);
+    multitask::async_rt::spawn(async move {
+        let completion = match request.await {
+            Ok(result) => Completion {
+                user_data,
+                error: SYSCALL_SUCCESS,
+                result,
+            },
+            Err(error) => Completion {
+                user_data,
+                error,
+                result: 0,
+            },
+        };
+
+        if let Some(process) = process.upgrade() {
+            multitask::process::post_completion(&process, completion);
+        }
+    });
+
+    Ok(())
+}
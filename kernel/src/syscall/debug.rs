@@ -1,12 +1,29 @@
-use core::mem::MaybeUninit;
+use core::slice;
+
+use alloc::vec::Vec;
+use cos_sys::debug::{HandleInfo, LockStats, MemoryRegionInfo, SyscallTraceRecord};
 
 use crate::{
-    io, kprint, kprintln, memory, multitask,
-    sync::{int::IrqGuard, percpu},
-    syscall::SYSCALL_SUCCESS,
+    cmdline::LogLevel,
+    display, io, klog, kprintln,
+    multitask::{
+        self,
+        capability::Capabilities,
+        vma::{VmaBacking, VmaKind},
+    },
+    sync::{int::IrqGuard, percpu, spin},
+    syscall::{SYSCALL_SUCCESS, handle_error, trace},
     syscall_handler,
+    user::{
+        handle::{ConsoleObject, HandleObject},
+        range::UserRange,
+        slice::UserSlice,
+    },
 };
 
+/// 日志目标名的最大长度
+const MAX_LOG_TARGET_LEN: u64 = 64;
+
 syscall_handler! {
     fn syscall_test() {
         kprintln!("syscall test pass");
@@ -15,10 +32,6 @@ syscall_handler! {
 
 syscall_handler! {
     fn get_char(char_ptr: u64) -> u64 {
-        if !memory::page::is_user_space_virtual_memory(char_ptr as usize) {
-            return cos_sys::error::ErrorKind::BadPointer as u64;
-        }
-
         let thread_id = percpu::get_current_thread_id();
         let process_id = {
             let _guard = IrqGuard::cli();
@@ -26,19 +39,21 @@ syscall_handler! {
         };
         let process = multitask::process::get_process(process_id).unwrap();
 
-        let receiver = io::keyboard::receiver();
-        let char = multitask::async_rt::block_on(async {
-            receiver.lock().await.recv().await.unwrap()
+        let Ok(char_slice) = UserSlice::writable_of::<u8>(&process, char_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let console = multitask::process::process_console(&process);
+        let char = multitask::async_rt::block_on(async move {
+            io::keyboard::read_char(console).await.unwrap()
         });
         let char = match char {
             Ok(ch) => ch,
             Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
         };
 
-        unsafe {
-            if multitask::process::write_user_process_memory_struct(&process, char_ptr, &char).is_err() {
-                return cos_sys::error::ErrorKind::BadPointer as u64;
-            }
+        if char_slice.write_struct(&char).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
         }
 
         SYSCALL_SUCCESS
@@ -47,10 +62,6 @@ syscall_handler! {
 
 syscall_handler! {
     fn put_char(char_ptr: u64) -> u64 {
-        if !memory::page::is_user_space_virtual_memory(char_ptr as usize) {
-            return cos_sys::error::ErrorKind::BadPointer as u64;
-        }
-
         let thread_id = percpu::get_current_thread_id();
         let process_id = {
             let _guard = IrqGuard::cli();
@@ -58,16 +69,319 @@ syscall_handler! {
         };
         let process = multitask::process::get_process(process_id).unwrap();
 
-        let char: u8;
-        unsafe {
-            let mut uninit_char = MaybeUninit::uninit();
-            if multitask::process::read_user_process_memory_struct(&process, char_ptr, &mut uninit_char).is_err() {
-                return cos_sys::error::ErrorKind::BadPointer as u64;
+        let Ok(char_slice) = UserSlice::readable_of::<u8>(&process, char_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        // Safety: u8的任意取值均合法
+        let Ok(char) = (unsafe { char_slice.read_struct::<u8>() }) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let console = multitask::process::process_console(&process);
+        display::vga_text::_kprint_to(console, format_args!("{}", char as char));
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn put_str(buf_ptr: u64, buf_len: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let buf = match UserSlice::readable(&process, buf_ptr, buf_len as usize) {
+            Ok(buf) => buf,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let buf = match buf.read_to_vec() {
+            Ok(buf) => buf,
+            Err(error) => return error.error_kind() as u64,
+        };
+
+        let console = multitask::process::process_console(&process);
+        display::vga_text::_kprint_bytes(console, &buf);
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn open_keyboard(handle_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+        if !multitask::process::has_capability(&process, Capabilities::RAW_CONSOLE) {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+
+        let Ok(handle_slice) = UserSlice::writable_of::<u64>(&process, handle_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let console = multitask::process::process_console(&process);
+        let handle = match multitask::process::insert_process_handle(&process, HandleObject::Keyboard(ConsoleObject::new(console))) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
+
+        if handle_slice.write_struct(&handle).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn list_handles(handles_ptr: u64, handles_len: u64, count_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(count_slice) = UserSlice::writable_of::<u64>(&process, count_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let handles_range = match UserRange::array::<HandleInfo>(handles_ptr, handles_len) {
+            Ok(handles_range) => handles_range,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let handles_slice = match UserSlice::from_range(&process, handles_range, true) {
+            Ok(handles_slice) => handles_slice,
+            Err(error) => return error.error_kind() as u64,
+        };
+
+        let handles = multitask::process::list_process_handles(&process);
+        let infos: Vec<HandleInfo> = handles
+            .iter()
+            .take(handles_len as usize)
+            .map(|&(handle, kind)| HandleInfo { handle, kind })
+            .collect();
+
+        // Safety: HandleInfo为repr(C)且仅包含u64字段，不存在填充字节
+        let bytes = unsafe { slice::from_raw_parts(infos.as_ptr() as *const u8, size_of_val(&*infos)) };
+        if handles_slice.write(bytes).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+        if count_slice.write_struct(&(handles.len() as u64)).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn memory_maps(process_id: u64, regions_ptr: u64, regions_len: u64, count_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(count_slice) = UserSlice::writable_of::<u64>(&process, count_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let regions_range = match UserRange::array::<MemoryRegionInfo>(regions_ptr, regions_len) {
+            Ok(regions_range) => regions_range,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let regions_slice = match UserSlice::from_range(&process, regions_range, true) {
+            Ok(regions_slice) => regions_slice,
+            Err(error) => return error.error_kind() as u64,
+        };
+        // 进程ID从1开始，0表示当前进程
+        let target = if process_id == 0 {
+            process.clone()
+        } else {
+            let Some(target) = multitask::process::get_process(process_id) else {
+                return cos_sys::error::ErrorKind::BadArgument as u64;
+            };
+            target
+        };
+        // 内存映射会暴露地址空间布局，仅允许查看自身与子进程
+        if !multitask::process::can_inspect(&process, &target) {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+
+        let regions = multitask::process::list_process_regions(&target);
+        let infos: Vec<MemoryRegionInfo> = regions
+            .iter()
+            .take(regions_len as usize)
+            .map(|vma| {
+                let kind = match vma.kind {
+                    VmaKind::Image => cos_sys::debug::MEMORY_REGION_IMAGE,
+                    VmaKind::Stack => cos_sys::debug::MEMORY_REGION_STACK,
+                    VmaKind::Data => cos_sys::debug::MEMORY_REGION_DATA,
+                    VmaKind::Vdso => cos_sys::debug::MEMORY_REGION_VDSO,
+                };
+                let mut flags = 0;
+                if vma.writable {
+                    flags |= cos_sys::debug::MEMORY_REGION_WRITABLE;
+                }
+                if vma.executable {
+                    flags |= cos_sys::debug::MEMORY_REGION_EXECUTABLE;
+                }
+                if matches!(vma.backing, VmaBacking::File(_)) {
+                    flags |= cos_sys::debug::MEMORY_REGION_FILE;
+                }
+                MemoryRegionInfo { start: vma.start, size: vma.size, kind, flags }
+            })
+            .collect();
+
+        // Safety: MemoryRegionInfo为repr(C)且仅包含u64字段，不存在填充字节
+        let bytes = unsafe { slice::from_raw_parts(infos.as_ptr() as *const u8, size_of_val(&*infos)) };
+        if regions_slice.write(bytes).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+        if count_slice.write_struct(&(regions.len() as u64)).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn trace_process(process_id: u64, flags: u64) -> u64 {
+        if flags & !(cos_sys::debug::TRACE_SYSCALLS | cos_sys::debug::TRACE_CHILDREN) != 0 {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        }
+        let process = multitask::process::current_process().unwrap();
+        let tracer = multitask::process::process_id(&process);
+        // 进程ID从1开始，0表示当前进程
+        let process_id = if process_id == 0 {
+            tracer
+        } else {
+            let Some(target) = multitask::process::get_process(process_id) else {
+                return cos_sys::error::ErrorKind::BadArgument as u64;
+            };
+            // 跟踪记录包含系统调用参数，仅允许跟踪自身与子进程
+            if !multitask::process::can_inspect(&process, &target) {
+                return cos_sys::error::ErrorKind::PermissionDenied as u64;
             }
-            char = uninit_char.assume_init();
+            process_id
+        };
+
+        trace::set_flags(tracer, process_id, flags);
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn read_trace(process_id: u64, records_ptr: u64, records_len: u64, count_ptr: u64, dropped_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(count_slice) = UserSlice::writable_of::<u64>(&process, count_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let Ok(dropped_slice) = UserSlice::writable_of::<u64>(&process, dropped_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let records_range = match UserRange::array::<SyscallTraceRecord>(records_ptr, records_len) {
+            Ok(records_range) => records_range,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let records_slice = match UserSlice::from_range(&process, records_range, true) {
+            Ok(records_slice) => records_slice,
+            Err(error) => return error.error_kind() as u64,
+        };
+
+        // 只能取出自身设置的跟踪产生的记录，超级用户与特权进程可以取出指定进程的所有记录
+        let tracer = multitask::process::process_id(&process);
+        let privileged = multitask::process::is_privileged(&process)
+            || multitask::process::credentials(&process).is_root();
+        let (records, dropped) =
+            trace::take_records(tracer, process_id, privileged, records_len as usize);
+        // Safety: SyscallTraceRecord为repr(C)且仅包含u64字段，不存在填充字节
+        let bytes = unsafe { slice::from_raw_parts(records.as_ptr() as *const u8, size_of_val(&*records)) };
+        if records_slice.write(bytes).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+        if count_slice.write_struct(&(records.len() as u64)).is_err()
+            || dropped_slice.write_struct(&dropped).is_err()
+        {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn set_log_filter(target_ptr: u64, target_len: u64, level: u64) -> u64 {
+        let filter = match level {
+            cos_sys::debug::LOG_LEVEL_OFF => Some(None),
+            cos_sys::debug::LOG_LEVEL_ERROR => Some(Some(LogLevel::Error)),
+            cos_sys::debug::LOG_LEVEL_WARN => Some(Some(LogLevel::Warn)),
+            cos_sys::debug::LOG_LEVEL_INFO => Some(Some(LogLevel::Info)),
+            cos_sys::debug::LOG_LEVEL_DEBUG => Some(Some(LogLevel::Debug)),
+            cos_sys::debug::LOG_LEVEL_DEFAULT => None,
+            _ => return cos_sys::error::ErrorKind::BadArgument as u64,
+        };
+        if target_len == 0 || target_len > MAX_LOG_TARGET_LEN {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        }
+        let process = multitask::process::current_process().unwrap();
+
+        let target = match UserSlice::readable(&process, target_ptr, target_len as usize) {
+            Ok(target) => target,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let target = match target.read_to_vec() {
+            Ok(target) => target,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let Ok(target) = str::from_utf8(&target) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+
+        klog::set_filter(target, filter);
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn serial_write(buf_ptr: u64, buf_len: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let buf = match UserSlice::readable(&process, buf_ptr, buf_len as usize) {
+            Ok(buf) => buf,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let buf = match buf.read_to_vec() {
+            Ok(buf) => buf,
+            Err(error) => return error.error_kind() as u64,
+        };
+
+        io::serial::write(&buf);
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn exit_emulator(code: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+        if !multitask::process::is_privileged(&process) {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+
+        io::qemu::exit(code as u32);
+
+        // 未运行于qemu，或qemu未配置isa-debug-exit设备
+        cos_sys::error::ErrorKind::NotSupported as u64
+    }
+}
+
+syscall_handler! {
+    fn lock_stats(stats_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(stats_slice) = UserSlice::writable_of::<LockStats>(&process, stats_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
         };
 
-        kprint!("{}", char as char);
+        let spin_stats = spin::stats();
+        let stats = LockStats {
+            acquisitions: spin_stats.acquisitions,
+            contended: spin_stats.contended,
+            spins: spin_stats.spins,
+            irq_acquisitions: spin_stats.irq_acquisitions,
+        };
+        if stats_slice.write_struct(&stats).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
 
         SYSCALL_SUCCESS
     }
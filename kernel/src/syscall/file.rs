@@ -1,36 +1,65 @@
+use alloc::sync::Arc;
+use cos_sys::file::{
+    BlockDeviceInfo, CONTROL_GET_CONSOLE_SIZE, CONTROL_SET_CONSOLE_SIZE, ConsoleSize,
+    DirectoryEntryInfo, FileInfo, ScrubInfo,
+};
+use filesystem::fs::permission::FilePermission;
+
 use crate::{
-    syscall::SYSCALL_SUCCESS,
-    io, memory, multitask, syscall_handler,
-    user::handle::{FileHandleObject, HandleObject},
+    display,
+    io::{self, disk::ScrubStatus},
+    multitask::{self, capability::Capabilities, process::Process},
+    sync::spin::SpinLock,
+    syscall::{
+        SYSCALL_SUCCESS, block_device_error, filesystem_error, handle_error, mount_error,
+        permission_error, unmount_error,
+    },
+    syscall_handler,
+    user::{
+        handle::{CharDeviceObject, FileHandleObject, HandleObject},
+        range::UserRange,
+        slice::UserSlice,
+    },
 };
 
 syscall_handler! {
     fn create(path_ptr: u64, path_len: u64) -> u64 {
-        if !memory::page::is_user_space_virtual_memory(path_ptr as usize) ||
-            !memory::page::is_user_space_virtual_memory((path_ptr + path_len) as usize) {
-            return cos_sys::error::ErrorKind::BadPointer as u64;
+        let process = multitask::process::current_process().unwrap();
+        if !multitask::process::has_capability(&process, Capabilities::FS_WRITE) {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
         }
 
-        let process = multitask::process::current_process().unwrap();
+        let path = match UserSlice::readable(&process, path_ptr, path_len as usize) {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let path = match path.read_to_vec() {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
 
-        let mut path = alloc::vec![0u8; path_len as usize];
-        unsafe {
-            if multitask::process::read_user_process_memory(&process, path_ptr, path.as_mut_ptr(), path_len as usize).is_err() {
-                return cos_sys::error::ErrorKind::BadPointer as u64;
-            }
-        }
+        let credentials = multitask::process::credentials(&process);
 
-        let filesystem = io::disk::FILE_SYSTEMS.lock().get(&0).cloned().unwrap();
         let (sender, receiver) = async_locks::channel::oneshot::channel();
         multitask::async_rt::spawn(async move {
             let Ok(path) = filesystem::path::PathBuf::from_bytes(&path) else {
                 sender.send(Err(cos_sys::error::ErrorKind::BadArgument as u64)).await;
                 return;
             };
-            if filesystem.create_file(path.as_path()).await.is_err() {
-                sender.send(Err(cos_sys::error::ErrorKind::Unknown as u64)).await; // TODO: 错误类型占位
+            let path = path.as_path();
+            if !io::permission::check_parent(credentials, path).await {
+                sender.send(Err(cos_sys::error::ErrorKind::PermissionDenied as u64)).await;
+                return;
+            }
+            let Some((filesystem, relative)) = io::vfs::resolve(&path) else {
+                sender.send(Err(cos_sys::error::ErrorKind::FileNotFound as u64)).await;
+                return;
+            };
+            if let Err(error) = filesystem.create_file(relative).await {
+                sender.send(Err(filesystem_error(&error))).await;
                 return ;
             }
+            io::permission::created(credentials, path).await;
             sender.send(Ok(())).await;
         });
 
@@ -48,32 +77,66 @@ syscall_handler! {
 
 syscall_handler! {
     fn open(path_ptr: u64, path_len: u64, handle_ptr: u64) -> u64 {
-        if !memory::page::is_user_space_virtual_memory(path_ptr as usize) ||
-            !memory::page::is_user_space_virtual_memory((path_ptr + path_len) as usize) {
-            return cos_sys::error::ErrorKind::BadPointer as u64;
-        }
-
         let process = multitask::process::current_process().unwrap();
 
-        let mut path = alloc::vec![0u8; path_len as usize];
-        unsafe {
-            if multitask::process::read_user_process_memory(&process, path_ptr, path.as_mut_ptr(), path_len as usize).is_err() {
-                return cos_sys::error::ErrorKind::BadPointer as u64;
-            }
-        }
+        let Ok(handle_slice) = UserSlice::writable_of::<u64>(&process, handle_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let path = match UserSlice::readable(&process, path_ptr, path_len as usize) {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let path = match path.read_to_vec() {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+
+        let credentials = multitask::process::credentials(&process);
+        let caller = process.clone();
 
-        let filesystem = io::disk::FILE_SYSTEMS.lock().get(&0).cloned().unwrap();
         let (sender, receiver) = async_locks::channel::oneshot::channel();
         multitask::async_rt::spawn(async move {
             let Ok(path) = filesystem::path::PathBuf::from_bytes(&path) else {
                 sender.send(Err(cos_sys::error::ErrorKind::BadArgument as u64)).await;
                 return;
             };
-            let Ok(handle) = filesystem.open_file(path.as_path()).await else {
-                sender.send(Err(cos_sys::error::ErrorKind::Unknown as u64)).await; // TODO: 错误类型占位
-                return ;
+            let path = path.as_path();
+            // 句柄只允许具有权限的访问，两者均没有权限时拒绝打开
+            let permission = io::permission::get(path).await;
+            let mut access = 0;
+            for bit in [FilePermission::READ, FilePermission::WRITE] {
+                if permission.allows(credentials.uid, credentials.gid, bit) {
+                    access |= bit;
+                }
+            }
+            if access == 0 {
+                sender.send(Err(cos_sys::error::ErrorKind::PermissionDenied as u64)).await;
+                return;
+            }
+            let Some((filesystem, path)) = io::vfs::resolve(&path) else {
+                sender.send(Err(cos_sys::error::ErrorKind::FileNotFound as u64)).await;
+                return;
             };
-            sender.send(Ok(handle)).await;
+            // 字符设备不经过文件句柄
+            if let Some(device) = io::devfs::open_char_device(&filesystem, path) {
+                // 控制台与键盘句柄相同，只能打开可以直接访问的控制台
+                if let Some(console) = device.console()
+                    && !can_access_console(&caller, console)
+                {
+                    sender.send(Err(cos_sys::error::ErrorKind::PermissionDenied as u64)).await;
+                    return;
+                }
+                sender.send(Ok(HandleObject::CharDevice(CharDeviceObject::new(device, access)))).await;
+                return;
+            }
+            let handle = match filesystem.open_file(path).await {
+                Ok(handle) => handle,
+                Err(error) => {
+                    sender.send(Err(filesystem_error(&error))).await;
+                    return;
+                }
+            };
+            sender.send(Ok(HandleObject::File(FileHandleObject::new(handle, filesystem, access)))).await;
         });
 
 
@@ -87,13 +150,13 @@ syscall_handler! {
             Err(error) => return error,
         };
 
-        let file_handle = FileHandleObject::new(handle);
-        let handle = multitask::process::insert_process_handle(&process, HandleObject::File(file_handle)) as u64;
+        let handle = match multitask::process::insert_process_handle(&process, handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
 
-        unsafe {
-            if multitask::process::write_user_process_memory_struct(&process, handle_ptr, &handle).is_err() {
-                return cos_sys::error::ErrorKind::BadPointer as u64;
-            }
+        if handle_slice.write_struct(&handle).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
         }
 
         SYSCALL_SUCCESS
@@ -102,19 +165,164 @@ syscall_handler! {
 
 syscall_handler! {
     fn read(handle: u64, buffer_ptr: u64, buffer_len: u64, read_count_ptr: u64) -> u64 {
-        if !memory::page::is_user_space_virtual_memory(buffer_ptr as usize) ||
-            !memory::page::is_user_space_virtual_memory((buffer_ptr + buffer_len) as usize) ||
-            !memory::page::is_user_space_virtual_memory(read_count_ptr as usize) {
+        let process = multitask::process::current_process().unwrap();
+
+        let buffer_slice = match UserSlice::writable(&process, buffer_ptr, buffer_len as usize) {
+            Ok(buffer_slice) => buffer_slice,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let Ok(read_count_slice) = UserSlice::writable_of::<u64>(&process, read_count_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let handle = match multitask::process::get_process_handle(&process, handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
+
+        // 将用户缓冲区映射到内核空间，文件系统直接写入用户内存页
+        let mut buffer = match buffer_slice.into_mapping() {
+            Ok(buffer) => buffer,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let (sender, receiver) = async_locks::channel::oneshot::channel();
+        multitask::async_rt::spawn(async move {
+            let result = match &*handle {
+                HandleObject::File(handle) if handle.allows(FilePermission::READ) => {
+                    handle.lock().await.read(&mut buffer).await
+                }
+                HandleObject::CharDevice(device) if device.allows(FilePermission::READ) => {
+                    device.read(&mut buffer).await
+                }
+                HandleObject::File(_) | HandleObject::CharDevice(_) => {
+                    sender.send(Err(cos_sys::error::ErrorKind::PermissionDenied as u64)).await;
+                    return;
+                }
+                _ => {
+                    sender.send(Err(cos_sys::error::ErrorKind::BadArgument as u64)).await;
+                    return;
+                }
+            };
+            let count = match result {
+                Ok(count) => count,
+                Err(error) => {
+                    sender.send(Err(filesystem_error(&error))).await;
+                    return;
+                }
+            };
+            sender.send(Ok(count)).await;
+        });
+        
+        let block_on = match multitask::async_rt::block_on(receiver.recv()) {
+            Ok(res) => res,
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        let read_count = match block_on.unwrap() {
+            Ok(read) => read,
+            Err(error) => return error,
+        };
+
+        if read_count_slice.write_struct(&read_count).is_err() {
             return cos_sys::error::ErrorKind::BadPointer as u64;
         }
 
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn write(handle: u64, buffer_ptr: u64, buffer_len: u64, write_count_ptr: u64) -> u64 {
         let process = multitask::process::current_process().unwrap();
 
-        let Some(handle) = multitask::process::get_process_handle(&process, handle as usize) else {
-            return cos_sys::error::ErrorKind::BadArgument as u64;
+        let buffer_slice = match UserSlice::readable(&process, buffer_ptr, buffer_len as usize) {
+            Ok(buffer_slice) => buffer_slice,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let Ok(write_count_slice) = UserSlice::writable_of::<u64>(&process, write_count_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let handle = match multitask::process::get_process_handle(&process, handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
+        // 写入字符设备（如控制台）不修改文件系统，不需要FS_WRITE能力
+        if matches!(&*handle, HandleObject::File(_))
+            && !multitask::process::has_capability(&process, Capabilities::FS_WRITE)
+        {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+
+        let buffer = match buffer_slice.read_to_vec() {
+            Ok(buffer) => buffer,
+            Err(error) => return error.error_kind() as u64,
+        };
+
+        let (sender, receiver) = async_locks::channel::oneshot::channel();
+        multitask::async_rt::spawn(async move {
+            let result = match &*handle {
+                HandleObject::File(handle) if handle.allows(FilePermission::WRITE) => {
+                    handle.lock().await.write(&buffer).await
+                }
+                HandleObject::CharDevice(device) if device.allows(FilePermission::WRITE) => {
+                    device.write(&buffer).await
+                }
+                HandleObject::File(_) | HandleObject::CharDevice(_) => {
+                    sender.send(Err(cos_sys::error::ErrorKind::PermissionDenied as u64)).await;
+                    return;
+                }
+                _ => {
+                    sender.send(Err(cos_sys::error::ErrorKind::BadArgument as u64)).await;
+                    return;
+                }
+            };
+            if let Err(error) = result {
+                sender.send(Err(filesystem_error(&error))).await;
+                return;
+            }
+            sender.send(Ok(buffer.len() as u64)).await;
+        });
+        let write_count = match multitask::async_rt::block_on(receiver.recv()) {
+            Ok(res) => res,
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        let write_count = match write_count.unwrap() {
+            Ok(write_count) => write_count,
+            Err(error) => return error,
+        };
+
+        if write_count_slice.write_struct(&write_count).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn close(handle: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        match multitask::process::remove_process_handle(&process, handle) {
+            Ok(()) => SYSCALL_SUCCESS,
+            Err(error) => handle_error(&error),
+        }
+    }
+}
+
+syscall_handler! {
+    fn get_pos(handle: u64, pos_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(pos_slice) = UserSlice::writable_of::<u64>(&process, pos_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let handle = match multitask::process::get_process_handle(&process, handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
         };
 
-        let mut buffer = alloc::vec![0u8; buffer_len as usize];
         let (sender, receiver) = async_locks::channel::oneshot::channel();
         multitask::async_rt::spawn(async move {
             let HandleObject::File(handle) = &*handle else {
@@ -123,172 +331,789 @@ syscall_handler! {
             };
 
             let mut file = handle.lock().await;
-            let Ok(count) = file.read(&mut buffer).await else {
-                sender.send(Err(cos_sys::error::ErrorKind::Unknown as u64)).await;
-                return;
+            let count = match file.get_pointer().await {
+                Ok(count) => count,
+                Err(error) => {
+                    sender.send(Err(filesystem_error(&error))).await;
+                    return;
+                }
             };
-            sender.send(Ok((count, buffer))).await;
+            sender.send(Ok(count)).await;
         });
-        
-        let block_on = match multitask::async_rt::block_on(receiver.recv()) {
+        let pos = match multitask::async_rt::block_on(receiver.recv()) {
             Ok(res) => res,
             Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
         };
-        let (read_count, buffer) = match block_on.unwrap() {
-            Ok(read) => read,
+        let pos = match pos.unwrap() {
+            Ok(pos) => pos,
             Err(error) => return error,
         };
 
-        unsafe {
-            if multitask::process::write_user_process_memory(&process, buffer_ptr, buffer.as_ptr(), read_count as usize).is_err() {
-                return cos_sys::error::ErrorKind::BadPointer as u64;
+        if pos_slice.write_struct(&pos).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn set_pos(handle: u64, pos: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let handle = match multitask::process::get_process_handle(&process, handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
+
+        let (sender, receiver) = async_locks::channel::oneshot::channel();
+        multitask::async_rt::spawn(async move {
+            let HandleObject::File(handle) = &*handle else {
+                sender.send(Err(cos_sys::error::ErrorKind::BadArgument as u64)).await;
+                return;
+            };
+
+            let mut file = handle.lock().await;
+            if let Err(error) = file.move_pointer(pos).await {
+                sender.send(Err(filesystem_error(&error))).await;
+                return;
+            };
+            sender.send(Ok(())).await;
+        });
+
+        let error = match multitask::async_rt::block_on(receiver.recv()) {
+            Ok(res) => res,
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        match error.unwrap() {
+            Ok(()) => SYSCALL_SUCCESS,
+            Err(error) => error,
+        }
+    }
+}
+
+syscall_handler! {
+    fn truncate(handle: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+        if !multitask::process::has_capability(&process, Capabilities::FS_WRITE) {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+
+        let handle = match multitask::process::get_process_handle(&process, handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
+
+        let (sender, receiver) = async_locks::channel::oneshot::channel();
+        multitask::async_rt::spawn(async move {
+            let HandleObject::File(handle) = &*handle else {
+                sender.send(Err(cos_sys::error::ErrorKind::BadArgument as u64)).await;
+                return;
+            };
+            if !handle.allows(FilePermission::WRITE) {
+                sender.send(Err(cos_sys::error::ErrorKind::PermissionDenied as u64)).await;
+                return;
+            }
+
+            let mut file = handle.lock().await;
+            if let Err(error) = file.truncate().await {
+                sender.send(Err(filesystem_error(&error))).await;
+                return;
+            };
+            sender.send(Ok(())).await;
+        });
+
+        let error = match multitask::async_rt::block_on(receiver.recv()) {
+            Ok(res) => res,
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        match error.unwrap() {
+            Ok(()) => SYSCALL_SUCCESS,
+            Err(error) => error,
+        }
+    }
+}
+
+syscall_handler! {
+    fn list_block_devices(devices_ptr: u64, devices_len: u64, count_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(count_slice) = UserSlice::writable_of::<u64>(&process, count_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let devices_range = match UserRange::array::<BlockDeviceInfo>(devices_ptr, devices_len) {
+            Ok(devices_range) => devices_range,
+            Err(error) => return error.error_kind() as u64,
+        };
+        if let Err(error) = UserSlice::from_range(&process, devices_range, true) {
+            return error.error_kind() as u64;
+        }
+
+        let (sender, receiver) = async_locks::channel::oneshot::channel();
+        multitask::async_rt::spawn(async move {
+            io::disk::rescan().await;
+            sender.send(io::disk::devices()).await;
+        });
+        let devices = match multitask::async_rt::block_on(receiver.recv()) {
+            Ok(devices) => devices.unwrap(),
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+
+        for (index, device) in devices.iter().take(devices_len as usize).enumerate() {
+            let mut info = BlockDeviceInfo {
+                block_size: device.device.block_size(),
+                block_count: device.device.block_count(),
+                partition_type: device.partition_type.unwrap_or(0) as u64,
+                ..Default::default()
+            };
+            let name = device.name.as_bytes();
+            let name_len = name.len().min(info.name.len());
+            info.name[..name_len].copy_from_slice(&name[..name_len]);
+            info.name_len = name_len as u64;
+            if let Some(path) = io::vfs::mount_path_of(&device.name) {
+                let path = alloc::string::ToString::to_string(&path);
+                let path_len = path.len().min(info.mount_path.len());
+                info.mount_path[..path_len].copy_from_slice(&path.as_bytes()[..path_len]);
+                info.mount_path_len = path_len as u64;
             }
-            if multitask::process::write_user_process_memory_struct(&process, read_count_ptr, &read_count).is_err() {
+
+            let info_slice = match devices_range.element::<BlockDeviceInfo>(index).and_then(|range| UserSlice::from_range(&process, range, true)) {
+                Ok(info_slice) => info_slice,
+                Err(error) => return error.error_kind() as u64,
+            };
+            if info_slice.write_struct(&info).is_err() {
                 return cos_sys::error::ErrorKind::BadPointer as u64;
             }
         }
+        if count_slice.write_struct(&(devices.len() as u64)).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
 
         SYSCALL_SUCCESS
     }
 }
 
 syscall_handler! {
-    fn write(handle: u64, buffer_ptr: u64, buffer_len: u64, write_count_ptr: u64) -> u64 {
-        if !memory::page::is_user_space_virtual_memory(buffer_ptr as usize) ||
-            !memory::page::is_user_space_virtual_memory((buffer_ptr + buffer_len) as usize) ||
-            !memory::page::is_user_space_virtual_memory(write_count_ptr as usize) {
-            return cos_sys::error::ErrorKind::BadPointer as u64;
+    fn mount(device_ptr: u64, device_len: u64, path_ptr: u64, path_len: u64) -> u64 {
+        // 只有超级用户可以挂载或卸载文件系统
+        let process = multitask::process::current_process().unwrap();
+        if !multitask::process::has_capability(&process, Capabilities::FS_WRITE)
+            || !multitask::process::credentials(&process).is_root()
+        {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+
+        let device = match UserSlice::readable(&process, device_ptr, device_len as usize) {
+            Ok(device) => device,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let device = match device.read_to_vec() {
+            Ok(device) => device,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let Ok(device) = alloc::string::String::from_utf8(device) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+        let path = match UserSlice::readable(&process, path_ptr, path_len as usize) {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let path = match path.read_to_vec() {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let Ok(path) = filesystem::path::PathBuf::from_bytes(&path) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+
+        let (sender, receiver) = async_locks::channel::oneshot::channel();
+        multitask::async_rt::spawn(async move {
+            let result = io::vfs::mount_device(&device, path).await;
+            sender.send(result.map_err(|error| mount_error(&error))).await;
+        });
+
+        let result = match multitask::async_rt::block_on(receiver.recv()) {
+            Ok(res) => res,
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        match result.unwrap() {
+            Ok(()) => SYSCALL_SUCCESS,
+            Err(error) => error,
+        }
+    }
+}
+
+syscall_handler! {
+    fn mount_encrypted(device_ptr: u64, device_len: u64, path_ptr: u64, path_len: u64, passphrase_ptr: u64, passphrase_len: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+        if !multitask::process::has_capability(&process, Capabilities::FS_WRITE)
+            || !multitask::process::credentials(&process).is_root()
+        {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
         }
 
+        let device = match UserSlice::readable(&process, device_ptr, device_len as usize) {
+            Ok(device) => device,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let device = match device.read_to_vec() {
+            Ok(device) => device,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let Ok(device) = alloc::string::String::from_utf8(device) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+        let path = match UserSlice::readable(&process, path_ptr, path_len as usize) {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let path = match path.read_to_vec() {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let Ok(path) = filesystem::path::PathBuf::from_bytes(&path) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+        let passphrase = match UserSlice::readable(&process, passphrase_ptr, passphrase_len as usize) {
+            Ok(passphrase) => passphrase,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let mut passphrase = match passphrase.read_to_vec() {
+            Ok(passphrase) => passphrase,
+            Err(error) => return error.error_kind() as u64,
+        };
+
+        let (sender, receiver) = async_locks::channel::oneshot::channel();
+        multitask::async_rt::spawn(async move {
+            let result = io::vfs::mount_encrypted(&device, &passphrase, path).await;
+            // 口令在派生密钥后不再需要，不应残留在内核堆中
+            crypto::zeroize(&mut passphrase);
+            sender.send(result.map_err(|error| mount_error(&error))).await;
+        });
+
+        let result = match multitask::async_rt::block_on(receiver.recv()) {
+            Ok(res) => res,
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        match result.unwrap() {
+            Ok(()) => SYSCALL_SUCCESS,
+            Err(error) => error,
+        }
+    }
+}
+
+syscall_handler! {
+    fn unmount(path_ptr: u64, path_len: u64) -> u64 {
         let process = multitask::process::current_process().unwrap();
+        if !multitask::process::has_capability(&process, Capabilities::FS_WRITE)
+            || !multitask::process::credentials(&process).is_root()
+        {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
 
-        let Some(handle) = multitask::process::get_process_handle(&process, handle as usize) else {
+        let path = match UserSlice::readable(&process, path_ptr, path_len as usize) {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let path = match path.read_to_vec() {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let Ok(path) = filesystem::path::PathBuf::from_bytes(&path) else {
             return cos_sys::error::ErrorKind::BadArgument as u64;
         };
 
-        let mut buffer = alloc::vec![0u8; buffer_len as usize];
-        unsafe {
-            if multitask::process::read_user_process_memory(&process, buffer_ptr, buffer.as_mut_ptr(), buffer_len as usize).is_err() {
-                return cos_sys::error::ErrorKind::BadPointer as u64;
-            }
+        let (sender, receiver) = async_locks::channel::oneshot::channel();
+        multitask::async_rt::spawn(async move {
+            let result = io::vfs::unmount(&path).await;
+            sender.send(result.map_err(|error| unmount_error(&error))).await;
+        });
+
+        let result = match multitask::async_rt::block_on(receiver.recv()) {
+            Ok(res) => res,
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        match result.unwrap() {
+            Ok(()) => SYSCALL_SUCCESS,
+            Err(error) => error,
+        }
+    }
+}
+
+syscall_handler! {
+    fn list_directory(path_ptr: u64, path_len: u64, entries_ptr: u64, entries_len: u64, count_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(count_slice) = UserSlice::writable_of::<u64>(&process, count_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let entries_range = match UserRange::array::<DirectoryEntryInfo>(entries_ptr, entries_len) {
+            Ok(entries_range) => entries_range,
+            Err(error) => return error.error_kind() as u64,
+        };
+        if let Err(error) = UserSlice::from_range(&process, entries_range, true) {
+            return error.error_kind() as u64;
         }
+        let path = match UserSlice::readable(&process, path_ptr, path_len as usize) {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let path = match path.read_to_vec() {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+
+        let credentials = multitask::process::credentials(&process);
 
         let (sender, receiver) = async_locks::channel::oneshot::channel();
         multitask::async_rt::spawn(async move {
-            let HandleObject::File(handle) = &*handle else {
+            let Ok(path) = filesystem::path::PathBuf::from_bytes(&path) else {
                 sender.send(Err(cos_sys::error::ErrorKind::BadArgument as u64)).await;
                 return;
             };
-
-            let mut file = handle.lock().await;
-            let Ok(count) = file.write(&buffer).await else {
-                sender.send(Err(cos_sys::error::ErrorKind::Unknown as u64)).await;
+            let path = path.as_path();
+            if !io::permission::check(credentials, path, FilePermission::READ).await {
+                sender.send(Err(cos_sys::error::ErrorKind::PermissionDenied as u64)).await;
+                return;
+            }
+            let Some((filesystem, path)) = io::vfs::resolve(&path) else {
+                sender.send(Err(cos_sys::error::ErrorKind::FileNotFound as u64)).await;
                 return;
             };
-            sender.send(Ok(count)).await;
+            let result = filesystem.list_directory(path).await;
+            sender.send(result.map_err(|error| filesystem_error(&error))).await;
         });
-        let read_count = match multitask::async_rt::block_on(receiver.recv()) {
+
+        let result = match multitask::async_rt::block_on(receiver.recv()) {
             Ok(res) => res,
             Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
         };
-        let read_count = match read_count.unwrap() {
-            Ok(read_count) => read_count,
+        let entries = match result.unwrap() {
+            Ok(entries) => entries,
             Err(error) => return error,
         };
 
-        unsafe {
-            if multitask::process::write_user_process_memory_struct(&process, write_count_ptr, &read_count).is_err() {
+        for (index, entry) in entries.iter().take(entries_len as usize).enumerate() {
+            let mut info = DirectoryEntryInfo {
+                size: if entry.is_directory { 0 } else { entry.size },
+                flags: if entry.is_directory { cos_sys::file::DIRECTORY_ENTRY_DIRECTORY } else { 0 },
+                ..Default::default()
+            };
+            let name = entry.name.as_bytes();
+            let name_len = name.len().min(info.name.len());
+            info.name[..name_len].copy_from_slice(&name[..name_len]);
+            info.name_len = name_len as u64;
+
+            let info_slice = match entries_range.element::<DirectoryEntryInfo>(index).and_then(|range| UserSlice::from_range(&process, range, true)) {
+                Ok(info_slice) => info_slice,
+                Err(error) => return error.error_kind() as u64,
+            };
+            if info_slice.write_struct(&info).is_err() {
                 return cos_sys::error::ErrorKind::BadPointer as u64;
             }
         }
+        if count_slice.write_struct(&(entries.len() as u64)).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
 
         SYSCALL_SUCCESS
     }
 }
 
 syscall_handler! {
-    fn close(handle: u64) {
+    fn delete(path_ptr: u64, path_len: u64) -> u64 {
         let process = multitask::process::current_process().unwrap();
+        if !multitask::process::has_capability(&process, Capabilities::FS_WRITE) {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+
+        let path = match UserSlice::readable(&process, path_ptr, path_len as usize) {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let path = match path.read_to_vec() {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+
+        let credentials = multitask::process::credentials(&process);
+
+        let (sender, receiver) = async_locks::channel::oneshot::channel();
+        multitask::async_rt::spawn(async move {
+            let Ok(path) = filesystem::path::PathBuf::from_bytes(&path) else {
+                sender.send(Err(cos_sys::error::ErrorKind::BadArgument as u64)).await;
+                return;
+            };
+            let path = path.as_path();
+            if !io::permission::check_parent(credentials, path).await {
+                sender.send(Err(cos_sys::error::ErrorKind::PermissionDenied as u64)).await;
+                return;
+            }
+            let Some((filesystem, relative)) = io::vfs::resolve(&path) else {
+                sender.send(Err(cos_sys::error::ErrorKind::FileNotFound as u64)).await;
+                return;
+            };
+            if let Err(error) = filesystem.delete_file(relative).await {
+                sender.send(Err(filesystem_error(&error))).await;
+                return;
+            }
+            io::permission::removed(path).await;
+            sender.send(Ok(())).await;
+        });
 
-        multitask::process::remove_process_handle(&process, handle as usize);
+        let result = match multitask::async_rt::block_on(receiver.recv()) {
+            Ok(res) => res,
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        let result = result.unwrap();
+        match result {
+            Ok(()) => SYSCALL_SUCCESS,
+            Err(error) => error,
+        }
     }
 }
 
 syscall_handler! {
-    fn get_pos(handle: u64, pos_ptr: u64) -> u64 {
-        if !memory::page::is_user_space_virtual_memory(pos_ptr as usize) {
-            return cos_sys::error::ErrorKind::BadPointer as u64;
+    fn rename(old_path_ptr: u64, old_path_len: u64, new_path_ptr: u64, new_path_len: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+        if !multitask::process::has_capability(&process, Capabilities::FS_WRITE) {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
         }
 
+        let old_path = match UserSlice::readable(&process, old_path_ptr, old_path_len as usize) {
+            Ok(old_path) => old_path,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let old_path = match old_path.read_to_vec() {
+            Ok(old_path) => old_path,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let new_path = match UserSlice::readable(&process, new_path_ptr, new_path_len as usize) {
+            Ok(new_path) => new_path,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let new_path = match new_path.read_to_vec() {
+            Ok(new_path) => new_path,
+            Err(error) => return error.error_kind() as u64,
+        };
+
+        let credentials = multitask::process::credentials(&process);
+
+        let (sender, receiver) = async_locks::channel::oneshot::channel();
+        multitask::async_rt::spawn(async move {
+            let (Ok(old_path), Ok(new_path)) = (
+                filesystem::path::PathBuf::from_bytes(&old_path),
+                filesystem::path::PathBuf::from_bytes(&new_path),
+            ) else {
+                sender.send(Err(cos_sys::error::ErrorKind::BadArgument as u64)).await;
+                return;
+            };
+            let (old_path, new_path) = (old_path.as_path(), new_path.as_path());
+            if !io::permission::check_parent(credentials, old_path).await
+                || !io::permission::check_parent(credentials, new_path).await
+            {
+                sender.send(Err(cos_sys::error::ErrorKind::PermissionDenied as u64)).await;
+                return;
+            }
+            let (Some((filesystem, old_relative)), Some((new_filesystem, new_relative))) =
+                (io::vfs::resolve(&old_path), io::vfs::resolve(&new_path))
+            else {
+                sender.send(Err(cos_sys::error::ErrorKind::FileNotFound as u64)).await;
+                return;
+            };
+            // 文件系统之间无法直接移动
+            if !Arc::ptr_eq(&filesystem, &new_filesystem) {
+                sender.send(Err(cos_sys::error::ErrorKind::NotSupported as u64)).await;
+                return;
+            }
+            if let Err(error) = filesystem.rename(old_relative, new_relative).await {
+                sender.send(Err(filesystem_error(&error))).await;
+                return;
+            }
+            io::permission::renamed(old_path, new_path).await;
+            sender.send(Ok(())).await;
+        });
+
+        let result = match multitask::async_rt::block_on(receiver.recv()) {
+            Ok(res) => res,
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        match result.unwrap() {
+            Ok(()) => SYSCALL_SUCCESS,
+            Err(error) => error,
+        }
+    }
+}
+
+syscall_handler! {
+    fn metadata(path_ptr: u64, path_len: u64, info_ptr: u64) -> u64 {
         let process = multitask::process::current_process().unwrap();
 
-        let Some(handle) = multitask::process::get_process_handle(&process, handle as usize) else {
-            return cos_sys::error::ErrorKind::BadArgument as u64;
+        let Ok(info_slice) = UserSlice::writable_of::<FileInfo>(&process, info_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let path = match UserSlice::readable(&process, path_ptr, path_len as usize) {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let path = match path.read_to_vec() {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
         };
 
         let (sender, receiver) = async_locks::channel::oneshot::channel();
         multitask::async_rt::spawn(async move {
-            let HandleObject::File(handle) = &*handle else {
+            let Ok(path) = filesystem::path::PathBuf::from_bytes(&path) else {
                 sender.send(Err(cos_sys::error::ErrorKind::BadArgument as u64)).await;
                 return;
             };
-
-            let mut file = handle.lock().await;
-            let Ok(count) = file.get_pointer().await else {
-                sender.send(Err(cos_sys::error::ErrorKind::Unknown as u64)).await;
+            let path = path.as_path();
+            let Some((filesystem, relative)) = io::vfs::resolve(&path) else {
+                sender.send(Err(cos_sys::error::ErrorKind::FileNotFound as u64)).await;
                 return;
             };
-            sender.send(Ok(count)).await;
+            let metadata = match filesystem.get_metadata(relative).await {
+                Ok(metadata) => metadata,
+                Err(error) => {
+                    sender.send(Err(filesystem_error(&error))).await;
+                    return;
+                }
+            };
+            drop(filesystem);
+            let permission = io::permission::get(path).await;
+            sender.send(Ok((metadata, permission))).await;
         });
-        let pos = match multitask::async_rt::block_on(receiver.recv()) {
+
+        let result = match multitask::async_rt::block_on(receiver.recv()) {
             Ok(res) => res,
             Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
         };
-        let pos = match pos.unwrap() {
-            Ok(pos) => pos,
+        let (metadata, permission) = match result.unwrap() {
+            Ok(result) => result,
             Err(error) => return error,
         };
 
-        unsafe {
-            if multitask::process::write_user_process_memory_struct(&process, pos_ptr, &pos).is_err() {
+        let info = FileInfo {
+            size: if metadata.is_directory { 0 } else { metadata.size },
+            allocated_size: metadata.allocated_size,
+            flags: if metadata.is_directory { cos_sys::file::DIRECTORY_ENTRY_DIRECTORY } else { 0 },
+            uid: permission.uid as u64,
+            gid: permission.gid as u64,
+            mode: permission.mode as u64,
+        };
+        if info_slice.write_struct(&info).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn scrub(device_ptr: u64, device_len: u64, blocks_ptr: u64, blocks_len: u64, info_ptr: u64) -> u64 {
+        // 扫描会读取整个块设备，只有超级用户可以发起
+        let process = multitask::process::current_process().unwrap();
+        if !multitask::process::credentials(&process).is_root() {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+
+        let Ok(info_slice) = UserSlice::writable_of::<ScrubInfo>(&process, info_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let blocks_range = match UserRange::array::<u64>(blocks_ptr, blocks_len) {
+            Ok(blocks_range) => blocks_range,
+            Err(error) => return error.error_kind() as u64,
+        };
+        if let Err(error) = UserSlice::from_range(&process, blocks_range, true) {
+            return error.error_kind() as u64;
+        }
+        let device = match UserSlice::readable(&process, device_ptr, device_len as usize) {
+            Ok(device) => device,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let device = match device.read_to_vec() {
+            Ok(device) => device,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let Ok(device) = alloc::string::String::from_utf8(device) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+
+        let Some(disk) = io::disk::find(&device) else {
+            return cos_sys::error::ErrorKind::FileNotFound as u64;
+        };
+        let Some(checksum) = disk.checksum else {
+            return cos_sys::error::ErrorKind::NotSupported as u64;
+        };
+
+        // 扫描在后台任务中执行，调用立即返回，扫描完成后再次调用取出结果
+        let report = match io::disk::scrub(&device, checksum) {
+            ScrubStatus::Running => return cos_sys::error::ErrorKind::WouldBlock as u64,
+            ScrubStatus::Finished(Ok(report)) => report,
+            ScrubStatus::Finished(Err(error)) => return block_device_error(&error),
+        };
+
+        for (index, block) in report.corrupted.iter().take(blocks_len as usize).enumerate() {
+            let block_slice = match blocks_range.element::<u64>(index).and_then(|range| UserSlice::from_range(&process, range, true)) {
+                Ok(block_slice) => block_slice,
+                Err(error) => return error.error_kind() as u64,
+            };
+            if block_slice.write_struct(block).is_err() {
                 return cos_sys::error::ErrorKind::BadPointer as u64;
             }
         }
+        let info = ScrubInfo {
+            checked: report.checked,
+            corrupted: report.corrupted.len() as u64,
+        };
+        if info_slice.write_struct(&info).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
 
         SYSCALL_SUCCESS
     }
 }
 
 syscall_handler! {
-    fn set_pos(handle: u64, pos: u64) -> u64 {
+    fn chmod(path_ptr: u64, path_len: u64, mode: u64) -> u64 {
         let process = multitask::process::current_process().unwrap();
+        if !multitask::process::has_capability(&process, Capabilities::FS_WRITE) {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
 
-        let Some(handle) = multitask::process::get_process_handle(&process, handle as usize) else {
-            return cos_sys::error::ErrorKind::BadArgument as u64;
+        let path = match UserSlice::readable(&process, path_ptr, path_len as usize) {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let path = match path.read_to_vec() {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
         };
 
+        let credentials = multitask::process::credentials(&process);
+
         let (sender, receiver) = async_locks::channel::oneshot::channel();
         multitask::async_rt::spawn(async move {
-            let HandleObject::File(handle) = &*handle else {
+            let Ok(path) = filesystem::path::PathBuf::from_bytes(&path) else {
                 sender.send(Err(cos_sys::error::ErrorKind::BadArgument as u64)).await;
                 return;
             };
+            let result = io::permission::chmod(credentials, path.as_path(), (mode & FilePermission::MODE_MASK as u64) as u16).await;
+            sender.send(result.map_err(|error| permission_error(&error))).await;
+        });
 
-            let mut file = handle.lock().await;
-            if file.move_pointer(pos).await.is_err() {
-                sender.send(Err(cos_sys::error::ErrorKind::Unknown as u64)).await;
+        let result = match multitask::async_rt::block_on(receiver.recv()) {
+            Ok(res) => res,
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        match result.unwrap() {
+            Ok(()) => SYSCALL_SUCCESS,
+            Err(error) => error,
+        }
+    }
+}
+
+syscall_handler! {
+    fn chown(path_ptr: u64, path_len: u64, uid: u64, gid: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+        if !multitask::process::has_capability(&process, Capabilities::FS_WRITE) {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+        let (Ok(uid), Ok(gid)) = (u32::try_from(uid), u32::try_from(gid)) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+
+        let path = match UserSlice::readable(&process, path_ptr, path_len as usize) {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let path = match path.read_to_vec() {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+
+        let credentials = multitask::process::credentials(&process);
+
+        let (sender, receiver) = async_locks::channel::oneshot::channel();
+        multitask::async_rt::spawn(async move {
+            let Ok(path) = filesystem::path::PathBuf::from_bytes(&path) else {
+                sender.send(Err(cos_sys::error::ErrorKind::BadArgument as u64)).await;
                 return;
             };
-            sender.send(Ok(())).await;
+            let result = io::permission::chown(credentials, path.as_path(), uid, gid).await;
+            sender.send(result.map_err(|error| permission_error(&error))).await;
         });
 
-        let error = match multitask::async_rt::block_on(receiver.recv()) {
+        let result = match multitask::async_rt::block_on(receiver.recv()) {
             Ok(res) => res,
             Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
         };
-        match error.unwrap() {
+        match result.unwrap() {
             Ok(()) => SYSCALL_SUCCESS,
             Err(error) => error,
         }
     }
 }
+
+syscall_handler! {
+    fn control(handle: u64, request: u64, arg_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let handle = match multitask::process::get_process_handle(&process, handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
+        // 目前只有控制台支持控制请求
+        let Some(console) = handle.console() else {
+            return cos_sys::error::ErrorKind::NotSupported as u64;
+        };
+        let Ok(size_slice) = UserSlice::writable_of::<ConsoleSize>(&process, arg_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let (rows, cols) = match request {
+            CONTROL_GET_CONSOLE_SIZE => display::console::size(console.console()),
+            CONTROL_SET_CONSOLE_SIZE => {
+                if let HandleObject::CharDevice(device) = &*handle
+                    && !device.allows(FilePermission::WRITE)
+                {
+                    return cos_sys::error::ErrorKind::PermissionDenied as u64;
+                }
+                // 句柄可能来自其他进程，修改时再次检查调用者能否访问该控制台
+                if !can_access_console(&process, console.console()) {
+                    return cos_sys::error::ErrorKind::PermissionDenied as u64;
+                }
+                // Safety: ConsoleSize的任意取值均合法
+                let Ok(size) = (unsafe { size_slice.read_struct::<ConsoleSize>() }) else {
+                    return cos_sys::error::ErrorKind::BadPointer as u64;
+                };
+                let rows = size.rows.min(u8::MAX as u32) as u8;
+                let cols = size.cols.min(u8::MAX as u32) as u8;
+                display::console::resize(console.console(), rows, cols)
+            }
+            _ => return cos_sys::error::ErrorKind::BadArgument as u64,
+        };
+
+        let size = ConsoleSize {
+            rows: rows as u32,
+            cols: cols as u32,
+        };
+        if size_slice.write_struct(&size).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+/// 判断进程能否直接访问控制台
+///
+/// 需具有 [Capabilities::RAW_CONSOLE] 能力，且控制台为进程自身的控制台，或进程位于控制台的前台进程组
+fn can_access_console(process: &SpinLock<Process>, console: usize) -> bool {
+    if !multitask::process::has_capability(process, Capabilities::RAW_CONSOLE) {
+        return false;
+    }
+    let (process_group, _) = multitask::process::process_group(process);
+    console == multitask::process::process_console(process)
+        || io::console::foreground_group(console) == process_group
+}
@@ -0,0 +1,126 @@
+use cos_sys::gfx::{ModeInfo, Rect};
+
+use crate::{
+    display::framebuffer,
+    multitask::{self, capability::Capabilities, process::ProcessPageType},
+    syscall::SYSCALL_SUCCESS,
+    syscall_handler,
+    user::{range::UserRange, slice::UserSlice},
+};
+
+/// 每次从后台缓冲区读取的最大像素数
+const CHUNK_PIXELS: usize = 1024;
+
+syscall_handler! {
+    fn mode_info(info_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(info_slice) = UserSlice::writable_of::<ModeInfo>(&process, info_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let Some(framebuffer) = framebuffer::info() else {
+            return cos_sys::error::ErrorKind::NotSupported as u64;
+        };
+        let info = ModeInfo {
+            width: framebuffer.width,
+            height: framebuffer.height,
+        };
+        if info_slice.write_struct(&info).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn map_buffer(addr_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+        if !multitask::process::has_capability(&process, Capabilities::GRAPHICS) {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+
+        let Ok(addr_slice) = UserSlice::writable_of::<u64>(&process, addr_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let Some(framebuffer) = framebuffer::info() else {
+            return cos_sys::error::ErrorKind::NotSupported as u64;
+        };
+        let info = ModeInfo {
+            width: framebuffer.width,
+            height: framebuffer.height,
+        };
+        let size = info.pages() as usize * 0x1000;
+        if !multitask::process::page_quota_available(&process, size) {
+            return cos_sys::error::ErrorKind::QuotaExceeded as u64;
+        }
+        let Some(addr) = multitask::process::create_process_page(&process, size, ProcessPageType::Data) else {
+            return cos_sys::error::ErrorKind::OutOfMemory as u64;
+        };
+
+        if addr_slice.write_struct(&addr).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn present(buffer_ptr: u64, buffer_len: u64, rect_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+        if !multitask::process::has_capability(&process, Capabilities::GRAPHICS) {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+
+        let Ok(rect_slice) = UserSlice::readable_of::<Rect>(&process, rect_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        // Safety: Rect的任意取值均合法
+        let Ok(rect) = (unsafe { rect_slice.read_struct::<Rect>() }) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let Some(framebuffer) = framebuffer::info() else {
+            return cos_sys::error::ErrorKind::NotSupported as u64;
+        };
+        let width = framebuffer.width as usize;
+        if buffer_len != (width * framebuffer.height as usize) as u64 {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        }
+        // 先检查整个缓冲区的范围，之后计算各行地址时不会溢出
+        if let Err(error) = UserRange::array::<u32>(buffer_ptr, buffer_len) {
+            return error.error_kind() as u64;
+        }
+        let rect = rect.clip(framebuffer.width, framebuffer.height);
+
+        // 逐行分块读取后台缓冲区，避免一次复制整个屏幕占用过多内核内存
+        let mut bytes = [0u8; CHUNK_PIXELS * 4];
+        let mut pixels = [0u32; CHUNK_PIXELS];
+        for y in rect.y..rect.y + rect.height {
+            let mut x = rect.x;
+            while x < rect.x + rect.width {
+                let count = CHUNK_PIXELS.min((rect.x + rect.width - x) as usize);
+                let addr = buffer_ptr + ((y as usize * width + x as usize) * 4) as u64;
+                let chunk = match UserSlice::readable(&process, addr, count * 4) {
+                    Ok(chunk) => chunk,
+                    Err(error) => return error.error_kind() as u64,
+                };
+                if chunk.read(&mut bytes[..count * 4]).is_err() {
+                    return cos_sys::error::ErrorKind::BadPointer as u64;
+                }
+                for (pixel, bytes) in pixels.iter_mut().zip(bytes[..count * 4].chunks_exact(4)) {
+                    *pixel = u32::from_ne_bytes(bytes.try_into().unwrap());
+                }
+                if !framebuffer::write_row(x, y, &pixels[..count]) {
+                    return cos_sys::error::ErrorKind::OutOfMemory as u64;
+                }
+                x += count as u32;
+            }
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
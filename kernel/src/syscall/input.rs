@@ -0,0 +1,73 @@
+use core::slice;
+
+use cos_sys::input::InputEvent;
+
+use crate::{
+    io,
+    multitask::{self, capability::Capabilities},
+    syscall::SYSCALL_SUCCESS,
+    syscall_handler,
+    user::{range::UserRange, slice::UserSlice},
+};
+
+syscall_handler! {
+    fn poll(events_ptr: u64, capacity: u64, count_ptr: u64) -> u64 {
+        take(events_ptr, capacity, count_ptr, false)
+    }
+}
+
+syscall_handler! {
+    fn wait(events_ptr: u64, capacity: u64, count_ptr: u64) -> u64 {
+        take(events_ptr, capacity, count_ptr, true)
+    }
+}
+
+fn take(events_ptr: u64, capacity: u64, count_ptr: u64, wait: bool) -> u64 {
+    let process = multitask::process::current_process().unwrap();
+    if !multitask::process::has_capability(&process, Capabilities::RAW_CONSOLE) {
+        return cos_sys::error::ErrorKind::PermissionDenied as u64;
+    }
+
+    let Ok(count_slice) = UserSlice::writable_of::<u64>(&process, count_ptr) else {
+        return cos_sys::error::ErrorKind::BadPointer as u64;
+    };
+    if capacity == 0 {
+        return cos_sys::error::ErrorKind::BadArgument as u64;
+    }
+    let events_slice = match UserRange::array::<InputEvent>(events_ptr, capacity)
+        .and_then(|range| UserSlice::from_range(&process, range, true))
+    {
+        Ok(events_slice) => events_slice,
+        Err(error) => return error.error_kind() as u64,
+    };
+
+    // 控制台存在前台进程组时，只有前台进程组中的进程可以读取输入事件
+    let console = multitask::process::process_console(&process);
+    let (process_group, _) = multitask::process::process_group(&process);
+    let foreground = io::console::foreground_group(console);
+    if foreground != 0 && foreground != process_group {
+        return cos_sys::error::ErrorKind::PermissionDenied as u64;
+    }
+    let events = multitask::async_rt::block_on(async move {
+        io::input::take(console, process_group, capacity as usize, wait).await
+    });
+    let events = match events {
+        Ok(events) => events,
+        Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+    };
+    if events.is_empty() {
+        return cos_sys::error::ErrorKind::WouldBlock as u64;
+    }
+
+    // Safety: InputEvent为repr(C)且仅包含4字节的字段，不存在填充字节
+    let bytes =
+        unsafe { slice::from_raw_parts(events.as_ptr() as *const u8, size_of_val(&*events)) };
+    if events_slice.write(bytes).is_err() {
+        return cos_sys::error::ErrorKind::BadPointer as u64;
+    }
+    if count_slice.write_struct(&(events.len() as u64)).is_err() {
+        return cos_sys::error::ErrorKind::BadPointer as u64;
+    }
+
+    SYSCALL_SUCCESS
+}
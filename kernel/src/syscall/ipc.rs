@@ -0,0 +1,134 @@
+use core::{
+    pin::Pin,
+    slice,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use alloc::{boxed::Box, vec::Vec};
+use cos_sys::ipc::{POLL_INVALID, PollFd};
+
+use crate::{
+    multitask::{self, async_task::Sleep},
+    syscall::SYSCALL_SUCCESS,
+    syscall_handler,
+    user::{range::UserRange, slice::UserSlice},
+};
+
+/// 表示无限等待的超时时间，与[`cos_sys::ipc::poll`]保持一致
+const TIMEOUT_INFINITE: u64 = u64::MAX;
+
+type ReadyFuture = Pin<Box<dyn Future<Output = u64>>>;
+
+syscall_handler! {
+    fn poll(fds_ptr: u64, fds_len: u64, timeout: u64, ready_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(ready_slice) = UserSlice::writable_of::<u64>(&process, ready_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let fds_range = match UserRange::array::<PollFd>(fds_ptr, fds_len) {
+            Ok(fds_range) => fds_range,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let fds_slice = match UserSlice::from_range(&process, fds_range, true) {
+            Ok(fds_slice) => fds_slice,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let fds_bytes = match fds_slice.read_to_vec() {
+            Ok(fds_bytes) => fds_bytes,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let mut fds: Vec<PollFd> = fds_bytes
+            .chunks_exact(size_of::<PollFd>())
+            // Safety: PollFd的字段均为u64，任意字节均为合法值
+            .map(|bytes| unsafe { (bytes.as_ptr() as *const PollFd).read_unaligned() })
+            .collect();
+        drop(fds_bytes);
+
+        let mut waits = Vec::new();
+        for (index, fd) in fds.iter_mut().enumerate() {
+            fd.revents = 0;
+            match multitask::process::get_process_handle(&process, fd.handle) {
+                Ok(handle) => {
+                    let events = fd.events;
+                    let wait = Box::pin(async move { handle.wait_ready(events).await });
+                    waits.push((index, wait as ReadyFuture));
+                }
+                Err(_) => fd.revents = POLL_INVALID,
+            }
+        }
+
+        // 已经存在无效句柄时，只检查当前状态，不再等待
+        let nonblocking = timeout == 0 || fds.iter().any(|fd| fd.revents != 0);
+        let timeout = if nonblocking || timeout == TIMEOUT_INFINITE {
+            None
+        } else {
+            Some(multitask::async_task::sleep(Duration::from_nanos(timeout)))
+        };
+
+        let ready = WaitAny {
+            waits,
+            timeout,
+            nonblocking,
+        };
+        let ready = match multitask::async_rt::block_on(ready) {
+            Ok(ready) => ready,
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        for (index, revents) in ready {
+            fds[index].revents = revents;
+        }
+
+        // Safety: PollFd为repr(C)且仅包含u64字段，不存在填充字节
+        let bytes = unsafe { slice::from_raw_parts(fds.as_ptr() as *const u8, size_of_val(&*fds)) };
+        if fds_slice.write(bytes).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+        let ready_count = fds.iter().filter(|fd| fd.revents != 0).count() as u64;
+        if ready_slice.write_struct(&ready_count).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+/// 等待任意一个句柄就绪或超时
+///
+/// 就绪时返回本次检查中全部已就绪的句柄序号及事件，超时返回空列表
+struct WaitAny {
+    waits: Vec<(usize, ReadyFuture)>,
+    timeout: Option<Sleep>,
+    nonblocking: bool,
+}
+
+impl Future for WaitAny {
+    type Output = Vec<(usize, u64)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // 非阻塞模式下不需要被唤醒
+        let mut noop = Context::from_waker(Waker::noop());
+        let cx = if self.nonblocking { &mut noop } else { cx };
+
+        let ready: Vec<(usize, u64)> = self
+            .waits
+            .iter_mut()
+            .filter_map(|(index, wait)| match wait.as_mut().poll(cx) {
+                Poll::Ready(revents) => Some((*index, revents)),
+                Poll::Pending => None,
+            })
+            .collect();
+        if !ready.is_empty() || self.nonblocking {
+            return Poll::Ready(ready);
+        }
+
+        if let Some(timeout) = &mut self.timeout
+            && Pin::new(timeout).poll(cx).is_ready()
+        {
+            return Poll::Ready(Vec::new());
+        }
+
+        Poll::Pending
+    }
+}
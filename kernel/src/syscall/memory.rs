@@ -1,30 +1,39 @@
+use core::num::NonZeroU64;
+
+use cos_sys::memory::{HeapClassStats, MemoryStats};
+
 use crate::{
     memory,
     multitask::{self, process::ProcessPageType},
     syscall_handler,
     syscall::SYSCALL_SUCCESS,
+    user::{range::UserRange, slice::UserSlice},
 };
 
 syscall_handler! {
     fn alloc_page(count: u64, addr_ptr: u64) -> u64 {
-        if !memory::page::is_user_space_virtual_memory(addr_ptr as usize) {
-            return cos_sys::error::ErrorKind::BadPointer as u64;
-        }
-
         if count == 0 {
             return SYSCALL_SUCCESS;
         }
 
         let process = multitask::process::current_process().unwrap();
 
-        let Some(addr) = multitask::process::create_process_page(&process, (count * 0x1000) as usize, ProcessPageType::Data) else {
+        let Ok(addr_slice) = UserSlice::writable_of::<u64>(&process, addr_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let Some(size) = count.checked_mul(0x1000) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+        if !multitask::process::page_quota_available(&process, size as usize) {
+            return cos_sys::error::ErrorKind::QuotaExceeded as u64;
+        }
+        let Some(addr) = multitask::process::create_process_page(&process, size as usize, ProcessPageType::Data) else {
             return cos_sys::error::ErrorKind::OutOfMemory as u64;
         };
 
-        unsafe {
-            if multitask::process::write_user_process_memory_struct(&process, addr_ptr, &addr).is_err() {
-                return cos_sys::error::ErrorKind::BadPointer as u64;
-            }
+        if addr_slice.write_struct(&addr).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
         }
 
         SYSCALL_SUCCESS
@@ -33,8 +42,7 @@ syscall_handler! {
 
 syscall_handler! {
     fn free_page(addr: u64, count: u64) -> u64 {
-        if !memory::page::is_user_space_virtual_memory(addr as usize)
-            || !memory::page::is_user_space_virtual_memory((addr + count * 0x1000) as usize) {
+        if (addr & 0xfff) != 0 {
             return cos_sys::error::ErrorKind::BadPointer as u64;
         }
 
@@ -42,9 +50,125 @@ syscall_handler! {
             return SYSCALL_SUCCESS;
         }
 
+        let Some(size) = count.checked_mul(0x1000) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+        let range = match UserRange::new(addr, size) {
+            Ok(range) => range,
+            Err(error) => return error.error_kind() as u64,
+        };
+
         let process = multitask::process::current_process().unwrap();
-        unsafe {
-            multitask::process::free_process_page(&process, addr as usize, (count * 0x1000) as usize);
+        let result = unsafe {
+            multitask::process::free_process_page(&process, range.start() as usize, range.len())
+        };
+
+        match result {
+            Ok(()) => SYSCALL_SUCCESS,
+            Err(error) => error.error_kind() as u64,
+        }
+    }
+}
+
+syscall_handler! {
+    fn alloc_page_at(count: u64, hint: u64, addr_ptr: u64) -> u64 {
+        if count == 0 {
+            return SYSCALL_SUCCESS;
+        }
+
+        let Some(size) = count.checked_mul(0x1000) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(addr_slice) = UserSlice::writable_of::<u64>(&process, addr_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        if !multitask::process::page_quota_available(&process, size as usize) {
+            return cos_sys::error::ErrorKind::QuotaExceeded as u64;
+        }
+
+        // 优先使用建议的地址，地址不可用时与alloc_page相同，由内核选址
+        let hint = hint & !0xfff;
+        let page_type = match NonZeroU64::new(hint) {
+            Some(hint) if memory::page::is_user_space_range(hint.get(), size as usize) => ProcessPageType::DataAt(hint),
+            _ => ProcessPageType::Data,
+        };
+        let addr = multitask::process::create_process_page(&process, size as usize, page_type);
+        let Some(addr) = addr else {
+            return cos_sys::error::ErrorKind::OutOfMemory as u64;
+        };
+
+        if addr_slice.write_struct(&addr).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn remap_page(addr: u64, old_count: u64, new_count: u64) -> u64 {
+        if (addr & 0xfff) != 0 {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        let (Some(old_size), Some(new_size)) = (old_count.checked_mul(0x1000), new_count.checked_mul(0x1000)) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+        // 扩展后的范围由grow_process_page检查，缩小时只需检查原范围
+        let range = match UserRange::new(addr, old_size) {
+            Ok(range) => range,
+            Err(error) => return error.error_kind() as u64,
+        };
+
+        let process = multitask::process::current_process().unwrap();
+        let result = if new_size > old_size {
+            multitask::process::grow_process_page(&process, addr, old_size as usize, new_size as usize)
+        } else if new_size < old_size {
+            // 缩小即释放末尾的内存页
+            unsafe {
+                multitask::process::free_process_page(&process, (range.start() + new_size) as usize, (old_size - new_size) as usize)
+            }
+        } else {
+            Ok(())
+        };
+
+        match result {
+            Ok(()) => SYSCALL_SUCCESS,
+            Err(error) => error.error_kind() as u64,
+        }
+    }
+}
+
+syscall_handler! {
+    fn memory_stats(stats_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(stats_slice) = UserSlice::writable_of::<MemoryStats>(&process, stats_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let frame_stats = memory::frame_stats();
+        let heap_stats = memory::heap_stats();
+        let stats = MemoryStats {
+            total_memory: frame_stats.total_memory,
+            reserved_memory: frame_stats.reserved_memory,
+            allocated_frames: frame_stats.allocated_frames,
+            free_list_frames: frame_stats.free_list_frames,
+            heap_classes: heap_stats.classes.map(|class| HeapClassStats {
+                size: class.size as u64,
+                allocated: class.allocated,
+                free: class.free,
+                pages: class.pages,
+            }),
+            heap_large_allocated: heap_stats.large_allocated,
+            heap_large_pages: heap_stats.large_pages,
+        };
+        if stats_slice.write_struct(&stats).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
         }
 
         SYSCALL_SUCCESS
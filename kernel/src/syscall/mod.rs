@@ -1,14 +1,213 @@
 use core::cmp::Ordering;
 
+use filesystem::{
+    device::{BlockDeviceError, crypt::CryptError},
+    fs::FileSystemError,
+};
+use netstack::stack::NetError;
+
+use crate::{
+    boot_slot::UpdateError,
+    io::{
+        console::ForegroundError,
+        permission::PermissionError,
+        vfs::{MountError, UnmountError},
+    },
+    module::ModuleError,
+    multitask::process::{CreateProcessError, ProcessGroupError},
+    user::handle::HandleError,
+};
+
+mod completion;
 mod debug;
 mod file;
+mod gfx;
+mod input;
+mod ipc;
 mod memory;
+mod module;
 mod multitask;
+mod net;
+mod random;
+mod sound;
+mod system;
+mod time;
+pub mod trace;
 
 pub type SyscallEntry = (u64, extern "C" fn(u64, u64, u64, u64, u64, u64) -> u64);
 
 const SYSCALL_SUCCESS: u64 = cos_sys::error::ErrorKind::Success as u64;
 
+/// 将文件系统错误转换为系统调用错误码
+fn filesystem_error(error: &FileSystemError) -> u64 {
+    use cos_sys::error::ErrorKind;
+
+    let kind = match error {
+        FileSystemError::IoError(error) => return block_device_error(error),
+        FileSystemError::FileNotFound => ErrorKind::FileNotFound,
+        FileSystemError::FileExists => ErrorKind::FileExists,
+        FileSystemError::DiskFull => ErrorKind::DiskFull,
+        FileSystemError::FileTypeMismatch => ErrorKind::FileTypeMismatch,
+        FileSystemError::NameTooLang => ErrorKind::NameTooLong,
+        FileSystemError::FileOccupied => ErrorKind::Occupied,
+        FileSystemError::OperationNotSupport => ErrorKind::NotSupported,
+        FileSystemError::Unmounted => ErrorKind::IoError,
+        FileSystemError::FileClosed => ErrorKind::BadArgument,
+        FileSystemError::FileTooLarge => ErrorKind::FileTooLarge,
+    };
+    kind as u64
+}
+
+/// 将创建进程错误转换为系统调用错误码
+fn create_process_error(error: &CreateProcessError) -> u64 {
+    use cos_sys::error::ErrorKind;
+
+    let kind = match error {
+        CreateProcessError::InvalidPath => ErrorKind::BadArgument,
+        CreateProcessError::FileSystemUnavailable => ErrorKind::IoError,
+        CreateProcessError::FileSystem(error) => return filesystem_error(error),
+        CreateProcessError::InvalidExecutable => ErrorKind::BadArgument,
+        CreateProcessError::VerificationFailed => ErrorKind::PermissionDenied,
+        CreateProcessError::PermissionDenied => ErrorKind::PermissionDenied,
+        CreateProcessError::OutOfMemory => ErrorKind::OutOfMemory,
+        CreateProcessError::QuotaExceeded => ErrorKind::QuotaExceeded,
+    };
+    kind as u64
+}
+
+/// 将句柄错误转换为系统调用错误码
+fn handle_error(error: &HandleError) -> u64 {
+    use cos_sys::error::ErrorKind;
+
+    let kind = match error {
+        HandleError::BadHandle => ErrorKind::BadHandle,
+        HandleError::TableFull => ErrorKind::QuotaExceeded,
+    };
+    kind as u64
+}
+
+/// 将块设备错误转换为系统调用错误码
+fn block_device_error(error: &BlockDeviceError) -> u64 {
+    use cos_sys::error::ErrorKind;
+
+    let kind = match error {
+        BlockDeviceError::OutOfMemory => ErrorKind::OutOfMemory,
+        BlockDeviceError::Corrupted => ErrorKind::Corrupted,
+        // 越界访问与底层错误对用户而言均为设备错误
+        _ => ErrorKind::IoError,
+    };
+    kind as u64
+}
+
+/// 将挂载错误转换为系统调用错误码
+fn mount_error(error: &MountError) -> u64 {
+    use cos_sys::error::ErrorKind;
+
+    let kind = match error {
+        MountError::NotFound => ErrorKind::FileNotFound,
+        MountError::AlreadyMounted => ErrorKind::FileExists,
+        MountError::FileSystem(error) => return filesystem_error(error),
+        MountError::InvalidFormat(_) => ErrorKind::NotSupported,
+        MountError::Crypt(CryptError::WrongPassphrase) => ErrorKind::PermissionDenied,
+        MountError::Crypt(CryptError::IoError(error)) => return block_device_error(error),
+        MountError::Crypt(_) => ErrorKind::NotSupported,
+    };
+    kind as u64
+}
+
+/// 将卸载错误转换为系统调用错误码
+fn unmount_error(error: &UnmountError) -> u64 {
+    use cos_sys::error::ErrorKind;
+
+    let kind = match error {
+        UnmountError::NotMounted => ErrorKind::FileNotFound,
+        UnmountError::Busy => ErrorKind::Occupied,
+        UnmountError::FileSystem(error) => return filesystem_error(error),
+    };
+    kind as u64
+}
+
+/// 将修改进程组错误转换为系统调用错误码
+fn process_group_error(error: &ProcessGroupError) -> u64 {
+    use cos_sys::error::ErrorKind;
+
+    let kind = match error {
+        ProcessGroupError::PermissionDenied => ErrorKind::PermissionDenied,
+        ProcessGroupError::GroupNotFound => ErrorKind::BadArgument,
+    };
+    kind as u64
+}
+
+/// 将设置前台进程组错误转换为系统调用错误码
+fn foreground_error(error: &ForegroundError) -> u64 {
+    use cos_sys::error::ErrorKind;
+
+    let kind = match error {
+        ForegroundError::NotControllingSession | ForegroundError::NotSessionLeader => {
+            ErrorKind::PermissionDenied
+        }
+        ForegroundError::GroupNotFound => ErrorKind::BadArgument,
+    };
+    kind as u64
+}
+
+/// 将修改权限错误转换为系统调用错误码
+fn permission_error(error: &PermissionError) -> u64 {
+    match error {
+        PermissionError::PermissionDenied => cos_sys::error::ErrorKind::PermissionDenied as u64,
+        PermissionError::FileSystem(error) => filesystem_error(error),
+    }
+}
+
+/// 将网络错误转换为系统调用错误码
+fn net_error(error: &NetError) -> u64 {
+    use cos_sys::error::ErrorKind;
+
+    let kind = match error {
+        NetError::AddressInUse => ErrorKind::Occupied,
+        NetError::NoFreePort => ErrorKind::QuotaExceeded,
+        NetError::Unreachable => ErrorKind::Unreachable,
+        NetError::MessageTooLarge => ErrorKind::BadArgument,
+        NetError::ConnectionRefused => ErrorKind::ConnectionRefused,
+        NetError::ConnectionReset => ErrorKind::ConnectionReset,
+        NetError::TimedOut => ErrorKind::TimedOut,
+        NetError::NotConnected => ErrorKind::NotConnected,
+        NetError::Device(_) => ErrorKind::IoError,
+    };
+    kind as u64
+}
+
+/// 将内核模块错误转换为系统调用错误码
+fn module_error(error: &ModuleError) -> u64 {
+    use cos_sys::error::ErrorKind;
+
+    let kind = match error {
+        ModuleError::NotSupported => ErrorKind::NotSupported,
+        ModuleError::BadName => ErrorKind::BadArgument,
+        ModuleError::AlreadyLoaded => ErrorKind::FileExists,
+        ModuleError::NotLoaded => ErrorKind::FileNotFound,
+        ModuleError::FileSystem(error) => return filesystem_error(error),
+        ModuleError::BadSignature => ErrorKind::PermissionDenied,
+        ModuleError::Format(_) => ErrorKind::Corrupted,
+        ModuleError::OutOfMemory => ErrorKind::OutOfMemory,
+        ModuleError::InitFailed(_) => ErrorKind::IoError,
+    };
+    kind as u64
+}
+
+/// 将内核更新错误转换为系统调用错误码
+fn update_error(error: &UpdateError) -> u64 {
+    use cos_sys::error::ErrorKind;
+
+    let kind = match error {
+        UpdateError::NotSupported => ErrorKind::NotSupported,
+        UpdateError::BadPath => ErrorKind::BadArgument,
+        UpdateError::BadImage => ErrorKind::Corrupted,
+        UpdateError::FileSystem(error) => return filesystem_error(error),
+    };
+    kind as u64
+}
+
 pub const SYSCALL_HANDLER: &[SyscallEntry] = &[
     (cos_sys::idx::IDX_EXIT_PROCESS, multitask::exit_process),
     (cos_sys::idx::IDX_EXIT_THREAD, multitask::exit_thread),
@@ -19,12 +218,59 @@ pub const SYSCALL_HANDLER: &[SyscallEntry] = &[
     (cos_sys::idx::IDX_THREAD_CREATE, multitask::create_thread),
     (cos_sys::idx::IDX_THREAD_JOIN, multitask::join_thread),
     (cos_sys::idx::IDX_THREAD_SLEEP, multitask::sleep_thread),
+    (
+        cos_sys::idx::IDX_THREAD_SET_TLS_BASE,
+        multitask::set_tls_base,
+    ),
     (cos_sys::idx::IDX_MEMORY_ALLOC, memory::alloc_page),
     (cos_sys::idx::IDX_MEMORY_FREE, memory::free_page),
+    (cos_sys::idx::IDX_MEMORY_STATS, memory::memory_stats),
+    (cos_sys::idx::IDX_MEMORY_ALLOC_AT, memory::alloc_page_at),
+    (cos_sys::idx::IDX_MEMORY_REMAP, memory::remap_page),
     (cos_sys::idx::IDX_PROCESS_CURRENT, multitask::current_process),
     (cos_sys::idx::IDX_PROCESS_CREATE, multitask::create_process),
     (cos_sys::idx::IDX_PROCESS_KILL, multitask::kill_process),
     (cos_sys::idx::IDX_PROCESS_WAIT, multitask::wait_process),
+    (
+        cos_sys::idx::IDX_PROCESS_SET_LIMIT,
+        multitask::set_process_limit,
+    ),
+    (cos_sys::idx::IDX_PROCESS_LIST, multitask::list_processes),
+    (cos_sys::idx::IDX_PROCESS_INFO, multitask::process_info),
+    (cos_sys::idx::IDX_PROCESS_CPU_TIMES, multitask::cpu_times),
+    (
+        cos_sys::idx::IDX_PROCESS_CREATE_WITH_ARGS,
+        multitask::create_process_with_args,
+    ),
+    (cos_sys::idx::IDX_PROCESS_ARGS, multitask::process_args),
+    (
+        cos_sys::idx::IDX_PROCESS_CREDENTIALS,
+        multitask::credentials,
+    ),
+    (
+        cos_sys::idx::IDX_PROCESS_SET_CREDENTIALS,
+        multitask::set_credentials,
+    ),
+    (
+        cos_sys::idx::IDX_PROCESS_GET_LIMIT,
+        multitask::get_process_limit,
+    ),
+    (
+        cos_sys::idx::IDX_PROCESS_SET_GROUP,
+        multitask::set_process_group,
+    ),
+    (
+        cos_sys::idx::IDX_PROCESS_CREATE_SESSION,
+        multitask::create_session,
+    ),
+    (
+        cos_sys::idx::IDX_PROCESS_SET_FOREGROUND,
+        multitask::set_foreground_group,
+    ),
+    (
+        cos_sys::idx::IDX_PROCESS_FOREGROUND,
+        multitask::foreground_group,
+    ),
     (cos_sys::idx::IDX_FILE_CREATE, file::create),
     (cos_sys::idx::IDX_FILE_OPEN, file::open),
     (cos_sys::idx::IDX_FILE_READ, file::read),
@@ -32,11 +278,82 @@ pub const SYSCALL_HANDLER: &[SyscallEntry] = &[
     (cos_sys::idx::IDX_FILE_GET_POS, file::get_pos),
     (cos_sys::idx::IDX_FILE_SET_POS, file::set_pos),
     (cos_sys::idx::IDX_FILE_CLOSE, file::close),
+    (
+        cos_sys::idx::IDX_FILE_LIST_BLOCK_DEVICES,
+        file::list_block_devices,
+    ),
+    (cos_sys::idx::IDX_FILE_MOUNT, file::mount),
+    (cos_sys::idx::IDX_FILE_UNMOUNT, file::unmount),
+    (cos_sys::idx::IDX_FILE_LIST_DIRECTORY, file::list_directory),
+    (cos_sys::idx::IDX_FILE_TRUNCATE, file::truncate),
+    (cos_sys::idx::IDX_FILE_DELETE, file::delete),
+    (cos_sys::idx::IDX_FILE_RENAME, file::rename),
+    (cos_sys::idx::IDX_FILE_METADATA, file::metadata),
+    (cos_sys::idx::IDX_FILE_SCRUB, file::scrub),
+    (cos_sys::idx::IDX_FILE_CHMOD, file::chmod),
+    (cos_sys::idx::IDX_FILE_CHOWN, file::chown),
+    (cos_sys::idx::IDX_FILE_CONTROL, file::control),
+    (cos_sys::idx::IDX_FILE_MOUNT_ENCRYPTED, file::mount_encrypted),
+    (cos_sys::idx::IDX_COMPLETION_SUBMIT, completion::submit),
+    (cos_sys::idx::IDX_COMPLETION_POLL, completion::poll),
+    (cos_sys::idx::IDX_COMPLETION_WAIT, completion::wait),
+    (cos_sys::idx::IDX_IPC_POLL, ipc::poll),
+    (cos_sys::idx::IDX_SYSTEM_CMDLINE, system::cmdline),
+    (cos_sys::idx::IDX_SYSTEM_SHUTDOWN, system::shutdown),
+    (cos_sys::idx::IDX_SYSTEM_REBOOT, system::reboot),
+    (cos_sys::idx::IDX_SYSTEM_ABI_INFO, system::abi_info),
+    (
+        cos_sys::idx::IDX_SYSTEM_UPDATE_KERNEL,
+        system::update_kernel,
+    ),
+    (cos_sys::idx::IDX_NET_UDP_BIND, net::udp_bind),
+    (cos_sys::idx::IDX_NET_UDP_SEND_TO, net::udp_send_to),
+    (cos_sys::idx::IDX_NET_UDP_RECV_FROM, net::udp_recv_from),
+    (cos_sys::idx::IDX_NET_TCP_CONNECT, net::tcp_connect),
+    (cos_sys::idx::IDX_NET_TCP_LISTEN, net::tcp_listen),
+    (cos_sys::idx::IDX_NET_TCP_ACCEPT, net::tcp_accept),
+    (cos_sys::idx::IDX_NET_TCP_READ, net::tcp_read),
+    (cos_sys::idx::IDX_NET_TCP_WRITE, net::tcp_write),
+    (cos_sys::idx::IDX_NET_TCP_SHUTDOWN, net::tcp_shutdown),
+    (cos_sys::idx::IDX_RANDOM_GET, random::get_random),
+    (cos_sys::idx::IDX_TIME_GET, time::get_time),
+    (cos_sys::idx::IDX_TIME_MONOTONIC, time::monotonic),
+    (cos_sys::idx::IDX_TIME_UTC_OFFSET, time::utc_offset),
+    (cos_sys::idx::IDX_GFX_MODE_INFO, gfx::mode_info),
+    (cos_sys::idx::IDX_GFX_MAP_BUFFER, gfx::map_buffer),
+    (cos_sys::idx::IDX_GFX_PRESENT, gfx::present),
+    (cos_sys::idx::IDX_INPUT_POLL, input::poll),
+    (cos_sys::idx::IDX_INPUT_WAIT, input::wait),
+    (cos_sys::idx::IDX_SOUND_BEEP, sound::beep),
+    (cos_sys::idx::IDX_MODULE_LOAD, module::load),
+    (cos_sys::idx::IDX_MODULE_UNLOAD, module::unload),
+    (cos_sys::idx::IDX_MODULE_LIST, module::list),
     (cos_sys::idx::IDX_DEBUG_INFO, debug::syscall_test),
     (cos_sys::idx::IDX_DEBUG_GET_CHAR, debug::get_char),
     (cos_sys::idx::IDX_DEBUG_PUT_CHAR, debug::put_char),
+    (cos_sys::idx::IDX_DEBUG_OPEN_KEYBOARD, debug::open_keyboard),
+    (cos_sys::idx::IDX_DEBUG_LIST_HANDLES, debug::list_handles),
+    (cos_sys::idx::IDX_DEBUG_SERIAL_WRITE, debug::serial_write),
+    (cos_sys::idx::IDX_DEBUG_EXIT_EMULATOR, debug::exit_emulator),
+    (cos_sys::idx::IDX_DEBUG_MEMORY_MAPS, debug::memory_maps),
+    (cos_sys::idx::IDX_DEBUG_TRACE_PROCESS, debug::trace_process),
+    (cos_sys::idx::IDX_DEBUG_READ_TRACE, debug::read_trace),
+    (
+        cos_sys::idx::IDX_DEBUG_SET_LOG_FILTER,
+        debug::set_log_filter,
+    ),
+    (cos_sys::idx::IDX_DEBUG_PUT_STR, debug::put_str),
+    (cos_sys::idx::IDX_DEBUG_LOCK_STATS, debug::lock_stats),
 ];
 
+/// 查找系统调用编号对应的处理函数
+pub fn find_handler(id: u64) -> Option<extern "C" fn(u64, u64, u64, u64, u64, u64) -> u64> {
+    SYSCALL_HANDLER
+        .binary_search_by(|&(entry_id, _)| entry_id.cmp(&id))
+        .ok()
+        .map(|index| SYSCALL_HANDLER[index].1)
+}
+
 // assert
 const _: () = {
     let len = SYSCALL_HANDLER.len();
@@ -0,0 +1,136 @@
+use cos_sys::module::ModuleInfo;
+
+use crate::{
+    module, multitask,
+    syscall::{SYSCALL_SUCCESS, module_error},
+    syscall_handler,
+    user::{range::UserRange, slice::UserSlice},
+};
+
+syscall_handler! {
+    fn load(name_ptr: u64, name_len: u64) -> u64 {
+        // 模块代码运行在内核空间，只有超级用户可以加载或卸载
+        let process = multitask::process::current_process().unwrap();
+        if !multitask::process::credentials(&process).is_root() {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+        let name = match UserSlice::readable(&process, name_ptr, name_len as usize) {
+            Ok(name) => name,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let name = match name.read_to_vec() {
+            Ok(name) => name,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let Ok(name) = alloc::string::String::from_utf8(name) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+
+        let (sender, receiver) = async_locks::channel::oneshot::channel();
+        multitask::async_rt::spawn(async move {
+            let result = module::load(&name).await;
+            sender.send(result.map_err(|error| module_error(&error))).await;
+        });
+        let result = match multitask::async_rt::block_on(receiver.recv()) {
+            Ok(res) => res,
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        match result.unwrap() {
+            Ok(()) => SYSCALL_SUCCESS,
+            Err(error) => error,
+        }
+    }
+}
+
+syscall_handler! {
+    fn unload(name_ptr: u64, name_len: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+        if !multitask::process::credentials(&process).is_root() {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+        let name = match UserSlice::readable(&process, name_ptr, name_len as usize) {
+            Ok(name) => name,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let name = match name.read_to_vec() {
+            Ok(name) => name,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let Ok(name) = alloc::string::String::from_utf8(name) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+
+        let (sender, receiver) = async_locks::channel::oneshot::channel();
+        multitask::async_rt::spawn(async move {
+            let result = module::unload(&name).await;
+            sender.send(result.map_err(|error| module_error(&error))).await;
+        });
+        let result = match multitask::async_rt::block_on(receiver.recv()) {
+            Ok(res) => res,
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        match result.unwrap() {
+            Ok(()) => SYSCALL_SUCCESS,
+            Err(error) => error,
+        }
+    }
+}
+
+syscall_handler! {
+    fn list(modules_ptr: u64, modules_len: u64, count_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+        let Ok(count_slice) = UserSlice::writable_of::<u64>(&process, count_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let modules_range = match UserRange::array::<ModuleInfo>(modules_ptr, modules_len) {
+            Ok(modules_range) => modules_range,
+            Err(error) => return error.error_kind() as u64,
+        };
+        if let Err(error) = UserSlice::from_range(&process, modules_range, true) {
+            return error.error_kind() as u64;
+        }
+
+        let (sender, receiver) = async_locks::channel::oneshot::channel();
+        multitask::async_rt::spawn(async move {
+            sender.send(module::list().await).await;
+        });
+        let modules = match multitask::async_rt::block_on(receiver.recv()) {
+            Ok(modules) => modules.unwrap(),
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+
+        // 模块地址会泄露内核布局，只对超级用户与特权进程可见
+        let show_address = multitask::process::credentials(&process).is_root()
+            || multitask::process::is_privileged(&process);
+        for (index, module) in modules.iter().take(modules_len as usize).enumerate() {
+            let mut info = ModuleInfo {
+                address: if show_address {
+                    module.address as u64
+                } else {
+                    0
+                },
+                size: module.size as u64,
+                ..Default::default()
+            };
+            let name = module.name.as_bytes();
+            info.name[..name.len()].copy_from_slice(name);
+            info.name_len = name.len() as u64;
+
+            let info_slice = match modules_range
+                .element::<ModuleInfo>(index)
+                .and_then(|range| UserSlice::from_range(&process, range, true))
+            {
+                Ok(info_slice) => info_slice,
+                Err(error) => return error.error_kind() as u64,
+            };
+            if info_slice.write_struct(&info).is_err() {
+                return cos_sys::error::ErrorKind::BadPointer as u64;
+            }
+        }
+        if count_slice.write_struct(&(modules.len() as u64)).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
@@ -1,10 +1,22 @@
 use core::time::Duration;
 
-use alloc::sync::Arc;
+use alloc::{sync::Arc, vec::Vec};
 use async_locks::channel::oneshot;
+use cos_sys::multitask::{CpuTimes, ProcessInfo};
 
 use crate::{
-    memory, multitask, syscall::SYSCALL_SUCCESS, syscall_handler, user::handle::HandleObject,
+    io, memory,
+    multitask::{
+        self,
+        capability::Capabilities,
+        process::{Credentials, Process, ProcessLimit},
+    },
+    sync::spin::SpinLock,
+    syscall::{
+        SYSCALL_SUCCESS, create_process_error, foreground_error, handle_error, process_group_error,
+    },
+    syscall_handler,
+    user::{handle::HandleObject, range::UserRange, slice::UserSlice},
 };
 
 syscall_handler! {
@@ -38,22 +50,23 @@ syscall_handler! {
 
 syscall_handler! {
     fn current_thread(thread_handle_ptr: u64) -> u64 {
-        if !memory::page::is_user_space_virtual_memory(thread_handle_ptr as usize) {
-            return  cos_sys::error::ErrorKind::BadPointer as u64;
-        }
-
         let process = multitask::process::current_process().unwrap();
+
+        let Ok(thread_handle_slice) = UserSlice::writable_of::<u64>(&process, thread_handle_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
         let thread = multitask::thread::current_thread().unwrap();
         let thread_handle = HandleObject::Thread {
             thread: Arc::downgrade(&thread),
             exit: multitask::thread::get_exit_code_subscriber(&thread),
         };
-        let handle = multitask::process::insert_process_handle(&process, thread_handle);
+        let handle = match multitask::process::insert_process_handle(&process, thread_handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
 
-        unsafe {
-            if multitask::process::write_user_process_memory_struct(&process, thread_handle_ptr, &handle).is_err() {
-                return cos_sys::error::ErrorKind::BadPointer as u64;
-            }
+        if thread_handle_slice.write_struct(&handle).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
         }
 
         SYSCALL_SUCCESS
@@ -62,11 +75,13 @@ syscall_handler! {
 
 syscall_handler! {
     fn wait_thread(addr: u64, expected: u64) -> u64 {
-        if !memory::page::is_user_space_virtual_memory(addr as usize) {
+        let process = multitask::process::current_process().unwrap();
+
+        // 仅用于检查地址合法性，注册完成后即可释放
+        if UserSlice::readable_of::<u64>(&process, addr).is_err() {
             return cos_sys::error::ErrorKind::BadPointer as u64;
         }
 
-        let process = multitask::process::current_process().unwrap();
         let (sender, receiver) = oneshot::channel();
         if multitask::process::register_futex_if_match(&process, addr, expected, sender).is_err() {
             return cos_sys::error::ErrorKind::BadPointer as u64;
@@ -95,8 +110,9 @@ syscall_handler! {
 syscall_handler! {
     fn kill_thread(thread_handle: u64) -> u64 {
         let process = multitask::process::current_process().unwrap();
-        let Some(thread_handle) = multitask::process::get_process_handle(&process, thread_handle as usize) else {
-            return cos_sys::error::ErrorKind::BadArgument as u64;
+        let thread_handle = match multitask::process::get_process_handle(&process, thread_handle) {
+            Ok(thread_handle) => thread_handle,
+            Err(error) => return handle_error(&error),
         };
 
         let HandleObject::Thread { thread, .. } = &*thread_handle else {
@@ -113,12 +129,19 @@ syscall_handler! {
 
 syscall_handler! {
     fn create_thread(rip: u64, rsp: u64, params: u64, thread_handle_ptr: u64) -> u64 {
-        if !memory::page::is_user_space_virtual_memory(thread_handle_ptr as usize) {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(thread_handle_slice) = UserSlice::writable_of::<u64>(&process, thread_handle_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        // 以iretq进入用户态时，非规范的指令地址会在内核中触发#GP
+        let is_user_address = |addr: u64| {
+            memory::page::is_user_space_virtual_memory(addr as usize) && memory::page::is_canonical(addr)
+        };
+        if !is_user_address(rip) || (rsp != 0 && !is_user_address(rsp)) {
             return cos_sys::error::ErrorKind::BadPointer as u64;
         }
 
-        let process = multitask::process::current_process().unwrap();
-
         let Some(thread) = multitask::process::create_user_thread(&process, rip, rsp, params) else {
             return cos_sys::error::ErrorKind::OutOfMemory as u64;
         };
@@ -126,12 +149,13 @@ syscall_handler! {
             thread: Arc::downgrade(&thread),
             exit: multitask::thread::get_exit_code_subscriber(&thread),
         };
-        let handle = multitask::process::insert_process_handle(&process, thread_handle);
+        let handle = match multitask::process::insert_process_handle(&process, thread_handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
 
-        unsafe {
-            if multitask::process::write_user_process_memory_struct(&process, thread_handle_ptr, &handle).is_err() {
-                return cos_sys::error::ErrorKind::BadPointer as u64;
-            }
+        if thread_handle_slice.write_struct(&handle).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
         }
 
         SYSCALL_SUCCESS
@@ -140,14 +164,15 @@ syscall_handler! {
 
 syscall_handler! {
     fn join_thread(thread_handle: u64, exit_code_ptr: u64) -> u64 {
-        if !memory::page::is_user_space_virtual_memory(exit_code_ptr as usize) {
-            return cos_sys::error::ErrorKind::BadPointer as u64;
-        }
-
         let process = multitask::process::current_process().unwrap();
 
-        let Some(handle) = multitask::process::get_process_handle(&process, thread_handle as usize) else {
-            return cos_sys::error::ErrorKind::BadArgument as u64;
+        let Ok(exit_code_slice) = UserSlice::writable_of::<u64>(&process, exit_code_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let handle = match multitask::process::get_process_handle(&process, thread_handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
         };
 
         let HandleObject::Thread { exit, .. } = &*handle else {
@@ -166,13 +191,12 @@ syscall_handler! {
             return cos_sys::error::ErrorKind::Unknown as u64;
         }
 
-        multitask::process::remove_process_handle(&process, thread_handle as usize);
+        // 句柄可能已被其他线程关闭
+        _ = multitask::process::remove_process_handle(&process, thread_handle);
 
         let code = *exit.borrow();
-        unsafe {
-            if multitask::process::write_user_process_memory_struct(&process, exit_code_ptr, &code).is_err() {
-                return cos_sys::error::ErrorKind::BadPointer as u64;
-            }
+        if exit_code_slice.write_struct(&code).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
         }
 
         SYSCALL_SUCCESS
@@ -183,7 +207,7 @@ syscall_handler! {
     fn sleep_thread(time_in_seconds: u64, time_in_ns: u64) {
         let mut time_in_seconds = time_in_seconds;
         let mut time_in_ns = time_in_ns;
-        if time_in_ns > 1_000_000_000 {
+        if time_in_ns >= 1_000_000_000 {
             time_in_seconds = time_in_seconds.saturating_add(time_in_ns / 1_000_000_000);
             time_in_ns = time_in_ns % 1_000_000_000;
         }
@@ -195,22 +219,37 @@ syscall_handler! {
 }
 
 syscall_handler! {
-    fn current_process(process_handle_ptr: u64) -> u64 {
-        if !memory::page::is_user_space_virtual_memory(process_handle_ptr as usize) {
-            return  cos_sys::error::ErrorKind::BadPointer as u64;
+    fn set_tls_base(base: u64) -> u64 {
+        if base != 0
+            && !(memory::page::is_user_space_virtual_memory(base as usize) && memory::page::is_canonical(base))
+        {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
         }
 
+        multitask::thread::set_current_fs_base(base);
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn current_process(process_handle_ptr: u64) -> u64 {
         let process = multitask::process::current_process().unwrap();
+
+        let Ok(process_handle_slice) = UserSlice::writable_of::<u64>(&process, process_handle_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
         let process_handle = HandleObject::Process {
             process: Arc::downgrade(&process),
             exit: multitask::process::get_exit_code_subscriber(&process),
         };
-        let handle = multitask::process::insert_process_handle(&process, process_handle);
+        let handle = match multitask::process::insert_process_handle(&process, process_handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
 
-        unsafe {
-            if multitask::process::write_user_process_memory_struct(&process, process_handle_ptr, &handle).is_err() {
-                return cos_sys::error::ErrorKind::BadPointer as u64;
-            }
+        if process_handle_slice.write_struct(&handle).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
         }
 
         SYSCALL_SUCCESS
@@ -219,66 +258,121 @@ syscall_handler! {
 
 syscall_handler! {
     fn create_process(exe_ptr: u64, exe_len: u64, process_handle_ptr: u64) -> u64 {
-        if !memory::page::is_user_space_virtual_memory(exe_ptr as usize) ||
-            !memory::page::is_user_space_virtual_memory((exe_ptr + exe_len) as usize) ||
-            !memory::page::is_user_space_virtual_memory(process_handle_ptr as usize) {
-                return cos_sys::error::ErrorKind::BadPointer as u64;
-        }
-
         let process = multitask::process::current_process().unwrap();
-
-        let mut exe = alloc::vec![0u8; exe_len as usize];
-        unsafe {
-            if multitask::process::read_user_process_memory(&process, exe_ptr, exe.as_mut_ptr(), exe_len as usize).is_err() {
-                return cos_sys::error::ErrorKind::BadPointer as u64;
-            }
+        if !multitask::process::has_capability(&process, Capabilities::SPAWN) {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
         }
 
-        let (sender, receiver) = async_locks::channel::oneshot::channel();
-        multitask::async_rt::spawn(async move {
-            let Ok(exe_str) = str::from_utf8(&exe) else {
-                sender.send(Err(cos_sys::error::ErrorKind::BadArgument)).await;
-                return;
-            };
+        let Ok(process_handle_slice) = UserSlice::writable_of::<u64>(&process, process_handle_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let exe = match UserSlice::readable(&process, exe_ptr, exe_len as usize) {
+            Ok(exe) => exe,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let exe = match exe.read_to_vec() {
+            Ok(exe) => exe,
+            Err(error) => return error.error_kind() as u64,
+        };
 
-            if let Some(process) = multitask::process::create_user_process(exe_str).await {
-                sender.send(Ok(process)).await;
-            } else {
-                sender.send(Err(cos_sys::error::ErrorKind::Unknown)).await; // TODO: 占位，应当返回具体错误类型
-            }
-        });
+        spawn_process(&process, exe, Vec::new(), &process_handle_slice)
+    }
+}
 
-        let created_process = match multitask::async_rt::block_on(receiver.recv()) {
-            Ok(res) => res,
-            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+syscall_handler! {
+    fn create_process_with_args(exe_ptr: u64, exe_len: u64, args_ptr: u64, args_len: u64, process_handle_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+        if !multitask::process::has_capability(&process, Capabilities::SPAWN) {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+
+        if args_len as usize > cos_sys::multitask::MAX_ARGS_LEN {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        }
+        let Ok(process_handle_slice) = UserSlice::writable_of::<u64>(&process, process_handle_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
         };
-        let created_process = match created_process.unwrap() {
-            Ok(process) => process,
-            Err(err) => return err as u64,
+        let exe = match UserSlice::readable(&process, exe_ptr, exe_len as usize) {
+            Ok(exe) => exe,
+            Err(error) => return error.error_kind() as u64,
         };
-
-        let handle = HandleObject::Process {
-            process: Arc::downgrade(&created_process),
-            exit: multitask::process::get_exit_code_subscriber(&created_process),
+        let exe = match exe.read_to_vec() {
+            Ok(exe) => exe,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let args = match UserSlice::readable(&process, args_ptr, args_len as usize) {
+            Ok(args) => args,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let args = match args.read_to_vec() {
+            Ok(args) => args,
+            Err(error) => return error.error_kind() as u64,
         };
 
-        let handle = multitask::process::insert_process_handle(&process, handle) as u64;
+        spawn_process(&process, exe, args, &process_handle_slice)
+    }
+}
 
-        unsafe {
-            if multitask::process::write_user_process_memory_struct(&process, process_handle_ptr, &handle).is_err() {
-                return cos_sys::error::ErrorKind::BadPointer as u64;
-            }
+/// 创建子进程，并将其句柄写入process_handle_slice
+fn spawn_process(
+    process: &SpinLock<Process>,
+    exe: Vec<u8>,
+    args: Vec<u8>,
+    process_handle_slice: &UserSlice,
+) -> u64 {
+    let parent_id = multitask::process::process_id(process);
+    let (sender, receiver) = async_locks::channel::oneshot::channel();
+    multitask::async_rt::spawn(async move {
+        let Ok(exe_str) = str::from_utf8(&exe) else {
+            sender
+                .send(Err(cos_sys::error::ErrorKind::BadArgument as u64))
+                .await;
+            return;
+        };
+
+        match multitask::process::create_user_process(exe_str, args, Some(parent_id)).await {
+            Ok(process) => sender.send(Ok(process)).await,
+            Err(error) => sender.send(Err(create_process_error(&error))).await,
+        }
+    });
+
+    let created_process = match multitask::async_rt::block_on(receiver.recv()) {
+        Ok(res) => res,
+        Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+    };
+    let created_process = match created_process.unwrap() {
+        Ok(process) => process,
+        Err(error) => return error,
+    };
+
+    let handle = HandleObject::Process {
+        process: Arc::downgrade(&created_process),
+        exit: multitask::process::get_exit_code_subscriber(&created_process),
+    };
+
+    let handle = match multitask::process::insert_process_handle(process, handle) {
+        Ok(handle) => handle,
+        Err(error) => {
+            // 调用方无法再管理新进程，将其停止
+            multitask::process::set_exit_code(&created_process, cos_sys::multitask::EXIT_KILL);
+            multitask::process::stop_all_thread(&created_process, cos_sys::multitask::EXIT_KILL);
+            return handle_error(&error);
         }
+    };
 
-        SYSCALL_SUCCESS
+    if process_handle_slice.write_struct(&handle).is_err() {
+        return cos_sys::error::ErrorKind::BadPointer as u64;
     }
+
+    SYSCALL_SUCCESS
 }
 
 syscall_handler! {
     fn kill_process(process_handle: u64) -> u64 {
         let process = multitask::process::current_process().unwrap();
-        let Some(process_handle) = multitask::process::get_process_handle(&process, process_handle as usize) else {
-            return cos_sys::error::ErrorKind::BadArgument as u64;
+        let process_handle = match multitask::process::get_process_handle(&process, process_handle) {
+            Ok(process_handle) => process_handle,
+            Err(error) => return handle_error(&error),
         };
 
         let HandleObject::Process { process, .. } = &*process_handle else {
@@ -296,14 +390,15 @@ syscall_handler! {
 
 syscall_handler! {
     fn wait_process(process_handle: u64, exit_code_ptr: u64) -> u64 {
-        if !memory::page::is_user_space_virtual_memory(exit_code_ptr as usize) {
-            return cos_sys::error::ErrorKind::BadPointer as u64;
-        }
-
         let process = multitask::process::current_process().unwrap();
 
-        let Some(handle) = multitask::process::get_process_handle(&process, process_handle as usize) else {
-            return cos_sys::error::ErrorKind::BadArgument as u64;
+        let Ok(exit_code_slice) = UserSlice::writable_of::<u64>(&process, exit_code_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let handle = match multitask::process::get_process_handle(&process, process_handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
         };
 
         let HandleObject::Process { exit, .. } = &*handle else {
@@ -322,13 +417,307 @@ syscall_handler! {
             return cos_sys::error::ErrorKind::Unknown as u64;
         }
 
-        multitask::process::remove_process_handle(&process, process_handle as usize);
+        // 句柄可能已被其他线程关闭
+        _ = multitask::process::remove_process_handle(&process, process_handle);
 
         let code = *exit.borrow();
-        unsafe {
-            if multitask::process::write_user_process_memory_struct(&process, exit_code_ptr, &code).is_err() {
-                return cos_sys::error::ErrorKind::BadPointer as u64;
+        if exit_code_slice.write_struct(&code).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn set_process_limit(process_handle: u64, limit: u64, value: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+        let privileged = multitask::process::is_privileged(&process);
+
+        let Some(limit) = process_limit_kind(limit) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+
+        let process_handle = match multitask::process::get_process_handle(&process, process_handle) {
+            Ok(process_handle) => process_handle,
+            Err(error) => return handle_error(&error),
+        };
+
+        let HandleObject::Process { process, .. } = &*process_handle else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+
+        if let Some(process) = process.upgrade() {
+            // 非特权进程只能降低限制
+            if !privileged && value as usize > multitask::process::process_limit(&process, limit) {
+                return cos_sys::error::ErrorKind::PermissionDenied as u64;
             }
+            multitask::process::set_process_limit(&process, limit, value as usize);
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn get_process_limit(process_handle: u64, limit: u64, value_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(value_slice) = UserSlice::writable_of::<u64>(&process, value_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let Some(limit) = process_limit_kind(limit) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+
+        let process_handle = match multitask::process::get_process_handle(&process, process_handle) {
+            Ok(process_handle) => process_handle,
+            Err(error) => return handle_error(&error),
+        };
+
+        let HandleObject::Process { process, .. } = &*process_handle else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+        // 进程已退出
+        let Some(process) = process.upgrade() else {
+            return cos_sys::error::ErrorKind::BadHandle as u64;
+        };
+
+        let value = multitask::process::process_limit(&process, limit) as u64;
+        if value_slice.write_struct(&value).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+fn process_limit_kind(limit: u64) -> Option<ProcessLimit> {
+    let limit = match limit {
+        cos_sys::multitask::LIMIT_KERNEL_MEMORY => ProcessLimit::KernelMemory,
+        cos_sys::multitask::LIMIT_SYSCALL_BUFFER => ProcessLimit::SyscallBuffer,
+        cos_sys::multitask::LIMIT_ASYNC_REQUESTS => ProcessLimit::AsyncRequests,
+        cos_sys::multitask::LIMIT_HANDLES => ProcessLimit::Handles,
+        cos_sys::multitask::LIMIT_PAGES => ProcessLimit::Pages,
+        cos_sys::multitask::LIMIT_CHILDREN => ProcessLimit::Children,
+        cos_sys::multitask::LIMIT_CPU_TIME => ProcessLimit::CpuTime,
+        _ => return None,
+    };
+    Some(limit)
+}
+
+syscall_handler! {
+    fn list_processes(process_ids_ptr: u64, process_ids_len: u64, count_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(count_slice) = UserSlice::writable_of::<u64>(&process, count_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let process_ids_range = match UserRange::array::<u64>(process_ids_ptr, process_ids_len) {
+            Ok(process_ids_range) => process_ids_range,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let process_ids_slice = match UserSlice::from_range(&process, process_ids_range, true) {
+            Ok(process_ids_slice) => process_ids_slice,
+            Err(error) => return error.error_kind() as u64,
+        };
+
+        let process_ids = multitask::process::list_processes();
+        let bytes: Vec<u8> = process_ids
+            .iter()
+            .take(process_ids_len as usize)
+            .flat_map(|process_id| process_id.to_ne_bytes())
+            .collect();
+        if process_ids_slice.write(&bytes).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+        if count_slice.write_struct(&(process_ids.len() as u64)).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn process_info(process_id: u64, info_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(info_slice) = UserSlice::writable_of::<ProcessInfo>(&process, info_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let Some(target) = multitask::process::get_process(process_id) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+
+        let info = multitask::process::process_info(&target);
+        if info_slice.write_struct(&info).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn cpu_times(times_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(times_slice) = UserSlice::writable_of::<CpuTimes>(&process, times_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let (total, idle) = multitask::thread::system_cpu_time();
+        if times_slice.write_struct(&CpuTimes { total, idle }).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn process_args(buf_ptr: u64, buf_len: u64, len_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(len_slice) = UserSlice::writable_of::<u64>(&process, len_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let buf_slice = match UserSlice::writable(&process, buf_ptr, buf_len as usize) {
+            Ok(buf_slice) => buf_slice,
+            Err(error) => return error.error_kind() as u64,
+        };
+
+        let args = multitask::process::process_args(&process);
+        let len = args.len().min(buf_len as usize);
+        if buf_slice.write(&args[..len]).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+        if len_slice.write_struct(&(args.len() as u64)).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn credentials(credentials_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(credentials_slice) =
+            UserSlice::writable_of::<cos_sys::multitask::Credentials>(&process, credentials_ptr)
+        else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let credentials = multitask::process::credentials(&process);
+        let credentials = cos_sys::multitask::Credentials {
+            uid: credentials.uid,
+            gid: credentials.gid,
+        };
+        if credentials_slice.write_struct(&credentials).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn set_credentials(uid: u64, gid: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let (Ok(uid), Ok(gid)) = (u32::try_from(uid), u32::try_from(gid)) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+        if !multitask::process::set_credentials(&process, Credentials { uid, gid }) {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn set_process_group(process_handle: u64, process_group: u64, process_group_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(process_group_slice) = UserSlice::writable_of::<u64>(&process, process_group_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let target_handle = match multitask::process::get_process_handle(&process, process_handle) {
+            Ok(target_handle) => target_handle,
+            Err(error) => return handle_error(&error),
+        };
+        let HandleObject::Process { process: target, .. } = &*target_handle else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+        // 进程已退出
+        let Some(target) = target.upgrade() else {
+            return cos_sys::error::ErrorKind::BadHandle as u64;
+        };
+
+        let process_group = match multitask::process::set_process_group(&process, &target, process_group) {
+            Ok(process_group) => process_group,
+            Err(error) => return process_group_error(&error),
+        };
+        if process_group_slice.write_struct(&process_group).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn create_session(session_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(session_slice) = UserSlice::writable_of::<u64>(&process, session_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let Some(session) = multitask::process::create_session(&process) else {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        };
+        if session_slice.write_struct(&session).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn set_foreground_group(process_group: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let process_id = multitask::process::process_id(&process);
+        let (_, session) = multitask::process::process_group(&process);
+        let console = multitask::process::process_console(&process);
+        if let Err(error) =
+            io::console::set_foreground_group(console, process_id, session, process_group)
+        {
+            return foreground_error(&error);
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn foreground_group(process_group_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(process_group_slice) = UserSlice::writable_of::<u64>(&process, process_group_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let console = multitask::process::process_console(&process);
+        if process_group_slice.write_struct(&io::console::foreground_group(console)).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
         }
 
         SYSCALL_SUCCESS
@@ -0,0 +1,329 @@
+use netstack::wire::{Ipv4Address, SocketAddrV4};
+
+use crate::{
+    io, multitask,
+    syscall::{SYSCALL_SUCCESS, handle_error, net_error},
+    syscall_handler,
+    user::{handle::HandleObject, slice::UserSlice},
+};
+
+syscall_handler! {
+    fn udp_bind(port: u64, handle_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(handle_slice) = UserSlice::writable_of::<u64>(&process, handle_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let Ok(port) = u16::try_from(port) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+        let Some(stack) = io::net::net_stack() else {
+            return cos_sys::error::ErrorKind::NotSupported as u64;
+        };
+
+        let socket = match multitask::async_rt::block_on(stack.bind_udp(port)) {
+            Ok(Ok(socket)) => socket,
+            Ok(Err(error)) => return net_error(&error),
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        let handle = match multitask::process::insert_process_handle(
+            &process,
+            HandleObject::UdpSocket(socket),
+        ) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
+
+        if handle_slice.write_struct(&handle).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn udp_send_to(handle: u64, buf_ptr: u64, buf_len: u64, addr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let buf = match UserSlice::readable(&process, buf_ptr, buf_len as usize) {
+            Ok(buf) => buf,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let buf = match buf.read_to_vec() {
+            Ok(buf) => buf,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let handle = match multitask::process::get_process_handle(&process, handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
+        let HandleObject::UdpSocket(socket) = &*handle else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+        let addr = cos_sys::net::SocketAddrV4::from_raw(addr);
+        let addr = SocketAddrV4::new(Ipv4Address(addr.ip), addr.port);
+
+        match multitask::async_rt::block_on(socket.send_to(&buf, addr)) {
+            Ok(Ok(())) => SYSCALL_SUCCESS,
+            Ok(Err(error)) => net_error(&error),
+            Err(_) => cos_sys::error::ErrorKind::Unknown as u64,
+        }
+    }
+}
+
+syscall_handler! {
+    fn udp_recv_from(handle: u64, buf_ptr: u64, buf_len: u64, len_ptr: u64, addr_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let buf_slice = match UserSlice::writable(&process, buf_ptr, buf_len as usize) {
+            Ok(buf_slice) => buf_slice,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let Ok(len_slice) = UserSlice::writable_of::<u64>(&process, len_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let Ok(addr_slice) = UserSlice::writable_of::<u64>(&process, addr_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let handle = match multitask::process::get_process_handle(&process, handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
+        let HandleObject::UdpSocket(socket) = &*handle else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+
+        // 数据报长度不超过MTU，先接收到内核缓冲区，避免为小数据报映射用户内存
+        let mut buf = alloc::vec![0u8; (buf_len as usize).min(socket.max_datagram_len())];
+        // 线程被终止时，接收被取消，数据报留在队列中
+        let (len, source) = match multitask::async_rt::block_on(socket.recv_from(&mut buf)) {
+            Ok(result) => result,
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        let source = cos_sys::net::SocketAddrV4::new(source.ip.0, source.port);
+
+        if buf_slice.write(&buf[..len]).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+        if len_slice.write_struct(&(len as u64)).is_err()
+            || addr_slice.write_struct(&source.to_raw()).is_err()
+        {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+/// 单次TCP读写在内核中缓冲的最大长度
+const TCP_BUFFER_LEN: usize = 65536;
+
+syscall_handler! {
+    fn tcp_connect(addr: u64, handle_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(handle_slice) = UserSlice::writable_of::<u64>(&process, handle_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let Some(stack) = io::net::net_stack() else {
+            return cos_sys::error::ErrorKind::NotSupported as u64;
+        };
+        let addr = cos_sys::net::SocketAddrV4::from_raw(addr);
+        let addr = SocketAddrV4::new(Ipv4Address(addr.ip), addr.port);
+
+        // 线程被终止时连接被取消，协议栈在下一次处理计时器时将其关闭
+        let stream = match multitask::async_rt::block_on(stack.connect_tcp(addr)) {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(error)) => return net_error(&error),
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        let handle = match multitask::process::insert_process_handle(
+            &process,
+            HandleObject::TcpStream(stream),
+        ) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
+
+        if handle_slice.write_struct(&handle).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn tcp_listen(port: u64, handle_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(handle_slice) = UserSlice::writable_of::<u64>(&process, handle_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let Ok(port) = u16::try_from(port) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+        let Some(stack) = io::net::net_stack() else {
+            return cos_sys::error::ErrorKind::NotSupported as u64;
+        };
+
+        let listener = match multitask::async_rt::block_on(stack.listen_tcp(port)) {
+            Ok(Ok(listener)) => listener,
+            Ok(Err(error)) => return net_error(&error),
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        let handle = match multitask::process::insert_process_handle(
+            &process,
+            HandleObject::TcpListener(listener),
+        ) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
+
+        if handle_slice.write_struct(&handle).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn tcp_accept(handle: u64, stream_ptr: u64, addr_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(stream_slice) = UserSlice::writable_of::<u64>(&process, stream_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let Ok(addr_slice) = UserSlice::writable_of::<u64>(&process, addr_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let handle = match multitask::process::get_process_handle(&process, handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
+        let HandleObject::TcpListener(listener) = &*handle else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+
+        // 线程被终止时，接受被取消，连接留在等待队列中
+        let stream = match multitask::async_rt::block_on(listener.accept()) {
+            Ok(stream) => stream,
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        let peer = stream.peer_addr();
+        let peer = cos_sys::net::SocketAddrV4::new(peer.ip.0, peer.port);
+        let stream_handle = match multitask::process::insert_process_handle(
+            &process,
+            HandleObject::TcpStream(stream),
+        ) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
+
+        if stream_slice.write_struct(&stream_handle).is_err()
+            || addr_slice.write_struct(&peer.to_raw()).is_err()
+        {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn tcp_read(handle: u64, buf_ptr: u64, buf_len: u64, len_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let buf_slice = match UserSlice::writable(&process, buf_ptr, buf_len as usize) {
+            Ok(buf_slice) => buf_slice,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let Ok(len_slice) = UserSlice::writable_of::<u64>(&process, len_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let handle = match multitask::process::get_process_handle(&process, handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
+        let HandleObject::TcpStream(stream) = &*handle else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+
+        let mut buf = alloc::vec![0u8; (buf_len as usize).min(TCP_BUFFER_LEN)];
+        // 线程被终止时，读取被取消，数据留在接收缓冲区中
+        let len = match multitask::async_rt::block_on(stream.read(&mut buf)) {
+            Ok(Ok(len)) => len,
+            Ok(Err(error)) => return net_error(&error),
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+
+        if buf_slice.write(&buf[..len]).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+        if len_slice.write_struct(&(len as u64)).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn tcp_write(handle: u64, buf_ptr: u64, buf_len: u64, len_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        // 单次最多写入TCP_BUFFER_LEN字节，调用方根据返回的长度继续写入
+        let buf_len = (buf_len as usize).min(TCP_BUFFER_LEN);
+        let buf = match UserSlice::readable(&process, buf_ptr, buf_len) {
+            Ok(buf) => buf,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let buf = match buf.read_to_vec() {
+            Ok(buf) => buf,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let Ok(len_slice) = UserSlice::writable_of::<u64>(&process, len_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let handle = match multitask::process::get_process_handle(&process, handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
+        let HandleObject::TcpStream(stream) = &*handle else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+
+        let len = match multitask::async_rt::block_on(stream.write(&buf)) {
+            Ok(Ok(len)) => len,
+            Ok(Err(error)) => return net_error(&error),
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+
+        if len_slice.write_struct(&(len as u64)).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn tcp_shutdown(handle: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let handle = match multitask::process::get_process_handle(&process, handle) {
+            Ok(handle) => handle,
+            Err(error) => return handle_error(&error),
+        };
+        let HandleObject::TcpStream(stream) = &*handle else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+
+        match multitask::async_rt::block_on(stream.shutdown()) {
+            Ok(()) => SYSCALL_SUCCESS,
+            Err(_) => cos_sys::error::ErrorKind::Unknown as u64,
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use crate::{multitask, random, syscall::SYSCALL_SUCCESS, syscall_handler, user::slice::UserSlice};
+
+/// 每次从熵池读取的最大字节数，避免长时间关闭中断
+const CHUNK_SIZE: usize = 256;
+
+syscall_handler! {
+    fn get_random(buf_ptr: u64, buf_len: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        if let Err(error) = UserSlice::writable(&process, buf_ptr, buf_len as usize) {
+            return error.error_kind() as u64;
+        }
+
+        let mut chunk = [0u8; CHUNK_SIZE];
+        let mut offset = 0;
+        while offset < buf_len as usize {
+            let len = CHUNK_SIZE.min(buf_len as usize - offset);
+            random::fill(&mut chunk[..len]);
+            let buf = match UserSlice::writable(&process, buf_ptr + offset as u64, len) {
+                Ok(buf) => buf,
+                Err(error) => return error.error_kind() as u64,
+            };
+            if buf.write(&chunk[..len]).is_err() {
+                return cos_sys::error::ErrorKind::BadPointer as u64;
+            }
+            offset += len;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
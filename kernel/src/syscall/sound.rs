@@ -0,0 +1,24 @@
+use core::time::Duration;
+
+use crate::{
+    io::speaker::{self, Beep, BeepError},
+    syscall::SYSCALL_SUCCESS,
+    syscall_handler,
+};
+
+syscall_handler! {
+    fn beep(frequency: u64, duration_ms: u64) -> u64 {
+        let Ok(frequency) = u32::try_from(frequency) else {
+            return cos_sys::error::ErrorKind::BadArgument as u64;
+        };
+        let beep = Beep {
+            frequency,
+            duration: Duration::from_millis(duration_ms),
+        };
+        match speaker::beep(beep) {
+            Ok(()) => SYSCALL_SUCCESS,
+            Err(BeepError::BadArgument) => cos_sys::error::ErrorKind::BadArgument as u64,
+            Err(BeepError::QueueFull) => cos_sys::error::ErrorKind::WouldBlock as u64,
+        }
+    }
+}
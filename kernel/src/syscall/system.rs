@@ -0,0 +1,146 @@
+use cos_sys::abi::{self, AbiInfo};
+
+use crate::{
+    boot_slot, bootloader, cmdline, display, io, log_file, module,
+    multitask::{self, process::Process},
+    sync::spin::SpinLock,
+    syscall::{SYSCALL_SUCCESS, update_error},
+    syscall_handler,
+    user::slice::UserSlice,
+};
+
+syscall_handler! {
+    fn cmdline(buf_ptr: u64, buf_len: u64, len_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(len_slice) = UserSlice::writable_of::<u64>(&process, len_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let buf = match UserSlice::writable(&process, buf_ptr, buf_len as usize) {
+            Ok(buf) => buf,
+            Err(error) => return error.error_kind() as u64,
+        };
+
+        let cmdline = bootloader::cmdline().as_bytes();
+        let copied = &cmdline[..cmdline.len().min(buf.len())];
+        if buf.write(copied).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+        if len_slice.write_struct(&(cmdline.len() as u64)).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn shutdown() -> u64 {
+        let process = multitask::process::current_process().unwrap();
+        if !can_power_off(&process) {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+        unmount_all();
+        io::acpi::shutdown()
+    }
+}
+
+syscall_handler! {
+    fn reboot() -> u64 {
+        let process = multitask::process::current_process().unwrap();
+        if !can_power_off(&process) {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+        unmount_all();
+        io::acpi::reboot()
+    }
+}
+
+syscall_handler! {
+    fn abi_info(info_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+        let Ok(info_slice) = UserSlice::writable_of::<AbiInfo>(&process, info_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+
+        let info = AbiInfo {
+            features: features(),
+            ..AbiInfo::current()
+        };
+        if info_slice.write_struct(&info).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn update_kernel(path_ptr: u64, path_len: u64) -> u64 {
+        // 内核镜像决定下次启动运行的代码，只有超级用户可以安装
+        let process = multitask::process::current_process().unwrap();
+        if !multitask::process::credentials(&process).is_root() {
+            return cos_sys::error::ErrorKind::PermissionDenied as u64;
+        }
+        let path = match UserSlice::readable(&process, path_ptr, path_len as usize) {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+        let path = match path.read_to_vec() {
+            Ok(path) => path,
+            Err(error) => return error.error_kind() as u64,
+        };
+
+        let (sender, receiver) = async_locks::channel::oneshot::channel();
+        multitask::async_rt::spawn(async move {
+            let Ok(path) = filesystem::path::PathBuf::from_bytes(&path) else {
+                sender.send(Err(cos_sys::error::ErrorKind::BadArgument as u64)).await;
+                return;
+            };
+            let result = boot_slot::install(path.as_path()).await;
+            sender.send(result.map(|_| ()).map_err(|error| update_error(&error))).await;
+        });
+        let result = match multitask::async_rt::block_on(receiver.recv()) {
+            Ok(res) => res,
+            Err(_) => return cos_sys::error::ErrorKind::Unknown as u64,
+        };
+        match result.unwrap() {
+            Ok(()) => SYSCALL_SUCCESS,
+            Err(error) => error,
+        }
+    }
+}
+
+/// 当前机器与内核配置下可用的功能
+fn features() -> u64 {
+    let mut features = 0;
+    if display::framebuffer::info().is_some() {
+        features |= abi::FEATURE_GRAPHICS;
+    }
+    if io::net::net_stack().is_some() {
+        features |= abi::FEATURE_NETWORK;
+    }
+    if module::supported() {
+        features |= abi::FEATURE_MODULES;
+    }
+    features
+}
+
+/// 只有超级用户或特权进程可以关机与重启
+fn can_power_off(process: &SpinLock<Process>) -> bool {
+    multitask::process::credentials(process).is_root() || multitask::process::is_privileged(process)
+}
+
+/// 关机与重启前卸载全部文件系统，确保缓存写回磁盘
+fn unmount_all() {
+    let (sender, receiver) = async_locks::channel::oneshot::channel();
+    multitask::async_rt::spawn(async move {
+        // 关机前写入尚未写入日志文件的日志
+        if cmdline::options().log_file {
+            _ = log_file::flush().await;
+        }
+        io::vfs::unmount_all().await;
+        sender.send(()).await;
+    });
+    _ = multitask::async_rt::block_on(receiver.recv());
+}
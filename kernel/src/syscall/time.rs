@@ -0,0 +1,48 @@
+use crate::{multitask, syscall::SYSCALL_SUCCESS, syscall_handler, time, user::slice::UserSlice};
+
+syscall_handler! {
+    fn get_time(time_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(time_slice) = UserSlice::writable_of::<u64>(&process, time_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let nanos = time::realtime().as_nanos() as u64;
+        if time_slice.write_struct(&nanos).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn monotonic(time_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(time_slice) = UserSlice::writable_of::<u64>(&process, time_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        let nanos = time::monotonic().as_nanos() as u64;
+        if time_slice.write_struct(&nanos).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
+
+syscall_handler! {
+    fn utc_offset(offset_ptr: u64) -> u64 {
+        let process = multitask::process::current_process().unwrap();
+
+        let Ok(offset_slice) = UserSlice::writable_of::<i64>(&process, offset_ptr) else {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        };
+        if offset_slice.write_struct(&time::utc_offset()).is_err() {
+            return cos_sys::error::ErrorKind::BadPointer as u64;
+        }
+
+        SYSCALL_SUCCESS
+    }
+}
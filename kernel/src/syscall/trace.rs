@@ -0,0 +1,242 @@
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use alloc::{
+    collections::{btree_map::BTreeMap, vec_deque::VecDeque},
+    vec::Vec,
+};
+use cos_sys::debug::{SyscallTraceRecord, TRACE_CHILDREN, TRACE_SYSCALLS};
+
+use crate::{
+    multitask,
+    sync::{int::IrqGuard, percpu, spin::SpinLock},
+    syscall::{self, SYSCALL_SUCCESS},
+    time,
+};
+
+/// 跟踪记录缓冲区的容量，缓冲区满时丢弃最早的记录
+const TRACE_BUFFER_CAPACITY: usize = 1024;
+
+static TRACE: SpinLock<TraceState> = SpinLock::new(TraceState::new());
+/// 是否有进程被跟踪，没有时系统调用入口无需查询跟踪选项
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+struct TraceState {
+    // 被跟踪的进程及其跟踪选项
+    processes: BTreeMap<u64, Traced>,
+    // 正在执行的被跟踪系统调用，按线程ID索引
+    pending: BTreeMap<u64, PendingSyscall>,
+    // 已完成的系统调用，按完成的先后排列
+    records: VecDeque<TracedRecord>,
+    // 因缓冲区满而丢弃的记录数量，取出记录时清零
+    dropped: u64,
+}
+
+struct Traced {
+    flags: u64,
+    // 设置跟踪的进程，跟踪记录只能由其取出
+    tracer: u64,
+}
+
+struct PendingSyscall {
+    tracer: u64,
+    process_id: u64,
+    syscall_id: u64,
+    start: Duration,
+}
+
+struct TracedRecord {
+    tracer: u64,
+    record: SyscallTraceRecord,
+}
+
+impl TraceState {
+    const fn new() -> Self {
+        Self {
+            processes: BTreeMap::new(),
+            pending: BTreeMap::new(),
+            records: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    fn push(&mut self, tracer: u64, record: SyscallTraceRecord) {
+        if self.records.len() >= TRACE_BUFFER_CAPACITY {
+            self.records.pop_front();
+            self.dropped += 1;
+        }
+        self.records.push_back(TracedRecord { tracer, record });
+    }
+
+    fn update_active(&self) {
+        ACTIVE.store(!self.processes.is_empty(), Ordering::Relaxed);
+    }
+}
+
+/// 由tracer设置进程的跟踪选项，flags为0时停止跟踪
+///
+/// 此后产生的记录归属于tracer，只能由tracer取出
+pub fn set_flags(tracer: u64, process_id: u64, flags: u64) {
+    let _guard = IrqGuard::cli();
+    let mut trace = TRACE.lock();
+    if flags == 0 {
+        trace.processes.remove(&process_id);
+    } else {
+        trace.processes.insert(process_id, Traced { flags, tracer });
+    }
+    trace.update_active();
+}
+
+/// 创建进程时调用，父进程设置了 [TRACE_CHILDREN] 时跟踪新进程，记录归属于父进程的跟踪者
+pub fn inherit(parent_id: Option<u64>, process_id: u64) {
+    let Some(parent_id) = parent_id else {
+        return;
+    };
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return;
+    }
+    let _guard = IrqGuard::cli();
+    let mut trace = TRACE.lock();
+    let Some(tracer) = trace
+        .processes
+        .get(&parent_id)
+        .filter(|traced| traced.flags & TRACE_CHILDREN != 0)
+        .map(|traced| traced.tracer)
+    else {
+        return;
+    };
+    trace.processes.insert(
+        process_id,
+        Traced {
+            flags: TRACE_SYSCALLS,
+            tracer,
+        },
+    );
+    trace.update_active();
+}
+
+/// 进程退出时调用，清除其跟踪选项，以及被停止的线程未完成的系统调用。已产生的记录仍保留
+pub fn forget(process_id: u64) {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return;
+    }
+    let _guard = IrqGuard::cli();
+    let mut trace = TRACE.lock();
+    trace.processes.remove(&process_id);
+    trace
+        .pending
+        .retain(|_, pending| pending.process_id != process_id);
+    trace.update_active();
+}
+
+/// 由tracer取出记录
+///
+/// process_id为0时取出tracer跟踪的所有进程的记录，否则只取出指定进程的记录。
+/// privileged为true时可以取出指定进程由其他进程跟踪产生的记录。
+/// 返回取出的记录，以及自上次取出以来丢弃的记录数量
+pub fn take_records(
+    tracer: u64,
+    process_id: u64,
+    privileged: bool,
+    max: usize,
+) -> (Vec<SyscallTraceRecord>, u64) {
+    let _guard = IrqGuard::cli();
+    let mut trace = TRACE.lock();
+    let mut taken = Vec::new();
+    trace.records.retain(|traced| {
+        let matched = if process_id == 0 {
+            traced.tracer == tracer
+        } else {
+            traced.record.process_id == process_id && (privileged || traced.tracer == tracer)
+        };
+        if taken.len() < max && matched {
+            taken.push(traced.record);
+            false
+        } else {
+            true
+        }
+    });
+    let dropped = core::mem::take(&mut trace.dropped);
+    (taken, dropped)
+}
+
+/// 系统调用入口查询处理函数时调用
+///
+/// 当前进程被跟踪时记录调用的开始，返回true，此时入口应改为调用 [traced_syscall]
+pub fn enter(syscall_id: u64) -> bool {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return false;
+    }
+    let thread_id = percpu::get_current_thread_id();
+    let Some(process_id) = multitask::thread::current_thread().and_then(|thread| {
+        let _guard = IrqGuard::cli();
+        thread.lock().process_id
+    }) else {
+        return false;
+    };
+    let process_id = process_id.get();
+
+    let start = time::monotonic();
+    let _guard = IrqGuard::cli();
+    let mut trace = TRACE.lock();
+    let Some(tracer) = trace
+        .processes
+        .get(&process_id)
+        .filter(|traced| traced.flags & TRACE_SYSCALLS != 0)
+        .map(|traced| traced.tracer)
+    else {
+        return false;
+    };
+    trace.pending.insert(
+        thread_id,
+        PendingSyscall {
+            tracer,
+            process_id,
+            syscall_id,
+            start,
+        },
+    );
+    true
+}
+
+/// 被跟踪的系统调用的处理函数，调用实际的处理函数，并记录参数、返回值与耗时
+pub extern "C" fn traced_syscall(p1: u64, p2: u64, p3: u64, p4: u64, p5: u64, p6: u64) -> u64 {
+    let thread_id = percpu::get_current_thread_id();
+    let pending = {
+        let _guard = IrqGuard::cli();
+        TRACE.lock().pending.remove(&thread_id)
+    }
+    .expect("codebug: traced syscall without pending record");
+    let handler =
+        syscall::find_handler(pending.syscall_id).expect("codebug: traced syscall without handler");
+
+    let mut record = SyscallTraceRecord {
+        process_id: pending.process_id,
+        thread_id,
+        syscall_id: pending.syscall_id,
+        args: [p1, p2, p3, p4, p5, p6],
+        result: SYSCALL_SUCCESS,
+        duration: 0,
+    };
+    // 退出类系统调用不会返回，在调用前记录
+    if matches!(
+        pending.syscall_id,
+        cos_sys::idx::IDX_EXIT_PROCESS | cos_sys::idx::IDX_EXIT_THREAD
+    ) {
+        {
+            let _guard = IrqGuard::cli();
+            TRACE.lock().push(pending.tracer, record);
+        }
+        return handler(p1, p2, p3, p4, p5, p6);
+    }
+
+    let result = handler(p1, p2, p3, p4, p5, p6);
+
+    record.result = result;
+    record.duration = time::monotonic().saturating_sub(pending.start).as_micros() as u64;
+    let _guard = IrqGuard::cli();
+    TRACE.lock().push(pending.tracer, record);
+    result
+}
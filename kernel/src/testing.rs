@@ -0,0 +1,133 @@
+use alloc::string::String;
+use core::{
+    arch::asm,
+    panic::PanicInfo,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    io,
+    sync::{self, spin::SpinLock},
+};
+
+/// 测试全部通过时写入isa-debug-exit的值，qemu的退出码为1
+const EXIT_SUCCESS: u32 = 0;
+/// 测试失败时写入isa-debug-exit的值，qemu的退出码为3
+const EXIT_FAILED: u32 = 1;
+
+/// 正在运行的测试名，panic时据此报告失败的用例
+static CURRENT_TEST: SpinLock<Option<&'static str>> = SpinLock::new(None);
+/// 正在运行的测试的附加信息，测试失败时一并输出，见 [`set_context`]
+static CURRENT_CONTEXT: SpinLock<Option<String>> = SpinLock::new(None);
+/// 已通过的测试数量
+static PASSED: AtomicUsize = AtomicUsize::new(0);
+
+/// 由`#[test_case]`标记的测试用例
+pub trait Testable {
+    /// 测试名，即测试函数的完整路径
+    fn name(&self) -> &'static str;
+
+    fn run(&self);
+}
+
+impl<T: Fn()> Testable for T {
+    fn name(&self) -> &'static str {
+        core::any::type_name::<T>()
+    }
+
+    fn run(&self) {
+        self()
+    }
+}
+
+/// 依次运行测试用例，通过串口报告结果后退出qemu
+///
+/// 输出格式与user/system/test-runner相同，由build-scripts中的集成测试解析。
+/// 内核以panic=abort编译，测试失败即panic，之后的用例不再运行，见 [`report_panic`]
+pub fn run(tests: &[&dyn Testable]) -> ! {
+    io::serial::_write_fmt(format_args!("COS-TEST BEGIN {}\n", tests.len()));
+    for test in tests {
+        let name = test.name();
+        *CURRENT_TEST.lock() = Some(name);
+        test.run();
+        *CURRENT_TEST.lock() = None;
+        io::serial::_write_fmt(format_args!("COS-TEST PASS {name}\n"));
+        PASSED.fetch_add(1, Ordering::SeqCst);
+    }
+    io::serial::_write_fmt(format_args!(
+        "COS-TEST END {} 0\n",
+        PASSED.load(Ordering::SeqCst)
+    ));
+    exit(EXIT_SUCCESS)
+}
+
+/// 以单个测试用例的形式运行异步测试，输出格式与 [`run`] 相同，完成后退出qemu
+///
+/// 用于需要异步运行时的测试，如 [`crate::fuzz`]，须在异步运行时中调用。
+/// 测试返回错误或panic时报告失败，并附加 [`set_context`] 设置的信息
+pub async fn run_async(name: &'static str, test: impl Future<Output = Result<(), String>>) {
+    io::serial::_write_fmt(format_args!("COS-TEST BEGIN 1\n"));
+    *CURRENT_TEST.lock_irqsave() = Some(name);
+    let result = test.await;
+    *CURRENT_TEST.lock_irqsave() = None;
+    match result {
+        Ok(()) => {
+            io::serial::_write_fmt(format_args!("COS-TEST PASS {name}\n"));
+            io::serial::_write_fmt(format_args!("COS-TEST END 1 0\n"));
+            exit(EXIT_SUCCESS)
+        }
+        Err(reason) => {
+            io::serial::_write_fmt(format_args!("COS-TEST FAIL {name} {reason}"));
+            if let Some(context) = CURRENT_CONTEXT.lock_irqsave().take() {
+                io::serial::_write_fmt(format_args!(" {context}"));
+            }
+            io::serial::_write_fmt(format_args!("\n"));
+            io::serial::_write_fmt(format_args!("COS-TEST END 0 1\n"));
+            exit(EXIT_FAILED)
+        }
+    }
+}
+
+/// 设置正在运行的测试的附加信息，如随机种子，测试失败时一并输出以便复现
+pub fn set_context(context: String) {
+    *CURRENT_CONTEXT.lock_irqsave() = Some(context);
+}
+
+/// 测试模式下的panic处理函数，报告正在运行的测试失败后退出qemu
+#[panic_handler]
+fn report_panic(info: &PanicInfo) -> ! {
+    sync::int::cli();
+    // 测试在持有锁时panic的可能性极小，此时无法得知测试名，按测试之外的panic报告
+    let current = CURRENT_TEST.try_lock().and_then(|current| *current);
+    match current {
+        Some(name) => {
+            io::serial::_write_fmt(format_args!("COS-TEST FAIL {name} {}", info.message()));
+            if let Some(context) = CURRENT_CONTEXT.try_lock()
+                && let Some(context) = &*context
+            {
+                io::serial::_write_fmt(format_args!(" {context}"));
+            }
+            io::serial::_write_fmt(format_args!("\n"));
+            if let Some(location) = info.location() {
+                io::serial::_write_fmt(format_args!("at {location}\n"));
+            }
+            io::serial::_write_fmt(format_args!(
+                "COS-TEST END {} 1\n",
+                PASSED.load(Ordering::SeqCst)
+            ));
+        }
+        // 不在测试中，说明内核在测试开始前或测试之间panic
+        None => io::serial::_write_fmt(format_args!("COS-TEST PANIC {}\n", info.message())),
+    }
+    exit(EXIT_FAILED)
+}
+
+fn exit(code: u32) -> ! {
+    io::qemu::exit(code);
+    // qemu未配置isa-debug-exit设备
+    loop {
+        unsafe {
+            asm!("hlt");
+        }
+    }
+}
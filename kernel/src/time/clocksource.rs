@@ -0,0 +1,339 @@
+use core::{
+    arch::{asm, x86_64::__cpuid},
+    hint::spin_loop,
+    ptr,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+use crate::{io, klog, memory, multitask, random::rdtsc, sync::int::IrqGuard};
+
+/// 时钟源，提供以固定频率单调递增的计数器
+pub trait ClockSource: Sync {
+    /// 名称，用于日志
+    fn name(&self) -> &'static str;
+    /// 读取计数器
+    fn read(&self) -> u64;
+    /// 计数器的频率（Hz）
+    fn frequency(&self) -> u64;
+}
+
+/// 时间戳计数器
+///
+/// 频率在启动时以HPET或PIT校准。仅当CPU支持恒定TSC（invariant TSC）时使用，
+/// 否则TSC的频率可能随CPU降频、休眠等改变
+struct Tsc {
+    hz: AtomicU64,
+}
+
+impl ClockSource for Tsc {
+    fn name(&self) -> &'static str {
+        "tsc"
+    }
+
+    fn read(&self) -> u64 {
+        rdtsc()
+    }
+
+    fn frequency(&self) -> u64 {
+        self.hz.load(Ordering::Relaxed)
+    }
+}
+
+/// 高精度事件计时器（HPET）的主计数器
+///
+/// 仅使用64位计数器，32位计数器在几分钟内即会回绕
+struct Hpet {
+    address: AtomicUsize,
+    hz: AtomicU64,
+}
+
+impl Hpet {
+    // 寄存器偏移
+    const REG_CAPABILITIES: usize = 0x00;
+    const REG_CONFIG: usize = 0x10;
+    const REG_COUNTER: usize = 0xF0;
+    // 能力寄存器第13位：计数器为64位
+    const CAP_COUNTER_64: u64 = 1 << 13;
+    // 配置寄存器第0位：启用主计数器
+    const CONFIG_ENABLE: u64 = 1 << 0;
+    // 计数周期的上限（飞秒），HPET规范要求不超过100ns
+    const MAX_PERIOD: u64 = 100_000_000;
+
+    fn read_register(&self, offset: usize) -> u64 {
+        let address = self.address.load(Ordering::Relaxed) + offset;
+        unsafe { ptr::read_volatile(address as *const u64) }
+    }
+
+    fn write_register(&self, offset: usize, value: u64) {
+        let address = self.address.load(Ordering::Relaxed) + offset;
+        unsafe { ptr::write_volatile(address as *mut u64, value) }
+    }
+
+    /// 映射并启用HPET，HPET不存在或不可用时返回false
+    fn init(&self) -> bool {
+        let Some(phys) = io::acpi::acpi().and_then(|acpi| acpi.hpet) else {
+            return false;
+        };
+        let Some(region) = memory::mmio::map_mmio(phys, 0x400) else {
+            return false;
+        };
+        // 检查通过后才保留映射，不可用时region被丢弃并解除映射
+        let capabilities = region.read_u32(Self::REG_CAPABILITIES) as u64
+            | (region.read_u32(Self::REG_CAPABILITIES + 4) as u64) << 32;
+        let period = capabilities >> 32;
+        if capabilities & Self::CAP_COUNTER_64 == 0 || period == 0 || period > Self::MAX_PERIOD {
+            return false;
+        }
+        self.address.store(region.leak(), Ordering::Relaxed);
+        let config = self.read_register(Self::REG_CONFIG);
+        self.write_register(Self::REG_CONFIG, config | Self::CONFIG_ENABLE);
+        self.hz
+            .store(1_000_000_000_000_000 / period, Ordering::Relaxed);
+        true
+    }
+}
+
+impl ClockSource for Hpet {
+    fn name(&self) -> &'static str {
+        "hpet"
+    }
+
+    fn read(&self) -> u64 {
+        self.read_register(Self::REG_COUNTER)
+    }
+
+    fn frequency(&self) -> u64 {
+        self.hz.load(Ordering::Relaxed)
+    }
+}
+
+/// 计时器中断累计的时间，精度为计时器中断的间隔，总是可用
+struct Tick;
+
+impl ClockSource for Tick {
+    fn name(&self) -> &'static str {
+        "tick"
+    }
+
+    fn read(&self) -> u64 {
+        multitask::async_task::uptime().as_micros() as u64
+    }
+
+    fn frequency(&self) -> u64 {
+        1_000_000
+    }
+}
+
+static TSC: Tsc = Tsc {
+    hz: AtomicU64::new(0),
+};
+static HPET: Hpet = Hpet {
+    address: AtomicUsize::new(0),
+    hz: AtomicU64::new(0),
+};
+static TICK: Tick = Tick;
+
+const SOURCE_TSC: usize = 0;
+const SOURCE_HPET: usize = 1;
+const SOURCE_TICK: usize = 2;
+static SOURCES: [&dyn ClockSource; 3] = [&TSC, &HPET, &TICK];
+
+/// 当前使用的时钟源在 [SOURCES] 中的下标
+static CURRENT: AtomicUsize = AtomicUsize::new(SOURCE_TICK);
+// 切换时钟源时的计数器与对应的时间（纳秒），之后的时间由计数器的增量换算
+static BASE_COUNTER: AtomicU64 = AtomicU64::new(0);
+static BASE_NANOS: AtomicU64 = AtomicU64::new(0);
+// 已返回的最大时间（纳秒），保证时间不回退
+static LAST_NANOS: AtomicU64 = AtomicU64::new(0);
+
+/// 校准TSC所用的时间（毫秒）
+const CALIBRATION_MS: u64 = 10;
+/// 校准时等待计数结束的最大轮询次数，超过时认为计时器不可用
+///
+/// 校准在关中断下进行，正常情况下10毫秒内的轮询远少于此值
+const MAX_POLLS: u64 = 1_000_000;
+
+/// 选择时钟源
+///
+/// CPU支持恒定TSC且校准成功时使用TSC，否则依次尝试HPET与计时器中断
+///
+/// 必须在ACPI与内存初始化之后调用
+pub fn init() {
+    let hpet = HPET.init();
+    let tsc_hz = if hpet {
+        calibrate_tsc_by_hpet()
+    } else {
+        calibrate_tsc_by_pit()
+    };
+    if let Some(hz) = tsc_hz {
+        TSC.hz.store(hz, Ordering::Relaxed);
+    }
+
+    let invariant = invariant_tsc();
+    let source = match (tsc_hz, invariant, hpet) {
+        (Some(_), true, _) => SOURCE_TSC,
+        (_, _, true) => SOURCE_HPET,
+        _ => SOURCE_TICK,
+    };
+    if !invariant {
+        klog!(warn, "time", "invariant tsc not supported");
+    }
+    switch(source);
+
+    let source = SOURCES[source];
+    klog!(
+        info,
+        "time",
+        "clocksource {}, {} Hz, tsc {} Hz",
+        source.name(),
+        source.frequency(),
+        tsc_hz.unwrap_or(0)
+    );
+}
+
+/// 切换到指定时钟源，新时钟源的时间从当前时间继续
+fn switch(source: usize) {
+    let _guard = IrqGuard::cli();
+    let now = now_ns();
+    BASE_COUNTER.store(SOURCES[source].read(), Ordering::Relaxed);
+    BASE_NANOS.store(now, Ordering::Relaxed);
+    CURRENT.store(source, Ordering::Release);
+}
+
+/// 当前使用的时钟源
+pub fn current() -> &'static dyn ClockSource {
+    SOURCES[CURRENT.load(Ordering::Acquire)]
+}
+
+/// 当前时钟源为TSC时，返回TSC的频率（Hz）、切换时钟源时的计数器及对应的时间（纳秒）
+///
+/// 用户态可据此由TSC计算时间而不必进入内核，见 [crate::multitask::vdso]
+pub fn tsc_base() -> Option<(u64, u64, u64)> {
+    if CURRENT.load(Ordering::Acquire) != SOURCE_TSC {
+        return None;
+    }
+    Some((
+        TSC.frequency(),
+        BASE_COUNTER.load(Ordering::Relaxed),
+        BASE_NANOS.load(Ordering::Relaxed),
+    ))
+}
+
+/// 系统启动后经过的时间（纳秒），保证单调不减
+pub fn now_ns() -> u64 {
+    let source = current();
+    let delta = source
+        .read()
+        .saturating_sub(BASE_COUNTER.load(Ordering::Relaxed));
+    let nanos = (delta as u128 * 1_000_000_000 / source.frequency() as u128) as u64;
+    let now = BASE_NANOS.load(Ordering::Relaxed) + nanos;
+    let last = LAST_NANOS.fetch_max(now, Ordering::Relaxed);
+    now.max(last)
+}
+
+/// CPU是否支持恒定TSC，即TSC的频率不随CPU的频率与电源状态改变
+fn invariant_tsc() -> bool {
+    // cpuid 0x80000007 edx第8位：恒定TSC
+    __cpuid(0x8000_0000).eax >= 0x8000_0007 && __cpuid(0x8000_0007).edx & (1 << 8) != 0
+}
+
+/// 以HPET校准TSC的频率
+fn calibrate_tsc_by_hpet() -> Option<u64> {
+    let _guard = IrqGuard::cli();
+    let ticks = HPET.frequency() * CALIBRATION_MS / 1000;
+    let start_counter = HPET.read();
+    let start_tsc = rdtsc();
+    let mut polls = 0;
+    while HPET.read().wrapping_sub(start_counter) < ticks {
+        polls += 1;
+        if polls > MAX_POLLS {
+            return None;
+        }
+        spin_loop();
+    }
+    let elapsed = rdtsc().wrapping_sub(start_tsc);
+    Some(elapsed * 1000 / CALIBRATION_MS).filter(|&hz| hz > 0)
+}
+
+/// 以PIT通道2校准TSC的频率
+///
+/// 通道2的门控与输出位于端口0x61，计数结束时输出变为高电平。
+/// 通道2同时连接PC扬声器，校准期间关闭扬声器输出
+fn calibrate_tsc_by_pit() -> Option<u64> {
+    // PIT的输入频率
+    const PIT_FREQUENCY: u64 = 1193182;
+    // 端口0x61：第0位为通道2门控，第1位为扬声器输出使能，第5位为通道2的输出
+    const GATE: u8 = 1 << 0;
+    const SPEAKER: u8 = 1 << 1;
+    const OUTPUT: u8 = 1 << 5;
+
+    let _guard = IrqGuard::cli();
+    let count = PIT_FREQUENCY * CALIBRATION_MS / 1000;
+    let control = unsafe { inb(0x61) };
+    unsafe {
+        // 关闭门控与扬声器，设置通道2为模式0（计数结束时输出高电平），先写低字节再写高字节
+        outb(0x61, control & !(GATE | SPEAKER));
+        outb(0x43, 0b1011_0000);
+        outb(0x42, (count & 0xFF) as u8);
+        outb(0x42, (count >> 8) as u8);
+        // 打开门控，开始计数
+        outb(0x61, (control & !SPEAKER) | GATE);
+    }
+    let start = rdtsc();
+    let mut polls = 0;
+    while unsafe { inb(0x61) } & OUTPUT == 0 {
+        polls += 1;
+        if polls > MAX_POLLS {
+            break;
+        }
+        spin_loop();
+    }
+    let elapsed = rdtsc().wrapping_sub(start);
+    unsafe {
+        outb(0x61, control);
+    }
+    if polls > MAX_POLLS {
+        return None;
+    }
+    Some(elapsed * 1000 / CALIBRATION_MS).filter(|&hz| hz > 0)
+}
+
+unsafe fn outb(port: u16, value: u8) {
+    unsafe {
+        asm!(
+            "out dx, al",
+            in("dx") port,
+            in("al") value,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+}
+
+unsafe fn inb(port: u16) -> u8 {
+    let value: u8;
+    unsafe {
+        asm!(
+            "in al, dx",
+            in("dx") port,
+            out("al") value,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_now_ns_monotonic() {
+        let mut last = now_ns();
+        for _ in 0..1000 {
+            let now = now_ns();
+            assert!(now >= last);
+            last = now;
+        }
+        assert!(current().frequency() > 0);
+    }
+}
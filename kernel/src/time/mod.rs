@@ -0,0 +1,104 @@
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use cos_sys::time::DateTime;
+
+use crate::{cmdline, io, klog};
+
+pub mod clocksource;
+
+// 单调时钟为0时对应的UNIX时间（纳秒）
+static BOOT_TIME: AtomicU64 = AtomicU64::new(0);
+
+/// 选择时钟源，并读取RTC初始化系统时间
+///
+/// 必须在ACPI与内存初始化之后调用
+pub fn init() {
+    clocksource::init();
+
+    let Some(rtc) = io::rtc::read() else {
+        klog!(warn, "time", "invalid rtc time, starting from unix epoch");
+        return;
+    };
+    let rtc_nanos = rtc.to_unix().max(0) as u64 * 1_000_000_000;
+    let boot_time = rtc_nanos.saturating_sub(monotonic().as_nanos() as u64);
+    BOOT_TIME.store(boot_time, Ordering::Relaxed);
+
+    klog!(info, "time", "rtc time {rtc} UTC");
+}
+
+/// 单调时钟，即系统启动后经过的时间，精度取决于时钟源，见 [clocksource]
+pub fn monotonic() -> Duration {
+    Duration::from_nanos(clocksource::now_ns())
+}
+
+/// 系统时间，即自UNIX纪元起经过的时间
+pub fn realtime() -> Duration {
+    boot_time() + monotonic()
+}
+
+/// 单调时钟为0时对应的系统时间
+pub fn boot_time() -> Duration {
+    Duration::from_nanos(BOOT_TIME.load(Ordering::Relaxed))
+}
+
+/// 本地时区相对UTC的偏移（秒），由启动选项`tz`设置
+pub fn utc_offset() -> i64 {
+    cmdline::options().utc_offset as i64
+}
+
+/// 当前的本地日期与时间
+pub fn local_time() -> DateTime {
+    let now = realtime();
+    DateTime::from_unix(now.as_secs() as i64 + utc_offset(), now.subsec_nanos())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_monotonic() {
+        let first = monotonic();
+        let second = monotonic();
+        assert!(second >= first);
+        assert!(realtime() >= first);
+    }
+
+    #[test_case]
+    fn test_date_time_round_trip() {
+        // 纪元前后、闰日、世纪年与400年周期的边界
+        for seconds in [
+            -1,
+            0,
+            951_782_400,   // 2000-02-29
+            4_107_542_399, // 2100-02-28 23:59:59
+            4_107_542_400, // 2100-03-01
+            1_709_251_199, // 2024-02-29 23:59:59
+        ] {
+            let time = DateTime::from_unix(seconds, 0);
+            assert_eq!(time.to_unix(), seconds);
+        }
+        let mut seconds = -10 * 365 * 86400;
+        while seconds < 200 * 365 * 86400 {
+            assert_eq!(DateTime::from_unix(seconds, 0).to_unix(), seconds);
+            seconds += 86400 * 7 + 3661;
+        }
+    }
+
+    #[test_case]
+    fn test_date_time_leap_year() {
+        let date = |seconds| {
+            let time = DateTime::from_unix(seconds, 0);
+            (time.year, time.month, time.day)
+        };
+        assert_eq!(date(951_782_400), (2000, 2, 29));
+        assert_eq!(date(1_709_164_800), (2024, 2, 29));
+        // 2100年不是闰年，2月28日之后为3月1日
+        assert_eq!(date(4_107_456_000), (2100, 2, 28));
+        assert_eq!(date(4_107_542_400), (2100, 3, 1));
+        assert_eq!(date(-1), (1969, 12, 31));
+    }
+}
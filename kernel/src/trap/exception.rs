@@ -0,0 +1,407 @@
+use core::fmt::{self, Display, Write};
+
+use crate::{
+    klog,
+    sync::{percpu, spin::SpinLock},
+    trap::idt::{StackFrame, StackFrameWithErrorCode},
+};
+
+/// CPU异常的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExceptionKind {
+    DivideError,
+    Overflow,
+    BoundRangeExceeded,
+    InvalidOpcode,
+    DeviceNotAvailable,
+    DoubleFault,
+    InvalidTss,
+    SegmentNotPresent,
+    StackSegmentFault,
+    GeneralProtection,
+    PageFault,
+    FloatingPointError,
+    AlignmentCheck,
+    MachineCheck,
+    SimdFloatingPoint,
+    Virtualization,
+    ControlProtection,
+}
+
+impl ExceptionKind {
+    /// 异常的助记符，如`#PF`
+    pub const fn mnemonic(self) -> &'static str {
+        match self {
+            Self::DivideError => "#DE",
+            Self::Overflow => "#OF",
+            Self::BoundRangeExceeded => "#BR",
+            Self::InvalidOpcode => "#UD",
+            Self::DeviceNotAvailable => "#NM",
+            Self::DoubleFault => "#DF",
+            Self::InvalidTss => "#TS",
+            Self::SegmentNotPresent => "#NP",
+            Self::StackSegmentFault => "#SS",
+            Self::GeneralProtection => "#GP",
+            Self::PageFault => "#PF",
+            Self::FloatingPointError => "#MF",
+            Self::AlignmentCheck => "#AC",
+            Self::MachineCheck => "#MC",
+            Self::SimdFloatingPoint => "#XM",
+            Self::Virtualization => "#VE",
+            Self::ControlProtection => "#CP",
+        }
+    }
+
+    /// 异常的名称
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::DivideError => "Divide Error",
+            Self::Overflow => "Overflow",
+            Self::BoundRangeExceeded => "BOUND Range Exceeded",
+            Self::InvalidOpcode => "Invalid Opcode",
+            Self::DeviceNotAvailable => "Device Not Available",
+            Self::DoubleFault => "Double Fault",
+            Self::InvalidTss => "Invalid TSS",
+            Self::SegmentNotPresent => "Segment Not Present",
+            Self::StackSegmentFault => "Stack Segment Fault",
+            Self::GeneralProtection => "General Protection",
+            Self::PageFault => "Page Fault",
+            Self::FloatingPointError => "x87 Floating-Point Error",
+            Self::AlignmentCheck => "Alignment Check",
+            Self::MachineCheck => "Machine Check",
+            Self::SimdFloatingPoint => "SIMD Floating-Point Exception",
+            Self::Virtualization => "Virtualization Exception",
+            Self::ControlProtection => "Control Protection Exception",
+        }
+    }
+
+    /// 错误码是否为段选择子格式
+    const fn selector_error_code(self) -> bool {
+        matches!(
+            self,
+            Self::InvalidTss
+                | Self::SegmentNotPresent
+                | Self::StackSegmentFault
+                | Self::GeneralProtection
+        )
+    }
+}
+
+/// 一次CPU异常的记录，包含错误码、缺页地址与被打断时的寄存器
+#[derive(Debug, Clone, Copy)]
+pub struct ExceptionRecord {
+    pub kind: ExceptionKind,
+    /// 异常的错误码，不压入错误码的异常为None
+    pub error_code: Option<u64>,
+    /// 缺页地址（CR2），仅缺页异常有效
+    pub fault_address: Option<u64>,
+    /// 被打断时的寄存器
+    pub registers: StackFrame,
+    /// 异常发生时的线程ID，per-cpu结构尚未初始化时为None
+    pub thread_id: Option<u64>,
+}
+
+impl ExceptionRecord {
+    pub fn new(kind: ExceptionKind, stack: &StackFrame) -> Self {
+        Self {
+            kind,
+            error_code: None,
+            fault_address: None,
+            registers: *stack,
+            thread_id: percpu::initialized().then(percpu::get_current_thread_id),
+        }
+    }
+
+    pub(super) fn with_error_code(kind: ExceptionKind, stack: &StackFrameWithErrorCode) -> Self {
+        let registers = StackFrame {
+            r15: stack.r15,
+            r14: stack.r14,
+            r13: stack.r13,
+            r12: stack.r12,
+            r11: stack.r11,
+            r10: stack.r10,
+            r9: stack.r9,
+            r8: stack.r8,
+            rbp: stack.rbp,
+            rdi: stack.rdi,
+            rsi: stack.rsi,
+            rdx: stack.rdx,
+            rcx: stack.rcx,
+            rbx: stack.rbx,
+            rax: stack.rax,
+            rip: stack.rip,
+            cs: stack.cs,
+            rflags: stack.rflags,
+            rsp: stack.rsp,
+            ss: stack.ss,
+        };
+        Self {
+            error_code: Some(stack.error_code),
+            ..Self::new(kind, &registers)
+        }
+    }
+
+    pub fn with_fault_address(self, fault_address: u64) -> Self {
+        Self {
+            fault_address: Some(fault_address),
+            ..self
+        }
+    }
+
+    /// 异常是否发生在用户态
+    pub fn from_user(&self) -> bool {
+        (self.registers.cs & 0b11) == 0b11
+    }
+
+    /// 错误码的解读，如`0x2 (not-present, write, supervisor)`
+    pub fn describe_error_code(&self) -> Option<ErrorCode> {
+        self.error_code.map(|code| ErrorCode {
+            kind: self.kind,
+            code,
+        })
+    }
+
+    /// 以结构化的形式输出到内核日志
+    ///
+    /// 内核态的异常以error级别输出，用户态的异常会结束线程，以warn级别输出
+    pub fn log(&self) {
+        let user = self.from_user();
+        let origin = if user { "user" } else { "kernel" };
+        let thread = ThreadId(self.thread_id);
+        let regs = &self.registers;
+        macro_rules! log {
+            ($($arg:tt)*) => {
+                if user {
+                    klog!(warn, "trap", $($arg)*);
+                } else {
+                    klog!(error, "trap", $($arg)*);
+                }
+            };
+        }
+        log!(
+            "{} {} in {origin} mode, thread {thread}, $rip=0x{:x}",
+            self.kind.mnemonic(),
+            self.kind.name(),
+            regs.rip
+        );
+        if let Some(error_code) = self.describe_error_code() {
+            log!("  error code {error_code}");
+        }
+        if let Some(fault_address) = self.fault_address {
+            log!("  cr2 0x{fault_address:x}");
+        }
+        log!(
+            "  rax {:016x} rbx {:016x} rcx {:016x} rdx {:016x}",
+            regs.rax,
+            regs.rbx,
+            regs.rcx,
+            regs.rdx
+        );
+        log!(
+            "  rsi {:016x} rdi {:016x} rbp {:016x} rsp {:016x}",
+            regs.rsi,
+            regs.rdi,
+            regs.rbp,
+            regs.rsp
+        );
+        log!(
+            "  r8  {:016x} r9  {:016x} r10 {:016x} r11 {:016x}",
+            regs.r8,
+            regs.r9,
+            regs.r10,
+            regs.r11
+        );
+        log!(
+            "  r12 {:016x} r13 {:016x} r14 {:016x} r15 {:016x}",
+            regs.r12,
+            regs.r13,
+            regs.r14,
+            regs.r15
+        );
+        log!(
+            "  cs {:x} ss {:x} rflags {:016x}",
+            regs.cs,
+            regs.ss,
+            regs.rflags
+        );
+    }
+
+    /// 以精简的形式写入蓝屏，屏幕空间有限，仅包含最关键的寄存器
+    ///
+    /// 不进行堆分配
+    pub fn write_summary<W: Write>(&self, writer: &mut W) -> fmt::Result {
+        let origin = if self.from_user() { "user" } else { "kernel" };
+        let regs = &self.registers;
+        write!(
+            writer,
+            "*** EXCEPTION: {} {} in {origin} mode, thread {}",
+            self.kind.mnemonic(),
+            self.kind.name(),
+            ThreadId(self.thread_id)
+        )?;
+        if let Some(error_code) = self.describe_error_code() {
+            write!(writer, ", error {error_code}")?;
+        }
+        writeln!(writer)?;
+        if let Some(fault_address) = self.fault_address {
+            writeln!(writer, "*** CR2: {fault_address:016x}")?;
+        }
+        writeln!(
+            writer,
+            "*** RIP={:016x} RSP={:016x} RBP={:016x}",
+            regs.rip, regs.rsp, regs.rbp
+        )?;
+        writeln!(
+            writer,
+            "*** RAX={:016x} RBX={:016x} RCX={:016x}",
+            regs.rax, regs.rbx, regs.rcx
+        )?;
+        writeln!(
+            writer,
+            "*** RDX={:016x} RSI={:016x} RDI={:016x}",
+            regs.rdx, regs.rsi, regs.rdi
+        )
+    }
+
+    /// 写入异常信息与全部寄存器，用于崩溃转储
+    ///
+    /// 不进行堆分配
+    pub fn write_details<W: Write>(&self, writer: &mut W) -> fmt::Result {
+        let regs = &self.registers;
+        self.write_summary(writer)?;
+        writeln!(
+            writer,
+            "*** R8={:016x} R9={:016x} R10={:016x} R11={:016x}",
+            regs.r8, regs.r9, regs.r10, regs.r11
+        )?;
+        writeln!(
+            writer,
+            "*** R12={:016x} R13={:016x} R14={:016x} R15={:016x}",
+            regs.r12, regs.r13, regs.r14, regs.r15
+        )?;
+        writeln!(
+            writer,
+            "*** CS={:x} SS={:x} RFLAGS={:016x}",
+            regs.cs, regs.ss, regs.rflags
+        )
+    }
+}
+
+/// 解读后的错误码，格式见 [ExceptionRecord::describe_error_code]
+pub struct ErrorCode {
+    kind: ExceptionKind,
+    code: u64,
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let code = self.code;
+        write!(f, "0x{code:x}")?;
+        if self.kind == ExceptionKind::PageFault {
+            // 第0位：页存在（保护错误），否则为缺页
+            // 第1位：写入；第2位：用户态；第3位：保留位被置位；第4位：取指令；第5位：保护键；第6位：影子栈
+            f.write_str(if code & (1 << 0) != 0 {
+                " (protection"
+            } else {
+                " (not-present"
+            })?;
+            f.write_str(if code & (1 << 1) != 0 {
+                ", write"
+            } else {
+                ", read"
+            })?;
+            f.write_str(if code & (1 << 2) != 0 {
+                ", user"
+            } else {
+                ", supervisor"
+            })?;
+            for (bit, name) in [
+                (3, ", reserved-bit"),
+                (4, ", instruction-fetch"),
+                (5, ", protection-key"),
+                (6, ", shadow-stack"),
+            ] {
+                if code & (1 << bit) != 0 {
+                    f.write_str(name)?;
+                }
+            }
+            f.write_str(")")
+        } else if self.kind.selector_error_code() && code != 0 {
+            // 第0位：由外部事件引发；第1位：选择子指向IDT；第2位：选择子指向LDT，否则为GDT；第3~15位：选择子索引
+            let table = if code & (1 << 1) != 0 {
+                "idt"
+            } else if code & (1 << 2) != 0 {
+                "ldt"
+            } else {
+                "gdt"
+            };
+            write!(f, " ({table} index {}", (code >> 3) & 0x1FFF)?;
+            if code & (1 << 0) != 0 {
+                f.write_str(", external")?;
+            }
+            f.write_str(")")
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// 线程ID的显示，未知时显示为`?`
+struct ThreadId(Option<u64>);
+
+impl Display for ThreadId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(thread_id) => write!(f, "{thread_id}"),
+            None => f.write_str("?"),
+        }
+    }
+}
+
+/// 最近一次导致panic的内核态异常，蓝屏时展示
+static LAST_KERNEL_EXCEPTION: SpinLock<Option<ExceptionRecord>> = SpinLock::new(None);
+
+/// 记录即将导致panic的内核态异常
+pub fn record_kernel_exception(record: &ExceptionRecord) {
+    *LAST_KERNEL_EXCEPTION.lock_irqsave() = Some(*record);
+}
+
+/// 最近一次导致panic的内核态异常
+///
+/// 供panic处理函数使用，锁被占用时返回None而不是等待
+pub fn last_kernel_exception() -> Option<ExceptionRecord> {
+    LAST_KERNEL_EXCEPTION.try_lock().and_then(|record| *record)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+
+    use super::*;
+
+    fn error_code(kind: ExceptionKind, code: u64) -> String {
+        ErrorCode { kind, code }.to_string()
+    }
+
+    #[test_case]
+    fn test_describe_error_code() {
+        assert_eq!(
+            error_code(ExceptionKind::PageFault, 0x2),
+            "0x2 (not-present, write, supervisor)"
+        );
+        assert_eq!(
+            error_code(ExceptionKind::PageFault, 0x15),
+            "0x15 (protection, read, user, instruction-fetch)"
+        );
+        assert_eq!(
+            error_code(ExceptionKind::GeneralProtection, 0x1A),
+            "0x1a (idt index 3)"
+        );
+        assert_eq!(
+            error_code(ExceptionKind::SegmentNotPresent, 0x29),
+            "0x29 (gdt index 5, external)"
+        );
+        assert_eq!(error_code(ExceptionKind::GeneralProtection, 0), "0x0");
+        assert_eq!(error_code(ExceptionKind::DoubleFault, 0), "0x0");
+    }
+}
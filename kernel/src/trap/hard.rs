@@ -1,12 +1,13 @@
 use core::{
     arch::asm,
     num::NonZeroU16,
-    sync::atomic::{AtomicU32, Ordering},
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
 };
 
 use crate::{
     trap::idt::{Idt, StackFrame},
-    interrupt_handler, io, kprintln, multitask,
+    cmdline, interrupt_handler, io, kprintln, multitask, random,
+    sync::int::{IrqContext, IrqGuard},
 };
 
 // 定时器 PIT Channel 0
@@ -54,7 +55,7 @@ pub const INDEX_FPU: usize = Idt::INDEX_USER_DEFINED + 13;
 // 主IDE通道
 const IRQ_IDE1: u8 = 14;
 pub const INDEX_IDE1: usize = Idt::INDEX_USER_DEFINED + 14;
-// 主IDE通道
+// 从IDE通道
 const IRQ_IDE2: u8 = 15;
 pub const INDEX_IDE2: usize = Idt::INDEX_USER_DEFINED + 15;
 
@@ -138,6 +139,9 @@ pub unsafe fn init() {
         );
     }
 
+    // 按启动选项设置计时器频率
+    init_timer();
+
     // 打开中断
     // 先暂时只开时钟中断和键盘中断，等后续再开全部中断
     unsafe {
@@ -156,6 +160,34 @@ pub unsafe fn init() {
     }
 }
 
+/// 打开指定IRQ的中断屏蔽
+///
+/// 中断处理函数必须已在IDT中注册
+pub fn unmask_irq(irq: u8) {
+    assert!(irq < 16);
+    let _guard = IrqGuard::cli();
+    let (port, bit) = if irq < 8 {
+        (PIC1_DATA, irq)
+    } else {
+        (PIC2_DATA, irq - 8)
+    };
+    let mask: u8;
+    unsafe {
+        asm!(
+            "in al, dx",
+            in("dx") port,
+            out("al") mask,
+            options(nostack, preserves_flags)
+        );
+        asm!(
+            "out dx, al",
+            in("dx") port,
+            in("al") mask & !(1 << bit),
+            options(nostack, preserves_flags)
+        );
+    }
+}
+
 // 发送EOI（End of Interrupt）
 unsafe fn send_eoi(irq: u8) {
     // 如果对应从片，则额外向从片发送
@@ -182,6 +214,24 @@ unsafe fn send_eoi(irq: u8) {
 
 // 硬件计时器的频率
 const TIMER_FREQUENCY: u32 = 1193182;
+// 启动选项允许设置的最高中断频率
+const MAX_TIMER_HZ: u32 = 1000;
+
+/// 计时器中断的间隔（微秒），默认与硬件计时器复位后的频率一致
+static TIMER_INTERVAL: AtomicU64 = AtomicU64::new(1_000_000 * 65535 / TIMER_FREQUENCY as u64);
+
+/// 按启动选项设置计时器中断频率，未指定时保持默认频率
+fn init_timer() {
+    let Some(hz) = cmdline::options().timer_hz else {
+        return;
+    };
+    let divisor = (TIMER_FREQUENCY / hz.min(MAX_TIMER_HZ)).min(u16::MAX as u32) as u16;
+    set_timer_interval(NonZeroU16::new(divisor).unwrap());
+    TIMER_INTERVAL.store(
+        1_000_000 * divisor as u64 / TIMER_FREQUENCY as u64,
+        Ordering::Relaxed,
+    );
+}
 
 /// 设置硬件计时器的中断频率
 ///
@@ -211,17 +261,25 @@ fn set_timer_interval(n: NonZeroU16) {
 
 interrupt_handler! {
     fn timer_irq(stack: &mut StackFrame) {
-        const ELAPSED: u64 = 1_000_000 * 65535 / TIMER_FREQUENCY as u64;
+        let irq_context = IrqContext::enter();
+        random::add_interrupt_randomness(IRQ_TIMER);
+        let elapsed = TIMER_INTERVAL.load(Ordering::Relaxed);
 
-        multitask::async_task::tick(ELAPSED);
+        multitask::async_task::tick(elapsed);
+        multitask::thread::account_cpu_time(elapsed);
+        multitask::watchdog::tick(stack);
+        multitask::vdso::update();
 
         // FIXME: 部分场景下硬盘会丢中断，我们在计时器中断这里补充调用
-        // io::disk::ata_lba::ata_irq();
+        // io::disk::ata_lba::ata_irq(0);
 
         unsafe {
             send_eoi(IRQ_TIMER);
         }
 
+        // 切换线程前离开中断上下文
+        drop(irq_context);
+
         // 抢占调度
         // TODO: 这里应该计算当前线程剩余时间片，而不是每次计时器中断都进行切换
         multitask::thread::thread_yield(false);
@@ -230,6 +288,8 @@ interrupt_handler! {
 
 interrupt_handler! {
     fn keyboard_irq(stack: &mut StackFrame) {
+        let _irq_context = IrqContext::enter();
+        random::add_interrupt_randomness(IRQ_KEYBOARD);
         // 获取键盘扫描码
         let scan_code: u8;
         unsafe {
@@ -240,7 +300,10 @@ interrupt_handler! {
             );
         }
 
-        io::keyboard::handle_keyboard_scan(scan_code);
+        // 扫描码的处理推迟到工作队列中，队列已满时丢弃按键
+        _ = multitask::workqueue::enqueue(multitask::workqueue::Priority::High, move || {
+            io::keyboard::handle_keyboard_scan(scan_code)
+        });
 
         unsafe {
             send_eoi(IRQ_KEYBOARD);
@@ -248,11 +311,87 @@ interrupt_handler! {
     }
 }
 
+interrupt_handler! {
+    fn mouse_irq(stack: &mut StackFrame) {
+        let _irq_context = IrqContext::enter();
+        random::add_interrupt_randomness(IRQ_MOUSE);
+        // 获取鼠标数据包的一个字节
+        let byte: u8;
+        unsafe {
+            asm!(
+                "in al, 0x60",
+                out("al") byte,
+                options(nostack, preserves_flags)
+            );
+        }
+
+        // 与键盘相同，数据包的处理推迟到工作队列中，队列已满时丢弃该字节
+        _ = multitask::workqueue::enqueue(multitask::workqueue::Priority::High, move || {
+            io::mouse::handle_mouse_byte(byte)
+        });
+
+        unsafe {
+            send_eoi(IRQ_MOUSE);
+        }
+    }
+}
+
 interrupt_handler! {
     fn primary_ide_irq(stack: &mut StackFrame) {
-        io::disk::ata_lba::ata_irq();
+        let _irq_context = IrqContext::enter();
+        random::add_interrupt_randomness(IRQ_IDE1);
+        io::disk::ata_lba::ata_irq(0);
         unsafe {
             send_eoi(IRQ_IDE1);
         }
     }
 }
+
+interrupt_handler! {
+    fn secondary_ide_irq(stack: &mut StackFrame) {
+        let _irq_context = IrqContext::enter();
+        random::add_interrupt_randomness(IRQ_IDE2);
+        io::disk::ata_lba::ata_irq(1);
+        unsafe {
+            send_eoi(IRQ_IDE2);
+        }
+    }
+}
+
+// PCI设备的传统中断可能共享以下中断线，由各驱动检查是否为自身产生的中断
+
+interrupt_handler! {
+    fn acpi_irq(stack: &mut StackFrame) {
+        let _irq_context = IrqContext::enter();
+        random::add_interrupt_randomness(IRQ_ACPI);
+        io::disk::ahci::ahci_irq();
+        io::net::virtio_net::virtio_net_irq();
+        unsafe {
+            send_eoi(IRQ_ACPI);
+        }
+    }
+}
+
+interrupt_handler! {
+    fn pci1_irq(stack: &mut StackFrame) {
+        let _irq_context = IrqContext::enter();
+        random::add_interrupt_randomness(IRQ_PCI1);
+        io::disk::ahci::ahci_irq();
+        io::net::virtio_net::virtio_net_irq();
+        unsafe {
+            send_eoi(IRQ_PCI1);
+        }
+    }
+}
+
+interrupt_handler! {
+    fn pci2_irq(stack: &mut StackFrame) {
+        let _irq_context = IrqContext::enter();
+        random::add_interrupt_randomness(IRQ_PCI2);
+        io::disk::ahci::ahci_irq();
+        io::net::virtio_net::virtio_net_irq();
+        unsafe {
+            send_eoi(IRQ_PCI2);
+        }
+    }
+}
@@ -40,7 +40,7 @@ pub(super) struct IDTEntry {
 type IntFn = extern "C" fn();
 
 /// 中断调用上下文，通过修改对应值可以改变中断返回时对应寄存器的值
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[repr(C)]
 #[allow(unused)]
 pub struct StackFrame {
@@ -112,9 +112,20 @@ pub(super) unsafe fn init() {
         MAIN_CPU_IDT[Idt::INDEX_BREAKPOINT].disable_interrupt();
         MAIN_CPU_IDT[Idt::INDEX_BREAKPOINT].enable_user_trigger();
         MAIN_CPU_IDT[Idt::INDEX_BREAKPOINT].enable();
+        MAIN_CPU_IDT[Idt::INDEX_OVERFLOW].set_function_pointer(soft::overflow);
+        MAIN_CPU_IDT[Idt::INDEX_OVERFLOW].disable_interrupt();
+        MAIN_CPU_IDT[Idt::INDEX_OVERFLOW].enable();
+        MAIN_CPU_IDT[Idt::INDEX_BOUND_RANGE_EXEEDED]
+            .set_function_pointer(soft::bound_range_exceeded);
+        MAIN_CPU_IDT[Idt::INDEX_BOUND_RANGE_EXEEDED].disable_interrupt();
+        MAIN_CPU_IDT[Idt::INDEX_BOUND_RANGE_EXEEDED].enable();
         MAIN_CPU_IDT[Idt::INDEX_INVALID_OPCODE].set_function_pointer(soft::invalid_opcode);
         MAIN_CPU_IDT[Idt::INDEX_INVALID_OPCODE].disable_interrupt();
         MAIN_CPU_IDT[Idt::INDEX_INVALID_OPCODE].enable();
+        MAIN_CPU_IDT[Idt::INDEX_DEVICE_NOT_AVAILABLE]
+            .set_function_pointer(soft::device_not_available);
+        MAIN_CPU_IDT[Idt::INDEX_DEVICE_NOT_AVAILABLE].disable_interrupt();
+        MAIN_CPU_IDT[Idt::INDEX_DEVICE_NOT_AVAILABLE].enable();
         MAIN_CPU_IDT[Idt::INDEX_DOUBLE_FAULT].set_function_pointer(soft::double_fault);
         MAIN_CPU_IDT[Idt::INDEX_DOUBLE_FAULT].disable_interrupt();
         MAIN_CPU_IDT[Idt::INDEX_DOUBLE_FAULT].set_stack_index(tss::DF_IST);
@@ -136,9 +147,28 @@ pub(super) unsafe fn init() {
         MAIN_CPU_IDT[Idt::INDEX_PAGE_FAULT].set_function_pointer(soft::page_fault);
         MAIN_CPU_IDT[Idt::INDEX_PAGE_FAULT].disable_interrupt();
         MAIN_CPU_IDT[Idt::INDEX_PAGE_FAULT].enable();
+        MAIN_CPU_IDT[Idt::INDEX_FPU_FLOATING_POINT_ERROR]
+            .set_function_pointer(soft::x87_floating_point);
+        MAIN_CPU_IDT[Idt::INDEX_FPU_FLOATING_POINT_ERROR].disable_interrupt();
+        MAIN_CPU_IDT[Idt::INDEX_FPU_FLOATING_POINT_ERROR].enable();
         MAIN_CPU_IDT[Idt::INDEX_ALIGNMENT_CHECK].set_function_pointer(soft::alignment_check);
         MAIN_CPU_IDT[Idt::INDEX_ALIGNMENT_CHECK].disable_interrupt();
         MAIN_CPU_IDT[Idt::INDEX_ALIGNMENT_CHECK].enable();
+        MAIN_CPU_IDT[Idt::INDEX_MACHINE_CHECK].set_function_pointer(soft::machine_check);
+        MAIN_CPU_IDT[Idt::INDEX_MACHINE_CHECK].disable_interrupt();
+        MAIN_CPU_IDT[Idt::INDEX_MACHINE_CHECK].enable();
+        MAIN_CPU_IDT[Idt::INDEX_SIMD_FLOATING_POINT_EXCEPTION]
+            .set_function_pointer(soft::simd_floating_point);
+        MAIN_CPU_IDT[Idt::INDEX_SIMD_FLOATING_POINT_EXCEPTION].disable_interrupt();
+        MAIN_CPU_IDT[Idt::INDEX_SIMD_FLOATING_POINT_EXCEPTION].enable();
+        MAIN_CPU_IDT[Idt::INDEX_VIRTUALIZATION_EXCEPTION]
+            .set_function_pointer(soft::virtualization);
+        MAIN_CPU_IDT[Idt::INDEX_VIRTUALIZATION_EXCEPTION].disable_interrupt();
+        MAIN_CPU_IDT[Idt::INDEX_VIRTUALIZATION_EXCEPTION].enable();
+        MAIN_CPU_IDT[Idt::INDEX_CONTROL_PROTECTION_EXCEPTION]
+            .set_function_pointer(soft::control_protection);
+        MAIN_CPU_IDT[Idt::INDEX_CONTROL_PROTECTION_EXCEPTION].disable_interrupt();
+        MAIN_CPU_IDT[Idt::INDEX_CONTROL_PROTECTION_EXCEPTION].enable();
 
         MAIN_CPU_IDT[hard::INDEX_TIMER].set_function_pointer(hard::timer_irq);
         MAIN_CPU_IDT[hard::INDEX_TIMER].disable_interrupt();
@@ -146,9 +176,24 @@ pub(super) unsafe fn init() {
         MAIN_CPU_IDT[hard::INDEX_KEYBOARD].set_function_pointer(hard::keyboard_irq);
         MAIN_CPU_IDT[hard::INDEX_KEYBOARD].disable_interrupt();
         MAIN_CPU_IDT[hard::INDEX_KEYBOARD].enable();
+        MAIN_CPU_IDT[hard::INDEX_MOUSE].set_function_pointer(hard::mouse_irq);
+        MAIN_CPU_IDT[hard::INDEX_MOUSE].disable_interrupt();
+        MAIN_CPU_IDT[hard::INDEX_MOUSE].enable();
         MAIN_CPU_IDT[hard::INDEX_IDE1].set_function_pointer(hard::primary_ide_irq);
         MAIN_CPU_IDT[hard::INDEX_IDE1].disable_interrupt();
         MAIN_CPU_IDT[hard::INDEX_IDE1].enable();
+        MAIN_CPU_IDT[hard::INDEX_IDE2].set_function_pointer(hard::secondary_ide_irq);
+        MAIN_CPU_IDT[hard::INDEX_IDE2].disable_interrupt();
+        MAIN_CPU_IDT[hard::INDEX_IDE2].enable();
+        MAIN_CPU_IDT[hard::INDEX_ACPI].set_function_pointer(hard::acpi_irq);
+        MAIN_CPU_IDT[hard::INDEX_ACPI].disable_interrupt();
+        MAIN_CPU_IDT[hard::INDEX_ACPI].enable();
+        MAIN_CPU_IDT[hard::INDEX_PCI1].set_function_pointer(hard::pci1_irq);
+        MAIN_CPU_IDT[hard::INDEX_PCI1].disable_interrupt();
+        MAIN_CPU_IDT[hard::INDEX_PCI1].enable();
+        MAIN_CPU_IDT[hard::INDEX_PCI2].set_function_pointer(hard::pci2_irq);
+        MAIN_CPU_IDT[hard::INDEX_PCI2].disable_interrupt();
+        MAIN_CPU_IDT[hard::INDEX_PCI2].enable();
 
         (&*(&raw const MAIN_CPU_IDT)).load();
     }
@@ -448,6 +493,7 @@ macro_rules! interrupt_handler {
                     unsafe {
                         ::core::arch::asm!("swapgs");
                     }
+                    $crate::memory::protect::clear_user_access();
                 }
                 $name($stack);
                 if !kernel_gs {
@@ -509,6 +555,7 @@ macro_rules! interrupt_handler {
                     unsafe {
                         ::core::arch::asm!("swapgs");
                     }
+                    $crate::memory::protect::clear_user_access();
                 }
                 $name($stack);
                 if !kernel_gs {
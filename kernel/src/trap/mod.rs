@@ -1,3 +1,4 @@
+pub mod exception;
 #[allow(unused)]
 mod hard;
 pub mod idt;
@@ -5,6 +6,8 @@ mod soft;
 mod syscall;
 pub mod tss;
 
+pub use hard::unmask_irq;
+
 pub unsafe fn init() {
     unsafe {
         // 硬中断初始化（初始化PIC芯片）
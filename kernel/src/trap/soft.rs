@@ -1,14 +1,17 @@
 use core::arch::asm;
 
 use crate::{
-    interrupt_handler, kprintln, multitask, sync,
-    trap::idt::{StackFrame, StackFrameWithErrorCode},
+    cmdline::LogLevel,
+    interrupt_handler, klog, kprintln, memory, multitask, sync,
+    trap::{
+        exception::{self, ExceptionKind, ExceptionRecord},
+        idt::{StackFrame, StackFrameWithErrorCode},
+    },
 };
 
 interrupt_handler! {
     fn divide_by_zero(stack: &mut StackFrame) {
-        user_kill_self(stack.cs);
-        panic!("#DE triggered, $rip=0x{:x}", stack.rip);
+        fatal(ExceptionRecord::new(ExceptionKind::DivideError, stack));
     }
 }
 
@@ -18,53 +21,68 @@ interrupt_handler! {
     }
 }
 
+interrupt_handler! {
+    fn overflow(stack: &mut StackFrame) {
+        fatal(ExceptionRecord::new(ExceptionKind::Overflow, stack));
+    }
+}
+
+interrupt_handler! {
+    fn bound_range_exceeded(stack: &mut StackFrame) {
+        fatal(ExceptionRecord::new(ExceptionKind::BoundRangeExceeded, stack));
+    }
+}
+
 interrupt_handler! {
     fn invalid_opcode(stack: &mut StackFrame) {
-        user_kill_self(stack.cs);
-        panic!("#UD triggered, $rip=0x{:x}", stack.rip);
+        fatal(ExceptionRecord::new(ExceptionKind::InvalidOpcode, stack));
+    }
+}
+
+interrupt_handler! {
+    fn device_not_available(stack: &mut StackFrame) {
+        fatal(ExceptionRecord::new(ExceptionKind::DeviceNotAvailable, stack));
     }
 }
 
 interrupt_handler! {
     #[with_error_code]
     fn double_fault(stack: &mut StackFrameWithErrorCode) {
-        panic!("#DF triggered, stack: {stack:x?}");
+        fatal(ExceptionRecord::with_error_code(ExceptionKind::DoubleFault, stack));
     }
 }
 
 interrupt_handler! {
     #[with_error_code]
     fn invalid_tss(stack: &mut StackFrameWithErrorCode) {
-        panic!("#TS triggered, $rsp=0x{:x}", stack.rsp);
+        fatal(ExceptionRecord::with_error_code(ExceptionKind::InvalidTss, stack));
     }
 }
 
 interrupt_handler! {
     #[with_error_code]
     fn segment_not_present(stack: &mut StackFrameWithErrorCode) {
-        panic!("#NP triggered, $rsp=0x{:x}", stack.rsp);
+        fatal(ExceptionRecord::with_error_code(ExceptionKind::SegmentNotPresent, stack));
     }
 }
 
 interrupt_handler! {
     #[with_error_code]
     fn stack_segment_fault(stack: &mut StackFrameWithErrorCode) {
-        panic!("#SS triggered, $rsp=0x{:x}", stack.rsp);
+        fatal(ExceptionRecord::with_error_code(ExceptionKind::StackSegmentFault, stack));
     }
 }
 
 interrupt_handler! {
     #[with_error_code]
     fn general_protection(stack: &mut StackFrameWithErrorCode) {
-        user_kill_self(stack.cs);
-        panic!("#GP triggered, $rip=0x{:x}, error=0x{:x}", stack.rip, stack.error_code);
+        fatal(ExceptionRecord::with_error_code(ExceptionKind::GeneralProtection, stack));
     }
 }
 
 interrupt_handler! {
     #[with_error_code]
     fn page_fault(stack: &mut StackFrameWithErrorCode) {
-        user_kill_self(stack.cs);
         let fault_addr: usize;
         unsafe {
             asm!(
@@ -73,15 +91,104 @@ interrupt_handler! {
                 options(nostack, preserves_flags)
             );
         }
-        panic!("#PF triggered, $rip=0x{:x}, fault_addr=0x{fault_addr:x}, error=0x{:x}", stack.rip, stack.error_code);
+        let record = ExceptionRecord::with_error_code(ExceptionKind::PageFault, stack)
+            .with_fault_address(fault_addr as u64);
+        report_user_page_fault(stack, fault_addr as u64);
+        // 内核复制用户内存时发生的缺页，跳转到恢复位置并由复制函数返回错误
+        if !record.from_user() && let Some(fixup) = memory::user_copy::fault_fixup(stack.rip) {
+            stack.rip = fixup;
+            return;
+        }
+        fatal(record);
+    }
+}
+
+interrupt_handler! {
+    fn x87_floating_point(stack: &mut StackFrame) {
+        fatal(ExceptionRecord::new(ExceptionKind::FloatingPointError, stack));
     }
 }
 
 interrupt_handler! {
     #[with_error_code]
     fn alignment_check(stack: &mut StackFrameWithErrorCode) {
-        user_kill_self(stack.cs);
-        panic!("#AC triggered, $rip=0x{:x}", stack.rip);
+        fatal(ExceptionRecord::with_error_code(ExceptionKind::AlignmentCheck, stack));
+    }
+}
+
+interrupt_handler! {
+    fn machine_check(stack: &mut StackFrame) {
+        fatal(ExceptionRecord::new(ExceptionKind::MachineCheck, stack));
+    }
+}
+
+interrupt_handler! {
+    fn simd_floating_point(stack: &mut StackFrame) {
+        fatal(ExceptionRecord::new(ExceptionKind::SimdFloatingPoint, stack));
+    }
+}
+
+interrupt_handler! {
+    fn virtualization(stack: &mut StackFrame) {
+        fatal(ExceptionRecord::new(ExceptionKind::Virtualization, stack));
+    }
+}
+
+interrupt_handler! {
+    #[with_error_code]
+    fn control_protection(stack: &mut StackFrameWithErrorCode) {
+        fatal(ExceptionRecord::with_error_code(ExceptionKind::ControlProtection, stack));
+    }
+}
+
+/// 处理无法恢复的异常
+///
+/// 输出异常记录后，用户态的异常结束当前线程；内核态的异常被记录下来供蓝屏展示，随后panic
+fn fatal(record: ExceptionRecord) -> ! {
+    record.log();
+    user_kill_self(record.registers.cs);
+    exception::record_kernel_exception(&record);
+    panic!(
+        "{} triggered, $rip=0x{:x}",
+        record.kind.mnemonic(),
+        record.registers.rip
+    );
+}
+
+/// 如果缺页发生在用户态，输出缺页地址所在的内存区域，便于区分越界访问与权限错误
+fn report_user_page_fault(stack: &StackFrameWithErrorCode, fault_addr: u64) {
+    if (stack.cs & 0b11) != 0b11 || !klog::enabled(LogLevel::Warn, "trap") {
+        return;
+    }
+    let Some(process) = multitask::process::current_process() else {
+        return;
+    };
+    let process_id = multitask::process::process_id(&process);
+    match multitask::process::find_process_region(&process, fault_addr) {
+        Some(vma) => {
+            klog!(
+                warn,
+                "trap",
+                "process {process_id}: page fault at 0x{fault_addr:x} in {:?} region 0x{:x}-0x{:x} (writable={}, executable={}, {:?}), $rip=0x{:x}, error=0x{:x}",
+                vma.kind,
+                vma.start,
+                vma.end(),
+                vma.writable,
+                vma.executable,
+                vma.backing,
+                stack.rip,
+                stack.error_code
+            );
+        }
+        None => {
+            klog!(
+                warn,
+                "trap",
+                "process {process_id}: page fault at 0x{fault_addr:x} outside any region, $rip=0x{:x}, error=0x{:x}",
+                stack.rip,
+                stack.error_code
+            );
+        }
     }
 }
 
@@ -53,12 +53,12 @@ pub(super) unsafe fn init() {
         );
     }
 
-    // 设置syscall后立刻关中断
+    // 设置syscall后立刻关中断，并清除AC，避免用户态设置的AC使SMAP失效
     unsafe {
         asm!(
             "wrmsr",
             in("ecx") IA32_FMASK,
-            in("eax") (1 << 9),
+            in("eax") (1 << 9) | (1 << 18),
             in("edx") 0,
             options(nostack, preserves_flags)
         )
@@ -177,13 +177,18 @@ extern "C" fn syscall_entry() {
 ///
 /// 查询 (id, sub_id) 对应的系统调用编号。
 /// 如果存在，将地址写入ptr。如果不存在，将0写入ptr。
+/// 当前进程被跟踪时，写入的是记录调用过程的 [crate::syscall::trace::traced_syscall]
 ///
 /// Safety:
 /// 调用方保证ptr是一个可以写入的指针
 unsafe extern "C" fn query_syscall_handler(id: u64, ptr: *mut u64) {
-    let handler = crate::syscall::SYSCALL_HANDLER
-        .binary_search_by(|&(entry_id, _)| entry_id.cmp(&id))
-        .map_or(0, |index| crate::syscall::SYSCALL_HANDLER[index].1 as u64);
+    let handler = match crate::syscall::find_handler(id) {
+        Some(_) if crate::syscall::trace::enter(id) => {
+            crate::syscall::trace::traced_syscall as *const () as u64
+        }
+        Some(handler) => handler as *const () as u64,
+        None => 0,
+    };
     unsafe {
         *ptr = handler;
     }
@@ -1,10 +1,25 @@
-use core::ops::Deref;
+use core::{
+    future,
+    ops::Deref,
+    pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::Poll,
+};
 
-use alloc::{boxed::Box, sync::Weak};
+use alloc::{
+    boxed::Box,
+    sync::{Arc, Weak},
+};
 use async_locks::{mutex::Mutex, watch};
-use filesystem::fs::FileHandle;
+use filesystem::{
+    BoxFuture,
+    fs::{FileHandle, FileSystem, FileSystemError},
+};
+use netstack::socket::{TcpListener, TcpStream, UdpSocket};
 
 use crate::{
+    display::console,
+    io,
     multitask::{self, process::Process, thread::Thread},
     sync::spin::SpinLock,
 };
@@ -19,26 +34,199 @@ pub enum HandleObject {
         exit: watch::Subscriber<u64>,
     },
     File(FileHandleObject),
+    CharDevice(CharDeviceObject),
+    // 读取指定控制台的键盘输入
+    Keyboard(ConsoleObject),
+    UdpSocket(UdpSocket),
+    TcpStream(TcpStream),
+    TcpListener(TcpListener),
+}
+
+impl HandleObject {
+    /// 句柄类型，定义见[`cos_sys::debug`]
+    pub fn kind(&self) -> u64 {
+        match self {
+            HandleObject::Process { .. } => cos_sys::debug::HANDLE_KIND_PROCESS,
+            HandleObject::Thread { .. } => cos_sys::debug::HANDLE_KIND_THREAD,
+            HandleObject::File(_) => cos_sys::debug::HANDLE_KIND_FILE,
+            HandleObject::CharDevice(_) => cos_sys::debug::HANDLE_KIND_CHAR_DEVICE,
+            HandleObject::Keyboard(_) => cos_sys::debug::HANDLE_KIND_KEYBOARD,
+            HandleObject::UdpSocket(_) => cos_sys::debug::HANDLE_KIND_UDP_SOCKET,
+            HandleObject::TcpStream(_) => cos_sys::debug::HANDLE_KIND_TCP_STREAM,
+            HandleObject::TcpListener(_) => cos_sys::debug::HANDLE_KIND_TCP_LISTENER,
+        }
+    }
+
+    /// 句柄对应的控制台，键盘句柄与控制台字符设备具有控制台
+    pub fn console(&self) -> Option<&ConsoleObject> {
+        match self {
+            HandleObject::Keyboard(console) => Some(console),
+            HandleObject::CharDevice(device) => device.console.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// 等待句柄上的任意一个事件就绪，返回已就绪的事件
+    ///
+    /// 事件定义见[`cos_sys::ipc`]，如果句柄不支持指定的任何事件，将永远等待
+    pub async fn wait_ready(&self, events: u64) -> u64 {
+        use cos_sys::ipc::{POLL_EXIT, POLL_READ, POLL_RESIZE, POLL_WRITE};
+
+        match self {
+            HandleObject::Process { exit, .. } | HandleObject::Thread { exit, .. }
+                if events & POLL_EXIT != 0 =>
+            {
+                // 退出码被设置或对象被回收时均视为已退出
+                let mut exit = exit.clone();
+                _ = exit.wait().await;
+                POLL_EXIT
+            }
+            // 文件读写不会阻塞
+            HandleObject::File(_) if events & (POLL_READ | POLL_WRITE) != 0 => {
+                events & (POLL_READ | POLL_WRITE)
+            }
+            // 写入字符设备不会阻塞
+            HandleObject::CharDevice(device)
+                if events & (POLL_READ | POLL_WRITE | POLL_RESIZE) != 0 =>
+            {
+                if events & POLL_WRITE != 0 {
+                    return POLL_WRITE;
+                }
+                let readable = async {
+                    if events & POLL_READ == 0 {
+                        return future::pending().await;
+                    }
+                    device.wait_readable().await;
+                    POLL_READ
+                };
+                race(wait_resized(device.console.as_ref(), events), readable).await
+            }
+            HandleObject::Keyboard(console) if events & (POLL_READ | POLL_RESIZE) != 0 => {
+                let readable = async {
+                    if events & POLL_READ == 0
+                        || io::keyboard::wait_readable(console.console).await.is_err()
+                    {
+                        return future::pending().await;
+                    }
+                    POLL_READ
+                };
+                race(wait_resized(Some(console), events), readable).await
+            }
+            HandleObject::UdpSocket(socket) if events & (POLL_READ | POLL_WRITE) != 0 => {
+                // 发送不会长时间阻塞，视为总是可写
+                if events & POLL_WRITE != 0 {
+                    return POLL_WRITE;
+                }
+                socket.wait_readable().await;
+                POLL_READ
+            }
+            HandleObject::TcpStream(stream) if events & (POLL_READ | POLL_WRITE) != 0 => {
+                let (readable, writable) = stream
+                    .wait_ready(events & POLL_READ != 0, events & POLL_WRITE != 0)
+                    .await;
+                let mut ready = 0;
+                if readable {
+                    ready |= POLL_READ;
+                }
+                if writable {
+                    ready |= POLL_WRITE;
+                }
+                ready
+            }
+            // 有可接受的连接时视为可读
+            HandleObject::TcpListener(listener) if events & POLL_READ != 0 => {
+                listener.wait_acceptable().await;
+                POLL_READ
+            }
+            _ => future::pending().await,
+        }
+    }
+}
+
+/// 等待控制台大小变化，返回POLL_RESIZE，events不包含POLL_RESIZE或句柄没有控制台时永远等待
+async fn wait_resized(console: Option<&ConsoleObject>, events: u64) -> u64 {
+    use cos_sys::ipc::POLL_RESIZE;
+
+    match console {
+        Some(console) if events & POLL_RESIZE != 0 => {
+            console.wait_resized().await;
+            POLL_RESIZE
+        }
+        _ => future::pending().await,
+    }
+}
+
+/// 等待两个事件中先就绪的一个，同时就绪时返回first的结果
+async fn race(first: impl Future<Output = u64>, second: impl Future<Output = u64>) -> u64 {
+    let mut first = pin::pin!(first);
+    let mut second = pin::pin!(second);
+    future::poll_fn(|cx| match first.as_mut().poll(cx) {
+        Poll::Ready(ready) => Poll::Ready(ready),
+        Poll::Pending => second.as_mut().poll(cx),
+    })
+    .await
+}
+
+/// 控制台句柄的状态
+pub struct ConsoleObject {
+    console: usize,
+    // 句柄已报告的控制台大小变化次数，见 [console::resize_count]
+    resizes: AtomicU64,
+}
+
+impl ConsoleObject {
+    pub fn new(console: usize) -> Self {
+        Self {
+            console,
+            resizes: AtomicU64::new(console::resize_count(console)),
+        }
+    }
+
+    /// 句柄对应的控制台
+    pub fn console(&self) -> usize {
+        self.console
+    }
+
+    /// 等待控制台大小在句柄创建或上次报告之后发生变化
+    async fn wait_resized(&self) {
+        let seen = self.resizes.load(Ordering::Relaxed);
+        let count = console::wait_resize(self.console, seen).await;
+        self.resizes.store(count, Ordering::Relaxed);
+    }
 }
 
 pub struct FileHandleObject {
     handle: Option<Mutex<Box<dyn FileHandle>>>,
+    // 文件所在的文件系统，持有引用使文件关闭前文件系统无法被卸载
+    filesystem: Option<Arc<dyn FileSystem>>,
+    // 打开时具有的访问权限，为FilePermission::READ与WRITE的组合
+    access: u16,
 }
 
 impl FileHandleObject {
-    pub fn new(handle: Box<dyn FileHandle>) -> Self {
+    pub fn new(handle: Box<dyn FileHandle>, filesystem: Arc<dyn FileSystem>, access: u16) -> Self {
         Self {
             handle: Some(Mutex::new(handle)),
+            filesystem: Some(filesystem),
+            access,
         }
     }
+
+    /// 句柄是否允许access指定的访问
+    pub fn allows(&self, access: u16) -> bool {
+        self.access & access == access
+    }
 }
 
 impl Drop for FileHandleObject {
     fn drop(&mut self) {
         let handle = self.handle.take().unwrap();
+        let filesystem = self.filesystem.take();
         multitask::async_rt::spawn(async move {
             // ignore close error, including duplicate close
             let _ = handle.lock().await.close().await;
+            // 文件关闭后才释放文件系统
+            drop(filesystem);
         });
     }
 }
@@ -50,3 +238,60 @@ impl Deref for FileHandleObject {
         self.handle.as_ref().unwrap()
     }
 }
+
+/// 字符设备
+///
+/// 与文件不同，字符设备没有文件指针与大小，读写直接交给设备驱动。读取可能阻塞，直到设备有数据可读
+pub trait CharDevice: Send + Sync + 'static {
+    /// 读取数据，返回读取的字节数，返回0表示设备已没有更多数据
+    fn read<'fut>(&'fut self, buf: &'fut mut [u8])
+    -> BoxFuture<'fut, Result<u64, FileSystemError>>;
+
+    /// 写入全部数据
+    fn write<'fut>(&'fut self, buf: &'fut [u8]) -> BoxFuture<'fut, Result<(), FileSystemError>>;
+
+    /// 等待设备可读，不会消耗数据
+    fn wait_readable(&self) -> BoxFuture<'_, ()>;
+
+    /// 设备对应的控制台，控制台设备支持查询大小与等待大小变化
+    fn console(&self) -> Option<usize> {
+        None
+    }
+}
+
+pub struct CharDeviceObject {
+    device: Arc<dyn CharDevice>,
+    // 打开时具有的访问权限，为FilePermission::READ与WRITE的组合
+    access: u16,
+    // 控制台设备的状态
+    console: Option<ConsoleObject>,
+}
+
+impl CharDeviceObject {
+    pub fn new(device: Arc<dyn CharDevice>, access: u16) -> Self {
+        let console = device.console().map(ConsoleObject::new);
+        Self {
+            device,
+            access,
+            console,
+        }
+    }
+
+    /// 句柄是否允许access指定的访问
+    pub fn allows(&self, access: u16) -> bool {
+        self.access & access == access
+    }
+}
+
+impl Deref for CharDeviceObject {
+    type Target = dyn CharDevice;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.device
+    }
+}
+
+pub use kernel_core::handle::HandleError;
+
+/// 进程句柄表，见 [`kernel_core::handle::HandleTable`]
+pub type HandleTable = kernel_core::handle::HandleTable<HandleObject>;
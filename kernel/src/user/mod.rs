@@ -1 +1,3 @@
 pub mod handle;
+pub mod range;
+pub mod slice;
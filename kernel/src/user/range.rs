@@ -0,0 +1,116 @@
+use crate::{memory, multitask::process::ProcessMemoryError};
+
+/// 经过检查的用户态内存范围 [start, start + len)
+///
+/// 系统调用参数中的指针与长度由用户程序任意指定，直接相加可能溢出回绕，从而绕过用户空间的范围检查。
+/// 系统调用应当通过[`UserRange::new`]或[`UserRange::array`]检查参数，再以此创建[`UserSlice`]，
+/// 由后者检查范围内的每一个内存页是否已映射。
+///
+/// 长度为0的范围不检查起始地址，用户程序可以为空缓冲区传入任意指针
+///
+/// [`UserSlice`]: super::slice::UserSlice
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserRange {
+    start: u64,
+    len: usize,
+}
+
+impl UserRange {
+    /// 检查指针与长度，结束地址溢出或范围超出用户空间时返回[`ProcessMemoryError::SegmentationFault`]
+    pub fn new(ptr: u64, len: u64) -> Result<Self, ProcessMemoryError> {
+        let len = usize::try_from(len).map_err(|_| ProcessMemoryError::SegmentationFault)?;
+        if len != 0 && !memory::page::is_user_space_range(ptr, len) {
+            return Err(ProcessMemoryError::SegmentationFault);
+        }
+        Ok(Self { start: ptr, len })
+    }
+
+    /// 检查由count个T组成的数组，总长度溢出时同样返回[`ProcessMemoryError::SegmentationFault`]
+    pub fn array<T>(ptr: u64, count: u64) -> Result<Self, ProcessMemoryError> {
+        let len = count
+            .checked_mul(size_of::<T>() as u64)
+            .ok_or(ProcessMemoryError::SegmentationFault)?;
+        Self::new(ptr, len)
+    }
+
+    /// 将范围视为T的数组，返回下标为index的元素所在的范围
+    pub fn element<T>(&self, index: usize) -> Result<Self, ProcessMemoryError> {
+        let offset = index
+            .checked_mul(size_of::<T>())
+            .filter(|offset| offset + size_of::<T>() <= self.len)
+            .ok_or(ProcessMemoryError::SegmentationFault)?;
+        Ok(Self {
+            start: self.start + offset as u64,
+            len: size_of::<T>(),
+        })
+    }
+
+    /// 起始地址
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// 结束地址（不含），构造时已检查不会溢出
+    pub fn end(&self) -> u64 {
+        self.start + self.len as u64
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER_ADDR: u64 = 0x0000_0080_0000_0000;
+
+    #[test_case]
+    fn valid_range() {
+        let range = UserRange::new(USER_ADDR, 0x2000).unwrap();
+        assert_eq!(range.start(), USER_ADDR);
+        assert_eq!(range.end(), USER_ADDR + 0x2000);
+    }
+
+    #[test_case]
+    fn overflow_rejected() {
+        assert!(matches!(
+            UserRange::new(USER_ADDR, u64::MAX - USER_ADDR + 1),
+            Err(ProcessMemoryError::SegmentationFault)
+        ));
+        assert!(matches!(
+            UserRange::array::<u64>(USER_ADDR, u64::MAX / 4),
+            Err(ProcessMemoryError::SegmentationFault)
+        ));
+    }
+
+    #[test_case]
+    fn kernel_range_rejected() {
+        assert!(UserRange::new(0, 1).is_err());
+        assert!(UserRange::new(0xFFFF_FF80_0000_0000, 1).is_err());
+        // 跨越用户空间结束地址
+        assert!(UserRange::new(0xFFFF_FF7F_FFFF_F000, 0x2000).is_err());
+    }
+
+    #[test_case]
+    fn empty_range_accepts_any_pointer() {
+        assert!(UserRange::new(0, 0).unwrap().is_empty());
+        assert!(UserRange::new(u64::MAX, 0).is_ok());
+    }
+
+    #[test_case]
+    fn array_element() {
+        let range = UserRange::array::<u64>(USER_ADDR, 4).unwrap();
+        assert_eq!(range.len(), 32);
+        let element = range.element::<u64>(3).unwrap();
+        assert_eq!(element.start(), USER_ADDR + 24);
+        assert_eq!(element.len(), 8);
+        assert!(range.element::<u64>(4).is_err());
+        assert!(range.element::<u64>(usize::MAX).is_err());
+    }
+}
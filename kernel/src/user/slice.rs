@@ -0,0 +1,241 @@
+use core::{
+    cell::Cell,
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+    slice,
+};
+
+use alloc::{sync::Arc, vec::Vec};
+
+use crate::{
+    multitask::{
+        self,
+        process::{Process, ProcessMemoryError},
+    },
+    sync::spin::SpinLock,
+    user::range::UserRange,
+};
+
+/// 用户态内存区域
+///
+/// 创建时先通过[`UserRange`]检查地址范围不溢出且位于用户空间，
+/// 再检查整个区域的每一个内存页是否均已映射到进程页表中，且具有相应的访问权限，
+/// 检查通过后该区域将被固定，在UserSlice释放前，用户程序无法通过系统调用释放这些内存页。
+///
+/// 系统调用访问用户内存时，应当先创建UserSlice，而不是仅检查首尾地址。
+/// 区域长度受进程的系统调用缓冲区限制约束，复制到内核的数据计入进程的内核内存配额，在UserSlice释放时归还。
+pub struct UserSlice {
+    process: Arc<SpinLock<Process>>,
+    range: UserRange,
+    writable: bool,
+    // 已申请的内核内存配额
+    charged: Cell<usize>,
+}
+
+impl UserSlice {
+    /// 创建只读的用户内存区域
+    pub fn readable(
+        process: &Arc<SpinLock<Process>>,
+        addr: u64,
+        len: usize,
+    ) -> Result<Self, ProcessMemoryError> {
+        Self::from_range(process, UserRange::new(addr, len as u64)?, false)
+    }
+
+    /// 创建可读写的用户内存区域
+    pub fn writable(
+        process: &Arc<SpinLock<Process>>,
+        addr: u64,
+        len: usize,
+    ) -> Result<Self, ProcessMemoryError> {
+        Self::from_range(process, UserRange::new(addr, len as u64)?, true)
+    }
+
+    /// 创建只读的用户内存区域，大小与T一致
+    pub fn readable_of<T>(
+        process: &Arc<SpinLock<Process>>,
+        addr: u64,
+    ) -> Result<Self, ProcessMemoryError> {
+        Self::readable(process, addr, size_of::<T>())
+    }
+
+    /// 创建可读写的用户内存区域，大小与T一致
+    pub fn writable_of<T>(
+        process: &Arc<SpinLock<Process>>,
+        addr: u64,
+    ) -> Result<Self, ProcessMemoryError> {
+        Self::writable(process, addr, size_of::<T>())
+    }
+
+    /// 以已检查的地址范围创建用户内存区域
+    pub fn from_range(
+        process: &Arc<SpinLock<Process>>,
+        range: UserRange,
+        writable: bool,
+    ) -> Result<Self, ProcessMemoryError> {
+        multitask::process::pin_user_process_memory(process, range.start(), range.len(), writable)?;
+
+        Ok(Self {
+            process: process.clone(),
+            range,
+            writable,
+            charged: Cell::new(0),
+        })
+    }
+
+    /// 区域起始地址
+    pub fn addr(&self) -> u64 {
+        self.range.start()
+    }
+
+    /// 区域长度
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+
+    /// 从区域起始位置读取数据，dst长度不能超过区域长度
+    pub fn read(&self, dst: &mut [u8]) -> Result<(), ProcessMemoryError> {
+        assert!(dst.len() <= self.len());
+        unsafe {
+            multitask::process::read_user_process_memory(
+                &self.process,
+                self.range.start(),
+                dst.as_mut_ptr(),
+                dst.len(),
+            )
+        }
+    }
+
+    /// 读取整个区域的数据
+    ///
+    /// 返回的缓冲区计入进程的内核内存配额，配额在UserSlice释放时归还，
+    /// 因此缓冲区的生命周期不应超过UserSlice
+    pub fn read_to_vec(&self) -> Result<Vec<u8>, ProcessMemoryError> {
+        multitask::process::charge_kernel_memory(&self.process, self.len())?;
+        self.charged.set(self.charged.get() + self.len());
+
+        let mut buffer = alloc::vec![0u8; self.len()];
+        self.read(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// 以T类型读取区域数据，区域长度必须与T一致
+    ///
+    /// # Safety
+    ///
+    /// 用户内存中的任意字节都必须是T的合法值
+    pub unsafe fn read_struct<T>(&self) -> Result<T, ProcessMemoryError> {
+        assert!(self.len() == size_of::<T>());
+        let mut value = MaybeUninit::<T>::uninit();
+        unsafe {
+            multitask::process::read_user_process_memory(
+                &self.process,
+                self.range.start(),
+                value.as_mut_ptr() as *mut u8,
+                size_of::<T>(),
+            )?;
+            Ok(value.assume_init())
+        }
+    }
+
+    /// 向区域起始位置写入数据，src长度不能超过区域长度
+    ///
+    /// 区域必须由[`UserSlice::writable`]创建
+    pub fn write(&self, src: &[u8]) -> Result<(), ProcessMemoryError> {
+        assert!(self.writable);
+        assert!(src.len() <= self.len());
+        unsafe {
+            multitask::process::write_user_process_memory(
+                &self.process,
+                self.range.start(),
+                src.as_ptr(),
+                src.len(),
+            )
+        }
+    }
+
+    /// 以T类型写入区域数据，区域长度必须与T一致
+    ///
+    /// 区域必须由[`UserSlice::writable_of`]创建
+    pub fn write_struct<T: Copy>(&self, src: &T) -> Result<(), ProcessMemoryError> {
+        assert!(self.writable);
+        assert!(self.len() == size_of::<T>());
+        unsafe {
+            multitask::process::write_user_process_memory_struct(
+                &self.process,
+                self.range.start(),
+                src,
+            )
+        }
+    }
+
+    /// 将区域映射到内核空间，得到可直接读写的缓冲区
+    ///
+    /// 映射与原用户内存共享物理内存页，通过映射写入的数据对用户程序立即可见，无需再次复制。
+    /// 映射期间区域保持固定，映射释放时一并解除固定。
+    ///
+    /// 区域必须由[`UserSlice::writable`]创建
+    pub fn into_mapping(self) -> Result<UserSliceMapping, ProcessMemoryError> {
+        assert!(self.writable);
+        let ptr = if self.is_empty() {
+            NonNull::dangling()
+        } else {
+            multitask::process::map_user_process_memory(
+                &self.process,
+                self.range.start(),
+                self.len(),
+            )?
+        };
+
+        Ok(UserSliceMapping { slice: self, ptr })
+    }
+}
+
+impl Drop for UserSlice {
+    fn drop(&mut self) {
+        multitask::process::release_kernel_memory(&self.process, self.charged.get());
+        multitask::process::unpin_user_process_memory(
+            &self.process,
+            self.range.start(),
+            self.len(),
+        );
+    }
+}
+
+/// 映射到内核空间的用户内存区域，由[`UserSlice::into_mapping`]创建
+pub struct UserSliceMapping {
+    slice: UserSlice,
+    ptr: NonNull<u8>,
+}
+
+// Safety: 映射位于内核空间，在任意内核线程中均可访问，且UserSliceMapping独占该映射
+unsafe impl Send for UserSliceMapping {}
+
+impl Deref for UserSliceMapping {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.slice.len()) }
+    }
+}
+
+impl DerefMut for UserSliceMapping {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.slice.len()) }
+    }
+}
+
+impl Drop for UserSliceMapping {
+    fn drop(&mut self) {
+        if !self.slice.is_empty() {
+            unsafe {
+                multitask::process::unmap_user_process_memory(self.ptr, self.slice.len());
+            }
+        }
+    }
+}
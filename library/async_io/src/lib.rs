@@ -13,6 +13,41 @@ pub trait Seekable {
     fn seek(&mut self, cursor: u64) -> impl Future<Output = Result<(), Self::SeekError>> + Send;
 }
 
+/// 内存中的字节序列，按AsyncRead与Seekable读取
+///
+/// 文件指针超出末尾时读取返回0
+pub struct SliceReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> SliceReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+}
+
+impl AsyncRead for SliceReader<'_> {
+    type ReadError = core::convert::Infallible;
+
+    async fn read(&mut self, buf: &mut [u8]) -> Result<u64, Self::ReadError> {
+        let remaining = self.data.get(self.position..).unwrap_or_default();
+        let len = remaining.len().min(buf.len());
+        buf[..len].copy_from_slice(&remaining[..len]);
+        self.position += len;
+        Ok(len as u64)
+    }
+}
+
+impl Seekable for SliceReader<'_> {
+    type SeekError = core::convert::Infallible;
+
+    async fn seek(&mut self, cursor: u64) -> Result<(), Self::SeekError> {
+        self.position = usize::try_from(cursor).unwrap_or(usize::MAX);
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub enum ReadExactError<E> {
     InnerError(E),
@@ -102,7 +102,7 @@ impl<T> Sender<T> {
             return Err(TrySendError::ReceiverLost(data));
         }
 
-        let guard = self.inner.producer.try_acquire(1);
+        let guard = self.inner.producer.try_acquire().ok();
         if guard.is_none() {
             return Err(TrySendError::BufferFull(data));
         }
@@ -146,7 +146,7 @@ impl<T> Sender<T> {
 impl<T> Receiver<T> {
     /// 不阻塞，尝试从缓冲区接收一个数据
     pub fn try_recv(&mut self) -> Result<T, TryReceiveError> {
-        let guard = self.inner.consumer.try_acquire(1);
+        let guard = self.inner.consumer.try_acquire().ok();
         if guard.is_some() {
             forget(guard);
             return Ok(self.recv_solt());
@@ -172,7 +172,7 @@ impl<T> Receiver<T> {
     pub async fn recv(&mut self) -> Result<T, SenderLost> {
         loop {
             if self.lost_sender {
-                let guard = self.inner.consumer.try_acquire(1);
+                let guard = self.inner.consumer.try_acquire().ok();
                 if guard.is_some() {
                     forget(guard);
                     return Ok(self.recv_solt());
@@ -282,7 +282,7 @@ impl<T> Future for SendReserveFuture<'_, T> {
                     }
                     *self = Self::Acquiring {
                         sender,
-                        fut: sender.inner.producer.acquire(1),
+                        fut: sender.inner.producer.acquire(),
                     };
                 }
                 Self::Acquiring { sender, fut } => {
@@ -344,7 +344,7 @@ impl<T> Future for RecvReserveFuture<'_, T> {
                     }
                     *self = Self::Acquiring {
                         receiver,
-                        fut: receiver.inner.consumer.acquire(1),
+                        fut: receiver.inner.consumer.acquire(),
                     };
                 }
                 Self::Acquiring { receiver, fut } => {
@@ -1,6 +1,7 @@
 use core::{
+    mem,
     pin::Pin,
-    sync::atomic::{AtomicU32, Ordering},
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
     task::{Context, Poll, Waker},
 };
 
@@ -18,11 +19,19 @@ use crate::{
 ///
 /// 异步队列是公平的，FIFO。
 ///
+/// # 虚假唤醒
+///
+/// [Condvar::wait] 可能在条件没有变化时返回，例如：被取消的等待者将唤醒转交给其他等待者，
+/// 或通知发生在等待开始之前（见 [Condvar::notify_waiters]）。
+/// 调用方应在循环中检查条件，或直接使用 [Condvar::wait_while] / [Condvar::wait_until]。
+///
 /// # 取消安全
 ///
 /// 如果一个已经被唤醒的 [CondvarWait]，无论其是否成功获取锁，都会尝试从队列中再次唤醒一个。
 pub struct Condvar {
     queue: SyncLock<VecDeque<Arc<CondvarWaker>>>,
+    // notify_waiters的调用次数，wait时记录，用于判断尚未开始等待的任务是否已被通知
+    generation: AtomicUsize,
 }
 
 pub struct CondvarWait<'cond, 'lock, T>(CondvarWaitInner<'cond, 'lock, T>);
@@ -31,10 +40,13 @@ impl Condvar {
     pub fn new() -> Self {
         Self {
             queue: SyncLock::new(VecDeque::new()),
+            generation: AtomicUsize::new(0),
         }
     }
 
     /// 释放锁并让出。在[Self::wake]或[Self::wake_all]被调用后，重新获取锁
+    ///
+    /// 返回时不保证条件已满足，见 [Condvar] 中关于虚假唤醒的说明
     pub fn wait<'cond, 'lock, T>(
         &'cond self,
         guard: MutexGuard<'lock, T>,
@@ -42,9 +54,33 @@ impl Condvar {
         CondvarWait(CondvarWaitInner::Init {
             condvar: self,
             guard,
+            generation: self.generation.load(Ordering::Acquire),
         })
     }
 
+    /// 在condition返回true期间等待，返回时condition为false且持有锁
+    ///
+    /// 每次被唤醒后重新检查条件，因此不受虚假唤醒影响。首次检查时条件已为false则直接返回
+    pub async fn wait_while<'lock, T>(
+        &self,
+        mut guard: MutexGuard<'lock, T>,
+        mut condition: impl FnMut(&mut T) -> bool,
+    ) -> MutexGuard<'lock, T> {
+        while condition(&mut guard) {
+            guard = self.wait(guard).await;
+        }
+        guard
+    }
+
+    /// 等待至condition返回true，返回时持有锁，见 [Self::wait_while]
+    pub async fn wait_until<'lock, T>(
+        &self,
+        guard: MutexGuard<'lock, T>,
+        mut condition: impl FnMut(&mut T) -> bool,
+    ) -> MutexGuard<'lock, T> {
+        self.wait_while(guard, |value| !condition(value)).await
+    }
+
     /// 唤醒一个等待中的任务
     pub fn wake(&self) {
         loop {
@@ -65,18 +101,29 @@ impl Condvar {
                 continue;
             }
 
-            waker.waker.wake_by_ref();
+            waker.waker.lock().wake_by_ref();
             return;
         }
     }
 
-    /// 唤醒全部等待中的任务
+    /// 唤醒全部等待中的任务，等同于 [Self::notify_waiters]
     pub fn wake_all(&self) {
-        loop {
-            let Some(waker) = self.queue.lock().pop_front() else {
-                return;
-            };
+        self.notify_waiters();
+    }
 
+    /// 唤醒调用时正在等待的全部任务
+    ///
+    /// 语义与tokio的 `Notify::notify_waiters` 一致：
+    /// - 调用前通过 [Self::wait] 创建的等待都会返回，包括尚未被poll、还未开始等待的；
+    /// - 调用后开始的等待不受影响，即使其在被唤醒的任务重新获取锁之前加入队列。
+    ///   被唤醒的任务再次等待时不会被同一次调用重复唤醒
+    pub fn notify_waiters(&self) {
+        let waiters = {
+            let mut queue = self.queue.lock();
+            self.generation.fetch_add(1, Ordering::Release);
+            mem::take(&mut *queue)
+        };
+        for waker in waiters {
             if waker
                 .status
                 .compare_exchange(
@@ -85,17 +132,21 @@ impl Condvar {
                     Ordering::Release,
                     Ordering::Relaxed,
                 )
-                .is_err()
+                .is_ok()
             {
-                continue;
+                waker.waker.lock().wake_by_ref();
             }
-
-            waker.waker.wake_by_ref();
         }
     }
 
-    fn queue(&self, waker: Arc<CondvarWaker>) {
-        self.queue.lock().push_back(waker);
+    /// 加入等待队列，wait之后已调用过 [Self::notify_waiters] 时不加入并返回false
+    fn queue(&self, waker: Arc<CondvarWaker>, generation: usize) -> bool {
+        let mut queue = self.queue.lock();
+        if self.generation.load(Ordering::Acquire) != generation {
+            return false;
+        }
+        queue.push_back(waker);
+        true
     }
 }
 
@@ -103,6 +154,7 @@ enum CondvarWaitInner<'cond, 'lock, T> {
     Init {
         condvar: &'cond Condvar,
         guard: MutexGuard<'lock, T>,
+        generation: usize,
     },
     Wait {
         condvar: &'cond Condvar,
@@ -117,7 +169,8 @@ enum CondvarWaitInner<'cond, 'lock, T> {
 }
 
 struct CondvarWaker {
-    waker: Waker,
+    // 每次poll时更新，任务可能在不同的上下文中被poll
+    waker: SyncLock<Waker>,
     status: AtomicU32,
 }
 
@@ -133,12 +186,24 @@ impl<'cond, 'lock, T> Future for CondvarWait<'cond, 'lock, T> {
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         loop {
             match &mut self.0 {
-                CondvarWaitInner::Init { condvar, guard } => {
+                CondvarWaitInner::Init {
+                    condvar,
+                    guard,
+                    generation,
+                } => {
                     let waker = Arc::new(CondvarWaker {
-                        waker: cx.waker().clone(),
+                        waker: SyncLock::new(cx.waker().clone()),
                         status: AtomicU32::new(CondvarWaker::STATUS_WAITING),
                     });
-                    condvar.queue(waker.clone());
+                    if !condvar.queue(waker.clone(), *generation) {
+                        // 开始等待前已被notify_waiters唤醒，仍持有锁，直接返回
+                        let CondvarWaitInner::Init { guard, .. } =
+                            mem::replace(&mut self.0, CondvarWaitInner::Done)
+                        else {
+                            unreachable!()
+                        };
+                        return Poll::Ready(guard);
+                    }
                     self.0 = CondvarWaitInner::Wait {
                         condvar,
                         waker: waker,
@@ -150,6 +215,13 @@ impl<'cond, 'lock, T> Future for CondvarWait<'cond, 'lock, T> {
                     waker,
                     mutex,
                 } => {
+                    // 先更新唤醒器再检查状态，避免错过在此期间发生的唤醒
+                    {
+                        let mut stored = waker.waker.lock();
+                        if !stored.will_wake(cx.waker()) {
+                            *stored = cx.waker().clone();
+                        }
+                    }
                     if waker.status.load(Ordering::Acquire) != CondvarWaker::STATUS_ACQUIRED {
                         return Poll::Pending;
                     }
@@ -193,3 +265,95 @@ impl<T> Drop for CondvarWait<'_, '_, T> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use core::{
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+
+    use alloc::boxed::Box;
+
+    use crate::{condvar::Condvar, mutex::Mutex};
+
+    fn poll<F: Future>(future: core::pin::Pin<&mut F>) -> Poll<F::Output> {
+        future.poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn test_wake_order() {
+        let mutex = Mutex::new(0);
+        let condvar = Condvar::new();
+        let mut first = pin!(condvar.wait(mutex.try_lock().unwrap()));
+        assert!(poll(first.as_mut()).is_pending());
+        let mut second = pin!(condvar.wait(mutex.try_lock().unwrap()));
+        assert!(poll(second.as_mut()).is_pending());
+
+        // 按等待的顺序唤醒
+        condvar.wake();
+        assert!(poll(second.as_mut()).is_pending());
+        let Poll::Ready(guard) = poll(first.as_mut()) else {
+            panic!("first waiter not woken");
+        };
+        drop(guard);
+        condvar.wake();
+        assert!(poll(second.as_mut()).is_ready());
+    }
+
+    #[test]
+    fn test_notify_waiters_snapshot() {
+        let mutex = Mutex::new(0);
+        let condvar = Condvar::new();
+        let mut waiting = pin!(condvar.wait(mutex.try_lock().unwrap()));
+        assert!(poll(waiting.as_mut()).is_pending());
+        // 已创建但尚未poll的等待也会被唤醒，此时锁仍被其持有
+        let mut created = pin!(condvar.wait(mutex.try_lock().unwrap()));
+        condvar.notify_waiters();
+        let Poll::Ready(guard) = poll(created.as_mut()) else {
+            panic!("created waiter not notified");
+        };
+        drop(guard);
+        assert!(poll(waiting.as_mut()).is_ready());
+
+        // 调用之后开始的等待不受影响
+        let mut later = pin!(condvar.wait(mutex.try_lock().unwrap()));
+        assert!(poll(later.as_mut()).is_pending());
+        condvar.wake();
+        assert!(poll(later.as_mut()).is_ready());
+    }
+
+    #[test]
+    fn test_wait_while_spurious_wakeup() {
+        let mutex = Mutex::new(false);
+        let condvar = Condvar::new();
+        let mut waiting = pin!(condvar.wait_until(mutex.try_lock().unwrap(), |ready| *ready));
+        assert!(poll(waiting.as_mut()).is_pending());
+
+        // 条件未变化时的唤醒不会使wait_until返回
+        condvar.wake_all();
+        assert!(poll(waiting.as_mut()).is_pending());
+
+        *mutex.try_lock().unwrap() = true;
+        condvar.wake_all();
+        let Poll::Ready(guard) = poll(waiting.as_mut()) else {
+            panic!("waiter not woken after condition changed");
+        };
+        assert!(*guard);
+    }
+
+    #[test]
+    fn test_cancelled_waiter_passes_wake() {
+        let mutex = Mutex::new(0);
+        let condvar = Condvar::new();
+        let mut first = Box::pin(condvar.wait(mutex.try_lock().unwrap()));
+        assert!(poll(first.as_mut()).is_pending());
+        let mut second = pin!(condvar.wait(mutex.try_lock().unwrap()));
+        assert!(poll(second.as_mut()).is_pending());
+
+        // 已被唤醒的等待者被取消时，唤醒转交给下一个等待者
+        condvar.wake();
+        drop(first);
+        assert!(poll(second.as_mut()).is_ready());
+    }
+}
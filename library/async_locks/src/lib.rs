@@ -9,7 +9,11 @@ type SyncLock<T> = spin::SpinLock<T>;
 
 pub mod channel;
 pub mod condvar;
+#[cfg(feature = "lockdep")]
+pub mod lockdep;
 pub mod mutex;
+pub mod once;
+pub mod once_cell;
 pub mod rwlock;
 pub mod semaphore;
 pub mod watch;
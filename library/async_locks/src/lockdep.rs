@@ -0,0 +1,61 @@
+use core::{
+    panic::Location,
+    ptr::null_mut,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+/// 锁的类别，即创建锁的位置
+///
+/// 同一处创建的锁视为同一类，死锁检测以类别而不是锁实例记录获取顺序
+pub type LockClass = &'static Location<'static>;
+
+/// 死锁检测的回调，由使用此crate的内核提供
+pub struct Hooks {
+    /// 即将等待锁，检查获取顺序是否与已有的顺序冲突，冲突时应panic
+    pub check: fn(LockClass),
+    /// 已获取锁，返回持有锁的上下文（线程或异步任务），不跟踪时返回None
+    pub acquired: fn(LockClass) -> Option<u64>,
+    /// 释放锁，上下文为获取锁时返回的值
+    pub release: fn(LockClass, u64),
+}
+
+static HOOKS: AtomicPtr<Hooks> = AtomicPtr::new(null_mut());
+
+/// 设置死锁检测的回调，此后获取的锁才会被跟踪
+pub fn set_hooks(hooks: &'static Hooks) {
+    HOOKS.store(hooks as *const Hooks as *mut Hooks, Ordering::Release);
+}
+
+fn hooks() -> Option<&'static Hooks> {
+    // Safety: 只会存入 'static 的引用
+    unsafe { HOOKS.load(Ordering::Acquire).as_ref() }
+}
+
+pub(crate) fn check(class: LockClass) {
+    if let Some(hooks) = hooks() {
+        (hooks.check)(class);
+    }
+}
+
+/// 持有锁的记录，随锁的守卫一起释放
+pub(crate) struct Held {
+    class: LockClass,
+    context: Option<u64>,
+}
+
+impl Held {
+    pub(crate) fn acquired(class: LockClass) -> Self {
+        Self {
+            class,
+            context: hooks().and_then(|hooks| (hooks.acquired)(class)),
+        }
+    }
+}
+
+impl Drop for Held {
+    fn drop(&mut self) {
+        if let (Some(hooks), Some(context)) = (hooks(), self.context) {
+            (hooks.release)(self.class, context);
+        }
+    }
+}
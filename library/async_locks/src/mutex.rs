@@ -5,6 +5,11 @@ use core::{
     task::{Context, Poll},
 };
 
+#[cfg(feature = "lockdep")]
+use core::panic::Location;
+
+#[cfg(feature = "lockdep")]
+use crate::lockdep::{self, Held, LockClass};
 use crate::semaphore::{Semaphore, SemaphoreAcquireFuture, SemaphoreGuard};
 
 /// 一个基于异步信号量实现的互斥量。
@@ -22,6 +27,8 @@ use crate::semaphore::{Semaphore, SemaphoreAcquireFuture, SemaphoreGuard};
 pub struct Mutex<T> {
     data: UnsafeCell<T>,
     semaphore: Semaphore,
+    #[cfg(feature = "lockdep")]
+    class: LockClass,
 }
 
 /// 一个互斥锁的持有标记，提供对内部数据的独占访问。
@@ -35,6 +42,8 @@ pub struct MutexGuard<'a, T> {
     pub(crate) mutex: &'a Mutex<T>,
     #[allow(dead_code)]
     semaphore_guard: SemaphoreGuard<'a>,
+    #[cfg(feature = "lockdep")]
+    _held: Held,
 }
 
 /// 代表一次异步加锁操作。
@@ -51,6 +60,8 @@ pub struct MutexGuard<'a, T> {
 pub struct MutexLockFuture<'a, T> {
     mutex: &'a Mutex<T>,
     semaphore_future: SemaphoreAcquireFuture<'a>,
+    #[cfg(feature = "lockdep")]
+    checked: bool,
 }
 
 unsafe impl<T: Send> Send for Mutex<T> {}
@@ -58,10 +69,13 @@ unsafe impl<T: Send> Sync for Mutex<T> {}
 
 impl<T> Mutex<T> {
     /// 创建一个新的互斥量，内部值为 `data`。
+    #[track_caller]
     pub const fn new(data: T) -> Self {
         Self {
             data: UnsafeCell::new(data),
             semaphore: Semaphore::new(1),
+            #[cfg(feature = "lockdep")]
+            class: Location::caller(),
         }
     }
 
@@ -72,10 +86,13 @@ impl<T> Mutex<T> {
     /// 该操作 **不会挂起**，因此本方法是 **无等待的、非阻塞** 调用。
     pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
         self.semaphore
-            .try_acquire(1)
+            .try_acquire()
+            .ok()
             .map(|semaphore_guard| MutexGuard {
                 mutex: self,
                 semaphore_guard,
+                #[cfg(feature = "lockdep")]
+                _held: Held::acquired(self.class),
             })
     }
 
@@ -94,7 +111,9 @@ impl<T> Mutex<T> {
     pub fn lock(&self) -> MutexLockFuture<'_, T> {
         MutexLockFuture {
             mutex: self,
-            semaphore_future: self.semaphore.acquire(1),
+            semaphore_future: self.semaphore.acquire(),
+            #[cfg(feature = "lockdep")]
+            checked: false,
         }
     }
 }
@@ -103,11 +122,20 @@ impl<'a, T> Future for MutexLockFuture<'a, T> {
     type Output = MutexGuard<'a, T>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        #[cfg(feature = "lockdep")]
+        if !self.checked {
+            self.checked = true;
+            lockdep::check(self.mutex.class);
+        }
+        // 互斥量不会关闭信号量
         Pin::new(&mut self.semaphore_future)
             .poll(cx)
+            .map(Result::unwrap)
             .map(|semaphore_guard| MutexGuard {
                 mutex: self.mutex,
                 semaphore_guard,
+                #[cfg(feature = "lockdep")]
+                _held: Held::acquired(self.mutex.class),
             })
     }
 }
@@ -0,0 +1,247 @@
+use core::{
+    cell::UnsafeCell,
+    convert::Infallible,
+    fmt,
+    hint::spin_loop,
+    mem::{self, MaybeUninit},
+    ops::Deref,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+/// 只能写入一次的同步单元，用于替代 `static mut Option<T>` 形式的全局变量。
+///
+/// 写入完成后，值在单元的整个生命周期内保持不变，可以被任意多个访问者共享读取。
+///
+/// 多个访问者同时初始化时，只有一个初始化函数会被执行，其他访问者自旋等待其完成。
+/// 因此：
+/// - 初始化函数中不能再次初始化同一个单元，否则会死锁；
+/// - 在裸机环境中，若中断处理程序可能访问单元，初始化期间应关闭中断，
+///   否则中断处理程序会自旋等待被其打断的初始化函数。
+///
+/// 不需要同步等待的异步场景可使用 [`crate::once_cell::OnceCell`]。
+pub struct OnceLock<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+unsafe impl<T: Send> Send for OnceLock<T> {}
+unsafe impl<T: Send + Sync> Sync for OnceLock<T> {}
+
+/// 首次访问时初始化的值。
+///
+/// 初始化函数在首次解引用时执行，此后解引用返回同一个值。同步语义与 [`OnceLock`] 相同。
+///
+/// ```ignore
+/// static TABLE: Lazy<[u8; 256]> = Lazy::new(build_table);
+///
+/// let value = TABLE[0];
+/// ```
+pub struct Lazy<T, F = fn() -> T> {
+    cell: OnceLock<T>,
+    init: UnsafeCell<Option<F>>,
+}
+
+// init仅在OnceLock的初始化函数中访问，同一时间至多一个访问者
+unsafe impl<T: Send, F: Send> Send for Lazy<T, F> {}
+unsafe impl<T: Send + Sync, F: Send> Sync for Lazy<T, F> {}
+
+impl<T> OnceLock<T> {
+    const STATE_INCOMPLETE: u8 = 0;
+    const STATE_RUNNING: u8 = 1;
+    const STATE_COMPLETE: u8 = 2;
+
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(Self::STATE_INCOMPLETE),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// 获取已写入的值，尚未写入或正在初始化时返回 `None`。
+    pub fn get(&self) -> Option<&T> {
+        if self.is_initialized() {
+            // Safety: 状态为COMPLETE时值已写入，且不会再被修改
+            Some(unsafe { self.get_unchecked() })
+        } else {
+            None
+        }
+    }
+
+    /// 获取已写入的值的可变引用。
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        if *self.state.get_mut() == Self::STATE_COMPLETE {
+            // Safety: 状态为COMPLETE时值已写入，可变借用保证没有其他访问者
+            Some(unsafe { self.value.get_mut().assume_init_mut() })
+        } else {
+            None
+        }
+    }
+
+    /// 是否已写入值。
+    pub fn is_initialized(&self) -> bool {
+        self.state.load(Ordering::Acquire) == Self::STATE_COMPLETE
+    }
+
+    /// 写入值，已写入时返回 `Err(value)`。
+    ///
+    /// 其他访问者正在初始化时，等待其完成后返回 `Err(value)`。
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let mut value = Some(value);
+        self.get_or_init(|| value.take().unwrap());
+        match value {
+            None => Ok(()),
+            Some(value) => Err(value),
+        }
+    }
+
+    /// 获取值，尚未写入时以 `f` 的返回值初始化。
+    pub fn get_or_init(&self, f: impl FnOnce() -> T) -> &T {
+        match self.get_or_try_init(|| Ok::<T, Infallible>(f())) {
+            Ok(value) => value,
+        }
+    }
+
+    /// 获取值，尚未写入时以 `f` 的返回值初始化。
+    ///
+    /// `f` 返回错误时，单元保持未写入的状态并返回该错误，之后的访问者可以重新初始化。
+    pub fn get_or_try_init<E>(&self, f: impl FnOnce() -> Result<T, E>) -> Result<&T, E> {
+        loop {
+            match self.state.compare_exchange(
+                Self::STATE_INCOMPLETE,
+                Self::STATE_RUNNING,
+                Ordering::Acquire,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break,
+                // Safety: 状态为COMPLETE时值已写入
+                Err(Self::STATE_COMPLETE) => return Ok(unsafe { self.get_unchecked() }),
+                Err(_) => spin_loop(),
+            }
+        }
+
+        // 初始化函数panic时恢复为未写入的状态，避免其他访问者永远等待
+        let reset = ResetOnDrop(&self.state);
+        let value = f()?;
+        // Safety: 状态为RUNNING时只有当前访问者可以写入
+        unsafe {
+            (*self.value.get()).write(value);
+        }
+        mem::forget(reset);
+        self.state.store(Self::STATE_COMPLETE, Ordering::Release);
+        // Safety: 值已写入
+        Ok(unsafe { self.get_unchecked() })
+    }
+
+    /// 取出已写入的值，单元恢复为未写入的状态。
+    pub fn take(&mut self) -> Option<T> {
+        if *self.state.get_mut() == Self::STATE_COMPLETE {
+            *self.state.get_mut() = Self::STATE_INCOMPLETE;
+            // Safety: 状态为COMPLETE时值已写入，状态已重置，值不会被再次读取
+            Some(unsafe { self.value.get_mut().assume_init_read() })
+        } else {
+            None
+        }
+    }
+
+    pub fn into_inner(mut self) -> Option<T> {
+        self.take()
+    }
+
+    /// Safety: 值必须已写入
+    unsafe fn get_unchecked(&self) -> &T {
+        unsafe { (*self.value.get()).assume_init_ref() }
+    }
+}
+
+struct ResetOnDrop<'a>(&'a AtomicU8);
+
+impl Drop for ResetOnDrop<'_> {
+    fn drop(&mut self) {
+        self.0
+            .store(OnceLock::<()>::STATE_INCOMPLETE, Ordering::Release);
+    }
+}
+
+impl<T> Default for OnceLock<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<T> for OnceLock<T> {
+    fn from(value: T) -> Self {
+        Self {
+            state: AtomicU8::new(Self::STATE_COMPLETE),
+            value: UnsafeCell::new(MaybeUninit::new(value)),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OnceLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get() {
+            Some(value) => f.debug_tuple("OnceLock").field(value).finish(),
+            None => f.write_str("OnceLock(<uninit>)"),
+        }
+    }
+}
+
+impl<T> Drop for OnceLock<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == Self::STATE_COMPLETE {
+            // Safety: 状态为COMPLETE时值已写入
+            unsafe { self.value.get_mut().assume_init_drop() }
+        }
+    }
+}
+
+impl<T, F: FnOnce() -> T> Lazy<T, F> {
+    pub const fn new(init: F) -> Self {
+        Self {
+            cell: OnceLock::new(),
+            init: UnsafeCell::new(Some(init)),
+        }
+    }
+
+    /// 获取值，尚未初始化时执行初始化函数。
+    ///
+    /// 初始化函数曾经panic时，再次访问会panic。
+    pub fn force(this: &Self) -> &T {
+        this.cell.get_or_init(|| {
+            // Safety: 只有获得初始化权的访问者会执行此处
+            let init = unsafe { (*this.init.get()).take() };
+            match init {
+                Some(init) => init(),
+                None => panic!("Lazy instance has previously been poisoned"),
+            }
+        })
+    }
+
+    /// 获取已初始化的值，尚未初始化时返回 `None`，不会执行初始化函数。
+    pub fn get(this: &Self) -> Option<&T> {
+        this.cell.get()
+    }
+}
+
+impl<T, F: FnOnce() -> T> Deref for Lazy<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        Self::force(self)
+    }
+}
+
+impl<T: Default> Default for Lazy<T> {
+    fn default() -> Self {
+        Self::new(T::default)
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for Lazy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.cell.get() {
+            Some(value) => f.debug_tuple("Lazy").field(value).finish(),
+            None => f.write_str("Lazy(<uninit>)"),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use core::{convert::Infallible, fmt, future::Future};
+
+use crate::{once::OnceLock, semaphore::Semaphore};
+
+/// 只能写入一次的异步单元。
+///
+/// 与 [`OnceLock`] 不同，初始化函数是异步的：同时初始化的访问者中只有一个会执行初始化函数，
+/// 其他访问者挂起等待，而不是自旋。初始化函数返回错误或被取消时，下一个等待者继续初始化。
+///
+/// 读取与写入（[`OnceCell::get`]、[`OnceCell::set`]）是同步的，不需要异步运行时。
+pub struct OnceCell<T> {
+    value: OnceLock<T>,
+    // 初始化权，同一时间只有一个访问者执行初始化函数
+    init: Semaphore,
+}
+
+impl<T> OnceCell<T> {
+    pub const fn new() -> Self {
+        Self {
+            value: OnceLock::new(),
+            init: Semaphore::new(1),
+        }
+    }
+
+    /// 获取已写入的值，尚未写入时返回 `None`。
+    pub fn get(&self) -> Option<&T> {
+        self.value.get()
+    }
+
+    /// 是否已写入值。
+    pub fn is_initialized(&self) -> bool {
+        self.value.is_initialized()
+    }
+
+    /// 写入值，已写入时返回 `Err(value)`。
+    ///
+    /// 不等待正在执行的初始化函数：写入成功后，该初始化函数的结果被丢弃。
+    pub fn set(&self, value: T) -> Result<(), T> {
+        self.value.set(value)
+    }
+
+    /// 获取值，尚未写入时以 `f` 的结果初始化。
+    pub async fn get_or_init<F, Fut>(&self, f: F) -> &T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let init = || async { Ok::<T, Infallible>(f().await) };
+        match self.get_or_try_init(init).await {
+            Ok(value) => value,
+        }
+    }
+
+    /// 获取值，尚未写入时以 `f` 的结果初始化。
+    ///
+    /// `f` 返回错误时，单元保持未写入的状态并返回该错误，下一个等待者继续初始化。
+    ///
+    /// ### 取消安全
+    /// 等待或初始化期间被取消时，初始化权交给下一个等待者，单元保持未写入的状态。
+    pub async fn get_or_try_init<F, Fut, E>(&self, f: F) -> Result<&T, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        if let Some(value) = self.get() {
+            return Ok(value);
+        }
+        // 初始化权的信号量不会关闭
+        let _permit = self.init.acquire().await.unwrap();
+        // 等待期间其他访问者可能已完成初始化
+        if let Some(value) = self.get() {
+            return Ok(value);
+        }
+        let value = f().await?;
+        // 初始化期间值可能已由set写入，此时丢弃初始化的结果
+        Ok(self.value.get_or_init(|| value))
+    }
+
+    pub fn into_inner(self) -> Option<T> {
+        self.value.into_inner()
+    }
+}
+
+impl<T> Default for OnceCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<T> for OnceCell<T> {
+    fn from(value: T) -> Self {
+        Self {
+            value: OnceLock::from(value),
+            init: Semaphore::new(1),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OnceCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get() {
+            Some(value) => f.debug_tuple("OnceCell").field(value).finish(),
+            None => f.write_str("OnceCell(<uninit>)"),
+        }
+    }
+}
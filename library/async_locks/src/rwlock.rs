@@ -5,6 +5,11 @@ use core::{
     task::{Context, Poll},
 };
 
+#[cfg(feature = "lockdep")]
+use core::panic::Location;
+
+#[cfg(feature = "lockdep")]
+use crate::lockdep::{self, Held, LockClass};
 use crate::semaphore::{Semaphore, SemaphoreAcquireFuture, SemaphoreGuard};
 
 /// 一个异步读写锁，允许多个并发的读访问或一个独占的写访问。
@@ -63,71 +68,94 @@ use crate::semaphore::{Semaphore, SemaphoreAcquireFuture, SemaphoreGuard};
 pub struct RwLock<T> {
     data: UnsafeCell<T>,
     semaphore: Semaphore,
+    #[cfg(feature = "lockdep")]
+    class: LockClass,
 }
 
 pub struct ReadLockGuard<'a, T> {
     lock: &'a RwLock<T>,
     #[allow(dead_code)]
     semaphore_guard: SemaphoreGuard<'a>,
+    #[cfg(feature = "lockdep")]
+    _held: Held,
 }
 
 pub struct WriteLockGuard<'a, T> {
     lock: &'a RwLock<T>,
     semaphore_guard: SemaphoreGuard<'a>,
+    #[cfg(feature = "lockdep")]
+    _held: Held,
 }
 
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct ReadLockFuture<'a, T> {
     lock: &'a RwLock<T>,
     semaphore_future: SemaphoreAcquireFuture<'a>,
+    #[cfg(feature = "lockdep")]
+    checked: bool,
 }
 
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct WriteLockFuture<'a, T> {
     lock: &'a RwLock<T>,
     semaphore_future: SemaphoreAcquireFuture<'a>,
+    #[cfg(feature = "lockdep")]
+    checked: bool,
 }
 
 unsafe impl<T: Send> Send for RwLock<T> {}
 unsafe impl<T: Send> Sync for RwLock<T> {}
 
 impl<T> RwLock<T> {
+    #[track_caller]
     pub const fn new(data: T) -> Self {
         Self {
             data: UnsafeCell::new(data),
             semaphore: Semaphore::new(usize::MAX),
+            #[cfg(feature = "lockdep")]
+            class: Location::caller(),
         }
     }
 
     pub fn try_read(&self) -> Option<ReadLockGuard<'_, T>> {
         self.semaphore
-            .try_acquire(1)
+            .try_acquire()
+            .ok()
             .map(|semaphore_guard| ReadLockGuard {
                 lock: self,
                 semaphore_guard,
+                #[cfg(feature = "lockdep")]
+                _held: Held::acquired(self.class),
             })
     }
 
     pub fn try_write(&self) -> Option<WriteLockGuard<'_, T>> {
         self.semaphore
-            .try_acquire(usize::MAX)
+            .try_acquire_many(usize::MAX)
+            .ok()
             .map(|semaphore_guard| WriteLockGuard {
                 lock: self,
                 semaphore_guard,
+                #[cfg(feature = "lockdep")]
+                _held: Held::acquired(self.class),
             })
     }
 
     pub fn read(&self) -> ReadLockFuture<'_, T> {
         ReadLockFuture {
             lock: self,
-            semaphore_future: self.semaphore.acquire(1),
+            semaphore_future: self.semaphore.acquire(),
+            #[cfg(feature = "lockdep")]
+            checked: false,
         }
     }
 
     pub fn write(&self) -> WriteLockFuture<'_, T> {
         WriteLockFuture {
             lock: self,
-            semaphore_future: self.semaphore.acquire(usize::MAX),
+            semaphore_future: self.semaphore.acquire_many(usize::MAX),
+            #[cfg(feature = "lockdep")]
+            checked: false,
         }
     }
 }
@@ -140,6 +168,8 @@ impl<'a, T> WriteLockGuard<'a, T> {
         ReadLockGuard {
             lock: self.lock,
             semaphore_guard: semaphore_guard,
+            #[cfg(feature = "lockdep")]
+            _held: self._held,
         }
     }
 }
@@ -148,11 +178,20 @@ impl<'a, T> Future for ReadLockFuture<'a, T> {
     type Output = ReadLockGuard<'a, T>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        #[cfg(feature = "lockdep")]
+        if !self.checked {
+            self.checked = true;
+            lockdep::check(self.lock.class);
+        }
+        // 读写锁不会关闭信号量
         Pin::new(&mut self.semaphore_future)
             .poll(cx)
+            .map(Result::unwrap)
             .map(|semaphore_guard| ReadLockGuard {
                 lock: self.lock,
                 semaphore_guard,
+                #[cfg(feature = "lockdep")]
+                _held: Held::acquired(self.lock.class),
             })
     }
 }
@@ -161,11 +200,20 @@ impl<'a, T> Future for WriteLockFuture<'a, T> {
     type Output = WriteLockGuard<'a, T>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        #[cfg(feature = "lockdep")]
+        if !self.checked {
+            self.checked = true;
+            lockdep::check(self.lock.class);
+        }
+        // 读写锁不会关闭信号量
         Pin::new(&mut self.semaphore_future)
             .poll(cx)
+            .map(Result::unwrap)
             .map(|semaphore_guard| WriteLockGuard {
                 lock: self.lock,
                 semaphore_guard,
+                #[cfg(feature = "lockdep")]
+                _held: Held::acquired(self.lock.class),
             })
     }
 }
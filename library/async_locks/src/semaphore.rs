@@ -1,7 +1,7 @@
 use core::{
     hint::spin_loop,
     pin::Pin,
-    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+    sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
     task::{Context, Poll, Waker},
 };
 
@@ -19,13 +19,29 @@ use crate::SyncLock;
 ///   `Poll::Pending` 的 Future；当许可可用时会被唤醒。
 /// - **公平**：等待任务按照进入等待队列的顺序依次被唤醒（FIFO）。
 ///
+/// # 公平性
+///
+/// - 存在等待者时，新的请求（包括 `try_acquire`）不会越过等待者获取许可；
+/// - 队首的等待者许可不足时，其后的等待者即使请求的许可更少也不会先获取，
+///   因此请求许可较多的等待者不会因许可被零散地取走而饿死。
+///
+/// 相应地，请求的许可数超过信号量总容量的等待者会一直阻塞其后的所有等待者。
+///
 /// # 获取许可
 ///
-/// 信号量提供两种获取许可的方式：
+/// 信号量提供以下获取许可的方式：
+///
+/// - [`Semaphore::try_acquire`] / [`Semaphore::try_acquire_many`] —— 立即尝试获取许可，
+///   若当前许可不足则返回 [`TryAcquireError::NoPermits`]；
+/// - [`Semaphore::acquire`] / [`Semaphore::acquire_many`] —— 返回一个 [`SemaphoreAcquireFuture`]，
+///   在 `poll` 时尝试获取；若获取失败，会加入等待队列，在许可可用时由内部唤醒器唤醒；
+/// - [`Semaphore::acquire_many_timeout`] —— 与 `acquire_many` 相同，但在给定的计时
+///   Future 完成时放弃等待，返回 [`AcquireError::Timeout`]。
 ///
-/// - [`Semaphore::try_acquire`] —— 立即尝试获取许可，若当前无许可则返回 `None`；
-/// - [`Semaphore::acquire`] —— 返回一个 [`SemaphoreAcquireFuture`]，在 `poll` 时尝试获取；
-///   若获取失败，会加入等待队列，在许可可用时由内部唤醒器唤醒。
+/// # 关闭
+///
+/// [`Semaphore::close`] 关闭信号量后，所有等待者被唤醒并返回 [`AcquireError::Closed`]，
+/// 此后的获取请求也立即返回该错误。已获取的许可不受影响，仍可正常归还。
 ///
 /// 成功获取后会返回 [`SemaphoreGuard`]。当该 Guard 被丢弃（`drop`）时，
 /// 会自动归还许可（符合 RAII 语义）。如果希望永久消耗许可，可使用 `core::mem::forget`
@@ -58,7 +74,9 @@ use crate::SyncLock;
 /// - 如果许可被永久忘记（例如通过 `mem::forget`），信号量的有效总容量会相应减少。  
 pub struct Semaphore {
     permits: AtomicUsize,
+    // (已分配给队列、尚未分给等待者的许可, 等待者)
     queue: SyncLock<(usize, VecDeque<Arc<SemaphoreWaker>>)>,
+    closed: AtomicBool,
 }
 
 /// 表示已成功获取的信号量许可。
@@ -75,8 +93,9 @@ pub struct SemaphoreGuard<'a> {
 /// 一次异步获取信号量许可的操作。
 ///
 /// 在 `poll` 时：
-/// - 若信号量中存在可用许可，则立即返回 `Poll::Ready(SemaphoreGuard)`；
-/// - 否则将当前任务加入等待队列，并返回 `Poll::Pending`；当许可可用时由内部唤醒。
+/// - 若信号量中存在可用许可，则立即返回 `Poll::Ready(Ok(SemaphoreGuard))`；
+/// - 若信号量已关闭，则返回 `Poll::Ready(Err(AcquireError::Closed))`；
+/// - 否则将当前任务加入等待队列，并返回 `Poll::Pending`；当许可可用或信号量关闭时由内部唤醒。
 ///
 /// 该 Future 本身不持有许可，只有在 `Poll::Ready` 时返回的 [`SemaphoreGuard`]
 /// 才表示许可已经成功获取。
@@ -102,6 +121,33 @@ pub struct SemaphoreAcquireFuture<'a> {
     waker: Option<Arc<SemaphoreWaker>>,
 }
 
+/// 带超时的异步获取信号量许可的操作，见 [`Semaphore::acquire_many_timeout`]。
+///
+/// 取消安全性与 [`SemaphoreAcquireFuture`] 相同。
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct SemaphoreAcquireTimeoutFuture<'a, F> {
+    acquire: SemaphoreAcquireFuture<'a>,
+    timeout: F,
+}
+
+/// 异步获取许可失败的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcquireError {
+    /// 信号量已关闭
+    Closed,
+    /// 等待超时
+    Timeout,
+}
+
+/// 立即获取许可失败的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryAcquireError {
+    /// 信号量已关闭
+    Closed,
+    /// 可用许可不足
+    NoPermits,
+}
+
 // 信号量应当是Send且Sync的
 const _: () = {
     assert_send_sync::<Semaphore>();
@@ -109,7 +155,8 @@ const _: () = {
 };
 
 struct SemaphoreWaker {
-    waker: Waker,
+    // 每次poll时更新，任务可能在不同的上下文中被poll
+    waker: SyncLock<Waker>,
     status: AtomicU32,
     permits: usize,
 }
@@ -118,6 +165,7 @@ impl SemaphoreWaker {
     const STATUS_WAITING: u32 = 0;
     const STATUS_ACQUIRED: u32 = 1;
     const STATUS_GIVEUP: u32 = 2;
+    const STATUS_CLOSED: u32 = 3;
 }
 
 impl Semaphore {
@@ -128,33 +176,46 @@ impl Semaphore {
         Self {
             permits: AtomicUsize::new(permits),
             queue: SyncLock::new((0, VecDeque::new())),
+            closed: AtomicBool::new(false),
         }
     }
 
+    /// 尝试获取 1 个许可，见 [`Semaphore::try_acquire_many`]。
+    pub fn try_acquire(&self) -> Result<SemaphoreGuard<'_>, TryAcquireError> {
+        self.try_acquire_many(1)
+    }
+
     /// 尝试获取 `n` 个许可。
     ///
-    /// 若当前可用许可数量不足，则立即返回 `None`，不会阻塞或挂起调用方。
+    /// 若当前可用许可数量不足，则立即返回错误，不会阻塞或挂起调用方。
     /// 若获取成功，则返回一个 [`SemaphoreGuard`]，其持有 `n` 个许可，
     /// 并在被丢弃时自动归还。
     ///
+    /// 存在等待者时，即使可用许可足够也不会越过等待者获取。
+    ///
     /// ### 内存语义
     /// 成功获取许可具备 **Acquire** 语义。
     ///
     /// # 返回值
-    /// - `Some(guard)`：成功获取到 `n` 个许可。
-    /// - `None`：当前许可不足，未获取任何许可。
-    pub fn try_acquire(&self, n: usize) -> Option<SemaphoreGuard<'_>> {
+    /// - `Ok(guard)`：成功获取到 `n` 个许可。
+    /// - `Err(TryAcquireError::NoPermits)`：当前许可不足，未获取任何许可。
+    /// - `Err(TryAcquireError::Closed)`：信号量已关闭。
+    pub fn try_acquire_many(&self, n: usize) -> Result<SemaphoreGuard<'_>, TryAcquireError> {
+        if self.is_closed() {
+            return Err(TryAcquireError::Closed);
+        }
+        // 存在等待者时，许可由队列持有，此处的计数为0
         loop {
             let permits = self.permits.load(Ordering::Acquire);
             if permits < n {
-                break None;
+                break Err(TryAcquireError::NoPermits);
             }
             if self
                 .permits
                 .compare_exchange(permits, permits - n, Ordering::AcqRel, Ordering::Relaxed)
                 .is_ok()
             {
-                break Some(SemaphoreGuard {
+                break Ok(SemaphoreGuard {
                     semaphore: self,
                     permits: n,
                 });
@@ -164,13 +225,21 @@ impl Semaphore {
         }
     }
 
+    /// 获取 1 个许可，见 [`Semaphore::acquire_many`]。
+    pub fn acquire(&self) -> SemaphoreAcquireFuture<'_> {
+        self.acquire_many(1)
+    }
+
     /// 获取 `n` 个许可，必要时会等待。
     ///
     /// 如果当前可用许可不足，此方法会返回一个 [`SemaphoreAcquireFuture`]，
     /// 在 `await` 期间挂起任务，直到许可可用。
     ///
     /// 成功完成 `await` 后，调用方已经获得 `n` 个许可，并会得到一个 [`SemaphoreGuard`]，
-    /// 在其被丢弃时自动归还许可。
+    /// 在其被丢弃时自动归还许可。信号量被关闭时返回 [`AcquireError::Closed`]。
+    ///
+    /// 等待者按排队顺序获取许可，请求许可较多的等待者不会被请求较少的后来者饿死，
+    /// 见 [`Semaphore`] 的公平性说明。
     ///
     /// ### 内存语义
     /// 成功获取许可具备 **Acquire** 语义。
@@ -178,7 +247,7 @@ impl Semaphore {
     /// ### 调度与等待行为
     /// - 此方法不会阻塞线程本身，而是通过 `Future` 协作式挂起任务（若运行时支持）。
     /// - 在裸机或无法挂起任务的环境中，本方法可能退化为自旋等待。
-    pub fn acquire(&self, n: usize) -> SemaphoreAcquireFuture<'_> {
+    pub fn acquire_many(&self, n: usize) -> SemaphoreAcquireFuture<'_> {
         SemaphoreAcquireFuture {
             semaphore: self,
             permits: n,
@@ -186,6 +255,30 @@ impl Semaphore {
         }
     }
 
+    /// 获取 `n` 个许可，`timeout` 完成时放弃等待并返回 [`AcquireError::Timeout`]。
+    ///
+    /// `timeout` 通常为运行时提供的定时器，如内核的 `async_task::sleep`：
+    /// ```ignore
+    /// let guard = semaphore
+    ///     .acquire_many_timeout(2, async_task::sleep(Duration::from_millis(100)))
+    ///     .await?;
+    /// ```
+    ///
+    /// 许可在超时的同时被分配给此请求时，仍返回获取到的许可。
+    pub fn acquire_many_timeout<F>(
+        &self,
+        n: usize,
+        timeout: F,
+    ) -> SemaphoreAcquireTimeoutFuture<'_, F>
+    where
+        F: Future<Output = ()>,
+    {
+        SemaphoreAcquireTimeoutFuture {
+            acquire: self.acquire_many(n),
+            timeout,
+        }
+    }
+
     /// 归还 `n` 个许可。
     ///
     /// 这会增加信号量中的可用许可数量，并可能唤醒一个或多个正在等待许可的任务/线程。
@@ -204,6 +297,7 @@ impl Semaphore {
             if status == SemaphoreWaker::STATUS_GIVEUP {
                 continue;
             }
+            // 队首许可不足时不唤醒后面的等待者，避免请求较多的等待者饿死
             if queue.0 < waker.permits {
                 queue.1.push_front(waker);
                 return;
@@ -216,7 +310,7 @@ impl Semaphore {
             );
             if acquired.is_ok() {
                 queue.0 -= waker.permits;
-                waker.waker.wake_by_ref();
+                waker.waker.lock().wake_by_ref();
             } else {
                 queue.1.push_front(waker);
                 return;
@@ -228,10 +322,48 @@ impl Semaphore {
         }
     }
 
-    fn queue(&self, waker: Arc<SemaphoreWaker>) {
+    /// 关闭信号量。
+    ///
+    /// 唤醒所有等待者并使其返回 [`AcquireError::Closed`]，此后的获取请求也立即返回错误。
+    /// 已获取的许可不受影响，仍可正常归还。
+    pub fn close(&self) {
+        let mut queue = self.queue.lock();
+        self.closed.store(true, Ordering::Release);
+        while let Some(waker) = queue.1.pop_front() {
+            let closed = waker.status.compare_exchange(
+                SemaphoreWaker::STATUS_WAITING,
+                SemaphoreWaker::STATUS_CLOSED,
+                Ordering::Release,
+                Ordering::Relaxed,
+            );
+            if closed.is_ok() {
+                waker.waker.lock().wake_by_ref();
+            }
+        }
+        self.permits.fetch_add(queue.0, Ordering::Release);
+        queue.0 = 0;
+    }
+
+    /// 信号量是否已关闭
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// 当前可用的许可数量，包括已分配给等待队列、尚未分给等待者的许可
+    pub fn available_permits(&self) -> usize {
+        let queue = self.queue.lock();
+        self.permits.load(Ordering::Acquire) + queue.0
+    }
+
+    /// 将等待者加入队列，信号量已关闭时返回false
+    fn queue(&self, waker: Arc<SemaphoreWaker>) -> bool {
         let mut lock = self.queue.lock();
+        if self.is_closed() {
+            return false;
+        }
         lock.0 += self.permits.swap(0, Ordering::AcqRel);
         lock.1.push_back(waker);
+        true
     }
 }
 
@@ -249,35 +381,74 @@ impl Drop for SemaphoreGuard<'_> {
     }
 }
 
+impl<'a> SemaphoreAcquireFuture<'a> {
+    /// 放弃等待，返回放弃前的状态，尚未排队时返回None
+    ///
+    /// 返回 `STATUS_ACQUIRED` 时许可已分配给此请求，由调用方负责归还或交给 [`SemaphoreGuard`]
+    fn give_up(&mut self) -> Option<u32> {
+        let waker = self.waker.take()?;
+        let status = waker
+            .status
+            .swap(SemaphoreWaker::STATUS_GIVEUP, Ordering::AcqRel);
+        if status == SemaphoreWaker::STATUS_WAITING {
+            // 触发一次队列检查，移除已放弃的等待者
+            self.semaphore.release(0);
+        }
+        Some(status)
+    }
+
+    fn guard(&mut self) -> SemaphoreGuard<'a> {
+        let permits = self.permits;
+        self.permits = 0;
+        SemaphoreGuard {
+            semaphore: self.semaphore,
+            permits,
+        }
+    }
+}
+
 impl<'a> Future for SemaphoreAcquireFuture<'a> {
-    type Output = SemaphoreGuard<'a>;
+    type Output = Result<SemaphoreGuard<'a>, AcquireError>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         match &self.waker {
             Some(waker) => {
-                if waker.status.load(Ordering::Acquire) == SemaphoreWaker::STATUS_ACQUIRED {
-                    let guard = SemaphoreGuard {
-                        semaphore: self.semaphore,
-                        permits: self.permits,
-                    };
-                    self.permits = 0;
-                    Poll::Ready(guard)
-                } else {
-                    Poll::Pending
+                // 先更新唤醒器再检查状态，避免错过在此期间发生的唤醒
+                {
+                    let mut stored = waker.waker.lock();
+                    if !stored.will_wake(cx.waker()) {
+                        *stored = cx.waker().clone();
+                    }
+                }
+                let status = waker.status.load(Ordering::Acquire);
+                match status {
+                    SemaphoreWaker::STATUS_ACQUIRED => {
+                        self.waker = None;
+                        Poll::Ready(Ok(self.guard()))
+                    }
+                    SemaphoreWaker::STATUS_CLOSED => {
+                        self.waker = None;
+                        Poll::Ready(Err(AcquireError::Closed))
+                    }
+                    _ => Poll::Pending,
                 }
             }
             None => {
-                if let Some(acquired) = self.semaphore.try_acquire(self.permits) {
-                    return Poll::Ready(acquired);
+                match self.semaphore.try_acquire_many(self.permits) {
+                    Ok(acquired) => return Poll::Ready(Ok(acquired)),
+                    Err(TryAcquireError::Closed) => return Poll::Ready(Err(AcquireError::Closed)),
+                    Err(TryAcquireError::NoPermits) => {}
                 }
 
                 let waker = Arc::new(SemaphoreWaker {
-                    waker: cx.waker().clone(),
+                    waker: SyncLock::new(cx.waker().clone()),
                     status: AtomicU32::new(SemaphoreWaker::STATUS_WAITING),
                     permits: self.permits,
                 });
 
-                self.semaphore.queue(waker.clone());
+                if !self.semaphore.queue(waker.clone()) {
+                    return Poll::Ready(Err(AcquireError::Closed));
+                }
 
                 self.waker = Some(waker);
 
@@ -292,15 +463,31 @@ impl<'a> Future for SemaphoreAcquireFuture<'a> {
 
 impl Drop for SemaphoreAcquireFuture<'_> {
     fn drop(&mut self) {
-        if let Some(waker) = &self.waker {
-            let status = waker
-                .status
-                .swap(SemaphoreWaker::STATUS_GIVEUP, Ordering::AcqRel);
-            if status == SemaphoreWaker::STATUS_ACQUIRED {
-                self.semaphore.release(self.permits);
-            } else {
-                self.semaphore.release(0);
-            }
+        if self.give_up() == Some(SemaphoreWaker::STATUS_ACQUIRED) {
+            self.semaphore.release(self.permits);
+        }
+    }
+}
+
+impl<'a, F: Future<Output = ()>> Future for SemaphoreAcquireTimeoutFuture<'a, F> {
+    type Output = Result<SemaphoreGuard<'a>, AcquireError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: timeout不会被移出，acquire是Unpin的
+        let this = unsafe { self.get_unchecked_mut() };
+        if let Poll::Ready(result) = Pin::new(&mut this.acquire).poll(cx) {
+            return Poll::Ready(result);
+        }
+        // Safety: timeout随self一起被固定
+        let timeout = unsafe { Pin::new_unchecked(&mut this.timeout) };
+        if timeout.poll(cx).is_pending() {
+            return Poll::Pending;
         }
+        Poll::Ready(match this.acquire.give_up() {
+            // 许可恰好在超时前被分配给此请求
+            Some(SemaphoreWaker::STATUS_ACQUIRED) => Ok(this.acquire.guard()),
+            Some(SemaphoreWaker::STATUS_CLOSED) => Err(AcquireError::Closed),
+            _ => Err(AcquireError::Timeout),
+        })
     }
 }
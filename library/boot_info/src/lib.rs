@@ -0,0 +1,166 @@
+#![no_std]
+
+//! 引导程序传递给内核的启动信息
+//!
+//! BIOS引导程序运行在32位模式下，UEFI引导程序与内核运行在64位模式下，
+//! 因此结构中的地址均使用u64，并按字段大小降序排列，保证在两种模式下布局一致。
+//! 除特别说明外，结构中的地址均为物理地址。
+
+use core::{slice, str};
+
+pub mod slot;
+
+/// 启动信息的magic
+pub const BOOT_INFO_MAGIC: u64 = u64::from_le_bytes(*b"COS_BOOT");
+/// 启动信息的版本，结构变化时递增
+pub const BOOT_INFO_VERSION: u32 = 3;
+
+/// 内核镜像在物理内存中的起始地址（2M，对齐Huge Page）
+pub const KERNEL_PHYSICAL_BASE: u64 = 0x20_0000;
+/// 内核镜像的起始虚拟地址，与kernel/linker.ld保持一致
+pub const KERNEL_IMAGE_BASE: u64 = 0xFFFF_FFFF_C000_0000;
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C, align(8))]
+pub struct BootInfo {
+    /// 固定为 [BOOT_INFO_MAGIC]
+    pub magic: u64,
+    /// 内存信息数组，已按地址排序且互不重叠
+    pub memory_region_ptr: u64,
+    pub memory_region_len: u64,
+    /// ACPI RSDP，为0时表示未找到
+    pub rsdp: u64,
+    /// 内核命令行，UTF-8编码，不以0结尾
+    pub cmdline_ptr: u64,
+    pub cmdline_len: u64,
+    /// 内核自身的ELF文件（含符号表），位于内核镜像之后，与内核一同映射。为0时表示未提供
+    pub kernel_elf_ptr: u64,
+    pub kernel_elf_len: u64,
+    /// 帧缓冲区，base为0时表示不可用（如VGA文本模式）
+    pub framebuffer: Framebuffer,
+    /// 固定为 [BOOT_INFO_VERSION]
+    pub version: u32,
+    /// 启动磁盘号，与BIOS磁盘号一致
+    pub startup_disk: u32,
+    /// 启动的内核槽位，见 [slot::Slot::boot_info_value]。为0时表示不是从槽位启动（如UEFI启动）
+    pub boot_slot: u32,
+    _reserved: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+pub struct MemoryRegion {
+    pub base_addr: u64,
+    pub length: u64,
+    pub region_type: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct Framebuffer {
+    pub base: u64,
+    /// 帧缓冲区大小，单位为字节
+    pub size: u64,
+    pub width: u32,
+    pub height: u32,
+    /// 每行的像素数，可能大于width
+    pub stride: u32,
+    /// 像素格式，见 [Framebuffer::FORMAT_RGB] 等
+    pub pixel_format: u32,
+}
+
+const _: () = {
+    assert!(size_of::<BootInfo>() == 112);
+    assert!(size_of::<MemoryRegion>() == 20);
+    assert!(size_of::<Framebuffer>() == 32);
+};
+
+impl BootInfo {
+    /// 仅包含magic与版本的启动信息，其他信息由引导程序填写
+    pub const EMPTY: Self = Self {
+        magic: BOOT_INFO_MAGIC,
+        memory_region_ptr: 0,
+        memory_region_len: 0,
+        rsdp: 0,
+        cmdline_ptr: 0,
+        cmdline_len: 0,
+        kernel_elf_ptr: 0,
+        kernel_elf_len: 0,
+        framebuffer: Framebuffer {
+            base: 0,
+            size: 0,
+            width: 0,
+            height: 0,
+            stride: 0,
+            pixel_format: 0,
+        },
+        version: BOOT_INFO_VERSION,
+        startup_disk: 0,
+        boot_slot: 0,
+        _reserved: 0,
+    };
+
+    /// 创建仅包含必要信息的启动信息，其他信息由引导程序按需填写
+    pub fn new(memory_region: &[MemoryRegion], startup_disk: u32) -> Self {
+        Self {
+            memory_region_ptr: memory_region.as_ptr() as usize as u64,
+            memory_region_len: memory_region.len() as u64,
+            startup_disk,
+            ..Self::EMPTY
+        }
+    }
+
+    /// magic与版本是否与内核一致
+    pub fn is_valid(&self) -> bool {
+        self.magic == BOOT_INFO_MAGIC && self.version == BOOT_INFO_VERSION
+    }
+
+    /// 内存信息
+    ///
+    /// # Safety
+    ///
+    /// 内存信息所在的物理内存必须已恒等映射
+    pub unsafe fn memory_regions(&self) -> &'static [MemoryRegion] {
+        unsafe {
+            slice::from_raw_parts(
+                self.memory_region_ptr as usize as *const MemoryRegion,
+                self.memory_region_len as usize,
+            )
+        }
+    }
+
+    /// 内核命令行，不是有效的UTF-8时返回空字符串
+    ///
+    /// # Safety
+    ///
+    /// 命令行所在的物理内存必须已恒等映射
+    pub unsafe fn cmdline(&self) -> &'static str {
+        if self.cmdline_len == 0 {
+            return "";
+        }
+        let bytes = unsafe {
+            slice::from_raw_parts(
+                self.cmdline_ptr as usize as *const u8,
+                self.cmdline_len as usize,
+            )
+        };
+        str::from_utf8(bytes).unwrap_or_default()
+    }
+
+    /// 帧缓冲区
+    pub fn framebuffer(&self) -> Option<Framebuffer> {
+        (self.framebuffer.base != 0).then_some(self.framebuffer)
+    }
+}
+
+impl MemoryRegion {
+    pub const TYPE_USABLE: u32 = 1;
+    pub const TYPE_RESERVED: u32 = 2;
+}
+
+impl Framebuffer {
+    /// 每像素4字节，内存中依次为R、G、B、保留
+    pub const FORMAT_RGB: u32 = 0;
+    /// 每像素4字节，内存中依次为B、G、R、保留
+    pub const FORMAT_BGR: u32 = 1;
+}
@@ -0,0 +1,229 @@
+//! A/B内核槽位与启动选择记录
+//!
+//! 根文件系统中保存两个内核槽位 [Slot::A]、[Slot::B]，以及记录当前槽位与状态的启动选择记录。
+//! 更新内核时写入未使用的槽位，并将其设为待启动状态；BIOS引导程序按以下规则选择槽位：
+//!
+//! * [SlotState::Confirmed]：启动当前槽位
+//! * [SlotState::Pending]：记录改为 [SlotState::Trying] 后启动当前槽位
+//! * [SlotState::Trying]：上次启动未能确认成功，切换到另一个槽位，记录改为 [SlotState::Confirmed]
+//!
+//! 内核完成初始化后，如果记录处于 [SlotState::Trying] 且当前槽位即为本次启动的槽位，
+//! 将记录改为 [SlotState::Confirmed]。
+//!
+//! 记录为 [RECORD_SIZE] 字节：magic、当前槽位（0为A，1为B）、状态（0为已确认、1为待启动、2为尝试中），
+//! 其余字节保留为0。
+
+/// 启动选择记录的路径
+pub const RECORD_PATH: &str = "/system/boot-slot";
+/// 启动选择记录的magic
+pub const RECORD_MAGIC: [u8; 8] = *b"COS_SLOT";
+/// 启动选择记录的大小
+pub const RECORD_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    A,
+    B,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotState {
+    /// 当前槽位已成功启动过
+    Confirmed,
+    /// 当前槽位刚写入新内核，尚未启动
+    Pending,
+    /// 当前槽位正在尝试启动，尚未确认成功
+    Trying,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotRecord {
+    pub active: Slot,
+    pub state: SlotState,
+}
+
+impl Slot {
+    /// 内核镜像在根文件系统中的路径
+    pub const fn path(self) -> &'static str {
+        match self {
+            Self::A => "/system/kernel-a.bin",
+            Self::B => "/system/kernel-b.bin",
+        }
+    }
+
+    /// 内核镜像的文件名，位于/system目录中
+    pub const fn file_name(self) -> &'static str {
+        match self {
+            Self::A => "kernel-a.bin",
+            Self::B => "kernel-b.bin",
+        }
+    }
+
+    /// 槽位名称
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::A => "a",
+            Self::B => "b",
+        }
+    }
+
+    pub const fn other(self) -> Self {
+        match self {
+            Self::A => Self::B,
+            Self::B => Self::A,
+        }
+    }
+
+    /// 写入 [crate::BootInfo::boot_slot] 的值
+    pub const fn boot_info_value(self) -> u32 {
+        match self {
+            Self::A => 1,
+            Self::B => 2,
+        }
+    }
+
+    /// 由 [crate::BootInfo::boot_slot] 得到槽位，不是从槽位启动时返回None
+    pub const fn from_boot_info_value(value: u32) -> Option<Self> {
+        match value {
+            1 => Some(Self::A),
+            2 => Some(Self::B),
+            _ => None,
+        }
+    }
+}
+
+impl SlotRecord {
+    /// 记录不存在或无效时使用的默认记录
+    pub const DEFAULT: Self = Self {
+        active: Slot::A,
+        state: SlotState::Confirmed,
+    };
+
+    /// 解析记录，magic或字段无效时返回None
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < RECORD_SIZE || bytes[..RECORD_MAGIC.len()] != RECORD_MAGIC {
+            return None;
+        }
+        let active = match bytes[8] {
+            0 => Slot::A,
+            1 => Slot::B,
+            _ => return None,
+        };
+        let state = match bytes[9] {
+            0 => SlotState::Confirmed,
+            1 => SlotState::Pending,
+            2 => SlotState::Trying,
+            _ => return None,
+        };
+        Some(Self { active, state })
+    }
+
+    pub fn to_bytes(self) -> [u8; RECORD_SIZE] {
+        let mut bytes = [0; RECORD_SIZE];
+        bytes[..RECORD_MAGIC.len()].copy_from_slice(&RECORD_MAGIC);
+        bytes[8] = match self.active {
+            Slot::A => 0,
+            Slot::B => 1,
+        };
+        bytes[9] = match self.state {
+            SlotState::Confirmed => 0,
+            SlotState::Pending => 1,
+            SlotState::Trying => 2,
+        };
+        bytes
+    }
+
+    /// 引导程序选择本次启动的槽位，返回槽位及需要写回的新记录，记录不变时为None
+    pub fn select(self) -> (Slot, Option<Self>) {
+        match self.state {
+            SlotState::Confirmed => (self.active, None),
+            SlotState::Pending => (
+                self.active,
+                Some(Self {
+                    state: SlotState::Trying,
+                    ..self
+                }),
+            ),
+            SlotState::Trying => {
+                let fallback = self.active.other();
+                (
+                    fallback,
+                    Some(Self {
+                        active: fallback,
+                        state: SlotState::Confirmed,
+                    }),
+                )
+            }
+        }
+    }
+
+    /// 内核启动成功后的新记录，无需修改时返回None
+    pub fn confirm(self, booted: Slot) -> Option<Self> {
+        (self.state == SlotState::Trying && self.active == booted).then_some(Self {
+            state: SlotState::Confirmed,
+            ..self
+        })
+    }
+
+    /// 从booted启动后，将新内核写入另一个槽位时的记录
+    pub fn installed(booted: Slot) -> Self {
+        Self {
+            active: booted.other(),
+            state: SlotState::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        for active in [Slot::A, Slot::B] {
+            for state in [SlotState::Confirmed, SlotState::Pending, SlotState::Trying] {
+                let record = SlotRecord { active, state };
+                assert_eq!(SlotRecord::parse(&record.to_bytes()), Some(record));
+            }
+        }
+        assert_eq!(SlotRecord::parse(&[0; RECORD_SIZE]), None);
+        assert_eq!(
+            SlotRecord::parse(&SlotRecord::DEFAULT.to_bytes()[..8]),
+            None
+        );
+        let mut bytes = SlotRecord::DEFAULT.to_bytes();
+        bytes[9] = 3;
+        assert_eq!(SlotRecord::parse(&bytes), None);
+    }
+
+    #[test]
+    fn test_update_flow() {
+        // 从A启动时安装新内核到B
+        let record = SlotRecord::installed(Slot::A);
+        assert_eq!(record.active, Slot::B);
+        assert_eq!(record.confirm(Slot::A), None);
+
+        // 第一次启动B
+        let (slot, record) = record.select();
+        let record = record.unwrap();
+        assert_eq!(slot, Slot::B);
+        assert_eq!(record.state, SlotState::Trying);
+
+        // B启动成功
+        let confirmed = record.confirm(Slot::B).unwrap();
+        assert_eq!(
+            confirmed,
+            SlotRecord {
+                active: Slot::B,
+                state: SlotState::Confirmed,
+            }
+        );
+        assert_eq!(confirmed.select(), (Slot::B, None));
+        assert_eq!(confirmed.confirm(Slot::B), None);
+
+        // B未能确认成功，回退到A
+        let (slot, record) = record.select();
+        assert_eq!(slot, Slot::A);
+        assert_eq!(record, Some(SlotRecord::DEFAULT));
+    }
+}
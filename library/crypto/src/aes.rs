@@ -0,0 +1,244 @@
+use crate::zeroize;
+
+/// 分组长度，单位为字节
+pub const BLOCK_SIZE: usize = 16;
+
+/// AES分组密码
+///
+/// 支持128、192、256位密钥，分别对应10、12、14轮。释放时清除轮密钥
+pub struct Aes {
+    round_keys: [[u8; BLOCK_SIZE]; 15],
+    rounds: usize,
+}
+
+const fn gmul(mut a: u8, mut b: u8) -> u8 {
+    let mut result = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            result ^= a;
+        }
+        let carry = a & 0x80;
+        a <<= 1;
+        if carry != 0 {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    result
+}
+
+/// 在编译期生成S盒和逆S盒
+const fn build_sbox() -> ([u8; 256], [u8; 256]) {
+    let mut sbox = [0u8; 256];
+    let mut inv_sbox = [0u8; 256];
+    let mut x = 0;
+    while x < 256 {
+        // GF(2^8)上的乘法逆元，即x^254
+        let mut inverse = 1u8;
+        let mut i = 0;
+        while i < 254 {
+            inverse = gmul(inverse, x as u8);
+            i += 1;
+        }
+        let b = inverse;
+        let value =
+            b ^ b.rotate_left(1) ^ b.rotate_left(2) ^ b.rotate_left(3) ^ b.rotate_left(4) ^ 0x63;
+        sbox[x] = value;
+        inv_sbox[value as usize] = x as u8;
+        x += 1;
+    }
+    (sbox, inv_sbox)
+}
+
+const SBOXES: ([u8; 256], [u8; 256]) = build_sbox();
+const SBOX: [u8; 256] = SBOXES.0;
+const INV_SBOX: [u8; 256] = SBOXES.1;
+const RCON: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+impl Aes {
+    /// 使用密钥创建AES实例
+    ///
+    /// 密钥长度必须为16、24或32字节，否则返回`None`
+    pub fn new(key: &[u8]) -> Option<Self> {
+        let key_words = match key.len() {
+            16 | 24 | 32 => key.len() / 4,
+            _ => return None,
+        };
+        let rounds = key_words + 6;
+
+        // 密钥扩展
+        let mut words = [[0u8; 4]; 60];
+        for (i, word) in key.chunks_exact(4).enumerate() {
+            words[i].copy_from_slice(word);
+        }
+        for i in key_words..4 * (rounds + 1) {
+            let mut temp = words[i - 1];
+            if i % key_words == 0 {
+                temp.rotate_left(1);
+                temp = temp.map(|byte| SBOX[byte as usize]);
+                temp[0] ^= RCON[i / key_words - 1];
+            } else if key_words > 6 && i % key_words == 4 {
+                temp = temp.map(|byte| SBOX[byte as usize]);
+            }
+            for j in 0..4 {
+                words[i][j] = words[i - key_words][j] ^ temp[j];
+            }
+        }
+
+        let mut round_keys = [[0u8; BLOCK_SIZE]; 15];
+        for (i, round_key) in round_keys.iter_mut().enumerate().take(rounds + 1) {
+            for j in 0..4 {
+                round_key[j * 4..j * 4 + 4].copy_from_slice(&words[i * 4 + j]);
+            }
+        }
+
+        zeroize(words.as_flattened_mut());
+        Some(Self { round_keys, rounds })
+    }
+
+    /// 加密一个分组
+    pub fn encrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+        add_round_key(block, &self.round_keys[0]);
+        for round in 1..self.rounds {
+            sub_bytes(block, &SBOX);
+            shift_rows(block);
+            mix_columns(block);
+            add_round_key(block, &self.round_keys[round]);
+        }
+        sub_bytes(block, &SBOX);
+        shift_rows(block);
+        add_round_key(block, &self.round_keys[self.rounds]);
+    }
+
+    /// 解密一个分组
+    pub fn decrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+        add_round_key(block, &self.round_keys[self.rounds]);
+        for round in (1..self.rounds).rev() {
+            inv_shift_rows(block);
+            sub_bytes(block, &INV_SBOX);
+            add_round_key(block, &self.round_keys[round]);
+            inv_mix_columns(block);
+        }
+        inv_shift_rows(block);
+        sub_bytes(block, &INV_SBOX);
+        add_round_key(block, &self.round_keys[0]);
+    }
+}
+
+impl Drop for Aes {
+    fn drop(&mut self) {
+        zeroize(self.round_keys.as_flattened_mut());
+    }
+}
+
+// 状态按列存储：state[row + 4 * column]
+
+fn add_round_key(state: &mut [u8; BLOCK_SIZE], round_key: &[u8; BLOCK_SIZE]) {
+    for (byte, key) in state.iter_mut().zip(round_key) {
+        *byte ^= key;
+    }
+}
+
+fn sub_bytes(state: &mut [u8; BLOCK_SIZE], sbox: &[u8; 256]) {
+    for byte in state.iter_mut() {
+        *byte = sbox[*byte as usize];
+    }
+}
+
+fn shift_rows(state: &mut [u8; BLOCK_SIZE]) {
+    let old = *state;
+    for row in 1..4 {
+        for column in 0..4 {
+            state[row + 4 * column] = old[row + 4 * ((column + row) % 4)];
+        }
+    }
+}
+
+fn inv_shift_rows(state: &mut [u8; BLOCK_SIZE]) {
+    let old = *state;
+    for row in 1..4 {
+        for column in 0..4 {
+            state[row + 4 * ((column + row) % 4)] = old[row + 4 * column];
+        }
+    }
+}
+
+fn mix_columns(state: &mut [u8; BLOCK_SIZE]) {
+    for column in state.chunks_exact_mut(4) {
+        let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+        column[0] = gmul(a0, 2) ^ gmul(a1, 3) ^ a2 ^ a3;
+        column[1] = a0 ^ gmul(a1, 2) ^ gmul(a2, 3) ^ a3;
+        column[2] = a0 ^ a1 ^ gmul(a2, 2) ^ gmul(a3, 3);
+        column[3] = gmul(a0, 3) ^ a1 ^ a2 ^ gmul(a3, 2);
+    }
+}
+
+fn inv_mix_columns(state: &mut [u8; BLOCK_SIZE]) {
+    for column in state.chunks_exact_mut(4) {
+        let [a0, a1, a2, a3] = [column[0], column[1], column[2], column[3]];
+        column[0] = gmul(a0, 14) ^ gmul(a1, 11) ^ gmul(a2, 13) ^ gmul(a3, 9);
+        column[1] = gmul(a0, 9) ^ gmul(a1, 14) ^ gmul(a2, 11) ^ gmul(a3, 13);
+        column[2] = gmul(a0, 13) ^ gmul(a1, 9) ^ gmul(a2, 14) ^ gmul(a3, 11);
+        column[3] = gmul(a0, 11) ^ gmul(a1, 13) ^ gmul(a2, 9) ^ gmul(a3, 14);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::aes::Aes;
+
+    const PLAINTEXT: [u8; 16] = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+        0xff,
+    ];
+
+    fn check(key_len: usize, expected: [u8; 16]) {
+        let key = core::array::from_fn::<u8, 32, _>(|i| i as u8);
+        let aes = Aes::new(&key[..key_len]).unwrap();
+        let mut block = PLAINTEXT;
+        aes.encrypt_block(&mut block);
+        assert_eq!(block, expected);
+        aes.decrypt_block(&mut block);
+        assert_eq!(block, PLAINTEXT);
+    }
+
+    // FIPS-197 附录C
+    #[test]
+    fn test_aes128() {
+        check(
+            16,
+            [
+                0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4,
+                0xc5, 0x5a,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_aes192() {
+        check(
+            24,
+            [
+                0xdd, 0xa9, 0x7c, 0xa4, 0x86, 0x4c, 0xdf, 0xe0, 0x6e, 0xaf, 0x70, 0xa0, 0xec, 0x0d,
+                0x71, 0x91,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_aes256() {
+        check(
+            32,
+            [
+                0x8e, 0xa2, 0xb7, 0xca, 0x51, 0x67, 0x45, 0xbf, 0xea, 0xfc, 0x49, 0x90, 0x4b, 0x49,
+                0x60, 0x89,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_invalid_key() {
+        assert!(Aes::new(&[0; 15]).is_none());
+        assert!(Aes::new(&[0; 64]).is_none());
+    }
+}
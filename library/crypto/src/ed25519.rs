@@ -0,0 +1,539 @@
+//! Ed25519签名（RFC 8032）
+//!
+//! 用于只需在验证方保存公钥的场景，如内核模块签名：签名方持有32字节的私钥，
+//! 验证方只需要由私钥导出的公钥。实现为可变时间，不应在签名方可被观测计时的环境中签名
+
+use crate::{sha512, zeroize};
+
+/// 公钥长度，单位为字节
+pub const PUBLIC_KEY_SIZE: usize = 32;
+/// 私钥（种子）长度，单位为字节
+pub const SECRET_KEY_SIZE: usize = 32;
+/// 签名长度，单位为字节
+pub const SIGNATURE_SIZE: usize = 64;
+
+/// 由私钥导出公钥
+pub fn public_key(secret: &[u8; SECRET_KEY_SIZE]) -> [u8; PUBLIC_KEY_SIZE] {
+    let mut expanded = sha512::sha512(secret);
+    let mut scalar = clamp(&expanded);
+    let public = Point::base().mul(&scalar).compress();
+    zeroize(&mut expanded);
+    zeroize(&mut scalar);
+    public
+}
+
+/// 以私钥对消息签名
+pub fn sign(secret: &[u8; SECRET_KEY_SIZE], message: &[u8]) -> [u8; SIGNATURE_SIZE] {
+    let mut expanded = sha512::sha512(secret);
+    let mut scalar = clamp(&expanded);
+    let public = Point::base().mul(&scalar).compress();
+
+    // r = SHA-512(私钥摘要的后半部分 || 消息) mod L
+    let mut hasher = sha512::Sha512::new();
+    hasher.update(&expanded[32..]);
+    hasher.update(message);
+    let r = reduce_wide(&hasher.finalize());
+    let encoded_r = Point::base().mul(&r).compress();
+
+    // S = (r + SHA-512(R || A || 消息) * a) mod L
+    let k = challenge(&encoded_r, &public, message);
+    let s = mul_add(&k, &scalar, &r);
+    zeroize(&mut expanded);
+    zeroize(&mut scalar);
+
+    let mut signature = [0; SIGNATURE_SIZE];
+    signature[..32].copy_from_slice(&encoded_r);
+    signature[32..].copy_from_slice(&s);
+    signature
+}
+
+/// 以公钥验证消息的签名
+pub fn verify(
+    public: &[u8; PUBLIC_KEY_SIZE],
+    message: &[u8],
+    signature: &[u8; SIGNATURE_SIZE],
+) -> bool {
+    let encoded_r: &[u8; 32] = signature[..32].try_into().unwrap();
+    let s: &[u8; 32] = signature[32..].try_into().unwrap();
+    if !is_canonical(s) {
+        return false;
+    }
+    let Some(a) = Point::decompress(public) else {
+        return false;
+    };
+    // [S]B = R + [k]A，即 [S]B + [k](-A) 的编码与R相同
+    let k = challenge(encoded_r, public, message);
+    let check = Point::base().mul(s).add(&a.neg().mul(&k));
+    check.compress() == *encoded_r
+}
+
+/// k = SHA-512(R || A || 消息) mod L
+fn challenge(encoded_r: &[u8; 32], public: &[u8; 32], message: &[u8]) -> [u8; 32] {
+    let mut hasher = sha512::Sha512::new();
+    hasher.update(encoded_r);
+    hasher.update(public);
+    hasher.update(message);
+    reduce_wide(&hasher.finalize())
+}
+
+/// 由私钥摘要的前半部分得到私有标量：清除低3位与最高位，置位第254位
+fn clamp(expanded: &[u8; 64]) -> [u8; 32] {
+    let mut scalar: [u8; 32] = expanded[..32].try_into().unwrap();
+    scalar[0] &= 0xf8;
+    scalar[31] &= 0x7f;
+    scalar[31] |= 0x40;
+    scalar
+}
+
+// ---- 模 L = 2^252 + 27742317777372353535851937790883648493 的标量运算 ----
+
+/// 基点的阶L，小端序的64位字
+const L: [u64; 4] = [
+    0x5812631a5cf5d3ed,
+    0x14def9dea2f79cd6,
+    0,
+    0x1000000000000000,
+];
+
+/// 标量是否小于L
+fn is_canonical(scalar: &[u8; 32]) -> bool {
+    let words = words_of(scalar);
+    for index in (0..4).rev() {
+        if words[index] != L[index] {
+            return words[index] < L[index];
+        }
+    }
+    false
+}
+
+/// 将小端序的512位整数对L取模
+///
+/// 从最高位开始逐位移入累加值，累加值不小于L时减去L。累加值总是小于L，左移后不会溢出
+fn reduce_wide(value: &[u8; 64]) -> [u8; 32] {
+    let mut acc = [0u64; 4];
+    for bit in (0..512).rev() {
+        let carry_in = ((value[bit / 8] >> (bit % 8)) & 1) as u64;
+        acc[3] = (acc[3] << 1) | (acc[2] >> 63);
+        acc[2] = (acc[2] << 1) | (acc[1] >> 63);
+        acc[1] = (acc[1] << 1) | (acc[0] >> 63);
+        acc[0] = (acc[0] << 1) | carry_in;
+        if !less_than_l(&acc) {
+            let mut borrow = 0;
+            for (word, l) in acc.iter_mut().zip(L) {
+                let (difference, borrow1) = word.overflowing_sub(l);
+                let (difference, borrow2) = difference.overflowing_sub(borrow);
+                *word = difference;
+                borrow = (borrow1 | borrow2) as u64;
+            }
+        }
+    }
+    let mut bytes = [0; 32];
+    for (chunk, word) in bytes.chunks_exact_mut(8).zip(acc) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    bytes
+}
+
+fn less_than_l(words: &[u64; 4]) -> bool {
+    for index in (0..4).rev() {
+        if words[index] != L[index] {
+            return words[index] < L[index];
+        }
+    }
+    false
+}
+
+/// (a * b + c) mod L
+fn mul_add(a: &[u8; 32], b: &[u8; 32], c: &[u8; 32]) -> [u8; 32] {
+    let (a, b, c) = (words_of(a), words_of(b), words_of(c));
+    let mut product = [0u64; 8];
+    for i in 0..4 {
+        let mut carry = 0u128;
+        for j in 0..4 {
+            let sum = product[i + j] as u128 + a[i] as u128 * b[j] as u128 + carry;
+            product[i + j] = sum as u64;
+            carry = sum >> 64;
+        }
+        product[i + 4] = carry as u64;
+    }
+    let mut carry = 0u128;
+    for (index, word) in product.iter_mut().enumerate() {
+        let sum = *word as u128 + c.get(index).copied().unwrap_or(0) as u128 + carry;
+        *word = sum as u64;
+        carry = sum >> 64;
+    }
+    let mut wide = [0; 64];
+    for (chunk, word) in wide.chunks_exact_mut(8).zip(product) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    reduce_wide(&wide)
+}
+
+fn words_of(bytes: &[u8; 32]) -> [u64; 4] {
+    core::array::from_fn(|index| u64::from_le_bytes(bytes[index * 8..][..8].try_into().unwrap()))
+}
+
+// ---- 模 p = 2^255 - 19 的域运算，以5个51位的字表示 ----
+
+const MASK: u64 = (1 << 51) - 1;
+
+/// 域元素，各字可能略大于51位，编码前完全约减
+#[derive(Clone, Copy)]
+struct Fe([u64; 5]);
+
+/// 曲线参数d = -121665/121666
+const D: [u8; 32] = [
+    0xa3, 0x78, 0x59, 0x13, 0xca, 0x4d, 0xeb, 0x75, 0xab, 0xd8, 0x41, 0x41, 0x4d, 0x0a, 0x70, 0x00,
+    0x98, 0xe8, 0x79, 0x77, 0x79, 0x40, 0xc7, 0x8c, 0x73, 0xfe, 0x6f, 0x2b, 0xee, 0x6c, 0x03, 0x52,
+];
+/// -1的平方根 2^((p-1)/4)
+const SQRT_M1: [u8; 32] = [
+    0xb0, 0xa0, 0x0e, 0x4a, 0x27, 0x1b, 0xee, 0xc4, 0x78, 0xe4, 0x2f, 0xad, 0x06, 0x18, 0x43, 0x2f,
+    0xa7, 0xd7, 0xfb, 0x3d, 0x99, 0x00, 0x4d, 0x2b, 0x0b, 0xdf, 0xc1, 0x4f, 0x80, 0x24, 0x83, 0x2b,
+];
+/// 基点的编码，y = 4/5，x为偶数
+const BASE: [u8; 32] = [
+    0x58, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+    0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+];
+/// 求逆所用的指数 p - 2 = 2^255 - 21，小端序
+const P_MINUS_2: [u8; 32] = exponent(0xeb, 0x7f);
+/// 求平方根所用的指数 (p - 5) / 8 = 2^252 - 3，小端序
+const P_MINUS_5_DIV_8: [u8; 32] = exponent(0xfd, 0x0f);
+
+/// 最低字节为low、最高字节为high、其余字节为0xff的指数
+const fn exponent(low: u8, high: u8) -> [u8; 32] {
+    let mut bytes = [0xff; 32];
+    bytes[0] = low;
+    bytes[31] = high;
+    bytes
+}
+
+impl Fe {
+    const ZERO: Self = Self([0; 5]);
+    const ONE: Self = Self([1, 0, 0, 0, 0]);
+
+    /// 解码小端序的32字节，忽略最高位
+    fn from_bytes(bytes: &[u8; 32]) -> Self {
+        let load = |index: usize| u64::from_le_bytes(bytes[index..index + 8].try_into().unwrap());
+        Self([
+            load(0) & MASK,
+            (load(6) >> 3) & MASK,
+            (load(12) >> 6) & MASK,
+            (load(19) >> 1) & MASK,
+            (load(24) >> 12) & MASK,
+        ])
+    }
+
+    /// 完全约减到 [0, p) 后编码为小端序的32字节
+    fn to_bytes(self) -> [u8; 32] {
+        let mut limbs = self.carry().carry().0;
+        // limbs + 19 不小于 2^255 时，值不小于p，需减去p
+        let mut q = (limbs[0] + 19) >> 51;
+        for limb in &limbs[1..] {
+            q = (limb + q) >> 51;
+        }
+        limbs[0] += 19 * q;
+        for index in 0..4 {
+            limbs[index + 1] += limbs[index] >> 51;
+            limbs[index] &= MASK;
+        }
+        limbs[4] &= MASK;
+
+        let [l0, l1, l2, l3, l4] = limbs;
+        let words = [
+            l0 | (l1 << 51),
+            (l1 >> 13) | (l2 << 38),
+            (l2 >> 26) | (l3 << 25),
+            (l3 >> 39) | (l4 << 12),
+        ];
+        let mut bytes = [0; 32];
+        for (chunk, word) in bytes.chunks_exact_mut(8).zip(words) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// 将各字超出51位的部分进位到下一字，最高字的进位乘以19加到最低字
+    fn carry(self) -> Self {
+        let mut limbs = self.0;
+        for index in 0..4 {
+            limbs[index + 1] += limbs[index] >> 51;
+            limbs[index] &= MASK;
+        }
+        limbs[0] += (limbs[4] >> 51) * 19;
+        limbs[4] &= MASK;
+        Self(limbs)
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        Self(core::array::from_fn(|index| self.0[index] + other.0[index])).carry()
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        // 先加上2p，避免下溢
+        const TWO_P: [u64; 5] = [2 * (MASK - 18), 2 * MASK, 2 * MASK, 2 * MASK, 2 * MASK];
+        let other = other.carry();
+        Self(core::array::from_fn(|index| {
+            self.0[index] + TWO_P[index] - other.0[index]
+        }))
+        .carry()
+    }
+
+    fn neg(&self) -> Self {
+        Self::ZERO.sub(self)
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        let [a0, a1, a2, a3, a4] = self.0;
+        let [b0, b1, b2, b3, b4] = other.0;
+        let m = |a: u64, b: u64| a as u128 * b as u128;
+        // 2^255 = 19 (mod p)，超出最高字的部分乘以19
+        let (b1_19, b2_19, b3_19, b4_19) = (b1 * 19, b2 * 19, b3 * 19, b4 * 19);
+        let r0 = m(a0, b0) + m(a1, b4_19) + m(a2, b3_19) + m(a3, b2_19) + m(a4, b1_19);
+        let r1 = m(a0, b1) + m(a1, b0) + m(a2, b4_19) + m(a3, b3_19) + m(a4, b2_19);
+        let r2 = m(a0, b2) + m(a1, b1) + m(a2, b0) + m(a3, b4_19) + m(a4, b3_19);
+        let r3 = m(a0, b3) + m(a1, b2) + m(a2, b1) + m(a3, b0) + m(a4, b4_19);
+        let r4 = m(a0, b4) + m(a1, b3) + m(a2, b2) + m(a3, b1) + m(a4, b0);
+
+        let mask = MASK as u128;
+        let r1 = r1 + (r0 >> 51);
+        let r2 = r2 + (r1 >> 51);
+        let r3 = r3 + (r2 >> 51);
+        let r4 = r4 + (r3 >> 51);
+        let r0 = (r0 & mask) + (r4 >> 51) * 19;
+        Self([
+            (r0 & mask) as u64,
+            ((r1 & mask) + (r0 >> 51)) as u64,
+            (r2 & mask) as u64,
+            (r3 & mask) as u64,
+            (r4 & mask) as u64,
+        ])
+        .carry()
+    }
+
+    fn square(&self) -> Self {
+        self.mul(self)
+    }
+
+    /// 计算self的exponent次方，exponent为小端序
+    fn pow(&self, exponent: &[u8; 32]) -> Self {
+        let mut result = Self::ONE;
+        for bit in (0..256).rev() {
+            result = result.square();
+            if (exponent[bit / 8] >> (bit % 8)) & 1 == 1 {
+                result = result.mul(self);
+            }
+        }
+        result
+    }
+
+    fn invert(&self) -> Self {
+        self.pow(&P_MINUS_2)
+    }
+
+    /// 完全约减后的最低位，作为x坐标的符号
+    fn is_negative(&self) -> bool {
+        self.to_bytes()[0] & 1 == 1
+    }
+
+    fn equals(&self, other: &Self) -> bool {
+        self.to_bytes() == other.to_bytes()
+    }
+}
+
+// ---- 扭曲爱德华兹曲线 -x^2 + y^2 = 1 + d x^2 y^2 上的点 ----
+
+/// 扩展坐标 (X:Y:Z:T) 表示的点，x = X/Z，y = Y/Z，xy = T/Z
+#[derive(Clone, Copy)]
+struct Point {
+    x: Fe,
+    y: Fe,
+    z: Fe,
+    t: Fe,
+}
+
+impl Point {
+    const IDENTITY: Self = Self {
+        x: Fe::ZERO,
+        y: Fe::ONE,
+        z: Fe::ONE,
+        t: Fe::ZERO,
+    };
+
+    fn base() -> Self {
+        Self::decompress(&BASE).expect("codebug: base point should be valid")
+    }
+
+    /// 解码点，编码不是曲线上的点时返回None
+    fn decompress(bytes: &[u8; 32]) -> Option<Self> {
+        let sign = bytes[31] >> 7 == 1;
+        let y = Fe::from_bytes(bytes);
+        // y须小于p
+        let mut canonical = *bytes;
+        canonical[31] &= 0x7f;
+        if y.to_bytes() != canonical {
+            return None;
+        }
+
+        // x^2 = (y^2 - 1) / (d y^2 + 1) = u / v
+        let yy = y.square();
+        let u = yy.sub(&Fe::ONE);
+        let v = Fe::from_bytes(&D).mul(&yy).add(&Fe::ONE);
+        // x = u v^3 (u v^7)^((p-5)/8)
+        let v3 = v.square().mul(&v);
+        let v7 = v3.square().mul(&v);
+        let mut x = u.mul(&v3).mul(&u.mul(&v7).pow(&P_MINUS_5_DIV_8));
+        let vxx = v.mul(&x.square());
+        if !vxx.equals(&u) {
+            if !vxx.equals(&u.neg()) {
+                return None;
+            }
+            x = x.mul(&Fe::from_bytes(&SQRT_M1));
+        }
+        if sign && x.equals(&Fe::ZERO) {
+            return None;
+        }
+        if x.is_negative() != sign {
+            x = x.neg();
+        }
+        Some(Self {
+            x,
+            y,
+            z: Fe::ONE,
+            t: x.mul(&y),
+        })
+    }
+
+    fn compress(&self) -> [u8; 32] {
+        let z_inverse = self.z.invert();
+        let x = self.x.mul(&z_inverse);
+        let y = self.y.mul(&z_inverse);
+        let mut bytes = y.to_bytes();
+        bytes[31] |= (x.is_negative() as u8) << 7;
+        bytes
+    }
+
+    /// 点加法，对相同的点同样适用
+    fn add(&self, other: &Self) -> Self {
+        let d2 = {
+            let d = Fe::from_bytes(&D);
+            d.add(&d)
+        };
+        let a = self.y.sub(&self.x).mul(&other.y.sub(&other.x));
+        let b = self.y.add(&self.x).mul(&other.y.add(&other.x));
+        let c = self.t.mul(&d2).mul(&other.t);
+        let d = {
+            let zz = self.z.mul(&other.z);
+            zz.add(&zz)
+        };
+        let (e, f, g, h) = (b.sub(&a), d.sub(&c), d.add(&c), b.add(&a));
+        Self {
+            x: e.mul(&f),
+            y: g.mul(&h),
+            z: f.mul(&g),
+            t: e.mul(&h),
+        }
+    }
+
+    fn neg(&self) -> Self {
+        Self {
+            x: self.x.neg(),
+            y: self.y,
+            z: self.z,
+            t: self.t.neg(),
+        }
+    }
+
+    /// 标量乘法，scalar为小端序
+    fn mul(&self, scalar: &[u8; 32]) -> Self {
+        let mut result = Self::IDENTITY;
+        for bit in (0..256).rev() {
+            result = result.add(&result);
+            if (scalar[bit / 8] >> (bit % 8)) & 1 == 1 {
+                result = result.add(self);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ed25519::{Fe, Point, SQRT_M1, public_key, sign, verify},
+        sha256::hex,
+    };
+
+    fn bytes<const N: usize>(hex: &str) -> [u8; N] {
+        core::array::from_fn(|index| {
+            u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).unwrap()
+        })
+    }
+
+    #[test]
+    fn test_constants() {
+        let sqrt_m1 = Fe::from_bytes(&SQRT_M1);
+        assert!(sqrt_m1.square().equals(&Fe::ONE.neg()));
+        let d = Fe::from_bytes(&super::D);
+        let expected = Fe([121665, 0, 0, 0, 0])
+            .neg()
+            .mul(&Fe([121666, 0, 0, 0, 0]).invert());
+        assert!(d.equals(&expected));
+        assert_eq!(Point::base().compress(), super::BASE);
+    }
+
+    /// RFC 8032 7.1节的测试向量
+    #[test]
+    fn test_rfc8032() {
+        let vectors: [(&str, &str, &[u8], &str); 3] = [
+            (
+                "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+                "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+                b"",
+                "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155\
+                 5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+            ),
+            (
+                "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+                "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+                b"\x72",
+                "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da\
+                 085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+            ),
+            (
+                "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+                "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+                b"\xaf\x82",
+                "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac\
+                 18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+            ),
+        ];
+        for (secret, public, message, signature) in vectors {
+            let secret = bytes(secret);
+            let public = bytes(public);
+            assert_eq!(public_key(&secret), public);
+            let signed = sign(&secret, message);
+            assert_eq!(hex(&signed), signature);
+            assert!(verify(&public, message, &signed));
+        }
+    }
+
+    #[test]
+    fn test_verify_rejects() {
+        let secret = [7; 32];
+        let public = public_key(&secret);
+        let signature = sign(&secret, b"module");
+        assert!(verify(&public, b"module", &signature));
+        assert!(!verify(&public, b"modulE", &signature));
+        assert!(!verify(&public_key(&[8; 32]), b"module", &signature));
+
+        let mut tampered = signature;
+        tampered[0] ^= 1;
+        assert!(!verify(&public, b"module", &tampered));
+        // S不小于L时拒绝
+        let mut large_s = signature;
+        large_s[63] |= 0xf0;
+        assert!(!verify(&public, b"module", &large_s));
+    }
+}
@@ -0,0 +1,32 @@
+//! 密码学原语
+//!
+//! 此crate提供块设备加密等场景所需的基础算法，不依赖堆分配：
+//! - [`aes`]：AES分组密码（128/192/256位密钥）
+//! - [`xts`]：基于AES的XTS模式，用于按扇区加密
+//! - [`sha256`]、[`hmac`]、[`pbkdf2`]：SHA-256摘要、HMAC-SHA256与基于口令的密钥派生
+//! - [`sha512`]、[`ed25519`]：SHA-512摘要与Ed25519签名，用于只在验证方保存公钥的签名校验
+//!
+//! 注意：这些实现以正确性和可读性为目标，没有针对侧信道攻击（如基于查表时间的攻击）进行防护。
+#![no_std]
+
+#[cfg(test)]
+extern crate std;
+
+pub mod aes;
+pub mod ed25519;
+pub mod hmac;
+pub mod pbkdf2;
+pub mod sha256;
+pub mod sha512;
+pub mod xts;
+
+/// 将保存密钥等敏感数据的缓冲区清零
+///
+/// 使用volatile写入，避免编译器因缓冲区之后不再被读取而省略清零
+pub fn zeroize(buf: &mut [u8]) {
+    for byte in buf {
+        // Safety: byte来自可变引用，地址有效且已对齐
+        unsafe { core::ptr::write_volatile(byte, 0) };
+    }
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+}
@@ -0,0 +1,65 @@
+use crate::{hmac::HmacSha256, sha256::DIGEST_SIZE, zeroize};
+
+/// 使用PBKDF2-HMAC-SHA256从口令派生密钥
+///
+/// 派生的密钥写入`out`，长度由`out`决定。`iterations`越大，暴力破解口令的成本越高，派生也越慢。
+pub fn pbkdf2_hmac_sha256(password: &[u8], salt: &[u8], iterations: u32, out: &mut [u8]) {
+    assert!(iterations > 0);
+    let prf = HmacSha256::new(password);
+
+    for (index, chunk) in out.chunks_mut(DIGEST_SIZE).enumerate() {
+        // U1 = PRF(P, S || INT(i))
+        let mut mac = prf.clone();
+        mac.update(salt);
+        mac.update(&(index as u32 + 1).to_be_bytes());
+        let mut u = mac.finalize();
+        let mut block = u;
+
+        // Uj = PRF(P, Uj-1)
+        for _ in 1..iterations {
+            let mut mac = prf.clone();
+            mac.update(&u);
+            u = mac.finalize();
+            for (byte, value) in block.iter_mut().zip(u) {
+                *byte ^= value;
+            }
+        }
+
+        chunk.copy_from_slice(&block[..chunk.len()]);
+        zeroize(&mut u);
+        zeroize(&mut block);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{pbkdf2::pbkdf2_hmac_sha256, sha256::hex};
+
+    #[test]
+    fn test_pbkdf2_hmac_sha256() {
+        let mut out = [0u8; 32];
+        pbkdf2_hmac_sha256(b"password", b"salt", 1, &mut out);
+        assert_eq!(
+            hex(&out),
+            "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"
+        );
+        pbkdf2_hmac_sha256(b"password", b"salt", 2, &mut out);
+        assert_eq!(
+            hex(&out),
+            "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43"
+        );
+
+        // 输出超过一个摘要长度
+        let mut out = [0u8; 40];
+        pbkdf2_hmac_sha256(
+            b"passwordPASSWORDpassword",
+            b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+            4096,
+            &mut out,
+        );
+        assert_eq!(
+            hex(&out),
+            "348c89dbcbd32b2f32d814b8116e84cf2b17347ebc1800181c4e2a1fb8dd53e1c635518c7dac47e9"
+        );
+    }
+}
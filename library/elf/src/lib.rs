@@ -2,6 +2,8 @@
 
 extern crate alloc;
 
+pub mod relocatable;
+
 use alloc::vec::Vec;
 use async_io::{AsyncRead, AsyncReadExt, ReadExactError, Seekable};
 
@@ -22,12 +24,33 @@ pub struct ElfHeader {
 }
 
 pub struct ElfProgram {
-    program_type: u32, // 类型，1LOAD/2DYNAMIC/3INTERP/4NOTE
+    program_type: u32, // 类型，1LOAD/2DYNAMIC/3INTERP/4NOTE/7TLS
     flag: u32,         // 标志，1X/2W/3R
     p_offset: u64,
     p_vaddr: u64,
     p_filesz: u64,
     p_memsz: u64,
+    p_align: u64,
+}
+
+/// 线程局部存储段（PT_TLS）
+///
+/// 初始化镜像位于某个LOAD段内，加载后可从 [ElfTls::vaddr] 读取。
+/// 每个线程的TLS块需复制前 [ElfTls::file_size] 字节，其余部分清零
+#[derive(Debug, Clone, Copy)]
+pub struct ElfTls {
+    pub vaddr: u64,     // 初始化镜像的虚拟地址
+    pub file_size: u64, // 初始化镜像的大小（.tdata）
+    pub mem_size: u64,  // TLS块的大小（.tdata + .tbss）
+    pub align: u64,     // TLS块的对齐要求
+}
+
+/// 可加载段（PT_LOAD）
+#[derive(Debug, Clone, Copy)]
+pub struct ElfSegment {
+    pub vaddr: u64,     // 虚拟地址
+    pub file_size: u64, // 文件中的大小
+    pub mem_size: u64,  // 内存中的大小，超出file_size的部分清零
 }
 
 // 读取ELF错误
@@ -149,12 +172,12 @@ where
             let p_vaddr = u64::from_le_bytes(header_buffer[16..24].try_into().unwrap());
             let p_filesz = u64::from_le_bytes(header_buffer[32..40].try_into().unwrap());
             let p_memsz = u64::from_le_bytes(header_buffer[40..48].try_into().unwrap());
-            let align = u64::from_le_bytes(header_buffer[48..56].try_into().unwrap());
+            let p_align = u64::from_le_bytes(header_buffer[48..56].try_into().unwrap());
 
-            if p_filesz > p_memsz {
+            if p_filesz > p_memsz || p_vaddr.checked_add(p_memsz).is_none() {
                 return Err(ElfReadError::Format);
             }
-            if align > 0x1000 {
+            if p_align > 0x1000 {
                 return Err(ElfReadError::Unsupport);
             }
 
@@ -165,6 +188,7 @@ where
                 p_vaddr,
                 p_filesz,
                 p_memsz,
+                p_align,
             });
         }
 
@@ -186,6 +210,34 @@ where
         &self.header
     }
 
+    /// 线程局部存储段，程序不使用TLS时为None
+    pub fn tls(&self) -> Option<ElfTls> {
+        self.program
+            .iter()
+            .find(|program| program.program_type == 7)
+            .map(|program| ElfTls {
+                vaddr: program.p_vaddr,
+                file_size: program.p_filesz,
+                mem_size: program.p_memsz,
+                align: program.p_align.max(1),
+            })
+    }
+
+    /// 全部可加载段，可在加载前检查段的地址范围
+    pub fn segments(&self) -> impl Iterator<Item = ElfSegment> + '_ {
+        self.program
+            .iter()
+            .filter(|program| program.program_type == 1)
+            .map(|program| ElfSegment {
+                vaddr: program.p_vaddr,
+                file_size: program.p_filesz,
+                mem_size: program.p_memsz,
+            })
+    }
+
+    /// 将可加载段加载到内存
+    ///
+    /// 段的内容从io中按块读取并写入，不会将整个文件读入内存
     pub async fn load<L: Loader + Send>(
         &mut self,
         loader: &mut L,
@@ -0,0 +1,655 @@
+//! 可重定位目标文件（ET_REL）的解析与链接
+//!
+//! 用于内核在运行时加载驱动模块：目标文件整体读入内存后解析，按段的权限将可分配的节排布到
+//! 代码、只读数据、可写数据三个区域，再根据重定位表填入节与外部符号的地址。
+//! 只支持x86-64中 `code-model=kernel`、`relocation-model=static` 编译产生的重定位类型
+
+use alloc::{string::String, vec::Vec};
+
+const SHT_SYMTAB: u32 = 2;
+const SHT_RELA: u32 = 4;
+const SHT_NOBITS: u32 = 8;
+const SHT_REL: u32 = 9;
+
+const SHF_WRITE: u64 = 1;
+const SHF_ALLOC: u64 = 2;
+const SHF_EXECINSTR: u64 = 4;
+
+const SHN_UNDEF: u16 = 0;
+const SHN_ABS: u16 = 0xfff1;
+const SHN_COMMON: u16 = 0xfff2;
+
+const STB_LOCAL: u8 = 0;
+const STT_SECTION: u8 = 3;
+
+const R_X86_64_NONE: u32 = 0;
+const R_X86_64_64: u32 = 1;
+const R_X86_64_PC32: u32 = 2;
+const R_X86_64_PLT32: u32 = 4;
+const R_X86_64_GOTPCREL: u32 = 9;
+const R_X86_64_32: u32 = 10;
+const R_X86_64_32S: u32 = 11;
+const R_X86_64_PC64: u32 = 24;
+const R_X86_64_GOTPCRELX: u32 = 41;
+const R_X86_64_REX_GOTPCRELX: u32 = 42;
+
+/// 区域的对齐粒度，各区域需要设置不同的页权限
+const REGION_ALIGN: u64 = 0x1000;
+
+/// 解析或链接可重定位目标文件的错误
+#[derive(Debug, PartialEq, Eq)]
+pub enum RelocatableError {
+    /// 格式错误，无法识别的ELF文件
+    Format,
+    /// 不支持的ELF文件，如非x86-64或不是可重定位目标文件
+    Unsupport,
+    /// 不支持的重定位类型
+    UnsupportedRelocation(u32),
+    /// 重定位结果超出字段的表示范围
+    RelocationOverflow,
+    /// 未定义且无法解析的符号
+    UndefinedSymbol(String),
+}
+
+/// 可分配节所在的区域
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    /// 可执行代码，链接后应为只读、可执行
+    Text,
+    /// 只读数据，包括GOT，链接后应为只读、不可执行
+    ReadOnly,
+    /// 可写数据，包括bss，链接后应为可写、不可执行
+    Data,
+}
+
+/// 链接后镜像中的一个区域，起始位置按页对齐
+#[derive(Debug, Clone, Copy)]
+pub struct Region {
+    pub kind: RegionKind,
+    pub offset: u64, // 相对镜像起始位置的偏移
+    pub size: u64,   // 区域大小，未按页对齐
+}
+
+/// 可分配节在镜像中的排布
+#[derive(Debug)]
+pub struct Layout {
+    /// 各节相对镜像起始位置的偏移，不可分配的节为None
+    section_offsets: Vec<Option<u64>>,
+    /// GOT相对镜像起始位置的偏移，GOT位于只读数据区域的末尾
+    got_offset: u64,
+    /// 通过GOT访问的符号，按GOT中的槽位排列
+    got_symbols: Vec<usize>,
+    /// 代码、只读数据、可写数据三个区域
+    regions: [Region; 3],
+    /// 镜像总大小，按页对齐
+    size: u64,
+}
+
+impl Layout {
+    /// 镜像总大小，按页对齐
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// 代码、只读数据、可写数据三个区域，大小可能为0
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+}
+
+#[derive(Debug)]
+struct Section {
+    section_type: u32, // 类型，2SYMTAB/4RELA/8NOBITS
+    flags: u64,        // 标志，1WRITE/2ALLOC/4EXECINSTR
+    offset: u64,
+    size: u64,
+    link: u32,
+    info: u32,
+    align: u64,
+}
+
+#[derive(Debug)]
+struct Symbol<'a> {
+    name: &'a str,
+    binding: u8, // 0LOCAL/1GLOBAL/2WEAK
+    kind: u8,    // 0NOTYPE/1OBJECT/2FUNC/3SECTION
+    section: u16,
+    value: u64,
+}
+
+#[derive(Debug)]
+struct Relocation {
+    offset: u64,
+    kind: u32,
+    symbol: usize,
+    addend: i64,
+}
+
+/// 已解析的可重定位目标文件，数据需整体位于内存中
+pub struct RelocatableObject<'a> {
+    data: &'a [u8],
+    sections: Vec<Section>,
+    symbols: Vec<Symbol<'a>>,
+    /// (被重定位的节, 重定位项)
+    relocations: Vec<(usize, Vec<Relocation>)>,
+}
+
+impl<'a> RelocatableObject<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, RelocatableError> {
+        if data.len() < 64 || data[0..4] != [0x7f, b'E', b'L', b'F'] {
+            return Err(RelocatableError::Format);
+        }
+        // 64位、小端、版本1、System V ABI
+        if data[4] != 2 || data[5] != 1 || data[6] != 1 || data[7] != 0 {
+            return Err(RelocatableError::Unsupport);
+        }
+        let elf_type = read_u16(data, 16)?;
+        let instruction = read_u16(data, 18)?;
+        if elf_type != 1 || instruction != 0x3e {
+            return Err(RelocatableError::Unsupport);
+        }
+        let section_offset = read_u64(data, 40)?;
+        let section_size = read_u16(data, 58)?;
+        let section_count = read_u16(data, 60)?;
+        if section_size != 64 {
+            return Err(RelocatableError::Unsupport);
+        }
+
+        let mut sections = Vec::with_capacity(section_count as usize);
+        for index in 0..section_count as u64 {
+            let header = section_offset
+                .checked_add(index * 64)
+                .ok_or(RelocatableError::Format)?;
+            let header = usize::try_from(header).map_err(|_| RelocatableError::Format)?;
+            let section = Section {
+                section_type: read_u32(data, header + 4)?,
+                flags: read_u64(data, header + 8)?,
+                offset: read_u64(data, header + 24)?,
+                size: read_u64(data, header + 32)?,
+                link: read_u32(data, header + 40)?,
+                info: read_u32(data, header + 44)?,
+                align: read_u64(data, header + 48)?.max(1),
+            };
+            if !section.align.is_power_of_two() || section.align > REGION_ALIGN {
+                return Err(RelocatableError::Unsupport);
+            }
+            if section.section_type != SHT_NOBITS {
+                section_data(data, &section)?;
+            }
+            sections.push(section);
+        }
+
+        let symbols = match sections
+            .iter()
+            .find(|section| section.section_type == SHT_SYMTAB)
+        {
+            Some(symtab) => parse_symbols(data, &sections, symtab)?,
+            None => Vec::new(),
+        };
+
+        let mut relocations = Vec::new();
+        for section in &sections {
+            if section.section_type == SHT_REL {
+                return Err(RelocatableError::Unsupport);
+            }
+            if section.section_type != SHT_RELA {
+                continue;
+            }
+            let target = section.info as usize;
+            let Some(target_section) = sections.get(target) else {
+                return Err(RelocatableError::Format);
+            };
+            // 调试信息等不可分配的节不会被加载，无需重定位
+            if target_section.flags & SHF_ALLOC == 0 {
+                continue;
+            }
+            relocations.push((target, parse_relocations(data, section, symbols.len())?));
+        }
+
+        Ok(Self {
+            data,
+            sections,
+            symbols,
+            relocations,
+        })
+    }
+
+    /// 计算可分配的节在镜像中的排布
+    pub fn layout(&self) -> Layout {
+        let mut section_offsets = alloc::vec![None; self.sections.len()];
+        let mut regions = [
+            Region {
+                kind: RegionKind::Text,
+                offset: 0,
+                size: 0,
+            },
+            Region {
+                kind: RegionKind::ReadOnly,
+                offset: 0,
+                size: 0,
+            },
+            Region {
+                kind: RegionKind::Data,
+                offset: 0,
+                size: 0,
+            },
+        ];
+
+        let mut got_symbols = Vec::new();
+        for (_, relocations) in &self.relocations {
+            for relocation in relocations {
+                if is_got_relocation(relocation.kind) && !got_symbols.contains(&relocation.symbol) {
+                    got_symbols.push(relocation.symbol);
+                }
+            }
+        }
+
+        let mut got_offset = 0;
+        let mut offset = 0;
+        for region in &mut regions {
+            region.offset = offset;
+            for (index, section) in self.sections.iter().enumerate() {
+                if section.flags & SHF_ALLOC == 0 || region_kind(section.flags) != region.kind {
+                    continue;
+                }
+                offset = offset.next_multiple_of(section.align);
+                section_offsets[index] = Some(offset);
+                offset += section.size;
+            }
+            if region.kind == RegionKind::ReadOnly {
+                offset = offset.next_multiple_of(8);
+                got_offset = offset;
+                offset += got_symbols.len() as u64 * 8;
+            }
+            region.size = offset - region.offset;
+            offset = offset.next_multiple_of(REGION_ALIGN);
+        }
+
+        Layout {
+            section_offsets,
+            got_offset,
+            got_symbols,
+            regions,
+            size: offset,
+        }
+    }
+
+    /// 将可分配的节复制到镜像中并完成重定位
+    ///
+    /// image的长度需为 [Layout::size]，base为镜像加载后的起始地址。
+    /// 未定义的符号通过resolve查找，找不到时返回 [RelocatableError::UndefinedSymbol]
+    pub fn link(
+        &self,
+        layout: &Layout,
+        image: &mut [u8],
+        base: u64,
+        mut resolve: impl FnMut(&str) -> Option<u64>,
+    ) -> Result<(), RelocatableError> {
+        assert_eq!(image.len() as u64, layout.size);
+        image.fill(0);
+        for (index, section) in self.sections.iter().enumerate() {
+            let Some(offset) = layout.section_offsets[index] else {
+                continue;
+            };
+            if section.section_type == SHT_NOBITS {
+                continue;
+            }
+            let offset = offset as usize;
+            image[offset..offset + section.size as usize]
+                .copy_from_slice(section_data(self.data, section)?);
+        }
+
+        let mut addresses = Vec::with_capacity(self.symbols.len());
+        for symbol in &self.symbols {
+            let address = match symbol.section {
+                SHN_UNDEF if symbol.name.is_empty() => 0,
+                SHN_UNDEF => resolve(symbol.name)
+                    .ok_or_else(|| RelocatableError::UndefinedSymbol(symbol.name.into()))?,
+                SHN_ABS => symbol.value,
+                SHN_COMMON => return Err(RelocatableError::Unsupport),
+                section => {
+                    let Some(Some(offset)) = layout.section_offsets.get(section as usize) else {
+                        // 指向不可分配节的符号（如调试信息）不能被引用，使用时报错
+                        addresses.push(None);
+                        continue;
+                    };
+                    base + offset + symbol.value
+                }
+            };
+            addresses.push(Some(address));
+        }
+        let address_of = |symbol: usize| addresses[symbol].ok_or(RelocatableError::Format);
+
+        for (slot, symbol) in layout.got_symbols.iter().enumerate() {
+            let offset = layout.got_offset as usize + slot * 8;
+            image[offset..offset + 8].copy_from_slice(&address_of(*symbol)?.to_le_bytes());
+        }
+
+        for (target, relocations) in &self.relocations {
+            let section_offset = layout.section_offsets[*target]
+                .expect("codebug: relocation target should be allocated");
+            let section_size = self.sections[*target].size;
+            for relocation in relocations {
+                if relocation.kind == R_X86_64_NONE {
+                    continue;
+                }
+                let width = relocation_width(relocation.kind)
+                    .ok_or(RelocatableError::UnsupportedRelocation(relocation.kind))?;
+                if relocation
+                    .offset
+                    .checked_add(width as u64)
+                    .is_none_or(|end| end > section_size)
+                {
+                    return Err(RelocatableError::Format);
+                }
+                let offset = (section_offset + relocation.offset) as usize;
+                let place = base + offset as u64;
+                let symbol = if is_got_relocation(relocation.kind) {
+                    let slot = layout
+                        .got_symbols
+                        .iter()
+                        .position(|symbol| *symbol == relocation.symbol)
+                        .expect("codebug: got symbol should be allocated in layout");
+                    base + layout.got_offset + slot as u64 * 8
+                } else {
+                    address_of(relocation.symbol)?
+                };
+                let value = apply(relocation.kind, symbol, relocation.addend, place)?;
+                image[offset..offset + width].copy_from_slice(&value.to_le_bytes()[..width]);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 查找已定义的全局符号在镜像中的地址
+    pub fn symbol_address(&self, layout: &Layout, base: u64, name: &str) -> Option<u64> {
+        let symbol = self.symbols.iter().find(|symbol| {
+            symbol.name == name && symbol.binding != STB_LOCAL && symbol.section != SHN_UNDEF
+        })?;
+        let offset = (*layout.section_offsets.get(symbol.section as usize)?)?;
+        Some(base + offset + symbol.value)
+    }
+}
+
+/// 计算重定位结果，返回按小端写入的值
+///
+/// symbol为符号地址（GOT类重定位为GOT槽位地址），place为被重定位字段的地址
+fn apply(kind: u32, symbol: u64, addend: i64, place: u64) -> Result<u64, RelocatableError> {
+    let absolute = symbol.wrapping_add_signed(addend);
+    let relative = absolute.wrapping_sub(place) as i64;
+    let value = match kind {
+        R_X86_64_64 => absolute,
+        R_X86_64_PC64 => relative as u64,
+        R_X86_64_PC32
+        | R_X86_64_PLT32
+        | R_X86_64_GOTPCREL
+        | R_X86_64_GOTPCRELX
+        | R_X86_64_REX_GOTPCRELX => {
+            i32::try_from(relative).map_err(|_| RelocatableError::RelocationOverflow)? as u32 as u64
+        }
+        R_X86_64_32 => {
+            u32::try_from(absolute).map_err(|_| RelocatableError::RelocationOverflow)? as u64
+        }
+        R_X86_64_32S => i32::try_from(absolute as i64)
+            .map_err(|_| RelocatableError::RelocationOverflow)? as u32
+            as u64,
+        _ => return Err(RelocatableError::UnsupportedRelocation(kind)),
+    };
+    Ok(value)
+}
+
+/// 重定位字段的字节数，不支持的类型返回None
+fn relocation_width(kind: u32) -> Option<usize> {
+    match kind {
+        R_X86_64_64 | R_X86_64_PC64 => Some(8),
+        R_X86_64_PC32
+        | R_X86_64_PLT32
+        | R_X86_64_GOTPCREL
+        | R_X86_64_GOTPCRELX
+        | R_X86_64_REX_GOTPCRELX
+        | R_X86_64_32
+        | R_X86_64_32S => Some(4),
+        _ => None,
+    }
+}
+
+fn is_got_relocation(kind: u32) -> bool {
+    matches!(
+        kind,
+        R_X86_64_GOTPCREL | R_X86_64_GOTPCRELX | R_X86_64_REX_GOTPCRELX
+    )
+}
+
+fn region_kind(flags: u64) -> RegionKind {
+    if flags & SHF_EXECINSTR != 0 {
+        RegionKind::Text
+    } else if flags & SHF_WRITE != 0 {
+        RegionKind::Data
+    } else {
+        RegionKind::ReadOnly
+    }
+}
+
+fn parse_symbols<'a>(
+    data: &'a [u8],
+    sections: &[Section],
+    symtab: &Section,
+) -> Result<Vec<Symbol<'a>>, RelocatableError> {
+    let strtab = sections
+        .get(symtab.link as usize)
+        .ok_or(RelocatableError::Format)?;
+    let strtab = section_data(data, strtab)?;
+    let table = section_data(data, symtab)?;
+    let mut symbols = Vec::with_capacity(table.len() / 24);
+    for entry in table.chunks_exact(24) {
+        let name = read_u32(entry, 0)? as usize;
+        let info = entry[4];
+        let section = read_u16(entry, 6)?;
+        let value = read_u64(entry, 8)?;
+        let name = strtab
+            .get(name..)
+            .and_then(|name| name.split(|byte| *byte == 0).next())
+            .and_then(|name| core::str::from_utf8(name).ok())
+            .ok_or(RelocatableError::Format)?;
+        let symbol = Symbol {
+            name,
+            binding: info >> 4,
+            kind: info & 0xf,
+            section,
+            value,
+        };
+        if symbol.kind == STT_SECTION && symbol.value != 0 {
+            return Err(RelocatableError::Format);
+        }
+        symbols.push(symbol);
+    }
+    Ok(symbols)
+}
+
+fn parse_relocations(
+    data: &[u8],
+    section: &Section,
+    symbol_count: usize,
+) -> Result<Vec<Relocation>, RelocatableError> {
+    let table = section_data(data, section)?;
+    let mut relocations = Vec::with_capacity(table.len() / 24);
+    for entry in table.chunks_exact(24) {
+        let info = read_u64(entry, 8)?;
+        let relocation = Relocation {
+            offset: read_u64(entry, 0)?,
+            kind: info as u32,
+            symbol: (info >> 32) as usize,
+            addend: read_u64(entry, 16)? as i64,
+        };
+        if relocation.symbol >= symbol_count {
+            return Err(RelocatableError::Format);
+        }
+        relocations.push(relocation);
+    }
+    Ok(relocations)
+}
+
+fn section_data<'a>(data: &'a [u8], section: &Section) -> Result<&'a [u8], RelocatableError> {
+    let start = usize::try_from(section.offset).map_err(|_| RelocatableError::Format)?;
+    let size = usize::try_from(section.size).map_err(|_| RelocatableError::Format)?;
+    data.get(start..start.checked_add(size).ok_or(RelocatableError::Format)?)
+        .ok_or(RelocatableError::Format)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, RelocatableError> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(RelocatableError::Format)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, RelocatableError> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(RelocatableError::Format)
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, RelocatableError> {
+    data.get(offset..offset + 8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(RelocatableError::Format)
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::relocatable::{RegionKind, RelocatableError, RelocatableObject};
+
+    /// 节的内容、类型、标志、link、info与对齐
+    type SectionSpec<'a> = (&'a [u8], u32, u64, u32, u32, u64);
+
+    /// 构造目标文件：.text中有函数init，.data中的指针指向init，.text中调用外部符号ext
+    fn build_object() -> Vec<u8> {
+        let text: &[u8] = &[0xe8, 0, 0, 0, 0, 0xc3, 0x90, 0x90];
+        let data = [0u8; 8];
+        let strtab = b"\0init\0ext\0";
+        let shstrtab = b"\0";
+
+        let mut symtab = Vec::new();
+        let mut symbol = |name: u32, info: u8, section: u16, value: u64| {
+            symtab.extend_from_slice(&name.to_le_bytes());
+            symtab.push(info);
+            symtab.push(0);
+            symtab.extend_from_slice(&section.to_le_bytes());
+            symtab.extend_from_slice(&value.to_le_bytes());
+            symtab.extend_from_slice(&0u64.to_le_bytes());
+        };
+        symbol(0, 0, 0, 0);
+        symbol(1, 0x12, 1, 0); // init: GLOBAL FUNC .text
+        symbol(6, 0x10, 0, 0); // ext: GLOBAL NOTYPE UND
+
+        let rela = |offset: u64, kind: u32, symbol: u64, addend: i64| {
+            let mut entry = Vec::new();
+            entry.extend_from_slice(&offset.to_le_bytes());
+            entry.extend_from_slice(&((symbol << 32) | kind as u64).to_le_bytes());
+            entry.extend_from_slice(&addend.to_le_bytes());
+            entry
+        };
+        let rela_text = rela(1, 4, 2, -4); // call ext
+        let rela_data = rela(0, 1, 1, 0); // .quad init
+
+        // 节：NULL, .text, .data, .bss, .symtab, .strtab, .rela.text, .rela.data, .shstrtab
+        let contents: [SectionSpec; 9] = [
+            (&[], 0, 0, 0, 0, 0),
+            (text, 1, 6, 0, 0, 16),
+            (&data, 1, 3, 0, 0, 8),
+            (&[], 8, 3, 0, 0, 8),
+            (&symtab, 2, 0, 5, 1, 8),
+            (strtab, 3, 0, 0, 0, 1),
+            (&rela_text, 4, 0, 4, 1, 8),
+            (&rela_data, 4, 0, 4, 2, 8),
+            (shstrtab, 3, 0, 0, 0, 1),
+        ];
+        let mut file = alloc::vec![0u8; 64];
+        let mut headers = Vec::new();
+        for (index, (content, kind, flags, link, info, align)) in contents.iter().enumerate() {
+            let offset = file.len() as u64;
+            file.extend_from_slice(content);
+            let size = if index == 3 { 16 } else { content.len() as u64 };
+            headers.extend_from_slice(&0u32.to_le_bytes());
+            headers.extend_from_slice(&kind.to_le_bytes());
+            headers.extend_from_slice(&flags.to_le_bytes());
+            headers.extend_from_slice(&0u64.to_le_bytes());
+            headers.extend_from_slice(&offset.to_le_bytes());
+            headers.extend_from_slice(&size.to_le_bytes());
+            headers.extend_from_slice(&link.to_le_bytes());
+            headers.extend_from_slice(&info.to_le_bytes());
+            headers.extend_from_slice(&align.to_le_bytes());
+            headers.extend_from_slice(&0u64.to_le_bytes());
+        }
+        let section_offset = file.len().next_multiple_of(8);
+        file.resize(section_offset, 0);
+        file.extend_from_slice(&headers);
+
+        file[0..8].copy_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+        file[16..18].copy_from_slice(&1u16.to_le_bytes());
+        file[18..20].copy_from_slice(&0x3eu16.to_le_bytes());
+        file[40..48].copy_from_slice(&(section_offset as u64).to_le_bytes());
+        file[58..60].copy_from_slice(&64u16.to_le_bytes());
+        file[60..62].copy_from_slice(&(contents.len() as u16).to_le_bytes());
+        file[62..64].copy_from_slice(&8u16.to_le_bytes());
+        file
+    }
+
+    #[test]
+    fn test_link() {
+        let file = build_object();
+        let object = RelocatableObject::parse(&file).unwrap();
+        let layout = object.layout();
+        let regions = layout.regions();
+        assert_eq!(regions[0].kind, RegionKind::Text);
+        assert_eq!((regions[0].offset, regions[0].size), (0, 8));
+        assert_eq!(regions[1].size, 0);
+        assert_eq!((regions[2].offset, regions[2].size), (0x1000, 24));
+        assert_eq!(layout.size(), 0x2000);
+
+        let base = 0xFFFF_FFFF_8000_0000;
+        let ext = base + 0x10_0000;
+        let mut image = alloc::vec![0xcc; layout.size() as usize];
+        object
+            .link(&layout, &mut image, base, |name| {
+                (name == "ext").then_some(ext)
+            })
+            .unwrap();
+        // call的目标为 ext - (base + 5)
+        let call = i32::from_le_bytes(image[1..5].try_into().unwrap());
+        assert_eq!(base as i64 + 5 + call as i64, ext as i64);
+        assert_eq!(image[5], 0xc3);
+        // .data中的指针指向init，.bss被清零
+        assert_eq!(
+            u64::from_le_bytes(image[0x1000..0x1008].try_into().unwrap()),
+            base
+        );
+        assert!(image[0x1008..0x1018].iter().all(|byte| *byte == 0));
+        assert_eq!(object.symbol_address(&layout, base, "init"), Some(base));
+        assert_eq!(object.symbol_address(&layout, base, "ext"), None);
+
+        assert_eq!(
+            object.link(&layout, &mut image, base, |_| None),
+            Err(RelocatableError::UndefinedSymbol("ext".into()))
+        );
+        // 被调用的符号距离超过2G时无法以32位相对地址表示
+        assert_eq!(
+            object.link(&layout, &mut image, base, |_| Some(0x1000_0000_0000)),
+            Err(RelocatableError::RelocationOverflow)
+        );
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let mut file = build_object();
+        assert!(RelocatableObject::parse(&file[..32]).is_err());
+        // 可执行文件不是可重定位目标文件
+        file[16] = 2;
+        assert!(matches!(
+            RelocatableObject::parse(&file),
+            Err(RelocatableError::Unsupport)
+        ));
+    }
+}
@@ -0,0 +1,584 @@
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use async_locks::rwlock::RwLock;
+
+use crate::{
+    BoxFuture,
+    device::{BlockDevice, BlockDeviceError},
+};
+
+/// 校验卷头部的魔数
+const CHECKSUM_MAGIC: [u8; 8] = *b"COSCKSUM";
+/// 校验卷头部的版本
+const CHECKSUM_VERSION: u32 = 1;
+/// 校验算法：CRC32（IEEE 802.3）
+const ALGORITHM_CRC32: u32 = 1;
+/// 每个校验值的长度，单位为字节
+const CHECKSUM_SIZE: u64 = 4;
+/// 格式化和扫描时每次访问的最大块数
+const BATCH_BLOCKS: u64 = 64;
+
+/// 带校验的块设备
+///
+/// 包装另一个块设备，为每个块保存一个CRC32校验值，读取时校验数据，校验失败时返回
+/// [`BlockDeviceError::Corrupted`]。校验值的计算包含块号，因此写入错误位置的数据同样能被发现。
+///
+/// 底层设备的第一个块为校验卷头部，之后依次为数据块和校验块，校验块保存在设备末尾，
+/// 每个校验块保存 `block_size / 4` 个校验值。因此校验设备比底层设备少一个头部块和若干校验块。
+///
+/// 调用 [`ChecksumDevice::format`] 在设备上创建校验卷，调用 [`ChecksumDevice::open`] 打开已有的校验卷。
+/// 调用 [`ChecksumDevice::scrub`] 可以检查整个设备上的数据。
+///
+/// 数据块与校验块的写入不是原子的，写入过程中断电可能导致对应块在之后的读取中报告校验失败。
+pub struct ChecksumDevice {
+    inner: Arc<dyn BlockDevice>,
+    // 块大小
+    block_size: u64,
+    // 数据块数量
+    block_count: u64,
+    // 每个校验块保存的校验值数量
+    checksums_per_block: u64,
+    // 读取持有读锁，写入持有写锁，避免读到新数据与旧校验值，也避免并发写入互相覆盖同一校验块
+    sync: RwLock<()>,
+}
+
+#[derive(Debug)]
+pub enum ChecksumError {
+    /// 底层IO错误
+    IoError(BlockDeviceError),
+    /// 块大小不是4字节的整数倍，或小于头部大小
+    BlockSizeNotExpected,
+    /// 设备容量不足
+    DeviceTooSmall,
+    /// 设备上没有校验卷，或头部版本、校验算法不受支持
+    InvalidHeader,
+}
+
+impl From<BlockDeviceError> for ChecksumError {
+    fn from(value: BlockDeviceError) -> Self {
+        Self::IoError(value)
+    }
+}
+
+/// 扫描结果，由 [`ChecksumDevice::scrub`] 返回
+#[derive(Debug, Default)]
+pub struct ScrubReport {
+    /// 已检查的块数量
+    pub checked: u64,
+    /// 校验失败的块
+    pub corrupted: Vec<u64>,
+}
+
+/// 校验卷头部，位于底层设备的0号块
+struct ChecksumHeader {
+    block_count: u64,
+}
+
+impl ChecksumHeader {
+    const SIZE: usize = 28;
+
+    fn parse(buf: &[u8]) -> Result<Self, ChecksumError> {
+        if buf[0..8] != CHECKSUM_MAGIC
+            || u32::from_le_bytes(buf[8..12].try_into().unwrap()) != CHECKSUM_VERSION
+            || u32::from_le_bytes(buf[12..16].try_into().unwrap()) != ALGORITHM_CRC32
+            || u32::from_le_bytes(buf[24..28].try_into().unwrap()) != crc32(0, &buf[0..24])
+        {
+            return Err(ChecksumError::InvalidHeader);
+        }
+        Ok(Self {
+            block_count: u64::from_le_bytes(buf[16..24].try_into().unwrap()),
+        })
+    }
+
+    fn write(&self, buf: &mut [u8]) {
+        buf[0..8].copy_from_slice(&CHECKSUM_MAGIC);
+        buf[8..12].copy_from_slice(&CHECKSUM_VERSION.to_le_bytes());
+        buf[12..16].copy_from_slice(&ALGORITHM_CRC32.to_le_bytes());
+        buf[16..24].copy_from_slice(&self.block_count.to_le_bytes());
+        let crc = crc32(0, &buf[0..24]);
+        buf[24..28].copy_from_slice(&crc.to_le_bytes());
+    }
+}
+
+impl ChecksumDevice {
+    /// 在块设备上创建校验卷
+    ///
+    /// 格式化会将所有数据块清零并写入对应的校验值，耗时与设备容量成正比。
+    pub async fn format(inner: Arc<dyn BlockDevice>) -> Result<Self, ChecksumError> {
+        let block_size = check_device(inner.as_ref())?;
+        let checksums_per_block = block_size / CHECKSUM_SIZE;
+        // 头部之外的块分为数据块和校验块，每个校验块覆盖checksums_per_block个数据块
+        let available = inner.block_count() - 1;
+        let mut block_count = available * checksums_per_block / (checksums_per_block + 1);
+        while block_count + block_count.div_ceil(checksums_per_block) > available {
+            block_count -= 1;
+        }
+        if block_count == 0 {
+            return Err(ChecksumError::DeviceTooSmall);
+        }
+
+        let device = Self::with_block_count(inner, block_count);
+        let mut start = 0;
+        while start < block_count {
+            let count = BATCH_BLOCKS.min(block_count - start);
+            device.inner.write_zeros(start + 1, count).await?;
+            start += count;
+        }
+        let zeros = alloc::vec![0u8; block_size as usize];
+        let mut trailer = alloc::vec![0u8; block_size as usize];
+        for trailer_index in 0..device.trailer_count() {
+            trailer.fill(0);
+            let first = trailer_index * checksums_per_block;
+            let last = (first + checksums_per_block).min(block_count);
+            for block_index in first..last {
+                device.set_checksum(&mut trailer, block_index, &zeros);
+            }
+            device
+                .inner
+                .write_block(device.trailer_start() + trailer_index, &trailer)
+                .await?;
+        }
+
+        // 最后写入头部，格式化中断时设备不会被识别为校验卷
+        let mut buf = alloc::vec![0u8; block_size as usize];
+        ChecksumHeader { block_count }.write(&mut buf);
+        device.inner.write_block(0, &buf).await?;
+        Ok(device)
+    }
+
+    /// 打开块设备上的校验卷
+    pub async fn open(inner: Arc<dyn BlockDevice>) -> Result<Self, ChecksumError> {
+        let block_size = check_device(inner.as_ref())?;
+
+        let mut buf = alloc::vec![0u8; block_size as usize];
+        inner.read_block(0, &mut buf).await?;
+        let header = ChecksumHeader::parse(&buf)?;
+
+        let checksums_per_block = block_size / CHECKSUM_SIZE;
+        let required = header
+            .block_count
+            .checked_add(header.block_count.div_ceil(checksums_per_block))
+            .and_then(|count| count.checked_add(1));
+        if header.block_count == 0 || required.is_none_or(|required| required > inner.block_count())
+        {
+            return Err(ChecksumError::InvalidHeader);
+        }
+        Ok(Self::with_block_count(inner, header.block_count))
+    }
+
+    fn with_block_count(inner: Arc<dyn BlockDevice>, block_count: u64) -> Self {
+        let block_size = inner.block_size();
+        Self {
+            inner,
+            block_size,
+            block_count,
+            checksums_per_block: block_size / CHECKSUM_SIZE,
+            sync: RwLock::new(()),
+        }
+    }
+
+    /// 检查整个设备上的数据，返回校验失败的块
+    ///
+    /// 扫描逐个校验块进行，每处理完一个校验块覆盖的数据块后释放锁，因此可以作为后台任务
+    /// 与正常的读写并发执行。底层IO错误会中止扫描。
+    pub async fn scrub(&self) -> Result<ScrubReport, BlockDeviceError> {
+        let mut report = ScrubReport::default();
+        let mut data = Vec::new();
+        data.try_reserve_exact((BATCH_BLOCKS * self.block_size) as usize)
+            .map_err(|_| BlockDeviceError::OutOfMemory)?;
+        data.resize((BATCH_BLOCKS * self.block_size) as usize, 0);
+        let mut trailer = alloc::vec![0u8; self.block_size as usize];
+
+        for trailer_index in 0..self.trailer_count() {
+            let _guard = self.sync.read().await;
+            self.inner
+                .read_block(self.trailer_start() + trailer_index, &mut trailer)
+                .await?;
+
+            let first = trailer_index * self.checksums_per_block;
+            let last = (first + self.checksums_per_block).min(self.block_count);
+            let mut start = first;
+            while start < last {
+                let count = BATCH_BLOCKS.min(last - start);
+                let data = &mut data[..(count * self.block_size) as usize];
+                self.inner.read_blocks(start + 1, count, data).await?;
+                for (i, block) in data.chunks_exact(self.block_size as usize).enumerate() {
+                    let block_index = start + i as u64;
+                    if !self.verify(&trailer, block_index, block) {
+                        report
+                            .corrupted
+                            .try_reserve(1)
+                            .map_err(|_| BlockDeviceError::OutOfMemory)?;
+                        report.corrupted.push(block_index);
+                    }
+                }
+                report.checked += count;
+                start += count;
+            }
+        }
+        Ok(report)
+    }
+
+    /// 校验块在底层设备中的起始块号
+    fn trailer_start(&self) -> u64 {
+        self.block_count + 1
+    }
+
+    /// 校验块数量
+    fn trailer_count(&self) -> u64 {
+        self.block_count.div_ceil(self.checksums_per_block)
+    }
+
+    /// 数据块的校验值在校验块中的偏移
+    fn checksum_offset(&self, block_index: u64) -> usize {
+        ((block_index % self.checksums_per_block) * CHECKSUM_SIZE) as usize
+    }
+
+    /// 计算数据块的校验值并写入`trailer`，`trailer`为数据块所在的校验块
+    fn set_checksum(&self, trailer: &mut [u8], block_index: u64, data: &[u8]) {
+        let offset = self.checksum_offset(block_index);
+        trailer[offset..offset + CHECKSUM_SIZE as usize]
+            .copy_from_slice(&block_checksum(block_index, data).to_le_bytes());
+    }
+
+    /// 校验数据块，`trailer`为数据块所在的校验块
+    fn verify(&self, trailer: &[u8], block_index: u64, data: &[u8]) -> bool {
+        let offset = self.checksum_offset(block_index);
+        let stored = u32::from_le_bytes(
+            trailer[offset..offset + CHECKSUM_SIZE as usize]
+                .try_into()
+                .unwrap(),
+        );
+        stored == block_checksum(block_index, data)
+    }
+
+    /// 读取覆盖`block_index..block_index + count`的所有校验块
+    async fn read_trailers(
+        &self,
+        block_index: u64,
+        count: u64,
+    ) -> Result<(u64, Vec<u8>), BlockDeviceError> {
+        let first = block_index / self.checksums_per_block;
+        let last = (block_index + count - 1) / self.checksums_per_block;
+        let trailer_count = last - first + 1;
+        let mut trailers = Vec::new();
+        trailers
+            .try_reserve_exact((trailer_count * self.block_size) as usize)
+            .map_err(|_| BlockDeviceError::OutOfMemory)?;
+        trailers.resize((trailer_count * self.block_size) as usize, 0);
+        self.inner
+            .read_blocks(self.trailer_start() + first, trailer_count, &mut trailers)
+            .await?;
+        Ok((first, trailers))
+    }
+
+    /// 数据块所在的校验块在`trailers`中的范围，`trailers`由 [`Self::read_trailers`] 读取
+    fn trailer_of<'a>(&self, trailers: &'a mut [u8], first: u64, block_index: u64) -> &'a mut [u8] {
+        let index = (block_index / self.checksums_per_block - first) as usize;
+        let size = self.block_size as usize;
+        &mut trailers[index * size..(index + 1) * size]
+    }
+
+    /// 更新`block_index`开始的连续块的校验值，`data`为这些块的数据
+    async fn update_checksums(
+        &self,
+        block_index: u64,
+        data: &[u8],
+    ) -> Result<(), BlockDeviceError> {
+        let count = data.len() as u64 / self.block_size;
+        let (first, mut trailers) = self.read_trailers(block_index, count).await?;
+        for (i, block) in data.chunks_exact(self.block_size as usize).enumerate() {
+            let index = block_index + i as u64;
+            let trailer = self.trailer_of(&mut trailers, first, index);
+            self.set_checksum(trailer, index, block);
+        }
+        let trailer_count = trailers.len() as u64 / self.block_size;
+        self.inner
+            .write_blocks(self.trailer_start() + first, trailer_count, &trailers)
+            .await
+    }
+
+    fn check_range(&self, block_index: u64, count: u64) -> Result<(), BlockDeviceError> {
+        if count == 0
+            || block_index
+                .checked_add(count)
+                .is_none_or(|end| end > self.block_count)
+        {
+            return Err(BlockDeviceError::OutOfBounds);
+        }
+        Ok(())
+    }
+}
+
+impl BlockDevice for ChecksumDevice {
+    fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    fn block_count(&self) -> u64 {
+        self.block_count
+    }
+
+    fn write_block<'fut>(
+        &'fut self,
+        block_index: u64,
+        buf: &'fut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        self.write_blocks(block_index, 1, buf)
+    }
+
+    fn read_block<'fut>(
+        &'fut self,
+        block_index: u64,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        self.read_blocks(block_index, 1, buf)
+    }
+
+    fn write_blocks<'fut>(
+        &'fut self,
+        block_index: u64,
+        count: u64,
+        buf: &'fut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.check_range(block_index, count)?;
+            let length = (count * self.block_size) as usize;
+            if buf.len() < length {
+                return Err(BlockDeviceError::OutOfBounds);
+            }
+
+            let _guard = self.sync.write().await;
+            self.inner
+                .write_blocks(block_index + 1, count, &buf[..length])
+                .await?;
+            self.update_checksums(block_index, &buf[..length]).await
+        })
+    }
+
+    fn read_blocks<'fut>(
+        &'fut self,
+        block_index: u64,
+        count: u64,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.check_range(block_index, count)?;
+            let length = (count * self.block_size) as usize;
+            if buf.len() < length {
+                return Err(BlockDeviceError::OutOfBounds);
+            }
+
+            let _guard = self.sync.read().await;
+            self.inner
+                .read_blocks(block_index + 1, count, &mut buf[..length])
+                .await?;
+            let (first, mut trailers) = self.read_trailers(block_index, count).await?;
+            for (i, block) in buf[..length]
+                .chunks_exact(self.block_size as usize)
+                .enumerate()
+            {
+                let index = block_index + i as u64;
+                if !self.verify(self.trailer_of(&mut trailers, first, index), index, block) {
+                    return Err(BlockDeviceError::Corrupted);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn write_zeros(
+        &self,
+        block_index: u64,
+        count: u64,
+    ) -> BoxFuture<'_, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.check_range(block_index, count)?;
+            let _guard = self.sync.write().await;
+            self.inner.write_zeros(block_index + 1, count).await?;
+
+            let zeros = alloc::vec![0u8; self.block_size as usize];
+            let (first, mut trailers) = self.read_trailers(block_index, count).await?;
+            for index in block_index..block_index + count {
+                let trailer = self.trailer_of(&mut trailers, first, index);
+                self.set_checksum(trailer, index, &zeros);
+            }
+            let trailer_count = trailers.len() as u64 / self.block_size;
+            self.inner
+                .write_blocks(self.trailer_start() + first, trailer_count, &trailers)
+                .await
+        })
+    }
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), BlockDeviceError>> {
+        self.inner.flush()
+    }
+}
+
+fn check_device(device: &dyn BlockDevice) -> Result<u64, ChecksumError> {
+    let block_size = device.block_size();
+    if !block_size.is_multiple_of(CHECKSUM_SIZE) || block_size < ChecksumHeader::SIZE as u64 {
+        return Err(ChecksumError::BlockSizeNotExpected);
+    }
+    if device.block_count() < 3 {
+        return Err(ChecksumError::DeviceTooSmall);
+    }
+    Ok(block_size)
+}
+
+/// 数据块的校验值，计算范围包括块号与块数据
+fn block_checksum(block_index: u64, data: &[u8]) -> u32 {
+    crc32(crc32(0, &block_index.to_le_bytes()), data)
+}
+
+/// CRC32（IEEE 802.3）查找表
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// 计算CRC32，`crc`为之前数据的CRC32，用于分段计算，首段传入0
+fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in data {
+        crc = CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use alloc::vec;
+
+    use crate::{
+        device::{
+            BlockDevice, BlockDeviceError,
+            checksum::{ChecksumDevice, ChecksumError, crc32},
+            memory::MemoryDevice,
+        },
+        fs::{FileSystem, fat32::Fat32FileSystem},
+        path::PathBuf,
+        run_task,
+    };
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(0, b""), 0);
+        assert_eq!(crc32(0, b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(crc32(0, b"12345"), b"6789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_read_write() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(512 * 300, 512));
+            assert!(matches!(
+                ChecksumDevice::open(device.clone()).await,
+                Err(ChecksumError::InvalidHeader)
+            ));
+
+            let checksum = ChecksumDevice::format(device.clone()).await.unwrap();
+            // 1个头部块，296个数据块，3个校验块（每个覆盖128个数据块）
+            assert_eq!(checksum.block_count(), 296);
+            let mut buf = vec![0xff; 512 * 3];
+            checksum.read_blocks(126, 3, &mut buf).await.unwrap();
+            assert_eq!(buf, [0; 512 * 3]);
+
+            // 跨越校验块边界的写入
+            checksum
+                .write_blocks(126, 3, &[0x5a; 512 * 3])
+                .await
+                .unwrap();
+            checksum.write_block(295, &[0xa5; 512]).await.unwrap();
+
+            let checksum = ChecksumDevice::open(device.clone()).await.unwrap();
+            assert_eq!(checksum.block_count(), 296);
+            checksum.read_blocks(126, 3, &mut buf).await.unwrap();
+            assert_eq!(buf, [0x5a; 512 * 3]);
+            checksum.read_block(295, &mut buf[..512]).await.unwrap();
+            assert_eq!(buf[..512], [0xa5; 512]);
+
+            checksum.write_zeros(127, 2).await.unwrap();
+            checksum.read_blocks(126, 3, &mut buf).await.unwrap();
+            assert_eq!(buf[..512], [0x5a; 512]);
+            assert_eq!(buf[512..], [0; 1024]);
+
+            assert!(matches!(
+                checksum.read_block(296, &mut buf[..512]).await,
+                Err(BlockDeviceError::OutOfBounds)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_detect_corruption() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(512 * 300, 512));
+            let checksum = ChecksumDevice::format(device.clone()).await.unwrap();
+            checksum.write_blocks(0, 4, &[0x11; 512 * 4]).await.unwrap();
+
+            // 绕过校验设备直接修改底层数据
+            device.write_block(3, &[0x22; 512]).await.unwrap();
+            device.write_block(200, &[0x33; 512]).await.unwrap();
+
+            let mut buf = vec![0; 512 * 4];
+            assert!(matches!(
+                checksum.read_blocks(0, 4, &mut buf).await,
+                Err(BlockDeviceError::Corrupted)
+            ));
+            checksum.read_blocks(0, 2, &mut buf[..1024]).await.unwrap();
+
+            let report = checksum.scrub().await.unwrap();
+            assert_eq!(report.checked, 296);
+            assert_eq!(report.corrupted, [2, 199]);
+
+            // 重新写入后恢复
+            checksum.write_block(2, &[0x11; 512]).await.unwrap();
+            checksum.write_zeros(199, 1).await.unwrap();
+            let report = checksum.scrub().await.unwrap();
+            assert!(report.corrupted.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_fat32_on_checksum() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(512 * 1100, 512));
+            let checksum = ChecksumDevice::format(device.clone()).await.unwrap();
+            let fs = Fat32FileSystem::with_format(Arc::new(checksum))
+                .await
+                .unwrap();
+            let path = PathBuf::from_str("data.txt").unwrap();
+            fs.create_file(path.as_path()).await.unwrap();
+            let mut handle = fs.open_file(path.as_path()).await.unwrap();
+            handle.write(b"checked").await.unwrap();
+            handle.close().await.unwrap();
+            fs.unmount().await.unwrap();
+
+            let checksum = ChecksumDevice::open(device.clone()).await.unwrap();
+            assert!(checksum.scrub().await.unwrap().corrupted.is_empty());
+            let fs = Fat32FileSystem::mount(Arc::new(checksum)).await.unwrap();
+            let mut handle = fs.open_file(path.as_path()).await.unwrap();
+            let mut buf = [0; 7];
+            assert_eq!(handle.read(&mut buf).await.unwrap(), 7);
+            assert_eq!(&buf, b"checked");
+            handle.close().await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,431 @@
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use crypto::{hmac::hmac_sha256, pbkdf2::pbkdf2_hmac_sha256, xts::Xts, zeroize};
+
+use crate::{
+    BoxFuture,
+    device::{BlockDevice, BlockDeviceError},
+};
+
+/// 加密卷头部的魔数
+const CRYPT_MAGIC: [u8; 8] = *b"COSCRYPT";
+/// 加密卷头部的版本
+const CRYPT_VERSION: u32 = 1;
+/// 用于校验口令的消息，与派生密钥计算HMAC后保存在头部
+const KEY_CHECK_MESSAGE: &[u8] = b"cos crypt key check";
+/// 派生密钥长度，XTS-AES-256需要两个256位密钥
+const KEY_SIZE: usize = 64;
+
+/// 默认的口令派生迭代次数
+pub const DEFAULT_ITERATIONS: u32 = 100_000;
+/// 口令派生迭代次数的上限，头部中的迭代次数超过此值时视为无效，避免打开卷时长时间占用CPU
+pub const MAX_ITERATIONS: u32 = 10_000_000;
+
+/// 加密块设备
+///
+/// 包装另一个块设备，对每个块使用XTS-AES-256加密，tweak为块号。
+///
+/// 底层设备的第一个块为加密卷头部，保存了盐值、口令派生的迭代次数和口令校验值，
+/// 数据密钥由口令经PBKDF2-HMAC-SHA256派生，不保存在设备上，派生后即从内存中清除，只保留展开的轮密钥。其余块为加密后的数据，
+/// 因此加密设备比底层设备少一个块。
+///
+/// 调用 [`CryptDevice::format`] 在设备上创建加密卷，调用 [`CryptDevice::open`] 使用口令打开已有的加密卷。
+/// 格式化只写入头部，不会清除已有数据；旧数据在新密钥下解密后是无意义的随机数据。
+pub struct CryptDevice {
+    inner: Arc<dyn BlockDevice>,
+    cipher: Xts,
+    // 块大小
+    block_size: u64,
+    // 块数量，不含头部
+    block_count: u64,
+}
+
+#[derive(Debug)]
+pub enum CryptError {
+    /// 底层IO错误
+    IoError(BlockDeviceError),
+    /// 块大小不是16字节的整数倍，或小于头部大小
+    BlockSizeNotExpected,
+    /// 设备容量不足
+    DeviceTooSmall,
+    /// 设备上没有加密卷，头部版本不受支持，或迭代次数超出范围
+    InvalidHeader,
+    /// 口令错误
+    WrongPassphrase,
+}
+
+impl From<BlockDeviceError> for CryptError {
+    fn from(value: BlockDeviceError) -> Self {
+        Self::IoError(value)
+    }
+}
+
+/// 加密卷头部，位于底层设备的0号块
+struct CryptHeader {
+    iterations: u32,
+    salt: [u8; 16],
+    key_check: [u8; 32],
+}
+
+impl CryptHeader {
+    const SIZE: usize = 64;
+
+    fn parse(buf: &[u8]) -> Result<Self, CryptError> {
+        if buf[0..8] != CRYPT_MAGIC
+            || u32::from_le_bytes(buf[8..12].try_into().unwrap()) != CRYPT_VERSION
+        {
+            return Err(CryptError::InvalidHeader);
+        }
+        let iterations = u32::from_le_bytes(buf[12..16].try_into().unwrap());
+        if iterations == 0 || iterations > MAX_ITERATIONS {
+            return Err(CryptError::InvalidHeader);
+        }
+        Ok(Self {
+            iterations,
+            salt: buf[16..32].try_into().unwrap(),
+            key_check: buf[32..64].try_into().unwrap(),
+        })
+    }
+
+    fn write(&self, buf: &mut [u8]) {
+        buf[0..8].copy_from_slice(&CRYPT_MAGIC);
+        buf[8..12].copy_from_slice(&CRYPT_VERSION.to_le_bytes());
+        buf[12..16].copy_from_slice(&self.iterations.to_le_bytes());
+        buf[16..32].copy_from_slice(&self.salt);
+        buf[32..64].copy_from_slice(&self.key_check);
+    }
+}
+
+impl CryptDevice {
+    /// 在块设备上创建加密卷
+    ///
+    /// `salt`应当是随机生成的，相同的口令和盐值会派生出相同的密钥。
+    /// `iterations`为口令派生的迭代次数，通常使用 [`DEFAULT_ITERATIONS`]，不能超过 [`MAX_ITERATIONS`]。
+    pub async fn format(
+        inner: Arc<dyn BlockDevice>,
+        passphrase: &[u8],
+        salt: [u8; 16],
+        iterations: u32,
+    ) -> Result<Self, CryptError> {
+        let block_size = check_device(inner.as_ref())?;
+        if iterations == 0 || iterations > MAX_ITERATIONS {
+            return Err(CryptError::InvalidHeader);
+        }
+
+        let mut key = derive_key(passphrase, &salt, iterations);
+        let header = CryptHeader {
+            iterations,
+            salt,
+            key_check: hmac_sha256(&key, KEY_CHECK_MESSAGE),
+        };
+        let device = Self::with_key(inner, &key);
+        zeroize(&mut key);
+
+        let mut buf = alloc::vec![0u8; block_size as usize];
+        header.write(&mut buf);
+        device.inner.write_block(0, &buf).await?;
+        Ok(device)
+    }
+
+    /// 使用口令打开块设备上的加密卷
+    pub async fn open(inner: Arc<dyn BlockDevice>, passphrase: &[u8]) -> Result<Self, CryptError> {
+        let block_size = check_device(inner.as_ref())?;
+
+        let mut buf = alloc::vec![0u8; block_size as usize];
+        inner.read_block(0, &mut buf).await?;
+        let header = CryptHeader::parse(&buf)?;
+
+        let mut key = derive_key(passphrase, &header.salt, header.iterations);
+        let matched = hmac_sha256(&key, KEY_CHECK_MESSAGE) == header.key_check;
+        let device = matched.then(|| Self::with_key(inner, &key));
+        zeroize(&mut key);
+        device.ok_or(CryptError::WrongPassphrase)
+    }
+
+    fn with_key(inner: Arc<dyn BlockDevice>, key: &[u8; KEY_SIZE]) -> Self {
+        Self {
+            cipher: Xts::new(key).unwrap(),
+            block_size: inner.block_size(),
+            block_count: inner.block_count() - 1,
+            inner,
+        }
+    }
+
+    fn check_range(&self, block_index: u64, count: u64) -> Result<(), BlockDeviceError> {
+        if block_index
+            .checked_add(count)
+            .is_none_or(|end| end > self.block_count)
+        {
+            return Err(BlockDeviceError::OutOfBounds);
+        }
+        Ok(())
+    }
+
+    /// 加密连续的多个块，`buf`中的块依次对应从`block_index`开始的块
+    fn encrypt(&self, block_index: u64, buf: &mut [u8]) {
+        for (i, block) in buf.chunks_exact_mut(self.block_size as usize).enumerate() {
+            self.cipher.encrypt_sector(block_index + i as u64, block);
+        }
+    }
+
+    /// 解密连续的多个块，`buf`中的块依次对应从`block_index`开始的块
+    fn decrypt(&self, block_index: u64, buf: &mut [u8]) {
+        for (i, block) in buf.chunks_exact_mut(self.block_size as usize).enumerate() {
+            self.cipher.decrypt_sector(block_index + i as u64, block);
+        }
+    }
+}
+
+impl BlockDevice for CryptDevice {
+    fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    fn block_count(&self) -> u64 {
+        self.block_count
+    }
+
+    fn write_block<'fut>(
+        &'fut self,
+        block_index: u64,
+        buf: &'fut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        self.write_blocks(block_index, 1, buf)
+    }
+
+    fn read_block<'fut>(
+        &'fut self,
+        block_index: u64,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        self.read_blocks(block_index, 1, buf)
+    }
+
+    fn write_blocks<'fut>(
+        &'fut self,
+        block_index: u64,
+        count: u64,
+        buf: &'fut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.check_range(block_index, count)?;
+            let length = (count * self.block_size) as usize;
+            if buf.len() < length {
+                return Err(BlockDeviceError::OutOfBounds);
+            }
+
+            let mut encrypted = Vec::new();
+            encrypted
+                .try_reserve_exact(length)
+                .map_err(|_| BlockDeviceError::OutOfMemory)?;
+            encrypted.extend_from_slice(&buf[..length]);
+            self.encrypt(block_index, &mut encrypted);
+            self.inner
+                .write_blocks(block_index + 1, count, &encrypted)
+                .await
+        })
+    }
+
+    fn read_blocks<'fut>(
+        &'fut self,
+        block_index: u64,
+        count: u64,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.check_range(block_index, count)?;
+            let length = (count * self.block_size) as usize;
+            if buf.len() < length {
+                return Err(BlockDeviceError::OutOfBounds);
+            }
+
+            self.inner
+                .read_blocks(block_index + 1, count, &mut buf[..length])
+                .await?;
+            self.decrypt(block_index, &mut buf[..length]);
+            Ok(())
+        })
+    }
+
+    fn write_zeros(
+        &self,
+        block_index: u64,
+        count: u64,
+    ) -> BoxFuture<'_, Result<(), BlockDeviceError>> {
+        // 写零后需要读到零，因此写入的是零的密文，不能直接在底层设备上写零
+        Box::pin(async move {
+            self.check_range(block_index, count)?;
+            let mut buf = alloc::vec![0u8; self.block_size as usize];
+            for i in 0..count {
+                buf.fill(0);
+                self.cipher.encrypt_sector(block_index + i, &mut buf);
+                self.inner.write_block(block_index + i + 1, &buf).await?;
+            }
+            Ok(())
+        })
+    }
+
+    fn clear_blocks(
+        &self,
+        block_index: u64,
+        count: u64,
+    ) -> BoxFuture<'_, Result<(), BlockDeviceError>> {
+        // 清空后的数据是未定义的，可以直接清空底层设备
+        Box::pin(async move {
+            self.check_range(block_index, count)?;
+            self.inner.clear_blocks(block_index + 1, count).await
+        })
+    }
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), BlockDeviceError>> {
+        self.inner.flush()
+    }
+}
+
+fn check_device(device: &dyn BlockDevice) -> Result<u64, CryptError> {
+    let block_size = device.block_size();
+    if !block_size.is_multiple_of(16) || block_size < CryptHeader::SIZE as u64 {
+        return Err(CryptError::BlockSizeNotExpected);
+    }
+    if device.block_count() < 2 {
+        return Err(CryptError::DeviceTooSmall);
+    }
+    Ok(block_size)
+}
+
+fn derive_key(passphrase: &[u8], salt: &[u8], iterations: u32) -> [u8; KEY_SIZE] {
+    let mut key = [0u8; KEY_SIZE];
+    pbkdf2_hmac_sha256(passphrase, salt, iterations, &mut key);
+    key
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use alloc::vec;
+
+    use crate::{
+        device::{
+            BlockDevice,
+            crypt::{CryptDevice, CryptError, MAX_ITERATIONS},
+            memory::MemoryDevice,
+        },
+        fs::{FileSystem, conformance::run_conformance, fat32::Fat32FileSystem},
+        path::PathBuf,
+        run_task,
+    };
+
+    const SALT: [u8; 16] = [7; 16];
+
+    #[test]
+    fn test_encrypt_blocks() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(512 * 16, 512));
+            let crypt = CryptDevice::format(device.clone(), b"secret", SALT, 16)
+                .await
+                .unwrap();
+            assert_eq!(crypt.block_count(), 15);
+
+            crypt.write_blocks(0, 2, &[0x5a; 1024]).await.unwrap();
+            let mut buf = vec![0; 1024];
+            crypt.read_blocks(0, 2, &mut buf).await.unwrap();
+            assert_eq!(buf, [0x5a; 1024]);
+
+            // 底层设备上是密文，相同明文在不同块中的密文不同
+            let mut raw = vec![0; 1024];
+            device.read_blocks(1, 2, &mut raw).await.unwrap();
+            assert_ne!(raw[..512], [0x5a; 512]);
+            assert_ne!(raw[..512], raw[512..]);
+
+            // 写零后读到零
+            crypt.write_zeros(1, 1).await.unwrap();
+            crypt.read_block(1, &mut buf[..512]).await.unwrap();
+            assert_eq!(buf[..512], [0; 512]);
+
+            assert!(crypt.read_block(15, &mut buf).await.is_err());
+        });
+    }
+
+    #[test]
+    fn test_open() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(512 * 16, 512));
+            assert!(matches!(
+                CryptDevice::open(device.clone(), b"secret").await,
+                Err(CryptError::InvalidHeader)
+            ));
+
+            let crypt = CryptDevice::format(device.clone(), b"secret", SALT, 16)
+                .await
+                .unwrap();
+            crypt.write_block(3, &[0xa5; 512]).await.unwrap();
+
+            assert!(matches!(
+                CryptDevice::open(device.clone(), b"wrong").await,
+                Err(CryptError::WrongPassphrase)
+            ));
+            let crypt = CryptDevice::open(device.clone(), b"secret").await.unwrap();
+            let mut buf = vec![0; 512];
+            crypt.read_block(3, &mut buf).await.unwrap();
+            assert_eq!(buf, [0xa5; 512]);
+        });
+    }
+
+    #[test]
+    fn test_iterations_limit() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(512 * 16, 512));
+            assert!(matches!(
+                CryptDevice::format(device.clone(), b"secret", SALT, MAX_ITERATIONS + 1).await,
+                Err(CryptError::InvalidHeader)
+            ));
+
+            // 头部中的迭代次数被篡改为超出上限时，不进行派生
+            CryptDevice::format(device.clone(), b"secret", SALT, 16)
+                .await
+                .unwrap();
+            let mut header = vec![0; 512];
+            device.read_block(0, &mut header).await.unwrap();
+            header[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+            device.write_block(0, &header).await.unwrap();
+            assert!(matches!(
+                CryptDevice::open(device.clone(), b"secret").await,
+                Err(CryptError::InvalidHeader)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_fat32_on_crypt() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(512 * 1024, 512));
+            let crypt = CryptDevice::format(device.clone(), b"secret", SALT, 16)
+                .await
+                .unwrap();
+            let fs = Fat32FileSystem::with_format(Arc::new(crypt)).await.unwrap();
+            let path = PathBuf::from_str("secret.txt").unwrap();
+            fs.create_file(path.as_path()).await.unwrap();
+            let mut handle = fs.open_file(path.as_path()).await.unwrap();
+            handle.write(b"top secret").await.unwrap();
+            handle.close().await.unwrap();
+            fs.unmount().await.unwrap();
+
+            let crypt = CryptDevice::open(device.clone(), b"secret").await.unwrap();
+            let fs = Fat32FileSystem::mount(Arc::new(crypt)).await.unwrap();
+            let mut handle = fs.open_file(path.as_path()).await.unwrap();
+            let mut buf = [0; 10];
+            assert_eq!(handle.read(&mut buf).await.unwrap(), 10);
+            assert_eq!(&buf, b"top secret");
+            handle.close().await.unwrap();
+
+            run_conformance(|| async {
+                let device = Arc::new(MemoryDevice::new(512 * 1025, 512));
+                let crypt = CryptDevice::format(device, b"secret", SALT, 1)
+                    .await
+                    .unwrap();
+                Fat32FileSystem::with_format(Arc::new(crypt)).await.unwrap()
+            })
+            .await;
+        });
+    }
+}
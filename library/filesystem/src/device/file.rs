@@ -0,0 +1,559 @@
+use alloc::{boxed::Box, collections::BTreeMap, vec::Vec};
+use async_locks::mutex::Mutex;
+
+use crate::{
+    BoxFuture,
+    device::{BlockDevice, BlockDeviceError},
+    fs::{FileHandle, FileSystem, FileSystemError},
+    path::Path,
+};
+
+/// 默认缓存的块数量
+pub const DEFAULT_CACHE_CAPACITY: usize = 64;
+
+/// 基于文件的块设备实现
+///
+/// 将另一个文件系统中的文件作为块设备使用，用于挂载镜像文件（loop设备）。
+/// 文件大小在创建时确定，不足一个块的尾部无法访问，设备不会改变文件的大小。
+///
+/// 单块读写经过写回缓存：读取的块会被缓存，写入的块仅修改缓存并标记为脏块，
+/// 缓存满时按最近最少使用的顺序淘汰，淘汰脏块时将其写入文件。多块读写直接访问文件，
+/// 以免大量顺序读写冲刷掉缓存中的元数据块。
+///
+/// 脏块只有在淘汰、调用 [`BlockDevice::flush`] 或 [`FileBlockDevice::close`] 时才会写入文件。
+/// 上层文件系统在卸载时会调用 [`BlockDevice::flush`]；不再使用设备时，应调用 [`FileBlockDevice::close`]
+/// 关闭底层文件。
+pub struct FileBlockDevice {
+    inner: Mutex<FileBlockDeviceInner>,
+    // 块大小
+    block_size: u64,
+    // 块数量
+    block_count: u64,
+}
+
+struct FileBlockDeviceInner {
+    // 底层文件
+    file: Box<dyn FileHandle>,
+    // 缓存的块，键为块索引
+    cache: BTreeMap<u64, CacheEntry>,
+    // 缓存容量，单位为块
+    cache_capacity: usize,
+    // 块大小
+    block_size: u64,
+    // 访问计数，用于确定最近最少使用的块
+    tick: u64,
+}
+
+struct CacheEntry {
+    data: Box<[u8]>,
+    dirty: bool,
+    last_used: u64,
+}
+
+impl FileBlockDevice {
+    /// 使用已打开的文件创建块设备
+    ///
+    /// `file_size`为文件大小，单位为字节。
+    /// `block_size`为每个块的大小，单位为字节。
+    /// `cache_capacity`为缓存的块数量，为0时不缓存，所有读写直接访问文件。
+    pub fn new(
+        file: Box<dyn FileHandle>,
+        file_size: u64,
+        block_size: u64,
+        cache_capacity: usize,
+    ) -> Self {
+        Self {
+            inner: Mutex::new(FileBlockDeviceInner {
+                file,
+                cache: BTreeMap::new(),
+                cache_capacity,
+                block_size,
+                tick: 0,
+            }),
+            block_size,
+            block_count: file_size / block_size,
+        }
+    }
+
+    /// 打开文件系统中的文件并创建块设备，使用 [`DEFAULT_CACHE_CAPACITY`] 作为缓存容量
+    ///
+    /// 如果指定路径不存在，返回 [`FileSystemError::FileNotFound`]。
+    /// 如果指定路径为目录，返回 [`FileSystemError::FileTypeMismatch`]。
+    pub async fn open(
+        fs: &dyn FileSystem,
+        path: Path<'_>,
+        block_size: u64,
+    ) -> Result<Self, FileSystemError> {
+        let metadata = fs.get_metadata(path).await?;
+        if metadata.is_directory {
+            return Err(FileSystemError::FileTypeMismatch);
+        }
+        let file = fs.open_file(path).await?;
+        Ok(Self::new(
+            file,
+            metadata.size,
+            block_size,
+            DEFAULT_CACHE_CAPACITY,
+        ))
+    }
+
+    /// 将缓存写入文件，并关闭文件
+    ///
+    /// 关闭后，所有读写均返回 [`BlockDeviceError::IoError`]。
+    pub async fn close(&self) -> Result<(), FileSystemError> {
+        let mut inner = self.inner.lock().await;
+        inner.flush().await?;
+        inner.cache.clear();
+        inner.file.close().await
+    }
+
+    /// 丢弃缓存中未修改的块，返回丢弃的块数量
+    ///
+    /// 用于内存不足时回收内存，不会等待锁：设备正在读写时不丢弃任何块，返回0
+    pub fn shrink(&self) -> usize {
+        let Some(mut inner) = self.inner.try_lock() else {
+            return 0;
+        };
+        let before = inner.cache.len();
+        inner.cache.retain(|_, entry| entry.dirty);
+        before - inner.cache.len()
+    }
+
+    fn check_range(
+        &self,
+        block_index: u64,
+        count: u64,
+        buf_len: usize,
+    ) -> Result<(), BlockDeviceError> {
+        let end = block_index
+            .checked_add(count)
+            .ok_or(BlockDeviceError::OutOfBounds)?;
+        if end > self.block_count || buf_len as u64 != count * self.block_size {
+            return Err(BlockDeviceError::OutOfBounds);
+        }
+        Ok(())
+    }
+}
+
+impl FileBlockDeviceInner {
+    /// 从文件读取数据，读取范围超出文件末尾的部分填零
+    async fn read_file(&mut self, offset: u64, buf: &mut [u8]) -> Result<(), FileSystemError> {
+        self.file.move_pointer(offset).await?;
+        let mut filled = 0;
+        while filled < buf.len() {
+            let len = self.file.read(&mut buf[filled..]).await? as usize;
+            if len == 0 {
+                buf[filled..].fill(0);
+                break;
+            }
+            filled += len;
+        }
+        Ok(())
+    }
+
+    async fn write_file(&mut self, offset: u64, buf: &[u8]) -> Result<(), FileSystemError> {
+        self.file.move_pointer(offset).await?;
+        self.file.write(buf).await
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// 将块放入缓存，缓存已满时先淘汰最近最少使用的块
+    async fn insert(
+        &mut self,
+        block_index: u64,
+        data: Box<[u8]>,
+        dirty: bool,
+    ) -> Result<(), FileSystemError> {
+        let block_size = self.block_size;
+        if self.cache_capacity == 0 {
+            if dirty {
+                self.write_file(block_index * block_size, &data).await?;
+            }
+            return Ok(());
+        }
+        if !self.cache.contains_key(&block_index) && self.cache.len() >= self.cache_capacity {
+            let victim = self
+                .cache
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(index, _)| *index)
+                .expect("codebug: cache is empty");
+            let entry = &self.cache[&victim];
+            if entry.dirty {
+                let data = entry.data.clone();
+                self.write_file(victim * block_size, &data).await?;
+            }
+            self.cache.remove(&victim);
+        }
+        let last_used = self.next_tick();
+        self.cache.insert(
+            block_index,
+            CacheEntry {
+                data,
+                dirty,
+                last_used,
+            },
+        );
+        Ok(())
+    }
+
+    /// 按块索引顺序将所有脏块写入文件
+    ///
+    /// 写入失败时，尚未写入的块仍保留脏标记，可以再次调用重试
+    async fn flush(&mut self) -> Result<(), FileSystemError> {
+        let dirty: Vec<u64> = self
+            .cache
+            .iter()
+            .filter(|(_, entry)| entry.dirty)
+            .map(|(index, _)| *index)
+            .collect();
+        for block_index in dirty {
+            let data = self.cache[&block_index].data.clone();
+            self.write_file(block_index * self.block_size, &data)
+                .await?;
+            if let Some(entry) = self.cache.get_mut(&block_index) {
+                entry.dirty = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 将文件系统错误转换为块设备错误
+fn to_block_device_error(error: FileSystemError) -> BlockDeviceError {
+    match error {
+        FileSystemError::IoError(error) => error,
+        _ => BlockDeviceError::IoError,
+    }
+}
+
+impl BlockDevice for FileBlockDevice {
+    fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    fn block_count(&self) -> u64 {
+        self.block_count
+    }
+
+    fn write_block<'fut>(
+        &'fut self,
+        block_index: u64,
+        buf: &'fut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.check_range(block_index, 1, buf.len())?;
+            let mut inner = self.inner.lock().await;
+            let tick = inner.next_tick();
+            if let Some(entry) = inner.cache.get_mut(&block_index) {
+                entry.data.copy_from_slice(buf);
+                entry.dirty = true;
+                entry.last_used = tick;
+                return Ok(());
+            }
+            inner
+                .insert(block_index, buf.into(), true)
+                .await
+                .map_err(to_block_device_error)
+        })
+    }
+
+    fn read_block<'fut>(
+        &'fut self,
+        block_index: u64,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.check_range(block_index, 1, buf.len())?;
+            let mut inner = self.inner.lock().await;
+            let tick = inner.next_tick();
+            if let Some(entry) = inner.cache.get_mut(&block_index) {
+                buf.copy_from_slice(&entry.data);
+                entry.last_used = tick;
+                return Ok(());
+            }
+            inner
+                .read_file(block_index * self.block_size, buf)
+                .await
+                .map_err(to_block_device_error)?;
+            inner
+                .insert(block_index, buf.into(), false)
+                .await
+                .map_err(to_block_device_error)
+        })
+    }
+
+    fn write_blocks<'fut>(
+        &'fut self,
+        block_index: u64,
+        count: u64,
+        buf: &'fut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.check_range(block_index, count, buf.len())?;
+            let mut inner = self.inner.lock().await;
+            inner
+                .write_file(block_index * self.block_size, buf)
+                .await
+                .map_err(to_block_device_error)?;
+            // 文件中已是最新数据，丢弃范围内的缓存
+            let cached: Vec<u64> = inner
+                .cache
+                .range(block_index..block_index + count)
+                .map(|(index, _)| *index)
+                .collect();
+            for index in cached {
+                inner.cache.remove(&index);
+            }
+            Ok(())
+        })
+    }
+
+    fn read_blocks<'fut>(
+        &'fut self,
+        block_index: u64,
+        count: u64,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.check_range(block_index, count, buf.len())?;
+            let mut inner = self.inner.lock().await;
+            inner
+                .read_file(block_index * self.block_size, buf)
+                .await
+                .map_err(to_block_device_error)?;
+            // 脏块尚未写入文件，用缓存覆盖
+            let size = self.block_size as usize;
+            for (index, entry) in inner.cache.range(block_index..block_index + count) {
+                if entry.dirty {
+                    let offset = (index - block_index) as usize * size;
+                    buf[offset..offset + size].copy_from_slice(&entry.data);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.inner
+                .lock()
+                .await
+                .flush()
+                .await
+                .map_err(to_block_device_error)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use alloc::{boxed::Box, vec, vec::Vec};
+
+    use crate::{
+        BoxFuture,
+        device::{BlockDevice, file::FileBlockDevice},
+        fs::{
+            FileHandle, FileSystem, FileSystemError, fat32::Fat32FileSystem, ramfs::RamFileSystem,
+        },
+        path::PathBuf,
+        run_task,
+    };
+
+    /// 数据可在外部观察的文件，用于检查哪些数据已写入文件
+    struct SharedFile {
+        data: Arc<Mutex<Vec<u8>>>,
+        pointer: u64,
+    }
+
+    impl FileHandle for SharedFile {
+        fn close(&mut self) -> BoxFuture<'_, Result<(), FileSystemError>> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn move_pointer(&mut self, position: u64) -> BoxFuture<'_, Result<(), FileSystemError>> {
+            self.pointer = position;
+            Box::pin(async { Ok(()) })
+        }
+
+        fn get_pointer(&mut self) -> BoxFuture<'_, Result<u64, FileSystemError>> {
+            let pointer = self.pointer;
+            Box::pin(async move { Ok(pointer) })
+        }
+
+        fn read<'fut>(
+            &'fut mut self,
+            buf: &'fut mut [u8],
+        ) -> BoxFuture<'fut, Result<u64, FileSystemError>> {
+            Box::pin(async move {
+                let data = self.data.lock().unwrap();
+                let start = (self.pointer as usize).min(data.len());
+                let len = buf.len().min(data.len() - start);
+                buf[..len].copy_from_slice(&data[start..start + len]);
+                self.pointer += len as u64;
+                Ok(len as u64)
+            })
+        }
+
+        fn write<'fut>(
+            &'fut mut self,
+            buf: &'fut [u8],
+        ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+            Box::pin(async move {
+                let mut data = self.data.lock().unwrap();
+                let start = self.pointer as usize;
+                if data.len() < start + buf.len() {
+                    data.resize(start + buf.len(), 0);
+                }
+                data[start..start + buf.len()].copy_from_slice(buf);
+                self.pointer += buf.len() as u64;
+                Ok(())
+            })
+        }
+
+        fn truncate(&mut self) -> BoxFuture<'_, Result<(), FileSystemError>> {
+            self.data.lock().unwrap().truncate(self.pointer as usize);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn shared_device(
+        blocks: usize,
+        cache_capacity: usize,
+    ) -> (FileBlockDevice, Arc<Mutex<Vec<u8>>>) {
+        let data = Arc::new(Mutex::new(vec![0u8; blocks * 512]));
+        let file = SharedFile {
+            data: data.clone(),
+            pointer: 0,
+        };
+        let device =
+            FileBlockDevice::new(Box::new(file), (blocks * 512) as u64, 512, cache_capacity);
+        (device, data)
+    }
+
+    #[test]
+    fn test_write_back() {
+        run_task(async {
+            let (device, data) = shared_device(8, 4);
+            assert_eq!(device.block_count(), 8);
+
+            // 写入仅修改缓存，读取能看到缓存中的数据
+            device.write_block(3, &[0xAA; 512]).await.unwrap();
+            assert!(data.lock().unwrap().iter().all(|&byte| byte == 0));
+            let mut buf = [0u8; 512];
+            device.read_block(3, &mut buf).await.unwrap();
+            assert_eq!(buf, [0xAA; 512]);
+
+            // 多块读取需要用脏块覆盖文件中的旧数据
+            let mut buf = [0u8; 512 * 2];
+            device.read_blocks(2, 2, &mut buf).await.unwrap();
+            assert_eq!(&buf[..512], &[0; 512]);
+            assert_eq!(&buf[512..], &[0xAA; 512]);
+
+            // 刷新后写入文件
+            device.flush().await.unwrap();
+            assert_eq!(&data.lock().unwrap()[512 * 3..512 * 4], &[0xAA; 512]);
+
+            // 越界访问
+            assert!(device.read_block(8, &mut [0u8; 512]).await.is_err());
+            assert!(device.write_blocks(7, 2, &[0u8; 1024]).await.is_err());
+        });
+    }
+
+    #[test]
+    fn test_eviction() {
+        run_task(async {
+            let (device, data) = shared_device(8, 2);
+            device.write_block(0, &[1; 512]).await.unwrap();
+            device.write_block(1, &[2; 512]).await.unwrap();
+            // 访问块0，块1成为最近最少使用的块
+            device.read_block(0, &mut [0u8; 512]).await.unwrap();
+            device.write_block(2, &[3; 512]).await.unwrap();
+            {
+                let data = data.lock().unwrap();
+                assert_eq!(&data[..512], &[0; 512]);
+                assert_eq!(&data[512..1024], &[2; 512]);
+                assert_eq!(&data[1024..1536], &[0; 512]);
+            }
+
+            // 多块写入直接写入文件，并使缓存失效
+            device.write_blocks(0, 3, &[4; 512 * 3]).await.unwrap();
+            device.flush().await.unwrap();
+            assert!(
+                data.lock().unwrap()[..512 * 3]
+                    .iter()
+                    .all(|&byte| byte == 4)
+            );
+            let mut buf = [0u8; 512];
+            device.read_block(2, &mut buf).await.unwrap();
+            assert_eq!(buf, [4; 512]);
+        });
+    }
+
+    #[test]
+    fn test_shrink() {
+        run_task(async {
+            let (device, data) = shared_device(8, 4);
+            device.read_block(0, &mut [0u8; 512]).await.unwrap();
+            device.write_block(1, &[1; 512]).await.unwrap();
+            // 仅丢弃未修改的块，已修改的块仍需写回
+            assert_eq!(device.shrink(), 1);
+            assert_eq!(device.shrink(), 0);
+            device.flush().await.unwrap();
+            assert_eq!(&data.lock().unwrap()[512..1024], &[1; 512]);
+            assert_eq!(device.shrink(), 1);
+        });
+    }
+
+    #[test]
+    fn test_loop_mount() {
+        run_task(async {
+            let host = RamFileSystem::new(1024 * 1024).unwrap();
+            let image = PathBuf::from_str("/extra.img").unwrap();
+            host.create_file(image.as_path()).await.unwrap();
+            let mut file = host.open_file(image.as_path()).await.unwrap();
+            file.write(&vec![0u8; 512 * 128]).await.unwrap();
+            file.close().await.unwrap();
+
+            // 在镜像文件中格式化FAT32并写入文件
+            let device = Arc::new(
+                FileBlockDevice::open(&host, image.as_path(), 512)
+                    .await
+                    .unwrap(),
+            );
+            let fs = Fat32FileSystem::with_format(device.clone()).await.unwrap();
+            let path = PathBuf::from_str("/hello.txt").unwrap();
+            fs.create_file(path.as_path()).await.unwrap();
+            let mut file = fs.open_file(path.as_path()).await.unwrap();
+            file.write(b"hello loop").await.unwrap();
+            file.close().await.unwrap();
+            fs.unmount().await.unwrap();
+            device.close().await.unwrap();
+
+            // 重新打开镜像文件并挂载
+            let device = Arc::new(
+                FileBlockDevice::open(&host, image.as_path(), 512)
+                    .await
+                    .unwrap(),
+            );
+            let fs = Fat32FileSystem::mount(device.clone()).await.unwrap();
+            let mut file = fs.open_file(path.as_path()).await.unwrap();
+            let mut buf = [0u8; 32];
+            let len = file.read(&mut buf).await.unwrap();
+            assert_eq!(&buf[..len as usize], b"hello loop");
+            file.close().await.unwrap();
+            fs.unmount().await.unwrap();
+            device.close().await.unwrap();
+
+            // 设备不改变镜像文件大小
+            let metadata = host.get_metadata(image.as_path()).await.unwrap();
+            assert_eq!(metadata.size, 512 * 128);
+        });
+    }
+}
@@ -9,6 +9,10 @@ use crate::{
 
 pub const PARTITION_TYPE_BOOTLOADER: u8 = 0xEB;
 pub const PARTITION_TYPE_FAT32: u8 = 0x0C;
+/// 崩溃转储分区，内核panic时写入崩溃转储
+pub const PARTITION_TYPE_CRASH: u8 = 0xEC;
+/// 加密卷分区，见 [`crate::device::crypt::CryptDevice`]
+pub const PARTITION_TYPE_CRYPT: u8 = 0xE8;
 
 // 分区表偏移
 const MBR_PARTITION_TABLE_OFFSET: usize = 446;
@@ -28,6 +32,7 @@ const MBR_PARTITION_ENTRY_COUNT: usize = 4;
 /// 调用[MbrPartitionDevice::mount]，从块设备中读取分区信息，并创建所有分区的块设备。
 /// 调用[MbrPartitionDevice::format]，重置分区为指定值，此操作仅修改分区信息，不修改分区内容。
 /// 调用[MbrPartitionDevice::get_partition_type]，获得此分区的分区类型。
+/// 调用[MbrPartitionDevice::get_partition_start]，获得此分区的起始块号。
 pub struct MbrPartitionDevice {
     inner: Arc<dyn BlockDevice>,
     partition_type: u8,
@@ -174,6 +179,11 @@ impl MbrPartitionDevice {
     pub fn get_partition_type(&self) -> u8 {
         self.partition_type
     }
+
+    /// 获取分区在底层块设备中的起始块号
+    pub fn get_partition_start(&self) -> u32 {
+        self.start
+    }
 }
 
 impl BlockDevice for MbrPartitionDevice {
@@ -276,6 +286,10 @@ impl BlockDevice for MbrPartitionDevice {
                 .await
         })
     }
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), BlockDeviceError>> {
+        self.inner.flush()
+    }
 }
 
 /// LBA逻辑地址转CHS柱面/磁头/扇区地址
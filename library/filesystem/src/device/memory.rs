@@ -1,11 +1,25 @@
-use core::fmt::{self, Write};
+use core::{
+    fmt::{self, Write},
+    ops::Range,
+};
 
-use alloc::{boxed::Box, vec::Vec};
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
 use async_locks::mutex::Mutex;
 
-use crate::device::BlockDevice;
+use crate::{
+    BoxFuture,
+    device::{BlockDevice, BlockDeviceError},
+};
 
 /// 基于内存的块设备实现
+///
+/// 除了作为普通块设备使用外，还提供了面向测试的能力：
+/// - 故障注入：让第N次写入失败（[`MemoryDevice::fail_nth_write`]），
+///   或让某个范围内的块读写失败（[`MemoryDevice::fail_block_range`]）
+/// - 延迟模拟：每次操作前等待钩子返回的Future（[`MemoryDevice::set_latency_hook`]）
+/// - 操作追踪：记录每次操作及其结果（[`MemoryDevice::set_tracing`]）
+///
+/// 注入的故障均返回 [`BlockDeviceError::IoError`]，失败的写入不会修改数据。
 pub struct MemoryDevice {
     // 数据
     data: Mutex<Vec<u8>>,
@@ -13,6 +27,47 @@ pub struct MemoryDevice {
     block_size: u64,
     // 块数量
     block_count: u64,
+    // 故障注入与追踪状态
+    state: Mutex<DeviceState>,
+    // 延迟钩子
+    latency_hook: Mutex<Option<Arc<LatencyHook>>>,
+}
+
+/// 延迟钩子，在每次操作执行前调用，设备会等待其返回的Future完成
+pub type LatencyHook = dyn Fn(DeviceOperation) -> BoxFuture<'static, ()> + Send + Sync;
+
+/// 块设备操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceOperation {
+    /// 读取指定块
+    Read(u64),
+    /// 写入指定块
+    Write(u64),
+    /// 刷新缓存
+    Flush,
+}
+
+/// 操作追踪记录
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceRecord {
+    /// 执行的操作
+    pub operation: DeviceOperation,
+    /// 操作是否因注入的故障而失败
+    pub failed: bool,
+}
+
+#[derive(Default)]
+struct DeviceState {
+    // 已执行的写入次数
+    write_count: u64,
+    // 写入次数达到此值时，该次写入失败
+    fail_write_at: Option<u64>,
+    // 读写失败的块范围
+    fail_blocks: Option<Range<u64>>,
+    // 是否记录操作
+    tracing: bool,
+    // 操作记录
+    trace: Vec<TraceRecord>,
 }
 
 impl MemoryDevice {
@@ -28,6 +83,82 @@ impl MemoryDevice {
             data: Mutex::new(data),
             block_size,
             block_count: aligned_size / block_size,
+            state: Mutex::new(DeviceState::default()),
+            latency_hook: Mutex::new(None),
+        }
+    }
+
+    /// 让从现在起的第`n`次写入失败，`n`从1开始
+    ///
+    /// 仅生效一次，失败后后续写入恢复正常
+    pub async fn fail_nth_write(&self, n: u64) {
+        assert!(n > 0);
+        let mut state = self.state.lock().await;
+        state.fail_write_at = Some(state.write_count + n);
+    }
+
+    /// 让指定范围内的块读写全部失败，直到调用 [`MemoryDevice::clear_faults`]
+    pub async fn fail_block_range(&self, range: Range<u64>) {
+        self.state.lock().await.fail_blocks = Some(range);
+    }
+
+    /// 清除所有注入的故障
+    pub async fn clear_faults(&self) {
+        let mut state = self.state.lock().await;
+        state.fail_write_at = None;
+        state.fail_blocks = None;
+    }
+
+    /// 设置延迟钩子，传入`None`以取消
+    pub async fn set_latency_hook(&self, hook: Option<Box<LatencyHook>>) {
+        *self.latency_hook.lock().await = hook.map(Arc::from);
+    }
+
+    /// 开启或关闭操作追踪
+    pub async fn set_tracing(&self, tracing: bool) {
+        self.state.lock().await.tracing = tracing;
+    }
+
+    /// 取出目前为止记录的操作，并清空记录
+    pub async fn take_trace(&self) -> Vec<TraceRecord> {
+        core::mem::take(&mut self.state.lock().await.trace)
+    }
+
+    /// 执行操作前的公共处理：等待延迟钩子，判断是否注入故障并记录
+    async fn begin(&self, operation: DeviceOperation) -> Result<(), BlockDeviceError> {
+        let hook = self.latency_hook.lock().await.clone();
+        if let Some(hook) = hook {
+            hook(operation).await;
+        }
+
+        let mut state = self.state.lock().await;
+        let block_failed = |block_index: u64| {
+            state
+                .fail_blocks
+                .as_ref()
+                .is_some_and(|range| range.contains(&block_index))
+        };
+        let failed = match operation {
+            DeviceOperation::Read(block_index) => block_failed(block_index),
+            DeviceOperation::Write(block_index) => {
+                let block_failed = block_failed(block_index);
+                state.write_count += 1;
+                let nth_failed = state.fail_write_at == Some(state.write_count);
+                if nth_failed {
+                    state.fail_write_at = None;
+                }
+                block_failed || nth_failed
+            }
+            DeviceOperation::Flush => false,
+        };
+        if state.tracing {
+            state.trace.push(TraceRecord { operation, failed });
+        }
+
+        if failed {
+            Err(BlockDeviceError::IoError)
+        } else {
+            Ok(())
         }
     }
 
@@ -97,6 +228,7 @@ impl BlockDevice for MemoryDevice {
             if (buf.len() as u64) < self.block_size {
                 return Err(super::BlockDeviceError::OutOfBounds);
             }
+            self.begin(DeviceOperation::Write(block_index)).await?;
 
             let start = (block_index * self.block_size) as usize;
             let end = start + self.block_size as usize;
@@ -118,6 +250,7 @@ impl BlockDevice for MemoryDevice {
             if (buf.len() as u64) < self.block_size {
                 return Err(super::BlockDeviceError::OutOfBounds);
             }
+            self.begin(DeviceOperation::Read(block_index)).await?;
 
             let start = (block_index * self.block_size) as usize;
             let end = start + self.block_size as usize;
@@ -127,4 +260,123 @@ impl BlockDevice for MemoryDevice {
             Ok(())
         })
     }
+    fn flush(&self) -> BoxFuture<'_, Result<(), BlockDeviceError>> {
+        Box::pin(self.begin(DeviceOperation::Flush))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    };
+
+    use alloc::{boxed::Box, vec};
+
+    use crate::{
+        device::{
+            BlockDevice, BlockDeviceError,
+            memory::{DeviceOperation, MemoryDevice, TraceRecord},
+        },
+        run_task,
+    };
+
+    #[test]
+    fn test_fail_nth_write() {
+        run_task(async {
+            let device = MemoryDevice::new(512 * 4, 512);
+            device.fail_nth_write(2).await;
+
+            device.write_block(0, &[1; 512]).await.unwrap();
+            assert!(matches!(
+                device.write_block(1, &[1; 512]).await,
+                Err(BlockDeviceError::IoError)
+            ));
+            device.write_block(2, &[1; 512]).await.unwrap();
+
+            // 失败的写入不修改数据
+            let mut buf = vec![0; 512];
+            device.read_block(1, &mut buf).await.unwrap();
+            assert_eq!(buf, [0; 512]);
+            device.read_block(2, &mut buf).await.unwrap();
+            assert_eq!(buf, [1; 512]);
+        });
+    }
+
+    #[test]
+    fn test_fail_block_range() {
+        run_task(async {
+            let device = MemoryDevice::new(512 * 4, 512);
+            device.fail_block_range(1..3).await;
+
+            let mut buf = vec![0; 512];
+            device.read_block(0, &mut buf).await.unwrap();
+            assert!(matches!(
+                device.read_block(1, &mut buf).await,
+                Err(BlockDeviceError::IoError)
+            ));
+            assert!(matches!(
+                device.write_block(2, &buf).await,
+                Err(BlockDeviceError::IoError)
+            ));
+            device.write_block(3, &buf).await.unwrap();
+
+            device.clear_faults().await;
+            device.read_block(1, &mut buf).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_trace() {
+        run_task(async {
+            let device = MemoryDevice::new(512 * 4, 512);
+            let mut buf = vec![0; 512];
+            device.read_block(0, &mut buf).await.unwrap();
+
+            device.set_tracing(true).await;
+            device.fail_nth_write(1).await;
+            _ = device.write_block(1, &buf).await;
+            device.read_blocks(2, 2, &mut vec![0; 1024]).await.unwrap();
+            device.flush().await.unwrap();
+            device.set_tracing(false).await;
+            device.read_block(0, &mut buf).await.unwrap();
+
+            let record = |operation, failed| TraceRecord { operation, failed };
+            assert_eq!(
+                device.take_trace().await,
+                [
+                    record(DeviceOperation::Write(1), true),
+                    record(DeviceOperation::Read(2), false),
+                    record(DeviceOperation::Read(3), false),
+                    record(DeviceOperation::Flush, false),
+                ]
+            );
+            assert!(device.take_trace().await.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_latency_hook() {
+        run_task(async {
+            let device = MemoryDevice::new(512 * 4, 512);
+            let delayed = Arc::new(AtomicU64::new(0));
+            let counter = delayed.clone();
+            device
+                .set_latency_hook(Some(Box::new(move |_| {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    Box::pin(async {})
+                })))
+                .await;
+
+            let mut buf = vec![0; 512];
+            device.write_block(0, &buf).await.unwrap();
+            device.read_block(0, &mut buf).await.unwrap();
+            assert_eq!(delayed.load(Ordering::Relaxed), 2);
+
+            device.set_latency_hook(None).await;
+            device.read_block(0, &mut buf).await.unwrap();
+            assert_eq!(delayed.load(Ordering::Relaxed), 2);
+        });
+    }
 }
@@ -2,8 +2,13 @@ use alloc::boxed::Box;
 
 use crate::BoxFuture;
 
+pub mod checksum;
+pub mod crypt;
+pub mod file;
 pub mod mbr;
 pub mod memory;
+pub mod qcow2;
+pub mod raid;
 
 /// 块设备的抽象
 ///
@@ -144,6 +149,14 @@ pub trait BlockDevice: Send + Sync + 'static {
     ) -> BoxFuture<'_, Result<(), BlockDeviceError>> {
         self.write_zeros(block_index, count)
     }
+
+    /// 将设备内部缓存的数据写入存储介质
+    ///
+    /// 带有写回缓存的实现在此函数返回前，需保证此前所有已完成的写入均已持久化。
+    /// 文件系统在卸载时会调用此函数。trait 提供了空实现，适用于没有写回缓存的设备。
+    fn flush(&self) -> BoxFuture<'_, Result<(), BlockDeviceError>> {
+        Box::pin(async { Ok(()) })
+    }
 }
 
 /// 块设备访问错误
@@ -155,6 +168,8 @@ pub enum BlockDeviceError {
     OutOfMemory,
     /// 底层IO错误
     IoError,
+    /// 数据校验失败，读到的数据与写入时不一致
+    Corrupted,
     #[cfg(feature = "dyn-io-error")]
     DynError(Box<dyn core::error::Error + Send + 'static>),
 }
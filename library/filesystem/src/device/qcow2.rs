@@ -0,0 +1,1155 @@
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    vec::Vec,
+};
+use async_locks::mutex::Mutex;
+
+use crate::{
+    BoxFuture,
+    device::{BlockDevice, BlockDeviceError},
+};
+
+/// qcow2文件头部的魔数
+pub const QCOW2_MAGIC: [u8; 4] = *b"QFI\xfb";
+/// 支持的qcow2版本
+const QCOW2_VERSION: u32 = 3;
+/// 引用计数的位宽为 `1 << REFCOUNT_ORDER`，即16位
+const REFCOUNT_ORDER: u32 = 4;
+/// 虚拟磁盘的块大小
+const BLOCK_SIZE: u64 = 512;
+/// 簇大小的范围，以2为底的对数表示，与qemu一致
+const MIN_CLUSTER_BITS: u32 = 9;
+const MAX_CLUSTER_BITS: u32 = 21;
+/// 默认簇大小为64KiB，与qemu-img一致
+pub const DEFAULT_CLUSTER_BITS: u32 = 16;
+
+/// L1、L2表项：簇的引用计数为1，可以原地写入
+const ENTRY_COPIED: u64 = 1 << 63;
+/// L2表项：压缩的簇
+const ENTRY_COMPRESSED: u64 = 1 << 62;
+/// L2表项：簇读取为零
+const ENTRY_ZERO: u64 = 1;
+/// L1、L2表项中的偏移
+const ENTRY_OFFSET_MASK: u64 = 0x00FF_FFFF_FFFF_FE00;
+
+/// qcow2格式的虚拟磁盘
+///
+/// 包装保存qcow2文件的块设备，对外表现为512字节块的虚拟磁盘。虚拟磁盘的簇在首次写入非零数据时才分配，
+/// 因此文件的大小与实际写入的数据量相当，而非虚拟磁盘的容量。生成的文件可以直接被qemu使用。
+///
+/// 整簇写入的数据按内容去重：内容相同的簇共享同一个宿主簇，写入共享的簇时先复制（写时复制）。
+/// 整簇写零会释放对应的宿主簇，释放的簇会被之后的写入重新使用，但文件不会缩短。
+/// 去重只在当前打开期间生效，重新打开后只有新写入的簇参与去重。
+///
+/// 调用 [`Qcow2Device::create`] 在设备上创建qcow2文件，调用 [`Qcow2Device::open`] 打开已有的文件。
+/// 仅支持version 3、16位引用计数且没有快照、后备文件、加密与压缩簇的文件。
+///
+/// 元数据与数据的写入没有排序，写入过程中断电可能使文件损坏。
+pub struct Qcow2Device {
+    inner: Arc<dyn BlockDevice>,
+    // 底层设备的块大小
+    inner_block_size: u64,
+    // 簇大小，以2为底的对数表示
+    cluster_bits: u32,
+    // 虚拟磁盘的块数量
+    block_count: u64,
+    // L1表在文件中的偏移
+    l1_table_offset: u64,
+    // 元数据与去重索引，所有读写持有此锁
+    state: Mutex<Qcow2State>,
+}
+
+struct Qcow2State {
+    // L1表，修改时同时写入文件
+    l1_table: Vec<u64>,
+    // 引用计数表在文件中的偏移
+    refcount_table_offset: u64,
+    // 引用计数表，每项为引用计数块的偏移，0表示尚未分配
+    refcount_table: Vec<u64>,
+    // 每个宿主簇的引用计数，与引用计数块中的内容一致，长度为已使用的宿主簇数量
+    refcounts: Vec<u16>,
+    // 已释放、可以重新分配的宿主簇
+    free_clusters: BTreeSet<u64>,
+    // 去重索引：内容的散列值 -> 宿主簇
+    dedup: BTreeMap<u64, u64>,
+    // 已加入去重索引的宿主簇 -> 内容的散列值，簇被修改或释放时用于移除索引
+    hashes: BTreeMap<u64, u64>,
+    // 被共享或已加入去重索引的宿主簇 -> 引用它的L2表项在文件中的偏移，用于维护COPIED标志
+    referrers: BTreeMap<u64, Vec<u64>>,
+}
+
+#[derive(Debug)]
+pub enum Qcow2Error {
+    /// 底层IO错误
+    IoError(BlockDeviceError),
+    /// 底层设备的块大小不是2的整数次幂，或大于簇大小
+    BlockSizeNotExpected,
+    /// 虚拟磁盘容量为0、不是512字节的整数倍或过大，或簇大小超出范围
+    InvalidSize,
+    /// 设备容量不足
+    DeviceTooSmall,
+    /// 设备上没有qcow2文件，或文件的元数据损坏
+    InvalidHeader,
+    /// 文件使用了不支持的版本或特性
+    Unsupported,
+}
+
+impl From<BlockDeviceError> for Qcow2Error {
+    fn from(value: BlockDeviceError) -> Self {
+        Self::IoError(value)
+    }
+}
+
+/// qcow2文件头部，所有字段均为大端序
+struct Qcow2Header {
+    cluster_bits: u32,
+    // 虚拟磁盘大小，单位为字节
+    size: u64,
+    // L1表项数量
+    l1_size: u32,
+    l1_table_offset: u64,
+    refcount_table_offset: u64,
+    refcount_table_clusters: u32,
+}
+
+impl Qcow2Header {
+    /// version 3头部的长度
+    const SIZE: usize = 104;
+
+    fn parse(buf: &[u8]) -> Result<Self, Qcow2Error> {
+        let u32_at =
+            |offset: usize| u32::from_be_bytes(buf[offset..offset + 4].try_into().unwrap());
+        let u64_at =
+            |offset: usize| u64::from_be_bytes(buf[offset..offset + 8].try_into().unwrap());
+        if buf[0..4] != QCOW2_MAGIC {
+            return Err(Qcow2Error::InvalidHeader);
+        }
+        // 后备文件、加密、快照、不兼容特性（如脏标志、外部数据文件）均不支持
+        if u32_at(4) != QCOW2_VERSION
+            || u64_at(8) != 0
+            || u32_at(32) != 0
+            || u32_at(60) != 0
+            || u64_at(72) != 0
+            || u32_at(96) != REFCOUNT_ORDER
+        {
+            return Err(Qcow2Error::Unsupported);
+        }
+        let header = Self {
+            cluster_bits: u32_at(20),
+            size: u64_at(24),
+            l1_size: u32_at(36),
+            l1_table_offset: u64_at(40),
+            refcount_table_offset: u64_at(48),
+            refcount_table_clusters: u32_at(56),
+        };
+        if !(MIN_CLUSTER_BITS..=MAX_CLUSTER_BITS).contains(&header.cluster_bits) {
+            return Err(Qcow2Error::InvalidHeader);
+        }
+        let cluster_mask = (1 << header.cluster_bits) - 1;
+        let l2_entries = 1 << (header.cluster_bits - 3);
+        if (header.l1_size as u64)
+            < header
+                .size
+                .div_ceil(1 << header.cluster_bits)
+                .div_ceil(l2_entries)
+            || header.l1_table_offset & cluster_mask != 0
+            || header.refcount_table_offset & cluster_mask != 0
+            || header.refcount_table_offset == 0
+            || header.refcount_table_clusters == 0
+        {
+            return Err(Qcow2Error::InvalidHeader);
+        }
+        Ok(header)
+    }
+
+    fn write(&self, buf: &mut [u8]) {
+        buf[..Self::SIZE].fill(0);
+        buf[0..4].copy_from_slice(&QCOW2_MAGIC);
+        buf[4..8].copy_from_slice(&QCOW2_VERSION.to_be_bytes());
+        buf[20..24].copy_from_slice(&self.cluster_bits.to_be_bytes());
+        buf[24..32].copy_from_slice(&self.size.to_be_bytes());
+        buf[36..40].copy_from_slice(&self.l1_size.to_be_bytes());
+        buf[40..48].copy_from_slice(&self.l1_table_offset.to_be_bytes());
+        buf[48..56].copy_from_slice(&self.refcount_table_offset.to_be_bytes());
+        buf[56..60].copy_from_slice(&self.refcount_table_clusters.to_be_bytes());
+        buf[96..100].copy_from_slice(&REFCOUNT_ORDER.to_be_bytes());
+        buf[100..104].copy_from_slice(&(Self::SIZE as u32).to_be_bytes());
+    }
+}
+
+/// 创建文件时的布局：头部、L1表、引用计数表依次位于文件开头
+struct Layout {
+    l1_size: u64,
+    l1_clusters: u64,
+    refcount_table_clusters: u64,
+    // 所有虚拟簇均已分配时宿主簇的数量
+    max_clusters: u64,
+}
+
+impl Layout {
+    fn new(size: u64, cluster_bits: u32) -> Result<Self, Qcow2Error> {
+        if size == 0
+            || !size.is_multiple_of(BLOCK_SIZE)
+            || size > 1 << 56
+            || !(MIN_CLUSTER_BITS..=MAX_CLUSTER_BITS).contains(&cluster_bits)
+        {
+            return Err(Qcow2Error::InvalidSize);
+        }
+        let cluster_size = 1u64 << cluster_bits;
+        let data_clusters = size.div_ceil(cluster_size);
+        let l1_size = data_clusters.div_ceil(cluster_size / 8);
+        if l1_size > u32::MAX as u64 {
+            return Err(Qcow2Error::InvalidSize);
+        }
+        let l1_clusters = (l1_size * 8).div_ceil(cluster_size);
+        // 引用计数表按最坏情况预先分配，之后不需要移动。引用计数块自身也占用簇，因此迭代计算
+        let refcounts_per_block = cluster_size / 2;
+        let mut refcount_table_clusters = 1;
+        loop {
+            let clusters = 1 + l1_clusters + refcount_table_clusters + l1_size + data_clusters;
+            let blocks = clusters.div_ceil(refcounts_per_block - 1);
+            let required = (blocks * 8).div_ceil(cluster_size);
+            if required <= refcount_table_clusters {
+                return Ok(Self {
+                    l1_size,
+                    l1_clusters,
+                    refcount_table_clusters,
+                    max_clusters: clusters + blocks,
+                });
+            }
+            refcount_table_clusters = required;
+        }
+    }
+}
+
+impl Qcow2Device {
+    /// 在块设备上创建qcow2文件，虚拟磁盘的容量为 `size` 字节，簇大小为 `1 << cluster_bits` 字节
+    ///
+    /// 创建时只写入头部与空的元数据表，底层设备的容量至少为 [`Qcow2Device::max_file_size`]
+    /// 时才能写满虚拟磁盘。
+    pub async fn create(
+        inner: Arc<dyn BlockDevice>,
+        size: u64,
+        cluster_bits: u32,
+    ) -> Result<Self, Qcow2Error> {
+        let layout = Layout::new(size, cluster_bits)?;
+        let inner_block_size = check_device(inner.as_ref(), cluster_bits)?;
+        let cluster_size = 1u64 << cluster_bits;
+        let metadata_clusters = 1 + layout.l1_clusters + layout.refcount_table_clusters;
+        // 还需要至少一个引用计数块
+        if (metadata_clusters + 1) * cluster_size > inner.block_count() * inner_block_size {
+            return Err(Qcow2Error::DeviceTooSmall);
+        }
+
+        let l1_table_offset = cluster_size;
+        let refcount_table_offset = (1 + layout.l1_clusters) * cluster_size;
+        let device = Self {
+            inner,
+            inner_block_size,
+            cluster_bits,
+            block_count: size / BLOCK_SIZE,
+            l1_table_offset,
+            state: Mutex::new(Qcow2State {
+                l1_table: alloc::vec![0; layout.l1_size as usize],
+                refcount_table_offset,
+                refcount_table: alloc::vec![
+                    0;
+                    (layout.refcount_table_clusters * cluster_size / 8) as usize
+                ],
+                refcounts: alloc::vec![0; metadata_clusters as usize],
+                free_clusters: BTreeSet::new(),
+                dedup: BTreeMap::new(),
+                hashes: BTreeMap::new(),
+                referrers: BTreeMap::new(),
+            }),
+        };
+        device
+            .inner
+            .write_zeros(0, metadata_clusters * cluster_size / inner_block_size)
+            .await?;
+        {
+            let mut state = device.state.lock().await;
+            for cluster in 0..metadata_clusters {
+                device.set_refcount(&mut state, cluster, 1).await?;
+            }
+        }
+
+        // 最后写入头部，创建中断时设备不会被识别为qcow2文件
+        let mut buf = [0u8; Qcow2Header::SIZE];
+        Qcow2Header {
+            cluster_bits,
+            size,
+            l1_size: layout.l1_size as u32,
+            l1_table_offset,
+            refcount_table_offset,
+            refcount_table_clusters: layout.refcount_table_clusters as u32,
+        }
+        .write(&mut buf);
+        device.write_at(0, &buf).await?;
+        Ok(device)
+    }
+
+    /// 打开块设备上的qcow2文件
+    ///
+    /// 打开时读取全部L1、L2表与引用计数块，耗时与已分配的簇数量成正比。
+    pub async fn open(inner: Arc<dyn BlockDevice>) -> Result<Self, Qcow2Error> {
+        if inner.block_count() * inner.block_size() < Qcow2Header::SIZE as u64 {
+            return Err(Qcow2Error::InvalidHeader);
+        }
+        let mut buf = [0u8; Qcow2Header::SIZE];
+        read_device_at(inner.as_ref(), 0, &mut buf).await?;
+        let header = Qcow2Header::parse(&buf)?;
+        let cluster_bits = header.cluster_bits;
+        let inner_block_size = check_device(inner.as_ref(), cluster_bits)?;
+        let cluster_size = 1u64 << cluster_bits;
+        let file_size = inner.block_count() * inner_block_size;
+        let fits =
+            |offset: u64, len: u64| offset.checked_add(len).is_some_and(|end| end <= file_size);
+        let l1_bytes = header.l1_size as u64 * 8;
+        let refcount_table_bytes = header.refcount_table_clusters as u64 * cluster_size;
+        if !fits(header.l1_table_offset, l1_bytes)
+            || !fits(header.refcount_table_offset, refcount_table_bytes)
+        {
+            return Err(Qcow2Error::InvalidHeader);
+        }
+
+        let device = Self {
+            inner,
+            inner_block_size,
+            cluster_bits,
+            block_count: header.size / BLOCK_SIZE,
+            l1_table_offset: header.l1_table_offset,
+            state: Mutex::new(Qcow2State {
+                l1_table: Vec::new(),
+                refcount_table_offset: header.refcount_table_offset,
+                refcount_table: Vec::new(),
+                refcounts: Vec::new(),
+                free_clusters: BTreeSet::new(),
+                dedup: BTreeMap::new(),
+                hashes: BTreeMap::new(),
+                referrers: BTreeMap::new(),
+            }),
+        };
+        let mut state = device.state.lock().await;
+
+        let mut buf = alloc::vec![0u8; l1_bytes as usize];
+        device.read_at(header.l1_table_offset, &mut buf).await?;
+        state.l1_table = parse_entries(&buf);
+        let mut buf = alloc::vec![0u8; refcount_table_bytes as usize];
+        device
+            .read_at(header.refcount_table_offset, &mut buf)
+            .await?;
+        state.refcount_table = parse_entries(&buf);
+
+        let refcounts_per_block = device.refcounts_per_block();
+        let mut block = alloc::vec![0u8; cluster_size as usize];
+        for (index, &offset) in state.refcount_table.clone().iter().enumerate() {
+            if offset == 0 {
+                continue;
+            }
+            if offset & (cluster_size - 1) != 0 || !fits(offset, cluster_size) {
+                return Err(Qcow2Error::InvalidHeader);
+            }
+            device.read_at(offset, &mut block).await?;
+            let first = index * refcounts_per_block as usize;
+            state.refcounts.resize(first, 0);
+            state.refcounts.extend(
+                block
+                    .chunks_exact(2)
+                    .map(|value| u16::from_be_bytes([value[0], value[1]])),
+            );
+        }
+        let used = state
+            .refcounts
+            .iter()
+            .rposition(|&count| count != 0)
+            .map_or(0, |last| last + 1);
+        state.refcounts.truncate(used);
+        state.free_clusters = (0..used as u64)
+            .filter(|&cluster| state.refcounts[cluster as usize] == 0)
+            .collect();
+
+        // 记录共享簇的引用者，写时复制后需要为剩下的引用者恢复COPIED标志
+        let mut table = alloc::vec![0u8; cluster_size as usize];
+        for l1_entry in state.l1_table.clone() {
+            let l2_offset = l1_entry & ENTRY_OFFSET_MASK;
+            if l2_offset == 0 {
+                continue;
+            }
+            if !fits(l2_offset, cluster_size) {
+                return Err(Qcow2Error::InvalidHeader);
+            }
+            device.read_at(l2_offset, &mut table).await?;
+            for (index, entry) in parse_entries(&table).into_iter().enumerate() {
+                if entry & ENTRY_COMPRESSED != 0 {
+                    return Err(Qcow2Error::Unsupported);
+                }
+                let host = (entry & ENTRY_OFFSET_MASK) >> cluster_bits;
+                if host == 0 {
+                    continue;
+                }
+                match state.refcounts.get(host as usize) {
+                    None | Some(0) => return Err(Qcow2Error::InvalidHeader),
+                    Some(1) => {}
+                    Some(_) => state
+                        .referrers
+                        .entry(host)
+                        .or_default()
+                        .push(l2_offset + index as u64 * 8),
+                }
+            }
+        }
+        drop(state);
+        Ok(device)
+    }
+
+    /// 容量为 `size` 字节的虚拟磁盘写满时，qcow2文件的最大长度
+    pub fn max_file_size(size: u64, cluster_bits: u32) -> Option<u64> {
+        let layout = Layout::new(size, cluster_bits).ok()?;
+        Some(layout.max_clusters << cluster_bits)
+    }
+
+    /// 已分配的宿主簇占用的字节数，包括元数据
+    pub async fn allocated_size(&self) -> u64 {
+        let state = self.state.lock().await;
+        let clusters = state.refcounts.len() - state.free_clusters.len();
+        (clusters as u64) << self.cluster_bits
+    }
+
+    fn cluster_size(&self) -> u64 {
+        1 << self.cluster_bits
+    }
+
+    fn refcounts_per_block(&self) -> u64 {
+        self.cluster_size() / 2
+    }
+
+    async fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<(), BlockDeviceError> {
+        read_device_at(self.inner.as_ref(), offset, buf).await
+    }
+
+    /// 写入文件中任意位置的数据，未对齐的部分先读取再写回
+    async fn write_at(&self, offset: u64, buf: &[u8]) -> Result<(), BlockDeviceError> {
+        let block_size = self.inner_block_size;
+        let len = buf.len() as u64;
+        if offset.is_multiple_of(block_size) && len.is_multiple_of(block_size) {
+            return self
+                .inner
+                .write_blocks(offset / block_size, len / block_size, buf)
+                .await;
+        }
+        let first = offset / block_size;
+        let count = (offset + len).div_ceil(block_size) - first;
+        let mut blocks = alloc::vec![0u8; (count * block_size) as usize];
+        self.inner.read_blocks(first, count, &mut blocks).await?;
+        let start = (offset - first * block_size) as usize;
+        blocks[start..start + buf.len()].copy_from_slice(buf);
+        self.inner.write_blocks(first, count, &blocks).await
+    }
+
+    async fn read_entry(&self, offset: u64) -> Result<u64, BlockDeviceError> {
+        let mut buf = [0u8; 8];
+        self.read_at(offset, &mut buf).await?;
+        Ok(u64::from_be_bytes(buf))
+    }
+
+    async fn write_entry(&self, offset: u64, entry: u64) -> Result<(), BlockDeviceError> {
+        self.write_at(offset, &entry.to_be_bytes()).await
+    }
+
+    /// 虚拟簇对应的L2表项在文件中的偏移，L2表不存在时，`allocate` 为true则分配，否则返回None
+    async fn l2_entry_offset(
+        &self,
+        state: &mut Qcow2State,
+        virtual_cluster: u64,
+        allocate: bool,
+    ) -> Result<Option<u64>, BlockDeviceError> {
+        let l2_entries = self.cluster_size() / 8;
+        let l1_index = (virtual_cluster / l2_entries) as usize;
+        let mut l2_offset = state.l1_table[l1_index] & ENTRY_OFFSET_MASK;
+        if l2_offset == 0 {
+            if !allocate {
+                return Ok(None);
+            }
+            l2_offset = self.alloc_cluster(state).await? << self.cluster_bits;
+            self.zero_cluster(l2_offset).await?;
+            state.l1_table[l1_index] = l2_offset | ENTRY_COPIED;
+            self.write_entry(
+                self.l1_table_offset + l1_index as u64 * 8,
+                state.l1_table[l1_index],
+            )
+            .await?;
+        }
+        Ok(Some(l2_offset + (virtual_cluster % l2_entries) * 8))
+    }
+
+    async fn zero_cluster(&self, offset: u64) -> Result<(), BlockDeviceError> {
+        let blocks = self.cluster_size() / self.inner_block_size;
+        self.inner
+            .write_zeros(offset / self.inner_block_size, blocks)
+            .await
+    }
+
+    /// 分配一个宿主簇，优先使用已释放的簇，其引用计数设为1
+    async fn alloc_cluster(&self, state: &mut Qcow2State) -> Result<u64, BlockDeviceError> {
+        let cluster = match state.free_clusters.pop_first() {
+            Some(cluster) => cluster,
+            None => self.append_cluster(state)?,
+        };
+        self.set_refcount(state, cluster, 1).await?;
+        Ok(cluster)
+    }
+
+    /// 在文件末尾追加一个宿主簇，引用计数暂为0
+    fn append_cluster(&self, state: &mut Qcow2State) -> Result<u64, BlockDeviceError> {
+        let cluster = state.refcounts.len() as u64;
+        if cluster >= state.refcount_table.len() as u64 * self.refcounts_per_block() {
+            return Err(BlockDeviceError::OutOfBounds);
+        }
+        state.refcounts.push(0);
+        Ok(cluster)
+    }
+
+    /// 修改宿主簇的引用计数，所在的引用计数块不存在时分配
+    async fn set_refcount(
+        &self,
+        state: &mut Qcow2State,
+        cluster: u64,
+        count: u16,
+    ) -> Result<(), BlockDeviceError> {
+        let per_block = self.refcounts_per_block();
+        let table_index = (cluster / per_block) as usize;
+        while state.refcount_table[table_index] == 0 {
+            // 新的引用计数块位于文件末尾，其自身所在范围的引用计数块也可能不存在，此时优先用于该范围
+            let block = self.append_cluster(state)?;
+            let own_index = (block / per_block) as usize;
+            let target = if state.refcount_table[own_index] == 0 {
+                own_index
+            } else {
+                table_index
+            };
+            let block_offset = block << self.cluster_bits;
+            self.zero_cluster(block_offset).await?;
+            state.refcount_table[target] = block_offset;
+            self.write_entry(
+                state.refcount_table_offset + target as u64 * 8,
+                block_offset,
+            )
+            .await?;
+            self.write_refcount(state, block, 1).await?;
+        }
+        self.write_refcount(state, cluster, count).await
+    }
+
+    /// 写入引用计数，所在的引用计数块必须已经存在
+    async fn write_refcount(
+        &self,
+        state: &mut Qcow2State,
+        cluster: u64,
+        count: u16,
+    ) -> Result<(), BlockDeviceError> {
+        let per_block = self.refcounts_per_block();
+        let block_offset = state.refcount_table[(cluster / per_block) as usize];
+        debug_assert!(block_offset != 0);
+        state.refcounts[cluster as usize] = count;
+        self.write_at(block_offset + cluster % per_block * 2, &count.to_be_bytes())
+            .await
+    }
+
+    /// 为宿主簇增加一个引用者，簇从独占变为共享时清除原引用者的COPIED标志
+    async fn share_cluster(
+        &self,
+        state: &mut Qcow2State,
+        cluster: u64,
+        referrer: u64,
+    ) -> Result<(), BlockDeviceError> {
+        let count = state.refcounts[cluster as usize] + 1;
+        self.set_refcount(state, cluster, count).await?;
+        if count == 2 {
+            for &offset in state.referrers.get(&cluster).into_iter().flatten() {
+                self.write_entry(offset, cluster << self.cluster_bits)
+                    .await?;
+            }
+        }
+        state.referrers.entry(cluster).or_default().push(referrer);
+        Ok(())
+    }
+
+    /// 移除宿主簇的一个引用者，引用计数降为0时释放，降为1时为剩下的引用者恢复COPIED标志
+    async fn release_cluster(
+        &self,
+        state: &mut Qcow2State,
+        cluster: u64,
+        referrer: u64,
+    ) -> Result<(), BlockDeviceError> {
+        let count = state.refcounts[cluster as usize] - 1;
+        self.set_refcount(state, cluster, count).await?;
+        if let Some(referrers) = state.referrers.get_mut(&cluster) {
+            referrers.retain(|&offset| offset != referrer);
+        }
+        match count {
+            0 => {
+                forget_content(state, cluster);
+                state.referrers.remove(&cluster);
+                state.free_clusters.insert(cluster);
+            }
+            1 => {
+                if let Some(&[offset]) = state.referrers.get(&cluster).map(Vec::as_slice) {
+                    self.write_entry(offset, (cluster << self.cluster_bits) | ENTRY_COPIED)
+                        .await?;
+                }
+                if !state.hashes.contains_key(&cluster) {
+                    state.referrers.remove(&cluster);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// 将宿主簇加入去重索引，`content` 为簇的完整内容
+    fn index_content(state: &mut Qcow2State, cluster: u64, content: &[u8], referrer: u64) {
+        let hash = content_hash(content);
+        state.hashes.insert(cluster, hash);
+        state.dedup.entry(hash).or_insert(cluster);
+        let referrers = state.referrers.entry(cluster).or_default();
+        if !referrers.contains(&referrer) {
+            referrers.push(referrer);
+        }
+    }
+
+    /// 去重索引中的宿主簇内容是否与 `content` 相同
+    async fn same_content(&self, cluster: u64, content: &[u8]) -> Result<bool, BlockDeviceError> {
+        let mut buf = alloc::vec![0u8; content.len()];
+        self.read_at(cluster << self.cluster_bits, &mut buf).await?;
+        Ok(buf == content)
+    }
+
+    async fn read_cluster(
+        &self,
+        state: &mut Qcow2State,
+        virtual_cluster: u64,
+        offset: usize,
+        buf: &mut [u8],
+    ) -> Result<(), BlockDeviceError> {
+        let Some(entry_offset) = self.l2_entry_offset(state, virtual_cluster, false).await? else {
+            buf.fill(0);
+            return Ok(());
+        };
+        let entry = self.read_entry(entry_offset).await?;
+        if entry & ENTRY_COMPRESSED != 0 {
+            return Err(BlockDeviceError::IoError);
+        }
+        let host_offset = entry & ENTRY_OFFSET_MASK;
+        if host_offset == 0 || entry & ENTRY_ZERO != 0 {
+            buf.fill(0);
+            return Ok(());
+        }
+        self.read_at(host_offset + offset as u64, buf).await
+    }
+
+    async fn write_cluster(
+        &self,
+        state: &mut Qcow2State,
+        virtual_cluster: u64,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<(), BlockDeviceError> {
+        let cluster_size = self.cluster_size() as usize;
+        let zero = data.iter().all(|&byte| byte == 0);
+        // 写零不需要分配L2表，未分配的簇读取为零
+        let Some(entry_offset) = self.l2_entry_offset(state, virtual_cluster, !zero).await? else {
+            return Ok(());
+        };
+        let entry = self.read_entry(entry_offset).await?;
+        if entry & ENTRY_COMPRESSED != 0 {
+            return Err(BlockDeviceError::IoError);
+        }
+        let host = (entry & ENTRY_OFFSET_MASK) >> self.cluster_bits;
+        let reads_zero = host == 0 || entry & ENTRY_ZERO != 0;
+        let full = data.len() == cluster_size;
+
+        if zero && (full || reads_zero) {
+            // 写零后簇的内容全部为零，解除映射并释放宿主簇
+            if entry != 0 {
+                self.write_entry(entry_offset, 0).await?;
+                if host != 0 {
+                    self.release_cluster(state, host, entry_offset).await?;
+                }
+            }
+            return Ok(());
+        }
+
+        if full {
+            let candidate = state.dedup.get(&content_hash(data)).copied();
+            if let Some(candidate) = candidate
+                && candidate != host
+                && state.refcounts[candidate as usize] < u16::MAX
+                && self.same_content(candidate, data).await?
+            {
+                self.write_entry(entry_offset, candidate << self.cluster_bits)
+                    .await?;
+                self.share_cluster(state, candidate, entry_offset).await?;
+                if host != 0 {
+                    self.release_cluster(state, host, entry_offset).await?;
+                }
+                return Ok(());
+            }
+        }
+
+        let mut content = None;
+        if !full {
+            let mut buf = alloc::vec![0u8; cluster_size];
+            if !reads_zero {
+                self.read_at(host << self.cluster_bits, &mut buf).await?;
+            }
+            buf[offset..offset + data.len()].copy_from_slice(data);
+            content = Some(buf);
+        }
+
+        if host != 0 && state.refcounts[host as usize] == 1 {
+            // 独占的簇，原地写入
+            forget_content(state, host);
+            let host_offset = host << self.cluster_bits;
+            if entry & ENTRY_ZERO != 0 {
+                self.write_at(host_offset, content.as_deref().unwrap_or(data))
+                    .await?;
+                self.write_entry(entry_offset, host_offset | ENTRY_COPIED)
+                    .await?;
+            } else {
+                self.write_at(host_offset + offset as u64, data).await?;
+            }
+            if full {
+                Self::index_content(state, host, data, entry_offset);
+            } else {
+                state.referrers.remove(&host);
+            }
+            return Ok(());
+        }
+
+        // 未分配或共享的簇，写入新分配的簇
+        let content = content.as_deref().unwrap_or(data);
+        let cluster = self.alloc_cluster(state).await?;
+        self.write_at(cluster << self.cluster_bits, content).await?;
+        self.write_entry(entry_offset, (cluster << self.cluster_bits) | ENTRY_COPIED)
+            .await?;
+        Self::index_content(state, cluster, content, entry_offset);
+        if host != 0 {
+            self.release_cluster(state, host, entry_offset).await?;
+        }
+        Ok(())
+    }
+
+    fn check_range(
+        &self,
+        block_index: u64,
+        count: u64,
+        buf_len: usize,
+    ) -> Result<usize, BlockDeviceError> {
+        let length = count
+            .checked_mul(BLOCK_SIZE)
+            .ok_or(BlockDeviceError::OutOfBounds)?;
+        if count == 0
+            || block_index
+                .checked_add(count)
+                .is_none_or(|end| end > self.block_count)
+            || (buf_len as u64) < length
+        {
+            return Err(BlockDeviceError::OutOfBounds);
+        }
+        Ok(length as usize)
+    }
+}
+
+impl BlockDevice for Qcow2Device {
+    fn block_size(&self) -> u64 {
+        BLOCK_SIZE
+    }
+
+    fn block_count(&self) -> u64 {
+        self.block_count
+    }
+
+    fn write_block<'fut>(
+        &'fut self,
+        block_index: u64,
+        buf: &'fut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        self.write_blocks(block_index, 1, buf)
+    }
+
+    fn read_block<'fut>(
+        &'fut self,
+        block_index: u64,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        self.read_blocks(block_index, 1, buf)
+    }
+
+    fn write_blocks<'fut>(
+        &'fut self,
+        block_index: u64,
+        count: u64,
+        buf: &'fut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            let length = self.check_range(block_index, count, buf.len())?;
+            let cluster_size = self.cluster_size();
+            let mut state = self.state.lock().await;
+            let mut position = block_index * BLOCK_SIZE;
+            for data in split_clusters(position, &buf[..length], cluster_size) {
+                let offset = (position % cluster_size) as usize;
+                self.write_cluster(&mut state, position >> self.cluster_bits, offset, data)
+                    .await?;
+                position += data.len() as u64;
+            }
+            Ok(())
+        })
+    }
+
+    fn read_blocks<'fut>(
+        &'fut self,
+        block_index: u64,
+        count: u64,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            let length = self.check_range(block_index, count, buf.len())?;
+            let cluster_size = self.cluster_size();
+            let mut state = self.state.lock().await;
+            let mut position = block_index * BLOCK_SIZE;
+            let mut rest = &mut buf[..length];
+            while !rest.is_empty() {
+                let offset = (position % cluster_size) as usize;
+                let len = rest.len().min(cluster_size as usize - offset);
+                let (data, remaining) = rest.split_at_mut(len);
+                self.read_cluster(&mut state, position >> self.cluster_bits, offset, data)
+                    .await?;
+                position += len as u64;
+                rest = remaining;
+            }
+            Ok(())
+        })
+    }
+
+    fn write_zeros(
+        &self,
+        block_index: u64,
+        count: u64,
+    ) -> BoxFuture<'_, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.check_range(block_index, count, usize::MAX)?;
+            let cluster_size = self.cluster_size();
+            let zeros = alloc::vec![0u8; cluster_size.min(count * BLOCK_SIZE) as usize];
+            let mut state = self.state.lock().await;
+            let mut position = block_index * BLOCK_SIZE;
+            let end = (block_index + count) * BLOCK_SIZE;
+            while position < end {
+                let offset = position % cluster_size;
+                let len = (cluster_size - offset).min(end - position);
+                self.write_cluster(
+                    &mut state,
+                    position >> self.cluster_bits,
+                    offset as usize,
+                    &zeros[..len as usize],
+                )
+                .await?;
+                position += len;
+            }
+            Ok(())
+        })
+    }
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), BlockDeviceError>> {
+        self.inner.flush()
+    }
+}
+
+/// 检查底层设备的块大小，块大小必须为2的整数次幂且不大于簇大小，使簇总是对齐到块
+fn check_device(device: &dyn BlockDevice, cluster_bits: u32) -> Result<u64, Qcow2Error> {
+    let block_size = device.block_size();
+    if !block_size.is_power_of_two() || block_size > 1 << cluster_bits {
+        return Err(Qcow2Error::BlockSizeNotExpected);
+    }
+    Ok(block_size)
+}
+
+/// 读取设备中任意位置的数据
+async fn read_device_at(
+    device: &dyn BlockDevice,
+    offset: u64,
+    buf: &mut [u8],
+) -> Result<(), BlockDeviceError> {
+    let block_size = device.block_size();
+    let len = buf.len() as u64;
+    if offset.is_multiple_of(block_size) && len.is_multiple_of(block_size) {
+        return device
+            .read_blocks(offset / block_size, len / block_size, buf)
+            .await;
+    }
+    let first = offset / block_size;
+    let count = (offset + len).div_ceil(block_size) - first;
+    let mut blocks = alloc::vec![0u8; (count * block_size) as usize];
+    device.read_blocks(first, count, &mut blocks).await?;
+    let start = (offset - first * block_size) as usize;
+    buf.copy_from_slice(&blocks[start..start + buf.len()]);
+    Ok(())
+}
+
+/// 将从 `position` 开始的数据按簇边界切分
+fn split_clusters(position: u64, buf: &[u8], cluster_size: u64) -> impl Iterator<Item = &[u8]> {
+    let first = (cluster_size - position % cluster_size).min(buf.len() as u64) as usize;
+    let (head, tail) = buf.split_at(first);
+    core::iter::once(head)
+        .filter(|head| !head.is_empty())
+        .chain(tail.chunks(cluster_size as usize))
+}
+
+fn parse_entries(buf: &[u8]) -> Vec<u64> {
+    buf.chunks_exact(8)
+        .map(|entry| u64::from_be_bytes(entry.try_into().unwrap()))
+        .collect()
+}
+
+/// 从去重索引中移除宿主簇，在簇的内容被修改或簇被释放时调用
+fn forget_content(state: &mut Qcow2State, cluster: u64) {
+    if let Some(hash) = state.hashes.remove(&cluster)
+        && state.dedup.get(&hash) == Some(&cluster)
+    {
+        state.dedup.remove(&hash);
+    }
+}
+
+/// 簇内容的散列值（FNV-1a），仅用于查找候选簇，去重前仍会比较完整内容
+fn content_hash(content: &[u8]) -> u64 {
+    content.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use alloc::{vec, vec::Vec};
+
+    use crate::{
+        device::{
+            BlockDevice, BlockDeviceError,
+            memory::MemoryDevice,
+            qcow2::{ENTRY_COPIED, ENTRY_OFFSET_MASK, QCOW2_MAGIC, Qcow2Device, Qcow2Error},
+        },
+        fs::{FileSystem, fat32::Fat32FileSystem},
+        path::PathBuf,
+        run_task,
+    };
+
+    /// 4KiB簇
+    const CLUSTER_BITS: u32 = 12;
+    const CLUSTER: usize = 1 << CLUSTER_BITS;
+
+    #[test]
+    fn test_read_write() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(1024 * 1024, 512));
+            assert!(matches!(
+                Qcow2Device::open(device.clone()).await,
+                Err(Qcow2Error::InvalidHeader)
+            ));
+
+            // 虚拟磁盘远大于底层设备
+            let qcow2 = Qcow2Device::create(device.clone(), 64 * 1024 * 1024, CLUSTER_BITS)
+                .await
+                .unwrap();
+            assert_eq!(qcow2.block_count(), 64 * 1024 * 2);
+            let metadata = qcow2.allocated_size().await;
+            let mut header = [0; 512];
+            device.read_block(0, &mut header).await.unwrap();
+            assert_eq!(header[..4], QCOW2_MAGIC);
+
+            let mut buf = vec![0xff; 512 * 3];
+            qcow2.read_blocks(100_000, 3, &mut buf).await.unwrap();
+            assert_eq!(buf, [0; 512 * 3]);
+
+            // 跨越簇边界的写入，以及远处的写入
+            qcow2.write_blocks(7, 3, &[0x5a; 512 * 3]).await.unwrap();
+            qcow2.write_block(131_071, &[0xa5; 512]).await.unwrap();
+            // 3个数据簇与2个L2表
+            assert_eq!(qcow2.allocated_size().await, metadata + 5 * CLUSTER as u64);
+
+            let qcow2 = Qcow2Device::open(device.clone()).await.unwrap();
+            assert_eq!(qcow2.block_count(), 64 * 1024 * 2);
+            let mut buf = vec![0; 512 * 5];
+            qcow2.read_blocks(6, 5, &mut buf).await.unwrap();
+            assert_eq!(buf[..512], [0; 512]);
+            assert_eq!(buf[512..512 * 4], [0x5a; 512 * 3]);
+            assert_eq!(buf[512 * 4..], [0; 512]);
+            qcow2.read_block(131_071, &mut buf[..512]).await.unwrap();
+            assert_eq!(buf[..512], [0xa5; 512]);
+
+            assert!(matches!(
+                qcow2.read_block(131_072, &mut buf[..512]).await,
+                Err(BlockDeviceError::OutOfBounds)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_dedup_and_copy_on_write() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(1024 * 1024, 512));
+            let qcow2 = Qcow2Device::create(device.clone(), 1024 * 1024, CLUSTER_BITS)
+                .await
+                .unwrap();
+            let blocks = (CLUSTER / 512) as u64;
+            qcow2
+                .write_blocks(0, blocks, &[0x11; CLUSTER])
+                .await
+                .unwrap();
+            let before = qcow2.allocated_size().await;
+            qcow2
+                .write_blocks(blocks, blocks, &[0x11; CLUSTER])
+                .await
+                .unwrap();
+            qcow2
+                .write_blocks(blocks * 2, blocks, &[0x11; CLUSTER])
+                .await
+                .unwrap();
+            // 内容相同的簇共享同一个宿主簇，且不带COPIED标志
+            assert_eq!(qcow2.allocated_size().await, before);
+            let entries = l2_entries(&qcow2, 3).await;
+            assert_eq!(entries[0], entries[1]);
+            assert_eq!(entries[0], entries[2]);
+            assert_eq!(entries[0] & ENTRY_COPIED, 0);
+
+            // 写入共享的簇时复制，其他簇不受影响
+            qcow2.write_block(blocks + 1, &[0x22; 512]).await.unwrap();
+            qcow2
+                .write_blocks(blocks * 2, blocks, &[0x33; CLUSTER])
+                .await
+                .unwrap();
+            assert_eq!(qcow2.allocated_size().await, before + 2 * CLUSTER as u64);
+            let entries = l2_entries(&qcow2, 3).await;
+            assert!(entries.iter().all(|entry| entry & ENTRY_COPIED != 0));
+
+            let qcow2 = Qcow2Device::open(device.clone()).await.unwrap();
+            let mut buf = vec![0; CLUSTER * 3];
+            qcow2.read_blocks(0, blocks * 3, &mut buf).await.unwrap();
+            assert_eq!(buf[..CLUSTER], [0x11; CLUSTER]);
+            assert_eq!(buf[CLUSTER..CLUSTER + 512], [0x11; 512]);
+            assert_eq!(buf[CLUSTER + 512..CLUSTER + 1024], [0x22; 512]);
+            assert_eq!(buf[CLUSTER + 1024..CLUSTER * 2], [0x11; CLUSTER - 1024]);
+            assert_eq!(buf[CLUSTER * 2..], [0x33; CLUSTER]);
+        });
+    }
+
+    #[test]
+    fn test_shared_after_open() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(1024 * 1024, 512));
+            let qcow2 = Qcow2Device::create(device.clone(), 1024 * 1024, CLUSTER_BITS)
+                .await
+                .unwrap();
+            let blocks = (CLUSTER / 512) as u64;
+            qcow2
+                .write_blocks(0, blocks, &[0x44; CLUSTER])
+                .await
+                .unwrap();
+            qcow2
+                .write_blocks(blocks, blocks, &[0x44; CLUSTER])
+                .await
+                .unwrap();
+
+            // 重新打开后共享关系仍然有效，写时复制后剩下的引用者恢复COPIED标志
+            let qcow2 = Qcow2Device::open(device.clone()).await.unwrap();
+            qcow2.write_block(0, &[0x55; 512]).await.unwrap();
+            let entries = l2_entries(&qcow2, 2).await;
+            assert_ne!(entries[0], entries[1]);
+            assert!(entries.iter().all(|entry| entry & ENTRY_COPIED != 0));
+            let mut buf = vec![0; CLUSTER];
+            qcow2.read_blocks(blocks, blocks, &mut buf).await.unwrap();
+            assert_eq!(buf, [0x44; CLUSTER]);
+        });
+    }
+
+    #[test]
+    fn test_zeros_release_clusters() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(1024 * 1024, 512));
+            let qcow2 = Qcow2Device::create(device.clone(), 1024 * 1024, CLUSTER_BITS)
+                .await
+                .unwrap();
+            let blocks = (CLUSTER / 512) as u64;
+            qcow2
+                .write_blocks(0, blocks, &[0x66; CLUSTER])
+                .await
+                .unwrap();
+            qcow2
+                .write_blocks(blocks, blocks, &[0x67; CLUSTER])
+                .await
+                .unwrap();
+            let before = qcow2.allocated_size().await;
+
+            // 写零不分配簇
+            qcow2.write_zeros(blocks * 4, blocks).await.unwrap();
+            qcow2.write_block(blocks * 5, &[0; 512]).await.unwrap();
+            assert_eq!(qcow2.allocated_size().await, before);
+
+            // 整簇写零释放宿主簇，部分写零保留
+            qcow2.write_zeros(1, blocks * 2 - 1).await.unwrap();
+            assert_eq!(qcow2.allocated_size().await, before - CLUSTER as u64);
+            let mut buf = vec![0xff; CLUSTER * 2];
+            qcow2.read_blocks(0, blocks * 2, &mut buf).await.unwrap();
+            assert_eq!(buf[..512], [0x66; 512]);
+            assert_eq!(buf[512..], [0; CLUSTER * 2 - 512]);
+
+            // 释放的簇被重新使用
+            qcow2
+                .write_blocks(blocks * 6, blocks, &[0x77; CLUSTER])
+                .await
+                .unwrap();
+            assert_eq!(qcow2.allocated_size().await, before);
+        });
+    }
+
+    #[test]
+    fn test_fat32_on_qcow2() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(2 * 1024 * 1024, 512));
+            let qcow2 = Qcow2Device::create(device.clone(), 64 * 1024 * 1024, CLUSTER_BITS)
+                .await
+                .unwrap();
+            let fs = Fat32FileSystem::with_format(Arc::new(qcow2)).await.unwrap();
+            let path = PathBuf::from_str("data.txt").unwrap();
+            fs.create_file(path.as_path()).await.unwrap();
+            let mut handle = fs.open_file(path.as_path()).await.unwrap();
+            handle.write(b"sparse").await.unwrap();
+            handle.close().await.unwrap();
+            fs.unmount().await.unwrap();
+
+            let qcow2 = Qcow2Device::open(device.clone()).await.unwrap();
+            let fs = Fat32FileSystem::mount(Arc::new(qcow2)).await.unwrap();
+            let mut handle = fs.open_file(path.as_path()).await.unwrap();
+            let mut buf = [0; 6];
+            assert_eq!(handle.read(&mut buf).await.unwrap(), 6);
+            assert_eq!(&buf, b"sparse");
+            handle.close().await.unwrap();
+        });
+    }
+
+    /// 读取前 `count` 个虚拟簇的L2表项
+    async fn l2_entries(qcow2: &Qcow2Device, count: u64) -> Vec<u64> {
+        let l1_entry = qcow2.state.lock().await.l1_table[0];
+        let mut entries = Vec::new();
+        for index in 0..count {
+            let entry = qcow2
+                .read_entry((l1_entry & ENTRY_OFFSET_MASK) + index * 8)
+                .await
+                .unwrap();
+            entries.push(entry);
+        }
+        entries
+    }
+}
@@ -0,0 +1,763 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use async_locks::{mutex::Mutex, rwlock::RwLock};
+
+use crate::{
+    BoxFuture,
+    device::{BlockDevice, BlockDeviceError},
+};
+
+/// 组建RAID设备时的错误
+#[derive(Debug)]
+pub enum RaidError {
+    /// 没有成员设备
+    NoMember,
+    /// 成员设备的块大小不一致
+    BlockSizeMismatch,
+    /// 成员设备容量不足
+    DeviceTooSmall,
+    /// 条带大小无效
+    InvalidStripeSize,
+    /// 成员索引无效，或成员当前状态不允许此操作
+    InvalidMember,
+    /// 底层IO错误
+    IoError(BlockDeviceError),
+}
+
+impl From<BlockDeviceError> for RaidError {
+    fn from(value: BlockDeviceError) -> Self {
+        Self::IoError(value)
+    }
+}
+
+/// 镜像成员的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberState {
+    /// 正常工作
+    Active,
+    /// 发生过IO错误，不再参与读写
+    Failed,
+    /// 正在重建，`synced`之前的块已与其他成员一致
+    Rebuilding { synced: u64 },
+}
+
+/// 镜像设备（RAID1）
+///
+/// 所有成员保存相同的数据：写入会写到每个成员，读取轮流从任意一个正常成员读取。
+///
+/// 成员发生IO错误时会被标记为 [`MemberState::Failed`]，此后不再参与读写，设备进入降级状态。
+/// 只要还有一个正常成员，读写就能继续进行；所有成员都失败后，读写返回错误。
+///
+/// 调用 [`MirrorDevice::rebuild`] 从正常成员复制数据，恢复失败的成员（或替换为新设备）。
+/// 重建期间设备可以正常读写，已同步区域的写入会同时写到重建中的成员。
+pub struct MirrorDevice {
+    members: Mutex<Vec<MirrorMember>>,
+    // 块大小
+    block_size: u64,
+    // 块数量，为所有成员中最小的块数量
+    block_count: u64,
+    // 下一次读取优先使用的成员
+    next_read: AtomicUsize,
+    // 写入持有读锁，重建复制数据时持有写锁，避免复制过程中写入的数据被旧数据覆盖
+    sync: RwLock<()>,
+}
+
+struct MirrorMember {
+    device: Arc<dyn BlockDevice>,
+    state: MemberState,
+}
+
+impl MirrorDevice {
+    /// 重建时每次复制的块数量
+    const REBUILD_CHUNK: u64 = 64;
+
+    /// 使用一组成员设备创建镜像设备
+    ///
+    /// 成员设备的块大小必须相同，设备容量取最小的成员。创建时不会同步成员间的数据。
+    pub fn new(members: Vec<Arc<dyn BlockDevice>>) -> Result<Self, RaidError> {
+        let (block_size, block_count) = check_members(&members)?;
+        Ok(Self {
+            members: Mutex::new(
+                members
+                    .into_iter()
+                    .map(|device| MirrorMember {
+                        device,
+                        state: MemberState::Active,
+                    })
+                    .collect(),
+            ),
+            block_size,
+            block_count,
+            next_read: AtomicUsize::new(0),
+            sync: RwLock::new(()),
+        })
+    }
+
+    /// 获取各成员的状态
+    pub async fn member_states(&self) -> Vec<MemberState> {
+        let members = self.members.lock().await;
+        members.iter().map(|member| member.state).collect()
+    }
+
+    /// 是否处于降级状态，即存在不正常工作的成员
+    pub async fn is_degraded(&self) -> bool {
+        let members = self.members.lock().await;
+        members
+            .iter()
+            .any(|member| member.state != MemberState::Active)
+    }
+
+    /// 重建成员
+    ///
+    /// 从正常成员复制全部数据到指定成员，完成后该成员恢复为 [`MemberState::Active`]。
+    /// `device`不为`None`时，先以其替换该成员，用于更换故障设备。
+    ///
+    /// 只能重建非正常状态的成员。重建失败时，该成员被标记为 [`MemberState::Failed`]。
+    pub async fn rebuild(
+        &self,
+        index: usize,
+        device: Option<Arc<dyn BlockDevice>>,
+    ) -> Result<(), RaidError> {
+        let target = {
+            let mut members = self.members.lock().await;
+            let member = members.get_mut(index).ok_or(RaidError::InvalidMember)?;
+            if member.state == MemberState::Active {
+                return Err(RaidError::InvalidMember);
+            }
+            if let Some(device) = device {
+                if device.block_size() != self.block_size {
+                    return Err(RaidError::BlockSizeMismatch);
+                }
+                if device.block_count() < self.block_count {
+                    return Err(RaidError::DeviceTooSmall);
+                }
+                member.device = device;
+            }
+            member.state = MemberState::Rebuilding { synced: 0 };
+            member.device.clone()
+        };
+
+        let result = self.copy_to(index, &target).await;
+        let mut members = self.members.lock().await;
+        if Arc::ptr_eq(&members[index].device, &target) {
+            members[index].state = match result {
+                Ok(()) => MemberState::Active,
+                Err(_) => MemberState::Failed,
+            };
+        }
+        result
+    }
+
+    /// 将正常成员的数据复制到重建中的成员
+    async fn copy_to(&self, index: usize, target: &Arc<dyn BlockDevice>) -> Result<(), RaidError> {
+        let mut buffer = alloc::vec![0u8; (Self::REBUILD_CHUNK * self.block_size) as usize];
+        let mut synced = 0;
+        while synced < self.block_count {
+            let count = Self::REBUILD_CHUNK.min(self.block_count - synced);
+            let buffer = &mut buffer[..(count * self.block_size) as usize];
+
+            let _sync = self.sync.write().await;
+            self.read(synced, count, buffer).await?;
+            target.write_blocks(synced, count, buffer).await?;
+            synced += count;
+
+            let mut members = self.members.lock().await;
+            if !Arc::ptr_eq(&members[index].device, target) {
+                // 重建期间成员被替换
+                return Err(RaidError::InvalidMember);
+            }
+            members[index].state = MemberState::Rebuilding { synced };
+        }
+        Ok(())
+    }
+
+    /// 获取当前成员的快照，避免在IO期间持有锁
+    async fn snapshot(&self) -> Vec<(usize, Arc<dyn BlockDevice>, MemberState)> {
+        let members = self.members.lock().await;
+        members
+            .iter()
+            .enumerate()
+            .map(|(index, member)| (index, member.device.clone(), member.state))
+            .collect()
+    }
+
+    /// 将发生IO错误的成员标记为失败
+    async fn mark_failed(&self, index: usize, device: &Arc<dyn BlockDevice>) {
+        let mut members = self.members.lock().await;
+        // 成员可能已被替换
+        if Arc::ptr_eq(&members[index].device, device) {
+            members[index].state = MemberState::Failed;
+        }
+    }
+
+    /// 从任意一个正常成员读取，失败时依次尝试其他成员
+    async fn read(
+        &self,
+        block_index: u64,
+        count: u64,
+        buf: &mut [u8],
+    ) -> Result<(), BlockDeviceError> {
+        let active = self
+            .snapshot()
+            .await
+            .into_iter()
+            .filter(|(_, _, state)| *state == MemberState::Active)
+            .collect::<Vec<_>>();
+        if active.is_empty() {
+            return Err(BlockDeviceError::IoError);
+        }
+
+        let start = self.next_read.fetch_add(1, Ordering::Relaxed);
+        let mut last_error = BlockDeviceError::IoError;
+        for i in 0..active.len() {
+            let (index, device, _) = &active[(start + i) % active.len()];
+            match device.read_blocks(block_index, count, buf).await {
+                Ok(()) => return Ok(()),
+                Err(error) => {
+                    self.mark_failed(*index, device).await;
+                    last_error = error;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    /// 写入每一个正常成员，至少一个成员写入成功即视为成功
+    async fn write(
+        &self,
+        block_index: u64,
+        count: u64,
+        buf: Option<&[u8]>,
+    ) -> Result<(), BlockDeviceError> {
+        let _sync = self.sync.read().await;
+
+        let mut written = false;
+        let mut last_error = BlockDeviceError::IoError;
+        for (index, device, state) in self.snapshot().await {
+            match state {
+                MemberState::Active => (),
+                // 已同步的区域需要同时写入，未同步的区域之后会被复制
+                MemberState::Rebuilding { synced } if block_index < synced => (),
+                _ => continue,
+            }
+
+            let result = match buf {
+                Some(buf) => device.write_blocks(block_index, count, buf).await,
+                None => device.write_zeros(block_index, count).await,
+            };
+            match result {
+                Ok(()) => written |= state == MemberState::Active,
+                Err(error) => {
+                    self.mark_failed(index, &device).await;
+                    last_error = error;
+                }
+            }
+        }
+
+        if written { Ok(()) } else { Err(last_error) }
+    }
+
+    fn check_range(&self, block_index: u64, count: u64) -> Result<(), BlockDeviceError> {
+        if block_index
+            .checked_add(count)
+            .is_none_or(|end| end > self.block_count)
+        {
+            return Err(BlockDeviceError::OutOfBounds);
+        }
+        Ok(())
+    }
+}
+
+impl BlockDevice for MirrorDevice {
+    fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    fn block_count(&self) -> u64 {
+        self.block_count
+    }
+
+    fn write_block<'fut>(
+        &'fut self,
+        block_index: u64,
+        buf: &'fut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        self.write_blocks(block_index, 1, buf)
+    }
+
+    fn read_block<'fut>(
+        &'fut self,
+        block_index: u64,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        self.read_blocks(block_index, 1, buf)
+    }
+
+    fn write_blocks<'fut>(
+        &'fut self,
+        block_index: u64,
+        count: u64,
+        buf: &'fut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.check_range(block_index, count)?;
+            self.write(block_index, count, Some(buf)).await
+        })
+    }
+
+    fn read_blocks<'fut>(
+        &'fut self,
+        block_index: u64,
+        count: u64,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.check_range(block_index, count)?;
+            self.read(block_index, count, buf).await
+        })
+    }
+
+    fn write_zeros(
+        &self,
+        block_index: u64,
+        count: u64,
+    ) -> BoxFuture<'_, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.check_range(block_index, count)?;
+            self.write(block_index, count, None).await
+        })
+    }
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            let mut flushed = false;
+            let mut last_error = BlockDeviceError::IoError;
+            for (index, device, state) in self.snapshot().await {
+                if state == MemberState::Failed {
+                    continue;
+                }
+                match device.flush().await {
+                    Ok(()) => flushed |= state == MemberState::Active,
+                    Err(error) => {
+                        self.mark_failed(index, &device).await;
+                        last_error = error;
+                    }
+                }
+            }
+            if flushed { Ok(()) } else { Err(last_error) }
+        })
+    }
+}
+
+/// 条带设备（RAID0）
+///
+/// 将数据按条带单元依次分布到各个成员上：第`i`个条带单元位于第`i % n`个成员。
+/// 连续的大块读写会被拆分到多个成员，容量为各成员之和，但没有冗余，任一成员故障都会导致数据损坏。
+pub struct StripeDevice {
+    members: Vec<Arc<dyn BlockDevice>>,
+    // 块大小
+    block_size: u64,
+    // 块数量
+    block_count: u64,
+    // 每个条带单元的块数量
+    stripe_blocks: u64,
+}
+
+impl StripeDevice {
+    /// 使用一组成员设备创建条带设备
+    ///
+    /// `stripe_blocks`为每个条带单元的块数量。成员的块大小必须相同，
+    /// 每个成员只使用最小成员容量中能被条带单元整除的部分。
+    pub fn new(members: Vec<Arc<dyn BlockDevice>>, stripe_blocks: u64) -> Result<Self, RaidError> {
+        if stripe_blocks == 0 {
+            return Err(RaidError::InvalidStripeSize);
+        }
+        let (block_size, member_block_count) = check_members(&members)?;
+        let member_block_count = member_block_count / stripe_blocks * stripe_blocks;
+        if member_block_count == 0 {
+            return Err(RaidError::DeviceTooSmall);
+        }
+        Ok(Self {
+            block_size,
+            block_count: member_block_count * members.len() as u64,
+            members,
+            stripe_blocks,
+        })
+    }
+
+    /// 将连续的块范围拆分为位于各成员上的片段
+    ///
+    /// 返回 (成员索引, 成员中的块索引, 片段在范围内的块偏移, 片段块数量)
+    fn segments(
+        &self,
+        block_index: u64,
+        count: u64,
+    ) -> impl Iterator<Item = (usize, u64, u64, u64)> + use<> {
+        let stripe_blocks = self.stripe_blocks;
+        let member_count = self.members.len() as u64;
+        let mut offset = 0;
+        core::iter::from_fn(move || {
+            if offset >= count {
+                return None;
+            }
+            let block = block_index + offset;
+            let stripe = block / stripe_blocks;
+            let in_stripe = block % stripe_blocks;
+            let length = (stripe_blocks - in_stripe).min(count - offset);
+            let segment = (
+                (stripe % member_count) as usize,
+                stripe / member_count * stripe_blocks + in_stripe,
+                offset,
+                length,
+            );
+            offset += length;
+            Some(segment)
+        })
+    }
+
+    fn check_range(&self, block_index: u64, count: u64) -> Result<(), BlockDeviceError> {
+        if block_index
+            .checked_add(count)
+            .is_none_or(|end| end > self.block_count)
+        {
+            return Err(BlockDeviceError::OutOfBounds);
+        }
+        Ok(())
+    }
+}
+
+impl BlockDevice for StripeDevice {
+    fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    fn block_count(&self) -> u64 {
+        self.block_count
+    }
+
+    fn write_block<'fut>(
+        &'fut self,
+        block_index: u64,
+        buf: &'fut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        self.write_blocks(block_index, 1, buf)
+    }
+
+    fn read_block<'fut>(
+        &'fut self,
+        block_index: u64,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        self.read_blocks(block_index, 1, buf)
+    }
+
+    fn write_blocks<'fut>(
+        &'fut self,
+        block_index: u64,
+        count: u64,
+        buf: &'fut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.check_range(block_index, count)?;
+            let block_size = self.block_size as usize;
+            for (member, member_block, offset, length) in self.segments(block_index, count) {
+                let start = offset as usize * block_size;
+                let end = start + length as usize * block_size;
+                self.members[member]
+                    .write_blocks(member_block, length, &buf[start..end])
+                    .await?;
+            }
+            Ok(())
+        })
+    }
+
+    fn read_blocks<'fut>(
+        &'fut self,
+        block_index: u64,
+        count: u64,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.check_range(block_index, count)?;
+            let block_size = self.block_size as usize;
+            for (member, member_block, offset, length) in self.segments(block_index, count) {
+                let start = offset as usize * block_size;
+                let end = start + length as usize * block_size;
+                self.members[member]
+                    .read_blocks(member_block, length, &mut buf[start..end])
+                    .await?;
+            }
+            Ok(())
+        })
+    }
+
+    fn write_zeros(
+        &self,
+        block_index: u64,
+        count: u64,
+    ) -> BoxFuture<'_, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.check_range(block_index, count)?;
+            for (member, member_block, _, length) in self.segments(block_index, count) {
+                self.members[member]
+                    .write_zeros(member_block, length)
+                    .await?;
+            }
+            Ok(())
+        })
+    }
+
+    fn clear_blocks(
+        &self,
+        block_index: u64,
+        count: u64,
+    ) -> BoxFuture<'_, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            self.check_range(block_index, count)?;
+            for (member, member_block, _, length) in self.segments(block_index, count) {
+                self.members[member]
+                    .clear_blocks(member_block, length)
+                    .await?;
+            }
+            Ok(())
+        })
+    }
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), BlockDeviceError>> {
+        Box::pin(async move {
+            for member in &self.members {
+                member.flush().await?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// 检查成员设备，返回块大小和最小的块数量
+fn check_members(members: &[Arc<dyn BlockDevice>]) -> Result<(u64, u64), RaidError> {
+    let first = members.first().ok_or(RaidError::NoMember)?;
+    let block_size = first.block_size();
+    if members
+        .iter()
+        .any(|member| member.block_size() != block_size)
+    {
+        return Err(RaidError::BlockSizeMismatch);
+    }
+    let block_count = members
+        .iter()
+        .map(|member| member.block_count())
+        .min()
+        .unwrap_or(0);
+    if block_count == 0 {
+        return Err(RaidError::DeviceTooSmall);
+    }
+    Ok((block_size, block_count))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use alloc::{vec, vec::Vec};
+
+    use crate::{
+        device::{
+            BlockDevice,
+            memory::MemoryDevice,
+            raid::{MemberState, MirrorDevice, RaidError, StripeDevice},
+        },
+        fs::{conformance::run_conformance, fat32::Fat32FileSystem},
+        run_task,
+    };
+
+    fn memory_devices(count: usize, blocks: u64) -> Vec<Arc<MemoryDevice>> {
+        (0..count)
+            .map(|_| Arc::new(MemoryDevice::new(512 * blocks, 512)))
+            .collect()
+    }
+
+    fn as_members(devices: &[Arc<MemoryDevice>]) -> Vec<Arc<dyn BlockDevice>> {
+        devices
+            .iter()
+            .map(|device| device.clone() as Arc<dyn BlockDevice>)
+            .collect()
+    }
+
+    async fn read_block(device: &dyn BlockDevice, block_index: u64) -> Vec<u8> {
+        let mut buf = vec![0; 512];
+        device.read_block(block_index, &mut buf).await.unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_mirror_write_read() {
+        run_task(async {
+            let devices = memory_devices(2, 16);
+            let mirror = MirrorDevice::new(as_members(&devices)).unwrap();
+
+            mirror.write_blocks(2, 2, &[0x5a; 1024]).await.unwrap();
+            for device in &devices {
+                assert_eq!(read_block(device.as_ref(), 3).await, [0x5a; 512]);
+            }
+            // 轮流从各成员读取
+            for _ in 0..4 {
+                assert_eq!(read_block(&mirror, 2).await, [0x5a; 512]);
+            }
+            assert!(!mirror.is_degraded().await);
+        });
+    }
+
+    #[test]
+    fn test_mirror_degraded() {
+        run_task(async {
+            let devices = memory_devices(2, 16);
+            let mirror = MirrorDevice::new(as_members(&devices)).unwrap();
+
+            // 成员0故障后，读写由成员1完成
+            devices[0].fail_block_range(0..16).await;
+            mirror.write_block(1, &[1; 512]).await.unwrap();
+            assert_eq!(read_block(&mirror, 1).await, [1; 512]);
+            assert_eq!(
+                mirror.member_states().await,
+                [MemberState::Failed, MemberState::Active]
+            );
+
+            // 失败的成员不再参与读写
+            devices[0].clear_faults().await;
+            devices[0].set_tracing(true).await;
+            mirror.write_block(2, &[2; 512]).await.unwrap();
+            assert_eq!(read_block(&mirror, 2).await, [2; 512]);
+            assert!(devices[0].take_trace().await.is_empty());
+
+            // 所有成员都失败后报错
+            devices[1].fail_block_range(0..16).await;
+            assert!(mirror.write_block(3, &[3; 512]).await.is_err());
+            assert!(mirror.read_block(3, &mut [0; 512]).await.is_err());
+        });
+    }
+
+    #[test]
+    fn test_mirror_rebuild() {
+        run_task(async {
+            let devices = memory_devices(2, 200);
+            let mirror = MirrorDevice::new(as_members(&devices)).unwrap();
+
+            // 正常成员不能重建
+            assert!(matches!(
+                mirror.rebuild(0, None).await,
+                Err(RaidError::InvalidMember)
+            ));
+
+            devices[0].fail_nth_write(1).await;
+            for i in 0..200 {
+                mirror.write_block(i, &[i as u8; 512]).await.unwrap();
+            }
+            assert!(mirror.is_degraded().await);
+
+            // 原地重建
+            mirror.rebuild(0, None).await.unwrap();
+            assert!(!mirror.is_degraded().await);
+            for i in 0..200 {
+                assert_eq!(read_block(devices[0].as_ref(), i).await, [i as u8; 512]);
+            }
+
+            // 替换为新设备后重建
+            devices[1].fail_nth_write(1).await;
+            mirror.write_block(0, &[0xff; 512]).await.unwrap();
+            let replacement = Arc::new(MemoryDevice::new(512 * 200, 512));
+            mirror.rebuild(1, Some(replacement.clone())).await.unwrap();
+            assert_eq!(
+                mirror.member_states().await,
+                [MemberState::Active, MemberState::Active]
+            );
+            assert_eq!(read_block(replacement.as_ref(), 0).await, [0xff; 512]);
+            assert_eq!(read_block(replacement.as_ref(), 199).await, [199; 512]);
+
+            // 容量不足的设备不能作为替换
+            devices[0].fail_nth_write(1).await;
+            mirror.write_block(0, &[0; 512]).await.unwrap();
+            assert!(matches!(
+                mirror
+                    .rebuild(0, Some(Arc::new(MemoryDevice::new(512 * 100, 512))))
+                    .await,
+                Err(RaidError::DeviceTooSmall)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_stripe_layout() {
+        run_task(async {
+            let devices = memory_devices(3, 17);
+            let stripe = StripeDevice::new(as_members(&devices), 4).unwrap();
+            // 每个成员只使用16块
+            assert_eq!(stripe.block_count(), 48);
+
+            let data = (0..48 * 512).map(|i| (i / 512) as u8).collect::<Vec<_>>();
+            stripe.write_blocks(0, 48, &data).await.unwrap();
+
+            // 条带单元0、3位于成员0，1、4位于成员1
+            assert_eq!(read_block(devices[0].as_ref(), 0).await, [0; 512]);
+            assert_eq!(read_block(devices[0].as_ref(), 4).await, [12; 512]);
+            assert_eq!(read_block(devices[1].as_ref(), 1).await, [5; 512]);
+            assert_eq!(read_block(devices[2].as_ref(), 7).await, [23; 512]);
+
+            // 跨条带单元读取
+            let mut buf = vec![0; 10 * 512];
+            stripe.read_blocks(3, 10, &mut buf).await.unwrap();
+            assert_eq!(buf, data[3 * 512..13 * 512]);
+
+            assert!(
+                stripe
+                    .read_blocks(40, 9, &mut vec![0; 9 * 512])
+                    .await
+                    .is_err()
+            );
+        });
+    }
+
+    #[test]
+    fn test_invalid_members() {
+        let mixed: Vec<Arc<dyn BlockDevice>> = vec![
+            Arc::new(MemoryDevice::new(512 * 16, 512)),
+            Arc::new(MemoryDevice::new(1024 * 16, 1024)),
+        ];
+        assert!(matches!(
+            MirrorDevice::new(mixed),
+            Err(RaidError::BlockSizeMismatch)
+        ));
+        assert!(matches!(
+            MirrorDevice::new(Vec::new()),
+            Err(RaidError::NoMember)
+        ));
+        assert!(matches!(
+            StripeDevice::new(as_members(&memory_devices(2, 16)), 0),
+            Err(RaidError::InvalidStripeSize)
+        ));
+        assert!(matches!(
+            StripeDevice::new(as_members(&memory_devices(2, 3)), 4),
+            Err(RaidError::DeviceTooSmall)
+        ));
+    }
+
+    #[test]
+    fn test_fat32_conformance() {
+        run_task(async {
+            run_conformance(|| async {
+                let mirror = MirrorDevice::new(as_members(&memory_devices(2, 1024))).unwrap();
+                Fat32FileSystem::with_format(Arc::new(mirror))
+                    .await
+                    .unwrap()
+            })
+            .await;
+            run_conformance(|| async {
+                let stripe = StripeDevice::new(as_members(&memory_devices(2, 512)), 8).unwrap();
+                Fat32FileSystem::with_format(Arc::new(stripe))
+                    .await
+                    .unwrap()
+            })
+            .await;
+        });
+    }
+}
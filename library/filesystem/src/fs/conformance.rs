@@ -0,0 +1,483 @@
+//! 文件系统通用行为测试套件
+//!
+//! 针对 [`FileSystem`] 与 [`FileHandle`] trait文档约定的语义编写测试用例，可用于检验任意文件系统实现。
+//!
+//! 测试套件由工厂函数参数化：每个用例开始前都会调用工厂函数创建一个新的空文件系统，用例之间互不影响。
+//! 各文件系统实现在自己的测试中调用 [`run_conformance`]。
+//!
+//! 用例只检查trait文档中明确约定的行为，实现自行定义的行为（如在文件下创建文件的错误类型）不在检查范围内。
+
+use std::{boxed::Box, string::String, vec, vec::Vec};
+
+use crate::{
+    fs::{FileHandle, FileSystem, FileSystemError},
+    path::PathBuf,
+};
+
+/// 对工厂函数创建的文件系统运行全部用例
+///
+/// 工厂函数需返回一个空的、已挂载的文件系统，可用空间至少为64KiB
+pub(crate) async fn run_conformance<F, Fut, FS>(factory: F)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = FS>,
+    FS: FileSystem,
+{
+    check_create(&factory().await).await;
+    check_open(&factory().await).await;
+    check_delete(&factory().await).await;
+    check_rename(&factory().await).await;
+    check_list_directory(&factory().await).await;
+    check_metadata(&factory().await).await;
+    check_pointer(&factory().await).await;
+    check_large_file(&factory().await).await;
+    check_truncate(&factory().await).await;
+    check_closed_handle(&factory().await).await;
+    check_unmount(&factory().await).await;
+}
+
+fn path(path: &str) -> PathBuf {
+    PathBuf::from_str(path).unwrap()
+}
+
+async fn create_file(fs: &dyn FileSystem, file: &str, content: &[u8]) {
+    fs.create_file(path(file).as_path()).await.unwrap();
+    let mut handle = fs.open_file(path(file).as_path()).await.unwrap();
+    handle.write(content).await.unwrap();
+    handle.close().await.unwrap();
+}
+
+async fn read_file(fs: &dyn FileSystem, file: &str) -> Vec<u8> {
+    let mut handle = fs.open_file(path(file).as_path()).await.unwrap();
+    let content = read_to_end(handle.as_mut()).await;
+    handle.close().await.unwrap();
+    content
+}
+
+async fn read_to_end(handle: &mut dyn FileHandle) -> Vec<u8> {
+    let mut content = Vec::new();
+    let mut buf = [0u8; 100];
+    loop {
+        let len = handle.read(&mut buf).await.unwrap() as usize;
+        if len == 0 {
+            return content;
+        }
+        content.extend_from_slice(&buf[..len]);
+    }
+}
+
+async fn list_names(fs: &dyn FileSystem, dir: &str) -> Vec<String> {
+    let mut names: Vec<String> = fs
+        .list_directory(path(dir).as_path())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|metadata| metadata.name)
+        .collect();
+    names.sort();
+    names
+}
+
+async fn check_create(fs: &dyn FileSystem) {
+    fs.create_directory(path("/dir").as_path()).await.unwrap();
+    fs.create_directory(path("/dir/sub").as_path())
+        .await
+        .unwrap();
+    fs.create_file(path("/dir/sub/file").as_path())
+        .await
+        .unwrap();
+
+    // 同名文件或目录已存在
+    assert!(matches!(
+        fs.create_file(path("/dir/sub/file").as_path()).await,
+        Err(FileSystemError::FileExists)
+    ));
+    assert!(matches!(
+        fs.create_directory(path("/dir/sub/file").as_path()).await,
+        Err(FileSystemError::FileExists)
+    ));
+    assert!(matches!(
+        fs.create_file(path("/dir/sub").as_path()).await,
+        Err(FileSystemError::FileExists)
+    ));
+
+    // 不会创建父级目录
+    assert!(matches!(
+        fs.create_file(path("/missing/file").as_path()).await,
+        Err(FileSystemError::FileNotFound)
+    ));
+    assert!(matches!(
+        fs.create_directory(path("/missing/dir").as_path()).await,
+        Err(FileSystemError::FileNotFound)
+    ));
+
+    // 新文件为空
+    assert!(read_file(fs, "/dir/sub/file").await.is_empty());
+}
+
+async fn check_open(fs: &dyn FileSystem) {
+    fs.create_directory(path("/dir").as_path()).await.unwrap();
+    fs.create_file(path("/file").as_path()).await.unwrap();
+
+    assert!(matches!(
+        fs.open_file(path("/missing").as_path()).await,
+        Err(FileSystemError::FileNotFound)
+    ));
+    assert!(matches!(
+        fs.open_file(path("/dir").as_path()).await,
+        Err(FileSystemError::FileTypeMismatch)
+    ));
+
+    // 同一文件同时只能打开一次，关闭后可以再次打开
+    let mut handle = fs.open_file(path("/file").as_path()).await.unwrap();
+    assert!(matches!(
+        fs.open_file(path("/file").as_path()).await,
+        Err(FileSystemError::FileOccupied)
+    ));
+    handle.close().await.unwrap();
+    let mut handle = fs.open_file(path("/file").as_path()).await.unwrap();
+    handle.close().await.unwrap();
+}
+
+async fn check_delete(fs: &dyn FileSystem) {
+    fs.create_directory(path("/dir").as_path()).await.unwrap();
+    create_file(fs, "/dir/file", b"content").await;
+
+    assert!(matches!(
+        fs.delete_file(path("/missing").as_path()).await,
+        Err(FileSystemError::FileNotFound)
+    ));
+    assert!(matches!(
+        fs.delete_file(path("/dir").as_path()).await,
+        Err(FileSystemError::FileTypeMismatch)
+    ));
+    assert!(matches!(
+        fs.delete_directory(path("/missing").as_path()).await,
+        Err(FileSystemError::FileNotFound)
+    ));
+    assert!(matches!(
+        fs.delete_directory(path("/dir/file").as_path()).await,
+        Err(FileSystemError::FileTypeMismatch)
+    ));
+
+    // 非空目录不能删除
+    assert!(matches!(
+        fs.delete_directory(path("/dir").as_path()).await,
+        Err(FileSystemError::FileExists)
+    ));
+
+    // 被打开的文件不能删除
+    let mut handle = fs.open_file(path("/dir/file").as_path()).await.unwrap();
+    assert!(matches!(
+        fs.delete_file(path("/dir/file").as_path()).await,
+        Err(FileSystemError::FileOccupied)
+    ));
+    handle.close().await.unwrap();
+
+    fs.delete_file(path("/dir/file").as_path()).await.unwrap();
+    assert!(matches!(
+        fs.get_metadata(path("/dir/file").as_path()).await,
+        Err(FileSystemError::FileNotFound)
+    ));
+    fs.delete_directory(path("/dir").as_path()).await.unwrap();
+    assert!(matches!(
+        fs.get_metadata(path("/dir").as_path()).await,
+        Err(FileSystemError::FileNotFound)
+    ));
+    assert!(list_names(fs, "/").await.is_empty());
+
+    // 删除后可以重新创建同名文件
+    create_file(fs, "/dir", b"new").await;
+    assert_eq!(read_file(fs, "/dir").await, b"new");
+}
+
+async fn check_rename(fs: &dyn FileSystem) {
+    fs.create_directory(path("/a").as_path()).await.unwrap();
+    fs.create_directory(path("/b").as_path()).await.unwrap();
+    create_file(fs, "/a/file", b"file content").await;
+    create_file(fs, "/a/other", b"other content").await;
+
+    assert!(matches!(
+        fs.rename(path("/missing").as_path(), path("/b/new").as_path())
+            .await,
+        Err(FileSystemError::FileNotFound)
+    ));
+    assert!(matches!(
+        fs.rename(path("/a/file").as_path(), path("/a/other").as_path())
+            .await,
+        Err(FileSystemError::FileExists)
+    ));
+    assert!(matches!(
+        fs.rename(path("/a/file").as_path(), path("/missing/new").as_path())
+            .await,
+        Err(FileSystemError::FileNotFound)
+    ));
+
+    // 同目录重命名
+    fs.rename(path("/a/file").as_path(), path("/a/renamed").as_path())
+        .await
+        .unwrap();
+    assert_eq!(list_names(fs, "/a").await, ["other", "renamed"]);
+    assert_eq!(read_file(fs, "/a/renamed").await, b"file content");
+
+    // 移动到其他目录并重命名
+    fs.rename(path("/a/renamed").as_path(), path("/b/moved").as_path())
+        .await
+        .unwrap();
+    assert_eq!(list_names(fs, "/a").await, ["other"]);
+    assert_eq!(list_names(fs, "/b").await, ["moved"]);
+    assert_eq!(read_file(fs, "/b/moved").await, b"file content");
+
+    // 移动目录时，子项随之移动
+    fs.rename(path("/a").as_path(), path("/b/a").as_path())
+        .await
+        .unwrap();
+    assert_eq!(list_names(fs, "/").await, ["b"]);
+    assert_eq!(list_names(fs, "/b").await, ["a", "moved"]);
+    assert_eq!(read_file(fs, "/b/a/other").await, b"other content");
+}
+
+async fn check_list_directory(fs: &dyn FileSystem) {
+    assert!(list_names(fs, "/").await.is_empty());
+
+    fs.create_directory(path("/dir").as_path()).await.unwrap();
+    create_file(fs, "/file", b"content").await;
+    create_file(fs, "/dir/nested", b"").await;
+
+    let mut entries = fs.list_directory(path("/").as_path()).await.unwrap();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].name, "dir");
+    assert!(entries[0].is_directory);
+    assert_eq!(entries[1].name, "file");
+    assert!(!entries[1].is_directory);
+    assert_eq!(entries[1].size, 7);
+
+    assert_eq!(list_names(fs, "/dir").await, ["nested"]);
+    assert!(matches!(
+        fs.list_directory(path("/missing").as_path()).await,
+        Err(FileSystemError::FileNotFound)
+    ));
+    assert!(matches!(
+        fs.list_directory(path("/file").as_path()).await,
+        Err(FileSystemError::FileTypeMismatch)
+    ));
+}
+
+async fn check_metadata(fs: &dyn FileSystem) {
+    fs.create_directory(path("/dir").as_path()).await.unwrap();
+    create_file(fs, "/dir/file.txt", b"hello").await;
+
+    let metadata = fs
+        .get_metadata(path("/dir/file.txt").as_path())
+        .await
+        .unwrap();
+    assert_eq!(metadata.name, "file.txt");
+    assert_eq!(metadata.size, 5);
+    assert!(!metadata.is_directory);
+    assert!(metadata.allocated_size >= metadata.size);
+
+    let metadata = fs.get_metadata(path("/dir").as_path()).await.unwrap();
+    assert_eq!(metadata.name, "dir");
+    assert!(metadata.is_directory);
+
+    assert!(matches!(
+        fs.get_metadata(path("/dir/missing").as_path()).await,
+        Err(FileSystemError::FileNotFound)
+    ));
+}
+
+async fn check_pointer(fs: &dyn FileSystem) {
+    fs.create_file(path("/file").as_path()).await.unwrap();
+    let mut handle = fs.open_file(path("/file").as_path()).await.unwrap();
+    assert_eq!(handle.get_pointer().await.unwrap(), 0);
+
+    // 写入后指针移动到写入内容之后
+    handle.write(b"hello world").await.unwrap();
+    assert_eq!(handle.get_pointer().await.unwrap(), 11);
+
+    // 位于文件末尾时读取返回0
+    let mut buf = [0u8; 16];
+    assert_eq!(handle.read(&mut buf).await.unwrap(), 0);
+
+    // 读取后指针同步移动
+    handle.move_pointer(6).await.unwrap();
+    assert_eq!(handle.get_pointer().await.unwrap(), 6);
+    assert_eq!(handle.read(&mut buf[..3]).await.unwrap(), 3);
+    assert_eq!(&buf[..3], b"wor");
+    assert_eq!(handle.get_pointer().await.unwrap(), 9);
+
+    // 指针不在末尾时，写入覆盖旧数据
+    handle.move_pointer(0).await.unwrap();
+    handle.write(b"HELLO").await.unwrap();
+    assert_eq!(handle.get_pointer().await.unwrap(), 5);
+
+    // 覆盖越过末尾时，超出部分为追加
+    handle.move_pointer(9).await.unwrap();
+    handle.write(b"LD!").await.unwrap();
+
+    handle.move_pointer(0).await.unwrap();
+    assert_eq!(read_to_end(handle.as_mut()).await, b"HELLO worLD!");
+    handle.close().await.unwrap();
+
+    let metadata = fs.get_metadata(path("/file").as_path()).await.unwrap();
+    assert_eq!(metadata.size, 12);
+}
+
+async fn check_large_file(fs: &dyn FileSystem) {
+    // 跨越多个扇区、簇的文件
+    let content: Vec<u8> = (0..20000u32).map(|i| (i * 7 % 251) as u8).collect();
+
+    fs.create_file(path("/large").as_path()).await.unwrap();
+    let mut handle = fs.open_file(path("/large").as_path()).await.unwrap();
+    for chunk in content.chunks(3001) {
+        handle.write(chunk).await.unwrap();
+    }
+    handle.close().await.unwrap();
+
+    assert_eq!(read_file(fs, "/large").await, content);
+
+    // 随机位置读取
+    let mut handle = fs.open_file(path("/large").as_path()).await.unwrap();
+    let mut buf = vec![0u8; 1000];
+    handle.move_pointer(12345).await.unwrap();
+    assert_eq!(handle.read(&mut buf).await.unwrap(), 1000);
+    assert_eq!(buf, content[12345..13345]);
+    handle.close().await.unwrap();
+
+    let metadata = fs.get_metadata(path("/large").as_path()).await.unwrap();
+    assert_eq!(metadata.size, content.len() as u64);
+}
+
+async fn check_truncate(fs: &dyn FileSystem) {
+    let content: Vec<u8> = (0..20000u32).map(|i| (i * 7 % 251) as u8).collect();
+    create_file(fs, "/file", &content).await;
+    let free_space = fs.free_space().await.unwrap();
+
+    // 丢弃文件指针之后的内容，指针不变，之后的写入为追加
+    let mut handle = fs.open_file(path("/file").as_path()).await.unwrap();
+    handle.move_pointer(100).await.unwrap();
+    handle.truncate().await.unwrap();
+    assert_eq!(handle.get_pointer().await.unwrap(), 100);
+    handle.write(b"tail").await.unwrap();
+    handle.close().await.unwrap();
+
+    let mut expected = content[..100].to_vec();
+    expected.extend_from_slice(b"tail");
+    assert_eq!(read_file(fs, "/file").await, expected);
+    let metadata = fs.get_metadata(path("/file").as_path()).await.unwrap();
+    assert_eq!(metadata.size, 104);
+    // 释放的空间可被其他文件使用
+    assert!(fs.free_space().await.unwrap() > free_space);
+
+    // 指针位于末尾时不做修改
+    let mut handle = fs.open_file(path("/file").as_path()).await.unwrap();
+    handle.move_pointer(104).await.unwrap();
+    handle.truncate().await.unwrap();
+    // 截断为空文件
+    handle.move_pointer(0).await.unwrap();
+    handle.truncate().await.unwrap();
+    handle.close().await.unwrap();
+    assert_eq!(read_file(fs, "/file").await, b"");
+}
+
+async fn check_closed_handle(fs: &dyn FileSystem) {
+    create_file(fs, "/file", b"content").await;
+    let mut handle: Box<dyn FileHandle> = fs.open_file(path("/file").as_path()).await.unwrap();
+    handle.close().await.unwrap();
+
+    let mut buf = [0u8; 8];
+    assert!(matches!(
+        handle.read(&mut buf).await,
+        Err(FileSystemError::FileClosed)
+    ));
+    assert!(matches!(
+        handle.write(b"data").await,
+        Err(FileSystemError::FileClosed)
+    ));
+    assert!(matches!(
+        handle.move_pointer(0).await,
+        Err(FileSystemError::FileClosed)
+    ));
+    assert!(matches!(
+        handle.get_pointer().await,
+        Err(FileSystemError::FileClosed)
+    ));
+    assert!(matches!(
+        handle.truncate().await,
+        Err(FileSystemError::FileClosed)
+    ));
+    assert!(matches!(
+        handle.close().await,
+        Err(FileSystemError::FileClosed)
+    ));
+
+    // 关闭的句柄不影响文件内容
+    assert_eq!(read_file(fs, "/file").await, b"content");
+}
+
+async fn check_unmount(fs: &dyn FileSystem) {
+    fs.create_directory(path("/dir").as_path()).await.unwrap();
+    create_file(fs, "/file", b"content").await;
+    let mut handle = fs.open_file(path("/file").as_path()).await.unwrap();
+
+    fs.unmount().await.unwrap();
+
+    // 卸载后所有操作均返回Unmounted
+    assert!(matches!(
+        fs.total_space().await,
+        Err(FileSystemError::Unmounted)
+    ));
+    assert!(matches!(
+        fs.free_space().await,
+        Err(FileSystemError::Unmounted)
+    ));
+    assert!(matches!(
+        fs.create_file(path("/new").as_path()).await,
+        Err(FileSystemError::Unmounted)
+    ));
+    assert!(matches!(
+        fs.create_directory(path("/new").as_path()).await,
+        Err(FileSystemError::Unmounted)
+    ));
+    assert!(matches!(
+        fs.open_file(path("/file").as_path()).await,
+        Err(FileSystemError::Unmounted)
+    ));
+    assert!(matches!(
+        fs.delete_file(path("/file").as_path()).await,
+        Err(FileSystemError::Unmounted)
+    ));
+    assert!(matches!(
+        fs.delete_directory(path("/dir").as_path()).await,
+        Err(FileSystemError::Unmounted)
+    ));
+    assert!(matches!(
+        fs.rename(path("/file").as_path(), path("/new").as_path())
+            .await,
+        Err(FileSystemError::Unmounted)
+    ));
+    assert!(matches!(
+        fs.get_metadata(path("/file").as_path()).await,
+        Err(FileSystemError::Unmounted)
+    ));
+    assert!(matches!(
+        fs.list_directory(path("/").as_path()).await,
+        Err(FileSystemError::Unmounted)
+    ));
+    assert!(matches!(
+        fs.unmount().await,
+        Err(FileSystemError::Unmounted)
+    ));
+
+    // 卸载前打开的文件同样不可读写
+    let mut buf = [0u8; 8];
+    assert!(matches!(
+        handle.read(&mut buf).await,
+        Err(FileSystemError::Unmounted)
+    ));
+    assert!(matches!(
+        handle.write(b"data").await,
+        Err(FileSystemError::Unmounted)
+    ));
+}
@@ -6,17 +6,20 @@ use core::{
 
 use alloc::{
     boxed::Box,
-    collections::BTreeSet,
+    collections::{BTreeMap, BTreeSet},
     string::{String, ToString},
     sync::{Arc, Weak},
     vec::Vec,
 };
-use async_locks::rwlock::RwLock;
+use async_locks::{mutex::Mutex, rwlock::RwLock};
 
 use crate::{
     BoxFuture,
     device::{BlockDevice, BlockDeviceError},
-    fs::{FileHandle, FileMetadata, FileSystem, FileSystemError},
+    fs::{
+        FileHandle, FileMetadata, FileSystem, FileSystemError,
+        readahead::{Readahead, ReadaheadConfig, ReadaheadStats, Spawner},
+    },
     internal::DiskStruct,
     path::Path,
 };
@@ -62,17 +65,71 @@ use crate::{
 /// 此结构提供了 [`Fat32FileSystem::mount`] 和 [`Fat32FileSystem::with_format`] 方法，
 /// 分别用于挂载已有的FAT32文件系统和格式化一个块设备为FAT32文件系统。
 ///
+/// 文件名默认不区分大小写（UTF-16长文件名按Unicode规则折叠大小写），
+/// 可通过 [`Fat32FileSystem::set_case_insensitive`] 关闭。
+///
+/// FAT表的修改缓存在内存中，卸载时才写回块设备，因此使用完毕后必须调用 [`FileSystem::unmount`]。
+///
+/// 调用 [`Fat32FileSystem::set_spawner`] 后，顺序读取文件时会在后台预读之后的簇，
+/// 预读参数与统计信息见 [`Fat32FileSystem::set_readahead_config`] 与 [`Fat32FileSystem::readahead_stats`]。
+///
+/// 调用 [`Fat32FileSystem::set_clock`] 后，创建和修改文件时会在目录项中记录时间，否则时间字段保持为0。
+///
 /// 注意：当前文件系统实现有缺陷，并非标准FAT32要求的文件系统，内部做了多个简化逻辑的处理方式。
 pub struct Fat32FileSystem {
     inner: Arc<RwLock<Fat32Inner>>,
 }
 
+/// 本地时间，用于目录项中的时间字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub millisecond: u16,
+}
+
+/// 获取当前本地时间的函数，由文件系统的使用方提供
+pub type Clock = dyn Fn() -> LocalTime + Send + Sync;
+
+/// 目录项中的时间
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FatTimestamp {
+    date: u16,      // 日期（年7位，月4位，日5位），年份从1980年起计
+    time: u16,      // 时间（小时5位，分钟6位，秒数除以2后5位）
+    hundredths: u8, // time中被舍去的秒数，单位为百分之一秒
+}
+
+impl FatTimestamp {
+    /// 转换本地时间，超出FAT可表示的范围（1980年至2107年）时返回None
+    fn from_local_time(time: LocalTime) -> Option<Self> {
+        if !(1980..=2107).contains(&time.year) {
+            return None;
+        }
+        Some(Self {
+            date: ((time.year - 1980) << 9) | ((time.month as u16) << 5) | time.day as u16,
+            time: ((time.hour as u16) << 11)
+                | ((time.minute as u16) << 5)
+                | (time.second as u16 / 2),
+            hundredths: ((time.second % 2) as u16 * 100 + time.millisecond / 10) as u8,
+        })
+    }
+}
+
 struct Fat32Inner {
     device: Arc<dyn BlockDevice>,
     bpb: Box<BPB>,
+    fat: FatTable,
+    readahead: Arc<Readahead>, // 文件数据的预读缓存，以簇为单元
     fs_info: Option<Box<FSInfo>>,
     max_cluster: u32,             // 磁盘能容纳的最大簇数，不包含前两个虚拟簇
     occupied_file: BTreeSet<u32>, // 正在占用的文件，记录的是起始簇号
+    unmounted: bool,              // 是否已卸载
+    case_insensitive: bool,       // 文件名匹配时是否忽略大小写
+    clock: Option<Arc<Clock>>,    // 目录项时间字段的来源，未设置时不记录时间
 }
 
 /// 引导记录，固定为第一个扇区
@@ -174,7 +231,6 @@ const _: () = {
 
 impl BPB {
     const RESERVED_CODE: [u8; 3] = [0xeb, 0xfe, 0x90];
-    const OEM_ID: [u8; 8] = *b"cosfs1.0";
     const FS_TYPE: [u8; 8] = *b"FAT32   ";
     const BOOT_SECTOR_SIGNATURE: u16 = 0xAA55;
 }
@@ -227,6 +283,58 @@ impl From<BlockDeviceError> for MountError {
 pub enum FormatError {
     IoError(BlockDeviceError),
     DeviceTooSmall,
+    /// 格式化选项不合法
+    InvalidOptions,
+}
+
+/// 格式化选项，用于 [`Fat32FileSystem::with_format_options`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// 每簇扇区数，必须为2的整数次幂，默认为8
+    pub sectors_per_cluster: u8,
+    /// 卷标，最长11个ASCII字符，为空时不写入卷标
+    pub volume_label: String,
+    /// OEM标识，最长8个ASCII字符
+    pub oem_id: String,
+    /// FAT表数量，1或2，默认为2
+    pub num_fats: u8,
+    /// 是否将数据区起始位置对齐到1MiB，对齐后闪存设备的簇与擦除块边界一致
+    pub align_1mib: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            sectors_per_cluster: 8,
+            volume_label: String::new(),
+            oem_id: String::from("cosfs1.0"),
+            num_fats: 2,
+            align_1mib: false,
+        }
+    }
+}
+
+impl FormatOptions {
+    fn validate(&self) -> Result<(), FormatError> {
+        let is_ascii_name = |name: &str, max_len: usize| {
+            name.len() <= max_len && name.bytes().all(|byte| (0x20..0x7f).contains(&byte))
+        };
+        if !self.sectors_per_cluster.is_power_of_two()
+            || !(1..=2).contains(&self.num_fats)
+            || !is_ascii_name(&self.volume_label, 11)
+            || !is_ascii_name(&self.oem_id, 8)
+        {
+            return Err(FormatError::InvalidOptions);
+        }
+        Ok(())
+    }
+}
+
+/// 将字符串以空格填充为定长数组
+fn pad_with_space<const N: usize>(name: &str) -> [u8; N] {
+    let mut array = [b' '; N];
+    array[..name.len()].copy_from_slice(name.as_bytes());
+    array
 }
 
 impl From<BlockDeviceError> for FormatError {
@@ -288,16 +396,35 @@ impl Fat32FileSystem {
 
         Ok(Self {
             inner: Arc::new(RwLock::new(Fat32Inner {
+                fat: FatTable::new(device.clone(), &bpb),
+                readahead: Arc::new(Readahead::new(
+                    device.clone(),
+                    bpb.sectors_per_cluster as u64,
+                )),
                 device,
                 bpb,
                 fs_info,
                 max_cluster,
                 occupied_file: BTreeSet::new(),
+                unmounted: false,
+                case_insensitive: true,
+                clock: None,
             })),
         })
     }
 
+    /// 使用默认选项格式化块设备
     pub async fn with_format(device: Arc<dyn BlockDevice>) -> Result<Self, FormatError> {
+        Self::with_format_options(device, &FormatOptions::default()).await
+    }
+
+    /// 按指定选项格式化块设备
+    pub async fn with_format_options(
+        device: Arc<dyn BlockDevice>,
+        options: &FormatOptions,
+    ) -> Result<Self, FormatError> {
+        options.validate()?;
+
         // 对磁盘容量和扇区大小进行检查
         let block_size = device.block_size();
         if block_size < 512 {
@@ -316,34 +443,48 @@ impl Fat32FileSystem {
         } else {
             1 << (63 - block_size.leading_zeros())
         };
-        // 每簇占用8个扇区
-        let sectors_per_cluster = 8u64;
+        let sectors_per_cluster = options.sectors_per_cluster as u64;
+        let num_fats = options.num_fats as u64;
         // 计算总簇数
         let (total_cluster_count, remain_block) = calc_cluster_count(
             reserved_sector_count,
             bytes_per_sector,
             sectors_per_cluster,
+            num_fats,
             block_count,
         );
-        if total_cluster_count < 2 {
-            return Err(FormatError::DeviceTooSmall);
-        }
-        // 剩余扇区加到保留扇区上
-        let reserved_sector_count = reserved_sector_count + remain_block;
         // 需要的FAT空间
         let fat_size_32 = {
             let cluster_per_fat = bytes_per_sector / size_of::<FatEntry>() as u64;
             (total_cluster_count + cluster_per_fat - 1) / cluster_per_fat
         };
+        let (reserved_sector_count, total_cluster_count) = if options.align_1mib {
+            // 增加保留扇区，使数据区从1MiB的整数倍开始，FAT空间保持不变，剩余的扇区不再使用
+            let align = (1024 * 1024 / bytes_per_sector).max(1);
+            let data_start =
+                (reserved_sector_count + fat_size_32 * num_fats).next_multiple_of(align);
+            let reserved_sector_count = data_start - fat_size_32 * num_fats;
+            let cluster_count = block_count.saturating_sub(data_start) / sectors_per_cluster;
+            (
+                reserved_sector_count,
+                total_cluster_count.min(cluster_count),
+            )
+        } else {
+            // 剩余扇区加到保留扇区上
+            (reserved_sector_count + remain_block, total_cluster_count)
+        };
+        if total_cluster_count < 2 || reserved_sector_count > u16::MAX as u64 {
+            return Err(FormatError::DeviceTooSmall);
+        }
 
         // BPB
         let bpb = Box::new(BPB {
             reserved_code: BPB::RESERVED_CODE,
-            oem_id: BPB::OEM_ID,
+            oem_id: pad_with_space(&options.oem_id),
             bytes_per_sector: bytes_per_sector as u16,
             sectors_per_cluster: sectors_per_cluster as u8,
             reserved_sector_count: reserved_sector_count as u16,
-            num_fats: 2,
+            num_fats: options.num_fats,
             root_entry_count: 0,
             total_sectors_16: 0,
             media: 0,
@@ -361,9 +502,18 @@ impl Fat32FileSystem {
             reserved: [0; 12],
             drive_number: 0x80,
             reserved1: 0,
-            boot_signature: 0x28,
+            // 0x29表示卷序列号与卷标有效
+            boot_signature: if options.volume_label.is_empty() {
+                0x28
+            } else {
+                0x29
+            },
             volume_id: 0,
-            volume_label: [0; 11],
+            volume_label: if options.volume_label.is_empty() {
+                [0; 11]
+            } else {
+                pad_with_space(&options.volume_label)
+            },
             fs_type: BPB::FS_TYPE,
             code: [0; 420],
             boot_sector_signature: BPB::BOOT_SECTOR_SIGNATURE,
@@ -404,7 +554,7 @@ impl Fat32FileSystem {
 
         // 格式化FAT区
         device
-            .write_zeros(reserved_sector_count, fat_size_32 * 2)
+            .write_zeros(reserved_sector_count, fat_size_32 * num_fats)
             .await?;
         {
             let mut buffer = alloc::vec![0u8; block_size as usize];
@@ -424,27 +574,69 @@ impl Fat32FileSystem {
                     FatEntry(FatEntry::FAT_ENTRY_EOC_START),
                 );
             }
-            device.write_block(reserved_sector_count, &buffer).await?;
-            device
-                .write_block(reserved_sector_count + fat_size_32, &buffer)
-                .await?;
+            for i in 0..num_fats {
+                device
+                    .write_block(reserved_sector_count + fat_size_32 * i, &buffer)
+                    .await?;
+            }
         }
 
         // 格式化根目录
         device
-            .write_zeros(reserved_sector_count + fat_size_32 * 2, sectors_per_cluster)
+            .write_zeros(
+                reserved_sector_count + fat_size_32 * num_fats,
+                sectors_per_cluster,
+            )
             .await?;
 
         Ok(Self {
             inner: Arc::new(RwLock::new(Fat32Inner {
+                fat: FatTable::new(device.clone(), &bpb),
+                readahead: Arc::new(Readahead::new(
+                    device.clone(),
+                    bpb.sectors_per_cluster as u64,
+                )),
                 device,
                 bpb,
                 fs_info: Some(fs_info),
                 max_cluster: total_cluster_count as u32,
                 occupied_file: BTreeSet::new(),
+                unmounted: false,
+                case_insensitive: true,
+                clock: None,
             })),
         })
     }
+
+    /// 设置文件名匹配时是否忽略大小写，默认忽略
+    ///
+    /// 忽略大小写时，仅大小写不同的文件名视为同一文件，无法同时创建
+    pub async fn set_case_insensitive(&self, case_insensitive: bool) {
+        self.inner.write().await.case_insensitive = case_insensitive;
+    }
+
+    /// 设置获取本地时间的函数，设置后创建和修改文件时才会记录时间
+    pub async fn set_clock(&self, clock: Arc<Clock>) {
+        self.inner.write().await.clock = Some(clock);
+    }
+
+    /// 设置启动后台任务的函数，设置后顺序读取文件时才会进行预读
+    pub async fn set_spawner(&self, spawner: Arc<Spawner>) {
+        let readahead = self.inner.read().await.readahead.clone();
+        readahead.set_spawner(Some(spawner)).await;
+    }
+
+    /// 设置预读参数，默认参数见 [`ReadaheadConfig::default`]
+    pub async fn set_readahead_config(&self, config: ReadaheadConfig) {
+        let readahead = self.inner.read().await.readahead.clone();
+        readahead.set_config(config).await;
+    }
+
+    /// 预读的命中与未命中统计
+    pub async fn readahead_stats(&self) -> ReadaheadStats {
+        let readahead = self.inner.read().await.readahead.clone();
+        readahead.stats().await
+    }
 }
 
 struct Fat32FileMetadata {
@@ -459,6 +651,25 @@ struct Fat32FileMetadata {
 }
 
 impl Fat32FileMetadata {
+    /// 记录创建时间，同时作为修改时间和访问日期
+    fn set_create_time(&mut self, timestamp: FatTimestamp) {
+        self.short.create_time_tenths = timestamp.hundredths;
+        self.short.create_time = timestamp.time;
+        self.short.create_date = timestamp.date;
+        self.set_write_time(timestamp);
+    }
+
+    /// 记录修改时间，同时作为访问日期。返回时间是否发生变化
+    fn set_write_time(&mut self, timestamp: FatTimestamp) -> bool {
+        let changed = self.short.write_time != timestamp.time
+            || self.short.write_date != timestamp.date
+            || self.short.last_access_date != timestamp.date;
+        self.short.write_time = timestamp.time;
+        self.short.write_date = timestamp.date;
+        self.short.last_access_date = timestamp.date;
+        changed
+    }
+
     fn new(name: &str, start_cluster: u32, attr: u8) -> Self {
         debug_assert!(!name.is_empty());
 
@@ -536,6 +747,12 @@ impl Fat32FileMetadata {
         }
     }
 
+    /// 是否为目录中的同一个条目
+    fn is_same_entry(&self, other: &Self) -> bool {
+        self.short_sector == other.short_sector
+            && self.short_sector_offset == other.short_sector_offset
+    }
+
     fn start_cluster(&self) -> u32 {
         (self.short.first_cluster_low as u32) | ((self.short.first_cluster_high as u32) << 16)
     }
@@ -568,7 +785,226 @@ impl Fat32FileMetadata {
     }
 }
 
+/// FAT表
+///
+/// 以扇区为单位在内存中缓存FAT表。读取时按需加载扇区，修改只写入缓存并标记为脏，
+/// 在淘汰或 [`FatTable::flush`] 时写回磁盘上的每一份FAT表。
+///
+/// 所有对簇链的访问（遍历、扩展、释放）都通过此结构进行。
+struct FatTable {
+    device: Arc<dyn BlockDevice>,
+    start_sector: u64,       // 第一份FAT表的起始扇区
+    fat_size: u64,           // 每份FAT表的扇区数
+    num_fats: u8,            // FAT表份数，写回时每份都要写入
+    entries_per_sector: u64, // 每扇区FAT表项数
+    cache: Mutex<FatCache>,
+}
+
+struct FatCache {
+    sectors: BTreeMap<u64, FatSector>, // 已缓存的扇区，键为扇区在FAT表内的偏移
+    clock: u64,                        // 访问计数，用于淘汰最久未使用的扇区
+}
+
+struct FatSector {
+    data: Vec<u8>,    // 扇区内容
+    dirty: bool,      // 是否有未写回的修改
+    last_access: u64, // 最近一次访问时的访问计数
+}
+
+impl FatTable {
+    /// 最多缓存的扇区数量
+    const CACHE_CAPACITY: usize = 64;
+
+    fn new(device: Arc<dyn BlockDevice>, bpb: &BPB) -> Self {
+        Self {
+            device,
+            start_sector: bpb.reserved_sector_count as u64,
+            fat_size: bpb.fat_size_32 as u64,
+            num_fats: bpb.num_fats,
+            entries_per_sector: (bpb.bytes_per_sector as usize / size_of::<FatEntry>()) as u64,
+            cache: Mutex::new(FatCache {
+                sectors: BTreeMap::new(),
+                clock: 0,
+            }),
+        }
+    }
+
+    /// 获取簇在FAT表中的表项，即下一个簇的簇号
+    async fn get(&self, cluster: u32) -> Result<FatEntry, FileSystemError> {
+        let mut cache = self.cache.lock().await;
+        self.read_entry(&mut cache, cluster).await
+    }
+
+    /// 更新簇在FAT表中的表项
+    async fn set(&self, cluster: u32, entry: FatEntry) -> Result<(), FileSystemError> {
+        let mut cache = self.cache.lock().await;
+        self.write_entry(&mut cache, cluster, entry).await
+    }
+
+    /// 遍历簇链，返回链上所有簇号
+    async fn chain(&self, start: u32) -> Result<Vec<u32>, FileSystemError> {
+        let mut cache = self.cache.lock().await;
+        let mut clusters = Vec::new();
+        let mut cluster = start;
+        while cluster != FatEntry::FAT_ENTRY_FREE && cluster < FatEntry::FAT_ENTRY_RESERVED_START {
+            clusters.push(cluster);
+            cluster = self.read_entry(&mut cache, cluster).await?.0;
+        }
+        Ok(clusters)
+    }
+
+    /// 将新簇挂载到簇链末尾，新簇成为链尾
+    async fn extend(&self, last_cluster: u32, cluster: u32) -> Result<(), FileSystemError> {
+        let mut cache = self.cache.lock().await;
+        self.write_entry(&mut cache, cluster, FatEntry(FatEntry::FAT_ENTRY_EOC_START))
+            .await?;
+        self.write_entry(&mut cache, last_cluster, FatEntry(cluster))
+            .await
+    }
+
+    /// 释放从指定簇开始的整条簇链
+    ///
+    /// 返回释放的簇数量
+    async fn free_chain(&self, start: u32) -> Result<u32, FileSystemError> {
+        let mut cache = self.cache.lock().await;
+        let mut count = 0;
+        let mut cluster = start;
+        while cluster != FatEntry::FAT_ENTRY_FREE && cluster < FatEntry::FAT_ENTRY_RESERVED_START {
+            let next_cluster = self.read_entry(&mut cache, cluster).await?.0;
+            self.write_entry(&mut cache, cluster, FatEntry(FatEntry::FAT_ENTRY_FREE))
+                .await?;
+            count += 1;
+            cluster = next_cluster;
+        }
+        Ok(count)
+    }
+
+    /// 在`start..end`范围内查找第一个空闲簇
+    async fn find_free(&self, start: u32, end: u32) -> Result<Option<u32>, FileSystemError> {
+        let mut cache = self.cache.lock().await;
+        for cluster in start..end {
+            if self.read_entry(&mut cache, cluster).await?.0 == FatEntry::FAT_ENTRY_FREE {
+                return Ok(Some(cluster));
+            }
+        }
+        Ok(None)
+    }
+
+    /// 将所有脏扇区写回磁盘
+    async fn flush(&self) -> Result<(), FileSystemError> {
+        let mut cache = self.cache.lock().await;
+        for (&sector, cached) in cache.sectors.iter_mut() {
+            if cached.dirty {
+                self.write_back(sector, &cached.data).await?;
+                cached.dirty = false;
+            }
+        }
+        Ok(())
+    }
+
+    async fn read_entry(
+        &self,
+        cache: &mut FatCache,
+        cluster: u32,
+    ) -> Result<FatEntry, FileSystemError> {
+        let offset = cluster as u64 % self.entries_per_sector;
+        let cached = self
+            .load(cache, cluster as u64 / self.entries_per_sector)
+            .await?;
+        // Safety: offset小于每扇区的表项数，不会越界
+        Ok(unsafe {
+            read_unaligned((cached.data.as_ptr() as *const FatEntry).add(offset as usize))
+        })
+    }
+
+    async fn write_entry(
+        &self,
+        cache: &mut FatCache,
+        cluster: u32,
+        entry: FatEntry,
+    ) -> Result<(), FileSystemError> {
+        let offset = cluster as u64 % self.entries_per_sector;
+        let cached = self
+            .load(cache, cluster as u64 / self.entries_per_sector)
+            .await?;
+        // Safety: offset小于每扇区的表项数，不会越界
+        unsafe {
+            write_unaligned(
+                (cached.data.as_mut_ptr() as *mut FatEntry).add(offset as usize),
+                entry,
+            );
+        }
+        cached.dirty = true;
+        Ok(())
+    }
+
+    /// 获取缓存的扇区，未缓存时从磁盘读取
+    async fn load<'a>(
+        &self,
+        cache: &'a mut FatCache,
+        sector: u64,
+    ) -> Result<&'a mut FatSector, FileSystemError> {
+        cache.clock += 1;
+        let clock = cache.clock;
+
+        if !cache.sectors.contains_key(&sector) {
+            // 缓存已满，淘汰最久未使用的扇区
+            if cache.sectors.len() >= Self::CACHE_CAPACITY {
+                let (&evict_sector, evicted) = cache
+                    .sectors
+                    .iter()
+                    .min_by_key(|(_, cached)| cached.last_access)
+                    .unwrap();
+                if evicted.dirty {
+                    self.write_back(evict_sector, &evicted.data).await?;
+                }
+                cache.sectors.remove(&evict_sector);
+            }
+
+            let mut data = alloc::vec![0u8; self.device.block_size() as usize];
+            self.device
+                .read_block(self.start_sector + sector, &mut data)
+                .await?;
+            cache.sectors.insert(
+                sector,
+                FatSector {
+                    data,
+                    dirty: false,
+                    last_access: clock,
+                },
+            );
+        }
+
+        let cached = cache.sectors.get_mut(&sector).unwrap();
+        cached.last_access = clock;
+        Ok(cached)
+    }
+
+    /// 将扇区写入每一份FAT表
+    async fn write_back(&self, sector: u64, data: &[u8]) -> Result<(), FileSystemError> {
+        for i in 0..self.num_fats as u64 {
+            self.device
+                .write_block(self.start_sector + self.fat_size * i + sector, data)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
 impl Fat32Inner {
+    /// 当前时间，未设置时钟或时间超出FAT可表示的范围时返回None
+    fn now(&self) -> Option<FatTimestamp> {
+        FatTimestamp::from_local_time((self.clock.as_ref()?)())
+    }
+
+    /// 检查文件系统是否已卸载
+    fn check_mounted(&self) -> Result<(), FileSystemError> {
+        if self.unmounted {
+            return Err(FileSystemError::Unmounted);
+        }
+        Ok(())
+    }
+
     /// 根据文件路径，获取文件元信息
     ///
     /// 文件元信息即为目录表上的项，如果为根目录，返回Nono
@@ -685,7 +1121,7 @@ impl Fat32Inner {
             }
 
             // 进入下一个簇继续寻找
-            cluster = self.get_next_cluster(cluster).await?.0;
+            cluster = self.fat.get(cluster).await?.0;
         }
 
         Ok(())
@@ -704,10 +1140,10 @@ impl Fat32Inner {
         let mut file = None;
         self.walk_file_meta_by_cluster(cluster, |mut metadata| {
             let name_matches = if metadata.long.is_empty() {
-                name_match_short(&metadata.short, name)
+                name_match_short(&metadata.short, name, self.case_insensitive)
             } else {
                 metadata.long.sort_unstable_by_key(|entry| entry.order);
-                name_match_long(&metadata.long, name)
+                name_match_long(&metadata.long, name, self.case_insensitive)
             };
 
             if name_matches {
@@ -742,31 +1178,10 @@ impl Fat32Inner {
     fn get_sector_by_cluster(&self, cluster: u32) -> u64 {
         assert!(cluster >= 2 && cluster < self.max_cluster + 2);
         self.bpb.reserved_sector_count as u64
-            + self.bpb.fat_size_32 as u64 * 2
+            + self.bpb.fat_size_32 as u64 * self.bpb.num_fats as u64
             + (cluster as u64 - 2) * self.bpb.sectors_per_cluster as u64
     }
 
-    /// 根据簇号，获取下一个簇的簇号
-    async fn get_next_cluster(&self, cluster: u32) -> Result<FatEntry, FileSystemError> {
-        // 计算该信息所在的位置
-        let cluster_per_sector =
-            (self.bpb.bytes_per_sector as usize / size_of::<FatEntry>()) as u64;
-        let sector_offset = (cluster as u64) / cluster_per_sector;
-        let offset = (cluster as u64) % cluster_per_sector;
-        let block_index = self.bpb.reserved_sector_count as u64 + sector_offset;
-
-        // 读取该扇区
-        let mut buffer = alloc::vec![0u8; self.device.block_size() as usize];
-        self.device.read_block(block_index, &mut buffer).await?;
-
-        // 获取下一个簇的簇号
-        // Safety: buffer为刚刚读盘拿到的数据，我们已经计算得出FatEntry在此扇区的偏移
-        let next_cluster =
-            unsafe { read_unaligned((buffer.as_ptr() as *const FatEntry).add(offset as usize)) };
-
-        Ok(next_cluster)
-    }
-
     /// 查找可用簇
     ///
     /// 返回的是对应簇号。如果没有可用簇，返回 [`FileSystemError::DiskFull`]
@@ -785,86 +1200,47 @@ impl Fat32Inner {
         let hint = self
             .fs_info
             .as_ref()
-            .map(|fs_info| fs_info.next_free_cluster);
-
-        let mut current_cluster = hint.unwrap_or(2);
-        let mut end = self.max_cluster + 2;
-
-        let mut cache_block_index = None;
-        let mut cache_buffer = alloc::vec![0u8; self.device.block_size() as usize];
-
-        loop {
-            // 扫描到end了
-            if current_cluster >= end {
-                // 如果是因为提示，我们没有从2开始扫描，那么我们就重新扫一次
-                if let Some(hint) = hint {
-                    end = hint as u32;
-                    current_cluster = 2;
-                    continue;
-                }
-
-                // 如果不是因为提示，那说明磁盘满了
-                // 更新提示信息，然后报错
-                if let Some(fs_info) = &mut self.fs_info {
-                    fs_info.free_cluster_count = 0;
-                    self.write_fs_info().await?;
-                }
-                return Err(FileSystemError::DiskFull);
-            }
-
-            // 计算簇可用信息所在的位置
-            let cluster_per_sector =
-                (self.bpb.bytes_per_sector as usize / size_of::<FatEntry>()) as u64;
-            let sector_offset = (current_cluster as u64) / cluster_per_sector;
-            let offset = (current_cluster as u64) % cluster_per_sector;
-            let block_index = self.bpb.reserved_sector_count as u64 + sector_offset;
-
-            // 如果缓存可用，使用缓存，否则进行读盘
-            if cache_block_index != Some(block_index) {
-                cache_block_index = Some(block_index);
-                self.device
-                    .read_block(block_index, &mut cache_buffer)
-                    .await?;
-            }
-            let fat_entry = unsafe {
-                read_unaligned((cache_buffer.as_ptr() as *const FatEntry).add(offset as usize))
-            };
-
-            // 如果该簇不空闲，则继续检查下一个
-            if fat_entry.0 != FatEntry::FAT_ENTRY_FREE {
-                current_cluster += 1;
-                continue;
-            }
-
-            // 该簇空闲，写入EOF，并返回簇
-            unsafe {
-                write_unaligned(
-                    (cache_buffer.as_ptr() as *mut FatEntry).add(offset as usize),
-                    FatEntry(FatEntry::FAT_ENTRY_EOC_START),
-                );
-            }
-            for i in 0..self.bpb.num_fats {
-                self.device
-                    .write_block(
-                        block_index + i as u64 * self.bpb.fat_size_32 as u64,
-                        &cache_buffer,
-                    )
-                    .await?;
-            }
+            .map(|fs_info| fs_info.next_free_cluster)
+            .filter(|hint| (2..self.max_cluster + 2).contains(hint));
+
+        let end = self.max_cluster + 2;
+        let mut cluster = self.fat.find_free(hint.unwrap_or(2), end).await?;
+        // 如果是因为提示，我们没有从2开始扫描，那么我们就重新扫一次
+        if cluster.is_none()
+            && let Some(hint) = hint
+        {
+            cluster = self.fat.find_free(2, hint).await?;
+        }
 
+        let Some(cluster) = cluster else {
+            // 磁盘满了，更新提示信息，然后报错
             if let Some(fs_info) = &mut self.fs_info {
-                fs_info.free_cluster_count -= 1;
+                fs_info.free_cluster_count = 0;
                 self.write_fs_info().await?;
             }
+            return Err(FileSystemError::DiskFull);
+        };
+
+        // 该簇空闲，写入EOF，并返回簇
+        self.fat
+            .set(cluster, FatEntry(FatEntry::FAT_ENTRY_EOC_START))
+            .await?;
 
-            return Ok(current_cluster);
+        if let Some(fs_info) = &mut self.fs_info {
+            fs_info.free_cluster_count -= 1;
+            self.write_fs_info().await?;
         }
+
+        Ok(cluster)
     }
 
     /// 归还使用完毕的簇
     async fn free_cluster(&mut self, cluster: u32) -> Result<(), FileSystemError> {
-        self.update_cluster(cluster, FatEntry(FatEntry::FAT_ENTRY_FREE))
+        self.fat
+            .set(cluster, FatEntry(FatEntry::FAT_ENTRY_FREE))
             .await?;
+        // 归还的簇可能被重新分配，其中的预读数据不再有效
+        self.readahead.clear().await;
 
         if let Some(fs_info) = &mut self.fs_info {
             fs_info.free_cluster_count += 1;
@@ -874,48 +1250,25 @@ impl Fat32Inner {
         Ok(())
     }
 
-    /// 更新簇在FAT表中的信息
-    async fn update_cluster(
-        &mut self,
-        cluster: u32,
-        entry: FatEntry,
-    ) -> Result<(), FileSystemError> {
-        // 计算该信息所在的位置
-        let cluster_per_sector =
-            (self.bpb.bytes_per_sector as usize / size_of::<FatEntry>()) as u64;
-        let sector_offset = (cluster as u64) / cluster_per_sector;
-        let offset = (cluster as u64) % cluster_per_sector;
-        let block_index = self.bpb.reserved_sector_count as u64 + sector_offset;
-
-        // 读取该扇区
-        let mut buffer = alloc::vec![0u8; self.device.block_size() as usize];
-        self.device.read_block(block_index, &mut buffer).await?;
-
-        // 更新
-        // Safety: buffer为刚刚读盘拿到的数据，我们已经计算得出FatEntry在此扇区的偏移
-        unsafe {
-            write_unaligned(
-                (buffer.as_ptr() as *mut FatEntry).add(offset as usize),
-                entry,
-            );
-        };
+    /// 归还从指定簇开始的整条簇链
+    async fn free_cluster_chain(&mut self, cluster: u32) -> Result<(), FileSystemError> {
+        let count = self.fat.free_chain(cluster).await?;
+        self.readahead.clear().await;
 
-        // 写回扇区，包含备份扇区
-        for i in 0..self.bpb.num_fats {
-            self.device
-                .write_block(
-                    block_index + self.bpb.fat_size_32 as u64 * i as u64,
-                    &buffer,
-                )
-                .await?;
+        if let Some(fs_info) = &mut self.fs_info {
+            fs_info.free_cluster_count += count;
+            self.write_fs_info().await?;
         }
 
         Ok(())
     }
 
     /// 将fs_info同步到磁盘
+    ///
+    /// 空闲簇数量与FAT表对应，先写回FAT表的脏扇区，避免崩溃后FSInfo与FAT表不一致
     async fn write_fs_info(&self) -> Result<(), FileSystemError> {
         if let Some(fs_info) = &self.fs_info {
+            self.fat.flush().await?;
             let mut buffer = alloc::vec![0u8; self.device.block_size() as usize];
             unsafe {
                 copy_nonoverlapping(
@@ -1003,6 +1356,8 @@ impl Fat32Inner {
                             ManuallyDrop::new(to_create.short.clone());
 
                         // 将此扇区内容写入
+                        // 新条目可能引用刚分配的簇，先写回FAT表，避免崩溃后条目指向未分配的簇
+                        self.fat.flush().await?;
                         self.device
                             .write_block(
                                 block_index + sector as u64,
@@ -1018,7 +1373,7 @@ impl Fat32Inner {
 
             // 查看下一个簇
             last_cluster = cluster;
-            cluster = self.get_next_cluster(cluster).await?.0;
+            cluster = self.fat.get(cluster).await?.0;
         }
 
         // 我们再找一个新的簇
@@ -1056,7 +1411,7 @@ impl Fat32Inner {
             .await?;
 
         // 更新文件目录
-        self.update_cluster(last_cluster, FatEntry(cluster)).await?;
+        self.fat.extend(last_cluster, cluster).await?;
 
         Ok(())
     }
@@ -1080,12 +1435,8 @@ impl Fat32Inner {
     /// 获取整个簇链的占用空间
     ///
     /// 返回的是以字节为单位的总大小
-    async fn get_allocated_size(&self, mut cluster: u32) -> Result<u64, FileSystemError> {
-        let mut cluster_count = 0;
-        while cluster != FatEntry::FAT_ENTRY_FREE && cluster < FatEntry::FAT_ENTRY_RESERVED_START {
-            cluster_count += 1;
-            cluster = self.get_next_cluster(cluster).await?.0;
-        }
+    async fn get_allocated_size(&self, cluster: u32) -> Result<u64, FileSystemError> {
+        let cluster_count = self.fat.chain(cluster).await?.len() as u64;
 
         Ok(cluster_count * self.bpb.bytes_per_sector as u64 * self.bpb.sectors_per_cluster as u64)
     }
@@ -1151,7 +1502,8 @@ impl Fat32Inner {
                 }
             }
 
-            // 存盘
+            // 存盘，此前对FAT表的修改先写回
+            self.fat.flush().await?;
             self.device
                 .write_blocks(sector, self.bpb.sectors_per_cluster as u64, &cluster_buffer)
                 .await?;
@@ -1160,7 +1512,7 @@ impl Fat32Inner {
                 break;
             }
 
-            cluster = self.get_next_cluster(cluster).await?.0;
+            cluster = self.fat.get(cluster).await?.0;
         }
 
         Ok(())
@@ -1194,7 +1546,8 @@ impl Fat32Inner {
             *target_ptr = metadata.short.clone();
         }
 
-        // 写盘
+        // 写盘，条目中的起始簇与大小依赖FAT表中的簇链，先写回FAT表
+        self.fat.flush().await?;
         self.device
             .write_block(metadata.short_sector, &mut cluster_buffer)
             .await?;
@@ -1207,6 +1560,7 @@ impl FileSystem for Fat32FileSystem {
     fn total_space(&self) -> BoxFuture<'_, Result<u64, FileSystemError>> {
         Box::pin(async {
             let inner = self.inner.read().await;
+            inner.check_mounted()?;
             Ok(inner.max_cluster as u64
                 * inner.bpb.bytes_per_sector as u64
                 * inner.bpb.sectors_per_cluster as u64)
@@ -1216,6 +1570,7 @@ impl FileSystem for Fat32FileSystem {
     fn free_space(&self) -> BoxFuture<'_, Result<u64, FileSystemError>> {
         Box::pin(async {
             let inner = self.inner.read().await;
+            inner.check_mounted()?;
 
             // TODO: 我们应该自己单独维护剩余空间，而非完全依赖fs_info
             Ok(inner
@@ -1239,6 +1594,8 @@ impl FileSystem for Fat32FileSystem {
             };
 
             let mut inner = self.inner.write().await;
+
+            inner.check_mounted()?;
             // 父文件目录（可能为根目录）
             let directory_metadata = inner.get_file_metadata(path.parent()).await?;
             if let Some(directory_metadata) = &directory_metadata {
@@ -1269,8 +1626,11 @@ impl FileSystem for Fat32FileSystem {
             let cluster = inner.find_available_cluster().await?;
 
             // 创建fat32_metadata
-            let file_metadata =
+            let mut file_metadata =
                 Fat32FileMetadata::new(name, cluster, DirectoryEntryShort::ATTR_ARCHIVE);
+            if let Some(timestamp) = inner.now() {
+                file_metadata.set_create_time(timestamp);
+            }
 
             // 写入
             if let Err(e) = inner
@@ -1296,6 +1656,8 @@ impl FileSystem for Fat32FileSystem {
             };
 
             let mut inner = self.inner.write().await;
+
+            inner.check_mounted()?;
             // 父文件目录（可能为根目录）
             let directory_metadata = inner.get_file_metadata(path.parent()).await?;
             if let Some(directory_metadata) = &directory_metadata {
@@ -1339,8 +1701,11 @@ impl FileSystem for Fat32FileSystem {
             }
 
             // 创建fat32_metadata
-            let file_metadata =
+            let mut file_metadata =
                 Fat32FileMetadata::new(name, cluster, DirectoryEntryShort::ATTR_DIRECTORY);
+            if let Some(timestamp) = inner.now() {
+                file_metadata.set_create_time(timestamp);
+            }
 
             // 写入
             if let Err(e) = inner
@@ -1361,6 +1726,7 @@ impl FileSystem for Fat32FileSystem {
     ) -> BoxFuture<'fut, Result<Box<dyn FileHandle>, FileSystemError>> {
         Box::pin(async move {
             let mut inner = self.inner.write().await;
+            inner.check_mounted()?;
 
             // 获取文件信息
             let Some(file) = inner.get_file_metadata(path).await? else {
@@ -1382,6 +1748,9 @@ impl FileSystem for Fat32FileSystem {
                 inner: Arc::downgrade(&self.inner),
                 metadata: file,
                 pointer: 0,
+                position: None,
+                sequential_end: 0,
+                readahead_end: 0,
                 closed: false,
             }) as Box<dyn FileHandle>)
         })
@@ -1393,6 +1762,7 @@ impl FileSystem for Fat32FileSystem {
     ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
         Box::pin(async move {
             let mut inner = self.inner.write().await;
+            inner.check_mounted()?;
 
             // 获取文件信息
             let Some(file) = inner.get_file_metadata(path).await? else {
@@ -1413,14 +1783,7 @@ impl FileSystem for Fat32FileSystem {
             inner.delete_file_meta(&file).await?;
 
             // 清空簇
-            let mut cluster = file.start_cluster();
-            while cluster != FatEntry::FAT_ENTRY_FREE
-                && cluster < FatEntry::FAT_ENTRY_RESERVED_START
-            {
-                let next_cluster = inner.get_next_cluster(cluster).await?;
-                inner.free_cluster(cluster).await?;
-                cluster = next_cluster.0;
-            }
+            inner.free_cluster_chain(file.start_cluster()).await?;
 
             // 完成
             Ok(())
@@ -1433,6 +1796,7 @@ impl FileSystem for Fat32FileSystem {
     ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
         Box::pin(async move {
             let mut inner = self.inner.write().await;
+            inner.check_mounted()?;
 
             // 获取文件信息
             let Some(file) = inner.get_file_metadata(path).await? else {
@@ -1456,14 +1820,7 @@ impl FileSystem for Fat32FileSystem {
             inner.delete_file_meta(&file).await?;
 
             // 清空簇
-            let mut cluster = file.start_cluster();
-            while cluster != FatEntry::FAT_ENTRY_FREE
-                && cluster < FatEntry::FAT_ENTRY_RESERVED_START
-            {
-                let next_cluster = inner.get_next_cluster(cluster).await?;
-                inner.free_cluster(cluster).await?;
-                cluster = next_cluster.0;
-            }
+            inner.free_cluster_chain(file.start_cluster()).await?;
 
             // 完成
             Ok(())
@@ -1477,6 +1834,7 @@ impl FileSystem for Fat32FileSystem {
     ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
         Box::pin(async move {
             let mut inner = self.inner.write().await;
+            inner.check_mounted()?;
             // 原文件
             let Some(src) = inner.get_file_metadata(old_path).await? else {
                 return Err(FileSystemError::OperationNotSupport);
@@ -1496,6 +1854,8 @@ impl FileSystem for Fat32FileSystem {
                 .get_file_metadata_by_cluster(dst_parent_cluster, last_segment)
                 .await
             {
+                // 忽略大小写时，仅修改大小写的重命名会找到原文件自身
+                Ok(dst) if dst.is_same_entry(&src) => (),
                 Ok(_) => return Err(FileSystemError::FileExists),
                 Err(FileSystemError::FileNotFound) => (),
                 Err(e) => return Err(e),
@@ -1506,8 +1866,10 @@ impl FileSystem for Fat32FileSystem {
 
             // 移除
             inner.delete_file_meta(&src).await?;
-            // 新增
-            inner.create_file_meta(dst_parent_cluster, &src).await?;
+            // 新增，使用新文件名，其余字段（起始簇、大小、属性等）保持不变
+            let mut dst = Fat32FileMetadata::new(last_segment, src.start_cluster(), src.short.attr);
+            dst.short = src.short.clone();
+            inner.create_file_meta(dst_parent_cluster, &dst).await?;
 
             Ok(())
         })
@@ -1519,6 +1881,7 @@ impl FileSystem for Fat32FileSystem {
     ) -> BoxFuture<'fut, Result<FileMetadata, FileSystemError>> {
         Box::pin(async move {
             let inner = self.inner.read().await;
+            inner.check_mounted()?;
 
             // 获取文件元信息
             let Some(file_metadata) = inner.get_file_metadata(path).await? else {
@@ -1536,6 +1899,7 @@ impl FileSystem for Fat32FileSystem {
     ) -> BoxFuture<'fut, Result<Vec<FileMetadata>, FileSystemError>> {
         Box::pin(async move {
             let inner = self.inner.read().await;
+            inner.check_mounted()?;
 
             // 获取文件信息
             let file = inner.get_file_metadata(path).await?;
@@ -1564,8 +1928,16 @@ impl FileSystem for Fat32FileSystem {
     }
 
     fn unmount(&self) -> BoxFuture<'_, Result<(), FileSystemError>> {
-        // 我们没有在内存中缓存什么，所有数据都是即时刷入块设备的，因此无需处理
-        Box::pin(async { Ok(()) })
+        // 内存中只缓存了FAT表，写回FAT表后刷新块设备的缓存并标记为已卸载
+        Box::pin(async {
+            let mut inner = self.inner.write().await;
+            inner.check_mounted()?;
+            inner.fat.flush().await?;
+            inner.device.flush().await?;
+            inner.readahead.clear().await;
+            inner.unmounted = true;
+            Ok(())
+        })
     }
 }
 
@@ -1573,9 +1945,56 @@ struct Fat32FileHandle {
     inner: Weak<RwLock<Fat32Inner>>,
     metadata: Fat32FileMetadata,
     pointer: u64,
+    // 缓存的读取位置：(簇起始处的文件偏移, 簇号)，避免每次读取都从起始簇遍历簇链
+    position: Option<(u64, u32)>,
+    // 上次读取结束处的文件偏移，从此处开始的读取视为顺序读取
+    sequential_end: u64,
+    // 已发起预读的范围终点（文件偏移）
+    readahead_end: u64,
     closed: bool,
 }
 
+impl Fat32FileHandle {
+    /// 顺序读取后发起预读
+    ///
+    /// 已预读的范围不足窗口的一半时，预读当前簇之后直到指针后`window_size`字节处的簇
+    async fn start_readahead(
+        &mut self,
+        inner: &Fat32Inner,
+        window_size: u64,
+    ) -> Result<(), FileSystemError> {
+        let file_size = self.metadata.short.file_size as u64;
+        if self.readahead_end >= file_size || self.readahead_end >= self.pointer + window_size / 2 {
+            return Ok(());
+        }
+        let Some((mut offset, mut cluster)) = self.position else {
+            return Ok(());
+        };
+
+        let bytes_per_cluster =
+            inner.bpb.bytes_per_sector as u64 * inner.bpb.sectors_per_cluster as u64;
+        let start = self
+            .readahead_end
+            .max(self.pointer.next_multiple_of(bytes_per_cluster));
+        let end = (self.pointer + window_size).min(file_size);
+        let mut units = Vec::new();
+        while offset < end
+            && cluster != FatEntry::FAT_ENTRY_FREE
+            && cluster < FatEntry::FAT_ENTRY_EOC_START
+        {
+            if offset >= start {
+                units.push(inner.get_sector_by_cluster(cluster));
+            }
+            cluster = inner.fat.get(cluster).await?.0;
+            offset += bytes_per_cluster;
+        }
+
+        self.readahead_end = offset;
+        inner.readahead.prefetch(&units).await;
+        Ok(())
+    }
+}
+
 impl FileHandle for Fat32FileHandle {
     fn close(&mut self) -> BoxFuture<'_, Result<(), FileSystemError>> {
         Box::pin(async move {
@@ -1637,90 +2056,140 @@ impl FileHandle for Fat32FileHandle {
 
             let inner = self.inner.upgrade().ok_or(FileSystemError::Unmounted)?;
             let inner = inner.read().await;
+            inner.check_mounted()?;
 
             let file_size = self.metadata.short.file_size as u64;
             let read_length = buf.len().min((file_size - self.pointer) as usize);
+            if read_length == 0 {
+                return Ok(0);
+            }
 
-            // 已经向buf中写入的字节数量
-            let mut assign_offset = 0;
-            // 循环中已经跳过的文件字节数量
-            let mut offset = 0;
-            // 剩余要写入buf的字节数量
-            let mut remain = read_length as u64;
-            // 当前循环的簇
-            let mut cluster = self.metadata.start_cluster();
             // 每扇区有效字节数
             let bytes_per_sector = inner.bpb.bytes_per_sector as u64;
+            // 每簇扇区数
+            let sectors_per_cluster = inner.bpb.sectors_per_cluster as u64;
             // 每簇有效字节数
-            let bytes_per_cluster = bytes_per_sector * inner.bpb.sectors_per_cluster as u64;
+            let bytes_per_cluster = bytes_per_sector * sectors_per_cluster;
             let block_size = inner.device.block_size();
-            // 簇缓冲
-            let mut cluster_buffer =
-                alloc::vec![0u8; block_size as usize* inner.bpb.sectors_per_cluster as usize];
+            // 预读窗口，未启用预读时为None
+            let window = inner.readahead.window().await;
+            // 从上次读取结束处继续读取视为顺序读取，否则重新开始预读
+            let sequential = self.pointer == self.sequential_end;
+            if !sequential {
+                self.readahead_end = 0;
+            }
 
-            // 循环各簇，找到要读取的部分
-            'cluster_loop: while cluster != FatEntry::FAT_ENTRY_FREE
+            // 从缓存的位置开始查找，缓存位于指针之后时只能从头开始
+            let (mut offset, mut cluster) = match self.position {
+                Some((offset, cluster)) if offset <= self.pointer => (offset, cluster),
+                _ => (0, self.metadata.start_cluster()),
+            };
+            while offset + bytes_per_cluster <= self.pointer
+                && cluster != FatEntry::FAT_ENTRY_FREE
                 && cluster < FatEntry::FAT_ENTRY_EOC_START
-                && remain > 0
             {
-                if offset + bytes_per_cluster < self.pointer {
-                    offset += bytes_per_cluster;
-                    cluster = inner.get_next_cluster(cluster).await?.0;
-                    continue;
-                }
+                cluster = inner.fat.get(cluster).await?.0;
+                offset += bytes_per_cluster;
+            }
 
-                // 读盘
-                inner
-                    .device
-                    .read_blocks(
-                        inner.get_sector_by_cluster(cluster),
-                        inner.bpb.sectors_per_cluster as u64,
-                        &mut cluster_buffer,
-                    )
-                    .await?;
+            // 已经向buf中写入的字节数量
+            let mut assign_offset = 0;
+            // 剩余要写入buf的字节数量
+            let mut remain = read_length as u64;
+            // 读盘缓冲
+            let mut sector_buffer = Vec::new();
 
-                // 循环各扇区
-                for i in 0..inner.bpb.sectors_per_cluster as usize {
-                    if offset + bytes_per_sector < self.pointer {
-                        offset += bytes_per_sector;
-                        continue;
+            // 循环各段连续的簇
+            while cluster != FatEntry::FAT_ENTRY_FREE
+                && cluster < FatEntry::FAT_ENTRY_EOC_START
+                && remain > 0
+            {
+                // 合并连续的簇，一次读盘。已预读的簇单独从缓存中读取
+                let first_cluster = cluster;
+                let mut last_cluster = cluster;
+                let mut run_end = offset + bytes_per_cluster;
+                let mut next_cluster = None;
+                let cached = window.is_some()
+                    && inner
+                        .readahead
+                        .contains(inner.get_sector_by_cluster(cluster))
+                        .await;
+                while !cached && run_end < self.pointer + remain {
+                    let next = inner.fat.get(last_cluster).await?.0;
+                    if next != last_cluster + 1
+                        || (window.is_some()
+                            && inner
+                                .readahead
+                                .contains(inner.get_sector_by_cluster(next))
+                                .await)
+                    {
+                        next_cluster = Some(next);
+                        break;
+                    }
+                    last_cluster = next;
+                    run_end += bytes_per_cluster;
+                }
+
+                // 只读取覆盖所需范围的扇区
+                let read_end = run_end.min(self.pointer + remain);
+                let first_sector = (self.pointer - offset) / bytes_per_sector;
+                let sector_count = (read_end - offset).div_ceil(bytes_per_sector) - first_sector;
+                sector_buffer.resize((sector_count * block_size) as usize, 0);
+                let first_block = inner.get_sector_by_cluster(first_cluster);
+                // 检查与读取之间缓存可能被淘汰，此时仍需读盘
+                if !cached
+                    || !inner
+                        .readahead
+                        .read(first_block, first_sector, sector_count, &mut sector_buffer)
+                        .await
+                {
+                    inner
+                        .device
+                        .read_blocks(first_block + first_sector, sector_count, &mut sector_buffer)
+                        .await?;
+                    if window.is_some() {
+                        let cluster_count = (run_end - offset) / bytes_per_cluster;
+                        inner.readahead.record_misses(cluster_count).await;
                     }
+                }
+
+                // 循环各扇区
+                for i in 0..sector_count {
+                    let sector_offset = offset + (first_sector + i) * bytes_per_sector;
 
                     // 复制内容
-                    let copy_start = self.pointer - offset;
+                    let copy_start = self.pointer - sector_offset;
                     let copy_length = (bytes_per_sector - copy_start).min(remain);
-                    assert!(
-                        cluster_buffer.len()
-                            >= i as usize * block_size as usize
-                                + copy_start as usize
-                                + copy_length as usize
+                    let buffer_start = (i * block_size + copy_start) as usize;
+                    buf[assign_offset..assign_offset + copy_length as usize].copy_from_slice(
+                        &sector_buffer[buffer_start..buffer_start + copy_length as usize],
                     );
-                    assert!(buf.len() >= assign_offset as usize + copy_length as usize);
-                    // Safety: 我们已靠断言保证复制是范围是安全的
-                    unsafe {
-                        copy_nonoverlapping(
-                            cluster_buffer
-                                .as_ptr()
-                                .add(i as usize * block_size as usize + copy_start as usize),
-                            buf.as_mut_ptr().add(assign_offset as usize),
-                            copy_length as usize,
-                        );
-                    }
 
                     // 维护循环不变量
-                    assign_offset += copy_length;
-                    offset += bytes_per_sector;
+                    assign_offset += copy_length as usize;
                     self.pointer += copy_length;
                     remain -= copy_length;
+                }
 
-                    if remain == 0 {
-                        break 'cluster_loop;
-                    }
+                // 记录本次读取的最后一个簇
+                let last_offset = run_end - bytes_per_cluster;
+                self.position = Some((last_offset, last_cluster));
 
-                    assert!(offset == self.pointer);
+                if remain > 0 {
+                    cluster = match next_cluster {
+                        Some(next) => next,
+                        None => inner.fat.get(last_cluster).await?.0,
+                    };
+                    offset = run_end;
                 }
+            }
 
-                cluster = inner.get_next_cluster(cluster).await?.0;
+            self.sequential_end = self.pointer;
+            if let Some(window) = window
+                && sequential
+            {
+                self.start_readahead(&inner, window as u64 * bytes_per_cluster)
+                    .await?;
             }
 
             Ok(read_length as u64)
@@ -1748,6 +2217,7 @@ impl FileHandle for Fat32FileHandle {
             // 理论上可以区分三种情况并减小锁粒度，但这里我们选取简单实现，不考虑性能
             let inner = self.inner.upgrade().ok_or(FileSystemError::Unmounted)?;
             let mut inner = inner.write().await;
+            inner.check_mounted()?;
 
             // 当前文件大小
             let file_size = self.metadata.short.file_size as u64;
@@ -1781,7 +2251,7 @@ impl FileHandle for Fat32FileHandle {
                 last_cluster = cluster;
                 if offset + bytes_per_cluster < self.pointer {
                     offset += bytes_per_cluster;
-                    cluster = inner.get_next_cluster(cluster).await?.0;
+                    cluster = inner.fat.get(cluster).await?.0;
                     continue;
                 }
 
@@ -1847,9 +2317,13 @@ impl FileHandle for Fat32FileHandle {
                         &cluster_buffer,
                     )
                     .await?;
+                inner
+                    .readahead
+                    .invalidate(current_sector, inner.bpb.sectors_per_cluster as u64)
+                    .await;
 
                 // 下一个簇
-                cluster = inner.get_next_cluster(cluster).await?.0;
+                cluster = inner.fat.get(cluster).await?.0;
             }
 
             // 如果仍有剩余空间，说明需要分配新簇
@@ -1865,9 +2339,7 @@ impl FileHandle for Fat32FileHandle {
                         && last_cluster < FatEntry::FAT_ENTRY_RESERVED_START
                 );
                 // 挂载新簇
-                inner
-                    .update_cluster(last_cluster, FatEntry(cluster))
-                    .await?;
+                inner.fat.extend(last_cluster, cluster).await?;
                 last_cluster = cluster;
 
                 // 准备向新簇中写入内容
@@ -1915,25 +2387,89 @@ impl FileHandle for Fat32FileHandle {
                 }
 
                 // 写盘
+                let current_sector = inner.get_sector_by_cluster(cluster);
                 inner
                     .device
                     .write_blocks(
-                        inner.get_sector_by_cluster(cluster),
+                        current_sector,
                         inner.bpb.sectors_per_cluster as u64,
                         &cluster_buffer,
                     )
                     .await?;
+                inner
+                    .readahead
+                    .invalidate(current_sector, inner.bpb.sectors_per_cluster as u64)
+                    .await;
             }
 
-            // 文件大小维护
+            // 写入使预读缓存失效，之后的顺序读取需重新预读
+            self.readahead_end = 0;
+
+            // 文件大小与修改时间维护。修改时间精确到2秒，同一时段内的多次写入只需更新一次目录项
+            let mut changed = false;
             if self.pointer > file_size {
                 self.metadata.short.file_size = self.pointer as u32;
+                changed = true;
+            }
+            if let Some(timestamp) = inner.now() {
+                changed |= self.metadata.set_write_time(timestamp);
+            }
+            if changed {
                 inner.update_file_metadata(&self.metadata).await?;
             }
 
             Ok(())
         })
     }
+
+    fn truncate(&mut self) -> BoxFuture<'_, Result<(), FileSystemError>> {
+        Box::pin(async move {
+            if self.closed {
+                return Err(FileSystemError::FileClosed);
+            }
+
+            let inner = self.inner.upgrade().ok_or(FileSystemError::Unmounted)?;
+            let mut inner = inner.write().await;
+            inner.check_mounted()?;
+
+            if self.pointer >= self.metadata.short.file_size as u64 {
+                return Ok(());
+            }
+
+            // 保留容纳新大小所需的簇，起始簇始终保留
+            let bytes_per_cluster =
+                inner.bpb.bytes_per_sector as u64 * inner.bpb.sectors_per_cluster as u64;
+            let keep_count = self.pointer.div_ceil(bytes_per_cluster).max(1);
+            let mut cluster = self.metadata.start_cluster();
+            for _ in 1..keep_count {
+                cluster = inner.fat.get(cluster).await?.0;
+            }
+
+            // 在保留的最后一个簇处结束链表，归还之后的簇
+            let next_cluster = inner.fat.get(cluster).await?.0;
+            if next_cluster != FatEntry::FAT_ENTRY_FREE
+                && next_cluster < FatEntry::FAT_ENTRY_RESERVED_START
+            {
+                inner
+                    .fat
+                    .set(cluster, FatEntry(FatEntry::FAT_ENTRY_EOC_START))
+                    .await?;
+                inner.free_cluster_chain(next_cluster).await?;
+            }
+
+            self.metadata.short.file_size = self.pointer as u32;
+            if let Some(timestamp) = inner.now() {
+                self.metadata.set_write_time(timestamp);
+            }
+            inner.update_file_metadata(&self.metadata).await?;
+
+            // 被归还的簇可能仍在缓存中
+            self.position = None;
+            self.readahead_end = 0;
+
+            Ok(())
+        })
+    }
 }
 
 fn check_bpb(bpb: &BPB, block_size: u64, block_count: u64) -> Result<(), MountError> {
@@ -2010,22 +2546,23 @@ fn check_fs_info(fs_info: &FSInfo) -> Result<(), MountError> {
     Ok(())
 }
 
-/// 根据保留扇区数、每扇区字节数、每簇扇区数、扇区总数计算总簇数
+/// 根据保留扇区数、每扇区字节数、每簇扇区数、FAT表数量、扇区总数计算总簇数
 ///
 /// 返回：(总簇数, 剩余无法分配的扇区数)
 fn calc_cluster_count(
     reserved_sector_count: u64, // 保留扇区数
     bytes_per_sector: u64,      // 每扇区字节数
     sectors_per_cluster: u64,   // 每簇扇区数
+    num_fats: u64,              // FAT表数量
     block_count: u64,           // 扇区总数
 ) -> (u64, u64) {
     // 每个FAT扇区可容纳的簇数量
     let cluster_per_fat = bytes_per_sector / size_of::<FatEntry>() as u64;
 
     // 我们假设所有的FAT均被填满，那么每一个FAT扇区都会对应cluster_per_sector个簇
-    // 由于FAT是两份，所以我们可以计算出每cluster_per_sector个簇对应多少扇区
+    // 由于FAT有num_fats份，所以我们可以计算出每cluster_per_sector个簇对应多少扇区
     // 我们将其称为 一组簇 对应 多少扇区
-    let sector_per_group = 2 + cluster_per_fat * sectors_per_cluster;
+    let sector_per_group = num_fats + cluster_per_fat * sectors_per_cluster;
 
     // 基于此，我们可以算出全部扇区可以表示容纳多少组簇
     // 需要注意，由于前两个簇实际上不占用空间（保留编号），因此在计算时需要单独处理这些空间
@@ -2034,12 +2571,13 @@ fn calc_cluster_count(
     let remain_block_count = virtual_block_count - sector_per_group * group_count;
 
     // 剩余的块已经不能填满一整组，那么有两种可能：
-    // 1. 还能分出2个FAT的扇区，然后再凑至少一个簇
+    // 1. 还能分出num_fats个FAT的扇区，然后再凑至少一个簇
     // 2. 扇区不足以做到1
     // 据此，我们能算出剩下簇的数量
-    let (remain_cluster, remain_block_count) = if remain_block_count > 2 {
-        let remain_cluster = (remain_block_count - 2) / sectors_per_cluster;
-        let remain_block_count = remain_block_count - 2 - remain_cluster * sectors_per_cluster;
+    let (remain_cluster, remain_block_count) = if remain_block_count > num_fats {
+        let remain_cluster = (remain_block_count - num_fats) / sectors_per_cluster;
+        let remain_block_count =
+            remain_block_count - num_fats - remain_cluster * sectors_per_cluster;
         (remain_cluster, remain_block_count)
     } else {
         (0, remain_block_count)
@@ -2051,22 +2589,30 @@ fn calc_cluster_count(
     (cluster_total, remain_block_count)
 }
 
-fn name_match_short(entry: &DirectoryEntryShort, name: &str) -> bool {
-    let mut bytes = name.as_bytes().iter().copied();
+// case_insensitive: 是否忽略ASCII大小写，短文件名只包含ASCII字符
+fn name_match_short(entry: &DirectoryEntryShort, name: &str, case_insensitive: bool) -> bool {
+    let (mut entry_name, mut entry_ext) = (entry.name, entry.ext);
+    let mut name = name.as_bytes().to_vec();
+    if case_insensitive {
+        entry_name.make_ascii_uppercase();
+        entry_ext.make_ascii_uppercase();
+        name.make_ascii_uppercase();
+    }
+    let mut bytes = name.into_iter();
 
-    if !match_with_array(&mut bytes, &entry.name) {
+    if !match_with_array(&mut bytes, &entry_name) {
         return false;
     }
 
     let name_has_dot = bytes.next() == Some(b'.');
-    let entry_has_ext = entry.ext[0] != 0;
+    let entry_has_ext = entry_ext[0] != 0;
 
     if name_has_dot ^ entry_has_ext {
         return false;
     }
 
     if name_has_dot {
-        if !match_with_array(&mut bytes, &entry.ext) {
+        if !match_with_array(&mut bytes, &entry_ext) {
             return false;
         }
     }
@@ -2075,25 +2621,36 @@ fn name_match_short(entry: &DirectoryEntryShort, name: &str) -> bool {
 }
 
 // entry: 必须已经排序
-fn name_match_long(entry: &[DirectoryEntryLong], name: &str) -> bool {
-    let mut utf16 = name.encode_utf16();
+// case_insensitive: 是否按Unicode规则忽略大小写
+fn name_match_long(entry: &[DirectoryEntryLong], name: &str, case_insensitive: bool) -> bool {
+    let units = long_name_units(entry);
+    if !case_insensitive {
+        return units.iter().copied().eq(name.encode_utf16());
+    }
+
+    // 长文件名中可能存在不成对的代理项，替换为U+FFFD，不会与合法的文件名匹配
+    let entry_chars = char::decode_utf16(units.iter().copied())
+        .map(|ch| ch.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .flat_map(fold_case);
+    entry_chars.eq(name.chars().flat_map(fold_case))
+}
 
+/// 长文件名的UTF-16编码，每段名称遇到0时结束
+// entry: 必须已经排序
+fn long_name_units(entry: &[DirectoryEntryLong]) -> Vec<u16> {
+    let mut units = Vec::new();
     for entry in entry {
-        let temp = entry.name1;
-        if !match_with_array(&mut utf16, &temp) {
-            return false;
-        }
-        let temp = entry.name2;
-        if !match_with_array(&mut utf16, &temp) {
-            return false;
-        }
-        let temp = entry.name3;
-        if !match_with_array(&mut utf16, &temp) {
-            return false;
+        let (name1, name2, name3) = (entry.name1, entry.name2, entry.name3);
+        for part in [&name1[..], &name2[..], &name3[..]] {
+            units.extend(part.iter().copied().take_while(|&unit| unit != 0));
         }
     }
+    units
+}
 
-    !utf16.next().is_some()
+/// 大小写折叠，先转为大写再转为小写，使`ς`与`σ`、`ß`与`ss`等视为相同
+fn fold_case(ch: char) -> impl Iterator<Item = char> {
+    ch.to_uppercase().flat_map(char::to_lowercase)
 }
 
 fn match_with_array<I, T>(iter: &mut I, array: &[T]) -> bool
@@ -2119,22 +2676,130 @@ where
 
 #[cfg(test)]
 mod test {
-    use std::sync::Arc;
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicU64, Ordering},
+        },
+        vec::Vec,
+    };
 
     use crate::{
-        device::memory::MemoryDevice,
+        BoxFuture,
+        device::{
+            BlockDevice, BlockDeviceError,
+            memory::{DeviceOperation, MemoryDevice},
+        },
         fs::{
             FileSystem, FileSystemError,
-            fat32::{Fat32FileSystem, FatEntry, calc_cluster_count},
+            conformance::run_conformance,
+            fat32::{
+                Fat32FileSystem, FatEntry, FatTable, FormatError, FormatOptions, LocalTime,
+                calc_cluster_count,
+            },
+            readahead::{ReadaheadConfig, ReadaheadStats, Spawner},
         },
         path::PathBuf,
         run_task,
     };
 
+    /// 统计读盘次数的块设备
+    struct CountingDevice {
+        inner: MemoryDevice,
+        // read_block调用次数，文件读取中主要来自FAT表查询
+        single_reads: AtomicU64,
+        // read_blocks调用次数
+        batch_reads: AtomicU64,
+        // 读取的块总数
+        blocks_read: AtomicU64,
+    }
+
+    impl CountingDevice {
+        fn new(size_in_bytes: u64, block_size: u64) -> Self {
+            Self {
+                inner: MemoryDevice::new(size_in_bytes, block_size),
+                single_reads: AtomicU64::new(0),
+                batch_reads: AtomicU64::new(0),
+                blocks_read: AtomicU64::new(0),
+            }
+        }
+
+        fn reset(&self) {
+            self.single_reads.store(0, Ordering::Relaxed);
+            self.batch_reads.store(0, Ordering::Relaxed);
+            self.blocks_read.store(0, Ordering::Relaxed);
+        }
+
+        fn counts(&self) -> (u64, u64, u64) {
+            (
+                self.single_reads.load(Ordering::Relaxed),
+                self.batch_reads.load(Ordering::Relaxed),
+                self.blocks_read.load(Ordering::Relaxed),
+            )
+        }
+    }
+
+    impl BlockDevice for CountingDevice {
+        fn block_size(&self) -> u64 {
+            self.inner.block_size()
+        }
+
+        fn block_count(&self) -> u64 {
+            self.inner.block_count()
+        }
+
+        fn write_block<'fut>(
+            &'fut self,
+            block_index: u64,
+            buf: &'fut [u8],
+        ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+            self.inner.write_block(block_index, buf)
+        }
+
+        fn read_block<'fut>(
+            &'fut self,
+            block_index: u64,
+            buf: &'fut mut [u8],
+        ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+            self.single_reads.fetch_add(1, Ordering::Relaxed);
+            self.blocks_read.fetch_add(1, Ordering::Relaxed);
+            self.inner.read_block(block_index, buf)
+        }
+
+        fn read_blocks<'fut>(
+            &'fut self,
+            block_index: u64,
+            count: u64,
+            buf: &'fut mut [u8],
+        ) -> BoxFuture<'fut, Result<(), BlockDeviceError>> {
+            self.batch_reads.fetch_add(1, Ordering::Relaxed);
+            self.blocks_read.fetch_add(count, Ordering::Relaxed);
+            self.inner.read_blocks(block_index, count, buf)
+        }
+    }
+
+    /// 后台任务队列，测试中手动执行其中的任务
+    type TaskQueue = Arc<std::sync::Mutex<Vec<BoxFuture<'static, ()>>>>;
+
+    fn task_spawner() -> (Arc<Spawner>, TaskQueue) {
+        let queue = TaskQueue::default();
+        let tasks = queue.clone();
+        let spawner = Arc::new(move |task| tasks.lock().unwrap().push(task)) as Arc<Spawner>;
+        (spawner, queue)
+    }
+
+    async fn run_tasks(queue: &TaskQueue) {
+        let tasks = core::mem::take(&mut *queue.lock().unwrap());
+        for task in tasks {
+            task.await;
+        }
+    }
+
     #[test]
     fn test_calc_cluster_count() {
-        assert_eq!(calc_cluster_count(32, 512, 8, 65536), (8172, 0));
-        assert_eq!(calc_cluster_count(32, 512, 7, 10000), (1420, 4));
+        assert_eq!(calc_cluster_count(32, 512, 8, 2, 65536), (8172, 0));
+        assert_eq!(calc_cluster_count(32, 512, 7, 2, 10000), (1420, 4));
+        assert_eq!(calc_cluster_count(32, 512, 8, 1, 65536), (8180, 0));
     }
 
     #[test]
@@ -2146,7 +2811,7 @@ mod test {
             let mut inner = fs.inner.write().await;
             // 2号簇是根路径，已经被占用
             assert_eq!(
-                inner.get_next_cluster(2).await.unwrap().0,
+                inner.fat.get(2).await.unwrap().0,
                 FatEntry::FAT_ENTRY_EOC_START
             );
 
@@ -2335,4 +3000,547 @@ mod test {
             assert!(matches!(err, FileSystemError::FileNotFound));
         });
     }
+
+    #[test]
+    fn test_format_options() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(4 * 1024 * 1024, 512));
+            let options = FormatOptions {
+                sectors_per_cluster: 16,
+                volume_label: "COS DISK".into(),
+                oem_id: "test".into(),
+                num_fats: 1,
+                align_1mib: true,
+            };
+            let fs = Fat32FileSystem::with_format_options(device.clone(), &options)
+                .await
+                .unwrap();
+            {
+                let inner = fs.inner.read().await;
+                let bpb = &inner.bpb;
+                let (sectors_per_cluster, num_fats) = (bpb.sectors_per_cluster, bpb.num_fats);
+                let (volume_label, oem_id) = (bpb.volume_label, bpb.oem_id);
+                assert_eq!(sectors_per_cluster, 16);
+                assert_eq!(num_fats, 1);
+                assert_eq!(&volume_label, b"COS DISK   ");
+                assert_eq!(&oem_id, b"test    ");
+                // 数据区从1MiB边界开始
+                assert_eq!(inner.get_sector_by_cluster(2) % 2048, 0);
+            }
+
+            let path = PathBuf::from_str("test.txt").unwrap();
+            fs.create_file(path.as_path()).await.unwrap();
+            let mut handle = fs.open_file(path.as_path()).await.unwrap();
+            handle.write(b"hello").await.unwrap();
+            handle.close().await.unwrap();
+            fs.unmount().await.unwrap();
+
+            let fs = Fat32FileSystem::mount(device.clone()).await.unwrap();
+            let file = fs.get_metadata(path.as_path()).await.unwrap();
+            assert_eq!(file.size, 5);
+            assert_eq!(file.allocated_size, 512 * 16);
+        });
+    }
+
+    #[test]
+    fn test_format_invalid_options() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(512 * 1024, 512));
+            for options in [
+                FormatOptions {
+                    sectors_per_cluster: 3,
+                    ..Default::default()
+                },
+                FormatOptions {
+                    num_fats: 0,
+                    ..Default::default()
+                },
+                FormatOptions {
+                    volume_label: "VOLUME LABEL".into(),
+                    ..Default::default()
+                },
+            ] {
+                assert!(matches!(
+                    Fat32FileSystem::with_format_options(device.clone(), &options).await,
+                    Err(FormatError::InvalidOptions)
+                ));
+            }
+
+            // 对齐后剩余空间不足
+            let options = FormatOptions {
+                align_1mib: true,
+                ..Default::default()
+            };
+            assert!(matches!(
+                Fat32FileSystem::with_format_options(device.clone(), &options).await,
+                Err(FormatError::DeviceTooSmall)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(512 * 28, 512));
+            let fs = Fat32FileSystem::with_format(device.clone()).await.unwrap();
+            let path = |s| PathBuf::from_str(s).unwrap();
+
+            fs.create_file(path("Test.txt").as_path()).await.unwrap();
+            let file = fs.get_metadata(path("TEST.TXT").as_path()).await.unwrap();
+            assert_eq!(file.name, "Test.txt");
+            assert!(matches!(
+                fs.create_file(path("test.txt").as_path()).await,
+                Err(FileSystemError::FileExists)
+            ));
+
+            // 仅修改大小写的重命名
+            fs.rename(path("test.TXT").as_path(), path("test.txt").as_path())
+                .await
+                .unwrap();
+            let file = fs.get_metadata(path("TEST.txt").as_path()).await.unwrap();
+            assert_eq!(file.name, "test.txt");
+        });
+    }
+
+    #[test]
+    fn test_case_insensitive_unicode() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(512 * 1024, 512));
+            let fs = Fat32FileSystem::with_format(device.clone()).await.unwrap();
+            let path = |s| PathBuf::from_str(s).unwrap();
+
+            fs.create_file(path("Äpfel").as_path()).await.unwrap();
+            fs.create_file(path("straße").as_path()).await.unwrap();
+            fs.create_file(path("ΟΔΟΣ").as_path()).await.unwrap();
+            assert!(fs.get_metadata(path("äPFEL").as_path()).await.is_ok());
+            assert!(fs.get_metadata(path("STRASSE").as_path()).await.is_ok());
+            assert!(fs.get_metadata(path("οδος").as_path()).await.is_ok());
+            assert!(matches!(
+                fs.get_metadata(path("apfel").as_path()).await,
+                Err(FileSystemError::FileNotFound)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_case_sensitive() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(512 * 28, 512));
+            let fs = Fat32FileSystem::with_format(device.clone()).await.unwrap();
+            fs.set_case_insensitive(false).await;
+            let path = |s| PathBuf::from_str(s).unwrap();
+
+            fs.create_file(path("TEST.TXT").as_path()).await.unwrap();
+            fs.create_file(path("test.txt").as_path()).await.unwrap();
+            let file = fs.get_metadata(path("test.txt").as_path()).await.unwrap();
+            assert_eq!(file.name, "test.txt");
+            assert!(matches!(
+                fs.get_metadata(path("Test.txt").as_path()).await,
+                Err(FileSystemError::FileNotFound)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_timestamps() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(512 * 28, 512));
+            let fs = Fat32FileSystem::with_format(device.clone()).await.unwrap();
+            // 时钟的分钟数
+            let minute = Arc::new(AtomicU64::new(30));
+            let clock_minute = minute.clone();
+            fs.set_clock(Arc::new(move || LocalTime {
+                year: 2024,
+                month: 5,
+                day: 17,
+                hour: 13,
+                minute: clock_minute.load(Ordering::Relaxed) as u8,
+                second: 21,
+                millisecond: 500,
+            }))
+            .await;
+            let path = PathBuf::from_str("test.txt").unwrap();
+            let short = async || {
+                let inner = fs.inner.read().await;
+                let metadata = inner.get_file_metadata(path.as_path()).await.unwrap();
+                metadata.unwrap().short
+            };
+
+            fs.create_file(path.as_path()).await.unwrap();
+            let entry = short().await;
+            let date = (44 << 9) | (5 << 5) | 17;
+            assert_eq!({ entry.create_date }, date);
+            assert_eq!({ entry.create_time }, (13 << 11) | (30 << 5) | 10);
+            assert_eq!(entry.create_time_tenths, 150);
+            assert_eq!({ entry.write_date }, date);
+            assert_eq!({ entry.write_time }, (13 << 11) | (30 << 5) | 10);
+            assert_eq!({ entry.last_access_date }, date);
+
+            minute.store(45, Ordering::Relaxed);
+            let mut handle = fs.open_file(path.as_path()).await.unwrap();
+            handle.write(b"hello").await.unwrap();
+            handle.close().await.unwrap();
+            let entry = short().await;
+            assert_eq!({ entry.create_time }, (13 << 11) | (30 << 5) | 10);
+            assert_eq!({ entry.write_time }, (13 << 11) | (45 << 5) | 10);
+        });
+    }
+
+    #[test]
+    fn test_read_batch_clusters() {
+        run_task(async {
+            let device = Arc::new(CountingDevice::new(512 * 1024, 512));
+            let fs = Fat32FileSystem::with_format(device.clone()).await.unwrap();
+
+            // 16个簇，新格式化的磁盘上簇是连续分配的
+            let file_path = PathBuf::from_str("test.bin").unwrap();
+            let content = (0..4096 * 16).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+            fs.create_file(file_path.as_path()).await.unwrap();
+            let mut handle = fs.open_file(file_path.as_path()).await.unwrap();
+            handle.write(&content).await.unwrap();
+            handle.move_pointer(0).await.unwrap();
+
+            // 整个文件只需一次批量读盘
+            device.reset();
+            let mut buf = alloc::vec![0; content.len()];
+            assert_eq!(handle.read(&mut buf).await.unwrap(), content.len() as u64);
+            assert_eq!(buf, content);
+            let (single_reads, batch_reads, blocks_read) = device.counts();
+            assert_eq!(batch_reads, 1);
+            assert_eq!(blocks_read - single_reads, 8 * 16);
+            assert!(single_reads < 16);
+
+            // 小范围读取只读所需扇区
+            handle.move_pointer(4096 * 3 + 100).await.unwrap();
+            device.reset();
+            let mut buf = [0; 10];
+            assert_eq!(handle.read(&mut buf).await.unwrap(), 10);
+            assert_eq!(buf, content[4096 * 3 + 100..4096 * 3 + 110]);
+            let (single_reads, batch_reads, blocks_read) = device.counts();
+            assert_eq!(batch_reads, 1);
+            assert_eq!(blocks_read - single_reads, 1);
+
+            handle.close().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_read_cached_position() {
+        run_task(async {
+            let device = Arc::new(CountingDevice::new(512 * 1024, 512));
+            let fs = Fat32FileSystem::with_format(device.clone()).await.unwrap();
+
+            let file_path = PathBuf::from_str("test.bin").unwrap();
+            let content = (0..4096 * 16).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+            fs.create_file(file_path.as_path()).await.unwrap();
+            let mut handle = fs.open_file(file_path.as_path()).await.unwrap();
+            handle.write(&content).await.unwrap();
+            handle.move_pointer(0).await.unwrap();
+
+            // 顺序小块读取，每个簇最多查询一次FAT表
+            device.reset();
+            let mut buf = [0; 512];
+            for chunk in content.chunks(512) {
+                assert_eq!(handle.read(&mut buf).await.unwrap(), 512);
+                assert_eq!(&buf, chunk);
+            }
+            let (single_reads, batch_reads, blocks_read) = device.counts();
+            assert_eq!(batch_reads, 128);
+            assert_eq!(blocks_read - single_reads, 128);
+            assert!(single_reads <= 16);
+
+            // 向前移动指针后仍能正确读取
+            handle.move_pointer(4096 + 7).await.unwrap();
+            assert_eq!(handle.read(&mut buf).await.unwrap(), 512);
+            assert_eq!(buf, content[4096 + 7..4096 + 7 + 512]);
+
+            // 截断后缓存失效
+            handle.move_pointer(4096 * 2).await.unwrap();
+            handle.truncate().await.unwrap();
+            handle.write(&[0x5a; 4096]).await.unwrap();
+            handle.move_pointer(4096 * 2 - 2).await.unwrap();
+            let mut buf = [0; 4];
+            assert_eq!(handle.read(&mut buf).await.unwrap(), 4);
+            assert_eq!(
+                buf,
+                [content[4096 * 2 - 2], content[4096 * 2 - 1], 0x5a, 0x5a]
+            );
+
+            handle.close().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_readahead_sequential() {
+        run_task(async {
+            let device = Arc::new(CountingDevice::new(512 * 2048, 512));
+            let fs = Fat32FileSystem::with_format(device.clone()).await.unwrap();
+            let (spawner, queue) = task_spawner();
+            fs.set_spawner(spawner).await;
+            fs.set_readahead_config(ReadaheadConfig {
+                enabled: true,
+                window: 8,
+                capacity: 64,
+            })
+            .await;
+
+            let file_path = PathBuf::from_str("test.bin").unwrap();
+            let content = (0..4096 * 32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+            fs.create_file(file_path.as_path()).await.unwrap();
+            let mut handle = fs.open_file(file_path.as_path()).await.unwrap();
+            handle.write(&content).await.unwrap();
+            handle.move_pointer(0).await.unwrap();
+
+            // 除第一个簇外，顺序读取的簇均已在后台预读
+            let mut buf = alloc::vec![0; 4096];
+            let mut foreground_reads = 0;
+            for chunk in content.chunks(4096) {
+                device.reset();
+                assert_eq!(handle.read(&mut buf).await.unwrap(), 4096);
+                assert_eq!(buf, chunk);
+                foreground_reads += device.counts().1;
+                run_tasks(&queue).await;
+            }
+            assert_eq!(foreground_reads, 1);
+            assert_eq!(
+                fs.readahead_stats().await,
+                ReadaheadStats {
+                    hits: 31,
+                    misses: 1,
+                    prefetched: 31,
+                    wasted: 0,
+                }
+            );
+
+            // 非顺序读取不会触发预读
+            handle.move_pointer(100).await.unwrap();
+            assert_eq!(handle.read(&mut buf[..10]).await.unwrap(), 10);
+            assert!(queue.lock().unwrap().is_empty());
+
+            handle.close().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_readahead_invalidate() {
+        run_task(async {
+            let device = Arc::new(CountingDevice::new(512 * 2048, 512));
+            let fs = Fat32FileSystem::with_format(device.clone()).await.unwrap();
+            let (spawner, queue) = task_spawner();
+            fs.set_spawner(spawner).await;
+
+            let file_path = PathBuf::from_str("test.bin").unwrap();
+            let content = (0..4096 * 16).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+            fs.create_file(file_path.as_path()).await.unwrap();
+            let mut handle = fs.open_file(file_path.as_path()).await.unwrap();
+            handle.write(&content).await.unwrap();
+            handle.move_pointer(0).await.unwrap();
+
+            // 预读完成前写入，预读的数据被丢弃
+            let mut buf = alloc::vec![0; 4096 * 2];
+            assert_eq!(handle.read(&mut buf[..4096]).await.unwrap(), 4096);
+            handle.move_pointer(4096 * 2).await.unwrap();
+            handle.write(&[0x5a; 100]).await.unwrap();
+            run_tasks(&queue).await;
+            assert_eq!(fs.readahead_stats().await.prefetched, 0);
+
+            handle.move_pointer(4096).await.unwrap();
+            assert_eq!(handle.read(&mut buf).await.unwrap(), 4096 * 2);
+            assert_eq!(buf[..4096], content[4096..4096 * 2]);
+            assert_eq!(buf[4096..4096 + 100], [0x5a; 100]);
+            assert_eq!(buf[4096 + 100..], content[4096 * 2 + 100..4096 * 3]);
+            run_tasks(&queue).await;
+            assert!(fs.readahead_stats().await.prefetched > 0);
+
+            // 写入已预读的簇后，读取到新数据
+            handle.move_pointer(4096 * 4 + 100).await.unwrap();
+            handle.write(&[0xa5; 10]).await.unwrap();
+            handle.move_pointer(4096 * 3).await.unwrap();
+            assert_eq!(handle.read(&mut buf).await.unwrap(), 4096 * 2);
+            assert_eq!(buf[..4096 + 100], content[4096 * 3..4096 * 4 + 100]);
+            assert_eq!(buf[4096 + 100..4096 + 110], [0xa5; 10]);
+            assert_eq!(buf[4096 + 110..], content[4096 * 4 + 110..4096 * 5]);
+
+            let stats = fs.readahead_stats().await;
+            assert!(stats.hits > 0);
+            assert!(stats.wasted > 0);
+            handle.close().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_fat_table_write_back() {
+        run_task(async {
+            let device = Arc::new(CountingDevice::new(512 * 1024, 512));
+            let fs = Fat32FileSystem::with_format(device.clone()).await.unwrap();
+
+            let file_path = PathBuf::from_str("test.bin").unwrap();
+            fs.create_file(file_path.as_path()).await.unwrap();
+            let mut handle = fs.open_file(file_path.as_path()).await.unwrap();
+            handle.write(&[0x5a; 4096 * 4]).await.unwrap();
+            handle.close().await.unwrap();
+
+            // 簇链已缓存，再次遍历无需读盘
+            {
+                let inner = fs.inner.read().await;
+                device.reset();
+                assert_eq!(inner.fat.chain(3).await.unwrap(), [3, 4, 5, 6]);
+                assert_eq!(device.counts(), (0, 0, 0));
+            }
+
+            let (fat_start, fat_size) = {
+                let inner = fs.inner.read().await;
+                let fat_start = inner.bpb.reserved_sector_count as u64;
+                (fat_start, inner.bpb.fat_size_32 as u64)
+            };
+            let read_fat = async |copy: u64| {
+                let mut buf = [0; 512];
+                device
+                    .read_block(fat_start + fat_size * copy, &mut buf)
+                    .await
+                    .unwrap();
+                buf
+            };
+            // 写入目录条目前，其引用的簇链已写回
+            assert_eq!(
+                u32::from_le_bytes(read_fat(0).await[12..16].try_into().unwrap()),
+                4
+            );
+
+            // 不影响目录条目的修改在卸载前尚未写回
+            fs.inner
+                .read()
+                .await
+                .fat
+                .set(10, FatEntry(FatEntry::FAT_ENTRY_EOC_START))
+                .await
+                .unwrap();
+            assert_eq!(read_fat(0).await[40..44], [0; 4]);
+
+            // 卸载后每一份FAT表都已写回
+            fs.unmount().await.unwrap();
+            let fat = read_fat(0).await;
+            assert_eq!(fat, read_fat(1).await);
+            assert_eq!(
+                u32::from_le_bytes(fat[40..44].try_into().unwrap()),
+                FatEntry::FAT_ENTRY_EOC_START
+            );
+
+            let fs = Fat32FileSystem::mount(device.clone()).await.unwrap();
+            let file = fs.get_metadata(file_path.as_path()).await.unwrap();
+            assert_eq!(file.allocated_size, 4096 * 4);
+        });
+    }
+
+    #[test]
+    fn test_fat_table_evict() {
+        run_task(async {
+            // 每簇1扇区，FAT表超过缓存容量
+            let device = Arc::new(MemoryDevice::new(512 * 10240, 512));
+            let options = FormatOptions {
+                sectors_per_cluster: 1,
+                ..Default::default()
+            };
+            let fs = Fat32FileSystem::with_format_options(device.clone(), &options)
+                .await
+                .unwrap();
+            let inner = fs.inner.read().await;
+            let sectors = FatTable::CACHE_CAPACITY as u32 + 8;
+            assert!(inner.bpb.fat_size_32 >= sectors);
+
+            for i in 1..sectors {
+                inner.fat.set(i * 128, FatEntry(i)).await.unwrap();
+            }
+            for i in 1..sectors {
+                assert_eq!(inner.fat.get(i * 128).await.unwrap().0, i);
+            }
+            drop(inner);
+            fs.unmount().await.unwrap();
+
+            // 淘汰与卸载时写回的扇区都能重新读出
+            let fs = Fat32FileSystem::mount(device.clone()).await.unwrap();
+            let inner = fs.inner.read().await;
+            for i in 1..sectors {
+                assert_eq!(inner.fat.get(i * 128).await.unwrap().0, i);
+            }
+        });
+    }
+
+    #[test]
+    fn test_write_io_error() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(512 * 1024, 512));
+            let fs = Fat32FileSystem::with_format(device.clone()).await.unwrap();
+
+            let file_path = PathBuf::from_str("test.bin").unwrap();
+            fs.create_file(file_path.as_path()).await.unwrap();
+            let mut handle = fs.open_file(file_path.as_path()).await.unwrap();
+
+            // 数据区不可写时，写入报错
+            device.fail_block_range(0..device.block_count()).await;
+            assert!(matches!(
+                handle.write(&[0x5a; 4096]).await,
+                Err(FileSystemError::IoError(BlockDeviceError::IoError))
+            ));
+
+            device.clear_faults().await;
+            handle.move_pointer(0).await.unwrap();
+            handle.write(&[0x5a; 4096]).await.unwrap();
+            handle.close().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_unmount_io_error() {
+        run_task(async {
+            let device = Arc::new(MemoryDevice::new(512 * 1024, 512));
+            let fs = Fat32FileSystem::with_format(device.clone()).await.unwrap();
+
+            let file_path = PathBuf::from_str("test.bin").unwrap();
+            fs.create_file(file_path.as_path()).await.unwrap();
+            let mut handle = fs.open_file(file_path.as_path()).await.unwrap();
+            handle.write(&[0x5a; 4096 * 3]).await.unwrap();
+            handle.close().await.unwrap();
+            fs.inner
+                .read()
+                .await
+                .fat
+                .set(10, FatEntry(FatEntry::FAT_ENTRY_EOC_START))
+                .await
+                .unwrap();
+
+            // 写回FAT表失败时卸载报错，文件系统仍保持挂载
+            device.fail_nth_write(1).await;
+            assert!(matches!(
+                fs.unmount().await,
+                Err(FileSystemError::IoError(BlockDeviceError::IoError))
+            ));
+
+            // 未写回的修改在重试时写回
+            device.set_tracing(true).await;
+            fs.unmount().await.unwrap();
+            let writes = device
+                .take_trace()
+                .await
+                .into_iter()
+                .filter(|record| matches!(record.operation, DeviceOperation::Write(_)))
+                .count();
+            assert_eq!(writes, 2);
+
+            let fs = Fat32FileSystem::mount(device.clone()).await.unwrap();
+            let file = fs.get_metadata(file_path.as_path()).await.unwrap();
+            assert_eq!(file.allocated_size, 4096 * 3);
+        });
+    }
+
+    #[test]
+    fn test_conformance() {
+        run_task(async {
+            run_conformance(|| async {
+                let device = Arc::new(MemoryDevice::new(512 * 1024, 512));
+                Fat32FileSystem::with_format(device).await.unwrap()
+            })
+            .await;
+        });
+    }
 }
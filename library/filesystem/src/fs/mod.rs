@@ -4,6 +4,12 @@ use async_io::{AsyncRead, Seekable};
 use crate::{BoxFuture, device::BlockDeviceError, path::Path};
 
 pub mod fat32;
+pub mod permission;
+pub mod ramfs;
+pub mod readahead;
+
+#[cfg(test)]
+pub(crate) mod conformance;
 
 /// 文件系统的抽象
 ///
@@ -163,6 +169,11 @@ pub trait FileHandle: Send + Sync + 'static {
     /// 如果文件指针在文件末尾，则写入意味着追加数据
     fn write<'fut>(&'fut mut self, buf: &'fut [u8])
     -> BoxFuture<'fut, Result<(), FileSystemError>>;
+
+    /// 截断文件
+    ///
+    /// 丢弃文件指针之后的全部内容，截断后文件大小等于文件指针位置，文件指针不变
+    fn truncate(&mut self) -> BoxFuture<'_, Result<(), FileSystemError>>;
 }
 
 /// 文件系统错误
@@ -0,0 +1,304 @@
+use alloc::{
+    collections::btree_map::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{
+    fs::{FileSystem, FileSystemError},
+    path::{Path, PathBuf},
+};
+
+/// 超级用户的用户ID，不受权限限制
+pub const ROOT_UID: u32 = 0;
+
+/// 文件的所有者与权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilePermission {
+    // 所有者的用户ID
+    pub uid: u32,
+    // 所有者的组ID
+    pub gid: u32,
+    // 权限位，与unix相同，依次为所有者、组、其他用户的读、写、执行权限
+    pub mode: u16,
+}
+
+impl FilePermission {
+    /// 没有记录的文件的权限
+    ///
+    /// FAT32等文件系统没有所有者信息，为兼容已有的文件，没有记录的文件所有用户均可读写执行
+    pub const DEFAULT: Self = Self {
+        uid: ROOT_UID,
+        gid: ROOT_UID,
+        mode: 0o777,
+    };
+
+    pub const READ: u16 = 0o4;
+    pub const WRITE: u16 = 0o2;
+    pub const EXECUTE: u16 = 0o1;
+    /// 权限位的最大值
+    pub const MODE_MASK: u16 = 0o777;
+
+    /// 判断用户是否具有access指定的全部权限，access为 [`Self::READ`] 等的组合
+    ///
+    /// 超级用户总是具有全部权限
+    pub fn allows(&self, uid: u32, gid: u32, access: u16) -> bool {
+        if uid == ROOT_UID {
+            return true;
+        }
+        let bits = if uid == self.uid {
+            self.mode >> 6
+        } else if gid == self.gid {
+            self.mode >> 3
+        } else {
+            self.mode
+        };
+        bits & access == access
+    }
+}
+
+/// 文件系统中各文件的所有者与权限
+///
+/// 不支持所有者信息的文件系统（如FAT32）将权限记录在根目录的附属文件 [`PermissionTable::SIDECAR`] 中，
+/// 每行为 `<uid> <gid> <八进制权限位> <绝对路径>`。路径为文件在该文件系统中的路径，与挂载位置无关。
+/// FAT32的文件名不区分大小写，路径按ASCII小写记录与比较，以不同大小写访问同一文件时得到相同的权限
+#[derive(Debug, Default)]
+pub struct PermissionTable {
+    // 规范化并转换为小写的路径 -> 权限，权限为DEFAULT的文件不记录
+    entries: BTreeMap<String, FilePermission>,
+}
+
+impl PermissionTable {
+    /// 附属文件的路径
+    pub const SIDECAR: &str = "/.permissions";
+
+    pub const fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// 从附属文件读取权限表，附属文件不存在时返回空表
+    pub async fn load(fs: &dyn FileSystem) -> Result<Self, FileSystemError> {
+        let path = PathBuf::from_str(Self::SIDECAR).unwrap();
+        let mut file = match fs.open_file(path.as_path()).await {
+            Ok(file) => file,
+            Err(FileSystemError::FileNotFound) => return Ok(Self::new()),
+            Err(error) => return Err(error),
+        };
+        let mut content = Vec::new();
+        let mut buffer = [0; 512];
+        let result = loop {
+            match file.read(&mut buffer).await {
+                Ok(0) => break Ok(()),
+                Ok(count) => content.extend_from_slice(&buffer[..count as usize]),
+                Err(error) => break Err(error),
+            }
+        };
+        file.close().await?;
+        result?;
+        Ok(Self::parse(&String::from_utf8_lossy(&content)))
+    }
+
+    /// 将权限表写入附属文件
+    pub async fn save(&self, fs: &dyn FileSystem) -> Result<(), FileSystemError> {
+        let path = PathBuf::from_str(Self::SIDECAR).unwrap();
+        match fs.create_file(path.as_path()).await {
+            Ok(()) | Err(FileSystemError::FileExists) => {}
+            Err(error) => return Err(error),
+        }
+        let mut file = fs.open_file(path.as_path()).await?;
+        let result = match file.write(&self.serialize()).await {
+            Ok(()) => file.truncate().await,
+            Err(error) => Err(error),
+        };
+        file.close().await?;
+        result
+    }
+
+    /// 解析附属文件的内容，无法识别的行被忽略
+    pub fn parse(text: &str) -> Self {
+        let mut table = Self::new();
+        for line in text.lines() {
+            let mut fields = line.splitn(4, ' ');
+            let (Some(uid), Some(gid), Some(mode), Some(path)) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let (Ok(uid), Ok(gid), Ok(mode), Ok(path)) = (
+                uid.parse(),
+                gid.parse(),
+                u16::from_str_radix(mode, 8),
+                PathBuf::from_str(path),
+            ) else {
+                continue;
+            };
+            let mode = mode & FilePermission::MODE_MASK;
+            table.set(path.as_path(), FilePermission { uid, gid, mode });
+        }
+        table
+    }
+
+    /// 生成附属文件的内容
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut content = String::new();
+        for (path, permission) in &self.entries {
+            content.push_str(&format!(
+                "{} {} {:o} {path}\n",
+                permission.uid, permission.gid, permission.mode
+            ));
+        }
+        content.into_bytes()
+    }
+
+    /// 获取文件的权限，没有记录时返回 [`FilePermission::DEFAULT`]
+    pub fn get(&self, path: Path<'_>) -> FilePermission {
+        self.entries
+            .get(&Self::key(path))
+            .copied()
+            .unwrap_or(FilePermission::DEFAULT)
+    }
+
+    /// 设置文件的权限
+    pub fn set(&mut self, path: Path<'_>, permission: FilePermission) {
+        if permission == FilePermission::DEFAULT {
+            self.entries.remove(&Self::key(path));
+        } else {
+            self.entries.insert(Self::key(path), permission);
+        }
+    }
+
+    /// 移除文件的记录，文件被删除时调用
+    pub fn remove(&mut self, path: Path<'_>) {
+        self.entries.remove(&Self::key(path));
+    }
+
+    /// 文件或文件夹被重命名时，将其与其中全部文件的记录移动到新路径
+    pub fn rename(&mut self, old_path: Path<'_>, new_path: Path<'_>) {
+        let old_key = Self::key(old_path);
+        let new_key = Self::key(new_path);
+        let moved: Vec<_> = self
+            .entries
+            .keys()
+            .filter(|key| Self::is_within(key, &old_key))
+            .cloned()
+            .collect();
+        for key in moved {
+            let permission = self.entries.remove(&key).unwrap();
+            self.entries
+                .insert(format!("{new_key}{}", &key[old_key.len()..]), permission);
+        }
+    }
+
+    /// 路径是否为附属文件本身，比较前规范化路径并忽略大小写
+    pub fn is_sidecar(path: Path<'_>) -> bool {
+        Self::key(path) == Self::SIDECAR
+    }
+
+    fn key(path: Path<'_>) -> String {
+        path.to_path_buf()
+            .normalize()
+            .to_string()
+            .to_ascii_lowercase()
+    }
+
+    // key是否为prefix本身或位于prefix中
+    fn is_within(key: &str, prefix: &str) -> bool {
+        match key.strip_prefix(prefix) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/') || prefix == "/",
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        fs::{
+            FileSystem,
+            permission::{FilePermission, PermissionTable},
+            ramfs::RamFileSystem,
+        },
+        path::PathBuf,
+        run_task,
+    };
+
+    fn path(s: &str) -> PathBuf {
+        PathBuf::from_str(s).unwrap()
+    }
+
+    const SYSTEM: FilePermission = FilePermission {
+        uid: 0,
+        gid: 0,
+        mode: 0o755,
+    };
+
+    #[test]
+    fn test_allows() {
+        let permission = FilePermission {
+            uid: 1000,
+            gid: 100,
+            mode: 0o640,
+        };
+        assert!(permission.allows(1000, 1000, FilePermission::READ | FilePermission::WRITE));
+        assert!(permission.allows(1001, 100, FilePermission::READ));
+        assert!(!permission.allows(1001, 100, FilePermission::WRITE));
+        assert!(!permission.allows(1001, 1001, FilePermission::READ));
+        assert!(permission.allows(0, 0, FilePermission::EXECUTE));
+    }
+
+    #[test]
+    fn test_rename_moves_children() {
+        let mut table = PermissionTable::new();
+        table.set(path("/system").as_path(), SYSTEM);
+        table.set(path("/system/init").as_path(), SYSTEM);
+        table.set(path("/systemd").as_path(), SYSTEM);
+        table.rename(path("/system").as_path(), path("/old").as_path());
+        assert_eq!(table.get(path("/old/init").as_path()), SYSTEM);
+        assert_eq!(table.get(path("/old").as_path()), SYSTEM);
+        assert_eq!(table.get(path("/systemd").as_path()), SYSTEM);
+        assert_eq!(
+            table.get(path("/system/init").as_path()),
+            FilePermission::DEFAULT
+        );
+    }
+
+    #[test]
+    fn test_ignores_case() {
+        let mut table = PermissionTable::new();
+        table.set(path("/System/Init").as_path(), SYSTEM);
+        assert_eq!(table.get(path("/SYSTEM/init").as_path()), SYSTEM);
+        table.rename(path("/SYSTEM").as_path(), path("/Old").as_path());
+        assert_eq!(table.get(path("/old/INIT").as_path()), SYSTEM);
+        assert!(PermissionTable::is_sidecar(path("/.PERMISSIONS").as_path()));
+        assert!(PermissionTable::is_sidecar(
+            path("/x/../.permissions").as_path()
+        ));
+    }
+
+    #[test]
+    fn test_save_load() {
+        run_task(async {
+            let fs = RamFileSystem::new(4096).unwrap();
+            assert!(PermissionTable::load(&fs).await.unwrap().entries.is_empty());
+
+            let mut table = PermissionTable::new();
+            table.set(path("/system/my file").as_path(), SYSTEM);
+            table.set(path("/home").as_path(), FilePermission::DEFAULT);
+            table.save(&fs).await.unwrap();
+
+            let table = PermissionTable::load(&fs).await.unwrap();
+            assert_eq!(table.entries.len(), 1);
+            assert_eq!(table.get(path("/system/my file").as_path()), SYSTEM);
+            assert_eq!(
+                PermissionTable::parse("bad line\n1 2 999 /x\n")
+                    .entries
+                    .len(),
+                0
+            );
+            fs.unmount().await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,667 @@
+use alloc::{
+    boxed::Box,
+    string::String,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use async_locks::rwlock::RwLock;
+use try_alloc::{error::AllocError, string::TryToString, vec::TryVec};
+
+use crate::{
+    BoxFuture,
+    fs::{FileHandle, FileMetadata, FileSystem, FileSystemError},
+    path::Path,
+};
+
+/// 基于内存的文件系统（tmpfs）
+///
+/// 文件与目录均保存在内存中，不依赖块设备，卸载或释放后数据即丢失。
+/// 通常用于内核启动早期的根文件系统（由initramfs解包得到）或临时文件。
+///
+/// 文件系统内部以inode表的形式组织：每个文件或目录对应一个inode，目录inode记录子项名称到inode编号的映射。
+/// 文件系统的容量在创建时指定，文件数据的总大小不能超过该容量。
+///
+/// 所有内存分配均为可失败的分配，内存不足时返回 [`FileSystemError::DiskFull`]，而不会触发panic。
+///
+/// 与 [`crate::fs::fat32::Fat32FileSystem`] 一致，同一文件同时只能被打开一次，被打开的文件无法删除。
+pub struct RamFileSystem {
+    inner: Arc<RwLock<RamInner>>,
+}
+
+struct RamInner {
+    // inode表，下标为inode编号，已删除的inode为None，其编号可被复用
+    inodes: Vec<Option<Inode>>,
+    capacity: u64,
+    used: u64,
+    unmounted: bool,
+}
+
+enum Inode {
+    File {
+        data: Vec<u8>,
+        // 是否正被打开
+        occupied: bool,
+    },
+    Directory {
+        entries: Vec<DirectoryEntry>,
+    },
+}
+
+struct DirectoryEntry {
+    name: String,
+    inode: usize,
+}
+
+/// 根目录的inode编号
+const ROOT_INODE: usize = 0;
+
+impl From<AllocError> for FileSystemError {
+    fn from(_: AllocError) -> Self {
+        // 内存文件系统的存储空间即为内存
+        Self::DiskFull
+    }
+}
+
+impl RamFileSystem {
+    /// 创建空的内存文件系统，capacity为文件数据的最大总大小，单位为字节
+    pub fn new(capacity: u64) -> Result<Self, AllocError> {
+        let mut inodes: Vec<_> = TryVec::try_with_capacity(1)?;
+        inodes.push(Some(Inode::Directory {
+            entries: Vec::new(),
+        }));
+
+        Ok(Self {
+            inner: Arc::new(RwLock::new(RamInner {
+                inodes,
+                capacity,
+                used: 0,
+                unmounted: false,
+            })),
+        })
+    }
+}
+
+impl RamInner {
+    fn check_mounted(&self) -> Result<(), FileSystemError> {
+        if self.unmounted {
+            return Err(FileSystemError::Unmounted);
+        }
+        Ok(())
+    }
+
+    fn inode(&self, inode: usize) -> &Inode {
+        self.inodes[inode]
+            .as_ref()
+            .expect("codebug: inode is referenced after removed")
+    }
+
+    fn inode_mut(&mut self, inode: usize) -> &mut Inode {
+        self.inodes[inode]
+            .as_mut()
+            .expect("codebug: inode is referenced after removed")
+    }
+
+    fn entries_mut(&mut self, inode: usize) -> &mut Vec<DirectoryEntry> {
+        match self.inode_mut(inode) {
+            Inode::Directory { entries } => entries,
+            Inode::File { .. } => unreachable!("codebug: inode {inode} is not a directory"),
+        }
+    }
+
+    /// 查找路径对应的inode编号
+    fn lookup(&self, path: Path<'_>) -> Result<usize, FileSystemError> {
+        let mut inode = ROOT_INODE;
+        for segment in path.iter() {
+            let Inode::Directory { entries } = self.inode(inode) else {
+                return Err(FileSystemError::FileNotFound);
+            };
+            inode = entries
+                .iter()
+                .find(|entry| entry.name == segment)
+                .ok_or(FileSystemError::FileNotFound)?
+                .inode;
+        }
+        Ok(inode)
+    }
+
+    /// 查找路径的父目录，返回父目录inode编号及最后一级名称
+    fn lookup_parent<'p>(&self, path: &'p Path<'_>) -> Result<(usize, &'p str), FileSystemError> {
+        let name = path.last_segment().ok_or(FileSystemError::FileExists)?;
+        let parent = self.lookup(path.parent())?;
+        if !matches!(self.inode(parent), Inode::Directory { .. }) {
+            return Err(FileSystemError::FileNotFound);
+        }
+        Ok((parent, name))
+    }
+
+    fn contains(&mut self, directory: usize, name: &str) -> bool {
+        self.entries_mut(directory)
+            .iter()
+            .any(|entry| entry.name == name)
+    }
+
+    /// 从目录中移除子项，返回子项的inode编号
+    fn unlink(&mut self, directory: usize, name: &str) -> usize {
+        let entries = self.entries_mut(directory);
+        let index = entries
+            .iter()
+            .position(|entry| entry.name == name)
+            .expect("codebug: unlink entry not exists");
+        entries.swap_remove(index).inode
+    }
+
+    /// 向目录中添加子项
+    fn link(&mut self, directory: usize, name: &str, inode: usize) -> Result<(), AllocError> {
+        let owned_name = name.try_to_string()?;
+        self.entries_mut(directory).try_push(DirectoryEntry {
+            name: owned_name,
+            inode,
+        })
+    }
+
+    /// 在目录下创建新的inode
+    fn create(&mut self, path: Path<'_>, inode: Inode) -> Result<(), FileSystemError> {
+        self.check_mounted()?;
+        let (parent, name) = self.lookup_parent(&path)?;
+        if self.contains(parent, name) {
+            return Err(FileSystemError::FileExists);
+        }
+
+        let id = match self.inodes.iter().position(Option::is_none) {
+            Some(id) => {
+                self.inodes[id] = Some(inode);
+                id
+            }
+            None => {
+                self.inodes.try_push(Some(inode))?;
+                self.inodes.len() - 1
+            }
+        };
+        if let Err(error) = self.link(parent, name, id) {
+            self.inodes[id] = None;
+            return Err(error.into());
+        }
+        Ok(())
+    }
+
+    fn metadata(&self, name: &str, inode: usize) -> Result<FileMetadata, AllocError> {
+        let owned_name = name.try_to_string()?;
+        Ok(match self.inode(inode) {
+            Inode::File { data, .. } => FileMetadata {
+                name: owned_name,
+                size: data.len() as u64,
+                is_directory: false,
+                allocated_size: data.capacity() as u64,
+            },
+            Inode::Directory { .. } => FileMetadata {
+                name: owned_name,
+                size: 0,
+                is_directory: true,
+                allocated_size: 0,
+            },
+        })
+    }
+}
+
+impl FileSystem for RamFileSystem {
+    fn total_space(&self) -> BoxFuture<'_, Result<u64, FileSystemError>> {
+        Box::pin(async {
+            let inner = self.inner.read().await;
+            inner.check_mounted()?;
+            Ok(inner.capacity)
+        })
+    }
+
+    fn free_space(&self) -> BoxFuture<'_, Result<u64, FileSystemError>> {
+        Box::pin(async {
+            let inner = self.inner.read().await;
+            inner.check_mounted()?;
+            Ok(inner.capacity - inner.used)
+        })
+    }
+
+    fn create_file<'fut>(
+        &'fut self,
+        path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async move {
+            let mut inner = self.inner.write().await;
+            inner.create(
+                path,
+                Inode::File {
+                    data: Vec::new(),
+                    occupied: false,
+                },
+            )
+        })
+    }
+
+    fn create_directory<'fut>(
+        &'fut self,
+        path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async move {
+            let mut inner = self.inner.write().await;
+            inner.create(
+                path,
+                Inode::Directory {
+                    entries: Vec::new(),
+                },
+            )
+        })
+    }
+
+    fn open_file<'fut>(
+        &'fut self,
+        path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<Box<dyn FileHandle>, FileSystemError>> {
+        Box::pin(async move {
+            let mut inner = self.inner.write().await;
+            inner.check_mounted()?;
+
+            let inode = inner.lookup(path)?;
+            let Inode::File { occupied, .. } = inner.inode_mut(inode) else {
+                return Err(FileSystemError::FileTypeMismatch);
+            };
+
+            // 文件占用检查 & 添加占用
+            if *occupied {
+                return Err(FileSystemError::FileOccupied);
+            }
+            *occupied = true;
+
+            Ok(Box::new(RamFileHandle {
+                inner: Arc::downgrade(&self.inner),
+                inode,
+                pointer: 0,
+                closed: false,
+            }) as Box<dyn FileHandle>)
+        })
+    }
+
+    fn delete_file<'fut>(
+        &'fut self,
+        path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async move {
+            let mut inner = self.inner.write().await;
+            inner.check_mounted()?;
+
+            let inode = inner.lookup(path)?;
+            let Inode::File { data, occupied } = inner.inode(inode) else {
+                return Err(FileSystemError::FileTypeMismatch);
+            };
+            if *occupied {
+                return Err(FileSystemError::FileOccupied);
+            }
+            let size = data.len() as u64;
+
+            let (parent, name) = inner.lookup_parent(&path)?;
+            inner.unlink(parent, name);
+            inner.inodes[inode] = None;
+            inner.used -= size;
+            Ok(())
+        })
+    }
+
+    fn delete_directory<'fut>(
+        &'fut self,
+        path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async move {
+            let mut inner = self.inner.write().await;
+            inner.check_mounted()?;
+
+            // 不允许删除根目录
+            if path.is_root() {
+                return Err(FileSystemError::OperationNotSupport);
+            }
+
+            let inode = inner.lookup(path)?;
+            let Inode::Directory { entries } = inner.inode(inode) else {
+                return Err(FileSystemError::FileTypeMismatch);
+            };
+            if !entries.is_empty() {
+                return Err(FileSystemError::FileExists);
+            }
+
+            let (parent, name) = inner.lookup_parent(&path)?;
+            inner.unlink(parent, name);
+            inner.inodes[inode] = None;
+            Ok(())
+        })
+    }
+
+    fn rename<'fut>(
+        &'fut self,
+        old_path: Path<'fut>,
+        new_path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async move {
+            let mut inner = self.inner.write().await;
+            inner.check_mounted()?;
+
+            if old_path.is_root() || new_path.is_root() {
+                return Err(FileSystemError::OperationNotSupport);
+            }
+            let inode = inner.lookup(old_path)?;
+            let (new_parent, new_name) = inner.lookup_parent(&new_path)?;
+            if inner.contains(new_parent, new_name) {
+                return Err(FileSystemError::FileExists);
+            }
+
+            // 不允许将目录移动到其自身的子目录中
+            if matches!(inner.inode(inode), Inode::Directory { .. })
+                && new_path.strip_prefix(old_path).is_some()
+            {
+                return Err(FileSystemError::OperationNotSupport);
+            }
+
+            // 先添加新的目录项，失败时文件系统保持不变
+            inner.link(new_parent, new_name, inode)?;
+            let (old_parent, old_name) = inner.lookup_parent(&old_path)?;
+            inner.unlink(old_parent, old_name);
+            Ok(())
+        })
+    }
+
+    fn get_metadata<'fut>(
+        &'fut self,
+        path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<FileMetadata, FileSystemError>> {
+        Box::pin(async move {
+            let inner = self.inner.read().await;
+            inner.check_mounted()?;
+
+            let inode = inner.lookup(path)?;
+            Ok(inner.metadata(path.last_segment().unwrap_or(""), inode)?)
+        })
+    }
+
+    fn list_directory<'fut>(
+        &'fut self,
+        path: Path<'fut>,
+    ) -> BoxFuture<'fut, Result<Vec<FileMetadata>, FileSystemError>> {
+        Box::pin(async move {
+            let inner = self.inner.read().await;
+            inner.check_mounted()?;
+
+            let inode = inner.lookup(path)?;
+            let Inode::Directory { entries } = inner.inode(inode) else {
+                return Err(FileSystemError::FileTypeMismatch);
+            };
+
+            let mut result: Vec<_> = TryVec::try_with_capacity(entries.len())?;
+            for entry in entries {
+                result.push(inner.metadata(&entry.name, entry.inode)?);
+            }
+            Ok(result)
+        })
+    }
+
+    fn unmount(&self) -> BoxFuture<'_, Result<(), FileSystemError>> {
+        Box::pin(async {
+            let mut inner = self.inner.write().await;
+            inner.check_mounted()?;
+            inner.unmounted = true;
+            Ok(())
+        })
+    }
+}
+
+struct RamFileHandle {
+    inner: Weak<RwLock<RamInner>>,
+    inode: usize,
+    pointer: u64,
+    closed: bool,
+}
+
+impl RamFileHandle {
+    fn filesystem(&self) -> Result<Arc<RwLock<RamInner>>, FileSystemError> {
+        if self.closed {
+            return Err(FileSystemError::FileClosed);
+        }
+        self.inner.upgrade().ok_or(FileSystemError::Unmounted)
+    }
+}
+
+impl FileHandle for RamFileHandle {
+    fn close(&mut self) -> BoxFuture<'_, Result<(), FileSystemError>> {
+        Box::pin(async move {
+            let inner = self.filesystem()?;
+            let mut inner = inner.write().await;
+
+            // 取消占用
+            if let Inode::File { occupied, .. } = inner.inode_mut(self.inode) {
+                *occupied = false;
+            }
+
+            self.inner = Weak::new();
+            self.closed = true;
+            Ok(())
+        })
+    }
+
+    fn move_pointer(&mut self, position: u64) -> BoxFuture<'_, Result<(), FileSystemError>> {
+        Box::pin(async move {
+            let inner = self.filesystem()?;
+            let inner = inner.read().await;
+            inner.check_mounted()?;
+
+            let Inode::File { data, .. } = inner.inode(self.inode) else {
+                unreachable!("codebug: opened inode is not a file");
+            };
+            self.pointer = position.min(data.len() as u64);
+            Ok(())
+        })
+    }
+
+    fn get_pointer(&mut self) -> BoxFuture<'_, Result<u64, FileSystemError>> {
+        Box::pin(async move {
+            if self.closed {
+                return Err(FileSystemError::FileClosed);
+            }
+
+            Ok(self.pointer)
+        })
+    }
+
+    fn read<'fut>(
+        &'fut mut self,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<u64, FileSystemError>> {
+        Box::pin(async move {
+            let inner = self.filesystem()?;
+            let inner = inner.read().await;
+            inner.check_mounted()?;
+
+            let Inode::File { data, .. } = inner.inode(self.inode) else {
+                unreachable!("codebug: opened inode is not a file");
+            };
+            let start = (self.pointer as usize).min(data.len());
+            let len = buf.len().min(data.len() - start);
+            buf[..len].copy_from_slice(&data[start..start + len]);
+            self.pointer += len as u64;
+            Ok(len as u64)
+        })
+    }
+
+    fn write<'fut>(
+        &'fut mut self,
+        buf: &'fut [u8],
+    ) -> BoxFuture<'fut, Result<(), FileSystemError>> {
+        Box::pin(async move {
+            let inner = self.filesystem()?;
+            let mut inner = inner.write().await;
+            inner.check_mounted()?;
+
+            let pointer = self.pointer as usize;
+            let Inode::File { data, .. } = inner.inode(self.inode) else {
+                unreachable!("codebug: opened inode is not a file");
+            };
+            let end = pointer
+                .checked_add(buf.len())
+                .ok_or(FileSystemError::FileTooLarge)?;
+            let grow = end.saturating_sub(data.len()) as u64;
+            if grow > inner.capacity - inner.used {
+                return Err(FileSystemError::DiskFull);
+            }
+
+            let Inode::File { data, .. } = inner.inode_mut(self.inode) else {
+                unreachable!("codebug: opened inode is not a file");
+            };
+            if end > data.len() {
+                data.try_reserve(end - data.len())
+                    .map_err(|_| FileSystemError::DiskFull)?;
+                data.resize(end, 0);
+            }
+            data[pointer..end].copy_from_slice(buf);
+            inner.used += grow;
+            self.pointer = end as u64;
+            Ok(())
+        })
+    }
+
+    fn truncate(&mut self) -> BoxFuture<'_, Result<(), FileSystemError>> {
+        Box::pin(async move {
+            let inner = self.filesystem()?;
+            let mut inner = inner.write().await;
+            inner.check_mounted()?;
+
+            let Inode::File { data, .. } = inner.inode_mut(self.inode) else {
+                unreachable!("codebug: opened inode is not a file");
+            };
+            let shrink = data.len().saturating_sub(self.pointer as usize) as u64;
+            data.truncate(self.pointer as usize);
+            inner.used -= shrink;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        fs::{FileSystem, FileSystemError, conformance::run_conformance, ramfs::RamFileSystem},
+        path::PathBuf,
+        run_task,
+    };
+
+    #[test]
+    fn test_write_read_file() {
+        run_task(async {
+            let fs = RamFileSystem::new(1024).unwrap();
+            let path = PathBuf::from_str("/test.txt").unwrap();
+            fs.create_file(path.as_path()).await.unwrap();
+
+            let mut file = fs.open_file(path.as_path()).await.unwrap();
+            file.write(b"hello world").await.unwrap();
+            file.move_pointer(6).await.unwrap();
+            file.write(b"ramfs").await.unwrap();
+            file.move_pointer(0).await.unwrap();
+            let mut buf = [0u8; 32];
+            let len = file.read(&mut buf).await.unwrap();
+            assert_eq!(&buf[..len as usize], b"hello ramfs");
+            file.close().await.unwrap();
+
+            assert_eq!(fs.free_space().await.unwrap(), 1024 - 11);
+        });
+    }
+
+    #[test]
+    fn test_directory() {
+        run_task(async {
+            let fs = RamFileSystem::new(1024).unwrap();
+            let dir = PathBuf::from_str("/dir").unwrap();
+            let file = PathBuf::from_str("/dir/file").unwrap();
+            fs.create_directory(dir.as_path()).await.unwrap();
+            fs.create_file(file.as_path()).await.unwrap();
+
+            let list = fs.list_directory(dir.as_path()).await.unwrap();
+            assert_eq!(list.len(), 1);
+            assert_eq!(list[0].name, "file");
+            assert!(!list[0].is_directory);
+
+            assert!(matches!(
+                fs.delete_directory(dir.as_path()).await,
+                Err(FileSystemError::FileExists)
+            ));
+            fs.delete_file(file.as_path()).await.unwrap();
+            fs.delete_directory(dir.as_path()).await.unwrap();
+            assert!(matches!(
+                fs.get_metadata(dir.as_path()).await,
+                Err(FileSystemError::FileNotFound)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_rename() {
+        run_task(async {
+            let fs = RamFileSystem::new(1024).unwrap();
+            let dir = PathBuf::from_str("/dir").unwrap();
+            let old = PathBuf::from_str("/a").unwrap();
+            let new = PathBuf::from_str("/dir/b").unwrap();
+            fs.create_directory(dir.as_path()).await.unwrap();
+            fs.create_file(old.as_path()).await.unwrap();
+
+            fs.rename(old.as_path(), new.as_path()).await.unwrap();
+            assert!(!fs.get_metadata(new.as_path()).await.unwrap().is_directory);
+            assert!(matches!(
+                fs.open_file(old.as_path()).await,
+                Err(FileSystemError::FileNotFound)
+            ));
+
+            // 不能将目录移动到自身中
+            let inside = PathBuf::from_str("/dir/inside").unwrap();
+            assert!(matches!(
+                fs.rename(dir.as_path(), inside.as_path()).await,
+                Err(FileSystemError::OperationNotSupport)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_disk_full() {
+        run_task(async {
+            let fs = RamFileSystem::new(8).unwrap();
+            let path = PathBuf::from_str("/test.txt").unwrap();
+            fs.create_file(path.as_path()).await.unwrap();
+
+            let mut file = fs.open_file(path.as_path()).await.unwrap();
+            file.write(b"12345678").await.unwrap();
+            assert!(matches!(
+                file.write(b"9").await,
+                Err(FileSystemError::DiskFull)
+            ));
+            file.close().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_unmount() {
+        run_task(async {
+            let fs = RamFileSystem::new(1024).unwrap();
+            let path = PathBuf::from_str("/test.txt").unwrap();
+            fs.create_file(path.as_path()).await.unwrap();
+            let mut file = fs.open_file(path.as_path()).await.unwrap();
+
+            fs.unmount().await.unwrap();
+            assert!(matches!(
+                fs.create_file(path.as_path()).await,
+                Err(FileSystemError::Unmounted)
+            ));
+            assert!(matches!(
+                file.write(b"data").await,
+                Err(FileSystemError::Unmounted)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_conformance() {
+        run_task(async {
+            run_conformance(|| async { RamFileSystem::new(64 * 1024).unwrap() }).await;
+        });
+    }
+}
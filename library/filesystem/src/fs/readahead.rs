@@ -0,0 +1,273 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    vec::Vec,
+};
+use async_locks::mutex::Mutex;
+
+use crate::{BoxFuture, device::BlockDevice};
+
+/// 启动后台任务的函数，由文件系统的使用方提供，如内核的异步运行时
+pub type Spawner = dyn Fn(BoxFuture<'static, ()>) + Send + Sync;
+
+/// 预读参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadaheadConfig {
+    /// 是否启用预读
+    pub enabled: bool,
+    /// 预读窗口，即顺序读取时提前读取的单元（簇）数量
+    pub window: u32,
+    /// 缓存中最多保存的单元数量，超出时淘汰最久未访问的单元
+    pub capacity: u32,
+}
+
+impl Default for ReadaheadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window: 16,
+            capacity: 64,
+        }
+    }
+}
+
+/// 预读统计信息，单位均为单元（簇）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadaheadStats {
+    /// 读取时命中预读缓存的数量
+    pub hits: u64,
+    /// 读取时未命中、直接从设备读取的数量
+    pub misses: u64,
+    /// 预读的数量
+    pub prefetched: u64,
+    /// 预读后未被使用即被淘汰或失效的数量
+    pub wasted: u64,
+}
+
+/// 预读管理器
+///
+/// 以固定块数的单元（对FAT32而言为簇）缓存预读的数据，键为单元的起始块号。预读由调用方发起，
+/// 在 [`Spawner`] 启动的后台任务中读取设备，未设置 [`Spawner`] 时不进行预读。
+///
+/// 单元中的数据被修改时，调用方需调用 [`Readahead::invalidate`]；无法确定修改范围时调用 [`Readahead::clear`]。
+/// 失效时仍在进行的预读不会将数据放入缓存。
+pub(crate) struct Readahead {
+    device: Arc<dyn BlockDevice>,
+    // 每个单元的块数
+    unit_blocks: u64,
+    state: Mutex<ReadaheadState>,
+}
+
+struct ReadaheadState {
+    config: ReadaheadConfig,
+    stats: ReadaheadStats,
+    spawner: Option<Arc<Spawner>>,
+    // 已预读的单元，键为起始块号
+    units: BTreeMap<u64, CachedUnit>,
+    // 正在后台读取的单元
+    pending: BTreeSet<u64>,
+    // 每次失效时递增，后台读取完成时据此丢弃失效前读取的数据
+    generation: u64,
+    // 逻辑时钟，用于LRU淘汰
+    clock: u64,
+}
+
+struct CachedUnit {
+    data: Vec<u8>,
+    last_access: u64,
+    // 是否被读取过，用于统计浪费的预读
+    used: bool,
+}
+
+impl ReadaheadState {
+    /// 淘汰最久未访问的单元，直到不超过容量
+    fn evict(&mut self) {
+        while self.units.len() > self.config.capacity as usize {
+            let Some(&oldest) = self
+                .units
+                .iter()
+                .min_by_key(|(_, unit)| unit.last_access)
+                .map(|(block, _)| block)
+            else {
+                break;
+            };
+            self.remove(oldest);
+        }
+    }
+
+    fn remove(&mut self, block: u64) {
+        if let Some(unit) = self.units.remove(&block)
+            && !unit.used
+        {
+            self.stats.wasted += 1;
+        }
+    }
+
+    fn clear(&mut self) {
+        while let Some(&block) = self.units.keys().next() {
+            self.remove(block);
+        }
+    }
+}
+
+impl Readahead {
+    pub(crate) fn new(device: Arc<dyn BlockDevice>, unit_blocks: u64) -> Self {
+        Self {
+            device,
+            unit_blocks,
+            state: Mutex::new(ReadaheadState {
+                config: ReadaheadConfig::default(),
+                stats: ReadaheadStats::default(),
+                spawner: None,
+                units: BTreeMap::new(),
+                pending: BTreeSet::new(),
+                generation: 0,
+                clock: 0,
+            }),
+        }
+    }
+
+    pub(crate) async fn set_spawner(&self, spawner: Option<Arc<Spawner>>) {
+        self.state.lock().await.spawner = spawner;
+    }
+
+    pub(crate) async fn set_config(&self, config: ReadaheadConfig) {
+        let mut state = self.state.lock().await;
+        state.config = config;
+        if config.enabled {
+            state.evict();
+        } else {
+            state.clear();
+        }
+    }
+
+    pub(crate) async fn stats(&self) -> ReadaheadStats {
+        self.state.lock().await.stats
+    }
+
+    /// 预读窗口，未启用预读或未设置 [`Spawner`] 时返回None
+    pub(crate) async fn window(&self) -> Option<u32> {
+        let state = self.state.lock().await;
+        (state.config.enabled && state.config.window > 0 && state.spawner.is_some())
+            .then_some(state.config.window)
+    }
+
+    /// 单元是否已在缓存中
+    pub(crate) async fn contains(&self, unit: u64) -> bool {
+        self.state.lock().await.units.contains_key(&unit)
+    }
+
+    /// 从缓存中读取单元内从`first`开始的`count`个块，未命中时返回false
+    pub(crate) async fn read(&self, unit: u64, first: u64, count: u64, buf: &mut [u8]) -> bool {
+        let mut state = self.state.lock().await;
+        state.clock += 1;
+        let clock = state.clock;
+        let Some(cached) = state.units.get_mut(&unit) else {
+            return false;
+        };
+        let block_size = self.device.block_size() as usize;
+        let start = first as usize * block_size;
+        let length = count as usize * block_size;
+        buf[..length].copy_from_slice(&cached.data[start..start + length]);
+        cached.last_access = clock;
+        cached.used = true;
+        state.stats.hits += 1;
+        true
+    }
+
+    /// 记录未命中缓存、直接从设备读取的单元数量
+    pub(crate) async fn record_misses(&self, count: u64) {
+        self.state.lock().await.stats.misses += count;
+    }
+
+    /// 在后台预读单元，已缓存或正在预读的单元会被跳过
+    ///
+    /// 起始块号连续的单元合并为一次读取。预读是尽力而为的，读取失败时直接丢弃
+    pub(crate) async fn prefetch(self: &Arc<Self>, units: &[u64]) {
+        let (spawner, generation, runs) = {
+            let mut state = self.state.lock().await;
+            let Some(spawner) = state.spawner.clone() else {
+                return;
+            };
+            let mut runs: Vec<(u64, u64)> = Vec::new();
+            for &unit in units {
+                if state.units.contains_key(&unit) || !state.pending.insert(unit) {
+                    continue;
+                }
+                match runs.last_mut() {
+                    Some((start, count)) if *start + *count * self.unit_blocks == unit => {
+                        *count += 1
+                    }
+                    _ => runs.push((unit, 1)),
+                }
+            }
+            (spawner, state.generation, runs)
+        };
+        if runs.is_empty() {
+            return;
+        }
+
+        let readahead = self.clone();
+        spawner(alloc::boxed::Box::pin(async move {
+            for (start, count) in runs {
+                readahead.fetch(generation, start, count).await;
+            }
+        }));
+    }
+
+    /// 读取连续的单元并放入缓存
+    async fn fetch(&self, generation: u64, start: u64, count: u64) {
+        let unit_size = (self.unit_blocks * self.device.block_size()) as usize;
+        let mut data = Vec::new();
+        let result = match data.try_reserve_exact(unit_size * count as usize) {
+            Ok(()) => {
+                data.resize(unit_size * count as usize, 0);
+                self.device
+                    .read_blocks(start, self.unit_blocks * count, &mut data)
+                    .await
+                    .is_ok()
+            }
+            Err(_) => false,
+        };
+
+        let mut state = self.state.lock().await;
+        let units = (0..count).map(|i| start + i * self.unit_blocks);
+        for (i, unit) in units.enumerate() {
+            state.pending.remove(&unit);
+            if !result || state.generation != generation || !state.config.enabled {
+                continue;
+            }
+            state.clock += 1;
+            let cached = CachedUnit {
+                data: data[i * unit_size..(i + 1) * unit_size].to_vec(),
+                last_access: state.clock,
+                used: false,
+            };
+            state.units.insert(unit, cached);
+            state.stats.prefetched += 1;
+        }
+        state.evict();
+    }
+
+    /// 使与`block..block + count`重叠的单元失效
+    pub(crate) async fn invalidate(&self, block: u64, count: u64) {
+        let mut state = self.state.lock().await;
+        state.generation += 1;
+        let first = block.saturating_sub(self.unit_blocks - 1);
+        let overlapped = state
+            .units
+            .range(first..block + count)
+            .map(|(&unit, _)| unit)
+            .collect::<Vec<_>>();
+        for unit in overlapped {
+            state.remove(unit);
+        }
+    }
+
+    /// 使所有单元失效
+    pub(crate) async fn clear(&self) {
+        let mut state = self.state.lock().await;
+        state.generation += 1;
+        state.clear();
+    }
+}
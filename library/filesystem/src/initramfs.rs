@@ -0,0 +1,318 @@
+//! initramfs镜像格式
+//!
+//! initramfs是一个简单的归档格式，由build-scripts生成并附加在内核镜像之后，
+//! 内核启动时将其解包到内存文件系统中，作为磁盘不可用时的根文件系统。
+//!
+//! 镜像由头部和若干条目组成，所有整数均为小端序：
+//!
+//! ```txt
+//! 头部（16字节）:   magic[8] | version: u32 | entry_count: u32
+//! 条目头部（16字节）: kind: u8 | reserved: u8 | path_len: u16 | reserved: u32 | data_len: u64
+//! 条目内容:         path[path_len] | data[data_len] | 填充至8字节对齐
+//! ```
+//!
+//! 条目按顺序解包，目录条目必须出现在其子项之前。
+
+use alloc::vec::Vec;
+
+use crate::{
+    fs::{FileSystem, FileSystemError},
+    path::PathBuf,
+};
+
+/// 镜像头部的魔数
+pub const MAGIC: [u8; 8] = *b"COSINITR";
+/// 当前镜像格式版本
+pub const VERSION: u32 = 1;
+
+const HEADER_SIZE: usize = 16;
+const ENTRY_HEADER_SIZE: usize = 16;
+const ALIGN: usize = 8;
+
+const KIND_FILE: u8 = 0;
+const KIND_DIRECTORY: u8 = 1;
+
+/// 条目类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Directory,
+}
+
+/// 镜像中的条目
+#[derive(Debug, Clone, Copy)]
+pub struct Entry<'a> {
+    /// 绝对路径，以 `/` 分隔
+    pub path: &'a str,
+    pub kind: EntryKind,
+    /// 文件内容，目录条目为空
+    pub data: &'a [u8],
+}
+
+/// initramfs错误
+#[derive(Debug)]
+pub enum InitramfsError {
+    /// 魔数或版本不匹配
+    BadMagic,
+    /// 镜像被截断或条目格式错误
+    Malformed,
+    /// 解包时文件系统操作失败
+    FileSystem(FileSystemError),
+}
+
+impl From<FileSystemError> for InitramfsError {
+    fn from(value: FileSystemError) -> Self {
+        Self::FileSystem(value)
+    }
+}
+
+/// 已解析的initramfs镜像
+pub struct Initramfs<'a> {
+    entry_count: u32,
+    entries: &'a [u8],
+}
+
+impl<'a> Initramfs<'a> {
+    /// 解析镜像头部
+    ///
+    /// data可以比镜像更长，多余的部分会被忽略
+    pub fn parse(data: &'a [u8]) -> Result<Self, InitramfsError> {
+        if data.len() < HEADER_SIZE || data[..8] != MAGIC {
+            return Err(InitramfsError::BadMagic);
+        }
+        if read_u32(&data[8..12]) != VERSION {
+            return Err(InitramfsError::BadMagic);
+        }
+
+        Ok(Self {
+            entry_count: read_u32(&data[12..16]),
+            entries: &data[HEADER_SIZE..],
+        })
+    }
+
+    /// 条目数量
+    pub fn len(&self) -> usize {
+        self.entry_count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entry_count == 0
+    }
+
+    /// 遍历镜像中的条目
+    pub fn entries(&self) -> Entries<'a> {
+        Entries {
+            remain: self.entry_count,
+            data: self.entries,
+        }
+    }
+
+    /// 将镜像中的全部条目解包到文件系统中
+    ///
+    /// 父目录需已存在于文件系统中，或在镜像中先于子项出现
+    pub async fn unpack(&self, fs: &dyn FileSystem) -> Result<(), InitramfsError> {
+        for entry in self.entries() {
+            let entry = entry?;
+            let path = PathBuf::from_str(entry.path).map_err(|_| InitramfsError::Malformed)?;
+            match entry.kind {
+                EntryKind::Directory => match fs.create_directory(path.as_path()).await {
+                    // 允许目录重复出现（如根目录）
+                    Ok(()) | Err(FileSystemError::FileExists) => {}
+                    Err(error) => return Err(error.into()),
+                },
+                EntryKind::File => {
+                    fs.create_file(path.as_path()).await?;
+                    let mut file = fs.open_file(path.as_path()).await?;
+                    let result = file.write(entry.data).await;
+                    file.close().await?;
+                    result?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 条目迭代器，由 [`Initramfs::entries`] 创建
+pub struct Entries<'a> {
+    remain: u32,
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Result<Entry<'a>, InitramfsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remain == 0 {
+            return None;
+        }
+        self.remain -= 1;
+
+        let entry = self.parse_entry();
+        if entry.is_err() {
+            // 格式错误后不再继续解析
+            self.remain = 0;
+        }
+        Some(entry)
+    }
+}
+
+impl<'a> Entries<'a> {
+    fn parse_entry(&mut self) -> Result<Entry<'a>, InitramfsError> {
+        let data = self.data;
+        if data.len() < ENTRY_HEADER_SIZE {
+            return Err(InitramfsError::Malformed);
+        }
+        let kind = match data[0] {
+            KIND_FILE => EntryKind::File,
+            KIND_DIRECTORY => EntryKind::Directory,
+            _ => return Err(InitramfsError::Malformed),
+        };
+        let path_len = u16::from_le_bytes([data[2], data[3]]) as usize;
+        let data_len =
+            usize::try_from(read_u64(&data[8..16])).map_err(|_| InitramfsError::Malformed)?;
+
+        let path_end = ENTRY_HEADER_SIZE + path_len;
+        let data_end = path_end
+            .checked_add(data_len)
+            .filter(|&end| end <= data.len())
+            .ok_or(InitramfsError::Malformed)?;
+        let path = str::from_utf8(&data[ENTRY_HEADER_SIZE..path_end])
+            .map_err(|_| InitramfsError::Malformed)?;
+
+        self.data = &data[align_up(data_end).min(data.len())..];
+        Ok(Entry {
+            path,
+            kind,
+            data: &data[path_end..data_end],
+        })
+    }
+}
+
+/// initramfs镜像构造器，供build-scripts等工具使用
+#[derive(Default)]
+pub struct InitramfsBuilder {
+    entry_count: u32,
+    entries: Vec<u8>,
+}
+
+impl InitramfsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加目录
+    pub fn add_directory(&mut self, path: &str) {
+        self.add_entry(KIND_DIRECTORY, path, &[]);
+    }
+
+    /// 添加文件
+    pub fn add_file(&mut self, path: &str, data: &[u8]) {
+        self.add_entry(KIND_FILE, path, data);
+    }
+
+    /// 生成镜像
+    pub fn build(self) -> Vec<u8> {
+        let mut image = Vec::with_capacity(HEADER_SIZE + self.entries.len());
+        image.extend_from_slice(&MAGIC);
+        image.extend_from_slice(&VERSION.to_le_bytes());
+        image.extend_from_slice(&self.entry_count.to_le_bytes());
+        image.extend_from_slice(&self.entries);
+        image
+    }
+
+    fn add_entry(&mut self, kind: u8, path: &str, data: &[u8]) {
+        let path_len = u16::try_from(path.len()).expect("initramfs path is too long");
+
+        self.entries.push(kind);
+        self.entries.push(0);
+        self.entries.extend_from_slice(&path_len.to_le_bytes());
+        self.entries.extend_from_slice(&[0; 4]);
+        self.entries
+            .extend_from_slice(&(data.len() as u64).to_le_bytes());
+        self.entries.extend_from_slice(path.as_bytes());
+        self.entries.extend_from_slice(data);
+        self.entries.resize(align_up(self.entries.len()), 0);
+        self.entry_count += 1;
+    }
+}
+
+fn align_up(value: usize) -> usize {
+    value.div_ceil(ALIGN) * ALIGN
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes.try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes.try_into().unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        fs::{FileSystem, ramfs::RamFileSystem},
+        initramfs::{EntryKind, Initramfs, InitramfsBuilder, InitramfsError},
+        path::PathBuf,
+        run_task,
+    };
+
+    #[test]
+    fn test_parse_entries() {
+        let mut builder = InitramfsBuilder::new();
+        builder.add_directory("/system");
+        builder.add_file("/system/init", b"init binary");
+        let image = builder.build();
+
+        let initramfs = Initramfs::parse(&image).unwrap();
+        assert_eq!(initramfs.len(), 2);
+        let entries = initramfs
+            .entries()
+            .collect::<Result<std::vec::Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(entries[0].path, "/system");
+        assert_eq!(entries[0].kind, EntryKind::Directory);
+        assert_eq!(entries[1].path, "/system/init");
+        assert_eq!(entries[1].kind, EntryKind::File);
+        assert_eq!(entries[1].data, b"init binary");
+    }
+
+    #[test]
+    fn test_parse_malformed() {
+        assert!(matches!(
+            Initramfs::parse(b"not an initramfs"),
+            Err(InitramfsError::BadMagic)
+        ));
+
+        let mut builder = InitramfsBuilder::new();
+        builder.add_file("/file", b"content");
+        let image = builder.build();
+        let truncated = Initramfs::parse(&image[..image.len() - 8]).unwrap();
+        assert!(matches!(
+            truncated.entries().next(),
+            Some(Err(InitramfsError::Malformed))
+        ));
+    }
+
+    #[test]
+    fn test_unpack() {
+        run_task(async {
+            let mut builder = InitramfsBuilder::new();
+            builder.add_directory("/system");
+            builder.add_file("/system/welcome.txt", b"welcome");
+            let image = builder.build();
+
+            let fs = RamFileSystem::new(1024).unwrap();
+            Initramfs::parse(&image).unwrap().unpack(&fs).await.unwrap();
+
+            let path = PathBuf::from_str("/system/welcome.txt").unwrap();
+            let mut file = fs.open_file(path.as_path()).await.unwrap();
+            let mut buf = [0u8; 16];
+            let len = file.read(&mut buf).await.unwrap();
+            assert_eq!(&buf[..len as usize], b"welcome");
+            file.close().await.unwrap();
+        });
+    }
+}
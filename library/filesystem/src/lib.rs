@@ -15,6 +15,7 @@ extern crate std;
 
 pub mod device;
 pub mod fs;
+pub mod initramfs;
 pub mod path;
 
 #[allow(unused)]
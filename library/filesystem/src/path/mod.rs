@@ -1,9 +1,18 @@
-use core::{ffi::CStr, ops::Add, str::FromStr};
+use core::{
+    ffi::CStr,
+    fmt::{self, Display, Formatter},
+    ops::Add,
+    str::FromStr,
+};
 
 use alloc::{string::String, vec::Vec};
 
 /// 路径分隔符
 pub const DELIMITER: u8 = b'/';
+/// 表示当前文件夹的路径段
+pub const CURRENT_DIR: &str = ".";
+/// 表示上级文件夹的路径段
+pub const PARENT_DIR: &str = "..";
 
 /// 文件路径
 ///
@@ -58,6 +67,34 @@ impl PathBuf {
         self.segments.extend_from_slice(&other.segments);
     }
 
+    /// 在路径末尾追加path中的各个路径段，path开头的分隔符同样被忽略
+    pub fn join(&self, path: &str) -> PathBuf {
+        let mut segments = self.segments.clone();
+        segments.extend(
+            path.split(DELIMITER as char)
+                .filter(|segment| !segment.is_empty())
+                .map(String::from),
+        );
+        PathBuf { segments }
+    }
+
+    /// 规范化路径，去除 [`CURRENT_DIR`]，并将 [`PARENT_DIR`] 与其之前的路径段抵消
+    ///
+    /// 路径视为绝对路径，根目录的上级仍为根目录
+    pub fn normalize(&self) -> PathBuf {
+        let mut segments = Vec::with_capacity(self.segments.len());
+        for segment in &self.segments {
+            match segment.as_str() {
+                CURRENT_DIR => (),
+                PARENT_DIR => {
+                    segments.pop();
+                }
+                _ => segments.push(segment.clone()),
+            }
+        }
+        PathBuf { segments }
+    }
+
     pub fn as_path(&self) -> Path<'_> {
         Path {
             segments: &self.segments,
@@ -65,6 +102,12 @@ impl PathBuf {
     }
 }
 
+impl Display for PathBuf {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.as_path(), f)
+    }
+}
+
 impl FromStr for PathBuf {
     type Err = ParsePathError;
 
@@ -107,6 +150,63 @@ impl Path<'_> {
     pub fn last_segment(&self) -> Option<&str> {
         self.segments.last().map(|s| s.as_str())
     }
+
+    /// 最后一个路径段，根目录及最后一段为 [`PARENT_DIR`] 时返回None
+    pub fn file_name(&self) -> Option<&str> {
+        self.last_segment().filter(|name| *name != PARENT_DIR)
+    }
+
+    /// 文件名中最后一个`.`之前的部分，以`.`开头且不含其他`.`的文件名整体视为文件名主干
+    pub fn file_stem(&self) -> Option<&str> {
+        self.split_file_name().map(|(stem, _)| stem)
+    }
+
+    /// 文件名中最后一个`.`之后的部分，不含`.`，没有扩展名时返回None
+    pub fn extension(&self) -> Option<&str> {
+        self.split_file_name().and_then(|(_, extension)| extension)
+    }
+
+    fn split_file_name(&self) -> Option<(&str, Option<&str>)> {
+        let name = self.file_name()?;
+        if name == CURRENT_DIR {
+            return Some((name, None));
+        }
+        Some(match name.rsplit_once('.') {
+            Some(("", _)) | None => (name, None),
+            Some((stem, extension)) => (stem, Some(extension)),
+        })
+    }
+
+    /// 路径是否以prefix开头，按完整的路径段比较
+    pub fn starts_with(&self, prefix: Path<'_>) -> bool {
+        self.segments.starts_with(prefix.segments)
+    }
+
+    /// 如果路径以prefix开头，返回去除prefix后的剩余部分
+    pub fn strip_prefix(&self, prefix: Path<'_>) -> Option<Path<'_>> {
+        self.segments
+            .strip_prefix(prefix.segments)
+            .map(|segments| Path { segments })
+    }
+
+    pub fn to_path_buf(&self) -> PathBuf {
+        PathBuf {
+            segments: self.segments.to_vec(),
+        }
+    }
+}
+
+/// 以绝对路径的形式显示，根目录显示为`/`
+impl Display for Path<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_root() {
+            return f.write_str("/");
+        }
+        for segment in self.iter() {
+            write!(f, "/{segment}")?;
+        }
+        Ok(())
+    }
 }
 
 pub struct PathIter<'s> {
@@ -120,3 +220,92 @@ impl<'s> Iterator for PathIter<'s> {
         self.inner.next().map(|s| s.as_str())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use alloc::string::ToString;
+
+    use super::PathBuf;
+
+    fn path(s: &str) -> PathBuf {
+        PathBuf::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn parse_ignores_empty_segments() {
+        assert_eq!(path("//a///b/"), path("/a/b"));
+        assert!(path("").as_path().is_root());
+        assert!(path("/").as_path().is_root());
+        assert!(PathBuf::from_bytes(b"/a/\xff").is_err());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(path("").to_string(), "/");
+        assert_eq!(path("/a/b").to_string(), "/a/b");
+        assert_eq!(path("a/b/").as_path().to_string(), "/a/b");
+    }
+
+    #[test]
+    fn join() {
+        assert_eq!(path("/a").join("b/c"), path("/a/b/c"));
+        assert_eq!(path("/a").join("/b//c/"), path("/a/b/c"));
+        assert_eq!(path("/a").join(""), path("/a"));
+        assert_eq!(path("").join("a"), path("/a"));
+    }
+
+    #[test]
+    fn normalize() {
+        assert_eq!(path("/a/./b/../c").normalize(), path("/a/c"));
+        assert_eq!(path("/a/b/../../..").normalize(), path("/"));
+        assert_eq!(path("/../a").normalize(), path("/a"));
+        assert_eq!(path("/a/.../b").normalize(), path("/a/.../b"));
+        assert_eq!(path("/a").join("../b").normalize(), path("/b"));
+    }
+
+    #[test]
+    fn file_name() {
+        assert_eq!(path("/a/b.txt").as_path().file_name(), Some("b.txt"));
+        assert_eq!(path("/").as_path().file_name(), None);
+        assert_eq!(path("/a/..").as_path().file_name(), None);
+    }
+
+    #[test]
+    fn extension() {
+        let cases = [
+            ("/a/b.txt", Some("b"), Some("txt")),
+            ("/a/b.tar.gz", Some("b.tar"), Some("gz")),
+            ("/a/b", Some("b"), None),
+            ("/a/.profile", Some(".profile"), None),
+            ("/a/.profile.bak", Some(".profile"), Some("bak")),
+            ("/a/b.", Some("b"), Some("")),
+            ("/a/.", Some("."), None),
+            ("/a/..", None, None),
+            ("/", None, None),
+        ];
+        for (s, stem, extension) in cases {
+            let path = path(s);
+            assert_eq!(path.as_path().file_stem(), stem, "{s}");
+            assert_eq!(path.as_path().extension(), extension, "{s}");
+        }
+    }
+
+    #[test]
+    fn starts_with_and_strip_prefix() {
+        let full = path("/a/bc/d");
+        let full = full.as_path();
+        assert!(full.starts_with(path("/a/bc").as_path()));
+        assert!(full.starts_with(path("/").as_path()));
+        assert!(!full.starts_with(path("/a/b").as_path()));
+        assert_eq!(
+            full.strip_prefix(path("/a").as_path())
+                .map(|rest| rest.to_path_buf()),
+            Some(path("bc/d"))
+        );
+        assert_eq!(full.strip_prefix(path("/a/b").as_path()), None);
+        assert_eq!(
+            full.strip_prefix(full).map(|rest| rest.to_string()),
+            Some("/".to_string())
+        );
+    }
+}
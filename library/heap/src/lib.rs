@@ -1,8 +1,15 @@
 #![no_std]
 
+#[cfg(test)]
+extern crate std;
+
+#[cfg(feature = "sanitize")]
+mod sanitize;
+
 use core::{
     alloc::Layout,
     ptr::{self, NonNull},
+    slice,
 };
 
 const HEAP_SIZE_CLASSES: [usize; 9] = [8, 16, 32, 64, 128, 256, 512, 1024, 2048];
@@ -28,12 +35,116 @@ pub unsafe trait MemoryPageProvider {
     }
 
     unsafe fn allocate_pages(&mut self, size: usize) -> Option<NonNull<u8>>;
+    /// 归还address开始、大小为size的内存页
+    ///
+    /// # Safety
+    ///
+    /// 内存页必须由同一提供者申请，且归还后不再被访问
     unsafe fn deallocate_pages(&mut self, address: NonNull<u8>, size: usize);
+
+    /// 将address开始、大小为old_size的内存页原地扩展至new_size，成功时返回true
+    ///
+    /// 用于 [RustHeap::reallocate] 原地扩展直接以内存页分配的内存块。默认不支持，返回false
+    ///
+    /// # Safety
+    ///
+    /// address开始、大小为old_size的内存页必须由同一提供者申请，且尚未归还
+    unsafe fn grow_pages(
+        &mut self,
+        _address: NonNull<u8>,
+        _old_size: usize,
+        _new_size: usize,
+    ) -> bool {
+        false
+    }
+
+    /// 记录当前调用栈的返回地址，由内向外依次写入frames，未使用的项保持为0
+    ///
+    /// 仅在启用`sanitize`特性时调用，用于在堆错误的panic信息中给出分配与释放的位置。默认不记录
+    fn capture_backtrace(&mut self, _frames: &mut [usize]) {}
 }
 
 pub struct RustHeap<P> {
+    // 各类别中尚有空闲内存块、且未完全空闲的页，分配时从链表头部取用
     bucket: [*mut HeapNodeHead; 9],
+    // 各类别链表的尾部，较空的页移至尾部时无需遍历链表
+    bucket_tail: [*mut HeapNodeHead; 9],
+    // 各类别保留的一个完全空闲的页，避免分配与释放交替时反复申请与归还内存页，可通过shrink归还
+    empty: [*mut HeapNodeHead; 9],
+    // 直接以内存页分配的内存块，释放时据此找回申请的内存页
+    large: LargeTable,
     provider: P,
+    stats: HeapStats,
+}
+
+/// 堆的使用情况，由 [RustHeap::stats] 返回
+#[derive(Debug, Clone, Copy)]
+pub struct HeapStats {
+    /// 各大小类别的使用情况，按大小升序排列
+    pub classes: [SizeClassStats; 9],
+    /// 超过2K、直接以内存页分配的内存数量
+    pub large_allocated: u64,
+    /// 直接分配的内存占用的内存页数量
+    pub large_pages: u64,
+}
+
+/// 单个大小类别的使用情况
+#[derive(Debug, Clone, Copy)]
+pub struct SizeClassStats {
+    /// 类别的内存块大小（字节）
+    pub size: usize,
+    /// 已分配的内存块数量
+    pub allocated: u64,
+    /// 已持有的内存页中空闲的内存块数量
+    pub free: u64,
+    /// 持有的内存页数量
+    pub pages: u64,
+}
+
+impl HeapStats {
+    const fn new() -> Self {
+        let mut classes = [SizeClassStats {
+            size: 0,
+            allocated: 0,
+            free: 0,
+            pages: 0,
+        }; 9];
+        let mut index = 0;
+        while index < classes.len() {
+            classes[index].size = HEAP_SIZE_CLASSES[index];
+            index += 1;
+        }
+        Self {
+            classes,
+            large_allocated: 0,
+            large_pages: 0,
+        }
+    }
+
+    /// 堆从页分配器持有的内存（字节）
+    pub fn held_bytes(&self) -> u64 {
+        let class_pages: u64 = self.classes.iter().map(|class| class.pages).sum();
+        (class_pages + self.large_pages) * 0x1000
+    }
+
+    /// 已分配出的内存（字节），按内存块大小计算，不含内存块内未使用的部分
+    pub fn allocated_bytes(&self) -> u64 {
+        let class_bytes: u64 = self
+            .classes
+            .iter()
+            .map(|class| class.allocated * class.size as u64)
+            .sum();
+        class_bytes + self.large_pages * 0x1000
+    }
+
+    /// 碎片率（百分比），即持有但未分配出的内存占持有内存的比例。未持有内存时为0
+    pub fn fragmentation_percent(&self) -> u64 {
+        let held = self.held_bytes();
+        if held == 0 {
+            return 0;
+        }
+        (held - self.allocated_bytes()) * 100 / held
+    }
 }
 
 // 使用双向链表管理空闲内存
@@ -52,54 +163,171 @@ struct NodeFreeBody {
     next: *mut NodeFreeBody,
 }
 
+// 直接以内存页分配的内存块的元数据
+//
+// 对齐要求超过4K时，需多申请内存页再取其中对齐的地址，因此返回的地址不一定是内存页的起始地址
+#[derive(Clone, Copy)]
+struct LargeBlock {
+    // 返回给调用方的地址
+    address: usize,
+    // 从页分配器申请的内存页及其大小
+    pages: NonNull<u8>,
+    size: usize,
+}
+
+// 直接分配的内存块的元数据表，按地址升序存放在单独申请的内存页中，释放时二分查找
+struct LargeTable {
+    entries: NonNull<LargeBlock>,
+    len: usize,
+    capacity: usize,
+}
+
 unsafe impl<P: Send> Send for RustHeap<P> {}
 
 impl<P: MemoryPageProvider> RustHeap<P> {
     pub const fn new(provider: P) -> Self {
         Self {
             bucket: [ptr::null_mut(); 9],
+            bucket_tail: [ptr::null_mut(); 9],
+            empty: [ptr::null_mut(); 9],
+            large: LargeTable {
+                entries: NonNull::dangling(),
+                len: 0,
+                capacity: 0,
+            },
             provider,
+            stats: HeapStats::new(),
         }
     }
 
+    /// 当前的使用情况
+    pub fn stats(&self) -> HeapStats {
+        self.stats
+    }
+
     pub fn allocate(&mut self, layout: Layout) -> *mut u8 {
+        #[cfg(feature = "sanitize")]
+        return sanitize::allocate(self, layout);
+        #[cfg(not(feature = "sanitize"))]
+        self.allocate_block(layout)
+    }
+
+    pub unsafe fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout) {
+        #[cfg(feature = "sanitize")]
+        unsafe {
+            sanitize::deallocate(self, ptr, layout)
+        }
+        #[cfg(not(feature = "sanitize"))]
+        unsafe {
+            self.deallocate_block(ptr, layout)
+        }
+    }
+
+    /// 调整内存块的大小，语义与 [core::alloc::GlobalAlloc::realloc] 相同
+    ///
+    /// 新大小仍在同一类别内，或直接分配的内存块可由页分配器原地扩展时，返回原地址；
+    /// 否则申请新内存块并复制内容，失败时返回空指针，原内存块不受影响
+    ///
+    /// # Safety
+    ///
+    /// ptr必须由同一个堆以layout分配且尚未释放，成功返回新地址后不能再通过ptr访问内存块
+    pub unsafe fn reallocate(
+        &mut self,
+        ptr: NonNull<u8>,
+        layout: Layout,
+        new_size: usize,
+    ) -> *mut u8 {
+        let Ok(new_layout) = Layout::from_size_align(new_size, layout.align()) else {
+            return ptr::null_mut();
+        };
+        // sanitize需要更新内存块的头部与哨兵，总是重新分配
+        #[cfg(not(feature = "sanitize"))]
+        if unsafe { self.resize_in_place(ptr, layout, new_layout) } {
+            return ptr.as_ptr();
+        }
+
+        let new_ptr = self.allocate(new_layout);
+        if !new_ptr.is_null() {
+            unsafe {
+                ptr::copy_nonoverlapping(ptr.as_ptr(), new_ptr, layout.size().min(new_size));
+                self.deallocate(ptr, layout);
+            }
+        }
+        new_ptr
+    }
+
+    /// 尝试原地调整内存块的大小，成功时返回true
+    #[cfg_attr(feature = "sanitize", allow(dead_code))]
+    unsafe fn resize_in_place(
+        &mut self,
+        ptr: NonNull<u8>,
+        layout: Layout,
+        new_layout: Layout,
+    ) -> bool {
         let index = Self::get_bucket_index_by_layout(layout);
+        let new_index = Self::get_bucket_index_by_layout(new_layout);
+        if index < HEAP_SIZE_CLASSES.len() || new_index < HEAP_SIZE_CLASSES.len() {
+            return index == new_index;
+        }
 
-        // 如果桶越界了，说明申请超过4K内存，我们直接申请对应内存页
-        if index >= HEAP_SIZE_CLASSES.len() {
-            let mut size = layout.align().max(layout.size());
-            if (size & 0xFFF) != 0 {
-                size = (size & !0xFFF) + 0x1000;
+        let (Some(size), Some(new_size)) = (Self::large_size(layout), Self::large_size(new_layout))
+        else {
+            return false;
+        };
+        // 缩小时不归还内存页，以免释放时的大小与页分配器记录的不一致
+        if new_size <= size {
+            return new_size == size;
+        }
+        let Ok(index) = self.find_large(ptr) else {
+            return false;
+        };
+        let pages = self.large_blocks()[index].pages;
+        if !unsafe { self.provider.grow_pages(pages, size, new_size) } {
+            return false;
+        }
+        self.large_blocks()[index].size = new_size;
+        self.stats.large_pages += ((new_size - size) / 0x1000) as u64;
+        true
+    }
+
+    /// 将保留的空闲页归还给页分配器，返回归还的页数量
+    ///
+    /// 内存不足时调用。已分配的内存块不会被移动，因此只有完全空闲的页可以归还
+    pub fn shrink(&mut self) -> usize {
+        let mut released = 0;
+        for index in 0..self.empty.len() {
+            let page = core::mem::replace(&mut self.empty[index], ptr::null_mut());
+            if let Some(page) = NonNull::new(page) {
+                unsafe {
+                    self.release_page(index, page);
+                }
+                released += 1;
             }
-            return match unsafe { self.provider.allocate_pages(size) } {
-                Some(ptr) => ptr.as_ptr(),
-                None => ptr::null_mut(),
-            };
         }
+        released
+    }
 
-        // 桶内的内存不足，申请新内存页
-        if self.bucket[index].is_null() {
-            let new_page = match unsafe { self.provider.allocate_page() } {
-                Some(ptr) => ptr.as_ptr(),
-                None => return ptr::null_mut(),
-            } as *mut HeapNodeHead;
+    fn allocate_block(&mut self, layout: Layout) -> *mut u8 {
+        let index = Self::get_bucket_index_by_layout(layout);
 
-            // 填充新页元数据
-            unsafe {
-                (*new_page).prev = ptr::null_mut();
-                (*new_page).next = self.bucket[index];
-                self.bucket[index] = new_page;
-
-                (*new_page).free_ptr = ptr::null_mut();
-                (*new_page).free_count = HEAP_FREE_COUNT[index];
-                let mut free_ptr = (new_page as usize
-                    + HEAP_SIZE_CLASSES[index].max(size_of::<HeapNodeHead>()))
-                    as *mut NodeFreeBody;
-                while (free_ptr as usize) < (new_page as usize + 0x1000) {
-                    (*free_ptr).next = (*new_page).free_ptr;
-                    (*new_page).free_ptr = free_ptr;
-                    free_ptr = (free_ptr as usize + HEAP_SIZE_CLASSES[index]) as *mut NodeFreeBody;
+        // 如果桶越界了，说明申请超过2K内存或对齐要求超过2K，我们直接申请对应内存页
+        if index >= HEAP_SIZE_CLASSES.len() {
+            return self.allocate_large(layout);
+        }
+
+        // 没有部分使用的页时，优先使用保留的空闲页，其次申请新内存页
+        if self.bucket[index].is_null() {
+            let page = core::mem::replace(&mut self.empty[index], ptr::null_mut());
+            let page = if page.is_null() {
+                match self.allocate_new_page(index) {
+                    Some(page) => page,
+                    None => return ptr::null_mut(),
                 }
+            } else {
+                page
+            };
+            unsafe {
+                self.push_front(index, page);
             }
         }
 
@@ -109,26 +337,104 @@ impl<P: MemoryPageProvider> RustHeap<P> {
             let ptr = (*page).free_ptr;
             (*page).free_ptr = (*ptr).next;
             (*page).free_count -= 1;
+            self.stats.classes[index].allocated += 1;
+            self.stats.classes[index].free -= 1;
             // 如果分配完成后，当前块已经没有空余内存，则移出链表
             if (*page).free_count == 0 {
-                self.bucket[index] = (*page).next;
-                if !self.bucket[index].is_null() {
-                    (*self.bucket[index]).prev = ptr::null_mut();
-                }
+                self.unlink(index, page);
             }
             ptr as *mut u8
         }
     }
 
-    pub unsafe fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout) {
-        // 如果对齐到4K，说明之前申请的完整的一页内存，直接交还给page frame
-        if (ptr.as_ptr() as usize & 0xFFF) == 0 {
-            let mut size = layout.align().max(layout.size());
-            if (size & 0xFFF) != 0 {
-                size = (size & !0xFFF) + 0x1000;
+    /// 直接以内存页分配内存块，并记录其元数据
+    fn allocate_large(&mut self, layout: Layout) -> *mut u8 {
+        let Some(size) = Self::large_size(layout) else {
+            return ptr::null_mut();
+        };
+        if self.large.len == self.large.capacity && !self.grow_large_table() {
+            return ptr::null_mut();
+        }
+        let Some(pages) = self.allocate_pages(size) else {
+            return ptr::null_mut();
+        };
+
+        let address = (pages.as_ptr() as usize).next_multiple_of(layout.align());
+        let Err(index) = self.find_large(NonNull::new(address as *mut u8).unwrap()) else {
+            panic!("heap: page provider returned {address:#x}, which is already allocated");
+        };
+        let len = self.large.len;
+        unsafe {
+            let entries = self.large.entries.as_ptr();
+            ptr::copy(entries.add(index), entries.add(index + 1), len - index);
+            entries.add(index).write(LargeBlock {
+                address,
+                pages,
+                size,
+            });
+        }
+        self.large.len += 1;
+        self.stats.large_allocated += 1;
+        self.stats.large_pages += (size / 0x1000) as u64;
+        address as *mut u8
+    }
+
+    /// 直接分配的内存块需申请的内存大小，溢出时返回None
+    ///
+    /// 页分配器返回的内存页对齐到4K，对齐要求更高时多申请`align - 4K`，以便从中取得对齐的地址
+    fn large_size(layout: Layout) -> Option<usize> {
+        layout
+            .size()
+            .max(1)
+            .checked_next_multiple_of(0x1000)?
+            .checked_add(layout.align().saturating_sub(0x1000))
+    }
+
+    /// 申请内存页，失败时归还保留的空闲页后重试一次
+    fn allocate_pages(&mut self, size: usize) -> Option<NonNull<u8>> {
+        if let Some(ptr) = unsafe { self.provider.allocate_pages(size) } {
+            return Some(ptr);
+        }
+        if self.shrink() == 0 {
+            return None;
+        }
+        unsafe { self.provider.allocate_pages(size) }
+    }
+
+    /// 申请新内存页并划分为内存块，新页不在任何链表中
+    fn allocate_new_page(&mut self, index: usize) -> Option<*mut HeapNodeHead> {
+        let new_page = self.allocate_pages(0x1000)?.as_ptr() as *mut HeapNodeHead;
+        self.stats.classes[index].pages += 1;
+        self.stats.classes[index].free += HEAP_FREE_COUNT[index];
+
+        // 清空新页，避免页中残留的释放标记被误认为已释放的内存块
+        #[cfg(feature = "sanitize")]
+        unsafe {
+            ptr::write_bytes(new_page.cast::<u8>(), 0, 0x1000);
+        }
+
+        // 填充新页元数据
+        unsafe {
+            (*new_page).free_ptr = ptr::null_mut();
+            (*new_page).free_count = HEAP_FREE_COUNT[index];
+            let mut free_ptr = (new_page as usize
+                + HEAP_SIZE_CLASSES[index].max(size_of::<HeapNodeHead>()))
+                as *mut NodeFreeBody;
+            while (free_ptr as usize) < (new_page as usize + 0x1000) {
+                (*free_ptr).next = (*new_page).free_ptr;
+                (*new_page).free_ptr = free_ptr;
+                free_ptr = (free_ptr as usize + HEAP_SIZE_CLASSES[index]) as *mut NodeFreeBody;
             }
+        }
+        Some(new_page)
+    }
+
+    unsafe fn deallocate_block(&mut self, ptr: NonNull<u8>, layout: Layout) {
+        // 与分配时相同，由Layout决定内存块是否直接以内存页分配
+        let index = Self::get_bucket_index_by_layout(layout);
+        if index >= HEAP_SIZE_CLASSES.len() {
             unsafe {
-                self.provider.deallocate_pages(ptr, size);
+                self.deallocate_large(ptr, layout);
             }
             return;
         }
@@ -136,7 +442,6 @@ impl<P: MemoryPageProvider> RustHeap<P> {
         // 其余情况，对齐到4K，获取bucket元数据
         let ptr = ptr.as_ptr();
         let head = (ptr as usize & !0xFFF) as *mut HeapNodeHead;
-        let index = Self::get_bucket_index_by_layout(layout);
 
         // 添加到free_list
         unsafe {
@@ -144,30 +449,140 @@ impl<P: MemoryPageProvider> RustHeap<P> {
             (*ptr).next = (*head).free_ptr;
             (*head).free_ptr = ptr;
             (*head).free_count += 1;
-            // 如果当前块首次释放，则加入链表
+            self.stats.classes[index].allocated -= 1;
+            self.stats.classes[index].free += 1;
+            // 如果当前块首次释放，则加入链表。此时页几乎是满的，放在头部以优先使用
             if (*head).free_count == 1 {
-                if !self.bucket[index].is_null() {
-                    (*self.bucket[index]).prev = head;
-                }
-                (*head).next = self.bucket[index];
-                (*head).prev = ptr::null_mut();
-                self.bucket[index] = head;
+                self.push_front(index, head);
             }
-            // 如果当前块全部释放，则返还page frame
+
             if (*head).free_count == HEAP_FREE_COUNT[index] {
-                if (*head).prev.is_null() {
-                    self.bucket[index] = (*head).next;
-                    if !self.bucket[index].is_null() {
-                        (*(*head).next).prev = ptr::null_mut();
-                    }
-                } else {
-                    (*(*head).prev).next = (*head).next;
-                    if !(*head).next.is_null() {
-                        (*(*head).next).prev = (*head).prev;
-                    }
+                // 如果当前块全部释放，则保留为空闲页，已保留空闲页时返还page frame
+                self.unlink(index, head);
+                let retained = core::mem::replace(&mut self.empty[index], head);
+                if let Some(retained) = NonNull::new(retained) {
+                    self.release_page(index, retained);
                 }
+            } else if (*head).free_count == HEAP_FREE_COUNT[index] / 2 + 1 {
+                // 空闲内存块超过一半时移至链表末尾，使分配优先使用更满的页，较空的页则有机会完全释放
+                self.unlink(index, head);
+                self.push_back(index, head);
+            }
+        }
+    }
+
+    /// 释放直接分配的内存块，将其内存页交还给page frame
+    ///
+    /// 找不到内存块的元数据，或Layout与分配时不一致时panic，以免将错误的内存页交还
+    unsafe fn deallocate_large(&mut self, ptr: NonNull<u8>, layout: Layout) {
+        let Ok(index) = self.find_large(ptr) else {
+            panic!("heap: free of {ptr:p}, which is not a large allocation");
+        };
+        let block = self.large_blocks()[index];
+        let size = block.size;
+        if Self::large_size(layout) != Some(size) {
+            panic!(
+                "heap: free of {ptr:p} with size {} align {}, but {size} bytes of pages were allocated",
+                layout.size(),
+                layout.align()
+            );
+        }
+        let len = self.large.len;
+        unsafe {
+            let entries = self.large.entries.as_ptr();
+            ptr::copy(entries.add(index + 1), entries.add(index), len - index - 1);
+        }
+        self.large.len -= 1;
+
+        unsafe {
+            self.provider.deallocate_pages(block.pages, size);
+        }
+        self.stats.large_allocated -= 1;
+        self.stats.large_pages -= (size / 0x1000) as u64;
+    }
+
+    /// 直接分配的内存块的元数据，按地址升序排列
+    fn large_blocks(&mut self) -> &mut [LargeBlock] {
+        unsafe { slice::from_raw_parts_mut(self.large.entries.as_ptr(), self.large.len) }
+    }
+
+    /// 二分查找直接分配的内存块的元数据，找不到时返回应插入的位置
+    fn find_large(&mut self, ptr: NonNull<u8>) -> Result<usize, usize> {
+        self.large_blocks()
+            .binary_search_by_key(&(ptr.as_ptr() as usize), |block| block.address)
+    }
+
+    /// 将元数据表的容量加倍，失败时返回false，原表不受影响
+    fn grow_large_table(&mut self) -> bool {
+        let old_size = self.large.capacity * size_of::<LargeBlock>();
+        let size = (old_size * 2).max(0x1000);
+        let Some(entries) = self.allocate_pages(size) else {
+            return false;
+        };
+        let entries = entries.cast::<LargeBlock>();
+        unsafe {
+            ptr::copy_nonoverlapping(
+                self.large.entries.as_ptr(),
+                entries.as_ptr(),
+                self.large.len,
+            );
+            if self.large.capacity > 0 {
                 self.provider
-                    .deallocate_pages(NonNull::new(head.cast::<u8>()).unwrap(), 0x1000);
+                    .deallocate_pages(self.large.entries.cast(), old_size);
+            }
+        }
+        self.large.entries = entries;
+        self.large.capacity = size / size_of::<LargeBlock>();
+        true
+    }
+
+    /// 将不在任何链表中的页返还page frame
+    unsafe fn release_page(&mut self, index: usize, page: NonNull<HeapNodeHead>) {
+        unsafe {
+            self.provider.deallocate_pages(page.cast(), 0x1000);
+        }
+        self.stats.classes[index].pages -= 1;
+        self.stats.classes[index].free -= HEAP_FREE_COUNT[index];
+    }
+
+    unsafe fn push_front(&mut self, index: usize, page: *mut HeapNodeHead) {
+        unsafe {
+            if self.bucket[index].is_null() {
+                self.bucket_tail[index] = page;
+            } else {
+                (*self.bucket[index]).prev = page;
+            }
+            (*page).next = self.bucket[index];
+            (*page).prev = ptr::null_mut();
+            self.bucket[index] = page;
+        }
+    }
+
+    unsafe fn push_back(&mut self, index: usize, page: *mut HeapNodeHead) {
+        unsafe {
+            let tail = self.bucket_tail[index];
+            if tail.is_null() {
+                self.push_front(index, page);
+                return;
+            }
+            (*tail).next = page;
+            (*page).prev = tail;
+            (*page).next = ptr::null_mut();
+            self.bucket_tail[index] = page;
+        }
+    }
+
+    unsafe fn unlink(&mut self, index: usize, page: *mut HeapNodeHead) {
+        unsafe {
+            if (*page).prev.is_null() {
+                self.bucket[index] = (*page).next;
+            } else {
+                (*(*page).prev).next = (*page).next;
+            }
+            if (*page).next.is_null() {
+                self.bucket_tail[index] = (*page).prev;
+            } else {
+                (*(*page).next).prev = (*page).prev;
             }
         }
     }
@@ -180,3 +595,257 @@ impl<P: MemoryPageProvider> RustHeap<P> {
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod test {
+    use core::{alloc::Layout, ptr::NonNull};
+
+    use crate::{MemoryPageProvider, RustHeap};
+
+    pub(crate) struct StdPageProvider;
+
+    unsafe impl MemoryPageProvider for StdPageProvider {
+        unsafe fn allocate_pages(&mut self, size: usize) -> Option<NonNull<u8>> {
+            let layout = Layout::from_size_align(size, 0x1000).unwrap();
+            NonNull::new(unsafe { std::alloc::alloc(layout) })
+        }
+
+        unsafe fn deallocate_pages(&mut self, address: NonNull<u8>, size: usize) {
+            let layout = Layout::from_size_align(size, 0x1000).unwrap();
+            unsafe { std::alloc::dealloc(address.as_ptr(), layout) }
+        }
+
+        fn capture_backtrace(&mut self, frames: &mut [usize]) {
+            frames[0] = 0xC0DE;
+        }
+    }
+
+    #[test]
+    #[cfg_attr(feature = "sanitize", ignore = "内存块包含头部与哨兵")]
+    fn test_stats() {
+        let mut heap = RustHeap::new(StdPageProvider);
+        let small = Layout::from_size_align(24, 8).unwrap();
+        let large = Layout::from_size_align(5000, 8).unwrap();
+
+        let a = NonNull::new(heap.allocate(small)).unwrap();
+        let b = NonNull::new(heap.allocate(small)).unwrap();
+        let c = NonNull::new(heap.allocate(large)).unwrap();
+        let stats = heap.stats();
+        let class = stats.classes[2];
+        // 直接分配的内存块的元数据存放在单独的表中，不占用小内存块的类别
+        assert_eq!(class.size, 32);
+        assert_eq!((class.allocated, class.pages), (2, 1));
+        assert_eq!(class.free, (0x1000 - 32) / 32 - 2);
+        assert_eq!((stats.large_allocated, stats.large_pages), (1, 2));
+        assert_eq!(stats.held_bytes(), 3 * 0x1000);
+        assert_eq!(stats.allocated_bytes(), 2 * 32 + 2 * 0x1000);
+
+        unsafe {
+            heap.deallocate(a, small);
+            heap.deallocate(c, large);
+        }
+        let stats = heap.stats();
+        assert_eq!((stats.classes[2].allocated, stats.classes[2].pages), (1, 1));
+        assert_eq!((stats.large_allocated, stats.large_pages), (0, 0));
+
+        // 整页释放后保留为空闲页，shrink时归还
+        unsafe {
+            heap.deallocate(b, small);
+        }
+        assert_eq!(heap.stats().classes[2].pages, 1);
+        assert_eq!(heap.shrink(), 1);
+        let stats = heap.stats();
+        assert_eq!(stats.classes[2].pages, 0);
+        assert_eq!(stats.classes[2].free, 0);
+        assert_eq!(stats.fragmentation_percent(), 0);
+    }
+
+    #[test]
+    fn test_large_align() {
+        let mut heap = RustHeap::new(StdPageProvider);
+        let layouts = [
+            Layout::from_size_align(8, 0x1000).unwrap(),
+            Layout::from_size_align(100, 0x4000).unwrap(),
+            Layout::from_size_align(0x3000, 0x10000).unwrap(),
+            Layout::from_size_align(5000, 8).unwrap(),
+        ];
+        let blocks: std::vec::Vec<_> = layouts
+            .iter()
+            .map(|&layout| {
+                let ptr = NonNull::new(heap.allocate(layout)).unwrap();
+                assert_eq!(ptr.as_ptr() as usize % layout.align(), 0);
+                unsafe {
+                    ptr.as_ptr().write_bytes(0x5A, layout.size());
+                }
+                ptr
+            })
+            .collect();
+        assert_eq!(heap.stats().large_allocated, 4);
+
+        // 按分配顺序释放，元数据表中的内存块不一定按此顺序排列
+        for (ptr, layout) in blocks.into_iter().zip(layouts) {
+            unsafe {
+                heap.deallocate(ptr, layout);
+            }
+        }
+        let stats = heap.stats();
+        assert_eq!((stats.large_allocated, stats.large_pages), (0, 0));
+    }
+
+    #[test]
+    fn test_large_table_grow() {
+        let mut heap = RustHeap::new(StdPageProvider);
+        let layout = Layout::from_size_align(5000, 8).unwrap();
+        // 超过一页元数据表的容量，迫使元数据表扩容
+        let mut blocks: std::vec::Vec<_> = (0..400)
+            .map(|_| NonNull::new(heap.allocate(layout)).unwrap())
+            .collect();
+        assert_eq!(heap.stats().large_allocated, 400);
+
+        // 先释放奇数位置再释放偶数位置，使删除发生在表的中间
+        let (odd, even): (std::vec::Vec<_>, std::vec::Vec<_>) = blocks
+            .drain(..)
+            .enumerate()
+            .partition(|(index, _)| index % 2 == 1);
+        for (_, ptr) in odd.into_iter().chain(even) {
+            unsafe {
+                heap.deallocate(ptr, layout);
+            }
+        }
+        let stats = heap.stats();
+        assert_eq!((stats.large_allocated, stats.large_pages), (0, 0));
+    }
+
+    // 从固定的内存区域依次分配内存页，位于末尾的内存页可以原地扩展
+    struct ArenaPageProvider {
+        arena: *mut u8,
+        used: usize,
+    }
+
+    impl ArenaPageProvider {
+        const SIZE: usize = 0x10000;
+
+        fn new() -> Self {
+            let layout = Layout::from_size_align(Self::SIZE, 0x1000).unwrap();
+            let arena = unsafe { std::alloc::alloc(layout) };
+            assert!(!arena.is_null());
+            Self { arena, used: 0 }
+        }
+    }
+
+    impl Drop for ArenaPageProvider {
+        fn drop(&mut self) {
+            let layout = Layout::from_size_align(Self::SIZE, 0x1000).unwrap();
+            unsafe { std::alloc::dealloc(self.arena, layout) }
+        }
+    }
+
+    unsafe impl MemoryPageProvider for ArenaPageProvider {
+        unsafe fn allocate_pages(&mut self, size: usize) -> Option<NonNull<u8>> {
+            if self.used + size > Self::SIZE {
+                return None;
+            }
+            let ptr = unsafe { self.arena.add(self.used) };
+            self.used += size;
+            NonNull::new(ptr)
+        }
+
+        unsafe fn deallocate_pages(&mut self, _address: NonNull<u8>, _size: usize) {}
+
+        unsafe fn grow_pages(
+            &mut self,
+            address: NonNull<u8>,
+            old_size: usize,
+            new_size: usize,
+        ) -> bool {
+            let end = unsafe { self.arena.add(self.used) };
+            if unsafe { address.as_ptr().add(old_size) } != end
+                || self.used + new_size - old_size > Self::SIZE
+            {
+                return false;
+            }
+            self.used += new_size - old_size;
+            true
+        }
+    }
+
+    #[test]
+    fn test_reallocate() {
+        let mut heap = RustHeap::new(ArenaPageProvider::new());
+        let layout = Layout::from_size_align(5000, 8).unwrap();
+        let ptr = NonNull::new(heap.allocate(layout)).unwrap();
+        unsafe {
+            ptr.as_ptr().write_bytes(0x5A, layout.size());
+        }
+
+        // 位于末尾的内存页原地扩展
+        let grown = unsafe { heap.reallocate(ptr, layout, 9000) };
+        let layout = Layout::from_size_align(9000, 8).unwrap();
+        #[cfg(not(feature = "sanitize"))]
+        {
+            assert_eq!(grown, ptr.as_ptr());
+            assert_eq!(heap.stats().large_pages, 3);
+        }
+        let grown = NonNull::new(grown).unwrap();
+
+        // 其后已分配其他内存页时，重新分配并复制内容
+        let other = Layout::from_size_align(0x1000, 0x1000).unwrap();
+        let other_ptr = NonNull::new(heap.allocate(other)).unwrap();
+        let moved = NonNull::new(unsafe { heap.reallocate(grown, layout, 0x4000) }).unwrap();
+        assert_ne!(moved, grown);
+        let content = unsafe { core::slice::from_raw_parts(moved.as_ptr(), 5000) };
+        assert!(content.iter().all(|&byte| byte == 0x5A));
+
+        unsafe {
+            heap.deallocate(moved, Layout::from_size_align(0x4000, 8).unwrap());
+            heap.deallocate(other_ptr, other);
+        }
+        assert_eq!(heap.stats().large_allocated, 0);
+    }
+
+    #[test]
+    #[cfg_attr(feature = "sanitize", ignore = "由sanitize先行检查")]
+    #[should_panic(expected = "which is not a large allocation")]
+    fn test_large_invalid_free() {
+        let mut heap = RustHeap::new(StdPageProvider);
+        let layout = Layout::from_size_align(5000, 8).unwrap();
+        let ptr = NonNull::new(heap.allocate(layout)).unwrap();
+        unsafe {
+            heap.deallocate(NonNull::new_unchecked(ptr.as_ptr().add(0x1000)), layout);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(feature = "sanitize", ignore = "内存块包含头部与哨兵")]
+    fn test_prefer_fuller_page() {
+        let mut heap = RustHeap::new(StdPageProvider);
+        // 每页3个内存块
+        let layout = Layout::from_size_align(1024, 8).unwrap();
+        let blocks: std::vec::Vec<_> = (0..9)
+            .map(|_| NonNull::new(heap.allocate(layout)).unwrap())
+            .collect();
+        let page_of = |ptr: NonNull<u8>| ptr.as_ptr() as usize & !0xFFF;
+        assert_eq!(heap.stats().classes[7].pages, 3);
+
+        // 第二页剩余2个内存块，第一页仅剩1个，尽管第一页最近释放，仍优先使用第二页
+        unsafe {
+            heap.deallocate(blocks[3], layout);
+            heap.deallocate(blocks[0], layout);
+            heap.deallocate(blocks[1], layout);
+        }
+        let ptr = NonNull::new(heap.allocate(layout)).unwrap();
+        assert_eq!(page_of(ptr), page_of(blocks[3]));
+
+        // 全部释放后仅保留一个空闲页
+        unsafe {
+            heap.deallocate(ptr, layout);
+            for index in [2, 4, 5, 6, 7, 8] {
+                heap.deallocate(blocks[index], layout);
+            }
+        }
+        assert_eq!(heap.stats().classes[7].pages, 1);
+        assert_eq!(heap.shrink(), 1);
+        assert_eq!(heap.shrink(), 0);
+        assert_eq!(heap.stats().held_bytes(), 0);
+    }
+}
@@ -0,0 +1,276 @@
+use core::{
+    alloc::Layout,
+    ptr::{self, NonNull},
+    slice,
+};
+
+use crate::{HEAP_SIZE_CLASSES, MemoryPageProvider, RustHeap};
+
+/// 记录的调用栈深度
+const BACKTRACE_DEPTH: usize = 4;
+/// 用户内存前后哨兵的最小长度
+const CANARY_SIZE: usize = 16;
+/// 哨兵的填充值
+const CANARY_BYTE: u8 = 0xCA;
+/// 新分配内存的填充值，便于发现对未初始化内存的使用
+const ALLOCATED_POISON: u8 = 0xCD;
+/// 已释放内存的填充值
+const FREED_POISON: u8 = 0xDD;
+
+const MAGIC_ALLOCATED: u64 = 0xA110_CA7E_D0D0_A110;
+const MAGIC_FREED: u64 = 0xF4EE_D0D0_F4EE_D0D0;
+
+// 内存块头部，位于前哨兵之前
+//
+// 内存块布局：头部 | 前哨兵 | 用户内存 | 后哨兵
+#[repr(C)]
+struct Header {
+    // 内存块释放后被空闲链表占用
+    _link: usize,
+    magic: u64,
+    // 分配时的Layout
+    size: usize,
+    align: usize,
+    allocated_at: [usize; BACKTRACE_DEPTH],
+    freed_at: [usize; BACKTRACE_DEPTH],
+}
+
+/// 分配内存，并在用户内存前后放置哨兵
+///
+/// 如果取得的是曾经释放的内存块，检查其毒化内容是否完好，以发现释放后的写入
+pub(crate) fn allocate<P: MemoryPageProvider>(heap: &mut RustHeap<P>, layout: Layout) -> *mut u8 {
+    let Some(block_layout) = block_layout(layout) else {
+        return ptr::null_mut();
+    };
+    let block = heap.allocate_block(block_layout);
+    if block.is_null() {
+        return block;
+    }
+
+    let header = block.cast::<Header>();
+    let offset = user_offset(layout);
+    unsafe {
+        // 只有小内存块会被复用，大内存块直接由内存页提供，其内容不可信
+        if let Some(class_size) = class_size(block_layout)
+            && (*header).magic == MAGIC_FREED
+        {
+            check_freed(header, class_size);
+        }
+
+        header.write(Header {
+            _link: 0,
+            magic: MAGIC_ALLOCATED,
+            size: layout.size(),
+            align: layout.align(),
+            allocated_at: [0; BACKTRACE_DEPTH],
+            freed_at: [0; BACKTRACE_DEPTH],
+        });
+        heap.provider.capture_backtrace(&mut (*header).allocated_at);
+
+        let user = block.add(offset);
+        let front = block.add(size_of::<Header>());
+        ptr::write_bytes(front, CANARY_BYTE, offset - size_of::<Header>());
+        ptr::write_bytes(user, ALLOCATED_POISON, layout.size());
+        ptr::write_bytes(user.add(layout.size()), CANARY_BYTE, CANARY_SIZE);
+        user
+    }
+}
+
+/// 释放内存，检查重复释放、Layout不一致与哨兵，随后毒化整个内存块
+///
+/// 检查失败时panic，panic信息中包含内存块分配（及释放）时的调用栈
+pub(crate) unsafe fn deallocate<P: MemoryPageProvider>(
+    heap: &mut RustHeap<P>,
+    ptr: NonNull<u8>,
+    layout: Layout,
+) {
+    let offset = user_offset(layout);
+    let user = ptr.as_ptr();
+    unsafe {
+        let block = user.sub(offset);
+        let header = block.cast::<Header>();
+        match (*header).magic {
+            MAGIC_ALLOCATED => {}
+            MAGIC_FREED => panic!(
+                "heap: double free of {user:p}, allocated at {:x?}, freed at {:x?}",
+                (*header).allocated_at,
+                (*header).freed_at
+            ),
+            _ => {
+                panic!("heap: free of {user:p}, which is not allocated or its header is corrupted")
+            }
+        }
+        if (*header).size != layout.size() || (*header).align != layout.align() {
+            panic!(
+                "heap: free of {user:p} with size {} align {}, but allocated with size {} align {}, allocated at {:x?}",
+                layout.size(),
+                layout.align(),
+                (*header).size,
+                (*header).align,
+                (*header).allocated_at
+            );
+        }
+
+        let front = block.add(size_of::<Header>());
+        let front_len = offset - size_of::<Header>();
+        if let Some(index) = find_mismatch(front, front_len, CANARY_BYTE) {
+            panic!(
+                "heap: buffer underflow of {user:p} at offset -{}, allocated at {:x?}",
+                front_len - index,
+                (*header).allocated_at
+            );
+        }
+        if let Some(index) = find_mismatch(user.add(layout.size()), CANARY_SIZE, CANARY_BYTE) {
+            panic!(
+                "heap: buffer overflow of {user:p} at offset {}, allocated at {:x?}",
+                layout.size() + index,
+                (*header).allocated_at
+            );
+        }
+
+        (*header).magic = MAGIC_FREED;
+        heap.provider.capture_backtrace(&mut (*header).freed_at);
+        ptr::write_bytes(front, FREED_POISON, front_len + layout.size() + CANARY_SIZE);
+
+        let block_layout = block_layout(layout).unwrap();
+        heap.deallocate_block(NonNull::new_unchecked(block), block_layout);
+    }
+}
+
+/// 用户内存在内存块中的偏移：头部之后至少保留 [`CANARY_SIZE`] 字节的前哨兵，并对齐至layout的要求
+fn user_offset(layout: Layout) -> usize {
+    (size_of::<Header>() + CANARY_SIZE).next_multiple_of(layout.align())
+}
+
+/// 包含头部与哨兵的内存块Layout，大小溢出时返回None
+fn block_layout(layout: Layout) -> Option<Layout> {
+    let size = user_offset(layout)
+        .checked_add(layout.size())?
+        .checked_add(CANARY_SIZE)?;
+    Layout::from_size_align(size, layout.align().max(align_of::<Header>())).ok()
+}
+
+/// 内存块所在桶的大小，大内存块返回None
+fn class_size(block_layout: Layout) -> Option<usize> {
+    let effective = block_layout.align().max(block_layout.size());
+    HEAP_SIZE_CLASSES
+        .iter()
+        .copied()
+        .find(|&class_size| class_size >= effective)
+}
+
+/// 检查已释放内存块的毒化内容，发现修改时panic
+///
+/// 毒化范围由上次释放时的Layout决定，且不超过桶的大小
+unsafe fn check_freed(header: *const Header, class_size: usize) {
+    unsafe {
+        let freed = Layout::from_size_align((*header).size, (*header).align)
+            .ok()
+            .and_then(block_layout)
+            .filter(|layout| layout.size() <= class_size);
+        let Some(freed) = freed else {
+            panic!(
+                "heap: freed block {header:p} has a corrupted header, freed at {:x?}",
+                (*header).freed_at
+            );
+        };
+
+        let start = header.cast::<u8>().add(size_of::<Header>());
+        let len = freed.size() - size_of::<Header>();
+        if let Some(index) = find_mismatch(start, len, FREED_POISON) {
+            let user_offset = user_offset(Layout::from_size_align_unchecked(
+                (*header).size,
+                (*header).align,
+            ));
+            panic!(
+                "heap: use after free, block {:p} modified at offset {}, allocated at {:x?}, freed at {:x?}",
+                header.cast::<u8>().add(user_offset),
+                (size_of::<Header>() + index) as isize - user_offset as isize,
+                (*header).allocated_at,
+                (*header).freed_at
+            );
+        }
+    }
+}
+
+/// 查找第一个不等于expected的字节
+unsafe fn find_mismatch(start: *const u8, len: usize, expected: u8) -> Option<usize> {
+    unsafe { slice::from_raw_parts(start, len) }
+        .iter()
+        .position(|&byte| byte != expected)
+}
+
+#[cfg(test)]
+mod test {
+    use core::{alloc::Layout, ptr::NonNull};
+
+    use crate::{RustHeap, test::StdPageProvider};
+
+    fn allocate(heap: &mut RustHeap<StdPageProvider>, layout: Layout) -> NonNull<u8> {
+        NonNull::new(heap.allocate(layout)).unwrap()
+    }
+
+    #[test]
+    fn test_allocate() {
+        let mut heap = RustHeap::new(StdPageProvider);
+        let layouts = [
+            Layout::from_size_align(1, 1).unwrap(),
+            Layout::from_size_align(24, 8).unwrap(),
+            Layout::from_size_align(100, 64).unwrap(),
+            Layout::from_size_align(3000, 8).unwrap(),
+            Layout::from_size_align(0x1000, 0x1000).unwrap(),
+        ];
+        for _ in 0..2 {
+            for layout in layouts {
+                let ptr = allocate(&mut heap, layout);
+                assert_eq!(ptr.as_ptr() as usize % layout.align(), 0);
+                unsafe {
+                    ptr.as_ptr().write_bytes(0x5A, layout.size());
+                    heap.deallocate(ptr, layout);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "buffer overflow")]
+    fn test_overflow() {
+        let mut heap = RustHeap::new(StdPageProvider);
+        let layout = Layout::from_size_align(24, 8).unwrap();
+        let ptr = allocate(&mut heap, layout);
+        unsafe {
+            ptr.as_ptr().add(24).write(0);
+            heap.deallocate(ptr, layout);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "allocated at [c0de, 0, 0, 0], freed at [c0de")]
+    fn test_double_free() {
+        let mut heap = RustHeap::new(StdPageProvider);
+        let layout = Layout::from_size_align(24, 8).unwrap();
+        let keep = allocate(&mut heap, layout);
+        let ptr = allocate(&mut heap, layout);
+        unsafe {
+            heap.deallocate(ptr, layout);
+            heap.deallocate(ptr, layout);
+        }
+        let _ = keep;
+    }
+
+    #[test]
+    #[should_panic(expected = "use after free, block")]
+    fn test_use_after_free() {
+        let mut heap = RustHeap::new(StdPageProvider);
+        let layout = Layout::from_size_align(24, 8).unwrap();
+        let keep = allocate(&mut heap, layout);
+        let ptr = allocate(&mut heap, layout);
+        unsafe {
+            heap.deallocate(ptr, layout);
+            ptr.as_ptr().write(0);
+        }
+        // 空闲链表后进先出，再次分配取得同一内存块
+        allocate(&mut heap, layout);
+        let _ = keep;
+    }
+}
@@ -0,0 +1,185 @@
+use core::marker::PhantomData;
+
+/// 硬件抽象层
+///
+/// 硬件状态是全局的，因此所有函数均不带self。内核中的实现直接执行特权指令，
+/// 测试中使用 [`crate::mock::MockHal`]
+pub trait Hal {
+    /// 中断是否开启
+    fn interrupts_enabled() -> bool;
+
+    /// 关中断
+    fn disable_interrupts();
+
+    /// 开中断
+    ///
+    /// 调用者需保证当前可以安全开中断
+    fn enable_interrupts();
+
+    /// 当前页表（CR3）的物理地址
+    fn page_table() -> u64;
+
+    /// 切换页表
+    ///
+    /// # Safety
+    ///
+    /// 新页表必须映射当前正在执行的代码、栈以及正在访问的数据
+    unsafe fn set_page_table(page_table: u64);
+
+    /// 读取物理内存
+    ///
+    /// # Safety
+    ///
+    /// 该物理内存必须存在
+    unsafe fn read_physical(address: u64, dst: &mut [u8]);
+
+    /// 写入物理内存
+    ///
+    /// # Safety
+    ///
+    /// 该物理内存必须存在，且写入不能破坏正在使用的数据
+    unsafe fn write_physical(address: u64, src: &[u8]);
+}
+
+/// 读取物理内存中的一个u64
+///
+/// # Safety
+///
+/// 同 [`Hal::read_physical`]
+pub unsafe fn read_physical_u64<H: Hal>(address: u64) -> u64 {
+    let mut bytes = [0; 8];
+    unsafe { H::read_physical(address, &mut bytes) };
+    u64::from_le_bytes(bytes)
+}
+
+/// 写入一个u64到物理内存
+///
+/// # Safety
+///
+/// 同 [`Hal::write_physical`]
+pub unsafe fn write_physical_u64<H: Hal>(address: u64, value: u64) {
+    unsafe { H::write_physical(address, &value.to_le_bytes()) };
+}
+
+pub struct IrqGuard<H: Hal> {
+    prev: bool,
+    _hal: PhantomData<H>,
+}
+
+impl<H: Hal> IrqGuard<H> {
+    /// 关中断，并在IrqGuard释放后恢复之前的中断状态
+    pub fn cli() -> Self {
+        let prev = H::interrupts_enabled();
+        H::disable_interrupts();
+        IrqGuard {
+            prev,
+            _hal: PhantomData,
+        }
+    }
+
+    /// 开中断，并在IrqGuard释放后恢复之前的中断状态
+    ///
+    /// Safety: 调用者需保证当前可以安全开中断
+    pub fn sti() -> Self {
+        let prev = H::interrupts_enabled();
+        H::enable_interrupts();
+        IrqGuard {
+            prev,
+            _hal: PhantomData,
+        }
+    }
+}
+
+impl<H: Hal> Drop for IrqGuard<H> {
+    fn drop(&mut self) {
+        if self.prev {
+            H::enable_interrupts();
+        } else {
+            H::disable_interrupts();
+        }
+    }
+}
+
+/// 关中断后临时切换到指定页表执行f，结束后恢复原页表
+///
+/// 关中断使执行期间不会切换到其他线程，否则线程切换会覆盖页表。
+/// 目标页表即当前页表时不切换，避免无谓地刷新TLB
+///
+/// # Safety
+///
+/// 同 [`Hal::set_page_table`]
+pub unsafe fn with_page_table<H: Hal, R>(page_table: u64, f: impl FnOnce() -> R) -> R {
+    let _guard = IrqGuard::<H>::cli();
+    let prev_page_table = H::page_table();
+    if prev_page_table == page_table {
+        return f();
+    }
+    unsafe { H::set_page_table(page_table) };
+    let result = f();
+    unsafe { H::set_page_table(prev_page_table) };
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        hal::{Hal, IrqGuard, with_page_table},
+        mock::MockHal,
+    };
+
+    #[test]
+    fn test_irq_guard_restore() {
+        MockHal::reset();
+        MockHal::enable_interrupts();
+        {
+            let _outer = IrqGuard::<MockHal>::cli();
+            assert!(!MockHal::interrupts_enabled());
+            {
+                let _inner = IrqGuard::<MockHal>::cli();
+                assert!(!MockHal::interrupts_enabled());
+            }
+            // 内层恢复为外层关中断后的状态
+            assert!(!MockHal::interrupts_enabled());
+        }
+        assert!(MockHal::interrupts_enabled());
+    }
+
+    #[test]
+    fn test_irq_guard_sti() {
+        MockHal::reset();
+        {
+            let _guard = IrqGuard::<MockHal>::sti();
+            assert!(MockHal::interrupts_enabled());
+        }
+        assert!(!MockHal::interrupts_enabled());
+    }
+
+    #[test]
+    fn test_with_page_table() {
+        MockHal::reset();
+        MockHal::enable_interrupts();
+        unsafe { MockHal::set_page_table(0x1000) };
+        let writes = MockHal::page_table_writes();
+
+        let seen = unsafe {
+            with_page_table::<MockHal, _>(0x2000, || {
+                assert!(!MockHal::interrupts_enabled());
+                MockHal::page_table()
+            })
+        };
+        assert_eq!(seen, 0x2000);
+        assert_eq!(MockHal::page_table(), 0x1000);
+        assert_eq!(MockHal::page_table_writes(), writes + 2);
+        assert!(MockHal::interrupts_enabled());
+    }
+
+    #[test]
+    fn test_with_same_page_table() {
+        MockHal::reset();
+        unsafe { MockHal::set_page_table(0x1000) };
+        let writes = MockHal::page_table_writes();
+
+        unsafe { with_page_table::<MockHal, _>(0x1000, || ()) };
+        assert_eq!(MockHal::page_table_writes(), writes);
+    }
+}
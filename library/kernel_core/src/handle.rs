@@ -0,0 +1,180 @@
+use alloc::{sync::Arc, vec::Vec};
+
+/// 进程句柄表
+///
+/// 用户程序持有的句柄由槽位序号与代数组成：低32位为序号，高32位为代数。
+/// 槽位每次被释放时代数加一，因此关闭后残留的旧句柄不会指向之后在同一槽位中创建的新对象。
+pub struct HandleTable<T> {
+    slots: Vec<HandleSlot<T>>,
+    // 已占用的槽位数量
+    len: usize,
+}
+
+struct HandleSlot<T> {
+    generation: u32,
+    object: Option<Arc<T>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum HandleError {
+    /// 句柄不存在或已被关闭
+    BadHandle,
+    /// 句柄数量超出进程限制
+    TableFull,
+}
+
+impl<T> HandleTable<T> {
+    pub const fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// 插入句柄对象，返回用户句柄
+    ///
+    /// 当已有句柄数量达到capacity时，返回[`HandleError::TableFull`]
+    pub fn insert(&mut self, object: T, capacity: usize) -> Result<u64, HandleError> {
+        if self.len >= capacity {
+            return Err(HandleError::TableFull);
+        }
+
+        let index = match self.slots.iter().position(|slot| slot.object.is_none()) {
+            Some(index) => index,
+            None => {
+                // 序号只有32位
+                if self.slots.len() > u32::MAX as usize {
+                    return Err(HandleError::TableFull);
+                }
+                self.slots.push(HandleSlot {
+                    generation: 0,
+                    object: None,
+                });
+                self.slots.len() - 1
+            }
+        };
+
+        let slot = &mut self.slots[index];
+        slot.object = Some(Arc::new(object));
+        self.len += 1;
+
+        Ok(encode_handle(index, slot.generation))
+    }
+
+    /// 获取句柄对象，序号或代数不匹配时返回[`HandleError::BadHandle`]
+    pub fn get(&self, handle: u64) -> Result<Arc<T>, HandleError> {
+        let (index, generation) = decode_handle(handle);
+        self.slots
+            .get(index)
+            .filter(|slot| slot.generation == generation)
+            .and_then(|slot| slot.object.clone())
+            .ok_or(HandleError::BadHandle)
+    }
+
+    /// 移除句柄，返回被移除的句柄对象
+    pub fn remove(&mut self, handle: u64) -> Result<Arc<T>, HandleError> {
+        let (index, generation) = decode_handle(handle);
+        let slot = self
+            .slots
+            .get_mut(index)
+            .filter(|slot| slot.generation == generation)
+            .ok_or(HandleError::BadHandle)?;
+        let object = slot.object.take().ok_or(HandleError::BadHandle)?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.len -= 1;
+
+        Ok(object)
+    }
+
+    /// 遍历全部有效句柄
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &Arc<T>)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.object
+                .as_ref()
+                .map(|object| (encode_handle(index, slot.generation), object))
+        })
+    }
+
+    /// 有效句柄数量
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<T> Default for HandleTable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn encode_handle(index: usize, generation: u32) -> u64 {
+    ((generation as u64) << 32) | index as u64
+}
+
+fn decode_handle(handle: u64) -> (usize, u32) {
+    ((handle & 0xFFFF_FFFF) as usize, (handle >> 32) as u32)
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::handle::{HandleError, HandleTable};
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut table = HandleTable::new();
+        let handle = table.insert(7, 16).unwrap();
+        assert_eq!(*table.get(handle).unwrap(), 7);
+        assert_eq!(table.len(), 1);
+
+        assert_eq!(*table.remove(handle).unwrap(), 7);
+        assert!(table.is_empty());
+        assert_eq!(table.get(handle).err(), Some(HandleError::BadHandle));
+        assert_eq!(table.remove(handle).err(), Some(HandleError::BadHandle));
+    }
+
+    #[test]
+    fn test_stale_handle() {
+        let mut table = HandleTable::new();
+        let old = table.insert(1, 16).unwrap();
+        table.remove(old).unwrap();
+
+        // 新对象复用同一槽位，但代数不同
+        let new = table.insert(2, 16).unwrap();
+        assert_eq!(old & 0xFFFF_FFFF, new & 0xFFFF_FFFF);
+        assert_ne!(old, new);
+        assert_eq!(table.get(old).err(), Some(HandleError::BadHandle));
+        assert_eq!(*table.get(new).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut table = HandleTable::new();
+        let first = table.insert(1, 2).unwrap();
+        table.insert(2, 2).unwrap();
+        assert_eq!(table.insert(3, 2).err(), Some(HandleError::TableFull));
+
+        table.remove(first).unwrap();
+        table.insert(3, 2).unwrap();
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut table = HandleTable::new();
+        let a = table.insert('a', 16).unwrap();
+        let b = table.insert('b', 16).unwrap();
+        let c = table.insert('c', 16).unwrap();
+        table.remove(b).unwrap();
+
+        let handles = table
+            .iter()
+            .map(|(handle, object)| (handle, **object))
+            .collect::<Vec<_>>();
+        assert_eq!(handles, [(a, 'a'), (c, 'c')]);
+    }
+}
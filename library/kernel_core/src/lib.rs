@@ -0,0 +1,21 @@
+//! 内核中与硬件无关的逻辑
+//!
+//! 线程调度、句柄表与页表遍历等逻辑原本与内联汇编、全局变量交织在内核中，无法在宿主机上测试。
+//! 此crate通过 [`hal::Hal`] 抽象中断控制、页表寄存器与物理内存访问，将这些逻辑从内核中分离出来。
+//!
+//! 内核提供直接执行特权指令的实现；启用`mock` feature（或运行此crate的测试）时，
+//! [`mock::MockHal`] 在宿主机上模拟这些硬件。
+#![no_std]
+
+extern crate alloc;
+#[cfg(any(test, feature = "mock"))]
+extern crate std;
+
+pub mod hal;
+pub mod handle;
+pub mod lockdep;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+pub mod paging;
+pub mod sched;
+pub mod wait_queue;
@@ -0,0 +1,153 @@
+use alloc::{
+    collections::{btree_map::BTreeMap, vec_deque::VecDeque},
+    vec::Vec,
+};
+
+/// 锁依赖图
+///
+/// 线程持有锁A时获取锁B，记录一条A到B的边，表示A先于B获取。获取锁前检查图中是否已存在
+/// 从B到A的路径：若存在，说明曾有线程以相反的顺序获取这些锁，两者并发时可能死锁。
+///
+/// 锁以K标识（通常为创建锁的位置，同一处创建的锁视为同一类），每条边保存首次建立该顺序时的T（通常为调用栈）
+pub struct LockGraph<K, T> {
+    // 先获取的锁 -> [(后获取的锁, 首次建立该顺序时的记录)]
+    edges: BTreeMap<K, Vec<(K, T)>>,
+}
+
+/// 与当前获取顺序相反的已有路径，见 [LockGraph::check]
+pub struct Conflict<'graph, K, T> {
+    /// 路径上的各条边，依次为(先获取的锁, 后获取的锁, 建立该顺序时的记录)
+    pub path: Vec<(K, K, &'graph T)>,
+}
+
+impl<K: Ord + Copy, T> LockGraph<K, T> {
+    pub const fn new() -> Self {
+        Self {
+            edges: BTreeMap::new(),
+        }
+    }
+
+    /// 检查持有held时获取lock是否与已有的顺序冲突，即是否存在从lock到held的路径
+    ///
+    /// 同一类锁之间不检查：同一处创建的多个锁实例嵌套获取是常见的用法
+    pub fn check(&self, held: K, lock: K) -> Option<Conflict<'_, K, T>> {
+        if held == lock {
+            return None;
+        }
+        // 广度优先搜索，记录到达每个节点的边，找到held后回溯出路径
+        let mut parents: BTreeMap<K, (K, &T)> = BTreeMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(lock);
+        while let Some(node) = queue.pop_front() {
+            for (next, record) in self.edges.get(&node).into_iter().flatten() {
+                if *next == lock || parents.contains_key(next) {
+                    continue;
+                }
+                parents.insert(*next, (node, record));
+                if *next == held {
+                    let mut path = Vec::new();
+                    let mut current = held;
+                    while current != lock {
+                        let (parent, record) = parents[&current];
+                        path.push((parent, current, record));
+                        current = parent;
+                    }
+                    path.reverse();
+                    return Some(Conflict { path });
+                }
+                queue.push_back(*next);
+            }
+        }
+        None
+    }
+
+    /// 记录持有held时获取lock，已有该边时不重复记录，record仅在新建边时调用
+    ///
+    /// 返回是否新建了边
+    pub fn add(&mut self, held: K, lock: K, record: impl FnOnce() -> T) -> bool {
+        if held == lock {
+            return false;
+        }
+        let edges = self.edges.entry(held).or_default();
+        if edges.iter().any(|(next, _)| *next == lock) {
+            return false;
+        }
+        edges.push((lock, record()));
+        true
+    }
+
+    /// 图中边的数量
+    pub fn edge_count(&self) -> usize {
+        self.edges.values().map(Vec::len).sum()
+    }
+}
+
+impl<K: Ord + Copy, T> Default for LockGraph<K, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::lockdep::LockGraph;
+
+    fn path(
+        graph: &LockGraph<u32, &'static str>,
+        held: u32,
+        lock: u32,
+    ) -> Option<Vec<(u32, u32, &'static str)>> {
+        graph.check(held, lock).map(|conflict| {
+            conflict
+                .path
+                .into_iter()
+                .map(|(from, to, record)| (from, to, *record))
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_consistent_order() {
+        let mut graph = LockGraph::new();
+        assert!(graph.add(1, 2, || "a"));
+        assert!(!graph.add(1, 2, || "b"));
+        assert!(graph.add(2, 3, || "c"));
+        assert_eq!(graph.edge_count(), 2);
+        // 始终按1、2、3的顺序获取不会冲突
+        assert!(path(&graph, 1, 3).is_none());
+        assert!(path(&graph, 1, 2).is_none());
+        // 同一类锁嵌套获取不检查
+        assert!(path(&graph, 2, 2).is_none());
+        assert!(!graph.add(2, 2, || "d"));
+    }
+
+    #[test]
+    fn test_reverse_order() {
+        let mut graph = LockGraph::new();
+        graph.add(1, 2, || "lock 2 holding 1");
+        // 持有2时获取1，与已有的顺序相反
+        assert_eq!(
+            path(&graph, 2, 1),
+            Some([(1, 2, "lock 2 holding 1")].into())
+        );
+    }
+
+    #[test]
+    fn test_transitive_cycle() {
+        let mut graph = LockGraph::new();
+        graph.add(1, 2, || "a");
+        graph.add(2, 3, || "b");
+        graph.add(4, 1, || "c");
+        // 持有3时获取1：已有1 -> 2 -> 3
+        assert_eq!(path(&graph, 3, 1), Some([(1, 2, "a"), (2, 3, "b")].into()));
+        // 持有3时获取4：已有4 -> 1 -> 2 -> 3
+        assert_eq!(
+            path(&graph, 3, 4),
+            Some([(4, 1, "c"), (1, 2, "a"), (2, 3, "b")].into())
+        );
+        // 持有4时获取3与已有顺序一致
+        assert!(path(&graph, 4, 3).is_none());
+    }
+}
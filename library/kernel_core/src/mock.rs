@@ -0,0 +1,151 @@
+use core::cell::RefCell;
+
+use alloc::{collections::btree_map::BTreeMap, vec, vec::Vec};
+
+use crate::hal::Hal;
+
+const PAGE_SIZE: u64 = 0x1000;
+
+std::thread_local! {
+    static STATE: RefCell<MockState> = const { RefCell::new(MockState::new()) };
+}
+
+struct MockState {
+    interrupts_enabled: bool,
+    page_table: u64,
+    // 切换页表的次数
+    page_table_writes: usize,
+    // 按页存放的物理内存，未写入过的页读出为0
+    memory: BTreeMap<u64, Vec<u8>>,
+    // 下一个由alloc_frame分配的物理页
+    next_frame: u64,
+}
+
+impl MockState {
+    const fn new() -> Self {
+        Self {
+            interrupts_enabled: false,
+            page_table: 0,
+            page_table_writes: 0,
+            memory: BTreeMap::new(),
+            // 不分配0地址，以便与空页表项区分
+            next_frame: PAGE_SIZE,
+        }
+    }
+}
+
+/// 在宿主机上模拟的硬件
+///
+/// 状态按线程隔离，并行运行的测试互不影响。每个测试开始时应调用 [`MockHal::reset`]，
+/// 因为测试框架可能在同一线程上依次运行多个测试
+pub struct MockHal;
+
+impl MockHal {
+    /// 恢复初始状态：关中断，页表为0，物理内存全部为0
+    pub fn reset() {
+        STATE.with_borrow_mut(|state| *state = MockState::new());
+    }
+
+    /// 切换页表的次数
+    pub fn page_table_writes() -> usize {
+        STATE.with_borrow(|state| state.page_table_writes)
+    }
+
+    /// 分配一个清零的物理页，返回其物理地址
+    pub fn alloc_frame() -> u64 {
+        STATE.with_borrow_mut(|state| {
+            let frame = state.next_frame;
+            state.next_frame += PAGE_SIZE;
+            state.memory.insert(frame, vec![0; PAGE_SIZE as usize]);
+            frame
+        })
+    }
+}
+
+impl Hal for MockHal {
+    fn interrupts_enabled() -> bool {
+        STATE.with_borrow(|state| state.interrupts_enabled)
+    }
+
+    fn disable_interrupts() {
+        STATE.with_borrow_mut(|state| state.interrupts_enabled = false);
+    }
+
+    fn enable_interrupts() {
+        STATE.with_borrow_mut(|state| state.interrupts_enabled = true);
+    }
+
+    fn page_table() -> u64 {
+        STATE.with_borrow(|state| state.page_table)
+    }
+
+    unsafe fn set_page_table(page_table: u64) {
+        STATE.with_borrow_mut(|state| {
+            state.page_table = page_table;
+            state.page_table_writes += 1;
+        });
+    }
+
+    unsafe fn read_physical(address: u64, dst: &mut [u8]) {
+        STATE.with_borrow(|state| {
+            for (offset, byte) in dst.iter_mut().enumerate() {
+                let address = address + offset as u64;
+                let page = address & !(PAGE_SIZE - 1);
+                *byte = state
+                    .memory
+                    .get(&page)
+                    .map_or(0, |frame| frame[(address - page) as usize]);
+            }
+        });
+    }
+
+    unsafe fn write_physical(address: u64, src: &[u8]) {
+        STATE.with_borrow_mut(|state| {
+            for (offset, &byte) in src.iter().enumerate() {
+                let address = address + offset as u64;
+                let page = address & !(PAGE_SIZE - 1);
+                state
+                    .memory
+                    .entry(page)
+                    .or_insert_with(|| vec![0; PAGE_SIZE as usize])[(address - page) as usize] =
+                    byte;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        hal::{Hal, read_physical_u64, write_physical_u64},
+        mock::MockHal,
+    };
+
+    #[test]
+    fn test_physical_memory() {
+        MockHal::reset();
+        let frame = MockHal::alloc_frame();
+        assert_ne!(frame, 0);
+        assert_ne!(MockHal::alloc_frame(), frame);
+
+        unsafe {
+            assert_eq!(read_physical_u64::<MockHal>(frame + 8), 0);
+            write_physical_u64::<MockHal>(frame + 8, 0x1122_3344_5566_7788);
+            assert_eq!(
+                read_physical_u64::<MockHal>(frame + 8),
+                0x1122_3344_5566_7788
+            );
+        }
+    }
+
+    #[test]
+    fn test_physical_memory_cross_page() {
+        MockHal::reset();
+        let mut bytes = [0; 4];
+        unsafe {
+            MockHal::write_physical(0x1FFE, &[1, 2, 3, 4]);
+            MockHal::read_physical(0x1FFE, &mut bytes);
+        }
+        assert_eq!(bytes, [1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::hal::{Hal, read_physical_u64};
+
+/// 页表项的存在位
+pub const P_PRESENT: u64 = 1 << 0;
+/// 页表项的可写位
+pub const P_RW: u64 = 1 << 1;
+/// 页表项的用户位
+pub const P_US: u64 = 1 << 2;
+/// 页表项中下一级页表或页的物理地址
+pub const ADDRESS_MASK: u64 = 0x000F_FFFF_FFFF_F000;
+
+/// 遍历四级页表，获取虚拟地址对应的1级页表项
+///
+/// 返回的页表项中，P_RW与P_US为各级页表项对应位的交集，即CPU实际生效的访问权限。
+/// 任意一级页表项不存在时返回None。不支持大页
+///
+/// # Safety
+///
+/// page_table必须为有效的4级页表的物理地址
+pub unsafe fn effective_entry<H: Hal>(page_table: u64, virtual_address: u64) -> Option<u64> {
+    let mut table = page_table;
+    let mut permission = P_RW | P_US;
+    let mut entry = 0;
+    // 依次为PML4、PDPT、PD、PT中的下标
+    for shift in [39, 30, 21, 12] {
+        let index = (virtual_address >> shift) & 0x1ff;
+        entry = unsafe { read_physical_u64::<H>(table + index * 8) };
+        if entry & P_PRESENT == 0 {
+            return None;
+        }
+        permission &= entry;
+        table = entry & ADDRESS_MASK;
+    }
+
+    Some((entry & !(P_RW | P_US)) | permission)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        hal::write_physical_u64,
+        mock::MockHal,
+        paging::{P_PRESENT, P_RW, P_US, effective_entry},
+    };
+
+    /// 在模拟的物理内存中为虚拟地址建立映射，返回页表
+    fn map(virtual_address: u64, frame: u64, flags: [u64; 4]) -> u64 {
+        let page_table = MockHal::alloc_frame();
+        let mut table = page_table;
+        for (level, shift) in [39, 30, 21, 12].into_iter().enumerate() {
+            let index = (virtual_address >> shift) & 0x1ff;
+            let next = if level == 3 {
+                frame
+            } else {
+                MockHal::alloc_frame()
+            };
+            unsafe { write_physical_u64::<MockHal>(table + index * 8, next | flags[level]) };
+            table = next;
+        }
+        page_table
+    }
+
+    #[test]
+    fn test_effective_entry() {
+        MockHal::reset();
+        let all = P_PRESENT | P_RW | P_US;
+        let page_table = map(0x1234_5000, 0x8000_0000, [all; 4]);
+
+        let entry = unsafe { effective_entry::<MockHal>(page_table, 0x1234_5678) }.unwrap();
+        assert_eq!(entry, 0x8000_0000 | all);
+        assert!(unsafe { effective_entry::<MockHal>(page_table, 0x1234_6000) }.is_none());
+    }
+
+    #[test]
+    fn test_effective_permission() {
+        MockHal::reset();
+        let all = P_PRESENT | P_RW | P_US;
+        // PDPT不可写，PD仅内核可访问，则页实际上只读且仅内核可访问
+        let page_table = map(
+            0x4000_0000,
+            0x9000_0000,
+            [all, P_PRESENT | P_US, P_PRESENT | P_RW, all],
+        );
+
+        let entry = unsafe { effective_entry::<MockHal>(page_table, 0x4000_0000) }.unwrap();
+        assert_eq!(entry, 0x9000_0000 | P_PRESENT);
+    }
+
+    #[test]
+    fn test_not_present() {
+        MockHal::reset();
+        let all = P_PRESENT | P_RW | P_US;
+        let page_table = map(0x4000_0000, 0x9000_0000, [all, all, all, P_RW | P_US]);
+
+        assert!(unsafe { effective_entry::<MockHal>(page_table, 0x4000_0000) }.is_none());
+    }
+}
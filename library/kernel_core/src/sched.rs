@@ -0,0 +1,197 @@
+// 线程状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadStatus {
+    Ready,       // 就绪，调度器可以将此线程调度到CPU上执行
+    Running,     // 执行，此线程当前正在CPU上执行
+    Suspend,     // 挂起，此线程正在等待某项资源而被暂停执行
+    Terminating, // 停止中，此线程已被要求停止，但它仍在占用CPU，在其离开CPU后会被终止
+    Terminated,  // 终止，此线程已被终止，但其仍持有资源。稍后内核将对其进行清理
+}
+
+impl ThreadStatus {
+    /// 线程离开CPU后的状态
+    ///
+    /// 已被要求停止的线程变为终止，否则根据是否挂起变为挂起或就绪
+    pub fn switched_out(self, suspend: bool) -> Self {
+        match self {
+            ThreadStatus::Terminating => ThreadStatus::Terminated,
+            _ if suspend => ThreadStatus::Suspend,
+            _ => ThreadStatus::Ready,
+        }
+    }
+
+    /// 线程被调度到CPU上后的状态
+    ///
+    /// 只有就绪的线程变为执行。停止中的线程仍需运行到安全点才能退出，保持停止中
+    pub fn switched_in(self) -> Self {
+        match self {
+            ThreadStatus::Ready => ThreadStatus::Running,
+            status => status,
+        }
+    }
+
+    /// 唤醒挂起的线程
+    ///
+    /// 返回线程是否因此变为就绪，此时调用方需将其加入就绪队列
+    pub fn wake(&mut self) -> bool {
+        if *self == ThreadStatus::Suspend {
+            *self = ThreadStatus::Ready;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 要求线程停止
+    ///
+    /// 线程已终止或正在停止时返回false
+    pub fn stop(&mut self) -> bool {
+        if matches!(self, ThreadStatus::Terminated | ThreadStatus::Terminating) {
+            return false;
+        }
+        *self = ThreadStatus::Terminating;
+        true
+    }
+}
+
+/// 从就绪队列中选出下一个运行的线程
+///
+/// next依次取出队列中的线程，返回None表示队列已空。resolve检查取出的线程是否仍可运行
+/// （线程可能已被回收，或在入队后被停止），不可运行的线程被丢弃。
+///
+/// 没有可运行的线程时，如果当前线程将被挂起，则改为运行idle给出的IDLE线程；
+/// 否则返回None，表示当前线程继续运行
+pub fn pick_next<Q, T>(
+    mut next: impl FnMut() -> Option<Q>,
+    mut resolve: impl FnMut(Q) -> Option<T>,
+    suspend: bool,
+    idle: impl FnOnce() -> Option<T>,
+) -> Option<T> {
+    while let Some(candidate) = next() {
+        if let Some(thread) = resolve(candidate) {
+            return Some(thread);
+        }
+    }
+    if suspend { idle() } else { None }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::{collections::vec_deque::VecDeque, vec, vec::Vec};
+
+    use crate::sched::{ThreadStatus, pick_next};
+
+    #[test]
+    fn test_switched_out() {
+        assert_eq!(
+            ThreadStatus::Running.switched_out(false),
+            ThreadStatus::Ready
+        );
+        assert_eq!(
+            ThreadStatus::Running.switched_out(true),
+            ThreadStatus::Suspend
+        );
+        assert_eq!(
+            ThreadStatus::Terminating.switched_out(false),
+            ThreadStatus::Terminated
+        );
+        assert_eq!(
+            ThreadStatus::Terminating.switched_out(true),
+            ThreadStatus::Terminated
+        );
+    }
+
+    #[test]
+    fn test_switched_in() {
+        assert_eq!(ThreadStatus::Ready.switched_in(), ThreadStatus::Running);
+        assert_eq!(
+            ThreadStatus::Terminating.switched_in(),
+            ThreadStatus::Terminating
+        );
+    }
+
+    #[test]
+    fn test_wake() {
+        let mut status = ThreadStatus::Suspend;
+        assert!(status.wake());
+        assert_eq!(status, ThreadStatus::Ready);
+        // 重复唤醒不会使线程重复入队
+        assert!(!status.wake());
+
+        let mut status = ThreadStatus::Terminating;
+        assert!(!status.wake());
+        assert_eq!(status, ThreadStatus::Terminating);
+    }
+
+    #[test]
+    fn test_stop() {
+        let mut status = ThreadStatus::Suspend;
+        assert!(status.stop());
+        assert_eq!(status, ThreadStatus::Terminating);
+        assert!(!status.stop());
+
+        let mut status = ThreadStatus::Terminated;
+        assert!(!status.stop());
+        assert_eq!(status, ThreadStatus::Terminated);
+    }
+
+    /// 模拟就绪队列：None表示已被回收的线程
+    fn pick(
+        queue: &mut VecDeque<Option<(u64, ThreadStatus)>>,
+        suspend: bool,
+        idle: Option<u64>,
+    ) -> Option<u64> {
+        pick_next(
+            || queue.pop_front(),
+            |thread| thread.filter(|(_, status)| *status == ThreadStatus::Ready),
+            suspend,
+            || idle.map(|id| (id, ThreadStatus::Running)),
+        )
+        .map(|(id, _)| id)
+    }
+
+    #[test]
+    fn test_pick_next_fifo() {
+        let mut queue = VecDeque::from(vec![
+            Some((1, ThreadStatus::Ready)),
+            Some((2, ThreadStatus::Ready)),
+        ]);
+        assert_eq!(pick(&mut queue, false, None), Some(1));
+        assert_eq!(pick(&mut queue, false, None), Some(2));
+        assert_eq!(pick(&mut queue, false, None), None);
+    }
+
+    #[test]
+    fn test_pick_next_skip_stale() {
+        let mut queue = VecDeque::from(vec![
+            None,
+            Some((1, ThreadStatus::Terminating)),
+            Some((2, ThreadStatus::Suspend)),
+            Some((3, ThreadStatus::Ready)),
+            Some((4, ThreadStatus::Ready)),
+        ]);
+        assert_eq!(pick(&mut queue, false, None), Some(3));
+        // 不可运行的线程已被丢弃
+        assert_eq!(
+            queue
+                .iter()
+                .flatten()
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>(),
+            [4]
+        );
+    }
+
+    #[test]
+    fn test_pick_next_idle() {
+        let mut queue = VecDeque::from(vec![Some((1, ThreadStatus::Suspend))]);
+        // 当前线程继续运行时不切换到IDLE线程
+        assert_eq!(pick(&mut queue, false, Some(100)), None);
+
+        let mut queue = VecDeque::from(vec![Some((1, ThreadStatus::Suspend))]);
+        assert_eq!(pick(&mut queue, true, Some(100)), Some(100));
+
+        let mut queue = VecDeque::from(vec![Some((1, ThreadStatus::Ready))]);
+        assert_eq!(pick(&mut queue, true, Some(100)), Some(1));
+    }
+}
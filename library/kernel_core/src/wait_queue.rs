@@ -0,0 +1,215 @@
+use alloc::{collections::vec_deque::VecDeque, vec::Vec};
+
+/// 线程优先级，数值越大优先级越高
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Priority(pub u8);
+
+impl Priority {
+    pub const LOW: Self = Self(64);
+    pub const NORMAL: Self = Self(128);
+    pub const HIGH: Self = Self(192);
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Self::NORMAL
+    }
+}
+
+/// 等待队列
+///
+/// 等待者按优先级从高到低排列，优先级相同时先进先出。
+/// 队列只负责排序，挂起与唤醒线程由内核完成
+pub struct WaitQueue<T> {
+    waiters: VecDeque<(Priority, T)>,
+}
+
+impl<T> WaitQueue<T> {
+    pub const fn new() -> Self {
+        Self {
+            waiters: VecDeque::new(),
+        }
+    }
+
+    /// 加入等待者，排在所有优先级不低于它的等待者之后
+    pub fn push(&mut self, waiter: T, priority: Priority) {
+        let index = self
+            .waiters
+            .iter()
+            .position(|(waiting, _)| *waiting < priority)
+            .unwrap_or(self.waiters.len());
+        self.waiters.insert(index, (priority, waiter));
+    }
+
+    /// 取出优先级最高且最早加入的等待者
+    pub fn pop(&mut self) -> Option<T> {
+        self.waiters.pop_front().map(|(_, waiter)| waiter)
+    }
+
+    /// 移除第一个满足条件的等待者，用于等待者放弃等待（如超时或线程被停止）
+    pub fn remove(&mut self, mut predicate: impl FnMut(&T) -> bool) -> Option<T> {
+        let index = self
+            .waiters
+            .iter()
+            .position(|(_, waiter)| predicate(waiter))?;
+        self.waiters.remove(index).map(|(_, waiter)| waiter)
+    }
+
+    /// 队首等待者的优先级，即队列中的最高优先级
+    pub fn highest_priority(&self) -> Option<Priority> {
+        self.waiters.front().map(|(priority, _)| *priority)
+    }
+
+    pub fn len(&self) -> usize {
+        self.waiters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiters.is_empty()
+    }
+}
+
+impl<T> Default for WaitQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 线程因优先级继承获得的提升
+///
+/// 线程持有锁时，锁的等待者中的最高优先级被继承给持有者，避免中等优先级的线程抢占持有者，
+/// 使高优先级的等待者无限期地等待（优先级反转）。线程可能同时持有多把锁，
+/// 因此按锁分别记录，释放其中一把锁时只撤销这把锁带来的提升
+#[derive(Debug, Default)]
+pub struct PriorityBoosts {
+    // (锁的标识, 继承的优先级)
+    boosts: Vec<(usize, Priority)>,
+}
+
+impl PriorityBoosts {
+    pub const fn new() -> Self {
+        Self { boosts: Vec::new() }
+    }
+
+    /// 记录锁的等待者带来的提升，同一把锁只保留最高的优先级
+    pub fn boost(&mut self, lock: usize, priority: Priority) {
+        match self.boosts.iter_mut().find(|(id, _)| *id == lock) {
+            Some((_, boosted)) => *boosted = (*boosted).max(priority),
+            None => self.boosts.push((lock, priority)),
+        }
+    }
+
+    /// 将锁带来的提升设为指定值，None表示撤销，用于释放锁或等待者离开后重新计算
+    pub fn set(&mut self, lock: usize, priority: Option<Priority>) {
+        self.boosts.retain(|(id, _)| *id != lock);
+        if let Some(priority) = priority {
+            self.boosts.push((lock, priority));
+        }
+    }
+
+    /// 基础优先级为base时的有效优先级
+    pub fn effective(&self, base: Priority) -> Priority {
+        self.boosts
+            .iter()
+            .map(|(_, priority)| *priority)
+            .fold(base, Priority::max)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::wait_queue::{Priority, PriorityBoosts, WaitQueue};
+
+    fn drain(queue: &mut WaitQueue<u64>) -> Vec<u64> {
+        let mut waiters = Vec::new();
+        while let Some(waiter) = queue.pop() {
+            waiters.push(waiter);
+        }
+        waiters
+    }
+
+    #[test]
+    fn test_fifo() {
+        let mut queue = WaitQueue::new();
+        for id in 1..=4 {
+            queue.push(id, Priority::NORMAL);
+        }
+        assert_eq!(queue.len(), 4);
+        assert_eq!(drain(&mut queue), [1, 2, 3, 4]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_priority_order() {
+        let mut queue = WaitQueue::new();
+        queue.push(1, Priority::LOW);
+        queue.push(2, Priority::NORMAL);
+        queue.push(3, Priority::HIGH);
+        queue.push(4, Priority::NORMAL);
+        queue.push(5, Priority::LOW);
+        queue.push(6, Priority::HIGH);
+        assert_eq!(queue.highest_priority(), Some(Priority::HIGH));
+        // 高优先级在前，同优先级保持加入顺序
+        assert_eq!(drain(&mut queue), [3, 6, 2, 4, 1, 5]);
+        assert_eq!(queue.highest_priority(), None);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut queue = WaitQueue::new();
+        queue.push(1, Priority::HIGH);
+        queue.push(2, Priority::LOW);
+        queue.push(3, Priority::NORMAL);
+        assert_eq!(queue.remove(|id| *id == 1), Some(1));
+        assert_eq!(queue.remove(|id| *id == 1), None);
+        assert_eq!(queue.highest_priority(), Some(Priority::NORMAL));
+        assert_eq!(drain(&mut queue), [3, 2]);
+    }
+
+    #[test]
+    fn test_inheritance_three_priorities() {
+        // 低优先级线程持有锁，高优先级线程等待，中优先级线程就绪
+        let low = Priority::LOW;
+        let mut holder = PriorityBoosts::new();
+        let mut queue = WaitQueue::new();
+        queue.push("high", Priority::HIGH);
+        holder.boost(0x1000, queue.highest_priority().unwrap());
+
+        // 持有者继承高优先级，中优先级线程无法再抢占它
+        assert_eq!(holder.effective(low), Priority::HIGH);
+        assert!(holder.effective(low) > Priority::NORMAL);
+
+        // 中优先级线程随后也开始等待，排在高优先级之后，不降低已继承的优先级
+        queue.push("normal", Priority::NORMAL);
+        holder.boost(0x1000, queue.highest_priority().unwrap());
+        assert_eq!(holder.effective(low), Priority::HIGH);
+
+        // 释放锁：撤销提升，锁交给高优先级线程，它继承剩余等待者的优先级
+        holder.set(0x1000, None);
+        assert_eq!(holder.effective(low), low);
+        assert_eq!(queue.pop(), Some("high"));
+        let mut next = PriorityBoosts::new();
+        next.set(0x1000, queue.highest_priority());
+        assert_eq!(next.effective(Priority::HIGH), Priority::HIGH);
+        assert_eq!(queue.pop(), Some("normal"));
+    }
+
+    #[test]
+    fn test_inheritance_nested_locks() {
+        let mut holder = PriorityBoosts::new();
+        holder.boost(0x1000, Priority::NORMAL);
+        holder.boost(0x2000, Priority::HIGH);
+        holder.boost(0x1000, Priority::LOW);
+        assert_eq!(holder.effective(Priority::LOW), Priority::HIGH);
+
+        // 释放第二把锁后，仍保留第一把锁的等待者带来的提升
+        holder.set(0x2000, None);
+        assert_eq!(holder.effective(Priority::LOW), Priority::NORMAL);
+        // 基础优先级更高时不被降低
+        assert_eq!(holder.effective(Priority::HIGH), Priority::HIGH);
+        holder.set(0x1000, None);
+        assert_eq!(holder.effective(Priority::LOW), Priority::LOW);
+    }
+}
@@ -0,0 +1,62 @@
+use alloc::{boxed::Box, collections::vec_deque::VecDeque, vec::Vec};
+use async_locks::{condvar::Condvar, mutex::Mutex};
+
+use crate::{
+    BoxFuture,
+    device::{DeviceError, NetworkDevice},
+    wire::{MacAddress, ethernet},
+};
+
+/// 回环设备
+///
+/// 发出的帧会被同一设备重新接收。协议栈连接本机地址时，ARP请求与数据报均经由此设备回到协议栈自身，
+/// 因此可以在宿主机上不借助任何网卡测试完整的收发流程。
+pub struct LoopbackDevice {
+    mac_address: MacAddress,
+    queue: Mutex<VecDeque<Vec<u8>>>,
+    readable: Condvar,
+}
+
+impl LoopbackDevice {
+    pub fn new(mac_address: MacAddress) -> Self {
+        Self {
+            mac_address,
+            queue: Mutex::new(VecDeque::new()),
+            readable: Condvar::new(),
+        }
+    }
+}
+
+impl NetworkDevice for LoopbackDevice {
+    fn mac_address(&self) -> MacAddress {
+        self.mac_address
+    }
+
+    fn transmit<'fut>(&'fut self, frame: &'fut [u8]) -> BoxFuture<'fut, Result<(), DeviceError>> {
+        Box::pin(async move {
+            if frame.len() > self.mtu() + ethernet::HEADER_LEN {
+                return Err(DeviceError::FrameTooLarge);
+            }
+            self.queue.lock().await.push_back(frame.to_vec());
+            self.readable.wake_all();
+            Ok(())
+        })
+    }
+
+    fn receive<'fut>(
+        &'fut self,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<usize, DeviceError>> {
+        Box::pin(async move {
+            let mut queue = self.queue.lock().await;
+            loop {
+                if let Some(frame) = queue.pop_front() {
+                    let len = frame.len().min(buf.len());
+                    buf[..len].copy_from_slice(&frame[..len]);
+                    return Ok(len);
+                }
+                queue = self.readable.wait(queue).await;
+            }
+        })
+    }
+}
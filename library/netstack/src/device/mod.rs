@@ -0,0 +1,54 @@
+use crate::{BoxFuture, wire::MacAddress};
+
+pub mod loopback;
+
+/// 网络设备的抽象
+///
+/// 此trait表示一个以太网设备，收发的数据均为完整的以太网帧（不含前导码与FCS）。
+/// 它的实现可以是物理网卡，可以是虚拟网卡，也可以是测试用的内存设备。
+///
+/// 此trait为dyn safe的，可以进行动态分发。
+///
+/// # Cancel Safety
+/// [`NetworkDevice::receive`] 应当是取消安全的：取消接收时，尚未交付的帧应保留在设备中，
+/// 供下一次接收使用。[`NetworkDevice::transmit`] 被取消时，帧可能已经发出，也可能没有发出。
+pub trait NetworkDevice: Send + Sync + 'static {
+    /// 设备的MAC地址
+    ///
+    /// MAC地址不能被修改。调用方可以假设每次调用此函数均返回相同的值。
+    fn mac_address(&self) -> MacAddress;
+
+    /// 设备支持的最大传输单元，即以太网帧负载的最大长度
+    fn mtu(&self) -> usize {
+        1500
+    }
+
+    /// 发送一个以太网帧
+    ///
+    /// `frame`为包含以太网头部的完整帧，长度不超过`mtu() + 14`。
+    /// 设备的发送队列已满时，等待至队列有空闲位置。
+    fn transmit<'fut>(&'fut self, frame: &'fut [u8]) -> BoxFuture<'fut, Result<(), DeviceError>>;
+
+    /// 接收一个以太网帧，返回帧的长度
+    ///
+    /// 没有可接收的帧时，等待至收到新的帧。
+    /// `buf`的长度至少为`mtu() + 14`，帧长度超过缓冲区长度时，超出部分被丢弃。
+    fn receive<'fut>(
+        &'fut self,
+        buf: &'fut mut [u8],
+    ) -> BoxFuture<'fut, Result<usize, DeviceError>>;
+}
+
+/// 网络设备访问错误
+#[derive(Debug, Clone, Copy)]
+pub enum DeviceError {
+    /// 帧长度超出设备限制
+    FrameTooLarge,
+    /// 内存不足
+    OutOfMemory,
+    /// 底层IO错误
+    IoError,
+}
+
+// 断言NetworkDevice是dyn safe的
+const _: fn(&dyn NetworkDevice) -> &dyn NetworkDevice = |x| x;
@@ -0,0 +1,100 @@
+//! 网络协议栈的实现
+//!
+//! 此crate实现了以太网、ARP、IPv4、UDP与TCP协议，并向上层提供UDP套接字与TCP流。
+//!
+//! 与文件系统类似，此crate不访问硬件——网卡的收发由 [`device::NetworkDevice`] 的实现者负责，
+//! 时间由 [`time::Clock`] 的实现者提供。协议栈的全部操作均为异步的，接收数据需要由调用方持续运行
+//! [`stack::NetStack::run`]，TCP的重传与超时需要由调用方持续运行 [`stack::NetStack::run_timers`]。
+#![no_std]
+use core::pin::Pin;
+
+use alloc::boxed::Box;
+
+extern crate alloc;
+#[cfg(test)]
+extern crate std;
+
+pub mod device;
+pub mod socket;
+pub mod stack;
+pub mod time;
+pub mod wire;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+#[cfg(test)]
+pub(crate) fn run_task<F: Future>(f: F) -> F::Output {
+    use core::{
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+
+    let waker = Waker::noop();
+    let mut ctx = Context::from_waker(waker);
+    let mut f = pin!(f);
+    loop {
+        match f.as_mut().poll(&mut ctx) {
+            Poll::Ready(v) => return v,
+            Poll::Pending => {}
+        }
+    }
+}
+
+/// 轮询`f`直至完成，每次轮询`f`后依次轮询`background`中未完成的任务
+///
+/// 轮询次数超过上限时panic，避免测试因死锁而永远不结束。
+#[cfg(test)]
+pub(crate) fn run_tasks<F: Future>(
+    mut background: alloc::vec::Vec<BoxFuture<'_, ()>>,
+    f: F,
+) -> F::Output {
+    use core::{
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+
+    let waker = Waker::noop();
+    let mut ctx = Context::from_waker(waker);
+    let mut f = pin!(f);
+    for _ in 0..10_000_000 {
+        if let Poll::Ready(v) = f.as_mut().poll(&mut ctx) {
+            return v;
+        }
+        background.retain_mut(|task| task.as_mut().poll(&mut ctx).is_pending());
+    }
+    panic!("task did not complete");
+}
+
+/// 测试用的时钟，仅在调用 [`ManualClock::advance`] 时前进
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct ManualClock(alloc::sync::Arc<core::sync::atomic::AtomicU64>);
+
+#[cfg(test)]
+impl ManualClock {
+    pub(crate) fn advance(&self, duration: core::time::Duration) {
+        self.0.fetch_add(
+            duration.as_micros() as u64,
+            core::sync::atomic::Ordering::Relaxed,
+        );
+    }
+}
+
+#[cfg(test)]
+impl time::Clock for ManualClock {
+    fn now(&self) -> core::time::Duration {
+        core::time::Duration::from_micros(self.0.load(core::sync::atomic::Ordering::Relaxed))
+    }
+
+    fn sleep(&self, duration: core::time::Duration) -> BoxFuture<'static, ()> {
+        let clock = self.clone();
+        let deadline = self.now() + duration;
+        Box::pin(core::future::poll_fn(move |_| {
+            if clock.now() >= deadline {
+                core::task::Poll::Ready(())
+            } else {
+                core::task::Poll::Pending
+            }
+        }))
+    }
+}
@@ -0,0 +1,9 @@
+//! 向上层提供的套接字
+
+mod tcp;
+mod udp;
+
+pub(crate) use tcp::{Segment, Tcb, TcpConnection, TcpListenerInner};
+pub use tcp::{TcpListener, TcpState, TcpStream};
+pub use udp::UdpSocket;
+pub(crate) use udp::UdpSocketInner;
@@ -0,0 +1,1114 @@
+use core::{task::Poll, time::Duration};
+
+use alloc::{
+    collections::vec_deque::VecDeque,
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
+use async_locks::{condvar::Condvar, mutex::Mutex};
+
+use crate::{
+    stack::{NetError, NetStack},
+    wire::{
+        SocketAddrV4,
+        tcp::{FLAG_ACK, FLAG_FIN, FLAG_PSH, FLAG_RST, FLAG_SYN, TcpPacket},
+    },
+};
+
+/// 发送缓冲区与接收缓冲区的容量，不使用窗口扩大选项，因此不超过65535
+const BUFFER_CAPACITY: usize = 65535;
+/// 对端未通告最大报文段长度时使用的默认值
+const DEFAULT_MSS: usize = 536;
+/// 初始重传超时时间（RFC 6298）
+const INITIAL_RTO: Duration = Duration::from_secs(1);
+const MIN_RTO: Duration = Duration::from_millis(200);
+const MAX_RTO: Duration = Duration::from_secs(60);
+/// 连续重传超过此次数时，认为连接已断开
+const MAX_RETRANSMISSIONS: u32 = 8;
+/// TIME_WAIT状态的持续时间，即2MSL
+const TIME_WAIT_DURATION: Duration = Duration::from_secs(30);
+/// 监听套接字最多缓存的未接受连接数量，超出时忽略新的连接请求
+const MAX_BACKLOG: usize = 16;
+
+/// TCP连接状态（RFC 793）
+///
+/// 被动打开的连接在握手完成后才交给用户，因此用户不会观察到LISTEN状态与未完成握手的SYN_RECEIVED状态。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TcpState {
+    Closed,
+    SynSent,
+    SynReceived,
+    Established,
+    FinWait1,
+    FinWait2,
+    CloseWait,
+    Closing,
+    LastAck,
+    TimeWait,
+}
+
+/// 待发送的TCP报文段，端口与地址由所属的连接决定
+pub(crate) struct Segment {
+    pub(crate) seq: u32,
+    pub(crate) ack: u32,
+    pub(crate) flags: u8,
+    pub(crate) window: u16,
+    pub(crate) mss: Option<u16>,
+    pub(crate) payload: Vec<u8>,
+}
+
+impl Segment {
+    fn reset(seq: u32) -> Self {
+        Self {
+            seq,
+            ack: 0,
+            flags: FLAG_RST,
+            window: 0,
+            mss: None,
+            payload: Vec::new(),
+        }
+    }
+
+    /// 对不属于任何连接的报文段回复RST，收到的报文段本身为RST时不回复
+    pub(crate) fn reset_for(packet: &TcpPacket) -> Option<Self> {
+        if packet.flags & FLAG_RST != 0 {
+            return None;
+        }
+        if packet.flags & FLAG_ACK != 0 {
+            return Some(Self::reset(packet.ack));
+        }
+        Some(Self {
+            ack: packet.seq.wrapping_add(packet.sequence_len()),
+            flags: FLAG_RST | FLAG_ACK,
+            ..Self::reset(0)
+        })
+    }
+}
+
+/// 序号比较，按照32位序号空间回绕处理
+fn seq_lt(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) < 0
+}
+
+fn seq_le(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) <= 0
+}
+
+/// 传输控制块，保存单个连接的全部状态
+///
+/// 此结构只负责状态转换，不进行任何IO：处理报文段或计时器后，返回需要发送的报文段，由协议栈负责发出。
+///
+/// 重传采用回退N策略：超时后从第一个未确认的字节开始重新发送。不支持乱序重组，
+/// 乱序到达的报文段被丢弃，并回复ACK告知对端期望的序号。
+pub(crate) struct Tcb {
+    state: TcpState,
+    /// 连接异常关闭的原因
+    error: Option<NetError>,
+    /// 初始发送序号，SYN占用此序号
+    iss: u32,
+    /// 第一个未被确认的序号
+    snd_una: u32,
+    /// 下一个要发送的序号
+    snd_nxt: u32,
+    /// 对端通告的接收窗口
+    snd_wnd: u32,
+    /// 发送报文段的最大负载长度
+    send_mss: usize,
+    /// 本机通告的最大报文段长度
+    local_mss: u16,
+    /// 未被确认的数据，首字节的序号为`snd_una`
+    send_buffer: VecDeque<u8>,
+    /// 用户已关闭发送方向，数据发送完毕后发送FIN
+    fin_queued: bool,
+    /// FIN已发出，其序号为`snd_nxt - 1`
+    fin_sent: bool,
+    /// 期望收到的下一个序号
+    rcv_nxt: u32,
+    /// 已收到但尚未被用户读取的数据
+    recv_buffer: VecDeque<u8>,
+    /// 对端已关闭发送方向
+    fin_received: bool,
+    /// 需要向对端发送ACK
+    ack_pending: bool,
+    /// 最近一次通告的接收窗口
+    advertised_window: u16,
+    /// 当前的重传超时时间
+    rto: Duration,
+    srtt: Option<Duration>,
+    rttvar: Duration,
+    /// 正在测量往返时间的序号与发送时刻，重传过的数据不参与测量（Karn算法）
+    rtt_sample: Option<(u32, Duration)>,
+    /// 重传计时器的到期时刻，对端窗口为0时同时作为窗口探测计时器
+    retransmit_deadline: Option<Duration>,
+    /// 连续超时重传的次数
+    retransmissions: u32,
+    /// 连接直接关闭的时刻，用于TIME_WAIT状态与被释放的FIN_WAIT_2状态
+    close_deadline: Option<Duration>,
+}
+
+impl Tcb {
+    fn new(state: TcpState, iss: u32, local_mss: u16) -> Self {
+        Self {
+            state,
+            error: None,
+            iss,
+            snd_una: iss,
+            snd_nxt: iss,
+            snd_wnd: 0,
+            send_mss: DEFAULT_MSS.min(local_mss as usize),
+            local_mss,
+            send_buffer: VecDeque::new(),
+            fin_queued: false,
+            fin_sent: false,
+            rcv_nxt: 0,
+            recv_buffer: VecDeque::new(),
+            fin_received: false,
+            ack_pending: false,
+            advertised_window: 0,
+            rto: INITIAL_RTO,
+            srtt: None,
+            rttvar: Duration::ZERO,
+            rtt_sample: None,
+            retransmit_deadline: None,
+            retransmissions: 0,
+            close_deadline: None,
+        }
+    }
+
+    /// 主动打开连接，SYN在下一次 [`Self::output`] 时发出
+    pub(crate) fn connect(iss: u32, local_mss: u16) -> Self {
+        Self::new(TcpState::SynSent, iss, local_mss)
+    }
+
+    /// 根据监听端口收到的SYN被动打开连接，SYN-ACK在下一次 [`Self::output`] 时发出
+    pub(crate) fn accept(iss: u32, local_mss: u16, syn: &TcpPacket) -> Self {
+        let mut tcb = Self::new(TcpState::SynReceived, iss, local_mss);
+        tcb.rcv_nxt = syn.seq.wrapping_add(1);
+        tcb.snd_wnd = syn.window as u32;
+        tcb.send_mss = Self::peer_mss(syn, local_mss);
+        tcb
+    }
+
+    fn peer_mss(syn: &TcpPacket, local_mss: u16) -> usize {
+        syn.mss
+            .map_or(DEFAULT_MSS, |mss| mss as usize)
+            .clamp(1, local_mss as usize)
+    }
+
+    pub(crate) fn state(&self) -> TcpState {
+        self.state
+    }
+
+    pub(crate) fn error(&self) -> Option<NetError> {
+        self.error
+    }
+
+    fn receive_window(&self) -> u16 {
+        (BUFFER_CAPACITY - self.recv_buffer.len()) as u16
+    }
+
+    fn segment(&mut self, seq: u32, flags: u8, payload: Vec<u8>) -> Segment {
+        let window = self.receive_window();
+        self.advertised_window = window;
+        Segment {
+            seq,
+            ack: if flags & FLAG_ACK != 0 {
+                self.rcv_nxt
+            } else {
+                0
+            },
+            flags,
+            window,
+            mss: (flags & FLAG_SYN != 0).then_some(self.local_mss),
+            payload,
+        }
+    }
+
+    fn arm_retransmit(&mut self, now: Duration) {
+        if self.retransmit_deadline.is_none() {
+            self.retransmit_deadline = Some(now + self.rto);
+        }
+    }
+
+    fn enter_time_wait(&mut self, now: Duration) {
+        self.state = TcpState::TimeWait;
+        self.retransmit_deadline = None;
+        self.close_deadline = Some(now + TIME_WAIT_DURATION);
+    }
+
+    /// 连接异常关闭，丢弃全部缓冲的数据
+    fn fail(&mut self, error: NetError) {
+        self.state = TcpState::Closed;
+        self.error = Some(error);
+        self.send_buffer.clear();
+        self.recv_buffer.clear();
+        self.retransmit_deadline = None;
+        self.close_deadline = None;
+    }
+
+    /// 根据新的往返时间样本更新重传超时时间（RFC 6298）
+    fn update_rto(&mut self, rtt: Duration) {
+        let srtt = match self.srtt {
+            None => {
+                self.rttvar = rtt / 2;
+                rtt
+            }
+            Some(srtt) => {
+                self.rttvar = (self.rttvar * 3 + srtt.abs_diff(rtt)) / 4;
+                (srtt * 7 + rtt) / 8
+            }
+        };
+        self.srtt = Some(srtt);
+        self.rto = (srtt + self.rttvar * 4).clamp(MIN_RTO, MAX_RTO);
+    }
+
+    /// 处理收到的报文段，返回需要发送的报文段
+    pub(crate) fn process(&mut self, packet: &TcpPacket, now: Duration) -> Vec<Segment> {
+        match self.state {
+            TcpState::Closed => return Vec::new(),
+            TcpState::SynSent => return self.process_syn_sent(packet, now),
+            _ => {}
+        }
+
+        if !self.acceptable(packet.seq, packet.sequence_len()) {
+            // 重复或窗口外的报文段，回复ACK告知对端期望的序号
+            if packet.flags & FLAG_RST == 0 {
+                self.ack_pending = true;
+                if packet.flags & FLAG_SYN != 0 && self.state == TcpState::SynReceived {
+                    // 对端重传了SYN，说明SYN-ACK丢失
+                    self.snd_nxt = self.iss;
+                }
+                if self.state == TcpState::TimeWait {
+                    // 对端重传了FIN，重新开始计时
+                    self.enter_time_wait(now);
+                }
+            }
+            return self.output(now);
+        }
+
+        if packet.flags & FLAG_RST != 0 {
+            // 仅接受序号恰好为期望值的RST，其余情况回复ACK（RFC 5961）
+            if packet.seq != self.rcv_nxt {
+                self.ack_pending = true;
+                return self.output(now);
+            }
+            self.fail(NetError::ConnectionReset);
+            return Vec::new();
+        }
+        if packet.flags & FLAG_SYN != 0 {
+            // 已同步的连接收到SYN，回复ACK（RFC 5961）
+            self.ack_pending = true;
+            return self.output(now);
+        }
+        if packet.flags & FLAG_ACK == 0 {
+            return Vec::new();
+        }
+
+        if self.state == TcpState::SynReceived {
+            if packet.ack != self.iss.wrapping_add(1) {
+                return vec![Segment::reset(packet.ack)];
+            }
+            self.state = TcpState::Established;
+            self.snd_una = packet.ack;
+            self.snd_nxt = packet.ack;
+            self.retransmit_deadline = None;
+            self.retransmissions = 0;
+            if let Some((_, sent_at)) = self.rtt_sample.take() {
+                self.update_rto(now - sent_at);
+            }
+        }
+        if seq_lt(self.snd_nxt, packet.ack) {
+            // 确认了尚未发送的序号
+            self.ack_pending = true;
+            return self.output(now);
+        }
+        self.process_ack(packet, now);
+        if self.state == TcpState::Closed {
+            return Vec::new();
+        }
+
+        if !packet.payload.is_empty() {
+            if matches!(
+                self.state,
+                TcpState::Established | TcpState::FinWait1 | TcpState::FinWait2
+            ) && seq_le(packet.seq, self.rcv_nxt)
+            {
+                let skip = self.rcv_nxt.wrapping_sub(packet.seq) as usize;
+                if let Some(data) = packet.payload.get(skip..) {
+                    let len = data.len().min(BUFFER_CAPACITY - self.recv_buffer.len());
+                    self.recv_buffer.extend(&data[..len]);
+                    self.rcv_nxt = self.rcv_nxt.wrapping_add(len as u32);
+                }
+            }
+            self.ack_pending = true;
+        }
+
+        let fin_seq = packet.seq.wrapping_add(packet.payload.len() as u32);
+        if packet.flags & FLAG_FIN != 0 && fin_seq == self.rcv_nxt && !self.fin_received {
+            self.rcv_nxt = self.rcv_nxt.wrapping_add(1);
+            self.fin_received = true;
+            self.ack_pending = true;
+            match self.state {
+                TcpState::Established => self.state = TcpState::CloseWait,
+                TcpState::FinWait1 => self.state = TcpState::Closing,
+                TcpState::FinWait2 => self.enter_time_wait(now),
+                _ => {}
+            }
+        }
+        self.output(now)
+    }
+
+    fn process_syn_sent(&mut self, packet: &TcpPacket, now: Duration) -> Vec<Segment> {
+        let has_ack = packet.flags & FLAG_ACK != 0;
+        if has_ack && packet.ack != self.iss.wrapping_add(1) {
+            return Segment::reset_for(packet).into_iter().collect();
+        }
+        if packet.flags & FLAG_RST != 0 {
+            if has_ack {
+                self.fail(NetError::ConnectionRefused);
+            }
+            return Vec::new();
+        }
+        if packet.flags & FLAG_SYN == 0 {
+            return Vec::new();
+        }
+
+        self.rcv_nxt = packet.seq.wrapping_add(1);
+        self.snd_wnd = packet.window as u32;
+        self.send_mss = Self::peer_mss(packet, self.local_mss);
+        self.ack_pending = true;
+        self.retransmit_deadline = None;
+        if has_ack {
+            self.state = TcpState::Established;
+            self.snd_una = packet.ack;
+            self.retransmissions = 0;
+            if let Some((_, sent_at)) = self.rtt_sample.take() {
+                self.update_rto(now - sent_at);
+            }
+        } else {
+            // 同时打开，以SYN-ACK重新发送SYN
+            self.state = TcpState::SynReceived;
+            self.snd_nxt = self.iss;
+        }
+        self.output(now)
+    }
+
+    /// 判断报文段是否位于接收窗口内（RFC 793）
+    ///
+    /// 接收窗口为0时，仍然接受序号恰好为期望值的报文段，以便处理其中的ACK。
+    fn acceptable(&self, seq: u32, len: u32) -> bool {
+        let window_end = self
+            .rcv_nxt
+            .wrapping_add((self.receive_window() as u32).max(1));
+        let in_window = |seq| seq_le(self.rcv_nxt, seq) && seq_lt(seq, window_end);
+        if len == 0 {
+            in_window(seq)
+        } else {
+            in_window(seq) || in_window(seq.wrapping_add(len - 1))
+        }
+    }
+
+    fn process_ack(&mut self, packet: &TcpPacket, now: Duration) {
+        let ack = packet.ack;
+        if seq_lt(self.snd_una, ack) {
+            let acked = ack.wrapping_sub(self.snd_una) as usize;
+            let data_acked = acked.min(self.send_buffer.len());
+            self.send_buffer.drain(..data_acked);
+            let fin_acked = self.fin_sent && ack == self.snd_nxt;
+            self.snd_una = ack;
+            if let Some((end, sent_at)) = self.rtt_sample
+                && seq_le(end, ack)
+            {
+                self.rtt_sample = None;
+                self.update_rto(now - sent_at);
+            }
+            self.retransmissions = 0;
+            self.retransmit_deadline = (self.snd_una != self.snd_nxt).then(|| now + self.rto);
+            if fin_acked {
+                match self.state {
+                    TcpState::FinWait1 => self.state = TcpState::FinWait2,
+                    TcpState::Closing => self.enter_time_wait(now),
+                    TcpState::LastAck => self.state = TcpState::Closed,
+                    _ => {}
+                }
+            }
+        }
+        if seq_le(self.snd_una, ack) {
+            self.snd_wnd = packet.window as u32;
+        }
+    }
+
+    /// 生成当前需要发送的报文段
+    pub(crate) fn output(&mut self, now: Duration) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        match self.state {
+            TcpState::SynSent | TcpState::SynReceived => {
+                if self.snd_nxt == self.iss {
+                    let flags = match self.state {
+                        TcpState::SynSent => FLAG_SYN,
+                        _ => FLAG_SYN | FLAG_ACK,
+                    };
+                    let segment = self.segment(self.iss, flags, Vec::new());
+                    segments.push(segment);
+                    self.snd_nxt = self.iss.wrapping_add(1);
+                    if self.retransmissions == 0 {
+                        self.rtt_sample = Some((self.snd_nxt, now));
+                    }
+                    self.arm_retransmit(now);
+                }
+            }
+            TcpState::Established
+            | TcpState::CloseWait
+            | TcpState::FinWait1
+            | TcpState::Closing
+            | TcpState::LastAck => self.output_data(now, 0, &mut segments),
+            _ => {}
+        }
+        if self.ack_pending {
+            self.ack_pending = false;
+            if segments.is_empty() && !matches!(self.state, TcpState::SynSent | TcpState::Closed) {
+                let segment = self.segment(self.snd_nxt, FLAG_ACK, Vec::new());
+                segments.push(segment);
+            }
+        }
+        segments
+    }
+
+    /// 在对端窗口允许的范围内发送数据，数据发送完毕且用户已关闭发送方向时发送FIN
+    ///
+    /// `min_window`用于窗口探测：对端窗口为0时，仍然发送不超过`min_window`字节的数据。
+    fn output_data(&mut self, now: Duration, min_window: usize, segments: &mut Vec<Segment>) {
+        let window = (self.snd_wnd as usize).max(min_window);
+        while !self.fin_sent {
+            let offset = self.snd_nxt.wrapping_sub(self.snd_una) as usize;
+            let remaining = self.send_buffer.len() - offset;
+            if remaining == 0 {
+                // FIN不受窗口限制
+                if self.fin_queued {
+                    let segment = self.segment(self.snd_nxt, FLAG_FIN | FLAG_ACK, Vec::new());
+                    segments.push(segment);
+                    self.snd_nxt = self.snd_nxt.wrapping_add(1);
+                    self.fin_sent = true;
+                    self.arm_retransmit(now);
+                }
+                break;
+            }
+            let usable = window.saturating_sub(offset);
+            if usable == 0 {
+                // 对端窗口已满，没有在途数据时由重传计时器定期发送窗口探测
+                if offset == 0 {
+                    self.arm_retransmit(now);
+                }
+                break;
+            }
+            let len = remaining.min(usable).min(self.send_mss);
+            let payload = self
+                .send_buffer
+                .range(offset..offset + len)
+                .copied()
+                .collect();
+            let seq = self.snd_nxt;
+            let segment = self.segment(seq, FLAG_ACK | FLAG_PSH, payload);
+            segments.push(segment);
+            self.snd_nxt = seq.wrapping_add(len as u32);
+            if self.rtt_sample.is_none() && self.retransmissions == 0 {
+                self.rtt_sample = Some((self.snd_nxt, now));
+            }
+            self.arm_retransmit(now);
+        }
+    }
+
+    /// 处理到期的计时器，并生成当前需要发送的报文段
+    pub(crate) fn on_timer(&mut self, now: Duration) -> Vec<Segment> {
+        if self.close_deadline.is_some_and(|deadline| now >= deadline) {
+            self.close_deadline = None;
+            self.state = TcpState::Closed;
+            return Vec::new();
+        }
+        if !self
+            .retransmit_deadline
+            .is_some_and(|deadline| now >= deadline)
+        {
+            return self.output(now);
+        }
+
+        self.retransmit_deadline = None;
+        self.rtt_sample = None;
+        let syn_unacked = matches!(self.state, TcpState::SynSent | TcpState::SynReceived);
+        // 窗口探测不计入重传次数，对端持续通告零窗口时连接不会超时
+        let probe = !syn_unacked && self.snd_wnd == 0 && !self.send_buffer.is_empty();
+        if !probe {
+            self.retransmissions += 1;
+            if self.retransmissions > MAX_RETRANSMISSIONS {
+                let reset = Segment::reset(self.snd_nxt);
+                self.fail(NetError::TimedOut);
+                return vec![reset];
+            }
+        }
+        self.rto = (self.rto * 2).min(MAX_RTO);
+
+        self.snd_nxt = if syn_unacked { self.iss } else { self.snd_una };
+        self.fin_sent = false;
+        if syn_unacked {
+            return self.output(now);
+        }
+        let mut segments = Vec::new();
+        self.output_data(now, if probe { 1 } else { 0 }, &mut segments);
+        segments
+    }
+
+    /// 关闭发送方向，FIN在缓冲的数据发送完毕后发出
+    ///
+    /// 尚未完成握手的连接直接关闭。
+    pub(crate) fn close(&mut self) {
+        match self.state {
+            TcpState::SynSent | TcpState::SynReceived => self.state = TcpState::Closed,
+            TcpState::Established => {
+                self.state = TcpState::FinWait1;
+                self.fin_queued = true;
+            }
+            TcpState::CloseWait => {
+                self.state = TcpState::LastAck;
+                self.fin_queued = true;
+            }
+            _ => {}
+        }
+    }
+
+    /// 发送FIN后经过TIME_WAIT时长仍未关闭时，直接关闭连接
+    ///
+    /// 用于用户已释放的连接，避免对端不发送FIN时连接永远停留在FIN_WAIT_2状态。
+    pub(crate) fn orphan(&mut self, now: Duration) {
+        if self.state == TcpState::FinWait2 && self.close_deadline.is_none() {
+            self.close_deadline = Some(now + TIME_WAIT_DURATION);
+        }
+    }
+
+    /// 立即关闭连接，并向对端发送RST
+    pub(crate) fn abort(&mut self) -> Vec<Segment> {
+        let reset = match self.state {
+            TcpState::Closed | TcpState::SynSent | TcpState::TimeWait => None,
+            _ => Some(Segment::reset(self.snd_nxt)),
+        };
+        self.state = TcpState::Closed;
+        self.send_buffer.clear();
+        self.retransmit_deadline = None;
+        self.close_deadline = None;
+        reset.into_iter().collect()
+    }
+
+    /// 从接收缓冲区读取数据，返回[`Poll::Pending`]表示需要等待数据到达
+    ///
+    /// 对端关闭发送方向后，读取完缓冲的数据时返回0。
+    pub(crate) fn read(&mut self, buf: &mut [u8]) -> Poll<Result<usize, NetError>> {
+        if !self.recv_buffer.is_empty() && !buf.is_empty() {
+            let len = buf.len().min(self.recv_buffer.len());
+            for (dst, src) in buf.iter_mut().zip(self.recv_buffer.drain(..len)) {
+                *dst = src;
+            }
+            // 接收窗口增大超过一个报文段时通告对端，避免糊涂窗口综合症
+            if self.receive_window() as usize
+                >= self.advertised_window as usize + self.local_mss as usize
+            {
+                self.ack_pending = true;
+            }
+            return Poll::Ready(Ok(len));
+        }
+        if let Some(error) = self.error {
+            return Poll::Ready(Err(error));
+        }
+        if buf.is_empty() || self.fin_received || self.state == TcpState::Closed {
+            return Poll::Ready(Ok(0));
+        }
+        Poll::Pending
+    }
+
+    /// 将数据写入发送缓冲区，返回写入的长度，返回[`Poll::Pending`]表示需要等待缓冲区有空闲空间
+    pub(crate) fn write(&mut self, data: &[u8]) -> Poll<Result<usize, NetError>> {
+        if let Some(error) = self.error {
+            return Poll::Ready(Err(error));
+        }
+        match self.state {
+            TcpState::Established | TcpState::CloseWait => {}
+            TcpState::SynSent | TcpState::SynReceived => return Poll::Pending,
+            _ => return Poll::Ready(Err(NetError::NotConnected)),
+        }
+        let len = data.len().min(BUFFER_CAPACITY - self.send_buffer.len());
+        if len == 0 && !data.is_empty() {
+            return Poll::Pending;
+        }
+        self.send_buffer.extend(&data[..len]);
+        Poll::Ready(Ok(len))
+    }
+
+    /// 读取操作是否可以立即完成
+    fn readable(&self) -> bool {
+        !self.recv_buffer.is_empty() || self.fin_received || self.state == TcpState::Closed
+    }
+
+    /// 写入操作是否可以立即完成
+    fn writable(&self) -> bool {
+        match self.state {
+            TcpState::SynSent | TcpState::SynReceived => false,
+            TcpState::Established | TcpState::CloseWait => self.send_buffer.len() < BUFFER_CAPACITY,
+            _ => true,
+        }
+    }
+}
+
+/// 协议栈中的一个TCP连接
+pub(crate) struct TcpConnection {
+    pub(crate) local_port: u16,
+    pub(crate) remote: SocketAddrV4,
+    pub(crate) tcb: Mutex<Tcb>,
+    /// 连接状态或缓冲区发生变化
+    pub(crate) changed: Condvar,
+    /// 被动打开的连接所属的监听套接字，握手完成后连接放入其等待队列
+    pub(crate) listener: Weak<TcpListenerInner>,
+}
+
+impl TcpConnection {
+    pub(crate) fn new(
+        local_port: u16,
+        remote: SocketAddrV4,
+        tcb: Tcb,
+        listener: Weak<TcpListenerInner>,
+    ) -> Self {
+        Self {
+            local_port,
+            remote,
+            tcb: Mutex::new(tcb),
+            changed: Condvar::new(),
+            listener,
+        }
+    }
+}
+
+pub(crate) struct TcpListenerInner {
+    pub(crate) port: u16,
+    /// 已完成握手、尚未被接受的连接
+    backlog: Mutex<VecDeque<Arc<TcpConnection>>>,
+    ready: Condvar,
+}
+
+impl TcpListenerInner {
+    pub(crate) fn new(port: u16) -> Self {
+        Self {
+            port,
+            backlog: Mutex::new(VecDeque::new()),
+            ready: Condvar::new(),
+        }
+    }
+
+    /// 等待队列是否已满，已满时忽略新的连接请求
+    pub(crate) async fn is_full(&self) -> bool {
+        self.backlog.lock().await.len() >= MAX_BACKLOG
+    }
+
+    /// 将完成握手的连接放入等待队列
+    pub(crate) async fn push(&self, connection: Arc<TcpConnection>) {
+        self.backlog.lock().await.push_back(connection);
+        self.ready.wake_all();
+    }
+}
+
+/// TCP连接
+///
+/// 通过 [`NetStack::connect_tcp`] 或 [`TcpListener::accept`] 创建。
+/// 流被释放后，协议栈在下一次处理计时器时关闭连接：缓冲的数据仍会发送完毕，随后发送FIN。
+pub struct TcpStream {
+    stack: Arc<NetStack>,
+    connection: Arc<TcpConnection>,
+}
+
+impl TcpStream {
+    pub(crate) fn new(stack: Arc<NetStack>, connection: Arc<TcpConnection>) -> Self {
+        Self { stack, connection }
+    }
+
+    pub fn local_addr(&self) -> SocketAddrV4 {
+        SocketAddrV4::new(self.stack.config().address, self.connection.local_port)
+    }
+
+    pub fn peer_addr(&self) -> SocketAddrV4 {
+        self.connection.remote
+    }
+
+    /// 连接当前的状态
+    pub async fn state(&self) -> TcpState {
+        self.connection.tcb.lock().await.state()
+    }
+
+    /// 读取数据，返回读取的长度
+    ///
+    /// 没有数据时等待至收到数据。对端关闭发送方向且数据已读取完毕时返回0。
+    ///
+    /// # Cancel Safety
+    /// 此函数是取消安全的，取消时不会丢失数据。需要通告的接收窗口在下一次处理计时器时发出。
+    pub async fn read(&self, buf: &mut [u8]) -> Result<usize, NetError> {
+        let mut tcb = self.connection.tcb.lock().await;
+        loop {
+            if let Poll::Ready(result) = tcb.read(buf) {
+                return result;
+            }
+            tcb = self.connection.changed.wait(tcb).await;
+        }
+    }
+
+    /// 写入数据，返回写入的长度
+    ///
+    /// 发送缓冲区已满时等待至有空闲空间。写入的长度可能小于`data`的长度。
+    /// 数据写入缓冲区后即返回，不等待对端确认。
+    pub async fn write(&self, data: &[u8]) -> Result<usize, NetError> {
+        let mut tcb = self.connection.tcb.lock().await;
+        loop {
+            if let Poll::Ready(result) = tcb.write(data) {
+                let segments = tcb.output(self.stack.now());
+                drop(tcb);
+                let _ = self.stack.send_tcp(&self.connection, segments).await;
+                return result;
+            }
+            tcb = self.connection.changed.wait(tcb).await;
+        }
+    }
+
+    /// 关闭发送方向，缓冲的数据发送完毕后向对端发送FIN。连接仍然可以读取数据
+    pub async fn shutdown(&self) {
+        let mut tcb = self.connection.tcb.lock().await;
+        tcb.close();
+        let segments = tcb.output(self.stack.now());
+        drop(tcb);
+        self.connection.changed.wake_all();
+        let _ = self.stack.send_tcp(&self.connection, segments).await;
+    }
+
+    /// 等待至读取（`read`为true时）或写入（`write`为true时）操作可以立即完成，
+    /// 返回读取与写入是否可以立即完成
+    ///
+    /// `read`与`write`均为false时永远等待。
+    pub async fn wait_ready(&self, read: bool, write: bool) -> (bool, bool) {
+        let mut tcb = self.connection.tcb.lock().await;
+        loop {
+            let ready = (read && tcb.readable(), write && tcb.writable());
+            if ready.0 || ready.1 {
+                return ready;
+            }
+            tcb = self.connection.changed.wait(tcb).await;
+        }
+    }
+}
+
+/// 监听TCP端口的套接字
+///
+/// 通过 [`NetStack::listen_tcp`] 创建，套接字被释放时解除端口绑定，尚未被接受的连接随之关闭。
+pub struct TcpListener {
+    stack: Arc<NetStack>,
+    inner: Arc<TcpListenerInner>,
+}
+
+impl TcpListener {
+    pub(crate) fn new(stack: Arc<NetStack>, inner: Arc<TcpListenerInner>) -> Self {
+        Self { stack, inner }
+    }
+
+    pub fn local_addr(&self) -> SocketAddrV4 {
+        SocketAddrV4::new(self.stack.config().address, self.inner.port)
+    }
+
+    /// 接受一个连接
+    ///
+    /// 没有完成握手的连接时等待。
+    ///
+    /// # Cancel Safety
+    /// 此函数是取消安全的，取消时不会丢失连接
+    pub async fn accept(&self) -> TcpStream {
+        let mut backlog = self.inner.backlog.lock().await;
+        loop {
+            if let Some(connection) = backlog.pop_front() {
+                return TcpStream::new(self.stack.clone(), connection);
+            }
+            backlog = self.inner.ready.wait(backlog).await;
+        }
+    }
+
+    /// 等待至有可接受的连接
+    pub async fn wait_acceptable(&self) {
+        let backlog = self.inner.backlog.lock().await;
+        self.inner
+            .ready
+            .wait_while(backlog, |backlog| backlog.is_empty())
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::{
+        future::poll_fn,
+        sync::atomic::{AtomicUsize, Ordering},
+        task::Poll,
+        time::Duration,
+    };
+
+    use alloc::{boxed::Box, sync::Arc, vec, vec::Vec};
+
+    use crate::{
+        BoxFuture, ManualClock,
+        device::{DeviceError, NetworkDevice, loopback::LoopbackDevice},
+        run_task, run_tasks,
+        socket::{TcpListener, TcpState, TcpStream},
+        stack::{Ipv4Config, NetError, NetStack},
+        time::Clock,
+        wire::{
+            MacAddress, SocketAddrV4,
+            ethernet::EthernetFrame,
+            ipv4::{Ipv4Packet, PROTOCOL_TCP},
+        },
+    };
+
+    const LOCAL_MAC: MacAddress = MacAddress([0x52, 0x54, 0, 0x12, 0x34, 0x56]);
+    /// 每次轮询后台任务时时钟前进的时长
+    const TICK: Duration = Duration::from_millis(10);
+
+    /// 回环设备，可以丢弃指定数量的TCP报文段以模拟丢包
+    struct LossyDevice {
+        inner: LoopbackDevice,
+        drop_count: AtomicUsize,
+    }
+
+    impl NetworkDevice for LossyDevice {
+        fn mac_address(&self) -> MacAddress {
+            self.inner.mac_address()
+        }
+
+        fn transmit<'fut>(
+            &'fut self,
+            frame: &'fut [u8],
+        ) -> BoxFuture<'fut, Result<(), DeviceError>> {
+            let is_tcp = EthernetFrame::parse(frame)
+                .and_then(|frame| Ipv4Packet::parse(frame.payload))
+                .is_some_and(|packet| packet.protocol == PROTOCOL_TCP);
+            if is_tcp
+                && self
+                    .drop_count
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                    .is_ok()
+            {
+                return Box::pin(async { Ok(()) });
+            }
+            self.inner.transmit(frame)
+        }
+
+        fn receive<'fut>(
+            &'fut self,
+            buf: &'fut mut [u8],
+        ) -> BoxFuture<'fut, Result<usize, DeviceError>> {
+            self.inner.receive(buf)
+        }
+    }
+
+    fn create_stack() -> (Arc<LossyDevice>, ManualClock, Arc<NetStack>) {
+        let device = Arc::new(LossyDevice {
+            inner: LoopbackDevice::new(LOCAL_MAC),
+            drop_count: AtomicUsize::new(0),
+        });
+        let clock = ManualClock::default();
+        let stack = NetStack::new(
+            device.clone(),
+            Arc::new(clock.clone()),
+            Ipv4Config::QEMU_USER_NETWORK,
+        );
+        (device, clock, stack)
+    }
+
+    fn local_addr(port: u16) -> SocketAddrV4 {
+        SocketAddrV4::new(Ipv4Config::QEMU_USER_NETWORK.address, port)
+    }
+
+    /// 协议栈的接收任务，以及每次轮询推进时钟的计时器任务
+    fn background(stack: &Arc<NetStack>, clock: &ManualClock) -> Vec<BoxFuture<'static, ()>> {
+        let receiver = stack.clone();
+        let timer = stack.clone();
+        let clock = clock.clone();
+        vec![
+            Box::pin(async move {
+                receiver.run().await;
+            }),
+            Box::pin(async move {
+                loop {
+                    clock.advance(TICK);
+                    timer.poll_timers().await;
+                    yield_now().await;
+                }
+            }),
+        ]
+    }
+
+    async fn yield_now() {
+        let mut yielded = false;
+        poll_fn(|_| {
+            if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    async fn write_all(stream: &TcpStream, mut data: &[u8]) {
+        while !data.is_empty() {
+            let len = stream.write(data).await.unwrap();
+            data = &data[len..];
+        }
+    }
+
+    async fn read_to_end(stream: &TcpStream) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut buf = [0u8; 1000];
+        loop {
+            let len = stream.read(&mut buf).await.unwrap();
+            if len == 0 {
+                return data;
+            }
+            data.extend_from_slice(&buf[..len]);
+        }
+    }
+
+    async fn wait_state(stream: &TcpStream, state: TcpState) {
+        while stream.state().await != state {
+            yield_now().await;
+        }
+    }
+
+    /// 接受一个连接，将收到的数据原样发回，对端关闭后关闭连接
+    async fn echo_server(listener: TcpListener) {
+        let stream = listener.accept().await;
+        let mut buf = [0u8; 100];
+        loop {
+            let len = stream.read(&mut buf).await.unwrap();
+            if len == 0 {
+                break;
+            }
+            write_all(&stream, &buf[..len]).await;
+        }
+        stream.shutdown().await;
+        wait_state(&stream, TcpState::Closed).await;
+    }
+
+    #[test]
+    fn test_echo() {
+        let (_device, clock, stack) = create_stack();
+        let listener = run_task(stack.listen_tcp(7)).unwrap();
+        let mut tasks = background(&stack, &clock);
+        tasks.push(Box::pin(echo_server(listener)));
+        run_tasks(tasks, async {
+            let stream = stack.connect_tcp(local_addr(7)).await.unwrap();
+            assert_eq!(stream.state().await, TcpState::Established);
+            assert_eq!(stream.peer_addr(), local_addr(7));
+            assert!(stream.local_addr().port >= 49152);
+
+            write_all(&stream, b"hello, world").await;
+            stream.shutdown().await;
+            assert_eq!(read_to_end(&stream).await, b"hello, world");
+            assert!(matches!(
+                stream.write(b"late").await,
+                Err(NetError::NotConnected)
+            ));
+
+            // 主动关闭的一方进入TIME_WAIT状态，经过2MSL后关闭
+            wait_state(&stream, TcpState::TimeWait).await;
+            let start = clock.now();
+            wait_state(&stream, TcpState::Closed).await;
+            assert!(clock.now() - start >= Duration::from_secs(29));
+        });
+    }
+
+    #[test]
+    fn test_connection_refused() {
+        let (_device, clock, stack) = create_stack();
+        run_tasks(background(&stack, &clock), async {
+            assert!(matches!(
+                stack.connect_tcp(local_addr(8)).await,
+                Err(NetError::ConnectionRefused)
+            ));
+
+            // 监听套接字关闭后，端口不再接受连接
+            let listener = stack.listen_tcp(8).await.unwrap();
+            assert!(matches!(
+                stack.listen_tcp(8).await,
+                Err(NetError::AddressInUse)
+            ));
+            drop(listener);
+            assert!(matches!(
+                stack.connect_tcp(local_addr(8)).await,
+                Err(NetError::ConnectionRefused)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_retransmission() {
+        let (device, clock, stack) = create_stack();
+        let listener = run_task(stack.listen_tcp(7)).unwrap();
+        let mut tasks = background(&stack, &clock);
+        tasks.push(Box::pin(echo_server(listener)));
+        run_tasks(tasks, async {
+            // SYN丢失，在初始重传超时后重传
+            device.drop_count.store(1, Ordering::Relaxed);
+            let start = clock.now();
+            let stream = stack.connect_tcp(local_addr(7)).await.unwrap();
+            assert!(clock.now() - start >= Duration::from_secs(1));
+
+            // 数据丢失
+            device.drop_count.store(1, Ordering::Relaxed);
+            write_all(&stream, b"lost once").await;
+            let mut buf = [0u8; 9];
+            let mut len = 0;
+            while len < buf.len() {
+                len += stream.read(&mut buf[len..]).await.unwrap();
+            }
+            assert_eq!(&buf, b"lost once");
+        });
+    }
+
+    #[test]
+    fn test_timeout() {
+        let (device, clock, stack) = create_stack();
+        let listener = run_task(stack.listen_tcp(7)).unwrap();
+        run_tasks(background(&stack, &clock), async {
+            device.drop_count.store(usize::MAX, Ordering::Relaxed);
+            assert!(matches!(
+                stack.connect_tcp(local_addr(7)).await,
+                Err(NetError::TimedOut)
+            ));
+        });
+        drop(listener);
+    }
+
+    #[test]
+    fn test_flow_control() {
+        let (_device, clock, stack) = create_stack();
+        let listener = run_task(stack.listen_tcp(7)).unwrap();
+        // 数据量超过双方缓冲区的总和，发送方必须等待接收方读取
+        let data: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+        let mut tasks = background(&stack, &clock);
+        tasks.push(Box::pin(async {
+            let stream = stack.connect_tcp(local_addr(7)).await.unwrap();
+            write_all(&stream, &data).await;
+            stream.shutdown().await;
+            wait_state(&stream, TcpState::TimeWait).await;
+        }));
+        run_tasks(tasks, async {
+            let stream = listener.accept().await;
+            // 接收方暂不读取，发送方填满窗口后通过窗口探测等待窗口打开
+            let start = clock.now();
+            while clock.now() - start < Duration::from_secs(5) {
+                yield_now().await;
+            }
+            assert_eq!(read_to_end(&stream).await, data);
+        });
+    }
+}
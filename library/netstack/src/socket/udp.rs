@@ -0,0 +1,106 @@
+use alloc::{collections::vec_deque::VecDeque, sync::Arc, vec::Vec};
+use async_locks::{condvar::Condvar, mutex::Mutex};
+
+use crate::{
+    stack::{NetError, NetStack},
+    wire::SocketAddrV4,
+};
+
+/// 每个套接字最多缓存的未读数据报数量，超出时丢弃新到达的数据报
+const MAX_QUEUED_DATAGRAMS: usize = 64;
+
+/// 收到的数据报
+struct Datagram {
+    source: SocketAddrV4,
+    data: Vec<u8>,
+}
+
+pub(crate) struct UdpSocketInner {
+    port: u16,
+    queue: Mutex<VecDeque<Datagram>>,
+    readable: Condvar,
+}
+
+impl UdpSocketInner {
+    pub(crate) fn new(port: u16) -> Self {
+        Self {
+            port,
+            queue: Mutex::new(VecDeque::new()),
+            readable: Condvar::new(),
+        }
+    }
+
+    /// 将收到的数据报放入接收队列
+    pub(crate) async fn deliver(&self, source: SocketAddrV4, data: &[u8]) {
+        let mut queue = self.queue.lock().await;
+        if queue.len() >= MAX_QUEUED_DATAGRAMS {
+            return;
+        }
+        queue.push_back(Datagram {
+            source,
+            data: data.to_vec(),
+        });
+        drop(queue);
+        self.readable.wake_all();
+    }
+}
+
+/// UDP套接字
+///
+/// 通过 [`NetStack::bind_udp`] 创建，套接字被释放时解除端口绑定。
+pub struct UdpSocket {
+    stack: Arc<NetStack>,
+    inner: Arc<UdpSocketInner>,
+}
+
+impl UdpSocket {
+    pub(crate) fn new(stack: Arc<NetStack>, inner: Arc<UdpSocketInner>) -> Self {
+        Self { stack, inner }
+    }
+
+    /// 套接字绑定的本地地址
+    pub fn local_addr(&self) -> SocketAddrV4 {
+        SocketAddrV4::new(self.stack.config().address, self.inner.port)
+    }
+
+    /// 单个数据报可携带的最大数据长度
+    pub fn max_datagram_len(&self) -> usize {
+        self.stack.max_udp_payload()
+    }
+
+    /// 向`destination`发送一个数据报
+    ///
+    /// 目标地址尚未解析时，数据报在解析完成后发出，此时本函数已经返回
+    pub async fn send_to(&self, data: &[u8], destination: SocketAddrV4) -> Result<(), NetError> {
+        self.stack
+            .send_udp(self.inner.port, destination, data)
+            .await
+    }
+
+    /// 接收一个数据报，返回数据长度与发送方地址
+    ///
+    /// 没有数据报时等待至收到数据报。数据报长度超过`buf`时，超出部分被丢弃，返回的长度为截断后的长度。
+    ///
+    /// # Cancel Safety
+    /// 此函数是取消安全的，取消时不会丢失数据报
+    pub async fn recv_from(&self, buf: &mut [u8]) -> (usize, SocketAddrV4) {
+        let mut queue = self.inner.queue.lock().await;
+        loop {
+            if let Some(datagram) = queue.pop_front() {
+                let len = datagram.data.len().min(buf.len());
+                buf[..len].copy_from_slice(&datagram.data[..len]);
+                return (len, datagram.source);
+            }
+            queue = self.inner.readable.wait(queue).await;
+        }
+    }
+
+    /// 等待至接收队列中有数据报
+    pub async fn wait_readable(&self) {
+        let queue = self.inner.queue.lock().await;
+        self.inner
+            .readable
+            .wait_while(queue, |queue| queue.is_empty())
+            .await;
+    }
+}
@@ -0,0 +1,861 @@
+use core::{
+    sync::atomic::{AtomicU16, AtomicU32, Ordering},
+    time::Duration,
+};
+
+use alloc::{
+    collections::{btree_map::BTreeMap, vec_deque::VecDeque},
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
+use async_locks::mutex::Mutex;
+
+use crate::{
+    device::{DeviceError, NetworkDevice},
+    socket::{
+        Segment, Tcb, TcpConnection, TcpListener, TcpListenerInner, TcpState, TcpStream, UdpSocket,
+        UdpSocketInner,
+    },
+    time::Clock,
+    wire::{
+        Ipv4Address, MacAddress, SocketAddrV4,
+        arp::{ArpOperation, ArpPacket},
+        ethernet::{self, ETHERTYPE_ARP, ETHERTYPE_IPV4, EthernetFrame},
+        ipv4::{self, Ipv4Packet, PROTOCOL_TCP, PROTOCOL_UDP},
+        tcp::{self, FLAG_ACK, FLAG_RST, FLAG_SYN, TcpPacket},
+        udp::{self, UdpPacket},
+    },
+};
+
+/// 等待地址解析时，每个地址最多缓存的数据报数量，超出时丢弃最早的数据报
+const MAX_PENDING_PACKETS: usize = 8;
+/// 自动分配的端口范围
+const EPHEMERAL_PORTS: core::ops::RangeInclusive<u16> = 49152..=65535;
+/// 处理TCP计时器的间隔
+const TIMER_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 网络接口的IPv4配置
+#[derive(Debug, Clone, Copy)]
+pub struct Ipv4Config {
+    pub address: Ipv4Address,
+    pub netmask: Ipv4Address,
+    /// 默认网关，为None时仅能访问子网内的地址
+    pub gateway: Option<Ipv4Address>,
+}
+
+impl Ipv4Config {
+    /// qemu用户模式网络（slirp）的默认配置
+    pub const QEMU_USER_NETWORK: Self = Self {
+        address: Ipv4Address::new(10, 0, 2, 15),
+        netmask: Ipv4Address::new(255, 255, 255, 0),
+        gateway: Some(Ipv4Address::new(10, 0, 2, 2)),
+    };
+
+    /// 子网的广播地址
+    pub fn subnet_broadcast(&self) -> Ipv4Address {
+        Ipv4Address::from_u32(self.address.to_u32() | !self.netmask.to_u32())
+    }
+}
+
+/// 网络操作错误
+#[derive(Debug, Clone, Copy)]
+pub enum NetError {
+    /// 端口已被占用
+    AddressInUse,
+    /// 没有可分配的端口
+    NoFreePort,
+    /// 目标地址不可达
+    Unreachable,
+    /// 数据超出单个数据报的长度限制
+    MessageTooLarge,
+    /// 对端拒绝连接
+    ConnectionRefused,
+    /// 连接被对端重置
+    ConnectionReset,
+    /// 对端长时间没有响应
+    TimedOut,
+    /// 连接已关闭，或发送方向已关闭
+    NotConnected,
+    /// 网络设备错误
+    Device(DeviceError),
+}
+
+impl From<DeviceError> for NetError {
+    fn from(value: DeviceError) -> Self {
+        Self::Device(value)
+    }
+}
+
+/// 邻居表项
+enum Neighbor {
+    /// 已解析的MAC地址
+    Resolved(MacAddress),
+    /// 正在解析，暂存待发送的IPv4数据报
+    Pending(VecDeque<Vec<u8>>),
+}
+
+/// 单个网络接口上的协议栈
+///
+/// ARP表项不会过期，未完成的地址解析在下一次发送时重新请求。
+/// TCP的重传与超时依赖计时器，需要由调用方持续运行 [`Self::run_timers`]。
+pub struct NetStack {
+    device: Arc<dyn NetworkDevice>,
+    clock: Arc<dyn Clock>,
+    mac_address: MacAddress,
+    config: Ipv4Config,
+    neighbors: Mutex<BTreeMap<Ipv4Address, Neighbor>>,
+    /// 已绑定的UDP端口。套接字关闭后表项失效，在下一次访问时清理
+    udp_sockets: Mutex<BTreeMap<u16, Weak<UdpSocketInner>>>,
+    /// TCP连接，以本地端口与对端地址索引。连接关闭后移除
+    tcp_connections: Mutex<BTreeMap<(u16, SocketAddrV4), Arc<TcpConnection>>>,
+    /// 监听中的TCP端口。套接字关闭后表项失效，在下一次访问时清理
+    tcp_listeners: Mutex<BTreeMap<u16, Weak<TcpListenerInner>>>,
+    next_ephemeral_port: AtomicU16,
+    next_identification: AtomicU16,
+    next_iss: AtomicU32,
+}
+
+impl NetStack {
+    pub fn new(
+        device: Arc<dyn NetworkDevice>,
+        clock: Arc<dyn Clock>,
+        config: Ipv4Config,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            mac_address: device.mac_address(),
+            device,
+            clock,
+            config,
+            neighbors: Mutex::new(BTreeMap::new()),
+            udp_sockets: Mutex::new(BTreeMap::new()),
+            tcp_connections: Mutex::new(BTreeMap::new()),
+            tcp_listeners: Mutex::new(BTreeMap::new()),
+            next_ephemeral_port: AtomicU16::new(*EPHEMERAL_PORTS.start()),
+            next_identification: AtomicU16::new(0),
+            next_iss: AtomicU32::new(0),
+        })
+    }
+
+    pub fn config(&self) -> &Ipv4Config {
+        &self.config
+    }
+
+    pub fn mac_address(&self) -> MacAddress {
+        self.mac_address
+    }
+
+    /// 单个UDP数据报可携带的最大数据长度，协议栈不支持分片
+    pub fn max_udp_payload(&self) -> usize {
+        self.device.mtu() - ipv4::HEADER_LEN - udp::HEADER_LEN
+    }
+
+    /// TCP报文段的最大负载长度，作为本机通告的最大报文段长度
+    fn tcp_mss(&self) -> u16 {
+        (self.device.mtu() - ipv4::HEADER_LEN - tcp::HEADER_LEN) as u16
+    }
+
+    pub(crate) fn now(&self) -> Duration {
+        self.clock.now()
+    }
+
+    /// 持续接收并处理网络设备收到的帧，仅在设备出错时返回
+    pub async fn run(&self) -> DeviceError {
+        let mut buf = vec![0u8; self.device.mtu() + ethernet::HEADER_LEN];
+        loop {
+            match self.device.receive(&mut buf).await {
+                Ok(len) => self.handle_frame(&buf[..len]).await,
+                Err(e) => return e,
+            }
+        }
+    }
+
+    /// 处理一个收到的以太网帧，无法识别或不属于本机的帧被丢弃
+    pub async fn handle_frame(&self, frame: &[u8]) {
+        let Some(frame) = EthernetFrame::parse(frame) else {
+            return;
+        };
+        if frame.destination != self.mac_address && frame.destination != MacAddress::BROADCAST {
+            return;
+        }
+        match frame.ethertype {
+            ETHERTYPE_ARP => {
+                if let Some(packet) = ArpPacket::parse(frame.payload) {
+                    self.handle_arp(packet).await;
+                }
+            }
+            ETHERTYPE_IPV4 => {
+                if let Some(packet) = Ipv4Packet::parse(frame.payload) {
+                    self.handle_ipv4(packet).await;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    async fn handle_arp(&self, packet: ArpPacket) {
+        let for_us = packet.target_ip == self.config.address;
+        let pending = {
+            let mut neighbors = self.neighbors.lock().await;
+            // 仅记录与本机通信的地址，以及已在表中的地址
+            if !for_us && !neighbors.contains_key(&packet.sender_ip) {
+                None
+            } else {
+                match neighbors.insert(packet.sender_ip, Neighbor::Resolved(packet.sender_mac)) {
+                    Some(Neighbor::Pending(pending)) => Some(pending),
+                    _ => None,
+                }
+            }
+        };
+        for packet_data in pending.into_iter().flatten() {
+            let _ = self
+                .transmit_frame(packet.sender_mac, ETHERTYPE_IPV4, &packet_data)
+                .await;
+        }
+        if for_us && packet.operation == ArpOperation::Request {
+            let reply = ArpPacket {
+                operation: ArpOperation::Reply,
+                sender_mac: self.mac_address,
+                sender_ip: self.config.address,
+                target_mac: packet.sender_mac,
+                target_ip: packet.sender_ip,
+            };
+            let mut payload = Vec::new();
+            reply.emit(&mut payload);
+            let _ = self
+                .transmit_frame(packet.sender_mac, ETHERTYPE_ARP, &payload)
+                .await;
+        }
+    }
+
+    async fn handle_ipv4(&self, packet: Ipv4Packet<'_>) {
+        if packet.destination != self.config.address
+            && !packet.destination.is_broadcast()
+            && packet.destination != self.config.subnet_broadcast()
+        {
+            return;
+        }
+        if packet.protocol == PROTOCOL_TCP {
+            self.handle_tcp(packet).await;
+        } else if packet.protocol == PROTOCOL_UDP {
+            let Some(datagram) =
+                UdpPacket::parse(packet.payload, packet.source, packet.destination)
+            else {
+                return;
+            };
+            let socket = {
+                let mut sockets = self.udp_sockets.lock().await;
+                let socket = sockets
+                    .get(&datagram.destination_port)
+                    .map(|socket| socket.upgrade());
+                match socket {
+                    Some(Some(socket)) => socket,
+                    Some(None) => {
+                        sockets.remove(&datagram.destination_port);
+                        return;
+                    }
+                    None => return,
+                }
+            };
+            socket
+                .deliver(
+                    SocketAddrV4::new(packet.source, datagram.source_port),
+                    datagram.payload,
+                )
+                .await;
+        }
+    }
+
+    /// 绑定UDP端口，`port`为0时自动分配端口
+    pub async fn bind_udp(self: &Arc<Self>, port: u16) -> Result<UdpSocket, NetError> {
+        let mut sockets = self.udp_sockets.lock().await;
+        let port = if port != 0 {
+            if sockets
+                .get(&port)
+                .is_some_and(|socket| socket.strong_count() > 0)
+            {
+                return Err(NetError::AddressInUse);
+            }
+            port
+        } else {
+            self.alloc_ephemeral_port(|port| {
+                sockets
+                    .get(&port)
+                    .is_some_and(|socket| socket.strong_count() > 0)
+            })?
+        };
+        let inner = Arc::new(UdpSocketInner::new(port));
+        sockets.insert(port, Arc::downgrade(&inner));
+        Ok(UdpSocket::new(self.clone(), inner))
+    }
+
+    /// 分配一个未被占用的端口，`in_use`判断端口是否已被占用
+    fn alloc_ephemeral_port(&self, in_use: impl Fn(u16) -> bool) -> Result<u16, NetError> {
+        let count = EPHEMERAL_PORTS.len();
+        for _ in 0..count {
+            let port = self
+                .next_ephemeral_port
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |port| {
+                    Some(if port == *EPHEMERAL_PORTS.end() {
+                        *EPHEMERAL_PORTS.start()
+                    } else {
+                        port + 1
+                    })
+                })
+                .unwrap();
+            if !in_use(port) {
+                return Ok(port);
+            }
+        }
+        Err(NetError::NoFreePort)
+    }
+
+    /// 发送UDP数据报
+    pub(crate) async fn send_udp(
+        &self,
+        source_port: u16,
+        destination: SocketAddrV4,
+        data: &[u8],
+    ) -> Result<(), NetError> {
+        if data.len() > self.max_udp_payload() {
+            return Err(NetError::MessageTooLarge);
+        }
+        let mut payload = Vec::with_capacity(udp::HEADER_LEN + data.len());
+        UdpPacket {
+            source_port,
+            destination_port: destination.port,
+            payload: data,
+        }
+        .emit(&mut payload, self.config.address, destination.ip);
+        self.send_ipv4(destination.ip, PROTOCOL_UDP, &payload).await
+    }
+
+    async fn handle_tcp(&self, packet: Ipv4Packet<'_>) {
+        // TCP仅用于单播
+        if packet.destination != self.config.address {
+            return;
+        }
+        let Some(segment) = TcpPacket::parse(packet.payload, packet.source, packet.destination)
+        else {
+            return;
+        };
+        let remote = SocketAddrV4::new(packet.source, segment.source_port);
+        let key = (segment.destination_port, remote);
+        let now = self.clock.now();
+
+        let connection = self.tcp_connections.lock().await.get(&key).cloned();
+        if let Some(connection) = connection {
+            let mut tcb = connection.tcb.lock().await;
+            let handshaking = tcb.state() == TcpState::SynReceived;
+            let mut segments = tcb.process(&segment, now);
+            let mut listener = None;
+            if handshaking && matches!(tcb.state(), TcpState::Established | TcpState::CloseWait) {
+                listener = connection.listener.upgrade();
+                if listener.is_none() {
+                    // 监听套接字已关闭，没有人会接受此连接
+                    segments.extend(tcb.abort());
+                }
+            }
+            let closed = tcb.state() == TcpState::Closed;
+            drop(tcb);
+            connection.changed.wake_all();
+            let _ = self.send_tcp(&connection, segments).await;
+            if let Some(listener) = listener {
+                listener.push(connection.clone()).await;
+            }
+            if closed {
+                self.remove_tcp(&connection).await;
+            }
+            return;
+        }
+
+        if segment.flags & (FLAG_SYN | FLAG_ACK | FLAG_RST) == FLAG_SYN {
+            let listener = self
+                .tcp_listeners
+                .lock()
+                .await
+                .get(&segment.destination_port)
+                .and_then(Weak::upgrade);
+            if let Some(listener) = listener {
+                if listener.is_full().await {
+                    return;
+                }
+                let mut tcb = Tcb::accept(self.generate_iss(), self.tcp_mss(), &segment);
+                let segments = tcb.output(now);
+                let connection = Arc::new(TcpConnection::new(
+                    segment.destination_port,
+                    remote,
+                    tcb,
+                    Arc::downgrade(&listener),
+                ));
+                self.tcp_connections
+                    .lock()
+                    .await
+                    .insert(key, connection.clone());
+                let _ = self.send_tcp(&connection, segments).await;
+                return;
+            }
+        }
+        if let Some(reset) = Segment::reset_for(&segment) {
+            let _ = self
+                .send_tcp_segment(segment.destination_port, remote, &reset)
+                .await;
+        }
+    }
+
+    /// 生成初始发送序号，随时间增长以避免与旧连接的序号重叠
+    fn generate_iss(&self) -> u32 {
+        let now = self.clock.now().as_micros() as u32;
+        now.wrapping_add(self.next_iss.fetch_add(64000, Ordering::Relaxed))
+    }
+
+    /// 连接到`remote`，等待至握手完成
+    pub async fn connect_tcp(
+        self: &Arc<Self>,
+        remote: SocketAddrV4,
+    ) -> Result<TcpStream, NetError> {
+        let connection = {
+            let mut connections = self.tcp_connections.lock().await;
+            let listeners = self.tcp_listeners.lock().await;
+            let local_port = self.alloc_ephemeral_port(|port| {
+                listeners
+                    .get(&port)
+                    .is_some_and(|listener| listener.strong_count() > 0)
+                    || connections
+                        .keys()
+                        .any(|&(local_port, _)| local_port == port)
+            })?;
+            let tcb = Tcb::connect(self.generate_iss(), self.tcp_mss());
+            let connection = Arc::new(TcpConnection::new(local_port, remote, tcb, Weak::new()));
+            connections.insert((local_port, remote), connection.clone());
+            connection
+        };
+
+        let segments = connection.tcb.lock().await.output(self.clock.now());
+        if let Err(e) = self.send_tcp(&connection, segments).await {
+            connection.tcb.lock().await.abort();
+            self.remove_tcp(&connection).await;
+            return Err(e);
+        }
+        let mut tcb = connection.tcb.lock().await;
+        loop {
+            match tcb.state() {
+                TcpState::SynSent | TcpState::SynReceived => {
+                    tcb = connection.changed.wait(tcb).await;
+                }
+                TcpState::Closed => return Err(tcb.error().unwrap_or(NetError::NotConnected)),
+                _ => break,
+            }
+        }
+        drop(tcb);
+        Ok(TcpStream::new(self.clone(), connection))
+    }
+
+    /// 监听TCP端口，`port`为0时自动分配端口
+    pub async fn listen_tcp(self: &Arc<Self>, port: u16) -> Result<TcpListener, NetError> {
+        let connections = self.tcp_connections.lock().await;
+        let mut listeners = self.tcp_listeners.lock().await;
+        let in_use = |port| {
+            listeners
+                .get(&port)
+                .is_some_and(|listener| listener.strong_count() > 0)
+        };
+        let port = if port != 0 {
+            if in_use(port) {
+                return Err(NetError::AddressInUse);
+            }
+            port
+        } else {
+            self.alloc_ephemeral_port(|port| {
+                in_use(port)
+                    || connections
+                        .keys()
+                        .any(|&(local_port, _)| local_port == port)
+            })?
+        };
+        drop(connections);
+        let inner = Arc::new(TcpListenerInner::new(port));
+        listeners.insert(port, Arc::downgrade(&inner));
+        Ok(TcpListener::new(self.clone(), inner))
+    }
+
+    /// 持续处理TCP计时器，需要与 [`Self::run`] 同时运行
+    pub async fn run_timers(&self) {
+        loop {
+            self.clock.sleep(TIMER_INTERVAL).await;
+            self.poll_timers().await;
+        }
+    }
+
+    /// 处理全部TCP连接的重传与超时，并关闭已被用户释放的连接
+    pub async fn poll_timers(&self) {
+        let now = self.clock.now();
+        let connections: Vec<_> = self
+            .tcp_connections
+            .lock()
+            .await
+            .values()
+            .cloned()
+            .collect();
+        for connection in connections {
+            let mut tcb = connection.tcb.lock().await;
+            let mut segments = Vec::new();
+            // 除连接表与此处外没有其他引用，说明用户已释放连接，且连接不在监听套接字的等待队列中
+            if Arc::strong_count(&connection) == 2 {
+                match tcb.state() {
+                    TcpState::SynReceived => {
+                        if connection.listener.strong_count() == 0 {
+                            segments.extend(tcb.abort());
+                        }
+                    }
+                    _ => {
+                        tcb.close();
+                        tcb.orphan(now);
+                    }
+                }
+            }
+            segments.extend(tcb.on_timer(now));
+            let closed = tcb.state() == TcpState::Closed;
+            drop(tcb);
+            connection.changed.wake_all();
+            let _ = self.send_tcp(&connection, segments).await;
+            if closed {
+                self.remove_tcp(&connection).await;
+            }
+        }
+    }
+
+    async fn remove_tcp(&self, connection: &Arc<TcpConnection>) {
+        let mut connections = self.tcp_connections.lock().await;
+        let key = (connection.local_port, connection.remote);
+        if connections
+            .get(&key)
+            .is_some_and(|current| Arc::ptr_eq(current, connection))
+        {
+            connections.remove(&key);
+        }
+    }
+
+    /// 发送属于`connection`的报文段
+    pub(crate) async fn send_tcp(
+        &self,
+        connection: &TcpConnection,
+        segments: Vec<Segment>,
+    ) -> Result<(), NetError> {
+        for segment in &segments {
+            self.send_tcp_segment(connection.local_port, connection.remote, segment)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn send_tcp_segment(
+        &self,
+        local_port: u16,
+        remote: SocketAddrV4,
+        segment: &Segment,
+    ) -> Result<(), NetError> {
+        let mut payload = Vec::with_capacity(tcp::HEADER_LEN + 4 + segment.payload.len());
+        TcpPacket {
+            source_port: local_port,
+            destination_port: remote.port,
+            seq: segment.seq,
+            ack: segment.ack,
+            flags: segment.flags,
+            window: segment.window,
+            mss: segment.mss,
+            payload: &segment.payload,
+        }
+        .emit(&mut payload, self.config.address, remote.ip);
+        self.send_ipv4(remote.ip, PROTOCOL_TCP, &payload).await
+    }
+
+    /// 发送IPv4数据报，目标地址未解析时暂存数据报并发出ARP请求
+    async fn send_ipv4(
+        &self,
+        destination: Ipv4Address,
+        protocol: u8,
+        payload: &[u8],
+    ) -> Result<(), NetError> {
+        let mut packet = Ipv4Packet::new(self.config.address, destination, protocol, payload);
+        packet.identification = self.next_identification.fetch_add(1, Ordering::Relaxed);
+        let mut packet_data = Vec::with_capacity(ipv4::HEADER_LEN + payload.len());
+        packet.emit(&mut packet_data);
+
+        if destination.is_broadcast() || destination == self.config.subnet_broadcast() {
+            return self
+                .transmit_frame(MacAddress::BROADCAST, ETHERTYPE_IPV4, &packet_data)
+                .await;
+        }
+        let next_hop = if destination.in_subnet(self.config.address, self.config.netmask) {
+            destination
+        } else {
+            self.config.gateway.ok_or(NetError::Unreachable)?
+        };
+
+        let mut neighbors = self.neighbors.lock().await;
+        match neighbors
+            .entry(next_hop)
+            .or_insert_with(|| Neighbor::Pending(VecDeque::new()))
+        {
+            Neighbor::Resolved(mac_address) => {
+                let mac_address = *mac_address;
+                drop(neighbors);
+                self.transmit_frame(mac_address, ETHERTYPE_IPV4, &packet_data)
+                    .await
+            }
+            Neighbor::Pending(pending) => {
+                if pending.len() >= MAX_PENDING_PACKETS {
+                    pending.pop_front();
+                }
+                pending.push_back(packet_data);
+                drop(neighbors);
+                self.send_arp_request(next_hop).await
+            }
+        }
+    }
+
+    async fn send_arp_request(&self, target: Ipv4Address) -> Result<(), NetError> {
+        let request = ArpPacket {
+            operation: ArpOperation::Request,
+            sender_mac: self.mac_address,
+            sender_ip: self.config.address,
+            target_mac: MacAddress::ZERO,
+            target_ip: target,
+        };
+        let mut payload = Vec::new();
+        request.emit(&mut payload);
+        self.transmit_frame(MacAddress::BROADCAST, ETHERTYPE_ARP, &payload)
+            .await
+    }
+
+    async fn transmit_frame(
+        &self,
+        destination: MacAddress,
+        ethertype: u16,
+        payload: &[u8],
+    ) -> Result<(), NetError> {
+        let mut frame = Vec::with_capacity(ethernet::HEADER_LEN + payload.len());
+        EthernetFrame {
+            destination,
+            source: self.mac_address,
+            ethertype,
+            payload,
+        }
+        .emit(&mut frame);
+        Ok(self.device.transmit(&frame).await?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::{boxed::Box, sync::Arc, vec::Vec};
+    use std::sync::Mutex;
+
+    use crate::{
+        BoxFuture, ManualClock,
+        device::{DeviceError, NetworkDevice},
+        run_task,
+        stack::{Ipv4Config, NetError, NetStack},
+        wire::{
+            Ipv4Address, MacAddress, SocketAddrV4,
+            arp::{ArpOperation, ArpPacket},
+            ethernet::{ETHERTYPE_ARP, ETHERTYPE_IPV4, EthernetFrame},
+            ipv4::{Ipv4Packet, PROTOCOL_UDP},
+            udp::UdpPacket,
+        },
+    };
+
+    const LOCAL_MAC: MacAddress = MacAddress([0x52, 0x54, 0, 0x12, 0x34, 0x56]);
+    const GATEWAY_MAC: MacAddress = MacAddress([0x52, 0x55, 0x0a, 0, 0x02, 0x02]);
+    const GATEWAY_IP: Ipv4Address = Ipv4Address::new(10, 0, 2, 2);
+
+    /// 记录发出的帧，不会收到任何帧
+    struct MockDevice {
+        transmitted: Mutex<Vec<Vec<u8>>>,
+    }
+
+    impl MockDevice {
+        fn take_transmitted(&self) -> Vec<Vec<u8>> {
+            core::mem::take(&mut *self.transmitted.lock().unwrap())
+        }
+    }
+
+    impl NetworkDevice for MockDevice {
+        fn mac_address(&self) -> MacAddress {
+            LOCAL_MAC
+        }
+
+        fn transmit<'fut>(
+            &'fut self,
+            frame: &'fut [u8],
+        ) -> BoxFuture<'fut, Result<(), DeviceError>> {
+            self.transmitted.lock().unwrap().push(frame.to_vec());
+            Box::pin(async { Ok(()) })
+        }
+
+        fn receive<'fut>(
+            &'fut self,
+            _buf: &'fut mut [u8],
+        ) -> BoxFuture<'fut, Result<usize, DeviceError>> {
+            Box::pin(async { Err(DeviceError::IoError) })
+        }
+    }
+
+    fn create_stack() -> (Arc<MockDevice>, Arc<NetStack>) {
+        let device = Arc::new(MockDevice {
+            transmitted: Mutex::new(Vec::new()),
+        });
+        let stack = NetStack::new(
+            device.clone(),
+            Arc::new(ManualClock::default()),
+            Ipv4Config::QEMU_USER_NETWORK,
+        );
+        (device, stack)
+    }
+
+    fn arp_frame(packet: ArpPacket, destination: MacAddress) -> Vec<u8> {
+        let mut payload = Vec::new();
+        packet.emit(&mut payload);
+        let mut frame = Vec::new();
+        EthernetFrame {
+            destination,
+            source: packet.sender_mac,
+            ethertype: ETHERTYPE_ARP,
+            payload: &payload,
+        }
+        .emit(&mut frame);
+        frame
+    }
+
+    fn udp_frame(source: SocketAddrV4, destination: SocketAddrV4, data: &[u8]) -> Vec<u8> {
+        let mut udp = Vec::new();
+        UdpPacket {
+            source_port: source.port,
+            destination_port: destination.port,
+            payload: data,
+        }
+        .emit(&mut udp, source.ip, destination.ip);
+        let mut ip = Vec::new();
+        Ipv4Packet::new(source.ip, destination.ip, PROTOCOL_UDP, &udp).emit(&mut ip);
+        let mut frame = Vec::new();
+        EthernetFrame {
+            destination: LOCAL_MAC,
+            source: GATEWAY_MAC,
+            ethertype: ETHERTYPE_IPV4,
+            payload: &ip,
+        }
+        .emit(&mut frame);
+        frame
+    }
+
+    fn gateway_arp(operation: ArpOperation) -> Vec<u8> {
+        arp_frame(
+            ArpPacket {
+                operation,
+                sender_mac: GATEWAY_MAC,
+                sender_ip: GATEWAY_IP,
+                target_mac: MacAddress::ZERO,
+                target_ip: Ipv4Config::QEMU_USER_NETWORK.address,
+            },
+            MacAddress::BROADCAST,
+        )
+    }
+
+    #[test]
+    fn test_arp_reply() {
+        run_task(async {
+            let (device, stack) = create_stack();
+            stack
+                .handle_frame(&gateway_arp(ArpOperation::Request))
+                .await;
+
+            let transmitted = device.take_transmitted();
+            assert_eq!(transmitted.len(), 1);
+            let frame = EthernetFrame::parse(&transmitted[0]).unwrap();
+            assert_eq!(frame.destination, GATEWAY_MAC);
+            let reply = ArpPacket::parse(frame.payload).unwrap();
+            assert_eq!(reply.operation, ArpOperation::Reply);
+            assert_eq!(reply.sender_mac, LOCAL_MAC);
+            assert_eq!(reply.sender_ip, Ipv4Config::QEMU_USER_NETWORK.address);
+            assert_eq!(reply.target_ip, GATEWAY_IP);
+        })
+    }
+
+    #[test]
+    fn test_send_after_resolve() {
+        run_task(async {
+            let (device, stack) = create_stack();
+            let socket = stack.bind_udp(0).await.unwrap();
+            // 子网外的地址经由网关发送
+            let destination = SocketAddrV4::new(Ipv4Address::new(8, 8, 8, 8), 53);
+            socket.send_to(b"query", destination).await.unwrap();
+
+            // 先发出对网关的ARP请求
+            let transmitted = device.take_transmitted();
+            assert_eq!(transmitted.len(), 1);
+            let frame = EthernetFrame::parse(&transmitted[0]).unwrap();
+            assert_eq!(frame.destination, MacAddress::BROADCAST);
+            let request = ArpPacket::parse(frame.payload).unwrap();
+            assert_eq!(request.operation, ArpOperation::Request);
+            assert_eq!(request.target_ip, GATEWAY_IP);
+
+            // 收到应答后发出暂存的数据报
+            stack.handle_frame(&gateway_arp(ArpOperation::Reply)).await;
+            let transmitted = device.take_transmitted();
+            assert_eq!(transmitted.len(), 1);
+            let frame = EthernetFrame::parse(&transmitted[0]).unwrap();
+            assert_eq!(frame.destination, GATEWAY_MAC);
+            let packet = Ipv4Packet::parse(frame.payload).unwrap();
+            assert_eq!(packet.destination, destination.ip);
+            let datagram =
+                UdpPacket::parse(packet.payload, packet.source, packet.destination).unwrap();
+            assert_eq!(datagram.source_port, socket.local_addr().port);
+            assert_eq!(datagram.destination_port, 53);
+            assert_eq!(datagram.payload, b"query");
+
+            // 已解析的地址直接发送
+            socket.send_to(b"again", destination).await.unwrap();
+            assert_eq!(device.take_transmitted().len(), 1);
+        })
+    }
+
+    #[test]
+    fn test_receive() {
+        run_task(async {
+            let (_device, stack) = create_stack();
+            let socket = stack.bind_udp(7).await.unwrap();
+            let source = SocketAddrV4::new(GATEWAY_IP, 1234);
+            stack
+                .handle_frame(&udp_frame(source, socket.local_addr(), b"hello"))
+                .await;
+            // 未绑定的端口，数据报被丢弃
+            let other = SocketAddrV4::new(Ipv4Config::QEMU_USER_NETWORK.address, 8);
+            stack.handle_frame(&udp_frame(source, other, b"lost")).await;
+
+            socket.wait_readable().await;
+            let mut buf = [0u8; 3];
+            assert_eq!(socket.recv_from(&mut buf).await, (3, source));
+            assert_eq!(&buf, b"hel");
+        })
+    }
+
+    #[test]
+    fn test_bind() {
+        run_task(async {
+            let (_device, stack) = create_stack();
+            let socket = stack.bind_udp(7).await.unwrap();
+            assert!(matches!(
+                stack.bind_udp(7).await,
+                Err(NetError::AddressInUse)
+            ));
+            drop(socket);
+            stack.bind_udp(7).await.unwrap();
+
+            let first = stack.bind_udp(0).await.unwrap();
+            let second = stack.bind_udp(0).await.unwrap();
+            assert!(first.local_addr().port >= 49152);
+            assert_ne!(first.local_addr().port, second.local_addr().port);
+        })
+    }
+}
@@ -0,0 +1,20 @@
+use core::time::Duration;
+
+use crate::BoxFuture;
+
+/// 协议栈使用的时钟
+///
+/// TCP的重传与TIME_WAIT等计时依赖此trait。与 [`crate::device::NetworkDevice`] 类似，
+/// 时钟由协议栈的使用者提供：内核使用系统时钟，测试中可以使用手动推进的时钟。
+///
+/// 此trait为dyn safe的，可以进行动态分发。
+pub trait Clock: Send + Sync + 'static {
+    /// 自某个固定时刻起经过的时间，必须单调不减
+    fn now(&self) -> Duration;
+
+    /// 等待至少`duration`时长
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+// 断言Clock是dyn safe的
+const _: fn(&dyn Clock) -> &dyn Clock = |x| x;
@@ -0,0 +1,98 @@
+use alloc::vec::Vec;
+
+use crate::wire::{Ipv4Address, MacAddress, ethernet::ETHERTYPE_IPV4, read_u16};
+
+/// 以太网上IPv4地址解析报文的长度
+pub const PACKET_LEN: usize = 28;
+
+const HARDWARE_ETHERNET: u16 = 1;
+
+/// ARP操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArpOperation {
+    Request,
+    Reply,
+}
+
+/// 以太网上IPv4地址的ARP报文
+///
+/// 其他硬件类型与协议类型的报文不被支持
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArpPacket {
+    pub operation: ArpOperation,
+    pub sender_mac: MacAddress,
+    pub sender_ip: Ipv4Address,
+    pub target_mac: MacAddress,
+    pub target_ip: Ipv4Address,
+}
+
+impl ArpPacket {
+    /// 解析ARP报文，报文格式不受支持时返回None
+    pub fn parse(buf: &[u8]) -> Option<Self> {
+        if buf.len() < PACKET_LEN
+            || read_u16(buf, 0) != HARDWARE_ETHERNET
+            || read_u16(buf, 2) != ETHERTYPE_IPV4
+            || buf[4] != 6
+            || buf[5] != 4
+        {
+            return None;
+        }
+        let operation = match read_u16(buf, 6) {
+            1 => ArpOperation::Request,
+            2 => ArpOperation::Reply,
+            _ => return None,
+        };
+        Some(Self {
+            operation,
+            sender_mac: MacAddress(buf[8..14].try_into().unwrap()),
+            sender_ip: Ipv4Address(buf[14..18].try_into().unwrap()),
+            target_mac: MacAddress(buf[18..24].try_into().unwrap()),
+            target_ip: Ipv4Address(buf[24..28].try_into().unwrap()),
+        })
+    }
+
+    /// 将ARP报文追加到`buf`末尾
+    pub fn emit(&self, buf: &mut Vec<u8>) {
+        let operation: u16 = match self.operation {
+            ArpOperation::Request => 1,
+            ArpOperation::Reply => 2,
+        };
+        buf.extend_from_slice(&HARDWARE_ETHERNET.to_be_bytes());
+        buf.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        buf.extend_from_slice(&[6, 4]);
+        buf.extend_from_slice(&operation.to_be_bytes());
+        buf.extend_from_slice(&self.sender_mac.0);
+        buf.extend_from_slice(&self.sender_ip.0);
+        buf.extend_from_slice(&self.target_mac.0);
+        buf.extend_from_slice(&self.target_ip.0);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::wire::{
+        Ipv4Address, MacAddress,
+        arp::{ArpOperation, ArpPacket, PACKET_LEN},
+    };
+
+    #[test]
+    fn test_parse_emit() {
+        let packet = ArpPacket {
+            operation: ArpOperation::Request,
+            sender_mac: MacAddress([0x52, 0x54, 0, 0x12, 0x34, 0x56]),
+            sender_ip: Ipv4Address::new(10, 0, 2, 15),
+            target_mac: MacAddress::ZERO,
+            target_ip: Ipv4Address::new(10, 0, 2, 2),
+        };
+        let mut buf = Vec::new();
+        packet.emit(&mut buf);
+        assert_eq!(buf.len(), PACKET_LEN);
+        assert_eq!(ArpPacket::parse(&buf), Some(packet));
+
+        // 不支持的操作类型
+        buf[7] = 3;
+        assert_eq!(ArpPacket::parse(&buf), None);
+    }
+}
@@ -0,0 +1,67 @@
+use alloc::vec::Vec;
+
+use crate::wire::{MacAddress, read_u16};
+
+/// 以太网头部长度
+pub const HEADER_LEN: usize = 14;
+
+pub const ETHERTYPE_IPV4: u16 = 0x0800;
+pub const ETHERTYPE_ARP: u16 = 0x0806;
+
+/// 以太网帧
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EthernetFrame<'a> {
+    pub destination: MacAddress,
+    pub source: MacAddress,
+    pub ethertype: u16,
+    pub payload: &'a [u8],
+}
+
+impl<'a> EthernetFrame<'a> {
+    /// 解析以太网帧，长度不足时返回None
+    pub fn parse(buf: &'a [u8]) -> Option<Self> {
+        if buf.len() < HEADER_LEN {
+            return None;
+        }
+        Some(Self {
+            destination: MacAddress(buf[0..6].try_into().unwrap()),
+            source: MacAddress(buf[6..12].try_into().unwrap()),
+            ethertype: read_u16(buf, 12),
+            payload: &buf[HEADER_LEN..],
+        })
+    }
+
+    /// 将以太网帧追加到`buf`末尾
+    pub fn emit(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.destination.0);
+        buf.extend_from_slice(&self.source.0);
+        buf.extend_from_slice(&self.ethertype.to_be_bytes());
+        buf.extend_from_slice(self.payload);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::wire::{
+        MacAddress,
+        ethernet::{ETHERTYPE_ARP, EthernetFrame},
+    };
+
+    #[test]
+    fn test_parse_emit() {
+        let frame = EthernetFrame {
+            destination: MacAddress::BROADCAST,
+            source: MacAddress([0x52, 0x54, 0, 0x12, 0x34, 0x56]),
+            ethertype: ETHERTYPE_ARP,
+            payload: &[1, 2, 3],
+        };
+        let mut buf = Vec::new();
+        frame.emit(&mut buf);
+        assert_eq!(buf.len(), 17);
+        assert_eq!(&buf[12..14], &[0x08, 0x06]);
+        assert_eq!(EthernetFrame::parse(&buf), Some(frame));
+        assert_eq!(EthernetFrame::parse(&buf[..13]), None);
+    }
+}
@@ -0,0 +1,163 @@
+use alloc::vec::Vec;
+
+use crate::wire::{Ipv4Address, checksum, read_u16};
+
+/// 不含选项的IPv4头部长度
+pub const HEADER_LEN: usize = 20;
+
+pub const PROTOCOL_ICMP: u8 = 1;
+pub const PROTOCOL_TCP: u8 = 6;
+pub const PROTOCOL_UDP: u8 = 17;
+
+/// 默认的生存时间
+const DEFAULT_TTL: u8 = 64;
+/// 标志位：不分片
+const FLAG_DONT_FRAGMENT: u16 = 0x4000;
+/// 标志位：更多分片
+const FLAG_MORE_FRAGMENTS: u16 = 0x2000;
+const FRAGMENT_OFFSET_MASK: u16 = 0x1FFF;
+
+/// IPv4数据报
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ipv4Packet<'a> {
+    pub source: Ipv4Address,
+    pub destination: Ipv4Address,
+    pub protocol: u8,
+    pub identification: u16,
+    pub ttl: u8,
+    pub payload: &'a [u8],
+}
+
+impl<'a> Ipv4Packet<'a> {
+    pub fn new(
+        source: Ipv4Address,
+        destination: Ipv4Address,
+        protocol: u8,
+        payload: &'a [u8],
+    ) -> Self {
+        Self {
+            source,
+            destination,
+            protocol,
+            identification: 0,
+            ttl: DEFAULT_TTL,
+            payload,
+        }
+    }
+
+    /// 解析IPv4数据报
+    ///
+    /// 头部格式错误、校验和错误时返回None。协议栈不支持分片重组，分片的数据报同样返回None。
+    /// 头部中的选项被忽略，链路层的填充字节根据总长度去除。
+    pub fn parse(buf: &'a [u8]) -> Option<Self> {
+        if buf.len() < HEADER_LEN || buf[0] >> 4 != 4 {
+            return None;
+        }
+        let header_len = (buf[0] & 0xF) as usize * 4;
+        let total_len = read_u16(buf, 2) as usize;
+        if header_len < HEADER_LEN || total_len < header_len || buf.len() < total_len {
+            return None;
+        }
+        if checksum(&[&buf[..header_len]]) != 0 {
+            return None;
+        }
+        let flags = read_u16(buf, 6);
+        if flags & FLAG_MORE_FRAGMENTS != 0 || flags & FRAGMENT_OFFSET_MASK != 0 {
+            return None;
+        }
+        Some(Self {
+            source: Ipv4Address(buf[12..16].try_into().unwrap()),
+            destination: Ipv4Address(buf[16..20].try_into().unwrap()),
+            protocol: buf[9],
+            identification: read_u16(buf, 4),
+            ttl: buf[8],
+            payload: &buf[header_len..total_len],
+        })
+    }
+
+    /// 将IPv4数据报追加到`buf`末尾
+    ///
+    /// 发出的数据报不含选项，并设置不分片标志
+    pub fn emit(&self, buf: &mut Vec<u8>) {
+        let total_len = (HEADER_LEN + self.payload.len()) as u16;
+        let mut header = [0u8; HEADER_LEN];
+        header[0] = 0x45;
+        header[2..4].copy_from_slice(&total_len.to_be_bytes());
+        header[4..6].copy_from_slice(&self.identification.to_be_bytes());
+        header[6..8].copy_from_slice(&FLAG_DONT_FRAGMENT.to_be_bytes());
+        header[8] = self.ttl;
+        header[9] = self.protocol;
+        header[12..16].copy_from_slice(&self.source.0);
+        header[16..20].copy_from_slice(&self.destination.0);
+        let checksum = checksum(&[&header]);
+        header[10..12].copy_from_slice(&checksum.to_be_bytes());
+        buf.extend_from_slice(&header);
+        buf.extend_from_slice(self.payload);
+    }
+}
+
+/// 计算TCP、UDP校验和时使用的伪头部
+pub fn pseudo_header(
+    source: Ipv4Address,
+    destination: Ipv4Address,
+    protocol: u8,
+    len: u16,
+) -> [u8; 12] {
+    let mut header = [0u8; 12];
+    header[0..4].copy_from_slice(&source.0);
+    header[4..8].copy_from_slice(&destination.0);
+    header[9] = protocol;
+    header[10..12].copy_from_slice(&len.to_be_bytes());
+    header
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::wire::{
+        Ipv4Address,
+        ipv4::{HEADER_LEN, Ipv4Packet, PROTOCOL_UDP},
+    };
+
+    #[test]
+    fn test_parse_emit() {
+        let mut packet = Ipv4Packet::new(
+            Ipv4Address::new(10, 0, 2, 15),
+            Ipv4Address::new(10, 0, 2, 2),
+            PROTOCOL_UDP,
+            &[1, 2, 3, 4, 5],
+        );
+        packet.identification = 0x1234;
+        let mut buf = Vec::new();
+        packet.emit(&mut buf);
+        assert_eq!(buf.len(), HEADER_LEN + 5);
+        assert_eq!(Ipv4Packet::parse(&buf), Some(packet));
+
+        // 链路层填充的字节不属于负载
+        buf.extend_from_slice(&[0; 8]);
+        assert_eq!(Ipv4Packet::parse(&buf).unwrap().payload, &[1, 2, 3, 4, 5]);
+
+        // 校验和错误
+        buf[8] = 1;
+        assert_eq!(Ipv4Packet::parse(&buf), None);
+    }
+
+    #[test]
+    fn test_drop_fragment() {
+        let packet = Ipv4Packet::new(
+            Ipv4Address::new(10, 0, 2, 15),
+            Ipv4Address::new(10, 0, 2, 2),
+            PROTOCOL_UDP,
+            &[0; 8],
+        );
+        let mut buf = Vec::new();
+        packet.emit(&mut buf);
+        // 设置MF标志并重新计算校验和
+        buf[6] = 0x20;
+        buf[10..12].fill(0);
+        let checksum = crate::wire::checksum(&[&buf[..HEADER_LEN]]);
+        buf[10..12].copy_from_slice(&checksum.to_be_bytes());
+        assert_eq!(Ipv4Packet::parse(&buf), None);
+    }
+}
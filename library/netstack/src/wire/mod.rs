@@ -0,0 +1,154 @@
+//! 各层协议的报文格式
+//!
+//! 此模块仅负责报文的解析与构造，不维护任何状态。所有多字节字段均为网络字节序（大端）。
+
+use core::fmt;
+
+pub mod arp;
+pub mod ethernet;
+pub mod ipv4;
+pub mod tcp;
+pub mod udp;
+
+/// 以太网MAC地址
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MacAddress(pub [u8; 6]);
+
+impl MacAddress {
+    pub const BROADCAST: Self = Self([0xFF; 6]);
+    pub const ZERO: Self = Self([0; 6]);
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+/// IPv4地址
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ipv4Address(pub [u8; 4]);
+
+impl Ipv4Address {
+    pub const UNSPECIFIED: Self = Self([0; 4]);
+    pub const BROADCAST: Self = Self([0xFF; 4]);
+
+    pub const fn new(a: u8, b: u8, c: u8, d: u8) -> Self {
+        Self([a, b, c, d])
+    }
+
+    pub const fn from_u32(value: u32) -> Self {
+        Self(value.to_be_bytes())
+    }
+
+    pub const fn to_u32(self) -> u32 {
+        u32::from_be_bytes(self.0)
+    }
+
+    pub fn is_broadcast(self) -> bool {
+        self == Self::BROADCAST
+    }
+
+    /// 是否与`other`位于同一子网
+    pub fn in_subnet(self, other: Self, netmask: Self) -> bool {
+        self.to_u32() & netmask.to_u32() == other.to_u32() & netmask.to_u32()
+    }
+}
+
+impl fmt::Display for Ipv4Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d] = self.0;
+        write!(f, "{a}.{b}.{c}.{d}")
+    }
+}
+
+/// IPv4地址与端口
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SocketAddrV4 {
+    pub ip: Ipv4Address,
+    pub port: u16,
+}
+
+impl SocketAddrV4 {
+    pub const fn new(ip: Ipv4Address, port: u16) -> Self {
+        Self { ip, port }
+    }
+}
+
+impl fmt::Display for SocketAddrV4 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.ip, self.port)
+    }
+}
+
+/// 计算互联网校验和（RFC 1071）
+///
+/// 数据可以分为多段传入，效果等同于将各段拼接后计算。
+/// 对包含校验和字段的报文计算时，结果为0表示校验通过。
+pub fn checksum(parts: &[&[u8]]) -> u16 {
+    let mut sum = 0u32;
+    // 上一段剩余的奇数字节，作为下一个16位字的高位
+    let mut pending: Option<u8> = None;
+    for part in parts {
+        let mut bytes = part.iter().copied();
+        if let Some(high) = pending.take() {
+            match bytes.next() {
+                Some(low) => sum += u16::from_be_bytes([high, low]) as u32,
+                None => {
+                    pending = Some(high);
+                    continue;
+                }
+            }
+        }
+        loop {
+            match (bytes.next(), bytes.next()) {
+                (Some(high), Some(low)) => sum += u16::from_be_bytes([high, low]) as u32,
+                (Some(high), None) => {
+                    pending = Some(high);
+                    break;
+                }
+                _ => break,
+            }
+        }
+        // 及时折叠进位，避免溢出
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    if let Some(high) = pending {
+        sum += (high as u32) << 8;
+    }
+    while sum >> 16 != 0 {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([buf[offset], buf[offset + 1]])
+}
+
+#[cfg(test)]
+mod test {
+    use crate::wire::{Ipv4Address, checksum};
+
+    #[test]
+    fn test_checksum() {
+        // RFC 1071 中的示例
+        let data = [0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7];
+        assert_eq!(checksum(&[&data]), !0xddf2);
+        // 分段传入时，奇数长度的分段与后续分段拼接
+        assert_eq!(checksum(&[&data[..3], &data[3..5], &data[5..]]), !0xddf2);
+        assert_eq!(checksum(&[&data[..1], &[], &data[1..]]), !0xddf2);
+        // 奇数长度的数据末尾补零
+        assert_eq!(checksum(&[&[0x12]]), !0x1200);
+    }
+
+    #[test]
+    fn test_subnet() {
+        let netmask = Ipv4Address::new(255, 255, 255, 0);
+        let address = Ipv4Address::new(10, 0, 2, 15);
+        assert!(address.in_subnet(Ipv4Address::new(10, 0, 2, 2), netmask));
+        assert!(!address.in_subnet(Ipv4Address::new(10, 0, 3, 2), netmask));
+        assert_eq!(Ipv4Address::from_u32(address.to_u32()), address);
+    }
+}
@@ -0,0 +1,209 @@
+use alloc::vec::Vec;
+
+use crate::wire::{
+    Ipv4Address, checksum,
+    ipv4::{PROTOCOL_TCP, pseudo_header},
+    read_u16,
+};
+
+/// 不含选项的TCP头部长度
+pub const HEADER_LEN: usize = 20;
+
+pub const FLAG_FIN: u8 = 0x01;
+pub const FLAG_SYN: u8 = 0x02;
+pub const FLAG_RST: u8 = 0x04;
+pub const FLAG_PSH: u8 = 0x08;
+pub const FLAG_ACK: u8 = 0x10;
+
+/// 选项类型：选项列表结束
+const OPTION_END: u8 = 0;
+/// 选项类型：无操作，用于对齐
+const OPTION_NOP: u8 = 1;
+/// 选项类型：最大报文段长度
+const OPTION_MSS: u8 = 2;
+
+/// TCP报文段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TcpPacket<'a> {
+    pub source_port: u16,
+    pub destination_port: u16,
+    pub seq: u32,
+    pub ack: u32,
+    pub flags: u8,
+    pub window: u16,
+    /// 最大报文段长度选项，仅在SYN报文段中有意义
+    pub mss: Option<u16>,
+    pub payload: &'a [u8],
+}
+
+impl<'a> TcpPacket<'a> {
+    /// 解析TCP报文段，`source`与`destination`为IP层的地址，用于验证校验和
+    ///
+    /// 头部格式错误或校验和错误时返回None。除最大报文段长度外的选项被忽略。
+    pub fn parse(buf: &'a [u8], source: Ipv4Address, destination: Ipv4Address) -> Option<Self> {
+        if buf.len() < HEADER_LEN {
+            return None;
+        }
+        let header_len = (buf[12] >> 4) as usize * 4;
+        if header_len < HEADER_LEN || header_len > buf.len() {
+            return None;
+        }
+        let pseudo_header = pseudo_header(source, destination, PROTOCOL_TCP, buf.len() as u16);
+        if checksum(&[&pseudo_header, buf]) != 0 {
+            return None;
+        }
+        Some(Self {
+            source_port: read_u16(buf, 0),
+            destination_port: read_u16(buf, 2),
+            seq: u32::from_be_bytes(buf[4..8].try_into().unwrap()),
+            ack: u32::from_be_bytes(buf[8..12].try_into().unwrap()),
+            flags: buf[13],
+            window: read_u16(buf, 14),
+            mss: parse_mss(&buf[HEADER_LEN..header_len]),
+            payload: &buf[header_len..],
+        })
+    }
+
+    /// 将TCP报文段追加到`buf`末尾
+    pub fn emit(&self, buf: &mut Vec<u8>, source: Ipv4Address, destination: Ipv4Address) {
+        let mut header = [0u8; HEADER_LEN + 4];
+        let header_len = match self.mss {
+            Some(mss) => {
+                header[20] = OPTION_MSS;
+                header[21] = 4;
+                header[22..24].copy_from_slice(&mss.to_be_bytes());
+                HEADER_LEN + 4
+            }
+            None => HEADER_LEN,
+        };
+        let header = &mut header[..header_len];
+        header[0..2].copy_from_slice(&self.source_port.to_be_bytes());
+        header[2..4].copy_from_slice(&self.destination_port.to_be_bytes());
+        header[4..8].copy_from_slice(&self.seq.to_be_bytes());
+        header[8..12].copy_from_slice(&self.ack.to_be_bytes());
+        header[12] = ((header_len / 4) as u8) << 4;
+        header[13] = self.flags;
+        header[14..16].copy_from_slice(&self.window.to_be_bytes());
+        let len = (header_len + self.payload.len()) as u16;
+        let pseudo_header = pseudo_header(source, destination, PROTOCOL_TCP, len);
+        let checksum = checksum(&[&pseudo_header, header, self.payload]);
+        header[16..18].copy_from_slice(&checksum.to_be_bytes());
+        buf.extend_from_slice(header);
+        buf.extend_from_slice(self.payload);
+    }
+
+    /// 报文段占用的序号数量，SYN与FIN各占用一个序号
+    pub fn sequence_len(&self) -> u32 {
+        let mut len = self.payload.len() as u32;
+        if self.flags & FLAG_SYN != 0 {
+            len += 1;
+        }
+        if self.flags & FLAG_FIN != 0 {
+            len += 1;
+        }
+        len
+    }
+}
+
+/// 在选项中查找最大报文段长度，选项格式错误时忽略剩余选项
+fn parse_mss(mut options: &[u8]) -> Option<u16> {
+    while let Some(&kind) = options.first() {
+        match kind {
+            OPTION_END => break,
+            OPTION_NOP => options = &options[1..],
+            _ => {
+                let len = *options.get(1)? as usize;
+                if len < 2 || len > options.len() {
+                    return None;
+                }
+                if kind == OPTION_MSS && len == 4 {
+                    return Some(read_u16(options, 2));
+                }
+                options = &options[len..];
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::wire::{
+        Ipv4Address,
+        tcp::{FLAG_ACK, FLAG_PSH, FLAG_SYN, HEADER_LEN, TcpPacket},
+    };
+
+    #[test]
+    fn test_parse_emit() {
+        let source = Ipv4Address::new(10, 0, 2, 15);
+        let destination = Ipv4Address::new(10, 0, 2, 2);
+        let syn = TcpPacket {
+            source_port: 49152,
+            destination_port: 80,
+            seq: 0x12345678,
+            ack: 0,
+            flags: FLAG_SYN,
+            window: 65535,
+            mss: Some(1460),
+            payload: &[],
+        };
+        let mut buf = Vec::new();
+        syn.emit(&mut buf, source, destination);
+        assert_eq!(buf.len(), HEADER_LEN + 4);
+        assert_eq!(TcpPacket::parse(&buf, source, destination), Some(syn));
+        assert_eq!(syn.sequence_len(), 1);
+
+        let data = TcpPacket {
+            flags: FLAG_ACK | FLAG_PSH,
+            ack: 0xFFFFFFFF,
+            mss: None,
+            payload: b"hello",
+            ..syn
+        };
+        let mut buf = Vec::new();
+        data.emit(&mut buf, source, destination);
+        assert_eq!(TcpPacket::parse(&buf, source, destination), Some(data));
+        assert_eq!(data.sequence_len(), 5);
+
+        // 伪头部中的地址参与校验和计算
+        let other = Ipv4Address::new(10, 0, 2, 3);
+        assert_eq!(TcpPacket::parse(&buf, source, other), None);
+        buf[HEADER_LEN] ^= 1;
+        assert_eq!(TcpPacket::parse(&buf, source, destination), None);
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let source = Ipv4Address::new(10, 0, 2, 15);
+        let destination = Ipv4Address::new(10, 0, 2, 2);
+        let packet = TcpPacket {
+            source_port: 1,
+            destination_port: 2,
+            seq: 0,
+            ack: 0,
+            flags: FLAG_SYN,
+            window: 0,
+            mss: None,
+            payload: &[],
+        };
+        let mut buf = Vec::new();
+        packet.emit(&mut buf, source, destination);
+        // 追加 NOP、窗口扩大(3字节)、MSS 与填充，并重新计算校验和
+        buf.extend_from_slice(&[1, 3, 3, 7, 2, 4, 0x05, 0xB4, 0, 0, 0, 0]);
+        buf[12] = 8 << 4;
+        buf[16..18].fill(0);
+        let pseudo_header = crate::wire::ipv4::pseudo_header(
+            source,
+            destination,
+            crate::wire::ipv4::PROTOCOL_TCP,
+            buf.len() as u16,
+        );
+        let checksum = crate::wire::checksum(&[&pseudo_header, &buf]);
+        buf[16..18].copy_from_slice(&checksum.to_be_bytes());
+        let parsed = TcpPacket::parse(&buf, source, destination).unwrap();
+        assert_eq!(parsed.mss, Some(1460));
+        assert!(parsed.payload.is_empty());
+    }
+}
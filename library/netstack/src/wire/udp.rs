@@ -0,0 +1,95 @@
+use alloc::vec::Vec;
+
+use crate::wire::{
+    Ipv4Address, checksum,
+    ipv4::{PROTOCOL_UDP, pseudo_header},
+    read_u16,
+};
+
+/// UDP头部长度
+pub const HEADER_LEN: usize = 8;
+
+/// UDP数据报
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UdpPacket<'a> {
+    pub source_port: u16,
+    pub destination_port: u16,
+    pub payload: &'a [u8],
+}
+
+impl<'a> UdpPacket<'a> {
+    /// 解析UDP数据报，`source`与`destination`为IP层的地址，用于验证校验和
+    ///
+    /// 长度错误或校验和错误时返回None。校验和字段为0表示发送方未计算校验和，不进行验证。
+    pub fn parse(buf: &'a [u8], source: Ipv4Address, destination: Ipv4Address) -> Option<Self> {
+        if buf.len() < HEADER_LEN {
+            return None;
+        }
+        let len = read_u16(buf, 4) as usize;
+        if len < HEADER_LEN || len > buf.len() {
+            return None;
+        }
+        let buf = &buf[..len];
+        if read_u16(buf, 6) != 0 {
+            let pseudo_header = pseudo_header(source, destination, PROTOCOL_UDP, len as u16);
+            if checksum(&[&pseudo_header, buf]) != 0 {
+                return None;
+            }
+        }
+        Some(Self {
+            source_port: read_u16(buf, 0),
+            destination_port: read_u16(buf, 2),
+            payload: &buf[HEADER_LEN..],
+        })
+    }
+
+    /// 将UDP数据报追加到`buf`末尾
+    pub fn emit(&self, buf: &mut Vec<u8>, source: Ipv4Address, destination: Ipv4Address) {
+        let len = (HEADER_LEN + self.payload.len()) as u16;
+        let mut header = [0u8; HEADER_LEN];
+        header[0..2].copy_from_slice(&self.source_port.to_be_bytes());
+        header[2..4].copy_from_slice(&self.destination_port.to_be_bytes());
+        header[4..6].copy_from_slice(&len.to_be_bytes());
+        let pseudo_header = pseudo_header(source, destination, PROTOCOL_UDP, len);
+        let checksum = match checksum(&[&pseudo_header, &header, self.payload]) {
+            // 计算结果为0时以全1表示，0保留为未计算校验和
+            0 => 0xFFFF,
+            checksum => checksum,
+        };
+        header[6..8].copy_from_slice(&checksum.to_be_bytes());
+        buf.extend_from_slice(&header);
+        buf.extend_from_slice(self.payload);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::wire::{
+        Ipv4Address,
+        udp::{HEADER_LEN, UdpPacket},
+    };
+
+    #[test]
+    fn test_parse_emit() {
+        let source = Ipv4Address::new(10, 0, 2, 15);
+        let destination = Ipv4Address::new(10, 0, 2, 2);
+        let packet = UdpPacket {
+            source_port: 49152,
+            destination_port: 53,
+            payload: b"hello",
+        };
+        let mut buf = Vec::new();
+        packet.emit(&mut buf, source, destination);
+        assert_eq!(buf.len(), HEADER_LEN + 5);
+        assert_eq!(UdpPacket::parse(&buf, source, destination), Some(packet));
+
+        // 地址不同，伪头部校验失败
+        assert_eq!(UdpPacket::parse(&buf, source, source), None);
+
+        // 校验和为0时不验证
+        buf[6..8].fill(0);
+        assert_eq!(UdpPacket::parse(&buf, source, source), Some(packet));
+    }
+}
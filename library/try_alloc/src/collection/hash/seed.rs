@@ -6,13 +6,22 @@ pub trait SeedSupplier {
     fn gen_seed(&self) -> u64;
 }
 
+static SEED: AtomicU64 = AtomicU64::new(0);
+
 #[derive(Debug, Clone, Copy)]
 pub struct SimpleGlobalSeed;
 
+impl SimpleGlobalSeed {
+    /// 设置生成种子的初始值
+    ///
+    /// 初始值固定时，哈希表的种子可被预测。使用方应在创建哈希表之前以随机数调用
+    pub fn set_seed(seed: u64) {
+        SEED.store(seed, Ordering::Relaxed);
+    }
+}
+
 impl SeedSupplier for SimpleGlobalSeed {
     fn gen_seed(&self) -> u64 {
-        static SEED: AtomicU64 = AtomicU64::new(0);
-
         let x = SEED.fetch_add(1, Ordering::Relaxed);
         x ^ (x << 13) ^ (x >> 7)
     }
@@ -0,0 +1,31 @@
+; 跳板代码，由loader复制到恒等映射的低端内存中执行，因此必须与位置无关
+;
+; 进入时：
+;   rdi/rsi/rdx - 传递给kmain的参数
+;   rcx         - 内核页表PML4的物理地址
+;   r8          - 内核入口地址
+;   r10         - GDTR的地址
+
+    bits 64
+    ; 加载64位GDT
+    lgdt [r10]
+    ; 远返回以重新加载cs
+    push 0x18
+    lea rax, [rel .reload_cs]
+    push rax
+    retfq
+
+.reload_cs:
+    ; 设置各段寄存器
+    mov ax, 0x20
+    mov ds, ax
+    mov es, ax
+    mov fs, ax
+    mov gs, ax
+    mov ss, ax
+    ; 切换到为内核准备的页表
+    mov cr3, rcx
+    ; 设置内核栈
+    mov rsp, 0xFFFF_FFFF_FFDF_FFF8
+    ; 调用内核kmain
+    jmp r8
@@ -0,0 +1,20 @@
+use std::{fs, path::Path, process::Command};
+
+fn main() {
+    let output = std::env::var("OUT_DIR").unwrap();
+    fs::create_dir_all(&output).unwrap();
+    let mut cmd = Command::new("nasm");
+    cmd.arg("-f")
+        .arg("bin")
+        .arg("./asm/trampoline.s")
+        .arg("-o")
+        .arg(Path::new(&output).join("trampoline.bin"));
+    let output = cmd.output().unwrap();
+    if !output.status.success() {
+        panic!(
+            "failed to build trampoline.s.\n  output: {}\n  stderr: {}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
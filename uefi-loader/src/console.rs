@@ -0,0 +1,69 @@
+use core::{fmt::Write, ptr};
+
+use crate::efi::{SimpleTextOutputProtocol, SystemTable};
+
+/// 固件提供的文本输出协议，退出启动服务后置空
+static mut CON_OUT: *mut SimpleTextOutputProtocol = ptr::null_mut();
+
+/// 通过UEFI文本输出协议输出文字信息的工具类
+///
+/// 退出启动服务后，输出会被忽略
+pub struct Console;
+
+/// 初始化控制台
+///
+/// Safety: system_table必须为固件传入的系统表，且尚未退出启动服务
+pub unsafe fn init(system_table: *mut SystemTable) {
+    unsafe {
+        CON_OUT = (*system_table).con_out;
+    }
+}
+
+/// 停用控制台，在退出启动服务前调用
+pub fn disable() {
+    unsafe {
+        CON_OUT = ptr::null_mut();
+    }
+}
+
+impl Console {
+    fn write_utf16(&mut self, s: &str) {
+        let con_out = unsafe { CON_OUT };
+        if con_out.is_null() {
+            return;
+        }
+
+        // 协议要求以0结尾的UCS-2字符串，分段转换后输出
+        let mut buf = [0u16; 128];
+        let mut len = 0;
+        for c in s.encode_utf16() {
+            // 换行需要同时回车
+            if c == b'\n' as u16 {
+                buf[len] = b'\r' as u16;
+                len += 1;
+            }
+            buf[len] = c;
+            len += 1;
+
+            if len >= buf.len() - 2 {
+                buf[len] = 0;
+                // Safety: con_out由固件提供，在退出启动服务前一直有效
+                unsafe {
+                    ((*con_out).output_string)(con_out, buf.as_ptr());
+                }
+                len = 0;
+            }
+        }
+        buf[len] = 0;
+        unsafe {
+            ((*con_out).output_string)(con_out, buf.as_ptr());
+        }
+    }
+}
+
+impl Write for Console {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write_utf16(s);
+        Ok(())
+    }
+}
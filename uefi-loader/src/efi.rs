@@ -0,0 +1,259 @@
+use core::ffi::c_void;
+
+// 以下为loader用到的UEFI结构与协议，仅声明了需要调用的部分，未使用的函数指针以usize占位
+
+pub type Handle = *mut c_void;
+pub type Status = usize;
+
+pub const STATUS_SUCCESS: Status = 0;
+/// 错误状态的最高位为1
+const STATUS_ERROR_BIT: Status = 1 << (usize::BITS - 1);
+pub const STATUS_INVALID_PARAMETER: Status = STATUS_ERROR_BIT | 2;
+pub const STATUS_BUFFER_TOO_SMALL: Status = STATUS_ERROR_BIT | 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct Guid(pub u32, pub u16, pub u16, pub [u8; 8]);
+
+pub const LOADED_IMAGE_PROTOCOL_GUID: Guid = Guid(
+    0x5B1B31A1,
+    0x9562,
+    0x11d2,
+    [0x8E, 0x3F, 0x00, 0xA0, 0xC9, 0x69, 0x72, 0x3B],
+);
+pub const SIMPLE_FILE_SYSTEM_PROTOCOL_GUID: Guid = Guid(
+    0x964E5B22,
+    0x6459,
+    0x11d2,
+    [0x8E, 0x39, 0x00, 0xA0, 0xC9, 0x69, 0x72, 0x3B],
+);
+pub const GRAPHICS_OUTPUT_PROTOCOL_GUID: Guid = Guid(
+    0x9042A9DE,
+    0x23DC,
+    0x4A38,
+    [0x96, 0xFB, 0x7A, 0xDE, 0xD0, 0x80, 0x51, 0x6A],
+);
+pub const ACPI_20_TABLE_GUID: Guid = Guid(
+    0x8868E871,
+    0xE4F1,
+    0x11D3,
+    [0xBC, 0x22, 0x00, 0x80, 0xC7, 0x3C, 0x88, 0x81],
+);
+pub const ACPI_TABLE_GUID: Guid = Guid(
+    0xEB9D2D30,
+    0x2D88,
+    0x11D3,
+    [0x9A, 0x16, 0x00, 0x90, 0x27, 0x3F, 0xC1, 0x4D],
+);
+
+#[repr(C)]
+pub struct TableHeader {
+    pub signature: u64,
+    pub revision: u32,
+    pub header_size: u32,
+    pub crc32: u32,
+    pub reserved: u32,
+}
+
+#[repr(C)]
+pub struct SystemTable {
+    pub hdr: TableHeader,
+    pub firmware_vendor: *const u16,
+    pub firmware_revision: u32,
+    pub console_in_handle: Handle,
+    pub con_in: usize,
+    pub console_out_handle: Handle,
+    pub con_out: *mut SimpleTextOutputProtocol,
+    pub standard_error_handle: Handle,
+    pub std_err: *mut SimpleTextOutputProtocol,
+    pub runtime_services: usize,
+    pub boot_services: *mut BootServices,
+    pub number_of_table_entries: usize,
+    pub configuration_table: *const ConfigurationTable,
+}
+
+#[repr(C)]
+pub struct ConfigurationTable {
+    pub vendor_guid: Guid,
+    pub vendor_table: *const c_void,
+}
+
+#[repr(C)]
+pub struct SimpleTextOutputProtocol {
+    pub reset: usize,
+    pub output_string: unsafe extern "efiapi" fn(this: *mut Self, string: *const u16) -> Status,
+}
+
+#[repr(C)]
+pub struct BootServices {
+    pub hdr: TableHeader,
+    pub raise_tpl: usize,
+    pub restore_tpl: usize,
+    pub allocate_pages: unsafe extern "efiapi" fn(
+        allocate_type: AllocateType,
+        memory_type: MemoryType,
+        pages: usize,
+        memory: *mut u64,
+    ) -> Status,
+    pub free_pages: unsafe extern "efiapi" fn(memory: u64, pages: usize) -> Status,
+    pub get_memory_map: unsafe extern "efiapi" fn(
+        memory_map_size: *mut usize,
+        memory_map: *mut MemoryDescriptor,
+        map_key: *mut usize,
+        descriptor_size: *mut usize,
+        descriptor_version: *mut u32,
+    ) -> Status,
+    pub allocate_pool: unsafe extern "efiapi" fn(
+        pool_type: MemoryType,
+        size: usize,
+        buffer: *mut *mut u8,
+    ) -> Status,
+    pub free_pool: unsafe extern "efiapi" fn(buffer: *mut u8) -> Status,
+    pub create_event: usize,
+    pub set_timer: usize,
+    pub wait_for_event: usize,
+    pub signal_event: usize,
+    pub close_event: usize,
+    pub check_event: usize,
+    pub install_protocol_interface: usize,
+    pub reinstall_protocol_interface: usize,
+    pub uninstall_protocol_interface: usize,
+    pub handle_protocol: unsafe extern "efiapi" fn(
+        handle: Handle,
+        protocol: *const Guid,
+        interface: *mut *mut c_void,
+    ) -> Status,
+    pub reserved: usize,
+    pub register_protocol_notify: usize,
+    pub locate_handle: usize,
+    pub locate_device_path: usize,
+    pub install_configuration_table: usize,
+    pub load_image: usize,
+    pub start_image: usize,
+    pub exit: usize,
+    pub unload_image: usize,
+    pub exit_boot_services:
+        unsafe extern "efiapi" fn(image_handle: Handle, map_key: usize) -> Status,
+    pub get_next_monotonic_count: usize,
+    pub stall: usize,
+    pub set_watchdog_timer: unsafe extern "efiapi" fn(
+        timeout: usize,
+        watchdog_code: u64,
+        data_size: usize,
+        watchdog_data: *const u16,
+    ) -> Status,
+    pub connect_controller: usize,
+    pub disconnect_controller: usize,
+    pub open_protocol: usize,
+    pub close_protocol: usize,
+    pub open_protocol_information: usize,
+    pub protocols_per_handle: usize,
+    pub locate_handle_buffer: usize,
+    pub locate_protocol: unsafe extern "efiapi" fn(
+        protocol: *const Guid,
+        registration: *mut c_void,
+        interface: *mut *mut c_void,
+    ) -> Status,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(u32)]
+#[allow(dead_code)]
+pub enum AllocateType {
+    AnyPages = 0,
+    MaxAddress = 1,
+    Address = 2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct MemoryType(pub u32);
+
+impl MemoryType {
+    pub const LOADER_CODE: Self = Self(1);
+    pub const LOADER_DATA: Self = Self(2);
+    pub const BOOT_SERVICES_CODE: Self = Self(3);
+    pub const BOOT_SERVICES_DATA: Self = Self(4);
+    pub const CONVENTIONAL: Self = Self(7);
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct MemoryDescriptor {
+    pub memory_type: MemoryType,
+    pub physical_start: u64,
+    pub virtual_start: u64,
+    pub number_of_pages: u64,
+    pub attribute: u64,
+}
+
+#[repr(C)]
+pub struct LoadedImageProtocol {
+    pub revision: u32,
+    pub parent_handle: Handle,
+    pub system_table: *mut SystemTable,
+    pub device_handle: Handle,
+}
+
+#[repr(C)]
+pub struct SimpleFileSystemProtocol {
+    pub revision: u64,
+    pub open_volume:
+        unsafe extern "efiapi" fn(this: *mut Self, root: *mut *mut FileProtocol) -> Status,
+}
+
+#[repr(C)]
+pub struct FileProtocol {
+    pub revision: u64,
+    pub open: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        new_handle: *mut *mut Self,
+        file_name: *const u16,
+        open_mode: u64,
+        attributes: u64,
+    ) -> Status,
+    pub close: unsafe extern "efiapi" fn(this: *mut Self) -> Status,
+    pub delete: usize,
+    pub read: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        buffer_size: *mut usize,
+        buffer: *mut u8,
+    ) -> Status,
+    pub write: usize,
+    pub get_position: unsafe extern "efiapi" fn(this: *mut Self, position: *mut u64) -> Status,
+    pub set_position: unsafe extern "efiapi" fn(this: *mut Self, position: u64) -> Status,
+}
+
+pub const FILE_MODE_READ: u64 = 1;
+
+/// GOP像素格式，RGB与BGR之外的格式不提供可直接使用的帧缓冲区
+pub const PIXEL_RED_GREEN_BLUE_RESERVED: u32 = 0;
+pub const PIXEL_BLUE_GREEN_RED_RESERVED: u32 = 1;
+
+#[repr(C)]
+pub struct GraphicsOutputProtocol {
+    pub query_mode: usize,
+    pub set_mode: usize,
+    pub blt: usize,
+    pub mode: *const GraphicsOutputMode,
+}
+
+#[repr(C)]
+pub struct GraphicsOutputMode {
+    pub max_mode: u32,
+    pub mode: u32,
+    pub info: *const GraphicsOutputModeInformation,
+    pub size_of_info: usize,
+    pub frame_buffer_base: u64,
+    pub frame_buffer_size: usize,
+}
+
+#[repr(C)]
+pub struct GraphicsOutputModeInformation {
+    pub version: u32,
+    pub horizontal_resolution: u32,
+    pub vertical_resolution: u32,
+    pub pixel_format: u32,
+    pub pixel_information: [u32; 4],
+    pub pixels_per_scan_line: u32,
+}
@@ -0,0 +1,235 @@
+use core::{ffi::c_void, ptr, slice};
+
+use boot_info::{KERNEL_IMAGE_BASE, KERNEL_PHYSICAL_BASE};
+
+use crate::efi::{
+    AllocateType, BootServices, FILE_MODE_READ, FileProtocol, Handle, LOADED_IMAGE_PROTOCOL_GUID,
+    LoadedImageProtocol, MemoryType, SIMPLE_FILE_SYSTEM_PROTOCOL_GUID, STATUS_SUCCESS,
+    SimpleFileSystemProtocol,
+};
+
+/// 内核ELF文件在ESP中的路径
+const KERNEL_PATH: &str = "\\cos\\kernel.elf";
+/// initramfs在ESP中的路径，文件不存在时不加载
+const INITRAMFS_PATH: &str = "\\cos\\initramfs.img";
+/// 内核命令行在ESP中的路径，文件不存在时命令行为空
+const CMDLINE_PATH: &str = "\\cos\\cmdline.txt";
+/// 内核中记录initramfs位置的结构的magic，需与kernel/src/io/initramfs.rs保持一致
+const INITRAMFS_LOCATION_MAGIC: &[u8; 16] = b"COS_INITRAMFS_AT";
+
+const SIZE_4K: u64 = 0x1000;
+const SIZE_2M: u64 = 0x20_0000;
+
+/// 已加载到物理内存的内核
+pub struct KernelImage {
+    /// 内核入口的虚拟地址
+    pub entry: u64,
+    /// 内核镜像（含initramfs与内核ELF）占用的4K页数量
+    pub page_count: u64,
+    /// 内核ELF在内核镜像中的偏移
+    pub elf_offset: u64,
+    /// 内核ELF的大小
+    pub elf_len: u64,
+    /// 内核命令行，位于启动服务分配的内存中
+    pub cmdline: &'static [u8],
+}
+
+/// 从loader所在的设备中读取内核ELF及initramfs，并加载到物理内存中
+///
+/// 内存布局与BIOS引导程序加载的扁平内核镜像一致：内核从2M开始存放，
+/// initramfs与内核ELF依次按页对齐附加在内核之后，随后按2M对齐预留2M的内核栈
+pub fn load_kernel(image: Handle, boot_services: &BootServices) -> KernelImage {
+    let root = open_root(image, boot_services);
+    let elf = read_file(boot_services, root, KERNEL_PATH).expect("kernel is not found in esp");
+    let initramfs = read_file(boot_services, root, INITRAMFS_PATH);
+    let cmdline = read_file(boot_services, root, CMDLINE_PATH).unwrap_or_default();
+    unsafe {
+        ((*root).close)(root);
+    }
+
+    // 解析ELF头，仅支持x86_64小端可执行文件
+    assert!(
+        elf.len() >= 64 && elf[0..4] == [0x7f, b'E', b'L', b'F'],
+        "kernel is not an elf file"
+    );
+    assert!(
+        elf[4] == 2 && elf[5] == 1 && read_u16(elf, 18) == 0x3e,
+        "kernel is not an x86_64 little-endian elf file"
+    );
+    let entry = read_u64(elf, 24);
+    let program_offset = read_u64(elf, 32) as usize;
+    let program_size = read_u16(elf, 54) as usize;
+    let program_count = read_u16(elf, 56) as usize;
+    assert_eq!(program_size, 56, "unsupported elf program header");
+
+    // 计算内核镜像大小
+    let programs = || {
+        (0..program_count)
+            .map(move |index| &elf[program_offset + index * program_size..][..program_size])
+            // PT_LOAD
+            .filter(|program| read_u32(program, 0) == 1)
+    };
+    let mut image_size = 0;
+    for program in programs() {
+        let vaddr = read_u64(program, 16);
+        let memsz = read_u64(program, 40);
+        assert!(
+            vaddr >= KERNEL_IMAGE_BASE,
+            "kernel segment is out of kernel image"
+        );
+        image_size = image_size.max(vaddr - KERNEL_IMAGE_BASE + memsz);
+    }
+    let image_size = image_size.next_multiple_of(SIZE_4K);
+    let elf_offset = (image_size + initramfs.map_or(0, |initramfs| initramfs.len() as u64))
+        .next_multiple_of(SIZE_4K);
+    let total_size = elf_offset + elf.len() as u64;
+    let page_count = total_size.div_ceil(SIZE_4K);
+
+    // 申请内核与内核栈的物理内存
+    let physical_size = (page_count * SIZE_4K).next_multiple_of(SIZE_2M) + SIZE_2M;
+    let mut address = KERNEL_PHYSICAL_BASE;
+    let status = unsafe {
+        (boot_services.allocate_pages)(
+            AllocateType::Address,
+            MemoryType::LOADER_DATA,
+            (physical_size / SIZE_4K) as usize,
+            &mut address,
+        )
+    };
+    assert_eq!(
+        status, STATUS_SUCCESS,
+        "failed to allocate memory for kernel at 2M"
+    );
+
+    // Safety: 内存已由固件分配给我们，且在启动服务中恒等映射
+    let memory = unsafe {
+        slice::from_raw_parts_mut(
+            KERNEL_PHYSICAL_BASE as *mut u8,
+            (page_count * SIZE_4K) as usize,
+        )
+    };
+    memory.fill(0);
+    for program in programs() {
+        let offset = read_u64(program, 8) as usize;
+        let start = (read_u64(program, 16) - KERNEL_IMAGE_BASE) as usize;
+        let filesz = read_u64(program, 32) as usize;
+        memory[start..start + filesz].copy_from_slice(&elf[offset..offset + filesz]);
+    }
+
+    // 将initramfs附加到内核之后，并将其位置写入内核
+    if let Some(initramfs) = initramfs {
+        let offset = image_size as usize;
+        memory[offset..offset + initramfs.len()].copy_from_slice(initramfs);
+
+        let mut positions = memory[..offset]
+            .windows(INITRAMFS_LOCATION_MAGIC.len())
+            .enumerate()
+            .filter(|(_, window)| window == INITRAMFS_LOCATION_MAGIC)
+            .map(|(position, _)| position);
+        let location = positions
+            .next()
+            .expect("initramfs location is not found in kernel")
+            + INITRAMFS_LOCATION_MAGIC.len();
+        assert!(
+            positions.next().is_none(),
+            "initramfs location is found more than once in kernel"
+        );
+        memory[location..location + 8].copy_from_slice(&(offset as u64).to_le_bytes());
+        memory[location + 8..location + 16]
+            .copy_from_slice(&(initramfs.len() as u64).to_le_bytes());
+    }
+
+    // 将内核ELF附加到最后，供内核解析符号表
+    memory[elf_offset as usize..][..elf.len()].copy_from_slice(elf);
+
+    KernelImage {
+        entry,
+        page_count,
+        elf_offset,
+        elf_len: elf.len() as u64,
+        cmdline,
+    }
+}
+
+/// 打开loader所在设备的根目录
+fn open_root(image: Handle, boot_services: &BootServices) -> *mut FileProtocol {
+    unsafe {
+        let mut loaded_image: *mut c_void = ptr::null_mut();
+        let status =
+            (boot_services.handle_protocol)(image, &LOADED_IMAGE_PROTOCOL_GUID, &mut loaded_image);
+        assert_eq!(
+            status, STATUS_SUCCESS,
+            "failed to get loaded image protocol"
+        );
+        let device = (*(loaded_image as *mut LoadedImageProtocol)).device_handle;
+
+        let mut file_system: *mut c_void = ptr::null_mut();
+        let status = (boot_services.handle_protocol)(
+            device,
+            &SIMPLE_FILE_SYSTEM_PROTOCOL_GUID,
+            &mut file_system,
+        );
+        assert_eq!(status, STATUS_SUCCESS, "failed to get file system of esp");
+        let file_system = file_system as *mut SimpleFileSystemProtocol;
+
+        let mut root = ptr::null_mut();
+        let status = ((*file_system).open_volume)(file_system, &mut root);
+        assert_eq!(status, STATUS_SUCCESS, "failed to open esp");
+        root
+    }
+}
+
+/// 读取文件的全部内容，文件不存在时返回None
+///
+/// 内容存放在启动服务分配的内存中，进入内核后才会被回收
+fn read_file(
+    boot_services: &BootServices,
+    root: *mut FileProtocol,
+    path: &str,
+) -> Option<&'static [u8]> {
+    let mut name = [0u16; 64];
+    for (index, c) in path.encode_utf16().enumerate() {
+        name[index] = c;
+    }
+
+    unsafe {
+        let mut file = ptr::null_mut();
+        if ((*root).open)(root, &mut file, name.as_ptr(), FILE_MODE_READ, 0) != STATUS_SUCCESS {
+            return None;
+        }
+
+        // 将位置设置为全1时会移动到文件末尾，以此获取文件大小
+        let mut size = 0;
+        ((*file).set_position)(file, u64::MAX);
+        ((*file).get_position)(file, &mut size);
+        ((*file).set_position)(file, 0);
+
+        let mut buffer = ptr::null_mut();
+        let status =
+            (boot_services.allocate_pool)(MemoryType::LOADER_DATA, size as usize, &mut buffer);
+        assert_eq!(status, STATUS_SUCCESS, "failed to allocate file buffer");
+
+        let mut read = 0;
+        while read < size as usize {
+            let mut len = size as usize - read;
+            let status = ((*file).read)(file, &mut len, buffer.add(read));
+            assert!(status == STATUS_SUCCESS && len > 0, "failed to read {path}");
+            read += len;
+        }
+        ((*file).close)(file);
+
+        Some(slice::from_raw_parts(buffer, size as usize))
+    }
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(buf[offset..offset + 2].try_into().unwrap())
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap())
+}
@@ -0,0 +1,168 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use core::{arch::asm, ffi::c_void, fmt::Write, ptr, slice};
+
+use boot_info::{BootInfo, Framebuffer, KERNEL_PHYSICAL_BASE};
+
+use crate::{
+    console::Console,
+    efi::{
+        ACPI_20_TABLE_GUID, ACPI_TABLE_GUID, BootServices, GRAPHICS_OUTPUT_PROTOCOL_GUID,
+        GraphicsOutputProtocol, Handle, PIXEL_BLUE_GREEN_RED_RESERVED,
+        PIXEL_RED_GREEN_BLUE_RESERVED, STATUS_SUCCESS, Status, SystemTable,
+    },
+    loader::load_kernel,
+    memory::{convert_memory_map, exit_boot_services},
+    paging::{BootArea, enter_kernel},
+};
+
+mod console;
+mod efi;
+mod loader;
+mod memory;
+mod paging;
+
+/// 传递给内核的启动磁盘号
+///
+/// UEFI不提供BIOS磁盘号，COS磁盘需作为IDE主通道主盘接入，与BIOS启动时的0x80一致
+const STARTUP_DISK: u32 = 0x80;
+
+#[unsafe(export_name = "efi_main")]
+extern "efiapi" fn efi_main(image: Handle, system_table: *mut SystemTable) -> Status {
+    // Safety: 系统表由固件传入，在退出启动服务前一直有效
+    let boot_services = unsafe {
+        console::init(system_table);
+        &*(*system_table).boot_services
+    };
+    let mut console = Console;
+    writeln!(console, "COS UEFI loader").unwrap();
+
+    // 关闭看门狗，避免固件在加载期间重启
+    unsafe {
+        (boot_services.set_watchdog_timer)(0, 0, 0, ptr::null());
+    }
+
+    let framebuffer = query_framebuffer(boot_services);
+    match framebuffer {
+        Some(framebuffer) => writeln!(
+            console,
+            "framebuffer: 0x{:x} ({} bytes), {}x{}, stride {}, format {}",
+            framebuffer.base,
+            framebuffer.size,
+            framebuffer.width,
+            framebuffer.height,
+            framebuffer.stride,
+            framebuffer.pixel_format
+        )
+        .unwrap(),
+        None => writeln!(console, "framebuffer is not available").unwrap(),
+    }
+
+    // Safety: 系统表由固件传入，配置表在退出启动服务后仍然有效
+    let rsdp = unsafe { find_rsdp(&*system_table) };
+    writeln!(console, "rsdp: 0x{:x}", rsdp.unwrap_or(0)).unwrap();
+
+    // 加载内核
+    let kernel = load_kernel(image, boot_services);
+    writeln!(
+        console,
+        "kernel loaded: entry 0x{:x}, {} pages",
+        kernel.entry, kernel.page_count
+    )
+    .unwrap();
+
+    let area = BootArea::allocate(boot_services);
+
+    // 退出启动服务，此后不能再使用固件提供的任何服务
+    let memory_map = exit_boot_services(image, boot_services);
+    let memory_region = convert_memory_map(&memory_map, area.memory_region_buffer());
+
+    let mut boot_info = BootInfo::new(memory_region, STARTUP_DISK);
+    boot_info.rsdp = rsdp.unwrap_or(0);
+    boot_info.kernel_elf_ptr = KERNEL_PHYSICAL_BASE + kernel.elf_offset;
+    boot_info.kernel_elf_len = kernel.elf_len;
+    if let Some(framebuffer) = framebuffer {
+        boot_info.framebuffer = framebuffer;
+    }
+    let boot_info = area.write_boot_info(boot_info, kernel.cmdline);
+
+    // Safety: 已经退出启动服务并加载完内核，启动信息位于引导区中
+    unsafe { enter_kernel(area, &kernel, boot_info) }
+}
+
+/// 在配置表中查找ACPI RSDP，优先使用ACPI 2.0及以上版本
+fn find_rsdp(system_table: &SystemTable) -> Option<u64> {
+    // Safety: 配置表由固件提供，条目数量与number_of_table_entries一致
+    let tables = unsafe {
+        slice::from_raw_parts(
+            system_table.configuration_table,
+            system_table.number_of_table_entries,
+        )
+    };
+    [ACPI_20_TABLE_GUID, ACPI_TABLE_GUID]
+        .iter()
+        .find_map(|guid| {
+            tables
+                .iter()
+                .find(|table| table.vendor_guid == *guid)
+                .map(|table| table.vendor_table as u64)
+        })
+}
+
+/// 通过GOP获取当前模式的帧缓冲区，像素格式不是RGB或BGR时视为不可用
+fn query_framebuffer(boot_services: &BootServices) -> Option<Framebuffer> {
+    unsafe {
+        let mut gop: *mut c_void = ptr::null_mut();
+        let status = (boot_services.locate_protocol)(
+            &GRAPHICS_OUTPUT_PROTOCOL_GUID,
+            ptr::null_mut(),
+            &mut gop,
+        );
+        if status != STATUS_SUCCESS {
+            return None;
+        }
+        let mode = &*(*(gop as *mut GraphicsOutputProtocol)).mode;
+        let info = &*mode.info;
+        let pixel_format = match info.pixel_format {
+            PIXEL_RED_GREEN_BLUE_RESERVED => Framebuffer::FORMAT_RGB,
+            PIXEL_BLUE_GREEN_RED_RESERVED => Framebuffer::FORMAT_BGR,
+            _ => return None,
+        };
+        Some(Framebuffer {
+            base: mode.frame_buffer_base,
+            size: mode.frame_buffer_size as u64,
+            width: info.horizontal_resolution,
+            height: info.vertical_resolution,
+            stride: info.pixels_per_scan_line,
+            pixel_format,
+        })
+    }
+}
+
+fn loop_halt() -> ! {
+    loop {
+        unsafe {
+            asm!("hlt");
+        }
+    }
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    // 退出启动服务后控制台不可用，输出会被忽略
+    let mut console = Console;
+    writeln!(console, "loader panic: {}", info.message()).unwrap();
+
+    if let Some(loc) = info.location() {
+        writeln!(console, "file: {}", loc.file()).unwrap();
+        writeln!(console, "line {} col {}", loc.line(), loc.column()).unwrap();
+    }
+
+    loop_halt()
+}
+
+#[cfg(test)]
+fn test_runner(_test_cases: &[&dyn Fn()]) {}
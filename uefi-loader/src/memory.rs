@@ -0,0 +1,158 @@
+use core::{ptr, slice};
+
+use boot_info::MemoryRegion;
+
+use crate::efi::{
+    BootServices, Handle, MemoryDescriptor, MemoryType, STATUS_BUFFER_TOO_SMALL,
+    STATUS_INVALID_PARAMETER, STATUS_SUCCESS,
+};
+
+/// 退出启动服务时获取的UEFI内存映射
+pub struct MemoryMap {
+    buffer: *const u8,
+    size: usize,
+    descriptor_size: usize,
+}
+
+/// 获取内存映射并退出启动服务
+///
+/// 获取内存映射后不能再调用其他启动服务，否则内存映射会失效，退出失败。
+/// 此函数返回后，启动服务已不可用
+pub fn exit_boot_services(image: Handle, boot_services: &BootServices) -> MemoryMap {
+    let mut size = 0;
+    let mut map_key = 0;
+    let mut descriptor_size = 0;
+    let mut descriptor_version = 0;
+    let status = unsafe {
+        (boot_services.get_memory_map)(
+            &mut size,
+            ptr::null_mut(),
+            &mut map_key,
+            &mut descriptor_size,
+            &mut descriptor_version,
+        )
+    };
+    assert_eq!(
+        status, STATUS_BUFFER_TOO_SMALL,
+        "failed to get memory map size"
+    );
+
+    // 申请缓冲区本身会使内存映射增加条目，额外预留空间
+    let capacity = size + 8 * descriptor_size;
+    let mut buffer = ptr::null_mut();
+    let status =
+        unsafe { (boot_services.allocate_pool)(MemoryType::LOADER_DATA, capacity, &mut buffer) };
+    assert_eq!(
+        status, STATUS_SUCCESS,
+        "failed to allocate memory map buffer"
+    );
+
+    // 此后不能再输出内容
+    crate::console::disable();
+
+    // 首次退出失败时，内存映射已经变化，重新获取后再次尝试
+    for _ in 0..2 {
+        let mut size = capacity;
+        let status = unsafe {
+            (boot_services.get_memory_map)(
+                &mut size,
+                buffer as *mut MemoryDescriptor,
+                &mut map_key,
+                &mut descriptor_size,
+                &mut descriptor_version,
+            )
+        };
+        assert_eq!(status, STATUS_SUCCESS, "failed to get memory map");
+
+        let status = unsafe { (boot_services.exit_boot_services)(image, map_key) };
+        if status == STATUS_SUCCESS {
+            return MemoryMap {
+                buffer,
+                size,
+                descriptor_size,
+            };
+        }
+        assert_eq!(
+            status, STATUS_INVALID_PARAMETER,
+            "failed to exit boot services"
+        );
+    }
+
+    panic!("failed to exit boot services: memory map keeps changing");
+}
+
+impl MemoryMap {
+    fn iter(&self) -> impl Iterator<Item = MemoryDescriptor> + '_ {
+        (0..self.size / self.descriptor_size).map(|index| {
+            // Safety: 缓冲区由get_memory_map写入，描述符大小可能大于结构体，需按descriptor_size步进
+            unsafe {
+                ptr::read_unaligned(
+                    self.buffer.add(index * self.descriptor_size) as *const MemoryDescriptor
+                )
+            }
+        })
+    }
+}
+
+/// 将UEFI内存映射转换为内核使用的内存信息，并按地址排序、合并相邻的同类型内存
+///
+/// 引导程序与启动服务使用的内存在进入内核后均可回收，视为可用。
+/// 内核与页表所在的内存位于内核页帧分配器的起始地址之前，不会被分配。
+pub fn convert_memory_map<'a>(
+    memory_map: &MemoryMap,
+    buffer: &'a mut [MemoryRegion],
+) -> &'a mut [MemoryRegion] {
+    let mut len = 0;
+    for descriptor in memory_map.iter() {
+        assert!(len < buffer.len(), "too many memory regions");
+        let region_type = match descriptor.memory_type {
+            MemoryType::LOADER_CODE
+            | MemoryType::LOADER_DATA
+            | MemoryType::BOOT_SERVICES_CODE
+            | MemoryType::BOOT_SERVICES_DATA
+            | MemoryType::CONVENTIONAL => MemoryRegion::TYPE_USABLE,
+            _ => MemoryRegion::TYPE_RESERVED,
+        };
+        buffer[len] = MemoryRegion {
+            base_addr: descriptor.physical_start,
+            length: descriptor.number_of_pages * 0x1000,
+            region_type,
+        };
+        len += 1;
+    }
+
+    // UEFI内存映射中的条目不会重叠，但不保证有序
+    let regions = &mut buffer[..len];
+    regions.sort_unstable_by_key(|region| region.base_addr);
+
+    // 合并相邻的同类型内存
+    let mut len = 0;
+    for i in 0..regions.len() {
+        if len > 0 {
+            let prev = regions[len - 1];
+            let current = regions[i];
+            if prev.region_type == current.region_type
+                && prev.base_addr + prev.length == current.base_addr
+            {
+                regions[len - 1].length += current.length;
+                continue;
+            }
+        }
+        regions[len] = regions[i];
+        len += 1;
+    }
+
+    &mut regions[..len]
+}
+
+/// 将缓冲区视为内存信息数组
+///
+/// Safety: 内存必须可读写，且在内核运行期间不会被回收
+pub unsafe fn region_buffer(address: u64, size: usize) -> &'static mut [MemoryRegion] {
+    unsafe {
+        slice::from_raw_parts_mut(
+            address as *mut MemoryRegion,
+            size / size_of::<MemoryRegion>(),
+        )
+    }
+}
@@ -0,0 +1,212 @@
+use core::{arch::asm, ptr, slice};
+
+use boot_info::{BootInfo, KERNEL_PHYSICAL_BASE, MemoryRegion};
+
+use crate::{
+    efi::{AllocateType, BootServices, MemoryType, STATUS_SUCCESS},
+    loader::KernelImage,
+    memory::region_buffer,
+};
+
+const P_PRESENT: u64 = 1 << 0;
+const P_RW: u64 = 1 << 1;
+const P_PS: u64 = 1 << 7;
+const SIZE_2M: u64 = 0x20_0000;
+const SIZE_4K: u64 = 0x1000;
+
+// 引导区中各页的用途，页表的顺序与BIOS引导程序一致
+const PML4: u64 = 0;
+const LOADER_PDPT: u64 = 1;
+const LOADER_PD: u64 = 2;
+const LOADER_PT: u64 = 3;
+const KERNEL_PDPT: u64 = 4;
+const KERNEL_PD: u64 = 5;
+const KERNEL_PT: u64 = 6;
+const GDT: u64 = 7;
+const MEMORY_REGION: u64 = 8;
+const MEMORY_REGION_PAGE_COUNT: u64 = 4;
+const TRAMPOLINE: u64 = MEMORY_REGION + MEMORY_REGION_PAGE_COUNT;
+/// 启动信息页，启动信息之后存放内核命令行
+const BOOT_INFO: u64 = TRAMPOLINE + 1;
+const BOOT_AREA_PAGE_COUNT: u64 = BOOT_INFO + 1;
+
+/// 内核的页表管理复用LOADER_PT中0x3000~0x4000的页作为临时映射，引导区不能占用
+const TEMP_PAGE: u64 = 0x3000;
+
+/// 与BIOS引导程序相同的64位GDT，TSS由内核设置
+const GDT_ENTRIES: [u64; 9] = [
+    0,                     // 0x00
+    0,                     // 0x08
+    0,                     // 0x10
+    0x00AF_9A00_0000_0000, // 0x18 Kernel Code
+    0x00CF_9200_0000_0000, // 0x20 Kernel Data
+    0,                     // 0x28 Tss Low
+    0,                     // 0x30 Tss High
+    0x00CF_F200_0000_0000, // 0x3B User Data
+    0x00AF_FA00_0000_0000, // 0x43 User Code
+];
+
+#[repr(C, packed)]
+struct DescriptorTablePointer {
+    limit: u16,
+    base: u64,
+}
+
+/// 引导区，存放进入内核后仍需使用的页表、GDT、内存信息与启动信息
+///
+/// 内核通过LOADER_PT恒等映射访问这些结构，因此必须位于2M以下。
+/// 引导区位于内核页帧分配器的起始地址之前，不会被内核回收
+pub struct BootArea {
+    base: u64,
+}
+
+impl BootArea {
+    /// 在2M以下申请引导区，并清空
+    pub fn allocate(boot_services: &BootServices) -> Self {
+        let mut base = SIZE_2M - 1;
+        let status = unsafe {
+            (boot_services.allocate_pages)(
+                AllocateType::MaxAddress,
+                MemoryType::LOADER_DATA,
+                BOOT_AREA_PAGE_COUNT as usize,
+                &mut base,
+            )
+        };
+        assert_eq!(
+            status, STATUS_SUCCESS,
+            "failed to allocate boot area below 2M"
+        );
+        assert!(
+            base > TEMP_PAGE || base + BOOT_AREA_PAGE_COUNT * SIZE_4K <= TEMP_PAGE,
+            "boot area overlaps temporary mapping page"
+        );
+
+        // Safety: 内存已由固件分配给我们，且在启动服务中恒等映射
+        unsafe {
+            ptr::write_bytes(
+                base as *mut u8,
+                0,
+                (BOOT_AREA_PAGE_COUNT * SIZE_4K) as usize,
+            );
+        }
+        Self { base }
+    }
+
+    fn page(&self, index: u64) -> u64 {
+        self.base + index * SIZE_4K
+    }
+
+    fn table(&self, index: u64) -> &'static mut [u64; 512] {
+        // Safety: 引导区已清空，且仅在此处以页表的形式访问
+        unsafe { &mut *(self.page(index) as *mut [u64; 512]) }
+    }
+
+    /// 用于存放内存信息的缓冲区
+    pub fn memory_region_buffer(&self) -> &'static mut [MemoryRegion] {
+        // Safety: 引导区在内核运行期间不会被回收
+        unsafe {
+            region_buffer(
+                self.page(MEMORY_REGION),
+                (MEMORY_REGION_PAGE_COUNT * SIZE_4K) as usize,
+            )
+        }
+    }
+
+    /// 将启动信息与内核命令行写入启动信息页，命令行超出页的剩余空间时被截断
+    pub fn write_boot_info(&self, mut boot_info: BootInfo, cmdline: &[u8]) -> &'static BootInfo {
+        let page = self.page(BOOT_INFO);
+        let cmdline_ptr = page + size_of::<BootInfo>() as u64;
+        let cmdline = &cmdline[..cmdline.len().min((page + SIZE_4K - cmdline_ptr) as usize)];
+        boot_info.cmdline_ptr = cmdline_ptr;
+        boot_info.cmdline_len = cmdline.len() as u64;
+        // Safety: 启动信息页已清空，空间足够存放启动信息与截断后的命令行
+        unsafe {
+            slice::from_raw_parts_mut(cmdline_ptr as *mut u8, cmdline.len())
+                .copy_from_slice(cmdline);
+            let boot_info_ptr = page as *mut BootInfo;
+            boot_info_ptr.write(boot_info);
+            &*boot_info_ptr
+        }
+    }
+}
+
+/// 建立与BIOS引导程序相同的页表，并跳转至内核
+///
+/// Safety: 必须已经退出启动服务，内核已加载，启动信息位于引导区中
+pub unsafe fn enter_kernel(area: BootArea, kernel: &KernelImage, boot_info: &BootInfo) -> ! {
+    // 1. 初始化页表
+    // loader 页表，恒等映射引导区与VGA显示
+    area.table(PML4)[0] = area.page(LOADER_PDPT) | P_PRESENT | P_RW;
+    area.table(LOADER_PDPT)[0] = area.page(LOADER_PD) | P_PRESENT | P_RW;
+    area.table(LOADER_PD)[0] = area.page(LOADER_PT) | P_PRESENT | P_RW;
+    let loader_pt = area.table(LOADER_PT);
+    for index in 0..BOOT_AREA_PAGE_COUNT {
+        let page = area.page(index);
+        loader_pt[(page / SIZE_4K) as usize] = page | P_PRESENT | P_RW;
+    }
+    loader_pt[0xb8] = 0xb8000 | P_PRESENT | P_RW;
+
+    // kernel 页表
+    // 0xFFFF_FFFF_FFC0_0000 ~ 0xFFFF_FFFF_FFDF_FFFF - 栈空间（2M）
+    // 0xFFFF_FFFF_C000_0000 ~ X                     - text段、bss段、rodata段
+    let kernel_binary_page_count = kernel.page_count;
+    assert!(kernel_binary_page_count <= 510 * 512, "kernel is too large");
+    let kernel_binary_start = KERNEL_PHYSICAL_BASE;
+    let kernel_stack_start =
+        (kernel_binary_start + kernel_binary_page_count * SIZE_4K).next_multiple_of(SIZE_2M);
+    area.table(PML4)[511] = area.page(KERNEL_PDPT) | P_PRESENT | P_RW;
+    area.table(KERNEL_PDPT)[511] = area.page(KERNEL_PD) | P_PRESENT | P_RW;
+    let kernel_pd = area.table(KERNEL_PD);
+    let kernel_pt = area.table(KERNEL_PT);
+    kernel_pd[510] = kernel_stack_start | P_PS | P_PRESENT | P_RW;
+    let mut i = 0;
+    while i < kernel_binary_page_count {
+        let remain = kernel_binary_page_count - i;
+        // 足够2M，用大页
+        if remain >= 512 {
+            kernel_pd[(i / 512) as usize] =
+                (kernel_binary_start + SIZE_2M * (i / 512)) | P_PS | P_PRESENT | P_RW;
+            i += 512;
+            continue;
+        }
+        // 不足2M，首次进入时将PT挂到PD上
+        if i % 512 == 0 {
+            kernel_pd[(i / 512) as usize] = area.page(KERNEL_PT) | P_PRESENT | P_RW;
+        }
+        kernel_pt[(i % 512) as usize] = (kernel_binary_start + SIZE_4K * i) | P_PRESENT | P_RW;
+        i += 1;
+    }
+
+    // 2. 准备GDT
+    let gdt = area.table(GDT);
+    gdt[..GDT_ENTRIES.len()].copy_from_slice(&GDT_ENTRIES);
+    let gdtr = &raw mut gdt[GDT_ENTRIES.len()] as *mut DescriptorTablePointer;
+    // Safety: GDT页剩余空间足够存放GDTR
+    unsafe {
+        gdtr.write_unaligned(DescriptorTablePointer {
+            limit: (size_of_val(&GDT_ENTRIES) - 1) as u16,
+            base: area.page(GDT),
+        });
+    }
+
+    // 3. 复制跳板代码并跳转。跳板负责加载GDT、切换页表与栈，最后进入kmain
+    const TRAMPOLINE_ASM: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/trampoline.bin"));
+    let trampoline = area.page(TRAMPOLINE);
+    // Safety: 跳板代码不会超过一页，跳板页在新旧页表中均为恒等映射
+    unsafe {
+        assert!(TRAMPOLINE_ASM.len() < SIZE_4K as usize);
+        slice::from_raw_parts_mut(trampoline as *mut u8, TRAMPOLINE_ASM.len())
+            .copy_from_slice(TRAMPOLINE_ASM);
+
+        asm!(
+            "cli",
+            "jmp {addr}",
+            in("rdi") boot_info,
+            in("rcx") area.page(PML4),
+            in("r8") kernel.entry,
+            in("r10") gdtr,
+            addr = in(reg) trampoline,
+            options(noreturn)
+        );
+    }
+}
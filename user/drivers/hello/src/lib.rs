@@ -0,0 +1,32 @@
+//! 内核模块示例，加载与卸载时输出日志
+//!
+//! 模块只能引用内核导出的符号（见 kernel/src/module/export.rs），
+//! 因此不能使用会调用core中非内联函数的功能，如格式化输出与会panic的操作
+#![no_std]
+
+unsafe extern "C" {
+    fn cos_log(level: u32, message: *const u8, len: usize);
+    fn cos_uptime_ns() -> u64;
+}
+
+/// cos_log的info级别
+const LOG_INFO: u32 = 2;
+
+fn log(message: &str) {
+    unsafe { cos_log(LOG_INFO, message.as_ptr(), message.len()) }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cos_module_init() -> i32 {
+    // 内核启动后才能加载模块，单调时钟不可能为0
+    if unsafe { cos_uptime_ns() } == 0 {
+        return -1;
+    }
+    log("hello from kernel module");
+    0
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn cos_module_exit() {
+    log("goodbye from kernel module");
+}
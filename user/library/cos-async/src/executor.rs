@@ -0,0 +1,360 @@
+use core::{
+    cell::RefCell,
+    pin::{Pin, pin},
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+    time::Duration,
+};
+
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, VecDeque},
+    rc::Rc,
+    vec::Vec,
+};
+use cos_sys::{
+    ipc::{PollFd, poll},
+    multitask::sleep_thread,
+    time::Instant,
+};
+
+/// 任务ID，[block_on] 的future使用 [MAIN_TASK]，[spawn] 创建的任务从1开始编号
+type TaskId = u64;
+
+const MAIN_TASK: TaskId = 0;
+
+static RUNTIME: RuntimeCell = RuntimeCell {
+    owner: AtomicUsize::new(0),
+    runtime: RefCell::new(Runtime::new()),
+};
+
+/// 标识当前线程：线程局部变量在各线程中的地址各不相同
+#[thread_local]
+static THREAD_MARKER: u8 = 0;
+
+struct RuntimeCell {
+    // 首次使用运行时的线程，以其 [THREAD_MARKER] 的地址标识，0表示尚未使用
+    owner: AtomicUsize,
+    runtime: RefCell<Runtime>,
+}
+
+// Safety: runtime只能通过 [RuntimeCell::get] 访问，其他线程访问时panic，因此只有所有者线程访问
+unsafe impl Sync for RuntimeCell {}
+
+impl RuntimeCell {
+    /// 获取运行时，当前线程不是首次使用运行时的线程时panic
+    fn get(&self) -> &RefCell<Runtime> {
+        let thread = &raw const THREAD_MARKER as usize;
+        if let Err(owner) =
+            self.owner
+                .compare_exchange(0, thread, Ordering::Relaxed, Ordering::Relaxed)
+            && owner != thread
+        {
+            panic!("cos-async: runtime can only be used by the thread that first used it");
+        }
+        &self.runtime
+    }
+}
+
+struct Runtime {
+    // 尚未完成的任务，正在执行的任务暂时移出
+    tasks: BTreeMap<TaskId, Pin<Box<dyn Future<Output = ()>>>>,
+    next_task: TaskId,
+    // 被唤醒、等待执行的任务
+    ready: VecDeque<TaskId>,
+    // 计时器，按到期时刻排序，序号区分同一时刻的计时器。到期时移除并唤醒
+    timers: BTreeMap<(Instant, u64), Waker>,
+    // 等待就绪的句柄，以序号区分
+    io: BTreeMap<u64, IoWait>,
+    // 计时器与IO等待的下一个序号
+    next_key: u64,
+    // 是否正在执行block_on
+    running: bool,
+}
+
+struct IoWait {
+    handle: u64,
+    events: u64,
+    // 已就绪的事件，为0时仍在等待
+    revents: u64,
+    waker: Waker,
+}
+
+impl Runtime {
+    const fn new() -> Self {
+        Self {
+            tasks: BTreeMap::new(),
+            next_task: MAIN_TASK + 1,
+            ready: VecDeque::new(),
+            timers: BTreeMap::new(),
+            io: BTreeMap::new(),
+            next_key: 0,
+            running: false,
+        }
+    }
+
+    fn next_key(&mut self) -> u64 {
+        let key = self.next_key;
+        self.next_key += 1;
+        key
+    }
+}
+
+fn with_runtime<R>(f: impl FnOnce(&mut Runtime) -> R) -> R {
+    f(&mut RUNTIME.get().borrow_mut())
+}
+
+/// 在当前线程运行执行器，直到future完成并返回其结果
+///
+/// 执行期间同时运行 [spawn] 创建的任务。返回时尚未完成的任务被保留，在下一次调用时继续运行。
+/// 不能在任务中嵌套调用，否则panic。所有任务都在等待，但没有计时器或IO可以唤醒它们时panic
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    with_runtime(|runtime| {
+        assert!(!runtime.running, "block_on cannot be nested");
+        runtime.running = true;
+        runtime.ready.push_back(MAIN_TASK);
+    });
+
+    let output = loop {
+        let Some(task_id) = with_runtime(|runtime| runtime.ready.pop_front()) else {
+            turn();
+            continue;
+        };
+        let waker = task_waker(task_id);
+        let mut cx = Context::from_waker(&waker);
+        if task_id == MAIN_TASK {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                break output;
+            }
+            continue;
+        }
+        // 任务已完成时，过期的唤醒被忽略
+        let Some(mut task) = with_runtime(|runtime| runtime.tasks.remove(&task_id)) else {
+            continue;
+        };
+        // 执行时不持有运行时，任务可以创建任务、注册计时器与IO等待
+        if task.as_mut().poll(&mut cx).is_pending() {
+            with_runtime(|runtime| runtime.tasks.insert(task_id, task));
+        }
+    };
+
+    with_runtime(|runtime| {
+        runtime.running = false;
+        runtime.ready.retain(|task_id| *task_id != MAIN_TASK);
+    });
+    output
+}
+
+/// 创建任务，任务在 [block_on] 中与其他任务并发执行
+///
+/// 返回的 [JoinHandle] 可以await任务的结果，释放 [JoinHandle] 不影响任务继续执行
+pub fn spawn<F: Future + 'static>(future: F) -> JoinHandle<F::Output> {
+    let state = Rc::new(RefCell::new(JoinState {
+        output: None,
+        waker: None,
+    }));
+    let task_state = state.clone();
+    let task = async move {
+        let output = future.await;
+        let waker = {
+            let mut state = task_state.borrow_mut();
+            state.output = Some(output);
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    };
+    with_runtime(|runtime| {
+        let task_id = runtime.next_task;
+        runtime.next_task += 1;
+        runtime.tasks.insert(task_id, Box::pin(task));
+        runtime.ready.push_back(task_id);
+    });
+    JoinHandle { state }
+}
+
+/// 任务的句柄，await得到任务的结果
+pub struct JoinHandle<T> {
+    state: Rc<RefCell<JoinState<T>>>,
+}
+
+struct JoinState<T> {
+    output: Option<T>,
+    // 等待结果的任务
+    waker: Option<Waker>,
+}
+
+impl<T> JoinHandle<T> {
+    /// 任务是否已完成
+    pub fn is_finished(&self) -> bool {
+        self.state.borrow().output.is_some()
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.borrow_mut();
+        match state.output.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// 让出执行，使其他已就绪的任务先执行
+pub async fn yield_now() {
+    let mut yielded = false;
+    core::future::poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+/// 注册计时器，到期时唤醒waker，返回计时器的序号
+pub(crate) fn register_timer(deadline: Instant, waker: &Waker) -> u64 {
+    with_runtime(|runtime| {
+        let key = runtime.next_key();
+        runtime.timers.insert((deadline, key), waker.clone());
+        key
+    })
+}
+
+/// 更新计时器的waker，计时器已到期时返回false
+pub(crate) fn update_timer(deadline: Instant, key: u64, waker: &Waker) -> bool {
+    with_runtime(|runtime| match runtime.timers.get_mut(&(deadline, key)) {
+        Some(registered) => {
+            registered.clone_from(waker);
+            true
+        }
+        None => false,
+    })
+}
+
+pub(crate) fn cancel_timer(deadline: Instant, key: u64) {
+    with_runtime(|runtime| runtime.timers.remove(&(deadline, key)));
+}
+
+/// 注册IO等待，句柄上的events任意一个就绪时唤醒waker，返回等待的序号
+pub(crate) fn register_io(handle: u64, events: u64, waker: &Waker) -> u64 {
+    with_runtime(|runtime| {
+        let key = runtime.next_key();
+        let wait = IoWait {
+            handle,
+            events,
+            revents: 0,
+            waker: waker.clone(),
+        };
+        runtime.io.insert(key, wait);
+        key
+    })
+}
+
+/// 检查IO等待，已就绪时移除等待并返回就绪的事件，否则更新waker
+pub(crate) fn poll_io(key: u64, waker: &Waker) -> Option<u64> {
+    with_runtime(|runtime| {
+        let wait = runtime
+            .io
+            .get_mut(&key)
+            .expect("codebug: io wait should be registered");
+        if wait.revents == 0 {
+            wait.waker.clone_from(waker);
+            return None;
+        }
+        runtime.io.remove(&key).map(|wait| wait.revents)
+    })
+}
+
+pub(crate) fn cancel_io(key: u64) {
+    with_runtime(|runtime| runtime.io.remove(&key));
+}
+
+/// 没有可执行的任务时，等待最早的计时器到期或任意IO就绪，并唤醒对应的任务
+fn turn() {
+    let (keys, mut fds, deadline) = with_runtime(|runtime| {
+        let (keys, fds): (Vec<u64>, Vec<PollFd>) = runtime
+            .io
+            .iter()
+            .filter(|(_, wait)| wait.revents == 0)
+            .map(|(key, wait)| (*key, PollFd::new(wait.handle, wait.events)))
+            .unzip();
+        let deadline = runtime.timers.keys().next().map(|(deadline, _)| *deadline);
+        (keys, fds, deadline)
+    });
+
+    let timeout = deadline.map(|deadline| deadline.duration_since(now()));
+    match timeout {
+        None if fds.is_empty() => {
+            panic!("cos-async: all tasks are pending, but no timer or io can wake them")
+        }
+        Some(timeout) if fds.is_empty() => sleep(timeout),
+        _ => {
+            poll(&mut fds, timeout).expect("failed to poll handles");
+        }
+    }
+
+    let now = now();
+    let wakers: Vec<Waker> = with_runtime(|runtime| {
+        let mut wakers = Vec::new();
+        while let Some(timer) = runtime.timers.first_entry()
+            && timer.key().0 <= now
+        {
+            wakers.push(timer.remove());
+        }
+        for (key, fd) in keys.iter().zip(&fds) {
+            if fd.revents == 0 {
+                continue;
+            }
+            if let Some(wait) = runtime.io.get_mut(key) {
+                wait.revents = fd.revents;
+                wakers.push(wait.waker.clone());
+            }
+        }
+        wakers
+    });
+    for waker in wakers {
+        waker.wake();
+    }
+}
+
+pub(crate) fn now() -> Instant {
+    Instant::now().expect("failed to read monotonic clock")
+}
+
+fn sleep(duration: Duration) {
+    sleep_thread(duration.as_secs(), duration.subsec_nanos() as u64).expect("failed to sleep");
+}
+
+/// 任务的waker，唤醒时将任务加入就绪队列
+fn task_waker(task_id: TaskId) -> Waker {
+    // Safety: vtable中的函数不访问data指向的内存，data仅用于保存任务ID
+    unsafe { Waker::from_raw(RawWaker::new(task_id as *const (), &VTABLE)) }
+}
+
+static VTABLE: RawWakerVTable = RawWakerVTable::new(clone_waker, wake_task, wake_task, drop_waker);
+
+fn clone_waker(data: *const ()) -> RawWaker {
+    RawWaker::new(data, &VTABLE)
+}
+
+fn wake_task(data: *const ()) {
+    let task_id = data as TaskId;
+    with_runtime(|runtime| {
+        if !runtime.ready.contains(&task_id) {
+            runtime.ready.push_back(task_id);
+        }
+    });
+}
+
+fn drop_waker(_data: *const ()) {}
@@ -0,0 +1,81 @@
+//! 等待句柄就绪
+//!
+//! 句柄就绪后执行对应的系统调用不会挂起线程，如TCP流可读时读取数据。
+//! 文件读写总是就绪的，因此文件可以直接使用 [cos_sys::file] 中的同步接口
+
+use core::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use cos_sys::{
+    debug,
+    error::Result,
+    ipc::{POLL_READ, POLL_WRITE},
+};
+
+use crate::executor;
+
+/// 等待句柄上的events中任意一个事件就绪，返回已就绪的事件
+///
+/// 事件定义见 [cos_sys::ipc]。句柄无效时返回 [cos_sys::ipc::POLL_INVALID]
+pub fn wait_ready(handle: u64, events: u64) -> Ready {
+    Ready {
+        handle,
+        events,
+        key: None,
+    }
+}
+
+/// 等待句柄可读
+pub fn readable(handle: u64) -> Ready {
+    wait_ready(handle, POLL_READ)
+}
+
+/// 等待句柄可写
+pub fn writable(handle: u64) -> Ready {
+    wait_ready(handle, POLL_WRITE)
+}
+
+/// 从进程所在的控制台读取一个字符，没有输入时让出执行
+///
+/// keyboard为 [debug::open_keyboard] 打开的键盘句柄
+pub async fn get_char(keyboard: u64) -> Result<u8> {
+    readable(keyboard).await;
+    debug::get_char()
+}
+
+/// [wait_ready] 返回的future
+pub struct Ready {
+    handle: u64,
+    events: u64,
+    // 注册的等待序号，首次等待时注册
+    key: Option<u64>,
+}
+
+impl Future for Ready {
+    type Output = u64;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let Some(key) = self.key else {
+            // 由执行器在没有可执行的任务时统一等待
+            self.key = Some(executor::register_io(self.handle, self.events, cx.waker()));
+            return Poll::Pending;
+        };
+        match executor::poll_io(key, cx.waker()) {
+            Some(revents) => {
+                self.key = None;
+                Poll::Ready(revents)
+            }
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for Ready {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            executor::cancel_io(key);
+        }
+    }
+}
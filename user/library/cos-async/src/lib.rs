@@ -0,0 +1,32 @@
+//! COS用户程序的协作式异步运行时
+//!
+//! 以单线程执行器运行异步任务，使用户程序可以与内核一样以async编写：
+//!
+//! - [block_on] 在当前线程运行执行器直到给定的future完成，期间同时运行 [spawn] 创建的任务
+//! - [time::sleep] 等计时器。没有等待中的IO时，执行器以休眠系统调用等待最早的计时器到期
+//! - [io::wait_ready] 等IO future，以 [cos_sys::ipc::poll] 等待句柄就绪后，再执行不会阻塞的系统调用。
+//!   [net] 与 [process] 在此基础上提供套接字与子进程的异步接口
+//!
+//! 任务之间协作式调度，只在await处让出，长时间不await的任务会阻塞其他任务，可以调用 [yield_now] 主动让出。
+//! 运行时尚不支持多线程，只能在首次使用它（调用 [block_on]、[spawn] 或唤醒任务）的线程中使用，
+//! 其他线程使用时panic。线程通过线程局部存储区分，因此不能以 [cos_sys::multitask::set_tls_base]
+//! 替换使用运行时的线程的线程指针
+
+#![cfg(target_arch = "x86_64")]
+#![no_std]
+#![feature(custom_test_frameworks)]
+#![feature(thread_local)]
+#![test_runner(test_runner)]
+
+extern crate alloc;
+
+mod executor;
+pub mod io;
+pub mod net;
+pub mod process;
+pub mod time;
+
+pub use executor::{JoinHandle, block_on, spawn, yield_now};
+
+#[cfg(test)]
+fn test_runner(_test_cases: &[&dyn Fn()]) {}
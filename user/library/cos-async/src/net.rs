@@ -0,0 +1,129 @@
+//! 异步网络套接字
+//!
+//! 包装 [cos_sys::net] 中的套接字，等待套接字就绪时让出执行，而不是挂起线程
+
+use cos_sys::{
+    error::Result,
+    net::{self, SocketAddrV4},
+};
+
+use crate::io;
+
+/// UDP套接字，见 [net::UdpSocket]
+#[derive(Debug)]
+pub struct UdpSocket {
+    inner: net::UdpSocket,
+}
+
+impl UdpSocket {
+    /// 绑定本地端口，`port`为0时由系统分配端口
+    pub fn bind(port: u16) -> Result<Self> {
+        net::UdpSocket::bind(port).map(Self::from)
+    }
+
+    /// 套接字的句柄
+    pub fn handle(&self) -> u64 {
+        self.inner.handle()
+    }
+
+    /// 发送一个数据报，发送不会长时间阻塞，因此不会让出执行
+    pub fn send_to(&self, buf: &[u8], addr: SocketAddrV4) -> Result<()> {
+        self.inner.send_to(buf, addr)
+    }
+
+    /// 接收一个数据报，返回数据长度与发送方地址
+    pub async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddrV4)> {
+        io::readable(self.handle()).await;
+        self.inner.recv_from(buf)
+    }
+}
+
+impl From<net::UdpSocket> for UdpSocket {
+    fn from(inner: net::UdpSocket) -> Self {
+        Self { inner }
+    }
+}
+
+/// TCP流，见 [net::TcpStream]
+#[derive(Debug)]
+pub struct TcpStream {
+    inner: net::TcpStream,
+}
+
+impl TcpStream {
+    /// 连接到`addr`
+    ///
+    /// 内核没有非阻塞的连接接口，因此连接期间挂起线程，其他任务也不会执行
+    pub fn connect(addr: SocketAddrV4) -> Result<Self> {
+        net::TcpStream::connect(addr).map(Self::from)
+    }
+
+    /// 流的句柄
+    pub fn handle(&self) -> u64 {
+        self.inner.handle()
+    }
+
+    /// 读取数据，返回读取的长度。对端关闭连接且数据已读取完毕时返回0
+    pub async fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        io::readable(self.handle()).await;
+        self.inner.read(buf)
+    }
+
+    /// 写入数据，返回写入的长度，可能小于`buf`的长度
+    pub async fn write(&self, buf: &[u8]) -> Result<usize> {
+        io::writable(self.handle()).await;
+        self.inner.write(buf)
+    }
+
+    /// 写入全部数据
+    pub async fn write_all(&self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            let len = self.write(buf).await?;
+            buf = &buf[len..];
+        }
+        Ok(())
+    }
+
+    /// 关闭发送方向，见 [net::TcpStream::shutdown]
+    pub fn shutdown(&self) -> Result<()> {
+        self.inner.shutdown()
+    }
+}
+
+impl From<net::TcpStream> for TcpStream {
+    fn from(inner: net::TcpStream) -> Self {
+        Self { inner }
+    }
+}
+
+/// TCP监听套接字，见 [net::TcpListener]
+#[derive(Debug)]
+pub struct TcpListener {
+    inner: net::TcpListener,
+}
+
+impl TcpListener {
+    /// 监听本地端口，`port`为0时由系统分配端口
+    pub fn bind(port: u16) -> Result<Self> {
+        net::TcpListener::bind(port).map(Self::from)
+    }
+
+    /// 套接字的句柄
+    pub fn handle(&self) -> u64 {
+        self.inner.handle()
+    }
+
+    /// 接受一个连接，返回TCP流与对端地址
+    pub async fn accept(&self) -> Result<(TcpStream, SocketAddrV4)> {
+        io::readable(self.handle()).await;
+        self.inner
+            .accept()
+            .map(|(stream, addr)| (TcpStream::from(stream), addr))
+    }
+}
+
+impl From<net::TcpListener> for TcpListener {
+    fn from(inner: net::TcpListener) -> Self {
+        Self { inner }
+    }
+}
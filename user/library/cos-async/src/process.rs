@@ -0,0 +1,11 @@
+//! 子进程
+
+use cos_sys::{error::Result, ipc::POLL_EXIT, multitask::Process};
+
+use crate::io;
+
+/// 等待进程退出并获取其退出码，见 [Process::wait]
+pub async fn wait(process: Process) -> Result<u64> {
+    io::wait_ready(process.handle(), POLL_EXIT).await;
+    process.wait()
+}
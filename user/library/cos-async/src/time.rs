@@ -0,0 +1,83 @@
+//! 计时器
+
+use core::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use cos_sys::time::Instant;
+
+use crate::executor;
+
+/// 等待一段时间
+pub fn sleep(duration: Duration) -> Sleep {
+    sleep_until(executor::now() + duration)
+}
+
+/// 等待到指定的时刻，时刻已过去时立即完成
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep {
+        deadline,
+        key: None,
+    }
+}
+
+/// 在时限内等待future完成，超时时future被释放并返回 [Elapsed]
+pub async fn timeout<F: Future>(duration: Duration, future: F) -> Result<F::Output, Elapsed> {
+    let mut future = core::pin::pin!(future);
+    let mut sleep = sleep(duration);
+    core::future::poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        Pin::new(&mut sleep).poll(cx).map(|()| Err(Elapsed))
+    })
+    .await
+}
+
+/// [timeout] 超时
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elapsed;
+
+/// [sleep] 返回的future
+pub struct Sleep {
+    deadline: Instant,
+    // 注册的计时器序号，首次等待时注册
+    key: Option<u64>,
+}
+
+impl Sleep {
+    /// 到期的时刻
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(key) = self.key {
+            if executor::update_timer(self.deadline, key, cx.waker()) {
+                return Poll::Pending;
+            }
+            // 到期的计时器已由执行器移除
+            self.key = None;
+            return Poll::Ready(());
+        }
+        if executor::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+        self.key = Some(executor::register_timer(self.deadline, cx.waker()));
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            executor::cancel_timer(self.deadline, key);
+        }
+    }
+}
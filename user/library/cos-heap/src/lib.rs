@@ -16,16 +16,49 @@ macro_rules! default_heap {
 
 pub use heap::RustHeap;
 
-pub struct SyscallMemoryProvider;
+pub struct SyscallMemoryProvider {
+    // 上次申请的内存页的结束地址，作为下次申请的建议地址，使堆的内存页尽量连续
+    next_hint: usize,
+}
+
+impl SyscallMemoryProvider {
+    pub const fn new() -> Self {
+        Self { next_hint: 0 }
+    }
+}
 
 unsafe impl heap::MemoryPageProvider for SyscallMemoryProvider {
     unsafe fn allocate_pages(&mut self, size: usize) -> Option<core::ptr::NonNull<u8>> {
-        cos_sys::memory::alloc_page((size / 0x1000) as u64).ok()
+        let ptr = cos_sys::memory::alloc_page_at((size / 0x1000) as u64, self.next_hint as *mut u8)
+            .ok()?;
+        self.next_hint = ptr.as_ptr() as usize + size;
+        Some(ptr)
     }
 
     unsafe fn deallocate_pages(&mut self, address: core::ptr::NonNull<u8>, size: usize) {
         unsafe { cos_sys::memory::free_page(address, (size / 0x1000) as u64).unwrap() }
     }
+
+    unsafe fn grow_pages(
+        &mut self,
+        address: core::ptr::NonNull<u8>,
+        old_size: usize,
+        new_size: usize,
+    ) -> bool {
+        let result = unsafe {
+            cos_sys::memory::remap_page(
+                address,
+                (old_size / 0x1000) as u64,
+                (new_size / 0x1000) as u64,
+            )
+        };
+        if result.is_err() {
+            return false;
+        }
+        let end = address.as_ptr() as usize + new_size;
+        self.next_hint = self.next_hint.max(end);
+        true
+    }
 }
 
 pub struct CosGlobalAllocator {
@@ -38,7 +71,7 @@ unsafe impl Sync for CosGlobalAllocator {}
 impl CosGlobalAllocator {
     pub const fn new() -> Self {
         Self {
-            heap: UnsafeCell::new(RustHeap::new(SyscallMemoryProvider)),
+            heap: UnsafeCell::new(RustHeap::new(SyscallMemoryProvider::new())),
         }
     }
 }
@@ -51,4 +84,8 @@ unsafe impl GlobalAlloc for CosGlobalAllocator {
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
         unsafe { (&mut *self.heap.get()).deallocate(NonNull::new(ptr).unwrap(), layout) }
     }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        unsafe { (&mut *self.heap.get()).reallocate(NonNull::new(ptr).unwrap(), layout, new_size) }
+    }
 }
@@ -0,0 +1,124 @@
+//! 系统调用ABI的版本与功能发现
+//!
+//! 内核与cos-sys各自编译时带有 [ABI_VERSION_MAJOR] 与 [ABI_VERSION_MINOR]。
+//! cos-sys在进程第一次系统调用时通过 [crate::idx::IDX_SYSTEM_ABI_INFO] 查询内核的版本，
+//! 不兼容时，此后除 [FROZEN_SYSCALLS] 以外的系统调用都不会进入内核，直接返回
+//! [ErrorKind::AbiMismatch]。
+//!
+//! # 兼容规则
+//!
+//! 主版本相同，且内核的次版本不低于cos-sys的次版本时兼容。不支持此查询的旧内核视为不兼容。
+//!
+//! # 修改系统调用的约定
+//!
+//! - 新增系统调用：在 [crate::idx] 中对应类别的末尾分配新编号，增加 [ABI_VERSION_MINOR]
+//! - 修改已有系统调用的参数、返回值或结构体布局，或删除系统调用：增加 [ABI_VERSION_MAJOR]，
+//!   次版本归零
+//! - 编号一经分配不再复用，删除的系统调用保留编号空缺
+//! - 新增错误码、新增功能位同样增加次版本；旧版本cos-sys会将未知错误码解析为 [ErrorKind::Unknown]
+//! - 仅在部分机器或内核配置上可用的功能分配功能位（`FEATURE_*`），程序应检查功能位，
+//!   而不是依赖具体的错误码判断功能是否存在
+//! - [FROZEN_SYSCALLS] 中的系统调用的编号与参数永不改变
+//!
+//! `0x1F` 类别的调试系统调用不受以上约定约束，可以随时修改。
+
+use core::{
+    mem::MaybeUninit,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use crate::{
+    error::{ErrorKind, Result, SyscallError},
+    idx,
+};
+
+/// ABI主版本，不兼容的修改时增加
+pub const ABI_VERSION_MAJOR: u32 = 1;
+/// ABI次版本，向后兼容的修改时增加
+pub const ABI_VERSION_MINOR: u32 = 3;
+
+/// 帧缓冲区可用，见 [crate::gfx]
+pub const FEATURE_GRAPHICS: u64 = 1 << 0;
+/// 网卡可用，见 [crate::net]
+pub const FEATURE_NETWORK: u64 = 1 << 1;
+/// 内核支持加载模块，见 [crate::module]
+pub const FEATURE_MODULES: u64 = 1 << 2;
+
+/// 编号与参数永不改变的系统调用，ABI不兼容时仍可使用
+pub const FROZEN_SYSCALLS: [u64; 3] = [
+    idx::IDX_EXIT_PROCESS,
+    idx::IDX_EXIT_THREAD,
+    idx::IDX_SYSTEM_ABI_INFO,
+];
+
+/// 内核的ABI信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct AbiInfo {
+    pub version_major: u32,
+    pub version_minor: u32,
+    // FEATURE_* 的组合
+    pub features: u64,
+}
+
+impl AbiInfo {
+    /// 当前cos-sys编译时的版本，不含功能位
+    pub const fn current() -> Self {
+        Self {
+            version_major: ABI_VERSION_MAJOR,
+            version_minor: ABI_VERSION_MINOR,
+            features: 0,
+        }
+    }
+
+    /// 以此版本编译的cos-sys能否在 `kernel` 版本的内核上运行
+    pub const fn is_compatible_with(&self, kernel: &AbiInfo) -> bool {
+        self.version_major == kernel.version_major && self.version_minor <= kernel.version_minor
+    }
+
+    /// 是否具有全部指定的功能位
+    pub const fn has_features(&self, features: u64) -> bool {
+        self.features & features == features
+    }
+}
+
+/// 查询内核的ABI信息
+///
+/// 此函数不受兼容检查影响，ABI不兼容时仍会返回内核的版本
+pub fn info() -> Result<AbiInfo> {
+    let mut info = MaybeUninit::<AbiInfo>::uninit();
+    let info_ptr = info.as_mut_ptr() as u64;
+    let error = unsafe { crate::raw_syscall(idx::IDX_SYSTEM_ABI_INFO, info_ptr, 0, 0, 0, 0, 0) };
+    SyscallError::to_result(error).map(|_| unsafe { info.assume_init() })
+}
+
+/// 内核是否兼容当前的cos-sys，首次调用时查询内核
+pub fn is_compatible() -> bool {
+    const UNCHECKED: u8 = 0;
+    const COMPATIBLE: u8 = 1;
+    const INCOMPATIBLE: u8 = 2;
+    static STATE: AtomicU8 = AtomicU8::new(UNCHECKED);
+
+    match STATE.load(Ordering::Relaxed) {
+        COMPATIBLE => true,
+        INCOMPATIBLE => false,
+        _ => {
+            // 多个线程可能同时查询，结果相同，无需加锁
+            let compatible =
+                info().is_ok_and(|kernel| AbiInfo::current().is_compatible_with(&kernel));
+            let state = if compatible { COMPATIBLE } else { INCOMPATIBLE };
+            STATE.store(state, Ordering::Relaxed);
+            compatible
+        }
+    }
+}
+
+/// 系统调用前的兼容检查，不兼容时返回错误码
+#[inline]
+pub(crate) fn check(id: u64) -> Option<u64> {
+    if FROZEN_SYSCALLS.contains(&id) || is_compatible() {
+        None
+    } else {
+        Some(ErrorKind::AbiMismatch as u64)
+    }
+}
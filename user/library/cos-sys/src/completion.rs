@@ -0,0 +1,141 @@
+//! 异步系统调用
+//!
+//! 同步的系统调用（如 [crate::file::read]）会挂起当前线程直到操作完成。
+//! 对于需要同时处理多个IO的程序，可以通过 [submit] 提交异步请求，请求提交后立即返回，
+//! 内核在后台执行操作，并在完成后将结果放入进程的完成队列。
+//! 程序通过 [poll] 或 [wait] 从完成队列中取出结果，并通过 [Completion::user_data] 区分不同的请求。
+//!
+//! 同时进行中的请求数量（包括已完成但尚未取出的请求）受进程资源限制
+//! [crate::multitask::LIMIT_ASYNC_REQUESTS] 约束，超出时 [submit] 返回
+//! [crate::error::ErrorKind::QuotaExceeded]。
+
+use core::mem::MaybeUninit;
+
+use crate::{
+    error::{Result, SyscallError},
+    idx, syscall,
+};
+
+/// 读取文件，完成结果为读取的字节数
+pub const OP_FILE_READ: u64 = 1;
+/// 写入文件，完成结果为写入的字节数
+pub const OP_FILE_WRITE: u64 = 2;
+/// 等待进程退出，完成结果为进程退出码
+pub const OP_PROCESS_WAIT: u64 = 3;
+
+/// 异步请求
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Request {
+    /// 操作类型，如 [OP_FILE_READ]
+    pub opcode: u64,
+    /// 操作的句柄
+    pub handle: u64,
+    /// 缓冲区地址，不需要缓冲区的操作忽略此字段
+    pub buffer: u64,
+    /// 缓冲区长度，不需要缓冲区的操作忽略此字段
+    pub length: u64,
+    /// 用户数据，内核不使用此字段，原样返回至 [Completion::user_data]
+    pub user_data: u64,
+}
+
+impl Request {
+    /// 从文件当前游标位置读取数据到buffer
+    pub fn file_read(handle: u64, buffer: *mut u8, length: usize, user_data: u64) -> Self {
+        Self {
+            opcode: OP_FILE_READ,
+            handle,
+            buffer: buffer as u64,
+            length: length as u64,
+            user_data,
+        }
+    }
+
+    /// 将buffer写入文件当前游标位置
+    pub fn file_write(handle: u64, buffer: *const u8, length: usize, user_data: u64) -> Self {
+        Self {
+            opcode: OP_FILE_WRITE,
+            handle,
+            buffer: buffer as u64,
+            length: length as u64,
+            user_data,
+        }
+    }
+
+    /// 等待进程退出
+    ///
+    /// 与 [crate::multitask::wait_process] 不同，完成后进程句柄不会被回收
+    pub const fn process_wait(process_handle: u64, user_data: u64) -> Self {
+        Self {
+            opcode: OP_PROCESS_WAIT,
+            handle: process_handle,
+            buffer: 0,
+            length: 0,
+            user_data,
+        }
+    }
+}
+
+/// 异步请求的完成结果
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Completion {
+    /// 提交请求时的 [Request::user_data]
+    pub user_data: u64,
+    /// 错误码，0表示成功
+    pub error: u64,
+    /// 操作结果，含义由操作类型决定
+    pub result: u64,
+}
+
+impl Completion {
+    /// 将完成结果转换为 [Result]
+    pub const fn result(&self) -> Result<u64> {
+        match SyscallError::new(self.error) {
+            Some(error) => Err(error),
+            None => Ok(self.result),
+        }
+    }
+}
+
+/// 提交异步请求
+///
+/// 内核会在提交时检查句柄与缓冲区，检查失败时直接返回错误，不会产生完成结果。
+/// 提交成功后，无论操作成功或失败，都会产生且仅产生一个完成结果。
+///
+/// 对于写入操作，内核在提交时复制缓冲区，提交返回后即可复用缓冲区。
+///
+/// # Safety
+///
+/// 对于读取操作，内核会在请求完成前的任意时刻写入缓冲区。
+/// 在取得对应的完成结果前，调用方不能访问或释放缓冲区。
+pub unsafe fn submit(request: &Request) -> Result {
+    let request_ptr = request as *const Request as u64;
+    let error = unsafe { syscall!(idx::IDX_COMPLETION_SUBMIT, request_ptr) };
+    SyscallError::to_result(error)
+}
+
+/// 取出已完成的请求，不会挂起线程
+///
+/// 最多取出 completions.len() 个结果，返回实际取出的数量。
+/// 如果完成队列为空，返回 [crate::error::ErrorKind::WouldBlock]
+pub fn poll(completions: &mut [Completion]) -> Result<usize> {
+    take(idx::IDX_COMPLETION_POLL, completions)
+}
+
+/// 取出已完成的请求
+///
+/// 与 [poll] 相同，但完成队列为空时会挂起当前线程，直到至少一个请求完成。
+/// 如果没有进行中的请求，立即返回0
+pub fn wait(completions: &mut [Completion]) -> Result<usize> {
+    take(idx::IDX_COMPLETION_WAIT, completions)
+}
+
+fn take(id: u64, completions: &mut [Completion]) -> Result<usize> {
+    let completions_ptr = completions.as_mut_ptr() as u64;
+    let capacity = completions.len() as u64;
+    let mut count = MaybeUninit::<u64>::uninit();
+    let count_ptr = count.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(id, completions_ptr, capacity, count_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { count.assume_init() as usize })
+}
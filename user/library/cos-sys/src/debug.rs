@@ -5,12 +5,122 @@ use crate::{
     idx, syscall,
 };
 
+/// 进程句柄
+pub const HANDLE_KIND_PROCESS: u64 = 1;
+/// 线程句柄
+pub const HANDLE_KIND_THREAD: u64 = 2;
+/// 文件句柄
+pub const HANDLE_KIND_FILE: u64 = 3;
+/// 键盘句柄
+pub const HANDLE_KIND_KEYBOARD: u64 = 4;
+/// UDP套接字句柄
+pub const HANDLE_KIND_UDP_SOCKET: u64 = 5;
+/// TCP流句柄
+pub const HANDLE_KIND_TCP_STREAM: u64 = 6;
+/// TCP监听套接字句柄
+pub const HANDLE_KIND_TCP_LISTENER: u64 = 7;
+/// 字符设备句柄，如打开/dev/null得到的句柄
+pub const HANDLE_KIND_CHAR_DEVICE: u64 = 8;
+
+/// 内存区域：可执行文件的段
+pub const MEMORY_REGION_IMAGE: u64 = 1;
+/// 内存区域：线程栈
+pub const MEMORY_REGION_STACK: u64 = 2;
+/// 内存区域：通过 [crate::memory::alloc_page] 等申请的内存
+pub const MEMORY_REGION_DATA: u64 = 3;
+/// 内存区域：内核映射的只读共享页，见 [crate::vdso]
+pub const MEMORY_REGION_VDSO: u64 = 4;
+
+/// 内存区域可写
+pub const MEMORY_REGION_WRITABLE: u64 = 1 << 0;
+/// 内存区域可执行
+pub const MEMORY_REGION_EXECUTABLE: u64 = 1 << 1;
+/// 内存区域的内容从文件加载
+pub const MEMORY_REGION_FILE: u64 = 1 << 2;
+
+/// 跟踪选项：记录进程的系统调用
+pub const TRACE_SYSCALLS: u64 = 1 << 0;
+/// 跟踪选项：进程此后创建的子进程从启动起即被跟踪（[TRACE_SYSCALLS]），进程自身不受影响
+pub const TRACE_CHILDREN: u64 = 1 << 1;
+
+/// 日志过滤级别：屏蔽目标的全部日志
+pub const LOG_LEVEL_OFF: u64 = 0;
+/// 日志过滤级别：仅输出错误
+pub const LOG_LEVEL_ERROR: u64 = 1;
+/// 日志过滤级别：输出警告及以上
+pub const LOG_LEVEL_WARN: u64 = 2;
+/// 日志过滤级别：输出信息及以上
+pub const LOG_LEVEL_INFO: u64 = 3;
+/// 日志过滤级别：输出全部日志
+pub const LOG_LEVEL_DEBUG: u64 = 4;
+/// 日志过滤级别：清除目标的过滤级别，恢复使用启动选项`loglevel`
+pub const LOG_LEVEL_DEFAULT: u64 = u64::MAX;
+
+/// 句柄信息，由 [list_handles] 返回
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HandleInfo {
+    /// 句柄
+    pub handle: u64,
+    /// 句柄类型，如 [HANDLE_KIND_FILE]
+    pub kind: u64,
+}
+
+/// 内存区域信息，由 [memory_maps] 返回
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryRegionInfo {
+    /// 起始地址
+    pub start: u64,
+    /// 大小（字节），为4K的整数倍
+    pub size: u64,
+    /// 区域用途，如 [MEMORY_REGION_DATA]
+    pub kind: u64,
+    /// 区域属性，如 [MEMORY_REGION_WRITABLE]
+    pub flags: u64,
+}
+
+/// 系统调用跟踪记录，由 [read_trace] 返回
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SyscallTraceRecord {
+    /// 发起调用的进程ID
+    pub process_id: u64,
+    /// 发起调用的线程ID
+    pub thread_id: u64,
+    /// 系统调用编号，见 [crate::idx]
+    pub syscall_id: u64,
+    /// 系统调用参数
+    pub args: [u64; 6],
+    /// 返回值，即错误码。不会返回的系统调用（如 [crate::multitask::exit]）记录为0
+    pub result: u64,
+    /// 耗时（us），精度为计时器中断的间隔
+    pub duration: u64,
+}
+
+/// 内核自旋锁的统计，由 [lock_stats] 返回
+///
+/// 各项均为系统启动以来全部自旋锁的累计值
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LockStats {
+    /// 获取锁的次数
+    pub acquisitions: u64,
+    /// 获取锁时锁已被占用、需要自旋等待的次数
+    pub contended: u64,
+    /// 自旋等待的总次数
+    pub spins: u64,
+    /// 在硬中断处理程序中获取锁的次数
+    pub irq_acquisitions: u64,
+}
+
 pub fn info() {
     unsafe {
         syscall!(idx::IDX_DEBUG_INFO);
     }
 }
 
+/// 从进程所在的控制台读取一个字符，没有输入时等待
 pub fn get_char() -> Result<u8> {
     let mut char = MaybeUninit::uninit();
     let char_ptr = char.as_mut_ptr() as u64;
@@ -18,8 +128,147 @@ pub fn get_char() -> Result<u8> {
     SyscallError::to_result(error).map(|_| unsafe { char.assume_init() })
 }
 
+/// 打开键盘句柄
+///
+/// 键盘句柄可用于 [crate::ipc::poll] 等待键盘输入，输入内容仍通过 [get_char] 读取
+pub fn open_keyboard() -> Result<u64> {
+    let mut handle = MaybeUninit::uninit();
+    let handle_ptr = handle.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_DEBUG_OPEN_KEYBOARD, handle_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { handle.assume_init() })
+}
+
 pub fn put_char(char: u8) -> Result<()> {
     let char_ptr = &raw const char as u64;
     let error = unsafe { syscall!(idx::IDX_DEBUG_PUT_CHAR, char_ptr) };
     SyscallError::to_result(error)
 }
+
+/// 向进程所在的控制台输出一段字节，其中的ANSI转义序列会被解释执行
+///
+/// 与逐字节调用 [put_char] 效果相同，但只需一次系统调用
+pub fn put_str(buf: &[u8]) -> Result<()> {
+    let buf_ptr = buf.as_ptr() as u64;
+    let buf_len = buf.len() as u64;
+    let error = unsafe { syscall!(idx::IDX_DEBUG_PUT_STR, buf_ptr, buf_len) };
+    SyscallError::to_result(error)
+}
+
+/// 列出当前进程的全部句柄，用于调试句柄泄漏
+///
+/// 最多写入 handles.len() 个句柄信息，返回进程持有的句柄总数。
+/// 如果返回值大于 handles.len()，说明缓冲区不足，部分句柄未被列出
+pub fn list_handles(handles: &mut [HandleInfo]) -> Result<usize> {
+    let handles_ptr = handles.as_mut_ptr() as u64;
+    let handles_len = handles.len() as u64;
+    let mut count = MaybeUninit::<u64>::uninit();
+    let count_ptr = count.as_mut_ptr() as u64;
+    let error = unsafe {
+        syscall!(
+            idx::IDX_DEBUG_LIST_HANDLES,
+            handles_ptr,
+            handles_len,
+            count_ptr
+        )
+    };
+    SyscallError::to_result(error).map(|_| unsafe { count.assume_init() as usize })
+}
+
+/// 列出进程的内存区域，用于调试内存泄漏与越界访问
+///
+/// process_id为0时列出当前进程的内存区域。只能查看自身与子进程，超级用户与特权进程除外，
+/// 否则返回 [ErrorKind::PermissionDenied](crate::error::ErrorKind::PermissionDenied)。
+/// 区域按起始地址升序排列，最多写入 regions.len() 个区域信息，返回进程的区域总数。
+/// 如果返回值大于 regions.len()，说明缓冲区不足，部分区域未被列出
+pub fn memory_maps(process_id: u64, regions: &mut [MemoryRegionInfo]) -> Result<usize> {
+    let regions_ptr = regions.as_mut_ptr() as u64;
+    let regions_len = regions.len() as u64;
+    let mut count = MaybeUninit::<u64>::uninit();
+    let count_ptr = count.as_mut_ptr() as u64;
+    let error = unsafe {
+        syscall!(
+            idx::IDX_DEBUG_MEMORY_MAPS,
+            process_id,
+            regions_ptr,
+            regions_len,
+            count_ptr
+        )
+    };
+    SyscallError::to_result(error).map(|_| unsafe { count.assume_init() as usize })
+}
+
+/// 设置进程的系统调用跟踪选项，用于调试用户程序
+///
+/// process_id为0时设置当前进程。flags为 [TRACE_SYSCALLS] 与 [TRACE_CHILDREN] 的组合，为0时停止跟踪，
+/// 已产生的记录仍可通过 [read_trace] 取出。进程退出后其跟踪选项随之清除。
+/// 只能跟踪自身与子进程，超级用户与特权进程除外，否则返回 [ErrorKind::PermissionDenied](crate::error::ErrorKind::PermissionDenied)。
+/// 跟踪产生的记录归属于调用者，由 [TRACE_CHILDREN] 跟踪的子进程的记录同样归属于调用者
+pub fn trace_process(process_id: u64, flags: u64) -> Result<()> {
+    let error = unsafe { syscall!(idx::IDX_DEBUG_TRACE_PROCESS, process_id, flags) };
+    SyscallError::to_result(error)
+}
+
+/// 取出系统调用跟踪记录
+///
+/// process_id为0时取出归属于当前进程的所有记录，否则只取出指定进程归属于当前进程的记录（进程退出后仍可取出）。
+/// 超级用户与特权进程可以取出指定进程归属于其他进程的记录。
+/// 记录按调用完成的先后排列，最多取出 records.len() 条，返回取出的数量，以及自上次调用以来因缓冲区满而丢弃的记录数量。
+/// 取出的记录从内核缓冲区中移除
+pub fn read_trace(process_id: u64, records: &mut [SyscallTraceRecord]) -> Result<(usize, u64)> {
+    let records_ptr = records.as_mut_ptr() as u64;
+    let records_len = records.len() as u64;
+    let mut count = MaybeUninit::<u64>::uninit();
+    let count_ptr = count.as_mut_ptr() as u64;
+    let mut dropped = MaybeUninit::<u64>::uninit();
+    let dropped_ptr = dropped.as_mut_ptr() as u64;
+    let error = unsafe {
+        syscall!(
+            idx::IDX_DEBUG_READ_TRACE,
+            process_id,
+            records_ptr,
+            records_len,
+            count_ptr,
+            dropped_ptr
+        )
+    };
+    SyscallError::to_result(error)
+        .map(|_| unsafe { (count.assume_init() as usize, dropped.assume_init()) })
+}
+
+/// 设置内核日志目标的过滤级别，用于调试时屏蔽无关子系统的日志，或单独开启某个子系统的调试日志
+///
+/// target为产生日志的子系统名，如 "ahci"、"net"。level为 [LOG_LEVEL_OFF] 至 [LOG_LEVEL_DEBUG] 之一，
+/// 或 [LOG_LEVEL_DEFAULT]。设置后该目标的日志只按此级别过滤，不再受启动选项`loglevel`影响
+pub fn set_log_filter(target: &str, level: u64) -> Result<()> {
+    let target_ptr = target.as_ptr() as u64;
+    let target_len = target.len() as u64;
+    let error = unsafe { syscall!(idx::IDX_DEBUG_SET_LOG_FILTER, target_ptr, target_len, level) };
+    SyscallError::to_result(error)
+}
+
+/// 向串口写入数据
+///
+/// 运行于qemu时，串口输出可被宿主机捕获，用于自动化测试等场景
+pub fn serial_write(buf: &[u8]) -> Result<()> {
+    let buf_ptr = buf.as_ptr() as u64;
+    let buf_len = buf.len() as u64;
+    let error = unsafe { syscall!(idx::IDX_DEBUG_SERIAL_WRITE, buf_ptr, buf_len) };
+    SyscallError::to_result(error)
+}
+
+/// 通过qemu的isa-debug-exit设备退出模拟器，仅特权进程可用
+///
+/// qemu的退出码为 `(code << 1) | 1`。成功时不会返回；
+/// 未运行于qemu或qemu未配置该设备时，返回 [crate::error::ErrorKind::NotSupported]
+pub fn exit_emulator(code: u32) -> Result<()> {
+    let error = unsafe { syscall!(idx::IDX_DEBUG_EXIT_EMULATOR, code as u64) };
+    SyscallError::to_result(error)
+}
+
+/// 获取内核自旋锁的统计，用于分析锁竞争
+pub fn lock_stats() -> Result<LockStats> {
+    let mut stats = MaybeUninit::<LockStats>::uninit();
+    let stats_ptr = stats.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_DEBUG_LOCK_STATS, stats_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { stats.assume_init() })
+}
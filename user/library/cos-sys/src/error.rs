@@ -9,7 +9,7 @@ pub struct SyscallError(NonZeroU64);
 
 pub type Result<T = (), E = SyscallError> = core::result::Result<T, E>;
 
-#[derive(Debug, Clone, Copy, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 #[repr(u64)]
 pub enum ErrorKind {
@@ -18,6 +18,25 @@ pub enum ErrorKind {
     OutOfMemory = 2,
     BadPointer = 3,
     BadArgument = 4,
+    QuotaExceeded = 5,
+    FileNotFound = 6,
+    FileExists = 7,
+    DiskFull = 8,
+    FileTypeMismatch = 9,
+    NameTooLong = 10,
+    Occupied = 11,
+    NotSupported = 12,
+    IoError = 13,
+    FileTooLarge = 14,
+    WouldBlock = 15,
+    BadHandle = 16,
+    Unreachable = 17,
+    ConnectionRefused = 18,
+    ConnectionReset = 19,
+    TimedOut = 20,
+    NotConnected = 21,
+    Corrupted = 22,
+    AbiMismatch = 23,
     Unknown = u64::MAX,
 }
 
@@ -65,10 +84,85 @@ impl ErrorKind {
             OutOfMemory,
             BadPointer,
             BadArgument,
+            QuotaExceeded,
+            FileNotFound,
+            FileExists,
+            DiskFull,
+            FileTypeMismatch,
+            NameTooLong,
+            Occupied,
+            NotSupported,
+            IoError,
+            FileTooLarge,
+            WouldBlock,
+            BadHandle,
+            Unreachable,
+            ConnectionRefused,
+            ConnectionReset,
+            TimedOut,
+            NotConnected,
+            Corrupted,
+            AbiMismatch,
         )
     }
 }
 
+impl From<u64> for ErrorKind {
+    fn from(value: u64) -> Self {
+        Self::from_error_code(value)
+    }
+}
+
+/// 类型化的系统调用错误
+///
+/// 与 [SyscallError] 相比，SysError 在构造时即解析出 [ErrorKind]，便于直接匹配错误类型，
+/// 同时保留原始错误码，以便处理当前版本未定义的错误码。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SysError {
+    kind: ErrorKind,
+    code: u64,
+}
+
+impl SysError {
+    pub const fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub const fn error_code(&self) -> u64 {
+        self.code
+    }
+}
+
+impl From<u64> for SysError {
+    fn from(value: u64) -> Self {
+        Self {
+            kind: ErrorKind::from_error_code(value),
+            code: value,
+        }
+    }
+}
+
+impl From<SyscallError> for SysError {
+    fn from(value: SyscallError) -> Self {
+        Self::from(value.error_code())
+    }
+}
+
+impl From<ErrorKind> for SysError {
+    fn from(value: ErrorKind) -> Self {
+        Self {
+            kind: value,
+            code: value as u64,
+        }
+    }
+}
+
+impl Display for SysError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        <ErrorKind as Display>::fmt(&self.kind, f)
+    }
+}
+
 impl Debug for SyscallError {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         let kind = self.kind();
@@ -94,6 +188,25 @@ impl Display for ErrorKind {
             ErrorKind::OutOfMemory => "system is run out of memory",
             ErrorKind::BadPointer => "application memory broken",
             ErrorKind::BadArgument => "bad argument",
+            ErrorKind::QuotaExceeded => "resource quota exceeded",
+            ErrorKind::FileNotFound => "file not found",
+            ErrorKind::FileExists => "file already exists",
+            ErrorKind::DiskFull => "disk is full",
+            ErrorKind::FileTypeMismatch => "file type mismatch",
+            ErrorKind::NameTooLong => "file name is too long",
+            ErrorKind::Occupied => "resource is occupied",
+            ErrorKind::NotSupported => "operation is not supported",
+            ErrorKind::IoError => "device io error",
+            ErrorKind::FileTooLarge => "file is too large",
+            ErrorKind::WouldBlock => "operation would block",
+            ErrorKind::BadHandle => "handle is invalid or closed",
+            ErrorKind::Unreachable => "network is unreachable",
+            ErrorKind::ConnectionRefused => "connection refused",
+            ErrorKind::ConnectionReset => "connection reset by peer",
+            ErrorKind::TimedOut => "operation timed out",
+            ErrorKind::NotConnected => "not connected",
+            ErrorKind::Corrupted => "data is corrupted",
+            ErrorKind::AbiMismatch => "system call abi mismatch with kernel",
             ErrorKind::Unknown => "unknown error",
         };
 
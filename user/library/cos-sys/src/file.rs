@@ -2,7 +2,10 @@ use core::mem::MaybeUninit;
 
 use crate::{
     error::{Result, SyscallError},
-    idx, syscall,
+    handle::OwnedHandle,
+    idx,
+    io::{Read, Write},
+    syscall,
 };
 
 pub fn create(path: &[u8]) -> Result<()> {
@@ -12,6 +15,12 @@ pub fn create(path: &[u8]) -> Result<()> {
     SyscallError::to_result(error)
 }
 
+/// 打开文件，返回文件句柄
+///
+/// 底层接口，句柄需调用 [close] 关闭。通常使用 [File::open]
+///
+/// 打开控制台设备（`/dev/console`、`/dev/tty<n>`）还需具有raw-console能力，且控制台为调用者自身的控制台
+/// 或调用者位于其前台进程组，否则返回 [crate::error::ErrorKind::PermissionDenied]
 pub fn open(path: &[u8]) -> Result<u64> {
     let path_ptr = path.as_ptr() as u64;
     let path_len = path.len() as u64;
@@ -67,7 +76,464 @@ pub fn set_pos(handle: u64, pos: u64) -> Result<()> {
     SyscallError::to_result(error)
 }
 
+/// 截断文件，丢弃文件指针之后的全部内容
+///
+/// 截断后文件大小等于文件指针位置，文件指针不变
+pub fn truncate(handle: u64) -> Result<()> {
+    let error = unsafe { syscall!(idx::IDX_FILE_TRUNCATE, handle) };
+    SyscallError::to_result(error)
+}
+
+/// 关闭句柄
+///
+/// 可以关闭任意类型的句柄。底层接口，[OwnedHandle] 及基于它的类型在释放时自动调用
 pub fn close(handle: u64) -> Result<()> {
     let error = unsafe { syscall!(idx::IDX_FILE_CLOSE, handle) };
     SyscallError::to_result(error)
 }
+
+/// 打开的文件
+///
+/// 文件被释放时关闭句柄
+#[derive(Debug)]
+pub struct File {
+    handle: OwnedHandle,
+}
+
+impl File {
+    /// 打开文件
+    pub fn open(path: &[u8]) -> Result<Self> {
+        open(path).map(|handle| Self {
+            // Safety: 句柄刚刚打开，没有其他所有者
+            handle: unsafe { OwnedHandle::from_raw(handle) },
+        })
+    }
+
+    /// 创建并打开文件，文件已存在时返回 [crate::error::ErrorKind::FileExists]
+    pub fn create(path: &[u8]) -> Result<Self> {
+        create(path)?;
+        Self::open(path)
+    }
+
+    /// 文件的句柄
+    pub fn handle(&self) -> u64 {
+        self.handle.as_raw()
+    }
+
+    /// 获取文件游标位置
+    pub fn position(&self) -> Result<u64> {
+        get_pos(self.handle())
+    }
+
+    /// 移动文件游标位置
+    pub fn seek(&mut self, pos: u64) -> Result<()> {
+        set_pos(self.handle(), pos)
+    }
+
+    /// 截断文件，见 [truncate]
+    pub fn truncate(&mut self) -> Result<()> {
+        truncate(self.handle())
+    }
+
+    /// 关闭文件并返回关闭的结果
+    pub fn close(self) -> Result<()> {
+        self.handle.close()
+    }
+}
+
+impl From<OwnedHandle> for File {
+    fn from(handle: OwnedHandle) -> Self {
+        Self { handle }
+    }
+}
+
+impl From<File> for OwnedHandle {
+    fn from(file: File) -> Self {
+        file.handle
+    }
+}
+
+impl Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        read(self.handle(), buf).map(|len| len as usize)
+    }
+}
+
+impl Write for File {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        write(self.handle(), buf).map(|len| len as usize)
+    }
+}
+
+/// 块设备名称的最大长度
+pub const BLOCK_DEVICE_NAME_LEN: usize = 16;
+/// [BlockDeviceInfo] 中挂载路径的最大长度，更长的路径会被截断
+pub const MOUNT_PATH_LEN: usize = 64;
+
+/// 块设备信息，由 [list_block_devices] 返回
+///
+/// 磁盘命名为`disk<n>`，其分区命名为`disk<n>p<m>`，m为分区在分区表中的序号，从1开始
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct BlockDeviceInfo {
+    /// 块设备名称，长度为 [BlockDeviceInfo::name_len]
+    pub name: [u8; BLOCK_DEVICE_NAME_LEN],
+    pub name_len: u64,
+    /// 块大小（字节）
+    pub block_size: u64,
+    /// 块数量
+    pub block_count: u64,
+    /// 分区类型，整块磁盘为0
+    pub partition_type: u64,
+    /// 挂载路径，长度为 [BlockDeviceInfo::mount_path_len]，未挂载时长度为0
+    pub mount_path: [u8; MOUNT_PATH_LEN],
+    pub mount_path_len: u64,
+}
+
+impl BlockDeviceInfo {
+    /// 块设备名称
+    pub fn name(&self) -> &[u8] {
+        &self.name[..(self.name_len as usize).min(BLOCK_DEVICE_NAME_LEN)]
+    }
+
+    /// 挂载路径，未挂载时返回None
+    pub fn mount_path(&self) -> Option<&[u8]> {
+        let len = (self.mount_path_len as usize).min(MOUNT_PATH_LEN);
+        (len > 0).then(|| &self.mount_path[..len])
+    }
+}
+
+impl Default for BlockDeviceInfo {
+    fn default() -> Self {
+        Self {
+            name: [0; BLOCK_DEVICE_NAME_LEN],
+            name_len: 0,
+            block_size: 0,
+            block_count: 0,
+            partition_type: 0,
+            mount_path: [0; MOUNT_PATH_LEN],
+            mount_path_len: 0,
+        }
+    }
+}
+
+/// 重新扫描所有磁盘的分区表，并列出块设备
+///
+/// 最多写入 devices.len() 个块设备，返回块设备的总数。
+/// 如果返回值大于 devices.len()，说明缓冲区不足，可以扩大缓冲区后重试
+pub fn list_block_devices(devices: &mut [BlockDeviceInfo]) -> Result<usize> {
+    let devices_ptr = devices.as_mut_ptr() as u64;
+    let devices_len = devices.len() as u64;
+    let mut count = MaybeUninit::<u64>::uninit();
+    let count_ptr = count.as_mut_ptr() as u64;
+    let error = unsafe {
+        syscall!(
+            idx::IDX_FILE_LIST_BLOCK_DEVICES,
+            devices_ptr,
+            devices_len,
+            count_ptr
+        )
+    };
+    SyscallError::to_result(error).map(|_| unsafe { count.assume_init() as usize })
+}
+
+/// 将块设备上的FAT32文件系统挂载到指定路径
+///
+/// 块设备不存在时返回 [crate::error::ErrorKind::FileNotFound]；
+/// 路径已挂载文件系统，或块设备（及其所在磁盘或其中的分区）已被挂载时返回 [crate::error::ErrorKind::FileExists]；
+/// 块设备不是FAT32文件系统时返回 [crate::error::ErrorKind::NotSupported]
+pub fn mount(device: &[u8], path: &[u8]) -> Result<()> {
+    let device_ptr = device.as_ptr() as u64;
+    let device_len = device.len() as u64;
+    let path_ptr = path.as_ptr() as u64;
+    let path_len = path.len() as u64;
+    let error = unsafe {
+        syscall!(
+            idx::IDX_FILE_MOUNT,
+            device_ptr,
+            device_len,
+            path_ptr,
+            path_len
+        )
+    };
+    SyscallError::to_result(error)
+}
+
+/// 以口令打开块设备上的加密卷，并将其中的FAT32文件系统挂载到指定路径
+///
+/// 错误同 [mount]；此外，口令错误时返回 [crate::error::ErrorKind::PermissionDenied]，
+/// 块设备上没有加密卷时返回 [crate::error::ErrorKind::NotSupported]。
+/// 内核派生密钥耗时较长，调用会阻塞至挂载完成
+pub fn mount_encrypted(device: &[u8], path: &[u8], passphrase: &[u8]) -> Result<()> {
+    let device_ptr = device.as_ptr() as u64;
+    let device_len = device.len() as u64;
+    let path_ptr = path.as_ptr() as u64;
+    let path_len = path.len() as u64;
+    let passphrase_ptr = passphrase.as_ptr() as u64;
+    let passphrase_len = passphrase.len() as u64;
+    let error = unsafe {
+        syscall!(
+            idx::IDX_FILE_MOUNT_ENCRYPTED,
+            device_ptr,
+            device_len,
+            path_ptr,
+            path_len,
+            passphrase_ptr,
+            passphrase_len
+        )
+    };
+    SyscallError::to_result(error)
+}
+
+/// 卸载指定路径上的文件系统
+///
+/// 内核会将文件系统的缓存写回块设备。路径上没有挂载文件系统时返回 [crate::error::ErrorKind::FileNotFound]；
+/// 文件系统中仍有打开的文件时返回 [crate::error::ErrorKind::Occupied]
+pub fn unmount(path: &[u8]) -> Result<()> {
+    let path_ptr = path.as_ptr() as u64;
+    let path_len = path.len() as u64;
+    let error = unsafe { syscall!(idx::IDX_FILE_UNMOUNT, path_ptr, path_len) };
+    SyscallError::to_result(error)
+}
+
+/// [DirectoryEntryInfo] 中文件名的最大长度，更长的文件名会被截断
+pub const FILE_NAME_LEN: usize = 256;
+
+/// 文件夹项为文件夹
+pub const DIRECTORY_ENTRY_DIRECTORY: u64 = 1 << 0;
+
+/// 文件夹项信息，由 [list_directory] 返回
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DirectoryEntryInfo {
+    /// 文件名，长度为 [DirectoryEntryInfo::name_len]
+    pub name: [u8; FILE_NAME_LEN],
+    pub name_len: u64,
+    /// 文件大小（字节），文件夹为0
+    pub size: u64,
+    /// 文件夹项属性，如 [DIRECTORY_ENTRY_DIRECTORY]
+    pub flags: u64,
+}
+
+impl DirectoryEntryInfo {
+    /// 文件名
+    pub fn name(&self) -> &[u8] {
+        &self.name[..(self.name_len as usize).min(FILE_NAME_LEN)]
+    }
+
+    /// 是否为文件夹
+    pub fn is_directory(&self) -> bool {
+        self.flags & DIRECTORY_ENTRY_DIRECTORY != 0
+    }
+}
+
+impl Default for DirectoryEntryInfo {
+    fn default() -> Self {
+        Self {
+            name: [0; FILE_NAME_LEN],
+            name_len: 0,
+            size: 0,
+            flags: 0,
+        }
+    }
+}
+
+/// 列出文件夹中的内容
+///
+/// 最多写入 entries.len() 个文件夹项，返回文件夹项的总数。
+/// 如果返回值大于 entries.len()，说明缓冲区不足，可以扩大缓冲区后重试。
+/// 路径不存在时返回 [crate::error::ErrorKind::FileNotFound]；路径为文件时返回 [crate::error::ErrorKind::FileTypeMismatch]
+pub fn list_directory(path: &[u8], entries: &mut [DirectoryEntryInfo]) -> Result<usize> {
+    let path_ptr = path.as_ptr() as u64;
+    let path_len = path.len() as u64;
+    let entries_ptr = entries.as_mut_ptr() as u64;
+    let entries_len = entries.len() as u64;
+    let mut count = MaybeUninit::<u64>::uninit();
+    let count_ptr = count.as_mut_ptr() as u64;
+    let error = unsafe {
+        syscall!(
+            idx::IDX_FILE_LIST_DIRECTORY,
+            path_ptr,
+            path_len,
+            entries_ptr,
+            entries_len,
+            count_ptr
+        )
+    };
+    SyscallError::to_result(error).map(|_| unsafe { count.assume_init() as usize })
+}
+
+/// 删除文件
+///
+/// 路径不存在时返回 [crate::error::ErrorKind::FileNotFound]；路径为文件夹时返回 [crate::error::ErrorKind::FileTypeMismatch]；
+/// 文件正被打开时返回 [crate::error::ErrorKind::Occupied]
+pub fn delete(path: &[u8]) -> Result<()> {
+    let path_ptr = path.as_ptr() as u64;
+    let path_len = path.len() as u64;
+    let error = unsafe { syscall!(idx::IDX_FILE_DELETE, path_ptr, path_len) };
+    SyscallError::to_result(error)
+}
+
+/// 重命名或移动文件、文件夹
+///
+/// 新路径已存在时返回 [crate::error::ErrorKind::FileExists]；
+/// 新旧路径位于不同的文件系统时返回 [crate::error::ErrorKind::NotSupported]，需复制后删除
+pub fn rename(old_path: &[u8], new_path: &[u8]) -> Result<()> {
+    let old_path_ptr = old_path.as_ptr() as u64;
+    let old_path_len = old_path.len() as u64;
+    let new_path_ptr = new_path.as_ptr() as u64;
+    let new_path_len = new_path.len() as u64;
+    let error = unsafe {
+        syscall!(
+            idx::IDX_FILE_RENAME,
+            old_path_ptr,
+            old_path_len,
+            new_path_ptr,
+            new_path_len
+        )
+    };
+    SyscallError::to_result(error)
+}
+
+/// 文件信息，由 [metadata] 返回
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileInfo {
+    /// 文件大小（字节），文件夹为0
+    pub size: u64,
+    /// 实际占用的磁盘空间（字节）
+    pub allocated_size: u64,
+    /// 文件属性，如 [DIRECTORY_ENTRY_DIRECTORY]
+    pub flags: u64,
+    /// 所有者的用户ID
+    pub uid: u64,
+    /// 所有者的组ID
+    pub gid: u64,
+    /// 权限位，与unix相同，如0o755
+    pub mode: u64,
+}
+
+impl FileInfo {
+    /// 是否为文件夹
+    pub fn is_directory(&self) -> bool {
+        self.flags & DIRECTORY_ENTRY_DIRECTORY != 0
+    }
+}
+
+/// 获取文件或文件夹的信息
+///
+/// 路径不存在时返回 [crate::error::ErrorKind::FileNotFound]
+pub fn metadata(path: &[u8]) -> Result<FileInfo> {
+    let path_ptr = path.as_ptr() as u64;
+    let path_len = path.len() as u64;
+    let mut info = MaybeUninit::<FileInfo>::uninit();
+    let info_ptr = info.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_FILE_METADATA, path_ptr, path_len, info_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { info.assume_init() })
+}
+
+/// 扫描结果，由 [scrub] 返回
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScrubInfo {
+    /// 已检查的块数量
+    pub checked: u64,
+    /// 校验失败的块数量
+    pub corrupted: u64,
+}
+
+/// 检查校验块设备上的全部数据
+///
+/// 扫描在内核的后台任务中进行：块设备上没有进行中的扫描时开始扫描，扫描未完成时返回
+/// [crate::error::ErrorKind::WouldBlock]，调用者可稍后重试；扫描完成后的调用取出结果，下一次调用将开始新的扫描。
+/// 校验失败的块号依次写入 corrupted，最多写入 corrupted.len() 个，失败块的总数见 [ScrubInfo::corrupted]。
+/// 只有超级用户可以扫描，否则返回 [crate::error::ErrorKind::PermissionDenied]；
+/// 块设备不存在时返回 [crate::error::ErrorKind::FileNotFound]；
+/// 块设备不是校验卷时返回 [crate::error::ErrorKind::NotSupported]
+pub fn scrub(device: &[u8], corrupted: &mut [u64]) -> Result<ScrubInfo> {
+    let device_ptr = device.as_ptr() as u64;
+    let device_len = device.len() as u64;
+    let corrupted_ptr = corrupted.as_mut_ptr() as u64;
+    let corrupted_len = corrupted.len() as u64;
+    let mut info = MaybeUninit::<ScrubInfo>::uninit();
+    let info_ptr = info.as_mut_ptr() as u64;
+    let error = unsafe {
+        syscall!(
+            idx::IDX_FILE_SCRUB,
+            device_ptr,
+            device_len,
+            corrupted_ptr,
+            corrupted_len,
+            info_ptr
+        )
+    };
+    SyscallError::to_result(error).map(|_| unsafe { info.assume_init() })
+}
+
+/// 修改文件或文件夹的权限位
+///
+/// mode与unix相同，如0o644，超出0o777的部分被忽略。只有文件的所有者与超级用户可以修改，
+/// 否则返回 [crate::error::ErrorKind::PermissionDenied]
+pub fn chmod(path: &[u8], mode: u16) -> Result<()> {
+    let path_ptr = path.as_ptr() as u64;
+    let path_len = path.len() as u64;
+    let error = unsafe { syscall!(idx::IDX_FILE_CHMOD, path_ptr, path_len, mode as u64) };
+    SyscallError::to_result(error)
+}
+
+/// 修改文件或文件夹的所有者
+///
+/// 只有超级用户可以修改，否则返回 [crate::error::ErrorKind::PermissionDenied]
+pub fn chown(path: &[u8], uid: u32, gid: u32) -> Result<()> {
+    let path_ptr = path.as_ptr() as u64;
+    let path_len = path.len() as u64;
+    let error = unsafe {
+        syscall!(
+            idx::IDX_FILE_CHOWN,
+            path_ptr,
+            path_len,
+            uid as u64,
+            gid as u64
+        )
+    };
+    SyscallError::to_result(error)
+}
+
+/// 控制请求：查询控制台大小，见 [console_size]
+pub const CONTROL_GET_CONSOLE_SIZE: u64 = 1;
+/// 控制请求：修改控制台大小，见 [set_console_size]
+pub const CONTROL_SET_CONSOLE_SIZE: u64 = 2;
+
+/// 控制台文本区域的大小
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConsoleSize {
+    /// 行数
+    pub rows: u32,
+    /// 列数
+    pub cols: u32,
+}
+
+/// 查询控制台大小
+///
+/// handle为控制台句柄，即 [crate::debug::open_keyboard] 返回的句柄或打开 `/dev/tty<n>` 得到的句柄，
+/// 其他句柄返回 [crate::error::ErrorKind::NotSupported]。
+/// 控制台大小变化时，句柄上的 [crate::ipc::POLL_RESIZE] 事件就绪
+pub fn console_size(handle: u64) -> Result<ConsoleSize> {
+    control(handle, CONTROL_GET_CONSOLE_SIZE, ConsoleSize::default())
+}
+
+/// 修改控制台大小，返回修改后的大小
+///
+/// 超出屏幕的部分被限制在屏幕大小。区域内的内容保持不变，区域外的内容被清除。
+/// 通过 `/dev/tty<n>` 修改时，句柄需要写权限。调用者还需满足打开控制台设备的条件（见 [open]），
+/// 否则返回 [crate::error::ErrorKind::PermissionDenied]
+pub fn set_console_size(handle: u64, size: ConsoleSize) -> Result<ConsoleSize> {
+    control(handle, CONTROL_SET_CONSOLE_SIZE, size)
+}
+
+fn control(handle: u64, request: u64, mut size: ConsoleSize) -> Result<ConsoleSize> {
+    let size_ptr = &mut size as *mut ConsoleSize as u64;
+    let error = unsafe { syscall!(idx::IDX_FILE_CONTROL, handle, request, size_ptr) };
+    SyscallError::to_result(error).map(|_| size)
+}
@@ -0,0 +1,217 @@
+//! 图形模式
+//!
+//! 以UEFI启动时，固件提供线性帧缓冲区。程序在后台缓冲区中绘制，再通过 [present] 将需要更新的区域
+//! 输出到屏幕，避免绘制过程中的画面撕裂。通常应使用 [Surface]，它封装了后台缓冲区与常用的绘制操作。
+//!
+//! 像素为 `0x00RRGGBB` 格式的u32，由内核转换为帧缓冲区的像素格式。使用图形模式需要 `graphics` 能力
+
+use core::{mem::MaybeUninit, ptr::NonNull, slice};
+
+use crate::{
+    error::{Result, SyscallError},
+    idx, memory, syscall,
+};
+
+/// 图形模式信息，由 [mode_info] 返回
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ModeInfo {
+    /// 屏幕宽度（像素）
+    pub width: u32,
+    /// 屏幕高度（像素）
+    pub height: u32,
+}
+
+impl ModeInfo {
+    /// 后台缓冲区的像素数量
+    pub const fn pixels(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// 后台缓冲区占用的内存页数量
+    pub const fn pages(&self) -> u64 {
+        (self.pixels() * 4).div_ceil(0x1000) as u64
+    }
+}
+
+/// 矩形区域，左上角为 (x, y)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub const fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// 与宽高为width、height的区域的交集，没有交集时宽高为0
+    pub fn clip(&self, width: u32, height: u32) -> Self {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        Self {
+            x,
+            y,
+            width: self.width.min(width - x),
+            height: self.height.min(height - y),
+        }
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
+/// 由红、绿、蓝分量组成像素
+pub const fn rgb(r: u8, g: u8, b: u8) -> u32 {
+    ((r as u32) << 16) | ((g as u32) << 8) | b as u32
+}
+
+/// 获取图形模式信息
+///
+/// 以VGA文本模式启动、没有帧缓冲区时返回NotSupported
+pub fn mode_info() -> Result<ModeInfo> {
+    let mut info = MaybeUninit::<ModeInfo>::uninit();
+    let info_ptr = info.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_GFX_MODE_INFO, info_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { info.assume_init() })
+}
+
+/// 申请后台缓冲区，返回缓冲区的地址
+///
+/// 缓冲区为 [ModeInfo::pages] 个连续的内存页，按行存放 [ModeInfo::pixels] 个像素，
+/// 不再使用时以 [memory::free_page] 释放
+pub fn map_buffer() -> Result<NonNull<u32>> {
+    let mut addr = MaybeUninit::<u64>::uninit();
+    let addr_ptr = addr.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_GFX_MAP_BUFFER, addr_ptr) };
+    SyscallError::to_result(error)
+        .map(|_| unsafe { NonNull::new_unchecked(addr.assume_init() as *mut u32) })
+}
+
+/// 将后台缓冲区中rect指定的区域输出到屏幕
+///
+/// buffer按行存放整个屏幕的像素，长度须为 [ModeInfo::pixels]。超出屏幕的区域被忽略
+pub fn present(buffer: &[u32], rect: Rect) -> Result<()> {
+    let buffer_ptr = buffer.as_ptr() as u64;
+    let buffer_len = buffer.len() as u64;
+    let rect_ptr = &raw const rect as u64;
+    let error = unsafe { syscall!(idx::IDX_GFX_PRESENT, buffer_ptr, buffer_len, rect_ptr) };
+    SyscallError::to_result(error)
+}
+
+/// 双缓冲的绘制表面
+///
+/// 绘制操作只修改后台缓冲区，调用 [Surface::present] 后才显示。超出屏幕的部分被裁剪
+pub struct Surface {
+    info: ModeInfo,
+    buffer: NonNull<u32>,
+}
+
+impl Surface {
+    /// 申请与屏幕大小相同的后台缓冲区
+    pub fn new() -> Result<Self> {
+        let info = mode_info()?;
+        let buffer = map_buffer()?;
+        let mut surface = Self { info, buffer };
+        surface.clear(0);
+        Ok(surface)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.info.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.info.height
+    }
+
+    /// 后台缓冲区的全部像素，按行存放
+    pub fn pixels(&self) -> &[u32] {
+        // Safety: 缓冲区由map_buffer申请，至少包含pixels个像素，在Surface释放前有效
+        unsafe { slice::from_raw_parts(self.buffer.as_ptr(), self.info.pixels()) }
+    }
+
+    /// 后台缓冲区的全部像素，按行存放
+    pub fn pixels_mut(&mut self) -> &mut [u32] {
+        // Safety: 同pixels，且通过&mut self保证独占
+        unsafe { slice::from_raw_parts_mut(self.buffer.as_ptr(), self.info.pixels()) }
+    }
+
+    /// 以color填充整个表面
+    pub fn clear(&mut self, color: u32) {
+        self.pixels_mut().fill(color);
+    }
+
+    /// 设置 (x, y) 处的像素
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: u32) {
+        if x < self.info.width && y < self.info.height {
+            let index = y as usize * self.info.width as usize + x as usize;
+            self.pixels_mut()[index] = color;
+        }
+    }
+
+    /// 以color填充矩形区域
+    pub fn fill_rect(&mut self, rect: Rect, color: u32) {
+        let rect = rect.clip(self.info.width, self.info.height);
+        let width = self.info.width as usize;
+        for y in rect.y..rect.y + rect.height {
+            let start = y as usize * width + rect.x as usize;
+            self.pixels_mut()[start..start + rect.width as usize].fill(color);
+        }
+    }
+
+    /// 以color绘制矩形的边框，边框宽度为1像素
+    pub fn draw_rect(&mut self, rect: Rect, color: u32) {
+        if rect.is_empty() {
+            return;
+        }
+        let right = rect.x.saturating_add(rect.width - 1);
+        let bottom = rect.y.saturating_add(rect.height - 1);
+        self.fill_rect(Rect::new(rect.x, rect.y, rect.width, 1), color);
+        self.fill_rect(Rect::new(rect.x, bottom, rect.width, 1), color);
+        self.fill_rect(Rect::new(rect.x, rect.y, 1, rect.height), color);
+        self.fill_rect(Rect::new(right, rect.y, 1, rect.height), color);
+    }
+
+    /// 将宽为width的图像复制到 (x, y) 处，image按行存放像素
+    pub fn blit(&mut self, x: u32, y: u32, width: u32, image: &[u32]) {
+        if width == 0 {
+            return;
+        }
+        let height = (image.len() / width as usize) as u32;
+        let rect = Rect::new(x, y, width, height).clip(self.info.width, self.info.height);
+        let stride = self.info.width as usize;
+        for row in 0..rect.height {
+            let src = row as usize * width as usize;
+            let dst = (rect.y + row) as usize * stride + rect.x as usize;
+            let len = rect.width as usize;
+            self.pixels_mut()[dst..dst + len].copy_from_slice(&image[src..src + len]);
+        }
+    }
+
+    /// 将整个表面输出到屏幕
+    pub fn present(&self) -> Result<()> {
+        self.present_rect(Rect::new(0, 0, self.info.width, self.info.height))
+    }
+
+    /// 将表面中rect指定的区域输出到屏幕，只更新变化的区域时比输出整个表面更快
+    pub fn present_rect(&self, rect: Rect) -> Result<()> {
+        present(self.pixels(), rect)
+    }
+}
+
+impl Drop for Surface {
+    fn drop(&mut self) {
+        _ = unsafe { memory::free_page(self.buffer.cast(), self.info.pages()) };
+    }
+}
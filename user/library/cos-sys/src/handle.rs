@@ -0,0 +1,48 @@
+use core::mem::ManuallyDrop;
+
+use crate::error::Result;
+
+/// 拥有所有权的句柄
+///
+/// 句柄被释放时自动关闭，关闭失败的错误会被忽略。需要得知关闭结果时调用 [OwnedHandle::close]。
+///
+/// [crate::file::File]、[crate::multitask::Process] 及网络套接字等类型均基于此类型实现。
+/// 直接返回 u64 句柄的函数（如 [crate::file::open]）是底层接口，其句柄需手动关闭，
+/// 可以使用 [OwnedHandle::from_raw] 接管其所有权。
+#[derive(Debug)]
+pub struct OwnedHandle {
+    handle: u64,
+}
+
+impl OwnedHandle {
+    /// 接管句柄的所有权
+    ///
+    /// # Safety
+    ///
+    /// handle 必须是当前进程中打开的句柄，且不能被其他所有者关闭，
+    /// 否则释放时可能关闭此后重新分配的、属于其他所有者的同值句柄
+    pub const unsafe fn from_raw(handle: u64) -> Self {
+        Self { handle }
+    }
+
+    /// 句柄的值，所有权不变
+    pub const fn as_raw(&self) -> u64 {
+        self.handle
+    }
+
+    /// 放弃所有权并返回句柄的值，句柄不会被关闭
+    pub fn into_raw(self) -> u64 {
+        ManuallyDrop::new(self).handle
+    }
+
+    /// 关闭句柄并返回关闭的结果
+    pub fn close(self) -> Result<()> {
+        crate::file::close(self.into_raw())
+    }
+}
+
+impl Drop for OwnedHandle {
+    fn drop(&mut self) {
+        _ = crate::file::close(self.handle);
+    }
+}
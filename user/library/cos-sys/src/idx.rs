@@ -1,13 +1,57 @@
 pub const IDX_DEBUG_INFO: u64 = 0x1F00001;
 pub const IDX_DEBUG_GET_CHAR: u64 = 0x1F00002;
 pub const IDX_DEBUG_PUT_CHAR: u64 = 0x1F00003;
+/// 打开键盘句柄
+///
+/// 函数封装为 [crate::debug::open_keyboard]
+pub const IDX_DEBUG_OPEN_KEYBOARD: u64 = 0x1F00004;
+/// 列出当前进程的全部句柄
+///
+/// 函数封装为 [crate::debug::list_handles]
+pub const IDX_DEBUG_LIST_HANDLES: u64 = 0x1F00005;
+/// 向串口写入数据
+///
+/// 函数封装为 [crate::debug::serial_write]
+pub const IDX_DEBUG_SERIAL_WRITE: u64 = 0x1F00006;
+/// 通过qemu的isa-debug-exit设备退出模拟器
+///
+/// 函数封装为 [crate::debug::exit_emulator]
+pub const IDX_DEBUG_EXIT_EMULATOR: u64 = 0x1F00007;
+/// 列出进程的内存区域
+///
+/// 函数封装为 [crate::debug::memory_maps]
+pub const IDX_DEBUG_MEMORY_MAPS: u64 = 0x1F00008;
+/// 设置进程的系统调用跟踪选项
+///
+/// 函数封装为 [crate::debug::trace_process]
+pub const IDX_DEBUG_TRACE_PROCESS: u64 = 0x1F00009;
+/// 取出系统调用跟踪记录
+///
+/// 函数封装为 [crate::debug::read_trace]
+pub const IDX_DEBUG_READ_TRACE: u64 = 0x1F0000A;
+/// 设置内核日志目标的过滤级别
+///
+/// 函数封装为 [crate::debug::set_log_filter]
+pub const IDX_DEBUG_SET_LOG_FILTER: u64 = 0x1F0000B;
+/// 向控制台输出一段字节
+///
+/// 函数封装为 [crate::debug::put_str]
+pub const IDX_DEBUG_PUT_STR: u64 = 0x1F0000C;
+/// 获取内核自旋锁的统计
+///
+/// 函数封装为 [crate::debug::lock_stats]
+pub const IDX_DEBUG_LOCK_STATS: u64 = 0x1F0000D;
 
 /// 退出当前进程
 ///
+/// 编号与参数永不改变
+///
 /// 函数封装为 [crate::multitask::exit]
 pub const IDX_EXIT_PROCESS: u64 = 0x100001;
 /// 退出当前线程
 ///
+/// 编号与参数永不改变
+///
 /// 函数封装为 [crate::multitask::exit_thread]
 pub const IDX_EXIT_THREAD: u64 = 0x100002;
 
@@ -39,6 +83,10 @@ pub const IDX_THREAD_JOIN: u64 = 0x200006;
 ///
 /// 函数封装为 [crate::multitask::sleep_thread]
 pub const IDX_THREAD_SLEEP: u64 = 0x200007;
+/// 设置线程局部存储基址
+///
+/// 函数封装为 [crate::multitask::set_tls_base]
+pub const IDX_THREAD_SET_TLS_BASE: u64 = 0x200008;
 
 /// 申请内存页，内存页默认为可读写不可执行
 ///
@@ -48,6 +96,18 @@ pub const IDX_MEMORY_ALLOC: u64 = 0x300001;
 ///
 /// 函数封装为 [crate::memory::free_page]
 pub const IDX_MEMORY_FREE: u64 = 0x300002;
+/// 获取系统内存使用情况
+///
+/// 函数封装为 [crate::memory::memory_stats]
+pub const IDX_MEMORY_STATS: u64 = 0x300003;
+/// 在建议的地址申请内存页
+///
+/// 函数封装为 [crate::memory::alloc_page_at]
+pub const IDX_MEMORY_ALLOC_AT: u64 = 0x300004;
+/// 原地扩展或缩小内存页
+///
+/// 函数封装为 [crate::memory::remap_page]
+pub const IDX_MEMORY_REMAP: u64 = 0x300005;
 
 /// 获取当前进程
 ///
@@ -65,6 +125,58 @@ pub const IDX_PROCESS_KILL: u64 = 0x400003;
 ///
 /// 函数封装为 [crate::multitask::wait_process]
 pub const IDX_PROCESS_WAIT: u64 = 0x400004;
+/// 设置进程资源限制
+///
+/// 函数封装为 [crate::multitask::set_process_limit]
+pub const IDX_PROCESS_SET_LIMIT: u64 = 0x400005;
+/// 列出全部进程
+///
+/// 函数封装为 [crate::multitask::list_processes]
+pub const IDX_PROCESS_LIST: u64 = 0x400006;
+/// 获取进程信息
+///
+/// 函数封装为 [crate::multitask::process_info]
+pub const IDX_PROCESS_INFO: u64 = 0x400007;
+/// 获取系统CPU时间
+///
+/// 函数封装为 [crate::multitask::cpu_times]
+pub const IDX_PROCESS_CPU_TIMES: u64 = 0x400008;
+/// 创建进程并传递启动参数
+///
+/// 函数封装为 [crate::multitask::create_process_with_args]
+pub const IDX_PROCESS_CREATE_WITH_ARGS: u64 = 0x400009;
+/// 获取当前进程的启动参数
+///
+/// 函数封装为 [crate::multitask::process_args]
+pub const IDX_PROCESS_ARGS: u64 = 0x40000A;
+/// 获取当前进程的用户与组
+///
+/// 函数封装为 [crate::multitask::credentials]
+pub const IDX_PROCESS_CREDENTIALS: u64 = 0x40000B;
+/// 修改当前进程的用户与组
+///
+/// 函数封装为 [crate::multitask::set_credentials]
+pub const IDX_PROCESS_SET_CREDENTIALS: u64 = 0x40000C;
+/// 获取进程资源限制
+///
+/// 函数封装为 [crate::multitask::get_process_limit]
+pub const IDX_PROCESS_GET_LIMIT: u64 = 0x40000D;
+/// 将进程移入进程组
+///
+/// 函数封装为 [crate::multitask::set_process_group]
+pub const IDX_PROCESS_SET_GROUP: u64 = 0x40000E;
+/// 创建新的会话
+///
+/// 函数封装为 [crate::multitask::create_session]
+pub const IDX_PROCESS_CREATE_SESSION: u64 = 0x40000F;
+/// 设置控制台的前台进程组
+///
+/// 函数封装为 [crate::multitask::set_foreground_group]
+pub const IDX_PROCESS_SET_FOREGROUND: u64 = 0x400010;
+/// 获取控制台的前台进程组
+///
+/// 函数封装为 [crate::multitask::foreground_group]
+pub const IDX_PROCESS_FOREGROUND: u64 = 0x400011;
 
 /// 创建文件
 ///
@@ -94,3 +206,203 @@ pub const IDX_FILE_SET_POS: u64 = 0x500006;
 ///
 /// 函数封装为 [crate::file::close]
 pub const IDX_FILE_CLOSE: u64 = 0x500007;
+/// 重新扫描分区表，并列出块设备
+///
+/// 函数封装为 [crate::file::list_block_devices]
+pub const IDX_FILE_LIST_BLOCK_DEVICES: u64 = 0x500008;
+/// 将块设备上的文件系统挂载到指定路径
+///
+/// 函数封装为 [crate::file::mount]
+pub const IDX_FILE_MOUNT: u64 = 0x500009;
+/// 卸载指定路径上的文件系统
+///
+/// 函数封装为 [crate::file::unmount]
+pub const IDX_FILE_UNMOUNT: u64 = 0x50000A;
+/// 列出文件夹中的内容
+///
+/// 函数封装为 [crate::file::list_directory]
+pub const IDX_FILE_LIST_DIRECTORY: u64 = 0x50000B;
+/// 截断文件
+///
+/// 函数封装为 [crate::file::truncate]
+pub const IDX_FILE_TRUNCATE: u64 = 0x50000C;
+/// 删除文件
+///
+/// 函数封装为 [crate::file::delete]
+pub const IDX_FILE_DELETE: u64 = 0x50000D;
+/// 重命名或移动文件、文件夹
+///
+/// 函数封装为 [crate::file::rename]
+pub const IDX_FILE_RENAME: u64 = 0x50000E;
+/// 获取文件或文件夹的信息
+///
+/// 函数封装为 [crate::file::metadata]
+pub const IDX_FILE_METADATA: u64 = 0x50000F;
+/// 检查校验块设备上的全部数据
+///
+/// 函数封装为 [crate::file::scrub]
+pub const IDX_FILE_SCRUB: u64 = 0x500010;
+/// 修改文件的权限位
+///
+/// 函数封装为 [crate::file::chmod]
+pub const IDX_FILE_CHMOD: u64 = 0x500011;
+/// 修改文件的所有者
+///
+/// 函数封装为 [crate::file::chown]
+pub const IDX_FILE_CHOWN: u64 = 0x500012;
+/// 对句柄执行设备相关的控制请求，如查询控制台大小
+///
+/// 函数封装为 [crate::file::console_size] 等
+pub const IDX_FILE_CONTROL: u64 = 0x500013;
+/// 以口令打开块设备上的加密卷，并挂载其中的文件系统
+///
+/// 函数封装为 [crate::file::mount_encrypted]
+pub const IDX_FILE_MOUNT_ENCRYPTED: u64 = 0x500014;
+
+/// 提交异步请求
+///
+/// 函数封装为 [crate::completion::submit]
+pub const IDX_COMPLETION_SUBMIT: u64 = 0x600001;
+/// 取出已完成的异步请求，不挂起线程
+///
+/// 函数封装为 [crate::completion::poll]
+pub const IDX_COMPLETION_POLL: u64 = 0x600002;
+/// 等待并取出已完成的异步请求
+///
+/// 函数封装为 [crate::completion::wait]
+pub const IDX_COMPLETION_WAIT: u64 = 0x600003;
+
+/// 等待多个句柄中的任意一个就绪
+///
+/// 函数封装为 [crate::ipc::poll]
+pub const IDX_IPC_POLL: u64 = 0x700001;
+
+/// 读取内核命令行
+///
+/// 函数封装为 [crate::system::cmdline]
+pub const IDX_SYSTEM_CMDLINE: u64 = 0x800001;
+
+/// 关机
+///
+/// 函数封装为 [crate::system::shutdown]
+pub const IDX_SYSTEM_SHUTDOWN: u64 = 0x800002;
+
+/// 重启
+///
+/// 函数封装为 [crate::system::reboot]
+pub const IDX_SYSTEM_REBOOT: u64 = 0x800003;
+
+/// 查询内核的ABI版本与功能位
+///
+/// 编号与参数永不改变
+///
+/// 函数封装为 [crate::abi::info]
+pub const IDX_SYSTEM_ABI_INFO: u64 = 0x800004;
+
+/// 安装新的内核镜像到另一个内核槽位，下次启动时生效
+///
+/// 函数封装为 [crate::system::update_kernel]
+pub const IDX_SYSTEM_UPDATE_KERNEL: u64 = 0x800005;
+
+/// 绑定UDP端口，创建UDP套接字
+///
+/// 函数封装为 [crate::net::UdpSocket::bind]
+pub const IDX_NET_UDP_BIND: u64 = 0x900001;
+
+/// 通过UDP套接字发送数据报
+///
+/// 函数封装为 [crate::net::UdpSocket::send_to]
+pub const IDX_NET_UDP_SEND_TO: u64 = 0x900002;
+
+/// 通过UDP套接字接收数据报
+///
+/// 函数封装为 [crate::net::UdpSocket::recv_from]
+pub const IDX_NET_UDP_RECV_FROM: u64 = 0x900003;
+
+/// 连接到TCP服务端，创建TCP流
+///
+/// 函数封装为 [crate::net::TcpStream::connect]
+pub const IDX_NET_TCP_CONNECT: u64 = 0x900004;
+
+/// 监听TCP端口，创建TCP监听套接字
+///
+/// 函数封装为 [crate::net::TcpListener::bind]
+pub const IDX_NET_TCP_LISTEN: u64 = 0x900005;
+
+/// 接受一个TCP连接
+///
+/// 函数封装为 [crate::net::TcpListener::accept]
+pub const IDX_NET_TCP_ACCEPT: u64 = 0x900006;
+
+/// 从TCP流读取数据
+///
+/// 函数封装为 [crate::net::TcpStream::read]
+pub const IDX_NET_TCP_READ: u64 = 0x900007;
+
+/// 向TCP流写入数据
+///
+/// 函数封装为 [crate::net::TcpStream::write]
+pub const IDX_NET_TCP_WRITE: u64 = 0x900008;
+
+/// 关闭TCP流的发送方向
+///
+/// 函数封装为 [crate::net::TcpStream::shutdown]
+pub const IDX_NET_TCP_SHUTDOWN: u64 = 0x900009;
+
+/// 获取随机数据
+///
+/// 函数封装为 [crate::random::fill]
+pub const IDX_RANDOM_GET: u64 = 0xA00001;
+
+/// 获取系统时间
+///
+/// 函数封装为 [crate::time::SystemTime::now]
+pub const IDX_TIME_GET: u64 = 0xB00001;
+/// 获取单调时钟的时刻
+///
+/// 函数封装为 [crate::time::Instant::now]
+pub const IDX_TIME_MONOTONIC: u64 = 0xB00002;
+/// 获取本地时区相对UTC的偏移
+///
+/// 函数封装为 [crate::time::utc_offset]
+pub const IDX_TIME_UTC_OFFSET: u64 = 0xB00003;
+
+/// 获取图形模式信息
+///
+/// 函数封装为 [crate::gfx::mode_info]
+pub const IDX_GFX_MODE_INFO: u64 = 0xC00001;
+/// 申请与屏幕大小相同的后台缓冲区
+///
+/// 函数封装为 [crate::gfx::map_buffer]
+pub const IDX_GFX_MAP_BUFFER: u64 = 0xC00002;
+/// 将后台缓冲区的区域输出到屏幕
+///
+/// 函数封装为 [crate::gfx::present]
+pub const IDX_GFX_PRESENT: u64 = 0xC00003;
+
+/// 取出输入事件，不会挂起线程
+///
+/// 函数封装为 [crate::input::poll]
+pub const IDX_INPUT_POLL: u64 = 0xD00001;
+/// 取出输入事件，没有事件时挂起线程
+///
+/// 函数封装为 [crate::input::wait]
+pub const IDX_INPUT_WAIT: u64 = 0xD00002;
+
+/// 以PC扬声器发声
+///
+/// 函数封装为 [crate::sound::beep]
+pub const IDX_SOUND_BEEP: u64 = 0xE00001;
+
+/// 加载内核模块
+///
+/// 函数封装为 [crate::module::load]
+pub const IDX_MODULE_LOAD: u64 = 0xF00001;
+/// 卸载内核模块
+///
+/// 函数封装为 [crate::module::unload]
+pub const IDX_MODULE_UNLOAD: u64 = 0xF00002;
+/// 列出已加载的内核模块
+///
+/// 函数封装为 [crate::module::list]
+pub const IDX_MODULE_LIST: u64 = 0xF00003;
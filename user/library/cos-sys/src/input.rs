@@ -0,0 +1,73 @@
+//! 原始输入事件
+//!
+//! 键盘与鼠标产生的事件进入同一个队列，按发生的顺序读取，适用于需要按键释放、鼠标等
+//! 字符输入（[crate::debug::get_char]）无法表达的信息的图形与TUI程序。
+//! 每个控制台具有独立的事件队列，事件发送到正在显示的控制台，进程读取所在控制台的事件。
+//! 队列已满时新的事件被丢弃。队列中可能留有程序启动前产生的事件，需要时可先以 [poll] 取出并丢弃。
+//! 读取事件需要 `raw-console` 能力。控制台设置了前台进程组（见 [crate::multitask::set_foreground_group]）时，
+//! 只有前台进程组中的进程可以读取，其他进程读取时返回 [crate::error::ErrorKind::PermissionDenied]；
+//! 事件只发送给产生时的前台进程组，之后切换到前台的进程组不会读取到此前的事件
+
+use core::mem::MaybeUninit;
+
+use crate::{
+    error::{Result, SyscallError},
+    idx, syscall,
+};
+
+/// 按键事件
+pub const EVENT_KEY: u32 = 1;
+/// 鼠标移动事件
+pub const EVENT_MOUSE_MOVE: u32 = 2;
+/// 鼠标按键事件
+pub const EVENT_MOUSE_BUTTON: u32 = 3;
+
+/// 鼠标左键
+pub const BUTTON_LEFT: u32 = 0;
+/// 鼠标右键
+pub const BUTTON_RIGHT: u32 = 1;
+/// 鼠标中键
+pub const BUTTON_MIDDLE: u32 = 2;
+
+/// 扩展键的扫描码前缀，如右Ctrl的扫描码为 `EXTENDED_KEY | 0x1D`
+pub const EXTENDED_KEY: u32 = 0xE000;
+
+/// 输入事件
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputEvent {
+    /// 事件类型，如 [EVENT_KEY]
+    pub kind: u32,
+    /// 按键事件为PS/2扫描码（第一套，不含释放标志位），鼠标按键事件为按键编号，如 [BUTTON_LEFT]
+    pub code: u32,
+    /// 按键事件与鼠标按键事件中，1为按下，0为释放
+    pub pressed: u32,
+    /// 鼠标移动事件中，向右的位移
+    pub dx: i32,
+    /// 鼠标移动事件中，向下的位移
+    pub dy: i32,
+}
+
+/// 取出输入事件，不会挂起线程
+///
+/// 最多取出 events.len() 个事件，返回实际取出的数量。
+/// 如果没有输入事件，返回 [crate::error::ErrorKind::WouldBlock]
+pub fn poll(events: &mut [InputEvent]) -> Result<usize> {
+    take(idx::IDX_INPUT_POLL, events)
+}
+
+/// 取出输入事件
+///
+/// 与 [poll] 相同，但没有输入事件时会挂起当前线程，直到至少产生一个事件
+pub fn wait(events: &mut [InputEvent]) -> Result<usize> {
+    take(idx::IDX_INPUT_WAIT, events)
+}
+
+fn take(id: u64, events: &mut [InputEvent]) -> Result<usize> {
+    let events_ptr = events.as_mut_ptr() as u64;
+    let capacity = events.len() as u64;
+    let mut count = MaybeUninit::<u64>::uninit();
+    let count_ptr = count.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(id, events_ptr, capacity, count_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { count.assume_init() as usize })
+}
@@ -0,0 +1,43 @@
+use crate::error::{ErrorKind, Result, SyscallError};
+
+/// 可读取字节的对象，如 [crate::file::File]、[crate::net::TcpStream]
+pub trait Read {
+    /// 读取数据，返回读取的长度
+    ///
+    /// 返回0表示已到达末尾（如文件结尾、对端关闭连接），或`buf`为空
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// 读取数据直至填满`buf`
+    ///
+    /// 填满前到达末尾时返回 [ErrorKind::IoError]，此时`buf`中已读取的内容不确定
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            let len = self.read(buf)?;
+            if len == 0 {
+                return Err(SyscallError::new(ErrorKind::IoError as u64).unwrap());
+            }
+            buf = &mut buf[len..];
+        }
+        Ok(())
+    }
+}
+
+/// 可写入字节的对象，如 [crate::file::File]、[crate::net::TcpStream]
+pub trait Write {
+    /// 写入数据，返回写入的长度，写入的长度可能小于`buf`的长度
+    fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+    /// 写入全部数据
+    ///
+    /// 无法继续写入（写入长度为0）时返回 [ErrorKind::IoError]
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            let len = self.write(buf)?;
+            if len == 0 {
+                return Err(SyscallError::new(ErrorKind::IoError as u64).unwrap());
+            }
+            buf = &buf[len..];
+        }
+        Ok(())
+    }
+}
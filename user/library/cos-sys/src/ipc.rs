@@ -0,0 +1,67 @@
+//! 进程间通信与事件等待
+
+use core::{mem::MaybeUninit, time::Duration};
+
+use crate::{
+    error::{Result, SyscallError},
+    idx, syscall,
+};
+
+/// 句柄可读，适用于文件与键盘
+pub const POLL_READ: u64 = 1 << 0;
+/// 句柄可写，适用于文件
+pub const POLL_WRITE: u64 = 1 << 1;
+/// 进程或线程已退出
+pub const POLL_EXIT: u64 = 1 << 2;
+/// 句柄无效，仅出现在 [PollFd::revents] 中，无需在 [PollFd::events] 中指定
+pub const POLL_INVALID: u64 = 1 << 3;
+/// 控制台大小或显示模式发生变化（如修改大小，或切换到该控制台显示），适用于控制台句柄，
+/// 见 [crate::file::console_size]
+///
+/// 每次变化只报告一次，全屏程序收到后应重新查询大小并重新绘制
+pub const POLL_RESIZE: u64 = 1 << 4;
+
+/// 表示无限等待的超时时间
+const TIMEOUT_INFINITE: u64 = u64::MAX;
+
+/// 等待的句柄及事件
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PollFd {
+    /// 句柄
+    pub handle: u64,
+    /// 关注的事件，如 [POLL_READ]
+    pub events: u64,
+    /// 已就绪的事件，由内核写入
+    pub revents: u64,
+}
+
+impl PollFd {
+    pub const fn new(handle: u64, events: u64) -> Self {
+        Self {
+            handle,
+            events,
+            revents: 0,
+        }
+    }
+}
+
+/// 等待多个句柄中的任意一个就绪
+///
+/// 内核会将每个句柄已就绪的事件写入 [PollFd::revents]，返回存在就绪事件的句柄数量。
+/// 无效的句柄会被标记为 [POLL_INVALID] 并计入就绪数量，而不会使整个调用失败。
+///
+/// 如果在timeout时间内没有句柄就绪，返回0。timeout为[None]时无限等待，
+/// 为[Duration::ZERO]时仅检查当前状态，不会挂起线程。
+pub fn poll(fds: &mut [PollFd], timeout: Option<Duration>) -> Result<usize> {
+    let fds_ptr = fds.as_mut_ptr() as u64;
+    let fds_len = fds.len() as u64;
+    let timeout = match timeout {
+        Some(timeout) => timeout.as_nanos().min((TIMEOUT_INFINITE - 1) as u128) as u64,
+        None => TIMEOUT_INFINITE,
+    };
+    let mut ready = MaybeUninit::<u64>::uninit();
+    let ready_ptr = ready.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_IPC_POLL, fds_ptr, fds_len, timeout, ready_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { ready.assume_init() as usize })
+}
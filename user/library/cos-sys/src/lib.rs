@@ -5,6 +5,9 @@
 //!
 //! 对于应用程序而言，尽量避免直接使用 [syscall()] 函数，而是使用它们的封装版本。
 //! 直接使用 [syscall()] 容易出错且会丧失可读性。
+//!
+//! 返回 u64 句柄的封装函数是底层接口，句柄需调用 [file::close] 手动关闭。通常应使用
+//! [file::File]、[multitask::Process] 等类型，它们在释放时自动关闭句柄。文件与TCP流实现了 [io::Read]、[io::Write]。
 
 #![cfg(target_arch = "x86_64")]
 #![no_std]
@@ -13,11 +16,25 @@
 
 use core::arch::asm;
 
+pub mod abi;
+pub mod completion;
 pub mod error;
 pub mod file;
+pub mod gfx;
+pub mod handle;
 pub mod idx;
+pub mod input;
+pub mod io;
+pub mod ipc;
 pub mod memory;
+pub mod module;
 pub mod multitask;
+pub mod net;
+pub mod random;
+pub mod sound;
+pub mod system;
+pub mod time;
+pub mod vdso;
 
 pub mod debug;
 
@@ -69,10 +86,29 @@ pub mod debug;
 ///
 /// - 各系统调用文档中的其他未定义行为
 ///
+/// # ABI兼容性
+///
+/// 首次调用时会查询内核的ABI版本，内核与此库不兼容时，除少数冻结的系统调用外直接返回
+/// [error::ErrorKind::AbiMismatch]，不会陷入内核。版本规则与修改系统调用的约定见 [abi]。
+///
 /// # 对应用程序开发者
 ///
 /// 系统调用API是不稳定的，建议应用程序开发者不要通过硬编码syscall方式使用系统功能，而是使用系统的动态链接库。
 pub unsafe fn syscall(id: u64, p1: u64, p2: u64, p3: u64, p4: u64, p5: u64, p6: u64) -> u64 {
+    if let Some(error) = abi::check(id) {
+        return error;
+    }
+
+    // Safety: 见函数说明
+    unsafe { raw_syscall(id, p1, p2, p3, p4, p5, p6) }
+}
+
+/// 不经ABI兼容检查进行系统调用
+///
+/// # Safety
+///
+/// 与 [syscall()] 相同
+unsafe fn raw_syscall(id: u64, p1: u64, p2: u64, p3: u64, p4: u64, p5: u64, p6: u64) -> u64 {
     let ret;
 
     // Safety: 见函数说明
@@ -5,6 +5,45 @@ use crate::{
     idx, syscall,
 };
 
+/// 内核堆的大小类别数量
+pub const HEAP_SIZE_CLASS_COUNT: usize = 9;
+
+/// 系统内存使用情况，由 [memory_stats] 返回
+///
+/// 各进程的内存使用情况见 [crate::multitask::ProcessInfo]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryStats {
+    /// 系统可用内存总量（字节）
+    pub total_memory: u64,
+    /// 启动时保留的内存（字节），包括loader与内核映像
+    pub reserved_memory: u64,
+    /// 页帧分配器已分配的4K页帧数量
+    pub allocated_frames: u64,
+    /// 已回收、等待再次分配的4K页帧数量
+    pub free_list_frames: u64,
+    /// 内核堆各大小类别的使用情况，按大小升序排列
+    pub heap_classes: [HeapClassStats; HEAP_SIZE_CLASS_COUNT],
+    /// 内核堆中超过2K、直接以内存页分配的内存数量
+    pub heap_large_allocated: u64,
+    /// 内核堆直接分配的内存占用的内存页数量
+    pub heap_large_pages: u64,
+}
+
+/// 内核堆单个大小类别的使用情况
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeapClassStats {
+    /// 内存块大小（字节）
+    pub size: u64,
+    /// 已分配的内存块数量
+    pub allocated: u64,
+    /// 已持有的内存页中空闲的内存块数量
+    pub free: u64,
+    /// 持有的内存页数量
+    pub pages: u64,
+}
+
 /// 申请内存页
 ///
 /// 如果可用空间充足，将分配连续可读写的内存页。内存页大小为4K。
@@ -24,8 +63,47 @@ pub fn alloc_page(count: u64) -> Result<NonNull<u8>> {
 /// 将指定内存页释放并归还系统。归还后的内存页仍有可能被 [alloc_page] 申请。
 /// 系统将从ptr所在的内存页开始，回收count数量的内存页。这意味着程序可以先申请大空间，
 /// 然后多次分批回收。
+///
+/// # Safety
+///
+/// 回收的内存页此后不能再被访问
 pub unsafe fn free_page(ptr: NonNull<u8>, count: u64) -> Result {
     let ptr = ptr.as_ptr() as u64;
     let error = unsafe { syscall!(idx::IDX_MEMORY_FREE, ptr, count) };
     SyscallError::to_result(error)
 }
+
+/// 在建议的地址申请内存页
+///
+/// 与 [alloc_page] 相同，但优先将内存页分配在hint所在的内存页，便于分配器将多次申请的内存页
+/// 拼接为连续区域。hint处的内存已被占用或不在用户空间时，由内核选址，因此调用方需检查返回的地址
+pub fn alloc_page_at(count: u64, hint: *mut u8) -> Result<NonNull<u8>> {
+    let mut addr = MaybeUninit::<u64>::uninit();
+    let addr_ptr = addr.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_MEMORY_ALLOC_AT, count, hint as u64, addr_ptr) };
+    SyscallError::to_result(error)
+        .map(|_| unsafe { NonNull::new_unchecked(addr.assume_init() as *mut u8) })
+}
+
+/// 原地扩展或缩小内存页
+///
+/// 将从ptr开始的old_count个内存页调整为new_count个，ptr保持不变。扩展时原区域必须已映射，
+/// 若其后的内存已被占用则返回 [crate::error::ErrorKind::Occupied]，此时原区域不受影响；
+/// 缩小时释放末尾的内存页，与 [free_page] 相同
+///
+/// # Safety
+///
+/// 缩小时被释放的内存页此后不能再被访问
+pub unsafe fn remap_page(ptr: NonNull<u8>, old_count: u64, new_count: u64) -> Result {
+    let ptr = ptr.as_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_MEMORY_REMAP, ptr, old_count, new_count) };
+    SyscallError::to_result(error)
+}
+
+/// 获取系统内存使用情况，包括页帧分配器与内核堆
+pub fn memory_stats() -> Result<MemoryStats> {
+    let mut stats = MaybeUninit::<MemoryStats>::uninit();
+    let stats_ptr = stats.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_MEMORY_STATS, stats_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { stats.assume_init() })
+}
@@ -0,0 +1,76 @@
+use core::mem::MaybeUninit;
+
+use crate::{
+    error::{Result, SyscallError},
+    idx, syscall,
+};
+
+/// 模块名的最大长度
+pub const MODULE_NAME_LEN: usize = 32;
+
+/// 内核模块信息，由 [list] 返回
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ModuleInfo {
+    /// 模块名，长度为 [ModuleInfo::name_len]
+    pub name: [u8; MODULE_NAME_LEN],
+    pub name_len: u64,
+    /// 模块在内核空间中的起始地址，调用者不是超级用户或特权进程时为0
+    pub address: u64,
+    /// 模块占用的内存（字节）
+    pub size: u64,
+}
+
+impl ModuleInfo {
+    /// 模块名
+    pub fn name(&self) -> &[u8] {
+        &self.name[..(self.name_len as usize).min(MODULE_NAME_LEN)]
+    }
+}
+
+/// 加载 `/system/drivers/<name>.ko` 并调用其初始化函数
+///
+/// 只有超级用户可以加载模块，否则返回 [crate::error::ErrorKind::PermissionDenied]；
+/// 签名不正确时同样返回 [crate::error::ErrorKind::PermissionDenied]。
+/// 内核构建时未提供验证签名的公钥时返回 [crate::error::ErrorKind::NotSupported]；
+/// 同名模块已加载时返回 [crate::error::ErrorKind::FileExists]；
+/// 模块格式错误或引用了内核未导出的符号时返回 [crate::error::ErrorKind::Corrupted]；
+/// 初始化函数失败时返回 [crate::error::ErrorKind::IoError]
+pub fn load(name: &str) -> Result<()> {
+    let error = unsafe {
+        syscall!(
+            idx::IDX_MODULE_LOAD,
+            name.as_ptr() as u64,
+            name.len() as u64
+        )
+    };
+    SyscallError::to_result(error)
+}
+
+/// 调用模块的退出函数并卸载模块
+///
+/// 只有超级用户可以卸载模块，模块未加载时返回 [crate::error::ErrorKind::FileNotFound]
+pub fn unload(name: &str) -> Result<()> {
+    let error = unsafe {
+        syscall!(
+            idx::IDX_MODULE_UNLOAD,
+            name.as_ptr() as u64,
+            name.len() as u64
+        )
+    };
+    SyscallError::to_result(error)
+}
+
+/// 列出已加载的模块
+///
+/// 最多写入 modules.len() 个模块，返回模块的总数。
+/// 只有超级用户与特权进程可以看到模块的地址。
+/// 如果返回值大于 modules.len()，说明缓冲区不足，可以扩大缓冲区后重试
+pub fn list(modules: &mut [ModuleInfo]) -> Result<usize> {
+    let modules_ptr = modules.as_mut_ptr() as u64;
+    let modules_len = modules.len() as u64;
+    let mut count = MaybeUninit::<u64>::uninit();
+    let count_ptr = count.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_MODULE_LIST, modules_ptr, modules_len, count_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { count.assume_init() as usize })
+}
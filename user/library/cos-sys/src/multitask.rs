@@ -1,12 +1,96 @@
-use core::{mem::MaybeUninit, ptr::NonNull};
+use core::{arch::asm, mem::MaybeUninit, ptr::NonNull};
 
 use crate::{
     error::{Result, SyscallError},
+    handle::OwnedHandle,
     idx, syscall,
 };
 
 pub const EXIT_SUCCESS: u64 = 0;
 pub const EXIT_KILL: u64 = 1;
+/// 进程所在的进程组在前台时，被Ctrl+C中断，见 [set_foreground_group]
+pub const EXIT_INTERRUPT: u64 = 130;
+
+/// 启动参数的最大总长度（字节）
+pub const MAX_ARGS_LEN: usize = 4096;
+
+/// 进程可用于系统调用缓冲区的内核内存总量（字节）
+pub const LIMIT_KERNEL_MEMORY: u64 = 1;
+/// 单次系统调用可传递的缓冲区最大长度（字节）
+pub const LIMIT_SYSCALL_BUFFER: u64 = 2;
+/// 同时进行中的异步请求数量，见 [crate::completion]
+pub const LIMIT_ASYNC_REQUESTS: u64 = 3;
+/// 进程可持有的句柄数量
+pub const LIMIT_HANDLES: u64 = 4;
+/// 进程可映射的用户内存页数量
+pub const LIMIT_PAGES: u64 = 5;
+/// 同时存在的子进程数量，达到限制时创建进程返回 [crate::error::ErrorKind::QuotaExceeded]
+pub const LIMIT_CHILDREN: u64 = 6;
+/// 进程可占用的CPU时间（us），超出时进程被强制停止，退出码为 [EXIT_KILL]
+pub const LIMIT_CPU_TIME: u64 = 7;
+/// 不限制，[LIMIT_PAGES]、[LIMIT_CHILDREN]、[LIMIT_CPU_TIME] 的默认值
+pub const LIMIT_UNLIMITED: u64 = u64::MAX;
+
+/// 进程正在运行
+pub const PROCESS_STATE_RUNNING: u64 = 1;
+/// 进程已设置退出码，正在停止其线程并回收资源
+pub const PROCESS_STATE_EXITING: u64 = 2;
+
+/// 进程信息，由 [process_info] 返回
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessInfo {
+    /// 进程ID
+    pub process_id: u64,
+    /// 父进程ID，0表示由内核直接创建
+    pub parent_id: u64,
+    /// 进程状态，如 [PROCESS_STATE_RUNNING]
+    pub state: u64,
+    /// 线程数量
+    pub thread_count: u64,
+    /// 持有的句柄数量
+    pub handle_count: u64,
+    /// 已用于系统调用缓冲区的内核内存（字节）
+    pub kernel_memory: u64,
+    /// 已映射的用户内存页数量，每页4KiB
+    pub resident_pages: u64,
+    /// 进程全部线程（包括已退出的线程）占用的CPU时间（微秒）
+    ///
+    /// 由计时器中断采样得到，是近似值
+    pub cpu_time: u64,
+    /// 进程组ID，见 [set_process_group]
+    pub process_group: u64,
+    /// 会话ID，见 [create_session]
+    pub session: u64,
+    /// 输入输出使用的控制台，0为VT1，子进程继承父进程的控制台
+    pub console: u64,
+}
+
+/// 系统CPU时间，由 [cpu_times] 返回
+///
+/// 两次采样的差值可用于计算CPU使用率：
+/// 进程CPU使用率为 [ProcessInfo::cpu_time] 的差值除以 [CpuTimes::total] 的差值
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CpuTimes {
+    /// 系统启动后经过的CPU时间（微秒）
+    pub total: u64,
+    /// 其中处于空闲状态的CPU时间（微秒）
+    pub idle: u64,
+}
+
+/// 超级用户的用户ID与组ID，不受文件权限限制
+pub const ROOT_ID: u32 = 0;
+
+/// 进程的用户与组，由 [credentials] 返回
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Credentials {
+    /// 用户ID
+    pub uid: u32,
+    /// 组ID
+    pub gid: u32,
+}
 
 /// 退出进程
 ///
@@ -156,7 +240,7 @@ pub fn current_process() -> Result<u64> {
 /// 指定一个可执行文件，将其加载为进程，创建主线程进入其入口点。
 /// 创建后的进程将作为当前进程的子进程。
 ///
-/// 如果成功，将返回其进程句柄
+/// 如果成功，将返回其进程句柄。底层接口，句柄需调用 [crate::file::close] 关闭，通常使用 [Process::spawn]
 pub fn create_process(exe: &str) -> Result<u64> {
     let exe_ptr = exe.as_ptr() as u64;
     let exe_len = exe.len() as u64;
@@ -166,6 +250,74 @@ pub fn create_process(exe: &str) -> Result<u64> {
     SyscallError::to_result(error).map(|_| unsafe { process_id.assume_init() })
 }
 
+/// 创建进程并传递启动参数
+///
+/// 与 [create_process] 相同，args为启动参数，每个参数以\0结尾，长度不能超过 [MAX_ARGS_LEN]。
+/// 新进程可通过 [process_args] 读取
+pub fn create_process_with_args(exe: &str, args: &[u8]) -> Result<u64> {
+    let exe_ptr = exe.as_ptr() as u64;
+    let exe_len = exe.len() as u64;
+    let args_ptr = args.as_ptr() as u64;
+    let args_len = args.len() as u64;
+    let mut process_id = MaybeUninit::<u64>::uninit();
+    let process_id_ptr = process_id.as_mut_ptr() as u64;
+    let error = unsafe {
+        syscall!(
+            idx::IDX_PROCESS_CREATE_WITH_ARGS,
+            exe_ptr,
+            exe_len,
+            args_ptr,
+            args_len,
+            process_id_ptr
+        )
+    };
+    SyscallError::to_result(error).map(|_| unsafe { process_id.assume_init() })
+}
+
+/// 获取当前进程的启动参数
+///
+/// 将启动参数写入buf，最多写入 buf.len() 字节，返回启动参数的总长度。
+/// 如果返回值大于 buf.len()，说明缓冲区不足，可使用 [split_args] 拆分为各个参数
+pub fn process_args(buf: &mut [u8]) -> Result<usize> {
+    let buf_ptr = buf.as_mut_ptr() as u64;
+    let buf_len = buf.len() as u64;
+    let mut len = MaybeUninit::<u64>::uninit();
+    let len_ptr = len.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_PROCESS_ARGS, buf_ptr, buf_len, len_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { len.assume_init() as usize })
+}
+
+/// 获取当前进程的用户与组
+///
+/// 子进程继承父进程的用户与组，由内核直接创建的进程为超级用户
+pub fn credentials() -> Result<Credentials> {
+    let mut credentials = MaybeUninit::<Credentials>::uninit();
+    let credentials_ptr = credentials.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_PROCESS_CREDENTIALS, credentials_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { credentials.assume_init() })
+}
+
+/// 修改当前进程的用户与组，之后创建的子进程同样使用新的用户与组
+///
+/// 超级用户可以切换为任意用户与组；其他用户只能切换为自身，否则返回
+/// [crate::error::ErrorKind::PermissionDenied]，因此放弃超级用户身份后无法恢复
+pub fn set_credentials(credentials: Credentials) -> Result<()> {
+    let error = unsafe {
+        syscall!(
+            idx::IDX_PROCESS_SET_CREDENTIALS,
+            credentials.uid as u64,
+            credentials.gid as u64
+        )
+    };
+    SyscallError::to_result(error)
+}
+
+/// 将启动参数拆分为各个参数，结尾缺少\0的参数同样被返回
+pub fn split_args(args: &[u8]) -> impl Iterator<Item = &[u8]> {
+    args.split_inclusive(|&byte| byte == 0)
+        .map(|arg| arg.strip_suffix(&[0]).unwrap_or(arg))
+}
+
 /// 强制停止进程
 ///
 /// 停止进程并清理其所有资源，并回收进程句柄
@@ -186,6 +338,192 @@ pub fn wait_process(process_handle: u64) -> Result<u64> {
     SyscallError::to_result(error).map(|_| unsafe { exit_code.assume_init() })
 }
 
+/// 设置进程资源限制
+///
+/// limit为资源类型，如 [LIMIT_KERNEL_MEMORY]、[LIMIT_SYSCALL_BUFFER]，value为新的限制值。
+/// 子进程创建时继承父进程的资源限制。
+///
+/// 特权进程可以任意调整限制；其他进程只能降低限制，否则返回 [crate::error::ErrorKind::PermissionDenied]。
+/// 超出限制的系统调用将返回 [crate::error::ErrorKind::QuotaExceeded]。
+pub fn set_process_limit(process_handle: u64, limit: u64, value: u64) -> Result<()> {
+    let error = unsafe { syscall!(idx::IDX_PROCESS_SET_LIMIT, process_handle, limit, value) };
+    SyscallError::to_result(error)
+}
+
+/// 获取进程资源限制，limit与 [set_process_limit] 相同
+pub fn get_process_limit(process_handle: u64, limit: u64) -> Result<u64> {
+    let mut value = MaybeUninit::<u64>::uninit();
+    let value_ptr = value.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_PROCESS_GET_LIMIT, process_handle, limit, value_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { value.assume_init() })
+}
+
+/// 子进程
+///
+/// 进程被释放时关闭句柄，进程本身不受影响，继续运行
+#[derive(Debug)]
+pub struct Process {
+    handle: OwnedHandle,
+}
+
+impl Process {
+    /// 创建进程，见 [create_process]
+    pub fn spawn(exe: &str) -> Result<Self> {
+        create_process(exe).map(Self::from_created)
+    }
+
+    /// 创建进程并传递启动参数，见 [create_process_with_args]
+    pub fn spawn_with_args(exe: &str, args: &[u8]) -> Result<Self> {
+        create_process_with_args(exe, args).map(Self::from_created)
+    }
+
+    fn from_created(handle: u64) -> Self {
+        Self {
+            // Safety: 句柄刚刚创建，没有其他所有者
+            handle: unsafe { OwnedHandle::from_raw(handle) },
+        }
+    }
+
+    /// 进程的句柄
+    pub fn handle(&self) -> u64 {
+        self.handle.as_raw()
+    }
+
+    /// 等待进程退出，并获取其退出码，见 [wait_process]
+    ///
+    /// 成功时句柄已被内核回收；失败时句柄随返回而关闭
+    pub fn wait(self) -> Result<u64> {
+        let exit_code = wait_process(self.handle())?;
+        _ = self.handle.into_raw();
+        Ok(exit_code)
+    }
+
+    /// 强制停止进程，见 [kill_process]
+    ///
+    /// 停止后仍可调用 [Process::wait] 获取退出码
+    pub fn kill(&self) -> Result<()> {
+        kill_process(self.handle())
+    }
+
+    /// 设置进程资源限制，见 [set_process_limit]
+    pub fn set_limit(&self, limit: u64, value: u64) -> Result<()> {
+        set_process_limit(self.handle(), limit, value)
+    }
+
+    /// 获取进程资源限制，见 [get_process_limit]
+    pub fn limit(&self, limit: u64) -> Result<u64> {
+        get_process_limit(self.handle(), limit)
+    }
+
+    /// 将进程移入进程组，见 [set_process_group]
+    pub fn set_group(&self, process_group: u64) -> Result<u64> {
+        set_process_group(self.handle(), process_group)
+    }
+}
+
+impl From<OwnedHandle> for Process {
+    fn from(handle: OwnedHandle) -> Self {
+        Self { handle }
+    }
+}
+
+impl From<Process> for OwnedHandle {
+    fn from(process: Process) -> Self {
+        process.handle
+    }
+}
+
+/// 列出全部存活的进程
+///
+/// 将进程ID按升序写入 process_ids，最多写入 process_ids.len() 个，返回存活进程总数。
+/// 如果返回值大于 process_ids.len()，说明缓冲区不足，部分进程未被列出
+pub fn list_processes(process_ids: &mut [u64]) -> Result<usize> {
+    let process_ids_ptr = process_ids.as_mut_ptr() as u64;
+    let process_ids_len = process_ids.len() as u64;
+    let mut count = MaybeUninit::<u64>::uninit();
+    let count_ptr = count.as_mut_ptr() as u64;
+    let error = unsafe {
+        syscall!(
+            idx::IDX_PROCESS_LIST,
+            process_ids_ptr,
+            process_ids_len,
+            count_ptr
+        )
+    };
+    SyscallError::to_result(error).map(|_| unsafe { count.assume_init() as usize })
+}
+
+/// 获取进程信息
+///
+/// process_id为 [list_processes] 返回的进程ID。如果进程已经退出，返回 [crate::error::ErrorKind::BadArgument]
+pub fn process_info(process_id: u64) -> Result<ProcessInfo> {
+    let mut info = MaybeUninit::<ProcessInfo>::uninit();
+    let info_ptr = info.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_PROCESS_INFO, process_id, info_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { info.assume_init() })
+}
+
+/// 将进程移入进程组，返回进程组ID
+///
+/// process_handle为当前进程或其子进程的句柄，目标进程须与当前进程位于同一会话，且不是会话首进程，
+/// 否则返回 [crate::error::ErrorKind::PermissionDenied]。process_group为0时以目标进程的ID创建新的进程组，
+/// 否则须为当前会话中已存在的进程组，不存在时返回 [crate::error::ErrorKind::BadArgument]。
+///
+/// 子进程创建时位于父进程的进程组与会话中
+pub fn set_process_group(process_handle: u64, process_group: u64) -> Result<u64> {
+    let mut new_group = MaybeUninit::<u64>::uninit();
+    let new_group_ptr = new_group.as_mut_ptr() as u64;
+    let error = unsafe {
+        syscall!(
+            idx::IDX_PROCESS_SET_GROUP,
+            process_handle,
+            process_group,
+            new_group_ptr
+        )
+    };
+    SyscallError::to_result(error).map(|_| unsafe { new_group.assume_init() })
+}
+
+/// 以当前进程创建新的会话与进程组，返回会话ID，即当前进程的ID
+///
+/// 当前进程已是进程组的首进程时返回 [crate::error::ErrorKind::PermissionDenied]
+pub fn create_session() -> Result<u64> {
+    let mut session = MaybeUninit::<u64>::uninit();
+    let session_ptr = session.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_PROCESS_CREATE_SESSION, session_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { session.assume_init() })
+}
+
+/// 设置控制台的前台进程组，process_group为0时取消前台进程组
+///
+/// 按下Ctrl+C时，前台进程组中的全部进程被强制停止，退出码为 [EXIT_INTERRUPT]；
+/// 没有前台进程组时，Ctrl+C作为普通字符（0x03）输入。
+///
+/// 只有会话首进程（见 [create_session]）可以设置，其他进程设置时返回
+/// [crate::error::ErrorKind::PermissionDenied]。首个设置前台进程组的会话成为控制台的控制会话，
+/// 直至其首进程退出，期间其他会话设置时同样返回 [crate::error::ErrorKind::PermissionDenied]；
+/// 进程组不在当前会话中时返回 [crate::error::ErrorKind::BadArgument]
+pub fn set_foreground_group(process_group: u64) -> Result<()> {
+    let error = unsafe { syscall!(idx::IDX_PROCESS_SET_FOREGROUND, process_group) };
+    SyscallError::to_result(error)
+}
+
+/// 获取控制台的前台进程组，没有前台进程组时返回0
+pub fn foreground_group() -> Result<u64> {
+    let mut process_group = MaybeUninit::<u64>::uninit();
+    let process_group_ptr = process_group.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_PROCESS_FOREGROUND, process_group_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { process_group.assume_init() })
+}
+
+/// 获取系统CPU时间
+pub fn cpu_times() -> Result<CpuTimes> {
+    let mut times = MaybeUninit::<CpuTimes>::uninit();
+    let times_ptr = times.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_PROCESS_CPU_TIMES, times_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { times.assume_init() })
+}
+
 /// 等待指定线程退出，并获取其退出码
 ///
 /// 在线程退出后，无法再次通过此函数获取其退出码。
@@ -203,3 +541,39 @@ pub fn sleep_thread(time_in_seconds: u64, time_in_ns: u64) -> Result<()> {
     let error = unsafe { syscall!(idx::IDX_THREAD_SLEEP, time_in_seconds, time_in_ns) };
     SyscallError::to_result(error)
 }
+
+/// 设置当前线程的线程局部存储（TLS）基址，即FS段基址
+///
+/// # 线程局部存储模型
+///
+/// COS采用x86-64 System V的TLS布局（variant II），仅支持静态链接的可执行文件（local-exec模型）：
+///
+/// - 线程指针保存在FS段基址中，TLS变量以相对线程指针的负偏移访问，如`mov rax, fs:[-8]`
+/// - 线程指针之前为线程的TLS块，之后为线程控制块（64字节），fs:0保存线程指针自身，
+///   可通过`mov rax, fs:0`读取线程指针，见 [tls_base]
+/// - 可执行文件包含PT_TLS段时，内核在创建每个线程（包括主线程）时分配TLS块与线程控制块，
+///   复制.tdata的初始化镜像并清零.tbss，然后设置线程指针。否则线程指针初始为0
+/// - 线程指针在切换线程时由内核保存与恢复，各线程互不影响
+///
+/// 用户代码可通过此函数自行管理线程指针，如为动态加载的模块分配更大的TLS块。
+/// base需为用户空间地址，传入0表示清除线程指针。内核不会读写base指向的内存，
+/// 之后通过FS访问内存时由用户保证其有效。内核分配的TLS块在线程退出后释放
+pub fn set_tls_base(base: u64) -> Result<()> {
+    let error = unsafe { syscall!(idx::IDX_THREAD_SET_TLS_BASE, base) };
+    SyscallError::to_result(error)
+}
+
+/// 读取当前线程的线程指针
+///
+/// 返回fs:0处保存的值，按 [set_tls_base] 描述的模型即为FS段基址
+///
+/// # Safety
+///
+/// 线程指针必须已经设置，且fs:0可读
+pub unsafe fn tls_base() -> u64 {
+    let base: u64;
+    unsafe {
+        asm!("mov {}, fs:0", out(reg) base, options(nostack, preserves_flags, readonly));
+    }
+    base
+}
@@ -0,0 +1,250 @@
+use core::mem::MaybeUninit;
+
+use crate::{
+    error::{Result, SyscallError},
+    handle::OwnedHandle,
+    idx,
+    io::{Read, Write},
+    syscall,
+};
+
+/// IPv4地址与端口
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SocketAddrV4 {
+    pub ip: [u8; 4],
+    pub port: u16,
+}
+
+impl SocketAddrV4 {
+    pub const fn new(ip: [u8; 4], port: u16) -> Self {
+        Self { ip, port }
+    }
+
+    /// 系统调用中地址的编码：第16~47位为IP地址，低16位为端口
+    pub const fn to_raw(self) -> u64 {
+        ((u32::from_be_bytes(self.ip) as u64) << 16) | self.port as u64
+    }
+
+    pub const fn from_raw(raw: u64) -> Self {
+        Self {
+            ip: ((raw >> 16) as u32).to_be_bytes(),
+            port: raw as u16,
+        }
+    }
+}
+
+/// UDP套接字
+///
+/// 套接字被释放时关闭句柄并解除端口绑定
+#[derive(Debug)]
+pub struct UdpSocket {
+    handle: OwnedHandle,
+}
+
+impl UdpSocket {
+    /// 绑定本地端口，`port`为0时由系统分配端口
+    pub fn bind(port: u16) -> Result<Self> {
+        let mut handle = MaybeUninit::uninit();
+        let handle_ptr = handle.as_mut_ptr() as u64;
+        let error = unsafe { syscall!(idx::IDX_NET_UDP_BIND, port as u64, handle_ptr) };
+        SyscallError::to_result(error).map(|_| Self {
+            // Safety: 句柄刚刚打开，没有其他所有者
+            handle: unsafe { OwnedHandle::from_raw(handle.assume_init()) },
+        })
+    }
+
+    /// 套接字的句柄，可用于 [crate::ipc::poll]
+    pub fn handle(&self) -> u64 {
+        self.handle.as_raw()
+    }
+
+    /// 发送一个数据报
+    pub fn send_to(&self, buf: &[u8], addr: SocketAddrV4) -> Result<()> {
+        let buf_ptr = buf.as_ptr() as u64;
+        let buf_len = buf.len() as u64;
+        let error = unsafe {
+            syscall!(
+                idx::IDX_NET_UDP_SEND_TO,
+                self.handle.as_raw(),
+                buf_ptr,
+                buf_len,
+                addr.to_raw()
+            )
+        };
+        SyscallError::to_result(error)
+    }
+
+    /// 接收一个数据报，返回数据长度与发送方地址
+    ///
+    /// 没有数据报时阻塞。数据报长度超过缓冲区时，超出部分被丢弃
+    pub fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddrV4)> {
+        let buf_ptr = buf.as_mut_ptr() as u64;
+        let buf_len = buf.len() as u64;
+        let mut len = MaybeUninit::<u64>::uninit();
+        let len_ptr = len.as_mut_ptr() as u64;
+        let mut addr = MaybeUninit::<u64>::uninit();
+        let addr_ptr = addr.as_mut_ptr() as u64;
+        let error = unsafe {
+            syscall!(
+                idx::IDX_NET_UDP_RECV_FROM,
+                self.handle.as_raw(),
+                buf_ptr,
+                buf_len,
+                len_ptr,
+                addr_ptr
+            )
+        };
+        SyscallError::to_result(error).map(|_| unsafe {
+            (
+                len.assume_init() as usize,
+                SocketAddrV4::from_raw(addr.assume_init()),
+            )
+        })
+    }
+}
+
+/// TCP流
+///
+/// 流被释放时关闭句柄，缓冲的数据发送完毕后连接关闭
+#[derive(Debug)]
+pub struct TcpStream {
+    handle: OwnedHandle,
+}
+
+impl TcpStream {
+    /// 连接到`addr`，阻塞至连接建立或失败
+    pub fn connect(addr: SocketAddrV4) -> Result<Self> {
+        let mut handle = MaybeUninit::uninit();
+        let handle_ptr = handle.as_mut_ptr() as u64;
+        let error = unsafe { syscall!(idx::IDX_NET_TCP_CONNECT, addr.to_raw(), handle_ptr) };
+        SyscallError::to_result(error).map(|_| Self {
+            // Safety: 句柄刚刚打开，没有其他所有者
+            handle: unsafe { OwnedHandle::from_raw(handle.assume_init()) },
+        })
+    }
+
+    /// 流的句柄，可用于 [crate::ipc::poll]
+    pub fn handle(&self) -> u64 {
+        self.handle.as_raw()
+    }
+
+    /// 读取数据，返回读取的长度
+    ///
+    /// 没有数据时阻塞。对端关闭连接且数据已读取完毕时返回0
+    pub fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        let buf_ptr = buf.as_mut_ptr() as u64;
+        let buf_len = buf.len() as u64;
+        let mut len = MaybeUninit::<u64>::uninit();
+        let len_ptr = len.as_mut_ptr() as u64;
+        let error = unsafe {
+            syscall!(
+                idx::IDX_NET_TCP_READ,
+                self.handle.as_raw(),
+                buf_ptr,
+                buf_len,
+                len_ptr
+            )
+        };
+        SyscallError::to_result(error).map(|_| unsafe { len.assume_init() as usize })
+    }
+
+    /// 写入数据，返回写入的长度
+    ///
+    /// 发送缓冲区已满时阻塞，写入的长度可能小于`buf`的长度
+    pub fn write(&self, buf: &[u8]) -> Result<usize> {
+        let buf_ptr = buf.as_ptr() as u64;
+        let buf_len = buf.len() as u64;
+        let mut len = MaybeUninit::<u64>::uninit();
+        let len_ptr = len.as_mut_ptr() as u64;
+        let error = unsafe {
+            syscall!(
+                idx::IDX_NET_TCP_WRITE,
+                self.handle.as_raw(),
+                buf_ptr,
+                buf_len,
+                len_ptr
+            )
+        };
+        SyscallError::to_result(error).map(|_| unsafe { len.assume_init() as usize })
+    }
+
+    /// 写入全部数据
+    pub fn write_all(&self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            let len = self.write(buf)?;
+            buf = &buf[len..];
+        }
+        Ok(())
+    }
+
+    /// 关闭发送方向，对端读取完数据后将读到0。流仍然可以读取数据
+    pub fn shutdown(&self) -> Result<()> {
+        let error = unsafe { syscall!(idx::IDX_NET_TCP_SHUTDOWN, self.handle.as_raw()) };
+        SyscallError::to_result(error)
+    }
+}
+
+impl Read for TcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        TcpStream::read(self, buf)
+    }
+}
+
+impl Write for TcpStream {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        TcpStream::write(self, buf)
+    }
+}
+
+/// TCP监听套接字
+///
+/// 套接字被释放时关闭句柄并停止监听
+#[derive(Debug)]
+pub struct TcpListener {
+    handle: OwnedHandle,
+}
+
+impl TcpListener {
+    /// 监听本地端口，`port`为0时由系统分配端口
+    pub fn bind(port: u16) -> Result<Self> {
+        let mut handle = MaybeUninit::uninit();
+        let handle_ptr = handle.as_mut_ptr() as u64;
+        let error = unsafe { syscall!(idx::IDX_NET_TCP_LISTEN, port as u64, handle_ptr) };
+        SyscallError::to_result(error).map(|_| Self {
+            // Safety: 句柄刚刚打开，没有其他所有者
+            handle: unsafe { OwnedHandle::from_raw(handle.assume_init()) },
+        })
+    }
+
+    /// 套接字的句柄，可用于 [crate::ipc::poll]
+    pub fn handle(&self) -> u64 {
+        self.handle.as_raw()
+    }
+
+    /// 接受一个连接，返回TCP流与对端地址
+    ///
+    /// 没有完成握手的连接时阻塞
+    pub fn accept(&self) -> Result<(TcpStream, SocketAddrV4)> {
+        let mut handle = MaybeUninit::<u64>::uninit();
+        let handle_ptr = handle.as_mut_ptr() as u64;
+        let mut addr = MaybeUninit::<u64>::uninit();
+        let addr_ptr = addr.as_mut_ptr() as u64;
+        let error = unsafe {
+            syscall!(
+                idx::IDX_NET_TCP_ACCEPT,
+                self.handle.as_raw(),
+                handle_ptr,
+                addr_ptr
+            )
+        };
+        SyscallError::to_result(error).map(|_| unsafe {
+            (
+                TcpStream {
+                    // Safety: 句柄刚刚打开，没有其他所有者
+                    handle: OwnedHandle::from_raw(handle.assume_init()),
+                },
+                SocketAddrV4::from_raw(addr.assume_init()),
+            )
+        })
+    }
+}
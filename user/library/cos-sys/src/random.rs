@@ -0,0 +1,14 @@
+use crate::{
+    error::{Result, SyscallError},
+    idx, syscall,
+};
+
+/// 以内核熵池产生的随机数据填充缓冲区
+///
+/// 数据适用于哈希种子、网络协议的初始序号等需要不可预测性的场景
+pub fn fill(buf: &mut [u8]) -> Result<()> {
+    let buf_ptr = buf.as_mut_ptr() as u64;
+    let buf_len = buf.len() as u64;
+    let error = unsafe { syscall!(idx::IDX_RANDOM_GET, buf_ptr, buf_len) };
+    SyscallError::to_result(error)
+}
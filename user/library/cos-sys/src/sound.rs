@@ -0,0 +1,20 @@
+use core::time::Duration;
+
+use crate::{
+    error::{Result, SyscallError},
+    idx, syscall,
+};
+
+/// 以PC扬声器按指定频率发声
+///
+/// 发声被加入内核的播放队列后立即返回，多次调用按顺序依次播放。frequency为0时保持静音，
+/// 可用于两次发声之间的间隔。频率须在20~20000Hz之间，时长不超过10秒，否则返回BadArgument；
+/// 等待播放的发声过多时返回WouldBlock。
+///
+/// 超级用户也可以写入设备文件`/dev/speaker`发声，每8字节为一次发声，依次为小端序的频率（Hz，u32）与时长（毫秒，u32），
+/// 队列已满时写入等待至有空位
+pub fn beep(frequency: u32, duration: Duration) -> Result<()> {
+    let duration_ms = duration.as_millis().min(u64::MAX as u128) as u64;
+    let error = unsafe { syscall!(idx::IDX_SOUND_BEEP, frequency as u64, duration_ms) };
+    SyscallError::to_result(error)
+}
@@ -0,0 +1,56 @@
+use core::mem::MaybeUninit;
+
+use crate::{
+    error::{Result, SyscallError},
+    idx, syscall,
+};
+
+/// 读取内核命令行
+///
+/// 最多写入 buf.len() 字节，返回命令行的总长度。
+/// 如果返回值大于 buf.len()，说明缓冲区不足，命令行被截断
+pub fn cmdline(buf: &mut [u8]) -> Result<usize> {
+    let buf_ptr = buf.as_mut_ptr() as u64;
+    let buf_len = buf.len() as u64;
+    let mut len = MaybeUninit::<u64>::uninit();
+    let len_ptr = len.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_SYSTEM_CMDLINE, buf_ptr, buf_len, len_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { len.assume_init() as usize })
+}
+
+/// 关机
+///
+/// 内核会先卸载全部文件系统，将缓存写回磁盘，然后通过ACPI关闭电源。
+/// 如果ACPI不可用，内核将停止运行。成功时此函数永不返回。
+/// 只有超级用户或特权进程可以关机，否则返回错误
+pub fn shutdown() -> SyscallError {
+    let error = unsafe { syscall!(idx::IDX_SYSTEM_SHUTDOWN) };
+    SyscallError::new(error).expect("shutdown returned without error")
+}
+
+/// 重启
+///
+/// 内核会先卸载全部文件系统，将缓存写回磁盘，然后通过ACPI复位寄存器重启，
+/// 不支持时改用键盘控制器复位。成功时此函数永不返回。
+/// 只有超级用户或特权进程可以重启，否则返回错误
+pub fn reboot() -> SyscallError {
+    let error = unsafe { syscall!(idx::IDX_SYSTEM_REBOOT) };
+    SyscallError::new(error).expect("reboot returned without error")
+}
+
+/// 安装新的内核镜像
+///
+/// 内核检查path处的文件是否为build-scripts生成的内核镜像，将其复制到本次未启动的内核槽位，
+/// 并设为下次启动的槽位。新内核启动后未能启动init时，再下次启动将回退到原来的槽位。
+///
+/// 只有超级用户可以调用。不是由BIOS引导程序从内核槽位启动时（如UEFI启动）返回
+/// [ErrorKind::NotSupported]，文件不是内核镜像时返回 [ErrorKind::Corrupted]
+///
+/// [ErrorKind::NotSupported]: crate::error::ErrorKind::NotSupported
+/// [ErrorKind::Corrupted]: crate::error::ErrorKind::Corrupted
+pub fn update_kernel(path: &str) -> Result<()> {
+    let path_ptr = path.as_ptr() as u64;
+    let path_len = path.len() as u64;
+    let error = unsafe { syscall!(idx::IDX_SYSTEM_UPDATE_KERNEL, path_ptr, path_len) };
+    SyscallError::to_result(error)
+}
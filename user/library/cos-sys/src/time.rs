@@ -0,0 +1,232 @@
+use core::{
+    fmt,
+    mem::MaybeUninit,
+    ops::{Add, AddAssign, Sub, SubAssign},
+    time::Duration,
+};
+
+use crate::{
+    error::{Result, SyscallError},
+    idx, syscall,
+};
+
+/// 系统时间，即自UNIX纪元（1970-01-01 00:00:00 UTC）起经过的时间
+///
+/// 系统时间来自RTC，不保证单调递增。测量时间间隔应使用 [Instant]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SystemTime(Duration);
+
+impl SystemTime {
+    /// UNIX纪元
+    pub const UNIX_EPOCH: Self = Self(Duration::ZERO);
+
+    /// 当前系统时间
+    pub fn now() -> Result<Self> {
+        let mut nanos = MaybeUninit::<u64>::uninit();
+        let nanos_ptr = nanos.as_mut_ptr() as u64;
+        let error = unsafe { syscall!(idx::IDX_TIME_GET, nanos_ptr) };
+        SyscallError::to_result(error)
+            .map(|_| Self(Duration::from_nanos(unsafe { nanos.assume_init() })))
+    }
+
+    /// 与更早的时间之间的间隔，`earlier`晚于自身时返回None
+    pub fn duration_since(&self, earlier: SystemTime) -> Option<Duration> {
+        self.0.checked_sub(earlier.0)
+    }
+
+    pub fn checked_add(&self, duration: Duration) -> Option<Self> {
+        self.0.checked_add(duration).map(Self)
+    }
+
+    pub fn checked_sub(&self, duration: Duration) -> Option<Self> {
+        self.0.checked_sub(duration).map(Self)
+    }
+}
+
+impl Add<Duration> for SystemTime {
+    type Output = Self;
+
+    fn add(self, rhs: Duration) -> Self {
+        self.checked_add(rhs)
+            .expect("overflow when adding duration to system time")
+    }
+}
+
+impl AddAssign<Duration> for SystemTime {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub<Duration> for SystemTime {
+    type Output = Self;
+
+    fn sub(self, rhs: Duration) -> Self {
+        self.checked_sub(rhs)
+            .expect("overflow when subtracting duration from system time")
+    }
+}
+
+impl SubAssign<Duration> for SystemTime {
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
+    }
+}
+
+/// 单调时钟的时刻，即系统启动后经过的时间
+///
+/// 单调时钟不受系统时间调整的影响，适合测量时间间隔
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(Duration);
+
+impl Instant {
+    /// 当前时刻
+    pub fn now() -> Result<Self> {
+        let mut nanos = MaybeUninit::<u64>::uninit();
+        let nanos_ptr = nanos.as_mut_ptr() as u64;
+        let error = unsafe { syscall!(idx::IDX_TIME_MONOTONIC, nanos_ptr) };
+        SyscallError::to_result(error)
+            .map(|_| Self(Duration::from_nanos(unsafe { nanos.assume_init() })))
+    }
+
+    /// 与更早的时刻之间的间隔，`earlier`晚于自身时返回0
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+
+    /// 自此时刻起经过的时间
+    pub fn elapsed(&self) -> Result<Duration> {
+        Instant::now().map(|now| now.duration_since(*self))
+    }
+
+    pub fn checked_add(&self, duration: Duration) -> Option<Self> {
+        self.0.checked_add(duration).map(Self)
+    }
+
+    pub fn checked_sub(&self, duration: Duration) -> Option<Self> {
+        self.0.checked_sub(duration).map(Self)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Self;
+
+    fn add(self, rhs: Duration) -> Self {
+        self.checked_add(rhs)
+            .expect("overflow when adding duration to instant")
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Self;
+
+    fn sub(self, rhs: Duration) -> Self {
+        self.checked_sub(rhs)
+            .expect("overflow when subtracting duration from instant")
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, rhs: Instant) -> Duration {
+        self.duration_since(rhs)
+    }
+}
+
+/// 本地时区相对UTC的偏移，单位为秒，东时区为正
+///
+/// 由内核启动选项`tz`设置，未设置时为0
+pub fn utc_offset() -> Result<i64> {
+    let mut offset = MaybeUninit::<i64>::uninit();
+    let offset_ptr = offset.as_mut_ptr() as u64;
+    let error = unsafe { syscall!(idx::IDX_TIME_UTC_OFFSET, offset_ptr) };
+    SyscallError::to_result(error).map(|_| unsafe { offset.assume_init() })
+}
+
+/// 公历日期与时间
+///
+/// 不记录时区，是UTC时间还是本地时间由使用方决定
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    pub year: i32,
+    /// 月份，1~12
+    pub month: u8,
+    /// 日，1~31
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub nanosecond: u32,
+}
+
+impl DateTime {
+    /// 由自UNIX纪元起的秒数构造
+    pub const fn from_unix(seconds: i64, nanosecond: u32) -> Self {
+        let days = seconds.div_euclid(86400);
+        let seconds_of_day = seconds.rem_euclid(86400);
+
+        // 由天数计算公历日期，以3月1日为一年的开始，使闰日位于年末
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let day_of_era = z.rem_euclid(146097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Self {
+            year: year as i32,
+            month: month as u8,
+            day: day as u8,
+            hour: (seconds_of_day / 3600) as u8,
+            minute: (seconds_of_day / 60 % 60) as u8,
+            second: (seconds_of_day % 60) as u8,
+            nanosecond,
+        }
+    }
+
+    /// 系统时间在指定时区下的日期与时间，`offset`为时区相对UTC的偏移秒数
+    pub const fn from_system_time(time: SystemTime, offset: i64) -> Self {
+        Self::from_unix(time.0.as_secs() as i64 + offset, time.0.subsec_nanos())
+    }
+
+    /// 转换为自UNIX纪元起的秒数，忽略纳秒
+    pub const fn to_unix(&self) -> i64 {
+        let year = self.year as i64 - if self.month <= 2 { 1 } else { 0 };
+        let month = self.month as i64;
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let mp = if month > 2 { month - 3 } else { month + 9 };
+        let day_of_year = (153 * mp + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146097 + day_of_era - 719468;
+
+        days * 86400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
@@ -0,0 +1,134 @@
+use core::{
+    arch::x86_64::_rdtsc,
+    sync::atomic::{AtomicI64, AtomicU64, Ordering, fence},
+    time::Duration,
+};
+
+/// 共享页的起始地址，内核在创建进程时映射，用户态只读
+///
+/// 共两页：第一页为 [SystemData]，所有进程映射同一物理页，由内核随计时器中断更新；
+/// 第二页为 [ProcessData]，每个进程独占，创建进程后不再改变
+pub const VDSO_ADDRESS: u64 = 0x0000_7FFF_FFFF_E000;
+/// 共享页的总大小
+pub const VDSO_SIZE: u64 = 0x2000;
+
+/// 系统共享数据
+///
+/// 内核以顺序锁更新：写入前将sequence加一变为奇数，写入后再加一变为偶数。
+/// 读取方在sequence为奇数或读取前后不一致时重试
+#[repr(C)]
+pub struct SystemData {
+    /// 顺序锁
+    pub sequence: AtomicU64,
+    /// 最近一次计时器中断时的单调时钟（纳秒）
+    pub monotonic_ns: AtomicU64,
+    /// 单调时钟为0时对应的UNIX时间（纳秒）
+    pub boot_time_ns: AtomicU64,
+    /// 本地时区相对UTC的偏移（秒）
+    pub utc_offset: AtomicI64,
+    /// TSC的频率（Hz），时钟源不是TSC时为0，此时只能使用 [SystemData::monotonic_ns]
+    pub tsc_hz: AtomicU64,
+    /// 时钟源切换为TSC时的计数器
+    pub tsc_base: AtomicU64,
+    /// tsc_base对应的单调时钟（纳秒）
+    pub tsc_base_ns: AtomicU64,
+}
+
+/// 进程独占的共享数据
+#[repr(C)]
+pub struct ProcessData {
+    /// 进程ID
+    pub process_id: u64,
+    /// 父进程ID，由内核直接创建的进程为0
+    pub parent_id: u64,
+}
+
+/// 读取时间相关数据的快照
+#[derive(Clone, Copy)]
+struct TimeSnapshot {
+    monotonic_ns: u64,
+    boot_time_ns: u64,
+    utc_offset: i64,
+    tsc_hz: u64,
+    tsc_base: u64,
+    tsc_base_ns: u64,
+}
+
+fn system_data() -> &'static SystemData {
+    // Safety: 内核在创建进程时映射共享页，进程存活期间不会解除映射
+    unsafe { &*(VDSO_ADDRESS as *const SystemData) }
+}
+
+fn process_data() -> &'static ProcessData {
+    // Safety: 同上，第二页为进程独占的数据
+    unsafe { &*((VDSO_ADDRESS + 0x1000) as *const ProcessData) }
+}
+
+fn snapshot() -> TimeSnapshot {
+    let data = system_data();
+    loop {
+        let sequence = data.sequence.load(Ordering::Acquire);
+        if sequence & 1 != 0 {
+            core::hint::spin_loop();
+            continue;
+        }
+        let snapshot = TimeSnapshot {
+            monotonic_ns: data.monotonic_ns.load(Ordering::Relaxed),
+            boot_time_ns: data.boot_time_ns.load(Ordering::Relaxed),
+            utc_offset: data.utc_offset.load(Ordering::Relaxed),
+            tsc_hz: data.tsc_hz.load(Ordering::Relaxed),
+            tsc_base: data.tsc_base.load(Ordering::Relaxed),
+            tsc_base_ns: data.tsc_base_ns.load(Ordering::Relaxed),
+        };
+        fence(Ordering::Acquire);
+        if data.sequence.load(Ordering::Relaxed) == sequence {
+            return snapshot;
+        }
+    }
+}
+
+impl TimeSnapshot {
+    /// 单调时钟（纳秒）
+    ///
+    /// 时钟源为TSC时由TSC计算，精度与 [crate::time::Instant::now] 相同；
+    /// 否则为最近一次计时器中断时的值，精度为计时器中断的间隔
+    fn monotonic_ns(&self) -> u64 {
+        if self.tsc_hz == 0 {
+            return self.monotonic_ns;
+        }
+        // Safety: 内核未限制用户态执行rdtsc
+        let delta = unsafe { _rdtsc() }.saturating_sub(self.tsc_base);
+        let nanos = (delta as u128 * 1_000_000_000 / self.tsc_hz as u128) as u64;
+        (self.tsc_base_ns + nanos).max(self.monotonic_ns)
+    }
+}
+
+/// 单调时钟，即系统启动后经过的时间，不进入内核
+///
+/// 与 [crate::time::Instant::now] 使用相同的时钟
+pub fn monotonic() -> Duration {
+    Duration::from_nanos(snapshot().monotonic_ns())
+}
+
+/// 系统时间，即自UNIX纪元起经过的时间，不进入内核
+///
+/// 与 [crate::time::SystemTime::now] 使用相同的时钟
+pub fn realtime() -> Duration {
+    let snapshot = snapshot();
+    Duration::from_nanos(snapshot.boot_time_ns + snapshot.monotonic_ns())
+}
+
+/// 本地时区相对UTC的偏移（秒），不进入内核，见 [crate::time::utc_offset]
+pub fn utc_offset() -> i64 {
+    snapshot().utc_offset
+}
+
+/// 当前进程ID，不进入内核
+pub fn process_id() -> u64 {
+    process_data().process_id
+}
+
+/// 父进程ID，由内核直接创建的进程返回None，不进入内核
+pub fn parent_process_id() -> Option<u64> {
+    Some(process_data().parent_id).filter(|&id| id != 0)
+}
@@ -0,0 +1,31 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use cos_sys::multitask::exit;
+
+use coreutils::{EXIT_FAILED, EXIT_SUCCESS, args, display, print, read_chunks, report, usage};
+
+/// 依次输出文件的内容
+#[unsafe(export_name = "_start")]
+fn main() -> ! {
+    let args = args();
+    if args.is_empty() {
+        usage("cat <path>...");
+    }
+    let mut code = EXIT_SUCCESS;
+    for path in &args {
+        if let Err(error) = read_chunks(path, print) {
+            report(
+                "cat",
+                format_args!("read {} failed: {error}", display(path)),
+            );
+            code = EXIT_FAILED;
+        }
+    }
+    exit(code);
+}
+
+#[cfg(test)]
+fn test_runner(_test_cases: &[&dyn Fn()]) {}
@@ -0,0 +1,42 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use cos_sys::{file::chmod, multitask::exit};
+
+use coreutils::{EXIT_FAILED, EXIT_SUCCESS, args, display, report, usage};
+
+/// 修改文件的权限位，权限位为八进制数，如644
+#[unsafe(export_name = "_start")]
+fn main() -> ! {
+    let args = args();
+    let Some((mode, paths)) = args.split_first() else {
+        usage("chmod <mode> <path>...");
+    };
+    let Some(mode) = str::from_utf8(mode)
+        .ok()
+        .and_then(|mode| u16::from_str_radix(mode, 8).ok())
+        .filter(|&mode| mode <= 0o777)
+    else {
+        report("chmod", format_args!("invalid mode: {}", display(mode)));
+        exit(EXIT_FAILED);
+    };
+    if paths.is_empty() {
+        usage("chmod <mode> <path>...");
+    }
+    let mut code = EXIT_SUCCESS;
+    for path in paths {
+        if let Err(error) = chmod(path, mode) {
+            report(
+                "chmod",
+                format_args!("chmod {} failed: {error}", display(path)),
+            );
+            code = EXIT_FAILED;
+        }
+    }
+    exit(code);
+}
+
+#[cfg(test)]
+fn test_runner(_test_cases: &[&dyn Fn()]) {}
@@ -0,0 +1,53 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use cos_sys::{
+    file::{chown, metadata},
+    multitask::exit,
+};
+
+use coreutils::{EXIT_FAILED, EXIT_SUCCESS, args, display, report, usage};
+
+/// 修改文件的所有者，所有者为`<uid>`或`<uid>:<gid>`，省略gid时保持原有的组
+#[unsafe(export_name = "_start")]
+fn main() -> ! {
+    let args = args();
+    let Some((owner, paths)) = args.split_first() else {
+        usage("chown <uid>[:<gid>] <path>...");
+    };
+    let Some((uid, gid)) = parse_owner(owner) else {
+        report("chown", format_args!("invalid owner: {}", display(owner)));
+        exit(EXIT_FAILED);
+    };
+    if paths.is_empty() {
+        usage("chown <uid>[:<gid>] <path>...");
+    }
+    let mut code = EXIT_SUCCESS;
+    for path in paths {
+        let result = match gid {
+            Some(gid) => chown(path, uid, gid),
+            None => metadata(path).and_then(|info| chown(path, uid, info.gid as u32)),
+        };
+        if let Err(error) = result {
+            report(
+                "chown",
+                format_args!("chown {} failed: {error}", display(path)),
+            );
+            code = EXIT_FAILED;
+        }
+    }
+    exit(code);
+}
+
+fn parse_owner(owner: &[u8]) -> Option<(u32, Option<u32>)> {
+    let owner = str::from_utf8(owner).ok()?;
+    match owner.split_once(':') {
+        Some((uid, gid)) => Some((uid.parse().ok()?, Some(gid.parse().ok()?))),
+        None => Some((owner.parse().ok()?, None)),
+    }
+}
+
+#[cfg(test)]
+fn test_runner(_test_cases: &[&dyn Fn()]) {}
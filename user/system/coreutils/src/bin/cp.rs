@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use cos_sys::multitask::exit;
+
+use coreutils::{EXIT_FAILED, EXIT_SUCCESS, args, copy_file, display, report, target_path, usage};
+
+/// 复制文件，目标为已存在的文件夹时复制到其中
+#[unsafe(export_name = "_start")]
+fn main() -> ! {
+    let args = args();
+    let [source, target] = &args[..] else {
+        usage("cp <source> <target>");
+    };
+    let target = target_path(source, target);
+    if let Err(error) = copy_file(source, &target) {
+        report(
+            "cp",
+            format_args!(
+                "copy {} to {} failed: {error}",
+                display(source),
+                display(&target)
+            ),
+        );
+        exit(EXIT_FAILED);
+    }
+    exit(EXIT_SUCCESS);
+}
+
+#[cfg(test)]
+fn test_runner(_test_cases: &[&dyn Fn()]) {}
@@ -0,0 +1,75 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+extern crate alloc;
+
+use alloc::{format, vec::Vec};
+use cos_sys::multitask::exit;
+
+use coreutils::{EXIT_FAILED, EXIT_SUCCESS, args, display, print, read_chunks, report, usage};
+
+/// 每行显示的字节数
+const LINE_BYTES: usize = 16;
+
+/// 以十六进制与ASCII输出文件的内容
+///
+/// 每行为偏移、16个字节的十六进制与对应的可打印字符，最后一行为文件长度
+#[unsafe(export_name = "_start")]
+fn main() -> ! {
+    let args = args();
+    let [path] = &args[..] else {
+        usage("hexdump <path>");
+    };
+
+    let mut offset = 0;
+    let mut line = Vec::with_capacity(LINE_BYTES);
+    let result = read_chunks(path, |chunk| {
+        for &byte in chunk {
+            line.push(byte);
+            if line.len() == LINE_BYTES {
+                print_line(offset, &line);
+                offset += LINE_BYTES;
+                line.clear();
+            }
+        }
+    });
+    if let Err(error) = result {
+        report(
+            "hexdump",
+            format_args!("read {} failed: {error}", display(path)),
+        );
+        exit(EXIT_FAILED);
+    }
+    if !line.is_empty() {
+        print_line(offset, &line);
+        offset += line.len();
+    }
+    print(format!("{offset:08x}\n").as_bytes());
+    exit(EXIT_SUCCESS);
+}
+
+fn print_line(offset: usize, bytes: &[u8]) {
+    let mut text = format!("{offset:08x} ");
+    for index in 0..LINE_BYTES {
+        // 前后两组8字节之间多一个空格
+        if index % 8 == 0 {
+            text.push(' ');
+        }
+        match bytes.get(index) {
+            Some(byte) => text += &format!("{byte:02x} "),
+            None => text += "   ",
+        }
+    }
+    text += " |";
+    text.extend(bytes.iter().map(|&byte| match byte {
+        0x20..=0x7E => byte as char,
+        _ => '.',
+    }));
+    text += "|\n";
+    print(text.as_bytes());
+}
+
+#[cfg(test)]
+fn test_runner(_test_cases: &[&dyn Fn()]) {}
@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use cos_sys::{
+    error::ErrorKind,
+    file::{delete, rename},
+    multitask::exit,
+};
+
+use coreutils::{EXIT_FAILED, EXIT_SUCCESS, args, copy_file, display, report, target_path, usage};
+
+/// 移动或重命名文件，目标为已存在的文件夹时移动到其中
+///
+/// 源与目标位于不同的文件系统时，复制后删除源文件
+#[unsafe(export_name = "_start")]
+fn main() -> ! {
+    let args = args();
+    let [source, target] = &args[..] else {
+        usage("mv <source> <target>");
+    };
+    let target = target_path(source, target);
+    let result = match rename(source, &target) {
+        Err(error) if error.kind() == ErrorKind::NotSupported => {
+            copy_file(source, &target).and_then(|_| delete(source))
+        }
+        result => result,
+    };
+    if let Err(error) = result {
+        report(
+            "mv",
+            format_args!(
+                "move {} to {} failed: {error}",
+                display(source),
+                display(&target)
+            ),
+        );
+        exit(EXIT_FAILED);
+    }
+    exit(EXIT_SUCCESS);
+}
+
+#[cfg(test)]
+fn test_runner(_test_cases: &[&dyn Fn()]) {}
@@ -0,0 +1,31 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use cos_sys::{file::delete, multitask::exit};
+
+use coreutils::{EXIT_FAILED, EXIT_SUCCESS, args, display, report, usage};
+
+/// 删除文件，某个文件删除失败时继续删除其余文件
+#[unsafe(export_name = "_start")]
+fn main() -> ! {
+    let args = args();
+    if args.is_empty() {
+        usage("rm <path>...");
+    }
+    let mut code = EXIT_SUCCESS;
+    for path in &args {
+        if let Err(error) = delete(path) {
+            report(
+                "rm",
+                format_args!("remove {} failed: {error}", display(path)),
+            );
+            code = EXIT_FAILED;
+        }
+    }
+    exit(code);
+}
+
+#[cfg(test)]
+fn test_runner(_test_cases: &[&dyn Fn()]) {}
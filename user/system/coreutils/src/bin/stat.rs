@@ -0,0 +1,55 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+extern crate alloc;
+
+use alloc::format;
+use cos_sys::{file::metadata, multitask::exit};
+
+use coreutils::{EXIT_FAILED, EXIT_SUCCESS, args, display, print, report, usage};
+
+/// 输出文件或文件夹的类型、大小、占用空间、所有者与权限
+#[unsafe(export_name = "_start")]
+fn main() -> ! {
+    let args = args();
+    if args.is_empty() {
+        usage("stat <path>...");
+    }
+    let mut code = EXIT_SUCCESS;
+    for path in &args {
+        match metadata(path) {
+            Ok(info) => {
+                let kind = if info.is_directory() {
+                    "directory"
+                } else {
+                    "file"
+                };
+                print(
+                    format!(
+                        "  File: {}\n  Type: {kind}\n  Size: {}\nBlocks: {} bytes\n Owner: {}:{}\n  Mode: {:03o}\n",
+                        display(path),
+                        info.size,
+                        info.allocated_size,
+                        info.uid,
+                        info.gid,
+                        info.mode
+                    )
+                    .as_bytes(),
+                );
+            }
+            Err(error) => {
+                report(
+                    "stat",
+                    format_args!("stat {} failed: {error}", display(path)),
+                );
+                code = EXIT_FAILED;
+            }
+        }
+    }
+    exit(code);
+}
+
+#[cfg(test)]
+fn test_runner(_test_cases: &[&dyn Fn()]) {}
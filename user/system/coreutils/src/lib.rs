@@ -0,0 +1,147 @@
+#![no_std]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+//! 文件操作工具集：cp、mv、rm、cat、hexdump、stat、chmod、chown
+//!
+//! 每个工具编译为src/bin中的一个独立程序，打包为/system/<工具名>。
+//! 此处为各工具共用的参数读取、输出与文件读写，工具成功时退出码为 [EXIT_SUCCESS]，
+//! 否则为 [EXIT_FAILED]
+
+extern crate alloc;
+extern crate rlibc;
+
+use alloc::vec::Vec;
+use core::fmt::Display;
+use cos_sys::{
+    debug::put_str,
+    error::{ErrorKind, Result, SyscallError},
+    file::{close, create, metadata, open, read, truncate, write},
+    multitask::{MAX_ARGS_LEN, exit, process_args, split_args},
+};
+
+pub use cos_sys::multitask::EXIT_SUCCESS;
+
+cos_heap::default_heap!();
+
+/// 参数错误或操作失败时的退出码
+pub const EXIT_FAILED: u64 = 2;
+
+/// 每次读写文件的字节数
+const CHUNK_SIZE: usize = 4096;
+
+/// 读取启动参数
+pub fn args() -> Vec<Vec<u8>> {
+    let mut args = alloc::vec![0u8; MAX_ARGS_LEN];
+    let len = process_args(&mut args).expect("failed to get process args");
+    split_args(&args[..len.min(MAX_ARGS_LEN)])
+        .map(<[u8]>::to_vec)
+        .collect()
+}
+
+pub fn print(string: &[u8]) {
+    put_str(string).expect("failed to print string");
+}
+
+/// 输出`<工具名>: <信息>`
+pub fn report(program: &str, message: impl Display) {
+    print(alloc::format!("{program}: {message}\n").as_bytes());
+}
+
+/// 输出用法并以 [EXIT_FAILED] 退出
+pub fn usage(usage: &str) -> ! {
+    print(alloc::format!("usage: {usage}\n").as_bytes());
+    exit(EXIT_FAILED);
+}
+
+/// 以路径显示的字符串，路径不是UTF-8时显示为`?`
+pub fn display(path: &[u8]) -> &str {
+    str::from_utf8(path).unwrap_or("?")
+}
+
+/// 路径的最后一级名称
+pub fn file_name(path: &[u8]) -> &[u8] {
+    let path = path.strip_suffix(b"/").unwrap_or(path);
+    path.rsplit(|&char| char == b'/').next().unwrap_or(path)
+}
+
+/// 复制或移动的目标路径，目标为已存在的文件夹时，复制到该文件夹中的同名文件
+pub fn target_path(source: &[u8], target: &[u8]) -> Vec<u8> {
+    match metadata(target) {
+        Ok(info) if info.is_directory() => {
+            let mut path = target.to_vec();
+            if !path.ends_with(b"/") {
+                path.push(b'/');
+            }
+            path.extend_from_slice(file_name(source));
+            path
+        }
+        _ => target.to_vec(),
+    }
+}
+
+/// 逐块读取文件，每读取一块调用一次f
+pub fn read_chunks(path: &[u8], mut f: impl FnMut(&[u8])) -> Result<()> {
+    let file = open(path)?;
+    let mut buffer = alloc::vec![0u8; CHUNK_SIZE];
+    let result = loop {
+        match read(file, &mut buffer) {
+            Ok(0) => break Ok(()),
+            Ok(read_count) => f(&buffer[..read_count as usize]),
+            Err(error) => break Err(error),
+        }
+    };
+    close(file)?;
+    result
+}
+
+/// 将source的内容复制到target，target不存在时创建，已存在时替换其内容
+pub fn copy_file(source: &[u8], target: &[u8]) -> Result<()> {
+    if metadata(source)?.is_directory() {
+        return Err(SyscallError::new(ErrorKind::FileTypeMismatch as u64).unwrap());
+    }
+    let source_file = open(source)?;
+    let target_file = match open(target) {
+        Ok(file) => file,
+        Err(error) if error.kind() == ErrorKind::FileNotFound => {
+            let created = create(target).and_then(|_| open(target));
+            match created {
+                Ok(file) => file,
+                Err(error) => {
+                    close(source_file)?;
+                    return Err(error);
+                }
+            }
+        }
+        Err(error) => {
+            close(source_file)?;
+            return Err(error);
+        }
+    };
+    let result = (|| {
+        let mut buffer = alloc::vec![0u8; CHUNK_SIZE];
+        loop {
+            let read_count = read(source_file, &mut buffer)? as usize;
+            if read_count == 0 {
+                break;
+            }
+            let mut written = 0;
+            while written < read_count {
+                written += write(target_file, &buffer[written..read_count])? as usize;
+            }
+        }
+        // 丢弃目标文件中超出新内容的部分
+        truncate(target_file)
+    })();
+    close(target_file)?;
+    close(source_file)?;
+    result
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    exit(3);
+}
+
+#[cfg(test)]
+fn test_runner(_test_cases: &[&dyn Fn()]) {}
@@ -0,0 +1,67 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+extern crate alloc;
+extern crate rlibc;
+
+use cos_async::net::{TcpListener, TcpStream};
+use cos_sys::{debug::put_str, multitask::exit};
+
+cos_heap::default_heap!();
+
+/// echo协议（RFC 862）的端口
+const ECHO_PORT: u16 = 7;
+
+#[unsafe(export_name = "_start")]
+fn main() -> ! {
+    let listener = TcpListener::bind(ECHO_PORT).expect("failed to listen on echo port");
+    print(b"echo server listening on port 7\n");
+
+    // 每个连接由独立的任务处理，将收到的数据原样发回
+    cos_async::block_on(async move {
+        loop {
+            let Ok((stream, peer)) = listener.accept().await else {
+                continue;
+            };
+            let line = alloc::format!(
+                "connection from {}.{}.{}.{}:{}\n",
+                peer.ip[0],
+                peer.ip[1],
+                peer.ip[2],
+                peer.ip[3],
+                peer.port
+            );
+            print(line.as_bytes());
+            cos_async::spawn(async move {
+                if echo(&stream).await.is_err() {
+                    print(b"connection aborted\n");
+                }
+            });
+        }
+    })
+}
+
+async fn echo(stream: &TcpStream) -> cos_sys::error::Result<()> {
+    let mut buffer = [0u8; 1024];
+    loop {
+        let len = stream.read(&mut buffer).await?;
+        if len == 0 {
+            return stream.shutdown();
+        }
+        stream.write_all(&buffer[..len]).await?;
+    }
+}
+
+fn print(string: &[u8]) {
+    put_str(string).expect("failed to print string");
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    exit(3);
+}
+
+#[cfg(test)]
+fn test_runner(_test_cases: &[&dyn Fn()]) {}
@@ -0,0 +1,384 @@
+use alloc::{format, string::String, vec::Vec};
+use cos_sys::{
+    debug::{get_char, open_keyboard},
+    error::{ErrorKind, Result, SyscallError},
+    file::{ConsoleSize, close, console_size, create, open, read, truncate, write},
+    handle::OwnedHandle,
+    ipc::{POLL_READ, POLL_RESIZE, PollFd, poll},
+};
+
+use crate::print;
+
+/// 无法查询控制台大小时使用的大小
+const DEFAULT_SIZE: ConsoleSize = ConsoleSize { rows: 25, cols: 80 };
+/// Tab键插入的空格数
+const TAB_SIZE: usize = 4;
+
+const CTRL_Q: u8 = 0x11;
+const CTRL_S: u8 = 0x13;
+
+/// 全屏文本编辑器
+///
+/// 文件按行编辑，保存时以`\n`连接各行。通过ANSI转义序列绘制，控制台大小变化时按新的大小重新绘制
+pub struct Editor {
+    path: Vec<u8>,
+    lines: Vec<Vec<u8>>,
+    // 光标所在的行与列
+    row: usize,
+    col: usize,
+    // 屏幕左上角对应的行与列
+    top: usize,
+    left: usize,
+    // 是否存在未保存的修改
+    modified: bool,
+    // 未保存时按下Ctrl+Q，再次按下时退出
+    quit_pending: bool,
+    // 状态栏中显示的提示，按键后清除
+    message: Option<String>,
+    // 转义序列之后多读取的字符，由下一次读取返回
+    pending: Option<u8>,
+    // 用于等待控制台大小变化的键盘句柄，缺少raw-console能力时为None，此时大小固定为DEFAULT_SIZE
+    console: Option<OwnedHandle>,
+    // 显示文本的行数，屏幕最后一行为状态栏
+    text_rows: usize,
+    // 每行显示的列数。在最后一列输出字符会使光标换行，屏幕最后一行换行时会滚动，因此不使用最后一列
+    screen_cols: usize,
+}
+
+enum Key {
+    Char(u8),
+    Enter,
+    Backspace,
+    Delete,
+    Tab,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Save,
+    Quit,
+    // 控制台大小发生变化
+    Resize,
+    Unknown,
+}
+
+impl Editor {
+    /// 读取文件，文件不存在时在保存时创建
+    pub fn open(path: &[u8]) -> Result<Self> {
+        let (content, message) = match read_file(path) {
+            Ok(content) => (content, None),
+            Err(error) if error.kind() == ErrorKind::FileNotFound => {
+                (Vec::new(), Some(String::from("new file")))
+            }
+            Err(error) => return Err(error),
+        };
+        // Safety: 句柄由open_keyboard新打开，只由Editor持有
+        let console = open_keyboard()
+            .ok()
+            .map(|handle| unsafe { OwnedHandle::from_raw(handle) });
+        let mut editor = Self {
+            path: path.to_vec(),
+            lines: content
+                .split(|&char| char == b'\n')
+                .map(<[u8]>::to_vec)
+                .collect(),
+            row: 0,
+            col: 0,
+            top: 0,
+            left: 0,
+            modified: false,
+            quit_pending: false,
+            message,
+            pending: None,
+            console,
+            text_rows: 0,
+            screen_cols: 0,
+        };
+        editor.update_size();
+        Ok(editor)
+    }
+
+    /// 处理按键直到退出，退出时清空屏幕
+    pub fn run(&mut self) {
+        loop {
+            self.render();
+            let key = self.read_key();
+            if let Key::Resize = key {
+                continue;
+            }
+            self.message = None;
+            let quit_pending = core::mem::take(&mut self.quit_pending);
+            match key {
+                Key::Char(char) => self.insert(&[char]),
+                Key::Tab => self.insert(&[b' '; TAB_SIZE][..TAB_SIZE - self.col % TAB_SIZE]),
+                Key::Enter => self.split_line(),
+                Key::Backspace => {
+                    if self.col > 0 || self.row > 0 {
+                        self.move_left();
+                        self.delete();
+                    }
+                }
+                Key::Delete => self.delete(),
+                Key::Left => self.move_left(),
+                Key::Right => self.move_right(),
+                Key::Up => self.move_to(self.row.saturating_sub(1)),
+                Key::Down => self.move_to((self.row + 1).min(self.lines.len() - 1)),
+                Key::Home => self.col = 0,
+                Key::End => self.col = self.lines[self.row].len(),
+                Key::Save => self.save(),
+                Key::Quit if self.modified && !quit_pending => {
+                    self.quit_pending = true;
+                    self.message = Some(String::from(
+                        "unsaved changes, press Ctrl+Q again to quit without saving",
+                    ));
+                }
+                Key::Quit => break,
+                Key::Resize | Key::Unknown => (),
+            }
+        }
+        print(b"\x1b[0m\x1b[2J\x1b[H");
+    }
+
+    fn insert(&mut self, bytes: &[u8]) {
+        self.lines[self.row].splice(self.col..self.col, bytes.iter().copied());
+        self.col += bytes.len();
+        self.modified = true;
+    }
+
+    fn split_line(&mut self) {
+        let tail = self.lines[self.row].split_off(self.col);
+        self.lines.insert(self.row + 1, tail);
+        self.row += 1;
+        self.col = 0;
+        self.modified = true;
+    }
+
+    /// 删除光标处的字符，位于行尾时与下一行合并
+    fn delete(&mut self) {
+        if self.col < self.lines[self.row].len() {
+            self.lines[self.row].remove(self.col);
+        } else if self.row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.row + 1);
+            self.lines[self.row].extend_from_slice(&next);
+        } else {
+            return;
+        }
+        self.modified = true;
+    }
+
+    fn move_left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.lines[self.row].len();
+        }
+    }
+
+    fn move_right(&mut self) {
+        if self.col < self.lines[self.row].len() {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    /// 移动到指定行，列超出行尾时移动到行尾
+    fn move_to(&mut self, row: usize) {
+        self.row = row;
+        self.col = self.col.min(self.lines[row].len());
+    }
+
+    fn save(&mut self) {
+        let content = self.lines.join(&b'\n');
+        self.message = Some(match write_file(&self.path, &content) {
+            Ok(()) => {
+                self.modified = false;
+                format!("saved {} bytes", content.len())
+            }
+            Err(error) => format!("save failed: {error}"),
+        });
+    }
+
+    /// 重新绘制屏幕，必要时滚动使光标可见
+    fn render(&mut self) {
+        if self.row < self.top {
+            self.top = self.row;
+        } else if self.row >= self.top + self.text_rows {
+            self.top = self.row + 1 - self.text_rows;
+        }
+        if self.col < self.left {
+            self.left = self.col;
+        } else if self.col >= self.left + self.screen_cols {
+            self.left = self.col + 1 - self.screen_cols;
+        }
+
+        let mut screen = Vec::new();
+        // 绘制期间隐藏光标
+        screen.extend_from_slice(b"\x1b[?25l\x1b[H");
+        for (index, line) in self.lines[self.top..]
+            .iter()
+            .chain(core::iter::repeat(&Vec::new()))
+            .take(self.text_rows)
+            .enumerate()
+        {
+            if index > 0 {
+                screen.push(b'\n');
+            }
+            let visible = line.get(self.left..).unwrap_or_default();
+            screen.extend(
+                visible
+                    .iter()
+                    .take(self.screen_cols)
+                    // 控制字符显示为`.`，保证每个字节占据一列
+                    .map(|&char| match char {
+                        0x20..=0x7E => char,
+                        _ => b'.',
+                    }),
+            );
+            screen.extend_from_slice(b"\x1b[K");
+        }
+
+        let name = str::from_utf8(&self.path).unwrap_or("?");
+        let modified = if self.modified { " [+]" } else { "" };
+        let hint = self.message.as_deref().unwrap_or("^S save  ^Q quit");
+        let status = format!(
+            "{name}{modified}  {}:{}  {hint}",
+            self.row + 1,
+            self.col + 1
+        );
+        let status = &status.as_bytes()[..status.len().min(self.screen_cols)];
+        screen.extend_from_slice(b"\n\x1b[30;47m");
+        screen.extend_from_slice(status);
+        screen.resize(screen.len() + self.screen_cols - status.len(), b' ');
+        screen.extend_from_slice(b"\x1b[0m");
+
+        screen.extend_from_slice(
+            format!(
+                "\x1b[{};{}H\x1b[?25h",
+                self.row - self.top + 1,
+                self.col - self.left + 1
+            )
+            .as_bytes(),
+        );
+        print(&screen);
+    }
+
+    /// 查询控制台大小，更新显示的行数与列数
+    fn update_size(&mut self) {
+        let size = self
+            .console
+            .as_ref()
+            .and_then(|console| console_size(console.as_raw()).ok())
+            .unwrap_or(DEFAULT_SIZE);
+        self.text_rows = (size.rows as usize).saturating_sub(1).max(1);
+        self.screen_cols = (size.cols as usize).saturating_sub(1).max(1);
+    }
+
+    /// 等待键盘输入或控制台大小变化，大小变化时更新大小并返回true
+    fn wait_resize(&mut self) -> bool {
+        let Some(console) = &self.console else {
+            return false;
+        };
+        let mut fds = [PollFd::new(console.as_raw(), POLL_READ | POLL_RESIZE)];
+        match poll(&mut fds, None) {
+            Ok(_) if fds[0].revents & POLL_RESIZE != 0 => {
+                self.update_size();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn read_char(&mut self) -> u8 {
+        match self.pending.take() {
+            Some(char) => char,
+            None => get_char().expect("failed to get char"),
+        }
+    }
+
+    fn read_key(&mut self) -> Key {
+        if self.pending.is_none() && self.wait_resize() {
+            return Key::Resize;
+        }
+        match self.read_char() {
+            b'\n' => Key::Enter,
+            0x08 => Key::Backspace,
+            b'\t' => Key::Tab,
+            CTRL_Q => Key::Quit,
+            CTRL_S => Key::Save,
+            0x1B => self.read_escape(),
+            char @ 0x20..=0x7E => Key::Char(char),
+            _ => Key::Unknown,
+        }
+    }
+
+    /// 读取`ESC`之后的转义序列
+    fn read_escape(&mut self) -> Key {
+        let char = self.read_char();
+        if char != b'[' {
+            // 单独按下Esc键，之后的字符作为普通输入
+            self.pending = Some(char);
+            return Key::Unknown;
+        }
+        let mut param = 0u32;
+        loop {
+            match self.read_char() {
+                digit @ b'0'..=b'9' => param = param.saturating_mul(10) + (digit - b'0') as u32,
+                b'A' => return Key::Up,
+                b'B' => return Key::Down,
+                b'C' => return Key::Right,
+                b'D' => return Key::Left,
+                b'H' => return Key::Home,
+                b'F' => return Key::End,
+                b'~' if param == 3 => return Key::Delete,
+                0x40..=0x7E => return Key::Unknown,
+                _ => (),
+            }
+        }
+    }
+}
+
+fn read_file(path: &[u8]) -> Result<Vec<u8>> {
+    let file = open(path)?;
+    let mut content = Vec::new();
+    let mut buffer = alloc::vec![0u8; 4096];
+    let result = loop {
+        match read(file, &mut buffer) {
+            Ok(0) => break Ok(content),
+            Ok(read_count) => content.extend_from_slice(&buffer[..read_count as usize]),
+            Err(error) => break Err(error),
+        }
+    };
+    close(file)?;
+    result
+}
+
+/// 以content替换文件内容，文件不存在时创建
+fn write_file(path: &[u8], content: &[u8]) -> Result<()> {
+    let file = match open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == ErrorKind::FileNotFound => {
+            create(path)?;
+            open(path)?
+        }
+        Err(error) => return Err(error),
+    };
+    let result = (|| {
+        let mut written = 0;
+        while written < content.len() {
+            // 写入0字节说明无法继续写入，如磁盘已满，避免无限循环
+            match write(file, &content[written..])? {
+                0 => return Err(SyscallError::new(ErrorKind::IoError as u64).unwrap()),
+                count => written += count as usize,
+            }
+        }
+        // 丢弃原文件中超出新内容的部分
+        truncate(file)
+    })();
+    close(file)?;
+    result
+}
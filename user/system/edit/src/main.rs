@@ -0,0 +1,53 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+extern crate alloc;
+extern crate rlibc;
+
+mod editor;
+
+use cos_sys::{
+    debug::put_str,
+    multitask::{EXIT_SUCCESS, MAX_ARGS_LEN, exit, process_args, split_args},
+};
+
+use crate::editor::Editor;
+
+cos_heap::default_heap!();
+
+/// 参数错误或无法打开文件时的退出码
+const EXIT_FAILED: u64 = 2;
+
+#[unsafe(export_name = "_start")]
+fn main() -> ! {
+    let mut args = alloc::vec![0u8; MAX_ARGS_LEN];
+    let len = process_args(&mut args).expect("failed to get process args");
+    let Some(path) = split_args(&args[..len.min(MAX_ARGS_LEN)]).next() else {
+        print(b"usage: edit <path>\n");
+        exit(EXIT_FAILED);
+    };
+
+    let mut editor = match Editor::open(path) {
+        Ok(editor) => editor,
+        Err(error) => {
+            print(alloc::format!("edit: open failed: {error}\n").as_bytes());
+            exit(EXIT_FAILED);
+        }
+    };
+    editor.run();
+    exit(EXIT_SUCCESS);
+}
+
+fn print(string: &[u8]) {
+    put_str(string).expect("failed to print string");
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    exit(3);
+}
+
+#[cfg(test)]
+fn test_runner(_test_cases: &[&dyn Fn()]) {}
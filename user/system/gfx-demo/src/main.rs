@@ -0,0 +1,136 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+extern crate alloc;
+extern crate rlibc;
+
+use cos_sys::{
+    debug::put_str,
+    gfx::{Rect, Surface, rgb},
+    input::{self, EVENT_KEY, EVENT_MOUSE_BUTTON, InputEvent},
+    multitask::{exit, sleep_thread},
+};
+
+cos_heap::default_heap!();
+
+/// 方块的边长
+const BOX_SIZE: u32 = 48;
+/// 每帧的间隔（纳秒）
+const FRAME_INTERVAL_NS: u64 = 16_000_000;
+
+#[unsafe(export_name = "_start")]
+fn main() -> ! {
+    let mut surface = match Surface::new() {
+        Ok(surface) => surface,
+        Err(error) => {
+            let line = alloc::format!("graphics mode is not available: {error:?}\n");
+            print(line.as_bytes());
+            exit(1);
+        }
+    };
+
+    // 丢弃启动前产生的事件，如启动本程序时的回车键
+    while input::poll(&mut [InputEvent::default(); 16]).is_ok() {}
+
+    draw_background(&mut surface);
+    surface.present().expect("failed to present surface");
+
+    // 弹跳的方块，每帧只输出方块移动前后覆盖的区域，按任意键或鼠标按键退出
+    let (width, height) = (surface.width(), surface.height());
+    let (mut x, mut y) = (0u32, 0u32);
+    let (mut dx, mut dy) = (4i32, 3i32);
+    let mut frame = 0u32;
+    while !exit_requested() {
+        let old = Rect::new(x, y, BOX_SIZE, BOX_SIZE);
+        draw_background_rect(&mut surface, old);
+        (x, dx) = step(x, dx, width.saturating_sub(BOX_SIZE));
+        (y, dy) = step(y, dy, height.saturating_sub(BOX_SIZE));
+        let new = Rect::new(x, y, BOX_SIZE, BOX_SIZE);
+        surface.fill_rect(new, rgb(255, (frame % 256) as u8, 64));
+        surface.draw_rect(new, rgb(255, 255, 255));
+        surface
+            .present_rect(union(old, new))
+            .expect("failed to present surface");
+        _ = sleep_thread(0, FRAME_INTERVAL_NS);
+        frame = frame.wrapping_add(1);
+    }
+
+    exit(0);
+}
+
+/// 自上一帧以来是否按下了任意键或鼠标按键
+fn exit_requested() -> bool {
+    let mut events = [InputEvent::default(); 16];
+    let Ok(count) = input::poll(&mut events) else {
+        return false;
+    };
+    events[..count]
+        .iter()
+        .any(|event| matches!(event.kind, EVENT_KEY | EVENT_MOUSE_BUTTON) && event.pressed != 0)
+}
+
+/// 绘制渐变背景与几个色块
+fn draw_background(surface: &mut Surface) {
+    draw_background_rect(surface, Rect::new(0, 0, surface.width(), surface.height()));
+}
+
+/// 重新绘制背景中rect覆盖的部分
+fn draw_background_rect(surface: &mut Surface, rect: Rect) {
+    let rect = rect.clip(surface.width(), surface.height());
+    let height = surface.height().max(1);
+    for row in rect.y..rect.y + rect.height {
+        let shade = (row * 255 / height) as u8;
+        surface.fill_rect(
+            Rect::new(rect.x, row, rect.width, 1),
+            rgb(0, shade / 2, shade),
+        );
+    }
+    // 色块覆盖在渐变之上，与rect相交的部分同样需要重新绘制
+    let colors = [rgb(220, 50, 50), rgb(50, 200, 80), rgb(240, 200, 40)];
+    for (index, color) in colors.into_iter().enumerate() {
+        let block = Rect::new(40 + index as u32 * 120, 40, 100, 60);
+        if let Some(block) = intersect(block, rect) {
+            surface.fill_rect(block, color);
+        }
+    }
+}
+
+/// 沿一个方向移动，到达边界时反弹
+fn step(position: u32, delta: i32, max: u32) -> (u32, i32) {
+    let next = position as i64 + delta as i64;
+    if next < 0 || next > max as i64 {
+        (position, -delta)
+    } else {
+        (next as u32, delta)
+    }
+}
+
+fn intersect(a: Rect, b: Rect) -> Option<Rect> {
+    let x = a.x.max(b.x);
+    let y = a.y.max(b.y);
+    let right = (a.x + a.width).min(b.x + b.width);
+    let bottom = (a.y + a.height).min(b.y + b.height);
+    (x < right && y < bottom).then(|| Rect::new(x, y, right - x, bottom - y))
+}
+
+fn union(a: Rect, b: Rect) -> Rect {
+    let x = a.x.min(b.x);
+    let y = a.y.min(b.y);
+    let right = (a.x + a.width).max(b.x + b.width);
+    let bottom = (a.y + a.height).max(b.y + b.height);
+    Rect::new(x, y, right - x, bottom - y)
+}
+
+fn print(string: &[u8]) {
+    put_str(string).expect("failed to print string");
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    exit(3);
+}
+
+#[cfg(test)]
+fn test_runner(_test_cases: &[&dyn Fn()]) {}
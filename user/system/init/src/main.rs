@@ -3,10 +3,29 @@
 #![feature(custom_test_frameworks)]
 #![test_runner(test_runner)]
 
-use cos_sys::multitask::{create_process, exit, wait_process};
+use cos_sys::{
+    file::{close, open},
+    multitask::{
+        Credentials, create_process, create_process_with_args, exit, set_credentials, wait_process,
+    },
+};
+
+/// 启动时执行的命令文件，不存在时跳过
+const AUTOEXEC_PATH: &str = "/system/autoexec";
+/// 交互shell使用的用户与组
+///
+/// 启动命令文件以超级用户执行，可以挂载文件系统；之后init放弃超级用户身份，
+/// shell及其创建的进程无法访问超级用户的文件
+const SHELL_CREDENTIALS: Credentials = Credentials {
+    uid: 1000,
+    gid: 1000,
+};
 
 #[unsafe(export_name = "_start")]
 fn main() -> ! {
+    run_autoexec();
+
+    set_credentials(SHELL_CREDENTIALS).expect("failed to drop privileges");
     let handle = create_process("/system/shell").expect("failed to start shell process");
     let code = wait_process(handle).expect("failed to wait for shell process");
     if code != 0 {
@@ -15,6 +34,20 @@ fn main() -> ! {
     panic!("shell exit");
 }
 
+/// 由shell执行启动命令文件并等待其结束，命令文件执行失败不影响之后的启动
+fn run_autoexec() {
+    let Ok(file) = open(AUTOEXEC_PATH.as_bytes()) else {
+        return;
+    };
+    let _ = close(file);
+
+    let mut args = [0u8; AUTOEXEC_PATH.len() + 1];
+    args[..AUTOEXEC_PATH.len()].copy_from_slice(AUTOEXEC_PATH.as_bytes());
+    if let Ok(handle) = create_process_with_args("/system/shell", &args) {
+        let _ = wait_process(handle);
+    }
+}
+
 #[panic_handler]
 fn panic(_info: &core::panic::PanicInfo) -> ! {
     exit(3);
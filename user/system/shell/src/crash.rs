@@ -0,0 +1,112 @@
+//! crashinfo命令：查看内核保存的上次panic时的崩溃转储，格式见kernel/src/crash_dump.rs
+
+use alloc::{format, vec::Vec};
+use cos_sys::error::ErrorKind;
+
+use crate::{KERNEL_MAP_PATH, Status, print, script::read_file};
+
+/// 崩溃转储保存的路径，需与kernel/src/crash_dump.rs保持一致
+const DUMP_PATH: &[u8] = b"/system/crash/last.dump";
+/// 崩溃转储的magic，需与kernel/src/crash_dump.rs保持一致
+const DUMP_MAGIC: &[u8; 16] = b"COS_CRASH_DUMP__";
+/// 崩溃转储格式的版本，需与kernel/src/crash_dump.rs保持一致
+const DUMP_VERSION: u32 = 1;
+/// 头部记录的最大调用栈深度，需与kernel/src/crash_dump.rs保持一致
+const MAX_FRAMES: usize = 32;
+const SECTOR_SIZE: usize = 512;
+
+/// 输出崩溃转储中的报告与调用栈，stack为true时同时输出panic时的内核栈
+pub fn print_crash_info(stack: bool) -> Status {
+    let dump = match read_file(DUMP_PATH) {
+        Ok(dump) => dump,
+        Err(error) if error.kind() == ErrorKind::FileNotFound => {
+            print(b"no crash dump\n");
+            return Status::Success;
+        }
+        Err(error) => {
+            print(format!("crashinfo failed: {}\n", error).as_bytes());
+            return Status::Failed;
+        }
+    };
+    let u32_at = |offset: usize| u32::from_le_bytes(dump[offset..offset + 4].try_into().unwrap());
+    let u64_at = |offset: usize| u64::from_le_bytes(dump[offset..offset + 8].try_into().unwrap());
+    if dump.len() < SECTOR_SIZE
+        || &dump[..DUMP_MAGIC.len()] != DUMP_MAGIC
+        || u32_at(16) != DUMP_VERSION
+    {
+        print(b"crashinfo failed: invalid crash dump\n");
+        return Status::Failed;
+    }
+    let uptime = u64_at(24);
+    let report_size = u32_at(32) as usize;
+    let stack_size = u32_at(36) as usize;
+    let stack_address = u64_at(40);
+    let depth = (u32_at(48) as usize).min(MAX_FRAMES);
+    let stack_offset = SECTOR_SIZE + report_size.next_multiple_of(SECTOR_SIZE);
+    if dump.len() < stack_offset + stack_size {
+        print(b"crashinfo failed: truncated crash dump\n");
+        return Status::Failed;
+    }
+
+    // 内核符号表不存在时只输出地址
+    let map = read_file(KERNEL_MAP_PATH).unwrap_or_default();
+    let symbols = parse_symbols(&map);
+
+    print(
+        format!(
+            "kernel crashed at {}.{:06}s after boot\n\n",
+            uptime / 1_000_000_000,
+            uptime % 1_000_000_000 / 1000
+        )
+        .as_bytes(),
+    );
+    print(&dump[SECTOR_SIZE..SECTOR_SIZE + report_size]);
+    print(b"\nbacktrace:\n");
+    for index in 0..depth {
+        let frame = u64_at(56 + index * 8);
+        print(format!("  #{index:<2} {frame:016x} ").as_bytes());
+        print_symbol(&symbols, frame);
+        print(b"\n");
+    }
+
+    if stack {
+        print(b"\nstack:\n");
+        let words = dump[stack_offset..stack_offset + stack_size].chunks_exact(8);
+        for (index, word) in words.enumerate() {
+            let value = u64::from_le_bytes(word.try_into().unwrap());
+            print(format!("  {:016x}: {value:016x} ", stack_address + index as u64 * 8).as_bytes());
+            print_symbol(&symbols, value);
+            print(b"\n");
+        }
+    }
+    Status::Success
+}
+
+/// 解析内核符号表，格式见build-scripts/src/build_info.rs，返回按地址升序排列的 (地址, 大小, 名称)
+fn parse_symbols(map: &[u8]) -> Vec<(u64, u64, &[u8])> {
+    map.split(|&ch| ch == b'\n')
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, |&ch| ch == b' ');
+            let address = parse_hex(fields.next()?)?;
+            let size = parse_hex(fields.next()?)?;
+            Some((address, size, fields.next()?))
+        })
+        .collect()
+}
+
+fn parse_hex(field: &[u8]) -> Option<u64> {
+    u64::from_str_radix(str::from_utf8(field).ok()?, 16).ok()
+}
+
+/// 输出地址所在的符号，如 `kernel::main+0x1a`，不在任何符号中时不输出
+fn print_symbol(symbols: &[(u64, u64, &[u8])], address: u64) {
+    let index = symbols.partition_point(|(start, _, _)| *start <= address);
+    let Some(&(start, size, name)) = index.checked_sub(1).map(|index| &symbols[index]) else {
+        return;
+    };
+    // 没有大小的符号只匹配其起始地址
+    if address - start < size.max(1) {
+        print(name);
+        print(format!("+{:#x}", address - start).as_bytes());
+    }
+}
@@ -0,0 +1,109 @@
+use core::time::Duration;
+
+use alloc::{format, string::String, vec::Vec};
+use cos_sys::{
+    ipc::{POLL_EXIT, POLL_INVALID, PollFd, poll},
+    multitask::{kill_process, wait_process},
+};
+
+use crate::print;
+
+/// 在后台运行的进程
+pub struct Job {
+    // 任务编号，从1开始
+    pub id: usize,
+    // 进程句柄
+    pub handle: u64,
+    // 进程组ID，0表示进程仍在shell的进程组中
+    pub process_group: u64,
+    // 启动任务的命令
+    pub command: Vec<u8>,
+}
+
+/// 后台任务表
+pub struct Jobs {
+    jobs: Vec<Job>,
+}
+
+impl Jobs {
+    pub const fn new() -> Self {
+        Self { jobs: Vec::new() }
+    }
+
+    /// 加入后台任务，返回任务编号
+    ///
+    /// 编号为现有最大编号加1，全部任务结束后重新从1开始
+    pub fn add(&mut self, handle: u64, process_group: u64, command: &[u8]) -> usize {
+        let id = self.jobs.last().map_or(1, |job| job.id + 1);
+        self.jobs.push(Job {
+            id,
+            handle,
+            process_group,
+            command: command.to_vec(),
+        });
+        id
+    }
+
+    /// 取出指定编号的任务，未指定编号时取出最近加入的任务
+    pub fn take(&mut self, id: Option<usize>) -> Option<Job> {
+        let index = match id {
+            Some(id) => self.jobs.iter().position(|job| job.id == id)?,
+            None => self.jobs.len().checked_sub(1)?,
+        };
+        Some(self.jobs.remove(index))
+    }
+
+    /// 回收已退出的任务并输出其退出码，不会挂起当前线程
+    pub fn reap(&mut self) {
+        if self.jobs.is_empty() {
+            return;
+        }
+        let mut fds = self
+            .jobs
+            .iter()
+            .map(|job| PollFd::new(job.handle, POLL_EXIT))
+            .collect::<Vec<_>>();
+        if poll(&mut fds, Some(Duration::ZERO)).unwrap_or(0) == 0 {
+            return;
+        }
+
+        let mut index = 0;
+        self.jobs.retain(|job| {
+            let revents = fds[index].revents;
+            index += 1;
+            if revents & (POLL_EXIT | POLL_INVALID) == 0 {
+                return true;
+            }
+            let state = match wait_process(job.handle) {
+                Ok(code) => format!("Done({code})"),
+                Err(_) => String::from("Lost"),
+            };
+            print_job(job, &state);
+            false
+        });
+    }
+
+    /// 强制停止指定编号的任务
+    pub fn kill(&mut self, id: usize) -> bool {
+        let Some(job) = self.take(Some(id)) else {
+            return false;
+        };
+        let _ = kill_process(job.handle);
+        // 回收进程句柄
+        let _ = wait_process(job.handle);
+        print_job(&job, "Killed");
+        true
+    }
+
+    pub fn print(&self) {
+        for job in &self.jobs {
+            print_job(job, "Running");
+        }
+    }
+}
+
+fn print_job(job: &Job, state: &str) {
+    print(format!("[{}] {:<10} ", job.id, state).as_bytes());
+    print(&job.command);
+    print(b"\n");
+}
@@ -0,0 +1,331 @@
+use alloc::{collections::vec_deque::VecDeque, format, vec::Vec};
+use cos_sys::{
+    debug::get_char,
+    file::{DirectoryEntryInfo, list_directory},
+};
+
+use crate::print;
+
+/// 一行的最大长度，提示符与输入需位于屏幕的同一行内，光标移动不会跨行
+const MAX_LINE_LEN: usize = 70;
+/// 保存的历史命令数量
+const HISTORY_LEN: usize = 32;
+
+/// 行编辑器，支持光标移动、插入与删除、历史命令与Tab补全
+///
+/// 方向键等由键盘驱动转换为ANSI转义序列输入，编辑结果同样通过ANSI转义序列回显
+pub struct LineEditor {
+    // 可补全的命令名
+    commands: &'static [&'static [u8]],
+    // 历史命令，最早的位于开头
+    history: VecDeque<Vec<u8>>,
+    // 转义序列之后多读取的字符，由下一次读取返回
+    pending: Option<u8>,
+}
+
+enum Key {
+    Char(u8),
+    Enter,
+    // Ctrl+C，放弃正在编辑的行
+    Interrupt,
+    Backspace,
+    Delete,
+    Tab,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Unknown,
+}
+
+/// 正在编辑的行
+struct Line<'a> {
+    prompt: &'a [u8],
+    buffer: Vec<u8>,
+    cursor: usize,
+    // 正在浏览的历史命令下标，以及开始浏览前正在编辑的内容
+    history_index: Option<usize>,
+    draft: Vec<u8>,
+}
+
+impl LineEditor {
+    pub fn new(commands: &'static [&'static [u8]]) -> Self {
+        Self {
+            commands,
+            history: VecDeque::new(),
+            pending: None,
+        }
+    }
+
+    /// 输出提示符并读取一行，返回的内容不含换行符，按下Ctrl+C时放弃已输入的内容并返回空行
+    pub fn read_line(&mut self, prompt: &[u8]) -> Vec<u8> {
+        print(prompt);
+        let mut line = Line {
+            prompt,
+            buffer: Vec::new(),
+            cursor: 0,
+            history_index: None,
+            draft: Vec::new(),
+        };
+
+        loop {
+            match self.read_key() {
+                Key::Enter => break,
+                Key::Interrupt => {
+                    print(b"^C");
+                    line.buffer.clear();
+                    break;
+                }
+                Key::Char(char) => line.insert(&[char]),
+                Key::Backspace => {
+                    if line.cursor > 0 {
+                        line.move_to(line.cursor - 1);
+                        line.delete();
+                    }
+                }
+                Key::Delete => line.delete(),
+                Key::Tab => self.complete(&mut line),
+                Key::Left => line.move_to(line.cursor.saturating_sub(1)),
+                Key::Right => line.move_to((line.cursor + 1).min(line.buffer.len())),
+                Key::Home => line.move_to(0),
+                Key::End => line.move_to(line.buffer.len()),
+                Key::Up => self.history_prev(&mut line),
+                Key::Down => self.history_next(&mut line),
+                Key::Unknown => (),
+            }
+        }
+
+        print(b"\n");
+        let buffer = line.buffer;
+        if !buffer.is_empty() && self.history.back() != Some(&buffer) {
+            if self.history.len() >= HISTORY_LEN {
+                self.history.pop_front();
+            }
+            self.history.push_back(buffer.clone());
+        }
+        buffer
+    }
+
+    fn read_char(&mut self) -> u8 {
+        match self.pending.take() {
+            Some(char) => char,
+            None => get_char().expect("failed to get char"),
+        }
+    }
+
+    fn read_key(&mut self) -> Key {
+        match self.read_char() {
+            b'\n' => Key::Enter,
+            0x03 => Key::Interrupt,
+            0x08 => Key::Backspace,
+            b'\t' => Key::Tab,
+            0x1B => self.read_escape(),
+            char @ 0x20..=0x7E => Key::Char(char),
+            _ => Key::Unknown,
+        }
+    }
+
+    /// 读取`ESC`之后的转义序列
+    fn read_escape(&mut self) -> Key {
+        let char = self.read_char();
+        if char != b'[' {
+            // 单独按下Esc键，之后的字符作为普通输入
+            self.pending = Some(char);
+            return Key::Unknown;
+        }
+        let mut param = 0u32;
+        loop {
+            match self.read_char() {
+                digit @ b'0'..=b'9' => param = param.saturating_mul(10) + (digit - b'0') as u32,
+                b'A' => return Key::Up,
+                b'B' => return Key::Down,
+                b'C' => return Key::Right,
+                b'D' => return Key::Left,
+                b'H' => return Key::Home,
+                b'F' => return Key::End,
+                b'~' if param == 3 => return Key::Delete,
+                0x40..=0x7E => return Key::Unknown,
+                _ => (),
+            }
+        }
+    }
+
+    fn history_prev(&self, line: &mut Line) {
+        let index = match line.history_index {
+            None if !self.history.is_empty() => {
+                line.draft = line.buffer.clone();
+                self.history.len() - 1
+            }
+            Some(index) if index > 0 => index - 1,
+            _ => return,
+        };
+        line.history_index = Some(index);
+        line.replace(&self.history[index]);
+    }
+
+    fn history_next(&self, line: &mut Line) {
+        let Some(index) = line.history_index else {
+            return;
+        };
+        if index + 1 < self.history.len() {
+            line.history_index = Some(index + 1);
+            line.replace(&self.history[index + 1]);
+        } else {
+            line.history_index = None;
+            let draft = core::mem::take(&mut line.draft);
+            line.replace(&draft);
+        }
+    }
+
+    /// 补全光标前的单词
+    ///
+    /// 第一个单词补全为命令名，以`/`开头的单词补全为路径，其他单词不补全。
+    /// 只有一个候选项时直接补全，有多个候选项时补全其公共前缀，无法继续补全时列出所有候选项
+    fn complete(&self, line: &mut Line) {
+        let word_start = line.buffer[..line.cursor]
+            .iter()
+            .rposition(|&char| char == b' ')
+            .map_or(0, |index| index + 1);
+        let word = &line.buffer[word_start..line.cursor];
+
+        // 候选项为单词的完整形式，补全后追加的字符
+        let (prefix_len, candidates): (usize, Vec<(Vec<u8>, u8)>) = if word.starts_with(b"/") {
+            let name_start = word.iter().rposition(|&char| char == b'/').unwrap() + 1;
+            let directory = if name_start == 1 {
+                &word[..1]
+            } else {
+                &word[..name_start - 1]
+            };
+            let prefix = &word[name_start..];
+            let candidates = list_entries(directory)
+                .iter()
+                .filter(|entry| entry.name().starts_with(prefix))
+                .map(|entry| {
+                    let suffix = if entry.is_directory() { b'/' } else { b' ' };
+                    (entry.name().to_vec(), suffix)
+                })
+                .collect();
+            (prefix.len(), candidates)
+        } else if word_start == 0 {
+            let candidates = self
+                .commands
+                .iter()
+                .filter(|command| command.starts_with(word))
+                .map(|command| (command.to_vec(), b' '))
+                .collect();
+            (word.len(), candidates)
+        } else {
+            return;
+        };
+
+        match &candidates[..] {
+            [] => (),
+            [(name, suffix)] => {
+                let mut completion = name[prefix_len..].to_vec();
+                completion.push(*suffix);
+                line.insert(&completion);
+            }
+            [(first, _), rest @ ..] => {
+                let common_len = rest.iter().fold(first.len(), |len, (name, _)| {
+                    first[..len]
+                        .iter()
+                        .zip(name)
+                        .take_while(|(a, b)| a == b)
+                        .count()
+                });
+                if common_len > prefix_len {
+                    line.insert(&first[prefix_len..common_len]);
+                    return;
+                }
+                print(b"\n");
+                for (name, _) in &candidates {
+                    print(name);
+                    print(b"  ");
+                }
+                print(b"\n");
+                line.redraw();
+            }
+        }
+    }
+}
+
+impl Line<'_> {
+    /// 在光标处插入，超出最大长度的部分被丢弃
+    fn insert(&mut self, bytes: &[u8]) {
+        let count = bytes.len().min(MAX_LINE_LEN - self.buffer.len());
+        if count == 0 {
+            return;
+        }
+        let bytes = &bytes[..count];
+        self.buffer
+            .splice(self.cursor..self.cursor, bytes.iter().copied());
+        print(&self.buffer[self.cursor..]);
+        self.cursor += count;
+        cursor_back(self.buffer.len() - self.cursor);
+    }
+
+    /// 删除光标处的字符
+    fn delete(&mut self) {
+        if self.cursor >= self.buffer.len() {
+            return;
+        }
+        self.buffer.remove(self.cursor);
+        print(&self.buffer[self.cursor..]);
+        print(b"\x1b[K");
+        cursor_back(self.buffer.len() - self.cursor);
+    }
+
+    fn move_to(&mut self, cursor: usize) {
+        if cursor < self.cursor {
+            cursor_back(self.cursor - cursor);
+        } else {
+            cursor_forward(cursor - self.cursor);
+        }
+        self.cursor = cursor;
+    }
+
+    /// 以新的内容替换整行，光标移至末尾
+    fn replace(&mut self, buffer: &[u8]) {
+        self.move_to(0);
+        self.buffer = buffer[..buffer.len().min(MAX_LINE_LEN)].to_vec();
+        print(&self.buffer);
+        print(b"\x1b[K");
+        self.cursor = self.buffer.len();
+    }
+
+    /// 在新的一行重新输出提示符与内容
+    fn redraw(&self) {
+        print(self.prompt);
+        print(&self.buffer);
+        cursor_back(self.buffer.len() - self.cursor);
+    }
+}
+
+fn cursor_back(count: usize) {
+    if count > 0 {
+        print(format!("\x1b[{count}D").as_bytes());
+    }
+}
+
+fn cursor_forward(count: usize) {
+    if count > 0 {
+        print(format!("\x1b[{count}C").as_bytes());
+    }
+}
+
+/// 列出文件夹中的内容，文件夹不存在时返回空列表
+fn list_entries(directory: &[u8]) -> Vec<DirectoryEntryInfo> {
+    let mut entries = alloc::vec![DirectoryEntryInfo::default(); 16];
+    loop {
+        let Ok(count) = list_directory(directory, &mut entries) else {
+            return Vec::new();
+        };
+        if count <= entries.len() {
+            entries.truncate(count);
+            return entries;
+        }
+        entries.resize(count, DirectoryEntryInfo::default());
+    }
+}
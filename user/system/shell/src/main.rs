@@ -6,102 +6,1137 @@
 extern crate alloc;
 extern crate rlibc;
 
+mod crash;
+mod jobs;
+mod line_editor;
+mod script;
+
+use core::time::Duration;
+
 use cos_sys::{
-    debug::{get_char, put_char},
-    file::{close, open, read},
-    multitask::{exit, sleep_thread},
+    debug::{
+        LOG_LEVEL_DEBUG, LOG_LEVEL_DEFAULT, LOG_LEVEL_ERROR, LOG_LEVEL_INFO, LOG_LEVEL_OFF,
+        LOG_LEVEL_WARN, MEMORY_REGION_EXECUTABLE, MEMORY_REGION_FILE, MEMORY_REGION_IMAGE,
+        MEMORY_REGION_STACK, MEMORY_REGION_VDSO, MEMORY_REGION_WRITABLE, MemoryRegionInfo,
+        SyscallTraceRecord, TRACE_CHILDREN, memory_maps, open_keyboard, put_str, read_trace,
+        set_log_filter, trace_process,
+    },
+    error::ErrorKind,
+    file::{
+        BlockDeviceInfo, ConsoleSize, close, console_size, list_block_devices, mount,
+        mount_encrypted, open, read, scrub, set_console_size, unmount,
+    },
+    handle::OwnedHandle,
+    idx,
+    memory::memory_stats,
+    module::{self, ModuleInfo},
+    multitask::{
+        EXIT_INTERRUPT, EXIT_SUCCESS, LIMIT_ASYNC_REQUESTS, LIMIT_CHILDREN, LIMIT_CPU_TIME,
+        LIMIT_HANDLES, LIMIT_KERNEL_MEMORY, LIMIT_PAGES, LIMIT_SYSCALL_BUFFER, LIMIT_UNLIMITED,
+        MAX_ARGS_LEN, PROCESS_STATE_EXITING, PROCESS_STATE_RUNNING, Process, create_process,
+        create_process_with_args, create_session, current_process, exit, list_processes,
+        process_args, process_info, set_foreground_group, set_process_group, sleep_thread,
+        split_args, wait_process,
+    },
+    sound::beep,
+    system::{reboot, shutdown, update_kernel},
+    time::{DateTime, SystemTime, utc_offset},
+};
+
+use crate::{
+    jobs::Jobs,
+    line_editor::LineEditor,
+    script::{Variables, is_variable_name, read_file},
 };
 
 cos_heap::default_heap!();
 
+/// 内置命令，用于Tab补全
+const BUILTIN_COMMANDS: &[&[u8]] = &[
+    b"help",
+    b"exit",
+    b"poweroff",
+    b"reboot",
+    b"echo",
+    b"ps",
+    b"meminfo",
+    b"date",
+    b"ver",
+    b"crashinfo",
+    b"update",
+    b"maps",
+    b"strace",
+    b"klog",
+    b"mount",
+    b"umount",
+    b"scrub",
+    b"sleep",
+    b"beep",
+    b"stty",
+    b"insmod",
+    b"rmmod",
+    b"lsmod",
+    b"sh",
+    b"set",
+    b"unset",
+    b"jobs",
+    b"fg",
+    b"kill",
+    b"ulimit",
+];
+
+/// ulimit可查看与设置的资源限制
+const LIMITS: &[(&str, u64)] = &[
+    ("kernel-memory", LIMIT_KERNEL_MEMORY),
+    ("syscall-buffer", LIMIT_SYSCALL_BUFFER),
+    ("async-requests", LIMIT_ASYNC_REQUESTS),
+    ("handles", LIMIT_HANDLES),
+    ("pages", LIMIT_PAGES),
+    ("children", LIMIT_CHILDREN),
+    ("cpu-time", LIMIT_CPU_TIME),
+];
+
+/// 命令文件执行失败时shell的退出码
+const EXIT_SCRIPT_FAILED: u64 = 2;
+
+/// 构建信息与内核符号表，由build-scripts生成，格式见build-scripts/src/build_info.rs
+const BUILD_INFO_PATH: &[u8] = b"/system/build-info";
+const KERNEL_MAP_PATH: &[u8] = b"/system/kernel.map";
+
+/// 命令的执行结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Success,
+    Failed,
+    /// 退出shell
+    Exit,
+}
+
+/// shell的状态
+struct Shell {
+    variables: Variables,
+    // 后台任务
+    jobs: Jobs,
+}
+
 #[unsafe(export_name = "_start")]
 fn main() -> ! {
-    print_welcome_file();
-    print(b"\n> ");
+    let mut shell = Shell {
+        variables: Variables::new(),
+        jobs: Jobs::new(),
+    };
 
-    let mut buffer = [0u8; 70];
-    let mut len = 0;
+    // 以命令文件路径为启动参数时，执行命令文件后退出，不进入交互模式
+    if let Some(path) = script_argument() {
+        let code = match script::run_script(&path, &mut shell) {
+            Status::Failed => EXIT_SCRIPT_FAILED,
+            _ => EXIT_SUCCESS,
+        };
+        exit(code);
+    }
 
-    loop {
-        let char = get_char().expect("failed to get char");
-
-        // 特殊char处理
-        match char {
-            b'\n' => {
-                put_char(b'\n').expect("failed to new line");
-                let should_exit = process_command(&buffer[..len]);
-                if should_exit {
-                    break;
-                }
-                len = 0;
-                print(b"> ");
-                continue;
-            }
-            0x08 => {
-                if len > 0 {
-                    print(&[0x08, b' ', 0x08]);
-                    len -= 1;
-                }
-                continue;
-            }
-            _ => (),
-        }
+    // 交互模式下成为会话首进程，以便设置控制台的前台进程组
+    _ = create_session();
+    print_welcome_file();
+    print(b"\n");
 
-        // 追加到缓冲
-        if len < buffer.len() {
-            buffer[len] = char;
-            len += 1;
-            put_char(char).expect("failed to put char");
-            continue;
+    let mut editor = LineEditor::new(BUILTIN_COMMANDS);
+    loop {
+        shell.jobs.reap();
+        let line = editor.read_line(b"> ");
+        if execute(&line, &mut shell) == Status::Exit {
+            break;
         }
     }
 
-    exit(0);
+    // 退出shell即关机
+    let error = shutdown();
+    print(alloc::format!("shutdown failed: {}\n", error).as_bytes());
+    exit(1);
+}
+
+/// 第一个启动参数，即要执行的命令文件路径
+fn script_argument() -> Option<alloc::vec::Vec<u8>> {
+    let mut args = alloc::vec![0u8; MAX_ARGS_LEN];
+    let len = process_args(&mut args).expect("failed to get process args");
+    split_args(&args[..len.min(MAX_ARGS_LEN)])
+        .next()
+        .map(<[u8]>::to_vec)
+}
+
+/// 展开变量后执行命令
+fn execute(line: &[u8], shell: &mut Shell) -> Status {
+    let cmd = script::expand(line, &shell.variables);
+    process_command(cmd.trim_ascii(), shell)
 }
 
-fn process_command(cmd: &[u8]) -> bool {
-    if cmd.len() == 0 {
-        return false;
+fn process_command(cmd: &[u8], shell: &mut Shell) -> Status {
+    if cmd.is_empty() {
+        return Status::Success;
     }
 
     if cmd == b"help" {
         print(b"COS Shell Helper:\n");
         print(b"  help - print this message\n");
-        print(b"  exit - exit shell interactive\n");
-        print(b"         (currently this will trigger kernel panic)\n");
+        print(b"  up/down - recall history, tab - complete commands and paths\n");
+        print(b"  exit, poweroff - unmount file systems and power off\n");
+        print(b"  reboot - unmount file systems and restart\n");
         print(b"  echo <msg> - print message after `echo` words\n");
+        print(b"  ps - list running processes\n");
+        print(b"  meminfo - show physical memory, kernel heap and per-process memory usage\n");
+        print(b"  date - print local date and time\n");
+        print(b"  ver - print build information of the system image\n");
+        print(b"  crashinfo [-s] - print crash dump of the last kernel panic, -s with stack\n");
+        print(b"  update <path> - install kernel image at path, boot it after reboot\n");
+        print(b"  maps <pid> - list memory regions of process\n");
+        print(b"  strace <exe> - run program and print its system calls\n");
+        print(b"  klog <target> <level> - filter kernel log of target\n");
+        print(b"    level: off, error, warn, info, debug, default\n");
+        print(b"  mount - rescan partitions and list block devices\n");
+        print(b"  mount <device> <path> - mount FAT32 file system on block device at path\n");
+        print(b"  mount <device> <path> <passphrase> - unlock encrypted volume and mount it at path\n");
+        print(b"  umount <path> - unmount file system at path\n");
+        print(b"  scrub <device> - verify all blocks of checksummed block device\n");
+        print(b"  beep [<frequency> [<ms>]] - beep with PC speaker, default to 880Hz 200ms\n");
+        print(b"  stty [<rows> <cols>] - print or resize console\n");
+        print(b"  insmod <name> - load kernel module /system/drivers/<name>.ko\n");
+        print(b"  rmmod <name> - unload kernel module\n");
+        print(b"  lsmod - list loaded kernel modules\n");
+        print(b"  sh <path> - run commands in file, stop at the first failed command\n");
+        print(b"  set [<name> <value>] - set variable, or list variables, use as $name\n");
+        print(b"  unset <name> - remove variable\n");
+        print(b"  <exe> [args] [&] - run program, `&` to run in background\n");
+        print(b"  jobs - list background jobs\n");
+        print(b"  fg [<job>] - wait for background job, default to the latest one\n");
+        print(b"  ctrl+c - interrupt foreground program\n");
+        print(b"  kill <job> - kill background job\n");
+        print(b"  ulimit [<name> <value>] - lower resource limit of shell and its programs, or list limits\n");
+        print(b"    value: number or unlimited, cpu-time in microseconds\n");
         print(b"\n");
-        return false;
+        return Status::Success;
+    }
+
+    if cmd == b"exit" || cmd == b"poweroff" {
+        return Status::Exit;
+    }
+
+    if cmd == b"reboot" {
+        let error = reboot();
+        print(alloc::format!("reboot failed: {}\n", error).as_bytes());
+        return Status::Failed;
+    }
+
+    if cmd == b"ps" {
+        print_processes();
+        return Status::Success;
+    }
+
+    if cmd == b"meminfo" {
+        print_memory_info();
+        return Status::Success;
+    }
+
+    if cmd == b"date" {
+        return print_date();
+    }
+
+    if cmd == b"ver" {
+        return print_version();
+    }
+
+    if cmd == b"crashinfo" || cmd == b"crashinfo -s" {
+        return crash::print_crash_info(cmd.ends_with(b"-s"));
+    }
+
+    if let Some(path) = cmd.strip_prefix(b"update ") {
+        // 无效的UTF-8按空路径处理，由内核返回BadArgument
+        let path = str::from_utf8(path.trim_ascii()).unwrap_or_default();
+        if let Err(error) = update_kernel(path) {
+            print(alloc::format!("update failed: {}\n", error).as_bytes());
+            return Status::Failed;
+        }
+        print(b"kernel installed, reboot to boot it\n");
+        return Status::Success;
+    }
+
+    if let Some(process_id) = cmd.strip_prefix(b"maps ")
+        && let Some(process_id) = str::from_utf8(process_id)
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+    {
+        return print_memory_maps(process_id);
+    }
+
+    if let Some(exe) = cmd.strip_prefix(b"strace ") {
+        return run_traced(exe.trim_ascii());
+    }
+
+    if let Some(args) = cmd.strip_prefix(b"klog ") {
+        let mut args = args.split(|&ch| ch == b' ').filter(|arg| !arg.is_empty());
+        if let (Some(target), Some(level), None) = (args.next(), args.next(), args.next()) {
+            return set_kernel_log_filter(target, level);
+        }
+    }
+
+    if cmd == b"ulimit" {
+        return print_limits();
+    }
+
+    if let Some(args) = cmd.strip_prefix(b"ulimit ") {
+        let mut args = args.split(|&ch| ch == b' ').filter(|arg| !arg.is_empty());
+        if let (Some(name), Some(value), None) = (args.next(), args.next(), args.next()) {
+            return set_limit(name, value);
+        }
+    }
+
+    if cmd == b"mount" {
+        print_block_devices();
+        return Status::Success;
+    }
+
+    if let Some(args) = cmd.strip_prefix(b"mount ") {
+        let mut args = args.split(|&ch| ch == b' ').filter(|arg| !arg.is_empty());
+        let result = match (args.next(), args.next(), args.next(), args.next()) {
+            (Some(device), Some(path), None, None) => Some(mount(device, path)),
+            (Some(device), Some(path), Some(passphrase), None) => {
+                Some(mount_encrypted(device, path, passphrase))
+            }
+            _ => None,
+        };
+        if let Some(result) = result {
+            if let Err(error) = result {
+                print(alloc::format!("mount failed: {}\n", error).as_bytes());
+                return Status::Failed;
+            }
+            return Status::Success;
+        }
+    }
+
+    if let Some(path) = cmd.strip_prefix(b"umount ") {
+        if let Err(error) = unmount(path.trim_ascii()) {
+            print(alloc::format!("umount failed: {}\n", error).as_bytes());
+            return Status::Failed;
+        }
+        return Status::Success;
     }
 
-    if cmd == b"exit" {
-        return true;
+    if let Some(device) = cmd.strip_prefix(b"scrub ") {
+        return scrub_device(device.trim_ascii());
     }
 
     if let Some(msg) = cmd.strip_prefix(b"echo ") {
         print(msg);
         print(b"\n");
-        return false;
+        return Status::Success;
     }
 
-    if let Some(time) = cmd.strip_prefix(b"sleep ") {
-        if let Some(time_in_ms) = str::from_utf8(time)
+    if let Some(time) = cmd.strip_prefix(b"sleep ")
+        && let Some(time_in_ms) = str::from_utf8(time)
             .ok()
             .and_then(|s| s.parse::<u64>().ok())
-        {
-            sleep_thread(time_in_ms / 1000, time_in_ms % 1000 * 1000).unwrap();
-            return false;
+    {
+        sleep_thread(time_in_ms / 1000, time_in_ms % 1000 * 1000).unwrap();
+        return Status::Success;
+    }
+
+    if cmd == b"beep" || cmd.starts_with(b"beep ") {
+        return run_beep(&cmd[4..]);
+    }
+
+    if cmd == b"stty" || cmd.starts_with(b"stty ") {
+        return run_stty(&cmd[4..]);
+    }
+
+    if let Some(name) = cmd.strip_prefix(b"insmod ") {
+        // 无效的UTF-8按空名称处理，由内核返回BadArgument
+        let name = str::from_utf8(name.trim_ascii()).unwrap_or_default();
+        if let Err(error) = module::load(name) {
+            print(alloc::format!("insmod failed: {}\n", error).as_bytes());
+            return Status::Failed;
+        }
+        return Status::Success;
+    }
+
+    if let Some(name) = cmd.strip_prefix(b"rmmod ") {
+        // 无效的UTF-8按空名称处理，由内核返回BadArgument
+        let name = str::from_utf8(name.trim_ascii()).unwrap_or_default();
+        if let Err(error) = module::unload(name) {
+            print(alloc::format!("rmmod failed: {}\n", error).as_bytes());
+            return Status::Failed;
+        }
+        return Status::Success;
+    }
+
+    if cmd == b"lsmod" {
+        print_modules();
+        return Status::Success;
+    }
+
+    if let Some(path) = cmd.strip_prefix(b"sh ") {
+        return script::run_script(path.trim_ascii(), shell);
+    }
+
+    if cmd == b"set" {
+        for (name, value) in shell.variables.iter() {
+            print(name);
+            print(b"=");
+            print(value);
+            print(b"\n");
+        }
+        return Status::Success;
+    }
+
+    if let Some(args) = cmd.strip_prefix(b"set ") {
+        let args = args.trim_ascii_start();
+        let (name, value) = match args.iter().position(|&ch| ch == b' ') {
+            Some(index) => (&args[..index], args[index + 1..].trim_ascii()),
+            None => (args, &b""[..]),
+        };
+        if !is_variable_name(name) {
+            print(b"set: invalid variable name\n");
+            return Status::Failed;
+        }
+        shell.variables.insert(name.to_vec(), value.to_vec());
+        return Status::Success;
+    }
+
+    if let Some(name) = cmd.strip_prefix(b"unset ") {
+        shell.variables.remove(name.trim_ascii());
+        return Status::Success;
+    }
+
+    if cmd == b"jobs" {
+        shell.jobs.reap();
+        shell.jobs.print();
+        return Status::Success;
+    }
+
+    if cmd == b"fg" || cmd.starts_with(b"fg ") {
+        let id = match parse_job_id(&cmd[2..]) {
+            Ok(id) => id,
+            Err(()) => {
+                print(b"fg: invalid job\n");
+                return Status::Failed;
+            }
+        };
+        let Some(job) = shell.jobs.take(id) else {
+            print(b"fg: no such job\n");
+            return Status::Failed;
+        };
+        print(&job.command);
+        print(b"\n");
+        return wait_foreground(job.handle, job.process_group);
+    }
+
+    if let Some(id) = cmd.strip_prefix(b"kill ") {
+        let killed = match parse_job_id(id) {
+            Ok(Some(id)) => shell.jobs.kill(id),
+            _ => false,
+        };
+        if !killed {
+            print(b"kill: no such job\n");
+            return Status::Failed;
         }
+        return Status::Success;
+    }
+
+    // 以`/`开头的命令为程序路径
+    if cmd.starts_with(b"/") {
+        return run_program(cmd, shell);
     }
 
     print(b"Unsupported Command, type `help` to see help message.\n\n");
-    false
+    Status::Failed
+}
+
+/// 解析任务编号，可带`%`前缀，为空时返回None
+fn parse_job_id(id: &[u8]) -> Result<Option<usize>, ()> {
+    let id = id.trim_ascii();
+    if id.is_empty() {
+        return Ok(None);
+    }
+    let id = id.strip_prefix(b"%").unwrap_or(id);
+    str::from_utf8(id)
+        .ok()
+        .and_then(|id| id.parse::<usize>().ok())
+        .map(Some)
+        .ok_or(())
+}
+
+/// 运行程序，之后的单词作为启动参数。以`&`结尾时在后台运行，否则等待其退出
+fn run_program(cmd: &[u8], shell: &mut Shell) -> Status {
+    let (cmd, background) = match cmd.strip_suffix(b"&") {
+        Some(cmd) => (cmd.trim_ascii_end(), true),
+        None => (cmd, false),
+    };
+    let mut words = cmd.split(|&ch| ch == b' ').filter(|word| !word.is_empty());
+    let Some(exe) = words.next().and_then(|exe| str::from_utf8(exe).ok()) else {
+        print(b"invalid path\n");
+        return Status::Failed;
+    };
+    let mut args = alloc::vec::Vec::new();
+    for word in words {
+        args.extend_from_slice(word);
+        args.push(0);
+    }
+
+    let handle = match create_process_with_args(exe, &args) {
+        Ok(handle) => handle,
+        Err(error) => {
+            print(alloc::format!("start {exe} failed: {}\n", error).as_bytes());
+            return Status::Failed;
+        }
+    };
+    // 程序在独立的进程组中运行，Ctrl+C只中断前台程序而不影响shell。
+    // 程序可能在此之前已经退出，此时仍留在shell的进程组中
+    let process_group = set_process_group(handle, 0).unwrap_or(0);
+    if background {
+        let id = shell.jobs.add(handle, process_group, cmd);
+        print(alloc::format!("[{id}] {exe}\n").as_bytes());
+        return Status::Success;
+    }
+    wait_foreground(handle, process_group)
+}
+
+/// 将进程组设为前台并等待进程退出，退出码不为0时视为失败
+///
+/// 等待结束后取消前台进程组，shell等待输入时Ctrl+C作为普通字符输入
+fn wait_foreground(handle: u64, process_group: u64) -> Status {
+    if process_group != 0 {
+        _ = set_foreground_group(process_group);
+    }
+    let result = wait_process(handle);
+    if process_group != 0 {
+        _ = set_foreground_group(0);
+    }
+    match result {
+        Ok(EXIT_SUCCESS) => Status::Success,
+        Ok(EXIT_INTERRUPT) => {
+            print(b"interrupted\n");
+            Status::Failed
+        }
+        Ok(code) => {
+            print(alloc::format!("exited with {code}\n").as_bytes());
+            Status::Failed
+        }
+        Err(error) => {
+            print(alloc::format!("wait failed: {}\n", error).as_bytes());
+            Status::Failed
+        }
+    }
 }
 
 fn print(string: &[u8]) {
-    for &ch in string {
-        put_char(ch).expect("failed to print string");
+    put_str(string).expect("failed to print string");
+}
+
+fn process_ids() -> alloc::vec::Vec<u64> {
+    let mut process_ids = alloc::vec![0u64; 16];
+    let count = loop {
+        let count = list_processes(&mut process_ids).expect("failed to list processes");
+        if count <= process_ids.len() {
+            break count;
+        }
+        process_ids.resize(count, 0);
+    };
+    process_ids.truncate(count);
+    process_ids
+}
+
+fn print_processes() {
+    print(b"  PID  PPID  PGID   SID TTY  STATE    THREADS HANDLES    KMEM   RSS(K)  TIME(ms)\n");
+    for process_id in process_ids() {
+        // 进程可能在列出后退出
+        let Ok(info) = process_info(process_id) else {
+            continue;
+        };
+        let state = match info.state {
+            PROCESS_STATE_RUNNING => "running",
+            PROCESS_STATE_EXITING => "exiting",
+            _ => "unknown",
+        };
+        let line = alloc::format!(
+            "{:>5} {:>5} {:>5} {:>5} tty{} {:<8} {:>7} {:>7} {:>7} {:>8} {:>9}\n",
+            info.process_id,
+            info.parent_id,
+            info.process_group,
+            info.session,
+            info.console + 1,
+            state,
+            info.thread_count,
+            info.handle_count,
+            info.kernel_memory,
+            info.resident_pages * 4,
+            info.cpu_time / 1000,
+        );
+        print(line.as_bytes());
+    }
+}
+
+fn print_memory_info() {
+    let stats = memory_stats().expect("failed to get memory stats");
+    let line = alloc::format!(
+        "Physical memory: total {}K, reserved {}K, allocated {}K, reusable {}K\n",
+        stats.total_memory / 1024,
+        stats.reserved_memory / 1024,
+        stats.allocated_frames * 4,
+        stats.free_list_frames * 4,
+    );
+    print(line.as_bytes());
+
+    let class_pages: u64 = stats.heap_classes.iter().map(|class| class.pages).sum();
+    let held = (class_pages + stats.heap_large_pages) * 4096;
+    let allocated = stats
+        .heap_classes
+        .iter()
+        .map(|class| class.allocated * class.size)
+        .sum::<u64>()
+        + stats.heap_large_pages * 4096;
+    // 持有但未分配出的内存占比
+    let fragmentation = (held - allocated) * 100 / held.max(1);
+    let line = alloc::format!(
+        "Kernel heap: held {}K, allocated {}K, fragmentation {}%\n",
+        held / 1024,
+        allocated / 1024,
+        fragmentation,
+    );
+    print(line.as_bytes());
+    print(b"   SIZE   ALLOC    FREE  PAGES\n");
+    for class in &stats.heap_classes {
+        let line = alloc::format!(
+            "{:>7} {:>7} {:>7} {:>6}\n",
+            class.size,
+            class.allocated,
+            class.free,
+            class.pages,
+        );
+        print(line.as_bytes());
+    }
+    let line = alloc::format!(
+        "{:>7} {:>7} {:>7} {:>6}\n",
+        "large",
+        stats.heap_large_allocated,
+        "-",
+        stats.heap_large_pages,
+    );
+    print(line.as_bytes());
+
+    print(b"  PID   RSS(K)    KMEM\n");
+    for process_id in process_ids() {
+        // 进程可能在列出后退出
+        let Ok(info) = process_info(process_id) else {
+            continue;
+        };
+        let line = alloc::format!(
+            "{:>5} {:>8} {:>7}\n",
+            info.process_id,
+            info.resident_pages * 4,
+            info.kernel_memory,
+        );
+        print(line.as_bytes());
+    }
+}
+
+fn print_memory_maps(process_id: u64) -> Status {
+    let mut regions = alloc::vec![MemoryRegionInfo::default(); 16];
+    let count = loop {
+        let count = match memory_maps(process_id, &mut regions) {
+            Ok(count) => count,
+            Err(error) => {
+                print(alloc::format!("maps failed: {}\n", error).as_bytes());
+                return Status::Failed;
+            }
+        };
+        if count <= regions.len() {
+            break count;
+        }
+        regions.resize(count, MemoryRegionInfo::default());
+    };
+
+    print(b"           START              END  SIZE(K) PERM KIND\n");
+    for region in &regions[..count] {
+        let perm = [
+            (MEMORY_REGION_WRITABLE, 'w'),
+            (MEMORY_REGION_EXECUTABLE, 'x'),
+            (MEMORY_REGION_FILE, 'f'),
+        ]
+        .map(|(flag, ch)| if region.flags & flag != 0 { ch } else { '-' });
+        let kind = match region.kind {
+            MEMORY_REGION_IMAGE => "image",
+            MEMORY_REGION_STACK => "stack",
+            MEMORY_REGION_VDSO => "vdso",
+            _ => "data",
+        };
+        let line = alloc::format!(
+            "{:016x} {:016x} {:>8} r{}{}{} {}\n",
+            region.start,
+            region.start + region.size,
+            region.size / 1024,
+            perm[0],
+            perm[1],
+            perm[2],
+            kind,
+        );
+        print(line.as_bytes());
+    }
+    Status::Success
+}
+
+/// 设置内核日志目标的过滤级别
+fn set_kernel_log_filter(target: &[u8], level: &[u8]) -> Status {
+    let Ok(target) = str::from_utf8(target) else {
+        print(b"klog: invalid target\n");
+        return Status::Failed;
+    };
+    let level = match level {
+        b"off" => LOG_LEVEL_OFF,
+        b"error" => LOG_LEVEL_ERROR,
+        b"warn" => LOG_LEVEL_WARN,
+        b"info" => LOG_LEVEL_INFO,
+        b"debug" => LOG_LEVEL_DEBUG,
+        b"default" => LOG_LEVEL_DEFAULT,
+        _ => {
+            print(b"klog: unknown level\n");
+            return Status::Failed;
+        }
+    };
+    if let Err(error) = set_log_filter(target, level) {
+        print(alloc::format!("klog failed: {}\n", error).as_bytes());
+        return Status::Failed;
+    }
+    Status::Success
+}
+
+/// 当前进程，即shell自身
+fn current() -> Option<Process> {
+    match current_process() {
+        // Safety: 句柄刚刚创建，没有其他所有者
+        Ok(handle) => Some(Process::from(unsafe { OwnedHandle::from_raw(handle) })),
+        Err(error) => {
+            print(alloc::format!("ulimit failed: {}\n", error).as_bytes());
+            None
+        }
+    }
+}
+
+/// 输出shell的资源限制，shell启动的程序继承这些限制
+fn print_limits() -> Status {
+    let Some(process) = current() else {
+        return Status::Failed;
+    };
+    for &(name, limit) in LIMITS {
+        let value = match process.limit(limit) {
+            Ok(LIMIT_UNLIMITED) => alloc::string::String::from("unlimited"),
+            Ok(value) => alloc::format!("{value}"),
+            Err(error) => alloc::format!("{error}"),
+        };
+        print(alloc::format!("{name:<16}{value}\n").as_bytes());
+    }
+    Status::Success
+}
+
+/// 设置shell的资源限制，shell不是特权进程，只能降低限制
+fn set_limit(name: &[u8], value: &[u8]) -> Status {
+    let Some(&(_, limit)) = LIMITS.iter().find(|(limit, _)| limit.as_bytes() == name) else {
+        print(b"ulimit: unknown limit\n");
+        return Status::Failed;
+    };
+    let value = match value {
+        b"unlimited" => LIMIT_UNLIMITED,
+        value => match str::from_utf8(value).ok().and_then(|s| s.parse().ok()) {
+            Some(value) => value,
+            None => {
+                print(b"ulimit: invalid value\n");
+                return Status::Failed;
+            }
+        },
+    };
+    let Some(process) = current() else {
+        return Status::Failed;
+    };
+    if let Err(error) = process.set_limit(limit, value) {
+        print(alloc::format!("ulimit failed: {}\n", error).as_bytes());
+        return Status::Failed;
+    }
+    Status::Success
+}
+
+/// 以PC扬声器发声，参数为频率（Hz）与时长（毫秒）
+fn run_beep(args: &[u8]) -> Status {
+    let mut args = args
+        .split(|byte| *byte == b' ')
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| {
+            str::from_utf8(arg)
+                .ok()
+                .and_then(|arg| arg.parse::<u32>().ok())
+        });
+    let frequency = args.next().unwrap_or(Some(880));
+    let duration = args.next().unwrap_or(Some(200));
+    let (Some(frequency), Some(duration), None) = (frequency, duration, args.next()) else {
+        print(b"usage: beep [<frequency> [<ms>]]\n");
+        return Status::Failed;
+    };
+    if let Err(error) = beep(frequency, Duration::from_millis(duration as u64)) {
+        print(alloc::format!("beep failed: {}\n", error).as_bytes());
+        return Status::Failed;
+    }
+    Status::Success
+}
+
+/// 输出控制台大小，指定行数与列数时先修改大小
+fn run_stty(args: &[u8]) -> Status {
+    let mut args = args
+        .split(|byte| *byte == b' ')
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| {
+            str::from_utf8(arg)
+                .ok()
+                .and_then(|arg| arg.parse::<u32>().ok())
+        });
+    let size = match (args.next(), args.next(), args.next()) {
+        (None, None, None) => None,
+        (Some(Some(rows)), Some(Some(cols)), None) => Some(ConsoleSize { rows, cols }),
+        _ => {
+            print(b"usage: stty [<rows> <cols>]\n");
+            return Status::Failed;
+        }
+    };
+    let console = match open_keyboard() {
+        // Safety: 句柄由open_keyboard新打开，只在此处持有
+        Ok(handle) => unsafe { OwnedHandle::from_raw(handle) },
+        Err(error) => {
+            print(alloc::format!("stty failed: {}\n", error).as_bytes());
+            return Status::Failed;
+        }
+    };
+    let result = match size {
+        Some(size) => set_console_size(console.as_raw(), size),
+        None => console_size(console.as_raw()),
+    };
+    match result {
+        Ok(size) => {
+            print(alloc::format!("rows {} cols {}\n", size.rows, size.cols).as_bytes());
+            Status::Success
+        }
+        Err(error) => {
+            print(alloc::format!("stty failed: {}\n", error).as_bytes());
+            Status::Failed
+        }
+    }
+}
+
+/// 运行程序并等待其退出，随后输出其系统调用
+fn run_traced(exe: &[u8]) -> Status {
+    let Ok(exe) = str::from_utf8(exe) else {
+        print(b"strace: invalid path\n");
+        return Status::Failed;
+    };
+    // 只跟踪子进程，shell自身的系统调用不被记录
+    if let Err(error) = trace_process(0, TRACE_CHILDREN) {
+        print(alloc::format!("strace failed: {}\n", error).as_bytes());
+        return Status::Failed;
+    }
+    let process = create_process(exe);
+    let _ = trace_process(0, 0);
+    let process = match process {
+        Ok(process) => process,
+        Err(error) => {
+            print(alloc::format!("strace: start {exe} failed: {}\n", error).as_bytes());
+            return Status::Failed;
+        }
+    };
+    let exit_code = wait_process(process);
+
+    let mut records = [SyscallTraceRecord::default(); 32];
+    loop {
+        let (count, dropped) = match read_trace(0, &mut records) {
+            Ok(result) => result,
+            Err(error) => {
+                print(alloc::format!("strace: read trace failed: {}\n", error).as_bytes());
+                return Status::Failed;
+            }
+        };
+        if dropped > 0 {
+            print(alloc::format!("... {dropped} records dropped\n").as_bytes());
+        }
+        if count == 0 {
+            break;
+        }
+        for record in &records[..count] {
+            let args = &record.args;
+            let line = alloc::format!(
+                "[{}:{}] {}(0x{:x}, 0x{:x}, 0x{:x}, 0x{:x}) = {} <{}us>\n",
+                record.process_id,
+                record.thread_id,
+                syscall_name(record.syscall_id),
+                args[0],
+                args[1],
+                args[2],
+                args[3],
+                record.result,
+                record.duration,
+            );
+            print(line.as_bytes());
+        }
+    }
+    match exit_code {
+        Ok(exit_code) => {
+            print(alloc::format!("+++ exited with {exit_code} +++\n").as_bytes());
+            Status::Success
+        }
+        Err(error) => {
+            print(alloc::format!("strace: wait failed: {}\n", error).as_bytes());
+            Status::Failed
+        }
+    }
+}
+
+fn syscall_name(syscall_id: u64) -> &'static str {
+    match syscall_id {
+        idx::IDX_DEBUG_INFO => "debug_info",
+        idx::IDX_DEBUG_GET_CHAR => "debug_get_char",
+        idx::IDX_DEBUG_PUT_CHAR => "debug_put_char",
+        idx::IDX_DEBUG_OPEN_KEYBOARD => "debug_open_keyboard",
+        idx::IDX_DEBUG_LIST_HANDLES => "debug_list_handles",
+        idx::IDX_DEBUG_SERIAL_WRITE => "debug_serial_write",
+        idx::IDX_DEBUG_EXIT_EMULATOR => "debug_exit_emulator",
+        idx::IDX_DEBUG_MEMORY_MAPS => "debug_memory_maps",
+        idx::IDX_DEBUG_TRACE_PROCESS => "debug_trace_process",
+        idx::IDX_DEBUG_READ_TRACE => "debug_read_trace",
+        idx::IDX_DEBUG_SET_LOG_FILTER => "debug_set_log_filter",
+        idx::IDX_DEBUG_PUT_STR => "debug_put_str",
+        idx::IDX_DEBUG_LOCK_STATS => "debug_lock_stats",
+        idx::IDX_EXIT_PROCESS => "exit_process",
+        idx::IDX_EXIT_THREAD => "exit_thread",
+        idx::IDX_THREAD_CURRENT => "thread_current",
+        idx::IDX_THREAD_WAIT => "thread_wait",
+        idx::IDX_THREAD_WAKE => "thread_wake",
+        idx::IDX_THREAD_KILL => "thread_kill",
+        idx::IDX_THREAD_CREATE => "thread_create",
+        idx::IDX_THREAD_JOIN => "thread_join",
+        idx::IDX_THREAD_SLEEP => "thread_sleep",
+        idx::IDX_THREAD_SET_TLS_BASE => "thread_set_tls_base",
+        idx::IDX_MEMORY_ALLOC => "memory_alloc",
+        idx::IDX_MEMORY_FREE => "memory_free",
+        idx::IDX_MEMORY_STATS => "memory_stats",
+        idx::IDX_MEMORY_ALLOC_AT => "memory_alloc_at",
+        idx::IDX_MEMORY_REMAP => "memory_remap",
+        idx::IDX_PROCESS_CURRENT => "process_current",
+        idx::IDX_PROCESS_CREATE => "process_create",
+        idx::IDX_PROCESS_KILL => "process_kill",
+        idx::IDX_PROCESS_WAIT => "process_wait",
+        idx::IDX_PROCESS_SET_LIMIT => "process_set_limit",
+        idx::IDX_PROCESS_LIST => "process_list",
+        idx::IDX_PROCESS_INFO => "process_info",
+        idx::IDX_PROCESS_CPU_TIMES => "process_cpu_times",
+        idx::IDX_PROCESS_CREATE_WITH_ARGS => "process_create_with_args",
+        idx::IDX_PROCESS_ARGS => "process_args",
+        idx::IDX_PROCESS_CREDENTIALS => "process_credentials",
+        idx::IDX_PROCESS_SET_CREDENTIALS => "process_set_credentials",
+        idx::IDX_PROCESS_GET_LIMIT => "process_get_limit",
+        idx::IDX_PROCESS_SET_GROUP => "process_set_group",
+        idx::IDX_PROCESS_CREATE_SESSION => "process_create_session",
+        idx::IDX_PROCESS_SET_FOREGROUND => "process_set_foreground",
+        idx::IDX_PROCESS_FOREGROUND => "process_foreground",
+        idx::IDX_FILE_CREATE => "file_create",
+        idx::IDX_FILE_OPEN => "file_open",
+        idx::IDX_FILE_READ => "file_read",
+        idx::IDX_FILE_WRITE => "file_write",
+        idx::IDX_FILE_GET_POS => "file_get_pos",
+        idx::IDX_FILE_SET_POS => "file_set_pos",
+        idx::IDX_FILE_CLOSE => "file_close",
+        idx::IDX_FILE_LIST_BLOCK_DEVICES => "file_list_block_devices",
+        idx::IDX_FILE_MOUNT => "file_mount",
+        idx::IDX_FILE_UNMOUNT => "file_unmount",
+        idx::IDX_FILE_LIST_DIRECTORY => "file_list_directory",
+        idx::IDX_FILE_TRUNCATE => "file_truncate",
+        idx::IDX_FILE_DELETE => "file_delete",
+        idx::IDX_FILE_RENAME => "file_rename",
+        idx::IDX_FILE_METADATA => "file_metadata",
+        idx::IDX_FILE_SCRUB => "file_scrub",
+        idx::IDX_FILE_CHMOD => "file_chmod",
+        idx::IDX_FILE_CHOWN => "file_chown",
+        idx::IDX_FILE_CONTROL => "file_control",
+        idx::IDX_FILE_MOUNT_ENCRYPTED => "file_mount_encrypted",
+        idx::IDX_COMPLETION_SUBMIT => "completion_submit",
+        idx::IDX_COMPLETION_POLL => "completion_poll",
+        idx::IDX_COMPLETION_WAIT => "completion_wait",
+        idx::IDX_IPC_POLL => "ipc_poll",
+        idx::IDX_SYSTEM_CMDLINE => "system_cmdline",
+        idx::IDX_SYSTEM_SHUTDOWN => "system_shutdown",
+        idx::IDX_SYSTEM_REBOOT => "system_reboot",
+        idx::IDX_SYSTEM_ABI_INFO => "system_abi_info",
+        idx::IDX_NET_UDP_BIND => "net_udp_bind",
+        idx::IDX_NET_UDP_SEND_TO => "net_udp_send_to",
+        idx::IDX_NET_UDP_RECV_FROM => "net_udp_recv_from",
+        idx::IDX_NET_TCP_CONNECT => "net_tcp_connect",
+        idx::IDX_NET_TCP_LISTEN => "net_tcp_listen",
+        idx::IDX_NET_TCP_ACCEPT => "net_tcp_accept",
+        idx::IDX_NET_TCP_READ => "net_tcp_read",
+        idx::IDX_NET_TCP_WRITE => "net_tcp_write",
+        idx::IDX_NET_TCP_SHUTDOWN => "net_tcp_shutdown",
+        idx::IDX_RANDOM_GET => "random_get",
+        idx::IDX_TIME_GET => "time_get",
+        idx::IDX_TIME_MONOTONIC => "time_monotonic",
+        idx::IDX_TIME_UTC_OFFSET => "time_utc_offset",
+        idx::IDX_GFX_MODE_INFO => "gfx_mode_info",
+        idx::IDX_GFX_MAP_BUFFER => "gfx_map_buffer",
+        idx::IDX_GFX_PRESENT => "gfx_present",
+        idx::IDX_INPUT_POLL => "input_poll",
+        idx::IDX_INPUT_WAIT => "input_wait",
+        idx::IDX_SOUND_BEEP => "sound_beep",
+        idx::IDX_MODULE_LOAD => "module_load",
+        idx::IDX_MODULE_UNLOAD => "module_unload",
+        idx::IDX_MODULE_LIST => "module_list",
+        _ => "unknown",
+    }
+}
+
+fn print_block_devices() {
+    let mut devices = alloc::vec![BlockDeviceInfo::default(); 8];
+    let count = loop {
+        let count = list_block_devices(&mut devices).expect("failed to list block devices");
+        if count <= devices.len() {
+            break count;
+        }
+        devices.resize(count, BlockDeviceInfo::default());
+    };
+
+    print(b"DEVICE       TYPE    SIZE(K) MOUNTED ON\n");
+    for device in &devices[..count] {
+        let name = str::from_utf8(device.name()).unwrap_or("?");
+        let partition_type = match device.partition_type {
+            0 => alloc::string::String::from("disk"),
+            partition_type => alloc::format!("0x{partition_type:02X}"),
+        };
+        let mount_path = device
+            .mount_path()
+            .and_then(|path| str::from_utf8(path).ok())
+            .unwrap_or("-");
+        let line = alloc::format!(
+            "{:<12} {:<4} {:>10} {}\n",
+            name,
+            partition_type,
+            device.block_size * device.block_count / 1024,
+            mount_path,
+        );
+        print(line.as_bytes());
+    }
+}
+
+fn print_modules() {
+    let mut modules = alloc::vec![ModuleInfo::default(); 8];
+    let count = loop {
+        let count = module::list(&mut modules).expect("failed to list modules");
+        if count <= modules.len() {
+            break count;
+        }
+        modules.resize(count, ModuleInfo::default());
+    };
+
+    print(b"MODULE                           SIZE(K) ADDRESS\n");
+    for module in &modules[..count] {
+        let line = alloc::format!(
+            "{:<32} {:>7} 0x{:x}\n",
+            str::from_utf8(module.name()).unwrap_or("?"),
+            module.size / 1024,
+            module.address,
+        );
+        print(line.as_bytes());
+    }
+}
+
+fn print_date() -> Status {
+    let (now, offset) = match (SystemTime::now(), utc_offset()) {
+        (Ok(now), Ok(offset)) => (now, offset),
+        (Err(error), _) | (_, Err(error)) => {
+            print(alloc::format!("date failed: {}\n", error).as_bytes());
+            return Status::Failed;
+        }
+    };
+
+    let date = DateTime::from_system_time(now, offset);
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.unsigned_abs();
+    print(
+        alloc::format!(
+            "{date} {sign}{:02}:{:02}\n",
+            offset / 3600,
+            offset % 3600 / 60
+        )
+        .as_bytes(),
+    );
+    Status::Success
+}
+
+fn scrub_device(device: &[u8]) -> Status {
+    let mut corrupted = alloc::vec![0u64; 16];
+    // 扫描在内核后台进行，完成前定期查询结果
+    let info = loop {
+        match scrub(device, &mut corrupted) {
+            Ok(info) => break info,
+            Err(error) if error.kind() == ErrorKind::WouldBlock => {
+                sleep_thread(0, 100_000_000).unwrap();
+            }
+            Err(error) => {
+                print(alloc::format!("scrub failed: {}\n", error).as_bytes());
+                return Status::Failed;
+            }
+        }
+    };
+
+    print(
+        alloc::format!(
+            "checked {} blocks, {} corrupted\n",
+            info.checked,
+            info.corrupted
+        )
+        .as_bytes(),
+    );
+    if info.corrupted == 0 {
+        return Status::Success;
+    }
+    for block in corrupted.iter().take(info.corrupted as usize) {
+        print(alloc::format!("  block {block}\n").as_bytes());
+    }
+    if info.corrupted > corrupted.len() as u64 {
+        print(
+            alloc::format!(
+                "  ... and {} more\n",
+                info.corrupted - corrupted.len() as u64
+            )
+            .as_bytes(),
+        );
+    }
+    Status::Failed
+}
+
+/// 输出镜像的构建信息，以及内核符号表中的符号数量
+fn print_version() -> Status {
+    let info = match read_file(BUILD_INFO_PATH) {
+        Ok(info) => info,
+        Err(error) => {
+            print(alloc::format!("ver failed: {}\n", error).as_bytes());
+            return Status::Failed;
+        }
+    };
+    for line in info.split(|&ch| ch == b'\n') {
+        if let Some(index) = line.iter().position(|&ch| ch == b'=') {
+            print(&line[..index]);
+            print(b": ");
+            print(&line[index + 1..]);
+            print(b"\n");
+        }
+    }
+    match read_file(KERNEL_MAP_PATH) {
+        Ok(map) => {
+            let symbols = map.iter().filter(|&&ch| ch == b'\n').count();
+            print(alloc::format!("kernel symbols: {symbols}\n").as_bytes());
+        }
+        Err(_) => print(b"kernel symbols: unavailable\n"),
     }
+    Status::Success
 }
 
 fn print_welcome_file() {
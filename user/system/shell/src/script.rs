@@ -0,0 +1,110 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use alloc::{collections::btree_map::BTreeMap, format, vec::Vec};
+use cos_sys::file::{close, open, read};
+
+use crate::{Shell, Status, execute, print};
+
+/// 命令文件的最大嵌套层数，防止命令文件执行自身时无限递归
+const MAX_SCRIPT_DEPTH: usize = 8;
+
+/// 正在执行的命令文件层数
+static SCRIPT_DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+/// shell变量，变量名到值的映射
+pub type Variables = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// 变量名是否合法，变量名由字母、数字与下划线组成
+pub fn is_variable_name(name: &[u8]) -> bool {
+    !name.is_empty() && name.iter().all(|&char| is_name_char(char))
+}
+
+fn is_name_char(char: u8) -> bool {
+    char.is_ascii_alphanumeric() || char == b'_'
+}
+
+/// 展开命令中的变量
+///
+/// `$NAME`与`${NAME}`替换为变量的值，未定义的变量展开为空。`$`之后不是变量名时原样保留
+pub fn expand(cmd: &[u8], variables: &Variables) -> Vec<u8> {
+    let mut result = Vec::with_capacity(cmd.len());
+    let mut rest = cmd;
+    while let Some((&char, tail)) = rest.split_first() {
+        rest = tail;
+        if char != b'$' {
+            result.push(char);
+            continue;
+        }
+        let (name, tail) = match rest
+            .strip_prefix(b"{")
+            .and_then(|braced| Some((braced, braced.iter().position(|&char| char == b'}')?)))
+        {
+            Some((braced, end)) => (&braced[..end], &braced[end + 1..]),
+            None => rest.split_at(rest.iter().take_while(|&&char| is_name_char(char)).count()),
+        };
+        if !is_variable_name(name) {
+            result.push(b'$');
+            continue;
+        }
+        if let Some(value) = variables.get(name) {
+            result.extend_from_slice(value);
+        }
+        rest = tail;
+    }
+    result
+}
+
+/// 执行命令文件
+///
+/// 逐行执行命令，忽略空行与`#`开头的注释行。某一行执行失败时输出其行号，不再执行之后的命令。
+/// 命令文件中的`exit`只结束命令文件的执行
+pub fn run_script(path: &[u8], shell: &mut Shell) -> Status {
+    let path_str = str::from_utf8(path).unwrap_or("?");
+    let content = match read_file(path) {
+        Ok(content) => content,
+        Err(error) => {
+            print(format!("sh: open {path_str} failed: {error}\n").as_bytes());
+            return Status::Failed;
+        }
+    };
+    if SCRIPT_DEPTH.fetch_add(1, Ordering::Relaxed) >= MAX_SCRIPT_DEPTH {
+        SCRIPT_DEPTH.fetch_sub(1, Ordering::Relaxed);
+        print(b"sh: too many nested scripts\n");
+        return Status::Failed;
+    }
+
+    let mut status = Status::Success;
+    for (index, line) in content.split(|&char| char == b'\n').enumerate() {
+        let line = line.trim_ascii();
+        if line.is_empty() || line.starts_with(b"#") {
+            continue;
+        }
+        match execute(line, shell) {
+            Status::Success => (),
+            Status::Failed => {
+                print(format!("sh: {path_str}:{}: command failed, stop\n", index + 1).as_bytes());
+                status = Status::Failed;
+                break;
+            }
+            Status::Exit => break,
+        }
+    }
+
+    SCRIPT_DEPTH.fetch_sub(1, Ordering::Relaxed);
+    status
+}
+
+pub fn read_file(path: &[u8]) -> cos_sys::error::Result<Vec<u8>> {
+    let file = open(path)?;
+    let mut content = Vec::new();
+    let mut buffer = alloc::vec![0u8; 4096];
+    let result = loop {
+        match read(file, &mut buffer) {
+            Ok(0) => break Ok(content),
+            Ok(read_count) => content.extend_from_slice(&buffer[..read_count as usize]),
+            Err(error) => break Err(error),
+        }
+    };
+    close(file)?;
+    result
+}